@@ -13,6 +13,8 @@ export interface ChannelInfo {
 	id: string;
 	platform: string;
 	display_name: string | null;
+	/** Short title of the conversation, generated or set by renaming it. */
+	title: string | null;
 	is_active: boolean;
 	last_activity_at: string;
 	created_at: string;
@@ -63,12 +65,25 @@ export interface WorkerStatusEvent {
 	status: string;
 }
 
+export interface WorkerArtifact {
+	id: string;
+	worker_id: string;
+	channel_id: string | null;
+	name: string;
+	description: string | null;
+	mime_type: string;
+	size_bytes: number;
+	path: string;
+	created_at: string;
+}
+
 export interface WorkerCompletedEvent {
 	type: "worker_completed";
 	agent_id: string;
 	channel_id: string | null;
 	worker_id: string;
 	result: string;
+	artifacts: WorkerArtifact[];
 }
 
 export interface BranchStartedEvent {
@@ -105,6 +120,64 @@ export interface ToolCompletedEvent {
 	tool_name: string;
 }
 
+export interface ChannelTitledEvent {
+	type: "channel_titled";
+	agent_id: string;
+	channel_id: string;
+	title: string;
+}
+
+export type CompactionAction =
+	| "trim_tool_outputs"
+	| "background"
+	| "aggressive"
+	| "emergency_truncate";
+
+export type CompactionTrigger = "threshold" | "before_turn" | "manual";
+
+export interface CompactionStartedEvent {
+	type: "compaction_started";
+	agent_id: string;
+	channel_id: string;
+	action: CompactionAction;
+	trigger: CompactionTrigger;
+	tokens_before: number;
+}
+
+export interface CompactionCompletedEvent {
+	type: "compaction_completed";
+	agent_id: string;
+	channel_id: string;
+	action: CompactionAction;
+	trigger: CompactionTrigger;
+	tokens_before: number;
+	tokens_after: number;
+	messages_compacted: number;
+	error: string | null;
+}
+
+export type CircuitBreakerState = "closed" | "open" | "half_open";
+
+export interface CircuitBreakerChangedEvent {
+	type: "circuit_breaker_changed";
+	agent_id: string;
+	process_type: string;
+	state: CircuitBreakerState;
+	consecutive_failures: number;
+	last_error: string | null;
+}
+
+export interface ProcessUnhealthyEvent {
+	type: "process_unhealthy";
+	agent_id: string;
+	channel_id: string;
+	process_type: ProcessType;
+	process_id: string;
+	score: number;
+	action: "nudge" | "kill";
+	diagnostic: string;
+}
+
 export type ApiEvent =
 	| InboundMessageEvent
 	| OutboundMessageEvent
@@ -115,7 +188,12 @@ export type ApiEvent =
 	| BranchStartedEvent
 	| BranchCompletedEvent
 	| ToolStartedEvent
-	| ToolCompletedEvent;
+	| ToolCompletedEvent
+	| ChannelTitledEvent
+	| CompactionStartedEvent
+	| CompactionCompletedEvent
+	| CircuitBreakerChangedEvent
+	| ProcessUnhealthyEvent;
 
 async function fetchJson<T>(path: string): Promise<T> {
 	const response = await fetch(`${API_BASE}${path}`);
@@ -263,6 +341,13 @@ export interface InstanceOverviewResponse {
 
 export type Deployment = "docker" | "hosted" | "native";
 
+export type UpdateChannel = "stable" | "beta" | "nightly";
+
+export interface VersionRecord {
+	version: string;
+	started_at: string;
+}
+
 export interface UpdateStatus {
 	current_version: string;
 	latest_version: string | null;
@@ -273,6 +358,9 @@ export interface UpdateStatus {
 	can_apply: boolean;
 	checked_at: string | null;
 	error: string | null;
+	channel: UpdateChannel;
+	history: VersionRecord[];
+	rollback_version: string | null;
 }
 
 export interface UpdateApplyResponse {
@@ -280,6 +368,11 @@ export interface UpdateApplyResponse {
 	error?: string;
 }
 
+export interface UpdateRollbackResponse {
+	status: "rolling_back" | "error";
+	error?: string;
+}
+
 export type MemoryType =
 	| "fact"
 	| "preference"
@@ -391,14 +484,24 @@ export type CortexEventType =
 	| "branch_killed"
 	| "circuit_breaker_tripped"
 	| "observation_created"
-	| "health_check";
+	| "health_check"
+	| "proactive_message"
+	| "scheduled_task"
+	| "circuit_breaker_closed"
+	| "identity_reflection"
+	| "identity_proposal"
+	| "queued_task"
+	| "trigger_fired"
+	| "dream";
 
 export const CORTEX_EVENT_TYPES: CortexEventType[] = [
 	"bulletin_generated", "bulletin_failed",
 	"maintenance_run", "memory_merged", "memory_decayed", "memory_pruned",
 	"association_created", "contradiction_flagged",
-	"worker_killed", "branch_killed", "circuit_breaker_tripped",
-	"observation_created", "health_check",
+	"worker_killed", "branch_killed", "circuit_breaker_tripped", "circuit_breaker_closed",
+	"observation_created", "health_check", "proactive_message",
+	"scheduled_task", "queued_task", "trigger_fired", "identity_reflection", "identity_proposal",
+	"dream",
 ];
 
 export interface CortexEvent {
@@ -414,6 +517,18 @@ export interface CortexEventsResponse {
 	total: number;
 }
 
+export interface CircuitBreakerStatus {
+	process_type: string;
+	state: CircuitBreakerState;
+	consecutive_failures: number;
+	last_error: string | null;
+	changed_at: string | null;
+}
+
+export interface CircuitBreakersResponse {
+	breakers: CircuitBreakerStatus[];
+}
+
 export interface CortexEventsParams {
 	limit?: number;
 	offset?: number;
@@ -454,6 +569,24 @@ export interface IdentityUpdateRequest {
 	user?: string | null;
 }
 
+export interface IdentityChange {
+	file: string;
+	current: string;
+	proposed: string;
+	diff: string;
+}
+
+export interface IdentityProposal {
+	id: string;
+	created_at: string;
+	rationale: string;
+	changes: IdentityChange[];
+}
+
+export interface IdentityProposalResponse {
+	proposal: IdentityProposal | null;
+}
+
 // -- Agent Config Types --
 
 export interface RoutingSection {
@@ -484,6 +617,7 @@ export interface CompactionSection {
 	background_threshold: number;
 	aggressive_threshold: number;
 	emergency_threshold: number;
+	tool_output_max_chars: number;
 }
 
 export interface CortexSection {
@@ -502,6 +636,7 @@ export interface CoalesceSection {
 	max_wait_ms: number;
 	min_messages: number;
 	multi_user_only: boolean;
+	sender_debounce_ms: number;
 }
 
 export interface MemoryPersistenceSection {
@@ -532,6 +667,7 @@ export interface BrowserSection {
 export interface DiscordSection {
 	enabled: boolean;
 	allow_bot_messages: boolean;
+	voice: boolean;
 }
 
 export interface AgentConfigResponse {
@@ -576,6 +712,7 @@ export interface CompactionUpdate {
 	background_threshold?: number;
 	aggressive_threshold?: number;
 	emergency_threshold?: number;
+	tool_output_max_chars?: number;
 }
 
 export interface CortexUpdate {
@@ -594,6 +731,7 @@ export interface CoalesceUpdate {
 	max_wait_ms?: number;
 	min_messages?: number;
 	multi_user_only?: boolean;
+	sender_debounce_ms?: number;
 }
 
 export interface MemoryPersistenceUpdate {
@@ -623,6 +761,7 @@ export interface BrowserUpdate {
 
 export interface DiscordUpdate {
 	allow_bot_messages?: boolean;
+	voice?: boolean;
 }
 
 export interface AgentConfigUpdateRequest {
@@ -690,6 +829,38 @@ export interface CronExecutionsParams {
 	limit?: number;
 }
 
+// -- Task Queue Types --
+
+export type TaskPriority = "low" | "normal" | "high" | "urgent";
+
+export type TaskStatus = "pending" | "running" | "done" | "failed" | "cancelled";
+
+export interface QueuedTask {
+	id: string;
+	task: string;
+	priority: TaskPriority;
+	status: TaskStatus;
+	origin: "channel" | "api" | "schedule" | "trigger";
+	origin_id: string | null;
+	report_to: string | null;
+	worker_id: string | null;
+	result: string | null;
+	attempts: number;
+	created_at: string;
+	started_at: string | null;
+	finished_at: string | null;
+}
+
+export interface TaskQueueResponse {
+	tasks: QueuedTask[];
+}
+
+export interface QueueTaskRequest {
+	task: string;
+	priority?: TaskPriority;
+	report_to?: string;
+}
+
 export interface ProviderStatus {
 	anthropic: boolean;
 	openai: boolean;
@@ -840,7 +1011,17 @@ export interface MessagingStatusResponse {
 	twitch: PlatformStatus;
 }
 
-export interface BindingInfo {
+/** Channel settings a binding overrides for its conversations. */
+export interface BindingOverrides {
+	channel_model?: string;
+	max_turns?: number;
+	context_window?: number;
+	background_threshold?: number;
+	aggressive_threshold?: number;
+	emergency_threshold?: number;
+}
+
+export interface BindingInfo extends BindingOverrides {
 	agent_id: string;
 	channel: string;
 	guild_id: string | null;
@@ -848,14 +1029,21 @@ export interface BindingInfo {
 	chat_id: string | null;
 	channel_ids: string[];
 	require_mention: boolean;
+	activation: "always" | "mention" | "wake_word" | "thread";
+	wake_words: string[];
+	reply_to_message: boolean;
 	dm_allowed_users: string[];
+	digest_interval_secs: number | null;
+	response_mode: "typing" | "stream" | "final";
+	allow_proactive: boolean;
+	tags: string[];
 }
 
 export interface BindingsListResponse {
 	bindings: BindingInfo[];
 }
 
-export interface CreateBindingRequest {
+export interface CreateBindingRequest extends BindingOverrides {
 	agent_id: string;
 	channel: string;
 	guild_id?: string;
@@ -863,7 +1051,14 @@ export interface CreateBindingRequest {
 	chat_id?: string;
 	channel_ids?: string[];
 	require_mention?: boolean;
+	activation?: "always" | "mention" | "wake_word" | "thread";
+	wake_words?: string[];
+	reply_to_message?: boolean;
 	dm_allowed_users?: string[];
+	digest_interval_secs?: number;
+	response_mode?: "typing" | "stream" | "final";
+	allow_proactive?: boolean;
+	tags?: string[];
 	platform_credentials?: {
 		discord_token?: string;
 		slack_bot_token?: string;
@@ -882,7 +1077,7 @@ export interface CreateBindingResponse {
 	message: string;
 }
 
-export interface UpdateBindingRequest {
+export interface UpdateBindingRequest extends BindingOverrides {
 	original_agent_id: string;
 	original_channel: string;
 	original_guild_id?: string;
@@ -895,7 +1090,31 @@ export interface UpdateBindingRequest {
 	chat_id?: string;
 	channel_ids?: string[];
 	require_mention?: boolean;
+	activation?: "always" | "mention" | "wake_word" | "thread";
+	wake_words?: string[];
+	reply_to_message?: boolean;
 	dm_allowed_users?: string[];
+	digest_interval_secs?: number;
+	response_mode?: "typing" | "stream" | "final";
+	allow_proactive?: boolean;
+	tags?: string[];
+}
+
+export interface AnnounceRequest {
+	agent_id: string;
+	title?: string;
+	/** Markdown, converted for each platform. */
+	text: string;
+	/** Only announce to bindings with one of these tags. */
+	tags?: string[];
+}
+
+export interface AnnounceResponse {
+	/** Whether every conversation got the announcement. */
+	success: boolean;
+	/** Delivered targets, like `discord:123456`. */
+	delivered: string[];
+	failed: { target: string; error: string }[];
 }
 
 export interface UpdateBindingResponse {
@@ -1055,6 +1274,8 @@ export const api = {
 		if (params.event_type) search.set("event_type", params.event_type);
 		return fetchJson<CortexEventsResponse>(`/cortex/events?${search}`);
 	},
+	circuitBreakers: (agentId: string) =>
+		fetchJson<CircuitBreakersResponse>(`/cortex/circuit-breakers?agent_id=${encodeURIComponent(agentId)}`),
 	cortexChatMessages: (agentId: string, threadId?: string, limit = 50) => {
 		const search = new URLSearchParams({ agent_id: agentId, limit: String(limit) });
 		if (threadId) search.set("thread_id", threadId);
@@ -1086,6 +1307,18 @@ export const api = {
 		}
 		return response.json() as Promise<IdentityFiles>;
 	},
+	identityProposal: (agentId: string) =>
+		fetchJson<IdentityProposalResponse>(`/agents/identity/proposal?agent_id=${encodeURIComponent(agentId)}`),
+	answerIdentityProposal: async (agentId: string, approve: boolean) => {
+		const response = await fetch(`${API_BASE}/agents/identity/proposal/${approve ? "approve" : "reject"}`, {
+			method: "POST",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify({ agent_id: agentId }),
+		});
+		if (!response.ok) {
+			throw new Error(`API error: ${response.status}`);
+		}
+	},
 	createAgent: async (agentId: string) => {
 		const response = await fetch(`${API_BASE}/agents`, {
 			method: "POST",
@@ -1181,6 +1414,36 @@ export const api = {
 		return response.json() as Promise<CronActionResponse>;
 	},
 
+	// Task queue API
+	listQueuedTasks: (agentId: string, status?: TaskStatus) => {
+		const search = new URLSearchParams({ agent_id: agentId });
+		if (status) search.set("status", status);
+		return fetchJson<TaskQueueResponse>(`/agents/task-queue?${search}`);
+	},
+
+	queueTask: async (agentId: string, request: QueueTaskRequest) => {
+		const response = await fetch(`${API_BASE}/agents/task-queue`, {
+			method: "POST",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify({ ...request, agent_id: agentId }),
+		});
+		if (!response.ok) {
+			throw new Error(`API error: ${response.status}`);
+		}
+		return response.json() as Promise<QueuedTask>;
+	},
+
+	cancelQueuedTask: async (agentId: string, taskId: string) => {
+		const response = await fetch(`${API_BASE}/agents/task-queue/cancel`, {
+			method: "POST",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify({ agent_id: agentId, id: taskId }),
+		});
+		if (!response.ok) {
+			throw new Error(`API error: ${response.status}`);
+		}
+	},
+
 	cancelProcess: async (channelId: string, processType: "worker" | "branch", processId: string) => {
 		const response = await fetch(`${API_BASE}/channels/cancel`, {
 			method: "POST",
@@ -1193,6 +1456,18 @@ export const api = {
 		return response.json() as Promise<{ success: boolean; message: string }>;
 	},
 
+	renameChannel: async (agentId: string, channelId: string, title: string) => {
+		const response = await fetch(`${API_BASE}/channels/title`, {
+			method: "PUT",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify({ agent_id: agentId, channel_id: channelId, title }),
+		});
+		if (!response.ok) {
+			throw new Error(`API error: ${response.status}`);
+		}
+		return response.json() as Promise<{ success: boolean; title: string }>;
+	},
+
 	// Provider management
 	providers: () => fetchJson<ProvidersResponse>("/providers"),
 	updateProvider: async (provider: string, apiKey: string, model: string) => {
@@ -1285,6 +1560,18 @@ export const api = {
 		return fetchJson<BindingsListResponse>(`/bindings${params}`);
 	},
 
+	announce: async (request: AnnounceRequest) => {
+		const response = await fetch(`${API_BASE}/agents/announce`, {
+			method: "POST",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify(request),
+		});
+		if (!response.ok) {
+			throw new Error(await response.text() || `API error: ${response.status}`);
+		}
+		return response.json() as Promise<AnnounceResponse>;
+	},
+
 	createBinding: async (request: CreateBindingRequest) => {
 		const response = await fetch(`${API_BASE}/bindings`, {
 			method: "POST",
@@ -1390,6 +1677,24 @@ export const api = {
 		}
 		return response.json() as Promise<UpdateApplyResponse>;
 	},
+	updateSetChannel: async (channel: UpdateChannel) => {
+		const response = await fetch(`${API_BASE}/update/channel`, {
+			method: "PUT",
+			headers: { "Content-Type": "application/json" },
+			body: JSON.stringify({ channel }),
+		});
+		if (!response.ok) {
+			throw new Error(`API error: ${response.status}`);
+		}
+		return response.json() as Promise<UpdateStatus>;
+	},
+	updateRollback: async () => {
+		const response = await fetch(`${API_BASE}/update/rollback`, { method: "POST" });
+		if (!response.ok) {
+			throw new Error(`API error: ${response.status}`);
+		}
+		return response.json() as Promise<UpdateRollbackResponse>;
+	},
 
 	// Skills API
 	listSkills: (agentId: string) =>
@@ -92,7 +92,7 @@ export function ChannelCard({
 				<div className="min-w-0 flex-1">
 					<div className="flex items-center gap-2">
 						<h3 className="truncate font-medium text-ink">
-							{channel.display_name ?? channel.id}
+							{channel.title ?? channel.display_name ?? channel.id}
 						</h3>
 						{isTyping && (
 							<div className="flex items-center gap-1">
@@ -106,6 +106,11 @@ export function ChannelCard({
 						<span className={`inline-flex items-center rounded-md px-1.5 py-0.5 text-tiny font-medium ${platformColor(channel.platform)}`}>
 							{platformIcon(channel.platform)}
 						</span>
+						{channel.title && channel.display_name && (
+							<span className="truncate text-tiny text-ink-faint">
+								{channel.display_name}
+							</span>
+						)}
 						<span className="text-tiny text-ink-faint">
 							{formatTimeAgo(channel.last_activity_at)}
 						</span>
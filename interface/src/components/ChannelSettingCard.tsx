@@ -255,6 +255,30 @@ export function ChannelSettingCard({
 		request.require_mention =
 			platform === "discord" ? bindingForm.require_mention : false;
 		request.dm_allowed_users = bindingForm.dm_allowed_users;
+		// Not editable here yet; keep whatever the config has.
+		if (editingBinding.digest_interval_secs)
+			request.digest_interval_secs = editingBinding.digest_interval_secs;
+		if (editingBinding.reply_to_message) request.reply_to_message = true;
+		if (editingBinding.allow_proactive) request.allow_proactive = true;
+		if (editingBinding.tags.length > 0) request.tags = editingBinding.tags;
+		for (const key of [
+			"channel_model",
+			"max_turns",
+			"context_window",
+			"background_threshold",
+			"aggressive_threshold",
+			"emergency_threshold",
+		] as const) {
+			if (editingBinding[key] !== undefined) request[key] = editingBinding[key];
+		}
+		if (
+			!request.require_mention &&
+			(editingBinding.activation === "wake_word" ||
+				editingBinding.activation === "thread")
+		) {
+			request.activation = editingBinding.activation;
+			request.wake_words = editingBinding.wake_words;
+		}
 		updateBindingMutation.mutate(request);
 	}
 
@@ -807,6 +831,9 @@ function BindingsSection({
 									{binding.require_mention && (
 										<span>Mention only</span>
 									)}
+									{binding.tags.length > 0 && (
+										<span>Tags: {binding.tags.join(", ")}</span>
+									)}
 									{!binding.guild_id &&
 										!binding.workspace_id &&
 										!binding.chat_id &&
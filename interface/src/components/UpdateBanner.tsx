@@ -67,6 +67,11 @@ export function UpdateBanner() {
 							Mount docker.sock for one-click updates
 						</span>
 					)}
+					{!data.can_apply && data.deployment === "native" && (
+						<span className="text-xs text-ink-faint">
+							Make the binary's directory writable for one-click updates
+						</span>
+					)}
 					<Button
 						onClick={() => setDismissed(true)}
 						variant="ghost"
@@ -43,8 +43,14 @@ export function LiveContextProvider({ children }: { children: ReactNode }) {
 		queryClient.invalidateQueries({ queryKey: ["agents"] });
 	}, [syncStatusSnapshot, queryClient]);
 
+	// Titles only live on the channel list, so refetch it when one changes.
 	const { connectionState } = useEventSource(api.eventsUrl, {
-		handlers,
+		handlers: {
+			...handlers,
+			channel_titled: () => {
+				queryClient.invalidateQueries({ queryKey: ["channels"] });
+			},
+		},
 		onReconnect,
 	});
 
@@ -33,6 +33,7 @@ export function AgentChannels({ agentId, liveStates }: AgentChannelsProps) {
 			(c) =>
 				c.id.toLowerCase().includes(query) ||
 				(c.display_name && c.display_name.toLowerCase().includes(query)) ||
+				(c.title && c.title.toLowerCase().includes(query)) ||
 				(c.platform && c.platform.toLowerCase().includes(query)),
 		);
 	}, [channelsData, agentId, searchQuery]);
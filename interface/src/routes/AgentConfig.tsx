@@ -654,6 +654,15 @@ function ConfigSectionEditor({ sectionId, label, description, detail, config, on
 							type="float"
 							showProgress
 						/>
+						<NumberStepper
+							label="Tool Output Limit"
+							description="Trim longer tool results before summarizing (0 to keep them whole)"
+							value={localValues.tool_output_max_chars as number}
+							onChange={(v) => handleChange("tool_output_max_chars", v)}
+							min={0}
+							step={500}
+							suffix=" chars"
+						/>
 					</div>
 				);
 			case "cortex":
@@ -759,6 +768,15 @@ function ConfigSectionEditor({ sectionId, label, description, detail, config, on
 							value={localValues.multi_user_only as boolean}
 							onChange={(v) => handleChange("multi_user_only", v)}
 						/>
+						<NumberStepper
+							label="Sender Debounce"
+							description="Wait this long for more messages from the same sender, DMs included (0 = off)"
+							value={localValues.sender_debounce_ms as number}
+							onChange={(v) => handleChange("sender_debounce_ms", v)}
+							min={0}
+							max={10000}
+							suffix="ms"
+						/>
 					</div>
 				);
 			case "memory":
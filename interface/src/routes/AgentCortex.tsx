@@ -27,16 +27,28 @@ const EVENT_CATEGORY_COLORS: Record<string, string> = {
 	worker_killed: "bg-amber-500/15 text-amber-400",
 	branch_killed: "bg-amber-500/15 text-amber-400",
 	circuit_breaker_tripped: "bg-amber-500/15 text-amber-400",
+	circuit_breaker_closed: "bg-amber-500/15 text-amber-400",
 	observation_created: "bg-cyan-500/15 text-cyan-400",
 	health_check: "bg-blue-500/15 text-blue-400",
+	proactive_message: "bg-pink-500/15 text-pink-400",
+	scheduled_task: "bg-teal-500/15 text-teal-400",
+	queued_task: "bg-teal-500/15 text-teal-400",
+	trigger_fired: "bg-teal-500/15 text-teal-400",
+	identity_reflection: "bg-rose-500/15 text-rose-400",
+	identity_proposal: "bg-rose-500/15 text-rose-400",
+	dream: "bg-purple-500/15 text-purple-400",
 };
 
-/** Groups for the filter pills — reduces clutter vs showing all 13 types. */
+/** Groups for the filter pills — reduces clutter vs showing all 21 types. */
 const FILTER_GROUPS: { label: string; types: CortexEventType[] }[] = [
 	{ label: "Bulletin", types: ["bulletin_generated", "bulletin_failed"] },
 	{ label: "Maintenance", types: ["maintenance_run", "memory_merged", "memory_decayed", "memory_pruned"] },
-	{ label: "Health", types: ["worker_killed", "branch_killed", "circuit_breaker_tripped", "health_check"] },
+	{ label: "Health", types: ["worker_killed", "branch_killed", "circuit_breaker_tripped", "circuit_breaker_closed", "health_check"] },
 	{ label: "Consolidation", types: ["association_created", "contradiction_flagged", "observation_created"] },
+	{ label: "Outreach", types: ["proactive_message"] },
+	{ label: "Tasks", types: ["scheduled_task", "queued_task", "trigger_fired"] },
+	{ label: "Identity", types: ["identity_reflection", "identity_proposal"] },
+	{ label: "Dreams", types: ["dream"] },
 ];
 
 function EventTypeBadge({ eventType }: { eventType: string }) {
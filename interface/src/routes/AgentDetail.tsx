@@ -715,8 +715,16 @@ const CORTEX_EVENT_COLORS: Record<string, string> = {
 	worker_killed: "bg-red-500/20 text-red-400",
 	branch_killed: "bg-red-500/20 text-red-400",
 	circuit_breaker_tripped: "bg-amber-500/20 text-amber-400",
+	circuit_breaker_closed: "bg-amber-500/20 text-amber-400",
 	observation_created: "bg-indigo-500/20 text-indigo-400",
 	health_check: "bg-gray-500/20 text-gray-400",
+	proactive_message: "bg-pink-500/20 text-pink-400",
+	scheduled_task: "bg-teal-500/20 text-teal-400",
+	queued_task: "bg-teal-500/20 text-teal-400",
+	trigger_fired: "bg-teal-500/20 text-teal-400",
+	identity_reflection: "bg-rose-500/20 text-rose-400",
+	identity_proposal: "bg-rose-500/20 text-rose-400",
+	dream: "bg-purple-500/20 text-purple-400",
 };
 
 function CortexEventsSection({
@@ -1,5 +1,6 @@
 import { useCallback, useEffect, useRef, useState } from "react";
 import { Link } from "@tanstack/react-router";
+import { useQueryClient } from "@tanstack/react-query";
 import { AnimatePresence, motion } from "framer-motion";
 import { api, type ChannelInfo, type TimelineItem, type TimelineBranchRun, type TimelineWorkerRun } from "@/api/client";
 import type { ChannelLiveState, ActiveWorker, ActiveBranch } from "@/hooks/useChannelLiveState";
@@ -40,6 +41,54 @@ function CancelButton({ onClick }: { onClick: () => void }) {
 	);
 }
 
+/** Channel title in the sub-header. Click it to rename the channel. */
+function ChannelTitle({ agentId, channelId, channel }: { agentId: string; channelId: string; channel: ChannelInfo | undefined }) {
+	const queryClient = useQueryClient();
+	const [editing, setEditing] = useState(false);
+	const [draft, setDraft] = useState("");
+	const title = channel?.title ?? channel?.display_name ?? channelId;
+
+	const save = () => {
+		setEditing(false);
+		const next = draft.trim();
+		if (!next || next === channel?.title) return;
+		api.renameChannel(agentId, channelId, next)
+			.then(() => queryClient.invalidateQueries({ queryKey: ["channels"] }))
+			.catch(console.warn);
+	};
+
+	if (editing) {
+		return (
+			<input
+				autoFocus
+				value={draft}
+				maxLength={80}
+				onChange={(e) => setDraft(e.target.value)}
+				onBlur={save}
+				onKeyDown={(e) => {
+					if (e.key === "Enter") save();
+					if (e.key === "Escape") setEditing(false);
+				}}
+				className="w-64 rounded-md border border-app-line bg-app-darkBox px-2 py-0.5 text-sm text-ink outline-none focus:border-accent"
+			/>
+		);
+	}
+
+	return (
+		<button
+			type="button"
+			onClick={() => {
+				setDraft(channel?.title ?? "");
+				setEditing(true);
+			}}
+			className="truncate text-sm font-medium text-ink hover:text-ink-dull"
+			title="Rename"
+		>
+			{title}
+		</button>
+	);
+}
+
 function LiveBranchRunItem({ item, live, channelId }: { item: TimelineBranchRun; live: ActiveBranch; channelId: string }) {
 	const displayTool = live.currentTool ?? live.lastTool;
 	return (
@@ -264,9 +313,10 @@ export function ChannelDetail({ agentId, channelId, channel, liveState, onLoadMo
 						Channels
 					</Link>
 					<span className="text-ink-faint/50">/</span>
-					<span className="text-sm font-medium text-ink">
-						{channel?.display_name ?? channelId}
-					</span>
+					<ChannelTitle agentId={agentId} channelId={channelId} channel={channel} />
+					{channel?.title && channel.display_name && (
+						<span className="text-tiny text-ink-faint">{channel.display_name}</span>
+					)}
 					{channel && (
 						<span className={`inline-flex items-center rounded-md px-1.5 py-0.5 text-tiny font-medium ${platformColor(channel.platform)}`}>
 							{platformIcon(channel.platform)}
@@ -442,6 +442,7 @@ interface ChannelInfo {
 	agent_id: string;
 	platform: string;
 	display_name: string | null;
+	title: string | null;
 	last_activity_at: string;
 }
 
@@ -465,7 +466,7 @@ function ChannelCard({
 		>
 			<div className="flex items-start justify-between">
 				<h4 className="truncate text-sm font-medium text-ink">
-					{channel.display_name ?? channel.id}
+					{channel.title ?? channel.display_name ?? channel.id}
 				</h4>
 				<div className="ml-2 flex-shrink-0">
 					<div
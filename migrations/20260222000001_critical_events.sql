@@ -0,0 +1,11 @@
+-- Process events that must reach their channel even if its event receiver
+-- lags. Rows are removed once the channel has handled the event.
+
+CREATE TABLE IF NOT EXISTS critical_events (
+    event_key TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_critical_events_channel ON critical_events(channel_id, created_at);
@@ -0,0 +1,4 @@
+-- Pinned memories are saved on purpose by a user and never decay or get
+-- pruned.
+
+ALTER TABLE memories ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
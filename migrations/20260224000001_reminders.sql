@@ -0,0 +1,14 @@
+-- Reminders set with the set_reminder tool. `source` is the JSON of the
+-- message the reminder was set from, replayed into its conversation when
+-- it's due. Times are Unix seconds. Rows are removed once delivered.
+
+CREATE TABLE IF NOT EXISTS reminders (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    message TEXT NOT NULL,
+    due_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    source TEXT NOT NULL
+);
+
+CREATE INDEX idx_reminders_due_at ON reminders(due_at);
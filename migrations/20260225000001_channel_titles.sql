@@ -0,0 +1,4 @@
+-- Short human-readable channel titles, generated after the first few turns
+-- or set through the API.
+
+ALTER TABLE channels ADD COLUMN title TEXT;
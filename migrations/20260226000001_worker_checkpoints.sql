@@ -0,0 +1,15 @@
+-- Progress of running workers, saved after every segment so they can be
+-- resumed after a restart. `history` is the JSON of the worker's messages.
+-- Rows are removed when the worker finishes or is cancelled.
+
+CREATE TABLE IF NOT EXISTS worker_checkpoints (
+    worker_id TEXT PRIMARY KEY,
+    channel_id TEXT,
+    task TEXT NOT NULL,
+    system_prompt TEXT NOT NULL,
+    history TEXT NOT NULL,
+    segments INTEGER NOT NULL DEFAULT 0,
+    resumes INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,18 @@
+-- Files workers hand back as output with the emit_artifact tool. Each file
+-- is copied to `path`, under the agent's data dir, so it outlives the
+-- workspace copy it was made from.
+
+CREATE TABLE IF NOT EXISTS worker_artifacts (
+    id TEXT PRIMARY KEY,
+    worker_id TEXT NOT NULL,
+    channel_id TEXT,
+    name TEXT NOT NULL,
+    description TEXT,
+    mime_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_worker_artifacts_worker ON worker_artifacts(worker_id);
+CREATE INDEX IF NOT EXISTS idx_worker_artifacts_created ON worker_artifacts(created_at);
@@ -0,0 +1,24 @@
+-- The cortex's task queue. Tasks are deferred by channels with the
+-- defer_task tool, added through the API or started by [[agents.tasks]]
+-- schedules, and run by detached workers in priority order. `source` is the
+-- JSON of the message a channel deferred the task from, so its result can be
+-- brought back to that conversation. Times are Unix seconds.
+
+CREATE TABLE IF NOT EXISTS task_queue (
+    id TEXT PRIMARY KEY,
+    task TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    origin TEXT NOT NULL,
+    origin_id TEXT,
+    report_to TEXT,
+    source TEXT,
+    worker_id TEXT,
+    result TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    started_at INTEGER,
+    finished_at INTEGER
+);
+
+CREATE INDEX idx_task_queue_status ON task_queue(status, priority, created_at);
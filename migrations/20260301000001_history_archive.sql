@@ -0,0 +1,16 @@
+-- Conversation history the compactor summarized or truncated away. Each
+-- compaction writes its transcript to archives/ in the agent's directory and
+-- splits it into chunks here, which the archive_search tool searches through
+-- their embeddings in the archive_embeddings LanceDB table. `path` is the
+-- transcript file, relative to the archives directory.
+
+CREATE TABLE IF NOT EXISTS history_archive (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_history_archive_channel ON history_archive(channel_id, archived_at);
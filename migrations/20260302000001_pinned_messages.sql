@@ -0,0 +1,15 @@
+-- Messages pinned in a conversation, with the pin_message channel tool or
+-- the API. The compactor keeps any history message containing a pin's
+-- content verbatim, however much it compacts. `message_id` is the pinned
+-- conversation_messages row, when the pin was made from one.
+
+CREATE TABLE IF NOT EXISTS pinned_messages (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    message_id TEXT,
+    content TEXT NOT NULL,
+    pinned_by TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pinned_messages_channel ON pinned_messages(channel_id, created_at);
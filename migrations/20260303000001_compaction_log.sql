@@ -0,0 +1,19 @@
+-- Compactions of each channel's history, for the API. One row per run,
+-- written when it finishes. `action` is trim_tool_outputs, background,
+-- aggressive or emergency_truncate; `trigger` is threshold, before_turn or
+-- manual. Token counts are the compactor's chars / 4 estimates.
+
+CREATE TABLE IF NOT EXISTS compaction_log (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    trigger TEXT NOT NULL,
+    tokens_before INTEGER NOT NULL,
+    tokens_after INTEGER NOT NULL,
+    messages_compacted INTEGER NOT NULL,
+    error TEXT,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_compaction_log_channel ON compaction_log(channel_id, started_at);
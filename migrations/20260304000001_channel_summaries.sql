@@ -0,0 +1,5 @@
+-- Rolling summary of each conversation, rewritten every few turns and shown
+-- to the channel in its system prompt. Kept apart from compaction summaries,
+-- which only cover the messages they replaced.
+
+ALTER TABLE channels ADD COLUMN summary TEXT;
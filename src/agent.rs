@@ -1,10 +1,37 @@
-//! Agent processes: channels, branches, workers, compactor, cortex.
+//! Agent processes: channels, branches, workers, compactor, cortex, voice.
 
+pub mod artifact;
+pub mod attachment_cache;
 pub mod branch;
 pub mod channel;
+pub mod checkpoint;
+pub mod circuit_breaker;
 pub mod compactor;
+pub mod confirmation;
 pub mod cortex;
 pub mod cortex_chat;
+pub mod document_extract;
+pub mod dream;
+pub mod fork;
+pub mod handoff;
+pub mod health;
+pub mod image_preprocess;
 pub mod ingestion;
+pub mod mailbox;
+pub mod outreach;
+pub mod reactions;
+pub mod reflection;
+pub mod schedule;
+pub mod snapshot;
 pub mod status;
+pub mod sub_worker;
+pub mod summary;
+pub mod task_queue;
+pub mod title;
+pub mod trigger;
+pub mod turn_hooks;
+pub mod typing;
+pub mod video_sample;
+pub mod voice;
 pub mod worker;
+pub mod worker_queue;
@@ -0,0 +1,284 @@
+//! Worker artifacts: files a worker hands back as output, alongside its
+//! result text.
+//!
+//! A worker emits an artifact with the `emit_artifact` tool. The file is
+//! copied to `<data_dir>/artifacts/<artifact id>/<name>`, so it outlives the
+//! workspace copy it was made from, and recorded in SQLite. The worker's
+//! `WorkerComplete` event lists its artifacts; the channel can send them to
+//! the user with `send_file`, and the API serves them for download.
+
+use crate::error::Result;
+use crate::{ChannelId, WorkerId};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row as _, SqlitePool};
+
+use std::path::{Path, PathBuf};
+
+/// Largest file a worker can emit as an artifact: 100 MB.
+pub const MAX_ARTIFACT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// A file a worker emitted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: String,
+    pub worker_id: WorkerId,
+    pub channel_id: Option<ChannelId>,
+    /// File name, as sent to the user.
+    pub name: String,
+    /// What the file is, in the worker's words.
+    pub description: Option<String>,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    /// Where the stored copy lives.
+    pub path: PathBuf,
+    pub created_at: String,
+}
+
+/// Worker artifact storage: files under the artifacts directory, indexed in
+/// SQLite.
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    pool: SqlitePool,
+    dir: PathBuf,
+}
+
+impl ArtifactStore {
+    /// A store keeping files under `data_dir/artifacts`.
+    pub fn new(pool: SqlitePool, data_dir: &Path) -> Self {
+        Self {
+            pool,
+            dir: data_dir.join("artifacts"),
+        }
+    }
+
+    /// Copy `source` into the store and record it as an artifact of
+    /// `worker_id`. `name` defaults to the source's file name.
+    pub async fn emit(
+        &self,
+        worker_id: WorkerId,
+        channel_id: Option<&ChannelId>,
+        source: &Path,
+        name: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<Artifact> {
+        let metadata = tokio::fs::metadata(source)
+            .await
+            .with_context(|| format!("can't read '{}'", source.display()))?;
+        if !metadata.is_file() {
+            return Err(anyhow::anyhow!("'{}' is not a file", source.display()).into());
+        }
+        if metadata.len() > MAX_ARTIFACT_BYTES {
+            return Err(anyhow::anyhow!(
+                "'{}' is too large ({} bytes, max {MAX_ARTIFACT_BYTES} bytes)",
+                source.display(),
+                metadata.len(),
+            )
+            .into());
+        }
+
+        // Only the last component, so a name can't point outside the store.
+        let name = name
+            .map(Path::new)
+            .unwrap_or(source)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "artifact".into());
+        let mime_type = mime_guess::from_path(&name)
+            .first_or_octet_stream()
+            .to_string();
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let dir = self.dir.join(&id);
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("failed to create '{}'", dir.display()))?;
+        let path = dir.join(&name);
+        let size_bytes = tokio::fs::copy(source, &path)
+            .await
+            .with_context(|| format!("failed to copy '{}'", source.display()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO worker_artifacts (id, worker_id, channel_id, name, description, mime_type, size_bytes, path)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&id)
+        .bind(worker_id.to_string())
+        .bind(channel_id.map(|channel_id| channel_id.to_string()))
+        .bind(&name)
+        .bind(description)
+        .bind(&mime_type)
+        .bind(size_bytes as i64)
+        .bind(path.to_string_lossy().as_ref())
+        .execute(&self.pool)
+        .await
+        .context("failed to record worker artifact")?;
+
+        tracing::info!(%worker_id, artifact_id = %id, %name, size_bytes, "worker artifact emitted");
+
+        self.get(&id)
+            .await?
+            .context("worker artifact missing after insert")
+            .map_err(Into::into)
+    }
+
+    /// One artifact by ID.
+    pub async fn get(&self, id: &str) -> Result<Option<Artifact>> {
+        let row = sqlx::query(&format!("{SELECT_ARTIFACTS} WHERE id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to load worker artifact")?;
+
+        Ok(row.as_ref().and_then(artifact_from_row))
+    }
+
+    /// A worker's artifacts, in the order it emitted them.
+    pub async fn list_for_worker(&self, worker_id: WorkerId) -> Result<Vec<Artifact>> {
+        let rows = sqlx::query(&format!(
+            "{SELECT_ARTIFACTS} WHERE worker_id = ? ORDER BY created_at ASC, rowid ASC"
+        ))
+        .bind(worker_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load worker artifacts")?;
+
+        Ok(rows.iter().filter_map(artifact_from_row).collect())
+    }
+
+    /// Hand a sub-worker's artifacts to the worker that spawned it, so they
+    /// roll up with its result. Returns the artifacts handed over.
+    pub async fn reassign(&self, from: WorkerId, to: WorkerId) -> Result<Vec<Artifact>> {
+        let artifacts = self.list_for_worker(from).await?;
+        sqlx::query("UPDATE worker_artifacts SET worker_id = ? WHERE worker_id = ?")
+            .bind(to.to_string())
+            .bind(from.to_string())
+            .execute(&self.pool)
+            .await
+            .context("failed to reassign worker artifacts")?;
+
+        Ok(artifacts)
+    }
+
+    /// The most recent artifacts of all workers, newest first.
+    pub async fn list_recent(&self, limit: i64) -> Result<Vec<Artifact>> {
+        let rows = sqlx::query(&format!(
+            "{SELECT_ARTIFACTS} ORDER BY created_at DESC, rowid DESC LIMIT ?"
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load worker artifacts")?;
+
+        Ok(rows.iter().filter_map(artifact_from_row).collect())
+    }
+}
+
+const SELECT_ARTIFACTS: &str = "SELECT id, worker_id, channel_id, name, description, mime_type, \
+                                size_bytes, path, created_at FROM worker_artifacts";
+
+fn artifact_from_row(row: &sqlx::sqlite::SqliteRow) -> Option<Artifact> {
+    Some(Artifact {
+        id: row.try_get("id").ok()?,
+        worker_id: row.try_get::<String, _>("worker_id").ok()?.parse().ok()?,
+        channel_id: row
+            .try_get::<Option<String>, _>("channel_id")
+            .ok()
+            .flatten()
+            .map(ChannelId::from),
+        name: row.try_get("name").ok()?,
+        description: row.try_get("description").ok().flatten(),
+        mime_type: row.try_get("mime_type").ok()?,
+        size_bytes: row.try_get::<i64, _>("size_bytes").unwrap_or(0) as u64,
+        path: PathBuf::from(row.try_get::<String, _>("path").ok()?),
+        created_at: row.try_get("created_at").unwrap_or_default(),
+    })
+}
+
+/// How a worker's artifacts are listed under its result, with the paths the
+/// channel sends them from.
+pub fn format_for_history(artifacts: &[Artifact]) -> Option<String> {
+    if artifacts.is_empty() {
+        return None;
+    }
+    let mut text = String::from("Artifacts:");
+    for artifact in artifacts {
+        text.push_str(&format!(
+            "\n- {} ({}, {} bytes): {}",
+            artifact.name,
+            artifact.mime_type,
+            artifact.size_bytes,
+            artifact.path.display()
+        ));
+        if let Some(description) = &artifact.description {
+            text.push_str(&format!(" — {description}"));
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn emitted_artifacts_are_copied_and_listed() {
+        let pool = crate::db::test_pool().await;
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::new(pool, data_dir.path());
+
+        let source = data_dir.path().join("report.csv");
+        tokio::fs::write(&source, "a,b\n1,2\n").await.unwrap();
+        let worker_id = uuid::Uuid::new_v4();
+        let channel_id = ChannelId::from("discord:1");
+
+        let artifact = store
+            .emit(
+                worker_id,
+                Some(&channel_id),
+                &source,
+                None,
+                Some("the numbers"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(artifact.name, "report.csv");
+        assert_eq!(artifact.mime_type, "text/csv");
+        assert_eq!(artifact.size_bytes, 8);
+        assert!(artifact.path.starts_with(data_dir.path().join("artifacts")));
+        assert_eq!(
+            tokio::fs::read(&artifact.path).await.unwrap(),
+            b"a,b\n1,2\n"
+        );
+
+        // A name can't escape the artifact's directory.
+        let renamed = store
+            .emit(worker_id, None, &source, Some("../../etc/passwd"), None)
+            .await
+            .unwrap();
+        assert_eq!(renamed.name, "passwd");
+
+        let listed = store.list_for_worker(worker_id).await.unwrap();
+        let ids = listed.iter().map(|a| a.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, [artifact.id.as_str(), renamed.id.as_str()]);
+        assert!(
+            store
+                .list_for_worker(uuid::Uuid::new_v4())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let parent = uuid::Uuid::new_v4();
+        assert_eq!(store.reassign(worker_id, parent).await.unwrap().len(), 2);
+        assert_eq!(store.list_for_worker(parent).await.unwrap().len(), 2);
+
+        let history = format_for_history(&listed).unwrap();
+        assert!(history.contains("report.csv (text/csv, 8 bytes)"));
+        assert!(history.contains("the numbers"));
+    }
+}
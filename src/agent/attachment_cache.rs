@@ -0,0 +1,431 @@
+//! Size-limited attachment downloads with an on-disk cache.
+//!
+//! Downloads are keyed by a SHA-256 hash of the URL and stored under the
+//! agent's data directory. A message that gets re-sent, replayed after an
+//! outage or retriggered reuses the cached bytes instead of fetching
+//! multi-megabyte files again before every turn. The cache has a total size
+//! limit: each write evicts expired entries, then the oldest ones until the
+//! cache fits. Bodies are read in chunks
+//! and abandoned as soon as they pass the size limit. Large files can be
+//! streamed straight to disk with [`fetch_attachment_to_file`] instead of
+//! being held in memory.
+//!
+//! Platforms that deliver attachments inline, like email, hand them over as
+//! base64 `data:` URLs. Those are decoded in place: nothing is fetched and
+//! nothing is cached.
+
+use base64::Engine as _;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt as _;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Why an attachment couldn't be fetched.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("attachment is {size} bytes, over the {limit} byte limit")]
+    TooLarge { size: u64, limit: u64 },
+    #[error("can't download attachment: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("can't write attachment: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("attachment has a malformed data URL")]
+    InvalidDataUrl,
+}
+
+/// On-disk cache of downloaded attachments, keyed by URL.
+#[derive(Debug, Clone)]
+pub struct AttachmentCache {
+    directory: PathBuf,
+    ttl: Duration,
+    max_size: u64,
+}
+
+impl AttachmentCache {
+    pub fn new(directory: impl Into<PathBuf>, ttl: Duration, max_size: u64) -> Self {
+        Self {
+            directory: directory.into(),
+            ttl,
+            max_size,
+        }
+    }
+
+    /// Cache location for an agent's data directory.
+    pub fn directory_for(data_dir: &Path) -> PathBuf {
+        data_dir.join("attachment_cache")
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.directory
+            .join(hex::encode(Sha256::digest(url.as_bytes())))
+    }
+
+    /// Path of the cached entry for a URL, unless missing or older than the TTL.
+    async fn fresh_entry(&self, url: &str) -> Option<PathBuf> {
+        let path = self.entry_path(url);
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or(Duration::MAX);
+        if age > self.ttl {
+            tokio::fs::remove_file(&path).await.ok();
+            return None;
+        }
+        Some(path)
+    }
+
+    /// Cached bytes for a URL, unless missing or older than the TTL.
+    pub async fn get(&self, url: &str) -> Option<Vec<u8>> {
+        let path = self.fresh_entry(url).await?;
+        tokio::fs::read(&path).await.ok()
+    }
+
+    /// Copy the cached entry for a URL to `destination`. Returns its size, or
+    /// `None` when there is no fresh entry.
+    pub async fn copy_to(&self, url: &str, destination: &Path) -> Option<u64> {
+        let path = self.fresh_entry(url).await?;
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent).await.ok()?;
+        }
+        tokio::fs::copy(&path, destination).await.ok()
+    }
+
+    /// Store downloaded bytes. Failures are logged and otherwise ignored,
+    /// since the download itself already succeeded. Entries bigger than the
+    /// whole cache aren't stored.
+    pub async fn put(&self, url: &str, bytes: &[u8]) {
+        if bytes.len() as u64 > self.max_size {
+            return;
+        }
+        let path = self.entry_path(url);
+        // Write to a temporary name first so a concurrent reader never sees
+        // a partial file.
+        let temporary = path.with_extension("partial");
+        let result = async {
+            tokio::fs::create_dir_all(&self.directory).await?;
+            tokio::fs::write(&temporary, bytes).await?;
+            tokio::fs::rename(&temporary, &path).await
+        }
+        .await;
+        if let Err(error) = result {
+            tracing::debug!(%error, path = %path.display(), "can't write attachment cache entry");
+            tokio::fs::remove_file(&temporary).await.ok();
+            return;
+        }
+        self.evict().await;
+    }
+
+    /// Store a file that was downloaded to disk. Failures are logged and
+    /// otherwise ignored, like [`put`](Self::put).
+    pub async fn put_file(&self, url: &str, source: &Path) {
+        let fits = tokio::fs::metadata(source)
+            .await
+            .is_ok_and(|metadata| metadata.len() <= self.max_size);
+        if !fits {
+            return;
+        }
+        let path = self.entry_path(url);
+        let temporary = path.with_extension("partial");
+        let result = async {
+            tokio::fs::create_dir_all(&self.directory).await?;
+            tokio::fs::copy(source, &temporary).await?;
+            tokio::fs::rename(&temporary, &path).await
+        }
+        .await;
+        if let Err(error) = result {
+            tracing::debug!(%error, path = %path.display(), "can't write attachment cache entry");
+            tokio::fs::remove_file(&temporary).await.ok();
+            return;
+        }
+        self.evict().await;
+    }
+
+    /// Remove expired entries, then the oldest ones until the cache fits in
+    /// `max_size`.
+    async fn evict(&self) {
+        let Ok(mut directory) = tokio::fs::read_dir(&self.directory).await else {
+            return;
+        };
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        let mut total = 0u64;
+        while let Ok(Some(entry)) = directory.next_entry().await {
+            let path = entry.path();
+            // Temporary `.partial` files belong to writes still in progress.
+            if path.extension().is_some() {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            if now.duration_since(modified).unwrap_or_default() > self.ttl {
+                tokio::fs::remove_file(&path).await.ok();
+                continue;
+            }
+            total += metadata.len();
+            entries.push((modified, metadata.len(), path));
+        }
+
+        entries.sort_by_key(|(modified, ..)| *modified);
+        for (_, size, path) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            if tokio::fs::remove_file(&path).await.is_ok() {
+                tracing::debug!(path = %path.display(), size, "evicted attachment cache entry");
+                total -= size;
+            }
+        }
+    }
+}
+
+/// Whether an attachment travels inline as a `data:` URL.
+pub fn is_data_url(url: &str) -> bool {
+    url.starts_with("data:")
+}
+
+/// Carry attachment bytes inline as a base64 `data:` URL.
+pub fn data_url(mime_type: &str, bytes: &[u8]) -> String {
+    format!(
+        "data:{mime_type};base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// Decode a base64 `data:` URL. Returns `None` for any other URL.
+fn decode_data_url(url: &str, max_bytes: u64) -> Option<Result<Vec<u8>, FetchError>> {
+    let payload = url.strip_prefix("data:")?;
+    let decoded = payload
+        .split_once(',')
+        .filter(|(header, _)| header.ends_with(";base64"))
+        .and_then(|(_, data)| base64::engine::general_purpose::STANDARD.decode(data).ok())
+        .ok_or(FetchError::InvalidDataUrl)
+        .and_then(|bytes| {
+            if bytes.len() as u64 > max_bytes {
+                Err(FetchError::TooLarge {
+                    size: bytes.len() as u64,
+                    limit: max_bytes,
+                })
+            } else {
+                Ok(bytes)
+            }
+        });
+    Some(decoded)
+}
+
+/// Download an attachment, using the cache when given one. The body is
+/// rejected once it exceeds `max_bytes`, whether or not the server sent a
+/// `Content-Length`.
+pub async fn fetch_attachment(
+    http: &reqwest::Client,
+    url: &str,
+    max_bytes: u64,
+    cache: Option<&AttachmentCache>,
+) -> Result<Vec<u8>, FetchError> {
+    if let Some(decoded) = decode_data_url(url, max_bytes) {
+        return decoded;
+    }
+    if let Some(cache) = cache
+        && let Some(bytes) = cache.get(url).await
+    {
+        tracing::debug!(size = bytes.len(), "attachment served from cache");
+        return Ok(bytes);
+    }
+
+    let mut response = http.get(url).send().await?.error_for_status()?;
+    if let Some(size) = response.content_length()
+        && size > max_bytes
+    {
+        return Err(FetchError::TooLarge {
+            size,
+            limit: max_bytes,
+        });
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > max_bytes {
+            return Err(FetchError::TooLarge {
+                size: bytes.len() as u64,
+                limit: max_bytes,
+            });
+        }
+    }
+
+    if let Some(cache) = cache {
+        cache.put(url, &bytes).await;
+    }
+    Ok(bytes)
+}
+
+/// Stream an attachment to `destination` without holding it in memory,
+/// using the cache when given one. Returns the file size. The size limit is
+/// enforced as in [`fetch_attachment`], and a partial file is removed on
+/// failure.
+pub async fn fetch_attachment_to_file(
+    http: &reqwest::Client,
+    url: &str,
+    max_bytes: u64,
+    cache: Option<&AttachmentCache>,
+    destination: &Path,
+) -> Result<u64, FetchError> {
+    if let Some(decoded) = decode_data_url(url, max_bytes) {
+        let bytes = decoded?;
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(destination, &bytes).await?;
+        return Ok(bytes.len() as u64);
+    }
+    if let Some(cache) = cache
+        && let Some(size) = cache.copy_to(url, destination).await
+    {
+        tracing::debug!(size, "attachment served from cache");
+        return Ok(size);
+    }
+
+    match stream_to_file(http, url, max_bytes, destination).await {
+        Ok(size) => {
+            if let Some(cache) = cache {
+                cache.put_file(url, destination).await;
+            }
+            Ok(size)
+        }
+        Err(error) => {
+            tokio::fs::remove_file(destination).await.ok();
+            Err(error)
+        }
+    }
+}
+
+async fn stream_to_file(
+    http: &reqwest::Client,
+    url: &str,
+    max_bytes: u64,
+    destination: &Path,
+) -> Result<u64, FetchError> {
+    let mut response = http.get(url).send().await?.error_for_status()?;
+    if let Some(size) = response.content_length()
+        && size > max_bytes
+    {
+        return Err(FetchError::TooLarge {
+            size,
+            limit: max_bytes,
+        });
+    }
+
+    if let Some(parent) = destination.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = tokio::fs::File::create(destination).await?;
+    let mut written = 0u64;
+    while let Some(chunk) = response.chunk().await? {
+        written += chunk.len() as u64;
+        if written > max_bytes {
+            return Err(FetchError::TooLarge {
+                size: written,
+                limit: max_bytes,
+            });
+        }
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cache_round_trips_and_expires() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AttachmentCache::new(dir.path(), Duration::from_secs(60), 1024);
+        let url = "https://cdn.example.com/report.pdf";
+
+        assert!(cache.get(url).await.is_none());
+        cache.put(url, b"contents").await;
+        assert_eq!(cache.get(url).await.as_deref(), Some(&b"contents"[..]));
+        assert!(
+            cache
+                .get("https://cdn.example.com/other.pdf")
+                .await
+                .is_none()
+        );
+
+        let expired = AttachmentCache::new(dir.path(), Duration::ZERO, 1024);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(expired.get(url).await.is_none());
+        assert!(cache.get(url).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn oldest_entries_are_evicted_past_the_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AttachmentCache::new(dir.path(), Duration::from_secs(60), 10);
+
+        cache.put("https://cdn.example.com/a", b"aaaaaa").await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        cache.put("https://cdn.example.com/b", b"bbbbbb").await;
+        assert!(cache.get("https://cdn.example.com/a").await.is_none());
+        assert_eq!(
+            cache.get("https://cdn.example.com/b").await.as_deref(),
+            Some(&b"bbbbbb"[..])
+        );
+
+        // Too big for the whole cache, so never stored.
+        cache.put("https://cdn.example.com/c", b"ccccccccccc").await;
+        assert!(cache.get("https://cdn.example.com/c").await.is_none());
+        assert!(cache.get("https://cdn.example.com/b").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn cached_files_copy_to_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AttachmentCache::new(dir.path().join("cache"), Duration::from_secs(60), 1024);
+        let url = "https://cdn.example.com/log.txt";
+        let source = dir.path().join("download.txt");
+        std::fs::write(&source, "line one\nline two\n").unwrap();
+
+        let destination = dir.path().join("workspace/attachments/log.txt");
+        assert!(cache.copy_to(url, &destination).await.is_none());
+
+        cache.put_file(url, &source).await;
+        assert_eq!(cache.copy_to(url, &destination).await, Some(18));
+        assert_eq!(
+            std::fs::read_to_string(&destination).unwrap(),
+            "line one\nline two\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn data_urls_decode_without_fetching() {
+        let http = reqwest::Client::new();
+        let url = data_url("text/plain", b"inline body");
+        assert!(is_data_url(&url));
+
+        let bytes = fetch_attachment(&http, &url, 1024, None).await.unwrap();
+        assert_eq!(bytes, b"inline body");
+        assert!(matches!(
+            fetch_attachment(&http, &url, 4, None).await,
+            Err(FetchError::TooLarge { size: 11, .. })
+        ));
+        assert!(matches!(
+            fetch_attachment(&http, "data:text/plain,plain", 1024, None).await,
+            Err(FetchError::InvalidDataUrl)
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("attachments/body.txt");
+        let size = fetch_attachment_to_file(&http, &url, 1024, None, &destination)
+            .await
+            .unwrap();
+        assert_eq!(size, 11);
+        assert_eq!(std::fs::read(&destination).unwrap(), b"inline body");
+    }
+}
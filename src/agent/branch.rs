@@ -1,19 +1,92 @@
 //! Branch: Fork context for thinking and delegation.
 
-use crate::agent::compactor::estimate_history_tokens;
+use crate::agent::compactor::{estimate_history_tokens, trim_process_history};
 use crate::error::Result;
 use crate::hooks::SpacebotHook;
+use crate::hooks::spacebot::CONCLUDED_REASON;
 use crate::llm::SpacebotModel;
 use crate::llm::routing::is_context_overflow_error;
+use crate::tools::{ConcludeTool, new_conclusion_slot};
 use crate::{AgentDeps, BranchId, ChannelId, ProcessEvent, ProcessId, ProcessType};
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use rig::tool::server::ToolServerHandle;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// Max consecutive context overflow recoveries before giving up.
 const MAX_OVERFLOW_RETRIES: usize = 2;
 
+/// Most supporting points rendered into the channel's history.
+const MAX_RENDERED_POINTS: usize = 5;
+
+/// How sure a branch is of its conclusion.
+/// Ordered from least to most confident.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Confidence::Low => write!(f, "low"),
+            Confidence::Medium => write!(f, "medium"),
+            Confidence::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A conclusion submitted through the `conclude` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BranchConclusion {
+    /// The answer to what the channel branched for.
+    pub summary: String,
+    /// What the channel should do or say next.
+    #[serde(default)]
+    pub recommendation: Option<String>,
+    #[serde(default)]
+    pub confidence: Confidence,
+    /// Facts the summary rests on.
+    #[serde(default)]
+    pub supporting_points: Vec<String>,
+}
+
+impl BranchConclusion {
+    /// The conclusion as plain text, for logs, the status block and the API.
+    pub fn to_text(&self) -> String {
+        match &self.recommendation {
+            Some(recommendation) => format!("{}\n\nRecommendation: {recommendation}", self.summary),
+            None => self.summary.clone(),
+        }
+    }
+
+    /// The conclusion as the channel sees it in its history. Supporting
+    /// points are only included when the branch isn't highly confident, so
+    /// the channel can hedge; a confident answer doesn't need its evidence.
+    pub fn render_for_channel(&self) -> String {
+        let mut rendered = format!("[Branch result]: {}", self.summary.trim());
+        if let Some(recommendation) = &self.recommendation {
+            rendered.push_str(&format!("\nRecommendation: {}", recommendation.trim()));
+        }
+        rendered.push_str(&format!("\nConfidence: {}", self.confidence));
+        if self.confidence != Confidence::High && !self.supporting_points.is_empty() {
+            rendered.push_str("\nSupporting points:");
+            for point in self.supporting_points.iter().take(MAX_RENDERED_POINTS) {
+                rendered.push_str(&format!("\n- {}", point.trim()));
+            }
+        }
+        rendered
+    }
+}
+
 /// A branch is a fork of a channel's context for thinking.
 pub struct Branch {
     pub id: BranchId,
@@ -50,7 +123,8 @@ impl Branch {
             ProcessType::Branch,
             Some(channel_id.clone()),
             deps.event_tx.clone(),
-        );
+        )
+        .with_loop_guard(deps.runtime_config.clone());
 
         Self {
             id,
@@ -90,6 +164,12 @@ impl Branch {
             .with_context(&*self.deps.agent_id, "branch")
             .with_routing((**routing).clone());
 
+        let conclusion_slot = new_conclusion_slot();
+        self.tool_server
+            .add_tool(ConcludeTool::new(conclusion_slot.clone()))
+            .await
+            .map_err(|error| crate::error::AgentError::Other(error.into()))?;
+
         let agent = AgentBuilder::new(model)
             .preamble(&self.system_prompt)
             .default_max_turns(self.max_turns)
@@ -114,6 +194,11 @@ impl Branch {
                     tracing::warn!("branch hit max turns, returning partial result");
                     break partial;
                 }
+                Err(rig::completion::PromptError::PromptCancelled { reason, .. })
+                    if reason == CONCLUDED_REASON =>
+                {
+                    break String::new();
+                }
                 Err(rig::completion::PromptError::PromptCancelled { reason, .. }) => {
                     tracing::info!(%reason, "branch cancelled");
                     break format!("Branch was cancelled: {reason}");
@@ -146,37 +231,85 @@ impl Branch {
             }
         };
 
-        // Send conclusion back to the channel
-        let _ = self.deps.event_tx.send(ProcessEvent::BranchResult {
-            agent_id: self.deps.agent_id.clone(),
-            branch_id: self.id,
-            channel_id: self.channel_id.clone(),
-            conclusion: conclusion.clone(),
-        });
+        // A submitted conclusion wins over the free text, even when the turn
+        // ended some other way after it was submitted.
+        let structured = conclusion_slot.lock().ok().and_then(|mut slot| slot.take());
+        let conclusion = match &structured {
+            Some(structured) => structured.to_text(),
+            None => conclusion,
+        };
+
+        send_result(
+            &self.deps,
+            self.id,
+            &self.channel_id,
+            &conclusion,
+            structured,
+        )
+        .await;
 
         tracing::info!("branch completed");
 
         Ok(conclusion)
     }
 
-    /// Compact history if approaching context window limit.
-    /// Removes the oldest 50% of messages when usage exceeds 70%.
+    /// Run the branch, giving up after `timeout`. A branch that runs out of
+    /// time is dropped mid-turn and concludes with a note that it timed out,
+    /// so the channel stops waiting on it.
+    pub async fn run_with_timeout(
+        self,
+        prompt: impl Into<String>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<String> {
+        let Some(timeout) = timeout else {
+            return self.run(prompt).await;
+        };
+        let (deps, branch_id, channel_id) = (self.deps.clone(), self.id, self.channel_id.clone());
+
+        match tokio::time::timeout(timeout, self.run(prompt)).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!(
+                    branch_id = %branch_id,
+                    timeout_secs = timeout.as_secs(),
+                    "branch timed out"
+                );
+                let conclusion = format!(
+                    "Branch timed out after {} seconds without a conclusion.",
+                    timeout.as_secs()
+                );
+                send_result(&deps, branch_id, &channel_id, &conclusion, None).await;
+                Ok(conclusion)
+            }
+        }
+    }
+
+    /// Compact history if approaching context window limit, with the
+    /// `compaction.branch` settings. Oversized tool results are trimmed first;
+    /// the oldest messages are only removed if that isn't enough.
     fn maybe_compact_history(&mut self) {
-        let context_window = **self.deps.runtime_config.context_window.load();
-        let estimated = estimate_history_tokens(&self.history);
-        let usage = estimated as f32 / context_window as f32;
+        let rc = &self.deps.runtime_config;
+        let context_window = **rc.context_window.load();
+        let compaction = **rc.compaction.load();
 
-        if usage < 0.70 {
+        if !trim_process_history(
+            &mut self.history,
+            context_window,
+            compaction.branch.threshold,
+            compaction.tool_output_max_chars,
+        ) {
             return;
         }
 
+        let estimated = estimate_history_tokens(&self.history);
+        let usage = estimated as f32 / context_window as f32;
         tracing::info!(
             branch_id = %self.id,
             usage = %format!("{:.0}%", usage * 100.0),
             history_len = self.history.len(),
             "branch pre-compacting history"
         );
-        self.compact_history(0.50);
+        self.compact_history(compaction.branch.fraction);
     }
 
     /// Aggressive compaction for overflow recovery. Removes 75% of messages.
@@ -209,6 +342,29 @@ impl Branch {
     }
 }
 
+/// Send a branch's conclusion back to its channel. Journaled so it survives
+/// the channel's receiver lagging.
+async fn send_result(
+    deps: &AgentDeps,
+    branch_id: BranchId,
+    channel_id: &ChannelId,
+    conclusion: &str,
+    structured: Option<BranchConclusion>,
+) {
+    crate::events::publish(
+        &deps.event_tx,
+        &crate::events::EventJournal::new(deps.sqlite_pool.clone()),
+        ProcessEvent::BranchResult {
+            agent_id: deps.agent_id.clone(),
+            branch_id,
+            channel_id: channel_id.clone(),
+            conclusion: conclusion.to_string(),
+            structured,
+        },
+    )
+    .await;
+}
+
 /// Extract the last assistant text message from a history.
 fn extract_last_assistant_text(history: &[rig::message::Message]) -> Option<String> {
     for message in history.iter().rev() {
@@ -230,3 +386,44 @@ fn extract_last_assistant_text(history: &[rig::message::Message]) -> Option<Stri
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supporting_points_are_rendered_only_when_unsure() {
+        let mut conclusion = BranchConclusion {
+            summary: "The user prefers PostgreSQL.".into(),
+            recommendation: Some("Suggest the Postgres setup.".into()),
+            confidence: Confidence::High,
+            supporting_points: vec!["Decided on PostgreSQL in March".into()],
+        };
+        assert_eq!(
+            conclusion.render_for_channel(),
+            "[Branch result]: The user prefers PostgreSQL.\n\
+             Recommendation: Suggest the Postgres setup.\n\
+             Confidence: high"
+        );
+
+        conclusion.confidence = Confidence::Low;
+        assert!(
+            conclusion
+                .render_for_channel()
+                .ends_with("Confidence: low\nSupporting points:\n- Decided on PostgreSQL in March")
+        );
+        assert_eq!(
+            conclusion.to_text(),
+            "The user prefers PostgreSQL.\n\nRecommendation: Suggest the Postgres setup."
+        );
+    }
+
+    #[test]
+    fn conclusions_default_to_medium_confidence() {
+        let conclusion: BranchConclusion =
+            serde_json::from_str(r#"{"summary": "Nothing relevant was found."}"#).unwrap();
+        assert_eq!(conclusion.confidence, Confidence::Medium);
+        assert!(conclusion.recommendation.is_none());
+        assert!(conclusion.supporting_points.is_empty());
+    }
+}
@@ -1,25 +1,47 @@
 //! Channel: User-facing conversation process.
 
-use crate::agent::branch::Branch;
+use crate::agent::artifact::{self, ArtifactStore};
+use crate::agent::attachment_cache::{
+    AttachmentCache, FetchError, fetch_attachment, fetch_attachment_to_file, is_data_url,
+};
+use crate::agent::branch::{Branch, Confidence};
+use crate::agent::checkpoint::{WorkerCheckpoint, WorkerCheckpointStore};
+use crate::agent::circuit_breaker;
 use crate::agent::compactor::Compactor;
-use crate::agent::status::StatusBlock;
-use crate::agent::worker::Worker;
-use crate::config::ApiType;
+use crate::agent::document_extract::{self, DocumentKind};
+use crate::agent::health::HealthAction;
+use crate::agent::image_preprocess::{self, ImageLimits};
+use crate::agent::status::{CANCELLED_STATUS, FAILED_STATUS, StatusBlock};
+use crate::agent::sub_worker::WorkerNesting;
+use crate::agent::task_queue::TaskPriority;
+use crate::agent::turn_hooks::{TURN_VETOED_REASON, TurnContext, TurnDecision, TurnOutcome};
+use crate::agent::typing::{TypingHolder, TypingKeepalive};
+use crate::agent::video_sample;
+use crate::agent::worker::{WORKER_FAILED_PREFIX, Worker};
+use crate::agent::worker_queue::{self, SlotRequest, Ticket};
+use crate::audit::{AuditAction, AuditLogger};
 use crate::conversation::{ChannelStore, ConversationLogger, ProcessRunLogger};
+use crate::egress::{EgressPolicy, EgressScope};
 use crate::error::{AgentError, Result};
+use crate::events::{EventFilter, EventJournal, EventSubscription};
 use crate::hooks::SpacebotHook;
 use crate::llm::SpacebotModel;
-use crate::memory::{is_semantically_duplicate, MemoryType, SearchConfig, SearchMode, SearchSort};
+use crate::llm::manager::ProviderCircuitEvent;
+use crate::llm::streaming::{CompletionDelta, partial_string_field};
+use crate::memory::{MemoryType, SearchConfig, SearchMode, SearchSort, is_semantically_duplicate};
+use crate::rbac::{Capability, Role};
 use crate::{
     AgentDeps, BranchId, ChannelId, InboundMessage, OutboundResponse, ProcessEvent, ProcessId,
     ProcessType, WorkerId,
 };
 
+use futures::StreamExt as _;
 use futures::future::join_all;
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use rig::message::{ImageMediaType, MimeType, UserContent};
 use rig::one_or_many::OneOrMany;
+use rig::tool::Tool as _;
 use rig::tool::server::ToolServer;
 use tokio::sync::broadcast;
 use tokio::sync::{RwLock, mpsc};
@@ -29,6 +51,7 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
 
 /// Debounce window for retriggers: coalesce rapid branch/worker completions
 /// into a single retrigger instead of firing one per event.
@@ -38,6 +61,43 @@ const RETRIGGER_DEBOUNCE_MS: u64 = 500;
 /// infinite retrigger cascades where each retrigger spawns more work.
 const MAX_RETRIGGERS_PER_TURN: usize = 3;
 
+/// Most branches that can race on one question.
+pub const MAX_RACE_BRANCHES: usize = 3;
+
+/// Metadata flag on messages replayed from the offline queue. Their user
+/// message was already logged when it first arrived.
+const OFFLINE_REPLAY_KEY: &str = "offline_replay";
+
+/// Metadata key set on messages from a digest-mode binding, holding the
+/// digest interval in seconds.
+pub const DIGEST_INTERVAL_KEY: &str = "digest_interval_secs";
+
+/// Metadata flag set on messages from a binding with `reply_to_message`.
+/// Adapters that support it send replies as replies to the message.
+pub const REPLY_TO_MESSAGE_KEY: &str = "reply_to_message";
+
+/// Metadata key set on messages from a binding with channel overrides,
+/// holding the [`BindingOverrides`](crate::config::BindingOverrides) as JSON.
+pub const BINDING_OVERRIDES_KEY: &str = "binding_overrides";
+
+/// Digest buffer size that triggers an early summary, so a busy channel
+/// doesn't build one enormous turn.
+const MAX_DIGEST_MESSAGES: usize = 200;
+
+/// Minimum time between previews of a reply being generated on stream-mode
+/// bindings, which keeps message edits under platform rate limits.
+const REPLY_PREVIEW_INTERVAL_MS: u64 = 1000;
+
+/// How a batch of messages came together, which decides the hint the LLM
+/// gets about it.
+#[derive(Debug, Clone, Copy)]
+enum BatchKind {
+    /// Messages that arrived in a quick burst.
+    Coalesced,
+    /// Messages accumulated by a digest-mode binding since the last summary.
+    Digest { interval_secs: u64 },
+}
+
 /// Stable prefix for injected memory context blocks.
 pub(crate) const INJECTION_BLOCK_PREFIX: &str = "[Context from memory]";
 
@@ -96,9 +156,25 @@ pub struct ChannelState {
     pub process_run_logger: ProcessRunLogger,
     /// Discord message ID to reply to for work spawned in the current turn.
     pub reply_target_message_id: Arc<RwLock<Option<u64>>>,
+    /// Latest platform message of the current turn. Forks start their
+    /// thread off it.
+    pub trigger_message: Arc<RwLock<Option<InboundMessage>>>,
+    /// RBAC role of the sender(s) that triggered the current turn.
+    pub sender_role: Arc<RwLock<Role>>,
+    /// Audit actor for the current turn (`platform:sender_id`, comma-separated
+    /// for coalesced batches).
+    pub sender_actor: Arc<RwLock<String>>,
     pub channel_store: ChannelStore,
+    /// Typing indicator, held while the user waits on the turn or on
+    /// branches and workers spawned for them.
+    pub typing: TypingKeepalive,
     pub screenshot_dir: std::path::PathBuf,
     pub logs_dir: std::path::PathBuf,
+    /// Sub-workers running under the channel's workers, counted against
+    /// `sub_workers.max_per_channel`.
+    pub sub_workers: Arc<AtomicUsize>,
+    /// The channel's compactor, for compactions requested through the API.
+    pub compactor: Arc<Compactor>,
 }
 
 impl ChannelState {
@@ -113,12 +189,24 @@ impl ChannelState {
             .remove(&worker_id)
             .is_some();
         self.worker_inputs.write().await.remove(&worker_id);
+        self.status_block.write().await.remove_worker(worker_id);
+        // An aborted worker can't delete its own checkpoint.
+        if let Err(error) = WorkerCheckpointStore::new(self.deps.sqlite_pool.clone())
+            .delete(worker_id)
+            .await
+        {
+            tracing::warn!(%worker_id, %error, "failed to delete worker checkpoint");
+        }
+
+        self.typing.release(TypingHolder::Worker(worker_id));
 
         if let Some(handle) = handle {
             handle.abort();
+            self.send_cancelled(ProcessId::Worker(worker_id));
             Ok(())
         } else if removed {
             // Worker was in active_workers but had no handle (shouldn't happen, but handle gracefully)
+            self.send_cancelled(ProcessId::Worker(worker_id));
             Ok(())
         } else {
             Err(format!("Worker {worker_id} not found"))
@@ -129,13 +217,29 @@ impl ChannelState {
     /// Returns an error message if the branch is not found.
     pub async fn cancel_branch(&self, branch_id: BranchId) -> std::result::Result<(), String> {
         let handle = self.active_branches.write().await.remove(&branch_id);
+        self.status_block.write().await.remove_branch(branch_id);
+        self.typing.release(TypingHolder::Branch(branch_id));
         if let Some(handle) = handle {
             handle.abort();
+            self.send_cancelled(ProcessId::Branch(branch_id));
             Ok(())
         } else {
             Err(format!("Branch {branch_id} not found"))
         }
     }
+
+    /// Tell agent-wide observers that a process ended without a result, since
+    /// an aborted task never sends its completion event.
+    fn send_cancelled(&self, process_id: ProcessId) {
+        self.deps
+            .event_tx
+            .send(ProcessEvent::StatusUpdate {
+                agent_id: self.deps.agent_id.clone(),
+                process_id,
+                status: CANCELLED_STATUS.to_string(),
+            })
+            .ok();
+    }
 }
 
 impl std::fmt::Debug for ChannelState {
@@ -146,6 +250,80 @@ impl std::fmt::Debug for ChannelState {
     }
 }
 
+/// Branches racing on the same question.
+#[derive(Debug, Default)]
+struct BranchRace {
+    /// Branches that haven't finished yet.
+    running: HashSet<BranchId>,
+    /// The most confident result so far, with the branch that sent it.
+    best: Option<(Confidence, BranchId, String)>,
+}
+
+impl BranchRace {
+    /// Record a racer ending, with its result, or `None` if it was cancelled
+    /// or failed. Returns whether the race is decided: by a confident result,
+    /// or by every racer having ended.
+    fn finish(&mut self, branch_id: BranchId, result: Option<(Confidence, String)>) -> bool {
+        self.running.remove(&branch_id);
+        let Some((confidence, message)) = result else {
+            return self.running.is_empty();
+        };
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(best, ..)| confidence > *best)
+        {
+            self.best = Some((confidence, branch_id, message));
+        }
+        confidence == Confidence::High || self.running.is_empty()
+    }
+}
+
+/// Messages held while every LLM provider is unreachable.
+#[derive(Debug, Default)]
+struct OfflineQueue {
+    /// Held messages, oldest first.
+    messages: VecDeque<InboundMessage>,
+    /// Whether the offline notice went out for the current outage.
+    notice_sent: bool,
+    /// Next attempt to replay the queue.
+    retry_deadline: Option<tokio::time::Instant>,
+}
+
+impl OfflineQueue {
+    /// Hold messages whose turn just failed. They're older than anything
+    /// already held, so they go to the front, whatever the bound.
+    fn requeue(&mut self, messages: Vec<InboundMessage>) {
+        for message in messages.into_iter().rev() {
+            self.messages.push_front(message);
+        }
+    }
+
+    /// Hold new arrivals at the back while fewer than `max` messages are
+    /// held. Returns how many were dropped.
+    fn push(&mut self, messages: Vec<InboundMessage>, max: usize) -> usize {
+        let mut dropped = 0;
+        for message in messages {
+            if self.messages.len() >= max {
+                dropped += 1;
+            } else {
+                self.messages.push_back(message);
+            }
+        }
+        dropped
+    }
+
+    /// Whether the offline notice should go out, once per outage.
+    fn take_notice(&mut self) -> bool {
+        !std::mem::replace(&mut self.notice_sent, true)
+    }
+
+    /// Every held message was answered, ending the outage.
+    fn drained(&mut self) {
+        self.notice_sent = false;
+    }
+}
+
 /// State for memory injection deduplication within a channel.
 ///
 /// Stored in RAM directly in Channel (not in ChannelState) because:
@@ -252,6 +430,8 @@ impl Default for ChannelInjectionState {
 /// User-facing conversation process.
 pub struct Channel {
     pub id: ChannelId,
+    /// Short title of the conversation, set once one is generated or given
+    /// through the API.
     pub title: Option<String>,
     pub deps: AgentDeps,
     pub hook: SpacebotHook,
@@ -260,8 +440,8 @@ pub struct Channel {
     pub tool_server: rig::tool::server::ToolServerHandle,
     /// Input channel for receiving messages.
     pub message_rx: mpsc::Receiver<InboundMessage>,
-    /// Event receiver for process events.
-    pub event_rx: broadcast::Receiver<ProcessEvent>,
+    /// Process events for this channel.
+    pub event_rx: EventSubscription,
     /// Outbound response sender for the messaging layer.
     pub response_tx: mpsc::Sender<OutboundResponse>,
     /// Self-sender for re-triggering the channel after background process completion.
@@ -271,19 +451,35 @@ pub struct Channel {
     /// Conversation context (platform, channel name, server) captured from the first message.
     pub conversation_context: Option<String>,
     /// Context monitor that triggers background compaction.
-    pub compactor: Compactor,
+    pub compactor: Arc<Compactor>,
     /// Count of user messages since last memory persistence branch.
     message_count: usize,
     /// Branch IDs for silent memory persistence branches (results not injected into history).
     memory_persistence_branches: HashSet<BranchId>,
     /// Optional Discord reply target captured when each branch was started.
     branch_reply_targets: HashMap<BranchId, u64>,
+    /// Undecided branch races, and the race each of their branches is in.
+    branch_races: HashMap<uuid::Uuid, BranchRace>,
+    racing_branches: HashMap<BranchId, uuid::Uuid>,
+    /// Branches that lost a race. A result they sent before being cancelled
+    /// is dropped.
+    race_losers: HashSet<BranchId>,
     /// Buffer for coalescing rapid-fire messages.
     coalesce_buffer: Vec<InboundMessage>,
     /// Deadline for flushing the coalesce buffer.
     coalesce_deadline: Option<tokio::time::Instant>,
+    /// Messages from a digest-mode binding waiting for the next summary.
+    digest_buffer: Vec<InboundMessage>,
+    /// When the digest buffer is summarized.
+    digest_deadline: Option<tokio::time::Instant>,
     /// Current turn number (incremented after each user message).
     current_turn: usize,
+    /// Whether title generation was started for this channel.
+    title_requested: bool,
+    /// Rolling summary of the whole conversation, shown in the system prompt.
+    summary: Option<String>,
+    /// Turn the summary was last updated at.
+    summarized_at_turn: usize,
     /// State for memory injection deduplication.
     injection_state: ChannelInjectionState,
     /// Number of retriggers fired since the last real user message.
@@ -294,6 +490,16 @@ pub struct Channel {
     pending_retrigger_metadata: HashMap<String, serde_json::Value>,
     /// Deadline for firing the pending retrigger (debounce timer).
     retrigger_deadline: Option<tokio::time::Instant>,
+    /// Messages held while every LLM provider is unreachable.
+    offline: OfflineQueue,
+    /// When the live status card is next posted or refreshed.
+    live_status_deadline: Option<tokio::time::Instant>,
+    /// Whether the live status card is up in the conversation.
+    live_status_posted: bool,
+    /// Last inbound message, from which the idle timeout runs.
+    last_activity: tokio::time::Instant,
+    /// Provider circuit changes, used to replay the offline queue on recovery.
+    circuit_rx: broadcast::Receiver<ProviderCircuitEvent>,
 }
 
 impl Channel {
@@ -317,7 +523,8 @@ impl Channel {
             ProcessType::Channel,
             Some(id.clone()),
             deps.event_tx.clone(),
-        );
+        )
+        .with_loop_guard(deps.runtime_config.clone());
         let status_block = Arc::new(RwLock::new(StatusBlock::new()));
         let history = Arc::new(RwLock::new(Vec::new()));
         let active_branches = Arc::new(RwLock::new(HashMap::new()));
@@ -328,7 +535,7 @@ impl Channel {
         let process_run_logger = ProcessRunLogger::new(deps.sqlite_pool.clone());
         let channel_store = ChannelStore::new(deps.sqlite_pool.clone());
 
-        let compactor = Compactor::new(id.clone(), deps.clone(), history.clone());
+        let compactor = Arc::new(Compactor::new(id.clone(), deps.clone(), history.clone()));
 
         let state = ChannelState {
             channel_id: id.clone(),
@@ -342,9 +549,15 @@ impl Channel {
             conversation_logger,
             process_run_logger,
             reply_target_message_id: Arc::new(RwLock::new(None)),
+            trigger_message: Arc::new(RwLock::new(None)),
+            sender_role: Arc::new(RwLock::new(Role::Guest)),
+            sender_actor: Arc::new(RwLock::new(String::new())),
             channel_store,
+            typing: TypingKeepalive::new(response_tx.clone()),
             screenshot_dir,
             logs_dir,
+            sub_workers: Arc::new(AtomicUsize::new(0)),
+            compactor: compactor.clone(),
         };
 
         // Each channel gets its own isolated tool server to avoid races between
@@ -352,6 +565,14 @@ impl Channel {
         let tool_server = ToolServer::new().run();
 
         let self_tx = message_tx.clone();
+        let circuit_rx = deps.llm_manager.subscribe_circuit_events();
+        let event_rx = EventSubscription::new(
+            deps.agent_id.clone(),
+            "channel",
+            event_rx,
+            EventFilter::all().channel(id.clone()),
+        )
+        .with_journal(EventJournal::new(deps.sqlite_pool.clone()));
         let channel = Self {
             id: id.clone(),
             title: None,
@@ -369,32 +590,113 @@ impl Channel {
             message_count: 0,
             memory_persistence_branches: HashSet::new(),
             branch_reply_targets: HashMap::new(),
+            branch_races: HashMap::new(),
+            racing_branches: HashMap::new(),
+            race_losers: HashSet::new(),
             coalesce_buffer: Vec::new(),
             coalesce_deadline: None,
+            digest_buffer: Vec::new(),
+            digest_deadline: None,
             current_turn: 0,
+            title_requested: false,
+            summary: None,
+            summarized_at_turn: 0,
             injection_state: ChannelInjectionState::new(),
             retrigger_count: 0,
             pending_retrigger: false,
             pending_retrigger_metadata: HashMap::new(),
             retrigger_deadline: None,
+            offline: OfflineQueue::default(),
+            live_status_deadline: None,
+            live_status_posted: false,
+            last_activity: tokio::time::Instant::now(),
+            circuit_rx,
         };
 
         (channel, message_tx)
     }
 
+    /// Restore up to `limit` recent messages from the conversation log into
+    /// history, for a channel materialized for a conversation that was active
+    /// before. Returns how many messages were restored.
+    pub async fn rehydrate_history(&self, limit: usize) -> usize {
+        let messages = match self
+            .state
+            .conversation_logger
+            .load_recent(&self.id, limit as i64)
+            .await
+        {
+            Ok(messages) => messages,
+            Err(error) => {
+                tracing::warn!(%error, channel_id = %self.id, "can't load conversation log to restore history");
+                return 0;
+            }
+        };
+        if messages.is_empty() {
+            return 0;
+        }
+
+        let mut transcript = String::new();
+        for message in &messages {
+            let label = if message.role == "assistant" {
+                "(you)"
+            } else {
+                message.sender_name.as_deref().unwrap_or("user")
+            };
+            transcript.push_str(&format!("{label}: {}\n", message.content));
+        }
+
+        let prompt_engine = self.deps.runtime_config.prompts.load();
+        let restored_text = prompt_engine
+            .render_system_history_backfill(transcript.trim_end())
+            .unwrap_or(transcript);
+        self.state
+            .history
+            .write()
+            .await
+            .push(rig::message::Message::from(restored_text));
+        messages.len()
+    }
+
     /// Run the channel event loop.
     pub async fn run(mut self) -> Result<()> {
         let channel_id = self.id.clone();
         tracing::info!(channel_id = %channel_id, "channel started");
 
+        match self.state.channel_store.summary(&channel_id).await {
+            Ok(summary) => self.summary = summary,
+            Err(error) => tracing::warn!(%error, "failed to load conversation summary"),
+        }
+
+        let mut hibernating = false;
+        let mut draining = false;
         loop {
-            // Compute next deadline from coalesce and retrigger timers
-            let next_deadline = match (self.coalesce_deadline, self.retrigger_deadline) {
-                (Some(a), Some(b)) => Some(a.min(b)),
-                (Some(a), None) => Some(a),
-                (None, Some(b)) => Some(b),
-                (None, None) => None,
-            };
+            // The sender is dropped at shutdown, or when the conversation
+            // moves to another channel: finish what's in flight, then stop.
+            if self.message_rx.is_closed() && self.message_rx.is_empty() {
+                if !draining {
+                    draining = true;
+                    tracing::info!(channel_id = %channel_id, "channel draining");
+                    self.start_drain().await;
+                }
+                if self.is_drained().await {
+                    break;
+                }
+            }
+
+            // Compute next deadline from coalesce, digest, retrigger, offline retry,
+            // live status and idle timers
+            let next_deadline = [
+                self.coalesce_deadline,
+                self.digest_deadline,
+                self.retrigger_deadline,
+                self.offline.retry_deadline,
+                self.live_status_deadline,
+                self.idle_deadline(),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
             let sleep_duration = next_deadline
                 .map(|deadline| {
                     let now = tokio::time::Instant::now();
@@ -407,9 +709,56 @@ impl Channel {
                 .unwrap_or(std::time::Duration::from_secs(3600)); // Default long timeout if no deadline
 
             tokio::select! {
-                Some(message) = self.message_rx.recv() => {
+                Some(mut message) = self.message_rx.recv() => {
+                    self.last_activity = tokio::time::Instant::now();
                     let config = self.deps.runtime_config.coalesce.load();
-                    if self.should_coalesce(&message, &config) {
+                    if let Some((history, parent)) = crate::agent::fork::take_seed(&mut message) {
+                        self.adopt_fork(history, &parent).await;
+                        // A fork without a topic only carries history.
+                        if message.content.to_string().is_empty() {
+                            continue;
+                        }
+                    }
+                    if let Some(snapshot) = crate::agent::snapshot::take_seed(&mut message) {
+                        self.adopt_snapshot(snapshot).await;
+                        continue;
+                    }
+                    if let Some(handoff) = crate::agent::handoff::take_seed(&mut message) {
+                        self.adopt_handoff(&mut message, &handoff).await;
+                    }
+                    if matches!(
+                        message.content,
+                        crate::MessageContent::Text(_) | crate::MessageContent::Media { .. }
+                    ) {
+                        self.acknowledge(&message).await;
+                    }
+                    if matches!(message.content, crate::MessageContent::Reaction { .. }) {
+                        // Only signal reactions start a turn, as the text they stand for.
+                        match self.handle_reaction(&message).await {
+                            Some(signal) => message = signal,
+                            None => continue,
+                        }
+                    }
+                    // Checked before buffering, so coalesced, digest and held
+                    // messages can't carry a command past RBAC.
+                    if message.metadata.contains_key("slack_command")
+                        && !self.allow_command(&message).await
+                    {
+                        continue;
+                    }
+                    if let crate::MessageContent::Interaction { action_id, .. } = &message.content
+                        && let Some(process_id) =
+                            crate::agent::status::parse_cancel_action(action_id)
+                    {
+                        self.handle_cancel_button(&message, process_id).await;
+                    } else if let Some(topic) = crate::agent::fork::parse_command(&message) {
+                        self.handle_fork_command(&message, &topic).await;
+                    } else if let Some(interval_secs) = digest_interval(&message) {
+                        self.queue_digest(message, interval_secs).await;
+                    } else if !self.offline.messages.is_empty() || self.llm_offline().await {
+                        // Keep arrival order: nothing overtakes the held backlog.
+                        self.hold_offline(vec![message], false).await;
+                    } else if self.should_coalesce(&message, &config) {
                         self.coalesce_buffer.push(message);
                         self.update_coalesce_deadline(&config).await;
                     } else {
@@ -422,7 +771,7 @@ impl Channel {
                         }
                     }
                 }
-                Ok(event) = self.event_rx.recv() => {
+                Some(event) = self.event_rx.recv() => {
                     // Events bypass coalescing - flush buffer first if needed
                     if let Err(error) = self.flush_coalesce_buffer().await {
                         tracing::error!(%error, channel_id = %channel_id, "error flushing coalesce buffer");
@@ -431,6 +780,13 @@ impl Channel {
                         tracing::error!(%error, channel_id = %channel_id, "error handling event");
                     }
                 }
+                Ok(event) = self.circuit_rx.recv() => {
+                    if matches!(event, ProviderCircuitEvent::Closed { .. })
+                        && !self.offline.messages.is_empty()
+                    {
+                        self.replay_offline_queue().await;
+                    }
+                }
                 _ = tokio::time::sleep(sleep_duration), if next_deadline.is_some() => {
                     let now = tokio::time::Instant::now();
                     // Check coalesce deadline
@@ -439,30 +795,148 @@ impl Channel {
                             tracing::error!(%error, channel_id = %self.id, "error flushing coalesce buffer on deadline");
                         }
                     }
+                    // Check digest deadline
+                    if self.digest_deadline.is_some_and(|d| d <= now) {
+                        if let Err(error) = self.flush_digest().await {
+                            tracing::error!(%error, channel_id = %self.id, "error flushing digest");
+                        }
+                    }
                     // Check retrigger deadline
                     if self.retrigger_deadline.is_some_and(|d| d <= now) {
                         self.flush_pending_retrigger().await;
                     }
+                    // Retry the offline backlog; the first turn doubles as a probe
+                    if self.offline.retry_deadline.is_some_and(|d| d <= now) {
+                        self.replay_offline_queue().await;
+                    }
+                    // Post or refresh the live status card
+                    if self.live_status_deadline.is_some_and(|d| d <= now) {
+                        self.refresh_live_status().await;
+                    }
+                    // Hibernate once idle. The next message materializes the
+                    // channel again from its conversation log.
+                    if self.idle_deadline().is_some_and(|d| d <= now) {
+                        if self.is_idle().await {
+                            hibernating = true;
+                            break;
+                        }
+                        self.last_activity = now;
+                    }
                 }
                 else => break,
             }
         }
 
+        if hibernating {
+            // Messages forwarded as the channel was stopping are still answered.
+            self.message_rx.close();
+            while let Ok(message) = self.message_rx.try_recv() {
+                if let Err(error) = self.handle_message(message).await {
+                    tracing::error!(%error, channel_id = %channel_id, "error handling message");
+                }
+            }
+            tracing::info!(channel_id = %channel_id, "channel idle, hibernating");
+        }
+
         // Flush any remaining buffer before shutting down
         if let Err(error) = self.flush_coalesce_buffer().await {
             tracing::error!(%error, channel_id = %channel_id, "error flushing coalesce buffer on shutdown");
         }
 
+        self.state.typing.clear();
+        if self.live_status_posted {
+            self.send_live_status(None, Vec::new()).await;
+        }
         tracing::info!(channel_id = %channel_id, "channel stopped");
         Ok(())
     }
 
+    /// When the channel hibernates unless a message arrives first.
+    fn idle_deadline(&self) -> Option<tokio::time::Instant> {
+        let idle_timeout_secs = self
+            .deps
+            .runtime_config
+            .hibernation
+            .load()
+            .idle_timeout_secs;
+        (idle_timeout_secs > 0)
+            .then(|| self.last_activity + std::time::Duration::from_secs(idle_timeout_secs))
+    }
+
+    /// Whether the channel can stop without losing work: nothing buffered or
+    /// held and no branch or worker running.
+    async fn is_idle(&self) -> bool {
+        self.message_rx.is_empty()
+            && self.coalesce_buffer.is_empty()
+            && self.digest_buffer.is_empty()
+            && self.offline.messages.is_empty()
+            && !self.pending_retrigger
+            && !self.live_status_posted
+            && self.state.active_branches.read().await.is_empty()
+            && self.state.active_workers.read().await.is_empty()
+    }
+
+    /// Start the work a stopping channel owes before it goes: buffered and
+    /// held messages, a pending digest, and memories from what's been said
+    /// since the last memory persistence. While the LLM is unreachable, held
+    /// messages and the digest are left in the conversation log instead of
+    /// holding up shutdown.
+    async fn start_drain(&mut self) {
+        if let Err(error) = self.flush_coalesce_buffer().await {
+            tracing::error!(%error, channel_id = %self.id, "error flushing coalesce buffer");
+        }
+        if !self.offline.messages.is_empty() && !self.llm_offline().await {
+            self.replay_offline_queue().await;
+        }
+        if !self.digest_buffer.is_empty()
+            && let Err(error) = self.flush_digest().await
+        {
+            tracing::error!(%error, channel_id = %self.id, "error flushing digest");
+        }
+        if !self.offline.messages.is_empty() || !self.digest_buffer.is_empty() {
+            tracing::info!(
+                channel_id = %self.id,
+                held = self.offline.messages.len(),
+                digest = self.digest_buffer.len(),
+                "LLM providers unreachable, stopping with messages logged but unanswered"
+            );
+            self.offline = OfflineQueue::default();
+            self.digest_buffer.clear();
+            self.digest_deadline = None;
+        }
+        let config = **self.deps.runtime_config.memory_persistence.load();
+        if config.enabled && self.message_count > 0 {
+            self.persist_memories().await;
+        }
+    }
+
+    /// Whether a stopping channel has nothing left to finish: no buffered
+    /// message, pending relay, branch or running worker. Workers waiting in
+    /// the worker queue don't count, since shutdown doesn't start them.
+    async fn is_drained(&self) -> bool {
+        if !self.coalesce_buffer.is_empty()
+            || !self.digest_buffer.is_empty()
+            || self.pending_retrigger
+            || !self.state.active_branches.read().await.is_empty()
+        {
+            return false;
+        }
+        let worker_slots = &self.deps.runtime_config.worker_slots;
+        self.state
+            .worker_handles
+            .read()
+            .await
+            .keys()
+            .all(|worker_id| worker_slots.position(*worker_id).is_some())
+    }
+
     /// Determine if a message should be coalesced (batched with other messages).
     ///
     /// Returns false for:
     /// - System re-trigger messages (always process immediately)
     /// - Messages when coalescing is disabled
-    /// - Messages in DMs when multi_user_only is true
+    /// - Messages in DMs when multi_user_only is true, unless the per-sender
+    ///   debounce is on
     fn should_coalesce(
         &self,
         message: &InboundMessage,
@@ -474,7 +948,7 @@ impl Channel {
         if message.source == "system" {
             return false;
         }
-        if config.multi_user_only && self.is_dm() {
+        if config.multi_user_only && config.sender_debounce_ms == 0 && self.is_dm() {
             return false;
         }
         true
@@ -505,8 +979,21 @@ impl Channel {
             let max_wait_ms = config.max_wait_ms;
             let debounce_ms = config.debounce_ms;
 
-            // If we have enough messages to trigger coalescing (min_messages threshold)
-            if self.coalesce_buffer.len() >= config.min_messages {
+            let single_sender = self
+                .coalesce_buffer
+                .iter()
+                .all(|message| message.sender_id == first_message.sender_id);
+
+            // One sender typing several short messages: every new message
+            // restarts the window, capped at max_wait from the first.
+            if config.sender_debounce_ms > 0 && single_sender {
+                let remaining_wait_ms = max_wait_ms.saturating_sub(elapsed_millis);
+                let max_deadline = now + std::time::Duration::from_millis(remaining_wait_ms);
+                let new_deadline =
+                    now + std::time::Duration::from_millis(config.sender_debounce_ms);
+                self.coalesce_deadline = Some(new_deadline.min(max_deadline));
+            } else if self.coalesce_buffer.len() >= config.min_messages {
+                // Enough messages to trigger coalescing (min_messages threshold)
                 // Cap at max_wait from the first message
                 let remaining_wait_ms = max_wait_ms.saturating_sub(elapsed_millis);
                 let max_deadline = now + std::time::Duration::from_millis(remaining_wait_ms);
@@ -541,23 +1028,108 @@ impl Channel {
 
         let messages: Vec<InboundMessage> = std::mem::take(&mut self.coalesce_buffer);
 
+        if self.llm_offline().await {
+            self.hold_offline(messages, false).await;
+            return Ok(());
+        }
+
         if messages.len() == 1 {
             // Single message - process normally
-            let message = messages.into_iter().next().ok_or_else(|| anyhow::anyhow!("empty iterator after length check"))?;
+            let message = messages
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("empty iterator after length check"))?;
             self.handle_message(message).await
         } else {
             // Multiple messages - batch them
-            self.handle_message_batch(messages).await
+            self.handle_message_batch(messages, BatchKind::Coalesced)
+                .await
+        }
+    }
+
+    /// Hold a message from a digest-mode binding until the next summary.
+    ///
+    /// The message is logged right away so the conversation log stays
+    /// complete while the summary is pending.
+    async fn queue_digest(&mut self, message: InboundMessage, interval_secs: u64) {
+        if self.conversation_id.is_none() {
+            self.conversation_id = Some(message.conversation_id.clone());
+        }
+        self.log_held_message(&message);
+
+        self.digest_buffer.push(message);
+        if self.digest_deadline.is_none() {
+            self.digest_deadline =
+                Some(tokio::time::Instant::now() + std::time::Duration::from_secs(interval_secs));
         }
+
+        if self.digest_buffer.len() >= MAX_DIGEST_MESSAGES {
+            tracing::info!(channel_id = %self.id, "digest buffer full, summarizing early");
+            if let Err(error) = self.flush_digest().await {
+                tracing::error!(%error, channel_id = %self.id, "error flushing digest");
+            }
+        }
+    }
+
+    /// Log a message that waits before its turn, so the conversation log
+    /// has it even if the channel stops first. Its turn then runs as a
+    /// replay or digest, which doesn't log it again.
+    fn log_held_message(&self, message: &InboundMessage) {
+        let sender_name = message
+            .metadata
+            .get("sender_display_name")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&message.sender_id);
+        let raw_text = match &message.content {
+            crate::MessageContent::Media { text, .. } => text.clone().unwrap_or_default(),
+            content => content.to_string(),
+        };
+        self.state.conversation_logger.log_user_message(
+            &self.state.channel_id,
+            sender_name,
+            &message.sender_id,
+            &raw_text,
+            &message.metadata,
+        );
+        self.state
+            .channel_store
+            .upsert(&message.conversation_id, &message.metadata);
+    }
+
+    /// Summarize everything in the digest buffer in one turn.
+    ///
+    /// While the LLM is unreachable the buffer is kept and the summary moves
+    /// to the next interval.
+    async fn flush_digest(&mut self) -> Result<()> {
+        self.digest_deadline = None;
+        let Some(interval_secs) = self.digest_buffer.first().and_then(digest_interval) else {
+            return Ok(());
+        };
+
+        if self.llm_offline().await {
+            tracing::info!(channel_id = %self.id, "LLM providers unreachable, postponing digest");
+            self.digest_deadline =
+                Some(tokio::time::Instant::now() + std::time::Duration::from_secs(interval_secs));
+            return Ok(());
+        }
+
+        let messages = std::mem::take(&mut self.digest_buffer);
+        self.handle_message_batch(messages, BatchKind::Digest { interval_secs })
+            .await
     }
 
     /// Handle a batch of messages as a single LLM turn.
     ///
     /// Formats all messages with attribution and timestamps, persists each
     /// individually to conversation history, then presents them as one user turn
-    /// with a coalesce hint telling the LLM this is a fast-moving conversation.
+    /// with a hint telling the LLM how the batch came together: a fast-moving
+    /// conversation, or a digest to summarize.
     #[tracing::instrument(skip(self, messages), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_count = messages.len()))]
-    async fn handle_message_batch(&mut self, messages: Vec<InboundMessage>) -> Result<()> {
+    async fn handle_message_batch(
+        &mut self,
+        messages: Vec<InboundMessage>,
+        kind: BatchKind,
+    ) -> Result<()> {
         let message_count = messages.len();
         let first_timestamp = messages
             .first()
@@ -615,6 +1187,28 @@ impl Channel {
             );
         }
 
+        // A batch runs with the least privileged role among its senders so one
+        // user can't borrow another's permissions by posting at the same time.
+        // Settled before attachments download, as they can spawn workers.
+        {
+            let rbac = self.deps.runtime_config.rbac.load();
+            let batch_role = messages
+                .iter()
+                .filter(|message| message.source != "system")
+                .map(|message| rbac.resolve_role(&message.source, &message.sender_id))
+                .min();
+            if let Some(role) = batch_role {
+                *self.state.sender_role.write().await = role;
+                let actors: std::collections::BTreeSet<String> = messages
+                    .iter()
+                    .filter(|message| message.source != "system")
+                    .map(|message| format!("{}:{}", message.source, message.sender_id))
+                    .collect();
+                *self.state.sender_actor.write().await =
+                    actors.into_iter().collect::<Vec<_>>().join(",");
+            }
+        }
+
         // Persist each message to conversation log (individual audit trail)
         let mut user_contents: Vec<UserContent> = Vec::new();
         let mut conversation_id = String::new();
@@ -633,21 +1227,25 @@ impl Channel {
                         (text.clone().unwrap_or_default(), attachments.clone())
                     }
                     // Render interactions as their Display form so the LLM sees plain text.
-                    crate::MessageContent::Interaction { .. } => {
+                    crate::MessageContent::Interaction { .. }
+                    | crate::MessageContent::Reaction { .. } => {
                         (message.content.to_string(), Vec::new())
                     }
                 };
 
-                self.state.conversation_logger.log_user_message(
-                    &self.state.channel_id,
-                    sender_name,
-                    &message.sender_id,
-                    &raw_text,
-                    &message.metadata,
-                );
-                self.state
-                    .channel_store
-                    .upsert(&message.conversation_id, &message.metadata);
+                // Digest messages were logged when they arrived.
+                if !matches!(kind, BatchKind::Digest { .. }) {
+                    self.state.conversation_logger.log_user_message(
+                        &self.state.channel_id,
+                        sender_name,
+                        &message.sender_id,
+                        &raw_text,
+                        &message.metadata,
+                    );
+                    self.state
+                        .channel_store
+                        .upsert(&message.conversation_id, &message.metadata);
+                }
 
                 conversation_id = message.conversation_id.clone();
 
@@ -675,7 +1273,7 @@ impl Channel {
 
                 // Download attachments for this message
                 if !attachments.is_empty() {
-                    let attachment_content = download_attachments(&self.deps, &attachments).await;
+                    let attachment_content = self.attachment_contents(&attachments).await;
                     for content in attachment_content {
                         user_contents.push(content);
                     }
@@ -694,27 +1292,39 @@ impl Channel {
             }
         }
 
-        let combined_text = format!(
-            "[{} messages arrived rapidly in this channel]\n\n{}",
-            message_count,
-            text_parts.join("\n")
-        );
+        let header = match kind {
+            BatchKind::Coalesced => {
+                format!("[{message_count} messages arrived rapidly in this channel]")
+            }
+            BatchKind::Digest { .. } => {
+                format!("[Digest: {message_count} messages since the last summary]")
+            }
+        };
+        let combined_text = format!("{header}\n\n{}", text_parts.join("\n"));
 
-        // Build system prompt with coalesce hint
+        // Build system prompt with the batch hint
         let system_prompt = self
-            .build_system_prompt_with_coalesce(message_count, elapsed_secs, unique_sender_count)
+            .build_system_prompt_for_batch(kind, message_count, elapsed_secs, unique_sender_count)
             .await?;
 
         {
             let mut reply_target = self.state.reply_target_message_id.write().await;
             *reply_target = messages.iter().rev().find_map(extract_discord_message_id);
         }
+        if let Some(trigger) = messages
+            .iter()
+            .rev()
+            .find(|message| message.source != "system")
+        {
+            *self.state.trigger_message.write().await = Some(trigger.clone());
+        }
 
         // Pre-hook: Compute memory injection on combined text
         let injected_context = self.compute_memory_injection(&combined_text).await;
 
         // Run agent turn with any image/audio attachments preserved
-        let (result, skip_flag, replied_flag) = self
+        let typing = self.state.typing.hold_turn();
+        let (result, skip_flag, replied_flag, turn) = self
             .run_agent_turn(
                 &combined_text,
                 &system_prompt,
@@ -724,10 +1334,27 @@ impl Channel {
             )
             .await?;
 
-        self.handle_agent_result(result, &skip_flag, &replied_flag, false)
+        if is_provider_failure(&result) && self.llm_offline().await {
+            drop(typing);
+            if let BatchKind::Digest { interval_secs } = kind {
+                // Put the digest back in front of anything that arrived since.
+                let newer = std::mem::replace(&mut self.digest_buffer, messages);
+                self.digest_buffer.extend(newer);
+                self.digest_deadline = Some(
+                    tokio::time::Instant::now() + std::time::Duration::from_secs(interval_secs),
+                );
+            } else {
+                self.hold_offline(messages, true).await;
+            }
+            return Ok(());
+        }
+
+        self.handle_agent_result(result, &turn, &skip_flag, &replied_flag, false)
             .await;
+        drop(typing);
         // Check compaction
-        if let Err(error) = self.compactor.check_and_compact().await {
+        let overrides = self.binding_overrides().await;
+        if let Err(error) = self.compactor.check_and_compact(&overrides).await {
             tracing::warn!(%error, "compaction check failed");
         }
 
@@ -735,13 +1362,16 @@ impl Channel {
         self.message_count += message_count;
         self.current_turn += 1;
         self.check_memory_persistence().await;
+        self.check_title();
+        self.check_summary();
 
         Ok(())
     }
 
-    /// Build system prompt with coalesce hint for batched messages.
-    async fn build_system_prompt_with_coalesce(
+    /// Build system prompt with the coalesce or digest hint for batched messages.
+    async fn build_system_prompt_for_batch(
         &self,
+        kind: BatchKind,
         message_count: usize,
         elapsed_secs: f64,
         unique_senders: usize,
@@ -765,11 +1395,19 @@ impl Channel {
             status.render()
         };
 
-        // Render coalesce hint
-        let elapsed_str = format!("{:.1}s", elapsed_secs);
-        let coalesce_hint = prompt_engine
-            .render_coalesce_hint(message_count, &elapsed_str, unique_senders)
-            .ok();
+        // Render the batch hint
+        let coalesce_hint = match kind {
+            BatchKind::Coalesced => {
+                let elapsed_str = format!("{:.1}s", elapsed_secs);
+                prompt_engine.render_coalesce_hint(message_count, &elapsed_str, unique_senders)
+            }
+            BatchKind::Digest { interval_secs } => prompt_engine.render_digest_hint(
+                message_count,
+                &format_digest_interval(interval_secs),
+                unique_senders,
+            ),
+        }
+        .ok();
 
         let available_channels = self.build_available_channels().await;
 
@@ -781,6 +1419,7 @@ impl Channel {
             empty_to_none(skills_prompt),
             worker_capabilities,
             self.conversation_context.clone(),
+            self.summary.clone(),
             empty_to_none(status_text),
             coalesce_hint,
             available_channels,
@@ -801,25 +1440,42 @@ impl Channel {
             self.conversation_id = Some(message.conversation_id.clone());
         }
 
+        // Re-triggers keep the role of the turn that caused them.
+        if message.source != "system" {
+            let rbac = self.deps.runtime_config.rbac.load();
+            let role = rbac.resolve_role(&message.source, &message.sender_id);
+            *self.state.sender_role.write().await = role;
+            *self.state.sender_actor.write().await =
+                format!("{}:{}", message.source, message.sender_id);
+
+            if message.metadata.contains_key("slack_command") && !self.allow_command(&message).await
+            {
+                return Ok(());
+            }
+        }
+
         let (raw_text, attachments) = match &message.content {
             crate::MessageContent::Text(text) => (text.clone(), Vec::new()),
             crate::MessageContent::Media { text, attachments } => {
                 (text.clone().unwrap_or_default(), attachments.clone())
             }
             // Render interactions as their Display form so the LLM sees plain text.
-            crate::MessageContent::Interaction { .. } => (message.content.to_string(), Vec::new()),
+            crate::MessageContent::Interaction { .. } | crate::MessageContent::Reaction { .. } => {
+                (message.content.to_string(), Vec::new())
+            }
         };
 
         let user_text = format_user_message(&raw_text, &message);
 
         let attachment_content = if !attachments.is_empty() {
-            download_attachments(&self.deps, &attachments).await
+            self.attachment_contents(&attachments).await
         } else {
             Vec::new()
         };
 
-        // Persist user messages (skip system re-triggers)
-        if message.source != "system" {
+        // Persist user messages (skip system re-triggers and offline replays)
+        let is_replay = message.metadata.contains_key(OFFLINE_REPLAY_KEY);
+        if message.source != "system" && !is_replay {
             let sender_name = message
                 .metadata
                 .get("sender_display_name")
@@ -872,17 +1528,21 @@ impl Channel {
             let mut reply_target = self.state.reply_target_message_id.write().await;
             *reply_target = extract_discord_message_id(&message);
         }
+        if message.source != "system" {
+            *self.state.trigger_message.write().await = Some(message.clone());
+        }
 
-    let is_retrigger = message.source == "system";
+        let is_retrigger = message.source == "system";
 
-    // Pre-hook: Compute memory injection (skip for system re-triggers)
-    let injected_context = if !is_retrigger {
+        // Pre-hook: Compute memory injection (skip for system re-triggers)
+        let injected_context = if !is_retrigger {
             self.compute_memory_injection(&user_text).await
         } else {
             None
         };
 
-        let (result, skip_flag, replied_flag) = self
+        let typing = self.state.typing.hold_turn();
+        let (result, skip_flag, replied_flag, turn) = self
             .run_agent_turn(
                 &user_text,
                 &system_prompt,
@@ -892,69 +1552,424 @@ impl Channel {
             )
             .await?;
 
-        self.handle_agent_result(result, &skip_flag, &replied_flag, is_retrigger)
+        if is_provider_failure(&result) && self.llm_offline().await {
+            drop(typing);
+            self.hold_offline(vec![message], true).await;
+            return Ok(());
+        }
+
+        self.handle_agent_result(result, &turn, &skip_flag, &replied_flag, is_retrigger)
             .await;
+        drop(typing);
 
         // Check context size and trigger compaction if needed
-        if let Err(error) = self.compactor.check_and_compact().await {
+        let overrides = self.binding_overrides().await;
+        if let Err(error) = self.compactor.check_and_compact(&overrides).await {
             tracing::warn!(%error, "compaction check failed");
         }
 
         // Increment message counter and spawn memory persistence branch if threshold reached
         if !is_retrigger {
             self.retrigger_count = 0;
+            // Results of race losers have arrived or never will by now.
+            self.race_losers.clear();
             self.message_count += 1;
             self.current_turn += 1;
             self.check_memory_persistence().await;
+            self.check_title();
+            self.check_summary();
         }
 
         Ok(())
     }
 
-    /// Build the rendered available channels fragment for cross-channel awareness.
-    async fn build_available_channels(&self) -> Option<String> {
-        self.deps.messaging_manager.as_ref()?;
+    /// Download a message's attachments for the model. Text files too large
+    /// to inline are handed to a worker that summarizes them, unless that's
+    /// disabled or the worker can't be spawned.
+    async fn attachment_contents(&self, attachments: &[crate::Attachment]) -> Vec<UserContent> {
+        let downloaded =
+            download_attachments(&self.deps, &self.state.channel_id, attachments).await;
+        let summarize = self
+            .deps
+            .runtime_config
+            .attachments
+            .load()
+            .summarize_large_text;
+
+        let mut contents = Vec::with_capacity(downloaded.len());
+        for attachment in downloaded {
+            let file = match attachment {
+                DownloadedAttachment::Content(content) => {
+                    contents.push(content);
+                    continue;
+                }
+                DownloadedAttachment::Parts(parts) => {
+                    contents.extend(parts);
+                    continue;
+                }
+                DownloadedAttachment::LargeText(file) => file,
+            };
+            let summary_worker = if summarize {
+                self.spawn_summary_worker(&file).await
+            } else {
+                None
+            };
+            contents.push(file.content(summary_worker));
+        }
+        contents
+    }
 
-        let channels = match self.state.channel_store.list_active().await {
-            Ok(channels) => channels,
+    /// Spawn a worker that summarizes a large text attachment.
+    async fn spawn_summary_worker(&self, file: &LargeTextAttachment) -> Option<WorkerId> {
+        let task = self
+            .deps
+            .runtime_config
+            .prompts
+            .load()
+            .render_system_attachment_summary(
+                &file.filename,
+                &file.path.display().to_string(),
+                file.size,
+                file.line_count,
+                &file.summary_path().display().to_string(),
+            )
+            .inspect_err(|error| tracing::warn!(%error, "can't render attachment summary task"))
+            .ok()?;
+
+        match spawn_worker_from_state(&self.state, task, false, None, TaskPriority::Normal).await {
+            Ok(worker_id) => {
+                tracing::info!(
+                    channel_id = %self.id,
+                    %worker_id,
+                    filename = %file.filename,
+                    size = file.size,
+                    "summarizing large text attachment"
+                );
+                Some(worker_id)
+            }
             Err(error) => {
-                tracing::warn!(%error, "failed to list channels for system prompt");
-                return None;
+                tracing::info!(
+                    %error,
+                    filename = %file.filename,
+                    "can't spawn attachment summary worker, inlining the start only"
+                );
+                None
             }
-        };
-
-        // Filter out the current channel and cron channels
-        let entries: Vec<crate::prompts::engine::ChannelEntry> = channels
-            .into_iter()
-            .filter(|channel| {
-                channel.id.as_str() != self.id.as_ref()
-                    && channel.platform != "cron"
-                    && channel.platform != "webhook"
-            })
-            .map(|channel| crate::prompts::engine::ChannelEntry {
-                name: channel.display_name.unwrap_or_else(|| channel.id.clone()),
-                platform: channel.platform,
-                id: channel.id,
-            })
-            .collect();
-
-        if entries.is_empty() {
-            return None;
         }
+    }
 
+    /// Start this channel from a forked parent's history.
+    async fn adopt_fork(&self, mut history: Vec<rig::message::Message>, parent: &str) {
         let prompt_engine = self.deps.runtime_config.prompts.load();
-        prompt_engine.render_available_channels(entries).ok()
+        match prompt_engine.render_system_fork(parent) {
+            Ok(note) => history.push(rig::message::Message::from(note)),
+            Err(error) => tracing::warn!(%error, "can't render fork note"),
+        }
+        let message_count = history.len();
+        *self.state.history.write().await = history;
+        tracing::info!(channel_id = %self.id, %parent, message_count, "channel forked");
     }
 
-    /// Assemble the full system prompt using the PromptEngine.
-    async fn build_system_prompt(&self) -> crate::error::Result<String> {
-        let rc = &self.deps.runtime_config;
-        let prompt_engine = rc.prompts.load();
+    /// Take a restored snapshot's history, status block and summary as this
+    /// channel's own.
+    async fn adopt_snapshot(&mut self, snapshot: crate::agent::snapshot::HistorySnapshot) {
+        let message_count = snapshot.history.len();
+        *self.state.history.write().await = snapshot.history;
+        *self.state.status_block.write().await = snapshot.status_block;
+        self.summary = snapshot.summary;
+        tracing::info!(
+            channel_id = %self.id,
+            from_channel = %snapshot.channel_id,
+            taken_at = %snapshot.taken_at,
+            message_count,
+            "snapshot restored"
+        );
+    }
 
-        let identity_context = rc.identity.load().render();
-        let memory_bulletin = rc.memory_bulletin.load();
-        let skills = rc.skills.load();
-        let skills_prompt = skills.render_channel_prompt(&prompt_engine)?;
+    /// Take over a conversation handed off by another agent. The seed's
+    /// brief runs as a system turn under the role of the user it came from.
+    async fn adopt_handoff(
+        &self,
+        message: &mut InboundMessage,
+        handoff: &crate::agent::handoff::Handoff,
+    ) {
+        let rbac = self.deps.runtime_config.rbac.load();
+        *self.state.sender_role.write().await =
+            rbac.resolve_role(&message.source, &message.sender_id);
+        *self.state.sender_actor.write().await =
+            format!("{}:{}", message.source, message.sender_id);
+        *self.state.trigger_message.write().await = Some(message.clone());
+
+        message.source = "system".into();
+        tracing::info!(
+            channel_id = %self.id,
+            from_agent = %handoff.from_agent,
+            "conversation taken over"
+        );
+    }
+
+    /// Handle `/fork [topic]`: fork into a new thread and mark the command
+    /// with a thread reaction, or say why it couldn't be done. Like other
+    /// commands, it needs a role that allows commands.
+    async fn handle_fork_command(&self, message: &InboundMessage, topic: &str) {
+        if !self.allow_command(message).await {
+            return;
+        }
+        let response = match crate::agent::fork::fork(&self.state, message, "", topic).await {
+            Ok(_) => OutboundResponse::Reaction("🧵".into()),
+            Err(error) => {
+                tracing::warn!(%error, channel_id = %self.id, "fork command failed");
+                OutboundResponse::Text(format!("Couldn't fork this conversation: {error}"))
+            }
+        };
+        self.response_tx.send(response).await.ok();
+    }
+
+    /// Whether the sender's role allows commands. A sender without it is
+    /// told so privately.
+    async fn allow_command(&self, message: &InboundMessage) -> bool {
+        let rbac = self.deps.runtime_config.rbac.load();
+        let role = rbac.resolve_role(&message.source, &message.sender_id);
+        if rbac.allows(role, Capability::Commands) {
+            return true;
+        }
+        tracing::info!(
+            sender_id = %message.sender_id,
+            %role,
+            "command denied by rbac"
+        );
+        self.response_tx
+            .send(OutboundResponse::Ephemeral {
+                text: format!(
+                    "You need the {} role to use commands here.",
+                    rbac.min_role(Capability::Commands)
+                ),
+                user_id: message.sender_id.clone(),
+            })
+            .await
+            .ok();
+        false
+    }
+
+    /// Cancel a branch or worker from its button on the live status card.
+    /// The clicking user needs the same permission as the cancel tool.
+    async fn handle_cancel_button(&mut self, message: &InboundMessage, process_id: ProcessId) {
+        let rbac = self.deps.runtime_config.rbac.load();
+        let role = rbac.resolve_role(&message.source, &message.sender_id);
+        if !rbac.allows(role, Capability::ToolApproval) {
+            tracing::info!(
+                sender_id = %message.sender_id,
+                %role,
+                %process_id,
+                "cancel button ignored, sender can't cancel work"
+            );
+            return;
+        }
+
+        let result = match &process_id {
+            ProcessId::Worker(worker_id) => self.state.cancel_worker(*worker_id).await,
+            ProcessId::Branch(branch_id) => self.state.cancel_branch(*branch_id).await,
+            ProcessId::Channel(_) => return,
+        };
+        match result {
+            Ok(()) => tracing::info!(
+                sender_id = %message.sender_id,
+                %process_id,
+                "cancelled from the live status card"
+            ),
+            Err(error) => tracing::debug!(%error, "cancel button target already gone"),
+        }
+        self.refresh_live_status().await;
+    }
+
+    /// Schedule the live status card: after the configured delay for a new
+    /// card, or the update interval for one that's already up.
+    fn schedule_live_status(&mut self) {
+        let config = self.deps.runtime_config.live_status.load();
+        if !config.enabled || self.live_status_deadline.is_some() {
+            return;
+        }
+        let wait_secs = if self.live_status_posted {
+            config.update_interval_secs
+        } else {
+            config.delay_secs
+        };
+        self.live_status_deadline =
+            Some(tokio::time::Instant::now() + std::time::Duration::from_secs(wait_secs));
+    }
+
+    /// Post or refresh the live status card while work runs, and remove it
+    /// once nothing does.
+    async fn refresh_live_status(&mut self) {
+        self.live_status_deadline = None;
+        let card = if self.deps.runtime_config.live_status.load().enabled {
+            self.state.status_block.read().await.live_card()
+        } else {
+            None
+        };
+        match card {
+            Some((card, interactive_elements)) => {
+                self.send_live_status(Some(card), interactive_elements)
+                    .await;
+                self.live_status_posted = true;
+                self.schedule_live_status();
+            }
+            None if self.live_status_posted => {
+                self.send_live_status(None, Vec::new()).await;
+                self.live_status_posted = false;
+            }
+            None => {}
+        }
+    }
+
+    async fn send_live_status(
+        &self,
+        card: Option<crate::Card>,
+        interactive_elements: Vec<crate::InteractiveElements>,
+    ) {
+        self.response_tx
+            .send(OutboundResponse::Status(crate::StatusUpdate::Live {
+                card,
+                interactive_elements,
+            }))
+            .await
+            .ok();
+    }
+
+    /// React to an incoming message with the configured acknowledgement, so
+    /// the sender sees it arrived before the reply does.
+    async fn acknowledge(&self, message: &InboundMessage) {
+        let ack_emoji = self.deps.runtime_config.reactions.load().ack_emoji.clone();
+        if ack_emoji.is_empty() || message.source == "system" {
+            return;
+        }
+        if let Some(messaging_manager) = &self.deps.messaging_manager
+            && let Err(error) = messaging_manager
+                .respond(message, OutboundResponse::Reaction(ack_emoji))
+                .await
+        {
+            tracing::debug!(%error, "can't acknowledge message with a reaction");
+        }
+    }
+
+    /// Save the reacted message on the capture reaction, or turn a signal
+    /// reaction into the message the channel should answer.
+    async fn handle_reaction(&self, message: &InboundMessage) -> Option<InboundMessage> {
+        let crate::MessageContent::Reaction {
+            emoji,
+            message_id,
+            target_author,
+            target_text,
+            to_bot,
+        } = &message.content
+        else {
+            return None;
+        };
+
+        let config = self.deps.runtime_config.reaction_memory.load();
+        if !config.enabled || *emoji != config.emoji {
+            let reactions = self.deps.runtime_config.reactions.load();
+            let signal = crate::agent::reactions::parse_signal(&reactions, emoji, *to_bot)?;
+            tracing::info!(
+                ?signal,
+                %message_id,
+                sender_id = %message.sender_id,
+                "reaction read as a signal"
+            );
+            return crate::agent::reactions::signal_message(message, signal);
+        }
+        let Some(text) = target_text else {
+            tracing::debug!(%message_id, "reacted message has no text to save");
+            return None;
+        };
+        let content = match target_author {
+            Some(author) => format!("{author}: {text}"),
+            None => text.clone(),
+        };
+
+        let result = crate::tools::MemorySaveTool::new(self.deps.memory_search.clone())
+            .call(crate::tools::MemorySaveArgs {
+                content,
+                memory_type: config.memory_type.to_string(),
+                importance: Some(1.0),
+                source: Some("reaction".into()),
+                channel_id: Some(self.id.to_string()),
+                associations: Vec::new(),
+                pinned: true,
+            })
+            .await;
+        let memory_id = match result {
+            Ok(output) => output.memory_id,
+            Err(error) => {
+                tracing::warn!(%error, %message_id, "can't save reacted message as memory");
+                return None;
+            }
+        };
+        tracing::info!(
+            %memory_id,
+            %message_id,
+            sender_id = %message.sender_id,
+            "reacted message saved as pinned memory"
+        );
+
+        if !config.confirm_emoji.is_empty()
+            && let Some(messaging_manager) = &self.deps.messaging_manager
+            && let Err(error) = messaging_manager
+                .respond(
+                    message,
+                    OutboundResponse::Reaction(config.confirm_emoji.clone()),
+                )
+                .await
+        {
+            tracing::debug!(%error, "can't confirm reaction memory capture");
+        }
+        None
+    }
+
+    /// Build the rendered available channels fragment for cross-channel awareness.
+    async fn build_available_channels(&self) -> Option<String> {
+        self.deps.messaging_manager.as_ref()?;
+
+        let channels = match self.state.channel_store.list_active().await {
+            Ok(channels) => channels,
+            Err(error) => {
+                tracing::warn!(%error, "failed to list channels for system prompt");
+                return None;
+            }
+        };
+
+        // Filter out the current channel and cron channels
+        let entries: Vec<crate::prompts::engine::ChannelEntry> = channels
+            .into_iter()
+            .filter(|channel| {
+                channel.id.as_str() != self.id.as_ref()
+                    && channel.platform != "cron"
+                    && channel.platform != "webhook"
+            })
+            .map(|channel| crate::prompts::engine::ChannelEntry {
+                name: channel.display_name.unwrap_or_else(|| channel.id.clone()),
+                platform: channel.platform,
+                id: channel.id,
+            })
+            .collect();
+
+        if entries.is_empty() {
+            return None;
+        }
+
+        let prompt_engine = self.deps.runtime_config.prompts.load();
+        prompt_engine.render_available_channels(entries).ok()
+    }
+
+    /// Assemble the full system prompt using the PromptEngine.
+    async fn build_system_prompt(&self) -> crate::error::Result<String> {
+        let rc = &self.deps.runtime_config;
+        let prompt_engine = rc.prompts.load();
+
+        let identity_context = rc.identity.load().render();
+        let memory_bulletin = rc.memory_bulletin.load();
+        let skills = rc.skills.load();
+        let skills_prompt = skills.render_channel_prompt(&prompt_engine)?;
 
         let browser_enabled = rc.browser_config.load().enabled;
         let web_search_enabled = rc.brave_search_key.load().is_some();
@@ -971,17 +1986,17 @@ impl Channel {
 
         let empty_to_none = |s: String| if s.is_empty() { None } else { Some(s) };
 
-        prompt_engine
-            .render_channel_prompt(
-                empty_to_none(identity_context),
-                empty_to_none(memory_bulletin.to_string()),
-                empty_to_none(skills_prompt),
-                worker_capabilities,
-                self.conversation_context.clone(),
-                empty_to_none(status_text),
-                None, // coalesce_hint - only set for batched messages
-                available_channels,
-            )
+        prompt_engine.render_channel_prompt(
+            empty_to_none(identity_context),
+            empty_to_none(memory_bulletin.to_string()),
+            empty_to_none(skills_prompt),
+            worker_capabilities,
+            self.conversation_context.clone(),
+            self.summary.clone(),
+            empty_to_none(status_text),
+            None, // coalesce_hint - only set for batched messages
+            available_channels,
+        )
     }
 
     /// Compute memories to inject before the LLM turn (pre-hook).
@@ -1308,7 +2323,9 @@ impl Channel {
 
     /// Register per-turn tools, run the LLM agentic loop, and clean up.
     ///
-    /// Returns the prompt result and skip flag for the caller to dispatch.
+    /// Returns the prompt result, flags and turn hook context for the caller
+    /// to dispatch. A turn vetoed by a hook returns a cancelled prompt
+    /// without calling the LLM.
     #[tracing::instrument(skip(self, user_text, system_prompt, attachment_content, injected_context), fields(channel_id = %self.id, agent_id = %self.deps.agent_id))]
     async fn run_agent_turn(
         &self,
@@ -1321,14 +2338,48 @@ impl Channel {
         std::result::Result<String, rig::completion::PromptError>,
         crate::tools::SkipFlag,
         crate::tools::RepliedFlag,
+        TurnContext,
     )> {
         let skip_flag = crate::tools::new_skip_flag();
         let replied_flag = crate::tools::new_replied_flag();
 
+        let mut turn = TurnContext {
+            agent_id: self.deps.agent_id.clone(),
+            channel_id: self.id.clone(),
+            conversation_id: conversation_id.to_string(),
+            user_text: user_text.to_string(),
+            system_prompt: system_prompt.to_string(),
+        };
+        if let TurnDecision::Veto { reply } = self.deps.turn_hooks.before_turn(&mut turn) {
+            if let Some(reply) = reply {
+                self.state
+                    .conversation_logger
+                    .log_bot_message(&self.state.channel_id, &reply);
+                if let Err(error) = self.response_tx.send(OutboundResponse::Text(reply)).await {
+                    tracing::error!(%error, "failed to send turn veto reply");
+                }
+                replied_flag.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            let cancelled = Err(rig::completion::PromptError::PromptCancelled {
+                chat_history: Box::default(),
+                reason: TURN_VETOED_REASON.to_string(),
+            });
+            return Ok((cancelled, skip_flag, replied_flag, turn));
+        }
+
+        // With turn hooks, the turn's responses are collected and passed
+        // through them before going out.
+        let (response_tx, mut hooked_responses) = if self.deps.turn_hooks.is_empty() {
+            (self.response_tx.clone(), None)
+        } else {
+            let (response_tx, response_rx) = mpsc::channel(32);
+            (response_tx, Some(response_rx))
+        };
+
         if let Err(error) = crate::tools::add_channel_tools(
             &self.tool_server,
             self.state.clone(),
-            self.response_tx.clone(),
+            response_tx.clone(),
             conversation_id,
             skip_flag.clone(),
             replied_flag.clone(),
@@ -1342,23 +2393,30 @@ impl Channel {
 
         let rc = &self.deps.runtime_config;
         let routing = rc.routing.load();
-        let max_turns = **rc.max_turns.load();
-        let model_name = routing.resolve(ProcessType::Channel, None);
-        let model = SpacebotModel::make(&self.deps.llm_manager, model_name)
+        let overrides = self.binding_overrides().await;
+        let max_turns = overrides.max_turns.unwrap_or(**rc.max_turns.load());
+        let model_name = overrides
+            .channel_model
+            .as_deref()
+            .unwrap_or(routing.resolve(ProcessType::Channel, None));
+        let mut model = SpacebotModel::make(&self.deps.llm_manager, model_name)
             .with_context(&*self.deps.agent_id, "channel")
             .with_routing((**routing).clone());
+        if self.streams_replies().await {
+            let (deltas_tx, deltas_rx) = mpsc::unbounded_channel();
+            model = model.with_deltas(deltas_tx);
+            tokio::spawn(
+                preview_reply(deltas_rx, response_tx.clone(), replied_flag.clone())
+                    .in_current_span(),
+            );
+        }
 
         let agent = AgentBuilder::new(model)
-            .preamble(system_prompt)
+            .preamble(&turn.system_prompt)
             .default_max_turns(max_turns)
             .tool_server_handle(self.tool_server.clone())
             .build();
 
-        let _ = self
-            .response_tx
-            .send(OutboundResponse::Status(crate::StatusUpdate::Thinking))
-            .await;
-
         // Inject attachments as a user message before the text prompt
         if !attachment_content.is_empty() {
             let mut history = self.state.history.write().await;
@@ -1369,6 +2427,19 @@ impl Channel {
             drop(history);
         }
 
+        // Make room first if this turn's prompt and input would overflow the
+        // context; compaction otherwise only runs after the turn.
+        let incoming_chars = turn.system_prompt.len()
+            + turn.user_text.len()
+            + injected_context.as_ref().map_or(0, String::len);
+        if let Err(error) = self
+            .compactor
+            .compact_before_turn(&overrides, incoming_chars / 4)
+            .await
+        {
+            tracing::warn!(%error, "pre-turn compaction failed");
+        }
+
         // Clone history out so the write lock is released before the agentic loop.
         // The branch tool needs a read lock on history to clone it for the branch,
         // and holding a write lock across the entire agentic loop would deadlock.
@@ -1392,10 +2463,15 @@ impl Channel {
             history.push(rig::message::Message::from(context_message));
         }
 
-        let mut result = agent
-            .prompt(user_text)
-            .with_history(&mut history)
-            .with_hook(self.hook.clone())
+        let mut result = self
+            .relay_hooked_responses(
+                &turn,
+                hooked_responses.as_mut(),
+                agent
+                    .prompt(&turn.user_text)
+                    .with_history(&mut history)
+                    .with_hook(self.hook.clone()),
+            )
             .await;
 
         // If the LLM responded with text that looks like tool call syntax, it failed
@@ -1406,10 +2482,15 @@ impl Channel {
             tracing::warn!("LLM emitted tool syntax as text, retrying with correction");
             let prompt_engine = self.deps.runtime_config.prompts.load();
             let correction = prompt_engine.render_system_tool_syntax_correction()?;
-            result = agent
-                .prompt(&correction)
-                .with_history(&mut history)
-                .with_hook(self.hook.clone())
+            result = self
+                .relay_hooked_responses(
+                    &turn,
+                    hooked_responses.as_mut(),
+                    agent
+                        .prompt(&correction)
+                        .with_history(&mut history)
+                        .with_hook(self.hook.clone()),
+                )
                 .await;
         }
 
@@ -1422,31 +2503,101 @@ impl Channel {
             tracing::warn!(%error, "failed to remove channel tools");
         }
 
-        Ok((result, skip_flag, replied_flag))
+        Ok((result, skip_flag, replied_flag, turn))
+    }
+
+    /// Run `work`, passing the responses it sends through the turn hooks as
+    /// they arrive. Without hooks, responses already go straight out.
+    async fn relay_hooked_responses<T>(
+        &self,
+        turn: &TurnContext,
+        responses: Option<&mut mpsc::Receiver<OutboundResponse>>,
+        work: impl std::future::IntoFuture<Output = T>,
+    ) -> T {
+        let Some(responses) = responses else {
+            return work.await;
+        };
+        let work = work.into_future();
+        tokio::pin!(work);
+        loop {
+            tokio::select! {
+                output = &mut work => {
+                    while let Ok(response) = responses.try_recv() {
+                        self.send_hooked_response(turn, response).await;
+                    }
+                    return output;
+                }
+                Some(response) = responses.recv() => {
+                    self.send_hooked_response(turn, response).await;
+                }
+            }
+        }
+    }
+
+    /// Send a response of the turn, unless a turn hook drops it.
+    async fn send_hooked_response(&self, turn: &TurnContext, response: OutboundResponse) {
+        if let Some(response) = self.deps.turn_hooks.on_response(turn, response)
+            && let Err(error) = self.response_tx.send(response).await
+        {
+            tracing::error!(%error, "failed to send outbound response");
+        }
     }
 
-    /// Dispatch the LLM result: send fallback text, log errors, clean up typing.
+    /// Settings the binding of the current turn's message overrides.
+    async fn binding_overrides(&self) -> crate::config::BindingOverrides {
+        self.state
+            .trigger_message
+            .read()
+            .await
+            .as_ref()
+            .and_then(|message| message.metadata.get(BINDING_OVERRIDES_KEY))
+            .and_then(|overrides| serde_json::from_value(overrides.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether the message being answered came through a stream-mode binding
+    /// on an adapter that edits streamed messages, so replies are shown while
+    /// they're generated.
+    async fn streams_replies(&self) -> bool {
+        let Some(message) = self.state.trigger_message.read().await.clone() else {
+            return false;
+        };
+        if crate::messaging::response_mode::response_mode(&message)
+            != crate::config::ResponseMode::Stream
+        {
+            return false;
+        }
+        match &self.deps.messaging_manager {
+            Some(messaging_manager) => messaging_manager.supports_streaming(&message).await,
+            None => false,
+        }
+    }
+
+    /// Dispatch the LLM result: send fallback text and log errors.
     ///
     /// On retrigger turns (`is_retrigger = true`), fallback text is suppressed.
     /// The LLM must explicitly call the `reply` tool to send a message; returning
     /// plain text on a retrigger is treated as internal acknowledgment, not a
-    /// user-facing response.
+    /// user-facing response. Turn hooks see the fallback text and the outcome.
     async fn handle_agent_result(
         &self,
         result: std::result::Result<String, rig::completion::PromptError>,
+        turn: &TurnContext,
         skip_flag: &crate::tools::SkipFlag,
         replied_flag: &crate::tools::RepliedFlag,
         is_retrigger: bool,
     ) {
-        match result {
+        let replied = replied_flag.load(std::sync::atomic::Ordering::Relaxed);
+        let outcome = match result {
             Ok(response) => {
                 let skipped = skip_flag.load(std::sync::atomic::Ordering::Relaxed);
-                let replied = replied_flag.load(std::sync::atomic::Ordering::Relaxed);
 
                 if skipped {
                     tracing::debug!("channel turn skipped (no response)");
+                    TurnOutcome::Silent
                 } else if replied {
                     tracing::debug!(channel_id = %self.id, "channel turn replied via tool (fallback suppressed)");
+                    TurnOutcome::Replied
                 } else if is_retrigger {
                     // On retrigger turns, suppress fallback text. The LLM should
                     // use the reply tool explicitly if it has something to say, or
@@ -1457,6 +2608,7 @@ impl Channel {
                         response_len = response.len(),
                         "retrigger turn fallback suppressed (LLM did not use reply/skip tool)"
                     );
+                    TurnOutcome::Silent
                 } else {
                     // If the LLM returned text without using the reply tool, send it
                     // directly. Some models respond with text instead of tool calls.
@@ -1473,54 +2625,162 @@ impl Channel {
                         extracted.as_deref().unwrap_or(text),
                         source,
                     );
-                    if !final_text.is_empty() {
-                        if extracted.is_some() {
-                            tracing::warn!("extracted reply from malformed tool syntax in LLM text output");
-                        }
-                        self.state
-                            .conversation_logger
-                            .log_bot_message(&self.state.channel_id, &final_text);
-                        if let Err(error) = self
-                            .response_tx
-                            .send(OutboundResponse::Text(final_text))
-                            .await
-                        {
-                            tracing::error!(%error, "failed to send fallback reply");
+                    let fallback = (!final_text.is_empty())
+                        .then(|| {
+                            self.deps
+                                .turn_hooks
+                                .on_response(turn, OutboundResponse::Text(final_text))
+                        })
+                        .flatten();
+                    let outcome = match fallback {
+                        Some(response) => {
+                            if extracted.is_some() {
+                                tracing::warn!(
+                                    "extracted reply from malformed tool syntax in LLM text output"
+                                );
+                            }
+                            if let OutboundResponse::Text(text) = &response {
+                                self.state
+                                    .conversation_logger
+                                    .log_bot_message(&self.state.channel_id, text);
+                            }
+                            if let Err(error) = self.response_tx.send(response).await {
+                                tracing::error!(%error, "failed to send fallback reply");
+                            }
+                            TurnOutcome::Replied
                         }
-                    }
+                        None => TurnOutcome::Silent,
+                    };
 
                     tracing::debug!("channel turn completed");
+                    outcome
                 }
             }
             Err(rig::completion::PromptError::MaxTurnsError { .. }) => {
                 tracing::warn!("channel hit max turns");
+                if replied {
+                    TurnOutcome::Replied
+                } else {
+                    TurnOutcome::Failed
+                }
             }
             Err(rig::completion::PromptError::PromptCancelled { reason, .. }) => {
-                if reason == "reply delivered" {
-                    tracing::debug!("channel turn completed via reply tool");
+                if reason == TURN_VETOED_REASON {
+                    TurnOutcome::Vetoed
                 } else {
-                    tracing::info!(%reason, "channel turn cancelled");
+                    if reason == "reply delivered" {
+                        tracing::debug!("channel turn completed via reply tool");
+                    } else {
+                        tracing::info!(%reason, "channel turn cancelled");
+                    }
+                    if replied {
+                        TurnOutcome::Replied
+                    } else {
+                        TurnOutcome::Silent
+                    }
                 }
             }
             Err(error) => {
                 tracing::error!(%error, "channel LLM call failed");
+                TurnOutcome::Failed
             }
+        };
+        self.deps.turn_hooks.after_turn(turn, outcome);
+    }
+
+    /// Whether offline mode applies: it's enabled and the last call to the
+    /// provider of the channel model and of each fallback failed.
+    async fn llm_offline(&self) -> bool {
+        if !self.deps.runtime_config.offline.load().enabled {
+            return false;
         }
+        let routing = self.deps.runtime_config.routing.load();
+        let overrides = self.binding_overrides().await;
+        let model_name = overrides
+            .channel_model
+            .as_deref()
+            .unwrap_or(routing.resolve(ProcessType::Channel, None));
+        let models: Vec<&str> = std::iter::once(model_name)
+            .chain(routing.get_fallbacks(model_name).iter().map(String::as_str))
+            .collect();
+        self.deps.llm_manager.all_providers_down(&models).await
+    }
 
-        // Ensure typing indicator is always cleaned up, even on error paths
-        let _ = self
-            .response_tx
-            .send(OutboundResponse::Status(crate::StatusUpdate::StopTyping))
-            .await;
+    /// Put messages into the offline queue and send the offline notice once
+    /// per outage. Messages whose turn just failed are older than anything
+    /// already held, so they go to the front; new arrivals go to the back.
+    async fn hold_offline(&mut self, messages: Vec<InboundMessage>, failed_turn: bool) {
+        let config = self.deps.runtime_config.offline.load();
+
+        let dropped = if failed_turn {
+            self.offline.requeue(messages);
+            0
+        } else {
+            // A failed turn already logged its messages; new arrivals are
+            // logged here, as their replay won't.
+            let first_held = self.offline.messages.len();
+            let dropped = self.offline.push(messages, config.max_queued_messages);
+            for message in self.offline.messages.range(first_held..) {
+                self.log_held_message(message);
+            }
+            dropped
+        };
+        if dropped > 0 {
+            tracing::warn!(
+                channel_id = %self.id,
+                dropped,
+                max_queued = config.max_queued_messages,
+                "offline queue full, dropping messages"
+            );
+        }
+        tracing::info!(
+            channel_id = %self.id,
+            queued = self.offline.messages.len(),
+            "LLM providers unreachable, holding messages"
+        );
+
+        if self.offline.take_notice() {
+            self.response_tx
+                .send(OutboundResponse::Text(config.notice.clone()))
+                .await
+                .ok();
+        }
+        if self.offline.retry_deadline.is_none() {
+            self.offline.retry_deadline = Some(
+                tokio::time::Instant::now()
+                    + std::time::Duration::from_secs(config.retry_interval_secs),
+            );
+        }
     }
 
-    /// Handle a process event (branch results, worker completions, status updates).
-    async fn handle_event(&mut self, event: ProcessEvent) -> Result<()> {
-        // Only process events targeted at this channel
-        if !event_is_for_channel(&event, &self.id) {
-            return Ok(());
+    /// Process held messages in arrival order. Stops as soon as one lands
+    /// back in the queue, which reschedules the retry.
+    async fn replay_offline_queue(&mut self) {
+        self.offline.retry_deadline = None;
+        tracing::info!(
+            channel_id = %self.id,
+            queued = self.offline.messages.len(),
+            "replaying offline queue"
+        );
+
+        while let Some(mut message) = self.offline.messages.pop_front() {
+            message
+                .metadata
+                .insert(OFFLINE_REPLAY_KEY.into(), serde_json::Value::Bool(true));
+            if let Err(error) = self.handle_message(message).await {
+                tracing::error!(%error, channel_id = %self.id, "error handling queued message");
+            }
+            if self.offline.retry_deadline.is_some() {
+                return;
+            }
         }
 
+        self.offline.drained();
+        tracing::info!(channel_id = %self.id, "offline queue drained");
+    }
+
+    /// Handle a process event (branch results, worker completions, status updates).
+    async fn handle_event(&mut self, event: ProcessEvent) -> Result<()> {
         // Update status block
         {
             let mut status = self.state.status_block.write().await;
@@ -1537,23 +2797,33 @@ impl Channel {
                 channel_id,
                 description,
                 reply_to_message_id,
+                race_id,
                 ..
             } => {
                 run_logger.log_branch_started(channel_id, *branch_id, description);
                 if let Some(message_id) = reply_to_message_id {
                     self.branch_reply_targets.insert(*branch_id, *message_id);
                 }
+                if let Some(race_id) = race_id {
+                    self.branch_races
+                        .entry(*race_id)
+                        .or_default()
+                        .running
+                        .insert(*branch_id);
+                    self.racing_branches.insert(*branch_id, *race_id);
+                }
             }
             ProcessEvent::BranchResult {
                 branch_id,
                 conclusion,
+                structured,
                 ..
             } => {
                 run_logger.log_branch_completed(*branch_id, conclusion);
 
                 // Remove from active branches
-                let mut branches = self.state.active_branches.write().await;
-                branches.remove(branch_id);
+                self.state.active_branches.write().await.remove(branch_id);
+                self.state.typing.release(TypingHolder::Branch(*branch_id));
 
                 #[cfg(feature = "metrics")]
                 crate::telemetry::Metrics::global()
@@ -1567,21 +2837,66 @@ impl Channel {
                 if self.memory_persistence_branches.remove(branch_id) {
                     self.branch_reply_targets.remove(branch_id);
                     tracing::info!(branch_id = %branch_id, "memory persistence branch completed");
+                } else if self.race_losers.remove(branch_id) {
+                    self.branch_reply_targets.remove(branch_id);
+                    tracing::info!(branch_id = %branch_id, "result of a branch that lost its race dropped");
                 } else {
-                    // Regular branch: inject conclusion into history
-                    let mut history = self.state.history.write().await;
-                    let branch_message = format!("[Branch result]: {conclusion}");
-                    history.push(rig::message::Message::from(branch_message));
-                    should_retrigger = true;
+                    let branch_message = match structured {
+                        Some(structured) => structured.render_for_channel(),
+                        None => format!("[Branch result]: {conclusion}"),
+                    };
+                    // Free-text conclusions count as medium confidence.
+                    let confidence = structured
+                        .as_ref()
+                        .map_or(Confidence::Medium, |structured| structured.confidence);
+                    let result = match self.racing_branches.remove(branch_id) {
+                        Some(race_id) => {
+                            self.settle_race(
+                                race_id,
+                                *branch_id,
+                                Some((confidence, branch_message)),
+                            )
+                            .await
+                        }
+                        None => Some((*branch_id, branch_message)),
+                    };
 
-                    if let Some(message_id) = self.branch_reply_targets.remove(branch_id) {
-                        retrigger_metadata.insert(
-                            "discord_reply_to_message_id".to_string(),
-                            serde_json::Value::from(message_id),
-                        );
+                    // Regular branch or race winner: inject conclusion into history
+                    if let Some((branch_id, branch_message)) = result {
+                        self.incorporate_branch_result(
+                            branch_id,
+                            branch_message,
+                            &mut retrigger_metadata,
+                        )
+                        .await;
+                        should_retrigger = true;
+                    }
+                }
+            }
+            ProcessEvent::StatusUpdate {
+                process_id: ProcessId::Branch(branch_id),
+                status,
+                ..
+            } if status == CANCELLED_STATUS || status == FAILED_STATUS => {
+                // A cancelled branch was cleaned up by `cancel_branch`.
+                if status == FAILED_STATUS {
+                    self.state.active_branches.write().await.remove(branch_id);
+                    self.state.typing.release(TypingHolder::Branch(*branch_id));
+                }
+                // A racer that ends without a result may still decide its race.
+                if let Some(race_id) = self.racing_branches.remove(branch_id) {
+                    self.branch_reply_targets.remove(branch_id);
+                    if let Some((branch_id, branch_message)) =
+                        self.settle_race(race_id, *branch_id, None).await
+                    {
+                        self.incorporate_branch_result(
+                            branch_id,
+                            branch_message,
+                            &mut retrigger_metadata,
+                        )
+                        .await;
+                        should_retrigger = true;
                     }
-
-                    tracing::info!(branch_id = %branch_id, "branch result incorporated");
                 }
             }
             ProcessEvent::WorkerStarted {
@@ -1601,6 +2916,7 @@ impl Channel {
                 worker_id,
                 result,
                 notify,
+                artifacts,
                 ..
             } => {
                 run_logger.log_worker_completed(*worker_id, result);
@@ -1611,16 +2927,129 @@ impl Channel {
 
                 self.state.worker_handles.write().await.remove(worker_id);
                 self.state.worker_inputs.write().await.remove(worker_id);
+                self.state.typing.release(TypingHolder::Worker(*worker_id));
 
                 if *notify {
                     let mut history = self.state.history.write().await;
-                    let worker_message = format!("[Worker completed]: {result}");
+                    let mut worker_message = format!("[Worker completed]: {result}");
+                    if let Some(artifacts) = artifact::format_for_history(artifacts) {
+                        worker_message.push_str("\n\n");
+                        worker_message.push_str(&artifacts);
+                    }
                     history.push(rig::message::Message::from(worker_message));
                     should_retrigger = true;
                 }
 
                 tracing::info!(worker_id = %worker_id, "worker completed");
             }
+            ProcessEvent::LoopDetected {
+                process_id, detail, ..
+            } => match process_id {
+                ProcessId::Channel(_) => {
+                    // The channel's own turn was stopped, so nobody else will
+                    // tell the user why the answer never came.
+                    let notice = "I got stuck repeating the same steps and stopped. \
+                                  Could you rephrase or tell me what to try differently?";
+                    self.state
+                        .conversation_logger
+                        .log_bot_message(&self.state.channel_id, notice);
+                    self.response_tx
+                        .send(OutboundResponse::Text(notice.to_string()))
+                        .await
+                        .ok();
+                }
+                ProcessId::Worker(worker_id) => {
+                    // One-shot workers report the stop in their completion
+                    // result. Interactive workers pause and wait for input.
+                    if self
+                        .state
+                        .worker_inputs
+                        .read()
+                        .await
+                        .contains_key(worker_id)
+                    {
+                        let mut history = self.state.history.write().await;
+                        history.push(rig::message::Message::from(format!(
+                            "[Loop guard]: worker {worker_id} was paused ({detail}). \
+                             Send it new instructions with route, or cancel it."
+                        )));
+                        should_retrigger = true;
+                    }
+                }
+                // Branches return the stop as their conclusion.
+                ProcessId::Branch(_) => {}
+            },
+            ProcessEvent::ProcessUnhealthy {
+                process_id,
+                action,
+                diagnostic,
+                ..
+            } => match action {
+                HealthAction::Nudge => {
+                    // Only interactive workers can be told directly. For the
+                    // rest, the diagnostic on the status block is the nudge.
+                    let input = match process_id {
+                        ProcessId::Worker(worker_id) => self
+                            .state
+                            .worker_inputs
+                            .read()
+                            .await
+                            .get(worker_id)
+                            .cloned(),
+                        _ => None,
+                    };
+                    if let Some(input) = input {
+                        input
+                            .send(format!(
+                                "[Cortex]: you look stuck ({diagnostic}). Try a different \
+                                 approach, or finish with what you have."
+                            ))
+                            .await
+                            .ok();
+                    }
+                }
+                HealthAction::Kill => {
+                    let result = match process_id {
+                        ProcessId::Worker(worker_id) => self.state.cancel_worker(*worker_id).await,
+                        ProcessId::Branch(branch_id) => self.state.cancel_branch(*branch_id).await,
+                        ProcessId::Channel(_) => Err("channels aren't killed".to_string()),
+                    };
+                    match result {
+                        Ok(()) => {
+                            tracing::warn!(%process_id, %diagnostic, "killed stuck process");
+                            let mut history = self.state.history.write().await;
+                            history.push(rig::message::Message::from(format!(
+                                "[Cortex]: {process_id} was stopped because it was stuck \
+                                 ({diagnostic}). Its result won't arrive; tell the user or \
+                                 try again differently."
+                            )));
+                            should_retrigger = true;
+                            if self.live_status_posted {
+                                self.refresh_live_status().await;
+                            }
+                        }
+                        Err(error) => tracing::debug!(%error, "stuck process already gone"),
+                    }
+                }
+            },
+            ProcessEvent::ChannelTitled { title, .. } => {
+                self.title = Some(title.clone());
+            }
+            ProcessEvent::ChannelSummarized { summary, .. } => {
+                self.summary = Some(summary.clone());
+            }
+            _ => {}
+        }
+
+        match &event {
+            ProcessEvent::BranchStarted { .. } | ProcessEvent::WorkerStarted { .. } => {
+                self.schedule_live_status();
+            }
+            ProcessEvent::BranchResult { .. } | ProcessEvent::WorkerComplete { .. }
+                if self.live_status_posted =>
+            {
+                self.refresh_live_status().await;
+            }
             _ => {}
         }
 
@@ -1649,6 +3078,59 @@ impl Channel {
         Ok(())
     }
 
+    /// Add a branch's conclusion to history, replying to the message that
+    /// started the branch on the next turn.
+    async fn incorporate_branch_result(
+        &mut self,
+        branch_id: BranchId,
+        branch_message: String,
+        retrigger_metadata: &mut std::collections::HashMap<String, serde_json::Value>,
+    ) {
+        self.state
+            .history
+            .write()
+            .await
+            .push(rig::message::Message::from(branch_message));
+
+        if let Some(message_id) = self.branch_reply_targets.remove(&branch_id) {
+            retrigger_metadata.insert(
+                "discord_reply_to_message_id".to_string(),
+                serde_json::Value::from(message_id),
+            );
+        }
+
+        tracing::info!(branch_id = %branch_id, "branch result incorporated");
+    }
+
+    /// Record a racing branch ending, with its result or `None` if it was
+    /// cancelled or failed. Returns the race's winning branch and its message
+    /// once the race is decided: by this result if it's confident, or by the
+    /// most confident result once every branch has ended. The branches still
+    /// running then are cancelled.
+    async fn settle_race(
+        &mut self,
+        race_id: uuid::Uuid,
+        branch_id: BranchId,
+        result: Option<(Confidence, String)>,
+    ) -> Option<(BranchId, String)> {
+        let race = self.branch_races.get_mut(&race_id)?;
+        if !race.finish(branch_id, result) {
+            return None;
+        }
+
+        let race = self.branch_races.remove(&race_id)?;
+        for loser in race.running {
+            self.racing_branches.remove(&loser);
+            self.race_losers.insert(loser);
+            if let Err(error) = self.state.cancel_branch(loser).await {
+                tracing::debug!(branch_id = %loser, %error, "race loser already finished");
+            }
+        }
+        let (confidence, winner, message) = race.best?;
+        tracing::info!(%race_id, branch_id = %winner, %confidence, "branch race decided");
+        Some((winner, message))
+    }
+
     /// Flush the pending retrigger: send a synthetic system message to re-trigger
     /// the channel LLM so it can process background results and respond.
     async fn flush_pending_retrigger(&mut self) {
@@ -1718,8 +3200,13 @@ impl Channel {
             return;
         }
 
+        self.persist_memories().await;
+    }
+
+    /// Spawn a memory persistence branch over the messages since the last one.
+    async fn persist_memories(&mut self) {
         // Reset counter before spawning so subsequent messages don't pile up
-        self.message_count = 0;
+        let message_count = std::mem::take(&mut self.message_count);
 
         match spawn_memory_persistence_branch(&self.state, &self.deps).await {
             Ok(branch_id) => {
@@ -1727,7 +3214,7 @@ impl Channel {
                 tracing::info!(
                     channel_id = %self.id,
                     branch_id = %branch_id,
-                    interval = config.message_interval,
+                    message_count,
                     "memory persistence branch spawned"
                 );
             }
@@ -1740,6 +3227,28 @@ impl Channel {
             }
         }
     }
+
+    /// Start generating a title once the conversation has had a few turns.
+    fn check_title(&mut self) {
+        if self.title.is_some()
+            || self.title_requested
+            || self.current_turn < crate::agent::title::TITLE_AFTER_TURNS
+        {
+            return;
+        }
+        self.title_requested = true;
+        crate::agent::title::spawn_title_generation(&self.state);
+    }
+
+    /// Rewrite the rolling summary every few turns.
+    fn check_summary(&mut self) {
+        if self.current_turn < self.summarized_at_turn + crate::agent::summary::SUMMARY_EVERY_TURNS
+        {
+            return;
+        }
+        self.summarized_at_turn = self.current_turn;
+        crate::agent::summary::spawn_summary_update(&self.state, self.summary.clone());
+    }
 }
 
 /// Spawn a branch from a ChannelState. Used by the BranchTool.
@@ -1757,14 +3266,65 @@ pub async fn spawn_branch_from_state(
         )
         .map_err(|e| AgentError::Other(anyhow::anyhow!("{e}")))?;
 
-    spawn_branch(
+    let branch_id = spawn_branch(
         state,
         &description,
         &description,
         &system_prompt,
         &description,
+        None,
     )
-    .await
+    .await?;
+    state.typing.hold(TypingHolder::Branch(branch_id));
+    Ok(branch_id)
+}
+
+/// Spawn `racers` branches on the same question. The channel keeps the first
+/// confident conclusion and cancels the rest.
+pub async fn spawn_branch_race(
+    state: &ChannelState,
+    description: impl Into<String>,
+    racers: usize,
+) -> std::result::Result<Vec<BranchId>, AgentError> {
+    let description = description.into();
+    let max_branches = **state.deps.runtime_config.max_concurrent_branches.load();
+    if state.active_branches.read().await.len() + racers > max_branches {
+        return Err(AgentError::BranchLimitReached {
+            channel_id: state.channel_id.to_string(),
+            max: max_branches,
+        });
+    }
+
+    let rc = &state.deps.runtime_config;
+    let prompt_engine = rc.prompts.load();
+    let system_prompt = prompt_engine
+        .render_branch_prompt(
+            &rc.instance_dir.display().to_string(),
+            &rc.workspace_dir.display().to_string(),
+        )
+        .map_err(|e| AgentError::Other(anyhow::anyhow!("{e}")))?;
+    let race_note = prompt_engine
+        .render_system_branch_race(racers)
+        .map_err(|e| AgentError::Other(anyhow::anyhow!("{e}")))?;
+    let prompt = format!("{description}\n\n{race_note}");
+
+    let race_id = uuid::Uuid::new_v4();
+    let mut branch_ids = Vec::with_capacity(racers);
+    for _ in 0..racers {
+        let branch_id = spawn_branch(
+            state,
+            &description,
+            &prompt,
+            &system_prompt,
+            &description,
+            Some(race_id),
+        )
+        .await?;
+        state.typing.hold(TypingHolder::Branch(branch_id));
+        branch_ids.push(branch_id);
+    }
+    tracing::info!(%race_id, racers, "branch race started");
+    Ok(branch_ids)
 }
 
 /// Spawn a silent memory persistence branch.
@@ -1790,6 +3350,7 @@ async fn spawn_memory_persistence_branch(
         &prompt,
         &system_prompt,
         "persisting memories...",
+        None,
     )
     .await
 }
@@ -1798,13 +3359,20 @@ async fn spawn_memory_persistence_branch(
 ///
 /// Checks the branch limit, clones history, creates a Branch, spawns it as
 /// a tokio task, and registers it in the channel's active branches and status block.
+/// `race_id` is set for branches racing on the same question.
 async fn spawn_branch(
     state: &ChannelState,
     description: &str,
     prompt: &str,
     system_prompt: &str,
     status_label: &str,
+    race_id: Option<uuid::Uuid>,
 ) -> std::result::Result<BranchId, AgentError> {
+    state
+        .deps
+        .runtime_config
+        .circuit_breakers
+        .check(ProcessType::Branch)?;
     let max_branches = **state.deps.runtime_config.max_concurrent_branches.load();
     {
         let branches = state.active_branches.read().await;
@@ -1823,8 +3391,10 @@ async fn spawn_branch(
 
     let tool_server = crate::tools::create_branch_tool_server(
         state.deps.memory_search.clone(),
+        state.deps.history_archive.clone(),
         state.conversation_logger.clone(),
         state.channel_store.clone(),
+        audit_logger_for_turn(state).await,
     );
     let branch_max_turns = **state.deps.runtime_config.branch_max_turns.load();
 
@@ -1839,7 +3409,10 @@ async fn spawn_branch(
     );
 
     let branch_id = branch.id;
+    let deps = state.deps.clone();
     let prompt = prompt.to_owned();
+    let timeout_secs = state.deps.runtime_config.cortex.load().branch_timeout_secs;
+    let timeout = (timeout_secs > 0).then(|| std::time::Duration::from_secs(timeout_secs));
 
     let branch_span = tracing::info_span!(
         "branch.run",
@@ -1849,8 +3422,20 @@ async fn spawn_branch(
     );
     let handle = tokio::spawn(
         async move {
-            if let Err(error) = branch.run(&prompt).await {
-                tracing::error!(branch_id = %branch_id, %error, "branch failed");
+            match branch.run_with_timeout(&prompt, timeout).await {
+                Ok(_) => circuit_breaker::record(&deps, ProcessType::Branch, Ok(())),
+                Err(error) => {
+                    tracing::error!(branch_id = %branch_id, %error, "branch failed");
+                    circuit_breaker::record(&deps, ProcessType::Branch, Err(&error.to_string()));
+                    // Without a result, the channel only learns it ended from this.
+                    deps.event_tx
+                        .send(ProcessEvent::StatusUpdate {
+                            agent_id: deps.agent_id.clone(),
+                            process_id: ProcessId::Branch(branch_id),
+                            status: FAILED_STATUS.to_string(),
+                        })
+                        .ok();
+                }
             }
         }
         .instrument(branch_span),
@@ -1881,6 +3466,7 @@ async fn spawn_branch(
             channel_id: state.channel_id.clone(),
             description: status_label.to_string(),
             reply_to_message_id: *state.reply_target_message_id.read().await,
+            race_id,
         })
         .ok();
 
@@ -1889,27 +3475,79 @@ async fn spawn_branch(
     Ok(branch_id)
 }
 
-/// Check whether the channel has capacity for another worker.
-async fn check_worker_limit(state: &ChannelState) -> std::result::Result<(), AgentError> {
-    let max_workers = **state.deps.runtime_config.max_concurrent_workers.load();
-    let workers = state.active_workers.read().await;
-    if workers.len() >= max_workers {
-        return Err(AgentError::WorkerLimitReached {
-            channel_id: state.channel_id.to_string(),
-            max: max_workers,
+/// Check whether the sender of the current turn may use a capability.
+pub async fn check_capability(
+    state: &ChannelState,
+    capability: Capability,
+) -> std::result::Result<(), AgentError> {
+    let rbac = state.deps.runtime_config.rbac.load();
+    let role = *state.sender_role.read().await;
+    if !rbac.allows(role, capability) {
+        return Err(AgentError::PermissionDenied {
+            capability: capability.to_string(),
+            role: role.to_string(),
+            required: rbac.min_role(capability).to_string(),
         });
     }
     Ok(())
 }
 
+/// Audit logger attributed to the sender(s) of the current turn.
+pub async fn audit_logger_for_turn(state: &ChannelState) -> AuditLogger {
+    let actor = state.sender_actor.read().await.clone();
+    AuditLogger::new(state.deps.sqlite_pool.clone()).with_actor(actor)
+}
+
+/// Take a worker slot for a channel's worker, or a place in the agent's
+/// worker queue. Fails when the queue is full.
+fn request_worker_slot(
+    state: &ChannelState,
+    worker_id: WorkerId,
+    priority: TaskPriority,
+) -> std::result::Result<Ticket, AgentError> {
+    let rc = &state.deps.runtime_config;
+    rc.worker_slots.request(
+        SlotRequest {
+            worker_id,
+            channel_id: Some(state.channel_id.clone()),
+            channel_limit: **rc.max_concurrent_workers.load(),
+            priority,
+        },
+        &rc.worker_queue.load(),
+        &state.deps.agent_id,
+        &state.deps.event_tx,
+    )
+}
+
+/// Show a worker that had to wait as queued. Sent after `WorkerStarted` so
+/// the status block and health monitor know the worker by then.
+fn report_queued_worker(deps: &AgentDeps, worker_id: WorkerId, channel_id: Option<ChannelId>) {
+    if let Some(position) = deps.runtime_config.worker_slots.position(worker_id) {
+        deps.event_tx
+            .send(crate::ProcessEvent::WorkerStatus {
+                agent_id: deps.agent_id.clone(),
+                worker_id,
+                channel_id,
+                status: worker_queue::queued_status(position),
+            })
+            .ok();
+    }
+}
+
 /// Spawn a worker from a ChannelState. Used by the SpawnWorkerTool.
 pub async fn spawn_worker_from_state(
     state: &ChannelState,
     task: impl Into<String>,
     interactive: bool,
     skill_name: Option<&str>,
+    priority: TaskPriority,
 ) -> std::result::Result<WorkerId, AgentError> {
-    check_worker_limit(state).await?;
+    check_capability(state, Capability::Workers).await?;
+    state
+        .deps
+        .runtime_config
+        .circuit_breakers
+        .check(ProcessType::Worker)?;
     let task = task.into();
 
     let rc = &state.deps.runtime_config;
@@ -1936,7 +3574,7 @@ pub async fn spawn_worker_from_state(
         worker_system_prompt
     };
 
-    let worker = if interactive {
+    let mut worker = if interactive {
         let (worker, input_tx) = Worker::new_interactive(
             Some(state.channel_id.clone()),
             &task,
@@ -1966,8 +3604,16 @@ pub async fn spawn_worker_from_state(
             state.logs_dir.clone(),
         )
     };
+    worker.nesting = WorkerNesting::root(state.sub_workers.clone());
 
     let worker_id = worker.id;
+    let ticket = match request_worker_slot(state, worker_id, priority) {
+        Ok(ticket) => ticket,
+        Err(error) => {
+            state.worker_inputs.write().await.remove(&worker_id);
+            return Err(error);
+        }
+    };
 
     let worker_span = tracing::info_span!(
         "worker.run",
@@ -1977,9 +3623,14 @@ pub async fn spawn_worker_from_state(
     let handle = spawn_worker_task(
         worker_id,
         state.deps.event_tx.clone(),
+        EventJournal::new(state.deps.sqlite_pool.clone()),
+        ArtifactStore::new(
+            state.deps.sqlite_pool.clone(),
+            &state.deps.runtime_config.data_dir,
+        ),
         state.deps.agent_id.clone(),
         Some(state.channel_id.clone()),
-        worker.run().instrument(worker_span),
+        worker_queue::run_with_slot(ticket, worker.run()).instrument(worker_span),
     );
 
     state.worker_handles.write().await.insert(worker_id, handle);
@@ -1999,12 +3650,148 @@ pub async fn spawn_worker_from_state(
             task: task.clone(),
         })
         .ok();
+    report_queued_worker(&state.deps, worker_id, Some(state.channel_id.clone()));
+
+    // Interactive workers wait on the user, not the other way around.
+    if !interactive {
+        state.typing.hold(TypingHolder::Worker(worker_id));
+    }
 
     tracing::info!(worker_id = %worker_id, "worker spawned");
 
     Ok(worker_id)
 }
 
+/// Spawn a worker that doesn't belong to a channel, for automations that
+/// run outside a conversation. Its result is only published as a
+/// `WorkerComplete` event. It waits in the agent's worker queue like any
+/// other worker.
+pub fn spawn_detached_worker(
+    deps: &AgentDeps,
+    task: impl Into<String>,
+    priority: TaskPriority,
+    screenshot_dir: std::path::PathBuf,
+    logs_dir: std::path::PathBuf,
+) -> std::result::Result<WorkerId, AgentError> {
+    deps.runtime_config
+        .circuit_breakers
+        .check(ProcessType::Worker)?;
+    let task = task.into();
+    let rc = &deps.runtime_config;
+    let system_prompt = rc
+        .prompts
+        .load()
+        .render_worker_prompt(
+            &rc.instance_dir.display().to_string(),
+            &rc.workspace_dir.display().to_string(),
+        )
+        .map_err(|e| AgentError::Other(anyhow::anyhow!("{e}")))?;
+
+    let worker = Worker::new(
+        None,
+        &task,
+        &system_prompt,
+        deps.clone(),
+        (**rc.browser_config.load()).clone(),
+        screenshot_dir,
+        (**rc.brave_search_key.load()).clone(),
+        logs_dir,
+    );
+    let worker_id = worker.id;
+    let ticket = rc.worker_slots.request(
+        SlotRequest {
+            worker_id,
+            channel_id: None,
+            channel_limit: usize::MAX,
+            priority,
+        },
+        &rc.worker_queue.load(),
+        &deps.agent_id,
+        &deps.event_tx,
+    )?;
+
+    let worker_span = tracing::info_span!("worker.run", worker_id = %worker_id);
+    spawn_worker_task(
+        worker_id,
+        deps.event_tx.clone(),
+        EventJournal::new(deps.sqlite_pool.clone()),
+        ArtifactStore::new(deps.sqlite_pool.clone(), &deps.runtime_config.data_dir),
+        deps.agent_id.clone(),
+        None,
+        worker_queue::run_with_slot(ticket, worker.run()).instrument(worker_span),
+    );
+
+    deps.event_tx
+        .send(crate::ProcessEvent::WorkerStarted {
+            agent_id: deps.agent_id.clone(),
+            worker_id,
+            channel_id: None,
+            task,
+        })
+        .ok();
+    report_queued_worker(deps, worker_id, None);
+
+    tracing::info!(worker_id = %worker_id, "detached worker spawned");
+    Ok(worker_id)
+}
+
+/// Respawn a worker from its checkpoint after a restart. Its result goes to
+/// the channel it was spawned from, which gets it when it next runs. It waits
+/// in the agent's worker queue like any other worker.
+pub fn spawn_resumed_worker(
+    deps: &AgentDeps,
+    checkpoint: WorkerCheckpoint,
+    screenshot_dir: std::path::PathBuf,
+    logs_dir: std::path::PathBuf,
+) -> std::result::Result<WorkerId, AgentError> {
+    let rc = &deps.runtime_config;
+    let channel_id = checkpoint.channel_id.clone();
+    let task = checkpoint.task.clone();
+    let worker = Worker::resume(
+        checkpoint,
+        deps.clone(),
+        (**rc.browser_config.load()).clone(),
+        screenshot_dir,
+        (**rc.brave_search_key.load()).clone(),
+        logs_dir,
+    );
+    let worker_id = worker.id;
+    let ticket = rc.worker_slots.request(
+        SlotRequest {
+            worker_id,
+            channel_id: channel_id.clone(),
+            channel_limit: **rc.max_concurrent_workers.load(),
+            priority: TaskPriority::Normal,
+        },
+        &rc.worker_queue.load(),
+        &deps.agent_id,
+        &deps.event_tx,
+    )?;
+
+    let worker_span = tracing::info_span!("worker.run", worker_id = %worker_id, resumed = true);
+    spawn_worker_task(
+        worker_id,
+        deps.event_tx.clone(),
+        EventJournal::new(deps.sqlite_pool.clone()),
+        ArtifactStore::new(deps.sqlite_pool.clone(), &deps.runtime_config.data_dir),
+        deps.agent_id.clone(),
+        channel_id.clone(),
+        worker_queue::run_with_slot(ticket, worker.run()).instrument(worker_span),
+    );
+
+    deps.event_tx
+        .send(crate::ProcessEvent::WorkerStarted {
+            agent_id: deps.agent_id.clone(),
+            worker_id,
+            channel_id: channel_id.clone(),
+            task,
+        })
+        .ok();
+    report_queued_worker(deps, worker_id, channel_id);
+
+    Ok(worker_id)
+}
+
 /// Spawn an OpenCode-backed worker for coding tasks.
 ///
 /// Instead of a Rig agent loop, this spawns an OpenCode subprocess that has its
@@ -2015,8 +3802,14 @@ pub async fn spawn_opencode_worker_from_state(
     task: impl Into<String>,
     directory: &str,
     interactive: bool,
+    priority: TaskPriority,
 ) -> std::result::Result<crate::WorkerId, AgentError> {
-    check_worker_limit(state).await?;
+    check_capability(state, Capability::OpencodeWorkers).await?;
+    state
+        .deps
+        .runtime_config
+        .circuit_breakers
+        .check(ProcessType::Worker)?;
     let task = task.into();
     let directory = std::path::PathBuf::from(directory);
 
@@ -2030,6 +3823,7 @@ pub async fn spawn_opencode_worker_from_state(
     }
 
     let server_pool = rc.opencode_server_pool.clone();
+    let audit_target = directory.display().to_string();
 
     let worker = if interactive {
         let (worker, input_tx) = crate::opencode::OpenCodeWorker::new_interactive(
@@ -2059,6 +3853,24 @@ pub async fn spawn_opencode_worker_from_state(
     };
 
     let worker_id = worker.id;
+    let ticket = match request_worker_slot(state, worker_id, priority) {
+        Ok(ticket) => ticket,
+        Err(error) => {
+            state.worker_inputs.write().await.remove(&worker_id);
+            return Err(error);
+        }
+    };
+
+    audit_logger_for_turn(state).await.log(
+        AuditAction::OpencodeWorker,
+        &audit_target,
+        serde_json::json!({
+            "worker_id": worker_id.to_string(),
+            "channel_id": state.channel_id.to_string(),
+            "task": task,
+            "interactive": interactive,
+        }),
+    );
 
     let worker_span = tracing::info_span!(
         "worker.run",
@@ -2069,12 +3881,17 @@ pub async fn spawn_opencode_worker_from_state(
     let handle = spawn_worker_task(
         worker_id,
         state.deps.event_tx.clone(),
+        EventJournal::new(state.deps.sqlite_pool.clone()),
+        ArtifactStore::new(
+            state.deps.sqlite_pool.clone(),
+            &state.deps.runtime_config.data_dir,
+        ),
         state.deps.agent_id.clone(),
         Some(state.channel_id.clone()),
-        async move {
+        worker_queue::run_with_slot(ticket, async move {
             let result = worker.run().await?;
             Ok::<String, anyhow::Error>(result.result_text)
-        }
+        })
         .instrument(worker_span),
     );
 
@@ -2096,6 +3913,7 @@ pub async fn spawn_opencode_worker_from_state(
             task: opencode_task,
         })
         .ok();
+    report_queued_worker(&state.deps, worker_id, Some(state.channel_id.clone()));
 
     tracing::info!(worker_id = %worker_id, "OpenCode worker spawned");
 
@@ -2110,6 +3928,8 @@ pub async fn spawn_opencode_worker_from_state(
 fn spawn_worker_task<F, E>(
     worker_id: WorkerId,
     event_tx: broadcast::Sender<ProcessEvent>,
+    journal: EventJournal,
+    artifacts: ArtifactStore,
     agent_id: crate::AgentId,
     channel_id: Option<ChannelId>,
     future: F,
@@ -2132,7 +3952,7 @@ where
             Ok(text) => (text, true),
             Err(error) => {
                 tracing::error!(worker_id = %worker_id, %error, "worker failed");
-                (format!("Worker failed: {error}"), true)
+                (format!("{WORKER_FAILED_PREFIX}{error}"), true)
             }
         };
         #[cfg(feature = "metrics")]
@@ -2148,16 +3968,80 @@ where
                 .observe(worker_start.elapsed().as_secs_f64());
         }
 
-        let _ = event_tx.send(ProcessEvent::WorkerComplete {
-            agent_id,
-            worker_id,
-            channel_id,
-            result: result_text,
-            notify,
-        });
+        let artifacts = artifacts
+            .list_for_worker(worker_id)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!(%worker_id, %error, "failed to load worker artifacts");
+                Vec::new()
+            });
+
+        // Journaled so the result survives the channel's receiver lagging.
+        crate::events::publish(
+            &event_tx,
+            &journal,
+            ProcessEvent::WorkerComplete {
+                agent_id,
+                worker_id,
+                channel_id,
+                result: result_text,
+                notify,
+                artifacts,
+            },
+        )
+        .await;
     })
 }
 
+/// Send the `content` of the reply tool call the model is writing as
+/// `StreamChunk` previews, at most once per [`REPLY_PREVIEW_INTERVAL_MS`].
+/// Ends with the turn's completions, or once the reply itself has been sent.
+async fn preview_reply(
+    mut deltas: mpsc::UnboundedReceiver<CompletionDelta>,
+    response_tx: mpsc::Sender<OutboundResponse>,
+    replied_flag: crate::tools::RepliedFlag,
+) {
+    let interval = std::time::Duration::from_millis(REPLY_PREVIEW_INTERVAL_MS);
+    let mut reply_call = None;
+    let mut arguments = String::new();
+    let mut preview = String::new();
+    let mut last_sent: Option<tokio::time::Instant> = None;
+    while let Some(delta) = deltas.recv().await {
+        match delta {
+            CompletionDelta::ToolCall { id, name } if name.eq_ignore_ascii_case("reply") => {
+                reply_call = Some(id);
+                arguments.clear();
+            }
+            CompletionDelta::ToolArguments { id, json } if reply_call.as_ref() == Some(&id) => {
+                arguments.push_str(&json);
+            }
+            _ => continue,
+        }
+        if replied_flag.load(std::sync::atomic::Ordering::Relaxed) {
+            break;
+        }
+        if last_sent.is_some_and(|sent| sent.elapsed() < interval) {
+            continue;
+        }
+        let Some(content) = partial_string_field(&arguments, "content") else {
+            continue;
+        };
+        let content = content.trim_end();
+        if content.is_empty() || content == preview {
+            continue;
+        }
+        preview = content.to_string();
+        if response_tx
+            .send(OutboundResponse::StreamChunk(preview.clone()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+        last_sent = Some(tokio::time::Instant::now());
+    }
+}
+
 /// Some models emit tool call syntax as plain text instead of making actual tool calls.
 /// When the text starts with a tool-like prefix (e.g. `[reply]`, `(reply)`), try to
 /// extract the reply content so we can send it cleanly instead of showing raw JSON.
@@ -2267,6 +4151,41 @@ fn format_user_message(raw_text: &str, message: &InboundMessage) -> String {
     format!("{display_name}{bot_tag}{reply_context}: {raw_text}")
 }
 
+/// Whether a turn failed because the model call itself failed, as opposed
+/// to a cancellation, tool error or turn limit.
+fn is_provider_failure(result: &std::result::Result<String, rig::completion::PromptError>) -> bool {
+    matches!(
+        result,
+        Err(rig::completion::PromptError::CompletionError(_))
+    )
+}
+
+/// The digest interval of a message from a digest-mode binding.
+fn digest_interval(message: &InboundMessage) -> Option<u64> {
+    message
+        .metadata
+        .get(DIGEST_INTERVAL_KEY)
+        .and_then(|value| value.as_u64())
+        .filter(|secs| *secs > 0)
+}
+
+/// Spell out a digest interval for the prompt, e.g. "hour" or "30 minutes".
+fn format_digest_interval(secs: u64) -> String {
+    let (amount, unit) = if secs % 86_400 == 0 {
+        (secs / 86_400, "day")
+    } else if secs % 3600 == 0 {
+        (secs / 3600, "hour")
+    } else if secs % 60 == 0 {
+        (secs / 60, "minute")
+    } else {
+        (secs, "second")
+    };
+    match amount {
+        1 => unit.to_string(),
+        _ => format!("{amount} {unit}s"),
+    }
+}
+
 fn extract_discord_message_id(message: &InboundMessage) -> Option<u64> {
     if message.source != "discord" {
         return None;
@@ -2275,32 +4194,7 @@ fn extract_discord_message_id(message: &InboundMessage) -> Option<u64> {
     message
         .metadata
         .get("discord_message_id")
-        .and_then(|value| value.as_u64())
-}
-
-/// Check if a ProcessEvent is targeted at a specific channel.
-///
-/// Events from branches and workers carry a channel_id. We only process events
-/// that originated from this channel — otherwise broadcast events from one
-/// channel's workers would leak into sibling channels (e.g. threads).
-fn event_is_for_channel(event: &ProcessEvent, channel_id: &ChannelId) -> bool {
-    match event {
-        ProcessEvent::BranchResult {
-            channel_id: event_channel,
-            ..
-        } => event_channel == channel_id,
-        ProcessEvent::WorkerComplete {
-            channel_id: event_channel,
-            ..
-        } => event_channel.as_ref() == Some(channel_id),
-        ProcessEvent::WorkerStatus {
-            channel_id: event_channel,
-            ..
-        } => event_channel.as_ref() == Some(channel_id),
-        // Status block updates, tool events, etc. — match on agent_id which
-        // is already filtered by the event bus subscription. Let them through.
-        _ => true,
-    }
+        .and_then(|value| value.as_u64())
 }
 
 /// Image MIME types we support for vision.
@@ -2319,238 +4213,522 @@ const TEXT_MIME_PREFIXES: &[&str] = &[
 
 /// Download attachments and convert them to LLM-ready UserContent parts.
 ///
-/// Images become `UserContent::Image` (base64). Text files get inlined.
-/// Other file types get a metadata-only description.
+/// Images are scaled down to the configured limits and become
+/// `UserContent::Image` (base64), with a thumbnail saved for the channel.
+/// Text files are streamed to the workspace and inlined, unless they are
+/// too large, in which case they stay in the workspace. PDF, DOCX and XLSX
+/// documents are converted to text and handled the same way. Videos become
+/// a few sampled frames. Other file types get a metadata-only description. Downloads run concurrently up to
+/// `attachments.max_concurrent_downloads` and are cached on disk, and the
+/// results keep the order of `attachments`.
 async fn download_attachments(
     deps: &AgentDeps,
+    channel_id: &ChannelId,
     attachments: &[crate::Attachment],
-) -> Vec<UserContent> {
-    let http = deps.llm_manager.http_client();
-    let mut parts = Vec::new();
+) -> Vec<DownloadedAttachment> {
+    let egress = EgressPolicy::new(&deps.runtime_config.egress.load());
+    let http = &egress.client(EgressScope::Attachments);
+    let config = deps.runtime_config.attachments.load();
+    let cache = config.cache_enabled.then(|| {
+        AttachmentCache::new(
+            AttachmentCache::directory_for(&deps.runtime_config.data_dir),
+            std::time::Duration::from_secs(config.cache_ttl_secs),
+            config.cache_max_size_bytes(),
+        )
+    });
+    let max_bytes = config.max_file_size_bytes();
+    let inline_text_limit = config.inline_text_limit_bytes();
+    let images = ImageOptions {
+        limits: ImageLimits {
+            max_dimension: config.max_image_dimension,
+            max_bytes: config.max_image_size_bytes(),
+        },
+        thumbnail_dir: config
+            .thumbnails_enabled
+            .then(|| image_preprocess::thumbnail_dir(&deps.runtime_config.data_dir, channel_id)),
+        video_frames: config.video_frames,
+    };
 
-    for attachment in attachments {
-        let is_image = IMAGE_MIME_PREFIXES
-            .iter()
-            .any(|p| attachment.mime_type.starts_with(p));
-        let is_text = TEXT_MIME_PREFIXES
-            .iter()
-            .any(|p| attachment.mime_type.starts_with(p));
-
-        let content = if is_image {
-            download_image_attachment(http, attachment).await
-        } else if is_text {
-            download_text_attachment(http, attachment).await
-        } else if attachment.mime_type.starts_with("audio/") {
-            transcribe_audio_attachment(deps, http, attachment).await
-        } else {
-            let size_str = attachment
-                .size_bytes
-                .map(|s| format!("{:.1} KB", s as f64 / 1024.0))
-                .unwrap_or_else(|| "unknown size".into());
-            UserContent::text(format!(
-                "[Attachment: {} ({}, {})]",
-                attachment.filename, attachment.mime_type, size_str
-            ))
-        };
+    futures::stream::iter(attachments)
+        .map(|attachment| {
+            let egress = &egress;
+            let cache = cache.as_ref();
+            let images = &images;
+            async move {
+                download_attachment(
+                    deps,
+                    egress,
+                    http,
+                    cache,
+                    max_bytes,
+                    inline_text_limit,
+                    images,
+                    attachment,
+                )
+                .await
+            }
+        })
+        .buffered(config.max_concurrent_downloads)
+        .collect()
+        .await
+}
+
+/// A downloaded attachment.
+enum DownloadedAttachment {
+    /// Ready for the model as is.
+    Content(UserContent),
+    /// A text file too large to inline.
+    LargeText(LargeTextAttachment),
+    /// Several parts, like the frames sampled from a video.
+    Parts(Vec<UserContent>),
+}
 
-        parts.push(content);
+impl From<UserContent> for DownloadedAttachment {
+    fn from(content: UserContent) -> Self {
+        Self::Content(content)
     }
+}
+
+/// A text attachment saved to the workspace because it's too large to inline.
+struct LargeTextAttachment {
+    filename: String,
+    mime_type: String,
+    path: std::path::PathBuf,
+    size: u64,
+    line_count: usize,
+    /// The start of the file, up to the inline limit.
+    head: String,
+}
+
+impl LargeTextAttachment {
+    /// Where a worker summarizing the file saves its summary.
+    fn summary_path(&self) -> std::path::PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".summary.md");
+        path.into()
+    }
+
+    /// The start of the file, with a pointer to the rest and to the
+    /// summarizing worker, if one was spawned.
+    fn content(&self, summary_worker: Option<WorkerId>) -> UserContent {
+        let summary_note = match summary_worker {
+            Some(worker_id) => format!(
+                " Worker {worker_id} is summarizing it. The summary arrives when that worker finishes, and is saved at {}.",
+                self.summary_path().display()
+            ),
+            None => " Read the rest with your file or shell tools, or hand it to a worker.".into(),
+        };
+        UserContent::text(format!(
+            "<file name=\"{}\" mime=\"{}\" path=\"{}\">\n{}\n[... showing the first {} of {} bytes ({} lines). The full file is saved at {}.{}]\n</file>",
+            self.filename,
+            self.mime_type,
+            self.path.display(),
+            self.head,
+            self.head.len(),
+            self.size,
+            self.line_count,
+            self.path.display(),
+            summary_note
+        ))
+    }
+}
 
-    parts
+/// How downloaded images, and frames sampled from videos, are prepared for
+/// the model.
+struct ImageOptions {
+    limits: ImageLimits,
+    /// Where thumbnails are saved, when enabled.
+    thumbnail_dir: Option<std::path::PathBuf>,
+    /// Frames sampled from each video. 0 leaves videos as a description.
+    video_frames: usize,
 }
 
-/// Download an image attachment and encode it as base64 for the LLM.
-async fn download_image_attachment(
+/// Download a single attachment and convert it to a content part.
+#[allow(clippy::too_many_arguments)]
+async fn download_attachment(
+    deps: &AgentDeps,
+    egress: &EgressPolicy,
     http: &reqwest::Client,
+    cache: Option<&AttachmentCache>,
+    max_bytes: u64,
+    inline_text_limit: usize,
+    images: &ImageOptions,
     attachment: &crate::Attachment,
-) -> UserContent {
-    let response = match http.get(&attachment.url).send().await {
-        Ok(r) => r,
-        Err(error) => {
-            tracing::warn!(%error, filename = %attachment.filename, "failed to download image");
-            return UserContent::text(format!(
-                "[Failed to download image: {}]",
-                attachment.filename
-            ));
-        }
-    };
+) -> DownloadedAttachment {
+    // Inline attachments carry their bytes, so nothing leaves the host.
+    if !is_data_url(&attachment.url)
+        && let Err(error) = egress.check_url(EgressScope::Attachments, &attachment.url)
+    {
+        tracing::warn!(%error, filename = %attachment.filename, "attachment blocked by egress policy");
+        return UserContent::text(format!(
+            "[Attachment blocked by egress policy: {}]",
+            attachment.filename
+        ))
+        .into();
+    }
 
-    let bytes = match response.bytes().await {
-        Ok(b) => b,
-        Err(error) => {
-            tracing::warn!(%error, filename = %attachment.filename, "failed to read image bytes");
-            return UserContent::text(format!(
-                "[Failed to download image: {}]",
-                attachment.filename
-            ));
-        }
-    };
+    let is_image = IMAGE_MIME_PREFIXES
+        .iter()
+        .any(|p| attachment.mime_type.starts_with(p));
+    let is_text = TEXT_MIME_PREFIXES
+        .iter()
+        .any(|p| attachment.mime_type.starts_with(p));
+    let is_audio = attachment.mime_type.starts_with("audio/");
+    let is_video = attachment.mime_type.starts_with("video/") && images.video_frames > 0;
+    let document = DocumentKind::detect(&attachment.mime_type, &attachment.filename);
+
+    if !is_image && !is_text && !is_audio && !is_video && document.is_none() {
+        let size_str = attachment
+            .size_bytes
+            .map(|s| format!("{:.1} KB", s as f64 / 1024.0))
+            .unwrap_or_else(|| "unknown size".into());
+        return UserContent::text(format!(
+            "[Attachment: {} ({}, {})]",
+            attachment.filename, attachment.mime_type, size_str
+        ))
+        .into();
+    }
 
-    use base64::Engine as _;
-    let base64_data = base64::engine::general_purpose::STANDARD.encode(&bytes);
-    let media_type = ImageMediaType::from_mime_type(&attachment.mime_type);
+    // Skip before downloading when the platform already reported the size.
+    if let Some(size) = attachment.size_bytes
+        && size > max_bytes
+    {
+        return fetch_error_content(
+            attachment,
+            FetchError::TooLarge {
+                size,
+                limit: max_bytes,
+            },
+        )
+        .into();
+    }
+
+    if is_video {
+        return video_attachment_content(http, cache, max_bytes, images, attachment).await;
+    }
+
+    // Text goes straight to disk, so a large file is never held in memory.
+    if is_text {
+        let path = attachment_workspace_path(&deps.runtime_config.workspace_dir, attachment);
+        return match fetch_attachment_to_file(http, &attachment.url, max_bytes, cache, &path).await
+        {
+            Ok(size) => {
+                tracing::info!(
+                    filename = %attachment.filename,
+                    mime = %attachment.mime_type,
+                    size,
+                    "downloaded attachment to workspace"
+                );
+                saved_text_attachment(attachment, path, size, inline_text_limit).await
+            }
+            Err(error) => fetch_error_content(attachment, error).into(),
+        };
+    }
+
+    let bytes = match fetch_attachment(http, &attachment.url, max_bytes, cache).await {
+        Ok(bytes) => bytes,
+        Err(error) => return fetch_error_content(attachment, error).into(),
+    };
 
     tracing::info!(
         filename = %attachment.filename,
         mime = %attachment.mime_type,
         size = bytes.len(),
-        "downloaded image attachment"
+        "downloaded attachment"
     );
 
-    UserContent::image_base64(base64_data, media_type, None)
+    if let Some(kind) = document {
+        return document_attachment_content(deps, attachment, kind, bytes, inline_text_limit).await;
+    }
+    if is_image {
+        image_attachment_content(attachment, bytes, images).await
+    } else {
+        transcribe_audio_attachment(deps, http, attachment, &bytes).await
+    }
+    .into()
 }
 
-/// Download an audio attachment and transcribe it with the configured voice model.
-async fn transcribe_audio_attachment(
+/// Describe a failed download to the model.
+fn fetch_error_content(attachment: &crate::Attachment, error: FetchError) -> UserContent {
+    match error {
+        FetchError::TooLarge { size, limit } => {
+            tracing::info!(
+                filename = %attachment.filename,
+                size,
+                limit,
+                "attachment over size limit, skipped"
+            );
+            UserContent::text(format!(
+                "[Attachment too large to process: {} ({:.1} MB, limit {:.1} MB)]",
+                attachment.filename,
+                size as f64 / (1024.0 * 1024.0),
+                limit as f64 / (1024.0 * 1024.0)
+            ))
+        }
+        error => {
+            tracing::warn!(%error, filename = %attachment.filename, "failed to download attachment");
+            UserContent::text(format!(
+                "[Failed to download file: {}]",
+                attachment.filename
+            ))
+        }
+    }
+}
+
+/// Convert a document to text, saved to the workspace next to text
+/// attachments and then inlined or summarized like one.
+async fn document_attachment_content(
     deps: &AgentDeps,
-    http: &reqwest::Client,
     attachment: &crate::Attachment,
-) -> UserContent {
-    let response = match http.get(&attachment.url).send().await {
-        Ok(r) => r,
-        Err(error) => {
-            tracing::warn!(%error, filename = %attachment.filename, "failed to download audio");
+    kind: DocumentKind,
+    bytes: Vec<u8>,
+    inline_limit: usize,
+) -> DownloadedAttachment {
+    let extracted =
+        tokio::task::spawn_blocking(move || document_extract::extract_text(kind, &bytes)).await;
+    let text = match extracted {
+        Ok(Ok(text)) if !text.trim().is_empty() => text,
+        Ok(Ok(_)) => {
             return UserContent::text(format!(
-                "[Failed to download audio: {}]",
+                "[Document has no extractable text, it may be scanned: {}]",
                 attachment.filename
-            ));
+            ))
+            .into();
         }
-    };
-
-    let bytes = match response.bytes().await {
-        Ok(b) => b,
+        Ok(Err(error)) => {
+            tracing::warn!(%error, filename = %attachment.filename, "can't extract document text");
+            return UserContent::text(format!(
+                "[Failed to extract text from document: {}]",
+                attachment.filename
+            ))
+            .into();
+        }
+        // The PDF parser panics on some malformed files.
         Err(error) => {
-            tracing::warn!(%error, filename = %attachment.filename, "failed to read audio bytes");
+            tracing::warn!(%error, filename = %attachment.filename, "document extraction panicked");
             return UserContent::text(format!(
-                "[Failed to download audio: {}]",
+                "[Failed to extract text from document: {}]",
                 attachment.filename
-            ));
+            ))
+            .into();
         }
     };
 
+    let mut path = attachment_workspace_path(&deps.runtime_config.workspace_dir, attachment);
+    path.as_mut_os_string().push(".txt");
+    let saved = async {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &text).await
+    }
+    .await;
+    if let Err(error) = saved {
+        tracing::warn!(%error, path = %path.display(), "can't save extracted document text");
+        return UserContent::text(format!(
+            "[Failed to save extracted text of document: {}]",
+            attachment.filename
+        ))
+        .into();
+    }
     tracing::info!(
         filename = %attachment.filename,
-        mime = %attachment.mime_type,
-        size = bytes.len(),
-        "downloaded audio attachment"
+        ?kind,
+        size = text.len(),
+        "extracted document text"
     );
 
-    let routing = deps.runtime_config.routing.load();
-    let voice_model = routing.voice.trim();
-    if voice_model.is_empty() {
-        return UserContent::text(format!(
-            "[Audio attachment received but no voice model is configured in routing.voice: {}]",
-            attachment.filename
-        ));
-    }
+    saved_text_attachment(attachment, path, text.len() as u64, inline_limit).await
+}
 
-    let (provider_id, model_name) = match deps.llm_manager.resolve_model(voice_model) {
-        Ok(parts) => parts,
-        Err(error) => {
-            tracing::warn!(%error, model = %voice_model, "invalid voice model route");
+/// Where a text attachment is saved: `attachments/` in the workspace, under
+/// a unique prefix so two uploads with the same name don't collide.
+fn attachment_workspace_path(
+    workspace_dir: &std::path::Path,
+    attachment: &crate::Attachment,
+) -> std::path::PathBuf {
+    let safe_name: String = attachment
+        .filename
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || matches!(character, '.' | '-' | '_') {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let prefix = uuid::Uuid::new_v4().simple().to_string();
+    workspace_dir.join("attachments").join(format!(
+        "{}-{}",
+        &prefix[..8],
+        safe_name.trim_start_matches('.')
+    ))
+}
+
+/// Fit a downloaded image within the configured limits, save its
+/// thumbnail, and encode it as base64 for the LLM.
+async fn image_attachment_content(
+    attachment: &crate::Attachment,
+    bytes: Vec<u8>,
+    images: &ImageOptions,
+) -> UserContent {
+    use base64::Engine as _;
+
+    let original_size = bytes.len();
+    let limits = images.limits;
+    let thumbnail = images.thumbnail_dir.is_some();
+    let prepared =
+        tokio::task::spawn_blocking(move || image_preprocess::prepare(&bytes, limits, thumbnail))
+            .await;
+    let prepared = match prepared {
+        Ok(Ok(prepared)) => prepared,
+        Ok(Err(error)) => {
+            tracing::warn!(%error, filename = %attachment.filename, "can't read image attachment");
             return UserContent::text(format!(
-                "[Audio transcription failed for {}: invalid voice model '{}']",
-                attachment.filename, voice_model
+                "[Image could not be read: {}]",
+                attachment.filename
             ));
         }
-    };
-
-    let provider = match deps.llm_manager.get_provider(&provider_id) {
-        Ok(provider) => provider,
         Err(error) => {
-            tracing::warn!(%error, provider = %provider_id, "voice provider not configured");
+            tracing::warn!(%error, filename = %attachment.filename, "image preprocessing panicked");
             return UserContent::text(format!(
-                "[Audio transcription failed for {}: provider '{}' is not configured]",
-                attachment.filename, provider_id
+                "[Image could not be read: {}]",
+                attachment.filename
             ));
         }
     };
 
-    if provider.api_type == ApiType::Anthropic {
-        return UserContent::text(format!(
-            "[Audio transcription failed for {}: provider '{}' does not support input_audio on this endpoint]",
-            attachment.filename, provider_id
-        ));
+    if prepared.resized() || prepared.bytes.len() != original_size {
+        tracing::info!(
+            filename = %attachment.filename,
+            from = format!("{}x{}", prepared.original_width, prepared.original_height),
+            to = format!("{}x{}", prepared.width, prepared.height),
+            original_size,
+            size = prepared.bytes.len(),
+            "preprocessed image attachment"
+        );
     }
 
-    let format = audio_format_for_attachment(attachment);
-    use base64::Engine as _;
-    let base64_audio = base64::engine::general_purpose::STANDARD.encode(&bytes);
+    if let (Some(directory), Some(thumbnail)) = (&images.thumbnail_dir, &prepared.thumbnail) {
+        let path = image_preprocess::thumbnail_path(directory, &attachment.url);
+        let saved = match tokio::fs::create_dir_all(directory).await {
+            Ok(()) => tokio::fs::write(&path, thumbnail).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = saved {
+            tracing::warn!(%error, path = %path.display(), "failed to save image thumbnail");
+        }
+    }
 
-    let endpoint = format!(
-        "{}/v1/chat/completions",
-        provider.base_url.trim_end_matches('/')
-    );
-    let body = serde_json::json!({
-        "model": model_name,
-        "messages": [{
-            "role": "user",
-            "content": [
-                {
-                    "type": "text",
-                    "text": "Transcribe this audio verbatim. Return only the transcription text."
-                },
-                {
-                    "type": "input_audio",
-                    "input_audio": {
-                        "data": base64_audio,
-                        "format": format,
-                    }
-                }
-            ]
-        }],
-        "temperature": 0
-    });
+    let base64_data = base64::engine::general_purpose::STANDARD.encode(&prepared.bytes);
+    let media_type = ImageMediaType::from_mime_type(prepared.mime_type);
+    UserContent::image_base64(base64_data, media_type, None)
+}
 
-    let response = match http
-        .post(&endpoint)
-        .header("authorization", format!("Bearer {}", provider.api_key))
-        .header("content-type", "application/json")
-        .json(&body)
-        .send()
-        .await
+/// Download a video and sample frames from it for the model, introduced by
+/// the video's name and duration. The video itself isn't kept.
+async fn video_attachment_content(
+    http: &reqwest::Client,
+    cache: Option<&AttachmentCache>,
+    max_bytes: u64,
+    images: &ImageOptions,
+    attachment: &crate::Attachment,
+) -> DownloadedAttachment {
+    use base64::Engine as _;
+
+    let unreadable = || -> DownloadedAttachment {
+        UserContent::text(format!(
+            "[Video could not be read: {}]",
+            attachment.filename
+        ))
+        .into()
+    };
+    let directory = match tempfile::tempdir() {
+        Ok(directory) => directory,
+        Err(error) => {
+            tracing::warn!(%error, "can't create a directory for video sampling");
+            return unreadable();
+        }
+    };
+    let path = directory.path().join("video");
+    if let Err(error) =
+        fetch_attachment_to_file(http, &attachment.url, max_bytes, cache, &path).await
     {
-        Ok(response) => response,
+        return fetch_error_content(attachment, error).into();
+    }
+
+    let sampled = match video_sample::sample_frames(&path, images.video_frames).await {
+        Ok(sampled) => sampled,
         Err(error) => {
-            tracing::warn!(%error, model = %voice_model, "voice transcription request failed");
-            return UserContent::text(format!(
-                "[Audio transcription failed for {}]",
-                attachment.filename
-            ));
+            tracing::warn!(%error, filename = %attachment.filename, "can't sample video frames");
+            return unreadable();
         }
     };
 
-    let status = response.status();
-    let response_body = match response.json::<serde_json::Value>().await {
-        Ok(body) => body,
+    let mut frames = Vec::with_capacity(sampled.frames.len());
+    let mut timestamps = Vec::with_capacity(sampled.frames.len());
+    for frame in sampled.frames {
+        let limits = images.limits;
+        let timestamp_secs = frame.timestamp_secs;
+        let prepared = tokio::task::spawn_blocking(move || {
+            image_preprocess::prepare(&frame.jpeg, limits, false)
+        })
+        .await;
+        let Ok(Ok(prepared)) = prepared else {
+            tracing::warn!(filename = %attachment.filename, "can't read sampled video frame");
+            continue;
+        };
+        let base64_data = base64::engine::general_purpose::STANDARD.encode(&prepared.bytes);
+        let media_type = ImageMediaType::from_mime_type(prepared.mime_type);
+        frames.push(UserContent::image_base64(base64_data, media_type, None));
+        timestamps.push(format!("{timestamp_secs:.1}s"));
+    }
+    if frames.is_empty() {
+        return unreadable();
+    }
+
+    tracing::info!(
+        filename = %attachment.filename,
+        duration_secs = ?sampled.duration_secs,
+        frames = frames.len(),
+        "sampled video attachment"
+    );
+    let duration = sampled
+        .duration_secs
+        .map(|duration| format!("{duration:.1}s"))
+        .unwrap_or_else(|| "unknown".into());
+    let mut parts = vec![UserContent::text(format!(
+        "<video name=\"{}\" mime=\"{}\" duration=\"{duration}\">\nThe {} images after this are frames from the video, taken near {}.\n</video>",
+        attachment.filename,
+        attachment.mime_type,
+        frames.len(),
+        timestamps.join(", ")
+    ))];
+    parts.extend(frames);
+    DownloadedAttachment::Parts(parts)
+}
+
+/// Transcribe a downloaded audio attachment with the agent's transcription
+/// backend.
+async fn transcribe_audio_attachment(
+    deps: &AgentDeps,
+    http: &reqwest::Client,
+    attachment: &crate::Attachment,
+    bytes: &[u8],
+) -> UserContent {
+    let format = audio_format_for_attachment(attachment);
+    let transcript = match crate::agent::voice::transcribe(deps, http, bytes, format).await {
+        Ok(transcript) => transcript,
         Err(error) => {
-            tracing::warn!(%error, model = %voice_model, "invalid transcription response");
             return UserContent::text(format!(
-                "[Audio transcription failed for {}]",
+                "[Audio transcription failed for {}: {error}]",
                 attachment.filename
             ));
         }
     };
-
-    if !status.is_success() {
-        let message = response_body["error"]["message"]
-            .as_str()
-            .unwrap_or("unknown error");
-        tracing::warn!(
-            status = %status,
-            model = %voice_model,
-            error = %message,
-            "voice transcription provider returned error"
-        );
-        return UserContent::text(format!(
-            "[Audio transcription failed for {}: {}]",
-            attachment.filename, message
-        ));
-    }
-
-    let transcript = extract_transcript_text(&response_body);
     if transcript.is_empty() {
-        tracing::warn!(model = %voice_model, "empty transcription returned");
+        tracing::warn!("empty transcription returned");
         return UserContent::text(format!(
             "[Audio transcription returned empty text for {}]",
             attachment.filename
@@ -2602,74 +4780,83 @@ fn audio_format_for_attachment(attachment: &crate::Attachment) -> &'static str {
     }
 }
 
-fn extract_transcript_text(body: &serde_json::Value) -> String {
-    if let Some(text) = body["choices"][0]["message"]["content"].as_str() {
-        return text.trim().to_string();
-    }
-
-    let Some(parts) = body["choices"][0]["message"]["content"].as_array() else {
-        return String::new();
-    };
-
-    parts
-        .iter()
-        .filter_map(|part| {
-            if part["type"].as_str() == Some("text") {
-                part["text"].as_str().map(str::trim)
-            } else {
-                None
-            }
-        })
-        .filter(|text| !text.is_empty())
-        .collect::<Vec<_>>()
-        .join("\n")
-}
-
-/// Download a text attachment and inline its content for the LLM.
-async fn download_text_attachment(
-    http: &reqwest::Client,
+/// Inline a text attachment saved to the workspace.
+///
+/// Files up to `inline_limit` bytes are inlined whole and removed again.
+/// Larger files are read in chunks to get their start and line count, and
+/// stay in the workspace so the rest can be read with the file and shell
+/// tools.
+async fn saved_text_attachment(
     attachment: &crate::Attachment,
-) -> UserContent {
-    let response = match http.get(&attachment.url).send().await {
-        Ok(r) => r,
+    path: std::path::PathBuf,
+    size: u64,
+    inline_limit: usize,
+) -> DownloadedAttachment {
+    let (head, line_count) = match scan_text_file(&path, inline_limit).await {
+        Ok(scan) => scan,
         Err(error) => {
-            tracing::warn!(%error, filename = %attachment.filename, "failed to download text file");
-            return UserContent::text(format!(
-                "[Failed to download file: {}]",
-                attachment.filename
-            ));
+            tracing::warn!(%error, path = %path.display(), "can't read saved attachment");
+            return UserContent::text(format!("[Failed to read file: {}]", attachment.filename))
+                .into();
         }
     };
 
-    let content = match response.text().await {
-        Ok(c) => c,
-        Err(error) => {
-            tracing::warn!(%error, filename = %attachment.filename, "failed to read text file");
-            return UserContent::text(format!("[Failed to read file: {}]", attachment.filename));
-        }
-    };
+    if size <= inline_limit as u64 {
+        tokio::fs::remove_file(&path).await.ok();
+        return UserContent::text(format!(
+            "<file name=\"{}\" mime=\"{}\">\n{}\n</file>",
+            attachment.filename, attachment.mime_type, head
+        ))
+        .into();
+    }
 
-    // Truncate very large files to avoid blowing up context
-    let truncated = if content.len() > 50_000 {
-        format!(
-            "{}...\n[truncated — {} bytes total]",
-            &content[..50_000],
-            content.len()
-        )
-    } else {
-        content
-    };
+    DownloadedAttachment::LargeText(LargeTextAttachment {
+        filename: attachment.filename.clone(),
+        mime_type: attachment.mime_type.clone(),
+        path,
+        size,
+        line_count,
+        head,
+    })
+}
 
-    tracing::info!(
-        filename = %attachment.filename,
-        mime = %attachment.mime_type,
-        "downloaded text attachment"
-    );
+/// Read a text file in chunks, keeping its first `head_bytes` bytes and
+/// counting its lines.
+async fn scan_text_file(
+    path: &std::path::Path,
+    head_bytes: usize,
+) -> std::io::Result<(String, usize)> {
+    use tokio::io::AsyncReadExt as _;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut head = Vec::with_capacity(head_bytes.min(buffer.len()));
+    let mut newlines = 0;
+    let mut last_byte = None;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buffer[..read];
+        if head.len() < head_bytes {
+            let take = (head_bytes - head.len()).min(read);
+            head.extend_from_slice(&chunk[..take]);
+        }
+        newlines += chunk.iter().filter(|byte| **byte == b'\n').count();
+        last_byte = chunk.last().copied();
+    }
 
-    UserContent::text(format!(
-        "<file name=\"{}\" mime=\"{}\">\n{}\n</file>",
-        attachment.filename, attachment.mime_type, truncated
-    ))
+    // A final line without a trailing newline still counts.
+    let line_count = match last_byte {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    };
+    // The cut can land inside a multi-byte character.
+    let head = String::from_utf8_lossy(&head)
+        .trim_end_matches('\u{FFFD}')
+        .to_string();
+    Ok((head, line_count))
 }
 
 /// Write history back after the agentic loop completes.
@@ -2711,9 +4898,12 @@ fn apply_history_after_turn(
 #[cfg(test)]
 mod tests {
     use super::{
-        apply_history_after_turn, is_injection_block, prune_old_injection_blocks,
-        ChannelInjectionState, INJECTION_BLOCK_PREFIX,
+        BranchRace, ChannelInjectionState, INJECTION_BLOCK_PREFIX, OfflineQueue,
+        apply_history_after_turn, format_digest_interval, is_injection_block,
+        prune_old_injection_blocks, scan_text_file,
     };
+    use crate::agent::branch::Confidence;
+    use crate::{BranchId, InboundMessage, MessageContent};
     use rig::completion::{CompletionError, PromptError};
     use rig::message::Message;
     use rig::tool::ToolSetError;
@@ -3134,4 +5324,131 @@ mod tests {
         assert_eq!(injected_count, 0);
         assert_eq!(history.len(), 2);
     }
+
+    #[tokio::test]
+    async fn scan_text_file_keeps_head_and_counts_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        let mut contents = "é".repeat(3);
+        for index in 0..200_000 {
+            contents.push_str(&format!("\nline {index}"));
+        }
+        std::fs::write(&path, &contents).unwrap();
+
+        // 5 bytes cuts the third "é" in half; the partial character is dropped.
+        let (head, line_count) = scan_text_file(&path, 5).await.unwrap();
+        assert_eq!(head, "éé");
+        assert_eq!(line_count, 200_001);
+    }
+
+    #[test]
+    fn digest_intervals_read_naturally() {
+        assert_eq!(format_digest_interval(3600), "hour");
+        assert_eq!(format_digest_interval(7200), "2 hours");
+        assert_eq!(format_digest_interval(1800), "30 minutes");
+        assert_eq!(format_digest_interval(86_400), "day");
+        assert_eq!(format_digest_interval(90), "90 seconds");
+    }
+
+    fn race(racers: &[BranchId]) -> BranchRace {
+        BranchRace {
+            running: racers.iter().copied().collect(),
+            best: None,
+        }
+    }
+
+    #[test]
+    fn racers_ending_without_a_result_leave_the_race_to_the_rest() {
+        let racers = [BranchId::new_v4(), BranchId::new_v4(), BranchId::new_v4()];
+        let mut race = race(&racers);
+
+        // Cancelled, then a medium result: the third racer can still win.
+        assert!(!race.finish(racers[0], None));
+        assert!(!race.finish(racers[1], Some((Confidence::Medium, "medium".into()))));
+        // The last racer failing decides the race for the result that arrived.
+        assert!(race.finish(racers[2], None));
+        let (confidence, winner, message) = race.best.unwrap();
+        assert_eq!(confidence, Confidence::Medium);
+        assert_eq!(winner, racers[1]);
+        assert_eq!(message, "medium");
+    }
+
+    #[test]
+    fn a_race_whose_racers_all_fail_has_no_winner() {
+        let racers = [BranchId::new_v4(), BranchId::new_v4()];
+        let mut race = race(&racers);
+
+        assert!(!race.finish(racers[0], None));
+        assert!(race.finish(racers[1], None));
+        assert!(race.best.is_none());
+    }
+
+    #[test]
+    fn a_confident_result_decides_the_race_early() {
+        let racers = [BranchId::new_v4(), BranchId::new_v4(), BranchId::new_v4()];
+        let mut race = race(&racers);
+
+        assert!(!race.finish(racers[0], Some((Confidence::Low, "low".into()))));
+        assert!(race.finish(racers[1], Some((Confidence::High, "high".into()))));
+        // The racer still running is cancelled as a loser.
+        assert_eq!(race.running.len(), 1);
+        assert!(race.running.contains(&racers[2]));
+        assert_eq!(race.best.unwrap().1, racers[1]);
+    }
+
+    fn inbound(text: &str) -> InboundMessage {
+        InboundMessage {
+            id: text.into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "3".into(),
+            agent_id: None,
+            content: MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: std::collections::HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    fn held_ids(queue: &OfflineQueue) -> Vec<&str> {
+        queue
+            .messages
+            .iter()
+            .map(|message| message.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn offline_queue_keeps_failed_turns_ahead_of_new_arrivals() {
+        let mut queue = OfflineQueue::default();
+        queue.push(vec![inbound("c"), inbound("d")], 10);
+        queue.requeue(vec![inbound("a"), inbound("b")]);
+        queue.push(vec![inbound("e")], 10);
+
+        assert_eq!(held_ids(&queue), ["a", "b", "c", "d", "e"]);
+        assert_eq!(queue.messages.pop_front().unwrap().id, "a");
+    }
+
+    #[test]
+    fn offline_queue_drops_new_arrivals_past_the_bound() {
+        let mut queue = OfflineQueue::default();
+        let dropped = queue.push(vec![inbound("a"), inbound("b"), inbound("c")], 2);
+        assert_eq!(dropped, 1);
+        assert_eq!(held_ids(&queue), ["a", "b"]);
+
+        // A failed turn's messages were already held once and aren't dropped.
+        queue.requeue(vec![inbound("x")]);
+        assert_eq!(held_ids(&queue), ["x", "a", "b"]);
+        assert_eq!(queue.push(vec![inbound("d")], 2), 1);
+    }
+
+    #[test]
+    fn offline_notice_goes_out_once_per_outage() {
+        let mut queue = OfflineQueue::default();
+        assert!(queue.take_notice());
+        assert!(!queue.take_notice());
+
+        queue.drained();
+        assert!(queue.take_notice());
+    }
 }
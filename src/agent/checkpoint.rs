@@ -0,0 +1,248 @@
+//! Worker checkpoints: the progress of running workers, kept in SQLite so
+//! they survive a restart.
+//!
+//! A fire-and-forget worker saves its task, system prompt and history when
+//! it starts and after every segment, and its checkpoint is deleted when it
+//! finishes or is cancelled. Checkpoints left over at startup belong to
+//! workers that were running when Spacebot stopped; [`resume_workers`]
+//! respawns each of them from its last checkpoint. Interactive and OpenCode
+//! workers aren't checkpointed.
+
+use crate::error::Result;
+use crate::{AgentDeps, ChannelId, ProcessEvent, WorkerId};
+
+use anyhow::Context as _;
+use sqlx::{Row as _, SqlitePool};
+
+use std::path::PathBuf;
+
+/// Restarts a worker is resumed across before it's given up on, so a worker
+/// that brings Spacebot down isn't resumed forever.
+pub const MAX_RESUMES: u32 = 3;
+
+/// A worker's progress as of its last segment.
+#[derive(Debug, Clone)]
+pub struct WorkerCheckpoint {
+    pub worker_id: WorkerId,
+    pub channel_id: Option<ChannelId>,
+    pub task: String,
+    pub system_prompt: String,
+    pub history: Vec<rig::message::Message>,
+    /// Segments the worker had run.
+    pub segments: usize,
+    /// Times the worker has been resumed from a checkpoint.
+    pub resumes: u32,
+}
+
+/// Worker checkpoint storage (SQLite).
+#[derive(Debug, Clone)]
+pub struct WorkerCheckpointStore {
+    pool: SqlitePool,
+}
+
+impl WorkerCheckpointStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Save a worker's progress, replacing its previous checkpoint.
+    pub async fn save(
+        &self,
+        worker_id: WorkerId,
+        channel_id: Option<&ChannelId>,
+        task: &str,
+        system_prompt: &str,
+        history: &[rig::message::Message],
+        segments: usize,
+    ) -> Result<()> {
+        let history = serde_json::to_string(history).context("failed to encode worker history")?;
+        sqlx::query(
+            r#"
+            INSERT INTO worker_checkpoints (worker_id, channel_id, task, system_prompt, history, segments)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(worker_id) DO UPDATE SET
+                history = excluded.history,
+                segments = excluded.segments,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(worker_id.to_string())
+        .bind(channel_id.map(|channel_id| channel_id.to_string()))
+        .bind(task)
+        .bind(system_prompt)
+        .bind(history)
+        .bind(segments as i64)
+        .execute(&self.pool)
+        .await
+        .context("failed to save worker checkpoint")?;
+
+        Ok(())
+    }
+
+    /// Every checkpoint, oldest first. Unreadable ones are deleted.
+    pub async fn load_all(&self) -> Result<Vec<WorkerCheckpoint>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT worker_id, channel_id, task, system_prompt, history, segments, resumes
+            FROM worker_checkpoints
+            ORDER BY created_at ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load worker checkpoints")?;
+
+        let mut checkpoints = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.try_get("worker_id").unwrap_or_default();
+            let worker_id = id.parse::<WorkerId>().ok();
+            let history = row
+                .try_get::<String, _>("history")
+                .ok()
+                .and_then(|history| serde_json::from_str(&history).ok());
+            let (Some(worker_id), Some(history)) = (worker_id, history) else {
+                tracing::warn!(worker_id = %id, "can't read worker checkpoint, dropping it");
+                self.delete_raw(&id).await?;
+                continue;
+            };
+            checkpoints.push(WorkerCheckpoint {
+                worker_id,
+                channel_id: row
+                    .try_get::<Option<String>, _>("channel_id")
+                    .ok()
+                    .flatten()
+                    .map(ChannelId::from),
+                task: row.try_get("task").unwrap_or_default(),
+                system_prompt: row.try_get("system_prompt").unwrap_or_default(),
+                history,
+                segments: row.try_get::<i64, _>("segments").unwrap_or(0) as usize,
+                resumes: row.try_get::<i64, _>("resumes").unwrap_or(0) as u32,
+            });
+        }
+        Ok(checkpoints)
+    }
+
+    /// Count a resume of the worker.
+    pub async fn mark_resumed(&self, worker_id: WorkerId) -> Result<()> {
+        sqlx::query("UPDATE worker_checkpoints SET resumes = resumes + 1 WHERE worker_id = ?")
+            .bind(worker_id.to_string())
+            .execute(&self.pool)
+            .await
+            .context("failed to update worker checkpoint")?;
+
+        Ok(())
+    }
+
+    /// Delete a worker's checkpoint.
+    pub async fn delete(&self, worker_id: WorkerId) -> Result<()> {
+        self.delete_raw(&worker_id.to_string()).await
+    }
+
+    async fn delete_raw(&self, worker_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM worker_checkpoints WHERE worker_id = ?")
+            .bind(worker_id)
+            .execute(&self.pool)
+            .await
+            .context("failed to delete worker checkpoint")?;
+
+        Ok(())
+    }
+}
+
+/// Respawn the workers that were running when Spacebot stopped. Workers
+/// already resumed [`MAX_RESUMES`] times are given up on, and their channel
+/// is told they failed.
+pub async fn resume_workers(deps: &AgentDeps, screenshot_dir: PathBuf, logs_dir: PathBuf) {
+    let store = WorkerCheckpointStore::new(deps.sqlite_pool.clone());
+    let checkpoints = match store.load_all().await {
+        Ok(checkpoints) => checkpoints,
+        Err(error) => {
+            tracing::warn!(agent_id = %deps.agent_id, %error, "failed to load worker checkpoints");
+            return;
+        }
+    };
+
+    for checkpoint in checkpoints {
+        let worker_id = checkpoint.worker_id;
+        if checkpoint.resumes >= MAX_RESUMES {
+            tracing::warn!(%worker_id, resumes = checkpoint.resumes, "giving up on resuming worker");
+            if let Err(error) = store.delete(worker_id).await {
+                tracing::warn!(%worker_id, %error, "failed to delete worker checkpoint");
+            }
+            crate::events::publish(
+                &deps.event_tx,
+                &crate::events::EventJournal::new(deps.sqlite_pool.clone()),
+                ProcessEvent::WorkerComplete {
+                    agent_id: deps.agent_id.clone(),
+                    worker_id,
+                    channel_id: checkpoint.channel_id.clone(),
+                    result: format!(
+                        "Worker failed: Spacebot restarted {MAX_RESUMES} times while it was running, so it wasn't resumed again."
+                    ),
+                    notify: true,
+                    artifacts: Vec::new(),
+                },
+            )
+            .await;
+            continue;
+        }
+
+        if let Err(error) = store.mark_resumed(worker_id).await {
+            tracing::warn!(%worker_id, %error, "failed to count worker resume");
+        }
+        if let Err(error) = crate::agent::channel::spawn_resumed_worker(
+            deps,
+            checkpoint,
+            screenshot_dir.clone(),
+            logs_dir.clone(),
+        ) {
+            tracing::warn!(%worker_id, %error, "failed to resume worker from checkpoint");
+            continue;
+        }
+        tracing::info!(%worker_id, "worker resumed from checkpoint");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn checkpoints_are_saved_and_resumed() {
+        let pool = crate::db::test_pool().await;
+        let store = WorkerCheckpointStore::new(pool);
+
+        let worker_id = uuid::Uuid::new_v4();
+        let channel_id = ChannelId::from("discord:1:2");
+        store
+            .save(worker_id, Some(&channel_id), "build it", "prompt", &[], 0)
+            .await
+            .unwrap();
+        let history = vec![rig::message::Message::from("build it")];
+        store
+            .save(
+                worker_id,
+                Some(&channel_id),
+                "build it",
+                "prompt",
+                &history,
+                2,
+            )
+            .await
+            .unwrap();
+        store.mark_resumed(worker_id).await.unwrap();
+
+        let checkpoints = store.load_all().await.unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        let checkpoint = &checkpoints[0];
+        assert_eq!(checkpoint.worker_id, worker_id);
+        assert_eq!(checkpoint.channel_id.as_deref(), Some("discord:1:2"));
+        assert_eq!(checkpoint.task, "build it");
+        assert_eq!(checkpoint.history.len(), 1);
+        assert_eq!(checkpoint.segments, 2);
+        assert_eq!(checkpoint.resumes, 1);
+
+        store.delete(worker_id).await.unwrap();
+        assert!(store.load_all().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+//! Circuit breakers for the agent's process types.
+//!
+//! After `cortex.circuit_breaker_threshold` failures in a row of one process
+//! type, its breaker opens: channels can't spawn workers or branches while
+//! theirs is open, and get an error saying why. After
+//! [`HALF_OPEN_AFTER_SECS`] the breaker goes half-open and lets processes
+//! through again. The next one to finish closes it if it succeeded, or opens
+//! it again if it failed.
+//!
+//! Workers are counted from their `WorkerComplete` events; branches and the
+//! cortex's bulletin generation report their outcome directly. Every state
+//! change is published as a `CircuitBreakerChanged` event and logged to the
+//! cortex log, and the API serves the current state.
+
+use crate::agent::cortex::CortexLogger;
+use crate::agent::worker::WORKER_FAILED_PREFIX;
+use crate::error::AgentError;
+use crate::events::{EventFilter, EventSubscription};
+use crate::{AgentDeps, ProcessEvent, ProcessType};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Seconds an open breaker waits before letting processes through again.
+pub const HALF_OPEN_AFTER_SECS: i64 = 300;
+
+/// Process types with a breaker, listed even while they're closed.
+const TRACKED: [ProcessType; 3] = [
+    ProcessType::Worker,
+    ProcessType::Branch,
+    ProcessType::Cortex,
+];
+
+/// How often open breakers are checked for going half-open.
+const CHECK_INTERVAL_SECS: u64 = 10;
+
+/// State of one breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Processes run normally.
+    Closed,
+    /// Too many failures in a row; no new processes are spawned.
+    Open,
+    /// The wait is over; the next process to finish decides.
+    HalfOpen,
+}
+
+impl std::fmt::Display for BreakerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakerState::Closed => write!(f, "closed"),
+            BreakerState::Open => write!(f, "open"),
+            BreakerState::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+/// One process type's breaker, as the API shows it.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub process_type: ProcessType,
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// When the breaker last changed state.
+    pub changed_at: Option<DateTime<Utc>>,
+}
+
+impl BreakerStatus {
+    fn closed(process_type: ProcessType) -> Self {
+        Self {
+            process_type,
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            last_error: None,
+            changed_at: None,
+        }
+    }
+}
+
+/// A breaker changing state.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub process_type: ProcessType,
+    pub from: BreakerState,
+    pub to: BreakerState,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+/// The breakers of one agent, kept on its `RuntimeConfig`.
+#[derive(Debug, Default)]
+pub struct CircuitBreakers {
+    breakers: Mutex<HashMap<ProcessType, BreakerStatus>>,
+}
+
+impl CircuitBreakers {
+    /// Count a failure. Opens the breaker once `threshold` failures are in a
+    /// row, or right away when it was half-open. A threshold of 0 never
+    /// opens it.
+    pub fn record_failure(
+        &self,
+        process_type: ProcessType,
+        error: &str,
+        threshold: u8,
+        now: DateTime<Utc>,
+    ) -> Option<Transition> {
+        let mut breakers = self.breakers.lock().ok()?;
+        let breaker = breakers
+            .entry(process_type)
+            .or_insert_with(|| BreakerStatus::closed(process_type));
+        breaker.consecutive_failures += 1;
+        breaker.last_error = Some(error.to_string());
+
+        let trips = match breaker.state {
+            BreakerState::Closed => {
+                threshold > 0 && breaker.consecutive_failures >= u32::from(threshold)
+            }
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => false,
+        };
+        trips.then(|| transition(breaker, BreakerState::Open, now))
+    }
+
+    /// Count a success, which closes the breaker.
+    pub fn record_success(
+        &self,
+        process_type: ProcessType,
+        now: DateTime<Utc>,
+    ) -> Option<Transition> {
+        let mut breakers = self.breakers.lock().ok()?;
+        let breaker = breakers.get_mut(&process_type)?;
+        breaker.consecutive_failures = 0;
+        (breaker.state != BreakerState::Closed)
+            .then(|| transition(breaker, BreakerState::Closed, now))
+    }
+
+    /// Turn the breakers that have been open long enough half-open.
+    pub fn half_open_due(&self, now: DateTime<Utc>) -> Vec<Transition> {
+        let Ok(mut breakers) = self.breakers.lock() else {
+            return Vec::new();
+        };
+        breakers
+            .values_mut()
+            .filter(|breaker| {
+                breaker.state == BreakerState::Open
+                    && breaker.changed_at.is_some_and(|changed_at| {
+                        now - changed_at >= chrono::Duration::seconds(HALF_OPEN_AFTER_SECS)
+                    })
+            })
+            .map(|breaker| transition(breaker, BreakerState::HalfOpen, now))
+            .collect()
+    }
+
+    /// Fail when `process_type`'s breaker is open.
+    pub fn check(&self, process_type: ProcessType) -> Result<(), AgentError> {
+        let Ok(breakers) = self.breakers.lock() else {
+            return Ok(());
+        };
+        match breakers.get(&process_type) {
+            Some(breaker) if breaker.state == BreakerState::Open => Err(AgentError::CircuitOpen {
+                process_type: process_type.to_string(),
+                failures: breaker.consecutive_failures,
+                last_error: breaker.last_error.clone().unwrap_or_default(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every breaker's state, tracked process types first.
+    pub fn snapshot(&self) -> Vec<BreakerStatus> {
+        let Ok(breakers) = self.breakers.lock() else {
+            return Vec::new();
+        };
+        let mut statuses = TRACKED
+            .iter()
+            .map(|process_type| {
+                breakers
+                    .get(process_type)
+                    .cloned()
+                    .unwrap_or_else(|| BreakerStatus::closed(*process_type))
+            })
+            .collect::<Vec<_>>();
+        statuses.extend(
+            breakers
+                .values()
+                .filter(|breaker| !TRACKED.contains(&breaker.process_type))
+                .cloned(),
+        );
+        statuses
+    }
+}
+
+fn transition(breaker: &mut BreakerStatus, to: BreakerState, now: DateTime<Utc>) -> Transition {
+    let from = breaker.state;
+    breaker.state = to;
+    breaker.changed_at = Some(now);
+    Transition {
+        process_type: breaker.process_type,
+        from,
+        to,
+        consecutive_failures: breaker.consecutive_failures,
+        last_error: breaker.last_error.clone(),
+    }
+}
+
+/// Record how a process of `process_type` ended, and announce the state
+/// change if it caused one.
+pub fn record(deps: &AgentDeps, process_type: ProcessType, outcome: Result<(), &str>) {
+    let breakers = &deps.runtime_config.circuit_breakers;
+    let now = Utc::now();
+    let transition = match outcome {
+        Ok(()) => breakers.record_success(process_type, now),
+        Err(error) => {
+            let threshold = deps.runtime_config.cortex.load().circuit_breaker_threshold;
+            breakers.record_failure(process_type, error, threshold, now)
+        }
+    };
+    if let Some(transition) = transition {
+        announce(deps, &transition);
+    }
+}
+
+fn announce(deps: &AgentDeps, transition: &Transition) {
+    let process_type = transition.process_type;
+    let details = serde_json::json!({
+        "process_type": process_type,
+        "from": transition.from,
+        "to": transition.to,
+        "consecutive_failures": transition.consecutive_failures,
+        "last_error": transition.last_error,
+    });
+    let logger = CortexLogger::new(deps.sqlite_pool.clone());
+    match transition.to {
+        BreakerState::Open => {
+            tracing::warn!(
+                %process_type,
+                failures = transition.consecutive_failures,
+                last_error = ?transition.last_error,
+                "circuit breaker opened"
+            );
+            logger.log(
+                "circuit_breaker_tripped",
+                &format!(
+                    "{process_type} circuit breaker opened after {} consecutive failures",
+                    transition.consecutive_failures
+                ),
+                Some(details),
+            );
+        }
+        BreakerState::HalfOpen => {
+            tracing::info!(%process_type, "circuit breaker half-open");
+        }
+        BreakerState::Closed => {
+            tracing::info!(%process_type, "circuit breaker closed");
+            logger.log(
+                "circuit_breaker_closed",
+                &format!("{process_type} circuit breaker closed"),
+                Some(details),
+            );
+        }
+    }
+
+    deps.event_tx
+        .send(ProcessEvent::CircuitBreakerChanged {
+            agent_id: deps.agent_id.clone(),
+            process_type,
+            state: transition.to,
+            consecutive_failures: transition.consecutive_failures,
+            last_error: transition.last_error.clone(),
+        })
+        .ok();
+}
+
+/// Count worker outcomes and turn open breakers half-open when their wait
+/// is over.
+pub fn spawn_circuit_breaker_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let agent_id = deps.agent_id.clone();
+        let mut events = EventSubscription::new(
+            agent_id.clone(),
+            "circuit_breakers",
+            deps.event_tx.subscribe(),
+            EventFilter::all().agent(agent_id),
+        );
+        let mut tick = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    for transition in deps.runtime_config.circuit_breakers.half_open_due(Utc::now()) {
+                        announce(&deps, &transition);
+                    }
+                }
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    if let ProcessEvent::WorkerComplete { result, .. } = event {
+                        let outcome = match result.strip_prefix(WORKER_FAILED_PREFIX) {
+                            Some(error) => Err(error),
+                            None => Ok(()),
+                        };
+                        record(&deps, ProcessType::Worker, outcome);
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_threshold_and_closes_after_a_successful_trial() {
+        let breakers = CircuitBreakers::default();
+        let start = Utc::now();
+
+        assert!(
+            breakers
+                .record_failure(ProcessType::Worker, "timeout", 3, start)
+                .is_none()
+        );
+        breakers.record_failure(ProcessType::Worker, "timeout", 3, start);
+        let opened = breakers
+            .record_failure(ProcessType::Worker, "rate limited", 3, start)
+            .unwrap();
+        assert_eq!(opened.to, BreakerState::Open);
+        assert_eq!(opened.consecutive_failures, 3);
+        assert!(matches!(
+            breakers.check(ProcessType::Worker),
+            Err(AgentError::CircuitOpen { failures: 3, .. })
+        ));
+        assert!(breakers.check(ProcessType::Branch).is_ok());
+
+        assert!(breakers.half_open_due(start).is_empty());
+        let later = start + chrono::Duration::seconds(HALF_OPEN_AFTER_SECS);
+        let half_open = breakers.half_open_due(later);
+        assert_eq!(half_open.len(), 1);
+        assert_eq!(half_open[0].to, BreakerState::HalfOpen);
+        assert!(breakers.check(ProcessType::Worker).is_ok());
+
+        // A failed trial opens it again straight away.
+        let reopened = breakers
+            .record_failure(ProcessType::Worker, "timeout", 3, later)
+            .unwrap();
+        assert_eq!(reopened.from, BreakerState::HalfOpen);
+        assert_eq!(reopened.to, BreakerState::Open);
+
+        let closed = breakers.record_success(ProcessType::Worker, later).unwrap();
+        assert_eq!(closed.to, BreakerState::Closed);
+        let snapshot = breakers.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot[0].process_type, ProcessType::Worker);
+        assert_eq!(snapshot[0].state, BreakerState::Closed);
+        assert_eq!(snapshot[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn zero_threshold_never_opens() {
+        let breakers = CircuitBreakers::default();
+        for _ in 0..10 {
+            assert!(
+                breakers
+                    .record_failure(ProcessType::Branch, "failed", 0, Utc::now())
+                    .is_none()
+            );
+        }
+        assert!(breakers.check(ProcessType::Branch).is_ok());
+    }
+}
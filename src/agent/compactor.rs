@@ -3,18 +3,48 @@
 //! The compactor is NOT an LLM process. It watches a channel's context size and
 //! spawns compaction workers when thresholds are crossed. The LLM work (summarization
 //! + memory extraction) happens in the spawned worker, not here.
+//!
+//! Before any messages are summarized, oversized tool results (file dumps,
+//! search results) outside the latest messages are trimmed to their opening
+//! lines. They're usually most of the context, and trimming them keeps every
+//! user and assistant turn. Messages are only summarized if that isn't enough.
+//!
+//! Pinned messages (see [`crate::conversation::pins`]) are never compacted:
+//! they go back into the history ahead of the summary or truncation marker.
+//!
+//! Messages compaction removes are written to the agent's history archive
+//! (see [`crate::conversation::archive`]), where `archive_search` finds them.
+//!
+//! Every run sends `CompactionStarted` and `CompactionCompleted` events with
+//! the history's estimated tokens before and after, and is recorded in the
+//! compaction log (see [`crate::conversation::compactions`]).
 
+use crate::agent::channel::is_injection_block;
+use crate::config::{BindingOverrides, CompactionConfig};
+use crate::conversation::compactions::CompactionRecord;
+use crate::conversation::pins::take_pinned;
+use crate::conversation::{CompactionStore, PinStore};
 use crate::error::Result;
 use crate::llm::SpacebotModel;
-use crate::agent::channel::is_injection_block;
-use crate::{AgentDeps, ChannelId, ProcessType};
+use crate::{AgentDeps, ChannelId, ProcessEvent, ProcessType};
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel as _, Prompt as _};
 use rig::message::{AssistantContent, Message, UserContent};
 use rig::tool::server::{ToolServer, ToolServerHandle};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Tool results in this many of the newest messages are never trimmed, so
+/// the conversation keeps what its latest tool calls returned.
+const RECENT_MESSAGES_KEPT: usize = 6;
+
+/// Chars kept from the start of a trimmed tool result.
+const TRIMMED_TOOL_OUTPUT_HEAD: usize = 500;
+
+/// Ends a tool result that was trimmed, so it isn't trimmed again.
+const TRIMMED_TOOL_OUTPUT_MARKER: &str = "chars of tool output removed by compaction]";
+
 /// Programmatic monitor that watches channel context size and triggers compaction.
 pub struct Compactor {
     pub channel_id: ChannelId,
@@ -35,34 +65,40 @@ impl Compactor {
         }
     }
 
-    /// Check context size and trigger compaction if needed.
+    /// Check context size and trigger compaction if needed, with the context
+    /// window and thresholds of the channel's binding where it sets them.
     ///
     /// Called by the channel after each turn. Returns the action taken, if any.
-    pub async fn check_and_compact(&self) -> Result<Option<CompactionAction>> {
+    pub async fn check_and_compact(
+        &self,
+        overrides: &BindingOverrides,
+    ) -> Result<Option<CompactionAction>> {
         let is_compacting = *self.is_compacting.read().await;
         if is_compacting {
             return Ok(None);
         }
 
         let rc = &self.deps.runtime_config;
-        let context_window = **rc.context_window.load();
-        let compaction_config = **rc.compaction.load();
-
-        let usage = {
-            let history = self.history.read().await;
-            let estimated_tokens = estimate_history_tokens(&history);
-            estimated_tokens as f32 / context_window as f32
-        };
-
-        let action = if usage >= compaction_config.emergency_threshold {
-            Some(CompactionAction::EmergencyTruncate)
-        } else if usage >= compaction_config.aggressive_threshold {
-            Some(CompactionAction::Aggressive)
-        } else if usage >= compaction_config.background_threshold {
-            Some(CompactionAction::Background)
-        } else {
-            None
-        };
+        let context_window = overrides
+            .context_window
+            .unwrap_or(**rc.context_window.load());
+        let compaction_config = overrides.compaction(**rc.compaction.load());
+
+        let mut usage = self.usage(context_window).await;
+        let mut action = CompactionAction::for_usage(usage, &compaction_config);
+
+        // Oversized tool results go first; messages are only summarized if
+        // trimming them doesn't bring usage back under the thresholds.
+        if action.is_some()
+            && self
+                .trim_tool_outputs(&compaction_config, CompactionTrigger::Threshold)
+                .await
+                > 0
+        {
+            usage = self.usage(context_window).await;
+            action = CompactionAction::for_usage(usage, &compaction_config)
+                .or(Some(CompactionAction::TrimToolOutputs));
+        }
 
         if let Some(action) = action {
             tracing::info!(
@@ -73,13 +109,18 @@ impl Compactor {
             );
 
             match action {
+                CompactionAction::TrimToolOutputs => {
+                    // Already done above.
+                }
                 CompactionAction::EmergencyTruncate => {
                     // Emergency is synchronous — fast, no LLM
-                    self.emergency_truncate().await?;
+                    self.emergency_truncate(CompactionTrigger::Threshold)
+                        .await?;
                 }
                 CompactionAction::Background | CompactionAction::Aggressive => {
                     // Background/aggressive spawn a worker
-                    self.spawn_compaction_worker(action).await;
+                    self.spawn_compaction_worker(action, CompactionTrigger::Threshold)
+                        .await;
                 }
             }
 
@@ -89,21 +130,187 @@ impl Compactor {
         }
     }
 
+    /// Compact right away if the history plus the turn about to start,
+    /// `incoming_tokens` of system prompt and new input, would reach the
+    /// emergency threshold, so the turn's request doesn't overflow the
+    /// context window.
+    ///
+    /// Called by the channel before each turn, which waits for it. Oversized
+    /// tool outputs are trimmed first, then the oldest half of the messages
+    /// summarized, then messages truncated until the turn fits. A turn that
+    /// reaches the threshold on its own leaves the history alone, since
+    /// emptying it wouldn't make room. Returns the last action taken, if any.
+    pub async fn compact_before_turn(
+        &self,
+        overrides: &BindingOverrides,
+        incoming_tokens: usize,
+    ) -> Result<Option<CompactionAction>> {
+        let rc = &self.deps.runtime_config;
+        let context_window = overrides
+            .context_window
+            .unwrap_or(**rc.context_window.load());
+        let compaction_config = overrides.compaction(**rc.compaction.load());
+        let threshold = compaction_config.emergency_threshold;
+        let incoming = incoming_tokens as f32 / context_window as f32;
+
+        if incoming >= threshold {
+            tracing::warn!(
+                channel_id = %self.channel_id,
+                incoming = %format!("{:.1}%", incoming * 100.0),
+                "turn alone would overflow the context, not compacting before it"
+            );
+            return Ok(None);
+        }
+
+        let mut usage = self.usage(context_window).await;
+        let mut last = None;
+        while let Some(step) = CompactionAction::before_turn(usage, incoming, threshold, last) {
+            if last.is_none() {
+                tracing::warn!(
+                    channel_id = %self.channel_id,
+                    usage = %format!("{:.1}%", (usage + incoming) * 100.0),
+                    "turn would overflow the context, compacting before it"
+                );
+            }
+            match step {
+                CompactionAction::TrimToolOutputs => {
+                    self.trim_tool_outputs(&compaction_config, CompactionTrigger::BeforeTurn)
+                        .await;
+                }
+                CompactionAction::Background | CompactionAction::Aggressive => {
+                    self.compact_for_turn().await;
+                }
+                CompactionAction::EmergencyTruncate => {
+                    let before = self.history.read().await.len();
+                    self.emergency_truncate(CompactionTrigger::BeforeTurn)
+                        .await?;
+                    if self.history.read().await.len() >= before {
+                        // Nothing left that can go.
+                        return Ok(Some(step));
+                    }
+                }
+            }
+            last = Some(step);
+            usage = self.usage(context_window).await;
+        }
+        Ok(last)
+    }
+
+    /// Summarize the oldest half of the messages and wait for it. A
+    /// background compaction already running has drained its messages and
+    /// isn't waited for; truncation makes up the difference.
+    async fn compact_for_turn(&self) {
+        if std::mem::replace(&mut *self.is_compacting.write().await, true) {
+            return;
+        }
+        let result = async {
+            let compactor_prompt = self
+                .deps
+                .runtime_config
+                .prompts
+                .load()
+                .render_static("compactor")?;
+            run_compaction(
+                &self.deps,
+                &self.channel_id,
+                &compactor_prompt,
+                &self.history,
+                CompactionAction::Aggressive,
+                CompactionTrigger::BeforeTurn,
+            )
+            .await
+        }
+        .await;
+        *self.is_compacting.write().await = false;
+        match result {
+            Ok(turns_compacted) => tracing::info!(
+                channel_id = %self.channel_id,
+                turns_compacted,
+                "pre-turn compaction completed"
+            ),
+            Err(error) => tracing::error!(
+                channel_id = %self.channel_id,
+                %error,
+                "pre-turn compaction failed"
+            ),
+        }
+    }
+
+    /// Compact the channel now, whatever its usage: trim oversized tool
+    /// outputs, then summarize the oldest half of the messages in the
+    /// background. Returns false if a compaction is already running.
+    pub async fn compact_now(&self) -> bool {
+        if *self.is_compacting.read().await {
+            return false;
+        }
+        let compaction_config = **self.deps.runtime_config.compaction.load();
+        self.trim_tool_outputs(&compaction_config, CompactionTrigger::Manual)
+            .await;
+        self.spawn_compaction_worker(CompactionAction::Aggressive, CompactionTrigger::Manual)
+            .await;
+        true
+    }
+
+    /// Estimated share of the context window the history takes up.
+    async fn usage(&self, context_window: usize) -> f32 {
+        let history = self.history.read().await;
+        estimate_history_tokens(&history) as f32 / context_window as f32
+    }
+
+    /// Trim oversized tool results, archiving them whole. Returns how many
+    /// were trimmed.
+    async fn trim_tool_outputs(
+        &self,
+        compaction_config: &CompactionConfig,
+        trigger: CompactionTrigger,
+    ) -> usize {
+        if compaction_config.tool_output_max_chars == 0 {
+            return 0;
+        }
+        let (trimmed, tokens_before, tokens_after) = {
+            let mut history = self.history.write().await;
+            let tokens_before = estimate_history_tokens(&history);
+            let trimmed = trim_tool_outputs(&mut history, compaction_config.tool_output_max_chars);
+            (trimmed, tokens_before, estimate_history_tokens(&history))
+        };
+        if trimmed.is_empty() {
+            return 0;
+        }
+        tracing::info!(
+            channel_id = %self.channel_id,
+            trimmed = trimmed.len(),
+            tokens_before,
+            tokens_after,
+            "trimmed oversized tool outputs"
+        );
+        CompactionReport::start(
+            &self.deps,
+            &self.channel_id,
+            CompactionAction::TrimToolOutputs,
+            trigger,
+            tokens_before,
+        )
+        .finish(tokens_after, trimmed.len(), None);
+        archive_transcript(
+            &self.deps,
+            &self.channel_id,
+            trimmed
+                .iter()
+                .map(|output| format!("[Tool Result]: {output}\n"))
+                .collect(),
+        );
+        trimmed.len()
+    }
+
     /// Spawn a compaction worker in the background.
     ///
     /// The worker reads old messages, runs an LLM to produce a summary + extract
     /// memories, then swaps the summary into the channel's history.
-    async fn spawn_compaction_worker(&self, action: CompactionAction) {
+    async fn spawn_compaction_worker(&self, action: CompactionAction, trigger: CompactionTrigger) {
         let mut is_compacting = self.is_compacting.write().await;
         *is_compacting = true;
         drop(is_compacting);
 
-        let fraction = match action {
-            CompactionAction::Background => 0.3,
-            CompactionAction::Aggressive => 0.5,
-            CompactionAction::EmergencyTruncate => unreachable!(),
-        };
-
         let history = self.history.clone();
         let is_compacting = self.is_compacting.clone();
         let channel_id = self.channel_id.clone();
@@ -120,7 +327,15 @@ impl Compactor {
         };
 
         tokio::spawn(async move {
-            let result = run_compaction(&deps, &compactor_prompt, &history, fraction).await;
+            let result = run_compaction(
+                &deps,
+                &channel_id,
+                &compactor_prompt,
+                &history,
+                action,
+                trigger,
+            )
+            .await;
 
             match result {
                 Ok(turns_compacted) => {
@@ -147,22 +362,48 @@ impl Compactor {
     /// Emergency truncation: drop oldest messages without LLM summarization.
     ///
     /// Only fires at 95%+ context usage. Removes the oldest half of messages and
-    /// inserts a marker. Fast and synchronous.
-    async fn emergency_truncate(&self) -> Result<()> {
+    /// inserts a marker. Fast and synchronous; the removed messages are archived
+    /// in the background.
+    async fn emergency_truncate(&self, trigger: CompactionTrigger) -> Result<()> {
+        let pins = load_pins(&self.deps, &self.channel_id).await;
         let mut history = self.history.write().await;
         let total = history.len();
         if total <= 2 {
             return Ok(());
         }
+        let tokens_before = estimate_history_tokens(&history);
 
-        let remove_count = total / 2;
+        let mut removed: Vec<Message> = history.drain(..total / 2).collect();
+        let pinned = take_pinned(&mut removed, &pins);
+        history.splice(0..0, pinned);
+        let remove_count = removed.len();
+        if remove_count == 0 {
+            return Ok(());
+        }
+        archive_transcript(
+            &self.deps,
+            &self.channel_id,
+            render_messages_as_transcript(&removed),
+        );
 
-        let removed: Vec<Message> = history.drain(..remove_count).collect();
-        drop(removed);
+        let report = CompactionReport::start(
+            &self.deps,
+            &self.channel_id,
+            CompactionAction::EmergencyTruncate,
+            trigger,
+            tokens_before,
+        );
 
         // Insert a marker at the beginning
         let prompt_engine = self.deps.runtime_config.prompts.load();
-        let marker = prompt_engine.render_system_truncation(remove_count)?;
+        let marker = match prompt_engine.render_system_truncation(remove_count) {
+            Ok(marker) => marker,
+            Err(error) => {
+                let tokens_after = estimate_history_tokens(&history);
+                report.finish(tokens_after, remove_count, Some(error.to_string()));
+                return Err(error);
+            }
+        };
         history.insert(0, Message::from(marker));
 
         tracing::warn!(
@@ -171,35 +412,74 @@ impl Compactor {
             remaining = history.len(),
             "emergency truncation performed"
         );
+        report.finish(estimate_history_tokens(&history), remove_count, None);
 
         Ok(())
     }
 }
 
-/// Run the actual compaction: summarize via LLM, extract memories, swap summary into history.
+/// Run a background or aggressive compaction and report it. Returns how many
+/// messages were summarized.
 #[tracing::instrument(skip(deps, compactor_prompt, history), fields(agent_id = %deps.agent_id))]
 async fn run_compaction(
     deps: &AgentDeps,
+    channel_id: &ChannelId,
+    compactor_prompt: &str,
+    history: &Arc<RwLock<Vec<Message>>>,
+    action: CompactionAction,
+    trigger: CompactionTrigger,
+) -> Result<usize> {
+    let fraction = match action {
+        CompactionAction::Background => 0.3,
+        _ => 0.5,
+    };
+    let tokens_before = estimate_history_tokens(&history.read().await);
+    let report = CompactionReport::start(deps, channel_id, action, trigger, tokens_before);
+
+    let result = summarize_oldest(deps, channel_id, compactor_prompt, history, fraction).await;
+
+    let tokens_after = estimate_history_tokens(&history.read().await);
+    match &result {
+        Ok(messages_compacted) => report.finish(tokens_after, *messages_compacted, None),
+        Err(error) => report.finish(tokens_after, 0, Some(error.to_string())),
+    }
+    result
+}
+
+/// Summarize the oldest `fraction` of the history via LLM, extract memories,
+/// and swap the summary into history.
+async fn summarize_oldest(
+    deps: &AgentDeps,
+    channel_id: &ChannelId,
     compactor_prompt: &str,
     history: &Arc<RwLock<Vec<Message>>>,
     fraction: f32,
 ) -> Result<usize> {
-    // 1. Read and remove the oldest messages from history
+    // 1. Read and remove the oldest messages from history. Pinned ones go
+    // straight back, ahead of where the summary will be.
+    let pins = load_pins(deps, channel_id).await;
     let (removed_messages, remove_count) = {
         let mut hist = history.write().await;
         let total = hist.len();
-        let remove_count = ((total as f32 * fraction) as usize)
+        let drain_count = ((total as f32 * fraction) as usize)
             .max(1)
             .min(total.saturating_sub(2));
-        if remove_count == 0 {
+        if drain_count == 0 {
             return Ok(0);
         }
-        let removed: Vec<Message> = hist.drain(..remove_count).collect();
+        let mut removed: Vec<Message> = hist.drain(..drain_count).collect();
+        let pinned = take_pinned(&mut removed, &pins);
+        hist.splice(0..0, pinned);
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        let remove_count = removed.len();
         (removed, remove_count)
     };
 
     // 2. Build the transcript text for the LLM
     let transcript = render_messages_as_transcript(&removed_messages);
+    archive_transcript(deps, channel_id, transcript.clone());
 
     // 3. Run the compaction LLM to produce summary + extracted memories
     let routing = deps.runtime_config.routing.load();
@@ -245,6 +525,170 @@ async fn run_compaction(
     Ok(remove_count)
 }
 
+/// The content of the channel's pinned messages. Compaction goes ahead
+/// without them if they can't be loaded.
+async fn load_pins(deps: &AgentDeps, channel_id: &ChannelId) -> Vec<String> {
+    PinStore::new(deps.sqlite_pool.clone())
+        .contents(channel_id)
+        .await
+        .unwrap_or_else(|error| {
+            tracing::warn!(%error, %channel_id, "failed to load pinned messages");
+            Vec::new()
+        })
+}
+
+/// A compaction run, reported with a `CompactionStarted` event when it starts
+/// and a `CompactionCompleted` event and compaction log record when it
+/// finishes.
+struct CompactionReport {
+    deps: AgentDeps,
+    channel_id: ChannelId,
+    action: CompactionAction,
+    trigger: CompactionTrigger,
+    tokens_before: usize,
+    started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CompactionReport {
+    fn start(
+        deps: &AgentDeps,
+        channel_id: &ChannelId,
+        action: CompactionAction,
+        trigger: CompactionTrigger,
+        tokens_before: usize,
+    ) -> Self {
+        deps.event_tx
+            .send(ProcessEvent::CompactionStarted {
+                agent_id: deps.agent_id.clone(),
+                channel_id: channel_id.clone(),
+                action,
+                trigger,
+                tokens_before,
+            })
+            .ok();
+        Self {
+            deps: deps.clone(),
+            channel_id: channel_id.clone(),
+            action,
+            trigger,
+            tokens_before,
+            started_at: chrono::Utc::now(),
+        }
+    }
+
+    fn finish(self, tokens_after: usize, messages_compacted: usize, error: Option<String>) {
+        self.deps
+            .event_tx
+            .send(ProcessEvent::CompactionCompleted {
+                agent_id: self.deps.agent_id.clone(),
+                channel_id: self.channel_id.clone(),
+                action: self.action,
+                trigger: self.trigger,
+                tokens_before: self.tokens_before,
+                tokens_after,
+                messages_compacted,
+                error: error.clone(),
+            })
+            .ok();
+
+        let record = CompactionRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: self.channel_id.to_string(),
+            action: self.action.as_str().to_string(),
+            trigger: self.trigger.as_str().to_string(),
+            tokens_before: self.tokens_before,
+            tokens_after,
+            messages_compacted,
+            error,
+            started_at: self.started_at,
+            finished_at: chrono::Utc::now(),
+        };
+        let store = CompactionStore::new(self.deps.sqlite_pool.clone());
+        crate::shutdown::spawn_write(async move {
+            if let Err(error) = store.record(&record).await {
+                tracing::warn!(%error, channel_id = %record.channel_id, "failed to record compaction");
+            }
+        });
+    }
+}
+
+/// Write the transcript of messages compaction removed to the history archive,
+/// in the background.
+fn archive_transcript(deps: &AgentDeps, channel_id: &ChannelId, transcript: String) {
+    if transcript.trim().is_empty() {
+        return;
+    }
+    let history_archive = deps.history_archive.clone();
+    let channel_id = channel_id.clone();
+    crate::shutdown::spawn_write(async move {
+        if let Err(error) = history_archive.archive(&channel_id, &transcript).await {
+            tracing::warn!(%error, %channel_id, "failed to archive compacted history");
+        }
+    });
+}
+
+/// Trim tool results longer than `max_chars` to their first few hundred
+/// chars, except in the newest messages. Returns the full text of each one
+/// trimmed.
+fn trim_tool_outputs(history: &mut [Message], max_chars: usize) -> Vec<String> {
+    let mut trimmed = Vec::new();
+    let older = history.len().saturating_sub(RECENT_MESSAGES_KEPT);
+    for message in &mut history[..older] {
+        let Message::User { content } = message else {
+            continue;
+        };
+        for item in content.iter_mut() {
+            let UserContent::ToolResult(tool_result) = item else {
+                continue;
+            };
+            for output in tool_result.content.iter_mut() {
+                let rig::message::ToolResultContent::Text(text) = output else {
+                    continue;
+                };
+                if text.text.len() <= max_chars || text.text.ends_with(TRIMMED_TOOL_OUTPUT_MARKER) {
+                    continue;
+                }
+                let head_end = text
+                    .text
+                    .floor_char_boundary(TRIMMED_TOOL_OUTPUT_HEAD.min(max_chars));
+                let removed = text.text.len() - head_end;
+                let head = text.text[..head_end].trim_end();
+                let full = std::mem::replace(
+                    &mut text.text,
+                    format!("{head}\n[... {removed} more {TRIMMED_TOOL_OUTPUT_MARKER}"),
+                );
+                trimmed.push(full);
+            }
+        }
+    }
+    trimmed
+}
+
+/// First step of compacting a branch's or worker's history, which have no
+/// `Compactor`: if the history takes up at least `threshold` of the context
+/// window, trim its oversized tool results. Returns whether it's still over
+/// the threshold, so the process should compact its oldest messages.
+pub fn trim_process_history(
+    history: &mut [Message],
+    context_window: usize,
+    threshold: f32,
+    tool_output_max_chars: usize,
+) -> bool {
+    let usage =
+        |history: &[Message]| estimate_history_tokens(history) as f32 / context_window as f32;
+    if usage(history) < threshold {
+        return false;
+    }
+    if tool_output_max_chars > 0 {
+        let trimmed = trim_tool_outputs(history, tool_output_max_chars);
+        if !trimmed.is_empty() {
+            tracing::info!(trimmed = trimmed.len(), "trimmed oversized tool outputs");
+            return usage(history) >= threshold;
+        }
+    }
+    true
+}
+
 /// Estimate token count for a history using chars/4 heuristic.
 ///
 /// This is intentionally rough — it's only used for threshold checks, not billing.
@@ -372,9 +816,76 @@ fn extract_summary_section(response: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::render_messages_as_transcript;
+    use super::{
+        CompactionAction, RECENT_MESSAGES_KEPT, TRIMMED_TOOL_OUTPUT_HEAD,
+        render_messages_as_transcript, trim_process_history, trim_tool_outputs,
+    };
     use crate::agent::channel::INJECTION_BLOCK_PREFIX;
-    use rig::message::{AssistantContent, Message, UserContent};
+    use rig::message::{AssistantContent, Message, ToolResultContent, UserContent};
+
+    fn tool_result(text: &str) -> Message {
+        Message::User {
+            content: rig::OneOrMany::one(UserContent::tool_result(
+                "call",
+                rig::OneOrMany::one(ToolResultContent::text(text)),
+            )),
+        }
+    }
+
+    fn tool_result_text(message: &Message) -> &str {
+        let Message::User { content } = message else {
+            panic!("not a user message");
+        };
+        let UserContent::ToolResult(tool_result) = content.first_ref() else {
+            panic!("not a tool result");
+        };
+        let ToolResultContent::Text(text) = tool_result.content.first_ref() else {
+            panic!("not text");
+        };
+        &text.text
+    }
+
+    #[test]
+    fn oversized_tool_outputs_are_trimmed_outside_recent_messages() {
+        let dump = "line\n".repeat(1000);
+        let mut history = vec![
+            tool_result(&dump),
+            tool_result("short"),
+            Message::User {
+                content: rig::OneOrMany::one(UserContent::text(dump.clone())),
+            },
+        ];
+        history.extend((0..RECENT_MESSAGES_KEPT).map(|_| tool_result(&dump)));
+
+        let trimmed = trim_tool_outputs(&mut history, 1000);
+        assert_eq!(trimmed, vec![dump.clone()]);
+        let kept = tool_result_text(&history[0]);
+        assert!(kept.len() < TRIMMED_TOOL_OUTPUT_HEAD + 100);
+        assert!(kept.contains("removed by compaction"));
+        assert_eq!(tool_result_text(&history[1]), "short");
+        assert_eq!(tool_result_text(history.last().unwrap()), dump);
+
+        // Trimmed outputs aren't trimmed again.
+        assert!(trim_tool_outputs(&mut history, 100).is_empty());
+    }
+
+    #[test]
+    fn process_history_is_trimmed_before_compacting() {
+        let dump = "x".repeat(40_000);
+        let mut history = vec![tool_result(&dump)];
+        history.extend((0..RECENT_MESSAGES_KEPT).map(|_| tool_result("short")));
+
+        // About 10,000 tokens: under the threshold of a large window.
+        assert!(!trim_process_history(&mut history, 100_000, 0.7, 4000));
+        assert_eq!(tool_result_text(&history[0]), dump);
+
+        // Over it in a small window, until the dump is trimmed.
+        assert!(!trim_process_history(&mut history, 12_000, 0.7, 4000));
+        assert!(tool_result_text(&history[0]).len() < 1000);
+
+        // Nothing left to trim, so the messages themselves must go.
+        assert!(trim_process_history(&mut history, 10, 0.7, 4000));
+    }
 
     #[test]
     fn render_transcript_skips_injection_blocks() {
@@ -400,11 +911,42 @@ mod tests {
         assert!(!transcript.contains(INJECTION_BLOCK_PREFIX));
         assert!(!transcript.contains("[Fact] hidden"));
     }
+
+    #[test]
+    fn turn_overflow_trims_then_summarizes_then_truncates() {
+        let step = |usage, last| CompactionAction::before_turn(usage, 0.1, 0.95, last);
+
+        assert_eq!(step(0.8, None), None);
+        assert_eq!(step(0.9, None), Some(CompactionAction::TrimToolOutputs));
+        assert_eq!(
+            step(0.9, Some(CompactionAction::TrimToolOutputs)),
+            Some(CompactionAction::Aggressive)
+        );
+        assert_eq!(
+            step(0.9, Some(CompactionAction::Aggressive)),
+            Some(CompactionAction::EmergencyTruncate)
+        );
+        assert_eq!(
+            step(0.9, Some(CompactionAction::EmergencyTruncate)),
+            Some(CompactionAction::EmergencyTruncate)
+        );
+        // Stops as soon as the turn fits.
+        assert_eq!(step(0.5, Some(CompactionAction::TrimToolOutputs)), None);
+    }
+
+    #[test]
+    fn turn_that_overflows_alone_leaves_the_history() {
+        assert_eq!(CompactionAction::before_turn(0.0, 0.95, 0.95, None), None);
+        assert_eq!(CompactionAction::before_turn(0.9, 1.2, 0.95, None), None);
+    }
 }
 
 /// Types of compaction actions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CompactionAction {
+    /// Oversized tool results trimmed, which was enough on its own.
+    TrimToolOutputs,
     /// Normal background compaction (~30% of oldest messages).
     Background,
     /// Aggressive compaction (~50% of oldest messages).
@@ -412,3 +954,64 @@ pub enum CompactionAction {
     /// Emergency truncation (no LLM, drop oldest 50%).
     EmergencyTruncate,
 }
+
+impl CompactionAction {
+    /// The action a context usage calls for, if any.
+    fn for_usage(usage: f32, config: &CompactionConfig) -> Option<Self> {
+        if usage >= config.emergency_threshold {
+            Some(Self::EmergencyTruncate)
+        } else if usage >= config.aggressive_threshold {
+            Some(Self::Aggressive)
+        } else if usage >= config.background_threshold {
+            Some(Self::Background)
+        } else {
+            None
+        }
+    }
+
+    /// The next step to make room for a turn taking up `incoming` of the
+    /// context window, after `last`: trim tool outputs, then summarize, then
+    /// truncate until the history and the turn fit under `threshold`. None
+    /// once they fit, or if the turn alone doesn't.
+    fn before_turn(usage: f32, incoming: f32, threshold: f32, last: Option<Self>) -> Option<Self> {
+        if incoming >= threshold || usage + incoming < threshold {
+            return None;
+        }
+        Some(match last {
+            None => Self::TrimToolOutputs,
+            Some(Self::TrimToolOutputs) => Self::Aggressive,
+            Some(_) => Self::EmergencyTruncate,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TrimToolOutputs => "trim_tool_outputs",
+            Self::Background => "background",
+            Self::Aggressive => "aggressive",
+            Self::EmergencyTruncate => "emergency_truncate",
+        }
+    }
+}
+
+/// What started a compaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionTrigger {
+    /// Usage crossed a threshold after a turn.
+    Threshold,
+    /// The next turn wouldn't have fit in the context window.
+    BeforeTurn,
+    /// Requested through the API.
+    Manual,
+}
+
+impl CompactionTrigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Threshold => "threshold",
+            Self::BeforeTurn => "before_turn",
+            Self::Manual => "manual",
+        }
+    }
+}
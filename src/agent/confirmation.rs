@@ -0,0 +1,297 @@
+//! Confirmations a tool waits on mid-turn.
+//!
+//! A tool sends a prompt with a few options as a `Choices` response, which
+//! adapters render natively (buttons on Discord, a numbered list elsewhere),
+//! and awaits the pick. The channel is busy with the turn while the tool
+//! waits, so the router takes the answer off the inbound stream before it
+//! reaches the channel: a button or menu pick carrying the request's id, or a
+//! text reply naming an option by number, label or value.
+
+use crate::{CHOICE_ACTION_ID, ChoiceOption, InboundMessage, MessageContent, OutboundResponse};
+
+use tokio::sync::{mpsc, oneshot};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Value of the approving option of a yes/no request.
+pub const APPROVE: &str = "yes";
+/// Value of the declining option of a yes/no request.
+pub const DECLINE: &str = "no";
+
+/// How long a request waits for an answer unless told otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Prefix of the option values sent to adapters, followed by the request id.
+const VALUE_PREFIX: &str = "confirm:";
+
+/// A question put to the user, answered by picking one of its options.
+#[derive(Debug, Clone)]
+pub struct ConfirmationRequest {
+    pub prompt: String,
+    pub options: Vec<ChoiceOption>,
+    pub timeout: Duration,
+}
+
+impl ConfirmationRequest {
+    /// A yes/no question answered with [`APPROVE`] or [`DECLINE`].
+    pub fn new(prompt: impl Into<String>) -> Self {
+        let option = |label: &str, value: &str| ChoiceOption {
+            label: label.to_string(),
+            value: Some(value.to_string()),
+            description: None,
+        };
+        Self {
+            prompt: prompt.into(),
+            options: vec![option("Yes", APPROVE), option("No", DECLINE)],
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Replace the yes/no options with custom ones.
+    pub fn with_options(mut self, options: Vec<ChoiceOption>) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+struct Pending {
+    id: String,
+    options: Vec<ChoiceOption>,
+    answer_tx: oneshot::Sender<String>,
+}
+
+/// Open confirmation requests of an agent, at most one per conversation.
+#[derive(Clone, Default)]
+pub struct Confirmations {
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+}
+
+impl std::fmt::Debug for Confirmations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pending = self
+            .pending
+            .lock()
+            .map(|pending| pending.len())
+            .unwrap_or(0);
+        f.debug_struct("Confirmations")
+            .field("pending", &pending)
+            .finish()
+    }
+}
+
+impl Confirmations {
+    /// Ask `request` in the conversation and wait for the value of the picked
+    /// option. Returns `None` on timeout or when a newer request in the same
+    /// conversation replaces this one.
+    pub async fn request(
+        &self,
+        conversation_id: &str,
+        response_tx: &mpsc::Sender<OutboundResponse>,
+        request: ConfirmationRequest,
+    ) -> Option<String> {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        let (answer_tx, answer_rx) = oneshot::channel();
+        let options = request
+            .options
+            .iter()
+            .map(|option| ChoiceOption {
+                value: Some(format!("{VALUE_PREFIX}{id}:{}", option.value())),
+                ..option.clone()
+            })
+            .collect();
+
+        self.lock().insert(
+            conversation_id.to_string(),
+            Pending {
+                id: id.clone(),
+                options: request.options,
+                answer_tx,
+            },
+        );
+
+        let response = OutboundResponse::Choices {
+            prompt: request.prompt,
+            options,
+        };
+        if response_tx.send(response).await.is_err() {
+            self.remove(conversation_id, &id);
+            return None;
+        }
+
+        match tokio::time::timeout(request.timeout, answer_rx).await {
+            Ok(answer) => answer.ok(),
+            Err(_) => {
+                tracing::debug!(conversation_id, "confirmation request timed out");
+                self.remove(conversation_id, &id);
+                None
+            }
+        }
+    }
+
+    /// Hand `message` to the request waiting in its conversation if it
+    /// answers it. Returns `true` when the message was consumed and shouldn't
+    /// reach the channel; late picks of expired requests are consumed too.
+    pub fn resolve(&self, message: &InboundMessage) -> bool {
+        let mut pending = self.lock();
+        let answer = pending
+            .get(&message.conversation_id)
+            .and_then(|request| answer_for(request, &message.content));
+        if let Some(answer) = answer {
+            if let Some(request) = pending.remove(&message.conversation_id) {
+                request.answer_tx.send(answer).ok();
+            }
+            return true;
+        }
+        is_confirmation_pick(&message.content)
+    }
+
+    fn remove(&self, conversation_id: &str, id: &str) {
+        let mut pending = self.lock();
+        if pending
+            .get(conversation_id)
+            .is_some_and(|request| request.id == id)
+        {
+            pending.remove(conversation_id);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Pending>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The value of the option `content` picks from `request`, if any.
+fn answer_for(request: &Pending, content: &MessageContent) -> Option<String> {
+    let find = |value: &str| {
+        request
+            .options
+            .iter()
+            .find(|option| option.value() == value)
+            .map(|option| option.value().to_string())
+    };
+
+    match content {
+        MessageContent::Interaction {
+            action_id, values, ..
+        } if action_id == CHOICE_ACTION_ID => {
+            let picked = values.first()?.strip_prefix(VALUE_PREFIX)?;
+            let (id, value) = picked.split_once(':')?;
+            (id == request.id).then(|| find(value)).flatten()
+        }
+        MessageContent::Text(text) => {
+            let text = text.trim();
+            if let Ok(number) = text.parse::<usize>() {
+                return number
+                    .checked_sub(1)
+                    .and_then(|index| request.options.get(index))
+                    .map(|option| option.value().to_string());
+            }
+            request
+                .options
+                .iter()
+                .find(|option| {
+                    option.label.eq_ignore_ascii_case(text)
+                        || option.value().eq_ignore_ascii_case(text)
+                })
+                .map(|option| option.value().to_string())
+        }
+        _ => None,
+    }
+}
+
+fn is_confirmation_pick(content: &MessageContent) -> bool {
+    matches!(
+        content,
+        MessageContent::Interaction { action_id, values, .. }
+            if action_id == CHOICE_ACTION_ID
+                && values.first().is_some_and(|value| value.starts_with(VALUE_PREFIX))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(content: MessageContent) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "cli".into(),
+            conversation_id: "cli:main".into(),
+            sender_id: "2".into(),
+            agent_id: None,
+            content,
+            timestamp: chrono::Utc::now(),
+            metadata: Default::default(),
+            formatted_author: None,
+        }
+    }
+
+    fn pick(value: &str) -> MessageContent {
+        MessageContent::Interaction {
+            action_id: CHOICE_ACTION_ID.into(),
+            block_id: None,
+            values: vec![value.into()],
+            label: None,
+            message_ts: None,
+        }
+    }
+
+    /// Start a request and return the option values it sent.
+    async fn ask(
+        confirmations: &Confirmations,
+    ) -> (tokio::task::JoinHandle<Option<String>>, Vec<String>) {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        let waiting = confirmations.clone();
+        let handle = tokio::spawn(async move {
+            waiting
+                .request("cli:main", &response_tx, ConfirmationRequest::new("Go?"))
+                .await
+        });
+        let Some(OutboundResponse::Choices { options, .. }) = response_rx.recv().await else {
+            panic!("expected a Choices response");
+        };
+        let values = options
+            .iter()
+            .map(|option| option.value().to_string())
+            .collect();
+        (handle, values)
+    }
+
+    #[tokio::test]
+    async fn text_replies_answer_by_number_or_label() {
+        let confirmations = Confirmations::default();
+
+        let (handle, _) = ask(&confirmations).await;
+        assert!(!confirmations.resolve(&message(MessageContent::Text("maybe".into()))));
+        assert!(confirmations.resolve(&message(MessageContent::Text(" 2 ".into()))));
+        assert_eq!(handle.await.unwrap().as_deref(), Some(DECLINE));
+
+        let (handle, _) = ask(&confirmations).await;
+        assert!(confirmations.resolve(&message(MessageContent::Text("YES".into()))));
+        assert_eq!(handle.await.unwrap().as_deref(), Some(APPROVE));
+        assert!(!confirmations.resolve(&message(MessageContent::Text("yes".into()))));
+    }
+
+    #[tokio::test]
+    async fn picks_answer_only_their_own_request() {
+        let confirmations = Confirmations::default();
+
+        let (first, stale) = ask(&confirmations).await;
+        let (second, values) = ask(&confirmations).await;
+        assert_eq!(first.await.unwrap(), None);
+
+        // A pick from the replaced request is swallowed without answering.
+        assert!(confirmations.resolve(&message(pick(&stale[0]))));
+        assert!(confirmations.resolve(&message(pick(&values[0]))));
+        assert_eq!(second.await.unwrap().as_deref(), Some(APPROVE));
+    }
+}
@@ -6,14 +6,27 @@
 //! conversations ambient awareness of who the user is, what's been decided,
 //! what happened recently, and what's going on.
 //!
-//! The cortex also observes system-wide activity via signals for future use in
-//! health monitoring and memory consolidation.
-
-use crate::error::Result;
+//! While the agent is idle, the cortex also consolidates memory: it merges
+//! near-duplicate memories, promotes the facts that keep being restated and
+//! archives stale ones, so the memory graph doesn't only grow.
+//!
+//! It also watches the branches and workers channels run, and nudges or kills
+//! the ones that get stuck (see [`crate::agent::health`]). When enabled, it
+//! reflects on recent conversations and proposes identity file changes (see
+//! [`crate::agent::reflection`]). Work deferred past a turn waits in its task
+//! queue (see [`crate::agent::task_queue`]), and file changes, webhooks and
+//! timers wake it through triggers (see [`crate::agent::trigger`]). At night
+//! it can dream the day's conversations into memories and a diary entry (see
+//! [`crate::agent::dream`]).
+
+use crate::agent::circuit_breaker;
+use crate::agent::status::StatusBlock;
 use crate::hooks::CortexHook;
 use crate::llm::SpacebotModel;
+use crate::memory::maintenance::{MaintenanceConfig, run_maintenance};
 use crate::memory::search::{SearchConfig, SearchMode, SearchSort};
 use crate::memory::types::{Association, MemoryType, RelationType};
+use crate::messaging::traits::Presence;
 use crate::{AgentDeps, ProcessEvent, ProcessType};
 
 use rig::agent::AgentBuilder;
@@ -23,7 +36,7 @@ use sqlx::{Row as _, SqlitePool};
 
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast};
 
 /// The cortex observes system-wide activity and maintains the memory bulletin.
 pub struct Cortex {
@@ -77,8 +90,9 @@ pub struct CortexEvent {
 
 /// Persists cortex actions to SQLite for audit and UI display.
 ///
-/// All writes are fire-and-forget — they spawn a tokio task and return
-/// immediately so the cortex never blocks on a DB write.
+/// Writes through `log` are fire-and-forget — they spawn a tokio task and
+/// return immediately so the cortex never blocks on a DB write. `record`
+/// waits, for events that something else counts on.
 #[derive(Debug, Clone)]
 pub struct CortexLogger {
     pool: SqlitePool,
@@ -91,28 +105,37 @@ impl CortexLogger {
 
     /// Log a cortex action. Fire-and-forget.
     pub fn log(&self, event_type: &str, summary: &str, details: Option<serde_json::Value>) {
-        let pool = self.pool.clone();
-        let id = uuid::Uuid::new_v4().to_string();
+        let logger = self.clone();
         let event_type = event_type.to_string();
         let summary = summary.to_string();
-        let details_json = details.map(|d| d.to_string());
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO cortex_events (id, event_type, summary, details) VALUES (?, ?, ?, ?)",
-            )
-            .bind(&id)
-            .bind(&event_type)
-            .bind(&summary)
-            .bind(&details_json)
-            .execute(&pool)
-            .await
-            {
+        crate::shutdown::spawn_write(async move {
+            if let Err(error) = logger.record(&event_type, &summary, details).await {
                 tracing::warn!(%error, "failed to persist cortex event");
             }
         });
     }
 
+    /// Log a cortex action and wait for it to be written.
+    pub async fn record(
+        &self,
+        event_type: &str,
+        summary: &str,
+        details: Option<serde_json::Value>,
+    ) -> std::result::Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO cortex_events (id, event_type, summary, details) VALUES (?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(event_type)
+        .bind(summary)
+        .bind(details.map(|d| d.to_string()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// Load cortex events with optional type filter, newest first.
     pub async fn load_events(
         &self,
@@ -162,6 +185,23 @@ impl CortexLogger {
 
         Ok(count.0)
     }
+
+    /// Count cortex events of a type logged at or after `since`.
+    pub async fn count_events_since(
+        &self,
+        event_type: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> std::result::Result<i64, sqlx::Error> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM cortex_events WHERE event_type = ? AND created_at >= ?",
+        )
+        .bind(event_type)
+        .bind(since.naive_utc())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0)
+    }
 }
 
 /// Internal row type for SQLite query mapping.
@@ -211,13 +251,14 @@ impl Cortex {
                 task_summary: "completed task".into(),
                 result_summary: result.lines().next().unwrap_or("done").into(),
             }),
-            ProcessEvent::CompactionTriggered {
+            ProcessEvent::CompactionCompleted {
                 channel_id,
-                threshold_reached,
+                messages_compacted,
+                error: None,
                 ..
             } => Some(Signal::Compaction {
                 channel_id: channel_id.to_string(),
-                turns_compacted: (*threshold_reached * 100.0) as i64,
+                turns_compacted: *messages_compacted as i64,
             }),
             _ => None,
         };
@@ -233,12 +274,6 @@ impl Cortex {
             tracing::debug!("cortex received signal, buffer size: {}", buffer.len());
         }
     }
-
-    /// Run periodic consolidation (future: health monitoring, memory maintenance).
-    pub async fn run_consolidation(&self) -> Result<()> {
-        tracing::info!("cortex running consolidation");
-        Ok(())
-    }
 }
 
 /// Spawn the cortex bulletin loop for an agent.
@@ -491,6 +526,7 @@ pub async fn generate_bulletin(deps: &AgentDeps, logger: &CortexLogger) -> bool
             deps.runtime_config
                 .memory_bulletin
                 .store(Arc::new(bulletin));
+            circuit_breaker::record(deps, ProcessType::Cortex, Ok(()));
             logger.log(
                 "bulletin_generated",
                 &format!("Bulletin generated: {word_count} words, {section_count} sections, {duration_ms}ms"),
@@ -506,6 +542,7 @@ pub async fn generate_bulletin(deps: &AgentDeps, logger: &CortexLogger) -> bool
         Err(error) => {
             let duration_ms = started.elapsed().as_millis() as u64;
             tracing::error!(%error, "cortex bulletin synthesis failed, keeping previous bulletin");
+            circuit_breaker::record(deps, ProcessType::Cortex, Err(&error.to_string()));
             logger.log(
                 "bulletin_failed",
                 &format!("Bulletin synthesis failed after {duration_ms}ms: {error}"),
@@ -726,6 +763,223 @@ async fn generate_profile(deps: &AgentDeps, logger: &CortexLogger) {
     }
 }
 
+// -- Presence loop --
+
+/// Spawn the presence loop for an agent.
+///
+/// Keeps an agent-wide status block from process events and, when
+/// `[presence]` is enabled, pushes the presence it renders to the messaging
+/// adapters. Changes are batched so at most one update goes out per
+/// `update_interval_secs`.
+pub fn spawn_presence_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run_presence_loop(&deps).await })
+}
+
+async fn run_presence_loop(deps: &AgentDeps) {
+    let mut event_rx = deps.event_tx.subscribe();
+    let mut status = StatusBlock::new();
+    let mut shown: Option<Presence> = None;
+
+    loop {
+        let interval = deps.runtime_config.presence.load().update_interval_secs;
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(interval);
+        loop {
+            tokio::select! {
+                event = event_rx.recv() => match event {
+                    Ok(ProcessEvent::WorkerStarted { worker_id, task, .. }) => {
+                        status.add_worker(worker_id, task, false);
+                    }
+                    Ok(ProcessEvent::BranchStarted {
+                        branch_id,
+                        description,
+                        ..
+                    }) => status.add_branch(branch_id, description),
+                    Ok(event) => {
+                        status.update(&event);
+                        // Only the running counts matter here.
+                        status.completed_items.clear();
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        crate::events::record_lag(&deps.agent_id, "presence", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = tokio::time::sleep_until(deadline) => break,
+            }
+        }
+
+        let Some(messaging_manager) = &deps.messaging_manager else {
+            continue;
+        };
+        let config = deps.runtime_config.presence.load();
+        let presence = if config.enabled {
+            Some(status.presence(&config))
+        } else {
+            None
+        };
+        if presence == shown {
+            continue;
+        }
+
+        // Disabling clears whatever was shown last.
+        messaging_manager
+            .set_presence(presence.clone().unwrap_or(Presence {
+                idle: false,
+                activity: None,
+            }))
+            .await;
+        shown = presence;
+    }
+}
+
+// -- Consolidation loop --
+
+/// How often the consolidation loop checks whether the agent is idle.
+const CONSOLIDATION_CHECK_SECS: u64 = 60;
+
+/// Spawn the memory consolidation loop for an agent.
+///
+/// Once nothing has happened for `consolidation_idle_secs` and no workers or
+/// branches are running, runs a maintenance pass over the agent's memories,
+/// at most once per `consolidation_interval_secs`. Each pass is logged as a
+/// `maintenance_run` cortex event and published as `MemoryConsolidated`.
+pub fn spawn_consolidation_loop(
+    deps: AgentDeps,
+    logger: CortexLogger,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run_consolidation_loop(&deps, &logger).await })
+}
+
+async fn run_consolidation_loop(deps: &AgentDeps, logger: &CortexLogger) {
+    let mut event_rx = deps.event_tx.subscribe();
+    let mut status = StatusBlock::new();
+    let mut last_activity = Instant::now();
+    let mut last_attempt: Option<Instant> = None;
+    let mut check = tokio::time::interval(Duration::from_secs(CONSOLIDATION_CHECK_SECS));
+
+    loop {
+        tokio::select! {
+            event = event_rx.recv() => match event {
+                Ok(ProcessEvent::MemoryConsolidated { .. }) => {}
+                Ok(event) => {
+                    last_activity = Instant::now();
+                    match event {
+                        ProcessEvent::WorkerStarted { worker_id, task, .. } => {
+                            status.add_worker(worker_id, task, false);
+                        }
+                        ProcessEvent::BranchStarted {
+                            branch_id,
+                            description,
+                            ..
+                        } => status.add_branch(branch_id, description),
+                        event => {
+                            status.update(&event);
+                            status.completed_items.clear();
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    crate::events::record_lag(&deps.agent_id, "consolidation", skipped);
+                    last_activity = Instant::now();
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = check.tick() => {
+                let config = **deps.runtime_config.cortex.load();
+                let idle = Duration::from_secs(config.consolidation_idle_secs);
+                let interval = Duration::from_secs(config.consolidation_interval_secs);
+                if config.consolidation_idle_secs == 0
+                    || last_activity.elapsed() < idle
+                    || !status.active_workers.is_empty()
+                    || status.active_branch_count() > 0
+                    || last_attempt.is_some_and(|attempt| attempt.elapsed() < interval)
+                {
+                    continue;
+                }
+
+                // The last pass may have run before a restart.
+                let since = chrono::Utc::now()
+                    - chrono::Duration::seconds(config.consolidation_interval_secs as i64);
+                match logger.count_events_since("maintenance_run", since).await {
+                    Ok(0) => {}
+                    Ok(_) => {
+                        last_attempt = Some(Instant::now());
+                        continue;
+                    }
+                    Err(error) => {
+                        tracing::warn!(%error, "failed to check the last consolidation pass");
+                        continue;
+                    }
+                }
+
+                last_attempt = Some(Instant::now());
+                consolidate_memories(deps, logger).await;
+            }
+        }
+    }
+}
+
+/// Run one maintenance pass over the agent's memories, then log and publish
+/// its report.
+#[tracing::instrument(skip(deps, logger), fields(agent_id = %deps.agent_id))]
+async fn consolidate_memories(deps: &AgentDeps, logger: &CortexLogger) {
+    let cortex_config = **deps.runtime_config.cortex.load();
+    let config = MaintenanceConfig {
+        merge_similarity_threshold: cortex_config.consolidation_merge_threshold,
+        stale_days: cortex_config.consolidation_stale_days as i64,
+        ..Default::default()
+    };
+
+    tracing::info!("cortex consolidating memories");
+    let started = Instant::now();
+    let report = match run_maintenance(&deps.memory_search, &config).await {
+        Ok(report) => report,
+        Err(error) => {
+            tracing::warn!(%error, "memory consolidation failed");
+            return;
+        }
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    tracing::info!(
+        merged = report.merged,
+        promoted = report.promoted,
+        archived = report.archived,
+        duration_ms,
+        "memory consolidation complete"
+    );
+    let summary = if report.is_empty() {
+        "Consolidation: nothing to merge or archive".to_string()
+    } else {
+        format!(
+            "Consolidation: merged {} duplicates, promoted {}, archived {} stale memories",
+            report.merged, report.promoted, report.archived
+        )
+    };
+    let details = serde_json::json!({
+        "merged": report.merged,
+        "promoted": report.promoted,
+        "archived": report.archived,
+        "merge_similarity_threshold": config.merge_similarity_threshold,
+        "stale_days": config.stale_days,
+        "duration_ms": duration_ms,
+    });
+    // Waited on: the next pass is scheduled from this event.
+    if let Err(error) = logger
+        .record("maintenance_run", &summary, Some(details))
+        .await
+    {
+        tracing::warn!(%error, "failed to persist cortex event");
+    }
+
+    deps.event_tx
+        .send(ProcessEvent::MemoryConsolidated {
+            agent_id: deps.agent_id.clone(),
+            report,
+        })
+        .ok();
+}
+
 // -- Association loop --
 
 /// Spawn the association loop for an agent.
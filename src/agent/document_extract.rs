@@ -0,0 +1,490 @@
+//! Text extraction from document attachments.
+//!
+//! PDF, Word (DOCX) and Excel (XLSX) attachments are converted to plain text
+//! before they reach the model, so it can read them like any text file. Page
+//! and sheet boundaries are kept as marker lines: `--- Page 2 ---` for PDF
+//! pages and Word page breaks, `--- Sheet: Budget ---` for spreadsheets.
+//! Office files are zip archives of XML parts. Only their text is pulled
+//! out; formatting, images and formulas are dropped, and spreadsheet cells
+//! give their last computed value.
+//!
+//! Extraction is synchronous and CPU-bound, so callers run it on a blocking
+//! thread.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Cursor, Read as _};
+
+/// Largest decompressed XML part read from an Office archive, so a zip bomb
+/// can't exhaust memory.
+const MAX_PART_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A document format text can be extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Xlsx,
+}
+
+impl DocumentKind {
+    /// Recognize a document by MIME type, falling back to the file extension
+    /// for platforms that report `application/octet-stream`.
+    pub fn detect(mime_type: &str, filename: &str) -> Option<Self> {
+        match mime_type {
+            "application/pdf" => return Some(Self::Pdf),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                return Some(Self::Docx);
+            }
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+                return Some(Self::Xlsx);
+            }
+            _ => {}
+        }
+        let (_, extension) = filename.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "xlsx" => Some(Self::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+/// Why a document's text couldn't be extracted.
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error("can't read PDF: {0}")]
+    Pdf(#[from] pdf_extract::OutputError),
+    #[error("can't open document archive: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("can't read document: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("document has no {0} part")]
+    MissingPart(String),
+}
+
+/// Extract a document's text, with page or sheet markers.
+pub fn extract_text(kind: DocumentKind, bytes: &[u8]) -> Result<String, ExtractError> {
+    match kind {
+        DocumentKind::Pdf => {
+            let pages = pdf_extract::extract_text_from_mem_by_pages(bytes)?;
+            Ok(with_page_markers(&pages))
+        }
+        DocumentKind::Docx => extract_docx(bytes),
+        DocumentKind::Xlsx => extract_xlsx(bytes),
+    }
+}
+
+fn with_page_markers(pages: &[String]) -> String {
+    pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| format!("--- Page {} ---\n{}", index + 1, page.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+type Archive<'a> = zip::ZipArchive<Cursor<&'a [u8]>>;
+
+fn read_part(archive: &mut Archive<'_>, name: &str) -> Result<String, ExtractError> {
+    let part = match archive.by_name(name) {
+        Ok(part) => part,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(ExtractError::MissingPart(name.to_string()));
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let mut xml = String::new();
+    part.take(MAX_PART_BYTES).read_to_string(&mut xml)?;
+    Ok(xml)
+}
+
+/// Paragraphs become lines and tabs stay tabs. Word records where it last
+/// broke pages when the file was saved; those breaks are used when present,
+/// otherwise only explicit page breaks start a new page.
+fn extract_docx(bytes: &[u8]) -> Result<String, ExtractError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let xml = read_part(&mut archive, "word/document.xml")?;
+    let rendered_breaks = xml.contains("<w:lastRenderedPageBreak");
+
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut in_run = false;
+    let mut in_text = false;
+    for event in XmlEvents::new(&xml) {
+        match event {
+            XmlEvent::Open {
+                name: "w:r", empty, ..
+            } => in_run = !empty,
+            XmlEvent::Close("w:r") => in_run = false,
+            XmlEvent::Open {
+                name: "w:t", empty, ..
+            } => in_text = !empty,
+            XmlEvent::Close("w:t") => in_text = false,
+            XmlEvent::Text(text) if in_text => page.push_str(&unescape(text)),
+            XmlEvent::Open { name: "w:tab", .. } if in_run => page.push('\t'),
+            XmlEvent::Open {
+                name: "w:br",
+                attributes,
+                ..
+            } if in_run => {
+                if attribute(attributes, "w:type") == Some("page") {
+                    if !rendered_breaks {
+                        pages.push(std::mem::take(&mut page));
+                    }
+                } else {
+                    page.push('\n');
+                }
+            }
+            XmlEvent::Open {
+                name: "w:lastRenderedPageBreak",
+                ..
+            } => pages.push(std::mem::take(&mut page)),
+            XmlEvent::Close("w:p") => page.push('\n'),
+            _ => {}
+        }
+    }
+    pages.push(page);
+
+    Ok(with_page_markers(&pages))
+}
+
+/// Each sheet becomes a block of tab-separated rows, in workbook order.
+fn extract_xlsx(bytes: &[u8]) -> Result<String, ExtractError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let shared_strings = match read_part(&mut archive, "xl/sharedStrings.xml") {
+        Ok(xml) => parse_shared_strings(&xml),
+        // Workbooks with only numbers have no shared strings.
+        Err(ExtractError::MissingPart(_)) => Vec::new(),
+        Err(error) => return Err(error),
+    };
+    let workbook = read_part(&mut archive, "xl/workbook.xml")?;
+    let relationships = read_part(&mut archive, "xl/_rels/workbook.xml.rels")?;
+
+    let mut targets = HashMap::new();
+    for event in XmlEvents::new(&relationships) {
+        if let XmlEvent::Open {
+            name: "Relationship",
+            attributes,
+            ..
+        } = event
+            && let (Some(id), Some(target)) =
+                (attribute(attributes, "Id"), attribute(attributes, "Target"))
+        {
+            targets.insert(id, target);
+        }
+    }
+
+    let mut sheets = Vec::new();
+    for event in XmlEvents::new(&workbook) {
+        if let XmlEvent::Open {
+            name: "sheet",
+            attributes,
+            ..
+        } = event
+            && let Some(target) = attribute(attributes, "r:id").and_then(|id| targets.get(id))
+        {
+            let name = attribute(attributes, "name").unwrap_or_default();
+            let path = match target.strip_prefix('/') {
+                Some(absolute) => absolute.to_string(),
+                None => format!("xl/{target}"),
+            };
+            let xml = read_part(&mut archive, &path)?;
+            sheets.push(format!(
+                "--- Sheet: {} ---\n{}",
+                unescape(name),
+                sheet_rows(&xml, &shared_strings)
+            ));
+        }
+    }
+
+    Ok(sheets.join("\n\n"))
+}
+
+fn parse_shared_strings(xml: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut in_phonetic = false;
+    let mut in_text = false;
+    for event in XmlEvents::new(xml) {
+        match event {
+            XmlEvent::Open { name: "si", .. } => strings.push(String::new()),
+            // Phonetic guides repeat the text in another script.
+            XmlEvent::Open {
+                name: "rPh", empty, ..
+            } => in_phonetic = !empty,
+            XmlEvent::Close("rPh") => in_phonetic = false,
+            XmlEvent::Open {
+                name: "t", empty, ..
+            } => in_text = !empty,
+            XmlEvent::Close("t") => in_text = false,
+            XmlEvent::Text(text) if in_text && !in_phonetic => {
+                if let Some(string) = strings.last_mut() {
+                    string.push_str(&unescape(text));
+                }
+            }
+            _ => {}
+        }
+    }
+    strings
+}
+
+fn sheet_rows(xml: &str, shared_strings: &[String]) -> String {
+    let mut lines = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut cell_type = "";
+    let mut cell_column = 0;
+    let mut value = String::new();
+    let mut in_value = false;
+    for event in XmlEvents::new(xml) {
+        match event {
+            XmlEvent::Open { name: "row", .. } => row.clear(),
+            XmlEvent::Open {
+                name: "c",
+                attributes,
+                ..
+            } => {
+                cell_type = attribute(attributes, "t").unwrap_or_default();
+                cell_column = attribute(attributes, "r")
+                    .map(column_index)
+                    .unwrap_or(row.len());
+                value.clear();
+            }
+            XmlEvent::Open {
+                name: "v" | "t",
+                empty,
+                ..
+            } => in_value = !empty,
+            XmlEvent::Close("v" | "t") => in_value = false,
+            XmlEvent::Text(text) if in_value => value.push_str(&unescape(text)),
+            XmlEvent::Close("c") => {
+                let text = match cell_type {
+                    "s" => value
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| shared_strings.get(index))
+                        .cloned()
+                        .unwrap_or_default(),
+                    "b" => String::from(if value.trim() == "1" { "TRUE" } else { "FALSE" }),
+                    _ => std::mem::take(&mut value),
+                };
+                if cell_column >= row.len() {
+                    row.resize(cell_column + 1, String::new());
+                }
+                row[cell_column] = text.replace(['\t', '\n'], " ");
+            }
+            XmlEvent::Close("row") => {
+                let line = row.join("\t");
+                if !line.trim().is_empty() {
+                    lines.push(line.trim_end().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    lines.join("\n")
+}
+
+/// Zero-based column of a cell reference like `C12`.
+fn column_index(reference: &str) -> usize {
+    reference
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .fold(0, |index, letter| {
+            index * 26 + (letter.to_ascii_uppercase() as usize - 'A' as usize + 1)
+        })
+        .saturating_sub(1)
+}
+
+/// A piece of an XML document. Enough of XML for Office parts: no DTDs, and
+/// processing instructions, comments and CDATA are skipped.
+#[derive(Debug, PartialEq, Eq)]
+enum XmlEvent<'a> {
+    Open {
+        name: &'a str,
+        attributes: &'a str,
+        empty: bool,
+    },
+    Close(&'a str),
+    Text(&'a str),
+}
+
+struct XmlEvents<'a> {
+    rest: &'a str,
+}
+
+impl<'a> XmlEvents<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { rest: xml }
+    }
+}
+
+impl<'a> Iterator for XmlEvents<'a> {
+    type Item = XmlEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let Some(after) = self.rest.strip_prefix('<') else {
+                let end = self.rest.find('<').unwrap_or(self.rest.len());
+                let (text, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                return Some(XmlEvent::Text(text));
+            };
+            let end = after.find('>')?;
+            let tag = &after[..end];
+            self.rest = &after[end + 1..];
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                return Some(XmlEvent::Close(name.trim()));
+            }
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            return Some(XmlEvent::Open {
+                name,
+                attributes,
+                empty,
+            });
+        }
+    }
+}
+
+/// The raw value of an attribute in a tag's attribute list.
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    loop {
+        let (key, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        let (value, remaining) = after[quote.len_utf8()..].split_once(quote)?;
+        if key.trim() == name {
+            return Some(value);
+        }
+        rest = remaining;
+    }
+}
+
+/// Decode the predefined entities and character references.
+fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+
+    fn archive(parts: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in parts {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn documents_are_detected_by_mime_type_or_extension() {
+        assert_eq!(
+            DocumentKind::detect("application/pdf", "scan"),
+            Some(DocumentKind::Pdf)
+        );
+        assert_eq!(
+            DocumentKind::detect("application/octet-stream", "Q3 Budget.XLSX"),
+            Some(DocumentKind::Xlsx)
+        );
+        assert_eq!(DocumentKind::detect("application/zip", "notes.zip"), None);
+    }
+
+    #[test]
+    fn docx_paragraphs_and_page_breaks() {
+        let document = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:pPr><w:tabs><w:tab w:val="left" w:pos="720"/></w:tabs></w:pPr><w:r><w:t>Name</w:t><w:tab/><w:t xml:space="preserve">Tom &amp; Jerry</w:t></w:r></w:p>
+<w:p><w:r><w:br w:type="page"/><w:t>Second page</w:t></w:r></w:p>
+</w:body></w:document>"#;
+        let bytes = archive(&[("word/document.xml", document)]);
+
+        assert_eq!(
+            extract_text(DocumentKind::Docx, &bytes).unwrap(),
+            "--- Page 1 ---\nName\tTom & Jerry\n\n--- Page 2 ---\nSecond page"
+        );
+    }
+
+    #[test]
+    fn xlsx_sheets_become_tab_separated_rows() {
+        let workbook = r#"<workbook xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Budget &amp; Plan" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+        let relationships = r#"<Relationships><Relationship Id="rId1" Type="worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+        let shared_strings = r#"<sst><si><t>Item</t></si><si><r><t>Co</t></r><r><t>st</t></r></si><si><t>Rent</t><rPh><t>x</t></rPh></si></sst>"#;
+        let sheet = r#"<worksheet><sheetData>
+<row r="1"><c r="A1" t="s"><v>0</v></c><c r="C1" t="s"><v>1</v></c></row>
+<row r="2"><c r="A2" t="s"><v>2</v></c><c r="B2" t="b"><v>1</v></c><c r="C2"><f>SUM(1,2)</f><v>1200.5</v></c></row>
+<row r="3"><c r="A3" t="inlineStr"><is><t>Total</t></is></c></row>
+</sheetData></worksheet>"#;
+        let bytes = archive(&[
+            ("xl/workbook.xml", workbook),
+            ("xl/_rels/workbook.xml.rels", relationships),
+            ("xl/sharedStrings.xml", shared_strings),
+            ("xl/worksheets/sheet1.xml", sheet),
+        ]);
+
+        assert_eq!(
+            extract_text(DocumentKind::Xlsx, &bytes).unwrap(),
+            "--- Sheet: Budget & Plan ---\nItem\t\tCost\nRent\tTRUE\t1200.5\nTotal"
+        );
+    }
+
+    #[test]
+    fn entities_and_character_references_decode() {
+        assert_eq!(unescape("a &lt;b&gt; &#233;&#x41; & c"), "a <b> éA & c");
+        assert_eq!(column_index("AB7"), 27);
+    }
+}
@@ -0,0 +1,255 @@
+//! Dreaming: the cortex going back over a day of conversations while the
+//! agent is idle.
+//!
+//! Opt in with `[dream] enabled = true`. Once a night, at `hour` in the
+//! agent's cron timezone, the cortex reads the last 24 hours of conversation,
+//! newest first up to `max_input_tokens`, and asks the LLM for the
+//! higher-level things the day showed. Those are saved as memories, at most
+//! `max_memories` of them, and a short diary entry for the day is written to
+//! `diary/YYYY-MM-DD.md` in the workspace.
+//!
+//! Dreams are logged as [`DREAM_EVENT`] cortex events, which is also how a
+//! restart during the dream hour doesn't dream twice.
+
+use crate::agent::cortex::CortexLogger;
+use crate::agent::reflection::load_transcript;
+use crate::llm::SpacebotModel;
+use crate::tools::{MemorySaveArgs, MemorySaveTool};
+use crate::{AgentDeps, ProcessType};
+
+use chrono::Timelike as _;
+use chrono_tz::Tz;
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+use rig::tool::Tool as _;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Cortex event type logged for each dream.
+pub const DREAM_EVENT: &str = "dream";
+
+/// Workspace directory diary entries are written to.
+pub const DIARY_DIR: &str = "diary";
+
+/// How often the loop checks whether a dream is due.
+const CHECK_SECS: u64 = 600;
+
+/// How far back a dream reads.
+const WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// Most messages loaded before the token cap applies.
+const MAX_MESSAGES: i64 = 2000;
+
+/// LLM response shape for a dream.
+#[derive(serde::Deserialize)]
+struct DreamLlmResponse {
+    #[serde(default)]
+    memories: Vec<DreamMemory>,
+    diary: String,
+}
+
+#[derive(serde::Deserialize)]
+struct DreamMemory {
+    content: String,
+    #[serde(default = "default_memory_type")]
+    memory_type: String,
+    importance: Option<f32>,
+}
+
+fn default_memory_type() -> String {
+    "observation".into()
+}
+
+/// What a dream left behind.
+struct Dream {
+    memory_ids: Vec<String>,
+    /// The diary entry, relative to the workspace.
+    diary: PathBuf,
+}
+
+/// Dream once a night while dreaming is enabled.
+pub fn spawn_dream_loop(deps: AgentDeps, logger: CortexLogger) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut check = tokio::time::interval(Duration::from_secs(CHECK_SECS));
+        loop {
+            check.tick().await;
+            let config = deps.runtime_config.dream.load();
+            if !config.enabled {
+                continue;
+            }
+            let timezone = deps.runtime_config.cron_timezone.load();
+            let now = chrono::Utc::now();
+            if local_time(now, timezone.as_deref()).hour() != config.hour {
+                continue;
+            }
+
+            // Checks run every few minutes through the dream hour, and the
+            // agent may have restarted since it dreamed.
+            let since = now - chrono::Duration::hours(20);
+            match logger.count_events_since(DREAM_EVENT, since).await {
+                Ok(0) => {}
+                Ok(_) => continue,
+                Err(error) => {
+                    tracing::warn!(%error, "failed to check the last dream");
+                    continue;
+                }
+            }
+
+            // Named for the day most of the window falls in, so a dream at
+            // 3am writes the previous day's entry.
+            let date = local_time(now - chrono::Duration::hours(12), timezone.as_deref()).date();
+            dream(&deps, &logger, date).await;
+        }
+    })
+}
+
+/// Run one dream and log how it went.
+#[tracing::instrument(skip(deps, logger), fields(agent_id = %deps.agent_id))]
+async fn dream(deps: &AgentDeps, logger: &CortexLogger, date: chrono::NaiveDate) {
+    tracing::info!("cortex dreaming about the day's conversations");
+
+    let (summary, details) = match run(deps, date).await {
+        Ok(None) => (
+            "Dream: no conversations to dream about".to_string(),
+            serde_json::json!({ "date": date }),
+        ),
+        Ok(Some(dream)) => (
+            format!(
+                "Dream: saved {} memories and wrote {}",
+                dream.memory_ids.len(),
+                dream.diary.display()
+            ),
+            serde_json::json!({
+                "date": date,
+                "memory_ids": dream.memory_ids,
+                "diary": dream.diary,
+            }),
+        ),
+        Err(error) => {
+            tracing::warn!(%error, "dream failed");
+            (
+                format!("Dream failed: {error}"),
+                serde_json::json!({ "date": date, "error": error.to_string() }),
+            )
+        }
+    };
+    // Waited on: the next check decides from this event whether to dream.
+    if let Err(error) = logger.record(DREAM_EVENT, &summary, Some(details)).await {
+        tracing::warn!(%error, "failed to persist cortex event");
+    }
+}
+
+/// Turn the last day of conversations into memories and a diary entry.
+/// Returns `None` when there were no conversations.
+async fn run(deps: &AgentDeps, date: chrono::NaiveDate) -> anyhow::Result<Option<Dream>> {
+    let config = deps.runtime_config.dream.load();
+    let transcript = load_transcript(&deps.sqlite_pool, WINDOW_SECS, MAX_MESSAGES).await?;
+    // chars/4, the same rough estimate compaction uses.
+    let transcript = fit_transcript(&transcript, config.max_input_tokens.saturating_mul(4));
+    if transcript.is_empty() {
+        return Ok(None);
+    }
+
+    let bulletin = deps.runtime_config.memory_bulletin.load();
+    let prompt_engine = deps.runtime_config.prompts.load();
+    let preamble = prompt_engine.render_static("cortex_dream")?;
+    let prompt = prompt_engine.render_system_dream(
+        &date.to_string(),
+        config.max_memories,
+        (!bulletin.trim().is_empty()).then_some(bulletin.as_str()),
+        transcript,
+    )?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = match &config.model {
+        Some(model) => model.clone(),
+        None => routing.resolve(ProcessType::Branch, None).to_string(),
+    };
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_context(&*deps.agent_id, "cortex")
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model)
+        .preamble(&preamble)
+        .max_tokens(config.max_output_tokens)
+        .build();
+
+    let response = agent.prompt(&prompt).await?;
+    let cleaned = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let response: DreamLlmResponse = serde_json::from_str(cleaned)?;
+
+    let mut memory_ids = Vec::new();
+    for memory in response.memories.into_iter().take(config.max_memories) {
+        if memory.content.trim().is_empty() {
+            continue;
+        }
+        let result = MemorySaveTool::new(deps.memory_search.clone())
+            .call(MemorySaveArgs {
+                content: memory.content,
+                memory_type: memory.memory_type,
+                importance: memory
+                    .importance
+                    .map(|importance| importance.clamp(0.0, 1.0)),
+                source: Some("dream".into()),
+                channel_id: None,
+                associations: Vec::new(),
+                pinned: false,
+            })
+            .await;
+        match result {
+            Ok(output) => memory_ids.push(output.memory_id),
+            Err(error) => tracing::warn!(%error, "failed to save dream memory"),
+        }
+    }
+
+    let diary = Path::new(DIARY_DIR).join(format!("{date}.md"));
+    let path = deps.runtime_config.workspace_dir.join(&diary);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, format!("# {date}\n\n{}\n", response.diary.trim())).await?;
+
+    Ok(Some(Dream { memory_ids, diary }))
+}
+
+/// `now` in `timezone`, or the system timezone when there's none.
+fn local_time(now: chrono::DateTime<chrono::Utc>, timezone: Option<&str>) -> chrono::NaiveDateTime {
+    match timezone.and_then(|name| name.parse::<Tz>().ok()) {
+        Some(timezone) => now.with_timezone(&timezone).naive_local(),
+        None => now.with_timezone(&chrono::Local).naive_local(),
+    }
+}
+
+/// The newest whole lines of `transcript` that fit in `max_chars`.
+fn fit_transcript(transcript: &str, max_chars: usize) -> &str {
+    if transcript.len() <= max_chars {
+        return transcript;
+    }
+    let start = transcript.ceil_char_boundary(transcript.len() - max_chars);
+    match transcript[start..].find('\n') {
+        Some(newline) => &transcript[start + newline + 1..],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcripts_keep_the_newest_whole_lines() {
+        let transcript = "[a] user: first\n[a] agent: second\n[b] user: third";
+        assert_eq!(fit_transcript(transcript, 100), transcript);
+        assert_eq!(fit_transcript(transcript, 20), "[b] user: third");
+        assert_eq!(
+            fit_transcript(transcript, 34),
+            "[a] agent: second\n[b] user: third"
+        );
+        assert_eq!(fit_transcript(transcript, 5), "");
+    }
+}
@@ -0,0 +1,210 @@
+//! Conversation forking into threads.
+//!
+//! A fork snapshots a channel's history and starts a sibling channel in a
+//! new platform thread, so a tangent can be explored there without adding to
+//! the main conversation's context. The sibling is started by injecting a
+//! seed message addressed to the thread. The seed carries the snapshot, and
+//! the new channel adopts it as its history before handling anything else.
+//! Forks are started with the `/fork [topic]` command or the `fork` tool.
+
+use crate::agent::channel::ChannelState;
+use crate::{InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+
+/// Metadata key carrying the parent's history snapshot on a seed message.
+pub const FORK_HISTORY_KEY: &str = "fork_history";
+
+/// Metadata key naming the conversation a thread was forked from.
+pub const FORK_PARENT_KEY: &str = "fork_parent";
+
+/// Longest thread name Discord accepts.
+const MAX_THREAD_NAME_CHARS: usize = 100;
+
+/// Sender metadata copied onto the seed, so the topic is attributed to
+/// whoever forked.
+const SENDER_KEYS: &[&str] = &[
+    "sender_id",
+    "sender_display_name",
+    "discord_user_id",
+    "discord_user_mention",
+    "discord_author_name",
+];
+
+/// A thread a conversation was forked into.
+#[derive(Debug, Clone)]
+pub struct Fork {
+    pub conversation_id: String,
+    pub thread_name: String,
+}
+
+/// The topic of a `/fork` command, or `None` when the message isn't one.
+/// The topic is empty when the command has no argument.
+pub fn parse_command(message: &InboundMessage) -> Option<String> {
+    let MessageContent::Text(text) = &message.content else {
+        return None;
+    };
+    let rest = text.trim().strip_prefix("/fork")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim().to_string())
+}
+
+/// Fork the channel into a new thread started off `source`. A non-empty
+/// `topic` is posted in the thread as `source`'s sender and answered there.
+pub async fn fork(
+    state: &ChannelState,
+    source: &InboundMessage,
+    thread_name: &str,
+    topic: &str,
+) -> anyhow::Result<Fork> {
+    let messaging_manager = state
+        .deps
+        .messaging_manager
+        .as_ref()
+        .context("no messaging adapters are running")?;
+
+    let thread_name = thread_name_for(thread_name, topic);
+    let thread = messaging_manager
+        .create_thread(source, &thread_name)
+        .await
+        .map_err(|error| anyhow::anyhow!("can't create thread: {error}"))?;
+
+    let snapshot = serde_json::to_value(&*state.history.read().await)
+        .context("can't snapshot channel history")?;
+
+    let mut metadata = thread.metadata;
+    metadata.insert(FORK_HISTORY_KEY.into(), snapshot);
+    metadata.insert(FORK_PARENT_KEY.into(), parent_name(source).into());
+    for key in SENDER_KEYS {
+        if let Some(value) = source.metadata.get(*key) {
+            metadata.insert((*key).into(), value.clone());
+        }
+    }
+
+    let seed = InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: source.source.clone(),
+        conversation_id: thread.conversation_id.clone(),
+        sender_id: source.sender_id.clone(),
+        agent_id: Some(state.deps.agent_id.clone()),
+        content: MessageContent::Text(topic.to_string()),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: source.formatted_author.clone(),
+    };
+    messaging_manager
+        .inject_message(seed)
+        .await
+        .map_err(|error| anyhow::anyhow!("can't start the forked channel: {error}"))?;
+
+    tracing::info!(
+        channel_id = %state.channel_id,
+        thread = %thread.conversation_id,
+        %thread_name,
+        "conversation forked into thread"
+    );
+
+    Ok(Fork {
+        conversation_id: thread.conversation_id,
+        thread_name,
+    })
+}
+
+/// Take the history snapshot off a fork seed, with the name of the
+/// conversation it came from. Returns `None` for every other message.
+pub fn take_seed(message: &mut InboundMessage) -> Option<(Vec<rig::message::Message>, String)> {
+    let snapshot = message.metadata.remove(FORK_HISTORY_KEY)?;
+    let parent = message
+        .metadata
+        .remove(FORK_PARENT_KEY)
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| "another conversation".into());
+
+    match serde_json::from_value(snapshot) {
+        Ok(history) => Some((history, parent)),
+        Err(error) => {
+            tracing::warn!(%error, "can't read forked history, starting the thread empty");
+            Some((Vec::new(), parent))
+        }
+    }
+}
+
+fn thread_name_for(name: &str, topic: &str) -> String {
+    let name = [name.trim(), topic.trim()]
+        .into_iter()
+        .find(|candidate| !candidate.is_empty())
+        .unwrap_or("Fork");
+    name.chars().take(MAX_THREAD_NAME_CHARS).collect()
+}
+
+fn parent_name(source: &InboundMessage) -> String {
+    source
+        .metadata
+        .get("discord_channel_name")
+        .and_then(|value| value.as_str())
+        .map(|name| format!("#{name}"))
+        .unwrap_or_else(|| source.conversation_id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn text_message(text: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "3".into(),
+            agent_id: None,
+            content: MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn fork_command_is_recognized() {
+        assert_eq!(parse_command(&text_message("/fork")), Some(String::new()));
+        assert_eq!(
+            parse_command(&text_message("  /fork  caching strategy ")),
+            Some("caching strategy".into())
+        );
+        assert_eq!(parse_command(&text_message("/forks")), None);
+        assert_eq!(parse_command(&text_message("let's /fork")), None);
+    }
+
+    #[test]
+    fn thread_names_fall_back_to_topic() {
+        assert_eq!(thread_name_for(" Billing ", "ignored"), "Billing");
+        assert_eq!(thread_name_for("", "caching strategy"), "caching strategy");
+        assert_eq!(thread_name_for("", ""), "Fork");
+        assert_eq!(
+            thread_name_for(&"x".repeat(150), "").chars().count(),
+            MAX_THREAD_NAME_CHARS
+        );
+    }
+
+    #[test]
+    fn seeds_carry_history() {
+        let history = vec![rig::message::Message::from("earlier context")];
+        let mut seed = text_message("");
+        seed.metadata.insert(
+            FORK_HISTORY_KEY.into(),
+            serde_json::to_value(&history).unwrap(),
+        );
+        seed.metadata
+            .insert(FORK_PARENT_KEY.into(), "#general".into());
+
+        let (restored, parent) = take_seed(&mut seed).unwrap();
+        assert_eq!(restored.len(), history.len());
+        assert_eq!(parent, "#general");
+        assert!(seed.metadata.is_empty());
+        assert!(take_seed(&mut text_message("hello")).is_none());
+    }
+}
@@ -0,0 +1,282 @@
+//! Handing conversations over between agents.
+//!
+//! A front agent that finds a conversation better served by a specialist
+//! hands it off. The receiving agent gets a summary written by the sender
+//! plus the recent messages, introduces itself, and from then on the
+//! conversation routes to it regardless of bindings. The route is kept in an
+//! instance-level redb database, so it survives restarts until it's cleared
+//! through the API.
+//!
+//! The handoff itself travels as a seed message injected into the router
+//! with the target agent preset. The router records the route when it sees
+//! the seed, and the receiving channel turns it into its first turn.
+
+use crate::agent::channel::ChannelState;
+use crate::{InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+/// conversation_id -> JSON-encoded [`Handoff`].
+const HANDOFFS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("handoffs");
+
+/// Metadata key carrying the [`Handoff`] record on a seed message.
+pub const HANDOFF_KEY: &str = "handoff";
+
+/// A conversation handed from one agent to another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handoff {
+    pub conversation_id: String,
+    pub from_agent: String,
+    pub to_agent: String,
+    pub reason: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Conversation routes created by handoffs, backed by redb.
+pub struct HandoffStore {
+    db: Database,
+}
+
+impl std::fmt::Debug for HandoffStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandoffStore").finish_non_exhaustive()
+    }
+}
+
+impl HandoffStore {
+    /// Open or create the handoff database at `path`.
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::create(path).context("failed to open handoff database")?;
+
+        let write_transaction = db.begin_write()?;
+        write_transaction
+            .open_table(HANDOFFS_TABLE)
+            .context("failed to open handoffs table")?;
+        write_transaction.commit()?;
+
+        Ok(Self { db })
+    }
+
+    /// Route a conversation to the agent it was handed to.
+    pub fn record(&self, handoff: &Handoff) -> anyhow::Result<()> {
+        let encoded = serde_json::to_vec(handoff)?;
+        let write_transaction = self.db.begin_write()?;
+        {
+            let mut table = write_transaction.open_table(HANDOFFS_TABLE)?;
+            table.insert(handoff.conversation_id.as_str(), encoded.as_slice())?;
+        }
+        write_transaction.commit()?;
+
+        tracing::info!(
+            conversation_id = %handoff.conversation_id,
+            from_agent = %handoff.from_agent,
+            to_agent = %handoff.to_agent,
+            "conversation handed off"
+        );
+        Ok(())
+    }
+
+    /// The latest handoff of a conversation, if it has one.
+    pub fn get(&self, conversation_id: &str) -> anyhow::Result<Option<Handoff>> {
+        let read_transaction = self.db.begin_read()?;
+        let table = read_transaction.open_table(HANDOFFS_TABLE)?;
+        let Some(entry) = table.get(conversation_id)? else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_slice(entry.value())?))
+    }
+
+    /// Every handed-off conversation, most recent first.
+    pub fn list(&self) -> anyhow::Result<Vec<Handoff>> {
+        let read_transaction = self.db.begin_read()?;
+        let table = read_transaction.open_table(HANDOFFS_TABLE)?;
+        let mut handoffs = Vec::new();
+        for entry in table.iter()? {
+            let (_, value) = entry?;
+            match serde_json::from_slice::<Handoff>(value.value()) {
+                Ok(handoff) => handoffs.push(handoff),
+                Err(error) => tracing::warn!(%error, "skipping unreadable handoff record"),
+            }
+        }
+        handoffs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(handoffs)
+    }
+
+    /// Return a conversation to its bindings. Returns whether it was handed off.
+    pub fn remove(&self, conversation_id: &str) -> anyhow::Result<bool> {
+        let write_transaction = self.db.begin_write()?;
+        let removed = {
+            let mut table = write_transaction.open_table(HANDOFFS_TABLE)?;
+            table.remove(conversation_id)?.is_some()
+        };
+        write_transaction.commit()?;
+        Ok(removed)
+    }
+}
+
+/// Hand the channel's conversation to `to_agent`. The receiving agent is
+/// briefed with `summary` and the channel's recent messages.
+pub async fn hand_off(
+    state: &ChannelState,
+    to_agent: &str,
+    reason: &str,
+    summary: &str,
+) -> anyhow::Result<Handoff> {
+    let from_agent = state.deps.agent_id.to_string();
+    if to_agent == from_agent {
+        anyhow::bail!("this conversation is already with {to_agent}");
+    }
+    let messaging_manager = state
+        .deps
+        .messaging_manager
+        .as_ref()
+        .context("no messaging adapters are running")?;
+    let source = state
+        .trigger_message
+        .read()
+        .await
+        .clone()
+        .context("there is no user message to hand off from")?;
+
+    let history_messages = state.deps.runtime_config.handoff.load().history_messages;
+    let transcript = if history_messages > 0 {
+        state
+            .conversation_logger
+            .load_channel_transcript(&state.channel_id, history_messages as i64)
+            .await
+            .map(|messages| {
+                messages
+                    .iter()
+                    .map(|message| {
+                        let author = match message.role.as_str() {
+                            "assistant" => format!("{from_agent} (agent)"),
+                            _ => message.sender_name.clone().unwrap_or_else(|| "user".into()),
+                        };
+                        format!("{author}: {}", message.content)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_else(|error| {
+                tracing::warn!(%error, "can't load recent messages for handoff");
+                String::new()
+            })
+    } else {
+        String::new()
+    };
+
+    let brief = state
+        .deps
+        .runtime_config
+        .prompts
+        .load()
+        .render_system_handoff(&from_agent, reason, summary, &transcript)
+        .context("can't render handoff brief")?;
+
+    let handoff = Handoff {
+        conversation_id: source.conversation_id.clone(),
+        from_agent,
+        to_agent: to_agent.to_string(),
+        reason: reason.to_string(),
+        created_at: chrono::Utc::now(),
+    };
+
+    let mut metadata = source.metadata.clone();
+    metadata.insert(HANDOFF_KEY.into(), serde_json::to_value(&handoff)?);
+    let seed = InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: source.source.clone(),
+        conversation_id: source.conversation_id.clone(),
+        sender_id: source.sender_id.clone(),
+        agent_id: Some(to_agent.into()),
+        content: MessageContent::Text(brief),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: source.formatted_author.clone(),
+    };
+    messaging_manager
+        .inject_message(seed)
+        .await
+        .map_err(|error| anyhow::anyhow!("can't reach {to_agent}: {error}"))?;
+
+    Ok(handoff)
+}
+
+/// The handoff a seed message carries. Returns `None` for every other message.
+pub fn seed_handoff(message: &InboundMessage) -> Option<Handoff> {
+    let value = message.metadata.get(HANDOFF_KEY)?;
+    serde_json::from_value(value.clone())
+        .inspect_err(|error| tracing::warn!(%error, "can't read handoff seed"))
+        .ok()
+}
+
+/// Take the handoff record off a seed message, leaving the brief as its text.
+pub fn take_seed(message: &mut InboundMessage) -> Option<Handoff> {
+    let handoff = seed_handoff(message);
+    message.metadata.remove(HANDOFF_KEY);
+    handoff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handoff(conversation_id: &str, minutes_ago: i64) -> Handoff {
+        Handoff {
+            conversation_id: conversation_id.into(),
+            from_agent: "front".into(),
+            to_agent: "billing".into(),
+            reason: "invoice question".into(),
+            created_at: chrono::Utc::now() - chrono::Duration::minutes(minutes_ago),
+        }
+    }
+
+    #[test]
+    fn routes_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HandoffStore::new(&dir.path().join("handoffs.redb")).unwrap();
+
+        store.record(&handoff("discord:1:2", 5)).unwrap();
+        store.record(&handoff("slack:T1:C2", 1)).unwrap();
+
+        let route = store.get("discord:1:2").unwrap().unwrap();
+        assert_eq!(route.to_agent, "billing");
+        assert!(store.get("discord:9:9").unwrap().is_none());
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].conversation_id, "slack:T1:C2");
+
+        assert!(store.remove("discord:1:2").unwrap());
+        assert!(!store.remove("discord:1:2").unwrap());
+        assert!(store.get("discord:1:2").unwrap().is_none());
+    }
+
+    #[test]
+    fn seeds_carry_the_handoff() {
+        let mut message = InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "3".into(),
+            agent_id: Some("billing".into()),
+            content: MessageContent::Text("brief".into()),
+            timestamp: chrono::Utc::now(),
+            metadata: std::collections::HashMap::new(),
+            formatted_author: None,
+        };
+        assert!(take_seed(&mut message).is_none());
+
+        message.metadata.insert(
+            HANDOFF_KEY.into(),
+            serde_json::to_value(handoff("discord:1:2", 0)).unwrap(),
+        );
+        assert_eq!(seed_handoff(&message).unwrap().from_agent, "front");
+        assert_eq!(take_seed(&mut message).unwrap().to_agent, "billing");
+        assert!(message.metadata.is_empty());
+    }
+}
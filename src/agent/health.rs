@@ -0,0 +1,429 @@
+//! Process health: the cortex's watch over running branches and workers.
+//!
+//! Every branch and worker a channel runs gets a health score from 100 down
+//! to 0, rechecked every `cortex.tick_interval_secs`. Two symptoms cost
+//! points. The first is a tool returning the same result call after call,
+//! which reaches 0 at `cortex.runaway_repeat_limit` identical results in a
+//! row. The second, for workers only, is time without progress, which
+//! reaches 0 at `cortex.worker_timeout_secs`. Progress is a tool result that
+//! differs from the previous one, or a new worker status. Branches already
+//! have a hard `branch_timeout_secs`. Workers waiting in the worker queue
+//! aren't scored until they start.
+//!
+//! A process scoring [`NUDGE_AT`] or less is nudged once, and one at 0 is
+//! killed. Either way the cortex publishes a `ProcessUnhealthy` event and
+//! the owning channel acts on it. It posts the diagnostic into its status
+//! block and passes nudges on to interactive workers. For kills, it cancels
+//! the process and tells the channel's LLM why. Detached workers have no
+//! channel and are left to their budgets.
+
+use crate::agent::cortex::CortexLogger;
+use crate::agent::worker_queue;
+use crate::config::CortexConfig;
+use crate::events::{EventFilter, EventSubscription};
+use crate::{AgentDeps, ChannelId, ProcessEvent, ProcessId};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
+/// Score at or below which a process is nudged.
+pub const NUDGE_AT: u8 = 50;
+
+/// What the cortex asks the owning channel to do with an unhealthy process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthAction {
+    /// Warn it and record the diagnostic; it keeps running.
+    Nudge,
+    /// Cancel it.
+    Kill,
+}
+
+impl std::fmt::Display for HealthAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthAction::Nudge => write!(f, "nudge"),
+            HealthAction::Kill => write!(f, "kill"),
+        }
+    }
+}
+
+/// An unhealthy process and what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Intervention {
+    pub process_id: ProcessId,
+    pub channel_id: ChannelId,
+    pub score: u8,
+    pub action: HealthAction,
+    pub diagnostic: String,
+}
+
+#[derive(Debug)]
+struct Tracked {
+    channel_id: ChannelId,
+    last_progress: DateTime<Utc>,
+    /// Tool and hash of the most recent tool result.
+    last_result: Option<(String, u64)>,
+    /// Identical results in a row, counting the first.
+    repeats: u32,
+    last_status: Option<String>,
+    nudged: bool,
+}
+
+impl Tracked {
+    fn new(channel_id: ChannelId, now: DateTime<Utc>) -> Self {
+        Self {
+            channel_id,
+            last_progress: now,
+            last_result: None,
+            repeats: 0,
+            last_status: None,
+            nudged: false,
+        }
+    }
+
+    fn progressed(&mut self, now: DateTime<Utc>) {
+        self.last_progress = now;
+        self.nudged = false;
+    }
+}
+
+/// Health tracking for one agent's channel-owned branches and workers.
+#[derive(Debug, Default)]
+pub struct HealthMonitor {
+    processes: HashMap<ProcessId, Tracked>,
+}
+
+impl HealthMonitor {
+    /// Track starts, progress and ends from the agent's events.
+    pub fn observe(&mut self, event: &ProcessEvent, now: DateTime<Utc>) {
+        match event {
+            ProcessEvent::BranchStarted {
+                branch_id,
+                channel_id,
+                ..
+            } => {
+                self.processes.insert(
+                    ProcessId::Branch(*branch_id),
+                    Tracked::new(channel_id.clone(), now),
+                );
+            }
+            ProcessEvent::WorkerStarted {
+                worker_id,
+                channel_id: Some(channel_id),
+                ..
+            } => {
+                self.processes.insert(
+                    ProcessId::Worker(*worker_id),
+                    Tracked::new(channel_id.clone(), now),
+                );
+            }
+            ProcessEvent::WorkerStatus {
+                worker_id, status, ..
+            } => {
+                if let Some(tracked) = self.processes.get_mut(&ProcessId::Worker(*worker_id))
+                    && tracked.last_status.as_ref() != Some(status)
+                {
+                    tracked.last_status = Some(status.clone());
+                    tracked.progressed(now);
+                }
+            }
+            ProcessEvent::ToolCompleted {
+                process_id,
+                tool_name,
+                result,
+                ..
+            } => {
+                let Some(tracked) = self.processes.get_mut(process_id) else {
+                    return;
+                };
+                let mut hasher = DefaultHasher::new();
+                result.hash(&mut hasher);
+                let current = (tool_name.clone(), hasher.finish());
+                if tracked.last_result.as_ref() == Some(&current) {
+                    tracked.repeats += 1;
+                } else {
+                    tracked.last_result = Some(current);
+                    tracked.repeats = 1;
+                    tracked.progressed(now);
+                }
+            }
+            ProcessEvent::WorkerComplete { worker_id, .. } => {
+                self.processes.remove(&ProcessId::Worker(*worker_id));
+            }
+            ProcessEvent::BranchResult { branch_id, .. } => {
+                self.processes.remove(&ProcessId::Branch(*branch_id));
+            }
+            ProcessEvent::StatusUpdate {
+                process_id, status, ..
+            } if status == crate::agent::status::CANCELLED_STATUS
+                || status == crate::agent::status::FAILED_STATUS =>
+            {
+                self.processes.remove(process_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Score every tracked process and return the ones to nudge or kill.
+    /// Killed processes stop being tracked.
+    pub fn check(&mut self, config: &CortexConfig, now: DateTime<Utc>) -> Vec<Intervention> {
+        let mut interventions = Vec::new();
+        self.processes.retain(|process_id, tracked| {
+            // Waiting for a slot isn't being stuck.
+            if tracked
+                .last_status
+                .as_deref()
+                .is_some_and(worker_queue::is_queued_status)
+            {
+                return true;
+            }
+            let (score, symptoms) = score(process_id, tracked, config, now);
+            let action = if score == 0 {
+                HealthAction::Kill
+            } else if score <= NUDGE_AT && !tracked.nudged {
+                tracked.nudged = true;
+                HealthAction::Nudge
+            } else {
+                return true;
+            };
+            interventions.push(Intervention {
+                process_id: process_id.clone(),
+                channel_id: tracked.channel_id.clone(),
+                score,
+                action,
+                diagnostic: format!("health {score}/100: {}", symptoms.join("; ")),
+            });
+            action != HealthAction::Kill
+        });
+        interventions
+    }
+}
+
+/// A process's score and the symptoms that cost it points.
+fn score(
+    process_id: &ProcessId,
+    tracked: &Tracked,
+    config: &CortexConfig,
+    now: DateTime<Utc>,
+) -> (u8, Vec<String>) {
+    let mut penalty = 0;
+    let mut symptoms = Vec::new();
+
+    let limit = config.runaway_repeat_limit;
+    if limit > 0
+        && tracked.repeats > 1
+        && let Some((tool, _)) = &tracked.last_result
+    {
+        let steps = limit.saturating_sub(1).max(1);
+        penalty += ((tracked.repeats - 1) * 100 / steps).min(100);
+        symptoms.push(format!(
+            "`{tool}` returned the same result {} times in a row",
+            tracked.repeats
+        ));
+    }
+
+    let timeout = config.worker_timeout_secs;
+    let stalled_secs = (now - tracked.last_progress).num_seconds().max(0) as u64;
+    if matches!(process_id, ProcessId::Worker(_)) && timeout > 0 && stalled_secs * 4 >= timeout {
+        penalty += (stalled_secs * 100 / timeout).min(100) as u32;
+        symptoms.push(format!("no progress for {stalled_secs}s"));
+    }
+
+    (100 - penalty.min(100) as u8, symptoms)
+}
+
+/// Watch the agent's channel-owned processes and publish interventions
+/// until its event bus closes.
+pub fn spawn_health_loop(deps: AgentDeps, logger: CortexLogger) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let agent_id = deps.agent_id.clone();
+        let mut events = EventSubscription::new(
+            agent_id.clone(),
+            "process_health",
+            deps.event_tx.subscribe(),
+            EventFilter::all().agent(agent_id),
+        );
+        let mut monitor = HealthMonitor::default();
+        let mut tick_secs = deps.runtime_config.cortex.load().tick_interval_secs.max(1);
+        let mut tick = tokio::time::interval(Duration::from_secs(tick_secs));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    let config = **deps.runtime_config.cortex.load();
+                    for intervention in monitor.check(&config, Utc::now()) {
+                        intervene(&deps, &logger, intervention);
+                    }
+                    if config.tick_interval_secs.max(1) != tick_secs {
+                        tick_secs = config.tick_interval_secs.max(1);
+                        tick = tokio::time::interval(Duration::from_secs(tick_secs));
+                        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                    }
+                }
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    monitor.observe(&event, Utc::now());
+                }
+            }
+        }
+    })
+}
+
+fn intervene(deps: &AgentDeps, logger: &CortexLogger, intervention: Intervention) {
+    let (kind, event_type) = match (&intervention.process_id, intervention.action) {
+        (ProcessId::Worker(_), HealthAction::Kill) => ("worker", "worker_killed"),
+        (ProcessId::Branch(_), HealthAction::Kill) => ("branch", "branch_killed"),
+        (ProcessId::Worker(_), HealthAction::Nudge) => ("worker", "health_check"),
+        _ => ("branch", "health_check"),
+    };
+    let id = match &intervention.process_id {
+        ProcessId::Worker(id) => id.to_string(),
+        ProcessId::Branch(id) => id.to_string(),
+        ProcessId::Channel(id) => id.to_string(),
+    };
+    tracing::warn!(
+        process_id = %intervention.process_id,
+        channel_id = %intervention.channel_id,
+        action = %intervention.action,
+        diagnostic = %intervention.diagnostic,
+        "unhealthy process"
+    );
+    let verb = match intervention.action {
+        HealthAction::Nudge => "nudged",
+        HealthAction::Kill => "killed",
+    };
+    logger.log(
+        event_type,
+        &format!("Stuck {kind} {verb} ({})", intervention.diagnostic),
+        Some(serde_json::json!({
+            "process_type": kind,
+            "process_id": id,
+            "channel_id": intervention.channel_id.to_string(),
+            "score": intervention.score,
+            "action": intervention.action,
+        })),
+    );
+
+    deps.event_tx
+        .send(ProcessEvent::ProcessUnhealthy {
+            agent_id: deps.agent_id.clone(),
+            process_id: intervention.process_id,
+            channel_id: intervention.channel_id,
+            score: intervention.score,
+            action: intervention.action,
+            diagnostic: intervention.diagnostic,
+        })
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_completed(process_id: ProcessId, result: &str) -> ProcessEvent {
+        ProcessEvent::ToolCompleted {
+            agent_id: "main".into(),
+            process_id,
+            channel_id: Some("discord:1".into()),
+            tool_name: "shell".into(),
+            result: result.into(),
+        }
+    }
+
+    #[test]
+    fn repeated_results_nudge_then_kill() {
+        let config = CortexConfig {
+            runaway_repeat_limit: 5,
+            ..CortexConfig::default()
+        };
+        let start = Utc::now();
+        let branch_id = uuid::Uuid::new_v4();
+        let process_id = ProcessId::Branch(branch_id);
+        let mut monitor = HealthMonitor::default();
+        monitor.observe(
+            &ProcessEvent::BranchStarted {
+                agent_id: "main".into(),
+                branch_id,
+                channel_id: "discord:1".into(),
+                description: "check the deploy".into(),
+                reply_to_message_id: None,
+                race_id: None,
+            },
+            start,
+        );
+
+        for _ in 0..3 {
+            monitor.observe(&tool_completed(process_id.clone(), "pending"), start);
+        }
+        let nudges = monitor.check(&config, start);
+        assert_eq!(nudges.len(), 1);
+        assert_eq!(nudges[0].action, HealthAction::Nudge);
+        assert_eq!(nudges[0].score, NUDGE_AT);
+        assert!(monitor.check(&config, start).is_empty());
+
+        // A different result is progress.
+        monitor.observe(&tool_completed(process_id.clone(), "running"), start);
+        assert!(monitor.check(&config, start).is_empty());
+
+        for _ in 0..4 {
+            monitor.observe(&tool_completed(process_id.clone(), "running"), start);
+        }
+        let kills = monitor.check(&config, start);
+        assert_eq!(kills.len(), 1);
+        assert_eq!(kills[0].action, HealthAction::Kill);
+        assert_eq!(kills[0].channel_id.as_ref(), "discord:1");
+        assert!(kills[0].diagnostic.contains("5 times in a row"));
+        assert!(monitor.check(&config, start).is_empty());
+    }
+
+    #[test]
+    fn stalled_workers_are_killed_at_the_timeout() {
+        let config = CortexConfig {
+            worker_timeout_secs: 300,
+            ..CortexConfig::default()
+        };
+        let start = Utc::now();
+        let worker_id = uuid::Uuid::new_v4();
+        let mut monitor = HealthMonitor::default();
+        monitor.observe(
+            &ProcessEvent::WorkerStarted {
+                agent_id: "main".into(),
+                worker_id,
+                channel_id: Some("discord:1".into()),
+                task: "build the release".into(),
+            },
+            start,
+        );
+        // Detached workers aren't tracked.
+        monitor.observe(
+            &ProcessEvent::WorkerStarted {
+                agent_id: "main".into(),
+                worker_id: uuid::Uuid::new_v4(),
+                channel_id: None,
+                task: "nightly report".into(),
+            },
+            start,
+        );
+
+        assert!(
+            monitor
+                .check(&config, start + chrono::Duration::seconds(100))
+                .is_empty()
+        );
+        let nudges = monitor.check(&config, start + chrono::Duration::seconds(200));
+        assert_eq!(nudges.len(), 1);
+        assert_eq!(nudges[0].action, HealthAction::Nudge);
+        assert!(nudges[0].diagnostic.contains("no progress for 200s"));
+
+        let kills = monitor.check(&config, start + chrono::Duration::seconds(300));
+        assert_eq!(kills.len(), 1);
+        assert_eq!(kills[0].process_id, ProcessId::Worker(worker_id));
+        assert_eq!(kills[0].action, HealthAction::Kill);
+    }
+}
@@ -0,0 +1,226 @@
+//! Image preprocessing for vision requests.
+//!
+//! Phone photos routinely weigh 10-20 MB, past what providers accept in a
+//! vision request and expensive when they are accepted. Before an image is
+//! sent to the model it's decoded, turned upright per its EXIF orientation,
+//! downscaled to the configured dimension and size limits and re-encoded.
+//! Re-encoding also drops EXIF metadata such as GPS coordinates. A small
+//! JPEG thumbnail can be produced alongside, stored per conversation under
+//! the agent's data directory.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder as _, ImageFormat, ImageReader, ImageResult};
+use sha2::{Digest, Sha256};
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// JPEG qualities tried, in order, before an image is shrunk further.
+const JPEG_QUALITIES: &[u8] = &[85, 70, 55];
+
+/// Smallest long edge an image is shrunk to while chasing the size limit.
+const MIN_DIMENSION: u32 = 256;
+
+/// Long edge of generated thumbnails.
+const THUMBNAIL_DIMENSION: u32 = 256;
+
+/// How large an image sent to the model may be.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageLimits {
+    /// Longest edge, in pixels.
+    pub max_dimension: u32,
+    /// Encoded size, in bytes.
+    pub max_bytes: usize,
+}
+
+/// An image ready to be sent to the model.
+#[derive(Debug)]
+pub struct PreparedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub original_width: u32,
+    pub original_height: u32,
+    /// JPEG thumbnail, when one was requested.
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+impl PreparedImage {
+    /// Whether the image was scaled down from its original size.
+    pub fn resized(&self) -> bool {
+        (self.width, self.height) != (self.original_width, self.original_height)
+    }
+}
+
+/// Fit an image within `limits`, stripping its metadata. Small GIFs are
+/// passed through untouched so animations survive.
+pub fn prepare(bytes: &[u8], limits: ImageLimits, thumbnail: bool) -> ImageResult<PreparedImage> {
+    let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = reader.format();
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let (original_width, original_height) = (image.width(), image.height());
+    let thumbnail = thumbnail
+        .then(|| {
+            encode_jpeg(
+                &image.thumbnail(THUMBNAIL_DIMENSION, THUMBNAIL_DIMENSION),
+                80,
+            )
+        })
+        .transpose()?;
+    let prepared = |bytes, mime_type, image: &DynamicImage, thumbnail| PreparedImage {
+        bytes,
+        mime_type,
+        width: image.width(),
+        height: image.height(),
+        original_width,
+        original_height,
+        thumbnail,
+    };
+
+    let fits_dimension = original_width.max(original_height) <= limits.max_dimension;
+    if format == Some(ImageFormat::Gif) && fits_dimension && bytes.len() <= limits.max_bytes {
+        return Ok(prepared(bytes.to_vec(), "image/gif", &image, thumbnail));
+    }
+
+    if !fits_dimension {
+        image = image.resize(
+            limits.max_dimension,
+            limits.max_dimension,
+            FilterType::Lanczos3,
+        );
+    }
+
+    // Lossless sources such as screenshots stay PNG while they fit.
+    let prefer_png = format == Some(ImageFormat::Png) || image.color().has_alpha();
+    loop {
+        if let Some((bytes, mime_type)) = encode_within(&image, limits.max_bytes, prefer_png)? {
+            return Ok(prepared(bytes, mime_type, &image, thumbnail));
+        }
+
+        let long_edge = image.width().max(image.height());
+        if long_edge <= MIN_DIMENSION {
+            // Can't get under the limit; send the smallest attempt.
+            let quality = JPEG_QUALITIES[JPEG_QUALITIES.len() - 1];
+            let bytes = encode_jpeg(&image, quality)?;
+            return Ok(prepared(bytes, "image/jpeg", &image, thumbnail));
+        }
+        let target = (long_edge * 3 / 4).max(MIN_DIMENSION);
+        image = image.resize(target, target, FilterType::Triangle);
+    }
+}
+
+/// Where the thumbnails of a conversation's images are stored.
+pub fn thumbnail_dir(data_dir: &Path, channel_id: &str) -> PathBuf {
+    let safe_id: String = channel_id
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || matches!(character, '-' | '_') {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    data_dir.join("thumbnails").join(safe_id)
+}
+
+/// Thumbnail file of the image at `url`.
+pub fn thumbnail_path(thumbnail_dir: &Path, url: &str) -> PathBuf {
+    thumbnail_dir.join(format!(
+        "{}.jpg",
+        hex::encode(Sha256::digest(url.as_bytes()))
+    ))
+}
+
+fn encode_within(
+    image: &DynamicImage,
+    max_bytes: usize,
+    prefer_png: bool,
+) -> ImageResult<Option<(Vec<u8>, &'static str)>> {
+    if prefer_png {
+        let mut png = Vec::new();
+        image.write_with_encoder(PngEncoder::new(&mut png))?;
+        if png.len() <= max_bytes {
+            return Ok(Some((png, "image/png")));
+        }
+    }
+    for quality in JPEG_QUALITIES {
+        let jpeg = encode_jpeg(image, *quality)?;
+        if jpeg.len() <= max_bytes {
+            return Ok(Some((jpeg, "image/jpeg")));
+        }
+    }
+    Ok(None)
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: u8) -> ImageResult<Vec<u8>> {
+    let mut jpeg = Vec::new();
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, quality))?;
+    Ok(jpeg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        // Noise, so the encoded size scales with the pixel count.
+        let image = image::RgbImage::from_fn(width, height, |x, y| {
+            let seed = x.wrapping_mul(2_654_435_761) ^ y.wrapping_mul(40_503);
+            image::Rgb([seed as u8, (seed >> 8) as u8, (seed >> 16) as u8])
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn oversized_images_are_scaled_down() {
+        let limits = ImageLimits {
+            max_dimension: 800,
+            max_bytes: 10 * 1024 * 1024,
+        };
+        let prepared = prepare(&encoded(1600, 1200, ImageFormat::Jpeg), limits, true).unwrap();
+
+        assert!(prepared.resized());
+        assert_eq!((prepared.width, prepared.height), (800, 600));
+        assert_eq!(prepared.mime_type, "image/jpeg");
+        let thumbnail = image::load_from_memory(&prepared.thumbnail.unwrap()).unwrap();
+        assert_eq!(thumbnail.width(), THUMBNAIL_DIMENSION);
+    }
+
+    #[test]
+    fn images_shrink_to_the_size_limit() {
+        let limits = ImageLimits {
+            max_dimension: 4096,
+            max_bytes: 256 * 1024,
+        };
+        let prepared = prepare(&encoded(1024, 1024, ImageFormat::Png), limits, false).unwrap();
+
+        assert!(prepared.bytes.len() <= limits.max_bytes);
+        assert!(prepared.width < 1024);
+        assert!(prepared.thumbnail.is_none());
+    }
+
+    #[test]
+    fn small_pngs_stay_png() {
+        let limits = ImageLimits {
+            max_dimension: 1568,
+            max_bytes: 10 * 1024 * 1024,
+        };
+        let prepared = prepare(&encoded(64, 64, ImageFormat::Png), limits, false).unwrap();
+
+        assert!(!prepared.resized());
+        assert_eq!(prepared.mime_type, "image/png");
+    }
+}
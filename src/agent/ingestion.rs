@@ -473,8 +473,10 @@ async fn process_chunk(
     let channel_store = crate::conversation::ChannelStore::new(deps.sqlite_pool.clone());
     let tool_server: ToolServerHandle = crate::tools::create_branch_tool_server(
         deps.memory_search.clone(),
+        deps.history_archive.clone(),
         conversation_logger,
         channel_store,
+        crate::audit::AuditLogger::new(deps.sqlite_pool.clone()).with_actor("ingestion"),
     );
 
     let agent = AgentBuilder::new(model)
@@ -0,0 +1,216 @@
+//! Worker mailboxes: messages one running worker sends another, so workers
+//! can hand work along a pipeline ("research, then write it up") without
+//! going through the channel.
+//!
+//! Each Rig worker opens a mailbox when it starts, registered under its
+//! worker ID until the worker ends. Workers can only message workers of
+//! their own agent. Mail waits in the mailbox until the receiving worker
+//! reads it with the `read_worker_messages` tool. Sub-workers' results are
+//! delivered to their parent's mailbox the same way.
+
+use crate::{AgentId, WorkerId};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+/// Unread messages a mailbox holds before senders are turned away.
+pub const MAILBOX_CAPACITY: usize = 32;
+
+/// Longest a worker can wait for mail in one read.
+pub const MAX_WAIT_SECS: u64 = 300;
+
+/// A message from another worker.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerMail {
+    pub from: WorkerId,
+    pub text: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Why mail couldn't be sent.
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error("a worker can't message itself")]
+    ToSelf,
+    #[error("no running worker {0}")]
+    UnknownWorker(WorkerId),
+    #[error("worker {0} has {MAILBOX_CAPACITY} unread messages already")]
+    Full(WorkerId),
+}
+
+struct Registration {
+    agent_id: AgentId,
+    task: String,
+    sender: mpsc::Sender<WorkerMail>,
+}
+
+static MAILBOXES: LazyLock<Mutex<HashMap<WorkerId, Registration>>> =
+    LazyLock::new(Default::default);
+
+/// Leave `text` from `from` in the mailbox of `agent_id`'s worker `to`. The
+/// sender doesn't need a mailbox of its own.
+pub fn deliver(
+    agent_id: &AgentId,
+    from: WorkerId,
+    to: WorkerId,
+    text: &str,
+) -> Result<(), MailError> {
+    let sender = MAILBOXES
+        .lock()
+        .ok()
+        .and_then(|mailboxes| {
+            mailboxes
+                .get(&to)
+                .filter(|registration| &registration.agent_id == agent_id)
+                .map(|registration| registration.sender.clone())
+        })
+        .ok_or(MailError::UnknownWorker(to))?;
+
+    let mail = WorkerMail {
+        from,
+        text: text.to_string(),
+        sent_at: Utc::now(),
+    };
+    sender.try_send(mail).map_err(|error| match error {
+        mpsc::error::TrySendError::Full(_) => MailError::Full(to),
+        mpsc::error::TrySendError::Closed(_) => MailError::UnknownWorker(to),
+    })
+}
+
+/// A worker's mailbox, shared by its messaging tools.
+#[derive(Debug)]
+pub struct Mailbox {
+    agent_id: AgentId,
+    worker_id: WorkerId,
+    receiver: tokio::sync::Mutex<mpsc::Receiver<WorkerMail>>,
+}
+
+/// Keeps a mailbox registered. Dropping it, including when the worker is
+/// aborted, turns new mail for the worker away.
+#[derive(Debug)]
+pub struct MailboxGuard(WorkerId);
+
+impl Drop for MailboxGuard {
+    fn drop(&mut self) {
+        if let Ok(mut mailboxes) = MAILBOXES.lock() {
+            mailboxes.remove(&self.0);
+        }
+    }
+}
+
+impl Mailbox {
+    /// Open and register a mailbox for a worker of `agent_id`.
+    pub fn open(
+        agent_id: AgentId,
+        worker_id: WorkerId,
+        task: &str,
+    ) -> (Arc<Mailbox>, MailboxGuard) {
+        let (sender, receiver) = mpsc::channel(MAILBOX_CAPACITY);
+        if let Ok(mut mailboxes) = MAILBOXES.lock() {
+            mailboxes.insert(
+                worker_id,
+                Registration {
+                    agent_id: agent_id.clone(),
+                    task: task.to_string(),
+                    sender,
+                },
+            );
+        }
+        let mailbox = Arc::new(Mailbox {
+            agent_id,
+            worker_id,
+            receiver: tokio::sync::Mutex::new(receiver),
+        });
+        (mailbox, MailboxGuard(worker_id))
+    }
+
+    pub fn worker_id(&self) -> WorkerId {
+        self.worker_id
+    }
+
+    /// Leave `text` in the mailbox of the agent's worker `to`.
+    pub fn send(&self, to: WorkerId, text: &str) -> Result<(), MailError> {
+        if to == self.worker_id {
+            return Err(MailError::ToSelf);
+        }
+        deliver(&self.agent_id, self.worker_id, to, text)
+    }
+
+    /// Take every unread message. When there's none, wait up to `wait` for
+    /// the next one.
+    pub async fn receive(&self, wait: Duration) -> Vec<WorkerMail> {
+        let mut receiver = self.receiver.lock().await;
+        let mut mail = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            mail.push(message);
+        }
+        if mail.is_empty()
+            && !wait.is_zero()
+            && let Ok(Some(message)) = tokio::time::timeout(wait, receiver.recv()).await
+        {
+            mail.push(message);
+        }
+        mail
+    }
+
+    /// The agent's other running workers and their tasks.
+    pub fn peers(&self) -> Vec<(WorkerId, String)> {
+        let Ok(mailboxes) = MAILBOXES.lock() else {
+            return Vec::new();
+        };
+        mailboxes
+            .iter()
+            .filter(|(worker_id, registration)| {
+                **worker_id != self.worker_id && registration.agent_id == self.agent_id
+            })
+            .map(|(worker_id, registration)| (*worker_id, registration.task.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn workers_message_each_other_within_their_agent() {
+        let (researcher, _researcher_guard) =
+            Mailbox::open(Arc::from("main"), uuid::Uuid::new_v4(), "research");
+        let (writer, writer_guard) =
+            Mailbox::open(Arc::from("main"), uuid::Uuid::new_v4(), "write");
+        let (other, _other_guard) = Mailbox::open(Arc::from("ops"), uuid::Uuid::new_v4(), "deploy");
+
+        researcher.send(writer.worker_id(), "findings").unwrap();
+        let mail = writer.receive(Duration::ZERO).await;
+        assert_eq!(mail.len(), 1);
+        assert_eq!(mail[0].from, researcher.worker_id());
+        assert_eq!(mail[0].text, "findings");
+        assert!(writer.receive(Duration::ZERO).await.is_empty());
+
+        assert!(matches!(
+            other.send(writer.worker_id(), "hi"),
+            Err(MailError::UnknownWorker(_))
+        ));
+        assert!(matches!(
+            researcher.send(researcher.worker_id(), "hi"),
+            Err(MailError::ToSelf)
+        ));
+        assert!(
+            researcher
+                .peers()
+                .iter()
+                .any(|(worker_id, task)| *worker_id == writer.worker_id() && task == "write")
+        );
+
+        drop(writer_guard);
+        assert!(matches!(
+            researcher.send(writer.worker_id(), "more"),
+            Err(MailError::UnknownWorker(_))
+        ));
+    }
+}
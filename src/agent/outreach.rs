@@ -0,0 +1,249 @@
+//! Proactive outreach: messages the cortex sends to a conversation without
+//! being asked, like "the nightly backup job failed".
+//!
+//! Only channels named by bindings with `allow_proactive` can be messaged:
+//! each of their `channel_ids`, and their Telegram `chat_id`. An agent sends
+//! at most `cortex.proactive_daily_limit` outreaches per UTC day. Each one is
+//! logged as a [`PROACTIVE_EVENT`] cortex event, and the limit counts those,
+//! so it holds across restarts.
+
+use crate::agent::cortex::CortexLogger;
+use crate::config::Binding;
+use crate::llm::SpacebotModel;
+use crate::messaging::target::{BroadcastTarget, binding_targets, parse_delivery_target};
+use crate::{AgentDeps, OutboundResponse, ProcessType};
+
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+
+use std::sync::LazyLock;
+
+/// Cortex event type logged for each outreach.
+pub const PROACTIVE_EVENT: &str = "proactive_message";
+
+/// Held from the limit check until the event is logged, so outreaches sent
+/// at the same time can't both slip under the limit.
+static SEND_LOCK: LazyLock<tokio::sync::Mutex<()>> = LazyLock::new(Default::default);
+
+/// Why a proactive message wasn't sent.
+#[derive(Debug, thiserror::Error)]
+pub enum OutreachError {
+    #[error("no binding of this agent allows proactive messages")]
+    NoTargets,
+    #[error("'{0}' isn't a channel of a binding that allows proactive messages")]
+    NotAllowed(String),
+    #[error("the daily limit of {0} proactive messages is used up")]
+    LimitReached(u32),
+    #[error("messaging isn't running")]
+    Unavailable,
+    #[error("failed to check the daily limit: {0}")]
+    Limit(#[from] sqlx::Error),
+    #[error("sending failed: {0}")]
+    Send(String),
+}
+
+/// Channels of `agent_id`'s bindings that allow proactive messages.
+pub fn proactive_targets(bindings: &[Binding], agent_id: &str) -> Vec<BroadcastTarget> {
+    binding_targets(
+        bindings
+            .iter()
+            .filter(|binding| binding.agent_id == agent_id && binding.allow_proactive),
+    )
+}
+
+/// The agent's proactive targets under the current bindings.
+pub fn targets(deps: &AgentDeps) -> Vec<BroadcastTarget> {
+    match &**deps.runtime_config.bindings.load() {
+        Some(bindings) => proactive_targets(&bindings.load(), &deps.agent_id),
+        None => Vec::new(),
+    }
+}
+
+/// Outreaches sent since midnight UTC.
+async fn sent_today(logger: &CortexLogger) -> Result<u32, sqlx::Error> {
+    let midnight = chrono::Utc::now()
+        .date_naive()
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc();
+    let count = logger.count_events_since(PROACTIVE_EVENT, midnight).await?;
+    Ok(count.try_into().unwrap_or(u32::MAX))
+}
+
+/// Send `text` unprompted to `target`, given as `adapter:target`, or to every
+/// proactive target when it's `None`. Counts as one outreach however many
+/// channels it reaches. Returns the targets it was delivered to.
+pub async fn send_proactive(
+    deps: &AgentDeps,
+    target: Option<&str>,
+    text: &str,
+) -> Result<Vec<BroadcastTarget>, OutreachError> {
+    let messaging_manager = deps
+        .messaging_manager
+        .as_ref()
+        .ok_or(OutreachError::Unavailable)?;
+    let allowed = targets(deps);
+    let targets = match target {
+        Some(raw) => vec![
+            parse_delivery_target(raw)
+                .filter(|target| allowed.contains(target))
+                .ok_or_else(|| OutreachError::NotAllowed(raw.to_string()))?,
+        ],
+        None if allowed.is_empty() => return Err(OutreachError::NoTargets),
+        None => allowed,
+    };
+
+    let _guard = SEND_LOCK.lock().await;
+    let logger = CortexLogger::new(deps.sqlite_pool.clone());
+    let limit = deps.runtime_config.cortex.load().proactive_daily_limit;
+    if sent_today(&logger).await? >= limit {
+        return Err(OutreachError::LimitReached(limit));
+    }
+
+    let mut delivered = Vec::new();
+    let mut last_error = None;
+    for target in targets {
+        let response = OutboundResponse::Text(text.to_string());
+        match messaging_manager
+            .broadcast(&target.adapter, &target.target, response)
+            .await
+        {
+            Ok(()) => delivered.push(target),
+            Err(error) => {
+                tracing::warn!(agent_id = %deps.agent_id, %target, %error, "failed to send proactive message");
+                last_error = Some(error.to_string());
+            }
+        }
+    }
+    if delivered.is_empty() {
+        return Err(OutreachError::Send(last_error.unwrap_or_default()));
+    }
+
+    let names: Vec<String> = delivered.iter().map(ToString::to_string).collect();
+    tracing::info!(agent_id = %deps.agent_id, targets = ?names, "proactive message sent");
+    if let Err(error) = logger
+        .record(
+            PROACTIVE_EVENT,
+            &format!("Sent a proactive message to {}", names.join(", ")),
+            Some(serde_json::json!({
+                "targets": names,
+                "text": text,
+            })),
+        )
+        .await
+    {
+        tracing::warn!(%error, "failed to log proactive message");
+    }
+
+    Ok(delivered)
+}
+
+/// Have the cortex tell the agent's proactive channels about `event` in its
+/// own words. Does nothing when no binding allows it or today's limit is
+/// used up.
+pub async fn report(deps: &AgentDeps, event: &str) {
+    if targets(deps).is_empty() {
+        return;
+    }
+    let logger = CortexLogger::new(deps.sqlite_pool.clone());
+    let limit = deps.runtime_config.cortex.load().proactive_daily_limit;
+    match sent_today(&logger).await {
+        Ok(sent) if sent >= limit => {
+            tracing::debug!(agent_id = %deps.agent_id, limit, "proactive limit used up, not reporting");
+            return;
+        }
+        Ok(_) => {}
+        Err(error) => {
+            tracing::warn!(%error, "failed to check proactive limit");
+            return;
+        }
+    }
+
+    let text = match compose(deps, event).await {
+        Ok(text) if !text.is_empty() => text,
+        Ok(_) => return,
+        Err(error) => {
+            tracing::warn!(agent_id = %deps.agent_id, %error, "failed to compose proactive message");
+            return;
+        }
+    };
+    if let Err(error) = send_proactive(deps, None, &text).await {
+        tracing::warn!(agent_id = %deps.agent_id, %error, "proactive message not sent");
+    }
+}
+
+/// Write the message for `event`.
+async fn compose(deps: &AgentDeps, event: &str) -> anyhow::Result<String> {
+    let prompt_engine = deps.runtime_config.prompts.load();
+    let preamble = prompt_engine.render_static("cortex_outreach")?;
+    let prompt = prompt_engine.render_system_proactive_outreach(event)?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = routing.resolve(ProcessType::Branch, None).to_string();
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_context(&*deps.agent_id, "cortex")
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+
+    Ok(agent.prompt(&prompt).await?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(agent_id: &str, channel: &str, allow_proactive: bool) -> Binding {
+        Binding {
+            agent_id: agent_id.into(),
+            channel: channel.into(),
+            guild_id: None,
+            workspace_id: None,
+            chat_id: None,
+            channel_ids: Vec::new(),
+            activation: Default::default(),
+            wake_words: Vec::new(),
+            reply_to_message: false,
+            dm_allowed_users: Vec::new(),
+            digest_interval_secs: None,
+            response_mode: Default::default(),
+            allow_proactive,
+            tags: Vec::new(),
+            overrides: Default::default(),
+        }
+    }
+
+    #[test]
+    fn only_opted_in_bindings_are_targets() {
+        let bindings = vec![
+            Binding {
+                guild_id: Some("100".into()),
+                channel_ids: vec!["200".into(), "201".into()],
+                ..binding("main", "discord", true)
+            },
+            Binding {
+                chat_id: Some("-1001234".into()),
+                ..binding("main", "telegram", true)
+            },
+            // Same channel again, not messaged twice.
+            Binding {
+                channel_ids: vec!["200".into()],
+                ..binding("main", "discord", true)
+            },
+            // Not opted in, another agent's, or no channel to name.
+            Binding {
+                channel_ids: vec!["300".into()],
+                ..binding("main", "discord", false)
+            },
+            Binding {
+                channel_ids: vec!["400".into()],
+                ..binding("ops", "discord", true)
+            },
+            binding("main", "slack", true),
+        ];
+
+        let targets: Vec<String> = proactive_targets(&bindings, "main")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(targets, ["discord:200", "discord:201", "telegram:-1001234"]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Reactions read as replies to the agent.
+//!
+//! A reaction from the configured approve, deny or retry sets becomes a turn:
+//! the channel gets a text message saying what was reacted to and what the
+//! reaction means, and answers it like any other message. Retry only counts
+//! on the agent's own messages. Other reactions, apart from the memory
+//! capture one, are ignored.
+
+use crate::config::ReactionsConfig;
+use crate::{InboundMessage, MessageContent};
+
+/// Longest quote of the reacted message put in the signal.
+const MAX_QUOTE_CHARS: usize = 300;
+
+/// What a reaction asks of the agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Approve,
+    Deny,
+    Retry,
+}
+
+/// The signal `emoji` carries, or `None` when it isn't one.
+pub fn parse_signal(config: &ReactionsConfig, emoji: &str, to_bot: bool) -> Option<Signal> {
+    if !config.enabled {
+        return None;
+    }
+    let contains = |set: &[String]| set.iter().any(|candidate| candidate == emoji);
+    if contains(&config.approve) {
+        Some(Signal::Approve)
+    } else if contains(&config.deny) {
+        Some(Signal::Deny)
+    } else if to_bot && contains(&config.retry) {
+        Some(Signal::Retry)
+    } else {
+        None
+    }
+}
+
+/// Rewrite a reaction into the text message the channel answers, keeping its
+/// sender and metadata.
+pub fn signal_message(message: &InboundMessage, signal: Signal) -> Option<InboundMessage> {
+    let MessageContent::Reaction {
+        emoji,
+        target_author,
+        target_text,
+        to_bot,
+        ..
+    } = &message.content
+    else {
+        return None;
+    };
+
+    let target = match (to_bot, target_author) {
+        (true, _) => "your message".to_string(),
+        (false, Some(author)) => format!("{author}'s message"),
+        (false, None) => "a message".to_string(),
+    };
+    let quote = target_text
+        .as_deref()
+        .map(|text| format!(": \"{}\"", clip(text)))
+        .unwrap_or_default();
+    let text = match signal {
+        Signal::Approve => format!("[reaction: {emoji} approves {target}{quote}]"),
+        Signal::Deny => format!("[reaction: {emoji} denies {target}{quote}]"),
+        Signal::Retry => format!(
+            "[reaction: {emoji} asks you to answer again instead of {target}{quote}. \
+             Reply to the same request differently.]"
+        ),
+    };
+
+    let mut signal_message = message.clone();
+    signal_message.content = MessageContent::Text(text);
+    Some(signal_message)
+}
+
+fn clip(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_QUOTE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reaction(emoji: &str, to_bot: bool) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "3".into(),
+            agent_id: None,
+            content: MessageContent::Reaction {
+                emoji: emoji.into(),
+                message_id: "4".into(),
+                target_author: Some("Ada".into()),
+                target_text: Some("Deploy to production?".into()),
+                to_bot,
+            },
+            timestamp: chrono::Utc::now(),
+            metadata: Default::default(),
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn retry_only_counts_on_bot_messages() {
+        let config = ReactionsConfig::default();
+        assert_eq!(parse_signal(&config, "👍", false), Some(Signal::Approve));
+        assert_eq!(parse_signal(&config, "❌", true), Some(Signal::Deny));
+        assert_eq!(parse_signal(&config, "🔁", true), Some(Signal::Retry));
+        assert_eq!(parse_signal(&config, "🔁", false), None);
+        assert_eq!(parse_signal(&config, "🎉", true), None);
+
+        let disabled = ReactionsConfig {
+            enabled: false,
+            ..ReactionsConfig::default()
+        };
+        assert_eq!(parse_signal(&disabled, "👍", true), None);
+    }
+
+    #[test]
+    fn signals_become_text_naming_the_reacted_message() {
+        let approved = signal_message(&reaction("👍", true), Signal::Approve).unwrap();
+        assert_eq!(
+            approved.content.to_string(),
+            "[reaction: 👍 approves your message: \"Deploy to production?\"]"
+        );
+        assert_eq!(approved.sender_id, "3");
+
+        let denied = signal_message(&reaction("👎", false), Signal::Deny).unwrap();
+        assert_eq!(
+            denied.content.to_string(),
+            "[reaction: 👎 denies Ada's message: \"Deploy to production?\"]"
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! Self-reflection: the cortex reviewing recent conversations to keep
+//! IDENTITY.md and USER.md current.
+//!
+//! Opt in with `[reflection] enabled = true`. At most once per
+//! `interval_secs`, the cortex reads the last `max_messages` messages sent
+//! since then and asks the LLM what they show about the agent and its users.
+//! What it wants to change comes back as a proposal, which is staged rather
+//! than applied (see [`crate::identity::proposal`]). The proposal and its diff
+//! are posted to `control_channel`, where an admin replies `identity approve`
+//! or `identity reject`, or it's answered through the API. No reflection runs
+//! while a proposal is pending.
+//!
+//! Runs are logged as [`REFLECTION_EVENT`] cortex events, which is also how
+//! the interval holds across restarts, and answers as [`ANSWER_EVENT`].
+
+use crate::agent::cortex::CortexLogger;
+use crate::audit::{AuditAction, AuditLogger};
+use crate::identity::proposal::{IdentityProposal, IdentityProposals, ProposalError};
+use crate::llm::SpacebotModel;
+use crate::rbac::Role;
+use crate::{AgentDeps, InboundMessage, MessageContent, OutboundResponse, ProcessType};
+
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+use sqlx::{Row as _, SqlitePool};
+
+use std::time::{Duration, Instant};
+
+/// Cortex event type logged for each reflection.
+pub const REFLECTION_EVENT: &str = "identity_reflection";
+
+/// Cortex event type logged when a proposal is approved or rejected.
+pub const ANSWER_EVENT: &str = "identity_proposal";
+
+/// How often the loop checks whether a reflection is due.
+const CHECK_SECS: u64 = 600;
+
+/// Characters of a message kept in the transcript.
+const MAX_MESSAGE_CHARS: usize = 1000;
+
+/// Characters of the diff posted to the control channel.
+const MAX_NOTICE_DIFF_CHARS: usize = 1500;
+
+/// LLM response shape for a reflection.
+#[derive(serde::Deserialize)]
+struct ReflectionLlmResponse {
+    rationale: String,
+    identity: Option<String>,
+    user: Option<String>,
+}
+
+/// The agent's pending proposal store.
+pub fn proposals(deps: &AgentDeps) -> IdentityProposals {
+    IdentityProposals::new(
+        &deps.runtime_config.data_dir,
+        &deps.runtime_config.workspace_dir,
+    )
+}
+
+/// Reflect on recent conversations whenever reflection is enabled and due.
+pub fn spawn_reflection_loop(deps: AgentDeps, logger: CortexLogger) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_attempt: Option<Instant> = None;
+        let mut check = tokio::time::interval(Duration::from_secs(CHECK_SECS));
+        loop {
+            check.tick().await;
+            let config = deps.runtime_config.reflection.load();
+            let interval = Duration::from_secs(config.interval_secs);
+            if !config.enabled || last_attempt.is_some_and(|attempt| attempt.elapsed() < interval) {
+                continue;
+            }
+
+            // The last reflection may have run before a restart.
+            let since = chrono::Utc::now() - chrono::Duration::seconds(config.interval_secs as i64);
+            match logger.count_events_since(REFLECTION_EVENT, since).await {
+                Ok(0) => {}
+                Ok(_) => {
+                    last_attempt = Some(Instant::now());
+                    continue;
+                }
+                Err(error) => {
+                    tracing::warn!(%error, "failed to check the last reflection");
+                    continue;
+                }
+            }
+            match proposals(&deps).pending().await {
+                Ok(None) => {}
+                Ok(Some(_)) => continue,
+                Err(error) => {
+                    tracing::warn!(%error, "failed to check for a pending identity proposal");
+                    continue;
+                }
+            }
+
+            last_attempt = Some(Instant::now());
+            reflect(&deps, &logger).await;
+        }
+    })
+}
+
+/// Run one reflection: stage a proposal if the conversations call for one,
+/// and post it to the control channel.
+#[tracing::instrument(skip(deps, logger), fields(agent_id = %deps.agent_id))]
+async fn reflect(deps: &AgentDeps, logger: &CortexLogger) {
+    let config = deps.runtime_config.reflection.load();
+    tracing::info!("cortex reflecting on recent conversations");
+
+    let (summary, details, proposal) = match propose(deps).await {
+        Ok(None) => (
+            "Reflection: nothing to change".to_string(),
+            serde_json::json!({}),
+            None,
+        ),
+        Ok(Some(proposal)) => {
+            if let Err(error) = proposals(deps).stage(&proposal).await {
+                tracing::warn!(%error, "failed to stage identity proposal");
+                return;
+            }
+            (
+                format!("Reflection proposes changes to {}", proposal.files()),
+                serde_json::json!({
+                    "proposal_id": proposal.id,
+                    "files": proposal.changes.iter().map(|change| &change.file).collect::<Vec<_>>(),
+                    "rationale": proposal.rationale,
+                }),
+                Some(proposal),
+            )
+        }
+        Err(error) => {
+            tracing::warn!(%error, "reflection failed");
+            (
+                format!("Reflection failed: {error}"),
+                serde_json::json!({ "error": error.to_string() }),
+                None,
+            )
+        }
+    };
+    // Waited on: the next reflection is scheduled from this event.
+    if let Err(error) = logger
+        .record(REFLECTION_EVENT, &summary, Some(details))
+        .await
+    {
+        tracing::warn!(%error, "failed to persist cortex event");
+    }
+
+    let (Some(proposal), Some(control_channel)) = (proposal, &config.control_channel) else {
+        return;
+    };
+    let Some(messaging_manager) = &deps.messaging_manager else {
+        return;
+    };
+    if let Err(error) = messaging_manager
+        .broadcast(
+            &control_channel.adapter,
+            &control_channel.target,
+            OutboundResponse::Text(notice(&proposal)),
+        )
+        .await
+    {
+        tracing::warn!(%error, "failed to post identity proposal");
+    }
+}
+
+/// Ask the LLM what recent conversations change about the identity files.
+async fn propose(deps: &AgentDeps) -> anyhow::Result<Option<IdentityProposal>> {
+    let config = deps.runtime_config.reflection.load();
+    let transcript =
+        load_transcript(&deps.sqlite_pool, config.interval_secs, config.max_messages).await?;
+    if transcript.is_empty() {
+        return Ok(None);
+    }
+
+    let workspace = &deps.runtime_config.workspace_dir;
+    let identity = tokio::fs::read_to_string(workspace.join("IDENTITY.md"))
+        .await
+        .unwrap_or_default();
+    let user = tokio::fs::read_to_string(workspace.join("USER.md"))
+        .await
+        .unwrap_or_default();
+
+    let prompt_engine = deps.runtime_config.prompts.load();
+    let preamble = prompt_engine.render_static("cortex_reflection")?;
+    let prompt = prompt_engine.render_system_identity_reflection(
+        (!identity.trim().is_empty()).then_some(identity.as_str()),
+        (!user.trim().is_empty()).then_some(user.as_str()),
+        &transcript,
+    )?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = routing.resolve(ProcessType::Branch, None).to_string();
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_context(&*deps.agent_id, "cortex")
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+
+    let response = agent.prompt(&prompt).await?;
+    let cleaned = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let response: ReflectionLlmResponse = serde_json::from_str(cleaned)?;
+
+    let mut files = Vec::new();
+    for (file, current, proposed) in [
+        ("IDENTITY.md", identity, response.identity),
+        ("USER.md", user, response.user),
+    ] {
+        if let Some(proposed) = proposed {
+            files.push((file, current, proposed));
+        }
+    }
+    Ok(IdentityProposal::new(response.rationale, files))
+}
+
+/// The newest `limit` messages of the last `window_secs`, oldest first, as
+/// `[channel] sender: text` lines.
+pub(crate) async fn load_transcript(
+    pool: &SqlitePool,
+    window_secs: u64,
+    limit: i64,
+) -> sqlx::Result<String> {
+    let rows = sqlx::query(
+        "SELECT channel_id, role, sender_name, content FROM conversation_messages \
+         WHERE created_at > datetime('now', ?) \
+         ORDER BY created_at DESC \
+         LIMIT ?",
+    )
+    .bind(format!("-{window_secs} seconds"))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let mut lines = Vec::with_capacity(rows.len());
+    for row in rows.iter().rev() {
+        let channel_id: String = row.try_get("channel_id")?;
+        let role: String = row.try_get("role")?;
+        let sender_name: Option<String> = row.try_get("sender_name")?;
+        let content: String = row.try_get("content")?;
+        let sender = match role.as_str() {
+            "assistant" => "agent".to_string(),
+            _ => sender_name.unwrap_or_else(|| "user".into()),
+        };
+        let end = content.floor_char_boundary(MAX_MESSAGE_CHARS);
+        lines.push(format!("[{channel_id}] {sender}: {}", &content[..end]));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The message posted to the control channel for a new proposal.
+fn notice(proposal: &IdentityProposal) -> String {
+    let mut diff = String::new();
+    for change in &proposal.changes {
+        diff.push_str(&format!("{}:\n{}", change.file, change.diff));
+    }
+    if diff.len() > MAX_NOTICE_DIFF_CHARS {
+        let end = diff.floor_char_boundary(MAX_NOTICE_DIFF_CHARS);
+        diff = format!("{}\n... (full diff in the API)", &diff[..end]);
+    }
+    format!(
+        "Reflection proposes changes to {}: {}\n```diff\n{diff}```\nReply `identity approve` or `identity reject`.",
+        proposal.files(),
+        proposal.rationale
+    )
+}
+
+/// Approve or reject the pending proposal on behalf of `actor`, then log
+/// and audit the answer.
+pub async fn answer_proposal(
+    pool: &SqlitePool,
+    proposals: &IdentityProposals,
+    approve: bool,
+    actor: &str,
+) -> Result<IdentityProposal, ProposalError> {
+    let proposal = if approve {
+        proposals.approve().await?
+    } else {
+        proposals.reject().await?.ok_or(ProposalError::NotFound)?
+    };
+
+    let answer = if approve { "approved" } else { "rejected" };
+    CortexLogger::new(pool.clone()).log(
+        ANSWER_EVENT,
+        &format!(
+            "Identity proposal {answer} by {actor}: {}",
+            proposal.files()
+        ),
+        Some(serde_json::json!({
+            "proposal_id": proposal.id,
+            "approved": approve,
+            "actor": actor,
+        })),
+    );
+    if approve {
+        AuditLogger::new(pool.clone()).with_actor(actor).log(
+            AuditAction::ConfigChanged,
+            "identity",
+            serde_json::json!({
+                "files": proposal.changes.iter().map(|change| &change.file).collect::<Vec<_>>(),
+                "proposal_id": proposal.id,
+            }),
+        );
+    }
+    Ok(proposal)
+}
+
+/// Handle `identity approve` or `identity reject` sent in the agent's
+/// reflection control channel. Returns the reply when `message` was such a
+/// command.
+pub async fn handle_command(deps: &AgentDeps, message: &InboundMessage) -> Option<String> {
+    let config = deps.runtime_config.reflection.load();
+    let control_channel = config.control_channel.as_ref()?;
+    if message.source != control_channel.adapter
+        || message.conversation_id.rsplit(':').next() != Some(control_channel.target.as_str())
+    {
+        return None;
+    }
+    let MessageContent::Text(text) = &message.content else {
+        return None;
+    };
+    let approve = parse_command(text)?;
+
+    let rbac = deps.runtime_config.rbac.load();
+    if rbac.resolve_role(&message.source, &message.sender_id) < Role::Admin {
+        return Some("Only admins can answer identity proposals.".into());
+    }
+
+    let actor = format!("{}:{}", message.source, message.sender_id);
+    let reply = match answer_proposal(&deps.sqlite_pool, &proposals(deps), approve, &actor).await {
+        Ok(proposal) if approve => format!("Updated {}.", proposal.files()),
+        Ok(_) => "Rejected the identity proposal.".into(),
+        Err(ProposalError::Other(error)) => {
+            tracing::error!(%error, "failed to answer identity proposal");
+            "Couldn't answer the identity proposal; see the logs.".into()
+        }
+        Err(error) => format!("{error}."),
+    };
+    Some(reply)
+}
+
+/// `identity approve` or `identity reject`, as whether it approves.
+fn parse_command(text: &str) -> Option<bool> {
+    let mut words = text.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("identity") {
+        return None;
+    }
+    let approve = match words.next()?.to_ascii_lowercase().as_str() {
+        "approve" => true,
+        "reject" => false,
+        _ => return None,
+    };
+    words.next().is_none().then_some(approve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_identity_commands() {
+        assert_eq!(parse_command("identity approve"), Some(true));
+        assert_eq!(parse_command(" Identity REJECT "), Some(false));
+        assert_eq!(parse_command("approve ABC123"), None);
+        assert_eq!(parse_command("identity approve now"), None);
+        assert_eq!(parse_command("identity"), None);
+    }
+}
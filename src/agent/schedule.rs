@@ -0,0 +1,124 @@
+//! Scheduled tasks: work the cortex queues on a cron schedule.
+//!
+//! Tasks come from `[[agents.tasks]]`. The task loop checks every enabled
+//! task's cron expression once a minute, in the agent's `cron_timezone`,
+//! and adds a run of each one that matches to the task queue (see
+//! [`crate::agent::task_queue`]), which starts a worker for it and posts its
+//! result and artifacts to the task's `report_to`. A task whose previous run
+//! is still queued or running is skipped. Runs are logged as [`TASK_EVENT`]
+//! cortex events. Minutes that pass while Spacebot is down aren't caught up.
+
+use crate::AgentDeps;
+use crate::agent::cortex::CortexLogger;
+use crate::agent::task_queue::{NewTask, TaskOrigin, TaskQueue};
+use crate::config::TaskDef;
+
+use chrono_tz::Tz;
+
+use std::time::Duration;
+
+/// Cortex event type logged when a scheduled task is queued.
+pub const TASK_EVENT: &str = "scheduled_task";
+
+/// How often the loop looks at the clock. Each minute is evaluated once.
+const TICK_SECS: u64 = 10;
+
+/// Queue the agent's scheduled tasks as they come due.
+pub fn spawn_task_loop(deps: AgentDeps, logger: CortexLogger) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let queue = TaskQueue::new(deps.sqlite_pool.clone());
+        let mut tick = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut last_minute = None;
+
+        loop {
+            tick.tick().await;
+            let now = chrono::Utc::now();
+            let minute = now.timestamp().div_euclid(60);
+            if last_minute == Some(minute) {
+                continue;
+            }
+            last_minute = Some(minute);
+            let tasks = deps.runtime_config.tasks.load();
+            let timezone = deps.runtime_config.cron_timezone.load();
+            for task in tasks.iter() {
+                if task.enabled && is_due(task, now, timezone.as_deref()) {
+                    queue_task(&queue, &logger, task).await;
+                }
+            }
+        }
+    })
+}
+
+/// Whether `task` fires in the minute of `now`, read in `timezone`, or the
+/// system timezone when there's none.
+fn is_due(task: &TaskDef, now: chrono::DateTime<chrono::Utc>, timezone: Option<&str>) -> bool {
+    match timezone.and_then(|name| name.parse::<Tz>().ok()) {
+        Some(timezone) => task.schedule.matches(&now.with_timezone(&timezone)),
+        None => task.schedule.matches(&now.with_timezone(&chrono::Local)),
+    }
+}
+
+async fn queue_task(queue: &TaskQueue, logger: &CortexLogger, task: &TaskDef) {
+    match queue.has_open(TaskOrigin::Schedule, &task.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            tracing::info!(task_id = %task.id, "previous run of scheduled task still going, skipping");
+            return;
+        }
+        Err(error) => {
+            tracing::warn!(task_id = %task.id, %error, "failed to check previous run of scheduled task");
+            return;
+        }
+    }
+
+    let new = NewTask {
+        origin_id: Some(task.id.clone()),
+        report_to: task.report_to.clone(),
+        ..NewTask::new(task.task.clone(), TaskOrigin::Schedule)
+    };
+    match queue.enqueue(new).await {
+        Ok(queued) => {
+            tracing::info!(task_id = %task.id, queued_id = %queued.id, "scheduled task queued");
+            logger.log(
+                TASK_EVENT,
+                &format!("Scheduled task '{}' queued", task.id),
+                Some(serde_json::json!({
+                    "task_id": task.id,
+                    "queued_id": queued.id,
+                    "schedule": task.schedule.to_string(),
+                })),
+            );
+        }
+        Err(error) => {
+            tracing::warn!(task_id = %task.id, %error, "failed to queue scheduled task");
+            logger.log(
+                TASK_EVENT,
+                &format!("Scheduled task '{}' failed to queue: {error}", task.id),
+                Some(serde_json::json!({ "task_id": task.id, "error": error.to_string() })),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_are_due_in_the_configured_timezone() {
+        let task = TaskDef {
+            id: "standup".into(),
+            schedule: "0 9 * * *".parse().unwrap(),
+            task: "Summarize yesterday's commits".into(),
+            report_to: None,
+            enabled: true,
+        };
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-02T08:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        assert!(is_due(&task, now, Some("Europe/Paris")));
+        assert!(!is_due(&task, now, Some("America/New_York")));
+    }
+}
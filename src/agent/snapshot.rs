@@ -0,0 +1,203 @@
+//! History snapshots, for reproducing channel turns.
+//!
+//! A snapshot is a channel's exact context at one moment: its history, status
+//! block and rolling summary, written as JSON to `snapshots/` in the agent's
+//! data directory. Restoring one starts a fresh webchat channel seeded with
+//! it, the way forks seed their thread, so a bad turn can be replayed or a
+//! prompt change tried against a real conversation. Snapshots are taken and
+//! restored with the `/api/channels/snapshot` and `/api/channels/restore`
+//! routes.
+
+use crate::agent::channel::ChannelState;
+use crate::agent::status::StatusBlock;
+use crate::conversation::archive::file_safe;
+use crate::error::Result;
+use crate::{InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Metadata key carrying the snapshot on a restore seed message.
+pub const SNAPSHOT_KEY: &str = "history_snapshot";
+
+/// A channel's context, as it was when the snapshot was taken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySnapshot {
+    pub agent_id: String,
+    pub channel_id: String,
+    pub taken_at: DateTime<Utc>,
+    pub history: Vec<rig::message::Message>,
+    pub status_block: StatusBlock,
+    /// The rolling conversation summary, if the channel had one.
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+/// A snapshot file in the agent's snapshots directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotFile {
+    pub name: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+impl HistorySnapshot {
+    /// Snapshot a live channel.
+    pub async fn take(state: &ChannelState) -> Result<Self> {
+        let summary = state.channel_store.summary(&state.channel_id).await?;
+        Ok(Self {
+            agent_id: state.deps.agent_id.to_string(),
+            channel_id: state.channel_id.to_string(),
+            taken_at: Utc::now(),
+            history: state.history.read().await.clone(),
+            status_block: state.status_block.read().await.clone(),
+            summary,
+        })
+    }
+
+    /// The file name the snapshot is written under.
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}_{}.json",
+            file_safe(&self.channel_id),
+            self.taken_at.format("%Y-%m-%dT%H-%M-%S%.3f")
+        )
+    }
+
+    /// Write the snapshot to `dir`. Returns its file name.
+    pub async fn write(&self, dir: &Path) -> Result<String> {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .context("failed to create snapshots directory")?;
+        let name = self.file_name();
+        let json = serde_json::to_vec_pretty(self).context("failed to serialize snapshot")?;
+        tokio::fs::write(dir.join(&name), json)
+            .await
+            .context("failed to write snapshot")?;
+        Ok(name)
+    }
+
+    /// Read a snapshot file.
+    pub async fn read(path: &Path) -> Result<Self> {
+        let json = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("failed to read snapshot {}", path.display()))?;
+        Ok(serde_json::from_slice(&json).context("failed to parse snapshot")?)
+    }
+
+    /// A seed message starting a fresh channel in `conversation_id` with
+    /// this snapshot as its context.
+    pub fn into_seed(self, conversation_id: &str) -> Result<InboundMessage> {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            SNAPSHOT_KEY.into(),
+            serde_json::to_value(&self).context("failed to serialize snapshot")?,
+        );
+        Ok(InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "webchat".into(),
+            conversation_id: conversation_id.to_string(),
+            sender_id: "api".into(),
+            agent_id: Some(self.agent_id.into()),
+            content: MessageContent::Text(String::new()),
+            timestamp: Utc::now(),
+            metadata,
+            formatted_author: None,
+        })
+    }
+}
+
+/// The snapshots directory of an agent with `data_dir`.
+pub fn snapshots_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("snapshots")
+}
+
+/// The path of snapshot `name` in `dir`, or `None` if `name` isn't a plain
+/// snapshot file name.
+pub fn snapshot_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let is_plain = Path::new(name).file_name().is_some_and(|file| file == name);
+    (is_plain && name.ends_with(".json")).then(|| dir.join(name))
+}
+
+/// The snapshots in `dir`, newest first.
+pub async fn list(dir: &Path) -> Result<Vec<SnapshotFile>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.ends_with(".json") {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        files.push(SnapshotFile {
+            name,
+            size_bytes: metadata.len(),
+            modified_at: metadata.modified().ok().map(DateTime::<Utc>::from),
+        });
+    }
+    files.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    Ok(files)
+}
+
+/// Take the snapshot off a restore seed. Returns `None` for every other
+/// message.
+pub fn take_seed(message: &mut InboundMessage) -> Option<HistorySnapshot> {
+    let snapshot = message.metadata.remove(SNAPSHOT_KEY)?;
+    match serde_json::from_value(snapshot) {
+        Ok(snapshot) => Some(snapshot),
+        Err(error) => {
+            tracing::warn!(%error, "can't read restored snapshot, starting the channel empty");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rig::OneOrMany;
+    use rig::message::{Message, UserContent};
+
+    #[test]
+    fn snapshots_round_trip_through_a_seed() {
+        let snapshot = HistorySnapshot {
+            agent_id: "main".into(),
+            channel_id: "discord:123:456".into(),
+            taken_at: Utc::now(),
+            history: vec![Message::User {
+                content: OneOrMany::one(UserContent::text("[alice]: hello")),
+            }],
+            status_block: StatusBlock::new(),
+            summary: Some("Alice said hello.".into()),
+        };
+        assert!(snapshot.file_name().starts_with("discord_123_456_"));
+
+        let mut seed = snapshot.into_seed("webchat:restore-1").unwrap();
+        assert_eq!(seed.conversation_id, "webchat:restore-1");
+        let restored = take_seed(&mut seed).unwrap();
+        assert_eq!(restored.history.len(), 1);
+        assert_eq!(restored.summary.as_deref(), Some("Alice said hello."));
+        assert!(take_seed(&mut seed).is_none());
+    }
+
+    #[test]
+    fn only_plain_file_names_resolve() {
+        let dir = Path::new("/data/snapshots");
+        assert_eq!(
+            snapshot_path(dir, "main_2026.json"),
+            Some(dir.join("main_2026.json"))
+        );
+        assert_eq!(snapshot_path(dir, "../config.json"), None);
+        assert_eq!(snapshot_path(dir, "/etc/passwd.json"), None);
+        assert_eq!(snapshot_path(dir, "notes.md"), None);
+    }
+}
@@ -1,10 +1,39 @@
 //! StatusBlock: Live status snapshot for channels.
+//!
+//! Besides the block injected into the channel's context, the snapshot
+//! renders as a live status card that adapters post and keep editing while
+//! branches and workers run, with a button to cancel each of them.
 
-use crate::{BranchId, ProcessEvent, ProcessId, WorkerId};
+use crate::agent::health::HealthAction;
+use crate::config::PresenceConfig;
+use crate::messaging::traits::{Activity, Presence};
+use crate::{
+    BranchId, Button, ButtonStyle, Card, CardField, InteractiveElements, ProcessEvent, ProcessId,
+    WorkerId,
+};
 use chrono::{DateTime, Utc};
 
+/// Prefix of the custom IDs carried by cancel buttons on the status card.
+const CANCEL_ACTION_PREFIX: &str = "spacebot:cancel:";
+
+/// Processes listed on the status card; the rest are only counted.
+const MAX_CARD_PROCESSES: usize = 10;
+
+/// Buttons per row on the status card (Discord's limit).
+const BUTTONS_PER_ROW: usize = 5;
+
+/// Health diagnostics kept on the block, newest last.
+const MAX_DIAGNOSTICS: usize = 5;
+
+/// Status sent for a branch or worker that was cancelled.
+pub const CANCELLED_STATUS: &str = "cancelled";
+
+/// Status sent for a branch that stopped with an error, which never sends a
+/// result.
+pub const FAILED_STATUS: &str = "failed";
+
 /// Live status block injected into channel context.
-#[derive(Debug, Clone, Default, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct StatusBlock {
     /// Currently running branches.
     pub active_branches: Vec<BranchStatus>,
@@ -12,18 +41,23 @@ pub struct StatusBlock {
     pub active_workers: Vec<WorkerStatus>,
     /// Recently completed work.
     pub completed_items: Vec<CompletedItem>,
+    /// The cortex's recent nudges and kills of this channel's processes.
+    pub diagnostics: Vec<HealthDiagnostic>,
 }
 
 /// Status of an active branch.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BranchStatus {
     pub id: BranchId,
     pub started_at: DateTime<Utc>,
     pub description: String,
+    pub tool_calls: usize,
+    /// Tool the branch most recently started.
+    pub last_tool: Option<String>,
 }
 
 /// Status of an active worker.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct WorkerStatus {
     pub id: WorkerId,
     pub task: String,
@@ -31,10 +65,12 @@ pub struct WorkerStatus {
     pub started_at: DateTime<Utc>,
     pub notify_on_complete: bool,
     pub tool_calls: usize,
+    /// Tool the worker most recently started.
+    pub last_tool: Option<String>,
 }
 
 /// Recently completed work item.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CompletedItem {
     pub id: String,
     pub item_type: CompletedItemType,
@@ -43,8 +79,17 @@ pub struct CompletedItem {
     pub result_summary: String,
 }
 
+/// A nudge or kill by the cortex's health checks.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HealthDiagnostic {
+    pub process_id: ProcessId,
+    pub action: HealthAction,
+    pub diagnostic: String,
+    pub at: DateTime<Utc>,
+}
+
 /// Type of completed item.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum CompletedItemType {
     Branch,
     Worker,
@@ -88,6 +133,27 @@ impl StatusBlock {
                     }
                 }
             }
+            ProcessEvent::ToolStarted {
+                process_id,
+                tool_name,
+                ..
+            } => match process_id {
+                ProcessId::Worker(worker_id) => {
+                    if let Some(worker) =
+                        self.active_workers.iter_mut().find(|w| w.id == *worker_id)
+                    {
+                        worker.last_tool = Some(tool_name.clone());
+                    }
+                }
+                ProcessId::Branch(branch_id) => {
+                    if let Some(branch) =
+                        self.active_branches.iter_mut().find(|b| b.id == *branch_id)
+                    {
+                        branch.last_tool = Some(tool_name.clone());
+                    }
+                }
+                ProcessId::Channel(_) => {}
+            },
             ProcessEvent::ToolCompleted {
                 process_id: ProcessId::Worker(worker_id),
                 ..
@@ -96,6 +162,21 @@ impl StatusBlock {
                     worker.tool_calls += 1;
                 }
             }
+            ProcessEvent::ToolCompleted {
+                process_id: ProcessId::Branch(branch_id),
+                ..
+            } => {
+                if let Some(branch) = self.active_branches.iter_mut().find(|b| b.id == *branch_id) {
+                    branch.tool_calls += 1;
+                }
+            }
+            ProcessEvent::StatusUpdate {
+                process_id, status, ..
+            } if status == CANCELLED_STATUS || status == FAILED_STATUS => match process_id {
+                ProcessId::Worker(worker_id) => self.remove_worker(*worker_id),
+                ProcessId::Branch(branch_id) => self.remove_branch(*branch_id),
+                ProcessId::Channel(_) => {}
+            },
             ProcessEvent::BranchResult {
                 branch_id,
                 conclusion,
@@ -118,6 +199,22 @@ impl StatusBlock {
                     self.completed_items.remove(0);
                 }
             }
+            ProcessEvent::ProcessUnhealthy {
+                process_id,
+                action,
+                diagnostic,
+                ..
+            } => {
+                self.diagnostics.push(HealthDiagnostic {
+                    process_id: process_id.clone(),
+                    action: *action,
+                    diagnostic: diagnostic.clone(),
+                    at: Utc::now(),
+                });
+                if self.diagnostics.len() > MAX_DIAGNOSTICS {
+                    self.diagnostics.remove(0);
+                }
+            }
             _ => {}
         }
     }
@@ -128,6 +225,8 @@ impl StatusBlock {
             id,
             started_at: Utc::now(),
             description: description.into(),
+            tool_calls: 0,
+            last_tool: None,
         });
     }
 
@@ -140,9 +239,20 @@ impl StatusBlock {
             started_at: Utc::now(),
             notify_on_complete,
             tool_calls: 0,
+            last_tool: None,
         });
     }
 
+    /// Drop a cancelled worker without recording it as completed.
+    pub fn remove_worker(&mut self, worker_id: WorkerId) {
+        self.active_workers.retain(|w| w.id != worker_id);
+    }
+
+    /// Drop a cancelled branch without recording it as completed.
+    pub fn remove_branch(&mut self, branch_id: BranchId) {
+        self.active_branches.retain(|b| b.id != branch_id);
+    }
+
     /// Render the status block as a string for context injection.
     pub fn render(&self) -> String {
         let mut output = String::new();
@@ -205,9 +315,115 @@ impl StatusBlock {
             output.push('\n');
         }
 
+        if !self.diagnostics.is_empty() {
+            output.push_str("## Health Diagnostics\n");
+            for item in &self.diagnostics {
+                let action = match item.action {
+                    HealthAction::Nudge => "looks stuck",
+                    HealthAction::Kill => "was stopped",
+                };
+                output.push_str(&format!(
+                    "- [{}] {} at {} ({})\n",
+                    item.process_id,
+                    action,
+                    item.at.format("%H:%M"),
+                    item.diagnostic
+                ));
+            }
+            output.push('\n');
+        }
+
         output
     }
 
+    /// Render the running work as a status card with a cancel button per
+    /// process. Returns `None` when nothing is running.
+    pub fn live_card(&self) -> Option<(Card, Vec<InteractiveElements>)> {
+        let now = Utc::now();
+        let processes: Vec<CardProcess> = self
+            .active_workers
+            .iter()
+            .map(|worker| CardProcess {
+                id: ProcessId::Worker(worker.id),
+                kind: "Worker",
+                title: &worker.task,
+                status: Some(&worker.status),
+                started_at: worker.started_at,
+                tool_calls: worker.tool_calls,
+                last_tool: worker.last_tool.as_deref(),
+            })
+            .chain(self.active_branches.iter().map(|branch| CardProcess {
+                id: ProcessId::Branch(branch.id),
+                kind: "Branch",
+                title: &branch.description,
+                status: None,
+                started_at: branch.started_at,
+                tool_calls: branch.tool_calls,
+                last_tool: branch.last_tool.as_deref(),
+            }))
+            .collect();
+        if processes.is_empty() {
+            return None;
+        }
+
+        let mut fields = Vec::new();
+        let mut buttons = Vec::new();
+        for (index, process) in processes.iter().take(MAX_CARD_PROCESSES).enumerate() {
+            let number = index + 1;
+            let mut details = vec![
+                format!("{} steps", process.tool_calls),
+                format!("{} elapsed", format_elapsed(now - process.started_at)),
+            ];
+            if let Some(tool) = process.last_tool {
+                details.push(format!("last tool `{tool}`"));
+            }
+            let mut value = details.join(" · ");
+            if let Some(status) = process.status.filter(|status| !status.is_empty()) {
+                value = format!("{}\n{value}", clip(status, 900));
+            }
+            fields.push(CardField {
+                name: format!("{number}. {}: {}", process.kind, clip(process.title, 200)),
+                value,
+                inline: false,
+            });
+            buttons.push(Button {
+                label: format!("Cancel {number}"),
+                custom_id: Some(cancel_action_id(&process.id)),
+                style: ButtonStyle::Danger,
+                url: None,
+            });
+        }
+
+        let hidden = processes.len().saturating_sub(MAX_CARD_PROCESSES);
+        let footer = if hidden > 0 {
+            format!(
+                "{hidden} more not shown · updated {}",
+                now.format("%H:%M:%S UTC")
+            )
+        } else {
+            format!("Updated {}", now.format("%H:%M:%S UTC"))
+        };
+        let card = Card {
+            title: Some(match processes.len() {
+                1 => "1 task running".to_string(),
+                count => format!("{count} tasks running"),
+            }),
+            description: None,
+            color: Some(0x5865F2),
+            url: None,
+            fields,
+            footer: Some(footer),
+            image_url: None,
+        };
+        let rows = buttons
+            .chunks(BUTTONS_PER_ROW)
+            .map(|row| InteractiveElements::Buttons {
+                buttons: row.to_vec(),
+            })
+            .collect();
+        Some((card, rows))
+    }
+
     /// Check if a worker is active.
     pub fn is_worker_active(&self, worker_id: WorkerId) -> bool {
         self.active_workers.iter().any(|w| w.id == worker_id)
@@ -217,4 +433,188 @@ impl StatusBlock {
     pub fn active_branch_count(&self) -> usize {
         self.active_branches.len()
     }
+
+    /// The bot presence for this snapshot: busy with the running counts while
+    /// anything runs, away otherwise.
+    pub fn presence(&self, config: &PresenceConfig) -> Presence {
+        let workers = self.active_workers.len();
+        let branches = self.active_branches.len();
+        if workers == 0 && branches == 0 {
+            return Presence {
+                idle: true,
+                activity: (!config.idle_text.is_empty()).then(|| Activity {
+                    kind: config.activity,
+                    text: config.idle_text.clone(),
+                }),
+            };
+        }
+
+        let text = config
+            .busy_text
+            .replace("{workers}", &workers.to_string())
+            .replace("{branches}", &branches.to_string());
+        Presence {
+            idle: false,
+            activity: Some(Activity {
+                kind: config.activity,
+                text,
+            }),
+        }
+    }
+}
+
+/// A branch or worker as listed on the status card.
+struct CardProcess<'a> {
+    id: ProcessId,
+    kind: &'static str,
+    title: &'a str,
+    status: Option<&'a str>,
+    started_at: DateTime<Utc>,
+    tool_calls: usize,
+    last_tool: Option<&'a str>,
+}
+
+/// Custom ID of the button that cancels `process_id`.
+pub fn cancel_action_id(process_id: &ProcessId) -> String {
+    match process_id {
+        ProcessId::Worker(worker_id) => format!("{CANCEL_ACTION_PREFIX}worker:{worker_id}"),
+        ProcessId::Branch(branch_id) => format!("{CANCEL_ACTION_PREFIX}branch:{branch_id}"),
+        ProcessId::Channel(channel_id) => format!("{CANCEL_ACTION_PREFIX}channel:{channel_id}"),
+    }
+}
+
+/// The process a status card cancel button targets. Returns `None` for any
+/// other interaction.
+pub fn parse_cancel_action(action_id: &str) -> Option<ProcessId> {
+    let (kind, id) = action_id
+        .strip_prefix(CANCEL_ACTION_PREFIX)?
+        .split_once(':')?;
+    match kind {
+        "worker" => id.parse().ok().map(ProcessId::Worker),
+        "branch" => id.parse().ok().map(ProcessId::Branch),
+        _ => None,
+    }
+}
+
+fn format_elapsed(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+fn clip(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let end = text.floor_char_boundary(max_bytes);
+    format!("{}…", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_events_update_progress() {
+        let mut status = StatusBlock::new();
+        let worker_id = uuid::Uuid::new_v4();
+        let branch_id = uuid::Uuid::new_v4();
+        status.add_worker(worker_id, "index the repo", false);
+        status.add_branch(branch_id, "recall preferences");
+
+        let agent_id: crate::AgentId = std::sync::Arc::from("agent");
+        for process_id in [ProcessId::Worker(worker_id), ProcessId::Branch(branch_id)] {
+            status.update(&ProcessEvent::ToolStarted {
+                agent_id: agent_id.clone(),
+                process_id: process_id.clone(),
+                channel_id: None,
+                tool_name: "shell".into(),
+            });
+            status.update(&ProcessEvent::ToolCompleted {
+                agent_id: agent_id.clone(),
+                process_id,
+                channel_id: None,
+                tool_name: "shell".into(),
+                result: String::new(),
+            });
+        }
+
+        assert_eq!(status.active_workers[0].tool_calls, 1);
+        assert_eq!(status.active_workers[0].last_tool.as_deref(), Some("shell"));
+        assert_eq!(status.active_branches[0].tool_calls, 1);
+        assert_eq!(
+            status.active_branches[0].last_tool.as_deref(),
+            Some("shell")
+        );
+    }
+
+    #[test]
+    fn live_card_lists_processes_with_cancel_buttons() {
+        let mut status = StatusBlock::new();
+        assert!(status.live_card().is_none());
+
+        let worker_ids: Vec<WorkerId> = (0..7).map(|_| uuid::Uuid::new_v4()).collect();
+        for worker_id in &worker_ids {
+            status.add_worker(*worker_id, "crawl docs", false);
+        }
+        let (card, rows) = status.live_card().unwrap();
+
+        assert_eq!(card.title.as_deref(), Some("7 tasks running"));
+        assert_eq!(card.fields.len(), 7);
+        assert_eq!(rows.len(), 2);
+        let InteractiveElements::Buttons { buttons } = &rows[0] else {
+            panic!("expected a button row");
+        };
+        let action_id = buttons[0].custom_id.as_deref().unwrap();
+        assert_eq!(
+            parse_cancel_action(action_id),
+            Some(ProcessId::Worker(worker_ids[0]))
+        );
+
+        status.remove_worker(worker_ids[0]);
+        assert_eq!(status.active_workers.len(), 6);
+    }
+
+    #[test]
+    fn presence_counts_running_work_until_cancelled() {
+        let config = PresenceConfig {
+            busy_text: "{workers} workers, {branches} branches".into(),
+            ..Default::default()
+        };
+        let mut status = StatusBlock::new();
+        assert_eq!(
+            status.presence(&config),
+            Presence {
+                idle: true,
+                activity: None
+            }
+        );
+
+        let worker_id = uuid::Uuid::new_v4();
+        status.add_worker(worker_id, "crawl docs", false);
+        status.add_branch(uuid::Uuid::new_v4(), "recall");
+        let presence = status.presence(&config);
+        assert!(!presence.idle);
+        assert_eq!(presence.activity.unwrap().text, "1 workers, 1 branches");
+
+        status.update(&ProcessEvent::StatusUpdate {
+            agent_id: "main".into(),
+            process_id: ProcessId::Worker(worker_id),
+            status: CANCELLED_STATUS.into(),
+        });
+        assert!(status.active_workers.is_empty());
+    }
+
+    #[test]
+    fn unrelated_interactions_are_not_cancel_actions() {
+        assert_eq!(parse_cancel_action("approve"), None);
+        assert_eq!(
+            parse_cancel_action("spacebot:cancel:worker:not-a-uuid"),
+            None
+        );
+        assert_eq!(format_elapsed(chrono::Duration::seconds(75)), "1m 15s");
+    }
 }
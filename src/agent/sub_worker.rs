@@ -0,0 +1,235 @@
+//! Sub-workers: workers spawned by another worker to take on part of its
+//! task.
+//!
+//! A sub-worker's result rolls up to the worker that spawned it, arriving in
+//! its mailbox, instead of going to the channel. Nesting is limited to
+//! `sub_workers.max_depth` levels, and the sub-workers running under one
+//! channel's workers share a `sub_workers.max_per_channel` cap. Sub-workers
+//! are stopped when their parent ends, and aren't checkpointed. Like any
+//! worker, a sub-worker waits for a slot in the agent's worker queue.
+//! Artifacts a sub-worker emits are handed to its parent when it finishes.
+
+use crate::agent::artifact::{self, ArtifactStore};
+use crate::agent::mailbox;
+use crate::agent::task_queue::TaskPriority;
+use crate::agent::worker::Worker;
+use crate::agent::worker_queue::{self, SlotRequest};
+use crate::config::BrowserConfig;
+use crate::error::AgentError;
+use crate::{AgentDeps, WorkerId};
+
+use tracing::Instrument as _;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Where a worker sits in the tree of workers spawned from one channel.
+#[derive(Debug, Clone)]
+pub struct WorkerNesting {
+    /// 1 for workers spawned by a channel, resumed or run outside a
+    /// conversation; one more for each level of sub-workers.
+    pub depth: usize,
+    /// The worker that spawned this one.
+    pub parent: Option<WorkerId>,
+    /// Sub-workers running under the channel, shared by all its workers.
+    pub running: Arc<AtomicUsize>,
+}
+
+impl WorkerNesting {
+    /// A top-level worker counting sub-workers in `running`.
+    pub fn root(running: Arc<AtomicUsize>) -> Self {
+        Self {
+            depth: 1,
+            parent: None,
+            running,
+        }
+    }
+
+    fn child(&self, parent: WorkerId) -> Self {
+        Self {
+            depth: self.depth + 1,
+            parent: Some(parent),
+            running: self.running.clone(),
+        }
+    }
+}
+
+impl Default for WorkerNesting {
+    fn default() -> Self {
+        Self::root(Arc::default())
+    }
+}
+
+/// Frees a sub-worker's slot in the channel's count, however it ends.
+struct RunningSlot(Arc<AtomicUsize>);
+
+impl Drop for RunningSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Spawns the sub-workers of one worker.
+pub struct SubWorkerSpawner {
+    deps: AgentDeps,
+    parent: WorkerId,
+    nesting: WorkerNesting,
+    browser_config: BrowserConfig,
+    screenshot_dir: PathBuf,
+    brave_search_key: Option<String>,
+    logs_dir: PathBuf,
+    handles: Arc<Mutex<Vec<tokio::task::AbortHandle>>>,
+}
+
+impl std::fmt::Debug for SubWorkerSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubWorkerSpawner")
+            .field("parent", &self.parent)
+            .field("depth", &self.nesting.depth)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stops a worker's sub-workers when dropped, including when the worker
+/// itself is cancelled.
+#[derive(Debug)]
+pub struct SubWorkerGuard(Arc<Mutex<Vec<tokio::task::AbortHandle>>>);
+
+impl Drop for SubWorkerGuard {
+    fn drop(&mut self) {
+        if let Ok(handles) = self.0.lock() {
+            for handle in handles.iter() {
+                handle.abort();
+            }
+        }
+    }
+}
+
+impl SubWorkerSpawner {
+    /// A spawner for the sub-workers of `worker`, or `None` when the worker
+    /// is already at the deepest nesting allowed.
+    pub fn for_worker(worker: &Worker) -> Option<(Arc<Self>, SubWorkerGuard)> {
+        let max_depth = worker.deps.runtime_config.sub_workers.load().max_depth;
+        if worker.nesting.depth >= max_depth {
+            return None;
+        }
+
+        let handles = Arc::new(Mutex::new(Vec::new()));
+        let spawner = Arc::new(Self {
+            deps: worker.deps.clone(),
+            parent: worker.id,
+            nesting: worker.nesting.child(worker.id),
+            browser_config: worker.browser_config.clone(),
+            screenshot_dir: worker.screenshot_dir.clone(),
+            brave_search_key: worker.brave_search_key.clone(),
+            logs_dir: worker.logs_dir.clone(),
+            handles: handles.clone(),
+        });
+        Some((spawner, SubWorkerGuard(handles)))
+    }
+
+    /// Spawn a sub-worker for `task`. Its result is delivered to the parent's
+    /// mailbox when it finishes.
+    pub fn spawn(&self, task: &str) -> std::result::Result<WorkerId, AgentError> {
+        let config = self.deps.runtime_config.sub_workers.load();
+        if self.nesting.depth > config.max_depth {
+            return Err(AgentError::WorkerDepthReached {
+                max: config.max_depth,
+            });
+        }
+        self.nesting
+            .running
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| {
+                (running < config.max_per_channel).then_some(running + 1)
+            })
+            .map_err(|_| AgentError::SubWorkerLimitReached {
+                max: config.max_per_channel,
+            })?;
+        let slot = RunningSlot(self.nesting.running.clone());
+
+        let rc = &self.deps.runtime_config;
+        let system_prompt = rc
+            .prompts
+            .load()
+            .render_worker_prompt(
+                &rc.instance_dir.display().to_string(),
+                &rc.workspace_dir.display().to_string(),
+            )
+            .map_err(|e| AgentError::Other(anyhow::anyhow!("{e}")))?;
+
+        let mut worker = Worker::new(
+            None,
+            task,
+            &system_prompt,
+            self.deps.clone(),
+            self.browser_config.clone(),
+            self.screenshot_dir.clone(),
+            self.brave_search_key.clone(),
+            self.logs_dir.clone(),
+        );
+        worker.nesting = self.nesting.clone();
+        let worker_id = worker.id;
+        // Sub-workers have their own per-channel cap, so only the agent-wide
+        // limit applies to them here.
+        let ticket = rc.worker_slots.request(
+            SlotRequest {
+                worker_id,
+                channel_id: None,
+                channel_limit: usize::MAX,
+                priority: TaskPriority::Normal,
+            },
+            &rc.worker_queue.load(),
+            &self.deps.agent_id,
+            &self.deps.event_tx,
+        )?;
+
+        let agent_id = self.deps.agent_id.clone();
+        let artifacts = ArtifactStore::new(
+            self.deps.sqlite_pool.clone(),
+            &self.deps.runtime_config.data_dir,
+        );
+        let parent = self.parent;
+        let worker_span = tracing::info_span!(
+            "worker.run",
+            worker_id = %worker_id,
+            parent_worker_id = %parent,
+            depth = self.nesting.depth,
+        );
+        let handle = tokio::spawn(
+            async move {
+                let _slot = slot;
+                let mut report = match worker_queue::run_with_slot(ticket, worker.run()).await {
+                    Ok(result) => format!("[Sub-worker completed]: {result}"),
+                    Err(error) => {
+                        tracing::warn!(%worker_id, %error, "sub-worker failed");
+                        format!("[Sub-worker failed]: {error}")
+                    }
+                };
+                match artifacts.reassign(worker_id, parent).await {
+                    Ok(artifacts) => {
+                        if let Some(listed) = artifact::format_for_history(&artifacts) {
+                            report.push_str("\n\n");
+                            report.push_str(&listed);
+                        }
+                    }
+                    Err(error) => {
+                        tracing::warn!(%worker_id, %error, "failed to hand over sub-worker artifacts");
+                    }
+                }
+                if let Err(error) = mailbox::deliver(&agent_id, worker_id, parent, &report) {
+                    tracing::warn!(%worker_id, %parent, %error, "can't deliver sub-worker result");
+                }
+            }
+            .instrument(worker_span),
+        );
+
+        if let Ok(mut handles) = self.handles.lock() {
+            handles.retain(|handle| !handle.is_finished());
+            handles.push(handle.abort_handle());
+        }
+
+        tracing::info!(%worker_id, %parent, depth = self.nesting.depth, "sub-worker spawned");
+        Ok(worker_id)
+    }
+}
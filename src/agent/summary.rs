@@ -0,0 +1,152 @@
+//! Rolling conversation summaries: the gist of a whole conversation, kept in
+//! the channel's system prompt.
+//!
+//! Compaction summaries only cover the messages they replaced, and after a
+//! few aggressive compactions or an emergency truncation little is left of
+//! how the conversation started. So every [`SUMMARY_EVERY_TURNS`] turns the
+//! channel asks the compactor model to rewrite its summary from the previous
+//! one and the latest logged messages. The summary is stored on the
+//! channel's row, so it survives restarts, and announced with a
+//! `ChannelSummarized` event.
+
+use crate::agent::channel::ChannelState;
+use crate::conversation::history::ConversationMessage;
+use crate::llm::SpacebotModel;
+use crate::{ProcessEvent, ProcessType};
+
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+use tracing::Instrument as _;
+
+/// User turns between two updates of the summary.
+pub const SUMMARY_EVERY_TURNS: usize = 10;
+
+/// Longest summary, in characters. Longer ones are cut at a line or word.
+pub const MAX_SUMMARY_CHARS: usize = 2000;
+
+/// Latest messages the summary is updated from. More than a round of turns
+/// brings in, so nothing falls between two updates.
+const TRANSCRIPT_MESSAGES: i64 = 40;
+
+/// Characters kept of each message in the transcript.
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// Rewrite the channel's summary in the background from `previous` and the
+/// latest messages.
+pub fn spawn_summary_update(state: &ChannelState, previous: Option<String>) {
+    let state = state.clone();
+    tokio::spawn(
+        async move {
+            let channel_id = &state.channel_id;
+            let summary = match generate(&state, previous.as_deref()).await {
+                Ok(Some(summary)) => summary,
+                Ok(None) => return,
+                Err(error) => {
+                    tracing::warn!(%channel_id, %error, "failed to update conversation summary");
+                    return;
+                }
+            };
+            if let Err(error) = state.channel_store.set_summary(channel_id, &summary).await {
+                tracing::warn!(%channel_id, %error, "failed to save conversation summary");
+            }
+            tracing::info!(%channel_id, chars = summary.len(), "conversation summary updated");
+            state
+                .deps
+                .event_tx
+                .send(ProcessEvent::ChannelSummarized {
+                    agent_id: state.deps.agent_id.clone(),
+                    channel_id: channel_id.clone(),
+                    summary,
+                })
+                .ok();
+        }
+        .in_current_span(),
+    );
+}
+
+/// Ask the compactor model for the updated summary. `None` when there are no
+/// messages yet or the model gave nothing usable.
+async fn generate(state: &ChannelState, previous: Option<&str>) -> anyhow::Result<Option<String>> {
+    let messages = state
+        .conversation_logger
+        .load_recent(&state.channel_id, TRANSCRIPT_MESSAGES)
+        .await?;
+    if messages.is_empty() {
+        return Ok(None);
+    }
+
+    let deps = &state.deps;
+    let prompt_engine = deps.runtime_config.prompts.load();
+    let preamble = prompt_engine.render_static("channel_summary")?;
+    let prompt =
+        prompt_engine.render_system_channel_summary(previous, &render_transcript(&messages))?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = routing.resolve(ProcessType::Compactor, None).to_string();
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_context(&*deps.agent_id, "compactor")
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+
+    Ok(clean_summary(&agent.prompt(&prompt).await?))
+}
+
+fn render_transcript(messages: &[ConversationMessage]) -> String {
+    let mut transcript = String::new();
+    for message in messages {
+        let speaker = match message.role.as_str() {
+            "assistant" => "Assistant",
+            _ => message.sender_name.as_deref().unwrap_or("User"),
+        };
+        let content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+        transcript.push_str(&format!("{speaker}: {}\n", content.trim()));
+    }
+    transcript
+}
+
+/// The summary in a model's answer, without a `Summary:` heading, cut to
+/// [`MAX_SUMMARY_CHARS`] at the last whole line, or word if there's none.
+pub fn clean_summary(raw: &str) -> Option<String> {
+    let summary = raw.trim();
+    let summary = summary
+        .strip_prefix("Summary:")
+        .or_else(|| summary.strip_prefix("## Summary"))
+        .unwrap_or(summary)
+        .trim();
+    if summary.is_empty() {
+        return None;
+    }
+    if summary.chars().count() <= MAX_SUMMARY_CHARS {
+        return Some(summary.to_string());
+    }
+
+    let cut: String = summary.chars().take(MAX_SUMMARY_CHARS).collect();
+    let cut = match cut.rfind('\n').or_else(|| cut.rfind(' ')) {
+        Some(end) if end > 0 => &cut[..end],
+        _ => &cut,
+    };
+    Some(cut.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_answers_are_cleaned_into_summaries() {
+        assert_eq!(
+            clean_summary("Summary:\nAlice is planning the March offsite.\n").as_deref(),
+            Some("Alice is planning the March offsite.")
+        );
+        assert_eq!(clean_summary("  \n "), None);
+
+        let long = format!("- First point.\n{}", "word ".repeat(500));
+        let cut = clean_summary(&long).unwrap();
+        assert!(cut.chars().count() <= MAX_SUMMARY_CHARS);
+        assert_eq!(cut, "- First point.");
+
+        let one_line = clean_summary(&"word ".repeat(500)).unwrap();
+        assert!(one_line.chars().count() <= MAX_SUMMARY_CHARS);
+        assert!(one_line.ends_with("word"));
+    }
+}
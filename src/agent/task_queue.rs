@@ -0,0 +1,760 @@
+//! Task queue: work the cortex runs in detached workers, kept in SQLite so
+//! nothing queued is lost to a restart.
+//!
+//! Tasks come from four places: channels defer them with the `defer_task`
+//! tool, the API adds them, and `[[agents.tasks]]` schedules and
+//! `[[agents.triggers]]` queue a run each time they fire. The queue loop starts the most urgent pending task,
+//! oldest first, whenever fewer than `cortex.queue_concurrency` are running.
+//! When its worker finishes, the result goes back to the conversation that
+//! deferred the task, or to the task's `report_to`. Tasks a restart
+//! interrupted are queued again, up to [`MAX_ATTEMPTS`] runs. Starts and
+//! finishes are logged as [`QUEUE_EVENT`] cortex events.
+
+use crate::agent::artifact::Artifact;
+use crate::agent::channel::spawn_detached_worker;
+use crate::agent::cortex::CortexLogger;
+use crate::agent::worker::WORKER_FAILED_PREFIX;
+use crate::error::{AgentError, Result};
+use crate::events::{EventFilter, EventSubscription};
+use crate::messaging::target::{BroadcastTarget, parse_delivery_target};
+use crate::{
+    AgentDeps, InboundMessage, MessageContent, OutboundFile, OutboundResponse, ProcessEvent,
+    ProcessType, WorkerId,
+};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Cortex event type logged when a queued task starts or finishes.
+pub const QUEUE_EVENT: &str = "queued_task";
+
+/// Runs a task gets before it's given up on, counting runs cut short by a
+/// restart.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// How often the loop looks for tasks to start.
+const TICK_SECS: u64 = 5;
+
+/// How urgent a task is. More urgent tasks start first.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl TaskPriority {
+    fn as_i64(self) -> i64 {
+        self as i64
+    }
+
+    fn from_i64(value: i64) -> Self {
+        match value {
+            i64::MIN..=0 => Self::Low,
+            1 => Self::Normal,
+            2 => Self::High,
+            _ => Self::Urgent,
+        }
+    }
+}
+
+/// Where a task is in its life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "running" => Some(Self::Running),
+            "done" => Some(Self::Done),
+            "failed" => Some(Self::Failed),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// What queued a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOrigin {
+    /// A channel's `defer_task` call.
+    Channel,
+    Api,
+    /// An `[[agents.tasks]]` schedule.
+    Schedule,
+    /// An `[[agents.triggers]]` trigger.
+    Trigger,
+}
+
+impl TaskOrigin {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Channel => "channel",
+            Self::Api => "api",
+            Self::Schedule => "schedule",
+            Self::Trigger => "trigger",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "channel" => Some(Self::Channel),
+            "api" => Some(Self::Api),
+            "schedule" => Some(Self::Schedule),
+            "trigger" => Some(Self::Trigger),
+            _ => None,
+        }
+    }
+}
+
+/// A task in the queue.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedTask {
+    pub id: String,
+    /// What the worker is asked to do.
+    pub task: String,
+    pub priority: TaskPriority,
+    pub status: TaskStatus,
+    pub origin: TaskOrigin,
+    /// The channel that deferred the task, or the ID of the schedule or
+    /// trigger that queued it.
+    pub origin_id: Option<String>,
+    /// Where the result is posted, as `adapter:target`.
+    pub report_to: Option<String>,
+    /// The message a channel deferred the task from.
+    #[serde(skip)]
+    pub source: Option<InboundMessage>,
+    pub worker_id: Option<String>,
+    /// The worker's result, once it finished.
+    pub result: Option<String>,
+    pub attempts: u32,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A task to add to the queue.
+#[derive(Debug, Clone)]
+pub struct NewTask {
+    pub task: String,
+    pub priority: TaskPriority,
+    pub origin: TaskOrigin,
+    pub origin_id: Option<String>,
+    pub report_to: Option<BroadcastTarget>,
+    pub source: Option<InboundMessage>,
+}
+
+impl NewTask {
+    pub fn new(task: impl Into<String>, origin: TaskOrigin) -> Self {
+        Self {
+            task: task.into(),
+            priority: TaskPriority::default(),
+            origin,
+            origin_id: None,
+            report_to: None,
+            source: None,
+        }
+    }
+}
+
+/// Task queue storage (SQLite).
+#[derive(Debug, Clone)]
+pub struct TaskQueue {
+    pool: SqlitePool,
+}
+
+const COLUMNS: &str = "id, task, priority, status, origin, origin_id, report_to, source, \
+                       worker_id, result, attempts, created_at, started_at, finished_at";
+
+impl TaskQueue {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Add a pending task.
+    pub async fn enqueue(&self, new: NewTask) -> Result<QueuedTask> {
+        let task = QueuedTask {
+            id: uuid::Uuid::new_v4().to_string(),
+            task: new.task,
+            priority: new.priority,
+            status: TaskStatus::Pending,
+            origin: new.origin,
+            origin_id: new.origin_id,
+            report_to: new.report_to.map(|target| target.to_string()),
+            source: new.source,
+            worker_id: None,
+            result: None,
+            attempts: 0,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        };
+        let source = task
+            .source
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .context("failed to encode task source")?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO task_queue (id, task, priority, status, origin, origin_id, report_to, source, created_at)
+            VALUES (?, ?, ?, 'pending', ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&task.id)
+        .bind(&task.task)
+        .bind(task.priority.as_i64())
+        .bind(task.origin.as_str())
+        .bind(&task.origin_id)
+        .bind(&task.report_to)
+        .bind(source)
+        .bind(task.created_at.timestamp())
+        .execute(&self.pool)
+        .await
+        .context("failed to queue task")?;
+
+        Ok(task)
+    }
+
+    /// Tasks with `status`, or all of them, newest first.
+    pub async fn list(&self, status: Option<TaskStatus>, limit: i64) -> Result<Vec<QueuedTask>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {COLUMNS} FROM task_queue \
+             WHERE ? IS NULL OR status = ? \
+             ORDER BY created_at DESC LIMIT ?"
+        ))
+        .bind(status.map(TaskStatus::as_str))
+        .bind(status.map(TaskStatus::as_str))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to list queued tasks")?;
+
+        let tasks = rows
+            .iter()
+            .map(task_from_row)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(tasks)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<QueuedTask>> {
+        let row = sqlx::query(&format!("SELECT {COLUMNS} FROM task_queue WHERE id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to load queued task")?;
+
+        Ok(row.as_ref().map(task_from_row).transpose()?)
+    }
+
+    /// Whether a task from `origin` and `origin_id` is pending or running.
+    pub async fn has_open(&self, origin: TaskOrigin, origin_id: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM task_queue \
+             WHERE origin = ? AND origin_id = ? AND status IN ('pending', 'running')",
+        )
+        .bind(origin.as_str())
+        .bind(origin_id)
+        .fetch_one(&self.pool)
+        .await
+        .context("failed to check queued tasks")?;
+
+        Ok(count > 0)
+    }
+
+    /// Mark the most urgent pending task, oldest first, as running.
+    pub async fn claim_next(&self) -> Result<Option<QueuedTask>> {
+        let row = sqlx::query(&format!(
+            "UPDATE task_queue SET status = 'running', attempts = attempts + 1, started_at = ? \
+             WHERE id = (SELECT id FROM task_queue WHERE status = 'pending' \
+                         ORDER BY priority DESC, created_at ASC LIMIT 1) \
+             RETURNING {COLUMNS}"
+        ))
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .context("failed to claim queued task")?;
+
+        Ok(row.as_ref().map(task_from_row).transpose()?)
+    }
+
+    /// Record the worker running a task.
+    pub async fn set_worker(&self, id: &str, worker_id: WorkerId) -> Result<()> {
+        sqlx::query("UPDATE task_queue SET worker_id = ? WHERE id = ?")
+            .bind(worker_id.to_string())
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("failed to update queued task")?;
+
+        Ok(())
+    }
+
+    /// Mark a running task done or failed with its result.
+    pub async fn finish(&self, id: &str, status: TaskStatus, result: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE task_queue SET status = ?, result = ?, finished_at = ? \
+             WHERE id = ? AND status = 'running'",
+        )
+        .bind(status.as_str())
+        .bind(result)
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .context("failed to update queued task")?;
+
+        Ok(())
+    }
+
+    /// Cancel a pending task. Returns false when it isn't pending.
+    pub async fn cancel(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE task_queue SET status = 'cancelled', finished_at = ? \
+             WHERE id = ? AND status = 'pending'",
+        )
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .context("failed to cancel queued task")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Put a claimed task back as pending without counting the attempt, for
+    /// when its worker couldn't be started yet.
+    pub async fn unclaim(&self, id: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE task_queue SET status = 'pending', attempts = MAX(attempts - 1, 0),              started_at = NULL WHERE id = ? AND status = 'running'",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .context("failed to requeue queued task")?;
+
+        Ok(())
+    }
+
+    /// Queue the tasks left running by the last shutdown again, failing
+    /// those that used up their attempts. Returns how many were queued.
+    pub async fn requeue_interrupted(&self) -> Result<u64> {
+        sqlx::query(
+            "UPDATE task_queue SET status = 'failed', finished_at = ?, \
+             result = 'Interrupted by a restart too many times' \
+             WHERE status = 'running' AND attempts >= ?",
+        )
+        .bind(Utc::now().timestamp())
+        .bind(MAX_ATTEMPTS as i64)
+        .execute(&self.pool)
+        .await
+        .context("failed to fail interrupted tasks")?;
+
+        let result = sqlx::query(
+            "UPDATE task_queue SET status = 'pending', worker_id = NULL, started_at = NULL \
+             WHERE status = 'running'",
+        )
+        .execute(&self.pool)
+        .await
+        .context("failed to queue interrupted tasks")?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn task_from_row(row: &sqlx::sqlite::SqliteRow) -> anyhow::Result<QueuedTask> {
+    let timestamp = |column: &str| -> anyhow::Result<Option<DateTime<Utc>>> {
+        row.try_get::<Option<i64>, _>(column)?
+            .map(|secs| {
+                DateTime::from_timestamp(secs, 0)
+                    .with_context(|| format!("queued task has an invalid {column}: {secs}"))
+            })
+            .transpose()
+    };
+    let id: String = row.try_get("id")?;
+    let status: String = row.try_get("status")?;
+    let origin: String = row.try_get("origin")?;
+    let source = row
+        .try_get::<Option<String>, _>("source")?
+        .map(|source| serde_json::from_str(&source))
+        .transpose()
+        .with_context(|| format!("queued task {id} has an unreadable source message"))?;
+
+    Ok(QueuedTask {
+        task: row.try_get("task")?,
+        priority: TaskPriority::from_i64(row.try_get("priority")?),
+        status: TaskStatus::parse(&status)
+            .with_context(|| format!("queued task {id} has an unknown status '{status}'"))?,
+        origin: TaskOrigin::parse(&origin)
+            .with_context(|| format!("queued task {id} has an unknown origin '{origin}'"))?,
+        origin_id: row.try_get("origin_id")?,
+        report_to: row.try_get("report_to")?,
+        source,
+        worker_id: row.try_get("worker_id")?,
+        result: row.try_get("result")?,
+        attempts: row.try_get::<i64, _>("attempts")? as u32,
+        created_at: timestamp("created_at")?
+            .with_context(|| format!("queued task {id} has no created_at"))?,
+        started_at: timestamp("started_at")?,
+        finished_at: timestamp("finished_at")?,
+        id,
+    })
+}
+
+/// Run the agent's queued tasks until its event bus closes.
+pub fn spawn_task_queue_loop(
+    deps: AgentDeps,
+    logger: CortexLogger,
+    screenshot_dir: PathBuf,
+    logs_dir: PathBuf,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let agent_id = deps.agent_id.clone();
+        let queue = TaskQueue::new(deps.sqlite_pool.clone());
+        match queue.requeue_interrupted().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(%agent_id, count, "queued tasks interrupted by restart"),
+            Err(error) => tracing::warn!(%agent_id, %error, "failed to requeue interrupted tasks"),
+        }
+
+        let mut events = EventSubscription::new(
+            agent_id.clone(),
+            "task_queue",
+            deps.event_tx.subscribe(),
+            EventFilter::all().agent(agent_id),
+        );
+        let mut tick = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        // Queued tasks being run, by worker.
+        let mut running: HashMap<WorkerId, QueuedTask> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    let concurrency = deps.runtime_config.cortex.load().queue_concurrency as usize;
+                    while running.len() < concurrency
+                        && deps.runtime_config.circuit_breakers.check(ProcessType::Worker).is_ok()
+                    {
+                        match start_next(&deps, &logger, &queue, &screenshot_dir, &logs_dir).await {
+                            Some((worker_id, task)) => {
+                                running.insert(worker_id, task);
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    if let ProcessEvent::WorkerComplete { worker_id, result, artifacts, .. } = event
+                        && let Some(task) = running.remove(&worker_id)
+                    {
+                        let deps = deps.clone();
+                        let logger = logger.clone();
+                        let queue = queue.clone();
+                        tokio::spawn(async move {
+                            finish_task(&deps, &logger, &queue, task, &result, &artifacts).await;
+                        });
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Start the next pending task. `None` when there's none, or it couldn't be
+/// started.
+async fn start_next(
+    deps: &AgentDeps,
+    logger: &CortexLogger,
+    queue: &TaskQueue,
+    screenshot_dir: &std::path::Path,
+    logs_dir: &std::path::Path,
+) -> Option<(WorkerId, QueuedTask)> {
+    let task = match queue.claim_next().await {
+        Ok(task) => task?,
+        Err(error) => {
+            tracing::warn!(%error, "failed to claim queued task");
+            return None;
+        }
+    };
+
+    match spawn_detached_worker(
+        deps,
+        task.task.clone(),
+        task.priority,
+        screenshot_dir.to_path_buf(),
+        logs_dir.to_path_buf(),
+    ) {
+        Ok(worker_id) => {
+            tracing::info!(task_id = %task.id, %worker_id, "queued task started");
+            if let Err(error) = queue.set_worker(&task.id, worker_id).await {
+                tracing::warn!(task_id = %task.id, %error, "failed to record worker of queued task");
+            }
+            logger.log(
+                QUEUE_EVENT,
+                &format!("Queued task started: {}", headline(&task.task)),
+                Some(serde_json::json!({
+                    "task_id": task.id,
+                    "worker_id": worker_id.to_string(),
+                    "origin": task.origin,
+                    "priority": task.priority,
+                    "attempt": task.attempts,
+                })),
+            );
+            Some((worker_id, task))
+        }
+        Err(AgentError::WorkerQueueFull { max_queued }) => {
+            // Not the task's fault: leave it for a later tick.
+            tracing::info!(task_id = %task.id, max_queued, "worker queue full, queued task put back");
+            if let Err(error) = queue.unclaim(&task.id).await {
+                tracing::warn!(task_id = %task.id, %error, "failed to put back queued task");
+            }
+            None
+        }
+        Err(AgentError::ShuttingDown) => {
+            // Left running, so the next start queues it again.
+            tracing::info!(task_id = %task.id, "shutting down, queued task left for the next start");
+            None
+        }
+        Err(error) => {
+            tracing::warn!(task_id = %task.id, %error, "failed to start queued task");
+            let result = format!("{WORKER_FAILED_PREFIX}{error}");
+            if let Err(error) = queue.finish(&task.id, TaskStatus::Failed, &result).await {
+                tracing::warn!(task_id = %task.id, %error, "failed to update queued task");
+            }
+            logger.log(
+                QUEUE_EVENT,
+                &format!("Queued task failed to start: {error}"),
+                Some(serde_json::json!({ "task_id": task.id, "error": error.to_string() })),
+            );
+            None
+        }
+    }
+}
+
+/// Store a finished task's result and report it.
+async fn finish_task(
+    deps: &AgentDeps,
+    logger: &CortexLogger,
+    queue: &TaskQueue,
+    task: QueuedTask,
+    result: &str,
+    artifacts: &[Artifact],
+) {
+    let failed = result.starts_with(WORKER_FAILED_PREFIX);
+    let status = if failed {
+        TaskStatus::Failed
+    } else {
+        TaskStatus::Done
+    };
+    if let Err(error) = queue.finish(&task.id, status, result).await {
+        tracing::warn!(task_id = %task.id, %error, "failed to store result of queued task");
+    }
+    logger.log(
+        QUEUE_EVENT,
+        &format!(
+            "Queued task {}: {}",
+            if failed { "failed" } else { "finished" },
+            headline(&task.task)
+        ),
+        Some(serde_json::json!({
+            "task_id": task.id,
+            "origin": task.origin,
+            "origin_id": task.origin_id,
+            "result": result,
+        })),
+    );
+
+    let Some(messaging_manager) = &deps.messaging_manager else {
+        return;
+    };
+
+    // A deferred task's result goes back to its conversation, where the
+    // channel picks it up with the context it was deferred in.
+    if let Some(source) = task.source.clone() {
+        let text = match deps
+            .runtime_config
+            .prompts
+            .load()
+            .render_system_deferred_task(
+                &task.task,
+                result,
+                failed,
+                &task.created_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            ) {
+            Ok(text) => text,
+            Err(error) => {
+                tracing::warn!(%error, "failed to render deferred task result");
+                return;
+            }
+        };
+        let message = InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            agent_id: Some(deps.agent_id.clone()),
+            content: MessageContent::Text(text),
+            timestamp: Utc::now(),
+            ..source
+        };
+        if let Err(error) = messaging_manager.inject_message(message).await {
+            tracing::warn!(task_id = %task.id, %error, "failed to deliver deferred task result");
+        }
+        return;
+    }
+
+    let Some(target) = task.report_to.as_deref().and_then(parse_delivery_target) else {
+        return;
+    };
+    let title = match (task.origin, &task.origin_id) {
+        (TaskOrigin::Schedule, Some(task_id)) => format!("Scheduled task '{task_id}'"),
+        (TaskOrigin::Trigger, Some(trigger_id)) => format!("Trigger '{trigger_id}'"),
+        _ => format!("Task '{}'", headline(&task.task)),
+    };
+    let text = format!("{title}:\n\n{result}");
+    let mut files = Vec::new();
+    for artifact in artifacts {
+        match tokio::fs::read(&artifact.path).await {
+            Ok(data) => files.push(OutboundFile {
+                filename: artifact.name.clone(),
+                data,
+                mime_type: artifact.mime_type.clone(),
+            }),
+            Err(error) => {
+                tracing::warn!(task_id = %task.id, artifact_id = %artifact.id, %error, "can't read artifact of queued task");
+            }
+        }
+    }
+    let response = if files.is_empty() {
+        OutboundResponse::Text(text)
+    } else {
+        OutboundResponse::Attachments {
+            text: Some(text),
+            files,
+        }
+    };
+
+    match messaging_manager
+        .broadcast(&target.adapter, &target.target, response)
+        .await
+    {
+        Ok(()) => tracing::info!(task_id = %task.id, %target, "queued task result delivered"),
+        Err(error) => {
+            tracing::error!(task_id = %task.id, %target, %error, "failed to deliver queued task result");
+        }
+    }
+}
+
+/// The first line of a task, cut to 80 characters.
+fn headline(task: &str) -> &str {
+    let line = task.lines().next().unwrap_or_default().trim();
+    &line[..line.floor_char_boundary(80)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tasks_run_by_priority_and_survive_restarts() {
+        let pool = crate::db::test_pool().await;
+        let queue = TaskQueue::new(pool);
+
+        let low = NewTask {
+            priority: TaskPriority::Low,
+            ..NewTask::new("tidy the notes", TaskOrigin::Api)
+        };
+        let urgent = NewTask {
+            priority: TaskPriority::Urgent,
+            ..NewTask::new("check the outage", TaskOrigin::Api)
+        };
+        let low = queue.enqueue(low).await.unwrap();
+        queue.enqueue(urgent).await.unwrap();
+
+        let first = queue.claim_next().await.unwrap().unwrap();
+        assert_eq!(first.task, "check the outage");
+        assert_eq!(first.status, TaskStatus::Running);
+        assert_eq!(first.attempts, 1);
+
+        // A restart puts the running task back in line, still first.
+        assert_eq!(queue.requeue_interrupted().await.unwrap(), 1);
+        let again = queue.claim_next().await.unwrap().unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.attempts, 2);
+        queue
+            .finish(&again.id, TaskStatus::Done, "all good")
+            .await
+            .unwrap();
+
+        assert!(queue.cancel(&low.id).await.unwrap());
+        assert!(!queue.cancel(&low.id).await.unwrap());
+        assert!(queue.claim_next().await.unwrap().is_none());
+
+        let done = queue.list(Some(TaskStatus::Done), 10).await.unwrap();
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].result.as_deref(), Some("all good"));
+    }
+
+    #[tokio::test]
+    async fn unclaimed_tasks_go_back_without_using_an_attempt() {
+        let pool = crate::db::test_pool().await;
+        let queue = TaskQueue::new(pool);
+        let queued = queue
+            .enqueue(NewTask::new("summarize the week", TaskOrigin::Api))
+            .await
+            .unwrap();
+
+        let claimed = queue.claim_next().await.unwrap().unwrap();
+        queue.unclaim(&claimed.id).await.unwrap();
+        let task = queue.get(&queued.id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.attempts, 0);
+        assert!(task.started_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn corrupt_rows_are_errors() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO task_queue (id, task, priority, status, origin, created_at) \
+             VALUES ('broken', 'do it', 1, 'paused', 'api', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let queue = TaskQueue::new(pool);
+
+        assert!(queue.get("broken").await.is_err());
+        assert!(queue.list(None, 10).await.is_err());
+    }
+}
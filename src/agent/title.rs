@@ -0,0 +1,184 @@
+//! Channel titles: a few words naming what a conversation is about, so the
+//! dashboard can tell conversations apart.
+//!
+//! Once a channel has had [`TITLE_AFTER_TURNS`] turns without a title, it
+//! asks the compactor model for one over the conversation so far. The
+//! title is stored on the channel's row and announced with a
+//! `ChannelTitled` event. A title set through the API is never replaced by
+//! a generated one.
+
+use crate::agent::channel::ChannelState;
+use crate::conversation::channels::ChannelInfo;
+use crate::conversation::history::ConversationMessage;
+use crate::llm::SpacebotModel;
+use crate::{ProcessEvent, ProcessType};
+
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+use tracing::Instrument as _;
+
+/// User turns before a channel gets a title.
+pub const TITLE_AFTER_TURNS: usize = 3;
+
+/// Longest title, in characters. Longer generated titles are cut at a word.
+pub const MAX_TITLE_CHARS: usize = 80;
+
+/// Latest messages the title is based on.
+const TRANSCRIPT_MESSAGES: i64 = 12;
+
+/// Characters kept of each message in the transcript.
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// Generate a title for the channel in the background, unless it already
+/// has one.
+pub fn spawn_title_generation(state: &ChannelState) {
+    let state = state.clone();
+    tokio::spawn(
+        async move {
+            if let Some(title) = title(&state).await {
+                state
+                    .deps
+                    .event_tx
+                    .send(ProcessEvent::ChannelTitled {
+                        agent_id: state.deps.agent_id.clone(),
+                        channel_id: state.channel_id.clone(),
+                        title,
+                    })
+                    .ok();
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// The channel's stored title, or a newly generated one.
+async fn title(state: &ChannelState) -> Option<String> {
+    let channel_id = &state.channel_id;
+    match state.channel_store.get(channel_id).await {
+        Ok(Some(ChannelInfo {
+            title: Some(title), ..
+        })) => return Some(title),
+        Ok(_) => {}
+        Err(error) => {
+            tracing::warn!(%channel_id, %error, "failed to load channel title");
+            return None;
+        }
+    }
+
+    let title = match generate(state).await {
+        Ok(title) => title?,
+        Err(error) => {
+            tracing::warn!(%channel_id, %error, "failed to generate channel title");
+            return None;
+        }
+    };
+    match state
+        .channel_store
+        .set_title_if_unset(channel_id, &title)
+        .await
+    {
+        Ok(true) => {
+            tracing::info!(%channel_id, %title, "channel titled");
+            Some(title)
+        }
+        // Renamed while the title was generated.
+        Ok(false) => state.channel_store.get(channel_id).await.ok()??.title,
+        Err(error) => {
+            tracing::warn!(%channel_id, %error, "failed to save channel title");
+            None
+        }
+    }
+}
+
+/// Ask the compactor model for a title. `None` when the conversation has no
+/// messages yet or the model gave nothing usable.
+async fn generate(state: &ChannelState) -> anyhow::Result<Option<String>> {
+    let messages = state
+        .conversation_logger
+        .load_recent(&state.channel_id, TRANSCRIPT_MESSAGES)
+        .await?;
+    if messages.is_empty() {
+        return Ok(None);
+    }
+
+    let deps = &state.deps;
+    let prompt_engine = deps.runtime_config.prompts.load();
+    let preamble = prompt_engine.render_static("channel_title")?;
+    let prompt = prompt_engine.render_system_channel_title(&render_transcript(&messages))?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = routing.resolve(ProcessType::Compactor, None).to_string();
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_context(&*deps.agent_id, "compactor")
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+
+    Ok(clean_title(&agent.prompt(&prompt).await?))
+}
+
+fn render_transcript(messages: &[ConversationMessage]) -> String {
+    let mut transcript = String::new();
+    for message in messages {
+        let speaker = match message.role.as_str() {
+            "assistant" => "Assistant",
+            _ => message.sender_name.as_deref().unwrap_or("User"),
+        };
+        let content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+        transcript.push_str(&format!("{speaker}: {}\n", content.trim()));
+    }
+    transcript
+}
+
+/// The title in a model's answer: its first line, without a `Title:`
+/// label, quotes or a trailing period, cut to [`MAX_TITLE_CHARS`].
+pub fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | '*' | '#'))
+        .trim_end_matches('.')
+        .trim();
+    if title.is_empty() {
+        return None;
+    }
+    if title.chars().count() <= MAX_TITLE_CHARS {
+        return Some(title.to_string());
+    }
+
+    let cut: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => &cut,
+    };
+    Some(cut.trim_end_matches([',', ';', ':', '-', ' ']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_answers_are_cleaned_into_titles() {
+        assert_eq!(
+            clean_title("Postgres backup failures").as_deref(),
+            Some("Postgres backup failures")
+        );
+        assert_eq!(
+            clean_title("\nTitle: \"Planning the March offsite.\"\n\nIt covers...").as_deref(),
+            Some("Planning the March offsite")
+        );
+        assert_eq!(
+            clean_title("**Rust lifetimes**").as_deref(),
+            Some("Rust lifetimes")
+        );
+        assert_eq!(clean_title("  \n \"\" "), None);
+
+        let long = clean_title(&"word ".repeat(40)).unwrap();
+        assert!(long.chars().count() <= MAX_TITLE_CHARS);
+        assert!(long.ends_with("word"));
+    }
+}
@@ -0,0 +1,381 @@
+//! Triggers: external signals the cortex wakes up for.
+//!
+//! Triggers come from `[[agents.triggers]]`. Each one has a source:
+//!
+//! - `watch`: a file or directory in the workspace changing. Changes are
+//!   collected until the path has been quiet for [`DEBOUNCE`], so a file
+//!   written in pieces fires once.
+//! - `webhook_token`: a call to `POST /api/triggers/{agent_id}/{trigger_id}`
+//!   with the token. The API checks the token and hands the call to the
+//!   trigger loop through the agent's [`TriggerHub`].
+//! - `every_secs`: a timer.
+//!
+//! When a trigger fires, the cortex messages its `notify` conversation and
+//! queues its `task` (see [`crate::agent::task_queue`]), with what fired it
+//! appended. A trigger fires at most once per `cooldown_secs`. Firings are
+//! logged as [`TRIGGER_EVENT`] cortex events. Triggers are reread from the
+//! config on every tick, so editing them doesn't need a restart.
+
+use crate::agent::cortex::CortexLogger;
+use crate::agent::task_queue::{NewTask, TaskOrigin, TaskQueue};
+use crate::config::{TriggerDef, TriggerSource};
+use crate::{AgentDeps, OutboundResponse};
+
+use notify::{RecursiveMode, Watcher as _};
+use tokio::sync::{broadcast, mpsc};
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Cortex event type logged when a trigger fires.
+pub const TRIGGER_EVENT: &str = "trigger_fired";
+
+/// Quiet time after a change before a watch trigger fires.
+pub const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// How often the loop checks timers and settled changes.
+const TICK: Duration = Duration::from_secs(1);
+
+/// Most bytes of a webhook body passed on to the task or message. The body
+/// is cut at the last character boundary below this.
+const MAX_PAYLOAD_BYTES: usize = 4000;
+
+/// Paths listed in a watch trigger's description.
+const MAX_LISTED_PATHS: usize = 10;
+
+/// A webhook call for one of the agent's triggers.
+#[derive(Debug, Clone)]
+pub struct WebhookCall {
+    pub trigger_id: String,
+    /// The request body.
+    pub payload: String,
+}
+
+/// Hands webhook calls from the API to the agent's trigger loop.
+#[derive(Debug)]
+pub struct TriggerHub {
+    tx: broadcast::Sender<WebhookCall>,
+}
+
+impl Default for TriggerHub {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self { tx }
+    }
+}
+
+impl TriggerHub {
+    /// Pass a call on to the trigger loop. Returns false when no loop is
+    /// running to take it.
+    pub fn fire_webhook(&self, call: WebhookCall) -> bool {
+        self.tx.send(call).is_ok()
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<WebhookCall> {
+        self.tx.subscribe()
+    }
+}
+
+/// Watch the agent's triggers and fire them.
+pub fn spawn_trigger_loop(deps: AgentDeps, logger: CortexLogger) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let queue = TaskQueue::new(deps.sqlite_pool.clone());
+        let mut webhooks = deps.runtime_config.trigger_hub.subscribe();
+        let (change_tx, mut changes) = mpsc::unbounded_channel::<PathBuf>();
+        let mut watcher = WorkspaceWatcher::new(change_tx);
+        let mut tick = tokio::time::interval(TICK);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        let started = Instant::now();
+        let mut last_fired: HashMap<String, Instant> = HashMap::new();
+        // Changed paths of each watch trigger, with when the last one came.
+        let mut pending: HashMap<String, (BTreeSet<PathBuf>, Instant)> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    let triggers = deps.runtime_config.triggers.load();
+                    let workspace = &deps.runtime_config.workspace_dir;
+                    watcher.update(workspace, &triggers);
+
+                    let now = Instant::now();
+                    for trigger in triggers.iter().filter(|trigger| trigger.enabled) {
+                        let detail = match &trigger.source {
+                            TriggerSource::Timer { every_secs } => {
+                                let since = last_fired.get(&trigger.id).copied().unwrap_or(started);
+                                if now.duration_since(since) < Duration::from_secs(*every_secs) {
+                                    continue;
+                                }
+                                format!(
+                                    "Timer: it's {}.",
+                                    chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")
+                                )
+                            }
+                            TriggerSource::Watch(_) => {
+                                let Some(paths) = take_settled(&mut pending, &trigger.id, now) else {
+                                    continue;
+                                };
+                                describe_changes(workspace, &paths)
+                            }
+                            TriggerSource::Webhook { .. } => continue,
+                        };
+                        fire(&deps, &logger, &queue, trigger, &detail, &mut last_fired).await;
+                    }
+                }
+                Some(path) = changes.recv() => {
+                    let triggers = deps.runtime_config.triggers.load();
+                    let workspace = &deps.runtime_config.workspace_dir;
+                    for trigger in triggers.iter().filter(|trigger| trigger.enabled) {
+                        if let TriggerSource::Watch(watched) = &trigger.source
+                            && path.starts_with(workspace.join(watched))
+                        {
+                            let entry = pending
+                                .entry(trigger.id.clone())
+                                .or_insert_with(|| (BTreeSet::new(), Instant::now()));
+                            entry.0.insert(path.clone());
+                            entry.1 = Instant::now();
+                        }
+                    }
+                }
+                call = webhooks.recv() => {
+                    let call = match call {
+                        Ok(call) => call,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!(agent_id = %deps.agent_id, skipped, "trigger loop dropped webhook calls");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let triggers = deps.runtime_config.triggers.load();
+                    let Some(trigger) = triggers
+                        .iter()
+                        .find(|trigger| trigger.enabled && trigger.id == call.trigger_id)
+                    else {
+                        continue;
+                    };
+                    let detail = describe_payload(&call.payload);
+                    fire(&deps, &logger, &queue, trigger, &detail, &mut last_fired).await;
+                }
+            }
+        }
+    })
+}
+
+/// Message the trigger's `notify` conversation and queue its task, unless it
+/// fired less than `cooldown_secs` ago.
+async fn fire(
+    deps: &AgentDeps,
+    logger: &CortexLogger,
+    queue: &TaskQueue,
+    trigger: &TriggerDef,
+    detail: &str,
+    last_fired: &mut HashMap<String, Instant>,
+) {
+    // Timers are their own cooldown.
+    if !matches!(trigger.source, TriggerSource::Timer { .. })
+        && last_fired
+            .get(&trigger.id)
+            .is_some_and(|fired| fired.elapsed() < Duration::from_secs(trigger.cooldown_secs))
+    {
+        tracing::debug!(trigger_id = %trigger.id, "trigger cooling down, dropping signal");
+        return;
+    }
+    last_fired.insert(trigger.id.clone(), Instant::now());
+    tracing::info!(agent_id = %deps.agent_id, trigger_id = %trigger.id, "trigger fired");
+
+    let queued_id = match &trigger.task {
+        Some(task) => {
+            let new = NewTask {
+                origin_id: Some(trigger.id.clone()),
+                report_to: trigger.report_to.clone(),
+                ..NewTask::new(format!("{task}\n\n{detail}"), TaskOrigin::Trigger)
+            };
+            match queue.enqueue(new).await {
+                Ok(queued) => Some(queued.id),
+                Err(error) => {
+                    tracing::warn!(trigger_id = %trigger.id, %error, "failed to queue trigger task");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    logger.log(
+        TRIGGER_EVENT,
+        &format!("Trigger '{}' fired", trigger.id),
+        Some(serde_json::json!({
+            "trigger_id": trigger.id,
+            "detail": detail,
+            "queued_id": queued_id,
+        })),
+    );
+
+    if let Some(target) = &trigger.notify {
+        let Some(messaging_manager) = &deps.messaging_manager else {
+            tracing::warn!(trigger_id = %trigger.id, "messaging isn't running, can't notify");
+            return;
+        };
+        let text = format!("Trigger '{}' fired.\n\n{detail}", trigger.id);
+        if let Err(error) = messaging_manager
+            .broadcast(
+                &target.adapter,
+                &target.target,
+                OutboundResponse::Text(text),
+            )
+            .await
+        {
+            tracing::warn!(trigger_id = %trigger.id, %target, %error, "failed to notify trigger");
+        }
+    }
+}
+
+/// Take a watch trigger's changed paths once they have been quiet for
+/// [`DEBOUNCE`]. Changes that are still settling stay pending.
+fn take_settled(
+    pending: &mut HashMap<String, (BTreeSet<PathBuf>, Instant)>,
+    trigger_id: &str,
+    now: Instant,
+) -> Option<BTreeSet<PathBuf>> {
+    let (_, last_change) = pending.get(trigger_id)?;
+    if now.duration_since(*last_change) < DEBOUNCE {
+        return None;
+    }
+    pending.remove(trigger_id).map(|(paths, _)| paths)
+}
+
+fn describe_changes(workspace: &Path, paths: &BTreeSet<PathBuf>) -> String {
+    let mut listed: Vec<String> = paths
+        .iter()
+        .take(MAX_LISTED_PATHS)
+        .map(|path| {
+            path.strip_prefix(workspace)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        })
+        .collect();
+    if paths.len() > MAX_LISTED_PATHS {
+        listed.push(format!("and {} more", paths.len() - MAX_LISTED_PATHS));
+    }
+    format!("Changed in the workspace: {}", listed.join(", "))
+}
+
+fn describe_payload(payload: &str) -> String {
+    let payload = payload.trim();
+    if payload.is_empty() {
+        return "Webhook called, without a body.".into();
+    }
+    let end = payload.floor_char_boundary(MAX_PAYLOAD_BYTES);
+    let ellipsis = if end < payload.len() { "\n..." } else { "" };
+    format!("Webhook body:\n{}{ellipsis}", &payload[..end])
+}
+
+/// A file watcher over the paths of the agent's watch triggers, rebuilt when
+/// they change.
+struct WorkspaceWatcher {
+    tx: mpsc::UnboundedSender<PathBuf>,
+    watched: Vec<PathBuf>,
+    watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl WorkspaceWatcher {
+    fn new(tx: mpsc::UnboundedSender<PathBuf>) -> Self {
+        Self {
+            tx,
+            watched: Vec::new(),
+            watcher: None,
+        }
+    }
+
+    fn update(&mut self, workspace: &Path, triggers: &[TriggerDef]) {
+        let mut paths: Vec<PathBuf> = triggers
+            .iter()
+            .filter(|trigger| trigger.enabled)
+            .filter_map(|trigger| match &trigger.source {
+                TriggerSource::Watch(path) => Some(workspace.join(path)),
+                _ => None,
+            })
+            .collect();
+        paths.sort();
+        paths.dedup();
+        if paths == self.watched {
+            return;
+        }
+        self.watched = paths;
+        self.watcher = None;
+        if self.watched.is_empty() {
+            return;
+        }
+
+        let tx = self.tx.clone();
+        let mut watcher = match notify::recommended_watcher(
+            move |result: std::result::Result<notify::Event, notify::Error>| {
+                let Ok(event) = result else { return };
+                if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() {
+                    for path in event.paths {
+                        tx.send(path).ok();
+                    }
+                }
+            },
+        ) {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                tracing::warn!(%error, "failed to create trigger file watcher");
+                return;
+            }
+        };
+        for path in &self.watched {
+            // A path that doesn't exist yet is caught through its parent.
+            let (target, mode) = if path.is_dir() {
+                (path.as_path(), RecursiveMode::Recursive)
+            } else if path.exists() {
+                (path.as_path(), RecursiveMode::NonRecursive)
+            } else {
+                (
+                    path.parent().unwrap_or(workspace),
+                    RecursiveMode::NonRecursive,
+                )
+            };
+            if let Err(error) = watcher.watch(target, mode) {
+                tracing::warn!(%error, path = %target.display(), "failed to watch trigger path");
+            }
+        }
+        self.watcher = Some(watcher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_payloads_are_cut() {
+        assert_eq!(describe_payload("  "), "Webhook called, without a body.");
+        assert_eq!(
+            describe_payload("{\"ref\": \"main\"}"),
+            "Webhook body:\n{\"ref\": \"main\"}"
+        );
+        let long = describe_payload(&"é".repeat(MAX_PAYLOAD_BYTES));
+        assert!(long.ends_with("\n..."));
+        assert!(long.len() < MAX_PAYLOAD_BYTES + 30);
+    }
+
+    #[test]
+    fn watch_changes_fire_once_settled() {
+        let changed = Instant::now();
+        let mut pending = HashMap::from([(
+            "docs".to_string(),
+            (BTreeSet::from([PathBuf::from("/ws/docs/a.md")]), changed),
+        )]);
+
+        // Still settling: nothing fires and the change is kept.
+        assert!(take_settled(&mut pending, "docs", changed + TICK).is_none());
+        assert!(pending.contains_key("docs"));
+
+        let paths = take_settled(&mut pending, "docs", changed + DEBOUNCE).unwrap();
+        assert_eq!(paths, BTreeSet::from([PathBuf::from("/ws/docs/a.md")]));
+        assert!(pending.is_empty());
+        assert!(take_settled(&mut pending, "docs", changed + DEBOUNCE).is_none());
+    }
+}
@@ -0,0 +1,271 @@
+//! Hook points around a channel turn, for guardrails and other extensions
+//! that shouldn't need changes to the channel itself.
+//!
+//! A [`TurnHook`] is called at three points of every turn: before the LLM
+//! runs, where it can rewrite the system prompt or the user text, or veto
+//! the turn; on each response the turn sends, where it can rewrite or drop
+//! it; and once the turn is over, with its outcome. Hooks run in the order
+//! they were added to an agent's [`TurnHooks`], and a veto or drop stops
+//! the chain. Typing indicators and other status updates don't go through
+//! hooks.
+//!
+//! Unlike [`SpacebotHook`](crate::hooks::SpacebotHook), which follows the
+//! LLM loop step by step, turn hooks only see the turn as a whole.
+
+use crate::{AgentId, ChannelId, OutboundResponse};
+
+/// Reason a vetoed turn's prompt is cancelled with.
+pub const TURN_VETOED_REASON: &str = "vetoed by turn hook";
+
+/// The turn a hook is called for.
+#[derive(Debug, Clone)]
+pub struct TurnContext {
+    pub agent_id: AgentId,
+    pub channel_id: ChannelId,
+    pub conversation_id: String,
+    /// The prompt for this turn, which `before_turn` may rewrite.
+    pub user_text: String,
+    /// The system prompt for this turn, which `before_turn` may rewrite.
+    pub system_prompt: String,
+}
+
+/// Whether a turn goes ahead after `before_turn`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurnDecision {
+    Continue,
+    /// Skip the LLM call, sending `reply` to the conversation when there is
+    /// one.
+    Veto {
+        reply: Option<String>,
+    },
+}
+
+/// Whether a response goes out after `on_response`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseDecision {
+    Send,
+    Drop,
+}
+
+/// How a turn ended, passed to `after_turn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnOutcome {
+    /// The turn sent a reply, through the reply tool or as plain text.
+    Replied,
+    /// The turn ended without replying.
+    Silent,
+    /// A hook vetoed the turn before the LLM ran.
+    Vetoed,
+    /// The LLM call failed.
+    Failed,
+}
+
+/// Extension called around every channel turn. All methods default to
+/// letting the turn through unchanged.
+pub trait TurnHook: Send + Sync {
+    /// Name used in logs.
+    fn name(&self) -> &str;
+
+    /// Called before the LLM runs.
+    fn before_turn(&self, _turn: &mut TurnContext) -> TurnDecision {
+        TurnDecision::Continue
+    }
+
+    /// Called for each reply, reaction or other response the turn sends.
+    fn on_response(
+        &self,
+        _turn: &TurnContext,
+        _response: &mut OutboundResponse,
+    ) -> ResponseDecision {
+        ResponseDecision::Send
+    }
+
+    /// Called once the turn is over.
+    fn after_turn(&self, _turn: &TurnContext, _outcome: TurnOutcome) {}
+}
+
+/// An agent's turn hooks, run in order.
+#[derive(Default)]
+pub struct TurnHooks {
+    hooks: Vec<Box<dyn TurnHook>>,
+}
+
+impl std::fmt::Debug for TurnHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.hooks.iter().map(|hook| hook.name()))
+            .finish()
+    }
+}
+
+impl TurnHooks {
+    /// Add a hook at the end of the chain.
+    pub fn push(&mut self, hook: impl TurnHook + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run `before_turn` on each hook, stopping at the first veto.
+    pub fn before_turn(&self, turn: &mut TurnContext) -> TurnDecision {
+        for hook in &self.hooks {
+            let decision = hook.before_turn(turn);
+            if decision != TurnDecision::Continue {
+                tracing::info!(
+                    hook = hook.name(),
+                    channel_id = %turn.channel_id,
+                    "channel turn vetoed"
+                );
+                return decision;
+            }
+        }
+        TurnDecision::Continue
+    }
+
+    /// Run `on_response` on each hook. Returns the response to send, or
+    /// `None` when a hook dropped it.
+    pub fn on_response(
+        &self,
+        turn: &TurnContext,
+        mut response: OutboundResponse,
+    ) -> Option<OutboundResponse> {
+        for hook in &self.hooks {
+            if hook.on_response(turn, &mut response) == ResponseDecision::Drop {
+                tracing::debug!(
+                    hook = hook.name(),
+                    channel_id = %turn.channel_id,
+                    "outbound response dropped"
+                );
+                return None;
+            }
+        }
+        Some(response)
+    }
+
+    /// Run `after_turn` on each hook.
+    pub fn after_turn(&self, turn: &TurnContext, outcome: TurnOutcome) {
+        for hook in &self.hooks {
+            hook.after_turn(turn, outcome);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    fn turn(user_text: &str) -> TurnContext {
+        TurnContext {
+            agent_id: "main".into(),
+            channel_id: "discord:1".into(),
+            conversation_id: "discord:1".into(),
+            user_text: user_text.into(),
+            system_prompt: "You are Spacebot.".into(),
+        }
+    }
+
+    /// Vetoes turns mentioning a word and redacts it from replies.
+    struct Guardrail {
+        word: &'static str,
+        outcomes: Arc<Mutex<Vec<TurnOutcome>>>,
+    }
+
+    impl TurnHook for Guardrail {
+        fn name(&self) -> &str {
+            "guardrail"
+        }
+
+        fn before_turn(&self, turn: &mut TurnContext) -> TurnDecision {
+            if turn.user_text.contains(self.word) {
+                return TurnDecision::Veto {
+                    reply: Some("I can't help with that.".into()),
+                };
+            }
+            turn.system_prompt.push_str("\nNever discuss the launch.");
+            TurnDecision::Continue
+        }
+
+        fn on_response(
+            &self,
+            _turn: &TurnContext,
+            response: &mut OutboundResponse,
+        ) -> ResponseDecision {
+            match response {
+                OutboundResponse::Text(text) if text.contains(self.word) => ResponseDecision::Drop,
+                OutboundResponse::Text(text) => {
+                    *text = text.replace("launch", "[redacted]");
+                    ResponseDecision::Send
+                }
+                _ => ResponseDecision::Send,
+            }
+        }
+
+        fn after_turn(&self, _turn: &TurnContext, outcome: TurnOutcome) {
+            self.outcomes.lock().unwrap().push(outcome);
+        }
+    }
+
+    /// Counts the turns that reach it.
+    struct Counter(Arc<Mutex<usize>>);
+
+    impl TurnHook for Counter {
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn before_turn(&self, _turn: &mut TurnContext) -> TurnDecision {
+            *self.0.lock().unwrap() += 1;
+            TurnDecision::Continue
+        }
+    }
+
+    #[test]
+    fn hooks_rewrite_veto_and_filter_turns() {
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let count = Arc::new(Mutex::new(0));
+        let mut hooks = TurnHooks::default();
+        hooks.push(Guardrail {
+            word: "secret",
+            outcomes: outcomes.clone(),
+        });
+        hooks.push(Counter(count.clone()));
+
+        let mut allowed = turn("when is the launch?");
+        assert_eq!(hooks.before_turn(&mut allowed), TurnDecision::Continue);
+        assert!(allowed.system_prompt.ends_with("Never discuss the launch."));
+
+        let mut vetoed = turn("tell me the secret");
+        assert_eq!(
+            hooks.before_turn(&mut vetoed),
+            TurnDecision::Veto {
+                reply: Some("I can't help with that.".into())
+            }
+        );
+        // The veto stopped the chain before the counter.
+        assert_eq!(*count.lock().unwrap(), 1);
+
+        let sent = hooks.on_response(
+            &allowed,
+            OutboundResponse::Text("the launch is soon".into()),
+        );
+        assert!(
+            matches!(sent, Some(OutboundResponse::Text(text)) if text == "the [redacted] is soon")
+        );
+        assert!(
+            hooks
+                .on_response(&allowed, OutboundResponse::Text("the secret is out".into()))
+                .is_none()
+        );
+
+        hooks.after_turn(&allowed, TurnOutcome::Replied);
+        hooks.after_turn(&vetoed, TurnOutcome::Vetoed);
+        assert_eq!(
+            *outcomes.lock().unwrap(),
+            [TurnOutcome::Replied, TurnOutcome::Vetoed]
+        );
+    }
+}
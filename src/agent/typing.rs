@@ -0,0 +1,202 @@
+//! Typing indicator keepalive for channels.
+//!
+//! Platforms expire a typing indicator after a few seconds (Discord after
+//! ten), so a single `Thinking` status doesn't last through a long turn or
+//! a worker the user is waiting on. The keepalive resends it every eight
+//! seconds for as long as anything holds it: the channel's turn, or a branch
+//! or worker spawned for the user. When the last holder lets go, or the
+//! keepalive itself is dropped, the indicator is stopped.
+
+use crate::{BranchId, OutboundResponse, StatusUpdate, WorkerId};
+
+use tokio::sync::{mpsc, oneshot};
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often `Thinking` is resent while the indicator is held.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(8);
+
+/// Something the user is waiting on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypingHolder {
+    Turn,
+    Branch(BranchId),
+    Worker(WorkerId),
+}
+
+/// Keeps a channel's typing indicator up while it's held. Cheap to clone;
+/// clones share the same holders.
+#[derive(Debug, Clone)]
+pub struct TypingKeepalive {
+    response_tx: mpsc::Sender<OutboundResponse>,
+    interval: Duration,
+    inner: Arc<Mutex<KeepaliveState>>,
+}
+
+#[derive(Debug, Default)]
+struct KeepaliveState {
+    holders: HashSet<TypingHolder>,
+    /// Dropping this stops the running keepalive task.
+    stop_tx: Option<oneshot::Sender<()>>,
+}
+
+impl TypingKeepalive {
+    pub fn new(response_tx: mpsc::Sender<OutboundResponse>) -> Self {
+        Self::with_interval(response_tx, KEEPALIVE_INTERVAL)
+    }
+
+    fn with_interval(response_tx: mpsc::Sender<OutboundResponse>, interval: Duration) -> Self {
+        Self {
+            response_tx,
+            interval,
+            inner: Arc::new(Mutex::new(KeepaliveState::default())),
+        }
+    }
+
+    /// Show the indicator until `holder` is released.
+    pub fn hold(&self, holder: TypingHolder) {
+        let mut state = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.holders.insert(holder);
+        if state.stop_tx.is_none() {
+            let (stop_tx, stop_rx) = oneshot::channel();
+            state.stop_tx = Some(stop_tx);
+            tokio::spawn(keep_typing(
+                self.response_tx.clone(),
+                self.interval,
+                stop_rx,
+            ));
+        }
+    }
+
+    /// Release `holder`, stopping the indicator if nothing else holds it.
+    pub fn release(&self, holder: TypingHolder) {
+        let mut state = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.holders.remove(&holder);
+        if state.holders.is_empty() {
+            state.stop_tx = None;
+        }
+    }
+
+    /// Hold the indicator for the current turn. It's released when the
+    /// guard drops, however the turn ends.
+    pub fn hold_turn(&self) -> TurnTyping {
+        self.hold(TypingHolder::Turn);
+        TurnTyping {
+            keepalive: self.clone(),
+        }
+    }
+
+    /// Release every holder and stop the indicator.
+    pub fn clear(&self) {
+        let mut state = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.holders.clear();
+        state.stop_tx = None;
+    }
+
+    /// Whether anything holds the indicator.
+    pub fn is_held(&self) -> bool {
+        !self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .holders
+            .is_empty()
+    }
+}
+
+/// Releases the turn's hold on the typing indicator when dropped.
+#[derive(Debug)]
+pub struct TurnTyping {
+    keepalive: TypingKeepalive,
+}
+
+impl Drop for TurnTyping {
+    fn drop(&mut self) {
+        self.keepalive.release(TypingHolder::Turn);
+    }
+}
+
+async fn keep_typing(
+    response_tx: mpsc::Sender<OutboundResponse>,
+    interval: Duration,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    loop {
+        if response_tx
+            .send(OutboundResponse::Status(StatusUpdate::Thinking))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = &mut stop_rx => break,
+        }
+    }
+    response_tx
+        .send(OutboundResponse::Status(StatusUpdate::StopTyping))
+        .await
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_thinking(response: &OutboundResponse) -> bool {
+        matches!(response, OutboundResponse::Status(StatusUpdate::Thinking))
+    }
+
+    fn is_stop(response: &OutboundResponse) -> bool {
+        matches!(response, OutboundResponse::Status(StatusUpdate::StopTyping))
+    }
+
+    #[tokio::test]
+    async fn typing_is_resent_until_the_last_holder_releases() {
+        let (response_tx, mut response_rx) = mpsc::channel(16);
+        let keepalive = TypingKeepalive::with_interval(response_tx, Duration::from_millis(20));
+        let worker_id = uuid::Uuid::new_v4();
+
+        let turn = keepalive.hold_turn();
+        keepalive.hold(TypingHolder::Worker(worker_id));
+        assert!(is_thinking(&response_rx.recv().await.unwrap()));
+
+        // The turn ends while the worker keeps running.
+        drop(turn);
+        assert!(is_thinking(&response_rx.recv().await.unwrap()));
+        assert!(keepalive.is_held());
+
+        keepalive.release(TypingHolder::Worker(worker_id));
+        assert!(!keepalive.is_held());
+        loop {
+            let response = response_rx.recv().await.unwrap();
+            if is_stop(&response) {
+                break;
+            }
+            assert!(is_thinking(&response));
+        }
+    }
+
+    #[tokio::test]
+    async fn dropping_the_keepalive_stops_typing() {
+        let (response_tx, mut response_rx) = mpsc::channel(16);
+        let keepalive = TypingKeepalive::new(response_tx);
+
+        keepalive.hold(TypingHolder::Turn);
+        assert!(is_thinking(&response_rx.recv().await.unwrap()));
+        drop(keepalive);
+        assert!(is_stop(&response_rx.recv().await.unwrap()));
+    }
+}
@@ -0,0 +1,138 @@
+//! Frame sampling from video attachments for vision requests.
+//!
+//! Models can't watch a video, but they can look at a few stills from it. A
+//! clip is probed for its duration with `ffprobe`, then `ffmpeg` grabs the
+//! keyframe nearest each of a few evenly spaced points through it. Only
+//! keyframes are decoded, so sampling stays fast on long or high-resolution
+//! clips. Both tools must be on the `PATH`.
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest a single `ffprobe` or `ffmpeg` run may take.
+const TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Stills taken from a video.
+#[derive(Debug)]
+pub struct SampledVideo {
+    /// Length of the clip, when the container reports it.
+    pub duration_secs: Option<f64>,
+    pub frames: Vec<SampledFrame>,
+}
+
+/// One still, as a JPEG.
+#[derive(Debug)]
+pub struct SampledFrame {
+    /// The point in the clip the frame was sampled near.
+    pub timestamp_secs: f64,
+    pub jpeg: Vec<u8>,
+}
+
+/// Sample up to `count` frames from the video at `path`. Points that land on
+/// the same keyframe yield it once, so short clips can give fewer frames.
+pub async fn sample_frames(path: &Path, count: usize) -> anyhow::Result<SampledVideo> {
+    let duration_secs = probe_duration(path).await?;
+    let directory = tempfile::tempdir()?;
+
+    let mut frames: Vec<SampledFrame> = Vec::new();
+    for (index, timestamp_secs) in frame_timestamps(duration_secs, count)
+        .into_iter()
+        .enumerate()
+    {
+        let output = directory.path().join(format!("frame-{index}.jpg"));
+        let result = run_with_timeout(
+            tokio::process::Command::new("ffmpeg")
+                .args(["-nostdin", "-loglevel", "error", "-y"])
+                .args(["-skip_frame", "nokey", "-noaccurate_seek", "-ss"])
+                .arg(format!("{timestamp_secs:.3}"))
+                .arg("-i")
+                .arg(path)
+                .args(["-frames:v", "1", "-q:v", "3"])
+                .arg(&output),
+        )
+        .await
+        .map_err(|error| anyhow::anyhow!("can't run ffmpeg: {error}"))?;
+        if !result.status.success() {
+            anyhow::bail!(
+                "ffmpeg can't decode the video: {}",
+                String::from_utf8_lossy(&result.stderr).trim()
+            );
+        }
+
+        // A point past the last keyframe produces no file.
+        let Ok(jpeg) = tokio::fs::read(&output).await else {
+            continue;
+        };
+        if frames.last().is_some_and(|frame| frame.jpeg == jpeg) {
+            continue;
+        }
+        frames.push(SampledFrame {
+            timestamp_secs,
+            jpeg,
+        });
+    }
+
+    if frames.is_empty() {
+        anyhow::bail!("no frames could be decoded");
+    }
+    Ok(SampledVideo {
+        duration_secs,
+        frames,
+    })
+}
+
+async fn probe_duration(path: &Path) -> anyhow::Result<Option<f64>> {
+    let output = run_with_timeout(
+        tokio::process::Command::new("ffprobe")
+            .args(["-v", "error", "-show_entries", "format=duration"])
+            .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+            .arg(path),
+    )
+    .await
+    .map_err(|error| anyhow::anyhow!("can't run ffprobe: {error}"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ffprobe can't read the video: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // Streams without a container duration report "N/A".
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|duration| duration.is_finite() && *duration > 0.0))
+}
+
+/// Points in the middle of each of `count` equal parts of the clip. Without
+/// a duration there is only the start.
+fn frame_timestamps(duration_secs: Option<f64>, count: usize) -> Vec<f64> {
+    match duration_secs {
+        Some(duration) if count > 0 => (0..count)
+            .map(|index| duration * (index as f64 + 0.5) / count as f64)
+            .collect(),
+        _ => vec![0.0],
+    }
+}
+
+async fn run_with_timeout(
+    command: &mut tokio::process::Command,
+) -> std::io::Result<std::process::Output> {
+    let output = command.kill_on_drop(true).output();
+    tokio::time::timeout(TOOL_TIMEOUT, output)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_spread_through_the_clip() {
+        assert_eq!(frame_timestamps(Some(8.0), 4), vec![1.0, 3.0, 5.0, 7.0]);
+        assert_eq!(frame_timestamps(Some(8.0), 1), vec![4.0]);
+        assert_eq!(frame_timestamps(None, 4), vec![0.0]);
+    }
+}
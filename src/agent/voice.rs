@@ -0,0 +1,972 @@
+//! Voice: realtime spoken conversation with an agent.
+//!
+//! A voice session runs next to the channel for the same conversation. The
+//! transport (web chat socket, voice chat bridge) feeds it 16-bit mono PCM
+//! and plays back the audio it emits. The session:
+//!
+//! 1. Detects speech by energy. After `silence_ms` of quiet the utterance is
+//!    transcribed by the configured backend: the `routing.voice` model, the
+//!    provider's transcription API, or a local whisper.cpp.
+//! 2. Streams the reply from the channel model.
+//! 3. Synthesizes each finished sentence while the rest is still being
+//!    generated, so playback starts after the first sentence.
+//!
+//! When the user talks over a reply (barge-in), the reply is cancelled and
+//! only the part already spoken is kept. Both sides are logged to the
+//! conversation, so the text channel sees what was said.
+//!
+//! Adapters that listen in voice channels (Discord) work the other way round:
+//! they send each utterance to the channel as an audio attachment marked
+//! with [`SPEAK_REPLIES_KEY`], and the channel's replies are spoken back with
+//! [`speak_reply`].
+
+use crate::config::{ApiType, ProviderConfig, TranscriptionBackend, VoiceConfig};
+use crate::conversation::history::ConversationLogger;
+use crate::llm::SpacebotModel;
+use crate::{AgentDeps, ChannelId, InboundMessage, OutboundResponse, ProcessType};
+
+use rig::completion::{AssistantContent, CompletionModel as _, Message};
+use serde::Serialize;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Sample rate of the PCM in [`VoiceEvent::Audio`].
+pub const OUTPUT_SAMPLE_RATE: u32 = 24_000;
+
+/// Metadata flag on messages that were spoken, asking for replies to be
+/// spoken back where they came from.
+pub const SPEAK_REPLIES_KEY: &str = "speak_replies";
+
+/// Longest a local transcription (and its ffmpeg conversion) may run.
+const TRANSCRIPTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Length of the frames speech detection works on.
+const FRAME_MS: u32 = 20;
+
+/// Consecutive voiced time before input counts as speech, so clicks and
+/// coughs don't interrupt a reply.
+const MIN_SPEECH_MS: u32 = 120;
+
+/// Audio kept from before speech was detected, so the first syllable isn't
+/// cut off.
+const PRE_ROLL_MS: u32 = 300;
+
+/// Something the session reports to the transport.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VoiceEvent {
+    /// The user started talking.
+    SpeechStarted,
+    /// What the user said.
+    Transcript {
+        text: String,
+    },
+    /// Reply text as it's generated.
+    ResponseDelta {
+        text: String,
+    },
+    /// 16-bit little-endian mono PCM at [`OUTPUT_SAMPLE_RATE`]. Transports
+    /// send this as binary rather than JSON.
+    #[serde(skip)]
+    Audio(Vec<u8>),
+    /// The reply is complete.
+    ResponseDone {
+        text: String,
+    },
+    /// The reply was cancelled because the user started talking.
+    Interrupted,
+    Error {
+        message: String,
+    },
+}
+
+/// Who is talking, for the conversation log.
+#[derive(Debug, Clone)]
+pub struct VoiceParticipant {
+    pub sender_id: String,
+    pub sender_name: String,
+}
+
+/// A running voice session. Dropping the audio sender ends it.
+pub struct VoiceSession {
+    pub audio_tx: mpsc::Sender<Vec<i16>>,
+    pub events: mpsc::Receiver<VoiceEvent>,
+}
+
+impl VoiceSession {
+    /// Start a session for a conversation. `sample_rate` is the rate of the
+    /// input PCM.
+    pub fn start(
+        deps: AgentDeps,
+        conversation_id: ChannelId,
+        participant: VoiceParticipant,
+        sample_rate: u32,
+    ) -> Self {
+        let (audio_tx, audio_rx) = mpsc::channel(256);
+        let (event_tx, events) = mpsc::channel(256);
+        let context = Arc::new(TurnContext {
+            logger: ConversationLogger::new(deps.sqlite_pool.clone()),
+            config: (**deps.runtime_config.voice.load()).clone(),
+            history: Mutex::new(Vec::new()),
+            deps,
+            conversation_id,
+            participant,
+            sample_rate,
+            event_tx,
+        });
+        tokio::spawn(run(context, audio_rx));
+        Self { audio_tx, events }
+    }
+}
+
+/// State shared by the session and its turns.
+struct TurnContext {
+    deps: AgentDeps,
+    config: VoiceConfig,
+    logger: ConversationLogger,
+    conversation_id: ChannelId,
+    participant: VoiceParticipant,
+    sample_rate: u32,
+    history: Mutex<Vec<Message>>,
+    event_tx: mpsc::Sender<VoiceEvent>,
+}
+
+impl TurnContext {
+    async fn emit(&self, event: VoiceEvent) {
+        self.event_tx.send(event).await.ok();
+    }
+}
+
+/// A reply being generated or spoken.
+struct ActiveTurn {
+    handle: JoinHandle<()>,
+    /// Reply text that has been synthesized so far.
+    spoken: Arc<Mutex<String>>,
+}
+
+async fn run(context: Arc<TurnContext>, mut audio_rx: mpsc::Receiver<Vec<i16>>) {
+    let agent_id = context.deps.agent_id.clone();
+    tracing::info!(%agent_id, conversation_id = %context.conversation_id, "voice session started");
+
+    *context.history.lock().await = load_history(&context).await;
+    let mut detector = SpeechDetector::new(context.sample_rate, &context.config);
+    let mut turn: Option<ActiveTurn> = None;
+
+    while let Some(samples) = audio_rx.recv().await {
+        for detection in detector.push(&samples) {
+            match detection {
+                Detection::SpeechStarted => {
+                    context.emit(VoiceEvent::SpeechStarted).await;
+                    if context.config.barge_in
+                        && let Some(active) = turn.take_if(|active| !active.handle.is_finished())
+                    {
+                        interrupt(&context, active).await;
+                    }
+                }
+                Detection::UtteranceEnded(utterance) => {
+                    // Without barge-in, a new utterance waits for the reply
+                    // in progress to finish.
+                    let previous = turn.take().map(|active| active.handle);
+                    let spoken = Arc::new(Mutex::new(String::new()));
+                    let handle = tokio::spawn({
+                        let context = context.clone();
+                        let spoken = spoken.clone();
+                        async move {
+                            if let Some(previous) = previous {
+                                previous.await.ok();
+                            }
+                            if let Err(error) = run_turn(&context, utterance, &spoken).await {
+                                tracing::warn!(%error, "voice turn failed");
+                                context
+                                    .emit(VoiceEvent::Error {
+                                        message: error.to_string(),
+                                    })
+                                    .await;
+                            }
+                        }
+                    });
+                    turn = Some(ActiveTurn { handle, spoken });
+                }
+            }
+        }
+    }
+
+    if let Some(active) = turn {
+        active.handle.abort();
+    }
+    tracing::info!(%agent_id, conversation_id = %context.conversation_id, "voice session ended");
+}
+
+/// Cancel a reply and keep what was already spoken.
+async fn interrupt(context: &TurnContext, active: ActiveTurn) {
+    active.handle.abort();
+    active.handle.await.ok();
+    context.emit(VoiceEvent::Interrupted).await;
+
+    let spoken = active.spoken.lock().await.trim().to_string();
+    if !spoken.is_empty() {
+        context
+            .logger
+            .log_bot_message(&context.conversation_id, &spoken);
+        context
+            .history
+            .lock()
+            .await
+            .push(Message::from(AssistantContent::from(spoken)));
+    }
+}
+
+/// Recent conversation messages, so the voice reply has the text context.
+async fn load_history(context: &TurnContext) -> Vec<Message> {
+    let messages = context
+        .logger
+        .load_recent(
+            &context.conversation_id,
+            context.config.history_messages as i64,
+        )
+        .await
+        .unwrap_or_default();
+    messages
+        .into_iter()
+        .filter_map(|message| match message.role.as_str() {
+            "user" => Some(Message::from(message.content.as_str())),
+            "assistant" => Some(Message::from(AssistantContent::from(message.content))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Transcribe one utterance, stream the reply and speak it.
+async fn run_turn(
+    context: &TurnContext,
+    utterance: Vec<i16>,
+    spoken: &Mutex<String>,
+) -> anyhow::Result<()> {
+    let deps = &context.deps;
+    let http = deps.llm_manager.http_client();
+    let audio = encode_wav(&utterance, context.sample_rate);
+    let transcript = transcribe(deps, http, &audio, "wav").await?;
+    if transcript.is_empty() {
+        return Ok(());
+    }
+    context
+        .emit(VoiceEvent::Transcript {
+            text: transcript.clone(),
+        })
+        .await;
+    let metadata = HashMap::from([("voice".to_string(), serde_json::Value::Bool(true))]);
+    context.logger.log_user_message(
+        &context.conversation_id,
+        &context.participant.sender_name,
+        &context.participant.sender_id,
+        &transcript,
+        &metadata,
+    );
+
+    let runtime_config = &deps.runtime_config;
+    let system_prompt = runtime_config.prompts.load().render_voice_prompt(
+        Some(runtime_config.identity.load().render()).filter(|identity| !identity.is_empty()),
+        Some(runtime_config.memory_bulletin.load().to_string())
+            .filter(|bulletin| !bulletin.is_empty()),
+    )?;
+    let routing = runtime_config.routing.load();
+    let model_name = routing.resolve(ProcessType::Voice, None).to_string();
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_context(&*deps.agent_id, "voice")
+        .with_routing((**routing).clone());
+    // The utterance joins the history now, so an interrupted reply still
+    // follows it.
+    let history = {
+        let mut history = context.history.lock().await;
+        let snapshot = history.clone();
+        history.push(Message::from(transcript.as_str()));
+        snapshot
+    };
+    let request = model
+        .completion_request(Message::from(transcript.as_str()))
+        .preamble(system_prompt)
+        .messages(history)
+        .build();
+    let mut chunks = model.stream_text(request).await?;
+
+    // Generation and speech run side by side: sentences are queued for
+    // synthesis while the model keeps writing.
+    let (sentence_tx, mut sentence_rx) = mpsc::unbounded_channel::<String>();
+    let generate = async {
+        let mut full = String::new();
+        let mut pending = String::new();
+        while let Some(chunk) = chunks.recv().await {
+            let chunk = chunk?;
+            full.push_str(&chunk);
+            pending.push_str(&chunk);
+            context
+                .emit(VoiceEvent::ResponseDelta { text: chunk })
+                .await;
+            while let Some(sentence) = take_sentence(&mut pending) {
+                sentence_tx.send(sentence).ok();
+            }
+        }
+        let rest = pending.trim();
+        if !rest.is_empty() {
+            sentence_tx.send(rest.to_string()).ok();
+        }
+        drop(sentence_tx);
+        anyhow::Ok(full)
+    };
+    let speak = async {
+        while let Some(sentence) = sentence_rx.recv().await {
+            let audio = synthesize(deps, http, &context.config, &sentence).await?;
+            context.emit(VoiceEvent::Audio(audio)).await;
+            let mut spoken = spoken.lock().await;
+            spoken.push_str(&sentence);
+            spoken.push(' ');
+        }
+        anyhow::Ok(())
+    };
+    let (full, spoken_result) = tokio::join!(generate, speak);
+    let full = full?;
+    spoken_result?;
+
+    let full = full.trim().to_string();
+    context
+        .logger
+        .log_bot_message(&context.conversation_id, &full);
+    {
+        let mut history = context.history.lock().await;
+        history.push(Message::from(AssistantContent::from(full.clone())));
+        let excess = history
+            .len()
+            .saturating_sub(context.config.history_messages);
+        history.drain(..excess);
+    }
+    context.emit(VoiceEvent::ResponseDone { text: full }).await;
+    Ok(())
+}
+
+/// Transcribe audio with the agent's transcription backend. `format` is the
+/// container, e.g. `wav` or `ogg`.
+pub async fn transcribe(
+    deps: &AgentDeps,
+    http: &reqwest::Client,
+    audio: &[u8],
+    format: &str,
+) -> anyhow::Result<String> {
+    let config = deps.runtime_config.voice.load();
+    match config.transcription {
+        TranscriptionBackend::Chat => transcribe_with_chat(deps, http, audio, format).await,
+        TranscriptionBackend::Api => transcribe_with_api(deps, http, audio, format).await,
+        TranscriptionBackend::WhisperCpp => {
+            transcribe_with_whisper_cpp(&config, audio, format).await
+        }
+    }
+}
+
+/// The `routing.voice` model's provider and model name.
+fn voice_provider(deps: &AgentDeps) -> anyhow::Result<(String, ProviderConfig, String)> {
+    let routing = deps.runtime_config.routing.load();
+    let voice_model = routing.voice.trim().to_string();
+    if voice_model.is_empty() {
+        anyhow::bail!("no voice model is configured in routing.voice");
+    }
+    let (provider_id, model_name) = deps
+        .llm_manager
+        .resolve_model(&voice_model)
+        .map_err(|_| anyhow::anyhow!("invalid voice model '{voice_model}'"))?;
+    let provider = deps
+        .llm_manager
+        .get_provider(&provider_id)
+        .map_err(|_| anyhow::anyhow!("provider '{provider_id}' is not configured"))?;
+    if provider.api_type == ApiType::Anthropic {
+        anyhow::bail!("provider '{provider_id}' has no audio input endpoint");
+    }
+    Ok((voice_model, provider, model_name))
+}
+
+/// Send the audio to the `routing.voice` model as `input_audio`.
+async fn transcribe_with_chat(
+    deps: &AgentDeps,
+    http: &reqwest::Client,
+    audio: &[u8],
+    format: &str,
+) -> anyhow::Result<String> {
+    let (voice_model, provider, model_name) = voice_provider(deps)?;
+
+    use base64::Engine as _;
+    let base64_audio = base64::engine::general_purpose::STANDARD.encode(audio);
+    let endpoint = format!(
+        "{}/v1/chat/completions",
+        provider.base_url.trim_end_matches('/')
+    );
+    let body = serde_json::json!({
+        "model": model_name,
+        "messages": [{
+            "role": "user",
+            "content": [
+                {
+                    "type": "text",
+                    "text": "Transcribe this audio verbatim. Return only the transcription text."
+                },
+                {
+                    "type": "input_audio",
+                    "input_audio": {
+                        "data": base64_audio,
+                        "format": format,
+                    }
+                }
+            ]
+        }],
+        "temperature": 0
+    });
+
+    let response = http
+        .post(&endpoint)
+        .header("authorization", format!("Bearer {}", provider.api_key))
+        .header("content-type", "application/json")
+        .json(&body)
+        .send()
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, model = %voice_model, "voice transcription request failed");
+            anyhow::anyhow!("request failed")
+        })?;
+    let status = response.status();
+    let response_body = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, model = %voice_model, "invalid transcription response");
+            anyhow::anyhow!("invalid response")
+        })?;
+    if !status.is_success() {
+        let message = response_body["error"]["message"]
+            .as_str()
+            .unwrap_or("unknown error");
+        tracing::warn!(
+            status = %status,
+            model = %voice_model,
+            error = %message,
+            "voice transcription provider returned error"
+        );
+        anyhow::bail!("{message}");
+    }
+
+    Ok(extract_transcript_text(&response_body))
+}
+
+/// Upload the audio to the provider's `/v1/audio/transcriptions` endpoint.
+async fn transcribe_with_api(
+    deps: &AgentDeps,
+    http: &reqwest::Client,
+    audio: &[u8],
+    format: &str,
+) -> anyhow::Result<String> {
+    let (voice_model, provider, model_name) = voice_provider(deps)?;
+    let file = reqwest::multipart::Part::bytes(audio.to_vec())
+        .file_name(format!("audio.{format}"))
+        .mime_str(&format!("audio/{format}"))?;
+    let form = reqwest::multipart::Form::new()
+        .text("model", model_name)
+        .text("response_format", "json")
+        .part("file", file);
+
+    let response = http
+        .post(format!(
+            "{}/v1/audio/transcriptions",
+            provider.base_url.trim_end_matches('/')
+        ))
+        .header("authorization", format!("Bearer {}", provider.api_key))
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, model = %voice_model, "transcription request failed");
+            anyhow::anyhow!("request failed")
+        })?;
+    let status = response.status();
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, model = %voice_model, "invalid transcription response");
+            anyhow::anyhow!("invalid response")
+        })?;
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("unknown error");
+        tracing::warn!(
+            status = %status,
+            model = %voice_model,
+            error = %message,
+            "transcription endpoint returned error"
+        );
+        anyhow::bail!("{message}");
+    }
+
+    Ok(body["text"].as_str().unwrap_or_default().trim().to_string())
+}
+
+/// Run a local whisper.cpp on the audio, converting it to the 16 kHz mono
+/// WAV whisper.cpp reads with ffmpeg when it isn't already.
+async fn transcribe_with_whisper_cpp(
+    config: &VoiceConfig,
+    audio: &[u8],
+    format: &str,
+) -> anyhow::Result<String> {
+    let Some(model) = &config.whisper_cpp_model else {
+        anyhow::bail!("whisper_cpp_model is not configured");
+    };
+    let directory = tempfile::tempdir()?;
+    let input = directory.path().join(format!("input.{format}"));
+    tokio::fs::write(&input, audio).await?;
+
+    let wav = if is_whisper_wav(audio) {
+        input
+    } else {
+        let wav = directory.path().join("input-16k.wav");
+        let output = run_with_timeout(
+            tokio::process::Command::new("ffmpeg")
+                .args(["-nostdin", "-loglevel", "error", "-y", "-i"])
+                .arg(&input)
+                .args(["-ar", "16000", "-ac", "1", "-c:a", "pcm_s16le"])
+                .arg(&wav),
+        )
+        .await
+        .map_err(|error| anyhow::anyhow!("can't convert audio with ffmpeg: {error}"))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "ffmpeg failed to convert audio: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        wav
+    };
+
+    let output = run_with_timeout(
+        tokio::process::Command::new(&config.whisper_cpp_binary)
+            .arg("-m")
+            .arg(model)
+            .arg("-f")
+            .arg(&wav)
+            .args(["--no-timestamps", "--no-prints", "-l", "auto"]),
+    )
+    .await
+    .map_err(|error| anyhow::anyhow!("can't run {}: {error}", config.whisper_cpp_binary))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "whisper.cpp failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Run a transcription helper, killing it if it hangs.
+async fn run_with_timeout(
+    command: &mut tokio::process::Command,
+) -> std::io::Result<std::process::Output> {
+    let output = command.kill_on_drop(true).output();
+    tokio::time::timeout(TRANSCRIPTION_TIMEOUT, output)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"))?
+}
+
+/// Whether audio is already the 16 kHz mono 16-bit WAV whisper.cpp expects.
+fn is_whisper_wav(audio: &[u8]) -> bool {
+    audio.len() >= 44
+        && &audio[0..4] == b"RIFF"
+        && &audio[8..12] == b"WAVE"
+        && audio[20..22] == 1u16.to_le_bytes()
+        && audio[22..24] == 1u16.to_le_bytes()
+        && audio[24..28] == 16_000u32.to_le_bytes()
+        && audio[34..36] == 16u16.to_le_bytes()
+}
+
+fn extract_transcript_text(body: &serde_json::Value) -> String {
+    if let Some(text) = body["choices"][0]["message"]["content"].as_str() {
+        return text.trim().to_string();
+    }
+
+    let Some(parts) = body["choices"][0]["message"]["content"].as_array() else {
+        return String::new();
+    };
+
+    parts
+        .iter()
+        .filter_map(|part| {
+            if part["type"].as_str() == Some("text") {
+                part["text"].as_str().map(str::trim)
+            } else {
+                None
+            }
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Speak text with the configured TTS model. Returns PCM at
+/// [`OUTPUT_SAMPLE_RATE`].
+async fn synthesize(
+    deps: &AgentDeps,
+    http: &reqwest::Client,
+    config: &VoiceConfig,
+    text: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let (provider_id, model_name) = deps
+        .llm_manager
+        .resolve_model(&config.tts_model)
+        .map_err(|_| anyhow::anyhow!("invalid TTS model '{}'", config.tts_model))?;
+    let provider = deps
+        .llm_manager
+        .get_provider(&provider_id)
+        .map_err(|_| anyhow::anyhow!("provider '{provider_id}' is not configured"))?;
+    if provider.api_type == ApiType::Anthropic {
+        anyhow::bail!("provider '{provider_id}' has no speech endpoint");
+    }
+
+    let response = http
+        .post(format!(
+            "{}/v1/audio/speech",
+            provider.base_url.trim_end_matches('/')
+        ))
+        .header("authorization", format!("Bearer {}", provider.api_key))
+        .json(&serde_json::json!({
+            "model": model_name,
+            "voice": config.tts_voice,
+            "input": text,
+            "response_format": "pcm",
+        }))
+        .send()
+        .await
+        .map_err(|error| anyhow::anyhow!("can't reach speech endpoint: {error}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("speech synthesis failed ({status}): {body}");
+    }
+    let audio = response
+        .bytes()
+        .await
+        .map_err(|error| anyhow::anyhow!("can't read synthesized audio: {error}"))?;
+    Ok(audio.to_vec())
+}
+
+/// The text of `response` to speak, when `message` asked for spoken replies.
+pub fn reply_to_speak(message: &InboundMessage, response: &OutboundResponse) -> Option<String> {
+    let speak = message
+        .metadata
+        .get(SPEAK_REPLIES_KEY)
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    if !speak {
+        return None;
+    }
+    let text = match response {
+        OutboundResponse::Text(text)
+        | OutboundResponse::ThreadReply { text, .. }
+        | OutboundResponse::RichMessage { text, .. }
+        | OutboundResponse::Rich { text, .. } => text.clone(),
+        OutboundResponse::Choices { prompt, options } => crate::choices_text(prompt, options),
+        _ => return None,
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Speak a reply where `message` was spoken, a sentence at a time so
+/// playback starts early. Failures are logged; the text reply has already
+/// gone out.
+pub async fn speak_reply(deps: &AgentDeps, message: &InboundMessage, text: &str) {
+    let config = deps.runtime_config.voice.load();
+    let Some(messaging_manager) = &deps.messaging_manager else {
+        return;
+    };
+    if !config.enabled {
+        return;
+    }
+    let http = deps.llm_manager.http_client();
+
+    let mut buffer = format!("{text} ");
+    let mut sentences = Vec::new();
+    while let Some(sentence) = take_sentence(&mut buffer) {
+        sentences.push(sentence);
+    }
+    if !buffer.trim().is_empty() {
+        sentences.push(buffer.trim().to_string());
+    }
+
+    for sentence in sentences {
+        let result = match synthesize(deps, http, &config, &sentence).await {
+            Ok(audio) => messaging_manager
+                .speak(message, audio, OUTPUT_SAMPLE_RATE)
+                .await
+                .map_err(anyhow::Error::from),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            tracing::warn!(
+                %error,
+                conversation_id = %message.conversation_id,
+                "can't speak reply"
+            );
+            return;
+        }
+    }
+}
+
+/// Take the first complete sentence off the front of `buffer`.
+fn take_sentence(buffer: &mut String) -> Option<String> {
+    loop {
+        let end = sentence_end(buffer)?;
+        let sentence = buffer[..end].trim().to_string();
+        buffer.drain(..end);
+        if !sentence.is_empty() {
+            return Some(sentence);
+        }
+    }
+}
+
+/// Byte offset just past the first sentence terminator followed by
+/// whitespace, so "3.5" doesn't end a sentence.
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut characters = text.char_indices().peekable();
+    while let Some((index, character)) = characters.next() {
+        let ends_sentence = match character {
+            '\n' => true,
+            '.' | '!' | '?' | ';' | ':' => characters
+                .peek()
+                .is_some_and(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if ends_sentence {
+            return Some(index + character.len_utf8());
+        }
+    }
+    None
+}
+
+/// A 16-bit mono WAV file around raw samples.
+pub fn encode_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_length = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + samples.len() * 2);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_length).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_length.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+#[derive(Debug, PartialEq)]
+enum Detection {
+    SpeechStarted,
+    UtteranceEnded(Vec<i16>),
+}
+
+/// Energy-based speech detection over fixed-length frames.
+struct SpeechDetector {
+    frame_length: usize,
+    threshold: f32,
+    min_speech_frames: usize,
+    silence_frames: usize,
+    pre_roll_frames: usize,
+    max_samples: usize,
+    /// Samples that don't fill a frame yet.
+    partial: Vec<i16>,
+    /// Recent frames before speech, or the utterance once speaking.
+    frames: std::collections::VecDeque<Vec<i16>>,
+    voiced_run: usize,
+    silent_run: usize,
+    speaking: bool,
+}
+
+impl SpeechDetector {
+    fn new(sample_rate: u32, config: &VoiceConfig) -> Self {
+        let frames_for = |milliseconds: u64| (milliseconds / FRAME_MS as u64).max(1) as usize;
+        Self {
+            frame_length: (sample_rate * FRAME_MS / 1000) as usize,
+            threshold: config.speech_threshold,
+            min_speech_frames: frames_for(MIN_SPEECH_MS as u64),
+            silence_frames: frames_for(config.silence_ms),
+            pre_roll_frames: frames_for(PRE_ROLL_MS as u64),
+            max_samples: (sample_rate as u64 * config.max_utterance_secs) as usize,
+            partial: Vec::new(),
+            frames: std::collections::VecDeque::new(),
+            voiced_run: 0,
+            silent_run: 0,
+            speaking: false,
+        }
+    }
+
+    fn push(&mut self, samples: &[i16]) -> Vec<Detection> {
+        self.partial.extend_from_slice(samples);
+        let mut detections = Vec::new();
+        while self.partial.len() >= self.frame_length {
+            let frame: Vec<i16> = self.partial.drain(..self.frame_length).collect();
+            if let Some(detection) = self.push_frame(frame) {
+                detections.push(detection);
+            }
+        }
+        detections
+    }
+
+    fn push_frame(&mut self, frame: Vec<i16>) -> Option<Detection> {
+        let voiced = rms(&frame) >= self.threshold;
+        self.frames.push_back(frame);
+
+        if !self.speaking {
+            self.voiced_run = if voiced { self.voiced_run + 1 } else { 0 };
+            if self.voiced_run >= self.min_speech_frames {
+                self.speaking = true;
+                self.silent_run = 0;
+                return Some(Detection::SpeechStarted);
+            }
+            while self.frames.len() > self.pre_roll_frames + self.min_speech_frames {
+                self.frames.pop_front();
+            }
+            return None;
+        }
+
+        self.silent_run = if voiced { 0 } else { self.silent_run + 1 };
+        let length = self.frames.len() * self.frame_length;
+        if self.silent_run >= self.silence_frames || length >= self.max_samples {
+            let utterance = self.frames.drain(..).flatten().collect();
+            self.speaking = false;
+            self.voiced_run = 0;
+            self.silent_run = 0;
+            return Some(Detection::UtteranceEnded(utterance));
+        }
+        None
+    }
+}
+
+/// Root mean square of a frame, from 0.0 (silence) to 1.0 (full scale).
+fn rms(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = frame
+        .iter()
+        .map(|sample| {
+            let sample = *sample as f64 / i16::MAX as f64;
+            sample * sample
+        })
+        .sum();
+    (sum / frame.len() as f64).sqrt() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(milliseconds: u32, amplitude: i16) -> Vec<i16> {
+        let samples = 16_000 * milliseconds / 1000;
+        (0..samples)
+            .map(|index| {
+                if index % 2 == 0 {
+                    amplitude
+                } else {
+                    -amplitude
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn utterances_are_split_on_silence() {
+        let config = VoiceConfig {
+            silence_ms: 200,
+            ..VoiceConfig::default()
+        };
+        let mut detector = SpeechDetector::new(16_000, &config);
+
+        assert!(detector.push(&tone(500, 0)).is_empty());
+        assert_eq!(
+            detector.push(&tone(400, 8_000)),
+            vec![Detection::SpeechStarted]
+        );
+        let detections = detector.push(&tone(300, 0));
+        let [Detection::UtteranceEnded(utterance)] = detections.as_slice() else {
+            panic!("expected the utterance to end, got {detections:?}");
+        };
+        // Speech, pre-roll and the trailing silence are all kept.
+        assert!(utterance.len() >= tone(400, 0).len());
+
+        // A cough is too short to count as speech.
+        assert!(detector.push(&tone(40, 8_000)).is_empty());
+        assert!(detector.push(&tone(100, 0)).is_empty());
+    }
+
+    #[test]
+    fn sentences_are_taken_as_they_complete() {
+        let mut buffer = String::from("Sure. The answer is 3.5 meters");
+        assert_eq!(take_sentence(&mut buffer), Some("Sure.".into()));
+        assert_eq!(take_sentence(&mut buffer), None);
+        buffer.push_str("! Anything else?");
+        assert_eq!(
+            take_sentence(&mut buffer),
+            Some("The answer is 3.5 meters!".into())
+        );
+        assert_eq!(buffer, " Anything else?");
+    }
+
+    #[test]
+    fn only_spoken_messages_get_spoken_replies() {
+        let mut message = InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "3".into(),
+            agent_id: None,
+            content: crate::MessageContent::Text(String::new()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        };
+        let reply = OutboundResponse::Text("On it.".into());
+        assert_eq!(reply_to_speak(&message, &reply), None);
+
+        message
+            .metadata
+            .insert(SPEAK_REPLIES_KEY.into(), serde_json::Value::Bool(true));
+        assert_eq!(reply_to_speak(&message, &reply), Some("On it.".into()));
+        assert_eq!(
+            reply_to_speak(&message, &OutboundResponse::StreamChunk("On".into())),
+            None
+        );
+    }
+
+    #[test]
+    fn wav_header_matches_samples() {
+        let wav = encode_wav(&[0, 1, -1], 16_000);
+        assert_eq!(wav.len(), 44 + 6);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 6);
+    }
+
+    #[test]
+    fn only_16k_mono_wav_skips_conversion() {
+        assert!(is_whisper_wav(&encode_wav(&[0; 160], 16_000)));
+        assert!(!is_whisper_wav(&encode_wav(&[0; 160], 24_000)));
+        assert!(!is_whisper_wav(b"OggS\0\0\0\0"));
+    }
+}
@@ -1,19 +1,34 @@
 //! Worker: Independent task execution process.
 
-use crate::agent::compactor::estimate_history_tokens;
+use crate::agent::artifact::ArtifactStore;
+use crate::agent::checkpoint::{WorkerCheckpoint, WorkerCheckpointStore};
+use crate::agent::compactor::{estimate_history_tokens, trim_process_history};
+use crate::agent::mailbox::Mailbox;
+use crate::agent::sub_worker::{SubWorkerSpawner, WorkerNesting};
+use crate::audit::AuditLogger;
 use crate::config::BrowserConfig;
+use crate::egress::EgressPolicy;
 use crate::error::Result;
 use crate::hooks::SpacebotHook;
+use crate::hooks::budget::{BUDGET_REASON_PREFIX, WorkerBudget};
+use crate::hooks::loop_guard::LOOP_GUARD_REASON_PREFIX;
+use crate::injection::InjectionGuard;
 use crate::llm::SpacebotModel;
 use crate::llm::routing::is_context_overflow_error;
+use crate::sandbox::Sandbox;
+use crate::tools::SpawnSubWorkerTool;
 use crate::{AgentDeps, ChannelId, ProcessId, ProcessType, WorkerId};
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use std::fmt::Write as _;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::sync::{mpsc, watch};
 use uuid::Uuid;
 
+/// Prefix of the result text of a worker that returned an error.
+pub const WORKER_FAILED_PREFIX: &str = "Worker failed: ";
+
 /// How many turns per segment before we check context and potentially compact.
 const TURNS_PER_SEGMENT: usize = 25;
 
@@ -62,6 +77,10 @@ pub struct Worker {
     /// Status updates.
     pub status_tx: watch::Sender<String>,
     pub status_rx: watch::Receiver<String>,
+    /// Checkpoint the worker continues from after a restart.
+    pub resumed: Option<WorkerCheckpoint>,
+    /// Depth and parent, when this is a sub-worker.
+    pub nesting: WorkerNesting,
 }
 
 impl Worker {
@@ -85,7 +104,8 @@ impl Worker {
             ProcessType::Worker,
             channel_id.clone(),
             deps.event_tx.clone(),
-        );
+        )
+        .with_loop_guard(deps.runtime_config.clone());
         let (status_tx, status_rx) = watch::channel("starting".to_string());
 
         Self {
@@ -103,9 +123,43 @@ impl Worker {
             logs_dir,
             status_tx,
             status_rx,
+            resumed: None,
+            nesting: WorkerNesting::default(),
         }
     }
 
+    /// Recreate a fire-and-forget worker from its checkpoint, keeping its ID.
+    pub fn resume(
+        checkpoint: WorkerCheckpoint,
+        deps: AgentDeps,
+        browser_config: BrowserConfig,
+        screenshot_dir: PathBuf,
+        brave_search_key: Option<String>,
+        logs_dir: PathBuf,
+    ) -> Self {
+        let mut worker = Self::new(
+            checkpoint.channel_id.clone(),
+            &checkpoint.task,
+            &checkpoint.system_prompt,
+            deps,
+            browser_config,
+            screenshot_dir,
+            brave_search_key,
+            logs_dir,
+        );
+        worker.id = checkpoint.worker_id;
+        worker.hook = SpacebotHook::new(
+            worker.deps.agent_id.clone(),
+            ProcessId::Worker(worker.id),
+            ProcessType::Worker,
+            worker.channel_id.clone(),
+            worker.deps.event_tx.clone(),
+        )
+        .with_loop_guard(worker.deps.runtime_config.clone());
+        worker.resumed = Some(checkpoint);
+        worker
+    }
+
     /// Create a new interactive worker.
     #[allow(clippy::too_many_arguments)]
     pub fn new_interactive(
@@ -126,7 +180,8 @@ impl Worker {
             ProcessType::Worker,
             channel_id.clone(),
             deps.event_tx.clone(),
-        );
+        )
+        .with_loop_guard(deps.runtime_config.clone());
         let (status_tx, status_rx) = watch::channel("starting".to_string());
         let (input_tx, input_rx) = mpsc::channel(32);
 
@@ -145,6 +200,8 @@ impl Worker {
             logs_dir,
             status_tx,
             status_rx,
+            resumed: None,
+            nesting: WorkerNesting::default(),
         };
 
         (worker, input_tx)
@@ -184,16 +241,93 @@ impl Worker {
     /// and compacts if the worker is approaching the context window limit.
     /// This prevents long-running workers from dying mid-task due to context
     /// exhaustion.
+    ///
+    /// Fire-and-forget workers other than sub-workers are checkpointed when they
+    /// start and after each segment, and the checkpoint is deleted once they're
+    /// done.
+    ///
+    /// The agent's `worker_budget` is enforced throughout. A worker over budget
+    /// is told to wrap up and reports what it got done; if it hasn't finished
+    /// `wrap_up_secs` later, it's cancelled.
     #[tracing::instrument(skip(self), fields(worker_id = %self.id))]
     pub async fn run(mut self) -> Result<String> {
+        let checkpointed = self.is_checkpointed();
+        let checkpoints = WorkerCheckpointStore::new(self.deps.sqlite_pool.clone());
+        let worker_id = self.id;
+
+        let rc = &self.deps.runtime_config;
+        let model_name = rc
+            .routing
+            .load()
+            .resolve(ProcessType::Worker, None)
+            .to_string();
+        let budget = Arc::new(WorkerBudget::new(
+            (**rc.worker_budget.load()).clone(),
+            model_name,
+        ));
+        self.hook = self.hook.with_budget(budget.clone());
+        let hook = self.hook.clone();
+        // Interactive workers spend most of their life waiting on the user.
+        let wall_time = self.input_rx.is_none();
+
+        let result = tokio::select! {
+            result = self.execute() => {
+                result.map(|text| budget.exceeded_result(&text).unwrap_or(text))
+            }
+            reason = budget.enforce(wall_time) => {
+                tracing::warn!(%worker_id, %reason, "worker over budget didn't wrap up, cancelling");
+                hook.send_status("cancelled (over budget)");
+                Ok(format!(
+                    "Worker cancelled, {BUDGET_REASON_PREFIX}: {reason}. It didn't wrap up in time, \
+                     so its progress wasn't reported."
+                ))
+            }
+        };
+
+        if checkpointed && let Err(error) = checkpoints.delete(worker_id).await {
+            tracing::warn!(%worker_id, %error, "failed to delete worker checkpoint");
+        }
+        result
+    }
+
+    async fn execute(mut self) -> Result<String> {
         self.status_tx.send_modify(|s| *s = "running".to_string());
         self.hook.send_status("running");
 
         tracing::info!(worker_id = %self.id, task_len = self.task.len(), "worker starting");
 
         let mcp_tools = self.deps.mcp_manager.get_tools().await;
+        let plugin_config = (**self.deps.runtime_config.plugins.load()).clone();
+        let plugins =
+            crate::plugins::load(&self.deps.runtime_config.workspace_dir, &plugin_config).await;
+        let plugin_tools = crate::tools::PluginTool::for_plugins(&plugins, &plugin_config);
 
         // Create per-worker ToolServer with task tools
+        let sandbox = Sandbox::new(
+            (**self.deps.runtime_config.sandbox.load()).clone(),
+            self.deps.runtime_config.workspace_dir.clone(),
+            self.id,
+        );
+
+        let injection_notice = self
+            .deps
+            .runtime_config
+            .prompts
+            .load()
+            .render_system_prompt_injection_notice()?;
+        let injection_guard = InjectionGuard::new(
+            (**self.deps.runtime_config.prompt_injection.load()).clone(),
+            injection_notice,
+            self.deps.agent_id.clone(),
+            ProcessId::Worker(self.id),
+            self.channel_id.clone(),
+            self.deps.event_tx.clone(),
+        );
+
+        // Other workers can message this one for as long as it runs
+        let (mailbox, _mailbox_guard) =
+            Mailbox::open(self.deps.agent_id.clone(), self.id, &self.task);
+
         let worker_tool_server = crate::tools::create_worker_tool_server(
             self.deps.agent_id.clone(),
             self.id,
@@ -205,8 +339,33 @@ impl Worker {
             self.deps.runtime_config.workspace_dir.clone(),
             self.deps.runtime_config.instance_dir.clone(),
             mcp_tools,
+            plugin_tools,
+            AuditLogger::new(self.deps.sqlite_pool.clone())
+                .with_actor(format!("worker:{}", self.id)),
+            sandbox,
+            injection_guard,
+            EgressPolicy::new(&self.deps.runtime_config.egress.load()),
+            (**self.deps.runtime_config.object_storage.load()).clone(),
+            mailbox,
+            ArtifactStore::new(
+                self.deps.sqlite_pool.clone(),
+                &self.deps.runtime_config.data_dir,
+            ),
         );
 
+        // Workers above the depth limit can hand parts of their task to
+        // sub-workers, which are stopped when this worker ends
+        let _sub_worker_guard = match SubWorkerSpawner::for_worker(&self) {
+            Some((spawner, guard)) => {
+                worker_tool_server
+                    .add_tool(SpawnSubWorkerTool::new(spawner))
+                    .await
+                    .map_err(|error| crate::error::AgentError::Other(error.into()))?;
+                Some(guard)
+            }
+            None => None,
+        };
+
         let routing = self.deps.runtime_config.routing.load();
         let model_name = routing.resolve(ProcessType::Worker, None).to_string();
         let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
@@ -219,12 +378,24 @@ impl Worker {
             .tool_server_handle(worker_tool_server)
             .build();
 
-        // Fresh history for the worker (no channel context)
-        let mut history = Vec::new();
+        // Fresh history for the worker (no channel context), or the history
+        // it had at its last checkpoint
+        let (mut history, mut prompt, mut segments_run) = match self.resumed.take() {
+            Some(checkpoint) => {
+                let prompt_engine = self.deps.runtime_config.prompts.load();
+                (
+                    checkpoint.history,
+                    prompt_engine.render_system_worker_resume()?,
+                    checkpoint.segments,
+                )
+            }
+            None => (Vec::new(), self.task.clone(), 0),
+        };
+        // A resumed history may already be near the limit.
+        self.maybe_compact_history(&mut history).await;
+        self.checkpoint(&history, segments_run).await;
 
         // Run the initial task in segments with compaction checkpoints
-        let mut prompt = self.task.clone();
-        let mut segments_run = 0;
         let mut overflow_retries = 0;
 
         let result = loop {
@@ -269,6 +440,7 @@ impl Worker {
                     }
 
                     self.maybe_compact_history(&mut history).await;
+                    self.checkpoint(&history, segments_run).await;
                     prompt = "Continue where you left off. Do not repeat completed work.".into();
                     self.hook
                         .send_status(format!("working (segment {segments_run})"));
@@ -279,6 +451,24 @@ impl Worker {
                         "continuing to next segment"
                     );
                 }
+                Err(rig::completion::PromptError::PromptCancelled { reason, .. })
+                    if reason.starts_with(LOOP_GUARD_REASON_PREFIX) && self.input_rx.is_some() =>
+                {
+                    // Interactive workers pause instead of failing, so the user
+                    // can steer them out of the loop with a follow-up.
+                    drop_dangling_tool_call(&mut history);
+                    tracing::warn!(worker_id = %self.id, %reason, "worker paused by loop guard");
+                    break format!("Worker paused: {reason}");
+                }
+                Err(rig::completion::PromptError::PromptCancelled { reason, .. })
+                    if reason.starts_with(BUDGET_REASON_PREFIX) =>
+                {
+                    // Over budget and still not wrapping up. `run` reports
+                    // what was exceeded along with the last thing it wrote.
+                    drop_dangling_tool_call(&mut history);
+                    tracing::warn!(worker_id = %self.id, %reason, "worker stopped by budget");
+                    break extract_last_assistant_text(&history).unwrap_or_default();
+                }
                 Err(rig::completion::PromptError::PromptCancelled { reason, .. }) => {
                     self.state = WorkerState::Failed;
                     self.hook.send_status("cancelled");
@@ -303,6 +493,7 @@ impl Worker {
                     );
                     self.hook.send_status("compacting (overflow recovery)");
                     self.force_compact_history(&mut history).await;
+                    self.checkpoint(&history, segments_run).await;
                     prompt = "Continue where you left off. Do not repeat completed work. \
                               Your previous attempt exceeded the context limit, so older history \
                               has been compacted."
@@ -318,8 +509,11 @@ impl Worker {
             }
         };
 
-        // For interactive workers, enter a follow-up loop
-        if let Some(mut input_rx) = self.input_rx.take() {
+        // For interactive workers, enter a follow-up loop, unless they're
+        // already over budget
+        if let Some(mut input_rx) = self.input_rx.take()
+            && self.hook.budget_exceeded().is_none()
+        {
             self.state = WorkerState::WaitingForInput;
             self.hook.send_status("waiting for input");
 
@@ -341,6 +535,14 @@ impl Worker {
                         .await
                     {
                         Ok(_response) => break true,
+                        Err(rig::completion::PromptError::PromptCancelled { reason, .. })
+                            if reason.starts_with(LOOP_GUARD_REASON_PREFIX)
+                                || reason.starts_with(BUDGET_REASON_PREFIX) =>
+                        {
+                            drop_dangling_tool_call(&mut history);
+                            tracing::warn!(worker_id = %self.id, %reason, "worker follow-up stopped");
+                            break true;
+                        }
                         Err(error) if is_context_overflow_error(&error.to_string()) => {
                             follow_up_overflow_retries += 1;
                             if follow_up_overflow_retries > MAX_OVERFLOW_RETRIES {
@@ -368,7 +570,9 @@ impl Worker {
                     }
                 };
 
-                if follow_up_ok {
+                if self.hook.budget_exceeded().is_some() {
+                    break;
+                } else if follow_up_ok {
                     self.state = WorkerState::WaitingForInput;
                     self.hook.send_status("waiting for input");
                 } else {
@@ -392,22 +596,58 @@ impl Worker {
         Ok(result)
     }
 
+    /// Interactive workers and sub-workers aren't checkpointed: nobody is
+    /// waiting for a resumed interactive worker, and a sub-worker's parent
+    /// respawns it if it still needs it.
+    fn is_checkpointed(&self) -> bool {
+        self.input_rx.is_none() && self.nesting.parent.is_none()
+    }
+
+    /// Save the worker's progress so it can be resumed after a restart.
+    async fn checkpoint(&self, history: &[rig::message::Message], segments: usize) {
+        if self.is_checkpointed()
+            && let Err(error) = WorkerCheckpointStore::new(self.deps.sqlite_pool.clone())
+                .save(
+                    self.id,
+                    self.channel_id.as_ref(),
+                    &self.task,
+                    &self.system_prompt,
+                    history,
+                    segments,
+                )
+                .await
+        {
+            tracing::warn!(worker_id = %self.id, %error, "failed to checkpoint worker");
+        }
+    }
+
     /// Check context usage and compact history if approaching the limit.
     ///
     /// Workers don't have a full Compactor instance — they do inline compaction
-    /// by summarizing older tool calls and results into a condensed recap.
-    /// No LLM call, just programmatic truncation with a summary marker.
+    /// with the `compaction.worker` settings: oversized tool results are
+    /// trimmed first, then older tool calls and results are summarized into a
+    /// condensed recap. No LLM call, just programmatic truncation with a
+    /// summary marker.
     async fn maybe_compact_history(&self, history: &mut Vec<rig::message::Message>) {
-        let context_window = **self.deps.runtime_config.context_window.load();
-        let estimated = estimate_history_tokens(history);
-        let usage = estimated as f32 / context_window as f32;
+        let rc = &self.deps.runtime_config;
+        let context_window = **rc.context_window.load();
+        let compaction = **rc.compaction.load();
 
-        if usage < 0.70 {
+        if !trim_process_history(
+            history,
+            context_window,
+            compaction.worker.threshold,
+            compaction.tool_output_max_chars,
+        ) {
             return;
         }
 
-        self.compact_history(history, 0.50, "worker history compacted")
-            .await;
+        self.compact_history(
+            history,
+            compaction.worker.fraction,
+            "worker history compacted",
+        )
+        .await;
     }
 
     /// Aggressive compaction for context overflow recovery.
@@ -697,8 +937,20 @@ fn build_worker_recap(messages: &[rig::message::Message]) -> String {
     }
 }
 
+/// Remove a trailing assistant tool-call message that never got its results,
+/// which happens when the hook terminates a turn mid-batch. Leaving it in
+/// place makes the next prompt fail on the provider side.
+fn drop_dangling_tool_call(history: &mut Vec<rig::message::Message>) {
+    if let Some(rig::message::Message::Assistant { content, .. }) = history.last()
+        && content
+            .iter()
+            .any(|part| matches!(part, rig::message::AssistantContent::ToolCall(_)))
+    {
+        history.pop();
+    }
+}
+
 /// Extract the last assistant text message from a history.
-#[allow(dead_code)]
 fn extract_last_assistant_text(history: &[rig::message::Message]) -> Option<String> {
     for message in history.iter().rev() {
         if let rig::message::Message::Assistant { content, .. } = message {
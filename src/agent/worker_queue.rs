@@ -0,0 +1,500 @@
+//! Worker queue: the agent-wide cap on running workers.
+//!
+//! Every worker, whether a channel spawned it, an automation did, another
+//! worker did as a sub-worker, or it was resumed after a restart, takes a
+//! slot before it runs. While `[worker_queue] max_concurrent` workers are
+//! running, or its channel already runs `max_concurrent_workers`, a new
+//! worker waits in the queue instead of failing. Waiting workers start as
+//! slots free up, oldest first or most urgent first depending on `dispatch`.
+//! Spawning only fails once `max_queued` workers are waiting.
+//!
+//! A waiting worker is already spawned: it has its ID, shows in its channel's
+//! status block as `queued (position N)`, and can be cancelled like a running
+//! one. Positions are reported as `WorkerStatus` events whenever they change.
+//!
+//! At shutdown the queue closes: running workers finish, waiting ones never
+//! start (see [`crate::shutdown`]).
+
+use crate::agent::task_queue::TaskPriority;
+use crate::config::{WorkerDispatch, WorkerQueueConfig};
+use crate::error::AgentError;
+use crate::{AgentId, ChannelId, ProcessEvent, WorkerId};
+
+use tokio::sync::{broadcast, oneshot};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Status of a waiting worker, followed by its position.
+pub const QUEUED_STATUS: &str = "queued";
+
+/// Status reported when a waiting worker gets its slot.
+const STARTING_STATUS: &str = "starting";
+
+/// The status shown for a worker at `position` in the queue.
+pub fn queued_status(position: usize) -> String {
+    format!("{QUEUED_STATUS} (position {position})")
+}
+
+/// Whether `status` is that of a waiting worker.
+pub fn is_queued_status(status: &str) -> bool {
+    status.starts_with(QUEUED_STATUS)
+}
+
+/// A worker asking for a slot.
+#[derive(Debug, Clone)]
+pub struct SlotRequest {
+    pub worker_id: WorkerId,
+    /// The channel the worker belongs to, if any.
+    pub channel_id: Option<ChannelId>,
+    /// Most workers the channel may run at once.
+    pub channel_limit: usize,
+    pub priority: TaskPriority,
+}
+
+/// One agent's running workers and the ones waiting for a slot.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    running: usize,
+    running_per_channel: HashMap<ChannelId, usize>,
+    /// Waiting workers, in dispatch order.
+    waiting: Vec<Waiting>,
+    next_seq: u64,
+    /// Settings as of the latest request, used when a slot frees up.
+    config: WorkerQueueConfig,
+    /// Where position updates go.
+    events: Option<(AgentId, broadcast::Sender<ProcessEvent>)>,
+    /// Shutting down: nothing starts any more.
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct Waiting {
+    request: SlotRequest,
+    seq: u64,
+    tx: oneshot::Sender<()>,
+    /// Position last reported for the worker.
+    reported: usize,
+}
+
+impl WorkerQueue {
+    /// Take a slot for a worker, or a place in the queue when none is free.
+    /// Fails when the queue is full.
+    pub fn request(
+        &self,
+        request: SlotRequest,
+        config: &WorkerQueueConfig,
+        agent_id: &AgentId,
+        event_tx: &broadcast::Sender<ProcessEvent>,
+    ) -> Result<Ticket, AgentError> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(AgentError::ShuttingDown);
+        }
+        state.config = config.clone();
+        state.events = Some((agent_id.clone(), event_tx.clone()));
+        // The limits may have been raised since the last request.
+        state.dispatch();
+
+        let mut ticket = Ticket {
+            queue: self.clone(),
+            worker_id: request.worker_id,
+            channel_id: request.channel_id.clone(),
+            position: None,
+            held: false,
+            rx: None,
+        };
+        if state.has_room(&request) {
+            state.take(request.channel_id.as_ref());
+            ticket.held = true;
+            return Ok(ticket);
+        }
+        if state.waiting.len() >= state.config.max_queued {
+            return Err(AgentError::WorkerQueueFull {
+                max_queued: state.config.max_queued,
+            });
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.waiting.push(Waiting {
+            request,
+            seq,
+            tx,
+            reported: 0,
+        });
+        state.sort();
+        let position = state.position(ticket.worker_id);
+        // The caller reports the new worker's position itself, once the
+        // worker is known to have started.
+        if let Some(waiting) = state
+            .waiting
+            .iter_mut()
+            .find(|waiting| waiting.request.worker_id == ticket.worker_id)
+        {
+            waiting.reported = position.unwrap_or_default();
+        }
+        state.report_positions();
+
+        ticket.position = position;
+        ticket.rx = Some(rx);
+        Ok(ticket)
+    }
+
+    /// A waiting worker's position in the queue, from 1.
+    pub fn position(&self, worker_id: WorkerId) -> Option<usize> {
+        self.lock().position(worker_id)
+    }
+
+    /// Workers running and waiting.
+    pub fn counts(&self) -> (usize, usize) {
+        let state = self.lock();
+        (state.running, state.waiting.len())
+    }
+
+    /// Stop starting workers, for shutdown. Running workers keep their slots,
+    /// waiting ones stay queued and new requests fail.
+    pub fn close(&self) {
+        self.lock().closed = true;
+    }
+
+    /// Drop a waiting worker. Returns false when it isn't waiting.
+    fn leave(&self, worker_id: WorkerId) -> bool {
+        let mut state = self.lock();
+        let before = state.waiting.len();
+        state
+            .waiting
+            .retain(|waiting| waiting.request.worker_id != worker_id);
+        let left = state.waiting.len() < before;
+        if left {
+            state.report_positions();
+        }
+        left
+    }
+
+    /// Give back a slot and start whoever can run next.
+    fn release(&self, channel_id: Option<&ChannelId>) {
+        let mut state = self.lock();
+        state.running = state.running.saturating_sub(1);
+        if let Some(channel_id) = channel_id
+            && let Some(count) = state.running_per_channel.get_mut(channel_id)
+        {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                state.running_per_channel.remove(channel_id);
+            }
+        }
+        state.dispatch();
+        state.report_positions();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl QueueState {
+    fn has_room(&self, request: &SlotRequest) -> bool {
+        self.running < self.config.max_concurrent
+            && request.channel_id.as_ref().is_none_or(|channel_id| {
+                self.running_per_channel
+                    .get(channel_id)
+                    .copied()
+                    .unwrap_or_default()
+                    < request.channel_limit
+            })
+    }
+
+    fn take(&mut self, channel_id: Option<&ChannelId>) {
+        self.running += 1;
+        if let Some(channel_id) = channel_id {
+            *self
+                .running_per_channel
+                .entry(channel_id.clone())
+                .or_default() += 1;
+        }
+    }
+
+    fn sort(&mut self) {
+        match self.config.dispatch {
+            WorkerDispatch::Fifo => self.waiting.sort_by_key(|waiting| waiting.seq),
+            WorkerDispatch::Priority => self
+                .waiting
+                .sort_by_key(|waiting| (std::cmp::Reverse(waiting.request.priority), waiting.seq)),
+        }
+    }
+
+    fn position(&self, worker_id: WorkerId) -> Option<usize> {
+        self.waiting
+            .iter()
+            .position(|waiting| waiting.request.worker_id == worker_id)
+            .map(|index| index + 1)
+    }
+
+    /// Start waiting workers, in order, while there are slots. A worker whose
+    /// channel is at its limit is passed over for the next one.
+    fn dispatch(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.sort();
+        let mut index = 0;
+        while index < self.waiting.len() && self.running < self.config.max_concurrent {
+            if !self.has_room(&self.waiting[index].request) {
+                index += 1;
+                continue;
+            }
+            let waiting = self.waiting.remove(index);
+            self.take(waiting.request.channel_id.as_ref());
+            // Sent under the lock, so a ticket dropped at the same time sees
+            // either its place in the queue or its slot.
+            if waiting.tx.send(()).is_err() {
+                self.running -= 1;
+                if let Some(channel_id) = &waiting.request.channel_id
+                    && let Some(count) = self.running_per_channel.get_mut(channel_id)
+                {
+                    *count -= 1;
+                }
+                continue;
+            }
+            self.send_status(&waiting.request, STARTING_STATUS.into());
+        }
+    }
+
+    fn report_positions(&mut self) {
+        let mut changed = Vec::new();
+        for (index, waiting) in self.waiting.iter_mut().enumerate() {
+            if waiting.reported != index + 1 {
+                waiting.reported = index + 1;
+                changed.push((waiting.request.clone(), index + 1));
+            }
+        }
+        for (request, position) in changed {
+            self.send_status(&request, queued_status(position));
+        }
+    }
+
+    fn send_status(&self, request: &SlotRequest, status: String) {
+        if let Some((agent_id, event_tx)) = &self.events {
+            event_tx
+                .send(ProcessEvent::WorkerStatus {
+                    agent_id: agent_id.clone(),
+                    worker_id: request.worker_id,
+                    channel_id: request.channel_id.clone(),
+                    status,
+                })
+                .ok();
+        }
+    }
+}
+
+/// A worker's claim on a slot: held already, or a place in the queue.
+#[derive(Debug)]
+pub struct Ticket {
+    queue: WorkerQueue,
+    worker_id: WorkerId,
+    channel_id: Option<ChannelId>,
+    position: Option<usize>,
+    /// The slot is taken but not handed out yet.
+    held: bool,
+    /// Fires when a waiting worker gets its slot.
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Ticket {
+    /// Position in the queue when the worker had to wait, from 1.
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    /// Wait for the slot. Fails when the worker's place in the queue was
+    /// dropped without a slot being handed to it.
+    pub async fn wait(mut self) -> Result<WorkerSlot, AgentError> {
+        if let Some(rx) = self.rx.as_mut()
+            && rx.await.is_err()
+        {
+            // The sender is only dropped along with the queue, at shutdown.
+            // No slot was taken for us, so there's none to give back.
+            tracing::debug!(worker_id = %self.worker_id, "worker queue dropped, slot cancelled");
+            self.rx = None;
+            return Err(AgentError::ShuttingDown);
+        }
+        self.rx = None;
+        self.held = false;
+        Ok(WorkerSlot {
+            queue: self.queue.clone(),
+            channel_id: self.channel_id.clone(),
+        })
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if self.held {
+            self.queue.release(self.channel_id.as_ref());
+        } else if let Some(mut rx) = self.rx.take()
+            && !self.queue.leave(self.worker_id)
+            && rx.try_recv().is_ok()
+        {
+            // Dispatched just as the worker was cancelled.
+            self.queue.release(self.channel_id.as_ref());
+        }
+    }
+}
+
+/// A running worker's slot, given back when it's dropped.
+#[derive(Debug)]
+pub struct WorkerSlot {
+    queue: WorkerQueue,
+    channel_id: Option<ChannelId>,
+}
+
+impl Drop for WorkerSlot {
+    fn drop(&mut self) {
+        self.queue.release(self.channel_id.as_ref());
+    }
+}
+
+/// Run `future` once `ticket` has its slot, holding the slot until it's done.
+/// Fails without running it when the slot never comes.
+pub async fn run_with_slot<F, T, E>(ticket: Ticket, future: F) -> Result<T, E>
+where
+    F: std::future::Future<Output = Result<T, E>>,
+    E: From<AgentError>,
+{
+    let _slot = ticket.wait().await?;
+    future.await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_concurrent: usize, max_queued: usize) -> WorkerQueueConfig {
+        WorkerQueueConfig {
+            max_concurrent,
+            max_queued,
+            dispatch: WorkerDispatch::Priority,
+        }
+    }
+
+    fn request(channel: &str, priority: TaskPriority) -> SlotRequest {
+        SlotRequest {
+            worker_id: uuid::Uuid::new_v4(),
+            channel_id: Some(Arc::from(channel)),
+            channel_limit: 5,
+            priority,
+        }
+    }
+
+    #[tokio::test]
+    async fn waiting_workers_start_by_priority_as_slots_free() {
+        let queue = WorkerQueue::default();
+        let (event_tx, _) = broadcast::channel(16);
+        let agent_id: AgentId = Arc::from("main");
+        let config = config(1, 2);
+        let join = |request| queue.request(request, &config, &agent_id, &event_tx);
+
+        let running = join(request("a", TaskPriority::Normal)).unwrap();
+        assert_eq!(running.position(), None);
+        let low = join(request("a", TaskPriority::Low)).unwrap();
+        let urgent = join(request("b", TaskPriority::Urgent)).unwrap();
+        assert_eq!(low.position(), Some(1));
+        assert_eq!(urgent.position(), Some(1));
+        assert_eq!(queue.position(low.worker_id), Some(2));
+        assert!(matches!(
+            join(request("c", TaskPriority::High)),
+            Err(AgentError::WorkerQueueFull { max_queued: 2 })
+        ));
+
+        let slot = running.wait().await.unwrap();
+        drop(slot);
+        assert_eq!(queue.counts(), (1, 1));
+        let urgent_slot = urgent.wait().await.unwrap();
+        assert_eq!(queue.position(low.worker_id), Some(1));
+
+        // A cancelled waiting worker gives up its place.
+        drop(low);
+        assert_eq!(queue.counts(), (1, 0));
+        drop(urgent_slot);
+        assert_eq!(queue.counts(), (0, 0));
+    }
+
+    #[test]
+    fn channel_limits_pass_over_to_the_next_worker() {
+        let queue = WorkerQueue::default();
+        let (event_tx, _) = broadcast::channel(16);
+        let agent_id: AgentId = Arc::from("main");
+        let config = config(3, 5);
+        let limited = |channel| SlotRequest {
+            channel_limit: 1,
+            ..request(channel, TaskPriority::Normal)
+        };
+        let join = |request| queue.request(request, &config, &agent_id, &event_tx);
+
+        let _first = join(limited("a")).unwrap();
+        let second = join(limited("a")).unwrap();
+        let other = join(limited("b")).unwrap();
+        assert_eq!(second.position(), Some(1));
+        assert_eq!(other.position(), None);
+        assert_eq!(queue.counts(), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_place_gives_back_no_slot() {
+        let queue = WorkerQueue::default();
+        let (event_tx, _) = broadcast::channel(16);
+        let agent_id: AgentId = Arc::from("main");
+        let _running = queue
+            .request(
+                request("a", TaskPriority::Normal),
+                &config(1, 5),
+                &agent_id,
+                &event_tx,
+            )
+            .unwrap();
+
+        // A place whose sender went away without handing out a slot.
+        let (tx, rx) = oneshot::channel();
+        drop(tx);
+        let ticket = Ticket {
+            queue: queue.clone(),
+            worker_id: uuid::Uuid::new_v4(),
+            channel_id: Some(Arc::from("a")),
+            position: Some(1),
+            held: false,
+            rx: Some(rx),
+        };
+        assert!(matches!(ticket.wait().await, Err(AgentError::ShuttingDown)));
+        assert_eq!(queue.counts(), (1, 0));
+    }
+
+    #[test]
+    fn closed_queues_start_nothing() {
+        let queue = WorkerQueue::default();
+        let (event_tx, _) = broadcast::channel(16);
+        let agent_id: AgentId = Arc::from("main");
+        let config = config(1, 5);
+        let join = |request| queue.request(request, &config, &agent_id, &event_tx);
+
+        let running = join(request("a", TaskPriority::Normal)).unwrap();
+        let waiting = join(request("a", TaskPriority::Normal)).unwrap();
+        queue.close();
+        assert!(matches!(
+            join(request("b", TaskPriority::Urgent)),
+            Err(AgentError::ShuttingDown)
+        ));
+
+        drop(running);
+        assert_eq!(queue.counts(), (0, 1));
+        assert_eq!(queue.position(waiting.worker_id), Some(1));
+    }
+}
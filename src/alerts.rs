@@ -0,0 +1,482 @@
+//! Operator alerts for outages.
+//!
+//! A background monitor health-checks every messaging adapter on an interval
+//! and listens for LLM provider circuit changes. When an adapter fails
+//! `adapter_failure_threshold` checks in a row, or a provider's circuit opens,
+//! an alert goes to each configured target. Agents crossing a daily spend
+//! limit, and processes stopped by the loop guard, are reported the same way. An HTTP(S) URL receives a JSON
+//! POST, and an `adapter:target` string (e.g. `discord:123456789`) is sent
+//! through that messaging adapter. Repeats of the same alert are suppressed
+//! for `cooldown_secs`.
+
+use crate::llm::LlmManager;
+use crate::llm::manager::ProviderCircuitEvent;
+use crate::llm::spend::SpendEvent;
+use crate::messaging::MessagingManager;
+use crate::messaging::target::{BroadcastTarget, parse_delivery_target};
+use crate::{AgentId, OutboundResponse, ProcessEvent};
+
+use serde::Serialize;
+use tokio::sync::{Mutex, broadcast};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Alert delivery settings (instance-level).
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    /// Where alerts go. Alerting is off when empty.
+    pub targets: Vec<AlertTarget>,
+    /// Minimum time between two alerts with the same key.
+    pub cooldown_secs: u64,
+    /// How often adapters are health-checked.
+    pub health_check_interval_secs: u64,
+    /// Consecutive failed health checks before an adapter is reported down.
+    pub adapter_failure_threshold: u32,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            cooldown_secs: 900,
+            health_check_interval_secs: 60,
+            adapter_failure_threshold: 3,
+        }
+    }
+}
+
+/// A destination for alerts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertTarget {
+    /// POST the alert as JSON to this URL.
+    Webhook(String),
+    /// Send the alert text through a messaging adapter.
+    Messaging(BroadcastTarget),
+}
+
+impl AlertTarget {
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.starts_with("https://") || value.starts_with("http://") {
+            return reqwest::Url::parse(value)
+                .ok()
+                .map(|_| Self::Webhook(value.to_string()));
+        }
+        parse_delivery_target(value).map(Self::Messaging)
+    }
+}
+
+impl std::fmt::Display for AlertTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Webhook(url) => write!(f, "{url}"),
+            Self::Messaging(target) => write!(f, "{target}"),
+        }
+    }
+}
+
+/// Something an operator should know about.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Alert {
+    AdapterDown {
+        adapter: String,
+        failures: u32,
+        error: String,
+    },
+    AdapterRecovered {
+        adapter: String,
+    },
+    ProviderDown {
+        provider: String,
+        error: String,
+    },
+    ProviderRecovered {
+        provider: String,
+    },
+    SpendSoftLimit {
+        agent_id: String,
+        spent_usd: f64,
+        limit_usd: f64,
+        downgrade_model: Option<String>,
+    },
+    SpendHardLimit {
+        agent_id: String,
+        spent_usd: f64,
+        limit_usd: f64,
+    },
+    LoopDetected {
+        agent_id: String,
+        process: String,
+        detail: String,
+    },
+}
+
+impl Alert {
+    /// Cooldown key. Down and recovered alerts for the same component have
+    /// distinct keys so a recovery is never suppressed by the outage alert.
+    pub fn key(&self) -> String {
+        match self {
+            Self::AdapterDown { adapter, .. } => format!("adapter_down:{adapter}"),
+            Self::AdapterRecovered { adapter } => format!("adapter_recovered:{adapter}"),
+            Self::ProviderDown { provider, .. } => format!("provider_down:{provider}"),
+            Self::ProviderRecovered { provider } => format!("provider_recovered:{provider}"),
+            Self::SpendSoftLimit { agent_id, .. } => format!("spend_soft_limit:{agent_id}"),
+            Self::SpendHardLimit { agent_id, .. } => format!("spend_hard_limit:{agent_id}"),
+            Self::LoopDetected {
+                agent_id, process, ..
+            } => format!("loop_detected:{agent_id}:{process}"),
+        }
+    }
+
+    /// Human-readable text for chat targets.
+    pub fn message(&self) -> String {
+        match self {
+            Self::AdapterDown {
+                adapter,
+                failures,
+                error,
+            } => format!(
+                "Spacebot alert: the {adapter} adapter failed {failures} health checks in a row: {error}"
+            ),
+            Self::AdapterRecovered { adapter } => {
+                format!("Spacebot: the {adapter} adapter is healthy again")
+            }
+            Self::ProviderDown { provider, error } => {
+                format!("Spacebot alert: LLM provider {provider} is failing: {error}")
+            }
+            Self::ProviderRecovered { provider } => {
+                format!("Spacebot: LLM provider {provider} recovered")
+            }
+            Self::SpendSoftLimit {
+                agent_id,
+                spent_usd,
+                limit_usd,
+                downgrade_model,
+            } => {
+                let action = match downgrade_model {
+                    Some(model) => format!("switching to {model} for the rest of the day"),
+                    None => "no downgrade model is configured".into(),
+                };
+                format!(
+                    "Spacebot alert: agent {agent_id} spent ${spent_usd:.2} today, past its ${limit_usd:.2} soft limit; {action}"
+                )
+            }
+            Self::SpendHardLimit {
+                agent_id,
+                spent_usd,
+                limit_usd,
+            } => format!(
+                "Spacebot alert: agent {agent_id} spent ${spent_usd:.2} today, past its ${limit_usd:.2} hard limit; LLM calls are paused until 00:00 UTC"
+            ),
+            Self::LoopDetected {
+                agent_id,
+                process,
+                detail,
+            } => format!("Spacebot alert: agent {agent_id} stopped {process}: {detail}"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    alert: &'a Alert,
+    message: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Delivers alerts to the configured targets with per-key cooldown.
+pub struct AlertDispatcher {
+    config: AlertConfig,
+    messaging_manager: Arc<MessagingManager>,
+    http: reqwest::Client,
+    last_sent: Mutex<HashMap<String, Instant>>,
+}
+
+impl AlertDispatcher {
+    pub fn new(config: AlertConfig, messaging_manager: Arc<MessagingManager>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            config,
+            messaging_manager,
+            http,
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.targets.is_empty()
+    }
+
+    /// Send an alert to every target unless the same alert went out within
+    /// the cooldown. Returns whether it was sent.
+    pub async fn send(&self, alert: Alert) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        let key = alert.key();
+        {
+            let mut last_sent = self.last_sent.lock().await;
+            let cooldown = Duration::from_secs(self.config.cooldown_secs);
+            if last_sent
+                .get(&key)
+                .is_some_and(|sent_at| sent_at.elapsed() < cooldown)
+            {
+                tracing::debug!(%key, "alert suppressed by cooldown");
+                return false;
+            }
+            last_sent.insert(key.clone(), Instant::now());
+        }
+
+        let message = alert.message();
+        tracing::warn!(%key, %message, "sending alert");
+
+        for target in &self.config.targets {
+            let result = match target {
+                AlertTarget::Webhook(url) => self.post_webhook(url, &alert, &message).await,
+                AlertTarget::Messaging(target) => self
+                    .messaging_manager
+                    .broadcast(
+                        &target.adapter,
+                        &target.target,
+                        OutboundResponse::Text(message.clone()),
+                    )
+                    .await
+                    .map_err(|error| error.to_string()),
+            };
+            if let Err(error) = result {
+                tracing::error!(%target, %error, "failed to deliver alert");
+            }
+        }
+        true
+    }
+
+    async fn post_webhook(&self, url: &str, alert: &Alert, message: &str) -> Result<(), String> {
+        let payload = WebhookPayload {
+            alert,
+            message: message.to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        self.http
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// Start the background monitor. Does nothing when no targets are configured.
+pub fn spawn_monitor(dispatcher: Arc<AlertDispatcher>, llm_manager: Arc<LlmManager>) {
+    if !dispatcher.is_enabled() {
+        return;
+    }
+
+    let mut circuit_rx = llm_manager.subscribe_circuit_events();
+    let mut spend_rx = llm_manager.subscribe_spend_events();
+    let interval = Duration::from_secs(dispatcher.config.health_check_interval_secs);
+    let threshold = dispatcher.config.adapter_failure_threshold;
+
+    tracing::info!(
+        targets = dispatcher.config.targets.len(),
+        "alert monitor started"
+    );
+    tokio::spawn(async move {
+        let mut failures: HashMap<String, u32> = HashMap::new();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    for (adapter, result) in dispatcher.messaging_manager.health_check_all().await {
+                        if let Some(alert) = track_adapter_health(&mut failures, threshold, adapter, result) {
+                            dispatcher.send(alert).await;
+                        }
+                    }
+                }
+                event = circuit_rx.recv() => match event {
+                    Ok(ProviderCircuitEvent::Opened { provider, last_error }) => {
+                        dispatcher.send(Alert::ProviderDown { provider, error: last_error }).await;
+                    }
+                    Ok(ProviderCircuitEvent::Closed { provider }) => {
+                        dispatcher.send(Alert::ProviderRecovered { provider }).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "alert monitor lagged on provider circuit events");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                event = spend_rx.recv() => match event {
+                    Ok(event) => {
+                        dispatcher.send(spend_alert(event)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "alert monitor lagged on spend events");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+    });
+}
+
+/// Forward an agent's loop guard trips to the dispatcher. Does nothing when
+/// no targets are configured.
+pub fn watch_agent_events(
+    dispatcher: Arc<AlertDispatcher>,
+    agent_id: AgentId,
+    mut event_rx: broadcast::Receiver<ProcessEvent>,
+) {
+    if !dispatcher.is_enabled() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            match event_rx.recv().await {
+                Ok(ProcessEvent::LoopDetected {
+                    agent_id,
+                    process_id,
+                    detail,
+                    ..
+                }) => {
+                    dispatcher
+                        .send(Alert::LoopDetected {
+                            agent_id: agent_id.to_string(),
+                            process: process_id.to_string(),
+                            detail,
+                        })
+                        .await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    crate::events::record_lag(&agent_id, "alerts", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn spend_alert(event: SpendEvent) -> Alert {
+    match event {
+        SpendEvent::SoftLimitReached {
+            agent_id,
+            spent_usd,
+            limit_usd,
+            downgrade_model,
+        } => Alert::SpendSoftLimit {
+            agent_id: agent_id.to_string(),
+            spent_usd,
+            limit_usd,
+            downgrade_model,
+        },
+        SpendEvent::HardLimitReached {
+            agent_id,
+            spent_usd,
+            limit_usd,
+        } => Alert::SpendHardLimit {
+            agent_id: agent_id.to_string(),
+            spent_usd,
+            limit_usd,
+        },
+    }
+}
+
+/// Update an adapter's consecutive failure count and decide whether this
+/// check crosses into (or out of) the down state.
+fn track_adapter_health(
+    failures: &mut HashMap<String, u32>,
+    threshold: u32,
+    adapter: String,
+    result: Result<(), String>,
+) -> Option<Alert> {
+    match result {
+        Ok(()) => {
+            let previous = failures.remove(&adapter).unwrap_or(0);
+            (previous >= threshold).then_some(Alert::AdapterRecovered { adapter })
+        }
+        Err(error) => {
+            let count = failures.entry(adapter.clone()).or_default();
+            *count += 1;
+            (*count == threshold).then_some(Alert::AdapterDown {
+                adapter,
+                failures: threshold,
+                error,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_url_and_adapter_targets() {
+        assert_eq!(
+            AlertTarget::parse("https://hooks.example.com/alert"),
+            Some(AlertTarget::Webhook(
+                "https://hooks.example.com/alert".into()
+            ))
+        );
+        assert!(matches!(
+            AlertTarget::parse("discord:123456789"),
+            Some(AlertTarget::Messaging(_))
+        ));
+        assert_eq!(AlertTarget::parse("nonsense"), None);
+    }
+
+    #[test]
+    fn adapter_alerts_fire_once_at_threshold_and_on_recovery() {
+        let mut failures = HashMap::new();
+        let fail = |failures: &mut HashMap<String, u32>| {
+            track_adapter_health(failures, 2, "slack".into(), Err("timeout".into()))
+        };
+
+        assert!(fail(&mut failures).is_none());
+        assert!(matches!(
+            fail(&mut failures),
+            Some(Alert::AdapterDown { failures: 2, .. })
+        ));
+        assert!(fail(&mut failures).is_none());
+        assert!(matches!(
+            track_adapter_health(&mut failures, 2, "slack".into(), Ok(())),
+            Some(Alert::AdapterRecovered { .. })
+        ));
+        assert!(track_adapter_health(&mut failures, 2, "slack".into(), Ok(())).is_none());
+    }
+
+    #[tokio::test]
+    async fn repeated_alerts_respect_cooldown() {
+        let config = AlertConfig {
+            targets: vec![AlertTarget::Webhook("http://127.0.0.1:9/alert".into())],
+            ..AlertConfig::default()
+        };
+        let dispatcher = AlertDispatcher::new(config, Arc::new(MessagingManager::new()));
+        let alert = Alert::ProviderDown {
+            provider: "anthropic".into(),
+            error: "503".into(),
+        };
+
+        assert!(dispatcher.send(alert.clone()).await);
+        assert!(!dispatcher.send(alert).await);
+        assert!(
+            dispatcher
+                .send(Alert::ProviderRecovered {
+                    provider: "anthropic".into()
+                })
+                .await
+        );
+    }
+}
@@ -5,22 +5,29 @@
 //! Includes an SSE endpoint for realtime event streaming.
 
 mod agents;
+mod announcements;
+mod artifacts;
+mod audit;
 mod bindings;
 mod channels;
 mod config;
 mod cortex;
 mod cron;
+mod handoffs;
 mod ingest;
 mod mcp;
 mod memories;
 mod messaging;
 mod models;
+mod pairing;
 mod providers;
 mod server;
 mod settings;
 mod skills;
 mod state;
 mod system;
+mod task_queue;
+mod triggers;
 mod webchat;
 
 pub use server::start_http_server;
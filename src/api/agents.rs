@@ -119,6 +119,16 @@ pub(super) struct IdentityUpdateRequest {
     user: Option<String>,
 }
 
+#[derive(Serialize)]
+pub(super) struct IdentityProposalResponse {
+    proposal: Option<crate::identity::IdentityProposal>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct IdentityProposalAnswer {
+    agent_id: String,
+}
+
 #[derive(Deserialize)]
 pub(super) struct AgentOverviewQuery {
     agent_id: String,
@@ -294,11 +304,39 @@ pub(super) async fn create_agent(
         ingestion: None,
         cortex: None,
         browser: None,
+        sandbox: None,
+        live_status: None,
+        confirmations: None,
+        presence: None,
+        reflection: None,
+        dream: None,
+        worker_queue: None,
+        rate_limit: None,
+        handoff: None,
+        hibernation: None,
+        sub_workers: None,
+        worker_budget: None,
+        reaction_memory: None,
+        reactions: None,
+        voice: None,
+        scripting: None,
+        plugins: None,
+        vector_index: None,
+        attachments: None,
+        loop_guard: None,
+        disk_quota: None,
+        offline: None,
+        spend: None,
+        database: None,
+        egress: None,
+        prompt_injection: None,
         memory_injection: None,
         mcp: None,
         brave_search_key: None,
         cron_timezone: None,
         cron: Vec::new(),
+        tasks: Vec::new(),
+        triggers: Vec::new(),
     };
     let agent_config = raw_config.resolve(&instance_dir, defaults);
     let _ = defaults;
@@ -316,7 +354,7 @@ pub(super) async fn create_agent(
         })?;
     }
 
-    let db = crate::db::Db::connect(&agent_config.data_dir)
+    let db = crate::db::Db::connect(&agent_config.data_dir, &agent_config.database)
         .await
         .map_err(|error| {
             tracing::error!(%error, agent_id = %agent_id, "failed to connect agent databases");
@@ -348,7 +386,8 @@ pub(super) async fn create_agent(
         .map_err(|error| {
             tracing::error!(%error, agent_id = %agent_id, "failed to init embeddings");
             StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        })?
+        .with_index_config(agent_config.vector_index.clone());
 
     if let Err(error) = embedding_table.ensure_fts_index().await {
         tracing::warn!(%error, agent_id = %agent_id, "failed to create FTS index");
@@ -357,8 +396,19 @@ pub(super) async fn create_agent(
     let memory_search = std::sync::Arc::new(crate::memory::MemorySearch::new(
         memory_store,
         embedding_table,
-        embedding_model,
+        embedding_model.clone(),
     ));
+    let history_archive = crate::conversation::HistoryArchive::open(
+        &db,
+        agent_config.archives_dir.clone(),
+        agent_config.vector_index.clone(),
+        embedding_model,
+    )
+    .await
+    .map_err(|error| {
+        tracing::error!(%error, agent_id = %agent_id, "failed to init the history archive");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     let (event_tx, _) = tokio::sync::broadcast::channel(256);
     let arc_agent_id: crate::AgentId = std::sync::Arc::from(agent_id.as_str());
@@ -406,6 +456,12 @@ pub(super) async fn create_agent(
         skills,
     ));
     runtime_config.set_settings(settings_store.clone());
+    // RBAC and object storage are instance-wide; inherit them from any
+    // already-running agent.
+    if let Some(existing) = state.runtime_configs.load().values().next() {
+        runtime_config.set_rbac((**existing.rbac.load()).clone());
+        runtime_config.set_object_storage((**existing.object_storage.load()).clone());
+    }
 
     let llm_manager = {
         let guard = state.llm_manager.read().await;
@@ -418,12 +474,24 @@ pub(super) async fn create_agent(
             .clone()
     };
 
+    let spend_tracker = crate::llm::spend::SpendTracker::new(
+        arc_agent_id.clone(),
+        db.sqlite.clone(),
+        runtime_config.clone(),
+        llm_manager.spend_event_sender(),
+    )
+    .await;
+    llm_manager
+        .register_spend_tracker(&agent_id, std::sync::Arc::new(spend_tracker))
+        .await;
+
     let mcp_manager = std::sync::Arc::new(crate::mcp::McpManager::new(agent_config.mcp.clone()));
     mcp_manager.connect_all().await;
 
     let deps = crate::AgentDeps {
         agent_id: arc_agent_id.clone(),
         memory_search: memory_search.clone(),
+        history_archive: std::sync::Arc::new(history_archive),
         llm_manager,
         mcp_manager: mcp_manager.clone(),
         cron_tool: None,
@@ -434,6 +502,8 @@ pub(super) async fn create_agent(
             let guard = state.messaging_manager.read().await;
             guard.as_ref().cloned()
         },
+        confirmations: Default::default(),
+        turn_hooks: Default::default(),
     };
 
     let event_rx = event_tx.subscribe();
@@ -455,6 +525,9 @@ pub(super) async fn create_agent(
     };
     let scheduler = std::sync::Arc::new(crate::cron::Scheduler::new(cron_context));
     runtime_config.set_cron(cron_store.clone(), scheduler.clone());
+    if let Some(bindings) = state.bindings.read().await.clone() {
+        runtime_config.set_bindings(bindings);
+    }
 
     let cron_tool = crate::tools::CronTool::new(cron_store.clone(), scheduler.clone());
 
@@ -465,6 +538,7 @@ pub(super) async fn create_agent(
     let channel_store = crate::conversation::ChannelStore::new(db.sqlite.clone());
     let cortex_tool_server = crate::tools::create_cortex_chat_tool_server(
         memory_search.clone(),
+        deps.history_archive.clone(),
         conversation_logger,
         channel_store,
         browser_config,
@@ -472,6 +546,11 @@ pub(super) async fn create_agent(
         brave_search_key,
         runtime_config.workspace_dir.clone(),
         runtime_config.instance_dir.clone(),
+        crate::audit::AuditLogger::new(db.sqlite.clone()).with_actor("cortex_chat"),
+        crate::egress::EgressPolicy::new(&runtime_config.egress.load()),
+        &agent_config.id,
+        (**runtime_config.object_storage.load()).clone(),
+        crate::tools::SendProactiveMessageTool::new(deps.clone()),
     );
     let cortex_store = crate::agent::cortex_chat::CortexChatStore::new(db.sqlite.clone());
     let cortex_session = crate::agent::cortex_chat::CortexChatSession::new(
@@ -484,13 +563,45 @@ pub(super) async fn create_agent(
     let _bulletin_loop =
         crate::agent::cortex::spawn_bulletin_loop(deps.clone(), cortex_logger.clone());
     let _association_loop =
-        crate::agent::cortex::spawn_association_loop(deps.clone(), cortex_logger);
+        crate::agent::cortex::spawn_association_loop(deps.clone(), cortex_logger.clone());
+    let _consolidation_loop =
+        crate::agent::cortex::spawn_consolidation_loop(deps.clone(), cortex_logger.clone());
+    let _health_loop = crate::agent::health::spawn_health_loop(deps.clone(), cortex_logger.clone());
+    let _reflection_loop =
+        crate::agent::reflection::spawn_reflection_loop(deps.clone(), cortex_logger.clone());
+    let _dream_loop = crate::agent::dream::spawn_dream_loop(deps.clone(), cortex_logger.clone());
+    let _task_loop = crate::agent::schedule::spawn_task_loop(deps.clone(), cortex_logger.clone());
+    let _trigger_loop =
+        crate::agent::trigger::spawn_trigger_loop(deps.clone(), cortex_logger.clone());
+    let _task_queue_loop = crate::agent::task_queue::spawn_task_queue_loop(
+        deps.clone(),
+        cortex_logger,
+        agent_config.screenshot_dir(),
+        agent_config.logs_dir(),
+    );
+    let _presence_loop = crate::agent::cortex::spawn_presence_loop(deps.clone());
+    let _circuit_breaker_loop =
+        crate::agent::circuit_breaker::spawn_circuit_breaker_loop(deps.clone());
+    let _reminder_loop = crate::reminders::spawn_reminder_loop(deps.clone());
+    let _audit_loop = crate::audit::spawn_event_audit_loop(
+        deps.agent_id.clone(),
+        deps.event_tx.subscribe(),
+        crate::audit::AuditLogger::new(db.sqlite.clone()),
+    );
 
     let ingestion_config = **runtime_config.ingestion.load();
     if ingestion_config.enabled {
         crate::agent::ingestion::spawn_ingestion_loop(agent_config.ingest_dir(), deps.clone());
     }
 
+    let disk_quota = Arc::new(crate::quota::DiskQuota::new(
+        agent_id.clone(),
+        agent_config.workspace.clone(),
+        agent_config.screenshot_dir(),
+        runtime_config.clone(),
+    ));
+    crate::quota::spawn_quota_loop(disk_quota.clone());
+
     let sqlite_pool = db.sqlite.clone();
     let mut deps_with_cron = deps.clone();
     deps_with_cron.cron_tool = Some(cron_tool);
@@ -542,6 +653,18 @@ pub(super) async fn create_agent(
         cron_stores.insert(agent_id.clone(), cron_store);
         state.cron_stores.store(std::sync::Arc::new(cron_stores));
 
+        let mut disk_quotas = (**state.disk_quotas.load()).clone();
+        disk_quotas.insert(agent_id.clone(), disk_quota);
+        state.disk_quotas.store(std::sync::Arc::new(disk_quotas));
+
+        if let Some(dispatcher) = state.alert_dispatcher.load().as_ref() {
+            crate::alerts::watch_agent_events(
+                dispatcher.clone(),
+                deps.agent_id.clone(),
+                deps.event_tx.subscribe(),
+            );
+        }
+
         let mut cron_schedulers = (**state.cron_schedulers.load()).clone();
         cron_schedulers.insert(agent_id.clone(), scheduler);
         state
@@ -671,6 +794,10 @@ pub(super) async fn delete_agent(
         cron_stores.remove(&agent_id);
         state.cron_stores.store(std::sync::Arc::new(cron_stores));
 
+        let mut disk_quotas = (**state.disk_quotas.load()).clone();
+        disk_quotas.remove(&agent_id);
+        state.disk_quotas.store(std::sync::Arc::new(disk_quotas));
+
         let mut cron_schedulers = (**state.cron_schedulers.load()).clone();
         cron_schedulers.remove(&agent_id);
         state
@@ -975,6 +1102,22 @@ pub(super) async fn get_agent_profile(
     Ok(Json(AgentProfileResponse { profile }))
 }
 
+/// Get workspace and screenshot disk usage against the agent's quotas.
+pub(super) async fn get_disk_usage(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<AgentOverviewQuery>,
+) -> Result<Json<crate::quota::DiskUsage>, StatusCode> {
+    let quotas = state.disk_quotas.load();
+    let quota = quotas.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let usage = quota.usage().await.map_err(|error| {
+        tracing::warn!(%error, agent_id = %query.agent_id, "failed to measure disk usage");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(usage))
+}
+
 /// Get identity files (SOUL.md, IDENTITY.md, USER.md) for an agent.
 pub(super) async fn get_identity(
     State(state): State<Arc<ApiState>>,
@@ -1032,6 +1175,20 @@ pub(super) async fn update_identity(
             })?;
     }
 
+    let files: Vec<&str> = [
+        ("SOUL.md", request.soul.is_some()),
+        ("IDENTITY.md", request.identity.is_some()),
+        ("USER.md", request.user.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(file, changed)| changed.then_some(file))
+    .collect();
+    state.audit_config_change(
+        Some(&request.agent_id),
+        "identity",
+        serde_json::json!({ "files": files }),
+    );
+
     let updated = crate::identity::Identity::load(workspace).await;
 
     Ok(Json(IdentityResponse {
@@ -1040,3 +1197,72 @@ pub(super) async fn update_identity(
         user: updated.user,
     }))
 }
+
+fn identity_proposals(
+    state: &ApiState,
+    agent_id: &str,
+) -> Result<crate::identity::IdentityProposals, StatusCode> {
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(crate::identity::IdentityProposals::new(
+        &runtime_config.data_dir,
+        &runtime_config.workspace_dir,
+    ))
+}
+
+/// Get the identity changes reflection proposed and nobody answered yet.
+pub(super) async fn get_identity_proposal(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<IdentityQuery>,
+) -> Result<Json<IdentityProposalResponse>, StatusCode> {
+    let proposal = identity_proposals(&state, &query.agent_id)?
+        .pending()
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to read identity proposal");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(IdentityProposalResponse { proposal }))
+}
+
+/// Write the pending identity proposal to the identity files.
+pub(super) async fn approve_identity_proposal(
+    State(state): State<Arc<ApiState>>,
+    axum::Json(request): axum::Json<IdentityProposalAnswer>,
+) -> Result<Json<crate::identity::IdentityProposal>, StatusCode> {
+    answer_identity_proposal(&state, &request.agent_id, true)
+        .await
+        .map(Json)
+}
+
+/// Drop the pending identity proposal.
+pub(super) async fn reject_identity_proposal(
+    State(state): State<Arc<ApiState>>,
+    axum::Json(request): axum::Json<IdentityProposalAnswer>,
+) -> Result<StatusCode, StatusCode> {
+    answer_identity_proposal(&state, &request.agent_id, false).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn answer_identity_proposal(
+    state: &ApiState,
+    agent_id: &str,
+    approve: bool,
+) -> Result<crate::identity::IdentityProposal, StatusCode> {
+    use crate::identity::proposal::ProposalError;
+
+    let proposals = identity_proposals(state, agent_id)?;
+    let pools = state.agent_pools.load();
+    let pool = pools.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    crate::agent::reflection::answer_proposal(pool, &proposals, approve, "api")
+        .await
+        .map_err(|error| match error {
+            ProposalError::NotFound => StatusCode::NOT_FOUND,
+            ProposalError::Stale(_) => StatusCode::CONFLICT,
+            ProposalError::Other(error) => {
+                tracing::warn!(%error, "failed to answer identity proposal");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })
+}
@@ -0,0 +1,95 @@
+//! Announcements to all of an agent's bound conversations.
+
+use super::state::ApiState;
+
+use crate::audit::{AuditAction, AuditLogger};
+use crate::messaging::announce::{self, AnnounceError, Announcement, AnnouncementReport};
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub(super) struct AnnounceRequest {
+    agent_id: String,
+    #[serde(default)]
+    title: Option<String>,
+    /// Markdown, converted for each platform.
+    text: String,
+    /// Only announce to bindings with one of these tags.
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct AnnounceResponse {
+    /// Whether every conversation got the announcement.
+    success: bool,
+    #[serde(flatten)]
+    report: AnnouncementReport,
+}
+
+/// Send an announcement to the agent's bound conversations. Responds with
+/// where it was delivered and where it failed.
+pub(super) async fn announce(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<AnnounceRequest>,
+) -> Result<Json<AnnounceResponse>, (StatusCode, String)> {
+    let pool = state
+        .agent_pools
+        .load()
+        .get(&request.agent_id)
+        .cloned()
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("no agent '{}'", request.agent_id),
+            )
+        })?;
+    let messaging_manager = state.messaging_manager.read().await.clone().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "messaging isn't running".to_string(),
+    ))?;
+    let bindings = match state.bindings.read().await.as_ref() {
+        Some(bindings) => bindings.load_full(),
+        None => Arc::default(),
+    };
+
+    let announcement = Announcement {
+        title: request.title,
+        text: request.text,
+        tags: request.tags,
+    };
+    let report = announce::announce(
+        &messaging_manager,
+        &bindings,
+        &request.agent_id,
+        &announcement,
+    )
+    .await
+    .map_err(|error| {
+        let status = match error {
+            AnnounceError::Empty => StatusCode::BAD_REQUEST,
+            AnnounceError::NoTargets => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, error.to_string())
+    })?;
+
+    AuditLogger::new(pool).with_actor("api").log(
+        AuditAction::Announcement,
+        "announcement",
+        serde_json::json!({
+            "title": announcement.title,
+            "tags": announcement.tags,
+            "delivered": report.delivered,
+            "failed": report.failed,
+        }),
+    );
+
+    Ok(Json(AnnounceResponse {
+        success: report.failed.is_empty(),
+        report,
+    }))
+}
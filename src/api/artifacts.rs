@@ -0,0 +1,99 @@
+use super::state::ApiState;
+
+use crate::agent::artifact::{Artifact, ArtifactStore};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub(super) struct ArtifactsResponse {
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct ArtifactsQuery {
+    agent_id: String,
+    /// Only this worker's artifacts.
+    #[serde(default)]
+    worker_id: Option<String>,
+    #[serde(default = "default_artifacts_limit")]
+    limit: i64,
+}
+
+fn default_artifacts_limit() -> i64 {
+    50
+}
+
+#[derive(Deserialize)]
+pub(super) struct ArtifactDownloadQuery {
+    agent_id: String,
+    id: String,
+}
+
+fn artifact_store(state: &ApiState, agent_id: &str) -> Result<ArtifactStore, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(ArtifactStore::new(pool.clone(), &runtime_config.data_dir))
+}
+
+/// List an agent's worker artifacts, newest first, or one worker's in the
+/// order it emitted them.
+pub(super) async fn list_artifacts(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ArtifactsQuery>,
+) -> Result<Json<ArtifactsResponse>, StatusCode> {
+    let store = artifact_store(&state, &query.agent_id)?;
+
+    let artifacts = match &query.worker_id {
+        Some(worker_id) => {
+            let worker_id = worker_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+            store.list_for_worker(worker_id).await
+        }
+        None => store.list_recent(query.limit.min(200)).await,
+    }
+    .map_err(|error| {
+        tracing::warn!(%error, agent_id = %query.agent_id, "failed to load worker artifacts");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(ArtifactsResponse { artifacts }))
+}
+
+/// Download an artifact's file.
+pub(super) async fn download_artifact(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ArtifactDownloadQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let store = artifact_store(&state, &query.agent_id)?;
+
+    let artifact = store
+        .get(&query.id)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, artifact_id = %query.id, "failed to load worker artifact");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let data = tokio::fs::read(&artifact.path).await.map_err(|error| {
+        tracing::warn!(%error, artifact_id = %query.id, "can't read worker artifact");
+        StatusCode::NOT_FOUND
+    })?;
+
+    let filename = artifact.name.replace(['"', '\\'], "_");
+    let headers = [
+        (header::CONTENT_TYPE, artifact.mime_type),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        ),
+    ];
+
+    Ok((headers, data))
+}
@@ -0,0 +1,71 @@
+use super::state::ApiState;
+
+use crate::audit::{AuditEntry, AuditFilter, AuditLogger};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub(super) struct AuditLogResponse {
+    entries: Vec<AuditEntry>,
+    total: i64,
+}
+
+#[derive(Deserialize)]
+pub(super) struct AuditLogQuery {
+    agent_id: String,
+    #[serde(default = "default_audit_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    action: Option<String>,
+    #[serde(default)]
+    actor: Option<String>,
+    /// RFC 3339 lower bound (inclusive).
+    #[serde(default)]
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    /// RFC 3339 upper bound (exclusive).
+    #[serde(default)]
+    until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn default_audit_limit() -> i64 {
+    50
+}
+
+/// List audit log entries for an agent with optional filters, newest first.
+pub(super) async fn audit_log(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<AuditLogResponse>, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let logger = AuditLogger::new(pool.clone());
+
+    let filter = AuditFilter {
+        action: query.action,
+        actor: query.actor,
+        since: query.since,
+        until: query.until,
+    };
+    let limit = query.limit.min(200);
+
+    let entries = logger
+        .load_entries(&filter, limit, query.offset)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, agent_id = %query.agent_id, "failed to load audit log");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let total = logger.count_entries(&filter).await.map_err(|error| {
+        tracing::warn!(%error, agent_id = %query.agent_id, "failed to count audit log entries");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(AuditLogResponse { entries, total }))
+}
@@ -1,5 +1,7 @@
 use super::state::ApiState;
 
+use crate::config::{Activation, BindingOverrides, ResponseMode};
+
 use axum::Json;
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
@@ -15,7 +17,16 @@ pub(super) struct BindingResponse {
     chat_id: Option<String>,
     channel_ids: Vec<String>,
     require_mention: bool,
+    activation: &'static str,
+    wake_words: Vec<String>,
+    reply_to_message: bool,
     dm_allowed_users: Vec<String>,
+    digest_interval_secs: Option<u64>,
+    response_mode: &'static str,
+    allow_proactive: bool,
+    tags: Vec<String>,
+    #[serde(flatten)]
+    overrides: BindingOverrides,
 }
 
 #[derive(Serialize)]
@@ -43,8 +54,28 @@ pub(super) struct CreateBindingRequest {
     channel_ids: Vec<String>,
     #[serde(default)]
     require_mention: bool,
+    /// `always`, `mention`, `wake_word` or `thread`. Overrides `require_mention`.
+    #[serde(default)]
+    activation: Option<String>,
+    #[serde(default)]
+    wake_words: Vec<String>,
+    #[serde(default)]
+    reply_to_message: bool,
     #[serde(default)]
     dm_allowed_users: Vec<String>,
+    #[serde(default)]
+    digest_interval_secs: Option<u64>,
+    /// `typing`, `stream` or `final`.
+    #[serde(default)]
+    response_mode: Option<String>,
+    #[serde(default)]
+    allow_proactive: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// `channel_model`, `max_turns`, `context_window` and compaction
+    /// thresholds for this binding's conversations.
+    #[serde(flatten)]
+    overrides: BindingOverrides,
     /// Optional: set platform credentials if not yet configured.
     #[serde(default)]
     platform_credentials: Option<PlatformCredentials>,
@@ -121,8 +152,28 @@ pub(super) struct UpdateBindingRequest {
     channel_ids: Vec<String>,
     #[serde(default)]
     require_mention: bool,
+    /// `always`, `mention`, `wake_word` or `thread`. Overrides `require_mention`.
+    #[serde(default)]
+    activation: Option<String>,
+    #[serde(default)]
+    wake_words: Vec<String>,
+    #[serde(default)]
+    reply_to_message: bool,
     #[serde(default)]
     dm_allowed_users: Vec<String>,
+    #[serde(default)]
+    digest_interval_secs: Option<u64>,
+    /// `typing`, `stream` or `final`.
+    #[serde(default)]
+    response_mode: Option<String>,
+    #[serde(default)]
+    allow_proactive: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// `channel_model`, `max_turns`, `context_window` and compaction
+    /// thresholds for this binding's conversations.
+    #[serde(flatten)]
+    overrides: BindingOverrides,
 }
 
 #[derive(Serialize)]
@@ -131,6 +182,62 @@ pub(super) struct UpdateBindingResponse {
     message: String,
 }
 
+/// The activation mode a create or update request asks for. `require_mention`
+/// is the older spelling of `activation = "mention"`.
+/// Write a binding's channel overrides to its table, removing the ones that
+/// aren't set.
+fn write_binding_overrides(binding: &mut toml_edit::Table, overrides: &BindingOverrides) {
+    match &overrides.channel_model {
+        Some(model) => binding["channel_model"] = toml_edit::value(model.as_str()),
+        None => {
+            binding.remove("channel_model");
+        }
+    }
+    for (key, value) in [
+        ("max_turns", overrides.max_turns),
+        ("context_window", overrides.context_window),
+    ] {
+        match value {
+            Some(value) => binding[key] = toml_edit::value(value as i64),
+            None => {
+                binding.remove(key);
+            }
+        }
+    }
+
+    let mut compaction = toml_edit::Table::new();
+    for (key, value) in [
+        ("background_threshold", overrides.background_threshold),
+        ("aggressive_threshold", overrides.aggressive_threshold),
+        ("emergency_threshold", overrides.emergency_threshold),
+    ] {
+        if let Some(value) = value {
+            compaction[key] = toml_edit::value(value as f64);
+        }
+    }
+    if compaction.is_empty() {
+        binding.remove("compaction");
+    } else {
+        binding["compaction"] = toml_edit::Item::Table(compaction);
+    }
+}
+
+fn request_activation(
+    require_mention: bool,
+    activation: Option<&str>,
+    wake_words: &[String],
+) -> Result<Activation, StatusCode> {
+    let activation = match activation {
+        Some(value) => Activation::parse(value).ok_or(StatusCode::BAD_REQUEST)?,
+        None if require_mention => Activation::Mention,
+        None => Activation::Always,
+    };
+    if activation == Activation::WakeWord && wake_words.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(activation)
+}
+
 /// List all bindings, optionally filtered by agent_id.
 pub(super) async fn list_bindings(
     State(state): State<Arc<ApiState>>,
@@ -156,8 +263,16 @@ pub(super) async fn list_bindings(
             workspace_id: b.workspace_id,
             chat_id: b.chat_id,
             channel_ids: b.channel_ids,
-            require_mention: b.require_mention,
+            require_mention: b.activation == Activation::Mention,
+            activation: b.activation.as_str(),
+            wake_words: b.wake_words,
+            reply_to_message: b.reply_to_message,
             dm_allowed_users: b.dm_allowed_users,
+            digest_interval_secs: b.digest_interval_secs,
+            response_mode: b.response_mode.as_str(),
+            allow_proactive: b.allow_proactive,
+            tags: b.tags,
+            overrides: b.overrides,
         })
         .collect();
 
@@ -169,6 +284,21 @@ pub(super) async fn create_binding(
     State(state): State<Arc<ApiState>>,
     axum::Json(request): axum::Json<CreateBindingRequest>,
 ) -> Result<Json<CreateBindingResponse>, StatusCode> {
+    if request.digest_interval_secs == Some(0) || request.overrides.validate().is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let activation = request_activation(
+        request.require_mention,
+        request.activation.as_deref(),
+        &request.wake_words,
+    )?;
+    let response_mode = request
+        .response_mode
+        .as_deref()
+        .map(|value| ResponseMode::parse(value).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()?
+        .unwrap_or_default();
+
     let config_path = state.config_path.read().await.clone();
     if config_path.as_os_str().is_empty() {
         return Err(StatusCode::INTERNAL_SERVER_ERROR);
@@ -316,8 +446,18 @@ pub(super) async fn create_binding(
         }
         binding_table["channel_ids"] = toml_edit::value(arr);
     }
-    if request.require_mention {
-        binding_table["require_mention"] = toml_edit::value(true);
+    if activation != Activation::Always {
+        binding_table["activation"] = toml_edit::value(activation.as_str());
+    }
+    if !request.wake_words.is_empty() {
+        let mut arr = toml_edit::Array::new();
+        for word in &request.wake_words {
+            arr.push(word.as_str());
+        }
+        binding_table["wake_words"] = toml_edit::value(arr);
+    }
+    if request.reply_to_message {
+        binding_table["reply_to_message"] = toml_edit::value(true);
     }
     if !request.dm_allowed_users.is_empty() {
         let mut arr = toml_edit::Array::new();
@@ -326,6 +466,23 @@ pub(super) async fn create_binding(
         }
         binding_table["dm_allowed_users"] = toml_edit::value(arr);
     }
+    if let Some(interval_secs) = request.digest_interval_secs {
+        binding_table["digest_interval_secs"] = toml_edit::value(interval_secs as i64);
+    }
+    if response_mode != ResponseMode::Typing {
+        binding_table["response_mode"] = toml_edit::value(response_mode.as_str());
+    }
+    if request.allow_proactive {
+        binding_table["allow_proactive"] = toml_edit::value(true);
+    }
+    if !request.tags.is_empty() {
+        let mut arr = toml_edit::Array::new();
+        for tag in &request.tags {
+            arr.push(tag.as_str());
+        }
+        binding_table["tags"] = toml_edit::value(arr);
+    }
+    write_binding_overrides(&mut binding_table, &request.overrides);
     bindings_array.push(binding_table);
 
     tokio::fs::write(&config_path, doc.to_string())
@@ -340,6 +497,11 @@ pub(super) async fn create_binding(
         channel = %request.channel,
         "binding created via API"
     );
+    state.audit_config_change(
+        Some(&request.agent_id),
+        &format!("binding:{}", request.channel),
+        serde_json::json!({ "change": "create" }),
+    );
 
     if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
         let bindings_guard = state.bindings.read().await;
@@ -396,7 +558,8 @@ pub(super) async fn create_binding(
                         }
                     }
                 };
-                let adapter = crate::messaging::discord::DiscordAdapter::new(&token, discord_perms);
+                let adapter = crate::messaging::discord::DiscordAdapter::new(&token, discord_perms)
+                    .with_pairing(state.pairing_store.load().as_ref().clone());
                 if let Err(error) = manager.register_and_start(adapter).await {
                     tracing::error!(%error, "failed to hot-start discord adapter");
                 }
@@ -468,7 +631,8 @@ pub(super) async fn create_binding(
                     std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(perms))
                 };
                 let adapter =
-                    crate::messaging::telegram::TelegramAdapter::new(&token, telegram_perms);
+                    crate::messaging::telegram::TelegramAdapter::new(&token, telegram_perms)
+                        .with_pairing(state.pairing_store.load().as_ref().clone());
                 if let Err(error) = manager.register_and_start(adapter).await {
                     tracing::error!(%error, "failed to hot-start telegram adapter");
                 }
@@ -521,6 +685,21 @@ pub(super) async fn update_binding(
     State(state): State<Arc<ApiState>>,
     axum::Json(request): axum::Json<UpdateBindingRequest>,
 ) -> Result<Json<UpdateBindingResponse>, StatusCode> {
+    if request.digest_interval_secs == Some(0) || request.overrides.validate().is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let activation = request_activation(
+        request.require_mention,
+        request.activation.as_deref(),
+        &request.wake_words,
+    )?;
+    let response_mode = request
+        .response_mode
+        .as_deref()
+        .map(|value| ResponseMode::parse(value).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()?
+        .unwrap_or_default();
+
     let config_path = state.config_path.read().await.clone();
     if !config_path.exists() {
         return Err(StatusCode::NOT_FOUND);
@@ -624,10 +803,27 @@ pub(super) async fn update_binding(
         binding.remove("channel_ids");
     }
 
-    if request.require_mention {
-        binding["require_mention"] = toml_edit::value(true);
+    binding.remove("require_mention");
+    if activation != Activation::Always {
+        binding["activation"] = toml_edit::value(activation.as_str());
+    } else {
+        binding.remove("activation");
+    }
+
+    if !request.wake_words.is_empty() {
+        let mut arr = toml_edit::Array::new();
+        for word in &request.wake_words {
+            arr.push(word.as_str());
+        }
+        binding["wake_words"] = toml_edit::value(arr);
+    } else {
+        binding.remove("wake_words");
+    }
+
+    if request.reply_to_message {
+        binding["reply_to_message"] = toml_edit::value(true);
     } else {
-        binding.remove("require_mention");
+        binding.remove("reply_to_message");
     }
 
     if !request.dm_allowed_users.is_empty() {
@@ -640,6 +836,36 @@ pub(super) async fn update_binding(
         binding.remove("dm_allowed_users");
     }
 
+    if let Some(interval_secs) = request.digest_interval_secs {
+        binding["digest_interval_secs"] = toml_edit::value(interval_secs as i64);
+    } else {
+        binding.remove("digest_interval_secs");
+    }
+
+    if response_mode != ResponseMode::Typing {
+        binding["response_mode"] = toml_edit::value(response_mode.as_str());
+    } else {
+        binding.remove("response_mode");
+    }
+
+    if request.allow_proactive {
+        binding["allow_proactive"] = toml_edit::value(true);
+    } else {
+        binding.remove("allow_proactive");
+    }
+
+    if !request.tags.is_empty() {
+        let mut arr = toml_edit::Array::new();
+        for tag in &request.tags {
+            arr.push(tag.as_str());
+        }
+        binding["tags"] = toml_edit::value(arr);
+    } else {
+        binding.remove("tags");
+    }
+
+    write_binding_overrides(binding, &request.overrides);
+
     tokio::fs::write(&config_path, doc.to_string())
         .await
         .map_err(|error| {
@@ -652,6 +878,11 @@ pub(super) async fn update_binding(
         channel = %request.channel,
         "binding updated via API"
     );
+    state.audit_config_change(
+        Some(&request.agent_id),
+        &format!("binding:{}", request.channel),
+        serde_json::json!({ "change": "update" }),
+    );
 
     if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
         let bindings_guard = state.bindings.read().await;
@@ -772,6 +1003,11 @@ pub(super) async fn delete_binding(
         channel = %request.channel,
         "binding deleted via API"
     );
+    state.audit_config_change(
+        Some(&request.agent_id),
+        &format!("binding:{}", request.channel),
+        serde_json::json!({ "change": "delete" }),
+    );
 
     if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
         let bindings_guard = state.bindings.read().await;
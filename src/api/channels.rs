@@ -1,7 +1,11 @@
-use super::state::ApiState;
+use super::state::{ApiEvent, ApiState};
 
+use crate::agent::snapshot::{self, HistorySnapshot, SnapshotFile};
 use crate::conversation::channels::ChannelStore;
+use crate::conversation::compactions::CompactionRecord;
 use crate::conversation::history::ProcessRunLogger;
+use crate::conversation::pins::Pin;
+use crate::conversation::{CompactionStore, PinStore};
 
 use axum::Json;
 use axum::extract::{Query, State};
@@ -16,6 +20,7 @@ pub(super) struct ChannelResponse {
     id: String,
     platform: String,
     display_name: Option<String>,
+    title: Option<String>,
     is_active: bool,
     last_activity_at: String,
     created_at: String,
@@ -57,6 +62,119 @@ pub(super) struct CancelProcessResponse {
     message: String,
 }
 
+#[derive(Deserialize)]
+pub(super) struct RenameChannelRequest {
+    agent_id: String,
+    channel_id: String,
+    title: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct RenameChannelResponse {
+    success: bool,
+    title: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PinsQuery {
+    agent_id: String,
+    channel_id: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct PinsResponse {
+    pins: Vec<Pin>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CompactionsQuery {
+    agent_id: String,
+    channel_id: String,
+    #[serde(default = "default_compactions_limit")]
+    limit: i64,
+}
+
+fn default_compactions_limit() -> i64 {
+    50
+}
+
+#[derive(Serialize)]
+pub(super) struct CompactionsResponse {
+    compactions: Vec<CompactionRecord>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CompactChannelRequest {
+    agent_id: String,
+    channel_id: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct CompactChannelResponse {
+    success: bool,
+    message: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct SnapshotChannelRequest {
+    agent_id: String,
+    channel_id: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct SnapshotChannelResponse {
+    success: bool,
+    /// The snapshot's file name, to restore it by.
+    snapshot: String,
+    message_count: usize,
+}
+
+#[derive(Deserialize)]
+pub(super) struct SnapshotsQuery {
+    agent_id: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct SnapshotsResponse {
+    snapshots: Vec<SnapshotFile>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RestoreSnapshotRequest {
+    /// The agent to run the restored channel.
+    agent_id: String,
+    /// The agent whose snapshots hold `snapshot`, when it isn't `agent_id`.
+    #[serde(default)]
+    from_agent_id: Option<String>,
+    snapshot: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct RestoreSnapshotResponse {
+    success: bool,
+    /// The restored channel's webchat conversation, to send messages to as
+    /// its `session_id`.
+    conversation_id: String,
+    message_count: usize,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PinRequest {
+    agent_id: String,
+    channel_id: String,
+    /// The logged message to pin, from the channel's timeline.
+    message_id: Option<String>,
+    /// Text to pin, when there's no `message_id`.
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct UnpinQuery {
+    agent_id: String,
+    channel_id: String,
+    pin_id: String,
+}
+
 /// List active channels across all agents.
 pub(super) async fn list_channels(State(state): State<Arc<ApiState>>) -> Json<ChannelsResponse> {
     let pools = state.agent_pools.load();
@@ -72,6 +190,7 @@ pub(super) async fn list_channels(State(state): State<Arc<ApiState>>) -> Json<Ch
                         id: channel.id,
                         platform: channel.platform,
                         display_name: channel.display_name,
+                        title: channel.title,
                         is_active: channel.is_active,
                         last_activity_at: channel.last_activity_at.to_rfc3339(),
                         created_at: channel.created_at.to_rfc3339(),
@@ -149,6 +268,58 @@ pub(super) async fn channel_status(
 }
 
 /// Cancel a running worker or branch via the API.
+/// Set a channel's title. Replaces a generated title, and is never replaced
+/// by one.
+pub(super) async fn rename_channel(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<RenameChannelRequest>,
+) -> Result<Json<RenameChannelResponse>, StatusCode> {
+    let title = request.title.trim().to_string();
+    if title.is_empty() || title.chars().count() > crate::agent::title::MAX_TITLE_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&request.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let renamed = ChannelStore::new(pool.clone())
+        .set_title(&request.channel_id, &title)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, channel_id = %request.channel_id, "failed to rename channel");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !renamed {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // A live channel picks the title up from the event, which also reaches
+    // SSE clients. Otherwise tell them directly.
+    let states = state.channel_states.read().await;
+    match states.get(&request.channel_id) {
+        Some(channel_state) if *channel_state.deps.agent_id == *request.agent_id => {
+            channel_state
+                .deps
+                .event_tx
+                .send(crate::ProcessEvent::ChannelTitled {
+                    agent_id: channel_state.deps.agent_id.clone(),
+                    channel_id: channel_state.channel_id.clone(),
+                    title: title.clone(),
+                })
+                .ok();
+        }
+        _ => state.send_event(ApiEvent::ChannelTitled {
+            agent_id: request.agent_id,
+            channel_id: request.channel_id,
+            title: title.clone(),
+        }),
+    }
+
+    Ok(Json(RenameChannelResponse {
+        success: true,
+        title,
+    }))
+}
+
 pub(super) async fn cancel_process(
     State(state): State<Arc<ApiState>>,
     Json(request): Json<CancelProcessRequest>,
@@ -190,3 +361,222 @@ pub(super) async fn cancel_process(
         _ => Err(StatusCode::BAD_REQUEST),
     }
 }
+
+/// GET /api/channels/pins — a channel's pinned messages, oldest first.
+pub(super) async fn list_pins(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<PinsQuery>,
+) -> Result<Json<PinsResponse>, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let pins = PinStore::new(pool.clone())
+        .list(&query.channel_id)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, channel_id = %query.channel_id, "failed to list pins");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(PinsResponse { pins }))
+}
+
+/// POST /api/channels/pins — pin a logged message by ID, or some text. The
+/// compactor keeps history messages containing it from then on.
+pub(super) async fn pin_message(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<PinRequest>,
+) -> Result<Json<Pin>, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&request.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let store = PinStore::new(pool.clone());
+
+    let content = match (&request.message_id, &request.content) {
+        (Some(message_id), _) => store
+            .load_logged(&request.channel_id, message_id)
+            .await
+            .map_err(|error| {
+                tracing::warn!(%error, %message_id, "failed to load message to pin");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .ok_or(StatusCode::NOT_FOUND)?,
+        (None, Some(content)) => content.clone(),
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    };
+    if content.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let pin = store
+        .pin(
+            &request.channel_id,
+            request.message_id.as_deref(),
+            &content,
+            "api",
+        )
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, channel_id = %request.channel_id, "failed to pin message");
+            StatusCode::CONFLICT
+        })?;
+    Ok(Json(pin))
+}
+
+/// DELETE /api/channels/pins — unpin a message.
+pub(super) async fn unpin_message(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<UnpinQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let removed = PinStore::new(pool.clone())
+        .unpin(&query.channel_id, &query.pin_id)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, pin_id = %query.pin_id, "failed to unpin message");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// GET /api/channels/compactions — a channel's compactions, newest first.
+pub(super) async fn list_compactions(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<CompactionsQuery>,
+) -> Result<Json<CompactionsResponse>, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let compactions = CompactionStore::new(pool.clone())
+        .list(&query.channel_id, query.limit.clamp(1, 500))
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, channel_id = %query.channel_id, "failed to list compactions");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(CompactionsResponse { compactions }))
+}
+
+/// POST /api/channels/compact — compact a live channel now, whatever its
+/// context usage. The summary is written in the background; watch for the
+/// `compaction_completed` event.
+pub(super) async fn compact_channel(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CompactChannelRequest>,
+) -> Result<Json<CompactChannelResponse>, StatusCode> {
+    let states = state.channel_states.read().await;
+    let channel_state = states
+        .get(&request.channel_id)
+        .filter(|channel_state| *channel_state.deps.agent_id == *request.agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !channel_state.compactor.compact_now().await {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(Json(CompactChannelResponse {
+        success: true,
+        message: format!("Compaction of {} started", request.channel_id),
+    }))
+}
+
+/// POST /api/channels/snapshot — write a live channel's history, status
+/// block and summary to the agent's snapshots directory.
+pub(super) async fn snapshot_channel(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<SnapshotChannelRequest>,
+) -> Result<Json<SnapshotChannelResponse>, StatusCode> {
+    let channel_state = state
+        .channel_states
+        .read()
+        .await
+        .get(&request.channel_id)
+        .filter(|channel_state| *channel_state.deps.agent_id == *request.agent_id)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let dir = snapshot::snapshots_dir(&channel_state.deps.runtime_config.data_dir);
+    let (snapshot, message_count) = async {
+        let taken = HistorySnapshot::take(&channel_state).await?;
+        let name = taken.write(&dir).await?;
+        Ok::<_, crate::error::Error>((name, taken.history.len()))
+    }
+    .await
+    .map_err(|error| {
+        tracing::warn!(%error, channel_id = %request.channel_id, "failed to snapshot channel");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!(channel_id = %request.channel_id, %snapshot, "channel snapshot taken");
+    Ok(Json(SnapshotChannelResponse {
+        success: true,
+        snapshot,
+        message_count,
+    }))
+}
+
+/// GET /api/channels/snapshots — an agent's snapshots, newest first.
+pub(super) async fn list_snapshots(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<SnapshotsQuery>,
+) -> Result<Json<SnapshotsResponse>, StatusCode> {
+    let dir = snapshots_dir(&state, &query.agent_id)?;
+    let snapshots = snapshot::list(&dir).await.map_err(|error| {
+        tracing::warn!(%error, agent_id = %query.agent_id, "failed to list snapshots");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(SnapshotsResponse { snapshots }))
+}
+
+/// POST /api/channels/restore — start a fresh webchat channel with a
+/// snapshot as its context. Its first turn runs on the next message sent to
+/// the returned conversation.
+pub(super) async fn restore_snapshot(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<RestoreSnapshotRequest>,
+) -> Result<Json<RestoreSnapshotResponse>, StatusCode> {
+    let from_agent_id = request
+        .from_agent_id
+        .as_deref()
+        .unwrap_or(&request.agent_id);
+    let dir = snapshots_dir(&state, from_agent_id)?;
+    let path = snapshot::snapshot_path(&dir, &request.snapshot).ok_or(StatusCode::BAD_REQUEST)?;
+    if !path.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let manager = state
+        .messaging_manager
+        .read()
+        .await
+        .clone()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let mut restored = HistorySnapshot::read(&path).await.map_err(|error| {
+        tracing::warn!(%error, snapshot = %request.snapshot, "failed to read snapshot");
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+    restored.agent_id = request.agent_id;
+    let message_count = restored.history.len();
+    let conversation_id = format!("webchat:restore-{}", uuid::Uuid::new_v4());
+    let seed = restored.into_seed(&conversation_id).map_err(|error| {
+        tracing::warn!(%error, "failed to build restore seed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    manager.inject_message(seed).await.map_err(|error| {
+        tracing::warn!(%error, "failed to start restored channel");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!(snapshot = %request.snapshot, %conversation_id, "snapshot restored");
+    Ok(Json(RestoreSnapshotResponse {
+        success: true,
+        conversation_id,
+        message_count,
+    }))
+}
+
+fn snapshots_dir(state: &ApiState, agent_id: &str) -> Result<std::path::PathBuf, StatusCode> {
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(snapshot::snapshots_dir(&runtime_config.data_dir))
+}
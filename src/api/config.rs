@@ -32,6 +32,7 @@ pub(super) struct CompactionSection {
     background_threshold: f32,
     aggressive_threshold: f32,
     emergency_threshold: f32,
+    tool_output_max_chars: usize,
 }
 
 #[derive(Serialize, Debug)]
@@ -52,6 +53,7 @@ pub(super) struct CoalesceSection {
     max_wait_ms: u64,
     min_messages: usize,
     multi_user_only: bool,
+    sender_debounce_ms: u64,
 }
 
 #[derive(Serialize, Debug)]
@@ -86,6 +88,7 @@ pub(super) struct BrowserSection {
 pub(super) struct DiscordSection {
     enabled: bool,
     allow_bot_messages: bool,
+    voice: bool,
 }
 
 #[derive(Serialize, Debug)]
@@ -158,6 +161,7 @@ pub(super) struct CompactionUpdate {
     background_threshold: Option<f32>,
     aggressive_threshold: Option<f32>,
     emergency_threshold: Option<f32>,
+    tool_output_max_chars: Option<usize>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -178,6 +182,7 @@ pub(super) struct CoalesceUpdate {
     max_wait_ms: Option<u64>,
     min_messages: Option<usize>,
     multi_user_only: Option<bool>,
+    sender_debounce_ms: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -211,6 +216,7 @@ pub(super) struct BrowserUpdate {
 #[derive(Deserialize, Debug)]
 pub(super) struct DiscordUpdate {
     allow_bot_messages: Option<bool>,
+    voice: Option<bool>,
 }
 
 /// Get the resolved configuration for an agent.
@@ -255,6 +261,7 @@ pub(super) async fn get_agent_config(
             background_threshold: compaction.background_threshold,
             aggressive_threshold: compaction.aggressive_threshold,
             emergency_threshold: compaction.emergency_threshold,
+            tool_output_max_chars: compaction.tool_output_max_chars,
         },
         cortex: CortexSection {
             tick_interval_secs: cortex.tick_interval_secs,
@@ -271,6 +278,7 @@ pub(super) async fn get_agent_config(
             max_wait_ms: coalesce.max_wait_ms,
             min_messages: coalesce.min_messages,
             multi_user_only: coalesce.multi_user_only,
+            sender_debounce_ms: coalesce.sender_debounce_ms,
         },
         memory_persistence: MemoryPersistenceSection {
             enabled: memory_persistence.enabled,
@@ -303,11 +311,13 @@ pub(super) async fn get_agent_config(
                     DiscordSection {
                         enabled: true,
                         allow_bot_messages: snapshot.allow_bot_messages,
+                        voice: snapshot.voice,
                     }
                 }
                 None => DiscordSection {
                     enabled: false,
                     allow_bot_messages: false,
+                    voice: false,
                 },
             }
         },
@@ -383,6 +393,30 @@ pub(super) async fn update_agent_config(
 
     tracing::info!(agent_id = %request.agent_id, "config.toml updated via API");
 
+    let sections: Vec<&str> = [
+        ("routing", request.routing.is_some()),
+        ("tuning", request.tuning.is_some()),
+        ("compaction", request.compaction.is_some()),
+        ("cortex", request.cortex.is_some()),
+        ("coalesce", request.coalesce.is_some()),
+        ("memory_persistence", request.memory_persistence.is_some()),
+        (
+            "memory_injection",
+            request.memory_injection.is_some()
+                || request.reset_memory_injection_override.unwrap_or(false),
+        ),
+        ("browser", request.browser.is_some()),
+        ("discord", request.discord.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(section, changed)| changed.then_some(section))
+    .collect();
+    state.audit_config_change(
+        Some(&request.agent_id),
+        "agent_config",
+        serde_json::json!({ "sections": sections }),
+    );
+
     match crate::config::Config::load_from_path(&config_path) {
         Ok(new_config) => {
             let runtime_configs = state.runtime_configs.load();
@@ -548,6 +582,9 @@ fn update_compaction_table(
     if let Some(v) = compaction.emergency_threshold {
         table["emergency_threshold"] = toml_edit::value(v as f64);
     }
+    if let Some(v) = compaction.tool_output_max_chars {
+        table["tool_output_max_chars"] = toml_edit::value(v as i64);
+    }
     Ok(())
 }
 
@@ -604,6 +641,9 @@ fn update_coalesce_table(
     if let Some(v) = coalesce.multi_user_only {
         table["multi_user_only"] = toml_edit::value(v);
     }
+    if let Some(v) = coalesce.sender_debounce_ms {
+        table["sender_debounce_ms"] = toml_edit::value(v as i64);
+    }
     Ok(())
 }
 
@@ -716,6 +756,9 @@ fn update_discord_table(
     if let Some(allow_bot_messages) = discord.allow_bot_messages {
         discord_table["allow_bot_messages"] = toml_edit::value(allow_bot_messages);
     }
+    if let Some(voice) = discord.voice {
+        discord_table["voice"] = toml_edit::value(voice);
+    }
 
     Ok(())
 }
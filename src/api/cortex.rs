@@ -1,5 +1,6 @@
 use super::state::ApiState;
 
+use crate::agent::circuit_breaker::BreakerStatus;
 use crate::agent::cortex::{CortexEvent, CortexLogger};
 use crate::agent::cortex_chat::{CortexChatEvent, CortexChatMessage, CortexChatStore};
 
@@ -177,3 +178,28 @@ pub(super) async fn cortex_events(
 
     Ok(Json(CortexEventsResponse { events, total }))
 }
+
+#[derive(Serialize)]
+pub(super) struct CircuitBreakersResponse {
+    breakers: Vec<BreakerStatus>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CircuitBreakersQuery {
+    agent_id: String,
+}
+
+/// Circuit breaker state of each of an agent's process types.
+pub(super) async fn circuit_breakers(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<CircuitBreakersQuery>,
+) -> Result<Json<CircuitBreakersResponse>, StatusCode> {
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs
+        .get(&query.agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(CircuitBreakersResponse {
+        breakers: runtime_config.circuit_breakers.snapshot(),
+    }))
+}
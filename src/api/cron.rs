@@ -216,6 +216,12 @@ pub(super) async fn create_or_update_cron(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    state.audit_config_change(
+        Some(&request.agent_id),
+        &format!("cron:{}", request.id),
+        serde_json::json!({ "change": "save" }),
+    );
+
     Ok(Json(CronActionResponse {
         success: true,
         message: format!("Cron job '{}' saved successfully", request.id),
@@ -242,6 +248,12 @@ pub(super) async fn delete_cron(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    state.audit_config_change(
+        Some(&query.agent_id),
+        &format!("cron:{}", query.cron_id),
+        serde_json::json!({ "change": "delete" }),
+    );
+
     Ok(Json(CronActionResponse {
         success: true,
         message: format!("Cron job '{}' deleted successfully", query.cron_id),
@@ -297,6 +309,11 @@ pub(super) async fn toggle_cron(
     } else {
         "disabled"
     };
+    state.audit_config_change(
+        Some(&request.agent_id),
+        &format!("cron:{}", request.cron_id),
+        serde_json::json!({ "change": status }),
+    );
     Ok(Json(CronActionResponse {
         success: true,
         message: format!("Cron job '{}' {}", request.cron_id, status),
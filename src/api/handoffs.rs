@@ -0,0 +1,126 @@
+//! Conversation handoffs between agents.
+
+use super::state::ApiState;
+
+use crate::agent::handoff::Handoff;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub(super) struct HandoffsResponse {
+    handoffs: Vec<Handoff>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CreateHandoffRequest {
+    channel_id: String,
+    agent_id: String,
+    #[serde(default)]
+    reason: Option<String>,
+    summary: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct CreateHandoffResponse {
+    success: bool,
+    message: String,
+    handoff: Option<Handoff>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct HandoffQuery {
+    channel_id: String,
+}
+
+/// List handed-off conversations, most recent first.
+pub(super) async fn list_handoffs(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<HandoffsResponse>, StatusCode> {
+    let store = state.handoff_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let handoffs = store.list().map_err(|error| {
+        tracing::warn!(%error, "failed to list handoffs");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(HandoffsResponse { handoffs }))
+}
+
+/// Hand an active conversation to another agent.
+pub(super) async fn create_handoff(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CreateHandoffRequest>,
+) -> Result<Json<CreateHandoffResponse>, StatusCode> {
+    if request.summary.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !state
+        .agent_configs
+        .load()
+        .iter()
+        .any(|agent| agent.id == request.agent_id)
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let channel_state = state
+        .channel_states
+        .read()
+        .await
+        .get(&request.channel_id)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let reason = request
+        .reason
+        .unwrap_or_else(|| "handed over by an operator".into());
+    match crate::agent::handoff::hand_off(
+        &channel_state,
+        &request.agent_id,
+        &reason,
+        &request.summary,
+    )
+    .await
+    {
+        Ok(handoff) => Ok(Json(CreateHandoffResponse {
+            success: true,
+            message: format!("Conversation handed to {}.", request.agent_id),
+            handoff: Some(handoff),
+        })),
+        Err(error) => Ok(Json(CreateHandoffResponse {
+            success: false,
+            message: error.to_string(),
+            handoff: None,
+        })),
+    }
+}
+
+/// Return a handed-off conversation to its bindings.
+pub(super) async fn delete_handoff(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<HandoffQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let store = state.handoff_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let removed = store.remove(&query.channel_id).map_err(|error| {
+        tracing::warn!(%error, "failed to remove handoff");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
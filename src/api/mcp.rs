@@ -203,6 +203,12 @@ pub(super) async fn create_mcp_server(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    state.audit_config_change(
+        None,
+        &format!("mcp_server:{}", request.name),
+        serde_json::json!({ "change": "create" }),
+    );
+
     Ok(Json(MutationResponse {
         success: true,
         message: format!("MCP server '{}' added", request.name),
@@ -283,6 +289,12 @@ pub(super) async fn update_mcp_server(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    state.audit_config_change(
+        None,
+        &format!("mcp_server:{}", request.name),
+        serde_json::json!({ "change": "update" }),
+    );
+
     Ok(Json(MutationResponse {
         success: true,
         message: format!("MCP server '{}' updated", request.name),
@@ -345,6 +357,12 @@ pub(super) async fn delete_mcp_server(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    state.audit_config_change(
+        None,
+        &format!("mcp_server:{server_name}"),
+        serde_json::json!({ "change": "delete" }),
+    );
+
     Ok(Json(MutationResponse {
         success: true,
         message: format!("MCP server '{}' removed", server_name),
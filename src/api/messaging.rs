@@ -323,7 +323,8 @@ pub(super) async fn toggle_platform(
                         let adapter = crate::messaging::discord::DiscordAdapter::new(
                             &discord_config.token,
                             perms,
-                        );
+                        )
+                        .with_pairing(state.pairing_store.load().as_ref().clone());
                         if let Err(error) = manager.register_and_start(adapter).await {
                             tracing::error!(%error, "failed to start discord adapter on toggle");
                         }
@@ -375,7 +376,8 @@ pub(super) async fn toggle_platform(
                         let adapter = crate::messaging::telegram::TelegramAdapter::new(
                             &telegram_config.token,
                             arc_swap,
-                        );
+                        )
+                        .with_pairing(state.pairing_store.load().as_ref().clone());
                         if let Err(error) = manager.register_and_start(adapter).await {
                             tracing::error!(%error, "failed to start telegram adapter on toggle");
                         }
@@ -387,6 +389,16 @@ pub(super) async fn toggle_platform(
                             webhook_config.port,
                             &webhook_config.bind,
                             webhook_config.auth_token.clone(),
+                        )
+                        .with_signing(
+                            webhook_config.signing_secret.clone(),
+                            webhook_config.sources.clone(),
+                        )
+                        .with_sessions(state.session_store.load().as_ref().clone())
+                        .with_deliveries(state.webhook_deliveries.load().as_ref().clone())
+                        .with_callback(
+                            webhook_config.callback_url.clone(),
+                            webhook_config.max_delivery_attempts,
                         );
                         if let Err(error) = manager.register_and_start(adapter).await {
                             tracing::error!(%error, "failed to start webhook adapter on toggle");
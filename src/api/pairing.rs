@@ -0,0 +1,101 @@
+//! Pairing requests from unknown DM users, and the users paired so far.
+
+use super::state::ApiState;
+
+use crate::messaging::pairing::{PairedUser, PairingRequest, PairingStore};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub(super) struct PairingResponse {
+    pending: Vec<PairingRequest>,
+    users: Vec<PairedUser>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PairingCodeRequest {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PairedUserQuery {
+    platform: String,
+    user_id: String,
+}
+
+fn pairing_store(state: &ApiState) -> Result<Arc<PairingStore>, StatusCode> {
+    state
+        .pairing_store
+        .load()
+        .as_ref()
+        .clone()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// List pending pairing requests and paired users.
+pub(super) async fn list_pairing(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<PairingResponse>, StatusCode> {
+    let store = pairing_store(&state)?;
+    let users = store.list().map_err(|error| {
+        tracing::warn!(%error, "failed to list paired users");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(PairingResponse {
+        pending: store.pending(),
+        users,
+    }))
+}
+
+/// Approve a pending pairing code.
+pub(super) async fn approve_pairing(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<PairingCodeRequest>,
+) -> Result<Json<PairedUser>, StatusCode> {
+    let store = pairing_store(&state)?;
+    store
+        .approve(&request.code, "api")
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to store paired user");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Deny a pending pairing code.
+pub(super) async fn deny_pairing(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<PairingCodeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let store = pairing_store(&state)?;
+    match store.deny(&request.code) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Take a paired user's DM access away.
+pub(super) async fn unpair_user(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<PairedUserQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let store = pairing_store(&state)?;
+    let removed = store
+        .unpair(&query.platform, &query.user_id)
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to unpair user");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -463,6 +463,12 @@ pub(super) async fn update_provider(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    state.audit_config_change(
+        None,
+        &format!("provider:{}", request.provider),
+        serde_json::json!({ "change": "update", "model": request.model }),
+    );
+
     state
         .provider_setup_tx
         .try_send(crate::ProviderSetupEvent::ProvidersConfigured)
@@ -597,6 +603,12 @@ pub(super) async fn delete_provider(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    state.audit_config_change(
+        None,
+        &format!("provider:{provider}"),
+        serde_json::json!({ "change": "delete" }),
+    );
+
     Ok(Json(ProviderUpdateResponse {
         success: true,
         message: format!("Provider '{}' removed", provider),
@@ -2,8 +2,9 @@
 
 use super::state::ApiState;
 use super::{
-    agents, bindings, channels, config, cortex, cron, ingest, mcp, memories, messaging, models,
-    providers, settings, skills, system, webchat,
+    agents, announcements, artifacts, audit, bindings, channels, config, cortex, cron, handoffs,
+    ingest, mcp, memories, messaging, models, pairing, providers, settings, skills, system,
+    task_queue, triggers, webchat,
 };
 
 use axum::Json;
@@ -56,8 +57,12 @@ pub async fn start_http_server(
         .route("/idle", get(system::idle))
         .route("/status", get(system::status))
         .route("/system/storage", get(system::storage_status))
+        .route("/system/event-bus", get(system::event_bus_status))
+        .route("/system/cluster", get(system::cluster_status))
         .route("/system/backup/export", get(system::backup_export))
         .route("/system/backup/restore", post(system::backup_restore))
+        .route("/system/backup/store", post(system::backup_store))
+        .route("/system/backup/stored", get(system::backup_list_stored))
         .route("/overview", get(agents::instance_overview))
         .route("/events", get(system::events_sse))
         .route(
@@ -92,13 +97,43 @@ pub async fn start_http_server(
             get(memories::memory_graph_neighbors),
         )
         .route("/cortex/events", get(cortex::cortex_events))
+        .route("/cortex/circuit-breakers", get(cortex::circuit_breakers))
+        .route("/agents/audit", get(audit::audit_log))
+        .route("/agents/artifacts", get(artifacts::list_artifacts))
+        .route(
+            "/agents/task-queue",
+            get(task_queue::list_tasks).post(task_queue::queue_task),
+        )
+        .route("/agents/task-queue/cancel", post(task_queue::cancel_task))
+        .route(
+            "/triggers/{agent_id}/{trigger_id}",
+            post(triggers::fire_trigger),
+        )
+        .route(
+            "/agents/artifacts/download",
+            get(artifacts::download_artifact),
+        )
+        .route("/agents/announce", post(announcements::announce))
         .route("/cortex-chat/messages", get(cortex::cortex_chat_messages))
         .route("/cortex-chat/send", post(cortex::cortex_chat_send))
         .route("/agents/profile", get(agents::get_agent_profile))
+        .route("/agents/disk-usage", get(agents::get_disk_usage))
         .route(
             "/agents/identity",
             get(agents::get_identity).put(agents::update_identity),
         )
+        .route(
+            "/agents/identity/proposal",
+            get(agents::get_identity_proposal),
+        )
+        .route(
+            "/agents/identity/proposal/approve",
+            post(agents::approve_identity_proposal),
+        )
+        .route(
+            "/agents/identity/proposal/reject",
+            post(agents::reject_identity_proposal),
+        )
         .route(
             "/agents/config",
             get(config::get_agent_config).put(config::update_agent_config),
@@ -113,6 +148,28 @@ pub async fn start_http_server(
         .route("/agents/cron/trigger", post(cron::trigger_cron))
         .route("/agents/cron/toggle", put(cron::toggle_cron))
         .route("/channels/cancel", post(channels::cancel_process))
+        .route("/channels/title", put(channels::rename_channel))
+        .route(
+            "/channels/pins",
+            get(channels::list_pins)
+                .post(channels::pin_message)
+                .delete(channels::unpin_message),
+        )
+        .route("/channels/compactions", get(channels::list_compactions))
+        .route("/channels/compact", post(channels::compact_channel))
+        .route("/channels/snapshot", post(channels::snapshot_channel))
+        .route("/channels/snapshots", get(channels::list_snapshots))
+        .route("/channels/restore", post(channels::restore_snapshot))
+        .route(
+            "/handoffs",
+            get(handoffs::list_handoffs)
+                .post(handoffs::create_handoff)
+                .delete(handoffs::delete_handoff),
+        )
+        .route("/pairing", get(pairing::list_pairing))
+        .route("/pairing/approve", post(pairing::approve_pairing))
+        .route("/pairing/deny", post(pairing::deny_pairing))
+        .route("/pairing/users", delete(pairing::unpair_user))
         .route(
             "/agents/ingest/files",
             get(ingest::list_ingest_files).delete(ingest::delete_ingest_file),
@@ -157,8 +214,23 @@ pub async fn start_http_server(
             get(settings::update_check).post(settings::update_check_now),
         )
         .route("/update/apply", post(settings::update_apply))
+        .route("/update/channel", put(settings::update_set_channel))
+        .route("/update/rollback", post(settings::update_rollback))
         .route("/webchat/send", post(webchat::webchat_send))
         .route("/webchat/history", get(webchat::webchat_history))
+        .route("/webchat/voice", get(webchat::webchat_voice))
+        .route(
+            "/webchat/sessions",
+            get(webchat::list_sessions).post(webchat::create_session),
+        )
+        .route(
+            "/webchat/sessions/revoke",
+            post(webchat::revoke_user_sessions),
+        )
+        .route(
+            "/webchat/sessions/{session_id}",
+            delete(webchat::revoke_session),
+        )
         .layer(DefaultBodyLimit::max(10 * 1024 * 1024))
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -204,6 +276,11 @@ async fn api_auth_middleware(
         return next.run(request).await;
     }
 
+    // Trigger webhooks carry their own per-trigger token.
+    if path.starts_with("/api/triggers/") {
+        return next.run(request).await;
+    }
+
     let is_authorized = request
         .headers()
         .get(header::AUTHORIZATION)
@@ -494,6 +494,8 @@ pub(super) async fn update_global_settings(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    state.audit_config_change(None, "global_settings", serde_json::json!({}));
+
     let message = if requires_restart {
         "Settings updated. API server changes require a restart to take effect.".to_string()
     } else {
@@ -524,11 +526,12 @@ pub(super) async fn update_check_now(
     Json((**status).clone())
 }
 
-/// Pull the new Docker image and recreate this container.
+/// Apply the available update: recreate the container on Docker installs,
+/// swap the binary and restart on native installs.
 pub(super) async fn update_apply(
     State(state): State<Arc<ApiState>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    match crate::update::apply_docker_update(&state.update_status).await {
+    match crate::update::apply_update(&state.update_status).await {
         Ok(()) => Ok(Json(serde_json::json!({ "status": "updating" }))),
         Err(error) => {
             tracing::error!(%error, "update apply failed");
@@ -540,6 +543,67 @@ pub(super) async fn update_apply(
     }
 }
 
+#[derive(Deserialize)]
+pub(super) struct UpdateChannelRequest {
+    channel: crate::update::UpdateChannel,
+}
+
+/// Switch the update channel, persist it to `[update]` in config.toml, and
+/// re-check against the new channel.
+pub(super) async fn update_set_channel(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<UpdateChannelRequest>,
+) -> Result<Json<crate::update::UpdateStatus>, StatusCode> {
+    let config_path = state.config_path.read().await.clone();
+    if !config_path.as_os_str().is_empty() {
+        let content = if config_path.exists() {
+            tokio::fs::read_to_string(&config_path)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        } else {
+            String::new()
+        };
+        let mut doc: toml_edit::DocumentMut = content
+            .parse()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if doc.get("update").is_none() {
+            doc["update"] = toml_edit::Item::Table(toml_edit::Table::new());
+        }
+        doc["update"]["channel"] = toml_edit::value(request.channel.as_str());
+        tokio::fs::write(&config_path, doc.to_string())
+            .await
+            .map_err(|error| {
+                tracing::warn!(%error, "failed to write config.toml");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        state.audit_config_change(
+            None,
+            "update_channel",
+            serde_json::json!({ "channel": request.channel.as_str() }),
+        );
+    }
+
+    crate::update::set_channel(&state.update_status, request.channel).await;
+    let status = state.update_status.load();
+    Ok(Json((**status).clone()))
+}
+
+/// Revert to the previously running version.
+pub(super) async fn update_rollback(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match crate::update::rollback(&state.update_status).await {
+        Ok(()) => Ok(Json(serde_json::json!({ "status": "rolling_back" }))),
+        Err(error) => {
+            tracing::error!(%error, "update rollback failed");
+            Ok(Json(serde_json::json!({
+                "status": "error",
+                "error": error.to_string(),
+            })))
+        }
+    }
+}
+
 pub(super) async fn get_raw_config(
     State(state): State<Arc<ApiState>>,
 ) -> Result<Json<RawConfigResponse>, StatusCode> {
@@ -588,6 +652,11 @@ pub(super) async fn update_raw_config(
         })?;
 
     tracing::info!("config.toml updated via raw editor");
+    state.audit_config_change(
+        None,
+        "raw_config",
+        serde_json::json!({ "bytes": request.content.len() }),
+    );
 
     match crate::config::Config::load_from_path(&config_path) {
         Ok(new_config) => {
@@ -2,15 +2,23 @@
 
 use crate::agent::channel::ChannelState;
 use crate::agent::cortex_chat::CortexChatSession;
+use crate::agent::handoff::HandoffStore;
 use crate::agent::status::StatusBlock;
+use crate::alerts::AlertDispatcher;
+use crate::audit::{AuditAction, AuditLogger};
+use crate::cluster::Cluster;
 use crate::config::{Binding, DefaultsConfig, DiscordPermissions, RuntimeConfig, SlackPermissions};
 use crate::cron::{CronStore, Scheduler};
 use crate::llm::LlmManager;
 use crate::mcp::McpManager;
 use crate::memory::{EmbeddingModel, MemorySearch};
 use crate::messaging::MessagingManager;
+use crate::messaging::delivery::DeliveryStore;
+use crate::messaging::pairing::PairingStore;
+use crate::messaging::session::SessionStore;
 use crate::messaging::webchat::WebChatAdapter;
 use crate::prompts::PromptEngine;
+use crate::quota::DiskQuota;
 use crate::update::SharedUpdateStatus;
 use crate::{ProcessEvent, ProcessId};
 
@@ -93,6 +101,20 @@ pub struct ApiState {
     pub agent_remove_tx: mpsc::Sender<String>,
     /// Shared webchat adapter for session management from API handlers.
     pub webchat_adapter: ArcSwap<Option<Arc<WebChatAdapter>>>,
+    /// Session tokens shared by the webhook and webchat adapters.
+    pub session_store: ArcSwap<Option<Arc<SessionStore>>>,
+    /// Delivery records of webhook responses.
+    pub webhook_deliveries: ArcSwap<Option<Arc<DeliveryStore>>>,
+    /// Conversation routes created by agent handoffs.
+    pub handoff_store: ArcSwap<Option<Arc<HandoffStore>>>,
+    /// Pairing codes and users paired through them.
+    pub pairing_store: ArcSwap<Option<Arc<PairingStore>>>,
+    /// Disk quota trackers keyed by agent ID.
+    pub disk_quotas: ArcSwap<HashMap<String, Arc<DiskQuota>>>,
+    /// Operator alert dispatcher, so agents created at runtime are watched too.
+    pub alert_dispatcher: ArcSwap<Option<Arc<AlertDispatcher>>>,
+    /// This node's cluster membership, when clustering is enabled.
+    pub cluster: ArcSwap<Option<Arc<Cluster>>>,
 }
 
 /// Events sent to SSE clients. Wraps ProcessEvents with agent context.
@@ -139,6 +161,7 @@ pub enum ApiEvent {
         channel_id: Option<String>,
         worker_id: String,
         result: String,
+        artifacts: Vec<crate::agent::artifact::Artifact>,
     },
     /// A branch was started.
     BranchStarted {
@@ -170,6 +193,75 @@ pub enum ApiEvent {
         process_id: String,
         tool_name: String,
     },
+    /// Untrusted tool output looked like a prompt injection.
+    PromptInjectionDetected {
+        agent_id: String,
+        channel_id: Option<String>,
+        process_type: String,
+        process_id: String,
+        tool_name: String,
+        signals: Vec<String>,
+        neutralized: bool,
+    },
+    /// The loop guard stopped a process.
+    LoopDetected {
+        agent_id: String,
+        channel_id: Option<String>,
+        process_type: String,
+        process_id: String,
+        kind: String,
+        detail: String,
+    },
+    /// The cortex nudged or killed a stuck process.
+    ProcessUnhealthy {
+        agent_id: String,
+        channel_id: String,
+        process_type: String,
+        process_id: String,
+        score: u8,
+        action: crate::agent::health::HealthAction,
+        diagnostic: String,
+    },
+    /// A channel got a title, generated or set through the API.
+    ChannelTitled {
+        agent_id: String,
+        channel_id: String,
+        title: String,
+    },
+    /// The compactor started compacting a channel's history.
+    CompactionStarted {
+        agent_id: String,
+        channel_id: String,
+        action: crate::agent::compactor::CompactionAction,
+        trigger: crate::agent::compactor::CompactionTrigger,
+        tokens_before: usize,
+    },
+    /// A compaction finished.
+    CompactionCompleted {
+        agent_id: String,
+        channel_id: String,
+        action: crate::agent::compactor::CompactionAction,
+        trigger: crate::agent::compactor::CompactionTrigger,
+        tokens_before: usize,
+        tokens_after: usize,
+        messages_compacted: usize,
+        error: Option<String>,
+    },
+    /// The cortex consolidated an agent's memories.
+    MemoryConsolidated {
+        agent_id: String,
+        merged: usize,
+        promoted: usize,
+        archived: usize,
+    },
+    /// A process type's circuit breaker opened, went half-open or closed.
+    CircuitBreakerChanged {
+        agent_id: String,
+        process_type: String,
+        state: crate::agent::circuit_breaker::BreakerState,
+        consecutive_failures: u32,
+        last_error: Option<String>,
+    },
     /// Configuration was reloaded (skills, identity, etc.).
     ConfigReloaded,
 }
@@ -211,6 +303,13 @@ impl ApiState {
             agent_tx,
             agent_remove_tx,
             webchat_adapter: ArcSwap::from_pointee(None),
+            session_store: ArcSwap::from_pointee(None),
+            webhook_deliveries: ArcSwap::from_pointee(None),
+            handoff_store: ArcSwap::from_pointee(None),
+            pairing_store: ArcSwap::from_pointee(None),
+            disk_quotas: ArcSwap::from_pointee(HashMap::new()),
+            alert_dispatcher: ArcSwap::from_pointee(None),
+            cluster: ArcSwap::from_pointee(None),
         }
     }
 
@@ -304,6 +403,7 @@ impl ApiState {
                                 worker_id,
                                 channel_id,
                                 result,
+                                artifacts,
                                 ..
                             } => {
                                 api_tx
@@ -312,6 +412,7 @@ impl ApiState {
                                         channel_id: channel_id.as_deref().map(|s| s.to_string()),
                                         worker_id: worker_id.to_string(),
                                         result: result.clone(),
+                                        artifacts: artifacts.clone(),
                                     })
                                     .ok();
                             }
@@ -364,11 +465,150 @@ impl ApiState {
                                     })
                                     .ok();
                             }
+                            ProcessEvent::PromptInjectionDetected {
+                                process_id,
+                                channel_id,
+                                tool_name,
+                                signals,
+                                neutralized,
+                                ..
+                            } => {
+                                let (process_type, id_str) = process_id_info(process_id);
+                                api_tx
+                                    .send(ApiEvent::PromptInjectionDetected {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.as_deref().map(|s| s.to_string()),
+                                        process_type,
+                                        process_id: id_str,
+                                        tool_name: tool_name.clone(),
+                                        signals: signals.clone(),
+                                        neutralized: *neutralized,
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::LoopDetected {
+                                process_id,
+                                channel_id,
+                                kind,
+                                detail,
+                                ..
+                            } => {
+                                let (process_type, id_str) = process_id_info(process_id);
+                                api_tx
+                                    .send(ApiEvent::LoopDetected {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.as_deref().map(|s| s.to_string()),
+                                        process_type,
+                                        process_id: id_str,
+                                        kind: kind.clone(),
+                                        detail: detail.clone(),
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::ProcessUnhealthy {
+                                process_id,
+                                channel_id,
+                                score,
+                                action,
+                                diagnostic,
+                                ..
+                            } => {
+                                let (process_type, id_str) = process_id_info(process_id);
+                                api_tx
+                                    .send(ApiEvent::ProcessUnhealthy {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.to_string(),
+                                        process_type,
+                                        process_id: id_str,
+                                        score: *score,
+                                        action: *action,
+                                        diagnostic: diagnostic.clone(),
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::ChannelTitled {
+                                channel_id, title, ..
+                            } => {
+                                api_tx
+                                    .send(ApiEvent::ChannelTitled {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.to_string(),
+                                        title: title.clone(),
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::CompactionStarted {
+                                channel_id,
+                                action,
+                                trigger,
+                                tokens_before,
+                                ..
+                            } => {
+                                api_tx
+                                    .send(ApiEvent::CompactionStarted {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.to_string(),
+                                        action: *action,
+                                        trigger: *trigger,
+                                        tokens_before: *tokens_before,
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::CompactionCompleted {
+                                channel_id,
+                                action,
+                                trigger,
+                                tokens_before,
+                                tokens_after,
+                                messages_compacted,
+                                error,
+                                ..
+                            } => {
+                                api_tx
+                                    .send(ApiEvent::CompactionCompleted {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.to_string(),
+                                        action: *action,
+                                        trigger: *trigger,
+                                        tokens_before: *tokens_before,
+                                        tokens_after: *tokens_after,
+                                        messages_compacted: *messages_compacted,
+                                        error: error.clone(),
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::MemoryConsolidated { report, .. } => {
+                                api_tx
+                                    .send(ApiEvent::MemoryConsolidated {
+                                        agent_id: agent_id.clone(),
+                                        merged: report.merged,
+                                        promoted: report.promoted,
+                                        archived: report.archived,
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::CircuitBreakerChanged {
+                                process_type,
+                                state,
+                                consecutive_failures,
+                                last_error,
+                                ..
+                            } => {
+                                api_tx
+                                    .send(ApiEvent::CircuitBreakerChanged {
+                                        agent_id: agent_id.clone(),
+                                        process_type: process_type.to_string(),
+                                        state: *state,
+                                        consecutive_failures: *consecutive_failures,
+                                        last_error: last_error.clone(),
+                                    })
+                                    .ok();
+                            }
                             _ => {}
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(count)) => {
-                        tracing::debug!(agent_id = %agent_id, count, "API event forwarder lagged, skipped events");
+                        crate::events::record_lag(&agent_id, "api", count);
                     }
                     Err(broadcast::error::RecvError::Closed) => break,
                 }
@@ -376,6 +616,30 @@ impl ApiState {
         });
     }
 
+    /// Record a config change made through the API in the audit log.
+    ///
+    /// Agent-scoped changes go to that agent's log. Instance-wide changes
+    /// (`agent_id` = None) affect every agent, so they're recorded in each one.
+    pub fn audit_config_change(
+        &self,
+        agent_id: Option<&str>,
+        target: &str,
+        payload: serde_json::Value,
+    ) {
+        let pools = self.agent_pools.load();
+        let selected: Vec<&sqlx::SqlitePool> = match agent_id {
+            Some(agent_id) => pools.get(agent_id).into_iter().collect(),
+            None => pools.values().collect(),
+        };
+        for pool in selected {
+            AuditLogger::new(pool.clone()).with_actor("api").log(
+                AuditAction::ConfigChanged,
+                target,
+                payload.clone(),
+            );
+        }
+    }
+
     /// Set the SQLite pools for all agents.
     pub fn set_agent_pools(&self, pools: HashMap<String, sqlx::SqlitePool>) {
         self.agent_pools.store(Arc::new(pools));
@@ -477,6 +741,41 @@ impl ApiState {
         self.webchat_adapter.store(Arc::new(Some(adapter)));
     }
 
+    /// Set the session store used by the webhook and webchat adapters.
+    pub fn set_session_store(&self, store: Arc<SessionStore>) {
+        self.session_store.store(Arc::new(Some(store)));
+    }
+
+    /// Set the store tracking webhook response deliveries.
+    pub fn set_webhook_deliveries(&self, store: Arc<DeliveryStore>) {
+        self.webhook_deliveries.store(Arc::new(Some(store)));
+    }
+
+    /// Set the store of conversation routes created by handoffs.
+    pub fn set_handoff_store(&self, store: Arc<HandoffStore>) {
+        self.handoff_store.store(Arc::new(Some(store)));
+    }
+
+    /// Set the store of pairing codes and paired users.
+    pub fn set_pairing_store(&self, store: Arc<PairingStore>) {
+        self.pairing_store.store(Arc::new(Some(store)));
+    }
+
+    /// Set the disk quota trackers for all agents.
+    pub fn set_disk_quotas(&self, quotas: HashMap<String, Arc<DiskQuota>>) {
+        self.disk_quotas.store(Arc::new(quotas));
+    }
+
+    /// Set the operator alert dispatcher.
+    pub fn set_alert_dispatcher(&self, dispatcher: Arc<AlertDispatcher>) {
+        self.alert_dispatcher.store(Arc::new(Some(dispatcher)));
+    }
+
+    /// Set this node's cluster membership.
+    pub fn set_cluster(&self, cluster: Arc<Cluster>) {
+        self.cluster.store(Arc::new(Some(cluster)));
+    }
+
     /// Send an event to all SSE subscribers.
     pub fn send_event(&self, event: ApiEvent) {
         let _ = self.event_tx.send(event);
@@ -1,13 +1,14 @@
 use super::state::{ApiEvent, ApiState};
+use crate::object_storage::{INSTANCE_SCOPE, ObjectKind, ObjectStorage, StoredObject};
 
 use axum::Json;
 use axum::body::Bytes;
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::http::header;
 use axum::response::IntoResponse;
 use axum::response::Sse;
 use futures::stream::Stream;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::io::Write as _;
 use std::path::Component;
@@ -70,6 +71,49 @@ pub(super) async fn status(State(state): State<Arc<ApiState>>) -> Json<StatusRes
     })
 }
 
+#[derive(Serialize)]
+pub(super) struct EventBusResponse {
+    subscribers: Vec<crate::events::SubscriberLag>,
+}
+
+/// Event bus subscribers that have fallen behind and dropped events.
+pub(super) async fn event_bus_status() -> Json<EventBusResponse> {
+    Json(EventBusResponse {
+        subscribers: crate::events::lag_stats(),
+    })
+}
+
+#[derive(Serialize)]
+pub(super) struct ClusterResponse {
+    enabled: bool,
+    node_id: Option<String>,
+    nodes: Vec<crate::cluster::NodeStatus>,
+}
+
+/// Cluster membership as seen from this node.
+pub(super) async fn cluster_status(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<ClusterResponse>, (axum::http::StatusCode, String)> {
+    let Some(cluster) = state.cluster.load_full().as_ref().clone() else {
+        return Ok(Json(ClusterResponse {
+            enabled: false,
+            node_id: None,
+            nodes: Vec::new(),
+        }));
+    };
+    let nodes = cluster.nodes().await.map_err(|error| {
+        (
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            format!("can't read cluster nodes: {error}"),
+        )
+    })?;
+    Ok(Json(ClusterResponse {
+        enabled: true,
+        node_id: Some(cluster.node_id().to_string()),
+        nodes,
+    }))
+}
+
 /// SSE endpoint streaming all agent events to connected clients.
 pub(super) async fn events_sse(
     State(state): State<Arc<ApiState>>,
@@ -92,6 +136,14 @@ pub(super) async fn events_sse(
                             ApiEvent::BranchCompleted { .. } => "branch_completed",
                             ApiEvent::ToolStarted { .. } => "tool_started",
                             ApiEvent::ToolCompleted { .. } => "tool_completed",
+                            ApiEvent::PromptInjectionDetected { .. } => "prompt_injection_detected",
+                            ApiEvent::LoopDetected { .. } => "loop_detected",
+                            ApiEvent::ProcessUnhealthy { .. } => "process_unhealthy",
+                            ApiEvent::ChannelTitled { .. } => "channel_titled",
+                            ApiEvent::CompactionStarted { .. } => "compaction_started",
+                            ApiEvent::CompactionCompleted { .. } => "compaction_completed",
+                            ApiEvent::MemoryConsolidated { .. } => "memory_consolidated",
+                            ApiEvent::CircuitBreakerChanged { .. } => "circuit_breaker_changed",
                             ApiEvent::ConfigReloaded => "config_reloaded",
                         };
                         yield Ok(axum::response::sse::Event::default()
@@ -223,8 +275,23 @@ pub(super) async fn backup_export(
         ));
     };
 
-    let instance_dir = runtime_config.instance_dir.clone();
-    let archive_bytes = tokio::task::spawn_blocking(move || build_backup_zip(&instance_dir))
+    let archive_bytes = generate_backup(runtime_config.instance_dir.clone()).await?;
+
+    let headers = [
+        (header::CONTENT_TYPE, "application/zip"),
+        (
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=spacebot-backup.zip",
+        ),
+    ];
+
+    Ok((headers, archive_bytes))
+}
+
+async fn generate_backup(
+    instance_dir: std::path::PathBuf,
+) -> Result<Vec<u8>, (axum::http::StatusCode, String)> {
+    tokio::task::spawn_blocking(move || build_backup_zip(&instance_dir))
         .await
         .map_err(|error| {
             (
@@ -239,30 +306,102 @@ pub(super) async fn backup_export(
                     format!("backup generation failed: {error}"),
                 )
             })
-        })?;
+        })
+}
 
-    let headers = [
-        (header::CONTENT_TYPE, "application/zip"),
+fn require_object_storage(
+    runtime_config: &crate::config::RuntimeConfig,
+) -> Result<Arc<ObjectStorage>, (axum::http::StatusCode, String)> {
+    (**runtime_config.object_storage.load())
+        .clone()
+        .ok_or_else(|| {
+            (
+                axum::http::StatusCode::BAD_REQUEST,
+                "object storage is not configured".to_string(),
+            )
+        })
+}
+
+#[derive(Serialize)]
+pub(super) struct StoredBackup {
+    key: String,
+    url: String,
+}
+
+/// Build a backup and upload it to object storage instead of downloading it.
+pub(super) async fn backup_store(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<StoredBackup>, (axum::http::StatusCode, String)> {
+    let runtime_configs = state.runtime_configs.load();
+    let Some(runtime_config) = runtime_configs.values().next() else {
+        return Err((
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "no runtime config available".to_string(),
+        ));
+    };
+    let storage = require_object_storage(runtime_config)?;
+
+    let archive_bytes = generate_backup(runtime_config.instance_dir.clone()).await?;
+    let name = format!(
+        "spacebot-backup-{}.zip",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let upload_error = |error: anyhow::Error| {
         (
-            header::CONTENT_DISPOSITION,
-            "attachment; filename=spacebot-backup.zip",
-        ),
-    ];
+            axum::http::StatusCode::BAD_GATEWAY,
+            format!("backup upload failed: {error}"),
+        )
+    };
+    let key = storage
+        .put(ObjectKind::Backup, INSTANCE_SCOPE, &name, archive_bytes)
+        .await
+        .map_err(upload_error)?;
+    let url = storage.signed_url(&key).await.map_err(upload_error)?;
 
-    Ok((headers, archive_bytes))
+    tracing::info!(%key, "backup stored in object storage");
+    Ok(Json(StoredBackup { key, url }))
 }
 
-pub(super) async fn backup_restore(
+#[derive(Serialize)]
+pub(super) struct StoredBackupsResponse {
+    backups: Vec<StoredObject>,
+}
+
+/// Backups in object storage, newest first.
+pub(super) async fn backup_list_stored(
     State(state): State<Arc<ApiState>>,
-    body: Bytes,
-) -> Result<Json<serde_json::Value>, (axum::http::StatusCode, String)> {
-    if body.is_empty() {
+) -> Result<Json<StoredBackupsResponse>, (axum::http::StatusCode, String)> {
+    let runtime_configs = state.runtime_configs.load();
+    let Some(runtime_config) = runtime_configs.values().next() else {
         return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            "backup archive payload is empty".to_string(),
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "no runtime config available".to_string(),
         ));
-    }
+    };
+    let storage = require_object_storage(runtime_config)?;
+    let backups = storage
+        .list(ObjectKind::Backup, INSTANCE_SCOPE)
+        .await
+        .map_err(|error| {
+            (
+                axum::http::StatusCode::BAD_GATEWAY,
+                format!("can't list backups: {error}"),
+            )
+        })?;
+    Ok(Json(StoredBackupsResponse { backups }))
+}
+
+#[derive(Deserialize)]
+pub(super) struct BackupRestoreQuery {
+    /// Object storage key to restore from instead of the request body.
+    key: Option<String>,
+}
 
+pub(super) async fn backup_restore(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<BackupRestoreQuery>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (axum::http::StatusCode, String)> {
     let runtime_configs = state.runtime_configs.load();
     let Some(runtime_config) = runtime_configs.values().next() else {
         return Err((
@@ -271,8 +410,26 @@ pub(super) async fn backup_restore(
         ));
     };
 
+    let archive = match query.key {
+        Some(key) => {
+            let storage = require_object_storage(runtime_config)?;
+            storage.get(&key).await.map_err(|error| {
+                (
+                    axum::http::StatusCode::BAD_GATEWAY,
+                    format!("can't fetch backup: {error}"),
+                )
+            })?
+        }
+        None => body.to_vec(),
+    };
+    if archive.is_empty() {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            "backup archive payload is empty".to_string(),
+        ));
+    }
+
     let instance_dir = runtime_config.instance_dir.clone();
-    let archive = body.to_vec();
 
     let restore_report =
         tokio::task::spawn_blocking(move || restore_backup_zip(&instance_dir, archive))
@@ -0,0 +1,125 @@
+//! The cortex's task queue: listing, adding and cancelling queued tasks.
+
+use super::state::ApiState;
+
+use crate::agent::task_queue::{
+    NewTask, QueuedTask, TaskOrigin, TaskPriority, TaskQueue, TaskStatus,
+};
+use crate::messaging::target::parse_delivery_target;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub(super) struct TaskQueueResponse {
+    tasks: Vec<QueuedTask>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct TaskQueueQuery {
+    agent_id: String,
+    /// Only tasks with this status.
+    #[serde(default)]
+    status: Option<TaskStatus>,
+    #[serde(default = "default_task_queue_limit")]
+    limit: i64,
+}
+
+fn default_task_queue_limit() -> i64 {
+    50
+}
+
+#[derive(Deserialize)]
+pub(super) struct QueueTaskRequest {
+    agent_id: String,
+    task: String,
+    #[serde(default)]
+    priority: TaskPriority,
+    /// Where the result is posted, as `adapter:target`.
+    #[serde(default)]
+    report_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CancelTaskRequest {
+    agent_id: String,
+    id: String,
+}
+
+fn task_queue(state: &ApiState, agent_id: &str) -> Result<TaskQueue, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(TaskQueue::new(pool.clone()))
+}
+
+/// List an agent's queued tasks, newest first.
+pub(super) async fn list_tasks(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<TaskQueueQuery>,
+) -> Result<Json<TaskQueueResponse>, StatusCode> {
+    let tasks = task_queue(&state, &query.agent_id)?
+        .list(query.status, query.limit.min(200))
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to list queued tasks");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(TaskQueueResponse { tasks }))
+}
+
+/// Add a task to an agent's queue.
+pub(super) async fn queue_task(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<QueueTaskRequest>,
+) -> Result<Json<QueuedTask>, StatusCode> {
+    let queue = task_queue(&state, &request.agent_id)?;
+    if request.task.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let report_to = match &request.report_to {
+        Some(raw) => Some(parse_delivery_target(raw).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    let queued = queue
+        .enqueue(NewTask {
+            priority: request.priority,
+            report_to,
+            ..NewTask::new(request.task.trim(), TaskOrigin::Api)
+        })
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to queue task");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(queued))
+}
+
+/// Cancel a pending task. Running and finished tasks can't be cancelled.
+pub(super) async fn cancel_task(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CancelTaskRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let queue = task_queue(&state, &request.agent_id)?;
+    let cancelled = queue.cancel(&request.id).await.map_err(|error| {
+        tracing::warn!(%error, "failed to cancel queued task");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if cancelled {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    match queue.get(&request.id).await {
+        Ok(Some(_)) => Err(StatusCode::CONFLICT),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(error) => {
+            tracing::warn!(%error, "failed to load queued task");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Inbound webhooks for `[[agents.triggers]]` with a `webhook_token`.
+//!
+//! These routes skip the API token check: each trigger carries its own token,
+//! so a webhook sender only ever holds the key to the one trigger it fires.
+
+use super::state::ApiState;
+
+use crate::agent::trigger::WebhookCall;
+use crate::config::TriggerSource;
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use subtle::ConstantTimeEq as _;
+
+use std::sync::Arc;
+
+/// Header a webhook sender can use for the token instead of `Authorization`.
+const TOKEN_HEADER: &str = "x-trigger-token";
+
+/// POST /api/triggers/{agent_id}/{trigger_id} — fire a webhook trigger. The
+/// request body is passed on to the trigger's task and notification.
+pub(super) async fn fire_trigger(
+    State(state): State<Arc<ApiState>>,
+    Path((agent_id, trigger_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    let configs = state.runtime_configs.load();
+    let Some(runtime_config) = configs.get(&agent_id) else {
+        return StatusCode::NOT_FOUND;
+    };
+    let triggers = runtime_config.triggers.load();
+    let Some(expected_token) = triggers
+        .iter()
+        .filter(|trigger| trigger.enabled && trigger.id == trigger_id)
+        .find_map(|trigger| match &trigger.source {
+            TriggerSource::Webhook { token } => Some(token),
+            _ => None,
+        })
+    else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            headers
+                .get(TOKEN_HEADER)
+                .and_then(|value| value.to_str().ok())
+        });
+    let token_valid =
+        token.is_some_and(|token| bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())));
+    if !token_valid {
+        tracing::warn!(%agent_id, %trigger_id, "trigger webhook called with a bad token");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let call = WebhookCall {
+        trigger_id,
+        payload: body,
+    };
+    if runtime_config.trigger_hub.fire_webhook(call) {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
@@ -1,13 +1,17 @@
 use super::state::ApiState;
+use crate::agent::voice::{VoiceEvent, VoiceParticipant, VoiceSession};
 use crate::conversation::ConversationLogger;
+use crate::messaging::session::{NewSession, Session, SessionError};
 use crate::messaging::webchat::WebChatEvent;
 use crate::{InboundMessage, MessageContent};
 
 use axum::Json;
-use axum::extract::{Query, State};
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::Sse;
 use futures::stream::Stream;
+use futures::{SinkExt as _, StreamExt as _};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::Infallible;
@@ -15,8 +19,16 @@ use std::sync::Arc;
 
 #[derive(Deserialize)]
 pub(super) struct WebChatSendRequest {
-    agent_id: String,
-    session_id: String,
+    /// Required unless the session token is bound to an agent.
+    #[serde(default)]
+    agent_id: Option<String>,
+    /// Caller-chosen conversation ID. Required without a session token.
+    #[serde(default)]
+    session_id: Option<String>,
+    /// Issued by `POST /webchat/sessions`. Supplies the sender, conversation
+    /// and (optionally) agent.
+    #[serde(default)]
+    session_token: Option<String>,
     #[serde(default = "default_sender_name")]
     sender_name: String,
     message: String,
@@ -46,26 +58,38 @@ pub(super) async fn webchat_send(
         .clone()
         .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
 
-    let conversation_id = request.session_id.clone();
+    let session = resolve_session(&state, request.session_token.as_deref())?;
+    let (conversation_id, sender_id, sender_name, agent_id) = resolve_participant(
+        session.as_ref(),
+        request.agent_id,
+        request.session_id,
+        request.sender_name,
+    )?;
 
     let mut event_rx = webchat.register_session(&conversation_id).await;
 
     let mut metadata = HashMap::new();
     metadata.insert(
         "display_name".into(),
-        serde_json::Value::String(request.sender_name.clone()),
+        serde_json::Value::String(sender_name.clone()),
     );
+    if let Some(session) = &session {
+        metadata.insert(
+            "session_id".into(),
+            serde_json::Value::String(session.id.clone()),
+        );
+    }
 
     let inbound = InboundMessage {
         id: uuid::Uuid::new_v4().to_string(),
         source: "webchat".into(),
         conversation_id: conversation_id.clone(),
-        sender_id: request.sender_name.clone(),
-        agent_id: Some(request.agent_id.into()),
+        sender_id,
+        agent_id: Some(agent_id.into()),
         content: MessageContent::Text(request.message),
         timestamp: chrono::Utc::now(),
         metadata,
-        formatted_author: Some(request.sender_name),
+        formatted_author: Some(sender_name),
     };
 
     manager.inject_message(inbound).await.map_err(|error| {
@@ -109,10 +133,115 @@ pub(super) async fn webchat_send(
     Ok(Sse::new(stream))
 }
 
+#[derive(Deserialize)]
+pub(super) struct WebChatVoiceQuery {
+    #[serde(default)]
+    agent_id: Option<String>,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    session_token: Option<String>,
+    #[serde(default = "default_sender_name")]
+    sender_name: String,
+    /// Sample rate of the PCM the client sends.
+    #[serde(default = "default_sample_rate")]
+    sample_rate: u32,
+}
+
+fn default_sample_rate() -> u32 {
+    16_000
+}
+
+/// Talk to an agent over a WebSocket. The client sends 16-bit little-endian
+/// mono PCM at `sample_rate` as binary frames. It receives the spoken reply
+/// as binary frames of 24 kHz PCM and `VoiceEvent`s as JSON text frames.
+pub(super) async fn webchat_voice(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<WebChatVoiceQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Result<axum::response::Response, StatusCode> {
+    let session = resolve_session(&state, query.session_token.as_deref())?;
+    let (conversation_id, sender_id, sender_name, agent_id) = resolve_participant(
+        session.as_ref(),
+        query.agent_id,
+        query.session_id,
+        query.sender_name,
+    )?;
+    if !(8_000..=48_000).contains(&query.sample_rate) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let deps = state
+        .cortex_chat_sessions
+        .load()
+        .get(&agent_id)
+        .map(|session| session.deps.clone())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !deps.runtime_config.voice.load().enabled {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let participant = VoiceParticipant {
+        sender_id,
+        sender_name,
+    };
+    let sample_rate = query.sample_rate;
+    Ok(upgrade.on_upgrade(move |socket| async move {
+        let voice = VoiceSession::start(deps, conversation_id.into(), participant, sample_rate);
+        run_voice_socket(socket, voice).await;
+    }))
+}
+
+async fn run_voice_socket(socket: WebSocket, voice: VoiceSession) {
+    let VoiceSession {
+        audio_tx,
+        mut events,
+    } = voice;
+    let (mut sender, mut receiver) = socket.split();
+
+    let outbound = tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            let message = match event {
+                VoiceEvent::Audio(audio) => WsMessage::Binary(audio.into()),
+                event => match serde_json::to_string(&event) {
+                    Ok(json) => WsMessage::Text(json.into()),
+                    Err(_) => continue,
+                },
+            };
+            if sender.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = receiver.next().await {
+        match message {
+            WsMessage::Binary(bytes) => {
+                let samples = bytes
+                    .chunks_exact(2)
+                    .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                if audio_tx.send(samples).await.is_err() {
+                    break;
+                }
+            }
+            WsMessage::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    // Dropping the audio sender ends the voice session.
+    drop(audio_tx);
+    outbound.abort();
+}
+
 #[derive(Deserialize)]
 pub(super) struct WebChatHistoryQuery {
     agent_id: String,
-    session_id: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    session_token: Option<String>,
     #[serde(default = "default_limit")]
     limit: i64,
 }
@@ -136,7 +265,11 @@ pub(super) async fn webchat_history(
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
     let logger = ConversationLogger::new(pool.clone());
 
-    let channel_id: crate::ChannelId = Arc::from(query.session_id.as_str());
+    let conversation_id = match resolve_session(&state, query.session_token.as_deref())? {
+        Some(session) => session_conversation_id(&session),
+        None => query.session_id.ok_or(StatusCode::BAD_REQUEST)?,
+    };
+    let channel_id: crate::ChannelId = Arc::from(conversation_id.as_str());
 
     let messages = logger
         .load_recent(&channel_id, query.limit.min(200))
@@ -157,3 +290,201 @@ pub(super) async fn webchat_history(
 
     Ok(Json(result))
 }
+
+/// Conversation, sender ID, sender name and agent for a request, taken from
+/// the session when there is one.
+fn resolve_participant(
+    session: Option<&Session>,
+    agent_id: Option<String>,
+    session_id: Option<String>,
+    sender_name: String,
+) -> Result<(String, String, String, String), StatusCode> {
+    let (conversation_id, sender_id, sender_name, agent_id) = match session {
+        Some(session) => (
+            session_conversation_id(session),
+            session.user_id.clone(),
+            session.sender_name().to_string(),
+            session.agent_id.clone().or(agent_id),
+        ),
+        None => (
+            session_id.ok_or(StatusCode::BAD_REQUEST)?,
+            sender_name.clone(),
+            sender_name,
+            agent_id,
+        ),
+    };
+    let agent_id = agent_id.ok_or(StatusCode::BAD_REQUEST)?;
+    Ok((conversation_id, sender_id, sender_name, agent_id))
+}
+
+/// Webchat conversations opened through a session are namespaced so a
+/// user-derived ID can't collide with a caller-chosen `session_id`.
+fn session_conversation_id(session: &Session) -> String {
+    format!("webchat:{}", session.conversation_id)
+}
+
+fn resolve_session(state: &ApiState, token: Option<&str>) -> Result<Option<Session>, StatusCode> {
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let store = state.session_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    match store.validate(token, "webchat") {
+        Ok(session) => Ok(Some(session)),
+        Err(SessionError::Storage(message)) => {
+            tracing::warn!(%message, "webchat session lookup failed");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        Err(error) => {
+            tracing::debug!(%error, "rejected webchat session token");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(super) struct CreateSessionRequest {
+    user_id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+    #[serde(default)]
+    agent_id: Option<String>,
+    #[serde(default)]
+    ttl_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub(super) struct CreateSessionResponse {
+    token: String,
+    session: Session,
+}
+
+/// Issue a webchat session token for a user.
+pub(super) async fn create_session(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CreateSessionRequest>,
+) -> Result<(StatusCode, Json<CreateSessionResponse>), StatusCode> {
+    if request.user_id.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let store = state.session_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let (token, session) = store
+        .issue(NewSession {
+            source: "webchat".into(),
+            user_id: request.user_id,
+            display_name: request.display_name,
+            conversation_id: request.conversation_id,
+            agent_id: request.agent_id,
+            ttl_secs: request.ttl_secs,
+        })
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to issue webchat session");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateSessionResponse { token, session }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub(super) struct ListSessionsQuery {
+    #[serde(default)]
+    user_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ListSessionsResponse {
+    sessions: Vec<Session>,
+}
+
+/// List webchat sessions, optionally for one user.
+pub(super) async fn list_sessions(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ListSessionsQuery>,
+) -> Result<Json<ListSessionsResponse>, StatusCode> {
+    let store = state.session_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let sessions = store
+        .list(Some("webchat"), query.user_id.as_deref())
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to list webchat sessions");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(ListSessionsResponse { sessions }))
+}
+
+/// Revoke a single webchat session.
+pub(super) async fn revoke_session(
+    State(state): State<Arc<ApiState>>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let store = state.session_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let session = store.get(&session_id).map_err(|error| {
+        tracing::warn!(%error, "failed to load webchat session");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if session.is_none_or(|session| session.source != "webchat") {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    store.revoke(&session_id).map_err(|error| {
+        tracing::warn!(%error, "failed to revoke webchat session");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub(super) struct RevokeUserSessionsRequest {
+    user_id: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct RevokeUserSessionsResponse {
+    revoked: usize,
+}
+
+/// Revoke every active webchat session for a user.
+pub(super) async fn revoke_user_sessions(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<RevokeUserSessionsRequest>,
+) -> Result<Json<RevokeUserSessionsResponse>, StatusCode> {
+    let store = state.session_store.load();
+    let store = store
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let revoked = store
+        .revoke_user("webchat", &request.user_id)
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to revoke webchat sessions");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(RevokeUserSessionsResponse { revoked }))
+}
@@ -0,0 +1,338 @@
+//! Append-only audit log of privileged actions.
+
+use crate::{AgentId, ProcessEvent};
+
+use serde::Serialize;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use tokio::sync::broadcast;
+
+/// Kinds of privileged action recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// Configuration changed through the API (agent config, providers, bindings, ...).
+    ConfigChanged,
+    /// An OpenCode worker asked for a permission, which OpenCode auto-allows.
+    PermissionRequest,
+    /// A user's follow-up message was routed to a running worker.
+    WorkerFollowUp,
+    /// A shell command ran.
+    ShellExec,
+    /// A program ran via the exec tool.
+    Exec,
+    /// An OpenCode worker was spawned.
+    OpencodeWorker,
+    /// A memory was deleted (forgotten).
+    MemoryDeleted,
+    /// An announcement was sent to an agent's bound conversations.
+    Announcement,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ConfigChanged => "config_changed",
+            Self::PermissionRequest => "permission_request",
+            Self::WorkerFollowUp => "worker_follow_up",
+            Self::ShellExec => "shell_exec",
+            Self::Exec => "exec",
+            Self::OpencodeWorker => "opencode_worker",
+            Self::MemoryDeleted => "memory_deleted",
+            Self::Announcement => "announcement",
+        }
+    }
+}
+
+/// A single audit entry, as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub action: String,
+    pub actor: String,
+    pub target: String,
+    pub payload: Option<serde_json::Value>,
+    pub created_at: String,
+}
+
+/// Optional filters for querying the audit log. All set fields must match.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Writes and reads audit entries in an agent's database.
+///
+/// Each logger is bound to an actor (e.g. `api`, `discord:1234`,
+/// `worker:<id>`) that is recorded with every entry it writes.
+#[derive(Debug, Clone)]
+pub struct AuditLogger {
+    pool: SqlitePool,
+    actor: String,
+}
+
+impl AuditLogger {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            actor: "system".into(),
+        }
+    }
+
+    /// Return a copy of this logger that records a different actor.
+    pub fn with_actor(&self, actor: impl Into<String>) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            actor: actor.into(),
+        }
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    /// Record a privileged action. Fire-and-forget.
+    pub fn log(&self, action: AuditAction, target: &str, payload: serde_json::Value) {
+        let logger = self.clone();
+        let target = target.to_string();
+
+        crate::shutdown::spawn_write(async move {
+            if let Err(error) = logger.write(action, &target, &payload).await {
+                tracing::warn!(%error, action = action.as_str(), "failed to persist audit entry");
+            }
+        });
+    }
+
+    async fn write(
+        &self,
+        action: AuditAction,
+        target: &str,
+        payload: &serde_json::Value,
+    ) -> std::result::Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO audit_log (id, action, actor, target, payload) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(action.as_str())
+        .bind(&self.actor)
+        .bind(target)
+        .bind(payload.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Load audit entries matching a filter, newest first.
+    pub async fn load_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: i64,
+        offset: i64,
+    ) -> std::result::Result<Vec<AuditEntry>, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT id, action, actor, target, payload, created_at FROM audit_log",
+        );
+        push_filter(&mut query, filter);
+        query
+            .push(" ORDER BY created_at DESC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let rows = query
+            .build_query_as::<AuditEntryRow>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.into_entry()).collect())
+    }
+
+    /// Count audit entries matching a filter.
+    pub async fn count_entries(
+        &self,
+        filter: &AuditFilter,
+    ) -> std::result::Result<i64, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM audit_log");
+        push_filter(&mut query, filter);
+
+        let count: (i64,) = query.build_query_as().fetch_one(&self.pool).await?;
+        Ok(count.0)
+    }
+}
+
+/// Record privileged actions that are only visible on the agent's event bus.
+///
+/// OpenCode permission requests are auto-allowed inside the worker, which has
+/// no database access, so they're picked up from `WorkerPermission` events.
+pub fn spawn_event_audit_loop(
+    agent_id: AgentId,
+    mut event_rx: broadcast::Receiver<ProcessEvent>,
+    logger: AuditLogger,
+) -> tokio::task::JoinHandle<()> {
+    let logger = logger.with_actor("opencode:auto");
+    tokio::spawn(async move {
+        loop {
+            match event_rx.recv().await {
+                Ok(ProcessEvent::WorkerPermission {
+                    worker_id,
+                    channel_id,
+                    permission_id,
+                    description,
+                    patterns,
+                    ..
+                }) => {
+                    logger.log(
+                        AuditAction::PermissionRequest,
+                        &description,
+                        serde_json::json!({
+                            "worker_id": worker_id.to_string(),
+                            "channel_id": channel_id.as_deref(),
+                            "permission_id": permission_id,
+                            "patterns": patterns,
+                        }),
+                    );
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    crate::events::record_lag(&agent_id, "audit", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Append a WHERE clause for every set filter field.
+fn push_filter(query: &mut QueryBuilder<'_, Sqlite>, filter: &AuditFilter) {
+    let mut separator = " WHERE ";
+    if let Some(action) = &filter.action {
+        query
+            .push(separator)
+            .push("action = ")
+            .push_bind(action.clone());
+        separator = " AND ";
+    }
+    if let Some(actor) = &filter.actor {
+        query
+            .push(separator)
+            .push("actor = ")
+            .push_bind(actor.clone());
+        separator = " AND ";
+    }
+    if let Some(since) = filter.since {
+        query
+            .push(separator)
+            .push("created_at >= ")
+            .push_bind(since.naive_utc());
+        separator = " AND ";
+    }
+    if let Some(until) = filter.until {
+        query
+            .push(separator)
+            .push("created_at < ")
+            .push_bind(until.naive_utc());
+    }
+}
+
+/// Internal row type for SQLite query mapping.
+#[derive(sqlx::FromRow)]
+struct AuditEntryRow {
+    id: String,
+    action: String,
+    actor: String,
+    target: String,
+    payload: Option<String>,
+    created_at: chrono::NaiveDateTime,
+}
+
+impl AuditEntryRow {
+    fn into_entry(self) -> AuditEntry {
+        AuditEntry {
+            id: self.id,
+            action: self.action,
+            actor: self.actor,
+            target: self.target,
+            payload: self
+                .payload
+                .and_then(|payload| serde_json::from_str(&payload).ok()),
+            created_at: self.created_at.and_utc().to_rfc3339(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn logger() -> AuditLogger {
+        AuditLogger::new(crate::db::test_pool().await)
+    }
+
+    #[tokio::test]
+    async fn filters_by_action_and_actor() {
+        let logger = logger().await;
+        let api = logger.with_actor("api");
+        let worker = logger.with_actor("worker:1");
+
+        api.write(
+            AuditAction::ConfigChanged,
+            "agent:main",
+            &serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        worker
+            .write(
+                AuditAction::ShellExec,
+                "ls -la",
+                &serde_json::json!({"cwd": "/tmp"}),
+            )
+            .await
+            .unwrap();
+
+        let all = AuditFilter::default();
+        assert_eq!(logger.count_entries(&all).await.unwrap(), 2);
+
+        let shell_only = AuditFilter {
+            action: Some("shell_exec".into()),
+            ..Default::default()
+        };
+        let entries = logger.load_entries(&shell_only, 10, 0).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "worker:1");
+        assert_eq!(entries[0].target, "ls -la");
+
+        let api_only = AuditFilter {
+            actor: Some("api".into()),
+            ..Default::default()
+        };
+        assert_eq!(logger.count_entries(&api_only).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn entries_are_append_only() {
+        let logger = logger().await;
+        logger
+            .write(
+                AuditAction::MemoryDeleted,
+                "memory-1",
+                &serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+
+        assert!(
+            sqlx::query("DELETE FROM audit_log")
+                .execute(&logger.pool)
+                .await
+                .is_err()
+        );
+        assert!(
+            sqlx::query("UPDATE audit_log SET actor = 'someone-else'")
+                .execute(&logger.pool)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,486 @@
+//! Load-testing harness behind `spacebot bench`.
+//!
+//! A [`BenchAdapter`] stands in for a messaging platform. It replays scripted
+//! conversations through the normal inbound path at a fixed concurrency and
+//! times each message until the agent's first reply. The run can use the
+//! configured LLM providers, or a local mock that answers every completion
+//! with canned text after a fixed delay so only spacebot's own overhead is
+//! measured. Runs use a scratch instance directory, so they never touch the
+//! real databases or connect to real platforms.
+
+use crate::config::{ApiType, Config, MessagingConfig, ProviderConfig};
+use crate::error::Result;
+use crate::llm::RoutingConfig;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use futures::StreamExt as _;
+use serde::Deserialize;
+use tokio::sync::{Mutex, mpsc, oneshot};
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Adapter name, and the `source` of every scripted message.
+pub const BENCH_ADAPTER: &str = "bench";
+
+/// Provider name and model the mock LLM is registered as.
+const MOCK_PROVIDER: &str = "bench";
+const MOCK_MODEL: &str = "bench/mock";
+
+/// Text of every mock completion.
+const MOCK_REPLY: &str = "Acknowledged. This is a canned reply from the benchmark mock model.";
+
+/// A scripted load test, read from TOML.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BenchScript {
+    /// Conversations in flight at once.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// How many times each conversation is replayed, each time as a new
+    /// conversation.
+    #[serde(default = "default_repeat")]
+    pub repeat: usize,
+    /// How long to wait for a reply before counting the turn as timed out.
+    #[serde(default = "default_turn_timeout_secs")]
+    pub turn_timeout_secs: u64,
+    pub conversations: Vec<ScriptedConversation>,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_repeat() -> usize {
+    1
+}
+
+fn default_turn_timeout_secs() -> u64 {
+    120
+}
+
+/// Messages sent one after another, each after the reply to the previous one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptedConversation {
+    pub name: String,
+    pub messages: Vec<String>,
+}
+
+impl BenchScript {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("can't read bench script {}", path.display()))?;
+        let script: Self = toml::from_str(&raw)
+            .with_context(|| format!("can't parse bench script {}", path.display()))?;
+        if script.conversations.is_empty() {
+            anyhow::bail!("bench script has no conversations");
+        }
+        if script.concurrency == 0 || script.repeat == 0 {
+            anyhow::bail!("bench script concurrency and repeat must be at least 1");
+        }
+        Ok(script)
+    }
+}
+
+#[derive(Default)]
+struct AdapterState {
+    inbound_tx: Option<mpsc::Sender<InboundMessage>>,
+    /// Conversations waiting for their next reply.
+    waiters: HashMap<String, oneshot::Sender<Instant>>,
+}
+
+/// Synthetic messaging adapter that feeds scripted messages in and signals
+/// replies back to the driver.
+#[derive(Clone, Default)]
+pub struct BenchAdapter {
+    state: Arc<Mutex<AdapterState>>,
+}
+
+impl BenchAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a message and wait for the first reply in its conversation.
+    /// Returns the time to that reply, or `None` on timeout.
+    async fn send_and_wait(
+        &self,
+        conversation_id: &str,
+        content: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<Option<Duration>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let inbound_tx = {
+            let mut state = self.state.lock().await;
+            state.waiters.insert(conversation_id.to_string(), reply_tx);
+            state
+                .inbound_tx
+                .clone()
+                .context("bench adapter not started")?
+        };
+
+        let message = InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: BENCH_ADAPTER.into(),
+            conversation_id: conversation_id.to_string(),
+            sender_id: "bench-user".into(),
+            agent_id: None,
+            content: MessageContent::Text(content.to_string()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::from([(
+                "sender_display_name".to_string(),
+                serde_json::Value::String("Bench User".into()),
+            )]),
+            formatted_author: None,
+        };
+        let sent_at = Instant::now();
+        inbound_tx
+            .send(message)
+            .await
+            .context("inbound stream closed")?;
+
+        match tokio::time::timeout(timeout, reply_rx).await {
+            Ok(Ok(replied_at)) => Ok(Some(replied_at.duration_since(sent_at))),
+            _ => {
+                self.state.lock().await.waiters.remove(conversation_id);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Messaging for BenchAdapter {
+    fn name(&self) -> &str {
+        BENCH_ADAPTER
+    }
+
+    async fn start(&self) -> Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        self.state.lock().await.inbound_tx = Some(inbound_tx);
+        Ok(Box::pin(tokio_stream::wrappers::ReceiverStream::new(
+            inbound_rx,
+        )))
+    }
+
+    async fn respond(&self, message: &InboundMessage, response: OutboundResponse) -> Result<()> {
+        let replied_at = Instant::now();
+        if matches!(
+            response,
+            OutboundResponse::Text(_)
+                | OutboundResponse::RichMessage { .. }
+                | OutboundResponse::Choices { .. }
+                | OutboundResponse::Rich { .. }
+                | OutboundResponse::ThreadReply { .. }
+        ) && let Some(waiter) = self
+            .state
+            .lock()
+            .await
+            .waiters
+            .remove(&message.conversation_id)
+        {
+            waiter.send(replied_at).ok();
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Turn latency and resource usage of a finished run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub turns: usize,
+    pub timeouts: usize,
+    pub elapsed: Duration,
+    pub latencies: Vec<Duration>,
+    pub usage: Option<ResourceUsage>,
+}
+
+impl BenchReport {
+    /// Latency at percentile `p` (0-100), by nearest rank.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        percentile(&self.latencies, p)
+    }
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let millis = |latency: Option<Duration>| {
+            latency
+                .map(|latency| format!("{} ms", latency.as_millis()))
+                .unwrap_or_else(|| "-".into())
+        };
+        let completed = self.latencies.len();
+        writeln!(
+            f,
+            "turns:       {} ({} timed out)",
+            self.turns, self.timeouts
+        )?;
+        writeln!(f, "elapsed:     {:.1} s", self.elapsed.as_secs_f64())?;
+        writeln!(
+            f,
+            "throughput:  {:.2} turns/s",
+            completed as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+        )?;
+        writeln!(f, "latency p50: {}", millis(self.percentile(50.0)))?;
+        writeln!(f, "latency p90: {}", millis(self.percentile(90.0)))?;
+        writeln!(f, "latency p99: {}", millis(self.percentile(99.0)))?;
+        writeln!(
+            f,
+            "latency max: {}",
+            millis(self.latencies.iter().max().copied())
+        )?;
+        if let Some(usage) = &self.usage {
+            writeln!(
+                f,
+                "peak rss:    {:.1} MB",
+                usage.peak_rss_bytes as f64 / (1024.0 * 1024.0)
+            )?;
+            writeln!(f, "cpu time:    {:.1} s", usage.cpu_time.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+fn percentile(latencies: &[Duration], p: f64) -> Option<Duration> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort_unstable();
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Process resource usage, from `getrusage`.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUsage {
+    pub peak_rss_bytes: u64,
+    /// User plus system CPU time.
+    pub cpu_time: Duration,
+}
+
+#[cfg(unix)]
+fn resource_usage() -> Option<ResourceUsage> {
+    // SAFETY: rusage is plain data, so all-zero is a valid value, and
+    // getrusage only writes into the struct it is given.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let timeval = |value: libc::timeval| {
+        Duration::from_secs(value.tv_sec as u64) + Duration::from_micros(value.tv_usec as u64)
+    };
+    // Linux reports the peak in kilobytes, macOS in bytes.
+    let peak_rss_bytes = if cfg!(target_os = "macos") {
+        usage.ru_maxrss as u64
+    } else {
+        usage.ru_maxrss as u64 * 1024
+    };
+    Some(ResourceUsage {
+        peak_rss_bytes,
+        cpu_time: timeval(usage.ru_utime) + timeval(usage.ru_stime),
+    })
+}
+
+#[cfg(not(unix))]
+fn resource_usage() -> Option<ResourceUsage> {
+    None
+}
+
+/// Replay the script through the adapter and collect the report.
+pub async fn run_script(adapter: BenchAdapter, script: BenchScript) -> BenchReport {
+    let timeout = Duration::from_secs(script.turn_timeout_secs);
+    let replays: Vec<(String, Vec<String>)> = (0..script.repeat)
+        .flat_map(|iteration| {
+            script.conversations.iter().map(move |conversation| {
+                (
+                    format!("{BENCH_ADAPTER}:{}:{iteration}", conversation.name),
+                    conversation.messages.clone(),
+                )
+            })
+        })
+        .collect();
+
+    let started = Instant::now();
+    let results: Vec<Vec<Option<Duration>>> = futures::stream::iter(replays)
+        .map(|(conversation_id, messages)| {
+            let adapter = adapter.clone();
+            async move {
+                let mut latencies = Vec::with_capacity(messages.len());
+                for content in &messages {
+                    match adapter
+                        .send_and_wait(&conversation_id, content, timeout)
+                        .await
+                    {
+                        Ok(latency) => latencies.push(latency),
+                        Err(error) => {
+                            tracing::warn!(%error, conversation_id, "bench conversation aborted");
+                            break;
+                        }
+                    }
+                }
+                latencies
+            }
+        })
+        .buffer_unordered(script.concurrency)
+        .collect()
+        .await;
+
+    let turns: Vec<Option<Duration>> = results.into_iter().flatten().collect();
+    BenchReport {
+        turns: turns.len(),
+        timeouts: turns.iter().filter(|latency| latency.is_none()).count(),
+        elapsed: started.elapsed(),
+        latencies: turns.into_iter().flatten().collect(),
+        usage: resource_usage(),
+    }
+}
+
+/// Point the config at a scratch instance directory under the real one, with
+/// no platform adapters, API server or alerts. The embedding model cache is
+/// shared so it isn't downloaded again.
+pub fn prepare_instance(config: &mut Config) -> anyhow::Result<PathBuf> {
+    let source_dir = config.instance_dir.clone();
+    let bench_dir = source_dir
+        .join("bench")
+        .join(chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string());
+    std::fs::create_dir_all(&bench_dir)
+        .with_context(|| format!("can't create {}", bench_dir.display()))?;
+
+    #[cfg(unix)]
+    if source_dir.join("embedding_cache").exists() {
+        std::os::unix::fs::symlink(
+            source_dir.join("embedding_cache"),
+            bench_dir.join("embedding_cache"),
+        )
+        .ok();
+    }
+
+    config.instance_dir = bench_dir.clone();
+    config.messaging = MessagingConfig {
+        sessions: config.messaging.sessions,
+        ..MessagingConfig::default()
+    };
+    config.api.enabled = false;
+    config.alerts = crate::alerts::AlertConfig::default();
+    for agent in &mut config.agents {
+        agent.workspace = None;
+    }
+    Ok(bench_dir)
+}
+
+/// Serve an OpenAI-compatible completions endpoint on localhost that answers
+/// every request with [`MOCK_REPLY`] after `latency`.
+pub async fn start_mock_llm(latency: Duration) -> anyhow::Result<SocketAddr> {
+    use axum::routing::post;
+
+    let app = axum::Router::new().route(
+        "/v1/chat/completions",
+        post(move || async move {
+            tokio::time::sleep(latency).await;
+            axum::Json(serde_json::json!({
+                "id": format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+                "object": "chat.completion",
+                "created": chrono::Utc::now().timestamp(),
+                "model": "mock",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": MOCK_REPLY },
+                    "finish_reason": "stop",
+                }],
+                "usage": { "prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0 },
+            }))
+        }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .context("can't bind mock LLM server")?;
+    let address = listener.local_addr()?;
+    tokio::spawn(async move {
+        if let Err(error) = axum::serve(listener, app).await {
+            tracing::error!(%error, "mock LLM server stopped");
+        }
+    });
+    Ok(address)
+}
+
+/// Route every process of every agent to the mock LLM at `address`.
+pub fn use_mock_llm(config: &mut Config, address: SocketAddr) {
+    config.llm.providers.insert(
+        MOCK_PROVIDER.into(),
+        ProviderConfig {
+            api_type: ApiType::OpenAiCompletions,
+            base_url: format!("http://{address}"),
+            api_key: "bench".into(),
+            name: Some("Bench mock".into()),
+        },
+    );
+
+    let route_to_mock = |routing: &mut RoutingConfig| {
+        for model in [
+            &mut routing.channel,
+            &mut routing.branch,
+            &mut routing.worker,
+            &mut routing.compactor,
+            &mut routing.cortex,
+        ] {
+            *model = MOCK_MODEL.into();
+        }
+        routing.task_overrides.clear();
+        routing.fallbacks.clear();
+    };
+    route_to_mock(&mut config.defaults.routing);
+    for agent in &mut config.agents {
+        if let Some(routing) = agent.routing.as_mut() {
+            route_to_mock(routing);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let latencies: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&latencies, 50.0), Some(Duration::from_millis(5)));
+        assert_eq!(percentile(&latencies, 90.0), Some(Duration::from_millis(9)));
+        assert_eq!(
+            percentile(&latencies, 99.0),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(percentile(&latencies, 0.0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[tokio::test]
+    async fn adapter_times_first_reply() {
+        let adapter = BenchAdapter::new();
+        let mut inbound = adapter.start().await.unwrap();
+
+        let responder = adapter.clone();
+        tokio::spawn(async move {
+            while let Some(message) = inbound.next().await {
+                responder
+                    .respond(&message, OutboundResponse::Text("hi".into()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let latency = adapter
+            .send_and_wait("bench:test:0", "hello", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(latency.is_some());
+    }
+}
@@ -0,0 +1,308 @@
+//! Multi-instance clustering.
+//!
+//! Several spacebot processes with the same config can share one bot. Every
+//! node connects to the shared adapters (e.g. the Discord gateway) and sees
+//! every message, so a Postgres coordination table decides which node
+//! handles each conversation: the first node to claim a conversation holds
+//! a lease on it, renewed by its heartbeat. When a node stops heartbeating,
+//! its leases expire after `lease_ttl_secs` and the next message in each of
+//! its conversations is claimed by a surviving node.
+//!
+//! Requires a build with `--features cluster`.
+
+#[cfg(feature = "cluster")]
+use sqlx::postgres::{PgPool, PgPoolOptions};
+#[cfg(feature = "cluster")]
+use tokio::sync::Mutex;
+
+use serde::Serialize;
+
+#[cfg(feature = "cluster")]
+use std::collections::HashSet;
+use std::sync::Arc;
+#[cfg(feature = "cluster")]
+use std::time::Duration;
+
+/// Cluster settings (instance-level).
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    /// Unique name of this node. Defaults to the hostname.
+    pub node_id: String,
+    /// Postgres URL of the coordination database.
+    pub database_url: Option<String>,
+    /// Adapter sources every node receives, whose conversations are
+    /// partitioned by lease. Messages from other sources reach a single node
+    /// and are always handled where they arrive.
+    pub shared_adapters: Vec<String>,
+    /// How often the node renews its leases.
+    pub heartbeat_interval_secs: u64,
+    /// How long a lease outlives the node's last heartbeat.
+    pub lease_ttl_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            node_id: default_node_id(),
+            database_url: None,
+            shared_adapters: vec!["discord".into(), "twitch".into()],
+            heartbeat_interval_secs: 5,
+            lease_ttl_secs: 30,
+        }
+    }
+}
+
+fn default_node_id() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| format!("node-{}", std::process::id()))
+}
+
+/// A node as seen in the coordination database.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeStatus {
+    pub node_id: String,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    pub alive: bool,
+    /// Conversations this node holds a lease on.
+    pub conversations: i64,
+}
+
+/// This node's membership in a cluster.
+#[cfg(feature = "cluster")]
+pub struct Cluster {
+    pool: PgPool,
+    config: ClusterConfig,
+    /// Conversations this node last held, used when the coordination
+    /// database is unreachable.
+    owned: Mutex<HashSet<String>>,
+}
+
+#[cfg(feature = "cluster")]
+impl Cluster {
+    /// Join the cluster when enabled: connect, create the coordination
+    /// tables and start the heartbeat.
+    pub async fn start(config: &ClusterConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let database_url = config
+            .database_url
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("cluster.database_url is required"))?;
+        let pool = PgPoolOptions::new()
+            .max_connections(4)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect(database_url)
+            .await
+            .map_err(|error| anyhow::anyhow!("can't connect to cluster database: {error}"))?;
+
+        for statement in [
+            "CREATE TABLE IF NOT EXISTS spacebot_cluster_nodes (
+                node_id TEXT PRIMARY KEY,
+                started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                last_seen TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+            "CREATE TABLE IF NOT EXISTS spacebot_cluster_leases (
+                conversation_id TEXT PRIMARY KEY,
+                node_id TEXT NOT NULL,
+                expires_at TIMESTAMPTZ NOT NULL
+            )",
+            "CREATE INDEX IF NOT EXISTS spacebot_cluster_leases_node
+                ON spacebot_cluster_leases (node_id)",
+        ] {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+
+        let cluster = Arc::new(Self {
+            pool,
+            config: config.clone(),
+            owned: Mutex::new(HashSet::new()),
+        });
+        cluster.heartbeat().await?;
+        tracing::info!(node_id = %config.node_id, "joined cluster");
+
+        let heartbeat = cluster.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                heartbeat.config.heartbeat_interval_secs,
+            ));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(error) = heartbeat.heartbeat().await {
+                    tracing::warn!(%error, "cluster heartbeat failed");
+                }
+            }
+        });
+
+        Ok(Some(cluster))
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.config.node_id
+    }
+
+    /// Whether this node should handle a message. Messages from adapters
+    /// that aren't shared are always handled; otherwise the node must hold,
+    /// or be able to take, the conversation's lease.
+    pub async fn should_handle(&self, source: &str, conversation_id: &str) -> bool {
+        if !self
+            .config
+            .shared_adapters
+            .iter()
+            .any(|shared| shared == source)
+        {
+            return true;
+        }
+        self.claim(conversation_id).await
+    }
+
+    /// Take or renew the lease on a conversation. Returns whether this node
+    /// holds it. When the coordination database is unreachable, a node keeps
+    /// the conversations it already held so a database blip doesn't stall
+    /// every channel.
+    pub async fn claim(&self, conversation_id: &str) -> bool {
+        let result = sqlx::query_scalar::<_, String>(
+            "INSERT INTO spacebot_cluster_leases (conversation_id, node_id, expires_at)
+             VALUES ($1, $2, now() + make_interval(secs => $3))
+             ON CONFLICT (conversation_id) DO UPDATE
+                SET node_id = EXCLUDED.node_id, expires_at = EXCLUDED.expires_at
+                WHERE spacebot_cluster_leases.node_id = EXCLUDED.node_id
+                   OR spacebot_cluster_leases.expires_at < now()
+             RETURNING node_id",
+        )
+        .bind(conversation_id)
+        .bind(&self.config.node_id)
+        .bind(self.config.lease_ttl_secs as f64)
+        .fetch_optional(&self.pool)
+        .await;
+
+        let mut owned = self.owned.lock().await;
+        match result {
+            Ok(Some(_)) => {
+                if owned.insert(conversation_id.to_string()) {
+                    tracing::info!(
+                        conversation_id,
+                        node_id = %self.config.node_id,
+                        "claimed conversation"
+                    );
+                }
+                true
+            }
+            Ok(None) => {
+                if owned.remove(conversation_id) {
+                    tracing::info!(conversation_id, "conversation moved to another node");
+                }
+                false
+            }
+            Err(error) => {
+                tracing::warn!(%error, conversation_id, "can't reach cluster database");
+                owned.contains(conversation_id)
+            }
+        }
+    }
+
+    /// Record this node as alive and renew its leases.
+    async fn heartbeat(&self) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO spacebot_cluster_nodes (node_id) VALUES ($1)
+             ON CONFLICT (node_id) DO UPDATE SET last_seen = now()",
+        )
+        .bind(&self.config.node_id)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            "UPDATE spacebot_cluster_leases
+             SET expires_at = now() + make_interval(secs => $2)
+             WHERE node_id = $1",
+        )
+        .bind(&self.config.node_id)
+        .bind(self.config.lease_ttl_secs as f64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Release every lease so other nodes take over immediately instead of
+    /// waiting for them to expire. Called on graceful shutdown.
+    pub async fn leave(&self) {
+        let result = async {
+            sqlx::query("DELETE FROM spacebot_cluster_leases WHERE node_id = $1")
+                .bind(&self.config.node_id)
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("DELETE FROM spacebot_cluster_nodes WHERE node_id = $1")
+                .bind(&self.config.node_id)
+                .execute(&self.pool)
+                .await
+        }
+        .await;
+        match result {
+            Ok(_) => tracing::info!(node_id = %self.config.node_id, "left cluster"),
+            Err(error) => tracing::warn!(%error, "can't release cluster leases"),
+        }
+        self.pool.close().await;
+    }
+
+    /// Every known node with its lease count.
+    pub async fn nodes(&self) -> anyhow::Result<Vec<NodeStatus>> {
+        let rows: Vec<(String, chrono::DateTime<chrono::Utc>, bool, i64)> = sqlx::query_as(
+            "SELECT n.node_id, n.last_seen,
+                    n.last_seen > now() - make_interval(secs => $1),
+                    count(l.conversation_id) FILTER (WHERE l.expires_at > now())
+             FROM spacebot_cluster_nodes n
+             LEFT JOIN spacebot_cluster_leases l ON l.node_id = n.node_id
+             GROUP BY n.node_id, n.last_seen
+             ORDER BY n.node_id",
+        )
+        .bind(self.config.lease_ttl_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(node_id, last_seen, alive, conversations)| NodeStatus {
+                node_id,
+                last_seen,
+                alive,
+                conversations,
+            })
+            .collect())
+    }
+}
+
+/// Stand-in for builds without the `cluster` feature. It has no values, so
+/// [`Cluster::start`] can only refuse an enabled cluster.
+#[cfg(not(feature = "cluster"))]
+pub enum Cluster {}
+
+#[cfg(not(feature = "cluster"))]
+impl Cluster {
+    pub async fn start(config: &ClusterConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        if config.enabled {
+            anyhow::bail!("clustering is enabled but this build lacks the `cluster` feature");
+        }
+        Ok(None)
+    }
+
+    pub fn node_id(&self) -> &str {
+        match *self {}
+    }
+
+    pub async fn should_handle(&self, _source: &str, _conversation_id: &str) -> bool {
+        match *self {}
+    }
+
+    pub async fn leave(&self) {
+        match *self {}
+    }
+
+    pub async fn nodes(&self) -> anyhow::Result<Vec<NodeStatus>> {
+        match *self {}
+    }
+}
@@ -6,12 +6,15 @@ use anyhow::Context as _;
 use arc_swap::ArcSwap;
 use chrono_tz::Tz;
 use serde::{Deserialize, Deserializer};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 const CRON_TIMEZONE_ENV_VAR: &str = "SPACEBOT_CRON_TIMEZONE";
 
+/// Environment variable holding the master key that decrypts `secrets.redb`.
+const SECRETS_MASTER_KEY_ENV_VAR: &str = "SPACEBOT_MASTER_KEY";
+
 /// OpenTelemetry export configuration.
 ///
 /// All fields are optional. If `otlp_endpoint` is not set (and the standard
@@ -52,6 +55,22 @@ pub struct Config {
     pub metrics: MetricsConfig,
     /// OpenTelemetry export configuration.
     pub telemetry: TelemetryConfig,
+    /// Role-based access control for platform users.
+    pub rbac: crate::rbac::RbacConfig,
+    /// Operator alerts for adapter and provider outages.
+    pub alerts: crate::alerts::AlertConfig,
+    /// URLs that receive agent events as they happen.
+    pub event_webhooks: Vec<crate::event_webhooks::EventWebhook>,
+    /// Release channel for update checks.
+    pub update: crate::update::UpdateConfig,
+    /// Multi-instance coordination.
+    pub cluster: crate::cluster::ClusterConfig,
+    /// S3-compatible bucket for screenshots, files, archives and backups.
+    pub object_storage: crate::object_storage::ObjectStorageConfig,
+    /// Filters and rewrites applied to inbound messages before routing.
+    pub middleware: crate::messaging::middleware::MiddlewareConfig,
+    /// Graceful shutdown settings.
+    pub shutdown: crate::shutdown::ShutdownConfig,
 }
 
 /// HTTP API server configuration.
@@ -256,6 +275,32 @@ pub struct DefaultsConfig {
     pub ingestion: IngestionConfig,
     pub cortex: CortexConfig,
     pub browser: BrowserConfig,
+    pub sandbox: SandboxConfig,
+    pub live_status: LiveStatusConfig,
+    pub confirmations: ConfirmationsConfig,
+    pub presence: PresenceConfig,
+    pub reflection: ReflectionConfig,
+    pub dream: DreamConfig,
+    pub worker_queue: WorkerQueueConfig,
+    pub rate_limit: RateLimitConfig,
+    pub handoff: HandoffConfig,
+    pub hibernation: HibernationConfig,
+    pub sub_workers: SubWorkersConfig,
+    pub worker_budget: WorkerBudgetConfig,
+    pub reaction_memory: ReactionMemoryConfig,
+    pub reactions: ReactionsConfig,
+    pub voice: VoiceConfig,
+    pub scripting: ScriptingConfig,
+    pub plugins: PluginConfig,
+    pub vector_index: VectorIndexConfig,
+    pub attachments: AttachmentConfig,
+    pub loop_guard: LoopGuardConfig,
+    pub disk_quota: DiskQuotaConfig,
+    pub offline: OfflineConfig,
+    pub spend: SpendConfig,
+    pub database: DatabaseConfig,
+    pub egress: EgressConfig,
+    pub prompt_injection: PromptInjectionConfig,
     pub mcp: Vec<McpServerConfig>,
     /// Brave Search API key for web search tool. Supports "env:VAR_NAME" references.
     pub brave_search_key: Option<String>,
@@ -285,6 +330,32 @@ impl std::fmt::Debug for DefaultsConfig {
             .field("ingestion", &self.ingestion)
             .field("cortex", &self.cortex)
             .field("browser", &self.browser)
+            .field("sandbox", &self.sandbox)
+            .field("live_status", &self.live_status)
+            .field("confirmations", &self.confirmations)
+            .field("presence", &self.presence)
+            .field("reflection", &self.reflection)
+            .field("dream", &self.dream)
+            .field("worker_queue", &self.worker_queue)
+            .field("rate_limit", &self.rate_limit)
+            .field("handoff", &self.handoff)
+            .field("hibernation", &self.hibernation)
+            .field("sub_workers", &self.sub_workers)
+            .field("worker_budget", &self.worker_budget)
+            .field("reaction_memory", &self.reaction_memory)
+            .field("reactions", &self.reactions)
+            .field("voice", &self.voice)
+            .field("scripting", &self.scripting)
+            .field("plugins", &self.plugins)
+            .field("vector_index", &self.vector_index)
+            .field("attachments", &self.attachments)
+            .field("loop_guard", &self.loop_guard)
+            .field("disk_quota", &self.disk_quota)
+            .field("offline", &self.offline)
+            .field("spend", &self.spend)
+            .field("database", &self.database)
+            .field("egress", &self.egress)
+            .field("prompt_injection", &self.prompt_injection)
             .field("mcp", &self.mcp)
             .field("brave_search_key", &self.brave_search_key.as_ref().map(|_| "[REDACTED]"))
             .field("history_backfill_count", &self.history_backfill_count)
@@ -332,6 +403,23 @@ pub struct CompactionConfig {
     pub background_threshold: f32,
     pub aggressive_threshold: f32,
     pub emergency_threshold: f32,
+    /// Tool results longer than this many chars are trimmed before any
+    /// messages are summarized. 0 leaves tool results alone.
+    pub tool_output_max_chars: usize,
+    /// Compaction of each branch's own history.
+    pub branch: ProcessCompactionConfig,
+    /// Compaction of each worker's own history.
+    pub worker: ProcessCompactionConfig,
+}
+
+/// Compaction of a branch's or worker's history, which runs inline in the
+/// process, without an LLM call.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessCompactionConfig {
+    /// Context usage at which the oldest messages are compacted.
+    pub threshold: f32,
+    /// Share of the oldest messages compacted.
+    pub fraction: f32,
 }
 
 /// Auto-branching memory persistence configuration.
@@ -362,6 +450,18 @@ impl Default for CompactionConfig {
             background_threshold: 0.80,
             aggressive_threshold: 0.85,
             emergency_threshold: 0.95,
+            tool_output_max_chars: 4000,
+            branch: ProcessCompactionConfig::default(),
+            worker: ProcessCompactionConfig::default(),
+        }
+    }
+}
+
+impl Default for ProcessCompactionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.70,
+            fraction: 0.50,
         }
     }
 }
@@ -383,6 +483,10 @@ pub struct CoalesceConfig {
     pub min_messages: usize,
     /// Apply only to multi-user conversations (skip for DMs).
     pub multi_user_only: bool,
+    /// Debounce window for consecutive messages from one sender
+    /// (milliseconds). Each new message from the same sender restarts it, up
+    /// to `max_wait_ms`. Applies in DMs too. 0 disables.
+    pub sender_debounce_ms: u64,
 }
 
 impl Default for CoalesceConfig {
@@ -393,6 +497,7 @@ impl Default for CoalesceConfig {
             max_wait_ms: 5000,
             min_messages: 2,
             multi_user_only: true,
+            sender_debounce_ms: 0,
         }
     }
 }
@@ -450,1450 +555,5126 @@ impl Default for BrowserConfig {
     }
 }
 
-/// OpenCode subprocess worker configuration.
+/// Where worker shell and exec commands run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SandboxBackend {
+    /// Directly on the host, as the bot's own user.
+    #[default]
+    Host,
+    /// Inside an ephemeral Docker container.
+    Docker,
+    /// Inside an ephemeral Podman container.
+    Podman,
+}
+
+impl SandboxBackend {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "host" => Some(Self::Host),
+            "docker" => Some(Self::Docker),
+            "podman" => Some(Self::Podman),
+            _ => None,
+        }
+    }
+
+    /// The container runtime binary, or None for the host backend.
+    pub fn runtime(&self) -> Option<&'static str> {
+        match self {
+            Self::Host => None,
+            Self::Docker => Some("docker"),
+            Self::Podman => Some("podman"),
+        }
+    }
+}
+
+/// Container isolation for worker shell and exec tools.
+///
+/// With a container backend, each worker gets its own ephemeral container
+/// with the agent workspace mounted at `/workspace`. The container is
+/// removed when the worker finishes.
 #[derive(Debug, Clone)]
-pub struct OpenCodeConfig {
-    /// Whether OpenCode workers are available.
-    pub enabled: bool,
-    /// Path to the OpenCode binary. Supports "env:VAR_NAME" references.
-    /// Falls back to "opencode" on PATH.
-    pub path: String,
-    /// Maximum concurrent OpenCode server processes.
-    pub max_servers: usize,
-    /// Timeout in seconds waiting for a server to become healthy.
-    pub server_startup_timeout_secs: u64,
-    /// Maximum restart attempts before giving up on a server.
-    pub max_restart_retries: u32,
-    /// Permission settings passed to OpenCode's config.
-    pub permissions: crate::opencode::OpenCodePermissions,
+pub struct SandboxConfig {
+    pub backend: SandboxBackend,
+    /// Image the worker container is started from.
+    pub image: String,
+    /// Container network: "none" disables networking, anything else is
+    /// passed through as the runtime's network name (e.g. "bridge").
+    pub network: String,
+    /// Memory limit in the runtime's format (e.g. "1g").
+    pub memory_limit: Option<String>,
+    /// CPU limit as a fraction of cores (e.g. 1.5).
+    pub cpus: Option<f64>,
+    /// Maximum number of processes in the container.
+    pub pids_limit: Option<u32>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            backend: SandboxBackend::Host,
+            image: "debian:bookworm-slim".into(),
+            network: "none".into(),
+            memory_limit: Some("1g".into()),
+            cpus: Some(1.0),
+            pids_limit: Some(256),
+        }
+    }
 }
 
-impl Default for OpenCodeConfig {
+/// What to do when untrusted tool output looks like a prompt injection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptInjectionMode {
+    /// No scanning.
+    Off,
+    /// Pass content through, with a notice telling the model to treat it as data.
+    #[default]
+    Flag,
+    /// Replace the matched instructions before they reach the model.
+    Neutralize,
+}
+
+impl PromptInjectionMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "flag" => Some(Self::Flag),
+            "neutralize" => Some(Self::Neutralize),
+            _ => None,
+        }
+    }
+}
+
+/// Prompt injection detection for worker tool results and fetched web content.
+#[derive(Debug, Clone)]
+pub struct PromptInjectionConfig {
+    pub mode: PromptInjectionMode,
+    /// Optional HTTP classifier. Receives `{"text": ...}` and returns `{"score": 0.0-1.0}`.
+    pub classifier_url: Option<String>,
+    /// Classifier score at or above which content is treated as an injection.
+    pub classifier_threshold: f32,
+}
+
+impl Default for PromptInjectionConfig {
     fn default() -> Self {
         Self {
-            enabled: false,
-            path: "opencode".to_string(),
-            max_servers: 5,
-            server_startup_timeout_secs: 30,
-            max_restart_retries: 5,
-            permissions: crate::opencode::OpenCodePermissions::default(),
+            mode: PromptInjectionMode::Flag,
+            classifier_url: None,
+            classifier_threshold: 0.8,
         }
     }
 }
 
-/// Cortex configuration.
-#[derive(Debug, Clone, Copy)]
-pub struct CortexConfig {
-    pub tick_interval_secs: u64,
-    pub worker_timeout_secs: u64,
-    pub branch_timeout_secs: u64,
-    pub circuit_breaker_threshold: u8,
-    /// Interval in seconds between memory bulletin refreshes.
-    pub bulletin_interval_secs: u64,
-    /// Target word count for the memory bulletin.
-    pub bulletin_max_words: usize,
-    /// Max LLM turns for bulletin generation.
-    pub bulletin_max_turns: usize,
-    /// Interval in seconds between association passes.
-    pub association_interval_secs: u64,
-    /// Minimum cosine similarity to create a RelatedTo edge.
-    pub association_similarity_threshold: f32,
-    /// Minimum cosine similarity to create an Updates edge (near-duplicate).
-    pub association_updates_threshold: f32,
-    /// Max associations to create per pass (rate limit).
-    pub association_max_per_pass: usize,
+/// Network egress policy for tools that reach the network: attachment
+/// downloads, web search and the browser.
+#[derive(Debug, Clone, Default)]
+pub struct EgressConfig {
+    /// If non-empty, only these domains (and their subdomains) are reachable.
+    /// IP-literal hosts must be listed as an address or CIDR range.
+    pub allowed_domains: Vec<String>,
+    /// Domains (and their subdomains) that are never reachable.
+    pub denied_domains: Vec<String>,
+    /// Block loopback, private, link-local and CGNAT addresses.
+    pub block_private_ranges: bool,
+    /// CIDR ranges that stay reachable even when private ranges are blocked.
+    pub allowed_networks: Vec<String>,
+    /// CIDR ranges that are never reachable.
+    pub denied_networks: Vec<String>,
+    /// Per-scope domain allowlists, applied on top of the global lists.
+    /// Keys are `attachments`, `web_search` and `browser`.
+    pub scopes: HashMap<String, Vec<String>>,
+}
+
+/// SQLite `synchronous` pragma setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SynchronousMode {
+    Off,
+    /// Safe with WAL: a power loss can lose the last commits but never
+    /// corrupts the database.
+    #[default]
+    Normal,
+    Full,
+    Extra,
+}
+
+impl SynchronousMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "normal" => Some(Self::Normal),
+            "full" => Some(Self::Full),
+            "extra" => Some(Self::Extra),
+            _ => None,
+        }
+    }
 }
 
-impl Default for CortexConfig {
+/// Per-agent SQLite database options.
+#[derive(Clone)]
+pub struct DatabaseConfig {
+    /// SQLCipher key for `spacebot.db`. Resolved from "env:VAR_NAME" or
+    /// "secret:NAME" at load. Requires a build with the `sqlcipher` feature.
+    pub encryption_key: Option<String>,
+    /// Use write-ahead logging, so readers don't block on writers.
+    pub wal: bool,
+    pub synchronous: SynchronousMode,
+    /// How long a connection waits on a locked database before failing.
+    pub busy_timeout_ms: u64,
+    /// Maximum connections in the pool.
+    pub max_connections: u32,
+}
+
+impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
-            tick_interval_secs: 30,
-            worker_timeout_secs: 300,
-            branch_timeout_secs: 60,
-            circuit_breaker_threshold: 3,
-            bulletin_interval_secs: 3600,
-            bulletin_max_words: 1500,
-            bulletin_max_turns: 15,
-            association_interval_secs: 300,
-            association_similarity_threshold: 0.85,
-            association_updates_threshold: 0.95,
-            association_max_per_pass: 100,
+            encryption_key: None,
+            wal: true,
+            synchronous: SynchronousMode::Normal,
+            busy_timeout_ms: 5000,
+            max_connections: 10,
         }
     }
 }
 
-/// Memory injection configuration for the pre-hook system.
+impl std::fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field(
+                "encryption_key",
+                &self.encryption_key.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field("wal", &self.wal)
+            .field("synchronous", &self.synchronous)
+            .field("busy_timeout_ms", &self.busy_timeout_ms)
+            .field("max_connections", &self.max_connections)
+            .finish()
+    }
+}
+
+/// Daily LLM spend limits for an agent, in estimated USD (UTC days).
+#[derive(Debug, Clone, Default)]
+pub struct SpendConfig {
+    /// At this spend, notify operators and switch to `downgrade_model`.
+    pub soft_limit_usd: Option<f64>,
+    /// At this spend, notify operators and refuse further LLM calls until
+    /// the next UTC day.
+    pub hard_limit_usd: Option<f64>,
+    /// Cheaper model used for every process once the soft limit is hit.
+    pub downgrade_model: Option<String>,
+}
+
+/// Offline mode for channels when every LLM provider is unreachable.
 ///
-/// Controls how memories are fetched and deduplicated before being injected
-/// into the channel's context window.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct MemoryInjectionConfig {
-    /// Whether memory injection is enabled.
-    #[serde(default = "default_enabled")]
+/// Inbound messages are held per channel and replayed in order once a
+/// provider answers again.
+#[derive(Debug, Clone)]
+pub struct OfflineConfig {
+    /// Queue messages instead of dropping the turn.
     pub enabled: bool,
+    /// Messages held per channel. New ones are dropped when full.
+    pub max_queued_messages: usize,
+    /// How often to retry the oldest queued message, in seconds.
+    pub retry_interval_secs: u64,
+    /// Sent once per outage to the conversation that hit it.
+    pub notice: String,
+}
 
-    /// Maximum results from contextual hybrid search.
-    #[serde(default = "default_search_limit")]
-    pub search_limit: usize,
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_queued_messages: 20,
+            retry_interval_secs: 30,
+            notice: "I'm temporarily offline. I'll answer your messages as soon as I'm back."
+                .into(),
+        }
+    }
+}
 
-    /// Minimum hybrid score for contextual candidates.
-    #[serde(default = "default_contextual_min_score")]
-    pub contextual_min_score: f32,
+/// What to do when a directory goes over its disk quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiskCleanup {
+    /// Delete the least recently modified files until usage is under the limit.
+    #[default]
+    OldestFirst,
+    /// Only log and report the overage.
+    None,
+}
 
-    /// Number of turns before a memory can be re-injected.
-    #[serde(default = "default_context_window_depth")]
-    pub context_window_depth: usize,
+impl DiskCleanup {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "oldest_first" => Some(Self::OldestFirst),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
 
-    /// Cosine similarity threshold for semantic deduplication.
-    #[serde(default = "default_semantic_threshold")]
-    pub semantic_threshold: f32,
+/// Disk quotas for an agent's workspace and screenshot directory.
+#[derive(Debug, Clone)]
+pub struct DiskQuotaConfig {
+    /// Workspace size limit in megabytes. Identity files, skills and the
+    /// ingest directory count toward it but are never cleaned up.
+    pub workspace_limit_mb: Option<u64>,
+    /// Screenshot directory size limit in megabytes.
+    pub screenshots_limit_mb: Option<u64>,
+    pub cleanup: DiskCleanup,
+    /// How often usage is measured and enforced, in seconds.
+    pub check_interval_secs: u64,
+}
+
+impl Default for DiskQuotaConfig {
+    fn default() -> Self {
+        Self {
+            workspace_limit_mb: None,
+            screenshots_limit_mb: None,
+            cleanup: DiskCleanup::default(),
+            check_interval_secs: 60,
+        }
+    }
+}
 
-    /// Memory types to always inject as ambient context.
-    /// Empty by default for community-bot safe behavior.
-    #[serde(default = "default_pinned_types")]
-    pub pinned_types: Vec<String>,
+/// Thresholds for detecting a process stuck in a tool-call loop.
+#[derive(Debug, Clone)]
+pub struct LoopGuardConfig {
+    pub enabled: bool,
+    /// Identical tool calls (same tool, same arguments) in a row.
+    pub max_identical_calls: usize,
+    /// Consecutive failed calls cycling between at most two distinct calls.
+    pub max_failing_calls: usize,
+    /// Branches and workers a single process may start within `spawn_window_secs`.
+    pub max_spawns: usize,
+    pub spawn_window_secs: u64,
+}
 
-    /// Whether ambient awareness is enabled.
-    #[serde(default = "default_ambient_enabled")]
-    pub ambient_enabled: bool,
+impl Default for LoopGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_identical_calls: 5,
+            max_failing_calls: 6,
+            max_spawns: 8,
+            spawn_window_secs: 60,
+        }
+    }
+}
 
-    /// Maximum memories to inject per pinned type.
-    #[serde(default = "default_pinned_limit")]
-    pub pinned_limit: i64,
+/// Attachment download settings.
+///
+/// Attachments on a message are downloaded concurrently and cached on disk
+/// by URL, so a re-sent or retriggered message doesn't fetch them again.
+#[derive(Debug, Clone)]
+pub struct AttachmentConfig {
+    /// Attachments downloaded at the same time for one message.
+    pub max_concurrent_downloads: usize,
+    /// Attachments larger than this are skipped, in megabytes.
+    pub max_file_size_mb: u64,
+    /// Cache downloads under the agent data directory.
+    pub cache_enabled: bool,
+    /// How long a cached download is reused, in seconds.
+    pub cache_ttl_secs: u64,
+    /// Total size of the cache, in megabytes. The oldest entries are evicted
+    /// past it.
+    pub cache_max_size_mb: u64,
+    /// Text attachments up to this size are inlined whole, in kilobytes.
+    /// Larger ones are saved to the workspace and only their start is inlined.
+    pub inline_text_limit_kb: usize,
+    /// Spawn a worker to summarize text attachments over the inline limit.
+    pub summarize_large_text: bool,
+    /// Images are scaled down to fit this long edge, in pixels.
+    pub max_image_dimension: u32,
+    /// Images are re-encoded and shrunk until they fit this size, in kilobytes.
+    pub max_image_size_kb: usize,
+    /// Save a thumbnail of each image with the conversation.
+    pub thumbnails_enabled: bool,
+    /// Frames sampled from each video and sent as images. 0 leaves videos
+    /// as a file description.
+    pub video_frames: usize,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_downloads: 4,
+            max_file_size_mb: 25,
+            cache_enabled: true,
+            cache_ttl_secs: 24 * 60 * 60,
+            cache_max_size_mb: 512,
+            inline_text_limit_kb: 50,
+            summarize_large_text: true,
+            max_image_dimension: 1568,
+            max_image_size_kb: 3584,
+            thumbnails_enabled: true,
+            video_frames: 4,
+        }
+    }
+}
 
-    /// Sort mode for pinned memories. Allowed values: "recent", "importance".
-    #[serde(default = "default_pinned_sort")]
-    pub pinned_sort: String,
+impl AttachmentConfig {
+    pub fn max_file_size_bytes(&self) -> u64 {
+        self.max_file_size_mb.saturating_mul(1024 * 1024)
+    }
 
-    /// Hard cap on total injected memories across pinned and contextual pools.
-    #[serde(default = "default_max_total")]
-    pub max_total: usize,
+    pub fn cache_max_size_bytes(&self) -> u64 {
+        self.cache_max_size_mb.saturating_mul(1024 * 1024)
+    }
 
-    /// Maximum number of injected context blocks kept in channel history.
-    /// Set to 0 for ephemeral mode.
-    #[serde(default = "default_max_injected_blocks_in_history")]
-    pub max_injected_blocks_in_history: usize,
-}
+    pub fn inline_text_limit_bytes(&self) -> usize {
+        self.inline_text_limit_kb.saturating_mul(1024)
+    }
 
-fn default_enabled() -> bool {
-    true
-}
-fn default_search_limit() -> usize {
-    20
+    pub fn max_image_size_bytes(&self) -> usize {
+        self.max_image_size_kb.saturating_mul(1024)
+    }
 }
-fn default_contextual_min_score() -> f32 {
-    0.01
+
+/// ANN index type for memory embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VectorIndexType {
+    /// IVF with product quantization. Smallest index, good recall with refinement.
+    #[default]
+    IvfPq,
+    /// IVF with an HNSW graph per partition and scalar quantization.
+    IvfHnswSq,
+    /// IVF with an HNSW graph per partition and product quantization.
+    IvfHnswPq,
+}
+
+impl VectorIndexType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ivf_pq" => Some(Self::IvfPq),
+            "ivf_hnsw_sq" => Some(Self::IvfHnswSq),
+            "ivf_hnsw_pq" => Some(Self::IvfHnswPq),
+            _ => None,
+        }
+    }
 }
-fn default_context_window_depth() -> usize {
-    10
+
+/// Vector index settings for the memory embeddings table.
+///
+/// Small stores are searched with an exact flat scan. Once the table reaches
+/// `auto_index_threshold` rows an ANN index is built, and searches use
+/// `nprobes` and `refine_factor`. Read when the agent starts.
+#[derive(Debug, Clone)]
+pub struct VectorIndexConfig {
+    pub index_type: VectorIndexType,
+    /// Row count at which the index is built automatically.
+    pub auto_index_threshold: usize,
+    /// IVF partitions. Unset lets LanceDB choose from the row count.
+    pub num_partitions: Option<u32>,
+    /// Partitions searched per query. Higher is more accurate and slower.
+    pub nprobes: usize,
+    /// Re-rank `limit * refine_factor` candidates by exact distance. `None`
+    /// returns approximate distances as-is.
+    pub refine_factor: Option<u32>,
+}
+
+impl Default for VectorIndexConfig {
+    fn default() -> Self {
+        Self {
+            index_type: VectorIndexType::IvfPq,
+            auto_index_threshold: 10_000,
+            num_partitions: None,
+            nprobes: 20,
+            refine_factor: Some(5),
+        }
+    }
 }
-fn default_semantic_threshold() -> f32 {
-    0.85
+
+/// WASM tool plugins loaded from `workspace/plugins/`.
+#[derive(Debug, Clone)]
+pub struct PluginConfig {
+    /// Load plugins and offer their tools to workers.
+    pub enabled: bool,
+    /// Linear memory a plugin instance may grow to, in megabytes.
+    pub max_memory_mb: u64,
+    /// Fuel per call. Roughly one unit per WebAssembly instruction.
+    pub fuel: u64,
+    /// Wall-clock limit per call, in seconds.
+    pub timeout_secs: u64,
 }
-fn default_pinned_types() -> Vec<String> {
-    Vec::new()
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_memory_mb: 64,
+            fuel: 10_000_000_000,
+            timeout_secs: 30,
+        }
+    }
 }
-fn default_ambient_enabled() -> bool {
-    false
+
+/// Rhai automation scripts loaded from `workspace/scripts/`.
+#[derive(Debug, Clone)]
+pub struct ScriptingConfig {
+    /// Run script hooks.
+    pub enabled: bool,
+    /// Rhai operations a single hook call may perform before it's stopped.
+    pub max_operations: u64,
+    /// Actions (messages, memories, workers) a single hook call may request.
+    pub max_actions: usize,
 }
-fn default_pinned_limit() -> i64 {
-    3
+
+impl Default for ScriptingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_operations: 1_000_000,
+            max_actions: 20,
+        }
+    }
 }
-fn default_pinned_sort() -> String {
-    "recent".to_string()
+
+/// Realtime voice conversations.
+#[derive(Debug, Clone)]
+pub struct VoiceConfig {
+    /// Accept voice sessions.
+    pub enabled: bool,
+    /// Text-to-speech model as `provider/model`. The provider must serve an
+    /// OpenAI-compatible `/v1/audio/speech` endpoint.
+    pub tts_model: String,
+    /// Voice name passed to the TTS model.
+    pub tts_voice: String,
+    /// Loudness (RMS, 0.0 to 1.0) above which input counts as speech.
+    pub speech_threshold: f32,
+    /// Quiet time that ends an utterance, in milliseconds.
+    pub silence_ms: u64,
+    /// Longest utterance before it's cut and answered, in seconds.
+    pub max_utterance_secs: u64,
+    /// Cancel the reply when the user starts talking over it.
+    pub barge_in: bool,
+    /// Recent conversation messages given to the model as context.
+    pub history_messages: usize,
+    /// How speech in voice sessions and audio attachments becomes text.
+    pub transcription: TranscriptionBackend,
+    /// whisper.cpp command-line binary, for the `whisper_cpp` backend.
+    pub whisper_cpp_binary: String,
+    /// ggml model file, required by the `whisper_cpp` backend.
+    pub whisper_cpp_model: Option<PathBuf>,
+}
+
+/// Where speech is transcribed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TranscriptionBackend {
+    /// The `routing.voice` model, sent the audio as `input_audio` over chat
+    /// completions.
+    #[default]
+    Chat,
+    /// The `routing.voice` model on an OpenAI-compatible
+    /// `/v1/audio/transcriptions` endpoint, e.g. `openai/whisper-1`.
+    Api,
+    /// A local whisper.cpp binary. Audio that isn't 16 kHz mono WAV is
+    /// converted with ffmpeg first.
+    WhisperCpp,
+}
+
+impl TranscriptionBackend {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "chat" => Some(Self::Chat),
+            "api" => Some(Self::Api),
+            "whisper_cpp" => Some(Self::WhisperCpp),
+            _ => None,
+        }
+    }
 }
-fn default_max_total() -> usize {
-    25
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tts_model: "openai/gpt-4o-mini-tts".into(),
+            tts_voice: "alloy".into(),
+            speech_threshold: 0.02,
+            silence_ms: 700,
+            max_utterance_secs: 30,
+            barge_in: true,
+            history_messages: 20,
+            transcription: TranscriptionBackend::Chat,
+            whisper_cpp_binary: "whisper-cli".into(),
+            whisper_cpp_model: None,
+        }
+    }
 }
-fn default_max_injected_blocks_in_history() -> usize {
-    3
+
+/// Saving messages as pinned memories by reacting to them.
+#[derive(Debug, Clone)]
+pub struct ReactionMemoryConfig {
+    /// Save messages that get the capture reaction.
+    pub enabled: bool,
+    /// Reaction that saves the message it's added to.
+    pub emoji: String,
+    /// Reaction added to the message once it's saved. Empty to stay silent.
+    pub confirm_emoji: String,
+    /// Type given to captured memories.
+    pub memory_type: crate::memory::MemoryType,
 }
 
-impl Default for MemoryInjectionConfig {
+impl Default for ReactionMemoryConfig {
     fn default() -> Self {
         Self {
-            enabled: default_enabled(),
-            search_limit: default_search_limit(),
-            contextual_min_score: default_contextual_min_score(),
-            context_window_depth: default_context_window_depth(),
-            semantic_threshold: default_semantic_threshold(),
-            pinned_types: default_pinned_types(),
-            ambient_enabled: default_ambient_enabled(),
-            pinned_limit: default_pinned_limit(),
-            pinned_sort: default_pinned_sort(),
-            max_total: default_max_total(),
-            max_injected_blocks_in_history: default_max_injected_blocks_in_history(),
+            enabled: true,
+            emoji: "📌".into(),
+            confirm_emoji: "✅".into(),
+            memory_type: crate::memory::MemoryType::Fact,
         }
     }
 }
 
-/// Per-agent configuration (raw, before resolution with defaults).
+/// Reactions read as replies to the agent, and the reaction it acknowledges
+/// messages with.
 #[derive(Debug, Clone)]
-pub struct AgentConfig {
-    pub id: String,
-    pub default: bool,
-    /// Custom workspace path. If None, resolved to instance_dir/agents/{id}/workspace.
-    pub workspace: Option<PathBuf>,
-    /// Per-agent routing overrides. None inherits from defaults.
-    pub routing: Option<RoutingConfig>,
-    pub max_concurrent_branches: Option<usize>,
-    pub max_concurrent_workers: Option<usize>,
-    pub max_turns: Option<usize>,
-    pub branch_max_turns: Option<usize>,
-    pub context_window: Option<usize>,
-    pub compaction: Option<CompactionConfig>,
-    pub memory_persistence: Option<MemoryPersistenceConfig>,
-    pub coalesce: Option<CoalesceConfig>,
-    pub ingestion: Option<IngestionConfig>,
-    pub cortex: Option<CortexConfig>,
-    pub browser: Option<BrowserConfig>,
-    pub memory_injection: Option<MemoryInjectionConfig>,
-    pub mcp: Option<Vec<McpServerConfig>>,
-    /// Per-agent Brave Search API key override. None inherits from defaults.
-    pub brave_search_key: Option<String>,
-    /// Optional timezone override for cron active-hours evaluation.
-    pub cron_timezone: Option<String>,
-    /// Cron job definitions for this agent.
-    pub cron: Vec<CronDef>,
+pub struct ReactionsConfig {
+    /// Start a turn when a message gets one of the signal reactions.
+    pub enabled: bool,
+    /// Reactions that approve the message they're added to.
+    pub approve: Vec<String>,
+    /// Reactions that deny the message they're added to.
+    pub deny: Vec<String>,
+    /// Reactions on the agent's own messages that ask it to answer again.
+    pub retry: Vec<String>,
+    /// Reaction added to each incoming message before the reply. Empty to
+    /// stay silent.
+    pub ack_emoji: String,
+}
+
+impl Default for ReactionsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            approve: vec!["👍".into(), "✅".into()],
+            deny: vec!["👎".into(), "❌".into()],
+            retry: vec!["🔁".into(), "🔄".into()],
+            ack_emoji: String::new(),
+        }
+    }
 }
 
-/// A cron job definition from config.
+/// Handing conversations over to other agents.
 #[derive(Debug, Clone)]
-pub struct CronDef {
-    pub id: String,
-    pub prompt: String,
-    pub interval_secs: u64,
-    /// Delivery target in "adapter:target" format (e.g. "discord:123456789").
-    pub delivery_target: String,
-    /// Optional active hours window (start_hour, end_hour) in 24h format.
-    pub active_hours: Option<(u8, u8)>,
-    pub enabled: bool,
-    pub run_once: bool,
-    /// Maximum wall-clock seconds to wait for the job to complete.
-    /// `None` uses the default of 120 seconds.
-    pub timeout_secs: Option<u64>,
+pub struct HandoffConfig {
+    /// Agents this agent may hand a conversation to. Empty disables handoff.
+    pub targets: Vec<String>,
+    /// Recent messages passed to the receiving agent with the summary.
+    pub history_messages: usize,
 }
 
-/// Fully resolved agent config (merged with defaults, paths resolved).
+impl Default for HandoffConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            history_messages: 20,
+        }
+    }
+}
+
+/// Stopping idle channels to free their resources. A hibernated channel is
+/// restored from its conversation log when the next message arrives.
 #[derive(Debug, Clone)]
-pub struct ResolvedAgentConfig {
-    pub id: String,
-    pub workspace: PathBuf,
-    pub data_dir: PathBuf,
-    pub archives_dir: PathBuf,
-    pub routing: RoutingConfig,
-    pub max_concurrent_branches: usize,
-    pub max_concurrent_workers: usize,
-    pub max_turns: usize,
-    pub branch_max_turns: usize,
-    pub context_window: usize,
-    pub compaction: CompactionConfig,
-    pub memory_persistence: MemoryPersistenceConfig,
-    pub coalesce: CoalesceConfig,
-    pub ingestion: IngestionConfig,
-    pub cortex: CortexConfig,
-    pub browser: BrowserConfig,
-    pub memory_injection: MemoryInjectionConfig,
-    pub mcp: Vec<McpServerConfig>,
-    pub brave_search_key: Option<String>,
-    pub cron_timezone: Option<String>,
-    /// Number of messages to fetch from the platform when a new channel is created.
-    pub history_backfill_count: usize,
-    pub cron: Vec<CronDef>,
+pub struct HibernationConfig {
+    /// Seconds without activity before an idle channel hibernates. 0 keeps
+    /// channels running.
+    pub idle_timeout_secs: u64,
 }
 
-impl Default for DefaultsConfig {
+impl Default for HibernationConfig {
     fn default() -> Self {
         Self {
-            routing: RoutingConfig::default(),
-            max_concurrent_branches: 5,
-            max_concurrent_workers: 5,
-            max_turns: 5,
-            branch_max_turns: 50,
-            context_window: 128_000,
-            compaction: CompactionConfig::default(),
-            memory_persistence: MemoryPersistenceConfig::default(),
-            coalesce: CoalesceConfig::default(),
-            ingestion: IngestionConfig::default(),
-            cortex: CortexConfig::default(),
-            browser: BrowserConfig::default(),
-            mcp: Vec::new(),
-            brave_search_key: None,
-            cron_timezone: None,
-            history_backfill_count: 50,
-            cron: Vec::new(),
-            opencode: OpenCodeConfig::default(),
-            worker_log_mode: crate::settings::WorkerLogMode::default(),
-            memory_injection: MemoryInjectionConfig::default(),
+            idle_timeout_secs: 1800,
         }
     }
 }
 
-impl AgentConfig {
-    /// Resolve this agent config against instance defaults and base paths.
-    pub fn resolve(&self, instance_dir: &Path, defaults: &DefaultsConfig) -> ResolvedAgentConfig {
-        let agent_root = instance_dir.join("agents").join(&self.id);
+/// Workers spawning sub-workers of their own. Sub-workers report back to the
+/// worker that spawned them instead of the channel.
+#[derive(Debug, Clone)]
+pub struct SubWorkersConfig {
+    /// Deepest level of nesting: workers spawned by a channel are at depth
+    /// 1, their sub-workers at 2, and so on. 1 disables sub-workers.
+    pub max_depth: usize,
+    /// Sub-workers running at once across all workers of a channel.
+    pub max_per_channel: usize,
+}
 
-        ResolvedAgentConfig {
-            id: self.id.clone(),
-            workspace: self
-                .workspace
-                .clone()
-                .unwrap_or_else(|| agent_root.join("workspace")),
-            data_dir: agent_root.join("data"),
-            archives_dir: agent_root.join("archives"),
-            routing: self
-                .routing
-                .clone()
-                .unwrap_or_else(|| defaults.routing.clone()),
-            max_concurrent_branches: self
-                .max_concurrent_branches
-                .unwrap_or(defaults.max_concurrent_branches),
-            max_concurrent_workers: self
-                .max_concurrent_workers
-                .unwrap_or(defaults.max_concurrent_workers),
-            max_turns: self.max_turns.unwrap_or(defaults.max_turns),
-            branch_max_turns: self.branch_max_turns.unwrap_or(defaults.branch_max_turns),
-            context_window: self.context_window.unwrap_or(defaults.context_window),
-            compaction: self.compaction.unwrap_or(defaults.compaction),
-            memory_persistence: self
-                .memory_persistence
-                .unwrap_or(defaults.memory_persistence),
-            coalesce: self.coalesce.unwrap_or(defaults.coalesce),
-            ingestion: self.ingestion.unwrap_or(defaults.ingestion),
-            cortex: self.cortex.unwrap_or(defaults.cortex),
-            browser: self
-                .browser
-                .clone()
-                .unwrap_or_else(|| defaults.browser.clone()),
-            memory_injection: self
-                .memory_injection
-                .clone()
-                .unwrap_or_else(|| defaults.memory_injection.clone()),
-            mcp: resolve_mcp_configs(&defaults.mcp, self.mcp.as_deref()),
-            brave_search_key: self
-                .brave_search_key
-                .clone()
-                .or_else(|| defaults.brave_search_key.clone()),
-            cron_timezone: resolve_cron_timezone(
-                &self.id,
-                self.cron_timezone.as_deref(),
-                defaults.cron_timezone.as_deref(),
-            ),
-            history_backfill_count: defaults.history_backfill_count,
-            cron: self.cron.clone(),
+impl Default for SubWorkersConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 2,
+            max_per_channel: 5,
         }
     }
 }
 
-impl ResolvedAgentConfig {
-    pub fn sqlite_path(&self) -> PathBuf {
-        self.data_dir.join("spacebot.db")
-    }
-    pub fn lancedb_path(&self) -> PathBuf {
-        self.data_dir.join("lancedb")
-    }
-    pub fn redb_path(&self) -> PathBuf {
-        self.data_dir.join("config.redb")
-    }
-    pub fn history_backfill_count(&self) -> usize {
-        self.history_backfill_count
-    }
-    /// Resolved screenshot directory, falling back to data_dir/screenshots.
-    pub fn screenshot_dir(&self) -> PathBuf {
-        self.browser
-            .screenshot_dir
-            .clone()
-            .unwrap_or_else(|| self.data_dir.join("screenshots"))
+/// Limits on what a single built-in worker may use. A worker over budget is
+/// told to wrap up, then cancelled if it doesn't. 0 disables a limit.
+#[derive(Debug, Clone)]
+pub struct WorkerBudgetConfig {
+    /// Seconds a fire-and-forget worker may run.
+    pub max_wall_time_secs: u64,
+    /// Tool calls a worker may make.
+    pub max_tool_calls: usize,
+    /// LLM tokens, input plus output, a worker may use across all its calls.
+    pub max_tokens: u64,
+    /// Estimated LLM spend a worker may run up, in USD.
+    pub max_cost_usd: Option<f64>,
+    /// Seconds an over-budget worker gets to report before it's cancelled.
+    pub wrap_up_secs: u64,
+}
+
+impl Default for WorkerBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_wall_time_secs: 3600,
+            max_tool_calls: 0,
+            max_tokens: 0,
+            max_cost_usd: None,
+            wrap_up_secs: 60,
+        }
     }
+}
 
-    /// Directory for worker execution logs written on failure.
-    pub fn logs_dir(&self) -> PathBuf {
-        self.data_dir.join("logs")
+/// Live status card posted while branches and workers run.
+#[derive(Debug, Clone)]
+pub struct LiveStatusConfig {
+    /// Whether the card is posted on adapters that support it.
+    pub enabled: bool,
+    /// How long work must run before the card appears.
+    pub delay_secs: u64,
+    /// How often the card is refreshed while work runs.
+    pub update_interval_secs: u64,
+}
+
+impl Default for LiveStatusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            delay_secs: 10,
+            update_interval_secs: 10,
+        }
     }
+}
 
-    /// Path to agent workspace skills directory.
-    pub fn skills_dir(&self) -> PathBuf {
-        self.workspace.join("skills")
+/// Tool calls that wait for the user's approval before running.
+#[derive(Debug, Clone)]
+pub struct ConfirmationsConfig {
+    /// Ask before `spawn_worker` starts a worker.
+    pub spawn_worker: bool,
+    /// How long to wait for an answer before treating it as a no.
+    pub timeout_secs: u64,
+}
+
+impl Default for ConfirmationsConfig {
+    fn default() -> Self {
+        Self {
+            spawn_worker: false,
+            timeout_secs: 300,
+        }
     }
+}
 
-    /// Path to the memory ingestion directory where users drop files.
-    pub fn ingest_dir(&self) -> PathBuf {
-        self.workspace.join("ingest")
+/// The bot's presence on platforms that show one, driven by worker activity.
+#[derive(Debug, Clone)]
+pub struct PresenceConfig {
+    pub enabled: bool,
+    /// Verb shown before the text.
+    pub activity: crate::messaging::traits::ActivityKind,
+    /// Shown while workers run. `{workers}` and `{branches}` are replaced
+    /// with the running counts.
+    pub busy_text: String,
+    /// Shown with an away status when nothing runs. Empty clears the activity.
+    pub idle_text: String,
+    /// Minimum seconds between two presence updates.
+    pub update_interval_secs: u64,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            activity: crate::messaging::traits::ActivityKind::Watching,
+            busy_text: "{workers} workers".into(),
+            idle_text: String::new(),
+            update_interval_secs: 15,
+        }
     }
 }
 
-/// Routes a messaging platform conversation to a specific agent.
+/// Periodic self-reflection, where the cortex reviews recent conversations
+/// and proposes changes to IDENTITY.md and USER.md for approval.
 #[derive(Debug, Clone)]
-pub struct Binding {
-    pub agent_id: String,
-    pub channel: String,
-    pub guild_id: Option<String>,
-    pub workspace_id: Option<String>, // Slack workspace (team) ID
-    pub chat_id: Option<String>,
-    /// Channel IDs this binding applies to. If empty, all channels in the guild/workspace are allowed.
-    pub channel_ids: Vec<String>,
-    /// Require explicit @mention (or reply-to-bot) for inbound messages.
-    pub require_mention: bool,
-    /// User IDs allowed to DM the bot through this binding.
-    pub dm_allowed_users: Vec<String>,
+pub struct ReflectionConfig {
+    pub enabled: bool,
+    /// Minimum seconds between two reflections.
+    pub interval_secs: u64,
+    /// Most recent messages reviewed per reflection.
+    pub max_messages: i64,
+    /// Where proposals are posted and admins answer them.
+    pub control_channel: Option<crate::messaging::target::BroadcastTarget>,
 }
 
-impl Binding {
-    /// Check if this binding matches an inbound message.
-    fn matches(&self, message: &crate::InboundMessage) -> bool {
-        if self.channel != message.source {
-            return false;
+impl Default for ReflectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 7 * 24 * 60 * 60,
+            max_messages: 200,
+            control_channel: None,
         }
+    }
+}
 
-        // For webchat messages, match based on agent_id in the message
-        if message.source == "webchat"
-            && let Some(message_agent_id) = &message.agent_id
-        {
-            return message_agent_id.as_ref() == self.agent_id;
+/// Offline "dreaming", where the cortex goes back over each day's
+/// conversations at night and turns them into higher-level memories and a
+/// diary entry in the workspace.
+#[derive(Debug, Clone)]
+pub struct DreamConfig {
+    pub enabled: bool,
+    /// Hour of the day (0-23) the dream runs at, in the agent's cron timezone.
+    pub hour: u32,
+    /// Model the dream runs on. Defaults to the branch model.
+    pub model: Option<String>,
+    /// Most tokens of conversation read per dream. The oldest messages are
+    /// left out first.
+    pub max_input_tokens: usize,
+    /// Most tokens the model writes per dream.
+    pub max_output_tokens: u64,
+    /// Most memories saved per dream.
+    pub max_memories: usize,
+}
+
+impl Default for DreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hour: 3,
+            model: None,
+            max_input_tokens: 30_000,
+            max_output_tokens: 4_000,
+            max_memories: 10,
         }
+    }
+}
 
-        // DM messages have no guild_id — match if the sender is in dm_allowed_users
-        let is_dm =
-            !message.metadata.contains_key("discord_guild_id") && message.source == "discord";
-        if is_dm {
-            return !self.dm_allowed_users.is_empty()
-                && self.dm_allowed_users.contains(&message.sender_id);
+/// The agent-wide cap on running workers, and the queue workers over it wait
+/// in.
+#[derive(Debug, Clone)]
+pub struct WorkerQueueConfig {
+    /// Workers the agent runs at once, across channels and automations.
+    pub max_concurrent: usize,
+    /// Workers that can wait for a slot. Spawning fails once this many wait.
+    pub max_queued: usize,
+    /// Which waiting worker starts next.
+    pub dispatch: WorkerDispatch,
+}
+
+impl Default for WorkerQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 8,
+            max_queued: 20,
+            dispatch: WorkerDispatch::Priority,
         }
+    }
+}
 
-        if let Some(guild_id) = &self.guild_id {
-            let message_guild = message
-                .metadata
-                .get("discord_guild_id")
-                .and_then(|v| v.as_u64())
-                .map(|v| v.to_string());
-            if message_guild.as_deref() != Some(guild_id) {
-                return false;
-            }
+/// Order waiting workers start in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerDispatch {
+    /// Oldest first.
+    Fifo,
+    /// Most urgent first, oldest first within a priority.
+    Priority,
+}
+
+/// Token-bucket limits on inbound messages, so one busy sender or
+/// conversation can't run up the LLM bill.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Messages one sender can send in a burst.
+    pub sender_burst: u32,
+    /// Rate a sender's allowance refills at, in messages per minute.
+    pub sender_per_minute: u32,
+    /// Messages one conversation can take in a burst, across senders.
+    pub conversation_burst: u32,
+    /// Rate a conversation's allowance refills at, in messages per minute.
+    pub conversation_per_minute: u32,
+    /// Reply sent once when a sender or conversation is throttled. Empty
+    /// drops throttled messages silently.
+    pub message: String,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sender_burst: 5,
+            sender_per_minute: 10,
+            conversation_burst: 20,
+            conversation_per_minute: 30,
+            message: "You're sending messages faster than I can keep up with. \
+                      Give me a minute and try again."
+                .into(),
         }
+    }
+}
 
-        if let Some(workspace_id) = &self.workspace_id {
-            let message_workspace = message
-                .metadata
-                .get("slack_workspace_id")
-                .and_then(|v| v.as_str());
-            if message_workspace != Some(workspace_id) {
-                return false;
-            }
+/// OpenCode subprocess worker configuration.
+#[derive(Debug, Clone)]
+pub struct OpenCodeConfig {
+    /// Whether OpenCode workers are available.
+    pub enabled: bool,
+    /// Path to the OpenCode binary. Supports "env:VAR_NAME" references.
+    /// Falls back to "opencode" on PATH.
+    pub path: String,
+    /// Maximum concurrent OpenCode server processes.
+    pub max_servers: usize,
+    /// Timeout in seconds waiting for a server to become healthy.
+    pub server_startup_timeout_secs: u64,
+    /// Maximum restart attempts before giving up on a server.
+    pub max_restart_retries: u32,
+    /// Permission settings passed to OpenCode's config.
+    pub permissions: crate::opencode::OpenCodePermissions,
+}
+
+impl Default for OpenCodeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "opencode".to_string(),
+            max_servers: 5,
+            server_startup_timeout_secs: 30,
+            max_restart_retries: 5,
+            permissions: crate::opencode::OpenCodePermissions::default(),
         }
+    }
+}
 
-        if !self.channel_ids.is_empty() {
-            let message_channel = message
-                .metadata
-                .get("discord_channel_id")
-                .and_then(|v| v.as_u64())
-                .map(|v| v.to_string());
-            let parent_channel = message
-                .metadata
-                .get("discord_parent_channel_id")
-                .and_then(|v| v.as_u64())
-                .map(|v| v.to_string());
+/// Cortex configuration.
+#[derive(Debug, Clone, Copy)]
+pub struct CortexConfig {
+    pub tick_interval_secs: u64,
+    pub worker_timeout_secs: u64,
+    pub branch_timeout_secs: u64,
+    pub circuit_breaker_threshold: u8,
+    /// Interval in seconds between memory bulletin refreshes.
+    pub bulletin_interval_secs: u64,
+    /// Target word count for the memory bulletin.
+    pub bulletin_max_words: usize,
+    /// Max LLM turns for bulletin generation.
+    pub bulletin_max_turns: usize,
+    /// Interval in seconds between association passes.
+    pub association_interval_secs: u64,
+    /// Minimum cosine similarity to create a RelatedTo edge.
+    pub association_similarity_threshold: f32,
+    /// Minimum cosine similarity to create an Updates edge (near-duplicate).
+    pub association_updates_threshold: f32,
+    /// Max associations to create per pass (rate limit).
+    pub association_max_per_pass: usize,
+    /// Seconds the agent must be idle before memories are consolidated.
+    /// 0 disables consolidation.
+    pub consolidation_idle_secs: u64,
+    /// Minimum seconds between consolidation passes.
+    pub consolidation_interval_secs: u64,
+    /// Minimum cosine similarity for two memories to be merged.
+    pub consolidation_merge_threshold: f32,
+    /// Days without being recalled before an unimportant memory is archived.
+    pub consolidation_stale_days: u64,
+    /// Max unprompted messages the cortex sends per UTC day, across all
+    /// bindings with `allow_proactive`.
+    pub proactive_daily_limit: u32,
+    /// Identical tool results in a row at which a branch or worker counts as
+    /// stuck and is stopped. It's nudged halfway there. 0 disables the check.
+    pub runaway_repeat_limit: u32,
+    /// Queued tasks the cortex runs at once. 0 pauses the queue.
+    pub queue_concurrency: u32,
+}
 
-            // Also check Slack and Twitch channel IDs
-            let slack_channel = message
-                .metadata
-                .get("slack_channel_id")
-                .and_then(|v| v.as_str());
-            let twitch_channel = message
-                .metadata
-                .get("twitch_channel")
-                .and_then(|v| v.as_str());
+impl Default for CortexConfig {
+    fn default() -> Self {
+        Self {
+            tick_interval_secs: 30,
+            worker_timeout_secs: 300,
+            branch_timeout_secs: 60,
+            circuit_breaker_threshold: 3,
+            bulletin_interval_secs: 3600,
+            bulletin_max_words: 1500,
+            bulletin_max_turns: 15,
+            association_interval_secs: 300,
+            association_similarity_threshold: 0.85,
+            association_updates_threshold: 0.95,
+            association_max_per_pass: 100,
+            consolidation_idle_secs: 1800,
+            consolidation_interval_secs: 86400,
+            consolidation_merge_threshold: 0.95,
+            consolidation_stale_days: 90,
+            proactive_daily_limit: 3,
+            runaway_repeat_limit: 6,
+            queue_concurrency: 2,
+        }
+    }
+}
 
-            let direct_match = message_channel
-                .as_ref()
-                .is_some_and(|id| self.channel_ids.contains(id))
-                || slack_channel.is_some_and(|id| self.channel_ids.contains(&id.to_string()))
-                || twitch_channel.is_some_and(|id| self.channel_ids.contains(&id.to_string()));
-            let parent_match = parent_channel
-                .as_ref()
-                .is_some_and(|id| self.channel_ids.contains(id));
+/// Memory injection configuration for the pre-hook system.
+///
+/// Controls how memories are fetched and deduplicated before being injected
+/// into the channel's context window.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MemoryInjectionConfig {
+    /// Whether memory injection is enabled.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Maximum results from contextual hybrid search.
+    #[serde(default = "default_search_limit")]
+    pub search_limit: usize,
+
+    /// Minimum hybrid score for contextual candidates.
+    #[serde(default = "default_contextual_min_score")]
+    pub contextual_min_score: f32,
+
+    /// Number of turns before a memory can be re-injected.
+    #[serde(default = "default_context_window_depth")]
+    pub context_window_depth: usize,
+
+    /// Cosine similarity threshold for semantic deduplication.
+    #[serde(default = "default_semantic_threshold")]
+    pub semantic_threshold: f32,
+
+    /// Memory types to always inject as ambient context.
+    /// Empty by default for community-bot safe behavior.
+    #[serde(default = "default_pinned_types")]
+    pub pinned_types: Vec<String>,
+
+    /// Whether ambient awareness is enabled.
+    #[serde(default = "default_ambient_enabled")]
+    pub ambient_enabled: bool,
+
+    /// Maximum memories to inject per pinned type.
+    #[serde(default = "default_pinned_limit")]
+    pub pinned_limit: i64,
+
+    /// Sort mode for pinned memories. Allowed values: "recent", "importance".
+    #[serde(default = "default_pinned_sort")]
+    pub pinned_sort: String,
+
+    /// Hard cap on total injected memories across pinned and contextual pools.
+    #[serde(default = "default_max_total")]
+    pub max_total: usize,
+
+    /// Maximum number of injected context blocks kept in channel history.
+    /// Set to 0 for ephemeral mode.
+    #[serde(default = "default_max_injected_blocks_in_history")]
+    pub max_injected_blocks_in_history: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_search_limit() -> usize {
+    20
+}
+fn default_contextual_min_score() -> f32 {
+    0.01
+}
+fn default_context_window_depth() -> usize {
+    10
+}
+fn default_semantic_threshold() -> f32 {
+    0.85
+}
+fn default_pinned_types() -> Vec<String> {
+    Vec::new()
+}
+fn default_ambient_enabled() -> bool {
+    false
+}
+fn default_pinned_limit() -> i64 {
+    3
+}
+fn default_pinned_sort() -> String {
+    "recent".to_string()
+}
+fn default_max_total() -> usize {
+    25
+}
+fn default_max_injected_blocks_in_history() -> usize {
+    3
+}
+
+impl Default for MemoryInjectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            search_limit: default_search_limit(),
+            contextual_min_score: default_contextual_min_score(),
+            context_window_depth: default_context_window_depth(),
+            semantic_threshold: default_semantic_threshold(),
+            pinned_types: default_pinned_types(),
+            ambient_enabled: default_ambient_enabled(),
+            pinned_limit: default_pinned_limit(),
+            pinned_sort: default_pinned_sort(),
+            max_total: default_max_total(),
+            max_injected_blocks_in_history: default_max_injected_blocks_in_history(),
+        }
+    }
+}
+
+/// Per-agent configuration (raw, before resolution with defaults).
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    pub id: String,
+    pub default: bool,
+    /// Custom workspace path. If None, resolved to instance_dir/agents/{id}/workspace.
+    pub workspace: Option<PathBuf>,
+    /// Per-agent routing overrides. None inherits from defaults.
+    pub routing: Option<RoutingConfig>,
+    pub max_concurrent_branches: Option<usize>,
+    pub max_concurrent_workers: Option<usize>,
+    pub max_turns: Option<usize>,
+    pub branch_max_turns: Option<usize>,
+    pub context_window: Option<usize>,
+    pub compaction: Option<CompactionConfig>,
+    pub memory_persistence: Option<MemoryPersistenceConfig>,
+    pub coalesce: Option<CoalesceConfig>,
+    pub ingestion: Option<IngestionConfig>,
+    pub cortex: Option<CortexConfig>,
+    pub browser: Option<BrowserConfig>,
+    pub sandbox: Option<SandboxConfig>,
+    pub live_status: Option<LiveStatusConfig>,
+    pub confirmations: Option<ConfirmationsConfig>,
+    pub presence: Option<PresenceConfig>,
+    pub reflection: Option<ReflectionConfig>,
+    pub dream: Option<DreamConfig>,
+    pub worker_queue: Option<WorkerQueueConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub handoff: Option<HandoffConfig>,
+    pub hibernation: Option<HibernationConfig>,
+    pub sub_workers: Option<SubWorkersConfig>,
+    pub worker_budget: Option<WorkerBudgetConfig>,
+    pub reaction_memory: Option<ReactionMemoryConfig>,
+    pub reactions: Option<ReactionsConfig>,
+    pub voice: Option<VoiceConfig>,
+    pub scripting: Option<ScriptingConfig>,
+    pub plugins: Option<PluginConfig>,
+    pub vector_index: Option<VectorIndexConfig>,
+    pub attachments: Option<AttachmentConfig>,
+    pub loop_guard: Option<LoopGuardConfig>,
+    pub disk_quota: Option<DiskQuotaConfig>,
+    pub offline: Option<OfflineConfig>,
+    pub spend: Option<SpendConfig>,
+    pub database: Option<DatabaseConfig>,
+    pub egress: Option<EgressConfig>,
+    pub prompt_injection: Option<PromptInjectionConfig>,
+    pub memory_injection: Option<MemoryInjectionConfig>,
+    pub mcp: Option<Vec<McpServerConfig>>,
+    /// Per-agent Brave Search API key override. None inherits from defaults.
+    pub brave_search_key: Option<String>,
+    /// Optional timezone override for cron active-hours evaluation.
+    pub cron_timezone: Option<String>,
+    /// Cron job definitions for this agent.
+    pub cron: Vec<CronDef>,
+    /// Scheduled tasks the cortex runs in workers.
+    pub tasks: Vec<TaskDef>,
+    /// External signals the cortex reacts to.
+    pub triggers: Vec<TriggerDef>,
+}
+
+/// A cron job definition from config.
+#[derive(Debug, Clone)]
+pub struct CronDef {
+    pub id: String,
+    pub prompt: String,
+    pub interval_secs: u64,
+    /// Delivery target in "adapter:target" format (e.g. "discord:123456789").
+    pub delivery_target: String,
+    /// Optional active hours window (start_hour, end_hour) in 24h format.
+    pub active_hours: Option<(u8, u8)>,
+    pub enabled: bool,
+    pub run_once: bool,
+    /// Maximum wall-clock seconds to wait for the job to complete.
+    /// `None` uses the default of 120 seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+/// A scheduled task from `[[agents.tasks]]`: the cortex spawns a worker for
+/// it whenever its cron expression matches.
+#[derive(Debug, Clone)]
+pub struct TaskDef {
+    pub id: String,
+    /// When it runs, evaluated in the agent's `cron_timezone`.
+    pub schedule: crate::cron::CronExpression,
+    /// What the worker is asked to do.
+    pub task: String,
+    /// Conversation the worker's result is posted to. Without one, results
+    /// only go to the cortex log.
+    pub report_to: Option<crate::messaging::target::BroadcastTarget>,
+    pub enabled: bool,
+}
+
+/// A trigger from `[[agents.triggers]]`: a signal the cortex reacts to by
+/// queueing a worker, messaging a conversation, or both.
+#[derive(Debug, Clone)]
+pub struct TriggerDef {
+    pub id: String,
+    pub source: TriggerSource,
+    /// What the queued worker is asked to do. What fired the trigger is
+    /// appended.
+    pub task: Option<String>,
+    /// Conversation the worker's result is posted to.
+    pub report_to: Option<crate::messaging::target::BroadcastTarget>,
+    /// Conversation told each time the trigger fires.
+    pub notify: Option<crate::messaging::target::BroadcastTarget>,
+    /// Least time between two firings. Signals in between are dropped.
+    pub cooldown_secs: u64,
+    pub enabled: bool,
+}
+
+/// What fires a trigger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerSource {
+    /// A file or directory in the workspace changing. Relative to the
+    /// workspace.
+    Watch(PathBuf),
+    /// A call to `POST /api/triggers/{agent_id}/{trigger_id}` carrying this
+    /// token.
+    Webhook { token: String },
+    /// A fixed interval.
+    Timer { every_secs: u64 },
+}
+
+/// Fully resolved agent config (merged with defaults, paths resolved).
+#[derive(Debug, Clone)]
+pub struct ResolvedAgentConfig {
+    pub id: String,
+    pub workspace: PathBuf,
+    pub data_dir: PathBuf,
+    pub archives_dir: PathBuf,
+    pub routing: RoutingConfig,
+    pub max_concurrent_branches: usize,
+    pub max_concurrent_workers: usize,
+    pub max_turns: usize,
+    pub branch_max_turns: usize,
+    pub context_window: usize,
+    pub compaction: CompactionConfig,
+    pub memory_persistence: MemoryPersistenceConfig,
+    pub coalesce: CoalesceConfig,
+    pub ingestion: IngestionConfig,
+    pub cortex: CortexConfig,
+    pub browser: BrowserConfig,
+    pub sandbox: SandboxConfig,
+    pub live_status: LiveStatusConfig,
+    pub confirmations: ConfirmationsConfig,
+    pub presence: PresenceConfig,
+    pub reflection: ReflectionConfig,
+    pub dream: DreamConfig,
+    pub worker_queue: WorkerQueueConfig,
+    pub rate_limit: RateLimitConfig,
+    pub handoff: HandoffConfig,
+    pub hibernation: HibernationConfig,
+    pub sub_workers: SubWorkersConfig,
+    pub worker_budget: WorkerBudgetConfig,
+    pub reaction_memory: ReactionMemoryConfig,
+    pub reactions: ReactionsConfig,
+    pub voice: VoiceConfig,
+    pub scripting: ScriptingConfig,
+    pub plugins: PluginConfig,
+    pub vector_index: VectorIndexConfig,
+    pub attachments: AttachmentConfig,
+    pub loop_guard: LoopGuardConfig,
+    pub disk_quota: DiskQuotaConfig,
+    pub offline: OfflineConfig,
+    pub spend: SpendConfig,
+    pub database: DatabaseConfig,
+    pub egress: EgressConfig,
+    pub prompt_injection: PromptInjectionConfig,
+    pub memory_injection: MemoryInjectionConfig,
+    pub mcp: Vec<McpServerConfig>,
+    pub brave_search_key: Option<String>,
+    pub cron_timezone: Option<String>,
+    /// Number of messages to fetch from the platform when a new channel is created.
+    pub history_backfill_count: usize,
+    pub cron: Vec<CronDef>,
+    pub tasks: Vec<TaskDef>,
+    pub triggers: Vec<TriggerDef>,
+}
+
+impl Default for DefaultsConfig {
+    fn default() -> Self {
+        Self {
+            routing: RoutingConfig::default(),
+            max_concurrent_branches: 5,
+            max_concurrent_workers: 5,
+            max_turns: 5,
+            branch_max_turns: 50,
+            context_window: 128_000,
+            compaction: CompactionConfig::default(),
+            memory_persistence: MemoryPersistenceConfig::default(),
+            coalesce: CoalesceConfig::default(),
+            ingestion: IngestionConfig::default(),
+            cortex: CortexConfig::default(),
+            browser: BrowserConfig::default(),
+            sandbox: SandboxConfig::default(),
+            live_status: LiveStatusConfig::default(),
+            confirmations: ConfirmationsConfig::default(),
+            presence: PresenceConfig::default(),
+            reflection: ReflectionConfig::default(),
+            dream: DreamConfig::default(),
+            worker_queue: WorkerQueueConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            handoff: HandoffConfig::default(),
+            hibernation: HibernationConfig::default(),
+            sub_workers: SubWorkersConfig::default(),
+            worker_budget: WorkerBudgetConfig::default(),
+            reaction_memory: ReactionMemoryConfig::default(),
+            reactions: ReactionsConfig::default(),
+            voice: VoiceConfig::default(),
+            scripting: ScriptingConfig::default(),
+            plugins: PluginConfig::default(),
+            vector_index: VectorIndexConfig::default(),
+            attachments: AttachmentConfig::default(),
+            loop_guard: LoopGuardConfig::default(),
+            disk_quota: DiskQuotaConfig::default(),
+            offline: OfflineConfig::default(),
+            spend: SpendConfig::default(),
+            database: DatabaseConfig::default(),
+            egress: EgressConfig::default(),
+            prompt_injection: PromptInjectionConfig::default(),
+            mcp: Vec::new(),
+            brave_search_key: None,
+            cron_timezone: None,
+            history_backfill_count: 50,
+            cron: Vec::new(),
+            opencode: OpenCodeConfig::default(),
+            worker_log_mode: crate::settings::WorkerLogMode::default(),
+            memory_injection: MemoryInjectionConfig::default(),
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Resolve this agent config against instance defaults and base paths.
+    pub fn resolve(&self, instance_dir: &Path, defaults: &DefaultsConfig) -> ResolvedAgentConfig {
+        let agent_root = instance_dir.join("agents").join(&self.id);
+
+        ResolvedAgentConfig {
+            id: self.id.clone(),
+            workspace: self
+                .workspace
+                .clone()
+                .unwrap_or_else(|| agent_root.join("workspace")),
+            data_dir: agent_root.join("data"),
+            archives_dir: agent_root.join("archives"),
+            routing: self
+                .routing
+                .clone()
+                .unwrap_or_else(|| defaults.routing.clone()),
+            max_concurrent_branches: self
+                .max_concurrent_branches
+                .unwrap_or(defaults.max_concurrent_branches),
+            max_concurrent_workers: self
+                .max_concurrent_workers
+                .unwrap_or(defaults.max_concurrent_workers),
+            max_turns: self.max_turns.unwrap_or(defaults.max_turns),
+            branch_max_turns: self.branch_max_turns.unwrap_or(defaults.branch_max_turns),
+            context_window: self.context_window.unwrap_or(defaults.context_window),
+            compaction: self.compaction.unwrap_or(defaults.compaction),
+            memory_persistence: self
+                .memory_persistence
+                .unwrap_or(defaults.memory_persistence),
+            coalesce: self.coalesce.unwrap_or(defaults.coalesce),
+            ingestion: self.ingestion.unwrap_or(defaults.ingestion),
+            cortex: self.cortex.unwrap_or(defaults.cortex),
+            browser: self
+                .browser
+                .clone()
+                .unwrap_or_else(|| defaults.browser.clone()),
+            sandbox: self
+                .sandbox
+                .clone()
+                .unwrap_or_else(|| defaults.sandbox.clone()),
+            live_status: self
+                .live_status
+                .clone()
+                .unwrap_or_else(|| defaults.live_status.clone()),
+            confirmations: self
+                .confirmations
+                .clone()
+                .unwrap_or_else(|| defaults.confirmations.clone()),
+            presence: self
+                .presence
+                .clone()
+                .unwrap_or_else(|| defaults.presence.clone()),
+            reflection: self
+                .reflection
+                .clone()
+                .unwrap_or_else(|| defaults.reflection.clone()),
+            dream: self.dream.clone().unwrap_or_else(|| defaults.dream.clone()),
+            worker_queue: self
+                .worker_queue
+                .clone()
+                .unwrap_or_else(|| defaults.worker_queue.clone()),
+            rate_limit: self
+                .rate_limit
+                .clone()
+                .unwrap_or_else(|| defaults.rate_limit.clone()),
+            handoff: self
+                .handoff
+                .clone()
+                .unwrap_or_else(|| defaults.handoff.clone()),
+            hibernation: self
+                .hibernation
+                .clone()
+                .unwrap_or_else(|| defaults.hibernation.clone()),
+            sub_workers: self
+                .sub_workers
+                .clone()
+                .unwrap_or_else(|| defaults.sub_workers.clone()),
+            worker_budget: self
+                .worker_budget
+                .clone()
+                .unwrap_or_else(|| defaults.worker_budget.clone()),
+            reaction_memory: self
+                .reaction_memory
+                .clone()
+                .unwrap_or_else(|| defaults.reaction_memory.clone()),
+            reactions: self
+                .reactions
+                .clone()
+                .unwrap_or_else(|| defaults.reactions.clone()),
+            voice: self.voice.clone().unwrap_or_else(|| defaults.voice.clone()),
+            scripting: self
+                .scripting
+                .clone()
+                .unwrap_or_else(|| defaults.scripting.clone()),
+            plugins: self
+                .plugins
+                .clone()
+                .unwrap_or_else(|| defaults.plugins.clone()),
+            vector_index: self
+                .vector_index
+                .clone()
+                .unwrap_or_else(|| defaults.vector_index.clone()),
+            attachments: self
+                .attachments
+                .clone()
+                .unwrap_or_else(|| defaults.attachments.clone()),
+            loop_guard: self
+                .loop_guard
+                .clone()
+                .unwrap_or_else(|| defaults.loop_guard.clone()),
+            disk_quota: self
+                .disk_quota
+                .clone()
+                .unwrap_or_else(|| defaults.disk_quota.clone()),
+            offline: self
+                .offline
+                .clone()
+                .unwrap_or_else(|| defaults.offline.clone()),
+            spend: self.spend.clone().unwrap_or_else(|| defaults.spend.clone()),
+            database: self
+                .database
+                .clone()
+                .unwrap_or_else(|| defaults.database.clone()),
+            egress: self
+                .egress
+                .clone()
+                .unwrap_or_else(|| defaults.egress.clone()),
+            prompt_injection: self
+                .prompt_injection
+                .clone()
+                .unwrap_or_else(|| defaults.prompt_injection.clone()),
+            memory_injection: self
+                .memory_injection
+                .clone()
+                .unwrap_or_else(|| defaults.memory_injection.clone()),
+            mcp: resolve_mcp_configs(&defaults.mcp, self.mcp.as_deref()),
+            brave_search_key: self
+                .brave_search_key
+                .clone()
+                .or_else(|| defaults.brave_search_key.clone()),
+            cron_timezone: resolve_cron_timezone(
+                &self.id,
+                self.cron_timezone.as_deref(),
+                defaults.cron_timezone.as_deref(),
+            ),
+            history_backfill_count: defaults.history_backfill_count,
+            cron: self.cron.clone(),
+            tasks: self.tasks.clone(),
+            triggers: self.triggers.clone(),
+        }
+    }
+}
+
+impl ResolvedAgentConfig {
+    pub fn sqlite_path(&self) -> PathBuf {
+        self.data_dir.join("spacebot.db")
+    }
+    pub fn lancedb_path(&self) -> PathBuf {
+        self.data_dir.join("lancedb")
+    }
+    pub fn redb_path(&self) -> PathBuf {
+        self.data_dir.join("config.redb")
+    }
+    pub fn history_backfill_count(&self) -> usize {
+        self.history_backfill_count
+    }
+    /// Resolved screenshot directory, falling back to data_dir/screenshots.
+    pub fn screenshot_dir(&self) -> PathBuf {
+        self.browser
+            .screenshot_dir
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("screenshots"))
+    }
+
+    /// Directory for worker execution logs written on failure.
+    pub fn logs_dir(&self) -> PathBuf {
+        self.data_dir.join("logs")
+    }
+
+    /// Path to agent workspace skills directory.
+    pub fn skills_dir(&self) -> PathBuf {
+        self.workspace.join("skills")
+    }
+
+    /// Path to the memory ingestion directory where users drop files.
+    pub fn ingest_dir(&self) -> PathBuf {
+        self.workspace.join("ingest")
+    }
+}
+
+/// Routes a messaging platform conversation to a specific agent.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub agent_id: String,
+    pub channel: String,
+    pub guild_id: Option<String>,
+    pub workspace_id: Option<String>, // Slack workspace (team) ID
+    pub chat_id: Option<String>,
+    /// Channel IDs this binding applies to. If empty, all channels in the guild/workspace are allowed.
+    pub channel_ids: Vec<String>,
+    /// Which messages in a shared channel the bot engages with.
+    pub activation: Activation,
+    /// Words that wake the bot under [`Activation::WakeWord`], matched
+    /// case-insensitively as whole words.
+    pub wake_words: Vec<String>,
+    /// Send replies as replies to the triggering message, where the platform
+    /// supports it (Discord), so they keep their context in busy channels.
+    pub reply_to_message: bool,
+    /// User IDs allowed to DM the bot through this binding.
+    pub dm_allowed_users: Vec<String>,
+    /// Digest mode: collect messages without replying and post one
+    /// summarized response every this many seconds. `None` replies normally.
+    pub digest_interval_secs: Option<u64>,
+    /// How replies are shown while the agent works on them.
+    pub response_mode: ResponseMode,
+    /// Let the cortex message this binding's conversations unprompted, like
+    /// reporting a failed cron job.
+    pub allow_proactive: bool,
+    /// Labels for picking bindings out, like `customers` or `internal`.
+    /// Announcements can be sent to the bindings with a tag.
+    pub tags: Vec<String>,
+    /// Channel settings that replace the agent's in this binding's
+    /// conversations.
+    pub overrides: BindingOverrides,
+}
+
+/// Channel settings a binding overrides for its conversations, so a busy
+/// support channel can run on a cheaper model than DMs with the owner. Unset
+/// fields fall back to the agent's config.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BindingOverrides {
+    /// Model for channel turns, like `anthropic/claude-haiku-4.5`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_threshold: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggressive_threshold: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emergency_threshold: Option<f32>,
+}
+
+impl BindingOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the values, returning what's wrong with the first bad one.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self
+            .channel_model
+            .as_deref()
+            .is_some_and(|model| model.trim().is_empty())
+        {
+            return Err("channel_model can't be empty".into());
+        }
+        if self.max_turns == Some(0) {
+            return Err("max_turns must be at least 1".into());
+        }
+        if self.context_window == Some(0) {
+            return Err("context_window must be at least 1".into());
+        }
+        for (name, threshold) in [
+            ("background_threshold", self.background_threshold),
+            ("aggressive_threshold", self.aggressive_threshold),
+            ("emergency_threshold", self.emergency_threshold),
+        ] {
+            if threshold.is_some_and(|threshold| !(threshold > 0.0 && threshold <= 1.0)) {
+                return Err(format!("compaction.{name} must be above 0 and at most 1"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The agent's compaction thresholds with this binding's applied.
+    pub fn compaction(&self, base: CompactionConfig) -> CompactionConfig {
+        CompactionConfig {
+            background_threshold: self
+                .background_threshold
+                .unwrap_or(base.background_threshold),
+            aggressive_threshold: self
+                .aggressive_threshold
+                .unwrap_or(base.aggressive_threshold),
+            emergency_threshold: self.emergency_threshold.unwrap_or(base.emergency_threshold),
+            ..base
+        }
+    }
+}
+
+/// When a binding engages with messages in a shared channel. Direct messages
+/// are always answered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Activation {
+    /// Every message.
+    #[default]
+    Always,
+    /// Messages that @mention the bot or reply to it.
+    Mention,
+    /// Messages containing one of the binding's wake words.
+    WakeWord,
+    /// Messages inside a thread.
+    Thread,
+}
+
+impl Activation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "always" => Some(Self::Always),
+            "mention" => Some(Self::Mention),
+            "wake_word" => Some(Self::WakeWord),
+            "thread" => Some(Self::Thread),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Mention => "mention",
+            Self::WakeWord => "wake_word",
+            Self::Thread => "thread",
+        }
+    }
+}
+
+/// How a binding's replies are shown while the agent works on them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseMode {
+    /// A typing indicator until each reply is posted.
+    #[default]
+    Typing,
+    /// Replies are edited into a single message as they arrive, on adapters
+    /// that can edit messages. Others fall back to `Typing`.
+    Stream,
+    /// No typing indicator; each reply is posted once it's complete.
+    Final,
+}
+
+impl ResponseMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "typing" => Some(Self::Typing),
+            "stream" => Some(Self::Stream),
+            "final" => Some(Self::Final),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Typing => "typing",
+            Self::Stream => "stream",
+            Self::Final => "final",
+        }
+    }
+}
+
+impl Binding {
+    /// Check if this binding's activation mode lets the bot engage with a
+    /// message it matched. Adapters mark mentions and threads in metadata.
+    pub fn is_addressed(&self, message: &crate::InboundMessage) -> bool {
+        let flag = |key: &str| {
+            message
+                .metadata
+                .get(key)
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false)
+        };
+        let is_direct = match message.source.as_str() {
+            "discord" => !message.metadata.contains_key("discord_guild_id"),
+            "slack" => message
+                .metadata
+                .get("slack_channel_id")
+                .and_then(|value| value.as_str())
+                .is_some_and(|channel_id| channel_id.starts_with('D')),
+            "telegram" => {
+                message
+                    .metadata
+                    .get("telegram_chat_type")
+                    .and_then(|value| value.as_str())
+                    == Some("private")
+            }
+            "xmpp" => !message.metadata.contains_key("xmpp_room"),
+            "irc" => !message.metadata.contains_key("irc_channel"),
+            _ => false,
+        };
+        // Interactions answer the bot's own components, and reactions to its
+        // messages are handled by the channel itself.
+        let to_bot = match &message.content {
+            crate::MessageContent::Interaction { .. } => true,
+            crate::MessageContent::Reaction { to_bot, .. } => *to_bot,
+            _ => false,
+        };
+        if is_direct || to_bot {
+            return true;
+        }
+
+        match self.activation {
+            Activation::Always => true,
+            Activation::Mention => {
+                flag("discord_mentions_or_replies_to_bot")
+                    || flag("slack_mentions_bot")
+                    || flag("telegram_mentions_bot")
+                    || flag("xmpp_mentions_bot")
+                    || flag("irc_mentions_bot")
+            }
+            Activation::WakeWord => {
+                let text = match &message.content {
+                    crate::MessageContent::Text(text) => text.as_str(),
+                    crate::MessageContent::Media {
+                        text: Some(text), ..
+                    } => text.as_str(),
+                    _ => return false,
+                }
+                .to_lowercase();
+                let words: Vec<&str> = text
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                    .collect();
+                self.wake_words.iter().any(|wake_word| {
+                    let wake_word = wake_word.to_lowercase();
+                    let wake: Vec<&str> = wake_word
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                        .collect();
+                    !wake.is_empty() && words.windows(wake.len()).any(|window| window == wake)
+                })
+            }
+            Activation::Thread => {
+                flag("discord_is_thread") || message.metadata.contains_key("slack_thread_ts")
+            }
+        }
+    }
+
+    /// Check if this binding matches an inbound message.
+    fn matches(&self, message: &crate::InboundMessage) -> bool {
+        if self.channel != message.source {
+            return false;
+        }
+
+        // For webchat messages, match based on agent_id in the message
+        if message.source == "webchat"
+            && let Some(message_agent_id) = &message.agent_id
+        {
+            return message_agent_id.as_ref() == self.agent_id;
+        }
+
+        // DM messages have no guild_id — match if the sender is in dm_allowed_users
+        let is_dm =
+            !message.metadata.contains_key("discord_guild_id") && message.source == "discord";
+        if is_dm {
+            return !self.dm_allowed_users.is_empty()
+                && self.dm_allowed_users.contains(&message.sender_id);
+        }
+
+        if let Some(guild_id) = &self.guild_id {
+            let message_guild = message
+                .metadata
+                .get("discord_guild_id")
+                .and_then(|v| v.as_u64())
+                .map(|v| v.to_string());
+            if message_guild.as_deref() != Some(guild_id) {
+                return false;
+            }
+        }
+
+        if let Some(workspace_id) = &self.workspace_id {
+            let message_workspace = message
+                .metadata
+                .get("slack_workspace_id")
+                .and_then(|v| v.as_str());
+            if message_workspace != Some(workspace_id) {
+                return false;
+            }
+        }
+
+        if !self.channel_ids.is_empty() {
+            let message_channel = message
+                .metadata
+                .get("discord_channel_id")
+                .and_then(|v| v.as_u64())
+                .map(|v| v.to_string());
+            let parent_channel = message
+                .metadata
+                .get("discord_parent_channel_id")
+                .and_then(|v| v.as_u64())
+                .map(|v| v.to_string());
+
+            // Also check Slack, Twitch, IRC and XMPP channel IDs
+            let slack_channel = message
+                .metadata
+                .get("slack_channel_id")
+                .and_then(|v| v.as_str());
+            let twitch_channel = message
+                .metadata
+                .get("twitch_channel")
+                .and_then(|v| v.as_str());
+            let irc_channel = message.metadata.get("irc_channel").and_then(|v| v.as_str());
+            let xmpp_room = message.metadata.get("xmpp_room").and_then(|v| v.as_str());
+
+            let direct_match = message_channel
+                .as_ref()
+                .is_some_and(|id| self.channel_ids.contains(id))
+                || slack_channel.is_some_and(|id| self.channel_ids.contains(&id.to_string()))
+                || twitch_channel.is_some_and(|id| self.channel_ids.contains(&id.to_string()))
+                || irc_channel.is_some_and(|channel| {
+                    self.channel_ids.iter().any(|id| {
+                        id.trim_start_matches('#')
+                            .eq_ignore_ascii_case(channel.trim_start_matches('#'))
+                    })
+                })
+                || xmpp_room.is_some_and(|room| {
+                    self.channel_ids
+                        .iter()
+                        .any(|id| id.eq_ignore_ascii_case(room))
+                });
+            let parent_match = parent_channel
+                .as_ref()
+                .is_some_and(|id| self.channel_ids.contains(id));
+
+            if !direct_match && !parent_match {
+                return false;
+            }
+        }
+
+        if let Some(chat_id) = &self.chat_id {
+            let message_chat = message.metadata.get("telegram_chat_id").and_then(|value| {
+                value
+                    .as_str()
+                    .map(std::borrow::ToOwned::to_owned)
+                    .or_else(|| value.as_i64().map(|id| id.to_string()))
+            });
+            if message_chat.as_deref() != Some(chat_id.as_str()) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Resolve which agent should handle an inbound message.
+///
+/// Checks bindings in order. First match wins. Falls back to the default
+/// agent if no binding matches.
+pub fn resolve_agent_for_message(
+    bindings: &[Binding],
+    message: &crate::InboundMessage,
+    default_agent_id: &str,
+) -> crate::AgentId {
+    match resolve_binding_for_message(bindings, message) {
+        Some(binding) => std::sync::Arc::from(binding.agent_id.as_str()),
+        None => std::sync::Arc::from(default_agent_id),
+    }
+}
+
+/// The first binding that matches an inbound message.
+pub fn resolve_binding_for_message<'a>(
+    bindings: &'a [Binding],
+    message: &crate::InboundMessage,
+) -> Option<&'a Binding> {
+    bindings.iter().find(|binding| binding.matches(message))
+}
+
+/// Messaging platform credentials (instance-level).
+#[derive(Debug, Clone, Default)]
+pub struct MessagingConfig {
+    pub discord: Option<DiscordConfig>,
+    pub slack: Option<SlackConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub twitch: Option<TwitchConfig>,
+    pub irc: Option<IrcConfig>,
+    pub email: Option<EmailConfig>,
+    pub xmpp: Option<XmppConfig>,
+    pub whatsapp: Option<WhatsAppConfig>,
+    /// Adapters from the [`registry`](crate::messaging::registry), by name.
+    pub custom: Vec<CustomAdapterConfig>,
+    pub sessions: SessionConfig,
+    pub pairing: PairingConfig,
+}
+
+/// Names of the adapters built into spacebot, which custom adapters can't take.
+const BUILTIN_ADAPTERS: &[&str] = &[
+    "discord", "slack", "telegram", "webhook", "twitch", "irc", "email", "xmpp", "whatsapp",
+    "webchat", "cli", "bench",
+];
+
+/// A `[messaging.custom.<name>]` table, built by the adapter registered under
+/// that name.
+#[derive(Clone)]
+pub struct CustomAdapterConfig {
+    pub name: String,
+    pub enabled: bool,
+    /// Every other key of the table, passed through to the adapter unparsed.
+    pub settings: toml::Table,
+}
+
+impl CustomAdapterConfig {
+    /// A string setting, with `env:VAR` references resolved.
+    pub fn setting(&self, key: &str) -> Option<String> {
+        self.settings
+            .get(key)
+            .and_then(|value| value.as_str())
+            .and_then(resolve_env_value)
+    }
+}
+
+impl std::fmt::Debug for CustomAdapterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Settings may hold credentials, so only their keys are shown.
+        f.debug_struct("CustomAdapterConfig")
+            .field("name", &self.name)
+            .field("enabled", &self.enabled)
+            .field("settings", &self.settings.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Session token lifetimes for the webhook and webchat adapters.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionConfig {
+    /// Lifetime of a newly issued session when the caller doesn't ask for one.
+    pub ttl_secs: u64,
+    /// Upper bound on a caller-requested lifetime.
+    pub max_ttl_secs: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 7 * 24 * 60 * 60,
+            max_ttl_secs: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// One-time codes that let unknown users DM the bot once an admin approves.
+#[derive(Debug, Clone)]
+pub struct PairingConfig {
+    /// Answer DMs from unknown users with a pairing code.
+    pub enabled: bool,
+    /// Where requests are posted and admins answer them.
+    pub control_channel: Option<crate::messaging::target::BroadcastTarget>,
+    /// How long a code can be approved.
+    pub code_ttl_secs: u64,
+    /// `platform:sender_id` identities allowed to answer requests, on top of
+    /// RBAC admins. Pairing needs these or RBAC enabled.
+    pub approvers: Vec<String>,
+}
+
+impl Default for PairingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            control_channel: None,
+            code_ttl_secs: 3600,
+            approvers: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DiscordConfig {
+    pub enabled: bool,
+    pub token: String,
+    /// User IDs allowed to DM the bot. If empty, DMs are ignored entirely.
+    pub dm_allowed_users: Vec<String>,
+    /// Whether to process messages from other bots (self-messages are always ignored).
+    pub allow_bot_messages: bool,
+    /// Let `/voice join` bring the bot into voice channels to listen and speak.
+    pub voice: bool,
+}
+
+impl std::fmt::Debug for DiscordConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiscordConfig")
+            .field("enabled", &self.enabled)
+            .field("token", &"[REDACTED]")
+            .field("dm_allowed_users", &self.dm_allowed_users)
+            .field("allow_bot_messages", &self.allow_bot_messages)
+            .field("voice", &self.voice)
+            .finish()
+    }
+}
+
+/// A single slash command definition for the Slack adapter.
+///
+/// Maps a Slack slash command (e.g. `/ask`) to a target agent.
+/// Commands not listed here are acknowledged but produce a "not configured" reply.
+#[derive(Debug, Clone)]
+pub struct SlackCommandConfig {
+    /// The slash command string exactly as Slack sends it, e.g. `"/ask"`.
+    pub command: String,
+    /// ID of the agent that should handle this command.
+    pub agent_id: String,
+    /// Short description shown in Slack's command autocomplete hint (optional).
+    pub description: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SlackConfig {
+    pub enabled: bool,
+    pub bot_token: String,
+    pub app_token: String,
+    /// User IDs allowed to DM the bot. If empty, DMs are ignored entirely.
+    pub dm_allowed_users: Vec<String>,
+    /// Slash command definitions. If empty, all slash commands are ignored.
+    pub commands: Vec<SlackCommandConfig>,
+}
+
+impl std::fmt::Debug for SlackConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlackConfig")
+            .field("enabled", &self.enabled)
+            .field("bot_token", &"[REDACTED]")
+            .field("app_token", &"[REDACTED]")
+            .field("dm_allowed_users", &self.dm_allowed_users)
+            .field("commands", &self.commands)
+            .finish()
+    }
+}
+
+/// Hot-reloadable Discord permission filters.
+///
+/// Derived from bindings + discord config. Shared with the Discord adapter
+/// via `Arc<ArcSwap<..>>` so the file watcher can swap in new values without
+/// restarting the gateway connection.
+#[derive(Debug, Clone, Default)]
+pub struct DiscordPermissions {
+    pub guild_filter: Option<Vec<u64>>,
+    pub channel_filter: std::collections::HashMap<u64, Vec<u64>>,
+    pub dm_allowed_users: Vec<u64>,
+    pub allow_bot_messages: bool,
+    pub voice: bool,
+}
+
+/// Hot-reloadable Slack permission filters.
+///
+/// Shared with the Slack adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct SlackPermissions {
+    pub workspace_filter: Option<Vec<String>>, // team IDs
+    pub channel_filter: std::collections::HashMap<String, Vec<String>>, // team_id -> allowed channel_ids
+    pub dm_allowed_users: Vec<String>,                                  // user IDs
+}
+
+impl SlackPermissions {
+    /// Build from the current config's slack settings and bindings.
+    pub fn from_config(slack: &SlackConfig, bindings: &[Binding]) -> Self {
+        let slack_bindings: Vec<&Binding> =
+            bindings.iter().filter(|b| b.channel == "slack").collect();
+
+        let workspace_filter = {
+            let workspace_ids: Vec<String> = slack_bindings
+                .iter()
+                .filter_map(|b| b.workspace_id.clone())
+                .collect();
+            if workspace_ids.is_empty() {
+                None
+            } else {
+                Some(workspace_ids)
+            }
+        };
+
+        let channel_filter = {
+            let mut filter: std::collections::HashMap<String, Vec<String>> =
+                std::collections::HashMap::new();
+            for binding in &slack_bindings {
+                if let Some(workspace_id) = &binding.workspace_id
+                    && !binding.channel_ids.is_empty()
+                {
+                    filter
+                        .entry(workspace_id.clone())
+                        .or_default()
+                        .extend(binding.channel_ids.clone());
+                }
+            }
+            filter
+        };
+
+        let dm_allowed_users = slack.dm_allowed_users.clone();
+
+        Self {
+            workspace_filter,
+            channel_filter,
+            dm_allowed_users,
+        }
+    }
+}
+
+impl DiscordPermissions {
+    /// Build from the current config's discord settings and bindings.
+    pub fn from_config(discord: &DiscordConfig, bindings: &[Binding]) -> Self {
+        let discord_bindings: Vec<&Binding> =
+            bindings.iter().filter(|b| b.channel == "discord").collect();
+
+        let guild_filter = {
+            let guild_ids: Vec<u64> = discord_bindings
+                .iter()
+                .filter_map(|b| b.guild_id.as_ref()?.parse::<u64>().ok())
+                .collect();
+            if guild_ids.is_empty() {
+                None
+            } else {
+                Some(guild_ids)
+            }
+        };
+
+        let channel_filter = {
+            let mut filter: std::collections::HashMap<u64, Vec<u64>> =
+                std::collections::HashMap::new();
+            for binding in &discord_bindings {
+                if let Some(guild_id) = binding
+                    .guild_id
+                    .as_ref()
+                    .and_then(|g| g.parse::<u64>().ok())
+                    && !binding.channel_ids.is_empty()
+                {
+                    let channel_ids: Vec<u64> = binding
+                        .channel_ids
+                        .iter()
+                        .filter_map(|id| id.parse::<u64>().ok())
+                        .collect();
+                    filter.entry(guild_id).or_default().extend(channel_ids);
+                }
+            }
+            filter
+        };
+
+        let mut dm_allowed_users: Vec<u64> = discord
+            .dm_allowed_users
+            .iter()
+            .filter_map(|id| id.parse::<u64>().ok())
+            .collect();
+
+        // Also collect dm_allowed_users from bindings
+        for binding in &discord_bindings {
+            for id in &binding.dm_allowed_users {
+                if let Ok(uid) = id.parse::<u64>()
+                    && !dm_allowed_users.contains(&uid)
+                {
+                    dm_allowed_users.push(uid);
+                }
+            }
+        }
+
+        Self {
+            guild_filter,
+            channel_filter,
+            dm_allowed_users,
+            allow_bot_messages: discord.allow_bot_messages,
+            voice: discord.voice,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TelegramConfig {
+    pub enabled: bool,
+    pub token: String,
+    /// User IDs allowed to DM the bot. If empty, DMs are ignored entirely.
+    pub dm_allowed_users: Vec<String>,
+}
+
+impl std::fmt::Debug for TelegramConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TelegramConfig")
+            .field("enabled", &self.enabled)
+            .field("token", &"[REDACTED]")
+            .field("dm_allowed_users", &self.dm_allowed_users)
+            .finish()
+    }
+}
+
+/// Hot-reloadable Telegram permission filters.
+///
+/// Shared with the Telegram adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct TelegramPermissions {
+    /// Allowed chat IDs (None = all chats accepted).
+    pub chat_filter: Option<Vec<i64>>,
+    /// User IDs allowed in private chats.
+    pub dm_allowed_users: Vec<i64>,
+}
+
+impl TelegramPermissions {
+    /// Build from the current config's telegram settings and bindings.
+    pub fn from_config(telegram: &TelegramConfig, bindings: &[Binding]) -> Self {
+        let telegram_bindings: Vec<&Binding> = bindings
+            .iter()
+            .filter(|b| b.channel == "telegram")
+            .collect();
+
+        let chat_filter = {
+            let chat_ids: Vec<i64> = telegram_bindings
+                .iter()
+                .filter_map(|b| b.chat_id.as_ref()?.parse::<i64>().ok())
+                .collect();
+            if chat_ids.is_empty() {
+                None
+            } else {
+                Some(chat_ids)
+            }
+        };
+
+        let mut dm_allowed_users: Vec<i64> = telegram
+            .dm_allowed_users
+            .iter()
+            .filter_map(|id| id.parse::<i64>().ok())
+            .collect();
+
+        for binding in &telegram_bindings {
+            for id in &binding.dm_allowed_users {
+                if let Ok(uid) = id.parse::<i64>()
+                    && !dm_allowed_users.contains(&uid)
+                {
+                    dm_allowed_users.push(uid);
+                }
+            }
+        }
+
+        Self {
+            chat_filter,
+            dm_allowed_users,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TwitchConfig {
+    pub enabled: bool,
+    pub username: String,
+    pub oauth_token: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub refresh_token: Option<String>,
+    /// Channels to join (without the # prefix).
+    pub channels: Vec<String>,
+    /// Optional prefix that triggers the bot (e.g. "!ask"). If empty, all messages are processed.
+    pub trigger_prefix: Option<String>,
+}
+
+impl std::fmt::Debug for TwitchConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwitchConfig")
+            .field("enabled", &self.enabled)
+            .field("username", &self.username)
+            .field("oauth_token", &"[REDACTED]")
+            .field("channels", &self.channels)
+            .field("trigger_prefix", &self.trigger_prefix)
+            .finish()
+    }
+}
+
+/// Hot-reloadable Twitch permission filters.
+///
+/// Shared with the Twitch adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct TwitchPermissions {
+    /// Allowed channel names (None = all joined channels accepted).
+    pub channel_filter: Option<Vec<String>>,
+    /// User login names allowed to interact with the bot. Empty = all users.
+    pub allowed_users: Vec<String>,
+}
+
+impl TwitchPermissions {
+    /// Build from the current config's twitch settings and bindings.
+    pub fn from_config(_twitch: &TwitchConfig, bindings: &[Binding]) -> Self {
+        let twitch_bindings: Vec<&Binding> =
+            bindings.iter().filter(|b| b.channel == "twitch").collect();
+
+        let channel_filter = {
+            let channel_ids: Vec<String> = twitch_bindings
+                .iter()
+                .flat_map(|b| b.channel_ids.clone())
+                .collect();
+            if channel_ids.is_empty() {
+                None
+            } else {
+                Some(channel_ids)
+            }
+        };
+
+        let mut allowed_users: Vec<String> = Vec::new();
+        for binding in &twitch_bindings {
+            for id in &binding.dm_allowed_users {
+                if !allowed_users.contains(id) {
+                    allowed_users.push(id.clone());
+                }
+            }
+        }
+
+        Self {
+            channel_filter,
+            allowed_users,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IrcConfig {
+    pub enabled: bool,
+    pub server: String,
+    pub port: u16,
+    pub use_tls: bool,
+    pub nickname: String,
+    /// Server password (`PASS`), if the network requires one.
+    pub password: Option<String>,
+    /// Password sent to NickServ after connecting.
+    pub nickserv_password: Option<String>,
+    /// Channels to join (the # prefix is optional).
+    pub channels: Vec<String>,
+    /// Optional prefix that triggers the bot (e.g. "!ask"). If empty, all messages are processed.
+    pub trigger_prefix: Option<String>,
+}
+
+impl std::fmt::Debug for IrcConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IrcConfig")
+            .field("enabled", &self.enabled)
+            .field("server", &self.server)
+            .field("port", &self.port)
+            .field("use_tls", &self.use_tls)
+            .field("nickname", &self.nickname)
+            .field("password", &self.password.as_ref().map(|_| "[REDACTED]"))
+            .field(
+                "nickserv_password",
+                &self.nickserv_password.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field("channels", &self.channels)
+            .field("trigger_prefix", &self.trigger_prefix)
+            .finish()
+    }
+}
+
+/// Hot-reloadable IRC permission filters.
+///
+/// Shared with the IRC adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct IrcPermissions {
+    /// Allowed channel names (None = all joined channels accepted).
+    pub channel_filter: Option<Vec<String>>,
+    /// Nicks allowed to talk to the bot. Empty = everyone in channels and
+    /// no private messages.
+    pub allowed_nicks: Vec<String>,
+}
+
+impl IrcPermissions {
+    /// Build from the current config's irc settings and bindings.
+    pub fn from_config(_irc: &IrcConfig, bindings: &[Binding]) -> Self {
+        let irc_bindings: Vec<&Binding> = bindings.iter().filter(|b| b.channel == "irc").collect();
+
+        let channel_filter = {
+            let channel_ids: Vec<String> = irc_bindings
+                .iter()
+                .flat_map(|b| b.channel_ids.clone())
+                .collect();
+            if channel_ids.is_empty() {
+                None
+            } else {
+                Some(channel_ids)
+            }
+        };
+
+        let mut allowed_nicks: Vec<String> = Vec::new();
+        for binding in &irc_bindings {
+            for nick in &binding.dm_allowed_users {
+                if !allowed_nicks.contains(nick) {
+                    allowed_nicks.push(nick.clone());
+                }
+            }
+        }
+
+        Self {
+            channel_filter,
+            allowed_nicks,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct XmppConfig {
+    pub enabled: bool,
+    /// Bare JID of the bot account (e.g. `spacebot@example.com`).
+    pub jid: String,
+    pub password: String,
+    /// Nickname used in rooms. Defaults to the JID's local part.
+    pub nickname: String,
+    /// Multi-user chat rooms to join, by bare JID.
+    pub rooms: Vec<String>,
+}
+
+impl std::fmt::Debug for XmppConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XmppConfig")
+            .field("enabled", &self.enabled)
+            .field("jid", &self.jid)
+            .field("password", &"[REDACTED]")
+            .field("nickname", &self.nickname)
+            .field("rooms", &self.rooms)
+            .finish()
+    }
+}
+
+/// Hot-reloadable XMPP permission filters.
+///
+/// Shared with the XMPP adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct XmppPermissions {
+    /// Allowed room JIDs (None = all joined rooms accepted).
+    pub room_filter: Option<Vec<String>>,
+    /// Bare JIDs allowed to chat with the bot directly. Empty = nobody.
+    pub allowed_users: Vec<String>,
+}
+
+impl XmppPermissions {
+    /// Build from the current config's xmpp settings and bindings.
+    pub fn from_config(_xmpp: &XmppConfig, bindings: &[Binding]) -> Self {
+        let xmpp_bindings: Vec<&Binding> =
+            bindings.iter().filter(|b| b.channel == "xmpp").collect();
+
+        let room_filter = {
+            let room_ids: Vec<String> = xmpp_bindings
+                .iter()
+                .flat_map(|b| b.channel_ids.clone())
+                .collect();
+            if room_ids.is_empty() {
+                None
+            } else {
+                Some(room_ids)
+            }
+        };
+
+        let mut allowed_users: Vec<String> = Vec::new();
+        for binding in &xmpp_bindings {
+            for user in &binding.dm_allowed_users {
+                if !allowed_users.contains(user) {
+                    allowed_users.push(user.clone());
+                }
+            }
+        }
+
+        Self {
+            room_filter,
+            allowed_users,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WhatsAppConfig {
+    pub enabled: bool,
+    /// Graph API ID of the business phone number messages are sent from.
+    pub phone_number_id: String,
+    pub access_token: String,
+    /// Token Meta echoes back when verifying the webhook subscription.
+    pub verify_token: String,
+    /// App secret used to check the `X-Hub-Signature-256` of webhook posts.
+    /// Required, so unsigned posts are never accepted.
+    pub app_secret: String,
+    pub port: u16,
+    pub bind: String,
+    pub api_version: String,
+    /// Approved template sent when the 24-hour service window is closed.
+    pub template_name: Option<String>,
+    pub template_language: String,
+}
+
+impl std::fmt::Debug for WhatsAppConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WhatsAppConfig")
+            .field("enabled", &self.enabled)
+            .field("phone_number_id", &self.phone_number_id)
+            .field("access_token", &"[REDACTED]")
+            .field("verify_token", &"[REDACTED]")
+            .field("app_secret", &"[REDACTED]")
+            .field("port", &self.port)
+            .field("bind", &self.bind)
+            .field("api_version", &self.api_version)
+            .field("template_name", &self.template_name)
+            .field("template_language", &self.template_language)
+            .finish()
+    }
+}
+
+/// Hot-reloadable WhatsApp sender allowlist.
+///
+/// Shared with the WhatsApp adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct WhatsAppPermissions {
+    /// Phone numbers (digits only, with country code) allowed to message the
+    /// bot. Empty = nobody.
+    pub allowed_numbers: Vec<String>,
+}
+
+impl WhatsAppPermissions {
+    /// Build from the current config's whatsapp settings and bindings.
+    pub fn from_config(_whatsapp: &WhatsAppConfig, bindings: &[Binding]) -> Self {
+        let mut allowed_numbers: Vec<String> = Vec::new();
+        for binding in bindings.iter().filter(|b| b.channel == "whatsapp") {
+            for number in &binding.dm_allowed_users {
+                let number: String = number.chars().filter(char::is_ascii_digit).collect();
+                if !number.is_empty() && !allowed_numbers.contains(&number) {
+                    allowed_numbers.push(number);
+                }
+            }
+        }
+
+        Self { allowed_numbers }
+    }
+}
+
+#[derive(Clone)]
+pub struct EmailConfig {
+    pub enabled: bool,
+    pub imap_host: String,
+    pub imap_port: u16,
+    pub smtp_host: String,
+    /// 465 connects with TLS; any other port upgrades with STARTTLS.
+    pub smtp_port: u16,
+    /// Login for both IMAP and SMTP.
+    pub username: String,
+    pub password: String,
+    /// Address replies are sent from. Defaults to the username.
+    pub from_address: String,
+    /// Display name on outgoing mail.
+    pub from_name: Option<String>,
+    /// Mailbox polled for new mail.
+    pub mailbox: String,
+    pub poll_interval_secs: u64,
+}
+
+impl std::fmt::Debug for EmailConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailConfig")
+            .field("enabled", &self.enabled)
+            .field("imap_host", &self.imap_host)
+            .field("imap_port", &self.imap_port)
+            .field("smtp_host", &self.smtp_host)
+            .field("smtp_port", &self.smtp_port)
+            .field("username", &self.username)
+            .field("password", &"[REDACTED]")
+            .field("from_address", &self.from_address)
+            .field("from_name", &self.from_name)
+            .field("mailbox", &self.mailbox)
+            .field("poll_interval_secs", &self.poll_interval_secs)
+            .finish()
+    }
+}
+
+/// Hot-reloadable email sender allowlist.
+///
+/// Shared with the email adapter via `Arc<ArcSwap<..>>` for hot-reloading.
+#[derive(Debug, Clone, Default)]
+pub struct EmailPermissions {
+    /// Addresses or `@domain` suffixes allowed to mail the bot. Empty = nobody.
+    pub allowed_senders: Vec<String>,
+}
+
+impl EmailPermissions {
+    /// Build from the current config's email settings and bindings.
+    pub fn from_config(_email: &EmailConfig, bindings: &[Binding]) -> Self {
+        let mut allowed_senders: Vec<String> = Vec::new();
+        for binding in bindings.iter().filter(|b| b.channel == "email") {
+            for sender in &binding.dm_allowed_users {
+                if !allowed_senders.contains(sender) {
+                    allowed_senders.push(sender.clone());
+                }
+            }
+        }
+
+        Self { allowed_senders }
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub bind: String,
+    pub auth_token: Option<String>,
+    /// Key `/send` bodies must be signed with. Unsigned sends are rejected
+    /// when set. Needs `auth_token`, which still guards the other endpoints.
+    pub signing_secret: Option<String>,
+    /// Signing keys of named sources, picked by the `X-Webhook-Source` header.
+    pub sources: HashMap<String, String>,
+    /// Push responses to this URL instead of buffering them for polling.
+    pub callback_url: Option<String>,
+    /// Callback attempts per response before it's marked failed.
+    pub max_delivery_attempts: u32,
+}
+
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sources: Vec<_> = self.sources.keys().collect();
+        sources.sort();
+        f.debug_struct("WebhookConfig")
+            .field("enabled", &self.enabled)
+            .field("port", &self.port)
+            .field("bind", &self.bind)
+            .field(
+                "auth_token",
+                &self.auth_token.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field(
+                "signing_secret",
+                &self.signing_secret.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field("sources", &sources)
+            .field("callback_url", &self.callback_url)
+            .field("max_delivery_attempts", &self.max_delivery_attempts)
+            .finish()
+    }
+}
+
+// -- TOML deserialization types --
+
+#[derive(Deserialize)]
+struct TomlConfig {
+    #[serde(default)]
+    llm: TomlLlmConfig,
+    #[serde(default)]
+    defaults: TomlDefaultsConfig,
+    #[serde(default)]
+    agents: Vec<TomlAgentConfig>,
+    #[serde(default)]
+    messaging: TomlMessagingConfig,
+    #[serde(default)]
+    bindings: Vec<TomlBinding>,
+    #[serde(default)]
+    api: TomlApiConfig,
+    #[serde(default)]
+    metrics: TomlMetricsConfig,
+    #[serde(default)]
+    telemetry: TomlTelemetryConfig,
+    #[serde(default)]
+    rbac: TomlRbacConfig,
+    #[serde(default)]
+    alerts: TomlAlertConfig,
+    #[serde(default)]
+    event_webhooks: Vec<TomlEventWebhook>,
+    #[serde(default)]
+    update: TomlUpdateConfig,
+    #[serde(default)]
+    cluster: TomlClusterConfig,
+    #[serde(default)]
+    object_storage: TomlObjectStorageConfig,
+    #[serde(default)]
+    middleware: TomlMiddlewareConfig,
+    #[serde(default)]
+    shutdown: TomlShutdownConfig,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlShutdownConfig {
+    drain_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlMiddlewareConfig {
+    strip_mentions: Option<TomlStripMentionsConfig>,
+    profanity: Option<TomlProfanityConfig>,
+}
+
+#[derive(Deserialize)]
+struct TomlStripMentionsConfig {
+    enabled: Option<bool>,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    sources: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlProfanityConfig {
+    enabled: Option<bool>,
+    #[serde(default)]
+    words: Vec<String>,
+    action: Option<String>,
+    message: Option<String>,
+    #[serde(default)]
+    sources: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlObjectStorageConfig {
+    bucket: Option<String>,
+    region: Option<String>,
+    endpoint: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+    prefix: Option<String>,
+    path_style: Option<bool>,
+    signed_url_ttl_secs: Option<u64>,
+    max_upload_mb: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlClusterConfig {
+    #[serde(default)]
+    enabled: bool,
+    node_id: Option<String>,
+    database_url: Option<String>,
+    shared_adapters: Option<Vec<String>>,
+    heartbeat_interval_secs: Option<u64>,
+    lease_ttl_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlUpdateConfig {
+    channel: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlAlertConfig {
+    #[serde(default)]
+    targets: Vec<String>,
+    cooldown_secs: Option<u64>,
+    health_check_interval_secs: Option<u64>,
+    adapter_failure_threshold: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TomlEventWebhook {
+    url: String,
+    secret: Option<String>,
+    #[serde(default)]
+    events: Vec<String>,
+    #[serde(default)]
+    agents: Vec<String>,
+    max_attempts: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlRbacConfig {
+    #[serde(default)]
+    enabled: bool,
+    default_role: Option<String>,
+    #[serde(default)]
+    users: Vec<TomlRbacUser>,
+    #[serde(default)]
+    capabilities: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct TomlRbacUser {
+    name: String,
+    role: String,
+    #[serde(default)]
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlTelemetryConfig {
+    otlp_endpoint: Option<String>,
+    otlp_headers: Option<String>,
+    service_name: Option<String>,
+    sample_rate: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct TomlApiConfig {
+    #[serde(default = "default_api_enabled")]
+    enabled: bool,
+    #[serde(default = "default_api_port")]
+    port: u16,
+    #[serde(default = "default_api_bind")]
+    bind: String,
+    #[serde(default)]
+    auth_token: Option<String>,
+}
+
+impl Default for TomlApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_api_enabled(),
+            port: default_api_port(),
+            bind: default_api_bind(),
+            auth_token: None,
+        }
+    }
+}
+
+fn default_api_enabled() -> bool {
+    true
+}
+fn default_api_port() -> u16 {
+    19898
+}
+fn default_api_bind() -> String {
+    "127.0.0.1".into()
+}
+
+fn hosted_api_bind(bind: String) -> String {
+    match std::env::var("SPACEBOT_DEPLOYMENT") {
+        Ok(deployment) if deployment.eq_ignore_ascii_case("hosted") => "[::]".into(),
+        _ => bind,
+    }
+}
+
+#[derive(Deserialize)]
+struct TomlMetricsConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_metrics_port")]
+    port: u16,
+    #[serde(default = "default_metrics_bind")]
+    bind: String,
+}
+
+impl Default for TomlMetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_metrics_port(),
+            bind: default_metrics_bind(),
+        }
+    }
+}
+
+fn default_metrics_port() -> u16 {
+    9090
+}
+fn default_metrics_bind() -> String {
+    "0.0.0.0".into()
+}
+
+#[derive(Deserialize, Debug)]
+struct TomlProviderConfig {
+    api_type: ApiType,
+    base_url: String,
+    api_key: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlLlmConfigFields {
+    anthropic_key: Option<String>,
+    openai_key: Option<String>,
+    openrouter_key: Option<String>,
+    zhipu_key: Option<String>,
+    groq_key: Option<String>,
+    together_key: Option<String>,
+    fireworks_key: Option<String>,
+    deepseek_key: Option<String>,
+    xai_key: Option<String>,
+    mistral_key: Option<String>,
+    gemini_key: Option<String>,
+    ollama_key: Option<String>,
+    ollama_base_url: Option<String>,
+    opencode_zen_key: Option<String>,
+    nvidia_key: Option<String>,
+    minimax_key: Option<String>,
+    minimax_cn_key: Option<String>,
+    moonshot_key: Option<String>,
+    zai_coding_plan_key: Option<String>,
+    #[serde(default)]
+    providers: HashMap<String, TomlProviderConfig>,
+    #[serde(default)]
+    #[serde(flatten)]
+    extra: HashMap<String, toml::Value>,
+}
+
+#[derive(Default)]
+struct TomlLlmConfig {
+    anthropic_key: Option<String>,
+    openai_key: Option<String>,
+    openrouter_key: Option<String>,
+    zhipu_key: Option<String>,
+    groq_key: Option<String>,
+    together_key: Option<String>,
+    fireworks_key: Option<String>,
+    deepseek_key: Option<String>,
+    xai_key: Option<String>,
+    mistral_key: Option<String>,
+    gemini_key: Option<String>,
+    ollama_key: Option<String>,
+    ollama_base_url: Option<String>,
+    opencode_zen_key: Option<String>,
+    nvidia_key: Option<String>,
+    minimax_key: Option<String>,
+    minimax_cn_key: Option<String>,
+    moonshot_key: Option<String>,
+    zai_coding_plan_key: Option<String>,
+    providers: HashMap<String, TomlProviderConfig>,
+}
+
+impl<'de> Deserialize<'de> for TomlLlmConfig {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut fields = TomlLlmConfigFields::deserialize(deserializer)?;
+        let mut providers = fields.providers;
+
+        for (key, value) in fields.extra {
+            if key == "provider" {
+                let table = value
+                    .as_table()
+                    .ok_or_else(|| serde::de::Error::custom("`llm.provider` must be a table"))?;
+                for (provider_id, provider_value) in table {
+                    let provider_config = provider_value
+                        .clone()
+                        .try_into()
+                        .map_err(serde::de::Error::custom)?;
+                    providers.insert(provider_id.to_string(), provider_config);
+                }
+            }
+
+            if let Some(provider_id) = key.strip_prefix("provider.") {
+                let provider_config = value.try_into().map_err(serde::de::Error::custom)?;
+                providers.insert(provider_id.to_string(), provider_config);
+            }
+        }
+
+        fields.providers = providers;
+
+        Ok(Self {
+            anthropic_key: fields.anthropic_key,
+            openai_key: fields.openai_key,
+            openrouter_key: fields.openrouter_key,
+            zhipu_key: fields.zhipu_key,
+            groq_key: fields.groq_key,
+            together_key: fields.together_key,
+            fireworks_key: fields.fireworks_key,
+            deepseek_key: fields.deepseek_key,
+            xai_key: fields.xai_key,
+            mistral_key: fields.mistral_key,
+            gemini_key: fields.gemini_key,
+            ollama_key: fields.ollama_key,
+            ollama_base_url: fields.ollama_base_url,
+            opencode_zen_key: fields.opencode_zen_key,
+            nvidia_key: fields.nvidia_key,
+            minimax_key: fields.minimax_key,
+            minimax_cn_key: fields.minimax_cn_key,
+            moonshot_key: fields.moonshot_key,
+            zai_coding_plan_key: fields.zai_coding_plan_key,
+            providers: fields.providers,
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct TomlDefaultsConfig {
+    routing: Option<TomlRoutingConfig>,
+    max_concurrent_branches: Option<usize>,
+    max_concurrent_workers: Option<usize>,
+    max_turns: Option<usize>,
+    branch_max_turns: Option<usize>,
+    context_window: Option<usize>,
+    compaction: Option<TomlCompactionConfig>,
+    memory_persistence: Option<TomlMemoryPersistenceConfig>,
+    coalesce: Option<TomlCoalesceConfig>,
+    ingestion: Option<TomlIngestionConfig>,
+    cortex: Option<TomlCortexConfig>,
+    browser: Option<TomlBrowserConfig>,
+    sandbox: Option<TomlSandboxConfig>,
+    live_status: Option<TomlLiveStatusConfig>,
+    confirmations: Option<TomlConfirmationsConfig>,
+    presence: Option<TomlPresenceConfig>,
+    reflection: Option<TomlReflectionConfig>,
+    dream: Option<TomlDreamConfig>,
+    worker_queue: Option<TomlWorkerQueueConfig>,
+    rate_limit: Option<TomlRateLimitConfig>,
+    handoff: Option<TomlHandoffConfig>,
+    hibernation: Option<TomlHibernationConfig>,
+    sub_workers: Option<TomlSubWorkersConfig>,
+    worker_budget: Option<TomlWorkerBudgetConfig>,
+    reaction_memory: Option<TomlReactionMemoryConfig>,
+    reactions: Option<TomlReactionsConfig>,
+    voice: Option<TomlVoiceConfig>,
+    scripting: Option<TomlScriptingConfig>,
+    plugins: Option<TomlPluginConfig>,
+    vector_index: Option<TomlVectorIndexConfig>,
+    attachments: Option<TomlAttachmentConfig>,
+    loop_guard: Option<TomlLoopGuardConfig>,
+    disk_quota: Option<TomlDiskQuotaConfig>,
+    offline: Option<TomlOfflineConfig>,
+    spend: Option<TomlSpendConfig>,
+    database: Option<TomlDatabaseConfig>,
+    egress: Option<TomlEgressConfig>,
+    prompt_injection: Option<TomlPromptInjectionConfig>,
+    #[serde(default)]
+    mcp: Vec<TomlMcpServerConfig>,
+    brave_search_key: Option<String>,
+    cron_timezone: Option<String>,
+    opencode: Option<TomlOpenCodeConfig>,
+    worker_log_mode: Option<String>,
+    memory_injection: Option<TomlMemoryInjectionConfig>,
+}
+
+#[derive(Deserialize)]
+struct TomlMemoryInjectionConfig {
+    enabled: Option<bool>,
+    search_limit: Option<usize>,
+    contextual_min_score: Option<f32>,
+    context_window_depth: Option<usize>,
+    semantic_threshold: Option<f32>,
+    pinned_types: Option<Vec<String>>,
+    ambient_enabled: Option<bool>,
+    pinned_limit: Option<i64>,
+    pinned_sort: Option<String>,
+    max_total: Option<usize>,
+    max_injected_blocks_in_history: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlRoutingConfig {
+    channel: Option<String>,
+    branch: Option<String>,
+    worker: Option<String>,
+    compactor: Option<String>,
+    cortex: Option<String>,
+    voice: Option<String>,
+    rate_limit_cooldown_secs: Option<u64>,
+    channel_thinking_effort: Option<String>,
+    branch_thinking_effort: Option<String>,
+    worker_thinking_effort: Option<String>,
+    compactor_thinking_effort: Option<String>,
+    cortex_thinking_effort: Option<String>,
+    #[serde(default)]
+    task_overrides: HashMap<String, String>,
+    fallbacks: Option<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Deserialize)]
+struct TomlMemoryPersistenceConfig {
+    enabled: Option<bool>,
+    message_interval: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TomlCoalesceConfig {
+    enabled: Option<bool>,
+    debounce_ms: Option<u64>,
+    max_wait_ms: Option<u64>,
+    min_messages: Option<usize>,
+    multi_user_only: Option<bool>,
+    sender_debounce_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlIngestionConfig {
+    enabled: Option<bool>,
+    poll_interval_secs: Option<u64>,
+    chunk_size: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlCompactionConfig {
+    background_threshold: Option<f32>,
+    aggressive_threshold: Option<f32>,
+    emergency_threshold: Option<f32>,
+    tool_output_max_chars: Option<usize>,
+    branch: Option<TomlProcessCompactionConfig>,
+    worker: Option<TomlProcessCompactionConfig>,
+}
+
+#[derive(Deserialize)]
+struct TomlProcessCompactionConfig {
+    threshold: Option<f32>,
+    fraction: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct TomlCortexConfig {
+    tick_interval_secs: Option<u64>,
+    worker_timeout_secs: Option<u64>,
+    branch_timeout_secs: Option<u64>,
+    circuit_breaker_threshold: Option<u8>,
+    bulletin_interval_secs: Option<u64>,
+    bulletin_max_words: Option<usize>,
+    bulletin_max_turns: Option<usize>,
+    association_interval_secs: Option<u64>,
+    association_similarity_threshold: Option<f32>,
+    association_updates_threshold: Option<f32>,
+    association_max_per_pass: Option<usize>,
+    consolidation_idle_secs: Option<u64>,
+    consolidation_interval_secs: Option<u64>,
+    consolidation_merge_threshold: Option<f32>,
+    consolidation_stale_days: Option<u64>,
+    proactive_daily_limit: Option<u32>,
+    runaway_repeat_limit: Option<u32>,
+    queue_concurrency: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TomlBrowserConfig {
+    enabled: Option<bool>,
+    headless: Option<bool>,
+    evaluate_enabled: Option<bool>,
+    executable_path: Option<String>,
+    screenshot_dir: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlSandboxConfig {
+    backend: Option<String>,
+    image: Option<String>,
+    network: Option<String>,
+    memory_limit: Option<String>,
+    cpus: Option<f64>,
+    pids_limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TomlPromptInjectionConfig {
+    mode: Option<String>,
+    classifier_url: Option<String>,
+    classifier_threshold: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct TomlEgressConfig {
+    allowed_domains: Option<Vec<String>>,
+    denied_domains: Option<Vec<String>>,
+    block_private_ranges: Option<bool>,
+    allowed_networks: Option<Vec<String>>,
+    denied_networks: Option<Vec<String>>,
+    scopes: Option<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Deserialize)]
+struct TomlDatabaseConfig {
+    encryption_key: Option<String>,
+    wal: Option<bool>,
+    synchronous: Option<String>,
+    busy_timeout_ms: Option<u64>,
+    max_connections: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TomlSpendConfig {
+    soft_limit_usd: Option<f64>,
+    hard_limit_usd: Option<f64>,
+    downgrade_model: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlOfflineConfig {
+    enabled: Option<bool>,
+    max_queued_messages: Option<usize>,
+    retry_interval_secs: Option<u64>,
+    notice: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlDiskQuotaConfig {
+    workspace_limit_mb: Option<u64>,
+    screenshots_limit_mb: Option<u64>,
+    cleanup: Option<String>,
+    check_interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlLoopGuardConfig {
+    enabled: Option<bool>,
+    max_identical_calls: Option<usize>,
+    max_failing_calls: Option<usize>,
+    max_spawns: Option<usize>,
+    spawn_window_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlAttachmentConfig {
+    max_concurrent_downloads: Option<usize>,
+    max_file_size_mb: Option<u64>,
+    cache_enabled: Option<bool>,
+    cache_ttl_secs: Option<u64>,
+    cache_max_size_mb: Option<u64>,
+    inline_text_limit_kb: Option<usize>,
+    summarize_large_text: Option<bool>,
+    max_image_dimension: Option<u32>,
+    max_image_size_kb: Option<usize>,
+    thumbnails_enabled: Option<bool>,
+    video_frames: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TomlVectorIndexConfig {
+    index_type: Option<String>,
+    auto_index_threshold: Option<usize>,
+    num_partitions: Option<u32>,
+    nprobes: Option<usize>,
+    refine_factor: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TomlPluginConfig {
+    enabled: Option<bool>,
+    max_memory_mb: Option<u64>,
+    fuel: Option<u64>,
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlScriptingConfig {
+    enabled: Option<bool>,
+    max_operations: Option<u64>,
+    max_actions: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TomlVoiceConfig {
+    enabled: Option<bool>,
+    tts_model: Option<String>,
+    tts_voice: Option<String>,
+    speech_threshold: Option<f32>,
+    silence_ms: Option<u64>,
+    max_utterance_secs: Option<u64>,
+    barge_in: Option<bool>,
+    history_messages: Option<usize>,
+    transcription: Option<String>,
+    whisper_cpp_binary: Option<String>,
+    whisper_cpp_model: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlReactionMemoryConfig {
+    enabled: Option<bool>,
+    emoji: Option<String>,
+    confirm_emoji: Option<String>,
+    memory_type: Option<crate::memory::MemoryType>,
+}
+
+#[derive(Deserialize)]
+struct TomlReactionsConfig {
+    enabled: Option<bool>,
+    approve: Option<Vec<String>>,
+    deny: Option<Vec<String>>,
+    retry: Option<Vec<String>>,
+    ack_emoji: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlHandoffConfig {
+    targets: Option<Vec<String>>,
+    history_messages: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TomlHibernationConfig {
+    idle_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlSubWorkersConfig {
+    max_depth: Option<usize>,
+    max_per_channel: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TomlWorkerBudgetConfig {
+    max_wall_time_secs: Option<u64>,
+    max_tool_calls: Option<usize>,
+    max_tokens: Option<u64>,
+    max_cost_usd: Option<f64>,
+    wrap_up_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlLiveStatusConfig {
+    enabled: Option<bool>,
+    delay_secs: Option<u64>,
+    update_interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlConfirmationsConfig {
+    spawn_worker: Option<bool>,
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlPresenceConfig {
+    enabled: Option<bool>,
+    activity: Option<String>,
+    busy_text: Option<String>,
+    idle_text: Option<String>,
+    update_interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlReflectionConfig {
+    enabled: Option<bool>,
+    interval_secs: Option<u64>,
+    max_messages: Option<i64>,
+    control_channel: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlDreamConfig {
+    enabled: Option<bool>,
+    hour: Option<u32>,
+    model: Option<String>,
+    max_input_tokens: Option<usize>,
+    max_output_tokens: Option<u64>,
+    max_memories: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TomlWorkerQueueConfig {
+    max_concurrent: Option<usize>,
+    max_queued: Option<usize>,
+    dispatch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlRateLimitConfig {
+    enabled: Option<bool>,
+    sender_burst: Option<u32>,
+    sender_per_minute: Option<u32>,
+    conversation_burst: Option<u32>,
+    conversation_per_minute: Option<u32>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlOpenCodeConfig {
+    enabled: Option<bool>,
+    path: Option<String>,
+    max_servers: Option<usize>,
+    server_startup_timeout_secs: Option<u64>,
+    max_restart_retries: Option<u32>,
+    permissions: Option<TomlOpenCodePermissions>,
+}
+
+#[derive(Deserialize)]
+struct TomlOpenCodePermissions {
+    edit: Option<String>,
+    bash: Option<String>,
+    webfetch: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+struct TomlMcpServerConfig {
+    name: String,
+    transport: String,
+    #[serde(default = "default_mcp_enabled")]
+    enabled: bool,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    url: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+fn default_mcp_enabled() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct TomlAgentConfig {
+    id: String,
+    #[serde(default)]
+    default: bool,
+    workspace: Option<String>,
+    routing: Option<TomlRoutingConfig>,
+    max_concurrent_branches: Option<usize>,
+    max_concurrent_workers: Option<usize>,
+    max_turns: Option<usize>,
+    branch_max_turns: Option<usize>,
+    context_window: Option<usize>,
+    compaction: Option<TomlCompactionConfig>,
+    memory_persistence: Option<TomlMemoryPersistenceConfig>,
+    coalesce: Option<TomlCoalesceConfig>,
+    ingestion: Option<TomlIngestionConfig>,
+    cortex: Option<TomlCortexConfig>,
+    browser: Option<TomlBrowserConfig>,
+    sandbox: Option<TomlSandboxConfig>,
+    live_status: Option<TomlLiveStatusConfig>,
+    confirmations: Option<TomlConfirmationsConfig>,
+    presence: Option<TomlPresenceConfig>,
+    reflection: Option<TomlReflectionConfig>,
+    dream: Option<TomlDreamConfig>,
+    worker_queue: Option<TomlWorkerQueueConfig>,
+    rate_limit: Option<TomlRateLimitConfig>,
+    handoff: Option<TomlHandoffConfig>,
+    hibernation: Option<TomlHibernationConfig>,
+    sub_workers: Option<TomlSubWorkersConfig>,
+    worker_budget: Option<TomlWorkerBudgetConfig>,
+    reaction_memory: Option<TomlReactionMemoryConfig>,
+    reactions: Option<TomlReactionsConfig>,
+    voice: Option<TomlVoiceConfig>,
+    scripting: Option<TomlScriptingConfig>,
+    plugins: Option<TomlPluginConfig>,
+    vector_index: Option<TomlVectorIndexConfig>,
+    attachments: Option<TomlAttachmentConfig>,
+    loop_guard: Option<TomlLoopGuardConfig>,
+    disk_quota: Option<TomlDiskQuotaConfig>,
+    offline: Option<TomlOfflineConfig>,
+    spend: Option<TomlSpendConfig>,
+    database: Option<TomlDatabaseConfig>,
+    egress: Option<TomlEgressConfig>,
+    prompt_injection: Option<TomlPromptInjectionConfig>,
+    memory_injection: Option<TomlMemoryInjectionConfig>,
+    mcp: Option<Vec<TomlMcpServerConfig>>,
+    brave_search_key: Option<String>,
+    cron_timezone: Option<String>,
+    #[serde(default)]
+    cron: Vec<TomlCronDef>,
+    #[serde(default)]
+    tasks: Vec<TomlTaskDef>,
+    #[serde(default)]
+    triggers: Vec<TomlTriggerDef>,
+}
+
+#[derive(Deserialize)]
+struct TomlCronDef {
+    id: String,
+    prompt: String,
+    interval_secs: Option<u64>,
+    delivery_target: String,
+    active_start_hour: Option<u8>,
+    active_end_hour: Option<u8>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default)]
+    run_once: bool,
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TomlTaskDef {
+    id: String,
+    schedule: String,
+    task: String,
+    report_to: Option<String>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct TomlTriggerDef {
+    id: String,
+    watch: Option<String>,
+    webhook_token: Option<String>,
+    every_secs: Option<u64>,
+    task: Option<String>,
+    report_to: Option<String>,
+    notify: Option<String>,
+    cooldown_secs: Option<u64>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlMessagingConfig {
+    discord: Option<TomlDiscordConfig>,
+    slack: Option<TomlSlackConfig>,
+    telegram: Option<TomlTelegramConfig>,
+    webhook: Option<TomlWebhookConfig>,
+    twitch: Option<TomlTwitchConfig>,
+    irc: Option<TomlIrcConfig>,
+    email: Option<TomlEmailConfig>,
+    xmpp: Option<TomlXmppConfig>,
+    whatsapp: Option<TomlWhatsAppConfig>,
+    #[serde(default)]
+    custom: BTreeMap<String, TomlCustomAdapterConfig>,
+    sessions: Option<TomlSessionConfig>,
+    pairing: Option<TomlPairingConfig>,
+}
+
+#[derive(Deserialize)]
+struct TomlCustomAdapterConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(flatten)]
+    settings: toml::Table,
+}
+
+#[derive(Deserialize)]
+struct TomlPairingConfig {
+    enabled: Option<bool>,
+    control_channel: Option<String>,
+    code_ttl_secs: Option<u64>,
+    #[serde(default)]
+    approvers: Vec<String>,
+}
 
-            if !direct_match && !parent_match {
-                return false;
-            }
-        }
+#[derive(Deserialize)]
+struct TomlSessionConfig {
+    ttl_secs: Option<u64>,
+    max_ttl_secs: Option<u64>,
+}
 
-        if self.channel == "discord" && self.require_mention {
-            let is_guild_message = message
-                .metadata
-                .get("discord_guild_id")
-                .and_then(|v| v.as_u64())
-                .is_some();
-            if is_guild_message {
-                let mentions_or_replies_to_bot = message
-                    .metadata
-                    .get("discord_mentions_or_replies_to_bot")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                if !mentions_or_replies_to_bot {
-                    return false;
-                }
-            }
-        }
+#[derive(Deserialize)]
+struct TomlDiscordConfig {
+    #[serde(default)]
+    enabled: bool,
+    token: Option<String>,
+    #[serde(default)]
+    dm_allowed_users: Vec<String>,
+    #[serde(default)]
+    allow_bot_messages: bool,
+    #[serde(default)]
+    voice: bool,
+}
 
-        if let Some(chat_id) = &self.chat_id {
-            let message_chat = message.metadata.get("telegram_chat_id").and_then(|value| {
-                value
-                    .as_str()
-                    .map(std::borrow::ToOwned::to_owned)
-                    .or_else(|| value.as_i64().map(|id| id.to_string()))
-            });
-            if message_chat.as_deref() != Some(chat_id.as_str()) {
-                return false;
-            }
-        }
+#[derive(Deserialize)]
+struct TomlSlackConfig {
+    #[serde(default)]
+    enabled: bool,
+    bot_token: Option<String>,
+    app_token: Option<String>,
+    #[serde(default)]
+    dm_allowed_users: Vec<String>,
+    #[serde(default)]
+    commands: Vec<TomlSlackCommandConfig>,
+}
 
-        true
-    }
+#[derive(Deserialize)]
+struct TomlSlackCommandConfig {
+    command: String,
+    agent_id: String,
+    description: Option<String>,
 }
 
-/// Resolve which agent should handle an inbound message.
-///
-/// Checks bindings in order. First match wins. Falls back to the default
-/// agent if no binding matches.
-pub fn resolve_agent_for_message(
-    bindings: &[Binding],
-    message: &crate::InboundMessage,
-    default_agent_id: &str,
-) -> crate::AgentId {
-    for binding in bindings {
-        if binding.matches(message) {
-            return std::sync::Arc::from(binding.agent_id.as_str());
-        }
-    }
-    std::sync::Arc::from(default_agent_id)
+#[derive(Deserialize)]
+struct TomlTelegramConfig {
+    #[serde(default)]
+    enabled: bool,
+    token: Option<String>,
+    #[serde(default)]
+    dm_allowed_users: Vec<String>,
 }
 
-/// Messaging platform credentials (instance-level).
-#[derive(Debug, Clone, Default)]
-pub struct MessagingConfig {
-    pub discord: Option<DiscordConfig>,
-    pub slack: Option<SlackConfig>,
-    pub telegram: Option<TelegramConfig>,
-    pub webhook: Option<WebhookConfig>,
-    pub twitch: Option<TwitchConfig>,
+#[derive(Deserialize)]
+struct TomlWebhookConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_webhook_port")]
+    port: u16,
+    #[serde(default = "default_webhook_bind")]
+    bind: String,
+    auth_token: Option<String>,
+    signing_secret: Option<String>,
+    #[serde(default)]
+    sources: HashMap<String, String>,
+    callback_url: Option<String>,
+    #[serde(default = "default_webhook_delivery_attempts")]
+    max_delivery_attempts: u32,
 }
 
-#[derive(Clone)]
-pub struct DiscordConfig {
-    pub enabled: bool,
-    pub token: String,
-    /// User IDs allowed to DM the bot. If empty, DMs are ignored entirely.
-    pub dm_allowed_users: Vec<String>,
-    /// Whether to process messages from other bots (self-messages are always ignored).
-    pub allow_bot_messages: bool,
+#[derive(Deserialize)]
+struct TomlTwitchConfig {
+    #[serde(default)]
+    enabled: bool,
+    username: Option<String>,
+    oauth_token: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+    #[serde(default)]
+    channels: Vec<String>,
+    trigger_prefix: Option<String>,
 }
 
-impl std::fmt::Debug for DiscordConfig {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("DiscordConfig")
-            .field("enabled", &self.enabled)
-            .field("token", &"[REDACTED]")
-            .field("dm_allowed_users", &self.dm_allowed_users)
-            .field("allow_bot_messages", &self.allow_bot_messages)
-            .finish()
-    }
+#[derive(Deserialize)]
+struct TomlIrcConfig {
+    #[serde(default)]
+    enabled: bool,
+    server: Option<String>,
+    port: Option<u16>,
+    #[serde(default = "default_irc_use_tls")]
+    use_tls: bool,
+    nickname: Option<String>,
+    password: Option<String>,
+    nickserv_password: Option<String>,
+    #[serde(default)]
+    channels: Vec<String>,
+    trigger_prefix: Option<String>,
 }
 
-/// A single slash command definition for the Slack adapter.
-///
-/// Maps a Slack slash command (e.g. `/ask`) to a target agent.
-/// Commands not listed here are acknowledged but produce a "not configured" reply.
-#[derive(Debug, Clone)]
-pub struct SlackCommandConfig {
-    /// The slash command string exactly as Slack sends it, e.g. `"/ask"`.
-    pub command: String,
-    /// ID of the agent that should handle this command.
-    pub agent_id: String,
-    /// Short description shown in Slack's command autocomplete hint (optional).
-    pub description: Option<String>,
+fn default_irc_use_tls() -> bool {
+    true
 }
 
-#[derive(Clone)]
-pub struct SlackConfig {
-    pub enabled: bool,
-    pub bot_token: String,
-    pub app_token: String,
-    /// User IDs allowed to DM the bot. If empty, DMs are ignored entirely.
-    pub dm_allowed_users: Vec<String>,
-    /// Slash command definitions. If empty, all slash commands are ignored.
-    pub commands: Vec<SlackCommandConfig>,
+#[derive(Deserialize)]
+struct TomlEmailConfig {
+    #[serde(default)]
+    enabled: bool,
+    imap_host: Option<String>,
+    #[serde(default = "default_email_imap_port")]
+    imap_port: u16,
+    smtp_host: Option<String>,
+    #[serde(default = "default_email_smtp_port")]
+    smtp_port: u16,
+    username: Option<String>,
+    password: Option<String>,
+    from_address: Option<String>,
+    from_name: Option<String>,
+    #[serde(default = "default_email_mailbox")]
+    mailbox: String,
+    #[serde(default = "default_email_poll_interval_secs")]
+    poll_interval_secs: u64,
 }
 
-impl std::fmt::Debug for SlackConfig {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("SlackConfig")
-            .field("enabled", &self.enabled)
-            .field("bot_token", &"[REDACTED]")
-            .field("app_token", &"[REDACTED]")
-            .field("dm_allowed_users", &self.dm_allowed_users)
-            .field("commands", &self.commands)
-            .finish()
-    }
+fn default_email_imap_port() -> u16 {
+    993
+}
+fn default_email_smtp_port() -> u16 {
+    587
+}
+fn default_email_mailbox() -> String {
+    "INBOX".into()
+}
+fn default_email_poll_interval_secs() -> u64 {
+    60
 }
 
-/// Hot-reloadable Discord permission filters.
-///
-/// Derived from bindings + discord config. Shared with the Discord adapter
-/// via `Arc<ArcSwap<..>>` so the file watcher can swap in new values without
-/// restarting the gateway connection.
-#[derive(Debug, Clone, Default)]
-pub struct DiscordPermissions {
-    pub guild_filter: Option<Vec<u64>>,
-    pub channel_filter: std::collections::HashMap<u64, Vec<u64>>,
-    pub dm_allowed_users: Vec<u64>,
-    pub allow_bot_messages: bool,
+#[derive(Deserialize)]
+struct TomlXmppConfig {
+    #[serde(default)]
+    enabled: bool,
+    jid: Option<String>,
+    password: Option<String>,
+    nickname: Option<String>,
+    #[serde(default)]
+    rooms: Vec<String>,
 }
 
-/// Hot-reloadable Slack permission filters.
-///
-/// Shared with the Slack adapter via `Arc<ArcSwap<..>>` for hot-reloading.
-#[derive(Debug, Clone, Default)]
-pub struct SlackPermissions {
-    pub workspace_filter: Option<Vec<String>>, // team IDs
-    pub channel_filter: std::collections::HashMap<String, Vec<String>>, // team_id -> allowed channel_ids
-    pub dm_allowed_users: Vec<String>,                                  // user IDs
+#[derive(Deserialize)]
+struct TomlWhatsAppConfig {
+    #[serde(default)]
+    enabled: bool,
+    phone_number_id: Option<String>,
+    access_token: Option<String>,
+    verify_token: Option<String>,
+    app_secret: Option<String>,
+    #[serde(default = "default_whatsapp_port")]
+    port: u16,
+    #[serde(default = "default_webhook_bind")]
+    bind: String,
+    #[serde(default = "default_whatsapp_api_version")]
+    api_version: String,
+    template_name: Option<String>,
+    #[serde(default = "default_whatsapp_template_language")]
+    template_language: String,
 }
 
-impl SlackPermissions {
-    /// Build from the current config's slack settings and bindings.
-    pub fn from_config(slack: &SlackConfig, bindings: &[Binding]) -> Self {
-        let slack_bindings: Vec<&Binding> =
-            bindings.iter().filter(|b| b.channel == "slack").collect();
+fn default_whatsapp_port() -> u16 {
+    18790
+}
+fn default_whatsapp_api_version() -> String {
+    "v21.0".into()
+}
+fn default_whatsapp_template_language() -> String {
+    "en_US".into()
+}
 
-        let workspace_filter = {
-            let workspace_ids: Vec<String> = slack_bindings
-                .iter()
-                .filter_map(|b| b.workspace_id.clone())
-                .collect();
-            if workspace_ids.is_empty() {
-                None
-            } else {
-                Some(workspace_ids)
-            }
-        };
+fn default_webhook_port() -> u16 {
+    18789
+}
+fn default_webhook_bind() -> String {
+    "127.0.0.1".into()
+}
 
-        let channel_filter = {
-            let mut filter: std::collections::HashMap<String, Vec<String>> =
-                std::collections::HashMap::new();
-            for binding in &slack_bindings {
-                if let Some(workspace_id) = &binding.workspace_id
-                    && !binding.channel_ids.is_empty()
-                {
-                    filter
-                        .entry(workspace_id.clone())
-                        .or_default()
-                        .extend(binding.channel_ids.clone());
-                }
-            }
-            filter
-        };
+fn default_webhook_delivery_attempts() -> u32 {
+    5
+}
 
-        let dm_allowed_users = slack.dm_allowed_users.clone();
+#[derive(Deserialize)]
+struct TomlBinding {
+    agent_id: String,
+    channel: String,
+    guild_id: Option<String>,
+    workspace_id: Option<String>,
+    chat_id: Option<String>,
+    #[serde(default)]
+    channel_ids: Vec<String>,
+    #[serde(default)]
+    require_mention: bool,
+    activation: Option<String>,
+    #[serde(default)]
+    wake_words: Vec<String>,
+    #[serde(default)]
+    reply_to_message: bool,
+    #[serde(default)]
+    dm_allowed_users: Vec<String>,
+    digest_interval_secs: Option<u64>,
+    response_mode: Option<String>,
+    #[serde(default)]
+    allow_proactive: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    channel_model: Option<String>,
+    max_turns: Option<usize>,
+    context_window: Option<usize>,
+    compaction: Option<TomlCompactionConfig>,
+}
 
-        Self {
-            workspace_filter,
-            channel_filter,
-            dm_allowed_users,
-        }
+/// Resolve a value that might be an "env:VAR_NAME" reference.
+fn resolve_env_value(value: &str) -> Option<String> {
+    if let Some(var_name) = value.strip_prefix("env:") {
+        std::env::var(var_name).ok()
+    } else {
+        Some(value.to_string())
     }
 }
 
-impl DiscordPermissions {
-    /// Build from the current config's discord settings and bindings.
-    pub fn from_config(discord: &DiscordConfig, bindings: &[Binding]) -> Self {
-        let discord_bindings: Vec<&Binding> =
-            bindings.iter().filter(|b| b.channel == "discord").collect();
-
-        let guild_filter = {
-            let guild_ids: Vec<u64> = discord_bindings
-                .iter()
-                .filter_map(|b| b.guild_id.as_ref()?.parse::<u64>().ok())
-                .collect();
-            if guild_ids.is_empty() {
-                None
-            } else {
-                Some(guild_ids)
-            }
-        };
-
-        let channel_filter = {
-            let mut filter: std::collections::HashMap<u64, Vec<u64>> =
-                std::collections::HashMap::new();
-            for binding in &discord_bindings {
-                if let Some(guild_id) = binding
-                    .guild_id
-                    .as_ref()
-                    .and_then(|g| g.parse::<u64>().ok())
-                    && !binding.channel_ids.is_empty()
-                {
-                    let channel_ids: Vec<u64> = binding
-                        .channel_ids
-                        .iter()
-                        .filter_map(|id| id.parse::<u64>().ok())
-                        .collect();
-                    filter.entry(guild_id).or_default().extend(channel_ids);
-                }
-            }
-            filter
-        };
-
-        let mut dm_allowed_users: Vec<u64> = discord
-            .dm_allowed_users
-            .iter()
-            .filter_map(|id| id.parse::<u64>().ok())
-            .collect();
+/// Resolve a "secret:NAME" reference from the instance's secrets store,
+/// decrypted with the master key in `SPACEBOT_MASTER_KEY`.
+fn resolve_secret_value(value: &str, instance_dir: &Path) -> anyhow::Result<String> {
+    let name = value
+        .strip_prefix("secret:")
+        .context("not a secret: reference")?;
+    let master_key = std::env::var(SECRETS_MASTER_KEY_ENV_VAR).with_context(|| {
+        format!("{SECRETS_MASTER_KEY_ENV_VAR} must be set to read secret '{name}'")
+    })?;
+    read_secret(instance_dir, name, master_key.as_bytes())
+}
 
-        // Also collect dm_allowed_users from bindings
-        for binding in &discord_bindings {
-            for id in &binding.dm_allowed_users {
-                if let Ok(uid) = id.parse::<u64>()
-                    && !dm_allowed_users.contains(&uid)
-                {
-                    dm_allowed_users.push(uid);
-                }
-            }
-        }
+fn read_secret(instance_dir: &Path, name: &str, master_key: &[u8]) -> anyhow::Result<String> {
+    let store = crate::secrets::store::SecretsStore::new(instance_dir.join("secrets.redb"))?;
+    let secret = store.get(name, master_key)?;
+    Ok(secret.expose().to_string())
+}
 
-        Self {
-            guild_filter,
-            channel_filter,
-            dm_allowed_users,
-            allow_bot_messages: discord.allow_bot_messages,
-        }
+fn normalize_timezone(value: &str) -> Option<String> {
+    let timezone = value.trim();
+    if timezone.is_empty() {
+        return None;
     }
+    Some(timezone.to_string())
 }
 
-#[derive(Clone)]
-pub struct TelegramConfig {
-    pub enabled: bool,
-    pub token: String,
-    /// User IDs allowed to DM the bot. If empty, DMs are ignored entirely.
-    pub dm_allowed_users: Vec<String>,
-}
+fn resolve_cron_timezone(
+    agent_id: &str,
+    agent_timezone: Option<&str>,
+    default_timezone: Option<&str>,
+) -> Option<String> {
+    let timezone = agent_timezone
+        .and_then(normalize_timezone)
+        .or_else(|| default_timezone.and_then(normalize_timezone))
+        .or_else(|| {
+            std::env::var(CRON_TIMEZONE_ENV_VAR)
+                .ok()
+                .and_then(|value| normalize_timezone(&value))
+        });
 
-impl std::fmt::Debug for TelegramConfig {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("TelegramConfig")
-            .field("enabled", &self.enabled)
-            .field("token", &"[REDACTED]")
-            .field("dm_allowed_users", &self.dm_allowed_users)
-            .finish()
+    let Some(timezone) = timezone else {
+        return None;
+    };
+
+    if timezone.parse::<Tz>().is_err() {
+        tracing::warn!(
+            agent_id,
+            cron_timezone = %timezone,
+            "invalid cron timezone configured, falling back to system local timezone"
+        );
+        return None;
     }
-}
 
-/// Hot-reloadable Telegram permission filters.
-///
-/// Shared with the Telegram adapter via `Arc<ArcSwap<..>>` for hot-reloading.
-#[derive(Debug, Clone, Default)]
-pub struct TelegramPermissions {
-    /// Allowed chat IDs (None = all chats accepted).
-    pub chat_filter: Option<Vec<i64>>,
-    /// User IDs allowed in private chats.
-    pub dm_allowed_users: Vec<i64>,
+    Some(timezone)
 }
 
-impl TelegramPermissions {
-    /// Build from the current config's telegram settings and bindings.
-    pub fn from_config(telegram: &TelegramConfig, bindings: &[Binding]) -> Self {
-        let telegram_bindings: Vec<&Binding> = bindings
-            .iter()
-            .filter(|b| b.channel == "telegram")
-            .collect();
-
-        let chat_filter = {
-            let chat_ids: Vec<i64> = telegram_bindings
-                .iter()
-                .filter_map(|b| b.chat_id.as_ref()?.parse::<i64>().ok())
-                .collect();
-            if chat_ids.is_empty() {
-                None
-            } else {
-                Some(chat_ids)
-            }
-        };
+fn parse_otlp_headers(value: Option<String>) -> Result<HashMap<String, String>> {
+    let Some(raw) = value else {
+        return Ok(HashMap::new());
+    };
 
-        let mut dm_allowed_users: Vec<i64> = telegram
-            .dm_allowed_users
-            .iter()
-            .filter_map(|id| id.parse::<i64>().ok())
-            .collect();
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(HashMap::new());
+    }
 
-        for binding in &telegram_bindings {
-            for id in &binding.dm_allowed_users {
-                if let Ok(uid) = id.parse::<i64>()
-                    && !dm_allowed_users.contains(&uid)
-                {
-                    dm_allowed_users.push(uid);
-                }
-            }
+    let mut headers = HashMap::new();
+    for entry in raw.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
         }
-
-        Self {
-            chat_filter,
-            dm_allowed_users,
+        let Some((key, value)) = entry.split_once('=') else {
+            return Err(ConfigError::Invalid(format!(
+                "invalid OTEL_EXPORTER_OTLP_HEADERS entry '{entry}', expected key=value"
+            )))?;
+        };
+        let key = key.trim();
+        let value = value.trim();
+        if key.is_empty() {
+            Err(ConfigError::Invalid(
+                "invalid OTEL_EXPORTER_OTLP_HEADERS entry: empty header name".into(),
+            ))?;
         }
+        headers.insert(key.to_string(), value.to_string());
     }
+
+    Ok(headers)
 }
 
-#[derive(Clone)]
-pub struct TwitchConfig {
-    pub enabled: bool,
-    pub username: String,
-    pub oauth_token: String,
-    pub client_id: Option<String>,
-    pub client_secret: Option<String>,
-    pub refresh_token: Option<String>,
-    /// Channels to join (without the # prefix).
-    pub channels: Vec<String>,
-    /// Optional prefix that triggers the bot (e.g. "!ask"). If empty, all messages are processed.
-    pub trigger_prefix: Option<String>,
+fn parse_rbac_role(value: &str, context: &str) -> Result<crate::rbac::Role> {
+    crate::rbac::Role::parse(value).ok_or_else(|| {
+        ConfigError::Invalid(format!(
+            "{context} has invalid role '{value}', expected 'owner', 'admin', 'member' or 'guest'"
+        ))
+        .into()
+    })
 }
 
-impl std::fmt::Debug for TwitchConfig {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("TwitchConfig")
-            .field("enabled", &self.enabled)
-            .field("username", &self.username)
-            .field("oauth_token", &"[REDACTED]")
-            .field("channels", &self.channels)
-            .field("trigger_prefix", &self.trigger_prefix)
-            .finish()
+fn parse_sandbox_config(
+    raw: TomlSandboxConfig,
+    base: &SandboxConfig,
+    context: &str,
+) -> Result<SandboxConfig> {
+    let backend = match raw.backend.as_deref() {
+        Some(value) => SandboxBackend::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.backend has invalid value '{value}', expected 'host', 'docker' or 'podman'"
+            ))
+        })?,
+        None => base.backend,
+    };
+
+    if let Some(cpus) = raw.cpus
+        && cpus <= 0.0
+    {
+        return Err(ConfigError::Invalid(format!("{context}.cpus must be greater than 0")).into());
     }
-}
 
-/// Hot-reloadable Twitch permission filters.
-///
-/// Shared with the Twitch adapter via `Arc<ArcSwap<..>>` for hot-reloading.
-#[derive(Debug, Clone, Default)]
-pub struct TwitchPermissions {
-    /// Allowed channel names (None = all joined channels accepted).
-    pub channel_filter: Option<Vec<String>>,
-    /// User login names allowed to interact with the bot. Empty = all users.
-    pub allowed_users: Vec<String>,
+    Ok(SandboxConfig {
+        backend,
+        image: raw.image.unwrap_or_else(|| base.image.clone()),
+        network: raw.network.unwrap_or_else(|| base.network.clone()),
+        memory_limit: raw.memory_limit.or_else(|| base.memory_limit.clone()),
+        cpus: raw.cpus.or(base.cpus),
+        pids_limit: raw.pids_limit.or(base.pids_limit),
+    })
 }
 
-impl TwitchPermissions {
-    /// Build from the current config's twitch settings and bindings.
-    pub fn from_config(_twitch: &TwitchConfig, bindings: &[Binding]) -> Self {
-        let twitch_bindings: Vec<&Binding> =
-            bindings.iter().filter(|b| b.channel == "twitch").collect();
+fn parse_prompt_injection_config(
+    raw: TomlPromptInjectionConfig,
+    base: &PromptInjectionConfig,
+    context: &str,
+) -> Result<PromptInjectionConfig> {
+    let mode = match raw.mode.as_deref() {
+        Some(value) => PromptInjectionMode::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.mode has invalid value '{value}', expected 'off', 'flag' or 'neutralize'"
+            ))
+        })?,
+        None => base.mode,
+    };
 
-        let channel_filter = {
-            let channel_ids: Vec<String> = twitch_bindings
-                .iter()
-                .flat_map(|b| b.channel_ids.clone())
-                .collect();
-            if channel_ids.is_empty() {
-                None
-            } else {
-                Some(channel_ids)
-            }
-        };
+    let classifier_threshold = raw
+        .classifier_threshold
+        .unwrap_or(base.classifier_threshold);
+    if !(0.0..=1.0).contains(&classifier_threshold) {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.classifier_threshold must be between 0.0 and 1.0"
+        ))
+        .into());
+    }
 
-        let mut allowed_users: Vec<String> = Vec::new();
-        for binding in &twitch_bindings {
-            for id in &binding.dm_allowed_users {
-                if !allowed_users.contains(id) {
-                    allowed_users.push(id.clone());
-                }
-            }
+    Ok(PromptInjectionConfig {
+        mode,
+        classifier_url: raw
+            .classifier_url
+            .as_deref()
+            .and_then(resolve_env_value)
+            .or_else(|| base.classifier_url.clone()),
+        classifier_threshold,
+    })
+}
+
+fn parse_egress_config(
+    raw: TomlEgressConfig,
+    base: &EgressConfig,
+    context: &str,
+) -> Result<EgressConfig> {
+    let allowed_networks = raw
+        .allowed_networks
+        .unwrap_or_else(|| base.allowed_networks.clone());
+    let denied_networks = raw
+        .denied_networks
+        .unwrap_or_else(|| base.denied_networks.clone());
+    for network in allowed_networks.iter().chain(&denied_networks) {
+        if crate::egress::IpNetwork::parse(network).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "{context} has invalid network '{network}', expected CIDR notation like '10.0.0.0/8'"
+            ))
+            .into());
         }
+    }
 
-        Self {
-            channel_filter,
-            allowed_users,
+    let scopes = raw.scopes.unwrap_or_else(|| base.scopes.clone());
+    for scope in scopes.keys() {
+        if crate::egress::EgressScope::parse(scope).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "{context}.scopes has unknown scope '{scope}', expected 'attachments', 'web_search' or 'browser'"
+            ))
+            .into());
         }
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct WebhookConfig {
-    pub enabled: bool,
-    pub port: u16,
-    pub bind: String,
-    pub auth_token: Option<String>,
+    Ok(EgressConfig {
+        allowed_domains: raw
+            .allowed_domains
+            .unwrap_or_else(|| base.allowed_domains.clone()),
+        denied_domains: raw
+            .denied_domains
+            .unwrap_or_else(|| base.denied_domains.clone()),
+        block_private_ranges: raw
+            .block_private_ranges
+            .unwrap_or(base.block_private_ranges),
+        allowed_networks,
+        denied_networks,
+        scopes,
+    })
 }
 
-// -- TOML deserialization types --
+fn parse_database_config(
+    raw: TomlDatabaseConfig,
+    base: &DatabaseConfig,
+    instance_dir: &Path,
+    context: &str,
+) -> Result<DatabaseConfig> {
+    let encryption_key = match raw.encryption_key.as_deref() {
+        Some(value) if value.starts_with("secret:") => {
+            Some(resolve_secret_value(value, instance_dir).map_err(|error| {
+                ConfigError::Invalid(format!("{context}.encryption_key: {error:#}"))
+            })?)
+        }
+        Some(value) => Some(resolve_env_value(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.encryption_key references an unset environment variable"
+            ))
+        })?),
+        None => base.encryption_key.clone(),
+    };
 
-#[derive(Deserialize)]
-struct TomlConfig {
-    #[serde(default)]
-    llm: TomlLlmConfig,
-    #[serde(default)]
-    defaults: TomlDefaultsConfig,
-    #[serde(default)]
-    agents: Vec<TomlAgentConfig>,
-    #[serde(default)]
-    messaging: TomlMessagingConfig,
-    #[serde(default)]
-    bindings: Vec<TomlBinding>,
-    #[serde(default)]
-    api: TomlApiConfig,
-    #[serde(default)]
-    metrics: TomlMetricsConfig,
-    #[serde(default)]
-    telemetry: TomlTelemetryConfig,
-}
+    if encryption_key.as_deref().is_some_and(str::is_empty) {
+        return Err(
+            ConfigError::Invalid(format!("{context}.encryption_key must not be empty")).into(),
+        );
+    }
 
-#[derive(Deserialize, Default)]
-struct TomlTelemetryConfig {
-    otlp_endpoint: Option<String>,
-    otlp_headers: Option<String>,
-    service_name: Option<String>,
-    sample_rate: Option<f64>,
-}
+    let synchronous = match raw.synchronous.as_deref() {
+        Some(value) => SynchronousMode::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.synchronous has invalid value '{value}', expected 'off', 'normal', 'full' or 'extra'"
+            ))
+        })?,
+        None => base.synchronous,
+    };
+    let max_connections = raw.max_connections.unwrap_or(base.max_connections);
+    if max_connections == 0 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.max_connections must be at least 1")).into(),
+        );
+    }
 
-#[derive(Deserialize)]
-struct TomlApiConfig {
-    #[serde(default = "default_api_enabled")]
-    enabled: bool,
-    #[serde(default = "default_api_port")]
-    port: u16,
-    #[serde(default = "default_api_bind")]
-    bind: String,
-    #[serde(default)]
-    auth_token: Option<String>,
+    Ok(DatabaseConfig {
+        encryption_key,
+        wal: raw.wal.unwrap_or(base.wal),
+        synchronous,
+        busy_timeout_ms: raw.busy_timeout_ms.unwrap_or(base.busy_timeout_ms),
+        max_connections,
+    })
 }
 
-impl Default for TomlApiConfig {
-    fn default() -> Self {
-        Self {
-            enabled: default_api_enabled(),
-            port: default_api_port(),
-            bind: default_api_bind(),
-            auth_token: None,
-        }
+fn parse_session_config(raw: TomlSessionConfig) -> Result<SessionConfig> {
+    let defaults = SessionConfig::default();
+    let max_ttl_secs = raw.max_ttl_secs.unwrap_or(defaults.max_ttl_secs);
+    let ttl_secs = raw.ttl_secs.unwrap_or(defaults.ttl_secs.min(max_ttl_secs));
+
+    if ttl_secs == 0 || ttl_secs > max_ttl_secs {
+        return Err(ConfigError::Invalid(format!(
+            "messaging.sessions.ttl_secs must be between 1 and max_ttl_secs ({max_ttl_secs})"
+        ))
+        .into());
     }
-}
 
-fn default_api_enabled() -> bool {
-    true
-}
-fn default_api_port() -> u16 {
-    19898
-}
-fn default_api_bind() -> String {
-    "127.0.0.1".into()
+    Ok(SessionConfig {
+        ttl_secs,
+        max_ttl_secs,
+    })
 }
 
-fn hosted_api_bind(bind: String) -> String {
-    match std::env::var("SPACEBOT_DEPLOYMENT") {
-        Ok(deployment) if deployment.eq_ignore_ascii_case("hosted") => "[::]".into(),
-        _ => bind,
+fn parse_pairing_config(raw: TomlPairingConfig) -> Result<PairingConfig> {
+    let defaults = PairingConfig::default();
+    let control_channel = raw
+        .control_channel
+        .map(|value| {
+            crate::messaging::target::parse_delivery_target(&value).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "messaging.pairing.control_channel '{value}' must be an adapter:target"
+                ))
+            })
+        })
+        .transpose()?;
+    let code_ttl_secs = raw.code_ttl_secs.unwrap_or(defaults.code_ttl_secs);
+
+    if code_ttl_secs == 0 {
+        return Err(ConfigError::Invalid(
+            "messaging.pairing.code_ttl_secs must be greater than 0".into(),
+        )
+        .into());
+    }
+    if let Some(approver) = raw
+        .approvers
+        .iter()
+        .find(|approver| !approver.contains(':'))
+    {
+        return Err(ConfigError::Invalid(format!(
+            "messaging.pairing.approvers has invalid id '{approver}', expected 'platform:sender_id'"
+        ))
+        .into());
     }
-}
 
-#[derive(Deserialize)]
-struct TomlMetricsConfig {
-    #[serde(default)]
-    enabled: bool,
-    #[serde(default = "default_metrics_port")]
-    port: u16,
-    #[serde(default = "default_metrics_bind")]
-    bind: String,
+    Ok(PairingConfig {
+        enabled: raw.enabled.unwrap_or(defaults.enabled),
+        control_channel,
+        code_ttl_secs,
+        approvers: raw.approvers,
+    })
 }
 
-impl Default for TomlMetricsConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            port: default_metrics_port(),
-            bind: default_metrics_bind(),
-        }
+fn parse_event_webhook(
+    raw: TomlEventWebhook,
+    index: usize,
+) -> Result<crate::event_webhooks::EventWebhook> {
+    use crate::event_webhooks::{EventKind, EventWebhook};
+
+    let context = format!("event_webhooks[{index}]");
+    let url = resolve_env_value(&raw.url).ok_or_else(|| {
+        ConfigError::Invalid(format!(
+            "{context}.url '{}' references an unset environment variable",
+            raw.url
+        ))
+    })?;
+    if !(url.starts_with("https://") || url.starts_with("http://"))
+        || reqwest::Url::parse(&url).is_err()
+    {
+        return Err(ConfigError::Invalid(format!("{context}.url must be an http(s) URL")).into());
     }
-}
 
-fn default_metrics_port() -> u16 {
-    9090
-}
-fn default_metrics_bind() -> String {
-    "0.0.0.0".into()
-}
+    let secret = match raw.secret {
+        Some(value) => Some(resolve_env_value(&value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.secret '{value}' references an unset environment variable"
+            ))
+        })?),
+        None => None,
+    };
 
-#[derive(Deserialize, Debug)]
-struct TomlProviderConfig {
-    api_type: ApiType,
-    base_url: String,
-    api_key: String,
-    name: Option<String>,
-}
+    let events = if raw.events.is_empty() {
+        EventKind::ALL.to_vec()
+    } else {
+        raw.events
+            .iter()
+            .map(|event| {
+                EventKind::parse(event).ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "{context}.events entry '{event}' must be worker_completed, memory_saved or error"
+                    ))
+                })
+            })
+            .collect::<std::result::Result<_, _>>()?
+    };
 
-#[derive(Deserialize, Default)]
-struct TomlLlmConfigFields {
-    anthropic_key: Option<String>,
-    openai_key: Option<String>,
-    openrouter_key: Option<String>,
-    zhipu_key: Option<String>,
-    groq_key: Option<String>,
-    together_key: Option<String>,
-    fireworks_key: Option<String>,
-    deepseek_key: Option<String>,
-    xai_key: Option<String>,
-    mistral_key: Option<String>,
-    gemini_key: Option<String>,
-    ollama_key: Option<String>,
-    ollama_base_url: Option<String>,
-    opencode_zen_key: Option<String>,
-    nvidia_key: Option<String>,
-    minimax_key: Option<String>,
-    minimax_cn_key: Option<String>,
-    moonshot_key: Option<String>,
-    zai_coding_plan_key: Option<String>,
-    #[serde(default)]
-    providers: HashMap<String, TomlProviderConfig>,
-    #[serde(default)]
-    #[serde(flatten)]
-    extra: HashMap<String, toml::Value>,
+    let max_attempts = raw.max_attempts.unwrap_or(5);
+    if max_attempts == 0 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.max_attempts must be at least 1")).into(),
+        );
+    }
+
+    Ok(EventWebhook {
+        url,
+        secret,
+        events,
+        agents: raw.agents,
+        max_attempts,
+    })
 }
 
-#[derive(Default)]
-struct TomlLlmConfig {
-    anthropic_key: Option<String>,
-    openai_key: Option<String>,
-    openrouter_key: Option<String>,
-    zhipu_key: Option<String>,
-    groq_key: Option<String>,
-    together_key: Option<String>,
-    fireworks_key: Option<String>,
-    deepseek_key: Option<String>,
-    xai_key: Option<String>,
-    mistral_key: Option<String>,
-    gemini_key: Option<String>,
-    ollama_key: Option<String>,
-    ollama_base_url: Option<String>,
-    opencode_zen_key: Option<String>,
-    nvidia_key: Option<String>,
-    minimax_key: Option<String>,
-    minimax_cn_key: Option<String>,
-    moonshot_key: Option<String>,
-    zai_coding_plan_key: Option<String>,
-    providers: HashMap<String, TomlProviderConfig>,
+fn parse_custom_adapter_config(
+    name: String,
+    raw: TomlCustomAdapterConfig,
+) -> Result<CustomAdapterConfig> {
+    if BUILTIN_ADAPTERS.contains(&name.as_str()) {
+        return Err(ConfigError::Invalid(format!(
+            "messaging.custom.{name} shadows the built-in {name} adapter"
+        ))
+        .into());
+    }
+
+    Ok(CustomAdapterConfig {
+        name,
+        enabled: raw.enabled,
+        settings: raw.settings,
+    })
 }
 
-impl<'de> Deserialize<'de> for TomlLlmConfig {
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let mut fields = TomlLlmConfigFields::deserialize(deserializer)?;
-        let mut providers = fields.providers;
+fn parse_cluster_config(raw: TomlClusterConfig) -> Result<crate::cluster::ClusterConfig> {
+    let defaults = crate::cluster::ClusterConfig::default();
 
-        for (key, value) in fields.extra {
-            if key == "provider" {
-                let table = value
-                    .as_table()
-                    .ok_or_else(|| serde::de::Error::custom("`llm.provider` must be a table"))?;
-                for (provider_id, provider_value) in table {
-                    let provider_config = provider_value
-                        .clone()
-                        .try_into()
-                        .map_err(serde::de::Error::custom)?;
-                    providers.insert(provider_id.to_string(), provider_config);
-                }
-            }
+    let database_url = match raw.database_url {
+        Some(value) => Some(resolve_env_value(&value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "cluster.database_url '{value}' references an unset environment variable"
+            ))
+        })?),
+        None => None,
+    };
+    if raw.enabled && database_url.is_none() {
+        return Err(ConfigError::Invalid(
+            "cluster.database_url is required when cluster.enabled is true".into(),
+        )
+        .into());
+    }
 
-            if let Some(provider_id) = key.strip_prefix("provider.") {
-                let provider_config = value.try_into().map_err(serde::de::Error::custom)?;
-                providers.insert(provider_id.to_string(), provider_config);
-            }
-        }
+    let heartbeat_interval_secs = raw
+        .heartbeat_interval_secs
+        .unwrap_or(defaults.heartbeat_interval_secs);
+    let lease_ttl_secs = raw.lease_ttl_secs.unwrap_or(defaults.lease_ttl_secs);
+    if heartbeat_interval_secs == 0 {
+        return Err(ConfigError::Invalid(
+            "cluster.heartbeat_interval_secs must be at least 1".into(),
+        )
+        .into());
+    }
+    // A lease has to survive a missed heartbeat, or a healthy node loses its
+    // conversations to a single slow round trip.
+    if lease_ttl_secs < heartbeat_interval_secs * 2 {
+        return Err(ConfigError::Invalid(format!(
+            "cluster.lease_ttl_secs must be at least twice heartbeat_interval_secs ({})",
+            heartbeat_interval_secs * 2
+        ))
+        .into());
+    }
 
-        fields.providers = providers;
+    Ok(crate::cluster::ClusterConfig {
+        enabled: raw.enabled,
+        node_id: raw
+            .node_id
+            .and_then(|value| resolve_env_value(&value))
+            .unwrap_or(defaults.node_id),
+        database_url,
+        shared_adapters: raw.shared_adapters.unwrap_or(defaults.shared_adapters),
+        heartbeat_interval_secs,
+        lease_ttl_secs,
+    })
+}
 
-        Ok(Self {
-            anthropic_key: fields.anthropic_key,
-            openai_key: fields.openai_key,
-            openrouter_key: fields.openrouter_key,
-            zhipu_key: fields.zhipu_key,
-            groq_key: fields.groq_key,
-            together_key: fields.together_key,
-            fireworks_key: fields.fireworks_key,
-            deepseek_key: fields.deepseek_key,
-            xai_key: fields.xai_key,
-            mistral_key: fields.mistral_key,
-            gemini_key: fields.gemini_key,
-            ollama_key: fields.ollama_key,
-            ollama_base_url: fields.ollama_base_url,
-            opencode_zen_key: fields.opencode_zen_key,
-            nvidia_key: fields.nvidia_key,
-            minimax_key: fields.minimax_key,
-            minimax_cn_key: fields.minimax_cn_key,
-            moonshot_key: fields.moonshot_key,
-            zai_coding_plan_key: fields.zai_coding_plan_key,
-            providers: fields.providers,
-        })
+fn parse_object_storage_config(
+    raw: TomlObjectStorageConfig,
+) -> Result<crate::object_storage::ObjectStorageConfig> {
+    let defaults = crate::object_storage::ObjectStorageConfig::default();
+
+    let resolve = |key: &str, value: Option<String>| -> Result<Option<String>> {
+        match value {
+            Some(value) => Ok(Some(resolve_env_value(&value).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "object_storage.{key} '{value}' references an unset environment variable"
+                ))
+            })?)),
+            None => Ok(None),
+        }
+    };
+    let bucket = resolve("bucket", raw.bucket)?.filter(|bucket| !bucket.is_empty());
+    let endpoint = resolve("endpoint", raw.endpoint)?;
+    let access_key_id = resolve("access_key_id", raw.access_key_id)?;
+    let secret_access_key = resolve("secret_access_key", raw.secret_access_key)?;
+    if access_key_id.is_some() != secret_access_key.is_some() {
+        return Err(ConfigError::Invalid(
+            "object_storage.access_key_id and object_storage.secret_access_key must be set together"
+                .into(),
+        )
+        .into());
+    }
+
+    let signed_url_ttl_secs = raw
+        .signed_url_ttl_secs
+        .unwrap_or(defaults.signed_url_ttl_secs);
+    if signed_url_ttl_secs == 0
+        || signed_url_ttl_secs > crate::object_storage::MAX_SIGNED_URL_TTL_SECS
+    {
+        return Err(ConfigError::Invalid(format!(
+            "object_storage.signed_url_ttl_secs must be between 1 and {} (7 days)",
+            crate::object_storage::MAX_SIGNED_URL_TTL_SECS
+        ))
+        .into());
     }
-}
 
-#[derive(Deserialize, Default)]
-struct TomlDefaultsConfig {
-    routing: Option<TomlRoutingConfig>,
-    max_concurrent_branches: Option<usize>,
-    max_concurrent_workers: Option<usize>,
-    max_turns: Option<usize>,
-    branch_max_turns: Option<usize>,
-    context_window: Option<usize>,
-    compaction: Option<TomlCompactionConfig>,
-    memory_persistence: Option<TomlMemoryPersistenceConfig>,
-    coalesce: Option<TomlCoalesceConfig>,
-    ingestion: Option<TomlIngestionConfig>,
-    cortex: Option<TomlCortexConfig>,
-    browser: Option<TomlBrowserConfig>,
-    #[serde(default)]
-    mcp: Vec<TomlMcpServerConfig>,
-    brave_search_key: Option<String>,
-    cron_timezone: Option<String>,
-    opencode: Option<TomlOpenCodeConfig>,
-    worker_log_mode: Option<String>,
-    memory_injection: Option<TomlMemoryInjectionConfig>,
+    Ok(crate::object_storage::ObjectStorageConfig {
+        bucket,
+        region: raw.region.unwrap_or(defaults.region),
+        // Self-hosted services rarely support virtual-hosted buckets.
+        path_style: raw.path_style.unwrap_or(endpoint.is_some()),
+        endpoint,
+        access_key_id,
+        secret_access_key,
+        prefix: raw.prefix.unwrap_or(defaults.prefix),
+        signed_url_ttl_secs,
+        max_upload_mb: raw.max_upload_mb.unwrap_or(defaults.max_upload_mb),
+    })
 }
 
-#[derive(Deserialize)]
-struct TomlMemoryInjectionConfig {
-    enabled: Option<bool>,
-    search_limit: Option<usize>,
-    contextual_min_score: Option<f32>,
-    context_window_depth: Option<usize>,
-    semantic_threshold: Option<f32>,
-    pinned_types: Option<Vec<String>>,
-    ambient_enabled: Option<bool>,
-    pinned_limit: Option<i64>,
-    pinned_sort: Option<String>,
-    max_total: Option<usize>,
-    max_injected_blocks_in_history: Option<usize>,
+fn parse_update_config(raw: TomlUpdateConfig) -> Result<crate::update::UpdateConfig> {
+    let Some(value) = raw.channel else {
+        return Ok(crate::update::UpdateConfig::default());
+    };
+    let channel = crate::update::UpdateChannel::parse(&value).ok_or_else(|| {
+        ConfigError::Invalid(format!(
+            "update.channel has invalid value '{value}', expected 'stable', 'beta' or 'nightly'"
+        ))
+    })?;
+    Ok(crate::update::UpdateConfig { channel })
+}
+
+fn parse_middleware_config(
+    raw: TomlMiddlewareConfig,
+) -> Result<crate::messaging::middleware::MiddlewareConfig> {
+    use crate::messaging::middleware::{
+        MiddlewareConfig, ProfanityAction, ProfanityConfig, StripMentionsConfig,
+    };
+
+    let strip_mentions = raw
+        .strip_mentions
+        .filter(|raw| raw.enabled.unwrap_or(true))
+        .map(|raw| StripMentionsConfig {
+            names: raw.names,
+            sources: raw.sources,
+        });
+
+    let profanity = match raw.profanity.filter(|raw| raw.enabled.unwrap_or(true)) {
+        Some(raw) => {
+            if raw.words.is_empty() {
+                return Err(ConfigError::Invalid(
+                    "middleware.profanity.words must list at least one word".into(),
+                )
+                .into());
+            }
+            let action = match raw.action.as_deref() {
+                Some(value) => ProfanityAction::parse(value).ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "middleware.profanity.action must be 'mask' or 'block', got '{value}'"
+                    ))
+                })?,
+                None => ProfanityAction::default(),
+            };
+            Some(ProfanityConfig {
+                words: raw.words,
+                action,
+                message: raw.message.unwrap_or_default(),
+                sources: raw.sources,
+            })
+        }
+        None => None,
+    };
+
+    Ok(MiddlewareConfig {
+        strip_mentions,
+        profanity,
+    })
 }
 
-#[derive(Deserialize, Default)]
-struct TomlRoutingConfig {
-    channel: Option<String>,
-    branch: Option<String>,
-    worker: Option<String>,
-    compactor: Option<String>,
-    cortex: Option<String>,
-    voice: Option<String>,
-    rate_limit_cooldown_secs: Option<u64>,
-    channel_thinking_effort: Option<String>,
-    branch_thinking_effort: Option<String>,
-    worker_thinking_effort: Option<String>,
-    compactor_thinking_effort: Option<String>,
-    cortex_thinking_effort: Option<String>,
-    #[serde(default)]
-    task_overrides: HashMap<String, String>,
-    fallbacks: Option<HashMap<String, Vec<String>>>,
+fn parse_alert_config(raw: TomlAlertConfig) -> Result<crate::alerts::AlertConfig> {
+    let defaults = crate::alerts::AlertConfig::default();
+
+    let mut targets = Vec::with_capacity(raw.targets.len());
+    for value in raw.targets {
+        let resolved = resolve_env_value(&value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "alerts.targets entry '{value}' references an unset environment variable"
+            ))
+        })?;
+        let target = crate::alerts::AlertTarget::parse(&resolved).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "alerts.targets entry '{value}' must be an http(s) URL or 'adapter:target'"
+            ))
+        })?;
+        targets.push(target);
+    }
+
+    let adapter_failure_threshold = raw
+        .adapter_failure_threshold
+        .unwrap_or(defaults.adapter_failure_threshold);
+    if adapter_failure_threshold == 0 {
+        return Err(ConfigError::Invalid(
+            "alerts.adapter_failure_threshold must be at least 1".into(),
+        )
+        .into());
+    }
+
+    Ok(crate::alerts::AlertConfig {
+        targets,
+        cooldown_secs: raw.cooldown_secs.unwrap_or(defaults.cooldown_secs),
+        health_check_interval_secs: raw
+            .health_check_interval_secs
+            .unwrap_or(defaults.health_check_interval_secs)
+            .max(5),
+        adapter_failure_threshold,
+    })
 }
 
-#[derive(Deserialize)]
-struct TomlMemoryPersistenceConfig {
-    enabled: Option<bool>,
-    message_interval: Option<usize>,
+fn parse_spend_config(
+    raw: TomlSpendConfig,
+    base: &SpendConfig,
+    context: &str,
+) -> Result<SpendConfig> {
+    let spend = SpendConfig {
+        soft_limit_usd: raw.soft_limit_usd.or(base.soft_limit_usd),
+        hard_limit_usd: raw.hard_limit_usd.or(base.hard_limit_usd),
+        downgrade_model: raw.downgrade_model.or_else(|| base.downgrade_model.clone()),
+    };
+
+    for (key, limit) in [
+        ("soft_limit_usd", spend.soft_limit_usd),
+        ("hard_limit_usd", spend.hard_limit_usd),
+    ] {
+        if limit.is_some_and(|limit| !limit.is_finite() || limit <= 0.0) {
+            return Err(
+                ConfigError::Invalid(format!("{context}.{key} must be a positive amount")).into(),
+            );
+        }
+    }
+    if let (Some(soft), Some(hard)) = (spend.soft_limit_usd, spend.hard_limit_usd)
+        && soft > hard
+    {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.soft_limit_usd must not exceed hard_limit_usd"
+        ))
+        .into());
+    }
+
+    Ok(spend)
 }
 
-#[derive(Deserialize)]
-struct TomlCoalesceConfig {
-    enabled: Option<bool>,
-    debounce_ms: Option<u64>,
-    max_wait_ms: Option<u64>,
-    min_messages: Option<usize>,
-    multi_user_only: Option<bool>,
+fn parse_offline_config(
+    raw: TomlOfflineConfig,
+    base: &OfflineConfig,
+    context: &str,
+) -> Result<OfflineConfig> {
+    let offline = OfflineConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        max_queued_messages: raw.max_queued_messages.unwrap_or(base.max_queued_messages),
+        retry_interval_secs: raw.retry_interval_secs.unwrap_or(base.retry_interval_secs),
+        notice: raw.notice.unwrap_or_else(|| base.notice.clone()),
+    };
+
+    if offline.max_queued_messages == 0 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.max_queued_messages must be at least 1"
+        ))
+        .into());
+    }
+    if offline.retry_interval_secs == 0 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.retry_interval_secs must be at least 1"
+        ))
+        .into());
+    }
+
+    Ok(offline)
 }
 
-#[derive(Deserialize)]
-struct TomlIngestionConfig {
-    enabled: Option<bool>,
-    poll_interval_secs: Option<u64>,
-    chunk_size: Option<usize>,
+fn parse_disk_quota_config(
+    raw: TomlDiskQuotaConfig,
+    base: &DiskQuotaConfig,
+    context: &str,
+) -> Result<DiskQuotaConfig> {
+    let cleanup = match raw.cleanup.as_deref() {
+        Some(value) => DiskCleanup::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.cleanup has invalid value '{value}', expected 'oldest_first' or 'none'"
+            ))
+        })?,
+        None => base.cleanup,
+    };
+
+    let check_interval_secs = raw.check_interval_secs.unwrap_or(base.check_interval_secs);
+    if check_interval_secs == 0 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.check_interval_secs must be at least 1"
+        ))
+        .into());
+    }
+
+    Ok(DiskQuotaConfig {
+        workspace_limit_mb: raw.workspace_limit_mb.or(base.workspace_limit_mb),
+        screenshots_limit_mb: raw.screenshots_limit_mb.or(base.screenshots_limit_mb),
+        cleanup,
+        check_interval_secs,
+    })
 }
 
-#[derive(Deserialize)]
-struct TomlCompactionConfig {
-    background_threshold: Option<f32>,
-    aggressive_threshold: Option<f32>,
-    emergency_threshold: Option<f32>,
+fn parse_loop_guard_config(
+    raw: TomlLoopGuardConfig,
+    base: &LoopGuardConfig,
+    context: &str,
+) -> Result<LoopGuardConfig> {
+    let loop_guard = LoopGuardConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        max_identical_calls: raw.max_identical_calls.unwrap_or(base.max_identical_calls),
+        max_failing_calls: raw.max_failing_calls.unwrap_or(base.max_failing_calls),
+        max_spawns: raw.max_spawns.unwrap_or(base.max_spawns),
+        spawn_window_secs: raw.spawn_window_secs.unwrap_or(base.spawn_window_secs),
+    };
+
+    for (key, value, minimum) in [
+        ("max_identical_calls", loop_guard.max_identical_calls, 2),
+        ("max_failing_calls", loop_guard.max_failing_calls, 2),
+        ("max_spawns", loop_guard.max_spawns, 1),
+        (
+            "spawn_window_secs",
+            loop_guard.spawn_window_secs as usize,
+            1,
+        ),
+    ] {
+        if value < minimum {
+            return Err(ConfigError::Invalid(format!(
+                "{context}.{key} must be at least {minimum}"
+            ))
+            .into());
+        }
+    }
+
+    Ok(loop_guard)
+}
+
+fn parse_attachment_config(
+    raw: TomlAttachmentConfig,
+    base: &AttachmentConfig,
+    context: &str,
+) -> Result<AttachmentConfig> {
+    let attachments = AttachmentConfig {
+        max_concurrent_downloads: raw
+            .max_concurrent_downloads
+            .unwrap_or(base.max_concurrent_downloads),
+        max_file_size_mb: raw.max_file_size_mb.unwrap_or(base.max_file_size_mb),
+        cache_enabled: raw.cache_enabled.unwrap_or(base.cache_enabled),
+        cache_ttl_secs: raw.cache_ttl_secs.unwrap_or(base.cache_ttl_secs),
+        cache_max_size_mb: raw.cache_max_size_mb.unwrap_or(base.cache_max_size_mb),
+        inline_text_limit_kb: raw
+            .inline_text_limit_kb
+            .unwrap_or(base.inline_text_limit_kb),
+        summarize_large_text: raw
+            .summarize_large_text
+            .unwrap_or(base.summarize_large_text),
+        max_image_dimension: raw.max_image_dimension.unwrap_or(base.max_image_dimension),
+        max_image_size_kb: raw.max_image_size_kb.unwrap_or(base.max_image_size_kb),
+        thumbnails_enabled: raw.thumbnails_enabled.unwrap_or(base.thumbnails_enabled),
+        video_frames: raw.video_frames.unwrap_or(base.video_frames),
+    };
+
+    if attachments.max_concurrent_downloads == 0 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.max_concurrent_downloads must be at least 1"
+        ))
+        .into());
+    }
+    if attachments.max_file_size_mb == 0 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.max_file_size_mb must be at least 1")).into(),
+        );
+    }
+    if attachments.cache_max_size_mb == 0 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.cache_max_size_mb must be at least 1"
+        ))
+        .into());
+    }
+    if attachments.max_image_dimension < 256 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.max_image_dimension must be at least 256"
+        ))
+        .into());
+    }
+    if attachments.max_image_size_kb < 64 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.max_image_size_kb must be at least 64"
+        ))
+        .into());
+    }
+    if attachments.video_frames > 16 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.video_frames must be at most 16")).into(),
+        );
+    }
+
+    Ok(attachments)
 }
 
-#[derive(Deserialize)]
-struct TomlCortexConfig {
-    tick_interval_secs: Option<u64>,
-    worker_timeout_secs: Option<u64>,
-    branch_timeout_secs: Option<u64>,
-    circuit_breaker_threshold: Option<u8>,
-    bulletin_interval_secs: Option<u64>,
-    bulletin_max_words: Option<usize>,
-    bulletin_max_turns: Option<usize>,
-    association_interval_secs: Option<u64>,
-    association_similarity_threshold: Option<f32>,
-    association_updates_threshold: Option<f32>,
-    association_max_per_pass: Option<usize>,
+/// Fewest rows PQ training works with.
+const MIN_VECTOR_INDEX_ROWS: usize = 256;
+
+fn parse_vector_index_config(
+    raw: TomlVectorIndexConfig,
+    base: &VectorIndexConfig,
+    context: &str,
+) -> Result<VectorIndexConfig> {
+    let index_type = match raw.index_type.as_deref() {
+        Some(value) => VectorIndexType::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.index_type has invalid value '{value}', expected 'ivf_pq', 'ivf_hnsw_sq' or 'ivf_hnsw_pq'"
+            ))
+        })?,
+        None => base.index_type,
+    };
+    let vector_index = VectorIndexConfig {
+        index_type,
+        auto_index_threshold: raw
+            .auto_index_threshold
+            .unwrap_or(base.auto_index_threshold),
+        num_partitions: raw.num_partitions.or(base.num_partitions),
+        nprobes: raw.nprobes.unwrap_or(base.nprobes),
+        // 0 turns refinement off.
+        refine_factor: match raw.refine_factor {
+            Some(0) => None,
+            Some(factor) => Some(factor),
+            None => base.refine_factor,
+        },
+    };
+
+    if vector_index.auto_index_threshold < MIN_VECTOR_INDEX_ROWS {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.auto_index_threshold must be at least {MIN_VECTOR_INDEX_ROWS}"
+        ))
+        .into());
+    }
+    if vector_index.num_partitions == Some(0) {
+        return Err(
+            ConfigError::Invalid(format!("{context}.num_partitions must be at least 1")).into(),
+        );
+    }
+    if vector_index.nprobes == 0 {
+        return Err(ConfigError::Invalid(format!("{context}.nprobes must be at least 1")).into());
+    }
+
+    Ok(vector_index)
 }
 
-#[derive(Deserialize)]
-struct TomlBrowserConfig {
-    enabled: Option<bool>,
-    headless: Option<bool>,
-    evaluate_enabled: Option<bool>,
-    executable_path: Option<String>,
-    screenshot_dir: Option<String>,
+fn parse_plugin_config(
+    raw: TomlPluginConfig,
+    base: &PluginConfig,
+    context: &str,
+) -> Result<PluginConfig> {
+    let plugins = PluginConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        max_memory_mb: raw.max_memory_mb.unwrap_or(base.max_memory_mb),
+        fuel: raw.fuel.unwrap_or(base.fuel),
+        timeout_secs: raw.timeout_secs.unwrap_or(base.timeout_secs),
+    };
+
+    for (key, value) in [
+        ("max_memory_mb", plugins.max_memory_mb),
+        ("fuel", plugins.fuel),
+        ("timeout_secs", plugins.timeout_secs),
+    ] {
+        if value == 0 {
+            return Err(ConfigError::Invalid(format!("{context}.{key} must be at least 1")).into());
+        }
+    }
+
+    Ok(plugins)
 }
 
-#[derive(Deserialize)]
-struct TomlOpenCodeConfig {
-    enabled: Option<bool>,
-    path: Option<String>,
-    max_servers: Option<usize>,
-    server_startup_timeout_secs: Option<u64>,
-    max_restart_retries: Option<u32>,
-    permissions: Option<TomlOpenCodePermissions>,
+fn parse_scripting_config(
+    raw: TomlScriptingConfig,
+    base: &ScriptingConfig,
+    context: &str,
+) -> Result<ScriptingConfig> {
+    let scripting = ScriptingConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        max_operations: raw.max_operations.unwrap_or(base.max_operations),
+        max_actions: raw.max_actions.unwrap_or(base.max_actions),
+    };
+
+    if scripting.max_operations == 0 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.max_operations must be at least 1")).into(),
+        );
+    }
+
+    Ok(scripting)
 }
 
-#[derive(Deserialize)]
-struct TomlOpenCodePermissions {
-    edit: Option<String>,
-    bash: Option<String>,
-    webfetch: Option<String>,
+fn parse_task_def(raw: TomlTaskDef, agent_id: &str) -> Result<TaskDef> {
+    let context = format!("agents.{agent_id}.tasks.{}", raw.id);
+    let schedule = raw
+        .schedule
+        .parse()
+        .map_err(|error| ConfigError::Invalid(format!("{context}.schedule: {error}")))?;
+    let report_to = raw
+        .report_to
+        .map(|target| {
+            crate::messaging::target::parse_delivery_target(&target).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "{context}.report_to must be 'adapter:target', got '{target}'"
+                ))
+            })
+        })
+        .transpose()?;
+
+    Ok(TaskDef {
+        id: raw.id,
+        schedule,
+        task: raw.task,
+        report_to,
+        enabled: raw.enabled,
+    })
 }
 
-#[derive(Deserialize, Clone)]
-struct TomlMcpServerConfig {
-    name: String,
-    transport: String,
-    #[serde(default = "default_mcp_enabled")]
-    enabled: bool,
-    command: Option<String>,
-    #[serde(default)]
-    args: Vec<String>,
-    #[serde(default)]
-    env: HashMap<String, String>,
-    url: Option<String>,
-    #[serde(default)]
-    headers: HashMap<String, String>,
+fn parse_trigger_def(raw: TomlTriggerDef, agent_id: &str) -> Result<TriggerDef> {
+    let context = format!("agents.{agent_id}.triggers.{}", raw.id);
+    let source = match (raw.watch, raw.webhook_token, raw.every_secs) {
+        (Some(path), None, None) => {
+            let path = PathBuf::from(path);
+            if path.as_os_str().is_empty()
+                || !path
+                    .components()
+                    .all(|component| matches!(component, std::path::Component::Normal(_)))
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "{context}.watch must be a path inside the workspace, got '{}'",
+                    path.display()
+                ))
+                .into());
+            }
+            TriggerSource::Watch(path)
+        }
+        (None, Some(token), None) => {
+            let token = resolve_env_value(&token).unwrap_or_default();
+            if token.len() < 16 {
+                return Err(ConfigError::Invalid(format!(
+                    "{context}.webhook_token must be at least 16 characters"
+                ))
+                .into());
+            }
+            TriggerSource::Webhook { token }
+        }
+        (None, None, Some(every_secs)) => {
+            if every_secs < 60 {
+                return Err(ConfigError::Invalid(format!(
+                    "{context}.every_secs must be at least 60, got {every_secs}"
+                ))
+                .into());
+            }
+            TriggerSource::Timer { every_secs }
+        }
+        _ => {
+            return Err(ConfigError::Invalid(format!(
+                "{context} needs exactly one of watch, webhook_token or every_secs"
+            ))
+            .into());
+        }
+    };
+    let parse_target = |key: &str, target: Option<String>| {
+        target
+            .map(|target| {
+                crate::messaging::target::parse_delivery_target(&target).ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "{context}.{key} must be 'adapter:target', got '{target}'"
+                    ))
+                })
+            })
+            .transpose()
+    };
+    let report_to = parse_target("report_to", raw.report_to)?;
+    let notify = parse_target("notify", raw.notify)?;
+    let task = raw.task.filter(|task| !task.trim().is_empty());
+    if task.is_none() && notify.is_none() {
+        return Err(ConfigError::Invalid(format!("{context} needs a task, notify or both")).into());
+    }
+
+    Ok(TriggerDef {
+        id: raw.id,
+        source,
+        task,
+        report_to,
+        notify,
+        cooldown_secs: raw.cooldown_secs.unwrap_or(60),
+        enabled: raw.enabled,
+    })
 }
 
-fn default_mcp_enabled() -> bool {
-    true
+fn parse_voice_config(
+    raw: TomlVoiceConfig,
+    base: &VoiceConfig,
+    context: &str,
+) -> Result<VoiceConfig> {
+    let transcription = match raw.transcription.as_deref() {
+        Some(value) => TranscriptionBackend::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.transcription must be one of chat, api, whisper_cpp; got '{value}'"
+            ))
+        })?,
+        None => base.transcription,
+    };
+    let voice = VoiceConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        tts_model: raw.tts_model.unwrap_or_else(|| base.tts_model.clone()),
+        tts_voice: raw.tts_voice.unwrap_or_else(|| base.tts_voice.clone()),
+        speech_threshold: raw.speech_threshold.unwrap_or(base.speech_threshold),
+        silence_ms: raw.silence_ms.unwrap_or(base.silence_ms),
+        max_utterance_secs: raw.max_utterance_secs.unwrap_or(base.max_utterance_secs),
+        barge_in: raw.barge_in.unwrap_or(base.barge_in),
+        history_messages: raw.history_messages.unwrap_or(base.history_messages),
+        transcription,
+        whisper_cpp_binary: raw
+            .whisper_cpp_binary
+            .unwrap_or_else(|| base.whisper_cpp_binary.clone()),
+        whisper_cpp_model: raw
+            .whisper_cpp_model
+            .map(PathBuf::from)
+            .or_else(|| base.whisper_cpp_model.clone()),
+    };
+
+    if voice.transcription == TranscriptionBackend::WhisperCpp && voice.whisper_cpp_model.is_none()
+    {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.whisper_cpp_model is required with transcription = 'whisper_cpp'"
+        ))
+        .into());
+    }
+
+    if !voice.tts_model.contains('/') {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.tts_model must be 'provider/model', got '{}'",
+            voice.tts_model
+        ))
+        .into());
+    }
+    if !(0.0..1.0).contains(&voice.speech_threshold) {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.speech_threshold must be between 0.0 and 1.0"
+        ))
+        .into());
+    }
+    for (key, value) in [
+        ("silence_ms", voice.silence_ms),
+        ("max_utterance_secs", voice.max_utterance_secs),
+    ] {
+        if value == 0 {
+            return Err(ConfigError::Invalid(format!("{context}.{key} must be at least 1")).into());
+        }
+    }
+
+    Ok(voice)
+}
+
+fn parse_reaction_memory_config(
+    raw: TomlReactionMemoryConfig,
+    base: &ReactionMemoryConfig,
+    context: &str,
+) -> Result<ReactionMemoryConfig> {
+    let reaction_memory = ReactionMemoryConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        emoji: raw.emoji.unwrap_or_else(|| base.emoji.clone()),
+        confirm_emoji: raw
+            .confirm_emoji
+            .unwrap_or_else(|| base.confirm_emoji.clone()),
+        memory_type: raw.memory_type.unwrap_or(base.memory_type),
+    };
+
+    if reaction_memory.emoji.trim().is_empty() {
+        return Err(ConfigError::Invalid(format!("{context}.emoji must not be empty")).into());
+    }
+
+    Ok(reaction_memory)
 }
 
-#[derive(Deserialize)]
-struct TomlAgentConfig {
-    id: String,
-    #[serde(default)]
-    default: bool,
-    workspace: Option<String>,
-    routing: Option<TomlRoutingConfig>,
-    max_concurrent_branches: Option<usize>,
-    max_concurrent_workers: Option<usize>,
-    max_turns: Option<usize>,
-    branch_max_turns: Option<usize>,
-    context_window: Option<usize>,
-    compaction: Option<TomlCompactionConfig>,
-    memory_persistence: Option<TomlMemoryPersistenceConfig>,
-    coalesce: Option<TomlCoalesceConfig>,
-    ingestion: Option<TomlIngestionConfig>,
-    cortex: Option<TomlCortexConfig>,
-    browser: Option<TomlBrowserConfig>,
-    memory_injection: Option<TomlMemoryInjectionConfig>,
-    mcp: Option<Vec<TomlMcpServerConfig>>,
-    brave_search_key: Option<String>,
-    cron_timezone: Option<String>,
-    #[serde(default)]
-    cron: Vec<TomlCronDef>,
-}
+fn parse_reactions_config(
+    raw: TomlReactionsConfig,
+    base: &ReactionsConfig,
+    context: &str,
+) -> Result<ReactionsConfig> {
+    let reactions = ReactionsConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        approve: raw.approve.unwrap_or_else(|| base.approve.clone()),
+        deny: raw.deny.unwrap_or_else(|| base.deny.clone()),
+        retry: raw.retry.unwrap_or_else(|| base.retry.clone()),
+        ack_emoji: raw.ack_emoji.unwrap_or_else(|| base.ack_emoji.clone()),
+    };
 
-#[derive(Deserialize)]
-struct TomlCronDef {
-    id: String,
-    prompt: String,
-    interval_secs: Option<u64>,
-    delivery_target: String,
-    active_start_hour: Option<u8>,
-    active_end_hour: Option<u8>,
-    #[serde(default = "default_enabled")]
-    enabled: bool,
-    #[serde(default)]
-    run_once: bool,
-    timeout_secs: Option<u64>,
-}
+    let signals = [
+        ("approve", &reactions.approve),
+        ("deny", &reactions.deny),
+        ("retry", &reactions.retry),
+    ];
+    for (index, (key, emoji)) in signals.iter().enumerate() {
+        if emoji.iter().any(|emoji| emoji.trim().is_empty()) {
+            return Err(ConfigError::Invalid(format!(
+                "{context}.{key} must not contain empty reactions"
+            ))
+            .into());
+        }
+        for (other_key, other) in &signals[index + 1..] {
+            if let Some(shared) = emoji.iter().find(|emoji| other.contains(emoji)) {
+                return Err(ConfigError::Invalid(format!(
+                    "{context}: {shared} can't be in both {key} and {other_key}"
+                ))
+                .into());
+            }
+        }
+    }
 
-#[derive(Deserialize, Default)]
-struct TomlMessagingConfig {
-    discord: Option<TomlDiscordConfig>,
-    slack: Option<TomlSlackConfig>,
-    telegram: Option<TomlTelegramConfig>,
-    webhook: Option<TomlWebhookConfig>,
-    twitch: Option<TomlTwitchConfig>,
+    Ok(reactions)
 }
 
-#[derive(Deserialize)]
-struct TomlDiscordConfig {
-    #[serde(default)]
-    enabled: bool,
-    token: Option<String>,
-    #[serde(default)]
-    dm_allowed_users: Vec<String>,
-    #[serde(default)]
-    allow_bot_messages: bool,
-}
+fn parse_handoff_config(
+    raw: TomlHandoffConfig,
+    base: &HandoffConfig,
+    context: &str,
+) -> Result<HandoffConfig> {
+    let handoff = HandoffConfig {
+        targets: raw.targets.unwrap_or_else(|| base.targets.clone()),
+        history_messages: raw.history_messages.unwrap_or(base.history_messages),
+    };
 
-#[derive(Deserialize)]
-struct TomlSlackConfig {
-    #[serde(default)]
-    enabled: bool,
-    bot_token: Option<String>,
-    app_token: Option<String>,
-    #[serde(default)]
-    dm_allowed_users: Vec<String>,
-    #[serde(default)]
-    commands: Vec<TomlSlackCommandConfig>,
-}
+    if handoff
+        .targets
+        .iter()
+        .any(|target| target.trim().is_empty())
+    {
+        return Err(
+            ConfigError::Invalid(format!("{context}.targets must not contain empty IDs")).into(),
+        );
+    }
 
-#[derive(Deserialize)]
-struct TomlSlackCommandConfig {
-    command: String,
-    agent_id: String,
-    description: Option<String>,
+    Ok(handoff)
 }
 
-#[derive(Deserialize)]
-struct TomlTelegramConfig {
-    #[serde(default)]
-    enabled: bool,
-    token: Option<String>,
-    #[serde(default)]
-    dm_allowed_users: Vec<String>,
+fn parse_hibernation_config(
+    raw: TomlHibernationConfig,
+    base: &HibernationConfig,
+) -> HibernationConfig {
+    HibernationConfig {
+        idle_timeout_secs: raw.idle_timeout_secs.unwrap_or(base.idle_timeout_secs),
+    }
 }
 
-#[derive(Deserialize)]
-struct TomlWebhookConfig {
-    #[serde(default)]
-    enabled: bool,
-    #[serde(default = "default_webhook_port")]
-    port: u16,
-    #[serde(default = "default_webhook_bind")]
-    bind: String,
-    auth_token: Option<String>,
+fn parse_sub_workers_config(
+    raw: TomlSubWorkersConfig,
+    base: &SubWorkersConfig,
+) -> SubWorkersConfig {
+    SubWorkersConfig {
+        max_depth: raw.max_depth.unwrap_or(base.max_depth),
+        max_per_channel: raw.max_per_channel.unwrap_or(base.max_per_channel),
+    }
 }
 
-#[derive(Deserialize)]
-struct TomlTwitchConfig {
-    #[serde(default)]
-    enabled: bool,
-    username: Option<String>,
-    oauth_token: Option<String>,
-    client_id: Option<String>,
-    client_secret: Option<String>,
-    refresh_token: Option<String>,
-    #[serde(default)]
-    channels: Vec<String>,
-    trigger_prefix: Option<String>,
+fn parse_worker_budget_config(
+    raw: TomlWorkerBudgetConfig,
+    base: &WorkerBudgetConfig,
+) -> WorkerBudgetConfig {
+    WorkerBudgetConfig {
+        max_wall_time_secs: raw.max_wall_time_secs.unwrap_or(base.max_wall_time_secs),
+        max_tool_calls: raw.max_tool_calls.unwrap_or(base.max_tool_calls),
+        max_tokens: raw.max_tokens.unwrap_or(base.max_tokens),
+        max_cost_usd: raw.max_cost_usd.or(base.max_cost_usd),
+        wrap_up_secs: raw.wrap_up_secs.unwrap_or(base.wrap_up_secs),
+    }
 }
 
-fn default_webhook_port() -> u16 {
-    18789
-}
-fn default_webhook_bind() -> String {
-    "127.0.0.1".into()
-}
+fn parse_live_status_config(
+    raw: TomlLiveStatusConfig,
+    base: &LiveStatusConfig,
+    context: &str,
+) -> Result<LiveStatusConfig> {
+    let live_status = LiveStatusConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        delay_secs: raw.delay_secs.unwrap_or(base.delay_secs),
+        update_interval_secs: raw
+            .update_interval_secs
+            .unwrap_or(base.update_interval_secs),
+    };
 
-#[derive(Deserialize)]
-struct TomlBinding {
-    agent_id: String,
-    channel: String,
-    guild_id: Option<String>,
-    workspace_id: Option<String>,
-    chat_id: Option<String>,
-    #[serde(default)]
-    channel_ids: Vec<String>,
-    #[serde(default)]
-    require_mention: bool,
-    #[serde(default)]
-    dm_allowed_users: Vec<String>,
+    // Discord rate-limits message edits; faster refreshes only queue up.
+    if live_status.update_interval_secs < 5 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.update_interval_secs must be at least 5"
+        ))
+        .into());
+    }
+
+    Ok(live_status)
 }
 
-/// Resolve a value that might be an "env:VAR_NAME" reference.
-fn resolve_env_value(value: &str) -> Option<String> {
-    if let Some(var_name) = value.strip_prefix("env:") {
-        std::env::var(var_name).ok()
-    } else {
-        Some(value.to_string())
+fn parse_confirmations_config(
+    raw: TomlConfirmationsConfig,
+    base: &ConfirmationsConfig,
+    context: &str,
+) -> Result<ConfirmationsConfig> {
+    let confirmations = ConfirmationsConfig {
+        spawn_worker: raw.spawn_worker.unwrap_or(base.spawn_worker),
+        timeout_secs: raw.timeout_secs.unwrap_or(base.timeout_secs),
+    };
+
+    if confirmations.timeout_secs == 0 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.timeout_secs must be greater than 0")).into(),
+        );
     }
+
+    Ok(confirmations)
 }
 
-fn normalize_timezone(value: &str) -> Option<String> {
-    let timezone = value.trim();
-    if timezone.is_empty() {
-        return None;
+fn parse_presence_config(
+    raw: TomlPresenceConfig,
+    base: &PresenceConfig,
+    context: &str,
+) -> Result<PresenceConfig> {
+    let activity = match raw.activity.as_deref() {
+        Some(value) => crate::messaging::traits::ActivityKind::parse(value).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{context}.activity must be one of playing, listening, watching, competing, custom; got '{value}'"
+            ))
+        })?,
+        None => base.activity,
+    };
+    let presence = PresenceConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        activity,
+        busy_text: raw.busy_text.unwrap_or_else(|| base.busy_text.clone()),
+        idle_text: raw.idle_text.unwrap_or_else(|| base.idle_text.clone()),
+        update_interval_secs: raw
+            .update_interval_secs
+            .unwrap_or(base.update_interval_secs),
+    };
+
+    // Discord drops presence updates sent faster than a few per minute.
+    if presence.update_interval_secs < 5 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.update_interval_secs must be at least 5"
+        ))
+        .into());
     }
-    Some(timezone.to_string())
-}
 
-fn resolve_cron_timezone(
-    agent_id: &str,
-    agent_timezone: Option<&str>,
-    default_timezone: Option<&str>,
-) -> Option<String> {
-    let timezone = agent_timezone
-        .and_then(normalize_timezone)
-        .or_else(|| default_timezone.and_then(normalize_timezone))
-        .or_else(|| {
-            std::env::var(CRON_TIMEZONE_ENV_VAR)
-                .ok()
-                .and_then(|value| normalize_timezone(&value))
-        });
+    Ok(presence)
+}
 
-    let Some(timezone) = timezone else {
-        return None;
+fn parse_reflection_config(
+    raw: TomlReflectionConfig,
+    base: &ReflectionConfig,
+    context: &str,
+) -> Result<ReflectionConfig> {
+    let control_channel = match raw.control_channel {
+        Some(value) => Some(
+            crate::messaging::target::parse_delivery_target(&value).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "{context}.control_channel '{value}' must be an adapter:target"
+                ))
+            })?,
+        ),
+        None => base.control_channel.clone(),
+    };
+    let reflection = ReflectionConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        interval_secs: raw.interval_secs.unwrap_or(base.interval_secs),
+        max_messages: raw.max_messages.unwrap_or(base.max_messages),
+        control_channel,
     };
 
-    if timezone.parse::<Tz>().is_err() {
-        tracing::warn!(
-            agent_id,
-            cron_timezone = %timezone,
-            "invalid cron timezone configured, falling back to system local timezone"
+    if reflection.interval_secs < 3600 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.interval_secs must be at least 3600")).into(),
+        );
+    }
+    if reflection.max_messages < 1 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.max_messages must be at least 1")).into(),
         );
-        return None;
     }
 
-    Some(timezone)
+    Ok(reflection)
+}
+
+fn parse_dream_config(
+    raw: TomlDreamConfig,
+    base: &DreamConfig,
+    context: &str,
+) -> Result<DreamConfig> {
+    let dream = DreamConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        hour: raw.hour.unwrap_or(base.hour),
+        model: raw.model.or_else(|| base.model.clone()),
+        max_input_tokens: raw.max_input_tokens.unwrap_or(base.max_input_tokens),
+        max_output_tokens: raw.max_output_tokens.unwrap_or(base.max_output_tokens),
+        max_memories: raw.max_memories.unwrap_or(base.max_memories),
+    };
+
+    if dream.hour > 23 {
+        return Err(ConfigError::Invalid(format!(
+            "{context}.hour must be between 0 and 23, got {}",
+            dream.hour
+        ))
+        .into());
+    }
+    for (key, value) in [
+        ("max_input_tokens", dream.max_input_tokens as u64),
+        ("max_output_tokens", dream.max_output_tokens),
+    ] {
+        if value < 1000 {
+            return Err(
+                ConfigError::Invalid(format!("{context}.{key} must be at least 1000")).into(),
+            );
+        }
+    }
+
+    Ok(dream)
 }
 
-fn parse_otlp_headers(value: Option<String>) -> Result<HashMap<String, String>> {
-    let Some(raw) = value else {
-        return Ok(HashMap::new());
+fn parse_worker_queue_config(
+    raw: TomlWorkerQueueConfig,
+    base: &WorkerQueueConfig,
+    context: &str,
+) -> Result<WorkerQueueConfig> {
+    let dispatch = match raw.dispatch.as_deref() {
+        None => base.dispatch,
+        Some("fifo") => WorkerDispatch::Fifo,
+        Some("priority") => WorkerDispatch::Priority,
+        Some(other) => {
+            return Err(ConfigError::Invalid(format!(
+                "{context}.dispatch must be 'fifo' or 'priority', got '{other}'"
+            ))
+            .into());
+        }
+    };
+    let worker_queue = WorkerQueueConfig {
+        max_concurrent: raw.max_concurrent.unwrap_or(base.max_concurrent),
+        max_queued: raw.max_queued.unwrap_or(base.max_queued),
+        dispatch,
     };
 
-    let raw = raw.trim();
-    if raw.is_empty() {
-        return Ok(HashMap::new());
+    if worker_queue.max_concurrent < 1 {
+        return Err(
+            ConfigError::Invalid(format!("{context}.max_concurrent must be at least 1")).into(),
+        );
     }
 
-    let mut headers = HashMap::new();
-    for entry in raw.split(',') {
-        let entry = entry.trim();
-        if entry.is_empty() {
-            continue;
+    Ok(worker_queue)
+}
+
+fn parse_rate_limit_config(
+    raw: TomlRateLimitConfig,
+    base: &RateLimitConfig,
+    context: &str,
+) -> Result<RateLimitConfig> {
+    let rate_limit = RateLimitConfig {
+        enabled: raw.enabled.unwrap_or(base.enabled),
+        sender_burst: raw.sender_burst.unwrap_or(base.sender_burst),
+        sender_per_minute: raw.sender_per_minute.unwrap_or(base.sender_per_minute),
+        conversation_burst: raw.conversation_burst.unwrap_or(base.conversation_burst),
+        conversation_per_minute: raw
+            .conversation_per_minute
+            .unwrap_or(base.conversation_per_minute),
+        message: raw.message.unwrap_or_else(|| base.message.clone()),
+    };
+
+    for (key, value) in [
+        ("sender_burst", rate_limit.sender_burst),
+        ("sender_per_minute", rate_limit.sender_per_minute),
+        ("conversation_burst", rate_limit.conversation_burst),
+        (
+            "conversation_per_minute",
+            rate_limit.conversation_per_minute,
+        ),
+    ] {
+        if value == 0 {
+            return Err(
+                ConfigError::Invalid(format!("{context}.{key} must be greater than 0")).into(),
+            );
         }
-        let Some((key, value)) = entry.split_once('=') else {
-            return Err(ConfigError::Invalid(format!(
-                "invalid OTEL_EXPORTER_OTLP_HEADERS entry '{entry}', expected key=value"
-            )))?;
-        };
-        let key = key.trim();
-        let value = value.trim();
-        if key.is_empty() {
-            Err(ConfigError::Invalid(
-                "invalid OTEL_EXPORTER_OTLP_HEADERS entry: empty header name".into(),
-            ))?;
+    }
+
+    Ok(rate_limit)
+}
+
+fn parse_rbac_config(raw: TomlRbacConfig) -> Result<crate::rbac::RbacConfig> {
+    let default_role = match raw.default_role.as_deref() {
+        Some(value) => parse_rbac_role(value, "rbac.default_role")?,
+        None => crate::rbac::RbacConfig::default().default_role,
+    };
+
+    let mut users = Vec::with_capacity(raw.users.len());
+    for user in raw.users {
+        let role = parse_rbac_role(&user.role, &format!("rbac user '{}'", user.name))?;
+        for identity in &user.ids {
+            if !identity.contains(':') {
+                return Err(ConfigError::Invalid(format!(
+                    "rbac user '{}' has invalid id '{identity}', expected 'platform:sender_id'",
+                    user.name
+                ))
+                .into());
+            }
         }
-        headers.insert(key.to_string(), value.to_string());
+        users.push(crate::rbac::RbacUser {
+            name: user.name,
+            role,
+            identities: user.ids,
+        });
     }
 
-    Ok(headers)
+    let mut capabilities = HashMap::new();
+    for (name, role) in raw.capabilities {
+        let capability = crate::rbac::Capability::parse(&name).ok_or_else(|| {
+            ConfigError::Invalid(format!("rbac.capabilities has unknown capability '{name}'"))
+        })?;
+        let role = parse_rbac_role(&role, &format!("rbac capability '{name}'"))?;
+        capabilities.insert(capability, role);
+    }
+
+    Ok(crate::rbac::RbacConfig {
+        enabled: raw.enabled,
+        default_role,
+        users,
+        capabilities,
+    })
 }
 
 fn parse_mcp_server_config(raw: TomlMcpServerConfig) -> Result<McpServerConfig> {
@@ -1943,6 +5724,34 @@ fn parse_mcp_server_config(raw: TomlMcpServerConfig) -> Result<McpServerConfig>
     })
 }
 
+/// Resolve a branch or worker compaction table against its base. Both ratios
+/// must be in (0.0, 1.0]: a threshold of 0 would compact on every turn.
+fn resolve_process_compaction(
+    toml: Option<TomlProcessCompactionConfig>,
+    base: ProcessCompactionConfig,
+    context: &str,
+) -> Result<ProcessCompactionConfig> {
+    let Some(toml) = toml else {
+        return Ok(base);
+    };
+    let resolved = ProcessCompactionConfig {
+        threshold: toml.threshold.unwrap_or(base.threshold),
+        fraction: toml.fraction.unwrap_or(base.fraction),
+    };
+    for (name, value) in [
+        ("threshold", resolved.threshold),
+        ("fraction", resolved.fraction),
+    ] {
+        if !(value > 0.0 && value <= 1.0) {
+            return Err(ConfigError::Invalid(format!(
+                "{context}.{name} must be greater than 0.0 and at most 1.0"
+            ))
+            .into());
+        }
+    }
+    Ok(resolved)
+}
+
 /// Resolve a TomlRoutingConfig against a base RoutingConfig.
 fn resolve_routing(toml: Option<TomlRoutingConfig>, base: &RoutingConfig) -> RoutingConfig {
     let Some(t) = toml else { return base.clone() };
@@ -2302,6 +6111,32 @@ impl Config {
             ingestion: None,
             cortex: None,
             browser: None,
+            sandbox: None,
+            live_status: None,
+            confirmations: None,
+            presence: None,
+            reflection: None,
+            dream: None,
+            worker_queue: None,
+            rate_limit: None,
+            handoff: None,
+            hibernation: None,
+            sub_workers: None,
+            worker_budget: None,
+            reaction_memory: None,
+            reactions: None,
+            voice: None,
+            scripting: None,
+            plugins: None,
+            vector_index: None,
+            attachments: None,
+            loop_guard: None,
+            disk_quota: None,
+            offline: None,
+            spend: None,
+            database: None,
+            egress: None,
+            prompt_injection: None,
             memory_injection: None,
             mcp: None,
             brave_search_key: None,
@@ -2328,6 +6163,14 @@ impl Config {
                     .unwrap_or_else(|_| "spacebot".into()),
                 sample_rate: 1.0,
             },
+            rbac: crate::rbac::RbacConfig::default(),
+            alerts: crate::alerts::AlertConfig::default(),
+            event_webhooks: Vec::new(),
+            update: crate::update::UpdateConfig::default(),
+            cluster: crate::cluster::ClusterConfig::default(),
+            object_storage: crate::object_storage::ObjectStorageConfig::default(),
+            middleware: crate::messaging::middleware::MiddlewareConfig::default(),
+            shutdown: crate::shutdown::ShutdownConfig::default(),
         })
     }
 
@@ -2678,17 +6521,33 @@ impl Config {
             compaction: toml
                 .defaults
                 .compaction
-                .map(|c| CompactionConfig {
-                    background_threshold: c
-                        .background_threshold
-                        .unwrap_or(base_defaults.compaction.background_threshold),
-                    aggressive_threshold: c
-                        .aggressive_threshold
-                        .unwrap_or(base_defaults.compaction.aggressive_threshold),
-                    emergency_threshold: c
-                        .emergency_threshold
-                        .unwrap_or(base_defaults.compaction.emergency_threshold),
+                .map(|c| -> Result<CompactionConfig> {
+                    Ok(CompactionConfig {
+                        background_threshold: c
+                            .background_threshold
+                            .unwrap_or(base_defaults.compaction.background_threshold),
+                        aggressive_threshold: c
+                            .aggressive_threshold
+                            .unwrap_or(base_defaults.compaction.aggressive_threshold),
+                        emergency_threshold: c
+                            .emergency_threshold
+                            .unwrap_or(base_defaults.compaction.emergency_threshold),
+                        tool_output_max_chars: c
+                            .tool_output_max_chars
+                            .unwrap_or(base_defaults.compaction.tool_output_max_chars),
+                        branch: resolve_process_compaction(
+                            c.branch,
+                            base_defaults.compaction.branch,
+                            "defaults.compaction.branch",
+                        )?,
+                        worker: resolve_process_compaction(
+                            c.worker,
+                            base_defaults.compaction.worker,
+                            "defaults.compaction.worker",
+                        )?,
+                    })
                 })
+                .transpose()?
                 .unwrap_or(base_defaults.compaction),
             memory_persistence: toml
                 .defaults
@@ -2715,6 +6574,9 @@ impl Config {
                     multi_user_only: c
                         .multi_user_only
                         .unwrap_or(base_defaults.coalesce.multi_user_only),
+                    sender_debounce_ms: c
+                        .sender_debounce_ms
+                        .unwrap_or(base_defaults.coalesce.sender_debounce_ms),
                 })
                 .unwrap_or(base_defaults.coalesce),
             ingestion: toml
@@ -2765,6 +6627,27 @@ impl Config {
                     association_max_per_pass: c
                         .association_max_per_pass
                         .unwrap_or(base_defaults.cortex.association_max_per_pass),
+                    consolidation_idle_secs: c
+                        .consolidation_idle_secs
+                        .unwrap_or(base_defaults.cortex.consolidation_idle_secs),
+                    consolidation_interval_secs: c
+                        .consolidation_interval_secs
+                        .unwrap_or(base_defaults.cortex.consolidation_interval_secs),
+                    consolidation_merge_threshold: c
+                        .consolidation_merge_threshold
+                        .unwrap_or(base_defaults.cortex.consolidation_merge_threshold),
+                    consolidation_stale_days: c
+                        .consolidation_stale_days
+                        .unwrap_or(base_defaults.cortex.consolidation_stale_days),
+                    proactive_daily_limit: c
+                        .proactive_daily_limit
+                        .unwrap_or(base_defaults.cortex.proactive_daily_limit),
+                    runaway_repeat_limit: c
+                        .runaway_repeat_limit
+                        .unwrap_or(base_defaults.cortex.runaway_repeat_limit),
+                    queue_concurrency: c
+                        .queue_concurrency
+                        .unwrap_or(base_defaults.cortex.queue_concurrency),
                 })
                 .unwrap_or(base_defaults.cortex),
             browser: toml
@@ -2784,6 +6667,192 @@ impl Config {
                     }
                 })
                 .unwrap_or_else(|| base_defaults.browser.clone()),
+            sandbox: toml
+                .defaults
+                .sandbox
+                .map(|s| parse_sandbox_config(s, &base_defaults.sandbox, "defaults.sandbox"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.sandbox.clone()),
+            live_status: toml
+                .defaults
+                .live_status
+                .map(|raw| parse_live_status_config(raw, &base_defaults.live_status, "defaults.live_status"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.live_status.clone()),
+            confirmations: toml
+                .defaults
+                .confirmations
+                .map(|raw| {
+                    parse_confirmations_config(
+                        raw,
+                        &base_defaults.confirmations,
+                        "defaults.confirmations",
+                    )
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.confirmations.clone()),
+            presence: toml
+                .defaults
+                .presence
+                .map(|raw| parse_presence_config(raw, &base_defaults.presence, "defaults.presence"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.presence.clone()),
+            reflection: toml
+                .defaults
+                .reflection
+                .map(|raw| {
+                    parse_reflection_config(raw, &base_defaults.reflection, "defaults.reflection")
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.reflection.clone()),
+            dream: toml
+                .defaults
+                .dream
+                .map(|raw| parse_dream_config(raw, &base_defaults.dream, "defaults.dream"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.dream.clone()),
+            worker_queue: toml
+                .defaults
+                .worker_queue
+                .map(|raw| {
+                    parse_worker_queue_config(
+                        raw,
+                        &base_defaults.worker_queue,
+                        "defaults.worker_queue",
+                    )
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.worker_queue.clone()),
+            rate_limit: toml
+                .defaults
+                .rate_limit
+                .map(|raw| {
+                    parse_rate_limit_config(raw, &base_defaults.rate_limit, "defaults.rate_limit")
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.rate_limit.clone()),
+            handoff: toml
+                .defaults
+                .handoff
+                .map(|raw| {
+                    parse_handoff_config(raw, &base_defaults.handoff, "defaults.handoff")
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.handoff.clone()),
+            hibernation: toml
+                .defaults
+                .hibernation
+                .map(|raw| parse_hibernation_config(raw, &base_defaults.hibernation))
+                .unwrap_or_else(|| base_defaults.hibernation.clone()),
+            sub_workers: toml
+                .defaults
+                .sub_workers
+                .map(|raw| parse_sub_workers_config(raw, &base_defaults.sub_workers))
+                .unwrap_or_else(|| base_defaults.sub_workers.clone()),
+            worker_budget: toml
+                .defaults
+                .worker_budget
+                .map(|raw| parse_worker_budget_config(raw, &base_defaults.worker_budget))
+                .unwrap_or_else(|| base_defaults.worker_budget.clone()),
+            reaction_memory: toml
+                .defaults
+                .reaction_memory
+                .map(|raw| {
+                    parse_reaction_memory_config(
+                        raw,
+                        &base_defaults.reaction_memory,
+                        "defaults.reaction_memory",
+                    )
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.reaction_memory.clone()),
+            reactions: toml
+                .defaults
+                .reactions
+                .map(|raw| {
+                    parse_reactions_config(raw, &base_defaults.reactions, "defaults.reactions")
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.reactions.clone()),
+            voice: toml
+                .defaults
+                .voice
+                .map(|raw| parse_voice_config(raw, &base_defaults.voice, "defaults.voice"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.voice.clone()),
+            scripting: toml
+                .defaults
+                .scripting
+                .map(|raw| parse_scripting_config(raw, &base_defaults.scripting, "defaults.scripting"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.scripting.clone()),
+            plugins: toml
+                .defaults
+                .plugins
+                .map(|raw| parse_plugin_config(raw, &base_defaults.plugins, "defaults.plugins"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.plugins.clone()),
+            vector_index: toml
+                .defaults
+                .vector_index
+                .map(|raw| parse_vector_index_config(raw, &base_defaults.vector_index, "defaults.vector_index"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.vector_index.clone()),
+            attachments: toml
+                .defaults
+                .attachments
+                .map(|raw| parse_attachment_config(raw, &base_defaults.attachments, "defaults.attachments"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.attachments.clone()),
+            loop_guard: toml
+                .defaults
+                .loop_guard
+                .map(|raw| parse_loop_guard_config(raw, &base_defaults.loop_guard, "defaults.loop_guard"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.loop_guard.clone()),
+            disk_quota: toml
+                .defaults
+                .disk_quota
+                .map(|raw| parse_disk_quota_config(raw, &base_defaults.disk_quota, "defaults.disk_quota"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.disk_quota.clone()),
+            offline: toml
+                .defaults
+                .offline
+                .map(|raw| parse_offline_config(raw, &base_defaults.offline, "defaults.offline"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.offline.clone()),
+            spend: toml
+                .defaults
+                .spend
+                .map(|raw| parse_spend_config(raw, &base_defaults.spend, "defaults.spend"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.spend.clone()),
+            database: toml
+                .defaults
+                .database
+                .map(|raw| {
+                    parse_database_config(
+                        raw,
+                        &base_defaults.database,
+                        &instance_dir,
+                        "defaults.database",
+                    )
+                })
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.database.clone()),
+            egress: toml
+                .defaults
+                .egress
+                .map(|raw| parse_egress_config(raw, &base_defaults.egress, "defaults.egress"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.egress.clone()),
+            prompt_injection: toml
+                .defaults
+                .prompt_injection
+                .map(|raw| parse_prompt_injection_config(raw, &base_defaults.prompt_injection, "defaults.prompt_injection"))
+                .transpose()?
+                .unwrap_or_else(|| base_defaults.prompt_injection.clone()),
             mcp: default_mcp,
             brave_search_key: toml
                 .defaults
@@ -2917,6 +6986,283 @@ impl Config {
                     })
                     .collect();
 
+                let tasks = a
+                    .tasks
+                    .into_iter()
+                    .map(|raw| parse_task_def(raw, &a.id))
+                    .collect::<Result<Vec<_>>>()?;
+                let triggers = a
+                    .triggers
+                    .into_iter()
+                    .map(|raw| parse_trigger_def(raw, &a.id))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let agent_sandbox = a
+                    .sandbox
+                    .map(|s| {
+                        parse_sandbox_config(
+                            s,
+                            &defaults.sandbox,
+                            &format!("agents.{}.sandbox", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_prompt_injection = a
+                    .prompt_injection
+                    .map(|raw| {
+                        parse_prompt_injection_config(
+                            raw,
+                            &defaults.prompt_injection,
+                            &format!("agents.{}.prompt_injection", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_egress = a
+                    .egress
+                    .map(|raw| {
+                        parse_egress_config(
+                            raw,
+                            &defaults.egress,
+                            &format!("agents.{}.egress", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_database = a
+                    .database
+                    .map(|raw| {
+                        parse_database_config(
+                            raw,
+                            &defaults.database,
+                            &instance_dir,
+                            &format!("agents.{}.database", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_spend = a
+                    .spend
+                    .map(|raw| {
+                        parse_spend_config(
+                            raw,
+                            &defaults.spend,
+                            &format!("agents.{}.spend", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_offline = a
+                    .offline
+                    .map(|raw| {
+                        parse_offline_config(
+                            raw,
+                            &defaults.offline,
+                            &format!("agents.{}.offline", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_disk_quota = a
+                    .disk_quota
+                    .map(|raw| {
+                        parse_disk_quota_config(
+                            raw,
+                            &defaults.disk_quota,
+                            &format!("agents.{}.disk_quota", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_loop_guard = a
+                    .loop_guard
+                    .map(|raw| {
+                        parse_loop_guard_config(
+                            raw,
+                            &defaults.loop_guard,
+                            &format!("agents.{}.loop_guard", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_attachments = a
+                    .attachments
+                    .map(|raw| {
+                        parse_attachment_config(
+                            raw,
+                            &defaults.attachments,
+                            &format!("agents.{}.attachments", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_vector_index = a
+                    .vector_index
+                    .map(|raw| {
+                        parse_vector_index_config(
+                            raw,
+                            &defaults.vector_index,
+                            &format!("agents.{}.vector_index", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_plugins = a
+                    .plugins
+                    .map(|raw| {
+                        parse_plugin_config(
+                            raw,
+                            &defaults.plugins,
+                            &format!("agents.{}.plugins", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_scripting = a
+                    .scripting
+                    .map(|raw| {
+                        parse_scripting_config(
+                            raw,
+                            &defaults.scripting,
+                            &format!("agents.{}.scripting", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_voice = a
+                    .voice
+                    .map(|raw| {
+                        parse_voice_config(
+                            raw,
+                            &defaults.voice,
+                            &format!("agents.{}.voice", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_reaction_memory = a
+                    .reaction_memory
+                    .map(|raw| {
+                        parse_reaction_memory_config(
+                            raw,
+                            &defaults.reaction_memory,
+                            &format!("agents.{}.reaction_memory", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_reactions = a
+                    .reactions
+                    .map(|raw| {
+                        parse_reactions_config(
+                            raw,
+                            &defaults.reactions,
+                            &format!("agents.{}.reactions", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_handoff = a
+                    .handoff
+                    .map(|raw| {
+                        parse_handoff_config(
+                            raw,
+                            &defaults.handoff,
+                            &format!("agents.{}.handoff", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_hibernation = a
+                    .hibernation
+                    .map(|raw| parse_hibernation_config(raw, &defaults.hibernation));
+                let agent_sub_workers = a
+                    .sub_workers
+                    .map(|raw| parse_sub_workers_config(raw, &defaults.sub_workers));
+                let agent_worker_budget = a
+                    .worker_budget
+                    .map(|raw| parse_worker_budget_config(raw, &defaults.worker_budget));
+                let agent_live_status = a
+                    .live_status
+                    .map(|raw| {
+                        parse_live_status_config(
+                            raw,
+                            &defaults.live_status,
+                            &format!("agents.{}.live_status", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_confirmations = a
+                    .confirmations
+                    .map(|raw| {
+                        parse_confirmations_config(
+                            raw,
+                            &defaults.confirmations,
+                            &format!("agents.{}.confirmations", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_presence = a
+                    .presence
+                    .map(|raw| {
+                        parse_presence_config(
+                            raw,
+                            &defaults.presence,
+                            &format!("agents.{}.presence", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_reflection = a
+                    .reflection
+                    .map(|raw| {
+                        parse_reflection_config(
+                            raw,
+                            &defaults.reflection,
+                            &format!("agents.{}.reflection", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_dream = a
+                    .dream
+                    .map(|raw| {
+                        parse_dream_config(raw, &defaults.dream, &format!("agents.{}.dream", a.id))
+                    })
+                    .transpose()?;
+                let agent_worker_queue = a
+                    .worker_queue
+                    .map(|raw| {
+                        parse_worker_queue_config(
+                            raw,
+                            &defaults.worker_queue,
+                            &format!("agents.{}.worker_queue", a.id),
+                        )
+                    })
+                    .transpose()?;
+                let agent_compaction = a
+                    .compaction
+                    .map(|c| -> Result<CompactionConfig> {
+                        Ok(CompactionConfig {
+                            background_threshold: c
+                                .background_threshold
+                                .unwrap_or(defaults.compaction.background_threshold),
+                            aggressive_threshold: c
+                                .aggressive_threshold
+                                .unwrap_or(defaults.compaction.aggressive_threshold),
+                            emergency_threshold: c
+                                .emergency_threshold
+                                .unwrap_or(defaults.compaction.emergency_threshold),
+                            tool_output_max_chars: c
+                                .tool_output_max_chars
+                                .unwrap_or(defaults.compaction.tool_output_max_chars),
+                            branch: resolve_process_compaction(
+                                c.branch,
+                                defaults.compaction.branch,
+                                &format!("agents.{}.compaction.branch", a.id),
+                            )?,
+                            worker: resolve_process_compaction(
+                                c.worker,
+                                defaults.compaction.worker,
+                                &format!("agents.{}.compaction.worker", a.id),
+                            )?,
+                        })
+                    })
+                    .transpose()?;
+                let agent_rate_limit = a
+                    .rate_limit
+                    .map(|raw| {
+                        parse_rate_limit_config(
+                            raw,
+                            &defaults.rate_limit,
+                            &format!("agents.{}.rate_limit", a.id),
+                        )
+                    })
+                    .transpose()?;
+
                 Ok(AgentConfig {
                     id: a.id,
                     default: a.default,
@@ -2927,17 +7273,7 @@ impl Config {
                     max_turns: a.max_turns,
                     branch_max_turns: a.branch_max_turns,
                     context_window: a.context_window,
-                    compaction: a.compaction.map(|c| CompactionConfig {
-                        background_threshold: c
-                            .background_threshold
-                            .unwrap_or(defaults.compaction.background_threshold),
-                        aggressive_threshold: c
-                            .aggressive_threshold
-                            .unwrap_or(defaults.compaction.aggressive_threshold),
-                        emergency_threshold: c
-                            .emergency_threshold
-                            .unwrap_or(defaults.compaction.emergency_threshold),
-                    }),
+                    compaction: agent_compaction,
                     memory_persistence: a.memory_persistence.map(|mp| MemoryPersistenceConfig {
                         enabled: mp.enabled.unwrap_or(defaults.memory_persistence.enabled),
                         message_interval: mp
@@ -2952,6 +7288,9 @@ impl Config {
                         multi_user_only: c
                             .multi_user_only
                             .unwrap_or(defaults.coalesce.multi_user_only),
+                        sender_debounce_ms: c
+                            .sender_debounce_ms
+                            .unwrap_or(defaults.coalesce.sender_debounce_ms),
                     }),
                     ingestion: a.ingestion.map(|ig| IngestionConfig {
                         enabled: ig.enabled.unwrap_or(defaults.ingestion.enabled),
@@ -2994,6 +7333,27 @@ impl Config {
                         association_max_per_pass: c
                             .association_max_per_pass
                             .unwrap_or(defaults.cortex.association_max_per_pass),
+                        consolidation_idle_secs: c
+                            .consolidation_idle_secs
+                            .unwrap_or(defaults.cortex.consolidation_idle_secs),
+                        consolidation_interval_secs: c
+                            .consolidation_interval_secs
+                            .unwrap_or(defaults.cortex.consolidation_interval_secs),
+                        consolidation_merge_threshold: c
+                            .consolidation_merge_threshold
+                            .unwrap_or(defaults.cortex.consolidation_merge_threshold),
+                        consolidation_stale_days: c
+                            .consolidation_stale_days
+                            .unwrap_or(defaults.cortex.consolidation_stale_days),
+                        proactive_daily_limit: c
+                            .proactive_daily_limit
+                            .unwrap_or(defaults.cortex.proactive_daily_limit),
+                        runaway_repeat_limit: c
+                            .runaway_repeat_limit
+                            .unwrap_or(defaults.cortex.runaway_repeat_limit),
+                        queue_concurrency: c
+                            .queue_concurrency
+                            .unwrap_or(defaults.cortex.queue_concurrency),
                     }),
                     browser: a.browser.map(|b| BrowserConfig {
                         enabled: b.enabled.unwrap_or(defaults.browser.enabled),
@@ -3009,6 +7369,32 @@ impl Config {
                             .map(PathBuf::from)
                             .or_else(|| defaults.browser.screenshot_dir.clone()),
                     }),
+                    sandbox: agent_sandbox,
+                    live_status: agent_live_status,
+                    confirmations: agent_confirmations,
+                    presence: agent_presence,
+                    reflection: agent_reflection,
+                    dream: agent_dream,
+                    worker_queue: agent_worker_queue,
+                    rate_limit: agent_rate_limit,
+                    handoff: agent_handoff,
+                    hibernation: agent_hibernation,
+                    sub_workers: agent_sub_workers,
+                    worker_budget: agent_worker_budget,
+                    reaction_memory: agent_reaction_memory,
+                    reactions: agent_reactions,
+                    voice: agent_voice,
+                    scripting: agent_scripting,
+                    plugins: agent_plugins,
+                    vector_index: agent_vector_index,
+                    attachments: agent_attachments,
+                    loop_guard: agent_loop_guard,
+                    disk_quota: agent_disk_quota,
+                    offline: agent_offline,
+                    spend: agent_spend,
+                    database: agent_database,
+                    egress: agent_egress,
+                    prompt_injection: agent_prompt_injection,
                     memory_injection: a.memory_injection.map(|mi| {
                         let base = &defaults.memory_injection;
                         let pinned_types = mi
@@ -3070,6 +7456,8 @@ impl Config {
                     brave_search_key: a.brave_search_key.as_deref().and_then(resolve_env_value),
                     cron_timezone: a.cron_timezone.as_deref().and_then(resolve_env_value),
                     cron,
+                    tasks,
+                    triggers,
                 })
             })
             .collect::<Result<Vec<_>>>()?;
@@ -3091,11 +7479,39 @@ impl Config {
                 ingestion: None,
                 cortex: None,
                 browser: None,
+                sandbox: None,
+                live_status: None,
+                confirmations: None,
+                presence: None,
+                reflection: None,
+                dream: None,
+                worker_queue: None,
+                rate_limit: None,
+                handoff: None,
+                hibernation: None,
+                sub_workers: None,
+                worker_budget: None,
+                reaction_memory: None,
+                reactions: None,
+                voice: None,
+                scripting: None,
+                plugins: None,
+                vector_index: None,
+                attachments: None,
+                loop_guard: None,
+                disk_quota: None,
+                offline: None,
+                spend: None,
+                database: None,
+                egress: None,
+                prompt_injection: None,
                 memory_injection: None,
                 mcp: None,
                 brave_search_key: None,
                 cron_timezone: None,
                 cron: Vec::new(),
+                tasks: Vec::new(),
+                triggers: Vec::new(),
             });
         }
 
@@ -3105,6 +7521,21 @@ impl Config {
             first.default = true;
         }
 
+        for agent in &agents {
+            let handoff = agent.handoff.as_ref().unwrap_or(&defaults.handoff);
+            if let Some(unknown) = handoff
+                .targets
+                .iter()
+                .find(|target| !agents.iter().any(|candidate| &candidate.id == *target))
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "handoff target '{unknown}' of agent '{}' is not a configured agent",
+                    agent.id
+                ))
+                .into());
+            }
+        }
+
         let messaging = MessagingConfig {
             discord: toml.messaging.discord.and_then(|d| {
                 let token = d
@@ -3117,6 +7548,7 @@ impl Config {
                     token,
                     dm_allowed_users: d.dm_allowed_users,
                     allow_bot_messages: d.allow_bot_messages,
+                    voice: d.voice,
                 })
             }),
             slack: toml.messaging.slack.and_then(|s| {
@@ -3163,6 +7595,14 @@ impl Config {
                 port: w.port,
                 bind: w.bind,
                 auth_token: w.auth_token.as_deref().and_then(resolve_env_value),
+                signing_secret: w.signing_secret.as_deref().and_then(resolve_env_value),
+                sources: w
+                    .sources
+                    .into_iter()
+                    .filter_map(|(name, secret)| Some((name, resolve_env_value(&secret)?)))
+                    .collect(),
+                callback_url: w.callback_url.as_deref().and_then(resolve_env_value),
+                max_delivery_attempts: w.max_delivery_attempts.max(1),
             }),
             twitch: toml.messaging.twitch.and_then(|t| {
                 let username = t
@@ -3201,22 +7641,249 @@ impl Config {
                     trigger_prefix: t.trigger_prefix,
                 })
             }),
+            irc: toml.messaging.irc.and_then(|i| {
+                let server = i
+                    .server
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("IRC_SERVER").ok())?;
+                let nickname = i
+                    .nickname
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("IRC_NICKNAME").ok())?;
+                let password = i
+                    .password
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("IRC_PASSWORD").ok());
+                let nickserv_password = i
+                    .nickserv_password
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("IRC_NICKSERV_PASSWORD").ok());
+                Some(IrcConfig {
+                    enabled: i.enabled,
+                    server,
+                    port: i.port.unwrap_or(if i.use_tls { 6697 } else { 6667 }),
+                    use_tls: i.use_tls,
+                    nickname,
+                    password,
+                    nickserv_password,
+                    channels: i.channels,
+                    trigger_prefix: i.trigger_prefix,
+                })
+            }),
+            email: toml.messaging.email.and_then(|e| {
+                let imap_host = e
+                    .imap_host
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("EMAIL_IMAP_HOST").ok())?;
+                let smtp_host = e
+                    .smtp_host
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("EMAIL_SMTP_HOST").ok())?;
+                let username = e
+                    .username
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("EMAIL_USERNAME").ok())?;
+                let password = e
+                    .password
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("EMAIL_PASSWORD").ok())?;
+                let from_address = e
+                    .from_address
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .unwrap_or_else(|| username.clone());
+                Some(EmailConfig {
+                    enabled: e.enabled,
+                    imap_host,
+                    imap_port: e.imap_port,
+                    smtp_host,
+                    smtp_port: e.smtp_port,
+                    username,
+                    password,
+                    from_address,
+                    from_name: e.from_name,
+                    mailbox: e.mailbox,
+                    poll_interval_secs: e.poll_interval_secs.max(10),
+                })
+            }),
+            xmpp: toml.messaging.xmpp.and_then(|x| {
+                let jid = x
+                    .jid
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("XMPP_JID").ok())?;
+                let password = x
+                    .password
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("XMPP_PASSWORD").ok())?;
+                let nickname = x
+                    .nickname
+                    .unwrap_or_else(|| jid.split('@').next().unwrap_or(&jid).to_string());
+                Some(XmppConfig {
+                    enabled: x.enabled,
+                    jid,
+                    password,
+                    nickname,
+                    rooms: x.rooms,
+                })
+            }),
+            whatsapp: toml.messaging.whatsapp.and_then(|w| {
+                let phone_number_id = w
+                    .phone_number_id
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("WHATSAPP_PHONE_NUMBER_ID").ok())?;
+                let access_token = w
+                    .access_token
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("WHATSAPP_ACCESS_TOKEN").ok())?;
+                let verify_token = w
+                    .verify_token
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("WHATSAPP_VERIFY_TOKEN").ok())?;
+                let app_secret = w
+                    .app_secret
+                    .as_deref()
+                    .and_then(resolve_env_value)
+                    .or_else(|| std::env::var("WHATSAPP_APP_SECRET").ok())?;
+                Some(WhatsAppConfig {
+                    enabled: w.enabled,
+                    phone_number_id,
+                    access_token,
+                    verify_token,
+                    app_secret,
+                    port: w.port,
+                    bind: w.bind,
+                    api_version: w.api_version,
+                    template_name: w.template_name,
+                    template_language: w.template_language,
+                })
+            }),
+            custom: toml
+                .messaging
+                .custom
+                .into_iter()
+                .map(|(name, custom)| parse_custom_adapter_config(name, custom))
+                .collect::<Result<_>>()?,
+            sessions: toml
+                .messaging
+                .sessions
+                .map(parse_session_config)
+                .transpose()?
+                .unwrap_or_default(),
+            pairing: toml
+                .messaging
+                .pairing
+                .map(parse_pairing_config)
+                .transpose()?
+                .unwrap_or_default(),
         };
+        // Signing only covers `/send`. Sessions, polls and deliveries are
+        // guarded by the auth token alone and would be open without one.
+        if let Some(webhook) = &messaging.webhook
+            && webhook.enabled
+            && webhook.auth_token.is_none()
+            && (webhook.signing_secret.is_some() || !webhook.sources.is_empty())
+        {
+            return Err(ConfigError::Invalid(
+                "messaging.webhook.signing_secret and sources need auth_token set".into(),
+            )
+            .into());
+        }
 
         let bindings = toml
             .bindings
             .into_iter()
-            .map(|b| Binding {
-                agent_id: b.agent_id,
-                channel: b.channel,
-                guild_id: b.guild_id,
-                workspace_id: b.workspace_id,
-                chat_id: b.chat_id,
-                channel_ids: b.channel_ids,
-                require_mention: b.require_mention,
-                dm_allowed_users: b.dm_allowed_users,
+            .map(|b| {
+                if b.digest_interval_secs == Some(0) {
+                    return Err(ConfigError::Invalid(format!(
+                        "bindings.digest_interval_secs for agent '{}' must be at least 1",
+                        b.agent_id
+                    ))
+                    .into());
+                }
+                let activation = match b.activation.as_deref() {
+                    None if b.require_mention => Activation::Mention,
+                    None => Activation::Always,
+                    Some(value) => {
+                        let activation = Activation::parse(value).ok_or_else(|| {
+                            ConfigError::Invalid(format!(
+                                "bindings.activation for agent '{}' must be one of \
+                                 always, mention, wake_word, thread (got '{value}')",
+                                b.agent_id
+                            ))
+                        })?;
+                        if b.require_mention && activation != Activation::Mention {
+                            return Err(ConfigError::Invalid(format!(
+                                "bindings.require_mention for agent '{}' conflicts with \
+                                 activation = '{value}'",
+                                b.agent_id
+                            ))
+                            .into());
+                        }
+                        activation
+                    }
+                };
+                if activation == Activation::WakeWord && b.wake_words.is_empty() {
+                    return Err(ConfigError::Invalid(format!(
+                        "bindings.wake_words for agent '{}' can't be empty with \
+                         activation = 'wake_word'",
+                        b.agent_id
+                    ))
+                    .into());
+                }
+                let response_mode = match b.response_mode.as_deref() {
+                    None => ResponseMode::Typing,
+                    Some(value) => ResponseMode::parse(value).ok_or_else(|| {
+                        ConfigError::Invalid(format!(
+                            "bindings.response_mode for agent '{}' must be one of \
+                             typing, stream, final (got '{value}')",
+                            b.agent_id
+                        ))
+                    })?,
+                };
+                let compaction = b.compaction.unwrap_or_default();
+                let overrides = BindingOverrides {
+                    channel_model: b.channel_model,
+                    max_turns: b.max_turns,
+                    context_window: b.context_window,
+                    background_threshold: compaction.background_threshold,
+                    aggressive_threshold: compaction.aggressive_threshold,
+                    emergency_threshold: compaction.emergency_threshold,
+                };
+                overrides.validate().map_err(|error| {
+                    ConfigError::Invalid(format!("bindings for agent '{}': {error}", b.agent_id))
+                })?;
+                Ok(Binding {
+                    agent_id: b.agent_id,
+                    channel: b.channel,
+                    guild_id: b.guild_id,
+                    workspace_id: b.workspace_id,
+                    chat_id: b.chat_id,
+                    channel_ids: b.channel_ids,
+                    activation,
+                    wake_words: b.wake_words,
+                    reply_to_message: b.reply_to_message,
+                    dm_allowed_users: b.dm_allowed_users,
+                    digest_interval_secs: b.digest_interval_secs,
+                    response_mode,
+                    allow_proactive: b.allow_proactive,
+                    tags: b.tags,
+                    overrides,
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
         let api = ApiConfig {
             enabled: toml.api.enabled,
@@ -3254,6 +7921,33 @@ impl Config {
             }
         };
 
+        let rbac = parse_rbac_config(toml.rbac)?;
+        // Without RBAC everyone resolves to owner, so anyone in the control
+        // channel could approve codes, their own included.
+        if messaging.pairing.enabled && messaging.pairing.approvers.is_empty() && !rbac.enabled {
+            return Err(ConfigError::Invalid(
+                "messaging.pairing needs rbac enabled or messaging.pairing.approvers set".into(),
+            )
+            .into());
+        }
+        let alerts = parse_alert_config(toml.alerts)?;
+        let event_webhooks = toml
+            .event_webhooks
+            .into_iter()
+            .enumerate()
+            .map(|(index, raw)| parse_event_webhook(raw, index))
+            .collect::<Result<_>>()?;
+        let update = parse_update_config(toml.update)?;
+        let cluster = parse_cluster_config(toml.cluster)?;
+        let object_storage = parse_object_storage_config(toml.object_storage)?;
+        let middleware = parse_middleware_config(toml.middleware)?;
+        let shutdown = crate::shutdown::ShutdownConfig {
+            drain_timeout_secs: toml
+                .shutdown
+                .drain_timeout_secs
+                .unwrap_or(crate::shutdown::ShutdownConfig::default().drain_timeout_secs),
+        };
+
         Ok(Config {
             instance_dir,
             llm,
@@ -3264,6 +7958,14 @@ impl Config {
             api,
             metrics,
             telemetry,
+            rbac,
+            alerts,
+            event_webhooks,
+            update,
+            cluster,
+            object_storage,
+            middleware,
+            shutdown,
         })
     }
 
@@ -3300,6 +8002,8 @@ pub struct RuntimeConfig {
     pub instance_dir: PathBuf,
     /// Agent workspace directory (e.g., ~/.spacebot/agents/{id}/workspace). Immutable after startup.
     pub workspace_dir: PathBuf,
+    /// Agent data directory (e.g., ~/.spacebot/agents/{id}/data). Immutable after startup.
+    pub data_dir: PathBuf,
     pub routing: ArcSwap<RoutingConfig>,
     pub compaction: ArcSwap<CompactionConfig>,
     pub memory_persistence: ArcSwap<MemoryPersistenceConfig>,
@@ -3311,10 +8015,41 @@ pub struct RuntimeConfig {
     pub max_concurrent_branches: ArcSwap<usize>,
     pub max_concurrent_workers: ArcSwap<usize>,
     pub browser_config: ArcSwap<BrowserConfig>,
+    pub sandbox: ArcSwap<SandboxConfig>,
+    pub live_status: ArcSwap<LiveStatusConfig>,
+    pub confirmations: ArcSwap<ConfirmationsConfig>,
+    pub presence: ArcSwap<PresenceConfig>,
+    pub reflection: ArcSwap<ReflectionConfig>,
+    pub dream: ArcSwap<DreamConfig>,
+    pub worker_queue: ArcSwap<WorkerQueueConfig>,
+    pub rate_limit: ArcSwap<RateLimitConfig>,
+    pub handoff: ArcSwap<HandoffConfig>,
+    pub hibernation: ArcSwap<HibernationConfig>,
+    pub sub_workers: ArcSwap<SubWorkersConfig>,
+    pub worker_budget: ArcSwap<WorkerBudgetConfig>,
+    pub reaction_memory: ArcSwap<ReactionMemoryConfig>,
+    pub reactions: ArcSwap<ReactionsConfig>,
+    pub voice: ArcSwap<VoiceConfig>,
+    pub scripting: ArcSwap<ScriptingConfig>,
+    pub plugins: ArcSwap<PluginConfig>,
+    pub vector_index: ArcSwap<VectorIndexConfig>,
+    pub attachments: ArcSwap<AttachmentConfig>,
+    pub loop_guard: ArcSwap<LoopGuardConfig>,
+    pub disk_quota: ArcSwap<DiskQuotaConfig>,
+    pub offline: ArcSwap<OfflineConfig>,
+    pub spend: ArcSwap<SpendConfig>,
+    pub egress: ArcSwap<EgressConfig>,
+    pub prompt_injection: ArcSwap<PromptInjectionConfig>,
     pub mcp: ArcSwap<Vec<McpServerConfig>>,
     pub history_backfill_count: ArcSwap<usize>,
     pub brave_search_key: ArcSwap<Option<String>>,
     pub cron_timezone: ArcSwap<Option<String>>,
+    /// Scheduled tasks, run by the cortex's task loop.
+    pub tasks: ArcSwap<Vec<TaskDef>>,
+    /// Triggers, watched by the cortex's trigger loop.
+    pub triggers: ArcSwap<Vec<TriggerDef>>,
+    /// Hands webhook calls from the API to the trigger loop.
+    pub trigger_hub: crate::agent::trigger::TriggerHub,
     pub cortex: ArcSwap<CortexConfig>,
     /// Memory injection configuration for the pre-hook system.
     pub memory_injection: ArcSwap<MemoryInjectionConfig>,
@@ -3324,6 +8059,13 @@ pub struct RuntimeConfig {
     pub prompts: ArcSwap<crate::prompts::PromptEngine>,
     pub identity: ArcSwap<crate::identity::Identity>,
     pub skills: ArcSwap<crate::skills::SkillSet>,
+    /// Scripts loaded from `workspace/scripts/`, kept current by the script
+    /// loop.
+    pub scripts: ArcSwap<crate::scripting::ScriptSet>,
+    /// Circuit breakers of the agent's process types.
+    pub circuit_breakers: crate::agent::circuit_breaker::CircuitBreakers,
+    /// Running workers and the ones waiting for a slot.
+    pub worker_slots: crate::agent::worker_queue::WorkerQueue,
     pub opencode: ArcSwap<OpenCodeConfig>,
     /// Shared pool of OpenCode server processes. Lazily initialized on first use.
     pub opencode_server_pool: Arc<crate::opencode::OpenCodeServerPool>,
@@ -3331,8 +8073,15 @@ pub struct RuntimeConfig {
     pub cron_store: ArcSwap<Option<Arc<crate::cron::CronStore>>>,
     /// Cron scheduler, set after agent initialization.
     pub cron_scheduler: ArcSwap<Option<Arc<crate::cron::Scheduler>>>,
+    /// Instance bindings, shared with the main loop and kept current on
+    /// reload. Set after agent initialization.
+    pub bindings: ArcSwap<Option<Arc<ArcSwap<Vec<Binding>>>>>,
     /// Settings store for agent-specific configuration.
     pub settings: ArcSwap<Option<Arc<crate::settings::SettingsStore>>>,
+    /// Instance-wide role-based access control, shared by all agents.
+    pub rbac: ArcSwap<crate::rbac::RbacConfig>,
+    /// Instance-wide object storage, when a bucket is configured.
+    pub object_storage: ArcSwap<Option<Arc<crate::object_storage::ObjectStorage>>>,
 }
 
 impl RuntimeConfig {
@@ -3355,6 +8104,7 @@ impl RuntimeConfig {
         Self {
             instance_dir: instance_dir.to_path_buf(),
             workspace_dir: agent_config.workspace.clone(),
+            data_dir: agent_config.data_dir.clone(),
             routing: ArcSwap::from_pointee(agent_config.routing.clone()),
             compaction: ArcSwap::from_pointee(agent_config.compaction),
             memory_persistence: ArcSwap::from_pointee(agent_config.memory_persistence),
@@ -3366,21 +8116,55 @@ impl RuntimeConfig {
             max_concurrent_branches: ArcSwap::from_pointee(agent_config.max_concurrent_branches),
             max_concurrent_workers: ArcSwap::from_pointee(agent_config.max_concurrent_workers),
             browser_config: ArcSwap::from_pointee(agent_config.browser.clone()),
+            sandbox: ArcSwap::from_pointee(agent_config.sandbox.clone()),
+            live_status: ArcSwap::from_pointee(agent_config.live_status.clone()),
+            confirmations: ArcSwap::from_pointee(agent_config.confirmations.clone()),
+            presence: ArcSwap::from_pointee(agent_config.presence.clone()),
+            reflection: ArcSwap::from_pointee(agent_config.reflection.clone()),
+            dream: ArcSwap::from_pointee(agent_config.dream.clone()),
+            worker_queue: ArcSwap::from_pointee(agent_config.worker_queue.clone()),
+            rate_limit: ArcSwap::from_pointee(agent_config.rate_limit.clone()),
+            handoff: ArcSwap::from_pointee(agent_config.handoff.clone()),
+            hibernation: ArcSwap::from_pointee(agent_config.hibernation.clone()),
+            sub_workers: ArcSwap::from_pointee(agent_config.sub_workers.clone()),
+            worker_budget: ArcSwap::from_pointee(agent_config.worker_budget.clone()),
+            reaction_memory: ArcSwap::from_pointee(agent_config.reaction_memory.clone()),
+            reactions: ArcSwap::from_pointee(agent_config.reactions.clone()),
+            voice: ArcSwap::from_pointee(agent_config.voice.clone()),
+            scripting: ArcSwap::from_pointee(agent_config.scripting.clone()),
+            plugins: ArcSwap::from_pointee(agent_config.plugins.clone()),
+            vector_index: ArcSwap::from_pointee(agent_config.vector_index.clone()),
+            attachments: ArcSwap::from_pointee(agent_config.attachments.clone()),
+            loop_guard: ArcSwap::from_pointee(agent_config.loop_guard.clone()),
+            disk_quota: ArcSwap::from_pointee(agent_config.disk_quota.clone()),
+            offline: ArcSwap::from_pointee(agent_config.offline.clone()),
+            spend: ArcSwap::from_pointee(agent_config.spend.clone()),
+            egress: ArcSwap::from_pointee(agent_config.egress.clone()),
+            prompt_injection: ArcSwap::from_pointee(agent_config.prompt_injection.clone()),
             mcp: ArcSwap::from_pointee(agent_config.mcp.clone()),
             history_backfill_count: ArcSwap::from_pointee(agent_config.history_backfill_count),
             brave_search_key: ArcSwap::from_pointee(agent_config.brave_search_key.clone()),
             cron_timezone: ArcSwap::from_pointee(agent_config.cron_timezone.clone()),
+            tasks: ArcSwap::from_pointee(agent_config.tasks.clone()),
+            triggers: ArcSwap::from_pointee(agent_config.triggers.clone()),
+            trigger_hub: Default::default(),
             cortex: ArcSwap::from_pointee(agent_config.cortex),
             memory_injection: ArcSwap::from_pointee(agent_config.memory_injection.clone()),
             memory_bulletin: ArcSwap::from_pointee(String::new()),
             prompts: ArcSwap::from_pointee(prompts),
             identity: ArcSwap::from_pointee(identity),
             skills: ArcSwap::from_pointee(skills),
+            scripts: ArcSwap::from_pointee(crate::scripting::ScriptSet::default()),
+            circuit_breakers: Default::default(),
+            worker_slots: Default::default(),
             opencode: ArcSwap::from_pointee(defaults.opencode.clone()),
             opencode_server_pool: Arc::new(server_pool),
             cron_store: ArcSwap::from_pointee(None),
             cron_scheduler: ArcSwap::from_pointee(None),
+            bindings: ArcSwap::from_pointee(None),
             settings: ArcSwap::from_pointee(None),
+            rbac: ArcSwap::from_pointee(crate::rbac::RbacConfig::default()),
+            object_storage: ArcSwap::from_pointee(None),
         }
     }
 
@@ -3394,11 +8178,26 @@ impl RuntimeConfig {
         self.cron_scheduler.store(Arc::new(Some(scheduler)));
     }
 
+    /// Share the instance bindings after initialization.
+    pub fn set_bindings(&self, bindings: Arc<ArcSwap<Vec<Binding>>>) {
+        self.bindings.store(Arc::new(Some(bindings)));
+    }
+
     /// Set the settings store after initialization.
     pub fn set_settings(&self, settings: Arc<crate::settings::SettingsStore>) {
         self.settings.store(Arc::new(Some(settings)));
     }
 
+    /// Set the instance-wide RBAC config. Also refreshed on config reload.
+    pub fn set_rbac(&self, rbac: crate::rbac::RbacConfig) {
+        self.rbac.store(Arc::new(rbac));
+    }
+
+    /// Set the instance-wide object storage.
+    pub fn set_object_storage(&self, storage: Option<Arc<crate::object_storage::ObjectStorage>>) {
+        self.object_storage.store(Arc::new(storage));
+    }
+
     /// Reload tunable config values from a freshly parsed Config.
     ///
     /// Finds the matching agent by ID, re-resolves it against defaults, and
@@ -3436,15 +8235,45 @@ impl RuntimeConfig {
         self.max_concurrent_workers
             .store(Arc::new(resolved.max_concurrent_workers));
         self.browser_config.store(Arc::new(resolved.browser));
+        self.sandbox.store(Arc::new(resolved.sandbox));
+        self.live_status.store(Arc::new(resolved.live_status));
+        self.confirmations.store(Arc::new(resolved.confirmations));
+        self.presence.store(Arc::new(resolved.presence));
+        self.reflection.store(Arc::new(resolved.reflection));
+        self.dream.store(Arc::new(resolved.dream));
+        self.worker_queue.store(Arc::new(resolved.worker_queue));
+        self.rate_limit.store(Arc::new(resolved.rate_limit));
+        self.handoff.store(Arc::new(resolved.handoff));
+        self.hibernation.store(Arc::new(resolved.hibernation));
+        self.sub_workers.store(Arc::new(resolved.sub_workers));
+        self.worker_budget.store(Arc::new(resolved.worker_budget));
+        self.reaction_memory
+            .store(Arc::new(resolved.reaction_memory));
+        self.reactions.store(Arc::new(resolved.reactions));
+        self.voice.store(Arc::new(resolved.voice));
+        self.scripting.store(Arc::new(resolved.scripting));
+        self.plugins.store(Arc::new(resolved.plugins));
+        self.vector_index.store(Arc::new(resolved.vector_index));
+        self.attachments.store(Arc::new(resolved.attachments));
+        self.loop_guard.store(Arc::new(resolved.loop_guard));
+        self.disk_quota.store(Arc::new(resolved.disk_quota));
+        self.offline.store(Arc::new(resolved.offline));
+        self.spend.store(Arc::new(resolved.spend));
+        self.egress.store(Arc::new(resolved.egress));
+        self.prompt_injection
+            .store(Arc::new(resolved.prompt_injection));
         self.mcp.store(Arc::new(new_mcp.clone()));
         self.history_backfill_count
             .store(Arc::new(resolved.history_backfill_count));
         self.brave_search_key
             .store(Arc::new(resolved.brave_search_key));
         self.cron_timezone.store(Arc::new(resolved.cron_timezone));
+        self.tasks.store(Arc::new(resolved.tasks));
+        self.triggers.store(Arc::new(resolved.triggers));
         self.cortex.store(Arc::new(resolved.cortex));
         self.memory_injection
             .store(Arc::new(resolved.memory_injection));
+        self.rbac.store(Arc::new(config.rbac.clone()));
 
         mcp_manager.reconcile(&old_mcp, &new_mcp).await;
 
@@ -3490,8 +8319,14 @@ pub fn spawn_file_watcher(
     slack_permissions: Option<Arc<arc_swap::ArcSwap<SlackPermissions>>>,
     telegram_permissions: Option<Arc<arc_swap::ArcSwap<TelegramPermissions>>>,
     twitch_permissions: Option<Arc<arc_swap::ArcSwap<TwitchPermissions>>>,
+    irc_permissions: Option<Arc<arc_swap::ArcSwap<IrcPermissions>>>,
+    email_permissions: Option<Arc<arc_swap::ArcSwap<EmailPermissions>>>,
+    xmpp_permissions: Option<Arc<arc_swap::ArcSwap<XmppPermissions>>>,
+    whatsapp_permissions: Option<Arc<arc_swap::ArcSwap<WhatsAppPermissions>>>,
     bindings: Arc<arc_swap::ArcSwap<Vec<Binding>>>,
+    middleware: Arc<arc_swap::ArcSwap<crate::messaging::middleware::MiddlewarePipeline>>,
     messaging_manager: Option<Arc<crate::messaging::MessagingManager>>,
+    pairing_store: Option<Arc<crate::messaging::pairing::PairingStore>>,
     llm_manager: Arc<crate::llm::LlmManager>,
 ) -> tokio::task::JoinHandle<()> {
     use notify::{Event, RecursiveMode, Watcher};
@@ -3648,6 +8483,12 @@ pub fn spawn_file_watcher(
                 bindings.store(Arc::new(config.bindings.clone()));
                 tracing::info!("bindings reloaded ({} entries)", config.bindings.len());
 
+                let pipeline = crate::messaging::middleware::MiddlewarePipeline::from_config(
+                    &config.middleware,
+                );
+                tracing::info!(middleware = ?pipeline, "inbound middleware reloaded");
+                middleware.store(Arc::new(pipeline));
+
                 if let Some(ref perms) = discord_permissions
                     && let Some(discord_config) = &config.messaging.discord
                 {
@@ -3682,6 +8523,39 @@ pub fn spawn_file_watcher(
                     tracing::info!("twitch permissions reloaded");
                 }
 
+                if let Some(ref perms) = irc_permissions
+                    && let Some(irc_config) = &config.messaging.irc
+                {
+                    let new_perms = IrcPermissions::from_config(irc_config, &config.bindings);
+                    perms.store(Arc::new(new_perms));
+                    tracing::info!("irc permissions reloaded");
+                }
+
+                if let Some(ref perms) = email_permissions
+                    && let Some(email_config) = &config.messaging.email
+                {
+                    let new_perms = EmailPermissions::from_config(email_config, &config.bindings);
+                    perms.store(Arc::new(new_perms));
+                    tracing::info!("email permissions reloaded");
+                }
+
+                if let Some(ref perms) = xmpp_permissions
+                    && let Some(xmpp_config) = &config.messaging.xmpp
+                {
+                    let new_perms = XmppPermissions::from_config(xmpp_config, &config.bindings);
+                    perms.store(Arc::new(new_perms));
+                    tracing::info!("xmpp permissions reloaded");
+                }
+
+                if let Some(ref perms) = whatsapp_permissions
+                    && let Some(whatsapp_config) = &config.messaging.whatsapp
+                {
+                    let new_perms =
+                        WhatsAppPermissions::from_config(whatsapp_config, &config.bindings);
+                    perms.store(Arc::new(new_perms));
+                    tracing::info!("whatsapp permissions reloaded");
+                }
+
                 // Hot-start adapters that are newly enabled in the config
                 if let Some(ref manager) = messaging_manager {
                     let rt = tokio::runtime::Handle::current();
@@ -3691,7 +8565,12 @@ pub fn spawn_file_watcher(
                     let slack_permissions = slack_permissions.clone();
                     let telegram_permissions = telegram_permissions.clone();
                     let twitch_permissions = twitch_permissions.clone();
+                    let irc_permissions = irc_permissions.clone();
+                    let email_permissions = email_permissions.clone();
+                    let xmpp_permissions = xmpp_permissions.clone();
+                    let whatsapp_permissions = whatsapp_permissions.clone();
                     let instance_dir = instance_dir.clone();
+                    let pairing_store = pairing_store.clone();
 
                     rt.spawn(async move {
                         // Discord: start if enabled and not already running
@@ -3707,7 +8586,8 @@ pub fn spawn_file_watcher(
                                 let adapter = crate::messaging::discord::DiscordAdapter::new(
                                     &discord_config.token,
                                     perms,
-                                );
+                                )
+                                .with_pairing(pairing_store.clone());
                                 if let Err(error) = manager.register_and_start(adapter).await {
                                     tracing::error!(%error, "failed to hot-start discord adapter from config change");
                                 }
@@ -3746,45 +8626,134 @@ pub fn spawn_file_watcher(
                                 let perms = match telegram_permissions {
                                     Some(ref existing) => existing.clone(),
                                     None => {
-                                        let perms = TelegramPermissions::from_config(telegram_config, &config.bindings);
+                                        let perms = TelegramPermissions::from_config(telegram_config, &config.bindings);
+                                        Arc::new(arc_swap::ArcSwap::from_pointee(perms))
+                                    }
+                                };
+                                let adapter = crate::messaging::telegram::TelegramAdapter::new(
+                                    &telegram_config.token,
+                                    perms,
+                                )
+                                .with_pairing(pairing_store.clone());
+                                if let Err(error) = manager.register_and_start(adapter).await {
+                                    tracing::error!(%error, "failed to hot-start telegram adapter from config change");
+                                }
+                            }
+
+                        // Twitch: start if enabled and not already running
+                        if let Some(twitch_config) = &config.messaging.twitch
+                            && twitch_config.enabled && !manager.has_adapter("twitch").await {
+                                let perms = match twitch_permissions {
+                                    Some(ref existing) => existing.clone(),
+                                    None => {
+                                        let perms = TwitchPermissions::from_config(twitch_config, &config.bindings);
+                                        Arc::new(arc_swap::ArcSwap::from_pointee(perms))
+                                    }
+                                };
+                                let token_path = instance_dir.join("twitch_token.json");
+                                let adapter = crate::messaging::twitch::TwitchAdapter::new(
+                                    &twitch_config.username,
+                                    &twitch_config.oauth_token,
+                                    twitch_config.client_id.clone(),
+                                    twitch_config.client_secret.clone(),
+                                    twitch_config.refresh_token.clone(),
+                                    Some(token_path),
+                                    twitch_config.channels.clone(),
+                                    twitch_config.trigger_prefix.clone(),
+                                    perms,
+                                );
+                                if let Err(error) = manager.register_and_start(adapter).await {
+                                    tracing::error!(%error, "failed to hot-start twitch adapter from config change");
+                                }
+                            }
+
+                        // IRC: start if enabled and not already running
+                        if let Some(irc_config) = &config.messaging.irc
+                            && irc_config.enabled && !manager.has_adapter("irc").await {
+                                let perms = match irc_permissions {
+                                    Some(ref existing) => existing.clone(),
+                                    None => {
+                                        let perms = IrcPermissions::from_config(irc_config, &config.bindings);
+                                        Arc::new(arc_swap::ArcSwap::from_pointee(perms))
+                                    }
+                                };
+                                let adapter = crate::messaging::irc::IrcAdapter::new(irc_config, perms);
+                                if let Err(error) = manager.register_and_start(adapter).await {
+                                    tracing::error!(%error, "failed to hot-start irc adapter from config change");
+                                }
+                            }
+
+                        // Email: start if enabled and not already running
+                        if let Some(email_config) = &config.messaging.email
+                            && email_config.enabled && !manager.has_adapter("email").await {
+                                let perms = match email_permissions {
+                                    Some(ref existing) => existing.clone(),
+                                    None => {
+                                        let perms = EmailPermissions::from_config(email_config, &config.bindings);
+                                        Arc::new(arc_swap::ArcSwap::from_pointee(perms))
+                                    }
+                                };
+                                match crate::messaging::email::EmailAdapter::new(email_config, perms) {
+                                    Ok(adapter) => {
+                                        if let Err(error) = manager.register_and_start(adapter).await {
+                                            tracing::error!(%error, "failed to hot-start email adapter from config change");
+                                        }
+                                    }
+                                    Err(error) => {
+                                        tracing::error!(%error, "failed to build email adapter from config change");
+                                    }
+                                }
+                            }
+
+                        // XMPP: start if enabled and not already running
+                        if let Some(xmpp_config) = &config.messaging.xmpp
+                            && xmpp_config.enabled && !manager.has_adapter("xmpp").await {
+                                let perms = match xmpp_permissions {
+                                    Some(ref existing) => existing.clone(),
+                                    None => {
+                                        let perms = XmppPermissions::from_config(xmpp_config, &config.bindings);
                                         Arc::new(arc_swap::ArcSwap::from_pointee(perms))
                                     }
                                 };
-                                let adapter = crate::messaging::telegram::TelegramAdapter::new(
-                                    &telegram_config.token,
-                                    perms,
-                                );
+                                let adapter = crate::messaging::xmpp::XmppAdapter::new(xmpp_config, perms);
                                 if let Err(error) = manager.register_and_start(adapter).await {
-                                    tracing::error!(%error, "failed to hot-start telegram adapter from config change");
+                                    tracing::error!(%error, "failed to hot-start xmpp adapter from config change");
                                 }
                             }
 
-                        // Twitch: start if enabled and not already running
-                        if let Some(twitch_config) = &config.messaging.twitch
-                            && twitch_config.enabled && !manager.has_adapter("twitch").await {
-                                let perms = match twitch_permissions {
+                        // WhatsApp: start if enabled and not already running
+                        if let Some(whatsapp_config) = &config.messaging.whatsapp
+                            && whatsapp_config.enabled && !manager.has_adapter("whatsapp").await {
+                                let perms = match whatsapp_permissions {
                                     Some(ref existing) => existing.clone(),
                                     None => {
-                                        let perms = TwitchPermissions::from_config(twitch_config, &config.bindings);
+                                        let perms = WhatsAppPermissions::from_config(whatsapp_config, &config.bindings);
                                         Arc::new(arc_swap::ArcSwap::from_pointee(perms))
                                     }
                                 };
-                                let token_path = instance_dir.join("twitch_token.json");
-                                let adapter = crate::messaging::twitch::TwitchAdapter::new(
-                                    &twitch_config.username,
-                                    &twitch_config.oauth_token,
-                                    twitch_config.client_id.clone(),
-                                    twitch_config.client_secret.clone(),
-                                    twitch_config.refresh_token.clone(),
-                                    Some(token_path),
-                                    twitch_config.channels.clone(),
-                                    twitch_config.trigger_prefix.clone(),
-                                    perms,
-                                );
+                                let adapter = crate::messaging::whatsapp::WhatsAppAdapter::new(whatsapp_config, perms);
                                 if let Err(error) = manager.register_and_start(adapter).await {
-                                    tracing::error!(%error, "failed to hot-start twitch adapter from config change");
+                                    tracing::error!(%error, "failed to hot-start whatsapp adapter from config change");
+                                }
+                            }
+
+                        // Custom adapters: start if enabled, registered and not already running
+                        for custom in config.messaging.custom.iter().filter(|custom| custom.enabled) {
+                            if manager.has_adapter(&custom.name).await {
+                                continue;
+                            }
+                            match crate::messaging::registry::build(custom) {
+                                Some(Ok(adapter)) => {
+                                    if let Err(error) = manager.register_and_start_dyn(adapter).await {
+                                        tracing::error!(adapter = %custom.name, %error, "failed to hot-start custom adapter from config change");
+                                    }
                                 }
+                                Some(Err(error)) => {
+                                    tracing::error!(adapter = %custom.name, %error, "failed to build custom adapter");
+                                }
+                                None => {}
                             }
+                        }
                     });
                 }
             }
@@ -4583,4 +9552,793 @@ id = "main"
         let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
         assert_eq!(resolved.cron_timezone, None);
     }
+
+    #[test]
+    fn test_cluster_config_validation() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error = parse("[cluster]\nenabled = true\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("cluster.database_url is required")
+        );
+
+        let error = parse(
+            "[cluster]\nenabled = true\ndatabase_url = \"postgres://db/spacebot\"\nheartbeat_interval_secs = 10\nlease_ttl_secs = 15\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("cluster.lease_ttl_secs"));
+
+        let config = parse(
+            "[cluster]\nenabled = true\nnode_id = \"node-a\"\ndatabase_url = \"postgres://db/spacebot\"\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(config.cluster.node_id, "node-a");
+        assert_eq!(config.cluster.shared_adapters, vec!["discord", "twitch"]);
+    }
+
+    #[test]
+    fn test_binding_digest_interval() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\ndigest_interval_secs = 3600\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(config.bindings[0].digest_interval_secs, Some(3600));
+
+        let error = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\ndigest_interval_secs = 0\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("digest_interval_secs"));
+    }
+
+    #[test]
+    fn test_binding_reply_to_message() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from(".")).expect("failed to build Config")
+        };
+
+        let config = parse("[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\n");
+        assert!(!config.bindings[0].reply_to_message);
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\nreply_to_message = true\n",
+        );
+        assert!(config.bindings[0].reply_to_message);
+    }
+
+    #[test]
+    fn test_binding_response_mode() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\n")
+            .expect("failed to build Config");
+        assert_eq!(config.bindings[0].response_mode, ResponseMode::Typing);
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\nresponse_mode = \"stream\"\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(config.bindings[0].response_mode, ResponseMode::Stream);
+
+        let error = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\nresponse_mode = \"edits\"\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("response_mode"));
+    }
+
+    #[test]
+    fn test_proactive_outreach_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\n")
+            .expect("failed to build Config");
+        assert!(!config.bindings[0].allow_proactive);
+        assert_eq!(config.defaults.cortex.proactive_daily_limit, 3);
+
+        let config = parse(
+            "[defaults.cortex]\nproactive_daily_limit = 10\n\n[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\nallow_proactive = true\n",
+        )
+        .expect("failed to build Config");
+        assert!(config.bindings[0].allow_proactive);
+        assert_eq!(config.defaults.cortex.proactive_daily_limit, 10);
+    }
+
+    #[test]
+    fn test_binding_tags() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\n")
+            .expect("failed to build Config");
+        assert!(config.bindings[0].tags.is_empty());
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\ntags = [\"customers\", \"eu\"]\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(config.bindings[0].tags, ["customers", "eu"]);
+    }
+
+    #[test]
+    fn test_binding_overrides() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\n")
+            .expect("failed to build Config");
+        assert!(config.bindings[0].overrides.is_empty());
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\nchannel_model = \"openai/gpt-4o-mini\"\nmax_turns = 3\n\n[bindings.compaction]\nbackground_threshold = 0.6\n",
+        )
+        .expect("failed to build Config");
+        let overrides = &config.bindings[0].overrides;
+        assert_eq!(
+            overrides.channel_model.as_deref(),
+            Some("openai/gpt-4o-mini")
+        );
+        assert_eq!(overrides.max_turns, Some(3));
+        assert_eq!(overrides.context_window, None);
+        let compaction = overrides.compaction(CompactionConfig::default());
+        assert_eq!(compaction.background_threshold, 0.6);
+        assert_eq!(
+            compaction.emergency_threshold,
+            CompactionConfig::default().emergency_threshold
+        );
+
+        let error = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"discord\"\n\n[bindings.compaction]\nemergency_threshold = 1.5\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("emergency_threshold"));
+    }
+
+    #[test]
+    fn test_process_compaction_ranges() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("[defaults.compaction.branch]\nthreshold = 0.8\nfraction = 1.0\n")
+            .expect("failed to build Config");
+        assert_eq!(config.defaults.compaction.branch.threshold, 0.8);
+        assert_eq!(config.defaults.compaction.branch.fraction, 1.0);
+
+        let error = parse("[defaults.compaction.worker]\nthreshold = 0.0\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("defaults.compaction.worker.threshold")
+        );
+
+        let error =
+            parse("[[agents]]\nid = \"main\"\n[agents.compaction.branch]\nfraction = 1.5\n")
+                .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("agents.main.compaction.branch.fraction")
+        );
+    }
+
+    #[test]
+    fn test_coalesce_sender_debounce() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from(".")).expect("failed to build Config")
+        };
+
+        let config = parse("[defaults.coalesce]\ndebounce_ms = 1000\n");
+        assert_eq!(config.defaults.coalesce.sender_debounce_ms, 0);
+
+        let config = parse(
+            "[defaults.coalesce]\nsender_debounce_ms = 2000\n\n[[agents]]\nid = \"main\"\n[agents.coalesce]\nmax_wait_ms = 8000\n",
+        );
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.coalesce.sender_debounce_ms, 2000);
+        assert_eq!(resolved.coalesce.max_wait_ms, 8000);
+    }
+
+    #[test]
+    fn test_binding_activation() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+        let message = |text: &str, metadata: serde_json::Value| crate::InboundMessage {
+            id: "1".into(),
+            source: "slack".into(),
+            conversation_id: "slack:T1:C1".into(),
+            sender_id: "U1".into(),
+            agent_id: None,
+            content: crate::MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: serde_json::from_value(metadata).unwrap(),
+            formatted_author: None,
+        };
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"slack\"\nrequire_mention = true\n",
+        )
+        .expect("failed to build Config");
+        let binding = &config.bindings[0];
+        assert_eq!(binding.activation, Activation::Mention);
+        assert!(!binding.is_addressed(&message(
+            "hello",
+            serde_json::json!({"slack_channel_id": "C1"})
+        )));
+        assert!(binding.is_addressed(&message(
+            "hello",
+            serde_json::json!({"slack_channel_id": "C1", "slack_mentions_bot": true})
+        )));
+        assert!(binding.is_addressed(&message(
+            "hello",
+            serde_json::json!({"slack_channel_id": "D1"})
+        )));
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"slack\"\nactivation = \"wake_word\"\nwake_words = [\"Hey Bot\"]\n",
+        )
+        .expect("failed to build Config");
+        let binding = &config.bindings[0];
+        let channel = serde_json::json!({"slack_channel_id": "C1"});
+        assert!(binding.is_addressed(&message("hey bot, what's up?", channel.clone())));
+        assert!(!binding.is_addressed(&message("they bottled it", channel.clone())));
+
+        let config = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"slack\"\nactivation = \"thread\"\n",
+        )
+        .expect("failed to build Config");
+        let binding = &config.bindings[0];
+        assert!(!binding.is_addressed(&message("hi", channel)));
+        assert!(binding.is_addressed(&message(
+            "hi",
+            serde_json::json!({"slack_channel_id": "C1", "slack_thread_ts": "1.2"})
+        )));
+
+        let error = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"slack\"\nactivation = \"wake_word\"\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("wake_words"));
+        let error = parse(
+            "[[bindings]]\nagent_id = \"main\"\nchannel = \"slack\"\nactivation = \"sometimes\"\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("got 'sometimes'"));
+    }
+
+    #[test]
+    fn test_handoff_targets_must_be_agents() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error =
+            parse("[[agents]]\nid = \"front\"\n[agents.handoff]\ntargets = [\"billing\"]\n")
+                .unwrap_err();
+        assert!(error.to_string().contains("handoff target 'billing'"));
+
+        let config = parse(
+            "[[agents]]\nid = \"front\"\n[agents.handoff]\ntargets = [\"billing\"]\n\n[[agents]]\nid = \"billing\"\n",
+        )
+        .expect("failed to build Config");
+        let front = config
+            .agents
+            .iter()
+            .find(|agent| agent.id == "front")
+            .unwrap();
+        assert_eq!(
+            front
+                .handoff
+                .as_ref()
+                .map(|handoff| handoff.targets.clone()),
+            Some(vec!["billing".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_hibernation_idle_timeout() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from(".")).expect("failed to build Config")
+        };
+
+        let config = parse("");
+        assert_eq!(config.defaults.hibernation.idle_timeout_secs, 1800);
+
+        let config = parse(
+            "[defaults.hibernation]\nidle_timeout_secs = 600\n\n[[agents]]\nid = \"main\"\n[agents.hibernation]\nidle_timeout_secs = 0\n",
+        );
+        assert_eq!(config.defaults.hibernation.idle_timeout_secs, 600);
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.hibernation.idle_timeout_secs, 0);
+    }
+
+    #[test]
+    fn test_sub_workers_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from(".")).expect("failed to build Config")
+        };
+
+        let config = parse("");
+        assert_eq!(config.defaults.sub_workers.max_depth, 2);
+        assert_eq!(config.defaults.sub_workers.max_per_channel, 5);
+
+        let config = parse(
+            "[defaults.sub_workers]\nmax_depth = 3\n\n[[agents]]\nid = \"main\"\n[agents.sub_workers]\nmax_depth = 1\nmax_per_channel = 2\n",
+        );
+        assert_eq!(config.defaults.sub_workers.max_depth, 3);
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.sub_workers.max_depth, 1);
+        assert_eq!(resolved.sub_workers.max_per_channel, 2);
+    }
+
+    #[test]
+    fn test_scheduled_tasks_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse(
+            r#"
+[[agents]]
+id = "main"
+
+[[agents.tasks]]
+id = "digest"
+schedule = "0 8 * * mon-fri"
+task = "Summarize overnight alerts"
+report_to = "discord:123456789"
+
+[[agents.tasks]]
+id = "cleanup"
+schedule = "@weekly"
+task = "Delete build caches older than a week"
+enabled = false
+"#,
+        )
+        .expect("failed to build Config");
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.tasks.len(), 2);
+        assert_eq!(resolved.tasks[0].schedule.to_string(), "0 8 * * mon-fri");
+        assert_eq!(
+            resolved.tasks[0]
+                .report_to
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("discord:123456789")
+        );
+        assert!(resolved.tasks[1].report_to.is_none());
+        assert!(!resolved.tasks[1].enabled);
+
+        let error = parse(
+            "[[agents]]\nid = \"main\"\n[[agents.tasks]]\nid = \"bad\"\nschedule = \"every day\"\ntask = \"x\"\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("agents.main.tasks.bad.schedule"));
+    }
+
+    #[test]
+    fn test_dream_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse(
+            "[defaults.dream]\nenabled = true\nmax_memories = 5\n\n[[agents]]\nid = \"main\"\n[agents.dream]\nhour = 23\nmodel = \"openai/gpt-4o-mini\"\n",
+        )
+        .expect("failed to build Config");
+        assert!(config.defaults.dream.enabled);
+        assert_eq!(config.defaults.dream.hour, 3);
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert!(resolved.dream.enabled);
+        assert_eq!(resolved.dream.hour, 23);
+        assert_eq!(resolved.dream.max_memories, 5);
+        assert_eq!(resolved.dream.model.as_deref(), Some("openai/gpt-4o-mini"));
+
+        let error = parse("[defaults.dream]\nhour = 24\n").unwrap_err();
+        assert!(error.to_string().contains("defaults.dream.hour"));
+        let error = parse("[defaults.dream]\nmax_output_tokens = 100\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("defaults.dream.max_output_tokens")
+        );
+    }
+
+    #[test]
+    fn test_worker_queue_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse(
+            "[defaults.worker_queue]\nmax_queued = 5\n\n[[agents]]\nid = \"main\"\n[agents.worker_queue]\nmax_concurrent = 2\ndispatch = \"fifo\"\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(config.defaults.worker_queue.max_concurrent, 8);
+        assert_eq!(
+            config.defaults.worker_queue.dispatch,
+            WorkerDispatch::Priority
+        );
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.worker_queue.max_concurrent, 2);
+        assert_eq!(resolved.worker_queue.max_queued, 5);
+        assert_eq!(resolved.worker_queue.dispatch, WorkerDispatch::Fifo);
+
+        let error = parse("[defaults.worker_queue]\ndispatch = \"random\"\n").unwrap_err();
+        assert!(error.to_string().contains("defaults.worker_queue.dispatch"));
+        let error = parse("[defaults.worker_queue]\nmax_concurrent = 0\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("defaults.worker_queue.max_concurrent")
+        );
+    }
+
+    #[test]
+    fn test_triggers_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse(
+            r#"
+[[agents]]
+id = "main"
+
+[[agents.triggers]]
+id = "inbox"
+watch = "inbox/new"
+task = "Sort the new files"
+
+[[agents.triggers]]
+id = "deploy"
+webhook_token = "0123456789abcdef"
+notify = "discord:123456789"
+cooldown_secs = 300
+
+[[agents.triggers]]
+id = "sweep"
+every_secs = 3600
+task = "Sweep the inbox"
+enabled = false
+"#,
+        )
+        .expect("failed to build Config");
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.triggers.len(), 3);
+        assert!(matches!(
+            &resolved.triggers[0].source,
+            TriggerSource::Watch(path) if path == Path::new("inbox/new")
+        ));
+        assert_eq!(resolved.triggers[0].cooldown_secs, 60);
+        assert!(matches!(
+            &resolved.triggers[1].source,
+            TriggerSource::Webhook { token } if token == "0123456789abcdef"
+        ));
+        assert!(resolved.triggers[1].task.is_none());
+        assert_eq!(resolved.triggers[1].cooldown_secs, 300);
+        assert!(matches!(
+            resolved.triggers[2].source,
+            TriggerSource::Timer { every_secs: 3600 }
+        ));
+        assert!(!resolved.triggers[2].enabled);
+
+        for (trigger, expected) in [
+            ("watch = \"../etc\"\ntask = \"x\"", "triggers.bad.watch"),
+            (
+                "webhook_token = \"short\"\ntask = \"x\"",
+                "triggers.bad.webhook_token",
+            ),
+            ("every_secs = 5\ntask = \"x\"", "triggers.bad.every_secs"),
+            (
+                "every_secs = 60\nwatch = \"x\"\ntask = \"x\"",
+                "exactly one of",
+            ),
+            ("every_secs = 60", "needs a task, notify or both"),
+        ] {
+            let error = parse(&format!(
+                "[[agents]]\nid = \"main\"\n[[agents.triggers]]\nid = \"bad\"\n{trigger}\n"
+            ))
+            .unwrap_err();
+            assert!(error.to_string().contains(expected), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_worker_budget_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from(".")).expect("failed to build Config")
+        };
+
+        let config = parse("");
+        assert_eq!(config.defaults.worker_budget.max_wall_time_secs, 3600);
+        assert_eq!(config.defaults.worker_budget.max_tool_calls, 0);
+        assert!(config.defaults.worker_budget.max_cost_usd.is_none());
+
+        let config = parse(
+            "[defaults.worker_budget]\nmax_tool_calls = 200\nmax_cost_usd = 2.5\n\n[[agents]]\nid = \"main\"\n[agents.worker_budget]\nmax_wall_time_secs = 0\n",
+        );
+        assert_eq!(config.defaults.worker_budget.max_tool_calls, 200);
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.worker_budget.max_wall_time_secs, 0);
+        assert_eq!(resolved.worker_budget.max_tool_calls, 200);
+        assert_eq!(resolved.worker_budget.max_cost_usd, Some(2.5));
+    }
+
+    #[test]
+    fn test_cortex_consolidation_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from(".")).expect("failed to build Config")
+        };
+
+        let config = parse("");
+        assert_eq!(config.defaults.cortex.consolidation_idle_secs, 1800);
+        assert_eq!(config.defaults.cortex.consolidation_stale_days, 90);
+
+        let config = parse(
+            "[defaults.cortex]\nconsolidation_stale_days = 30\n\n[[agents]]\nid = \"main\"\n[agents.cortex]\nconsolidation_idle_secs = 0\n",
+        );
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        assert_eq!(resolved.cortex.consolidation_idle_secs, 0);
+        assert_eq!(resolved.cortex.consolidation_stale_days, 30);
+        assert_eq!(resolved.cortex.consolidation_interval_secs, 86400);
+    }
+
+    #[test]
+    fn test_reaction_signals_must_not_overlap() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error = parse("[defaults.reactions]\napprove = [\"👍\"]\ndeny = [\"👎\", \"👍\"]\n")
+            .unwrap_err();
+        assert!(error.to_string().contains("both approve and deny"));
+
+        let config = parse("[defaults.reactions]\nretry = [\"🔃\"]\nack_emoji = \"👀\"\n")
+            .expect("failed to build Config");
+        assert_eq!(config.defaults.reactions.retry, vec!["🔃".to_string()]);
+        assert_eq!(config.defaults.reactions.approve, vec!["👍", "✅"]);
+        assert_eq!(config.defaults.reactions.ack_emoji, "👀");
+    }
+
+    #[test]
+    fn test_presence_activity_must_be_known() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error = parse("[defaults.presence]\nactivity = \"streaming\"\n").unwrap_err();
+        assert!(error.to_string().contains("got 'streaming'"));
+
+        let config = parse(
+            "[defaults.presence]\nenabled = true\nactivity = \"playing\"\n\n[[agents]]\nid = \"main\"\n[agents.presence]\nidle_text = \"idle\"\n",
+        )
+        .expect("failed to build Config");
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        let presence = resolved.presence;
+        assert!(presence.enabled);
+        assert_eq!(
+            presence.activity,
+            crate::messaging::traits::ActivityKind::Playing
+        );
+        assert_eq!(presence.idle_text, "idle");
+    }
+
+    #[test]
+    fn test_rate_limit_config() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error = parse("[defaults.rate_limit]\nsender_per_minute = 0\n").unwrap_err();
+        assert!(error.to_string().contains("sender_per_minute"));
+
+        let config = parse(
+            "[defaults.rate_limit]\nenabled = true\nsender_burst = 3\n\n[[agents]]\nid = \"main\"\n[agents.rate_limit]\nmessage = \"\"\n",
+        )
+        .expect("failed to build Config");
+        let resolved = config.agents[0].resolve(&config.instance_dir, &config.defaults);
+        let rate_limit = resolved.rate_limit;
+        assert!(rate_limit.enabled);
+        assert_eq!(rate_limit.sender_burst, 3);
+        assert_eq!(rate_limit.conversation_burst, 20);
+        assert!(rate_limit.message.is_empty());
+    }
+
+    #[test]
+    fn test_webhook_signing_needs_an_auth_token() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error =
+            parse("[messaging.webhook]\nenabled = true\nsigning_secret = \"key\"\n").unwrap_err();
+        assert!(error.to_string().contains("need auth_token set"));
+        let error = parse(
+            "[messaging.webhook]\nenabled = true\n[messaging.webhook.sources]\nci = \"key\"\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("need auth_token set"));
+
+        let config = parse(
+            "[messaging.webhook]\nenabled = true\nauth_token = \"token\"\nsigning_secret = \"key\"\n",
+        )
+        .expect("failed to build Config");
+        let webhook = config.messaging.webhook.expect("webhook config");
+        assert_eq!(webhook.signing_secret.as_deref(), Some("key"));
+    }
+
+    #[test]
+    fn test_voice_transcription_backend() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let error = parse("[defaults.voice]\ntranscription = \"whisper\"\n").unwrap_err();
+        assert!(error.to_string().contains("transcription"));
+
+        let error = parse("[defaults.voice]\ntranscription = \"whisper_cpp\"\n").unwrap_err();
+        assert!(error.to_string().contains("whisper_cpp_model"));
+
+        let config = parse(
+            "[defaults.voice]\ntranscription = \"whisper_cpp\"\nwhisper_cpp_model = \"/models/ggml-base.bin\"\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(
+            config.defaults.voice.transcription,
+            TranscriptionBackend::WhisperCpp
+        );
+        assert_eq!(config.defaults.voice.whisper_cpp_binary, "whisper-cli");
+        assert_eq!(
+            config.defaults.voice.whisper_cpp_model.as_deref(),
+            Some(Path::new("/models/ggml-base.bin"))
+        );
+    }
+
+    #[test]
+    fn test_middleware_config() {
+        use crate::messaging::middleware::ProfanityAction;
+
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("").expect("failed to build Config");
+        assert!(config.middleware.strip_mentions.is_none());
+        assert!(config.middleware.profanity.is_none());
+
+        let error = parse("[middleware.profanity]\nwords = []\n").unwrap_err();
+        assert!(error.to_string().contains("middleware.profanity.words"));
+
+        let error =
+            parse("[middleware.profanity]\nwords = [\"darn\"]\naction = \"drop\"\n").unwrap_err();
+        assert!(error.to_string().contains("middleware.profanity.action"));
+
+        let config = parse(
+            "[middleware.strip_mentions]\nnames = [\"spacebot\"]\n\n[middleware.profanity]\nwords = [\"darn\"]\naction = \"block\"\nsources = [\"discord\"]\n",
+        )
+        .expect("failed to build Config");
+        assert_eq!(
+            config.middleware.strip_mentions.unwrap().names,
+            vec!["spacebot".to_string()]
+        );
+        let profanity = config.middleware.profanity.unwrap();
+        assert_eq!(profanity.action, ProfanityAction::Block);
+        assert_eq!(profanity.sources, vec!["discord".to_string()]);
+
+        let config = parse("[middleware.strip_mentions]\nenabled = false\n")
+            .expect("failed to build Config");
+        assert!(config.middleware.strip_mentions.is_none());
+    }
+
+    #[test]
+    fn test_custom_adapter_settings_pass_through() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse(
+            "[messaging.custom.matrix]\nenabled = true\nhomeserver = \"https://matrix.org\"\nrooms = [\"#ops\"]\n",
+        )
+        .expect("failed to build Config");
+        let custom = &config.messaging.custom[0];
+        assert_eq!(custom.name, "matrix");
+        assert!(custom.enabled);
+        assert_eq!(
+            custom.setting("homeserver").as_deref(),
+            Some("https://matrix.org")
+        );
+        assert!(custom.settings["rooms"].is_array());
+        assert!(!custom.settings.contains_key("enabled"));
+
+        let error = parse("[messaging.custom.slack]\nenabled = true\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("shadows the built-in slack adapter")
+        );
+    }
+
+    #[test]
+    fn test_event_webhooks_default_to_every_event() {
+        let parse = |toml: &str| {
+            let parsed: TomlConfig = toml::from_str(toml).expect("failed to parse test TOML");
+            Config::from_toml(parsed, PathBuf::from("."))
+        };
+
+        let config = parse("[[event_webhooks]]\nurl = \"https://hooks.example.com/spacebot\"\n")
+            .expect("failed to build Config");
+        let webhook = &config.event_webhooks[0];
+        assert_eq!(
+            webhook.events,
+            crate::event_webhooks::EventKind::ALL.to_vec()
+        );
+        assert_eq!(webhook.max_attempts, 5);
+        assert!(webhook.secret.is_none());
+
+        let error = parse(
+            "[[event_webhooks]]\nurl = \"https://hooks.example.com\"\nevents = [\"worker_started\"]\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("event_webhooks[0].events"));
+    }
+
+    #[test]
+    fn test_database_keys_read_from_the_secrets_store() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        let store =
+            crate::secrets::store::SecretsStore::new(instance_dir.path().join("secrets.redb"))
+                .unwrap();
+        store.set("db_key", "hunter2", b"master").unwrap();
+        drop(store);
+
+        assert_eq!(
+            read_secret(instance_dir.path(), "db_key", b"master").unwrap(),
+            "hunter2"
+        );
+        assert!(read_secret(instance_dir.path(), "db_key", b"wrong").is_err());
+        assert!(read_secret(instance_dir.path(), "missing", b"master").is_err());
+    }
 }
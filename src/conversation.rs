@@ -1,8 +1,14 @@
 //! Conversation history and context management.
 
+pub mod archive;
 pub mod channels;
+pub mod compactions;
 pub mod context;
 pub mod history;
+pub mod pins;
 
+pub use archive::HistoryArchive;
 pub use channels::ChannelStore;
+pub use compactions::CompactionStore;
 pub use history::{ConversationLogger, ProcessRunLogger, TimelineItem};
+pub use pins::PinStore;
@@ -0,0 +1,279 @@
+//! Archive of conversation history the compactor dropped.
+//!
+//! When the compactor summarizes or truncates a channel's oldest messages,
+//! their transcript is written to `archives/{channel}/{timestamp}.md` in the
+//! agent's directory and indexed in chunks, so `archive_search` can still find
+//! what was said after the summary replaced it. Chunks are kept in the
+//! `history_archive` table, with their embeddings and full-text index in the
+//! `archive_embeddings` LanceDB table. They are kept apart from memories so
+//! old transcripts don't crowd recall, the bulletin or maintenance.
+
+use crate::config::VectorIndexConfig;
+use crate::db::Db;
+use crate::error::Result;
+use crate::memory::{EmbeddingModel, EmbeddingTable};
+
+use sqlx::{Row as _, SqlitePool};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// LanceDB table the archive chunks are embedded in.
+pub const ARCHIVE_TABLE: &str = "archive_embeddings";
+
+/// Rough size of an indexed chunk, in chars. Chunks break between lines.
+const CHUNK_CHARS: usize = 2000;
+
+/// Candidates taken from each search for every result asked for, so some
+/// are left after filtering by channel.
+const CANDIDATES_PER_RESULT: usize = 4;
+
+/// RRF constant used to merge vector and full-text results.
+const RRF_K: f64 = 60.0;
+
+/// Compacted history for one agent, on disk and indexed for search.
+pub struct HistoryArchive {
+    pool: SqlitePool,
+    dir: PathBuf,
+    embedding_table: EmbeddingTable,
+    embedding_model: Arc<EmbeddingModel>,
+}
+
+/// A chunk of archived transcript.
+#[derive(Debug, Clone)]
+pub struct ArchiveChunk {
+    pub id: String,
+    pub channel_id: String,
+    /// The transcript file, relative to the archives directory.
+    pub path: String,
+    pub content: String,
+    pub archived_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl HistoryArchive {
+    pub fn new(
+        pool: SqlitePool,
+        dir: PathBuf,
+        embedding_table: EmbeddingTable,
+        embedding_model: Arc<EmbeddingModel>,
+    ) -> Self {
+        Self {
+            pool,
+            dir,
+            embedding_table,
+            embedding_model,
+        }
+    }
+
+    /// Open the agent's archive, creating its embeddings table if needed.
+    pub async fn open(
+        db: &Db,
+        dir: PathBuf,
+        index_config: VectorIndexConfig,
+        embedding_model: Arc<EmbeddingModel>,
+    ) -> Result<Self> {
+        let embedding_table = EmbeddingTable::open_or_create_named(&db.lance, ARCHIVE_TABLE)
+            .await?
+            .with_index_config(index_config);
+        if let Err(error) = embedding_table.ensure_fts_index().await {
+            tracing::warn!(%error, "failed to create archive FTS index");
+        }
+        Ok(Self {
+            pool: db.sqlite.clone(),
+            dir,
+            embedding_table,
+            embedding_model,
+        })
+    }
+
+    /// Write a compacted transcript to disk and index it. Returns the file
+    /// written, relative to the archives directory.
+    pub async fn archive(&self, channel_id: &str, transcript: &str) -> Result<PathBuf> {
+        let archived_at = chrono::Utc::now();
+        let file_name = format!("{}.md", archived_at.format("%Y-%m-%dT%H-%M-%S%.3f"));
+        let path = Path::new(&file_safe(channel_id)).join(file_name);
+        let full_path = self.dir.join(&path);
+        if let Some(parent) = full_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(
+            &full_path,
+            format!(
+                "# {channel_id}\n\nArchived {}\n\n{transcript}",
+                archived_at.to_rfc3339()
+            ),
+        )
+        .await?;
+
+        let path_text = path.to_string_lossy();
+        for (index, chunk) in chunk_transcript(transcript, CHUNK_CHARS)
+            .into_iter()
+            .enumerate()
+        {
+            let id = uuid::Uuid::new_v4().to_string();
+            let embedding = self.embedding_model.embed_one(chunk).await?;
+            sqlx::query(
+                "INSERT INTO history_archive (id, channel_id, path, chunk_index, content, archived_at) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(channel_id)
+            .bind(path_text.as_ref())
+            .bind(index as i64)
+            .bind(chunk)
+            .bind(archived_at)
+            .execute(&self.pool)
+            .await?;
+            self.embedding_table.store(&id, chunk, &embedding).await?;
+        }
+
+        Ok(path)
+    }
+
+    /// Find the archived chunks closest to `query`, from one channel or all of
+    /// them, best first.
+    pub async fn search(
+        &self,
+        query: &str,
+        channel_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ArchiveChunk>> {
+        let candidates = limit.saturating_mul(CANDIDATES_PER_RESULT);
+
+        // Either search can fail on a fresh table; the other still answers.
+        let query_embedding = self.embedding_model.embed_one(query).await?;
+        let vector_ids = match self
+            .embedding_table
+            .vector_search(&query_embedding, candidates)
+            .await
+        {
+            Ok(matches) => matches.into_iter().map(|(id, _)| id).collect(),
+            Err(error) => {
+                tracing::debug!(%error, "archive vector search unavailable");
+                Vec::new()
+            }
+        };
+        let text_ids = match self.embedding_table.text_search(query, candidates).await {
+            Ok(matches) => matches.into_iter().map(|(id, _)| id).collect(),
+            Err(error) => {
+                tracing::debug!(%error, "archive full-text search unavailable");
+                Vec::new()
+            }
+        };
+
+        let mut chunks = Vec::new();
+        for id in fuse_rankings(&[vector_ids, text_ids]) {
+            if chunks.len() >= limit {
+                break;
+            }
+            if let Some(chunk) = self.load(&id).await?
+                && channel_id.is_none_or(|channel_id| chunk.channel_id == channel_id)
+            {
+                chunks.push(chunk);
+            }
+        }
+        Ok(chunks)
+    }
+
+    async fn load(&self, id: &str) -> Result<Option<ArchiveChunk>> {
+        let row = sqlx::query(
+            "SELECT id, channel_id, path, content, archived_at FROM history_archive WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| ArchiveChunk {
+            id: row.try_get("id").unwrap_or_default(),
+            channel_id: row.try_get("channel_id").unwrap_or_default(),
+            path: row.try_get("path").unwrap_or_default(),
+            content: row.try_get("content").unwrap_or_default(),
+            archived_at: row
+                .try_get("archived_at")
+                .unwrap_or_else(|_| chrono::Utc::now()),
+        }))
+    }
+}
+
+/// A channel ID usable as a directory name.
+pub(crate) fn file_safe(channel_id: &str) -> String {
+    channel_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Split a transcript into chunks of whole lines of about `max_chars`. A line
+/// longer than that is a chunk of its own.
+fn chunk_transcript(transcript: &str, max_chars: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for line in transcript.split_inclusive('\n') {
+        if end > start && end - start + line.len() > max_chars {
+            chunks.push(&transcript[start..end]);
+            start = end;
+        }
+        end += line.len();
+    }
+    if end > start {
+        chunks.push(&transcript[start..end]);
+    }
+    chunks
+        .into_iter()
+        .map(str::trim)
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+/// Merge ranked ID lists with reciprocal rank fusion, best first.
+fn fuse_rankings(rankings: &[Vec<String>]) -> Vec<String> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for ranking in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            *scores.entry(id.as_str()).or_default() += 1.0 / (RRF_K + rank as f64 + 1.0);
+        }
+    }
+    let mut fused: Vec<(&str, f64)> = scores.into_iter().collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    fused.into_iter().map(|(id, _)| id.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcripts_chunk_between_lines() {
+        let transcript = "User: one\nAssistant: two\nUser: three\n";
+        assert_eq!(chunk_transcript(transcript, 100), vec![transcript.trim()]);
+        assert_eq!(
+            chunk_transcript(transcript, 25),
+            vec!["User: one\nAssistant: two", "User: three"]
+        );
+        assert_eq!(
+            chunk_transcript(transcript, 5),
+            vec!["User: one", "Assistant: two", "User: three"]
+        );
+        assert!(chunk_transcript("\n\n", 5).is_empty());
+    }
+
+    #[test]
+    fn rankings_fuse_by_reciprocal_rank() {
+        let vector = vec!["a".to_string(), "b".to_string()];
+        let text = vec!["b".to_string(), "c".to_string()];
+        assert_eq!(fuse_rankings(&[vector, text]), vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn channel_ids_become_directory_names() {
+        assert_eq!(file_safe("discord:123:456"), "discord_123_456");
+        assert_eq!(file_safe("../etc"), "___etc");
+    }
+}
@@ -19,6 +19,8 @@ pub struct ChannelInfo {
     pub id: String,
     pub platform: String,
     pub display_name: Option<String>,
+    /// Short title of the conversation, generated or set through the API.
+    pub title: Option<String>,
     pub platform_meta: Option<serde_json::Value>,
     pub is_active: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
@@ -42,7 +44,7 @@ impl ChannelStore {
         let display_name = extract_display_name(&platform, &channel_id, metadata);
         let platform_meta = extract_platform_meta(&platform, metadata);
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) = sqlx::query(
                 "INSERT INTO channels (id, platform, display_name, platform_meta, last_activity_at) \
                  VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP) \
@@ -68,7 +70,7 @@ impl ChannelStore {
         let pool = self.pool.clone();
         let channel_id = channel_id.to_string();
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) =
                 sqlx::query("UPDATE channels SET last_activity_at = CURRENT_TIMESTAMP WHERE id = ?")
                     .bind(&channel_id)
@@ -80,10 +82,64 @@ impl ChannelStore {
         });
     }
 
+    /// Set a channel's title. Returns false when the channel isn't known.
+    pub async fn set_title(&self, channel_id: &str, title: &str) -> crate::error::Result<bool> {
+        let result = sqlx::query("UPDATE channels SET title = ? WHERE id = ?")
+            .bind(title)
+            .bind(channel_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set a channel's title unless it already has one, so a generated title
+    /// never replaces one set by hand. Returns whether it was set.
+    pub async fn set_title_if_unset(
+        &self,
+        channel_id: &str,
+        title: &str,
+    ) -> crate::error::Result<bool> {
+        let result = sqlx::query("UPDATE channels SET title = ? WHERE id = ? AND title IS NULL")
+            .bind(title)
+            .bind(channel_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A channel's rolling summary, if it has one yet.
+    pub async fn summary(&self, channel_id: &str) -> crate::error::Result<Option<String>> {
+        let summary =
+            sqlx::query_scalar::<_, Option<String>>("SELECT summary FROM channels WHERE id = ?")
+                .bind(channel_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(summary.flatten())
+    }
+
+    /// Replace a channel's rolling summary. Returns false when the channel
+    /// isn't known.
+    pub async fn set_summary(&self, channel_id: &str, summary: &str) -> crate::error::Result<bool> {
+        let result = sqlx::query("UPDATE channels SET summary = ? WHERE id = ?")
+            .bind(summary)
+            .bind(channel_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// List all active channels, most recently active first.
     pub async fn list_active(&self) -> crate::error::Result<Vec<ChannelInfo>> {
         let rows = sqlx::query(
-            "SELECT id, platform, display_name, platform_meta, is_active, created_at, last_activity_at \
+            "SELECT id, platform, display_name, title, platform_meta, is_active, created_at, last_activity_at \
              FROM channels \
              WHERE is_active = 1 \
              ORDER BY last_activity_at DESC"
@@ -140,7 +196,7 @@ impl ChannelStore {
     /// Get a single channel by exact ID.
     pub async fn get(&self, channel_id: &str) -> crate::error::Result<Option<ChannelInfo>> {
         let row = sqlx::query(
-            "SELECT id, platform, display_name, platform_meta, is_active, created_at, last_activity_at \
+            "SELECT id, platform, display_name, title, platform_meta, is_active, created_at, last_activity_at \
              FROM channels \
              WHERE id = ?"
         )
@@ -170,6 +226,7 @@ fn row_to_channel_info(row: sqlx::sqlite::SqliteRow) -> ChannelInfo {
         id: row.try_get("id").unwrap_or_default(),
         platform: row.try_get("platform").unwrap_or_default(),
         display_name: row.try_get("display_name").ok().flatten(),
+        title: row.try_get("title").ok().flatten(),
         platform_meta,
         is_active: row.try_get::<i32, _>("is_active").unwrap_or(1) == 1,
         created_at: row
@@ -262,6 +319,30 @@ fn extract_platform_meta(
                 meta.insert("twitch_channel".to_string(), value.clone());
             }
         }
+        "irc" => {
+            if let Some(value) = metadata.get("irc_channel") {
+                meta.insert("irc_channel".to_string(), value.clone());
+            }
+        }
+        "xmpp" => {
+            for key in ["xmpp_room", "xmpp_jid"] {
+                if let Some(value) = metadata.get(key) {
+                    meta.insert(key.to_string(), value.clone());
+                }
+            }
+        }
+        "whatsapp" => {
+            if let Some(value) = metadata.get("whatsapp_from") {
+                meta.insert("whatsapp_from".to_string(), value.clone());
+            }
+        }
+        "email" => {
+            for key in ["email_from", "email_subject"] {
+                if let Some(value) = metadata.get(key) {
+                    meta.insert(key.to_string(), value.clone());
+                }
+            }
+        }
         _ => {}
     }
 
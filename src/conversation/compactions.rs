@@ -0,0 +1,97 @@
+//! Log of the compactions run on each channel's history.
+//!
+//! The compactor writes a record when each run finishes, whatever started it
+//! and whether it succeeded, so the API can show how a conversation was
+//! compacted and what each run freed.
+
+use crate::error::Result;
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+/// A finished compaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionRecord {
+    pub id: String,
+    pub channel_id: String,
+    /// `trim_tool_outputs`, `background`, `aggressive` or `emergency_truncate`.
+    pub action: String,
+    /// `threshold`, `before_turn` or `manual`.
+    pub trigger: String,
+    /// Estimated history tokens when the run started.
+    pub tokens_before: usize,
+    /// Estimated history tokens when it finished.
+    pub tokens_after: usize,
+    /// Messages summarized or dropped, or tool outputs trimmed.
+    pub messages_compacted: usize,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Compaction log storage (SQLite).
+#[derive(Debug, Clone)]
+pub struct CompactionStore {
+    pool: SqlitePool,
+}
+
+impl CompactionStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(&self, record: &CompactionRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO compaction_log (id, channel_id, action, trigger, tokens_before, \
+             tokens_after, messages_compacted, error, started_at, finished_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&record.id)
+        .bind(&record.channel_id)
+        .bind(&record.action)
+        .bind(&record.trigger)
+        .bind(record.tokens_before as i64)
+        .bind(record.tokens_after as i64)
+        .bind(record.messages_compacted as i64)
+        .bind(&record.error)
+        .bind(record.started_at)
+        .bind(record.finished_at)
+        .execute(&self.pool)
+        .await
+        .context("failed to save compaction record")?;
+        Ok(())
+    }
+
+    /// A channel's most recent compactions, newest first.
+    pub async fn list(&self, channel_id: &str, limit: i64) -> Result<Vec<CompactionRecord>> {
+        let rows = sqlx::query(
+            "SELECT id, channel_id, action, trigger, tokens_before, tokens_after, \
+             messages_compacted, error, started_at, finished_at \
+             FROM compaction_log WHERE channel_id = ? ORDER BY started_at DESC LIMIT ?",
+        )
+        .bind(channel_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load compactions")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| CompactionRecord {
+                id: row.try_get("id").unwrap_or_default(),
+                channel_id: row.try_get("channel_id").unwrap_or_default(),
+                action: row.try_get("action").unwrap_or_default(),
+                trigger: row.try_get("trigger").unwrap_or_default(),
+                tokens_before: row.try_get::<i64, _>("tokens_before").unwrap_or(0) as usize,
+                tokens_after: row.try_get::<i64, _>("tokens_after").unwrap_or(0) as usize,
+                messages_compacted: row.try_get::<i64, _>("messages_compacted").unwrap_or(0)
+                    as usize,
+                error: row.try_get("error").ok().flatten(),
+                started_at: row.try_get("started_at").unwrap_or_else(|_| Utc::now()),
+                finished_at: row.try_get("finished_at").unwrap_or_else(|_| Utc::now()),
+            })
+            .collect())
+    }
+}
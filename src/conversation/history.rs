@@ -1,18 +1,41 @@
 //! Conversation message persistence (SQLite).
 
+use crate::shutdown::PendingWrite;
 use crate::{BranchId, ChannelId, WorkerId};
 
 use serde::Serialize;
 use sqlx::{Row as _, SqlitePool};
 use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// Most messages the background writer inserts in one transaction.
+const WRITE_BATCH_SIZE: usize = 64;
 
 /// Persists conversation messages (user and assistant) to SQLite.
 ///
-/// All write methods are fire-and-forget — they spawn a tokio task and return
-/// immediately so the caller never blocks on a DB write.
+/// All write methods are fire-and-forget: they queue the message for a
+/// background writer and return immediately, so the caller never blocks on
+/// a DB write. The writer inserts whatever has queued up in a single
+/// transaction, which keeps logging from contending with memory queries
+/// when many messages arrive at once.
 #[derive(Debug, Clone)]
 pub struct ConversationLogger {
     pool: SqlitePool,
+    writer: mpsc::UnboundedSender<PendingMessage>,
+}
+
+/// A message waiting for the background writer.
+#[derive(Debug)]
+struct PendingMessage {
+    id: String,
+    channel_id: String,
+    role: &'static str,
+    sender_name: Option<String>,
+    sender_id: Option<String>,
+    content: String,
+    metadata: Option<String>,
+    /// Keeps shutdown waiting until the message is written.
+    _pending: PendingWrite,
 }
 
 /// A persisted conversation message.
@@ -30,7 +53,9 @@ pub struct ConversationMessage {
 
 impl ConversationLogger {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        let (writer, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(write_messages(pool.clone(), receiver));
+        Self { pool, writer }
     }
 
     /// Log a user message. Fire-and-forget.
@@ -42,56 +67,38 @@ impl ConversationLogger {
         content: &str,
         metadata: &HashMap<String, serde_json::Value>,
     ) {
-        let pool = self.pool.clone();
-        let id = uuid::Uuid::new_v4().to_string();
-        let channel_id = channel_id.to_string();
-        let sender_name = sender_name.to_string();
-        let sender_id = sender_id.to_string();
-        let content = content.to_string();
-        let metadata_json = serde_json::to_string(metadata).ok();
-
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO conversation_messages (id, channel_id, role, sender_name, sender_id, content, metadata) \
-                 VALUES (?, ?, 'user', ?, ?, ?, ?)"
-            )
-            .bind(&id)
-            .bind(&channel_id)
-            .bind(&sender_name)
-            .bind(&sender_id)
-            .bind(&content)
-            .bind(&metadata_json)
-            .execute(&pool)
-            .await
-            {
-                tracing::warn!(%error, "failed to persist user message");
-            }
+        self.queue(PendingMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            role: "user",
+            sender_name: Some(sender_name.to_string()),
+            sender_id: Some(sender_id.to_string()),
+            content: content.to_string(),
+            metadata: serde_json::to_string(metadata).ok(),
+            _pending: PendingWrite::new(),
         });
     }
 
     /// Log a bot (assistant) message. Fire-and-forget.
     pub fn log_bot_message(&self, channel_id: &ChannelId, content: &str) {
-        let pool = self.pool.clone();
-        let id = uuid::Uuid::new_v4().to_string();
-        let channel_id = channel_id.to_string();
-        let content = content.to_string();
-
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO conversation_messages (id, channel_id, role, content) \
-                 VALUES (?, ?, 'assistant', ?)",
-            )
-            .bind(&id)
-            .bind(&channel_id)
-            .bind(&content)
-            .execute(&pool)
-            .await
-            {
-                tracing::warn!(%error, "failed to persist bot message");
-            }
+        self.queue(PendingMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            role: "assistant",
+            sender_name: None,
+            sender_id: None,
+            content: content.to_string(),
+            metadata: None,
+            _pending: PendingWrite::new(),
         });
     }
 
+    fn queue(&self, message: PendingMessage) {
+        if self.writer.send(message).is_err() {
+            tracing::warn!("conversation writer stopped, message not persisted");
+        }
+    }
+
     /// Load recent messages for a channel (oldest first).
     pub async fn load_recent(
         &self,
@@ -202,9 +209,40 @@ pub enum TimelineItem {
     },
 }
 
+/// Drain the queue in batches until every logger handle is dropped.
+async fn write_messages(pool: SqlitePool, mut receiver: mpsc::UnboundedReceiver<PendingMessage>) {
+    let mut batch = Vec::with_capacity(WRITE_BATCH_SIZE);
+    while receiver.recv_many(&mut batch, WRITE_BATCH_SIZE).await > 0 {
+        if let Err(error) = insert_messages(&pool, &batch).await {
+            tracing::warn!(%error, count = batch.len(), "failed to persist conversation messages");
+        }
+        batch.clear();
+    }
+}
+
+async fn insert_messages(pool: &SqlitePool, batch: &[PendingMessage]) -> sqlx::Result<()> {
+    let mut transaction = pool.begin().await?;
+    for message in batch {
+        sqlx::query(
+            "INSERT INTO conversation_messages (id, channel_id, role, sender_name, sender_id, content, metadata) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&message.id)
+        .bind(&message.channel_id)
+        .bind(message.role)
+        .bind(&message.sender_name)
+        .bind(&message.sender_id)
+        .bind(&message.content)
+        .bind(&message.metadata)
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await
+}
+
 /// Persists branch and worker run records for channel timeline history.
 ///
-/// All write methods are fire-and-forget, same pattern as ConversationLogger.
+/// All write methods are fire-and-forget: each spawns its own write task.
 #[derive(Debug, Clone)]
 pub struct ProcessRunLogger {
     pool: SqlitePool,
@@ -227,7 +265,7 @@ impl ProcessRunLogger {
         let channel_id = channel_id.to_string();
         let description = description.to_string();
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) = sqlx::query(
                 "INSERT OR IGNORE INTO branch_runs (id, channel_id, description) VALUES (?, ?, ?)",
             )
@@ -248,7 +286,7 @@ impl ProcessRunLogger {
         let id = branch_id.to_string();
         let conclusion = conclusion.to_string();
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) = sqlx::query(
                 "UPDATE branch_runs SET conclusion = ?, completed_at = CURRENT_TIMESTAMP WHERE id = ?"
             )
@@ -274,7 +312,7 @@ impl ProcessRunLogger {
         let channel_id = channel_id.map(|c| c.to_string());
         let task = task.to_string();
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) = sqlx::query(
                 "INSERT OR IGNORE INTO worker_runs (id, channel_id, task) VALUES (?, ?, ?)",
             )
@@ -295,7 +333,7 @@ impl ProcessRunLogger {
         let id = worker_id.to_string();
         let status = status.to_string();
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) = sqlx::query("UPDATE worker_runs SET status = ? WHERE id = ?")
                 .bind(&status)
                 .bind(&id)
@@ -313,7 +351,7 @@ impl ProcessRunLogger {
         let id = worker_id.to_string();
         let result = result.to_string();
 
-        tokio::spawn(async move {
+        crate::shutdown::spawn_write(async move {
             if let Err(error) = sqlx::query(
                 "UPDATE worker_runs SET result = ?, status = 'done', completed_at = CURRENT_TIMESTAMP WHERE id = ?"
             )
@@ -0,0 +1,289 @@
+//! Pinned messages: parts of a conversation compaction must keep verbatim.
+//!
+//! A pin holds the text of the message it was made from. The compactor keeps
+//! every history message containing a pin's content through summarization,
+//! tool output trimming and emergency truncation alike, re-inserting it ahead
+//! of the summary. Kept messages lose their tool calls and results, which
+//! can't outlive their pairs, but keep all their text. Pins are made with the
+//! `pin_message` channel tool or the `/api/channels/pins` routes.
+
+use crate::error::Result;
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use rig::OneOrMany;
+use rig::message::{AssistantContent, Message, UserContent};
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+/// Most pins a channel can have, so pinned messages can't fill the context.
+pub const MAX_PINS_PER_CHANNEL: usize = 20;
+
+/// A message pinned in a conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct Pin {
+    pub id: String,
+    pub channel_id: String,
+    /// The `conversation_messages` row pinned, if it was pinned from one.
+    pub message_id: Option<String>,
+    pub content: String,
+    /// Who made the pin: `agent` for the channel tool, `api` for the API.
+    pub pinned_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Pinned message storage (SQLite).
+#[derive(Debug, Clone)]
+pub struct PinStore {
+    pool: SqlitePool,
+}
+
+impl PinStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Pin `content` in a channel. Pinning content that's already pinned
+    /// returns the existing pin.
+    pub async fn pin(
+        &self,
+        channel_id: &str,
+        message_id: Option<&str>,
+        content: &str,
+        pinned_by: &str,
+    ) -> Result<Pin> {
+        let pins = self.list(channel_id).await?;
+        if let Some(pin) = pins.iter().find(|pin| pin.content == content) {
+            return Ok(pin.clone());
+        }
+        if pins.len() >= MAX_PINS_PER_CHANNEL {
+            return Err(anyhow::anyhow!(
+                "this conversation already has {MAX_PINS_PER_CHANNEL} pinned messages, unpin one first"
+            )
+            .into());
+        }
+
+        let pin = Pin {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            message_id: message_id.map(str::to_string),
+            content: content.to_string(),
+            pinned_by: pinned_by.to_string(),
+            created_at: Utc::now(),
+        };
+        sqlx::query(
+            "INSERT INTO pinned_messages (id, channel_id, message_id, content, pinned_by, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&pin.id)
+        .bind(&pin.channel_id)
+        .bind(&pin.message_id)
+        .bind(&pin.content)
+        .bind(&pin.pinned_by)
+        .bind(pin.created_at)
+        .execute(&self.pool)
+        .await
+        .context("failed to save pin")?;
+
+        Ok(pin)
+    }
+
+    /// Remove a pin. Returns whether it existed.
+    pub async fn unpin(&self, channel_id: &str, pin_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM pinned_messages WHERE channel_id = ? AND id = ?")
+            .bind(channel_id)
+            .bind(pin_id)
+            .execute(&self.pool)
+            .await
+            .context("failed to delete pin")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A channel's pins, oldest first.
+    pub async fn list(&self, channel_id: &str) -> Result<Vec<Pin>> {
+        let rows = sqlx::query(
+            "SELECT id, channel_id, message_id, content, pinned_by, created_at \
+             FROM pinned_messages WHERE channel_id = ? ORDER BY created_at ASC",
+        )
+        .bind(channel_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load pins")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Pin {
+                id: row.try_get("id").unwrap_or_default(),
+                channel_id: row.try_get("channel_id").unwrap_or_default(),
+                message_id: row.try_get("message_id").ok().flatten(),
+                content: row.try_get("content").unwrap_or_default(),
+                pinned_by: row.try_get("pinned_by").unwrap_or_default(),
+                created_at: row.try_get("created_at").unwrap_or_else(|_| Utc::now()),
+            })
+            .collect())
+    }
+
+    /// The content of a channel's pins, for matching history messages.
+    pub async fn contents(&self, channel_id: &str) -> Result<Vec<String>> {
+        Ok(self
+            .list(channel_id)
+            .await?
+            .into_iter()
+            .map(|pin| pin.content)
+            .filter(|content| !content.trim().is_empty())
+            .collect())
+    }
+
+    /// The newest logged message in a channel containing `quote`, as
+    /// `(message_id, content)`.
+    pub async fn find_logged(
+        &self,
+        channel_id: &str,
+        quote: &str,
+    ) -> Result<Option<(String, String)>> {
+        let row = sqlx::query(
+            "SELECT id, content FROM conversation_messages \
+             WHERE channel_id = ? AND instr(content, ?) > 0 \
+             ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(channel_id)
+        .bind(quote)
+        .fetch_optional(&self.pool)
+        .await
+        .context("failed to search conversation messages")?;
+        Ok(row.map(|row| {
+            (
+                row.try_get("id").unwrap_or_default(),
+                row.try_get("content").unwrap_or_default(),
+            )
+        }))
+    }
+
+    /// A logged message by ID, if it's in `channel_id`.
+    pub async fn load_logged(&self, channel_id: &str, message_id: &str) -> Result<Option<String>> {
+        let row = sqlx::query(
+            "SELECT content FROM conversation_messages WHERE channel_id = ? AND id = ?",
+        )
+        .bind(channel_id)
+        .bind(message_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("failed to load conversation message")?;
+        Ok(row.map(|row| row.try_get("content").unwrap_or_default()))
+    }
+}
+
+/// The text of a history message: user and assistant text, and what the
+/// agent replied through the `reply` tool.
+pub fn message_texts(message: &Message) -> Vec<&str> {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .filter_map(|item| match item {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        Message::Assistant { content, .. } => content
+            .iter()
+            .filter_map(|item| match item {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                AssistantContent::ToolCall(call) if call.function.name == "reply" => {
+                    call.function.arguments.get("content")?.as_str()
+                }
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// Whether a history message contains any of `pins`.
+pub fn is_pinned(message: &Message, pins: &[String]) -> bool {
+    !pins.is_empty()
+        && message_texts(message)
+            .iter()
+            .any(|text| pins.iter().any(|pin| text.contains(pin.as_str())))
+}
+
+/// A pinned message as compaction keeps it: all its text, without tool calls
+/// or results.
+pub fn pinned_copy(message: &Message) -> Option<Message> {
+    let texts = message_texts(message);
+    match message {
+        Message::User { .. } => {
+            let content = OneOrMany::many(texts.into_iter().map(UserContent::text)).ok()?;
+            Some(Message::User { content })
+        }
+        Message::Assistant { .. } => {
+            let content = OneOrMany::many(texts.into_iter().map(AssistantContent::text)).ok()?;
+            Some(Message::Assistant { id: None, content })
+        }
+    }
+}
+
+/// Take the pinned messages out of `messages`, as they're kept through
+/// compaction, oldest first.
+pub fn take_pinned(messages: &mut Vec<Message>, pins: &[String]) -> Vec<Message> {
+    if pins.is_empty() {
+        return Vec::new();
+    }
+    let mut pinned = Vec::new();
+    messages.retain(|message| {
+        if !is_pinned(message, pins) {
+            return true;
+        }
+        pinned.extend(pinned_copy(message));
+        false
+    });
+    pinned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> Message {
+        Message::User {
+            content: OneOrMany::one(UserContent::text(text)),
+        }
+    }
+
+    #[test]
+    fn pinned_messages_are_taken_out_by_content() {
+        let reply = Message::Assistant {
+            id: None,
+            content: OneOrMany::one(AssistantContent::tool_call(
+                "call",
+                "reply",
+                serde_json::json!({ "content": "I'll always answer in French." }),
+            )),
+        };
+        let mut messages = vec![
+            user("[alice]: hello"),
+            user("[alice]: always answer in French"),
+            reply,
+            user("[alice]: thanks"),
+        ];
+        let pins = vec![
+            "always answer in French".to_string(),
+            "answer in French.".to_string(),
+        ];
+
+        let pinned = take_pinned(&mut messages, &pins);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(pinned.len(), 2);
+        assert_eq!(
+            message_texts(&pinned[0]),
+            vec!["[alice]: always answer in French"]
+        );
+        // The reply's tool call is kept as plain text.
+        assert!(matches!(
+            &pinned[1],
+            Message::Assistant { content, .. }
+                if matches!(content.first_ref(), AssistantContent::Text(text)
+                    if text.text == "I'll always answer in French.")
+        ));
+
+        assert!(take_pinned(&mut messages, &[]).is_empty());
+    }
+}
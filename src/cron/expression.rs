@@ -0,0 +1,222 @@
+//! Cron expressions: the five-field `minute hour day month weekday` format.
+//!
+//! Fields take `*`, single values, ranges (`1-5`), lists (`1,15`) and steps
+//! (`*/15`, `9-17/2`). Months and weekdays also take three-letter names
+//! (`jan`, `mon`), and weekday 7 is Sunday like 0. The `@hourly`, `@daily`,
+//! `@weekly`, `@monthly` and `@yearly` shorthands are accepted. As in
+//! classic cron, when both day of month and weekday are restricted, a time
+//! matches if either does.
+
+use chrono::{DateTime, Datelike as _, TimeZone, Timelike as _};
+
+use std::fmt;
+use std::str::FromStr;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Why a cron expression couldn't be parsed.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("invalid cron expression '{expression}': {reason}")]
+pub struct CronExpressionError {
+    expression: String,
+    reason: String,
+}
+
+/// A parsed cron expression. Each field is a bit set of the values it
+/// matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpression {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day of month and weekday were given, rather than starting
+    /// with `*`.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronExpression {
+    /// Whether the expression fires in the minute containing `time`, read in
+    /// `time`'s timezone.
+    pub fn matches<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+
+        bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, time.month())
+            && day_matches
+    }
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+impl fmt::Display for CronExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for CronExpression {
+    type Err = CronExpressionError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| CronExpressionError {
+            expression: expression.to_string(),
+            reason,
+        };
+
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(error(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            )));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAY_NAMES)
+            .map_err(|reason| error(format!("weekday: {reason}")))?;
+        // 7 is Sunday too.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            source: expression.trim().to_string(),
+            minutes: parse_field(minute, 0, 59, &[])
+                .map_err(|reason| error(format!("minute: {reason}")))?,
+            hours: parse_field(hour, 0, 23, &[])
+                .map_err(|reason| error(format!("hour: {reason}")))?,
+            days: parse_field(day, 1, 31, &[])
+                .map_err(|reason| error(format!("day of month: {reason}")))?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES)
+                .map_err(|reason| error(format!("month: {reason}")))?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+}
+
+/// Parse one field into a bit set of the values between `min` and `max` it
+/// covers. `names` are accepted for the values starting at `min`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |raw: &str| -> Result<u32, String> {
+        let lowercase = raw.to_ascii_lowercase();
+        let value = match names.iter().position(|name| *name == lowercase) {
+            Some(index) => min + index as u32,
+            None => raw
+                .parse()
+                .map_err(|_| format!("'{raw}' is not a number"))?,
+        };
+        if value < min || value > max {
+            return Err(format!("{value} is outside {min}-{max}"));
+        }
+        Ok(value)
+    };
+
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("'{step}' is not a valid step"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` runs from 5 to the end of the range.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let value = value(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("range '{range}' runs backwards"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<chrono::Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+    }
+
+    #[test]
+    fn matches_fields_ranges_and_steps() {
+        let weekday_mornings: CronExpression = "*/15 9-17 * * mon-fri".parse().unwrap();
+        // 2026-03-02 is a Monday.
+        assert!(weekday_mornings.matches(&at("2026-03-02T09:00:00Z")));
+        assert!(weekday_mornings.matches(&at("2026-03-02T17:45:30Z")));
+        assert!(!weekday_mornings.matches(&at("2026-03-02T09:05:00Z")));
+        assert!(!weekday_mornings.matches(&at("2026-03-02T18:00:00Z")));
+        assert!(!weekday_mornings.matches(&at("2026-03-01T09:00:00Z")));
+
+        let sundays: CronExpression = "0 8 * * 7".parse().unwrap();
+        assert!(sundays.matches(&at("2026-03-01T08:00:00Z")));
+
+        // Day of month and weekday both restricted: either one matches.
+        let first_or_friday: CronExpression = "0 0 1 * fri".parse().unwrap();
+        assert!(first_or_friday.matches(&at("2026-03-01T00:00:00Z")));
+        assert!(first_or_friday.matches(&at("2026-03-06T00:00:00Z")));
+        assert!(!first_or_friday.matches(&at("2026-03-07T00:00:00Z")));
+
+        let daily: CronExpression = "@daily".parse().unwrap();
+        assert!(daily.matches(&at("2026-07-14T00:00:00Z")));
+        assert!(!daily.matches(&at("2026-07-14T01:00:00Z")));
+        assert_eq!(daily.to_string(), "@daily");
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * foo *",
+        ] {
+            assert!(
+                expression.parse::<CronExpression>().is_err(),
+                "{expression} should not parse"
+            );
+        }
+    }
+}
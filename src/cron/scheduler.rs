@@ -259,6 +259,24 @@ impl Scheduler {
                             }
                         };
 
+                        // Let the channels that opted in know, without
+                        // holding up the schedule.
+                        let event = if should_disable {
+                            format!(
+                                "Cron job '{job_id}' failed {MAX_CONSECUTIVE_FAILURES} times in a row and was disabled. Last error: {error}\nIts task: {}",
+                                job.prompt
+                            )
+                        } else {
+                            format!(
+                                "Cron job '{job_id}' failed: {error}\nIts task: {}",
+                                job.prompt
+                            )
+                        };
+                        let deps = context.deps.clone();
+                        tokio::spawn(async move {
+                            crate::agent::outreach::report(&deps, &event).await;
+                        });
+
                         if should_disable {
                             tracing::warn!(
                                 cron_id = %job_id,
@@ -602,6 +620,12 @@ async fn run_cron_job(job: &CronJob, context: &CronContext) -> Result<()> {
             Ok(Some(OutboundResponse::RichMessage { text, .. })) => {
                 collected_text.push(text);
             }
+            Ok(Some(OutboundResponse::Choices { prompt, options })) => {
+                collected_text.push(crate::choices_text(&prompt, &options));
+            }
+            Ok(Some(OutboundResponse::Rich { text, card })) => {
+                collected_text.push(crate::rich_text(&text, &card));
+            }
             Ok(Some(_)) => {
                 // Status updates, stream chunks, etc. — ignore for cron jobs
             }
@@ -450,13 +450,14 @@ fn cleanup_stale_files(paths: &DaemonPaths) {
 }
 
 /// Wait for the daemon process to exit after sending a shutdown command.
-/// Polls the PID with a short interval, times out after 10 seconds.
-pub fn wait_for_exit(pid: u32) -> bool {
-    for _ in 0..100 {
+/// Polls the PID with a short interval until `timeout`.
+pub fn wait_for_exit(pid: u32, timeout: std::time::Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    while std::time::Instant::now() < deadline {
         if !is_process_alive(pid) {
             return true;
         }
         std::thread::sleep(std::time::Duration::from_millis(100));
     }
-    false
+    !is_process_alive(pid)
 }
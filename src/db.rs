@@ -1,9 +1,14 @@
 //! Database connection management and migrations.
 
+use crate::config::{DatabaseConfig, SynchronousMode};
 use crate::error::{DbError, Result};
 use anyhow::Context as _;
-use sqlx::SqlitePool;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous,
+};
+use sqlx::{ConnectOptions as _, Connection as _, SqlitePool};
 use std::path::Path;
+use std::time::Duration;
 
 /// Database connections bundle.
 pub struct Db {
@@ -21,12 +26,21 @@ use std::sync::Arc;
 
 impl Db {
     /// Connect to all databases and run migrations.
-    pub async fn connect(data_dir: &Path) -> Result<Self> {
+    pub async fn connect(data_dir: &Path, database: &DatabaseConfig) -> Result<Self> {
         // SQLite
-        let sqlite_url = format!("sqlite:{}?mode=rwc", data_dir.join("spacebot.db").display());
-        let sqlite = SqlitePool::connect(&sqlite_url)
-            .await
-            .with_context(|| "failed to connect to SQLite")?;
+        let sqlite_path = data_dir.join("spacebot.db");
+        let sqlite = match &database.encryption_key {
+            Some(key) => connect_encrypted(&sqlite_path, key, database).await?,
+            None => {
+                let options = SqliteConnectOptions::new()
+                    .filename(&sqlite_path)
+                    .create_if_missing(true);
+                pool_options(database)
+                    .connect_with(tuned_options(options, database))
+                    .await
+                    .with_context(|| "failed to connect to SQLite")?
+            }
+        };
 
         // Run migrations
         sqlx::migrate!("./migrations")
@@ -66,3 +80,282 @@ impl Db {
         // LanceDB and redb close automatically when dropped
     }
 }
+
+/// Read-only health of an agent's SQLite database.
+#[derive(Debug)]
+pub struct SqliteHealth {
+    /// Problems reported by `PRAGMA quick_check`. Empty when healthy.
+    pub problems: Vec<String>,
+    /// Migrations that will run on the next start.
+    pub pending_migrations: usize,
+}
+
+/// Check an existing SQLite database without modifying it: run
+/// `PRAGMA quick_check` and count migrations not yet applied.
+pub async fn check_sqlite(data_dir: &Path, database: &DatabaseConfig) -> Result<SqliteHealth> {
+    let sqlite_path = data_dir.join("spacebot.db");
+    let mut options = SqliteConnectOptions::new()
+        .filename(&sqlite_path)
+        .read_only(true);
+    if let Some(key) = &database.encryption_key {
+        options = options.pragma("key", quote_key(key));
+    }
+    let mut connection = options.connect().await.map_err(DbError::SqliteConnect)?;
+
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_all(&mut connection)
+        .await
+        .map_err(DbError::SqliteConnect)?
+        .into_iter()
+        .filter(|line: &String| line != "ok")
+        .collect();
+
+    // A database that has never been migrated has no migrations table.
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(&mut connection)
+            .await
+            .unwrap_or_default();
+    let pending_migrations = sqlx::migrate!("./migrations")
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .count();
+
+    connection.close().await.ok();
+    Ok(SqliteHealth {
+        problems,
+        pending_migrations,
+    })
+}
+
+/// Pool sizing from the database config.
+fn pool_options(database: &DatabaseConfig) -> SqlitePoolOptions {
+    SqlitePoolOptions::new().max_connections(database.max_connections)
+}
+
+/// Apply the journal mode, synchronous and busy timeout settings.
+fn tuned_options(options: SqliteConnectOptions, database: &DatabaseConfig) -> SqliteConnectOptions {
+    let journal_mode = if database.wal {
+        SqliteJournalMode::Wal
+    } else {
+        SqliteJournalMode::Delete
+    };
+    let synchronous = match database.synchronous {
+        SynchronousMode::Off => SqliteSynchronous::Off,
+        SynchronousMode::Normal => SqliteSynchronous::Normal,
+        SynchronousMode::Full => SqliteSynchronous::Full,
+        SynchronousMode::Extra => SqliteSynchronous::Extra,
+    };
+    options
+        .journal_mode(journal_mode)
+        .synchronous(synchronous)
+        .busy_timeout(Duration::from_millis(database.busy_timeout_ms))
+}
+
+/// Connection options for a SQLCipher database. sqlx always issues the
+/// `key` pragma first, as SQLCipher requires.
+fn encrypted_options(path: &Path, key: &str) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .pragma("key", quote_key(key))
+}
+
+fn quote_key(key: &str) -> String {
+    format!("'{}'", key.replace('\'', "''"))
+}
+
+/// Open an encrypted SQLite pool, encrypting an existing plaintext database
+/// in place on first use.
+async fn connect_encrypted(
+    path: &Path,
+    key: &str,
+    database: &DatabaseConfig,
+) -> Result<SqlitePool> {
+    ensure_sqlcipher().await?;
+
+    if path.exists() && !opens_with_key(path, key).await {
+        if !opens_without_key(path).await {
+            return Err(DbError::WrongEncryptionKey(path.display().to_string()).into());
+        }
+        encrypt_in_place(path, key).await?;
+    }
+
+    let pool = pool_options(database)
+        .connect_with(tuned_options(encrypted_options(path, key), database))
+        .await
+        .with_context(|| "failed to connect to encrypted SQLite")?;
+    Ok(pool)
+}
+
+/// Plain SQLite silently ignores `PRAGMA key`, which would leave the
+/// database unencrypted. Refuse to continue unless SQLCipher is linked.
+async fn ensure_sqlcipher() -> Result<()> {
+    let mut connection = SqliteConnectOptions::new()
+        .in_memory(true)
+        .connect()
+        .await
+        .map_err(DbError::SqliteConnect)?;
+    let cipher_version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(&mut connection)
+        .await
+        .map_err(DbError::SqliteConnect)?;
+    connection.close().await.ok();
+
+    match cipher_version {
+        Some(_) => Ok(()),
+        None => Err(DbError::EncryptionUnavailable.into()),
+    }
+}
+
+async fn can_read_schema(options: SqliteConnectOptions) -> bool {
+    let Ok(mut connection) = options.connect().await else {
+        return false;
+    };
+    let readable = sqlx::query("SELECT count(*) FROM sqlite_master")
+        .fetch_one(&mut connection)
+        .await
+        .is_ok();
+    connection.close().await.ok();
+    readable
+}
+
+async fn opens_with_key(path: &Path, key: &str) -> bool {
+    can_read_schema(encrypted_options(path, key).create_if_missing(false)).await
+}
+
+async fn opens_without_key(path: &Path) -> bool {
+    can_read_schema(SqliteConnectOptions::new().filename(path)).await
+}
+
+/// Copy a plaintext database into a new encrypted file with
+/// `sqlcipher_export`, then swap it into place.
+async fn encrypt_in_place(path: &Path, key: &str) -> Result<()> {
+    let encrypted_path = path.with_extension("db.encrypting");
+    if encrypted_path.exists() {
+        std::fs::remove_file(&encrypted_path)
+            .with_context(|| "failed to remove stale encryption temp file")?;
+    }
+
+    let mut connection: SqliteConnection = SqliteConnectOptions::new()
+        .filename(path)
+        .connect()
+        .await
+        .map_err(DbError::SqliteConnect)?;
+
+    sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+        .bind(encrypted_path.display().to_string())
+        .bind(key)
+        .execute(&mut connection)
+        .await
+        .map_err(DbError::SqliteConnect)?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut connection)
+        .await
+        .map_err(DbError::SqliteConnect)?;
+    sqlx::query("DETACH DATABASE encrypted")
+        .execute(&mut connection)
+        .await
+        .map_err(DbError::SqliteConnect)?;
+    connection.close().await.map_err(DbError::SqliteConnect)?;
+
+    std::fs::rename(&encrypted_path, path)
+        .with_context(|| "failed to replace plaintext database with encrypted copy")?;
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = format!("{}{suffix}", path.display());
+        std::fs::remove_file(sidecar).ok();
+    }
+
+    tracing::info!(path = %path.display(), "encrypted existing SQLite database");
+    Ok(())
+}
+
+/// A migrated in-memory database for tests. It has a single connection, as
+/// each connection to an in-memory database opens a database of its own.
+#[cfg(test)]
+pub(crate) async fn test_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .expect("in-memory SQLite");
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("migrations apply");
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_quoted_for_pragma() {
+        assert_eq!(quote_key("hunter2"), "'hunter2'");
+        assert_eq!(quote_key("it's"), "'it''s'");
+    }
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[tokio::test]
+    async fn encryption_requires_sqlcipher_build() {
+        let error = ensure_sqlcipher().await.unwrap_err();
+        assert!(error.to_string().contains("SQLCipher"));
+    }
+
+    #[tokio::test]
+    async fn check_sqlite_reports_pending_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = DatabaseConfig::default();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.path().join("spacebot.db"))
+            .create_if_missing(true);
+        let mut connection = options.connect().await.unwrap();
+        sqlx::query("CREATE TABLE scratch (id INTEGER)")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        connection.close().await.unwrap();
+
+        let health = check_sqlite(dir.path(), &database).await.unwrap();
+        assert!(health.problems.is_empty());
+        assert_eq!(
+            health.pending_migrations,
+            sqlx::migrate!("./migrations").iter().count()
+        );
+    }
+
+    #[tokio::test]
+    async fn pool_applies_tuning_pragmas() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = DatabaseConfig {
+            busy_timeout_ms: 1234,
+            ..DatabaseConfig::default()
+        };
+        let options = SqliteConnectOptions::new()
+            .filename(dir.path().join("tuned.db"))
+            .create_if_missing(true);
+        let pool = pool_options(&database)
+            .connect_with(tuned_options(options, &database))
+            .await
+            .unwrap();
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        // NORMAL is 1.
+        let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(journal_mode, "wal");
+        assert_eq!(busy_timeout, 1234);
+        assert_eq!(synchronous, 1);
+    }
+}
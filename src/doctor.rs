@@ -0,0 +1,632 @@
+//! `spacebot doctor`: checks an instance for the problems behind most
+//! support requests.
+//!
+//! Every check produces findings with a severity and, for anything that
+//! isn't fine, what to do about it. Checks never modify the instance:
+//! databases are opened read-only and migrations are only counted.
+
+use crate::config::{ApiType, Config, ProviderConfig};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Free space below which the disk check fails.
+const MIN_FREE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Free space below which the disk check warns.
+const LOW_FREE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Timeout for each network probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Identity files each agent's workspace is expected to have.
+const IDENTITY_FILES: &[&str] = &["SOUL.md", "IDENTITY.md", "USER.md"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Failure,
+}
+
+impl Severity {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Warning => "warn",
+            Self::Failure => "FAIL",
+        }
+    }
+}
+
+/// One result from a check.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    /// Check that produced it, e.g. "providers" or "database".
+    pub check: &'static str,
+    pub severity: Severity,
+    pub summary: String,
+    /// What to do about it, for warnings and failures.
+    pub fix: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, summary: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: Severity::Ok,
+            summary: summary.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(check: &'static str, summary: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: Severity::Warning,
+            summary: summary.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failure(check: &'static str, summary: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: Severity::Failure,
+            summary: summary.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// All findings from a doctor run.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    pub fn has_failures(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Failure)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(
+                f,
+                "[{:>4}] {:<10} {}",
+                finding.severity.label(),
+                finding.check,
+                finding.summary
+            )?;
+            if let Some(fix) = &finding.fix {
+                writeln!(f, "{:18}-> {fix}", "")?;
+            }
+        }
+        write!(
+            f,
+            "\n{} ok, {} warnings, {} failures",
+            self.count(Severity::Ok),
+            self.count(Severity::Warning),
+            self.count(Severity::Failure)
+        )
+    }
+}
+
+/// Run every check. `config` is the result of loading the config, so a
+/// config that fails to load is reported instead of aborting the run; the
+/// checks that need it are skipped.
+pub async fn run(instance_dir: &Path, config: &anyhow::Result<Config>) -> Report {
+    let mut report = Report::default();
+
+    let config = match config {
+        Ok(config) => {
+            report.findings.push(Finding::ok(
+                "config",
+                format!(
+                    "loaded {} agent(s) from {}",
+                    config.agents.len(),
+                    config.instance_dir.join("config.toml").display()
+                ),
+            ));
+            Some(config)
+        }
+        Err(error) => {
+            report.findings.push(Finding::failure(
+                "config",
+                format!("{error:#}"),
+                "fix the reported key in config.toml, then run `spacebot doctor` again",
+            ));
+            None
+        }
+    };
+
+    report.findings.extend(check_disk_space(instance_dir));
+    report.findings.extend(check_embedding_cache(instance_dir));
+
+    let Some(config) = config else {
+        return report;
+    };
+
+    let http = match reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() {
+        Ok(http) => http,
+        Err(error) => {
+            report.findings.push(Finding::failure(
+                "network",
+                format!("can't build HTTP client: {error}"),
+                "check the system TLS configuration",
+            ));
+            return report;
+        }
+    };
+
+    report.findings.extend(check_providers(&http, config).await);
+    report.findings.extend(check_adapters(&http, config).await);
+    report.findings.extend(check_agents(config).await);
+    report
+}
+
+/// Free space on the filesystem holding the instance directory.
+fn check_disk_space(instance_dir: &Path) -> Vec<Finding> {
+    let existing = instance_dir
+        .ancestors()
+        .find(|path| path.exists())
+        .unwrap_or(Path::new("/"));
+    let available = match available_bytes(existing) {
+        Ok(available) => available,
+        Err(error) => {
+            return vec![Finding::warning(
+                "disk",
+                format!("can't read free space for {}: {error}", existing.display()),
+                "check the instance directory's mount manually with `df -h`",
+            )];
+        }
+    };
+
+    let summary = format!(
+        "{} free on the filesystem holding {}",
+        format_bytes(available),
+        instance_dir.display()
+    );
+    let finding = if available < MIN_FREE_BYTES {
+        Finding::failure(
+            "disk",
+            summary,
+            "free up space; SQLite and LanceDB writes fail when the disk fills up",
+        )
+    } else if available < LOW_FREE_BYTES {
+        Finding::warning(
+            "disk",
+            summary,
+            "free up space or prune old worker logs and archives before it runs out",
+        )
+    } else {
+        Finding::ok("disk", summary)
+    };
+    vec![finding]
+}
+
+fn available_bytes(path: &Path) -> anyhow::Result<u64> {
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    let path_cstring = std::ffi::CString::new(path.as_os_str().as_encoded_bytes())?;
+
+    let result = unsafe { libc::statvfs(path_cstring.as_ptr(), stats.as_mut_ptr()) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let stats = unsafe { stats.assume_init() };
+    Ok((stats.f_frsize as u128 * stats.f_bavail as u128) as u64)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= GIB {
+        format!("{:.1} GiB", bytes as f64 / GIB)
+    } else {
+        format!("{:.0} MiB", bytes as f64 / MIB)
+    }
+}
+
+/// The embedding model downloads into the instance's cache on first start.
+fn check_embedding_cache(instance_dir: &Path) -> Vec<Finding> {
+    let cache_dir = instance_dir.join("embedding_cache");
+    let populated = std::fs::read_dir(&cache_dir)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    let finding = if populated {
+        Finding::ok(
+            "embeddings",
+            format!("model cache present at {}", cache_dir.display()),
+        )
+    } else {
+        Finding::warning(
+            "embeddings",
+            format!("no embedding model cached at {}", cache_dir.display()),
+            "the model downloads on the next start; make sure the host can reach huggingface.co, or copy a cache from another instance",
+        )
+    };
+    vec![finding]
+}
+
+/// Probe each provider's model listing with its key.
+async fn check_providers(http: &reqwest::Client, config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let oauth = crate::auth::credentials_path(&config.instance_dir).exists();
+
+    if config.llm.providers.is_empty() && !oauth {
+        findings.push(Finding::failure(
+            "providers",
+            "no LLM provider configured",
+            "add a key under [llm] in config.toml, or run `spacebot auth login`",
+        ));
+        return findings;
+    }
+    if oauth {
+        findings.push(Finding::ok(
+            "providers",
+            "Anthropic OAuth credentials found",
+        ));
+    }
+
+    let mut providers: Vec<(&String, &ProviderConfig)> = config.llm.providers.iter().collect();
+    providers.sort_by_key(|(id, _)| id.as_str());
+    let probes = providers
+        .into_iter()
+        .map(|(id, provider)| probe_provider(http, id, provider));
+    findings.extend(futures::future::join_all(probes).await);
+    findings
+}
+
+async fn probe_provider(http: &reqwest::Client, id: &str, provider: &ProviderConfig) -> Finding {
+    let base_url = provider.base_url.trim_end_matches('/');
+    let request = match provider.api_type {
+        ApiType::Anthropic => http
+            .get(format!("{base_url}/v1/models"))
+            .header("x-api-key", &provider.api_key)
+            .header("anthropic-version", "2023-06-01"),
+        // Gemini's OpenAI-compatible base URL already includes the version,
+        // as do the Z.AI endpoints.
+        ApiType::Gemini => http
+            .get(format!("{base_url}/models"))
+            .bearer_auth(&provider.api_key),
+        _ if id == "zhipu" || id == "zai-coding-plan" => http
+            .get(format!("{base_url}/models"))
+            .bearer_auth(&provider.api_key),
+        ApiType::OpenAiCompletions | ApiType::OpenAiResponses => http
+            .get(format!("{base_url}/v1/models"))
+            .bearer_auth(&provider.api_key),
+    };
+
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {
+            Finding::ok("providers", format!("{id}: key accepted by {base_url}"))
+        }
+        Ok(response)
+            if matches!(
+                response.status(),
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+            ) =>
+        {
+            Finding::failure(
+                "providers",
+                format!("{id}: key rejected ({})", response.status()),
+                format!(
+                    "replace the {id} key in config.toml or the environment variable it references"
+                ),
+            )
+        }
+        Ok(response) => Finding::warning(
+            "providers",
+            format!(
+                "{id}: {base_url} is reachable but returned {} for the model list",
+                response.status()
+            ),
+            "the key couldn't be verified; send a test message to confirm it works",
+        ),
+        Err(error) => Finding::failure(
+            "providers",
+            format!("{id}: can't reach {base_url}: {error}"),
+            "check the base_url, DNS and any outbound proxy or firewall",
+        ),
+    }
+}
+
+/// Verify each enabled adapter's credentials against its platform.
+async fn check_adapters(http: &reqwest::Client, config: &Config) -> Vec<Finding> {
+    let messaging = &config.messaging;
+    let mut findings = Vec::new();
+
+    if let Some(discord) = messaging.discord.as_ref().filter(|discord| discord.enabled) {
+        let response = http
+            .get("https://discord.com/api/v10/users/@me")
+            .header("Authorization", format!("Bot {}", discord.token))
+            .send()
+            .await;
+        findings.push(credential_finding(
+            "discord",
+            response,
+            "reset the bot token in the Discord developer portal and update [messaging.discord].token",
+        ));
+    }
+
+    if let Some(slack) = messaging.slack.as_ref().filter(|slack| slack.enabled) {
+        let response = http
+            .post("https://slack.com/api/auth.test")
+            .bearer_auth(&slack.bot_token)
+            .send()
+            .await;
+        findings.push(match response {
+            Ok(response) => match response.json::<serde_json::Value>().await {
+                Ok(body) if body["ok"].as_bool() == Some(true) => {
+                    Finding::ok("slack", "bot token accepted")
+                }
+                Ok(body) => Finding::failure(
+                    "slack",
+                    format!(
+                        "bot token rejected: {}",
+                        body["error"].as_str().unwrap_or("unknown error")
+                    ),
+                    "reinstall the app to the workspace and copy the new xoxb- token into [messaging.slack].bot_token",
+                ),
+                Err(error) => Finding::warning(
+                    "slack",
+                    format!("unexpected auth.test response: {error}"),
+                    "retry later; Slack may be having an outage",
+                ),
+            },
+            Err(error) => Finding::failure(
+                "slack",
+                format!("can't reach slack.com: {error}"),
+                "check DNS and any outbound proxy or firewall",
+            ),
+        });
+        if !slack.app_token.starts_with("xapp-") {
+            findings.push(Finding::failure(
+                "slack",
+                "app token doesn't look like an app-level token",
+                "enable Socket Mode and put the xapp- token in [messaging.slack].app_token",
+            ));
+        }
+    }
+
+    if let Some(telegram) = messaging
+        .telegram
+        .as_ref()
+        .filter(|telegram| telegram.enabled)
+    {
+        let response = http
+            .get(format!(
+                "https://api.telegram.org/bot{}/getMe",
+                telegram.token
+            ))
+            .send()
+            .await;
+        findings.push(credential_finding(
+            "telegram",
+            response,
+            "get a new token from @BotFather and update [messaging.telegram].token",
+        ));
+    }
+
+    if let Some(twitch) = messaging.twitch.as_ref().filter(|twitch| twitch.enabled) {
+        let token = twitch
+            .oauth_token
+            .strip_prefix("oauth:")
+            .unwrap_or(&twitch.oauth_token);
+        let response = http
+            .get("https://id.twitch.tv/oauth2/validate")
+            .header("Authorization", format!("OAuth {token}"))
+            .send()
+            .await;
+        findings.push(credential_finding(
+            "twitch",
+            response,
+            "generate a new chat token, or set client_id, client_secret and refresh_token so it refreshes automatically",
+        ));
+    }
+
+    findings
+}
+
+fn credential_finding(
+    adapter: &'static str,
+    response: Result<reqwest::Response, reqwest::Error>,
+    fix: &str,
+) -> Finding {
+    match response {
+        Ok(response) if response.status().is_success() => {
+            Finding::ok(adapter, "credentials accepted")
+        }
+        Ok(response)
+            if matches!(
+                response.status(),
+                reqwest::StatusCode::UNAUTHORIZED
+                    | reqwest::StatusCode::FORBIDDEN
+                    | reqwest::StatusCode::NOT_FOUND
+            ) =>
+        {
+            Finding::failure(
+                adapter,
+                format!("credentials rejected ({})", response.status()),
+                fix,
+            )
+        }
+        Ok(response) => Finding::warning(
+            adapter,
+            format!("credential check returned {}", response.status()),
+            "retry later; the platform may be having an outage",
+        ),
+        Err(error) => Finding::failure(
+            adapter,
+            format!("can't reach the platform: {error}"),
+            "check DNS and any outbound proxy or firewall",
+        ),
+    }
+}
+
+/// Per-agent workspace, identity files and database integrity.
+async fn check_agents(config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for agent in config.resolve_agents() {
+        let id = &agent.id;
+
+        if !agent.workspace.exists() {
+            findings.push(Finding::warning(
+                "workspace",
+                format!("{id}: no workspace at {}", agent.workspace.display()),
+                "it is created with template identity files on the next start",
+            ));
+        } else {
+            let missing: Vec<&str> = IDENTITY_FILES
+                .iter()
+                .copied()
+                .filter(|file| !agent.workspace.join(file).exists())
+                .collect();
+            if missing.is_empty() {
+                findings.push(Finding::ok(
+                    "workspace",
+                    format!("{id}: identity files present"),
+                ));
+            } else {
+                findings.push(Finding::warning(
+                    "workspace",
+                    format!("{id}: missing {}", missing.join(", ")),
+                    format!(
+                        "templates are written on the next start; fill them in under {}",
+                        agent.workspace.display()
+                    ),
+                ));
+            }
+        }
+
+        findings.extend(check_agent_databases(id, &agent.data_dir, &agent.database).await);
+    }
+
+    findings
+}
+
+async fn check_agent_databases(
+    id: &str,
+    data_dir: &Path,
+    database: &crate::config::DatabaseConfig,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let sqlite_path = data_dir.join("spacebot.db");
+
+    if !sqlite_path.exists() {
+        findings.push(Finding::ok(
+            "database",
+            format!("{id}: no database yet, created on the next start"),
+        ));
+        return findings;
+    }
+
+    match crate::db::check_sqlite(data_dir, database).await {
+        Ok(health) if health.problems.is_empty() => {
+            let summary = if health.pending_migrations > 0 {
+                format!(
+                    "{id}: SQLite intact, {} migration(s) apply on the next start",
+                    health.pending_migrations
+                )
+            } else {
+                format!("{id}: SQLite intact and up to date")
+            };
+            findings.push(Finding::ok("database", summary));
+        }
+        Ok(health) => findings.push(Finding::failure(
+            "database",
+            format!(
+                "{id}: SQLite integrity check failed: {}",
+                health.problems.join("; ")
+            ),
+            format!(
+                "stop spacebot, back up {}, then recover it with `sqlite3 spacebot.db .recover`",
+                sqlite_path.display()
+            ),
+        )),
+        Err(error) => findings.push(Finding::failure(
+            "database",
+            format!("{id}: can't open SQLite: {error}"),
+            "check file permissions and, for encrypted databases, the encryption key",
+        )),
+    }
+
+    let lance_path: PathBuf = data_dir.join("lancedb");
+    if lance_path.exists() {
+        let tables = match lance_path.to_str() {
+            Some(path) => match lancedb::connect(path).execute().await {
+                Ok(connection) => connection
+                    .table_names()
+                    .execute()
+                    .await
+                    .map_err(|error| error.to_string()),
+                Err(error) => Err(error.to_string()),
+            },
+            None => Err("path is not valid UTF-8".to_string()),
+        };
+        findings.push(match tables {
+            Ok(tables) => Finding::ok(
+                "database",
+                format!("{id}: LanceDB opens ({} table(s))", tables.len()),
+            ),
+            Err(error) => Finding::failure(
+                "database",
+                format!("{id}: can't open LanceDB: {error}"),
+                format!(
+                    "move {} aside; memories stay in SQLite and embeddings are rebuilt",
+                    lance_path.display()
+                ),
+            ),
+        });
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_fixes_and_totals() {
+        let report = Report {
+            findings: vec![
+                Finding::ok("disk", "40.0 GiB free"),
+                Finding::failure("providers", "openai: key rejected", "replace the key"),
+            ],
+        };
+        assert!(report.has_failures());
+
+        let rendered = report.to_string();
+        assert!(rendered.contains("[  ok] disk"));
+        assert!(rendered.contains("[FAIL] providers"));
+        assert!(rendered.contains("-> replace the key"));
+        assert!(rendered.ends_with("1 ok, 0 warnings, 1 failures"));
+    }
+
+    #[test]
+    fn empty_embedding_cache_warns() {
+        let dir = tempfile::tempdir().unwrap();
+        let findings = check_embedding_cache(dir.path());
+        assert_eq!(findings[0].severity, Severity::Warning);
+
+        std::fs::create_dir_all(dir.path().join("embedding_cache/model")).unwrap();
+        let findings = check_embedding_cache(dir.path());
+        assert_eq!(findings[0].severity, Severity::Ok);
+    }
+}
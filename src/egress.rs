@@ -0,0 +1,593 @@
+//! Network egress policy for tools that reach the network.
+//!
+//! An `EgressPolicy` is built from the agent's `[egress]` config and enforced
+//! in two places: `check_url` rejects disallowed URLs before a request is
+//! made, and clients from `client_builder` re-check every resolved address
+//! and every redirect hop, so a domain that resolves to a private address
+//! or redirects elsewhere is still caught.
+
+use crate::config::EgressConfig;
+
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+/// Maximum redirects followed by egress-checked clients.
+const MAX_REDIRECTS: usize = 10;
+
+/// Which kind of caller is making a request. Each scope can have its own
+/// domain allowlist on top of the global lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EgressScope {
+    /// Downloading message attachments from platform CDNs.
+    Attachments,
+    /// The web search tool.
+    WebSearch,
+    /// Browser tool navigation.
+    Browser,
+}
+
+impl EgressScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Attachments => "attachments",
+            Self::WebSearch => "web_search",
+            Self::Browser => "browser",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "attachments" => Some(Self::Attachments),
+            "web_search" => Some(Self::WebSearch),
+            "browser" => Some(Self::Browser),
+            _ => None,
+        }
+    }
+}
+
+/// A request blocked by the egress policy.
+#[derive(Debug, thiserror::Error)]
+pub enum EgressError {
+    #[error("can't reach '{url}': invalid URL")]
+    InvalidUrl { url: String },
+
+    #[error("can't reach {host}: {reason}")]
+    Blocked { host: String, reason: String },
+}
+
+/// An IP network in CIDR notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// Parse `10.0.0.0/8` or `fd00::/8`. A bare address is a single-host network.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (address, prefix_len) = match value.split_once('/') {
+            Some((address, prefix_len)) => {
+                (address.parse::<IpAddr>().ok()?, prefix_len.parse().ok()?)
+            }
+            None => {
+                let address = value.parse::<IpAddr>().ok()?;
+                let max = if address.is_ipv4() { 32 } else { 128 };
+                (address, max)
+            }
+        };
+
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        (prefix_len <= max).then_some(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns true if the IP address belongs to a private, loopback, or
+/// link-local range.
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()                             // 127.0.0.0/8
+            || v4.is_private()                            // 10/8, 172.16/12, 192.168/16
+            || v4.is_link_local()                         // 169.254.0.0/16
+            || v4.is_broadcast()                          // 255.255.255.255
+            || v4.is_unspecified()                        // 0.0.0.0
+            || is_v4_cgnat(v4) // 100.64.0.0/10
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback()                             // ::1
+            || v6.is_unspecified()                        // ::
+            || is_v6_unique_local(v6)                    // fd00::/8 (fc00::/7)
+            || is_v6_link_local(v6)                      // fe80::/10
+            || is_v4_mapped_private(v6)
+        }
+    }
+}
+
+fn is_v4_cgnat(ip: Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    octets[0] == 100 && (octets[1] & 0xC0) == 64 // 100.64.0.0/10
+}
+
+fn is_v6_unique_local(ip: Ipv6Addr) -> bool {
+    (ip.segments()[0] & 0xFE00) == 0xFC00 // fc00::/7
+}
+
+fn is_v6_link_local(ip: Ipv6Addr) -> bool {
+    (ip.segments()[0] & 0xFFC0) == 0xFE80 // fe80::/10
+}
+
+/// Check if an IPv6 address is a v4-mapped address (::ffff:x.x.x.x)
+/// pointing to a private IPv4 range.
+fn is_v4_mapped_private(ip: Ipv6Addr) -> bool {
+    ip.to_ipv4_mapped()
+        .is_some_and(|v4| is_private_ip(IpAddr::V4(v4)))
+}
+
+/// True if `host` is `domain` or one of its subdomains.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches("*.").trim_end_matches('.');
+    host.eq_ignore_ascii_case(domain)
+        || host
+            .len()
+            .checked_sub(domain.len() + 1)
+            .is_some_and(|split| {
+                host.as_bytes()[split] == b'.' && host[split + 1..].eq_ignore_ascii_case(domain)
+            })
+}
+
+/// Compiled egress rules for one agent.
+#[derive(Debug, Clone, Default)]
+pub struct EgressPolicy {
+    allowed_domains: Vec<String>,
+    denied_domains: Vec<String>,
+    block_private_ranges: bool,
+    allowed_networks: Vec<IpNetwork>,
+    denied_networks: Vec<IpNetwork>,
+    scopes: HashMap<EgressScope, Vec<String>>,
+}
+
+impl EgressPolicy {
+    /// Build a policy from config. Invalid networks and unknown scopes are
+    /// rejected at config load, so they're skipped here.
+    pub fn new(config: &EgressConfig) -> Arc<Self> {
+        Arc::new(Self {
+            allowed_domains: config.allowed_domains.clone(),
+            denied_domains: config.denied_domains.clone(),
+            block_private_ranges: config.block_private_ranges,
+            allowed_networks: config
+                .allowed_networks
+                .iter()
+                .filter_map(|network| IpNetwork::parse(network))
+                .collect(),
+            denied_networks: config
+                .denied_networks
+                .iter()
+                .filter_map(|network| IpNetwork::parse(network))
+                .collect(),
+            scopes: config
+                .scopes
+                .iter()
+                .filter_map(|(scope, domains)| {
+                    EgressScope::parse(scope).map(|scope| (scope, domains.clone()))
+                })
+                .collect(),
+        })
+    }
+
+    /// Check a URL's scheme and host against the policy.
+    pub fn check_url(&self, scope: EgressScope, url: &str) -> Result<Url, EgressError> {
+        let parsed = Url::parse(url).map_err(|_| EgressError::InvalidUrl {
+            url: url.to_string(),
+        })?;
+        self.check_parsed_url(scope, &parsed)?;
+        Ok(parsed)
+    }
+
+    /// Like `check_url`, but also resolves the host and checks every address.
+    /// Use this when the request is made by something other than a client
+    /// from `client_builder` (e.g. the browser).
+    pub async fn check_url_resolved(
+        &self,
+        scope: EgressScope,
+        url: &str,
+    ) -> Result<Url, EgressError> {
+        let parsed = self.check_url(scope, url)?;
+        let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+            return Ok(parsed);
+        };
+        if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+            return Ok(parsed);
+        }
+
+        // Resolution failures are left to the caller's own request to report.
+        if let Ok(addresses) = tokio::net::lookup_host((host, port)).await {
+            for address in addresses {
+                self.check_ip(host, address.ip())?;
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn check_parsed_url(&self, scope: EgressScope, url: &Url) -> Result<(), EgressError> {
+        let host = url.host_str().unwrap_or_default();
+        match url.scheme() {
+            "http" | "https" => {}
+            other => {
+                return Err(EgressError::Blocked {
+                    host: host.to_string(),
+                    reason: format!("scheme '{other}' is not allowed"),
+                });
+            }
+        }
+        if host.is_empty() {
+            return Err(EgressError::InvalidUrl {
+                url: url.to_string(),
+            });
+        }
+
+        match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(ip) => {
+                self.check_ip(host, ip)?;
+                self.check_ip_allowlisted(scope, host, ip)
+            }
+            Err(_) => self.check_domain(scope, host),
+        }
+    }
+
+    /// An IP-literal host has no domain to match, so when a global or
+    /// per-scope allowlist is set the address itself has to be on it, as a
+    /// bare address or a CIDR network.
+    fn check_ip_allowlisted(
+        &self,
+        scope: EgressScope,
+        host: &str,
+        ip: IpAddr,
+    ) -> Result<(), EgressError> {
+        let listed = |entries: &[String]| {
+            entries.iter().any(|entry| {
+                IpNetwork::parse(entry.trim_matches(['[', ']']))
+                    .is_some_and(|network| network.contains(ip))
+            })
+        };
+        let blocked = |reason: String| EgressError::Blocked {
+            host: host.to_string(),
+            reason,
+        };
+
+        if !self.allowed_domains.is_empty() && !listed(&self.allowed_domains) {
+            return Err(blocked(format!(
+                "address {ip} is not in the egress allowlist"
+            )));
+        }
+        if let Some(domains) = self.scopes.get(&scope)
+            && !listed(domains)
+        {
+            return Err(blocked(format!(
+                "address {ip} is not allowed for {}",
+                scope.as_str()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a domain name against the global and per-scope domain lists.
+    pub fn check_domain(&self, scope: EgressScope, host: &str) -> Result<(), EgressError> {
+        let blocked = |reason: String| EgressError::Blocked {
+            host: host.to_string(),
+            reason,
+        };
+
+        if self
+            .denied_domains
+            .iter()
+            .any(|domain| domain_matches(host, domain))
+        {
+            return Err(blocked("domain is denied by the egress policy".into()));
+        }
+        if !self.allowed_domains.is_empty()
+            && !self
+                .allowed_domains
+                .iter()
+                .any(|domain| domain_matches(host, domain))
+        {
+            return Err(blocked("domain is not in the egress allowlist".into()));
+        }
+        if let Some(domains) = self.scopes.get(&scope)
+            && !domains.iter().any(|domain| domain_matches(host, domain))
+        {
+            return Err(blocked(format!(
+                "domain is not allowed for {}",
+                scope.as_str()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a resolved or literal address against the network rules.
+    pub fn check_ip(&self, host: &str, ip: IpAddr) -> Result<(), EgressError> {
+        let blocked = |reason: String| EgressError::Blocked {
+            host: host.to_string(),
+            reason,
+        };
+
+        if self
+            .denied_networks
+            .iter()
+            .any(|network| network.contains(ip))
+        {
+            return Err(blocked(format!(
+                "address {ip} is denied by the egress policy"
+            )));
+        }
+        if self.block_private_ranges
+            && is_private_ip(ip)
+            && !self
+                .allowed_networks
+                .iter()
+                .any(|network| network.contains(ip))
+        {
+            return Err(blocked(format!("address {ip} is in a private range")));
+        }
+        Ok(())
+    }
+
+    /// A client builder whose DNS resolution and redirects are checked
+    /// against this policy. Callers still `check_url` the initial URL,
+    /// since IP-literal URLs skip DNS resolution.
+    pub fn client_builder(self: &Arc<Self>, scope: EgressScope) -> reqwest::ClientBuilder {
+        let redirect_policy = self.clone();
+        reqwest::Client::builder()
+            .dns_resolver(Arc::new(PolicyResolver {
+                policy: self.clone(),
+                scope,
+            }))
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match redirect_policy.check_parsed_url(scope, attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(error) => attempt.error(error),
+                }
+            }))
+    }
+
+    /// A client for `scope` with the policy applied. Falls back to a default
+    /// client if the builder fails, which only happens on TLS backend errors.
+    pub fn client(self: &Arc<Self>, scope: EgressScope) -> reqwest::Client {
+        self.client_builder(scope).build().unwrap_or_else(|error| {
+            tracing::warn!(%error, "failed to build egress-checked HTTP client");
+            reqwest::Client::new()
+        })
+    }
+}
+
+/// DNS resolver that drops addresses the policy doesn't allow.
+struct PolicyResolver {
+    policy: Arc<EgressPolicy>,
+    scope: EgressScope,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        let scope = self.scope;
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            policy.check_domain(scope, &host)?;
+
+            let addresses = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+            let mut last_error = None;
+            let allowed = addresses
+                .into_iter()
+                .filter(|address| match policy.check_ip(&host, address.ip()) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        last_error = Some(error);
+                        false
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if allowed.is_empty()
+                && let Some(error) = last_error
+            {
+                return Err(error.into());
+            }
+            let addrs: Addrs = Box::new(allowed.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(config: EgressConfig) -> Arc<EgressPolicy> {
+        EgressPolicy::new(&config)
+    }
+
+    #[test]
+    fn default_policy_allows_everything_public_and_private() {
+        let policy = policy(EgressConfig::default());
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "https://example.com")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://127.0.0.1:3000")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "file:///etc/passwd")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn domain_lists_match_subdomains() {
+        let policy = policy(EgressConfig {
+            allowed_domains: vec!["example.com".into()],
+            denied_domains: vec!["evil.example.com".into()],
+            ..Default::default()
+        });
+        assert!(
+            policy
+                .check_domain(EgressScope::Browser, "docs.example.com")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_domain(EgressScope::Browser, "example.com")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_domain(EgressScope::Browser, "notexample.com")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_domain(EgressScope::Browser, "a.evil.example.com")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn scopes_restrict_further() {
+        let policy = policy(EgressConfig {
+            scopes: HashMap::from([(
+                "attachments".to_string(),
+                vec!["cdn.discordapp.com".to_string()],
+            )]),
+            ..Default::default()
+        });
+        assert!(
+            policy
+                .check_url(EgressScope::Attachments, "https://cdn.discordapp.com/a.png")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Attachments, "https://example.com/a.png")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::WebSearch, "https://example.com")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn allowlists_block_unlisted_ip_literals() {
+        let policy = policy(EgressConfig {
+            allowed_domains: vec!["example.com".into(), "198.51.100.0/24".into()],
+            scopes: HashMap::from([(
+                "attachments".to_string(),
+                vec!["cdn.discordapp.com".to_string()],
+            )]),
+            ..Default::default()
+        });
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://203.0.113.5/")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://198.51.100.7/")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Attachments, "http://198.51.100.7/")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn private_ranges_blocked_with_network_exceptions() {
+        let policy = policy(EgressConfig {
+            block_private_ranges: true,
+            allowed_networks: vec!["10.1.0.0/16".into()],
+            denied_networks: vec!["203.0.113.0/24".into()],
+            ..Default::default()
+        });
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://169.254.169.254/")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://[::1]:8080/")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://10.1.2.3/")
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://10.2.0.1/")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://203.0.113.9/")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_url(EgressScope::Browser, "http://93.184.216.34/")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn parses_cidr_networks() {
+        let network = IpNetwork::parse("192.168.0.0/16").unwrap();
+        assert!(network.contains("192.168.4.2".parse().unwrap()));
+        assert!(!network.contains("192.169.0.1".parse().unwrap()));
+        assert!(
+            IpNetwork::parse("::/0")
+                .unwrap()
+                .contains("2001:db8::1".parse().unwrap())
+        );
+        assert!(IpNetwork::parse("10.0.0.0/33").is_none());
+        assert!(IpNetwork::parse("not-a-network").is_none());
+    }
+}
@@ -112,6 +112,14 @@ pub enum DbError {
     #[error("migration failed: {0}")]
     Migration(String),
 
+    #[error(
+        "can't open encrypted database: this build doesn't include SQLCipher (rebuild with --features sqlcipher)"
+    )]
+    EncryptionUnavailable,
+
+    #[error("can't open encrypted database at {0}: wrong encryption key or corrupted file")]
+    WrongEncryptionKey(String),
+
     #[error("query failed: {0}")]
     Query(String),
 
@@ -184,12 +192,37 @@ pub enum AgentError {
     #[error("max concurrent branches ({max}) reached for channel {channel_id}")]
     BranchLimitReached { channel_id: String, max: usize },
 
-    #[error("max concurrent workers ({max}) reached for channel {channel_id}")]
-    WorkerLimitReached { channel_id: String, max: usize },
+    #[error("the worker queue is full ({max_queued} workers already waiting)")]
+    WorkerQueueFull { max_queued: usize },
+
+    #[error("spacebot is shutting down, no new workers can start")]
+    ShuttingDown,
+
+    #[error("max concurrent sub-workers ({max}) reached for the channel")]
+    SubWorkerLimitReached { max: usize },
+
+    #[error("max worker depth ({max}) reached, sub-workers can't spawn sub-workers")]
+    WorkerDepthReached { max: usize },
+
+    #[error(
+        "{process_type} circuit breaker is open after {failures} failures in a row (last: {last_error})"
+    )]
+    CircuitOpen {
+        process_type: String,
+        failures: u32,
+        last_error: String,
+    },
 
     #[error("worker state transition failed: {0}")]
     InvalidStateTransition(String),
 
+    #[error("can't use {capability}: sender role {role} is below required role {required}")]
+    PermissionDenied {
+        capability: String,
+        role: String,
+        required: String,
+    },
+
     #[error("compaction failed: {0}")]
     CompactionFailed(String),
 
@@ -0,0 +1,374 @@
+//! Outbound webhooks for agent events.
+//!
+//! Each `[[event_webhooks]]` entry receives a JSON POST for the events it
+//! subscribes to: a worker completing, a memory being saved, or an error (a
+//! failed worker, or a process stopped by the loop guard). With a `secret`,
+//! the body is signed with HMAC-SHA256 and the hex digest is sent as
+//! `X-Spacebot-Signature: sha256=<digest>`. Failed deliveries are retried with
+//! exponential backoff until `max_attempts`, then dropped with an error log.
+
+use crate::agent::health::HealthAction;
+use crate::agent::worker::WORKER_FAILED_PREFIX;
+use crate::{AgentId, ProcessEvent};
+
+use hmac::{Hmac, Mac as _};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::broadcast;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Longest backoff between two delivery attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(64);
+
+/// Agent events a webhook can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    WorkerCompleted,
+    MemorySaved,
+    Error,
+}
+
+impl EventKind {
+    pub const ALL: [Self; 3] = [Self::WorkerCompleted, Self::MemorySaved, Self::Error];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "worker_completed" => Some(Self::WorkerCompleted),
+            "memory_saved" => Some(Self::MemorySaved),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::WorkerCompleted => "worker_completed",
+            Self::MemorySaved => "memory_saved",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// A URL that receives agent events.
+#[derive(Clone)]
+pub struct EventWebhook {
+    pub url: String,
+    /// Key the body is signed with. Unsigned when `None`.
+    pub secret: Option<String>,
+    /// Events posted to this URL.
+    pub events: Vec<EventKind>,
+    /// Agents whose events are posted. Every agent when empty.
+    pub agents: Vec<String>,
+    /// Delivery attempts per event before it's dropped.
+    pub max_attempts: u32,
+}
+
+impl std::fmt::Debug for EventWebhook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventWebhook")
+            .field("url", &self.url)
+            .field("secret", &self.secret.as_ref().map(|_| "[REDACTED]"))
+            .field("events", &self.events)
+            .field("agents", &self.agents)
+            .field("max_attempts", &self.max_attempts)
+            .finish()
+    }
+}
+
+impl EventWebhook {
+    fn wants(&self, payload: &EventPayload) -> bool {
+        self.events.contains(&payload.event)
+            && (self.agents.is_empty() || self.agents.contains(&payload.agent_id))
+    }
+}
+
+/// The JSON body posted for one event.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPayload {
+    pub event: EventKind,
+    pub agent_id: String,
+    pub channel_id: Option<String>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Event-specific fields, flattened into the body.
+    #[serde(flatten)]
+    pub data: serde_json::Value,
+}
+
+impl EventPayload {
+    fn new(event: EventKind, process_event: &ProcessEvent, data: serde_json::Value) -> Self {
+        Self {
+            event,
+            agent_id: process_event.agent_id().to_string(),
+            channel_id: process_event.channel_id().map(|id| id.to_string()),
+            timestamp: chrono::Utc::now(),
+            data,
+        }
+    }
+}
+
+/// The webhook payloads a process event produces. A failed worker produces
+/// both `worker_completed` and `error`.
+pub fn payloads(event: &ProcessEvent) -> Vec<EventPayload> {
+    match event {
+        ProcessEvent::WorkerComplete {
+            worker_id,
+            result,
+            artifacts,
+            ..
+        } => {
+            let error = result.strip_prefix(WORKER_FAILED_PREFIX);
+            let mut payloads = vec![EventPayload::new(
+                EventKind::WorkerCompleted,
+                event,
+                serde_json::json!({
+                    "worker_id": worker_id.to_string(),
+                    "success": error.is_none(),
+                    "result": result,
+                    "artifacts": artifacts,
+                }),
+            )];
+            if let Some(error) = error {
+                payloads.push(EventPayload::new(
+                    EventKind::Error,
+                    event,
+                    serde_json::json!({
+                        "source": "worker",
+                        "process_id": worker_id.to_string(),
+                        "error": error,
+                    }),
+                ));
+            }
+            payloads
+        }
+        ProcessEvent::MemorySaved { memory_id, .. } => vec![EventPayload::new(
+            EventKind::MemorySaved,
+            event,
+            serde_json::json!({ "memory_id": memory_id }),
+        )],
+        ProcessEvent::LoopDetected {
+            process_id,
+            kind,
+            detail,
+            ..
+        } => vec![EventPayload::new(
+            EventKind::Error,
+            event,
+            serde_json::json!({
+                "source": "loop_guard",
+                "process_id": process_id.to_string(),
+                "kind": kind,
+                "error": detail,
+            }),
+        )],
+        ProcessEvent::ProcessUnhealthy {
+            process_id,
+            action: HealthAction::Kill,
+            diagnostic,
+            ..
+        } => vec![EventPayload::new(
+            EventKind::Error,
+            event,
+            serde_json::json!({
+                "source": "cortex_health",
+                "process_id": process_id.to_string(),
+                "error": diagnostic,
+            }),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// `sha256=<hex>` HMAC of `body` under `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Check a `sha256=<hex>` HMAC of `body` under `secret` in constant time.
+/// Inbound webhooks signed the way [`sign`] signs ours use this.
+pub fn signature_valid(secret: &str, body: &[u8], header: Option<&str>) -> bool {
+    let Some(signature) = header
+        .and_then(|header| header.strip_prefix("sha256="))
+        .and_then(|signature| hex::decode(signature).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Posts event payloads to the configured webhooks.
+pub struct EventWebhookDispatcher {
+    webhooks: Vec<EventWebhook>,
+    http: reqwest::Client,
+}
+
+impl EventWebhookDispatcher {
+    pub fn new(webhooks: Vec<EventWebhook>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self { webhooks, http }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.webhooks.is_empty()
+    }
+
+    /// Deliver `payload` to every webhook subscribed to it, each in the
+    /// background.
+    pub fn dispatch(&self, payload: &EventPayload) {
+        let webhooks: Vec<_> = self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.wants(payload))
+            .cloned()
+            .collect();
+        if webhooks.is_empty() {
+            return;
+        }
+
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(error) => {
+                tracing::error!(%error, "failed to serialize event webhook payload");
+                return;
+            }
+        };
+        for webhook in webhooks {
+            let http = self.http.clone();
+            let body = body.clone();
+            let event = payload.event;
+            tokio::spawn(async move { deliver(&http, &webhook, event, body).await });
+        }
+    }
+}
+
+async fn deliver(http: &reqwest::Client, webhook: &EventWebhook, event: EventKind, body: Vec<u8>) {
+    let mut delay = Duration::from_secs(1);
+    for attempt in 1..=webhook.max_attempts {
+        let mut request = http
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Spacebot-Event", event.as_str());
+        if let Some(secret) = &webhook.secret {
+            request = request.header("X-Spacebot-Signature", sign(secret, &body));
+        }
+
+        let result = request
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return,
+            Err(error) if attempt < webhook.max_attempts => {
+                tracing::debug!(
+                    url = %webhook.url,
+                    attempt,
+                    %error,
+                    "event webhook delivery failed, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+            Err(error) => {
+                tracing::error!(
+                    url = %webhook.url,
+                    event = event.as_str(),
+                    attempts = attempt,
+                    %error,
+                    "failed to deliver event webhook"
+                );
+            }
+        }
+    }
+}
+
+/// Post an agent's events to the dispatcher's webhooks. Does nothing when no
+/// webhooks are configured.
+pub fn watch_agent_events(
+    dispatcher: Arc<EventWebhookDispatcher>,
+    agent_id: AgentId,
+    mut event_rx: broadcast::Receiver<ProcessEvent>,
+) {
+    if !dispatcher.is_enabled() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            match event_rx.recv().await {
+                Ok(event) => {
+                    for payload in payloads(&event) {
+                        dispatcher.dispatch(&payload);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    crate::events::record_lag(&agent_id, "event_webhooks", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_workers_also_report_an_error() {
+        let event = ProcessEvent::WorkerComplete {
+            agent_id: Arc::from("main"),
+            worker_id: uuid::Uuid::nil(),
+            channel_id: Some(Arc::from("discord:1:2")),
+            result: "Worker failed: shell exited with 1".into(),
+            notify: true,
+            artifacts: Vec::new(),
+        };
+
+        let payloads = payloads(&event);
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0].event, EventKind::WorkerCompleted);
+        assert_eq!(payloads[0].data["success"], false);
+        assert_eq!(payloads[1].event, EventKind::Error);
+        assert_eq!(payloads[1].data["error"], "shell exited with 1");
+
+        let body = serde_json::to_value(&payloads[0]).unwrap();
+        assert_eq!(body["event"], "worker_completed");
+        assert_eq!(body["channel_id"], "discord:1:2");
+        assert_eq!(body["worker_id"], uuid::Uuid::nil().to_string());
+    }
+
+    #[test]
+    fn signature_matches_hmac_sha256() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn signatures_are_checked_against_the_body() {
+        let body = br#"{"entry":[]}"#;
+        let header = sign("secret", body);
+
+        assert!(signature_valid("secret", body, Some(&header)));
+        assert!(!signature_valid("other", body, Some(&header)));
+        assert!(!signature_valid("secret", b"{}", Some(&header)));
+        assert!(!signature_valid("secret", body, Some("sha256=zz")));
+        assert!(!signature_valid("secret", body, None));
+    }
+}
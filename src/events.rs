@@ -0,0 +1,418 @@
+//! Process event subscriptions: filtering, lag accounting and a journal for
+//! events that must not be lost.
+//!
+//! Each agent has a single tokio broadcast bus. A receiver that falls behind
+//! silently loses its oldest events, and for a channel that can be the
+//! `WorkerComplete` it is waiting on, leaving the user waiting forever.
+//! [`EventSubscription`] applies a filter on receive, so unrelated events
+//! don't take up the subscriber's attention. It also counts dropped events
+//! per subscriber (see [`lag_stats`]). When it starts and after a lag it
+//! replays branch and worker results from the [`EventJournal`], which
+//! publishers write to before broadcasting.
+
+use crate::{AgentId, ChannelId, ProcessEvent};
+
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+use tokio::sync::broadcast;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+/// Journal rows older than this are from an earlier run and are discarded.
+const JOURNAL_RETENTION_HOURS: i64 = 24;
+
+/// Critical event keys remembered per subscription to skip duplicates.
+const CLAIMED_KEYS_CAPACITY: usize = 256;
+
+/// Which events a subscriber receives.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    agent_id: Option<AgentId>,
+    channel_id: Option<ChannelId>,
+}
+
+impl EventFilter {
+    /// Every event on the bus.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Only events from this agent.
+    pub fn agent(mut self, agent_id: AgentId) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    /// Only events for this channel, plus events that aren't tied to any
+    /// channel.
+    pub fn channel(mut self, channel_id: ChannelId) -> Self {
+        self.channel_id = Some(channel_id);
+        self
+    }
+
+    pub fn matches(&self, event: &ProcessEvent) -> bool {
+        if let Some(agent_id) = &self.agent_id
+            && event.agent_id() != agent_id
+        {
+            return false;
+        }
+        match (&self.channel_id, event.channel_id()) {
+            (Some(channel_id), Some(event_channel)) => channel_id == event_channel,
+            _ => true,
+        }
+    }
+}
+
+/// Dropped-event counts for one subscriber.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubscriberLag {
+    pub agent_id: String,
+    pub subscriber: String,
+    /// Events the subscriber never saw.
+    pub dropped_events: u64,
+    /// How many times it fell behind.
+    pub lag_count: u64,
+    pub last_lagged_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+static LAG_STATS: LazyLock<Mutex<HashMap<(String, String), SubscriberLag>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Record that a subscriber fell behind and missed `dropped` events.
+pub fn record_lag(agent_id: &str, subscriber: &str, dropped: u64) {
+    tracing::warn!(
+        agent_id,
+        subscriber,
+        dropped,
+        "event subscriber lagged, events dropped"
+    );
+
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .event_bus_lagged_total
+        .with_label_values(&[agent_id, subscriber])
+        .inc_by(dropped);
+
+    let Ok(mut stats) = LAG_STATS.lock() else {
+        return;
+    };
+    let entry = stats
+        .entry((agent_id.to_string(), subscriber.to_string()))
+        .or_insert_with(|| SubscriberLag {
+            agent_id: agent_id.to_string(),
+            subscriber: subscriber.to_string(),
+            ..SubscriberLag::default()
+        });
+    entry.dropped_events += dropped;
+    entry.lag_count += 1;
+    entry.last_lagged_at = Some(chrono::Utc::now());
+}
+
+/// Lag counts for every subscriber that has fallen behind since startup.
+pub fn lag_stats() -> Vec<SubscriberLag> {
+    let Ok(stats) = LAG_STATS.lock() else {
+        return Vec::new();
+    };
+    let mut lags: Vec<SubscriberLag> = stats.values().cloned().collect();
+    lags.sort_by(|a, b| (&a.agent_id, &a.subscriber).cmp(&(&b.agent_id, &b.subscriber)));
+    lags
+}
+
+/// Durable copy of branch and worker results until their channel has them.
+#[derive(Debug, Clone)]
+pub struct EventJournal {
+    pool: SqlitePool,
+}
+
+impl EventJournal {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Journal a critical event. Other events are ignored.
+    pub async fn record(&self, event: &ProcessEvent) {
+        let (Some(key), Some(channel_id)) = (event.critical_key(), event.channel_id()) else {
+            return;
+        };
+        let payload = match serde_json::to_string(event) {
+            Ok(payload) => payload,
+            Err(error) => {
+                tracing::warn!(%error, %key, "can't serialize critical event");
+                return;
+            }
+        };
+        let result = sqlx::query(
+            "INSERT OR REPLACE INTO critical_events (event_key, channel_id, payload) VALUES (?, ?, ?)",
+        )
+        .bind(&key)
+        .bind(channel_id.as_ref())
+        .bind(payload)
+        .execute(&self.pool)
+        .await;
+        if let Err(error) = result {
+            tracing::warn!(%error, %key, "can't journal critical event");
+        }
+    }
+
+    /// Mark a critical event as handled.
+    pub async fn remove(&self, key: &str) {
+        let result = sqlx::query("DELETE FROM critical_events WHERE event_key = ?")
+            .bind(key)
+            .execute(&self.pool)
+            .await;
+        if let Err(error) = result {
+            tracing::warn!(%error, %key, "can't remove journaled event");
+        }
+    }
+
+    /// Journaled events for a channel that haven't been handled, oldest first.
+    pub async fn pending(&self, channel_id: &str) -> Vec<ProcessEvent> {
+        let cutoff = (chrono::Utc::now() - chrono::Duration::hours(JOURNAL_RETENTION_HOURS))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        sqlx::query("DELETE FROM critical_events WHERE created_at < ?")
+            .bind(&cutoff)
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        let rows = match sqlx::query(
+            "SELECT payload FROM critical_events WHERE channel_id = ? ORDER BY created_at",
+        )
+        .bind(channel_id)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(rows) => rows,
+            Err(error) => {
+                tracing::warn!(%error, channel_id, "can't load journaled events");
+                return Vec::new();
+            }
+        };
+        rows.iter()
+            .filter_map(|row| row.try_get::<String, _>("payload").ok())
+            .filter_map(|payload| serde_json::from_str(&payload).ok())
+            .collect()
+    }
+}
+
+/// Journal a critical event, then broadcast it.
+pub async fn publish(
+    event_tx: &broadcast::Sender<ProcessEvent>,
+    journal: &EventJournal,
+    event: ProcessEvent,
+) {
+    journal.record(&event).await;
+    event_tx.send(event).ok();
+}
+
+/// A filtered receiver on an agent's event bus.
+///
+/// `recv` is cancel-safe, so it can be used in a `tokio::select!` loop:
+/// journal reads and deletes that get interrupted are retried on the next
+/// call.
+#[derive(Debug)]
+pub struct EventSubscription {
+    agent_id: AgentId,
+    subscriber: &'static str,
+    receiver: broadcast::Receiver<ProcessEvent>,
+    filter: EventFilter,
+    journal: Option<EventJournal>,
+    /// Set after a lag until the journal has been read.
+    journal_load_pending: bool,
+    replay: VecDeque<ProcessEvent>,
+    /// Delivered critical events still to be removed from the journal.
+    delivered: Vec<String>,
+    claimed: HashSet<String>,
+    claimed_order: VecDeque<String>,
+}
+
+impl EventSubscription {
+    /// `subscriber` names the consumer in lag stats and metrics.
+    pub fn new(
+        agent_id: AgentId,
+        subscriber: &'static str,
+        receiver: broadcast::Receiver<ProcessEvent>,
+        filter: EventFilter,
+    ) -> Self {
+        Self {
+            agent_id,
+            subscriber,
+            receiver,
+            filter,
+            journal: None,
+            journal_load_pending: false,
+            replay: VecDeque::new(),
+            delivered: Vec::new(),
+            claimed: HashSet::new(),
+            claimed_order: VecDeque::new(),
+        }
+    }
+
+    /// Recover critical events for the filtered channel from the journal
+    /// after a lag, and clear them from the journal once received. Events
+    /// journaled before the subscription started, like the result of a
+    /// worker resumed while its channel wasn't running, are recovered on the
+    /// first receive.
+    pub fn with_journal(mut self, journal: EventJournal) -> Self {
+        self.journal = Some(journal);
+        self.journal_load_pending = true;
+        self
+    }
+
+    /// Next matching event, or `None` once the bus is closed.
+    pub async fn recv(&mut self) -> Option<ProcessEvent> {
+        loop {
+            if let Some(journal) = &self.journal {
+                while let Some(key) = self.delivered.last() {
+                    journal.remove(key).await;
+                    self.delivered.pop();
+                }
+                if self.journal_load_pending {
+                    self.load_journal().await;
+                }
+            }
+
+            let event = match self.replay.pop_front() {
+                Some(event) => event,
+                None => match self.receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                        record_lag(&self.agent_id, self.subscriber, dropped);
+                        self.journal_load_pending = self.journal.is_some();
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+            };
+            if !self.filter.matches(&event) {
+                continue;
+            }
+            if let Some(key) = event.critical_key()
+                && !self.claim(key)
+            {
+                continue;
+            }
+            return Some(event);
+        }
+    }
+
+    async fn load_journal(&mut self) {
+        let (Some(journal), Some(channel_id)) = (&self.journal, &self.filter.channel_id) else {
+            self.journal_load_pending = false;
+            return;
+        };
+        let pending = journal.pending(channel_id).await;
+        if !pending.is_empty() {
+            tracing::info!(
+                channel_id = %channel_id,
+                count = pending.len(),
+                "recovering journaled events"
+            );
+        }
+        self.replay.extend(pending);
+        self.journal_load_pending = false;
+    }
+
+    /// Returns false when the event was already delivered, which happens
+    /// when it survived in the broadcast buffer and was also replayed.
+    fn claim(&mut self, key: String) -> bool {
+        if self.claimed.contains(&key) {
+            return false;
+        }
+        if self.journal.is_some() {
+            self.delivered.push(key.clone());
+        }
+        if self.claimed_order.len() == CLAIMED_KEYS_CAPACITY
+            && let Some(oldest) = self.claimed_order.pop_front()
+        {
+            self.claimed.remove(&oldest);
+        }
+        self.claimed.insert(key.clone());
+        self.claimed_order.push_back(key);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    fn worker_complete(channel_id: &str, worker_id: uuid::Uuid) -> ProcessEvent {
+        ProcessEvent::WorkerComplete {
+            agent_id: Arc::from("main"),
+            worker_id,
+            channel_id: Some(Arc::from(channel_id)),
+            result: "done".into(),
+            notify: true,
+            artifacts: Vec::new(),
+        }
+    }
+
+    fn memory_saved(channel_id: Option<&str>) -> ProcessEvent {
+        ProcessEvent::MemorySaved {
+            agent_id: Arc::from("main"),
+            memory_id: "memory".into(),
+            channel_id: channel_id.map(Arc::from),
+        }
+    }
+
+    #[test]
+    fn channel_filter_passes_own_and_unscoped_events() {
+        let filter = EventFilter::all().channel(Arc::from("discord:1"));
+        assert!(filter.matches(&worker_complete("discord:1", uuid::Uuid::new_v4())));
+        assert!(!filter.matches(&worker_complete("discord:2", uuid::Uuid::new_v4())));
+        assert!(filter.matches(&memory_saved(None)));
+        assert!(
+            !EventFilter::all()
+                .agent(Arc::from("other"))
+                .matches(&memory_saved(None))
+        );
+    }
+
+    #[tokio::test]
+    async fn lagged_subscription_recovers_journaled_result() {
+        let pool = crate::db::test_pool().await;
+        let journal = EventJournal::new(pool);
+
+        let (event_tx, receiver) = broadcast::channel(2);
+        let mut subscription = EventSubscription::new(
+            Arc::from("main"),
+            "test",
+            receiver,
+            EventFilter::all().channel(Arc::from("discord:1")),
+        )
+        .with_journal(journal.clone());
+
+        // The result is pushed out of the buffer by noise before the
+        // subscriber reads it.
+        let worker_id = uuid::Uuid::new_v4();
+        publish(&event_tx, &journal, worker_complete("discord:1", worker_id)).await;
+        for _ in 0..3 {
+            event_tx.send(memory_saved(Some("discord:2"))).ok();
+        }
+        event_tx.send(memory_saved(None)).ok();
+
+        let recovered = subscription.recv().await.unwrap();
+        assert_eq!(
+            recovered.critical_key(),
+            Some(format!("worker_complete:{worker_id}"))
+        );
+        assert!(matches!(
+            subscription.recv().await,
+            Some(ProcessEvent::MemorySaved {
+                channel_id: None,
+                ..
+            })
+        ));
+        assert!(journal.pending("discord:1").await.is_empty());
+        assert!(
+            lag_stats()
+                .iter()
+                .any(|lag| lag.subscriber == "test" && lag.dropped_events > 0)
+        );
+    }
+}
@@ -1,6 +1,8 @@
 //! Prompt hooks for observing and controlling agent behavior.
 
+pub mod budget;
 pub mod cortex;
+pub mod loop_guard;
 pub mod spacebot;
 
 pub use cortex::CortexHook;
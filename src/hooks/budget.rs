@@ -0,0 +1,250 @@
+//! Resource budgets for workers.
+//!
+//! [`WorkerBudget`] counts a worker's tool calls, LLM tokens and estimated
+//! cost as the hook sees them, and trips when one goes over the agent's
+//! `worker_budget` limits, or when the worker runs past its wall time. Once
+//! tripped, the hook skips further tool calls and tells the worker to wrap
+//! up; a worker that keeps going is terminated after a couple of turns, or
+//! hard-cancelled once `wrap_up_secs` have passed.
+
+use crate::config::WorkerBudgetConfig;
+
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Prefix of the termination reason when a budget trips. Workers use it to
+/// tell a budget stop apart from a user cancellation.
+pub const BUDGET_REASON_PREFIX: &str = "budget exceeded";
+
+/// LLM calls a worker gets to wrap up after tripping its budget.
+const MAX_WRAP_UP_TURNS: usize = 2;
+
+#[derive(Debug, Default)]
+struct Usage {
+    tool_calls: usize,
+    tokens: u64,
+    cost_usd: f64,
+    wrap_up_turns: usize,
+}
+
+/// A worker's budget and what it has used so far.
+#[derive(Debug)]
+pub struct WorkerBudget {
+    config: WorkerBudgetConfig,
+    model_name: String,
+    started: Instant,
+    usage: Mutex<Usage>,
+    /// What was exceeded, once the budget trips.
+    tripped: watch::Sender<Option<String>>,
+}
+
+impl WorkerBudget {
+    /// A budget for a worker running `model_name`, starting now.
+    pub fn new(config: WorkerBudgetConfig, model_name: impl Into<String>) -> Self {
+        Self {
+            config,
+            model_name: model_name.into(),
+            started: Instant::now(),
+            usage: Mutex::new(Usage::default()),
+            tripped: watch::channel(None).0,
+        }
+    }
+
+    /// What was exceeded, if the budget has tripped.
+    pub fn tripped(&self) -> Option<String> {
+        self.tripped.borrow().clone()
+    }
+
+    /// Trip the budget. Only the first trip is kept.
+    pub fn trip(&self, reason: String) {
+        self.tripped.send_if_modified(|tripped| {
+            if tripped.is_some() {
+                return false;
+            }
+            tracing::warn!(%reason, "worker budget exceeded");
+            *tripped = Some(reason);
+            true
+        });
+    }
+
+    /// Count a tool call. Returns why the call should be skipped instead,
+    /// when the budget has tripped.
+    pub fn record_tool_call(&self) -> Option<String> {
+        if self.tripped().is_none()
+            && let Ok(mut usage) = self.usage.lock()
+        {
+            usage.tool_calls += 1;
+            let max = self.config.max_tool_calls;
+            if max > 0 && usage.tool_calls > max {
+                drop(usage);
+                self.trip(format!("made {max} tool calls"));
+            }
+        }
+        let reason = self.tripped()?;
+        Some(format!(
+            "Tool call skipped: {BUDGET_REASON_PREFIX} ({reason}). Don't call any more tools. \
+             Reply now with your final result: what you finished, what's left, and anything \
+             the channel should know."
+        ))
+    }
+
+    /// Count the tokens and cost of an LLM call.
+    pub fn record_usage(&self, usage: &rig::completion::Usage) {
+        let cost = crate::llm::pricing::estimate_cost(
+            &self.model_name,
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cached_input_tokens,
+        );
+        let Ok(mut used) = self.usage.lock() else {
+            return;
+        };
+        used.tokens += usage.input_tokens + usage.output_tokens;
+        used.cost_usd += cost;
+
+        let trip = if self.config.max_tokens > 0 && used.tokens > self.config.max_tokens {
+            Some(format!("used {} tokens", self.config.max_tokens))
+        } else {
+            self.config
+                .max_cost_usd
+                .filter(|max| used.cost_usd > *max)
+                .map(|max| format!("spent ${max:.2}"))
+        };
+        drop(used);
+        if let Some(reason) = trip {
+            self.trip(reason);
+        }
+    }
+
+    /// Called before each LLM call. Returns the termination reason once a
+    /// tripped worker has had its turns to wrap up.
+    pub fn check_completion(&self) -> Option<String> {
+        let reason = self.tripped()?;
+        let mut usage = self.usage.lock().ok()?;
+        usage.wrap_up_turns += 1;
+        (usage.wrap_up_turns > MAX_WRAP_UP_TURNS)
+            .then(|| format!("{BUDGET_REASON_PREFIX}: {reason}"))
+    }
+
+    /// Resolves when the worker should be hard-cancelled: `wrap_up_secs`
+    /// after the budget trips. With `wall_time`, the budget trips once the
+    /// worker has run for `max_wall_time_secs`. Returns what was exceeded.
+    pub async fn enforce(&self, wall_time: bool) -> String {
+        let deadline = (wall_time && self.config.max_wall_time_secs > 0)
+            .then(|| self.started + Duration::from_secs(self.config.max_wall_time_secs));
+        let mut tripped = self.tripped.subscribe();
+
+        tokio::select! {
+            _ = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                self.trip(format!("ran for {}s", self.config.max_wall_time_secs));
+            }
+            _ = tripped.wait_for(Option::is_some) => {}
+        }
+
+        tokio::time::sleep(Duration::from_secs(self.config.wrap_up_secs)).await;
+        self.tripped().unwrap_or_default()
+    }
+
+    /// The result a worker reports after tripping its budget, with whatever
+    /// it managed to write up.
+    pub fn exceeded_result(&self, text: &str) -> Option<String> {
+        let reason = self.tripped()?;
+        let text = text.trim();
+        Some(if text.is_empty() {
+            format!("Worker stopped, {BUDGET_REASON_PREFIX}: {reason}.")
+        } else {
+            format!("Worker stopped early, {BUDGET_REASON_PREFIX}: {reason}.\n\n{text}")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input_tokens: u64, output_tokens: u64) -> rig::completion::Usage {
+        rig::completion::Usage {
+            input_tokens,
+            output_tokens,
+            total_tokens: input_tokens + output_tokens,
+            cached_input_tokens: 0,
+        }
+    }
+
+    #[test]
+    fn trips_on_tool_calls_and_skips_the_rest() {
+        let config = WorkerBudgetConfig {
+            max_tool_calls: 2,
+            ..Default::default()
+        };
+        let budget = WorkerBudget::new(config, "anthropic/claude-sonnet-4");
+
+        assert!(budget.record_tool_call().is_none());
+        assert!(budget.record_tool_call().is_none());
+        assert!(budget.check_completion().is_none());
+
+        let skipped = budget.record_tool_call().unwrap();
+        assert!(skipped.contains("made 2 tool calls"));
+        assert!(budget.record_tool_call().is_some());
+
+        // Two turns to wrap up, then the worker is terminated.
+        assert!(budget.check_completion().is_none());
+        assert!(budget.check_completion().is_none());
+        let reason = budget.check_completion().unwrap();
+        assert!(reason.starts_with(BUDGET_REASON_PREFIX));
+
+        let result = budget.exceeded_result("Did half of it.").unwrap();
+        assert!(result.contains("made 2 tool calls"));
+        assert!(result.ends_with("Did half of it."));
+    }
+
+    #[test]
+    fn trips_on_tokens_and_cost() {
+        let config = WorkerBudgetConfig {
+            max_tokens: 1_000,
+            ..Default::default()
+        };
+        let budget = WorkerBudget::new(config, "anthropic/claude-sonnet-4");
+        budget.record_usage(&usage(600, 100));
+        assert!(budget.tripped().is_none());
+        budget.record_usage(&usage(600, 100));
+        assert_eq!(budget.tripped().as_deref(), Some("used 1000 tokens"));
+
+        let config = WorkerBudgetConfig {
+            max_cost_usd: Some(0.01),
+            ..Default::default()
+        };
+        let budget = WorkerBudget::new(config, "anthropic/claude-sonnet-4");
+        // $3/M input: 2,000 tokens is $0.006.
+        budget.record_usage(&usage(2_000, 0));
+        assert!(budget.tripped().is_none());
+        budget.record_usage(&usage(2_000, 0));
+        assert_eq!(budget.tripped().as_deref(), Some("spent $0.01"));
+        assert!(budget.exceeded_result("").is_some());
+    }
+
+    #[tokio::test]
+    async fn enforce_resolves_once_tripped() {
+        let config = WorkerBudgetConfig {
+            max_wall_time_secs: 3_600,
+            wrap_up_secs: 0,
+            ..Default::default()
+        };
+        let budget = WorkerBudget::new(config, "anthropic/claude-sonnet-4");
+
+        let enforcing = budget.enforce(true);
+        budget.trip("made 5 tool calls".into());
+        let reason = tokio::time::timeout(Duration::from_secs(5), enforcing)
+            .await
+            .unwrap();
+        assert_eq!(reason, "made 5 tool calls");
+    }
+}
@@ -0,0 +1,283 @@
+//! Loop detection for processes stuck repeating tool calls.
+//!
+//! [`LoopGuard`] keeps a short record of a process's recent tool calls and
+//! trips on three patterns: the same call with the same arguments over and
+//! over, a run of failures cycling between one or two calls, and a burst of
+//! branch or worker spawns. The hook stops the process's current run when it
+//! trips, instead of letting it burn through `max_turns` and tokens.
+
+use crate::config::LoopGuardConfig;
+
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Prefix of the termination reason when the guard trips. Processes use it
+/// to tell a loop stop apart from a user cancellation.
+pub const LOOP_GUARD_REASON_PREFIX: &str = "loop detected";
+
+/// Tool calls kept for pattern matching.
+const MAX_RECORDED_CALLS: usize = 32;
+
+/// Tools that start another process.
+const SPAWN_TOOLS: &[&str] = &["branch", "spawn_worker", "spawn_sub_worker"];
+
+/// Which pattern tripped the guard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopKind {
+    RepeatedCall,
+    FailingCycle,
+    RunawaySpawning,
+}
+
+impl LoopKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RepeatedCall => "repeated_call",
+            Self::FailingCycle => "failing_cycle",
+            Self::RunawaySpawning => "runaway_spawning",
+        }
+    }
+}
+
+/// A tripped guard, with a description for logs and notifications.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopTrip {
+    pub kind: LoopKind,
+    pub detail: String,
+}
+
+impl LoopTrip {
+    /// Reason passed to the hook's `Terminate` action.
+    pub fn reason(&self) -> String {
+        format!("{LOOP_GUARD_REASON_PREFIX}: {}", self.detail)
+    }
+}
+
+#[derive(Debug)]
+struct CallRecord {
+    tool_name: String,
+    signature: u64,
+    failed: bool,
+}
+
+/// Recent tool activity for one process.
+#[derive(Debug, Default)]
+pub struct LoopGuard {
+    calls: VecDeque<CallRecord>,
+    spawns: VecDeque<Instant>,
+}
+
+impl LoopGuard {
+    /// Record a completed tool call and check for a loop.
+    pub fn record(
+        &mut self,
+        config: &LoopGuardConfig,
+        tool_name: &str,
+        args: &str,
+        result: &str,
+    ) -> Option<LoopTrip> {
+        self.record_at(config, tool_name, args, result, Instant::now())
+    }
+
+    /// Forget recorded calls, so a paused process starts clean when resumed.
+    pub fn reset(&mut self) {
+        self.calls.clear();
+        self.spawns.clear();
+    }
+
+    fn record_at(
+        &mut self,
+        config: &LoopGuardConfig,
+        tool_name: &str,
+        args: &str,
+        result: &str,
+        now: Instant,
+    ) -> Option<LoopTrip> {
+        if self.calls.len() == MAX_RECORDED_CALLS {
+            self.calls.pop_front();
+        }
+        self.calls.push_back(CallRecord {
+            tool_name: tool_name.to_string(),
+            signature: call_signature(tool_name, args),
+            failed: looks_failed(result),
+        });
+
+        if SPAWN_TOOLS.contains(&tool_name) {
+            let window = Duration::from_secs(config.spawn_window_secs);
+            self.spawns.push_back(now);
+            while self
+                .spawns
+                .front()
+                .is_some_and(|started| now.duration_since(*started) > window)
+            {
+                self.spawns.pop_front();
+            }
+            if self.spawns.len() > config.max_spawns {
+                return Some(LoopTrip {
+                    kind: LoopKind::RunawaySpawning,
+                    detail: format!(
+                        "started {} branches and workers within {} seconds",
+                        self.spawns.len(),
+                        config.spawn_window_secs
+                    ),
+                });
+            }
+        }
+
+        let last = self.calls.back()?;
+        let identical = self
+            .calls
+            .iter()
+            .rev()
+            .take_while(|call| call.signature == last.signature)
+            .count();
+        if identical >= config.max_identical_calls {
+            return Some(LoopTrip {
+                kind: LoopKind::RepeatedCall,
+                detail: format!(
+                    "called {} with identical arguments {identical} times in a row",
+                    last.tool_name
+                ),
+            });
+        }
+
+        if self.calls.len() >= config.max_failing_calls {
+            let window: Vec<&CallRecord> = self
+                .calls
+                .iter()
+                .rev()
+                .take(config.max_failing_calls)
+                .collect();
+            let mut signatures: Vec<u64> = window.iter().map(|call| call.signature).collect();
+            signatures.sort_unstable();
+            signatures.dedup();
+            if window.iter().all(|call| call.failed) && signatures.len() <= 2 {
+                let mut tools: Vec<&str> =
+                    window.iter().map(|call| call.tool_name.as_str()).collect();
+                tools.sort_unstable();
+                tools.dedup();
+                return Some(LoopTrip {
+                    kind: LoopKind::FailingCycle,
+                    detail: format!(
+                        "the last {} calls ({}) all failed without making progress",
+                        config.max_failing_calls,
+                        tools.join(", ")
+                    ),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+fn call_signature(tool_name: &str, args: &str) -> u64 {
+    // Normalize JSON so key order and whitespace don't hide a repeat.
+    let normalized = serde_json::from_str::<serde_json::Value>(args)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|_| args.trim().to_string());
+    let mut hasher = DefaultHasher::new();
+    tool_name.hash(&mut hasher);
+    normalized.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether a tool result reports a failure: an error returned by the tool,
+/// `"success": false`, or a non-zero exit code.
+fn looks_failed(result: &str) -> bool {
+    let trimmed = result.trim_start();
+    if trimmed.starts_with("ToolCallError") || trimmed.starts_with("Toolset error") {
+        return true;
+    }
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(trimmed) else {
+        return false;
+    };
+    fields.get("success").and_then(|value| value.as_bool()) == Some(false)
+        || fields
+            .get("exit_code")
+            .and_then(|value| value.as_i64())
+            .is_some_and(|code| code != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OK: &str = r#"{"success": true, "exit_code": 0}"#;
+    const FAILED: &str = r#"{"success": false, "exit_code": 1}"#;
+
+    #[test]
+    fn trips_on_identical_calls() {
+        let config = LoopGuardConfig::default();
+        let mut guard = LoopGuard::default();
+        for _ in 0..config.max_identical_calls - 1 {
+            assert!(
+                guard
+                    .record(&config, "shell", r#"{"command": "ls"}"#, OK)
+                    .is_none()
+            );
+        }
+        // Same arguments with different formatting still count.
+        let trip = guard
+            .record(&config, "shell", r#"{ "command":"ls" }"#, OK)
+            .unwrap();
+        assert_eq!(trip.kind, LoopKind::RepeatedCall);
+        assert!(trip.reason().starts_with(LOOP_GUARD_REASON_PREFIX));
+    }
+
+    #[test]
+    fn trips_on_alternating_failures() {
+        let config = LoopGuardConfig::default();
+        let mut guard = LoopGuard::default();
+        let mut trip = None;
+        for index in 0..config.max_failing_calls {
+            let args = if index % 2 == 0 {
+                r#"{"command": "make"}"#
+            } else {
+                r#"{"command": "make clean"}"#
+            };
+            trip = guard.record(&config, "shell", args, FAILED);
+        }
+        assert_eq!(trip.unwrap().kind, LoopKind::FailingCycle);
+    }
+
+    #[test]
+    fn varied_failures_do_not_trip() {
+        let config = LoopGuardConfig::default();
+        let mut guard = LoopGuard::default();
+        for index in 0..config.max_failing_calls * 2 {
+            let args = format!(r#"{{"command": "attempt {index}"}}"#);
+            assert!(guard.record(&config, "shell", &args, FAILED).is_none());
+        }
+    }
+
+    #[test]
+    fn trips_on_spawn_burst_within_window() {
+        let config = LoopGuardConfig::default();
+        let mut guard = LoopGuard::default();
+        let start = Instant::now();
+        for index in 0..config.max_spawns {
+            let args = format!(r#"{{"task": "task {index}"}}"#);
+            assert!(
+                guard
+                    .record_at(&config, "spawn_worker", &args, OK, start)
+                    .is_none()
+            );
+        }
+        // Outside the window, old spawns no longer count.
+        let later = start + Duration::from_secs(config.spawn_window_secs + 1);
+        assert!(
+            guard
+                .record_at(&config, "branch", r#"{"description": "x"}"#, OK, later)
+                .is_none()
+        );
+
+        let mut trip = None;
+        for index in 0..config.max_spawns {
+            let args = format!(r#"{{"description": "again {index}"}}"#);
+            trip = guard.record_at(&config, "branch", &args, OK, later);
+        }
+        assert_eq!(trip.unwrap().kind, LoopKind::RunawaySpawning);
+    }
+}
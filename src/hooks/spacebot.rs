@@ -1,10 +1,17 @@
 //! SpacebotHook: Prompt hook for channels, branches, and workers.
 
+use crate::config::RuntimeConfig;
+use crate::hooks::budget::WorkerBudget;
+use crate::hooks::loop_guard::LoopGuard;
 use crate::{AgentId, ChannelId, ProcessEvent, ProcessId, ProcessType};
 use rig::agent::{HookAction, PromptHook, ToolCallHookAction};
 use rig::completion::{CompletionModel, CompletionResponse, Message};
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 
+/// Termination reason once a branch has submitted its conclusion.
+pub const CONCLUDED_REASON: &str = "conclusion submitted";
+
 /// Hook for observing agent behavior and sending events.
 #[derive(Clone)]
 pub struct SpacebotHook {
@@ -13,6 +20,11 @@ pub struct SpacebotHook {
     process_type: ProcessType,
     channel_id: Option<ChannelId>,
     event_tx: broadcast::Sender<ProcessEvent>,
+    /// Loop detection state, shared by clones of the hook for one process.
+    /// Thresholds are read from the runtime config on every tool result.
+    loop_guard: Option<(Arc<RuntimeConfig>, Arc<Mutex<LoopGuard>>)>,
+    /// Resource budget of a worker, shared with the worker that enforces it.
+    budget: Option<Arc<WorkerBudget>>,
 }
 
 impl SpacebotHook {
@@ -30,9 +42,61 @@ impl SpacebotHook {
             process_type,
             channel_id,
             event_tx,
+            loop_guard: None,
+            budget: None,
         }
     }
 
+    /// Enable loop detection using the agent's `loop_guard` config.
+    pub fn with_loop_guard(mut self, runtime_config: Arc<RuntimeConfig>) -> Self {
+        self.loop_guard = Some((runtime_config, Arc::new(Mutex::new(LoopGuard::default()))));
+        self
+    }
+
+    /// Count tool calls and LLM usage against a worker's budget.
+    pub fn with_budget(mut self, budget: Arc<WorkerBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// What the worker's budget was exceeded by, once it trips.
+    pub fn budget_exceeded(&self) -> Option<String> {
+        self.budget.as_ref()?.tripped()
+    }
+
+    /// Record a tool call with the loop guard. On a trip, the guard is reset,
+    /// a `LoopDetected` event is sent and the termination reason returned.
+    fn check_loop(&self, tool_name: &str, args: &str, result: &str) -> Option<String> {
+        let (runtime_config, guard) = self.loop_guard.as_ref()?;
+        let config = runtime_config.loop_guard.load();
+        if !config.enabled {
+            return None;
+        }
+
+        let mut guard = guard.lock().ok()?;
+        let trip = guard.record(&config, tool_name, args, result)?;
+        guard.reset();
+        drop(guard);
+
+        tracing::warn!(
+            process_id = %self.process_id,
+            tool_name,
+            kind = trip.kind.as_str(),
+            detail = %trip.detail,
+            "loop detected, stopping process run"
+        );
+        self.event_tx
+            .send(ProcessEvent::LoopDetected {
+                agent_id: self.agent_id.clone(),
+                process_id: self.process_id.clone(),
+                channel_id: self.channel_id.clone(),
+                kind: trip.kind.as_str().to_string(),
+                detail: trip.detail.clone(),
+            })
+            .ok();
+        Some(trip.reason())
+    }
+
     /// Send a status update event.
     pub fn send_status(&self, status: impl Into<String>) {
         let event = ProcessEvent::StatusUpdate {
@@ -156,26 +220,38 @@ where
     M: CompletionModel,
 {
     async fn on_completion_call(&self, _prompt: &Message, _history: &[Message]) -> HookAction {
-        // Log the completion call but don't block it
         tracing::debug!(
             process_id = %self.process_id,
             process_type = %self.process_type,
             "completion call started"
         );
 
+        // An over-budget worker only gets a couple of calls to wrap up.
+        if let Some(reason) = self
+            .budget
+            .as_ref()
+            .and_then(|budget| budget.check_completion())
+        {
+            return HookAction::Terminate { reason };
+        }
+
         HookAction::Continue
     }
 
     async fn on_completion_response(
         &self,
         _prompt: &Message,
-        _response: &CompletionResponse<M::Response>,
+        response: &CompletionResponse<M::Response>,
     ) -> HookAction {
         tracing::debug!(
             process_id = %self.process_id,
             "completion response received"
         );
 
+        if let Some(budget) = &self.budget {
+            budget.record_usage(&response.usage);
+        }
+
         HookAction::Continue
     }
 
@@ -199,6 +275,14 @@ where
             };
         }
 
+        if let Some(reason) = self
+            .budget
+            .as_ref()
+            .and_then(|budget| budget.record_tool_call())
+        {
+            return ToolCallHookAction::Skip { reason };
+        }
+
         // Send event without blocking
         let event = ProcessEvent::ToolStarted {
             agent_id: self.agent_id.clone(),
@@ -227,7 +311,7 @@ where
         tool_name: &str,
         _tool_call_id: Option<String>,
         _internal_call_id: &str,
-        _args: &str,
+        args: &str,
         result: &str,
     ) -> HookAction {
         // Scan for potential leaks in tool output and terminate if found.
@@ -285,6 +369,10 @@ where
             }
         }
 
+        if let Some(reason) = self.check_loop(tool_name, args, result) {
+            return HookAction::Terminate { reason };
+        }
+
         // Channel turns should end immediately after a successful reply tool call.
         // This avoids extra post-reply LLM iterations that add latency, cost, and
         // noisy logs when providers return empty trailing responses.
@@ -294,6 +382,13 @@ where
             };
         }
 
+        // Likewise, a branch is done once it has submitted its conclusion.
+        if self.process_type == ProcessType::Branch && tool_name == "conclude" {
+            return HookAction::Terminate {
+                reason: CONCLUDED_REASON.into(),
+            };
+        }
+
         HookAction::Continue
     }
 }
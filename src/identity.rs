@@ -1,5 +1,7 @@
 //! Identity file loading (SOUL.md, IDENTITY.md, USER.md).
 
 pub mod files;
+pub mod proposal;
 
 pub use files::{Identity, scaffold_identity_files};
+pub use proposal::{IdentityProposal, IdentityProposals};
@@ -0,0 +1,268 @@
+//! Staged identity changes proposed by the cortex's reflection.
+//!
+//! A proposal holds the new text of IDENTITY.md and USER.md, a line diff
+//! against the text it was based on and the cortex's reasons. It's written
+//! to a staging file in the agent's data directory, never to the workspace,
+//! and only reaches the identity files once it's approved. There's at most
+//! one pending proposal per agent. Approving it fails if a file changed
+//! since the proposal was made, so nobody's edits are overwritten.
+
+use crate::error::Result;
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+/// Identity files reflection may change. SOUL.md is left to humans.
+pub const REFLECTED_FILES: [&str; 2] = ["IDENTITY.md", "USER.md"];
+
+/// Staging file in the agent's data directory.
+const STAGING_FILE: &str = "identity_proposal.json";
+
+/// A proposed set of identity file changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityProposal {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    /// Why the cortex proposes these changes.
+    pub rationale: String,
+    pub changes: Vec<IdentityChange>,
+}
+
+/// One file's proposed change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityChange {
+    pub file: String,
+    /// Content the proposal is based on.
+    pub current: String,
+    pub proposed: String,
+    /// Line diff from `current` to `proposed`.
+    pub diff: String,
+}
+
+impl IdentityProposal {
+    /// Build a proposal from `(file, current, proposed)` triples, leaving out
+    /// files whose text doesn't change. `None` when nothing changes.
+    pub fn new(rationale: impl Into<String>, files: Vec<(&str, String, String)>) -> Option<Self> {
+        let changes: Vec<IdentityChange> = files
+            .into_iter()
+            .filter(|(_, current, proposed)| current.trim() != proposed.trim())
+            .map(|(file, current, proposed)| IdentityChange {
+                file: file.to_string(),
+                diff: line_diff(&current, &proposed),
+                current,
+                proposed,
+            })
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now(),
+            rationale: rationale.into(),
+            changes,
+        })
+    }
+
+    /// The changed files, comma-separated.
+    pub fn files(&self) -> String {
+        self.changes
+            .iter()
+            .map(|change| change.file.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Why a proposal couldn't be applied.
+#[derive(Debug, thiserror::Error)]
+pub enum ProposalError {
+    #[error("no identity proposal is pending")]
+    NotFound,
+    #[error("{0} changed since the proposal was made; reject it and wait for the next one")]
+    Stale(String),
+    #[error(transparent)]
+    Other(#[from] crate::error::Error),
+}
+
+/// The pending proposal of one agent.
+#[derive(Debug, Clone)]
+pub struct IdentityProposals {
+    staging_path: PathBuf,
+    workspace: PathBuf,
+}
+
+impl IdentityProposals {
+    pub fn new(data_dir: &Path, workspace: &Path) -> Self {
+        Self {
+            staging_path: data_dir.join(STAGING_FILE),
+            workspace: workspace.to_path_buf(),
+        }
+    }
+
+    /// The pending proposal, if any.
+    pub async fn pending(&self) -> Result<Option<IdentityProposal>> {
+        let data = match tokio::fs::read(&self.staging_path).await {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(anyhow::Error::new(error)
+                    .context("failed to read identity proposal")
+                    .into());
+            }
+        };
+        let proposal =
+            serde_json::from_slice(&data).context("failed to parse identity proposal")?;
+        Ok(Some(proposal))
+    }
+
+    /// Stage `proposal`, replacing any pending one.
+    pub async fn stage(&self, proposal: &IdentityProposal) -> Result<()> {
+        let data = serde_json::to_vec_pretty(proposal).context("failed to encode proposal")?;
+        tokio::fs::write(&self.staging_path, data)
+            .await
+            .context("failed to write identity proposal")?;
+        Ok(())
+    }
+
+    /// Write the pending proposal to the identity files and clear it.
+    pub async fn approve(&self) -> std::result::Result<IdentityProposal, ProposalError> {
+        let proposal = self.pending().await?.ok_or(ProposalError::NotFound)?;
+        for change in &proposal.changes {
+            let path = self.workspace.join(&change.file);
+            let now = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+            if now != change.current {
+                return Err(ProposalError::Stale(change.file.clone()));
+            }
+        }
+        for change in &proposal.changes {
+            tokio::fs::write(self.workspace.join(&change.file), &change.proposed)
+                .await
+                .with_context(|| format!("failed to write {}", change.file))
+                .map_err(crate::error::Error::from)?;
+        }
+        self.clear().await?;
+        Ok(proposal)
+    }
+
+    /// Drop the pending proposal. Returns it, or `None` if there was none.
+    pub async fn reject(&self) -> Result<Option<IdentityProposal>> {
+        let proposal = self.pending().await?;
+        if proposal.is_some() {
+            self.clear().await?;
+        }
+        Ok(proposal)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        match tokio::fs::remove_file(&self.staging_path).await {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(anyhow::Error::new(error)
+                .context("failed to remove identity proposal")
+                .into()),
+        }
+    }
+}
+
+/// A line diff: unchanged lines start with two spaces, removed ones with
+/// `- ` and added ones with `+ `. Identity files are short, so a plain
+/// longest-common-subsequence table is fine.
+pub fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j]: longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            output.push_str(&format!("  {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            output.push_str(&format!("- {}\n", old[i]));
+            i += 1;
+        } else {
+            output.push_str(&format!("+ {}\n", new[j]));
+            j += 1;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_changed_lines() {
+        let diff = line_diff(
+            "# User\nName: Sam\nLikes tea\n",
+            "# User\nName: Sam\nLikes coffee\nWorks nights\n",
+        );
+        assert_eq!(
+            diff,
+            "  # User\n  Name: Sam\n- Likes tea\n+ Likes coffee\n+ Works nights\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn approving_writes_files_unless_they_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        std::fs::write(workspace.join("USER.md"), "Likes tea\n").unwrap();
+        std::fs::write(workspace.join("IDENTITY.md"), "I'm Spacebot\n").unwrap();
+        let proposals = IdentityProposals::new(dir.path(), &workspace);
+
+        let proposal = IdentityProposal::new(
+            "The user switched to coffee",
+            vec![
+                ("USER.md", "Likes tea\n".into(), "Likes coffee\n".into()),
+                (
+                    "IDENTITY.md",
+                    "I'm Spacebot\n".into(),
+                    "I'm Spacebot\n".into(),
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(proposal.files(), "USER.md");
+        proposals.stage(&proposal).await.unwrap();
+
+        std::fs::write(workspace.join("USER.md"), "Likes green tea\n").unwrap();
+        assert!(matches!(
+            proposals.approve().await,
+            Err(ProposalError::Stale(file)) if file == "USER.md"
+        ));
+
+        std::fs::write(workspace.join("USER.md"), "Likes tea\n").unwrap();
+        let approved = proposals.approve().await.unwrap();
+        assert_eq!(approved.id, proposal.id);
+        assert_eq!(
+            std::fs::read_to_string(workspace.join("USER.md")).unwrap(),
+            "Likes coffee\n"
+        );
+        assert!(proposals.pending().await.unwrap().is_none());
+        assert!(matches!(
+            proposals.approve().await,
+            Err(ProposalError::NotFound)
+        ));
+    }
+}
@@ -1,24 +1,41 @@
 //! Spacebot: A Rust agentic system where every LLM process has a dedicated role.
 
 pub mod agent;
+pub mod alerts;
 pub mod api;
+pub mod audit;
 pub mod auth;
+pub mod bench;
+pub mod cluster;
 pub mod config;
 pub mod conversation;
 pub mod cron;
 pub mod daemon;
 pub mod db;
+pub mod doctor;
+pub mod egress;
 pub mod error;
+pub mod event_webhooks;
+pub mod events;
 pub mod hooks;
 pub mod identity;
+pub mod injection;
 pub mod llm;
 pub mod mcp;
 pub mod memory;
 pub mod messaging;
+pub mod object_storage;
 pub mod opencode;
+pub mod plugins;
 pub mod prompts;
+pub mod quota;
+pub mod rbac;
+pub mod reminders;
+pub mod sandbox;
+pub mod scripting;
 pub mod secrets;
 pub mod settings;
+pub mod shutdown;
 pub mod skills;
 #[cfg(feature = "metrics")]
 pub mod telemetry;
@@ -51,7 +68,7 @@ pub type WorkerId = uuid::Uuid;
 pub type BranchId = uuid::Uuid;
 
 /// Process identifier type (union of channel, worker, branch IDs).
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ProcessId {
     Channel(ChannelId),
     Worker(WorkerId),
@@ -69,7 +86,7 @@ impl std::fmt::Display for ProcessId {
 }
 
 /// Process types in the system.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessType {
     Channel,
@@ -77,6 +94,7 @@ pub enum ProcessType {
     Worker,
     Compactor,
     Cortex,
+    Voice,
 }
 
 impl std::fmt::Display for ProcessType {
@@ -87,6 +105,7 @@ impl std::fmt::Display for ProcessType {
             ProcessType::Worker => write!(f, "worker"),
             ProcessType::Compactor => write!(f, "compactor"),
             ProcessType::Cortex => write!(f, "cortex"),
+            ProcessType::Voice => write!(f, "voice"),
         }
     }
 }
@@ -101,12 +120,17 @@ pub enum ProcessEvent {
         channel_id: ChannelId,
         description: String,
         reply_to_message_id: Option<u64>,
+        /// Shared by branches racing on the same question.
+        race_id: Option<uuid::Uuid>,
     },
     BranchResult {
         agent_id: AgentId,
         branch_id: BranchId,
         channel_id: ChannelId,
         conclusion: String,
+        /// Set when the branch concluded through the `conclude` tool;
+        /// `conclusion` is then its plain text form.
+        structured: Option<crate::agent::branch::BranchConclusion>,
     },
     WorkerStarted {
         agent_id: AgentId,
@@ -126,6 +150,9 @@ pub enum ProcessEvent {
         channel_id: Option<ChannelId>,
         result: String,
         notify: bool,
+        /// Files the worker emitted with `emit_artifact`.
+        #[serde(default)]
+        artifacts: Vec<crate::agent::artifact::Artifact>,
     },
     ToolStarted {
         agent_id: AgentId,
@@ -145,10 +172,51 @@ pub enum ProcessEvent {
         memory_id: String,
         channel_id: Option<ChannelId>,
     },
-    CompactionTriggered {
+    /// The cortex finished a memory consolidation pass.
+    MemoryConsolidated {
+        agent_id: AgentId,
+        report: memory::maintenance::MaintenanceReport,
+    },
+    /// A process type's circuit breaker changed state.
+    CircuitBreakerChanged {
+        agent_id: AgentId,
+        process_type: ProcessType,
+        state: agent::circuit_breaker::BreakerState,
+        consecutive_failures: u32,
+        last_error: Option<String>,
+    },
+    /// The compactor started compacting a channel's history.
+    CompactionStarted {
+        agent_id: AgentId,
+        channel_id: ChannelId,
+        action: agent::compactor::CompactionAction,
+        trigger: agent::compactor::CompactionTrigger,
+        /// Estimated history tokens before compacting.
+        tokens_before: usize,
+    },
+    /// A compaction finished, successfully unless `error` is set.
+    CompactionCompleted {
+        agent_id: AgentId,
+        channel_id: ChannelId,
+        action: agent::compactor::CompactionAction,
+        trigger: agent::compactor::CompactionTrigger,
+        tokens_before: usize,
+        tokens_after: usize,
+        /// Messages summarized or dropped, or tool outputs trimmed.
+        messages_compacted: usize,
+        error: Option<String>,
+    },
+    /// A channel got a title, generated or set through the API.
+    ChannelTitled {
+        agent_id: AgentId,
+        channel_id: ChannelId,
+        title: String,
+    },
+    /// A channel's rolling conversation summary was rewritten.
+    ChannelSummarized {
         agent_id: AgentId,
         channel_id: ChannelId,
-        threshold_reached: f32,
+        summary: String,
     },
     StatusUpdate {
         agent_id: AgentId,
@@ -170,6 +238,100 @@ pub enum ProcessEvent {
         question_id: String,
         questions: Vec<opencode::QuestionInfo>,
     },
+    PromptInjectionDetected {
+        agent_id: AgentId,
+        process_id: ProcessId,
+        channel_id: Option<ChannelId>,
+        tool_name: String,
+        /// Names of the heuristics (or `classifier`) that matched.
+        signals: Vec<String>,
+        neutralized: bool,
+    },
+    /// A process was stopped by the loop guard.
+    LoopDetected {
+        agent_id: AgentId,
+        process_id: ProcessId,
+        channel_id: Option<ChannelId>,
+        /// `repeated_call`, `failing_cycle` or `runaway_spawning`.
+        kind: String,
+        detail: String,
+    },
+    /// The cortex found a branch or worker stuck and wants its channel to
+    /// nudge or kill it.
+    ProcessUnhealthy {
+        agent_id: AgentId,
+        process_id: ProcessId,
+        channel_id: ChannelId,
+        /// Health score from 0 to 100.
+        score: u8,
+        action: agent::health::HealthAction,
+        diagnostic: String,
+    },
+}
+
+impl ProcessEvent {
+    pub fn agent_id(&self) -> &AgentId {
+        match self {
+            Self::BranchStarted { agent_id, .. }
+            | Self::BranchResult { agent_id, .. }
+            | Self::WorkerStarted { agent_id, .. }
+            | Self::WorkerStatus { agent_id, .. }
+            | Self::WorkerComplete { agent_id, .. }
+            | Self::ToolStarted { agent_id, .. }
+            | Self::ToolCompleted { agent_id, .. }
+            | Self::MemorySaved { agent_id, .. }
+            | Self::MemoryConsolidated { agent_id, .. }
+            | Self::CircuitBreakerChanged { agent_id, .. }
+            | Self::CompactionStarted { agent_id, .. }
+            | Self::CompactionCompleted { agent_id, .. }
+            | Self::ChannelTitled { agent_id, .. }
+            | Self::ChannelSummarized { agent_id, .. }
+            | Self::StatusUpdate { agent_id, .. }
+            | Self::WorkerPermission { agent_id, .. }
+            | Self::WorkerQuestion { agent_id, .. }
+            | Self::PromptInjectionDetected { agent_id, .. }
+            | Self::LoopDetected { agent_id, .. }
+            | Self::ProcessUnhealthy { agent_id, .. } => agent_id,
+        }
+    }
+
+    /// The channel this event concerns, if any.
+    pub fn channel_id(&self) -> Option<&ChannelId> {
+        match self {
+            Self::BranchStarted { channel_id, .. }
+            | Self::BranchResult { channel_id, .. }
+            | Self::CompactionStarted { channel_id, .. }
+            | Self::CompactionCompleted { channel_id, .. }
+            | Self::ChannelTitled { channel_id, .. }
+            | Self::ChannelSummarized { channel_id, .. }
+            | Self::ProcessUnhealthy { channel_id, .. } => Some(channel_id),
+            Self::WorkerStarted { channel_id, .. }
+            | Self::WorkerStatus { channel_id, .. }
+            | Self::WorkerComplete { channel_id, .. }
+            | Self::ToolStarted { channel_id, .. }
+            | Self::ToolCompleted { channel_id, .. }
+            | Self::MemorySaved { channel_id, .. }
+            | Self::WorkerPermission { channel_id, .. }
+            | Self::WorkerQuestion { channel_id, .. }
+            | Self::PromptInjectionDetected { channel_id, .. }
+            | Self::LoopDetected { channel_id, .. } => channel_id.as_ref(),
+            Self::StatusUpdate { process_id, .. } => match process_id {
+                ProcessId::Channel(channel_id) => Some(channel_id),
+                _ => None,
+            },
+            Self::MemoryConsolidated { .. } | Self::CircuitBreakerChanged { .. } => None,
+        }
+    }
+
+    /// Unique key for events that must reach their channel: the result of a
+    /// branch or worker. `None` for every other event.
+    pub fn critical_key(&self) -> Option<String> {
+        match self {
+            Self::BranchResult { branch_id, .. } => Some(format!("branch_result:{branch_id}")),
+            Self::WorkerComplete { worker_id, .. } => Some(format!("worker_complete:{worker_id}")),
+            _ => None,
+        }
+    }
 }
 
 /// Shared dependency bundle for agent processes.
@@ -177,6 +339,8 @@ pub enum ProcessEvent {
 pub struct AgentDeps {
     pub agent_id: AgentId,
     pub memory_search: Arc<memory::MemorySearch>,
+    /// Compacted history, searchable with `archive_search`.
+    pub history_archive: Arc<conversation::HistoryArchive>,
     pub llm_manager: Arc<llm::LlmManager>,
     pub mcp_manager: Arc<mcp::McpManager>,
     pub cron_tool: Option<tools::CronTool>,
@@ -184,6 +348,10 @@ pub struct AgentDeps {
     pub event_tx: tokio::sync::broadcast::Sender<ProcessEvent>,
     pub sqlite_pool: sqlx::SqlitePool,
     pub messaging_manager: Option<Arc<messaging::MessagingManager>>,
+    /// Confirmation requests tools are waiting on, answered by the router.
+    pub confirmations: agent::confirmation::Confirmations,
+    /// Extensions called around every channel turn.
+    pub turn_hooks: Arc<agent::turn_hooks::TurnHooks>,
 }
 
 impl AgentDeps {
@@ -251,6 +419,23 @@ pub enum MessageContent {
         /// Platform-specific message reference (`ts` on Slack, message ID on Discord).
         message_ts: Option<String>,
     },
+    /// A user added an emoji reaction to a message in the conversation.
+    ///
+    /// Produced by the Discord adapter. Reactions don't start a turn on
+    /// their own; the channel acts on the ones it has a use for.
+    Reaction {
+        emoji: String,
+        /// Platform ID of the message that was reacted to.
+        message_id: String,
+        /// Display name of the reacted message's author, when it could be
+        /// resolved.
+        target_author: Option<String>,
+        /// Text of the reacted message, when it could be fetched.
+        target_text: Option<String>,
+        /// The reacted message was sent by the bot.
+        #[serde(default)]
+        to_bot: bool,
+    },
 }
 
 impl std::fmt::Display for MessageContent {
@@ -278,6 +463,7 @@ impl std::fmt::Display for MessageContent {
                     write!(f, "[interaction: {}]", action_id)
                 }
             }
+            MessageContent::Reaction { emoji, .. } => write!(f, "[reaction: {}]", emoji),
         }
     }
 }
@@ -310,6 +496,15 @@ pub enum OutboundResponse {
         mime_type: String,
         caption: Option<String>,
     },
+    /// Send several files at once, with optional text above them.
+    /// - Discord: one message with the files attached (ten per message)
+    /// - Webhook: one response carrying every file
+    /// - Other adapters: one `File` per file, with `text` as the first
+    ///   caption ([`attachment_files`])
+    Attachments {
+        text: Option<String>,
+        files: Vec<OutboundFile>,
+    },
     /// Add a reaction emoji to the triggering message.
     Reaction(String),
     /// Remove a reaction emoji from the triggering message.
@@ -345,6 +540,24 @@ pub enum OutboundResponse {
         #[serde(default)]
         poll: Option<Poll>,
     },
+    /// Ask the user to pick one of several options.
+    /// - Discord: buttons, or a select menu for longer lists or options with descriptions
+    /// - Other adapters: a numbered list, answered in text
+    ///
+    /// A pick comes back as an `Interaction` with `action_id` [`CHOICE_ACTION_ID`]
+    /// and the option's value.
+    Choices {
+        prompt: String,
+        options: Vec<ChoiceOption>,
+    },
+    /// A single card, for results and summaries that would otherwise be a
+    /// wall of text.
+    /// - Discord: an embed, with `text` as the message above it
+    /// - Other adapters: `text` followed by the card as plain text ([`rich_text`])
+    Rich {
+        text: String,
+        card: Card,
+    },
     /// Schedule a message to be posted at a future Unix timestamp (Slack only).
     /// Other adapters send immediately as a regular `Text` message.
     ScheduledMessage {
@@ -358,6 +571,15 @@ pub enum OutboundResponse {
     Status(StatusUpdate),
 }
 
+/// A file in an `Attachments` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundFile {
+    pub filename: String,
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
 /// A generic rich-formatted card (maps to Embeds in Discord).
 #[derive(Debug, Clone, Serialize, Deserialize, Default, schemars::JsonSchema)]
 pub struct Card {
@@ -368,6 +590,9 @@ pub struct Card {
     #[serde(default)]
     pub fields: Vec<CardField>,
     pub footer: Option<String>,
+    /// Large image shown at the bottom of the card.
+    #[serde(default)]
+    pub image_url: Option<String>,
 }
 
 /// A field within a generic Card.
@@ -440,6 +665,97 @@ fn default_poll_duration() -> u32 {
     24
 }
 
+/// `action_id` of the interaction sent back when a `Choices` option is picked.
+pub const CHOICE_ACTION_ID: &str = "choice";
+
+/// One option of a `Choices` prompt.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ChoiceOption {
+    pub label: String,
+    /// Sent back when the option is picked. Defaults to the label.
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl ChoiceOption {
+    pub fn value(&self) -> &str {
+        self.value.as_deref().unwrap_or(&self.label)
+    }
+}
+
+/// Render a `Choices` prompt as a numbered list, for adapters without
+/// buttons or menus.
+pub fn choices_text(prompt: &str, options: &[ChoiceOption]) -> String {
+    let mut text = prompt.trim_end().to_string();
+    for (index, option) in options.iter().enumerate() {
+        text.push_str(&format!("\n{}. {}", index + 1, option.label));
+        if let Some(description) = &option.description {
+            text.push_str(&format!(" — {description}"));
+        }
+    }
+    text
+}
+
+/// Plain-text rendering of a `Rich` response, for adapters without embeds.
+pub fn rich_text(text: &str, card: &Card) -> String {
+    let mut sections = Vec::new();
+    if !text.trim().is_empty() {
+        sections.push(text.trim_end().to_string());
+    }
+
+    let mut heading = Vec::new();
+    match (&card.title, &card.url) {
+        (Some(title), Some(url)) => heading.push(format!("{title} ({url})")),
+        (Some(title), None) => heading.push(title.clone()),
+        (None, Some(url)) => heading.push(url.clone()),
+        (None, None) => {}
+    }
+    heading.extend(card.description.clone());
+    if !heading.is_empty() {
+        sections.push(heading.join("\n"));
+    }
+
+    if !card.fields.is_empty() {
+        let fields = card
+            .fields
+            .iter()
+            .map(|field| format!("{}: {}", field.name, field.value))
+            .collect::<Vec<_>>();
+        sections.push(fields.join("\n"));
+    }
+
+    let closing = [card.image_url.clone(), card.footer.clone()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    if !closing.is_empty() {
+        sections.push(closing.join("\n"));
+    }
+
+    sections.join("\n\n")
+}
+
+/// Split an `Attachments` response into one `File` response per file, for
+/// adapters that send files one at a time. `text` becomes the first file's
+/// caption.
+pub fn attachment_files(text: Option<String>, files: Vec<OutboundFile>) -> Vec<OutboundResponse> {
+    let mut caption = text.filter(|text| !text.trim().is_empty());
+    if files.is_empty() {
+        return caption.map(OutboundResponse::Text).into_iter().collect();
+    }
+    files
+        .into_iter()
+        .map(|file| OutboundResponse::File {
+            filename: file.filename,
+            data: file.data,
+            mime_type: file.mime_type,
+            caption: caption.take(),
+        })
+        .collect()
+}
+
 /// Serde helper for encoding `Vec<u8>` as base64 in JSON.
 mod base64_bytes {
     use base64::Engine as _;
@@ -481,4 +797,69 @@ pub enum StatusUpdate {
         worker_id: WorkerId,
         result: String,
     },
+    /// Post or refresh the channel's live status card, or remove it when
+    /// `card` is `None`. Adapters without editable messages ignore it.
+    Live {
+        card: Option<Card>,
+        #[serde(default)]
+        interactive_elements: Vec<InteractiveElements>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutboundFile, OutboundResponse, attachment_files};
+
+    fn file(filename: &str) -> OutboundFile {
+        OutboundFile {
+            filename: filename.into(),
+            data: filename.as_bytes().to_vec(),
+            mime_type: "text/plain".into(),
+        }
+    }
+
+    fn file_parts(response: &OutboundResponse) -> (&str, Option<&str>) {
+        let OutboundResponse::File {
+            filename, caption, ..
+        } = response
+        else {
+            panic!("not a file: {response:?}");
+        };
+        (filename, caption.as_deref())
+    }
+
+    #[test]
+    fn attachments_split_into_files_captioned_by_the_text() {
+        let responses = attachment_files(
+            Some("Here are the logs".into()),
+            vec![file("a.log"), file("b.log"), file("c.log")],
+        );
+        let parts: Vec<_> = responses.iter().map(file_parts).collect();
+        assert_eq!(
+            parts,
+            [
+                ("a.log", Some("Here are the logs")),
+                ("b.log", None),
+                ("c.log", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn attachments_without_files_send_only_the_text() {
+        let responses = attachment_files(Some("Nothing matched".into()), Vec::new());
+        assert!(matches!(
+            responses.as_slice(),
+            [OutboundResponse::Text(text)] if text == "Nothing matched"
+        ));
+
+        assert!(attachment_files(Some("  ".into()), Vec::new()).is_empty());
+        assert!(attachment_files(None, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn blank_attachment_text_is_not_a_caption() {
+        let responses = attachment_files(Some("\n".into()), vec![file("a.log")]);
+        assert_eq!(file_parts(&responses[0]), ("a.log", None));
+    }
 }
@@ -6,6 +6,8 @@ pub mod model;
 pub mod pricing;
 pub mod providers;
 pub mod routing;
+pub mod spend;
+pub mod streaming;
 
 pub use manager::LlmManager;
 pub use model::SpacebotModel;
@@ -51,6 +51,8 @@ fn messages_url(base_url: &str) -> String {
 ///
 /// `thinking_effort` controls adaptive thinking: "auto" picks max for Opus /
 /// high for others, or pass "max", "high", "medium", "low" explicitly.
+///
+/// `stream` asks for a server-sent event stream instead of a single JSON body.
 pub fn build_anthropic_request(
     http_client: &reqwest::Client,
     api_key: &str,
@@ -58,6 +60,7 @@ pub fn build_anthropic_request(
     model_name: &str,
     request: &CompletionRequest,
     thinking_effort: &str,
+    stream: bool,
 ) -> AnthropicRequest {
     let is_oauth = auth::detect_auth_path(api_key) == AnthropicAuthPath::OAuthToken;
     let adaptive_thinking = supports_adaptive_thinking(model_name);
@@ -69,6 +72,9 @@ pub fn build_anthropic_request(
         "model": model_name,
         "max_tokens": request.max_tokens.unwrap_or(16_000),
     });
+    if stream {
+        body["stream"] = serde_json::json!(true);
+    }
 
     build_system_prompt(&mut body, request, is_oauth, &cache_control);
 
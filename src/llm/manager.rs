@@ -11,6 +11,8 @@
 use crate::auth::OAuthCredentials;
 use crate::config::{ApiType, LlmConfig, ProviderConfig};
 use crate::error::{LlmError, Result};
+use crate::llm::routing::{PROVIDER_CIRCUIT_THRESHOLD, provider_from_model};
+use crate::llm::spend::{SpendEvent, SpendTracker};
 
 use anyhow::Context as _;
 use arc_swap::ArcSwap;
@@ -18,7 +20,26 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast};
+
+/// A provider's circuit changed state.
+#[derive(Debug, Clone)]
+pub enum ProviderCircuitEvent {
+    /// The provider failed `PROVIDER_CIRCUIT_THRESHOLD` calls in a row.
+    Opened {
+        provider: String,
+        last_error: String,
+    },
+    /// A call succeeded after the circuit had opened.
+    Closed { provider: String },
+}
+
+/// Consecutive failure count per provider.
+#[derive(Debug, Default)]
+struct ProviderHealth {
+    consecutive_failures: u32,
+    open: bool,
+}
 
 /// Manages LLM provider clients and tracks rate limit state.
 pub struct LlmManager {
@@ -30,6 +51,12 @@ pub struct LlmManager {
     instance_dir: Option<PathBuf>,
     /// Cached OAuth credentials (refreshed lazily).
     oauth_credentials: RwLock<Option<OAuthCredentials>>,
+    /// Per-provider failure tracking for the circuit breaker.
+    provider_health: RwLock<HashMap<String, ProviderHealth>>,
+    circuit_tx: broadcast::Sender<ProviderCircuitEvent>,
+    /// Spend trackers by agent ID. Models look these up via their agent context.
+    spend_trackers: RwLock<HashMap<String, Arc<SpendTracker>>>,
+    spend_tx: broadcast::Sender<SpendEvent>,
 }
 
 impl LlmManager {
@@ -46,6 +73,10 @@ impl LlmManager {
             rate_limited: Arc::new(RwLock::new(HashMap::new())),
             instance_dir: None,
             oauth_credentials: RwLock::new(None),
+            provider_health: RwLock::new(HashMap::new()),
+            circuit_tx: broadcast::channel(32).0,
+            spend_trackers: RwLock::new(HashMap::new()),
+            spend_tx: broadcast::channel(32).0,
         })
     }
 
@@ -84,6 +115,10 @@ impl LlmManager {
             rate_limited: Arc::new(RwLock::new(HashMap::new())),
             instance_dir: Some(instance_dir),
             oauth_credentials: RwLock::new(oauth_credentials),
+            provider_health: RwLock::new(HashMap::new()),
+            circuit_tx: broadcast::channel(32).0,
+            spend_trackers: RwLock::new(HashMap::new()),
+            spend_tx: broadcast::channel(32).0,
         })
     }
 
@@ -226,4 +261,97 @@ impl LlmManager {
             .await
             .retain(|_, limited_at| limited_at.elapsed().as_secs() < cooldown_secs);
     }
+
+    /// Record a call that failed after exhausting its retries. Opens the
+    /// provider's circuit once the failures reach the threshold.
+    pub async fn record_provider_failure(&self, provider: &str, error: &str) {
+        let mut health = self.provider_health.write().await;
+        let entry = health.entry(provider.to_string()).or_default();
+        entry.consecutive_failures += 1;
+
+        if !entry.open && entry.consecutive_failures >= PROVIDER_CIRCUIT_THRESHOLD {
+            entry.open = true;
+            tracing::error!(
+                %provider,
+                failures = entry.consecutive_failures,
+                %error,
+                "provider circuit opened"
+            );
+            self.circuit_tx
+                .send(ProviderCircuitEvent::Opened {
+                    provider: provider.to_string(),
+                    last_error: error.to_string(),
+                })
+                .ok();
+        }
+    }
+
+    /// Record a successful call, closing the provider's circuit if it was open.
+    pub async fn record_provider_success(&self, provider: &str) {
+        let mut health = self.provider_health.write().await;
+        let Some(entry) = health.get_mut(provider) else {
+            return;
+        };
+        let was_open = entry.open;
+        health.remove(provider);
+
+        if was_open {
+            tracing::info!(%provider, "provider circuit closed");
+            self.circuit_tx
+                .send(ProviderCircuitEvent::Closed {
+                    provider: provider.to_string(),
+                })
+                .ok();
+        }
+    }
+
+    /// Providers whose circuit is currently open.
+    pub async fn open_circuits(&self) -> Vec<String> {
+        self.provider_health
+            .read()
+            .await
+            .iter()
+            .filter(|(_, health)| health.open)
+            .map(|(provider, _)| provider.clone())
+            .collect()
+    }
+
+    /// Whether the most recent call to the provider behind every one of these
+    /// models failed after exhausting its retries.
+    pub async fn all_providers_down(&self, models: &[&str]) -> bool {
+        let health = self.provider_health.read().await;
+        !models.is_empty()
+            && models.iter().all(|model| {
+                health
+                    .get(provider_from_model(model))
+                    .is_some_and(|health| health.consecutive_failures > 0)
+            })
+    }
+
+    /// Subscribe to provider circuit state changes.
+    pub fn subscribe_circuit_events(&self) -> broadcast::Receiver<ProviderCircuitEvent> {
+        self.circuit_tx.subscribe()
+    }
+
+    /// Attach a spend tracker to an agent's models.
+    pub async fn register_spend_tracker(&self, agent_id: &str, tracker: Arc<SpendTracker>) {
+        self.spend_trackers
+            .write()
+            .await
+            .insert(agent_id.to_string(), tracker);
+    }
+
+    pub async fn spend_tracker(&self, agent_id: &str) -> Option<Arc<SpendTracker>> {
+        self.spend_trackers.read().await.get(agent_id).cloned()
+    }
+
+    /// Sender handed to new spend trackers.
+    pub fn spend_event_sender(&self) -> broadcast::Sender<SpendEvent> {
+        self.spend_tx.clone()
+    }
+
+    /// Subscribe to spend limit crossings across all agents.
+    pub fn subscribe_spend_events(&self) -> broadcast::Receiver<SpendEvent> {
+        self.spend_tx.subscribe()
+    }
 }
@@ -5,7 +5,10 @@ use crate::llm::manager::LlmManager;
 use crate::llm::routing::{
     self, MAX_FALLBACK_ATTEMPTS, MAX_RETRIES_PER_MODEL, RETRY_BASE_DELAY_MS, RoutingConfig,
 };
+use crate::llm::spend::SpendStatus;
+use crate::llm::streaming::{self, DeltaSender};
 
+use futures::StreamExt as _;
 use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest, GetTokenUsage};
 use rig::message::{
     AssistantContent, DocumentSourceKind, Image, Message, MimeType, Text, ToolCall, ToolFunction,
@@ -14,6 +17,8 @@ use rig::message::{
 use rig::one_or_many::OneOrMany;
 use rig::streaming::StreamingCompletionResponse;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
 use std::sync::Arc;
 
 /// Raw provider response. Wraps the JSON so Rig can carry it through.
@@ -48,6 +53,8 @@ pub struct SpacebotModel {
     routing: Option<RoutingConfig>,
     agent_id: Option<String>,
     process_type: Option<String>,
+    /// Set for callers that show the reply while it's generated.
+    deltas: Option<DeltaSender>,
 }
 
 impl SpacebotModel {
@@ -78,11 +85,17 @@ impl SpacebotModel {
         self
     }
 
-    /// Direct call to the provider (no fallback logic).
-    async fn attempt_completion(
-        &self,
-        request: CompletionRequest,
-    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+    /// Stream calls to Anthropic and OpenAI chat completions endpoints,
+    /// sending their text and tool call arguments to `deltas` as they're
+    /// generated. Responses are still returned whole. Other APIs aren't
+    /// streamed.
+    pub fn with_deltas(mut self, deltas: DeltaSender) -> Self {
+        self.deltas = Some(deltas);
+        self
+    }
+
+    /// The provider ID and its config for this model.
+    async fn resolve_provider(&self) -> Result<(&str, ProviderConfig), CompletionError> {
         let provider_id = self
             .full_model_name
             .split_once('/')
@@ -99,6 +112,15 @@ impl SpacebotModel {
                 .get_provider(provider_id)
                 .map_err(|e| CompletionError::ProviderError(e.to_string()))?
         };
+        Ok((provider_id, provider_config))
+    }
+
+    /// Direct call to the provider (no fallback logic).
+    async fn attempt_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let (provider_id, provider_config) = self.resolve_provider().await?;
 
         if provider_id == "zai-coding-plan" || provider_id == "zhipu" {
             let display_name = if provider_id == "zhipu" {
@@ -145,7 +167,9 @@ impl SpacebotModel {
         let model = if model_name == self.full_model_name {
             self.clone()
         } else {
-            SpacebotModel::make(&self.llm_manager, model_name)
+            let mut model = SpacebotModel::make(&self.llm_manager, model_name);
+            model.deltas = self.deltas.clone();
+            model
         };
 
         let mut last_error = None;
@@ -162,7 +186,12 @@ impl SpacebotModel {
             }
 
             match model.attempt_completion(request.clone()).await {
-                Ok(response) => return Ok(response),
+                Ok(response) => {
+                    self.llm_manager
+                        .record_provider_success(&model.provider)
+                        .await;
+                    return Ok(response);
+                }
                 Err(error) => {
                     let error_str = error.to_string();
                     if !routing::is_retriable_error(&error_str) {
@@ -182,6 +211,13 @@ impl SpacebotModel {
 
         let error_str = last_error.unwrap_or_default();
         let was_rate_limit = routing::is_rate_limit_error(&error_str);
+        // Rate limits mean the provider is up but busy, so they don't count
+        // toward opening its circuit.
+        if !was_rate_limit {
+            self.llm_manager
+                .record_provider_failure(&model.provider, &error_str)
+                .await;
+        }
         Err((
             CompletionError::ProviderError(format!(
                 "{model_name} failed after {MAX_RETRIES_PER_MODEL} attempts: {error_str}"
@@ -219,6 +255,7 @@ impl CompletionModel for SpacebotModel {
             routing: None,
             agent_id: None,
             process_type: None,
+            deltas: None,
         }
     }
 
@@ -229,7 +266,38 @@ impl CompletionModel for SpacebotModel {
         #[cfg(feature = "metrics")]
         let start = std::time::Instant::now();
 
+        let spend_tracker = match &self.agent_id {
+            Some(agent_id) => self.llm_manager.spend_tracker(agent_id).await,
+            None => None,
+        };
+        let spend_status = match &spend_tracker {
+            Some(tracker) => tracker.status().await,
+            None => SpendStatus::Normal,
+        };
+        let downgrade_model = match spend_status {
+            SpendStatus::Normal => None,
+            SpendStatus::Downgraded { model } => (model != self.full_model_name).then_some(model),
+            SpendStatus::Stopped { limit_usd } => {
+                return Err(CompletionError::ProviderError(format!(
+                    "daily spend limit of ${limit_usd:.2} reached for agent {}, LLM calls resume at 00:00 UTC",
+                    self.agent_id.as_deref().unwrap_or("unknown")
+                )));
+            }
+        };
+        let billed_model = downgrade_model
+            .clone()
+            .unwrap_or_else(|| self.full_model_name.clone());
+
         let result = async move {
+            // Past the soft spend limit every call goes to the downgrade
+            // model, bypassing routing fallbacks.
+            if let Some(model) = &downgrade_model {
+                return self
+                    .attempt_with_retries(model, &request)
+                    .await
+                    .map_err(|(error, _)| error);
+            }
+
             let Some(routing) = &self.routing else {
                 // No routing config — just call the model directly, no fallback/retry
                 return self.attempt_completion(request).await;
@@ -321,6 +389,13 @@ impl CompletionModel for SpacebotModel {
         }
         .await;
 
+        if let (Some(tracker), Ok(response)) = (&spend_tracker, &result) {
+            let process_type = self.process_type.as_deref().unwrap_or("unknown");
+            tracker
+                .record(process_type, &billed_model, &response.usage)
+                .await;
+        }
+
         #[cfg(feature = "metrics")]
         {
             let elapsed = start.elapsed().as_secs_f64();
@@ -405,6 +480,179 @@ impl CompletionModel for SpacebotModel {
 }
 
 impl SpacebotModel {
+    /// Stream a text reply as it's generated, for callers that act on partial
+    /// output. Streams natively from Anthropic and OpenAI chat completions
+    /// endpoints. Other APIs, requests with tools and spend-downgraded calls
+    /// go through `completion()` and arrive as a single chunk. A streamed call
+    /// has no retries or fallbacks, since part of the reply may already have
+    /// been used.
+    pub async fn stream_text(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<mpsc::Receiver<Result<String, CompletionError>>, CompletionError> {
+        let spend_tracker = match &self.agent_id {
+            Some(agent_id) => self.llm_manager.spend_tracker(agent_id).await,
+            None => None,
+        };
+        let spend_status = match &spend_tracker {
+            Some(tracker) => tracker.status().await,
+            None => SpendStatus::Normal,
+        };
+        let (provider_id, provider_config) = self.resolve_provider().await?;
+        let streamable = matches!(
+            provider_config.api_type,
+            ApiType::Anthropic | ApiType::OpenAiCompletions
+        ) && !matches!(provider_id, "zai-coding-plan" | "zhipu")
+            && request.tools.is_empty()
+            && matches!(spend_status, SpendStatus::Normal);
+
+        let (chunk_tx, chunk_rx) = mpsc::channel(64);
+        if !streamable {
+            let response = self.completion(request).await?;
+            let text = response
+                .choice
+                .iter()
+                .filter_map(|content| match content {
+                    AssistantContent::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<String>();
+            chunk_tx.send(Ok(text)).await.ok();
+            return Ok(chunk_rx);
+        }
+
+        let anthropic = provider_config.api_type == ApiType::Anthropic;
+        let response = if anthropic {
+            let effort = self
+                .routing
+                .as_ref()
+                .map(|r| r.thinking_effort_for_model(&self.model_name))
+                .unwrap_or("auto");
+            let anthropic_request = crate::llm::anthropic::build_anthropic_request(
+                self.llm_manager.http_client(),
+                &provider_config.api_key,
+                &provider_config.base_url,
+                &self.model_name,
+                &request,
+                effort,
+                true,
+            );
+            anthropic_request.builder.send().await
+        } else {
+            let mut messages = Vec::new();
+            if let Some(preamble) = &request.preamble {
+                messages.push(serde_json::json!({
+                    "role": "system",
+                    "content": preamble,
+                }));
+            }
+            messages.extend(convert_messages_to_openai(&request.chat_history));
+
+            let mut body = serde_json::json!({
+                "model": self.model_name,
+                "messages": messages,
+                "stream": true,
+                "stream_options": { "include_usage": true },
+            });
+            if let Some(max_tokens) = request.max_tokens {
+                body["max_tokens"] = serde_json::json!(max_tokens);
+            }
+            if let Some(temperature) = request.temperature {
+                body["temperature"] = serde_json::json!(temperature);
+            }
+
+            self.llm_manager
+                .http_client()
+                .post(format!(
+                    "{}/v1/chat/completions",
+                    provider_config.base_url.trim_end_matches('/')
+                ))
+                .header(
+                    "authorization",
+                    format!("Bearer {}", provider_config.api_key),
+                )
+                .header("content-type", "application/json")
+                .json(&body)
+                .send()
+                .await
+        };
+
+        let response = response.map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let response_text = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&response_text)
+                .ok()
+                .and_then(|body| body["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| truncate_body(&response_text).to_string());
+            self.llm_manager
+                .record_provider_failure(&self.provider, &message)
+                .await;
+            return Err(CompletionError::ProviderError(format!(
+                "{} streaming error ({status}): {message}",
+                self.full_model_name
+            )));
+        }
+        self.llm_manager
+            .record_provider_success(&self.provider)
+            .await;
+
+        let model = self.full_model_name.clone();
+        let process_type = self
+            .process_type
+            .clone()
+            .unwrap_or_else(|| "unknown".into());
+        tokio::spawn(async move {
+            let mut bytes = response.bytes_stream();
+            let mut reader = streaming::SseReader::default();
+            let mut usage = completion::Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+                total_tokens: 0,
+                cached_input_tokens: 0,
+            };
+
+            'stream: while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(error) => {
+                        chunk_tx
+                            .send(Err(CompletionError::ProviderError(error.to_string())))
+                            .await
+                            .ok();
+                        break;
+                    }
+                };
+                for data in reader.push(&chunk) {
+                    let Ok(event) = serde_json::from_str::<serde_json::Value>(&data) else {
+                        continue;
+                    };
+                    match parse_stream_event(anthropic, &event, &mut usage) {
+                        Ok(Some(text)) => {
+                            // The receiver dropping means the caller stopped
+                            // listening, e.g. after being interrupted.
+                            if chunk_tx.send(Ok(text)).await.is_err() {
+                                break 'stream;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(error) => {
+                            chunk_tx.send(Err(error)).await.ok();
+                            break 'stream;
+                        }
+                    }
+                }
+            }
+
+            usage.total_tokens = usage.input_tokens + usage.output_tokens;
+            if let Some(tracker) = spend_tracker {
+                tracker.record(&process_type, &model, &usage).await;
+            }
+        });
+
+        Ok(chunk_rx)
+    }
+
     async fn call_anthropic(
         &self,
         request: CompletionRequest,
@@ -424,6 +672,7 @@ impl SpacebotModel {
             &self.model_name,
             &request,
             effort,
+            self.deltas.is_some(),
         );
 
         let is_oauth =
@@ -437,6 +686,16 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(deltas) = &self.deltas
+        {
+            let response_body = streaming::read_streamed_body(response, true, deltas).await?;
+            let mut completion = parse_anthropic_response(response_body)?;
+            if is_oauth && !original_tools.is_empty() {
+                reverse_map_tool_names(&mut completion, &original_tools);
+            }
+            return Ok(completion);
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
@@ -517,6 +776,11 @@ impl SpacebotModel {
             body["tools"] = serde_json::json!(tools);
         }
 
+        if self.deltas.is_some() {
+            body["stream"] = serde_json::json!(true);
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
         let chat_completions_url = format!(
             "{}/v1/chat/completions",
             provider_config.base_url.trim_end_matches('/')
@@ -542,6 +806,12 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(deltas) = &self.deltas
+        {
+            let response_body = streaming::read_streamed_body(response, false, deltas).await?;
+            return parse_openai_response(response_body, "OpenAI");
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
@@ -1408,6 +1678,52 @@ fn parse_openai_responses_response(
     })
 }
 
+/// Text delta from one streamed event, updating the usage counts as they
+/// arrive.
+fn parse_stream_event(
+    anthropic: bool,
+    event: &serde_json::Value,
+    usage: &mut completion::Usage,
+) -> Result<Option<String>, CompletionError> {
+    if let Some(message) = event["error"]["message"].as_str() {
+        return Err(CompletionError::ProviderError(message.to_string()));
+    }
+
+    if anthropic {
+        match event["type"].as_str() {
+            Some("message_start") => {
+                let message_usage = &event["message"]["usage"];
+                usage.input_tokens = message_usage["input_tokens"].as_u64().unwrap_or(0);
+                usage.cached_input_tokens = message_usage["cache_read_input_tokens"]
+                    .as_u64()
+                    .unwrap_or(0);
+            }
+            Some("message_delta") => {
+                usage.output_tokens = event["usage"]["output_tokens"]
+                    .as_u64()
+                    .unwrap_or(usage.output_tokens);
+            }
+            Some("content_block_delta") if event["delta"]["type"] == "text_delta" => {
+                return Ok(event["delta"]["text"].as_str().map(str::to_string));
+            }
+            _ => {}
+        }
+        return Ok(None);
+    }
+
+    if event["usage"].is_object() {
+        usage.input_tokens = event["usage"]["prompt_tokens"].as_u64().unwrap_or(0);
+        usage.output_tokens = event["usage"]["completion_tokens"].as_u64().unwrap_or(0);
+        usage.cached_input_tokens = event["usage"]["prompt_tokens_details"]["cached_tokens"]
+            .as_u64()
+            .unwrap_or(0);
+    }
+    Ok(event["choices"][0]["delta"]["content"]
+        .as_str()
+        .filter(|text| !text.is_empty())
+        .map(str::to_string))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1450,4 +1766,44 @@ mod tests {
             panic!("expected ToolCall");
         }
     }
+
+    #[test]
+    fn stream_events_yield_text_and_usage() {
+        let mut usage = completion::Usage {
+            input_tokens: 0,
+            output_tokens: 0,
+            total_tokens: 0,
+            cached_input_tokens: 0,
+        };
+
+        let start = serde_json::json!({
+            "type": "message_start",
+            "message": { "usage": { "input_tokens": 12 } }
+        });
+        let delta = serde_json::json!({
+            "type": "content_block_delta",
+            "delta": { "type": "text_delta", "text": "Hi" }
+        });
+        assert_eq!(parse_stream_event(true, &start, &mut usage).unwrap(), None);
+        assert_eq!(
+            parse_stream_event(true, &delta, &mut usage).unwrap(),
+            Some("Hi".into())
+        );
+        assert_eq!(usage.input_tokens, 12);
+
+        let chunk = serde_json::json!({ "choices": [{ "delta": { "content": "there" } }] });
+        let last = serde_json::json!({
+            "choices": [],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 7 }
+        });
+        assert_eq!(
+            parse_stream_event(false, &chunk, &mut usage).unwrap(),
+            Some("there".into())
+        );
+        assert_eq!(parse_stream_event(false, &last, &mut usage).unwrap(), None);
+        assert_eq!(usage.output_tokens, 7);
+
+        let error = serde_json::json!({ "type": "error", "error": { "message": "overloaded" } });
+        assert!(parse_stream_event(true, &error, &mut usage).is_err());
+    }
 }
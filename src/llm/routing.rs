@@ -79,6 +79,8 @@ impl RoutingConfig {
             ProcessType::Worker => &self.worker,
             ProcessType::Compactor => &self.compactor,
             ProcessType::Cortex => &self.cortex,
+            // Voice replies use the conversational model.
+            ProcessType::Voice => &self.channel,
         }
     }
 
@@ -392,6 +394,9 @@ pub const MAX_RETRIES_PER_MODEL: usize = 3;
 /// Base delay for exponential backoff between retries (milliseconds).
 pub const RETRY_BASE_DELAY_MS: u64 = 500;
 
+/// Consecutive exhausted-retry failures before a provider's circuit opens.
+pub const PROVIDER_CIRCUIT_THRESHOLD: u32 = 3;
+
 /// Whether an error indicates an actual rate limit (429) vs other transient failures.
 /// Only rate-limit errors should trigger cooldown — timeouts and 5xx errors are
 /// momentary and shouldn't lock out a model for the full cooldown period.
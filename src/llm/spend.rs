@@ -0,0 +1,369 @@
+//! Per-agent LLM spend accounting and daily limits.
+//!
+//! Every successful completion is written to the agent's `llm_usage` table
+//! with an estimated cost from [`crate::llm::pricing`]. The tracker keeps a
+//! running total for the current UTC day. At the soft limit it notifies
+//! operators and reports a downgrade model. At the hard limit it notifies
+//! again and LLM calls are refused until the day rolls over.
+
+use crate::AgentId;
+use crate::config::{RuntimeConfig, SpendConfig};
+
+use chrono::{NaiveDate, Utc};
+use rig::completion::Usage;
+use sqlx::SqlitePool;
+use tokio::sync::{Mutex, broadcast};
+
+use std::sync::Arc;
+
+/// A daily spend limit was crossed.
+#[derive(Debug, Clone)]
+pub enum SpendEvent {
+    SoftLimitReached {
+        agent_id: AgentId,
+        spent_usd: f64,
+        limit_usd: f64,
+        downgrade_model: Option<String>,
+    },
+    HardLimitReached {
+        agent_id: AgentId,
+        spent_usd: f64,
+        limit_usd: f64,
+    },
+}
+
+/// What the model layer should do for the next call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpendStatus {
+    Normal,
+    /// Soft limit reached. Use this model instead of the routed one.
+    Downgraded {
+        model: String,
+    },
+    /// Hard limit reached. Refuse the call.
+    Stopped {
+        limit_usd: f64,
+    },
+}
+
+#[derive(Debug)]
+struct DailySpend {
+    day: NaiveDate,
+    spent_usd: f64,
+    soft_notified: bool,
+    hard_notified: bool,
+}
+
+impl DailySpend {
+    fn new(day: NaiveDate, spent_usd: f64) -> Self {
+        Self {
+            day,
+            spent_usd,
+            soft_notified: false,
+            hard_notified: false,
+        }
+    }
+
+    /// How the next call should be handled at this spend.
+    fn status(&self, spend: &SpendConfig) -> SpendStatus {
+        if let Some(limit_usd) = spend.hard_limit_usd
+            && self.spent_usd >= limit_usd
+        {
+            return SpendStatus::Stopped { limit_usd };
+        }
+        if let Some(limit) = spend.soft_limit_usd
+            && self.spent_usd >= limit
+            && let Some(model) = &spend.downgrade_model
+        {
+            return SpendStatus::Downgraded {
+                model: model.clone(),
+            };
+        }
+        SpendStatus::Normal
+    }
+
+    /// Add a call's cost. Returns the limits it crossed that hadn't been
+    /// crossed yet today.
+    fn add(&mut self, agent_id: &AgentId, cost: f64, spend: &SpendConfig) -> Vec<SpendEvent> {
+        self.spent_usd += cost;
+
+        let mut events = Vec::new();
+        if let Some(limit_usd) = spend.soft_limit_usd
+            && !self.soft_notified
+            && self.spent_usd >= limit_usd
+        {
+            self.soft_notified = true;
+            events.push(SpendEvent::SoftLimitReached {
+                agent_id: agent_id.clone(),
+                spent_usd: self.spent_usd,
+                limit_usd,
+                downgrade_model: spend.downgrade_model.clone(),
+            });
+        }
+        if let Some(limit_usd) = spend.hard_limit_usd
+            && !self.hard_notified
+            && self.spent_usd >= limit_usd
+        {
+            self.hard_notified = true;
+            events.push(SpendEvent::HardLimitReached {
+                agent_id: agent_id.clone(),
+                spent_usd: self.spent_usd,
+                limit_usd,
+            });
+        }
+        events
+    }
+}
+
+/// Records usage for one agent and enforces its spend limits.
+pub struct SpendTracker {
+    agent_id: AgentId,
+    pool: SqlitePool,
+    runtime_config: Arc<RuntimeConfig>,
+    event_tx: broadcast::Sender<SpendEvent>,
+    today: Mutex<DailySpend>,
+}
+
+impl std::fmt::Debug for SpendTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpendTracker")
+            .field("agent_id", &self.agent_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SpendTracker {
+    /// Create a tracker, loading today's spend so far from the database.
+    pub async fn new(
+        agent_id: AgentId,
+        pool: SqlitePool,
+        runtime_config: Arc<RuntimeConfig>,
+        event_tx: broadcast::Sender<SpendEvent>,
+    ) -> Self {
+        let day = Utc::now().date_naive();
+        let spent_usd = load_spent_since(&pool, day).await.unwrap_or_else(|error| {
+            tracing::warn!(%error, %agent_id, "failed to load today's LLM spend");
+            0.0
+        });
+
+        let mut today = DailySpend::new(day, spent_usd);
+        // Don't re-notify after a restart for limits already crossed today.
+        let spend = runtime_config.spend.load();
+        today.soft_notified = spend.soft_limit_usd.is_some_and(|limit| spent_usd >= limit);
+        today.hard_notified = spend.hard_limit_usd.is_some_and(|limit| spent_usd >= limit);
+
+        Self {
+            agent_id,
+            pool,
+            runtime_config,
+            event_tx,
+            today: Mutex::new(today),
+        }
+    }
+
+    /// Estimated spend for the current UTC day.
+    pub async fn spent_today(&self) -> f64 {
+        let mut today = self.today.lock().await;
+        roll_over(&mut today);
+        today.spent_usd
+    }
+
+    /// Decide how the next call should be handled.
+    pub async fn status(&self) -> SpendStatus {
+        let mut today = self.today.lock().await;
+        roll_over(&mut today);
+        today.status(&self.runtime_config.spend.load())
+    }
+
+    /// Record a completed call and notify if it crossed a limit.
+    pub async fn record(&self, process_type: &str, model: &str, usage: &Usage) {
+        let cost = crate::llm::pricing::estimate_cost(
+            model,
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cached_input_tokens,
+        );
+        if let Err(error) = insert_usage(&self.pool, process_type, model, usage, cost).await {
+            tracing::warn!(%error, agent_id = %self.agent_id, "failed to record LLM usage");
+        }
+
+        let mut today = self.today.lock().await;
+        roll_over(&mut today);
+        let spend = self.runtime_config.spend.load();
+        for event in today.add(&self.agent_id, cost, &spend) {
+            match &event {
+                SpendEvent::SoftLimitReached {
+                    spent_usd,
+                    limit_usd,
+                    ..
+                } => tracing::warn!(
+                    agent_id = %self.agent_id,
+                    spent_usd,
+                    limit_usd,
+                    "daily LLM spend soft limit reached"
+                ),
+                SpendEvent::HardLimitReached {
+                    spent_usd,
+                    limit_usd,
+                    ..
+                } => tracing::error!(
+                    agent_id = %self.agent_id,
+                    spent_usd,
+                    limit_usd,
+                    "daily LLM spend hard limit reached, refusing further calls today"
+                ),
+            }
+            self.event_tx.send(event).ok();
+        }
+    }
+}
+
+/// Reset the running total when the UTC day changes.
+fn roll_over(today: &mut DailySpend) {
+    let current = Utc::now().date_naive();
+    if today.day != current {
+        *today = DailySpend::new(current, 0.0);
+    }
+}
+
+async fn insert_usage(
+    pool: &SqlitePool,
+    process_type: &str,
+    model: &str,
+    usage: &Usage,
+    cost: f64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO llm_usage \
+         (process_type, model, input_tokens, output_tokens, cached_input_tokens, estimated_cost_usd) \
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(process_type)
+    .bind(model)
+    .bind(usage.input_tokens as i64)
+    .bind(usage.output_tokens as i64)
+    .bind(usage.cached_input_tokens as i64)
+    .bind(cost)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn load_spent_since(pool: &SqlitePool, day: NaiveDate) -> Result<f64, sqlx::Error> {
+    let start = day.format("%Y-%m-%d 00:00:00").to_string();
+    let total: Option<f64> =
+        sqlx::query_scalar("SELECT SUM(estimated_cost_usd) FROM llm_usage WHERE created_at >= ?")
+            .bind(start)
+            .fetch_one(pool)
+            .await?;
+    Ok(total.unwrap_or(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> SpendConfig {
+        SpendConfig {
+            soft_limit_usd: Some(1.0),
+            hard_limit_usd: Some(2.0),
+            downgrade_model: Some("anthropic/claude-haiku-4".into()),
+        }
+    }
+
+    #[test]
+    fn soft_limit_falls_back_to_the_cheaper_model_and_notifies_once() {
+        let agent_id: AgentId = Arc::from("main");
+        let spend = limits();
+        let mut today = DailySpend::new(Utc::now().date_naive(), 0.0);
+
+        assert!(today.add(&agent_id, 0.5, &spend).is_empty());
+        assert_eq!(today.status(&spend), SpendStatus::Normal);
+
+        let events = today.add(&agent_id, 0.6, &spend);
+        assert!(matches!(
+            events.as_slice(),
+            [SpendEvent::SoftLimitReached { downgrade_model: Some(model), .. }]
+                if model == "anthropic/claude-haiku-4"
+        ));
+        assert_eq!(
+            today.status(&spend),
+            SpendStatus::Downgraded {
+                model: "anthropic/claude-haiku-4".into()
+            }
+        );
+
+        assert!(today.add(&agent_id, 0.1, &spend).is_empty());
+    }
+
+    #[test]
+    fn hard_limit_stops_calls_and_notifies_once() {
+        let agent_id: AgentId = Arc::from("main");
+        let spend = limits();
+        let mut today = DailySpend::new(Utc::now().date_naive(), 0.0);
+
+        // Crossing both limits in one call notifies for both.
+        let events = today.add(&agent_id, 2.5, &spend);
+        assert!(matches!(
+            events.as_slice(),
+            [
+                SpendEvent::SoftLimitReached { .. },
+                SpendEvent::HardLimitReached { limit_usd, .. }
+            ] if *limit_usd == 2.0
+        ));
+        assert_eq!(
+            today.status(&spend),
+            SpendStatus::Stopped { limit_usd: 2.0 }
+        );
+
+        assert!(today.add(&agent_id, 1.0, &spend).is_empty());
+    }
+
+    #[test]
+    fn soft_limit_without_a_downgrade_model_keeps_the_routed_one() {
+        let spend = SpendConfig {
+            downgrade_model: None,
+            ..limits()
+        };
+        let today = DailySpend::new(Utc::now().date_naive(), 1.5);
+        assert_eq!(today.status(&spend), SpendStatus::Normal);
+    }
+
+    #[test]
+    fn a_new_day_resets_the_spend_and_notices() {
+        let agent_id: AgentId = Arc::from("main");
+        let spend = limits();
+        let yesterday = Utc::now().date_naive().pred_opt().unwrap();
+        let mut today = DailySpend::new(yesterday, 0.0);
+        assert_eq!(today.add(&agent_id, 2.5, &spend).len(), 2);
+
+        roll_over(&mut today);
+        assert_eq!(today.day, Utc::now().date_naive());
+        assert_eq!(today.spent_usd, 0.0);
+        assert_eq!(today.status(&spend), SpendStatus::Normal);
+        assert_eq!(today.add(&agent_id, 1.0, &spend).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn recorded_usage_counts_toward_the_day() {
+        let pool = crate::db::test_pool().await;
+        let usage = Usage {
+            input_tokens: 1000,
+            output_tokens: 500,
+            total_tokens: 1500,
+            cached_input_tokens: 0,
+        };
+        let day = Utc::now().date_naive();
+
+        insert_usage(&pool, "channel", "anthropic/claude-sonnet-4", &usage, 0.25)
+            .await
+            .unwrap();
+        insert_usage(&pool, "worker", "anthropic/claude-sonnet-4", &usage, 0.5)
+            .await
+            .unwrap();
+
+        assert_eq!(load_spent_since(&pool, day).await.unwrap(), 0.75);
+        let tomorrow = day.succ_opt().unwrap();
+        assert_eq!(load_spent_since(&pool, tomorrow).await.unwrap(), 0.0);
+    }
+}
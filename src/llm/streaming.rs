@@ -0,0 +1,438 @@
+//! Streamed completions for callers that show a reply while it's generated.
+//!
+//! A model given a [`DeltaSender`] asks Anthropic and OpenAI chat completions
+//! endpoints for a server-sent event stream. Text and tool call arguments are
+//! forwarded as [`CompletionDelta`]s as they arrive, and the events are folded
+//! back into the body a non-streaming call would have returned, so the usual
+//! response parsing, tool calls and retries apply unchanged.
+
+use rig::completion::CompletionError;
+use tokio::sync::mpsc;
+
+use futures::StreamExt as _;
+
+/// Partial output of a streamed completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionDelta {
+    /// More of the text the model is writing outside tool calls.
+    Text(String),
+    /// The model started a tool call.
+    ToolCall { id: String, name: String },
+    /// More of a tool call's JSON arguments.
+    ToolArguments { id: String, json: String },
+}
+
+/// Receives deltas from every streamed call a model makes.
+pub type DeltaSender = mpsc::UnboundedSender<CompletionDelta>;
+
+/// Read a successful SSE response to the end, forwarding deltas, and return
+/// the equivalent non-streaming response body.
+pub(crate) async fn read_streamed_body(
+    response: reqwest::Response,
+    anthropic: bool,
+    deltas: &DeltaSender,
+) -> Result<serde_json::Value, CompletionError> {
+    let mut assembler = StreamAssembler::new(anthropic);
+    let mut bytes = response.bytes_stream();
+    let mut reader = SseReader::default();
+    while let Some(chunk) = bytes.next().await {
+        let chunk = chunk.map_err(|error| CompletionError::ProviderError(error.to_string()))?;
+        for data in reader.push(&chunk) {
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(&data) else {
+                continue;
+            };
+            for delta in assembler.apply(&event)? {
+                // The receiver going away only stops the preview.
+                deltas.send(delta).ok();
+            }
+        }
+    }
+    Ok(assembler.into_body())
+}
+
+/// Splits a server-sent event stream into its `data:` payloads.
+///
+/// Bytes are held until their line is complete, so a character split across
+/// two network chunks is decoded whole.
+#[derive(Debug, Default)]
+pub(crate) struct SseReader {
+    buffer: Vec<u8>,
+}
+
+impl SseReader {
+    /// Add a chunk and take the payloads of the lines it completed, leaving
+    /// any partial line for the next chunk.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let Some(end) = self.buffer.iter().rposition(|byte| *byte == b'\n') else {
+            return Vec::new();
+        };
+        let complete: Vec<u8> = self.buffer.drain(..=end).collect();
+        complete
+            .split(|byte| *byte == b'\n')
+            .filter_map(|line| line.strip_prefix(b"data:"))
+            .map(|data| String::from_utf8_lossy(data).trim().to_string())
+            .filter(|data| !data.is_empty() && data != "[DONE]")
+            .collect()
+    }
+}
+
+/// Folds streamed events into a non-streaming response body.
+struct StreamAssembler {
+    anthropic: bool,
+    body: serde_json::Value,
+    /// Raw JSON arguments of each tool call, by content block or tool call
+    /// index.
+    arguments: Vec<(usize, String)>,
+}
+
+impl StreamAssembler {
+    fn new(anthropic: bool) -> Self {
+        let body = if anthropic {
+            serde_json::json!({ "content": [], "usage": {} })
+        } else {
+            serde_json::json!({
+                "choices": [{ "message": { "content": "", "tool_calls": [] } }],
+                "usage": {},
+            })
+        };
+        Self {
+            anthropic,
+            body,
+            arguments: Vec::new(),
+        }
+    }
+
+    fn arguments_mut(&mut self, index: usize) -> &mut String {
+        let position = match self.arguments.iter().position(|(i, _)| *i == index) {
+            Some(position) => position,
+            None => {
+                self.arguments.push((index, String::new()));
+                self.arguments.len() - 1
+            }
+        };
+        &mut self.arguments[position].1
+    }
+
+    /// Apply one event, returning the deltas it carried.
+    fn apply(
+        &mut self,
+        event: &serde_json::Value,
+    ) -> Result<Vec<CompletionDelta>, CompletionError> {
+        if let Some(message) = event["error"]["message"].as_str() {
+            return Err(CompletionError::ProviderError(message.to_string()));
+        }
+        if self.anthropic {
+            Ok(self.apply_anthropic(event))
+        } else {
+            Ok(self.apply_openai(event))
+        }
+    }
+
+    fn apply_anthropic(&mut self, event: &serde_json::Value) -> Vec<CompletionDelta> {
+        let index = event["index"].as_u64().unwrap_or(0) as usize;
+        match event["type"].as_str() {
+            Some("message_start") => {
+                let mut message = event["message"].clone();
+                message["content"] = serde_json::json!([]);
+                self.body = message;
+            }
+            Some("content_block_start") => {
+                let block = event["content_block"].clone();
+                let delta = (block["type"] == "tool_use").then(|| CompletionDelta::ToolCall {
+                    id: block["id"].as_str().unwrap_or("").to_string(),
+                    name: block["name"].as_str().unwrap_or("").to_string(),
+                });
+                if let Some(content) = self.body["content"].as_array_mut() {
+                    content.resize(content.len().max(index + 1), serde_json::Value::Null);
+                    content[index] = block;
+                }
+                return delta.into_iter().collect();
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                let Some(block) = self.body["content"].get_mut(index) else {
+                    return Vec::new();
+                };
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        let text = delta["text"].as_str().unwrap_or("");
+                        let mut joined = block["text"].as_str().unwrap_or("").to_string();
+                        joined.push_str(text);
+                        block["text"] = joined.into();
+                        return vec![CompletionDelta::Text(text.to_string())];
+                    }
+                    Some("input_json_delta") => {
+                        let id = block["id"].as_str().unwrap_or("").to_string();
+                        let json = delta["partial_json"].as_str().unwrap_or("").to_string();
+                        self.arguments_mut(index).push_str(&json);
+                        return vec![CompletionDelta::ToolArguments { id, json }];
+                    }
+                    // Thinking isn't part of the reply.
+                    _ => {}
+                }
+            }
+            Some("content_block_stop") => {
+                if self.body["content"][index]["type"] == "tool_use" {
+                    let arguments = std::mem::take(self.arguments_mut(index));
+                    let input = if arguments.trim().is_empty() {
+                        serde_json::json!({})
+                    } else {
+                        serde_json::from_str(&arguments).unwrap_or(serde_json::json!({}))
+                    };
+                    if let Some(block) = self.body["content"].get_mut(index) {
+                        block["input"] = input;
+                    }
+                }
+            }
+            Some("message_delta") => {
+                if let Some(stop_reason) = event["delta"]["stop_reason"].as_str() {
+                    self.body["stop_reason"] = stop_reason.into();
+                }
+                if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.body["usage"]["output_tokens"] = output_tokens.into();
+                }
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn apply_openai(&mut self, event: &serde_json::Value) -> Vec<CompletionDelta> {
+        if event["usage"].is_object() {
+            self.body["usage"] = event["usage"].clone();
+        }
+        let choice = &event["choices"][0];
+        if let Some(finish_reason) = choice["finish_reason"].as_str() {
+            self.body["choices"][0]["finish_reason"] = finish_reason.into();
+        }
+
+        let mut deltas = Vec::new();
+        let delta = &choice["delta"];
+        let message = &mut self.body["choices"][0]["message"];
+        for field in ["content", "reasoning_content"] {
+            if let Some(text) = delta[field].as_str().filter(|text| !text.is_empty()) {
+                let mut joined = message[field].as_str().unwrap_or("").to_string();
+                joined.push_str(text);
+                message[field] = joined.into();
+                if field == "content" {
+                    deltas.push(CompletionDelta::Text(text.to_string()));
+                }
+            }
+        }
+
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let index = call["index"].as_u64().unwrap_or(0) as usize;
+            let Some(tool_calls) = self.body["choices"][0]["message"]["tool_calls"].as_array_mut()
+            else {
+                continue;
+            };
+            if tool_calls.len() <= index {
+                tool_calls.resize(
+                    index + 1,
+                    serde_json::json!({
+                        "type": "function",
+                        "function": { "name": "", "arguments": "" },
+                    }),
+                );
+            }
+            let entry = &mut tool_calls[index];
+            if let Some(id) = call["id"].as_str() {
+                entry["id"] = id.into();
+            }
+            if let Some(name) = call["function"]["name"].as_str() {
+                entry["function"]["name"] = name.into();
+                deltas.push(CompletionDelta::ToolCall {
+                    id: entry["id"].as_str().unwrap_or("").to_string(),
+                    name: name.to_string(),
+                });
+            }
+            if let Some(json) = call["function"]["arguments"].as_str() {
+                let id = entry["id"].as_str().unwrap_or("").to_string();
+                let mut arguments = entry["function"]["arguments"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string();
+                arguments.push_str(json);
+                entry["function"]["arguments"] = arguments.into();
+                if !json.is_empty() {
+                    deltas.push(CompletionDelta::ToolArguments {
+                        id,
+                        json: json.to_string(),
+                    });
+                }
+            }
+        }
+        deltas
+    }
+
+    fn into_body(self) -> serde_json::Value {
+        self.body
+    }
+}
+
+/// The value of a top-level string field in a JSON object that may still be
+/// arriving, as far as it has been written. `None` until the field starts.
+pub fn partial_string_field(json: &str, field: &str) -> Option<String> {
+    let key = format!("\"{field}\"");
+    let start = json.find(&key)? + key.len();
+    let rest = json[start..].trim_start().strip_prefix(':')?;
+    let mut characters = rest.trim_start().strip_prefix('"')?.chars();
+
+    let mut value = String::new();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' => break,
+            '\\' => match characters.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('b') => value.push('\u{8}'),
+                Some('f') => value.push('\u{c}'),
+                Some('u') => {
+                    let code: String = characters.by_ref().take(4).collect();
+                    // A surrogate half or an unfinished escape shows up once
+                    // the rest of it arrives.
+                    match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                        Some(decoded) if code.len() == 4 => value.push(decoded),
+                        _ => break,
+                    }
+                }
+                Some(escaped) => value.push(escaped),
+                None => break,
+            },
+            character => value.push(character),
+        }
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_all(
+        assembler: &mut StreamAssembler,
+        events: &[serde_json::Value],
+    ) -> Vec<CompletionDelta> {
+        events
+            .iter()
+            .flat_map(|event| assembler.apply(event).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn anthropic_events_rebuild_the_message() {
+        let mut assembler = StreamAssembler::new(true);
+        let deltas = apply_all(
+            &mut assembler,
+            &[
+                serde_json::json!({"type": "message_start", "message": {"content": [], "usage": {"input_tokens": 9}}}),
+                serde_json::json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                serde_json::json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "On it."}}),
+                serde_json::json!({"type": "content_block_stop", "index": 0}),
+                serde_json::json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "t1", "name": "reply", "input": {}}}),
+                serde_json::json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"content\": \"Hel"}}),
+                serde_json::json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "lo\"}"}}),
+                serde_json::json!({"type": "content_block_stop", "index": 1}),
+                serde_json::json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 4}}),
+            ],
+        );
+
+        assert_eq!(
+            deltas,
+            [
+                CompletionDelta::Text("On it.".into()),
+                CompletionDelta::ToolCall {
+                    id: "t1".into(),
+                    name: "reply".into()
+                },
+                CompletionDelta::ToolArguments {
+                    id: "t1".into(),
+                    json: "{\"content\": \"Hel".into()
+                },
+                CompletionDelta::ToolArguments {
+                    id: "t1".into(),
+                    json: "lo\"}".into()
+                },
+            ]
+        );
+        let body = assembler.into_body();
+        assert_eq!(body["content"][0]["text"], "On it.");
+        assert_eq!(body["content"][1]["input"]["content"], "Hello");
+        assert_eq!(body["usage"]["input_tokens"], 9);
+        assert_eq!(body["usage"]["output_tokens"], 4);
+        assert_eq!(body["stop_reason"], "tool_use");
+    }
+
+    #[test]
+    fn openai_chunks_rebuild_the_message() {
+        let mut assembler = StreamAssembler::new(false);
+        let deltas = apply_all(
+            &mut assembler,
+            &[
+                serde_json::json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "id": "c1", "function": {"name": "reply", "arguments": ""}}]}}]}),
+                serde_json::json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{\"content\":\"Hi"}}]}}]}),
+                serde_json::json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "\"}"}}]}, "finish_reason": "tool_calls"}]}),
+                serde_json::json!({"choices": [], "usage": {"prompt_tokens": 3, "completion_tokens": 2}}),
+            ],
+        );
+
+        assert_eq!(deltas.len(), 3);
+        let body = assembler.into_body();
+        let call = &body["choices"][0]["message"]["tool_calls"][0];
+        assert_eq!(call["id"], "c1");
+        assert_eq!(call["function"]["arguments"], "{\"content\":\"Hi\"}");
+        assert_eq!(body["usage"]["completion_tokens"], 2);
+    }
+
+    #[test]
+    fn sse_payloads_are_split_across_chunks() {
+        let mut reader = SseReader::default();
+        assert_eq!(
+            reader.push(b"event: x\ndata: {\"a\":1}\n\ndata: {\"b\""),
+            vec![r#"{"a":1}"#.to_string()]
+        );
+        assert_eq!(
+            reader.push(b":2}\r\n\ndata: [DONE]\n"),
+            vec![r#"{"b":2}"#.to_string()]
+        );
+        assert!(reader.buffer.is_empty());
+    }
+
+    #[test]
+    fn sse_characters_split_across_chunks_decode_whole() {
+        let line = "data: {\"text\":\"café 日本\"}\n".as_bytes();
+        // Cut inside the two-byte é and the three-byte 日.
+        let split_e = line.iter().position(|byte| *byte == 0xc3).unwrap() + 1;
+        let split_ri = line.iter().position(|byte| *byte == 0xe6).unwrap() + 2;
+
+        let mut reader = SseReader::default();
+        assert!(reader.push(&line[..split_e]).is_empty());
+        assert!(reader.push(&line[split_e..split_ri]).is_empty());
+        assert_eq!(
+            reader.push(&line[split_ri..]),
+            vec!["{\"text\":\"café 日本\"}".to_string()]
+        );
+    }
+
+    #[test]
+    fn partial_string_fields_decode_as_they_arrive() {
+        assert_eq!(
+            partial_string_field("{\"thread_name\": \"x\"", "content"),
+            None
+        );
+        assert_eq!(
+            partial_string_field("{\"content\": \"line\\none \\\"q", "content"),
+            Some("line\none \"q".into())
+        );
+        assert_eq!(
+            partial_string_field("{\"content\":\"caf\\u00e9 \\u00", "content"),
+            Some("café ".into())
+        );
+        assert_eq!(
+            partial_string_field("{\"content\": \"done\", \"cards\": []}", "content"),
+            Some("done".into())
+        );
+    }
+}
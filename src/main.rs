@@ -43,12 +43,37 @@ enum Command {
     },
     /// Show status of the running daemon
     Status,
+    /// Check config, provider keys, adapter credentials, databases, disk space and caches
+    Doctor,
     /// Manage skills
     #[command(subcommand)]
     Skill(SkillCommand),
     /// Manage authentication
     #[command(subcommand)]
     Auth(AuthCommand),
+    /// Replay scripted conversations and report turn latency
+    Bench {
+        /// Bench script (TOML) with the conversations to replay
+        script: std::path::PathBuf,
+        /// Conversations in flight at once (overrides the script)
+        #[arg(long)]
+        concurrency: Option<usize>,
+        /// Times each conversation is replayed (overrides the script)
+        #[arg(long)]
+        repeat: Option<usize>,
+        /// Answer every completion from a local mock instead of the configured providers
+        #[arg(long)]
+        mock_llm: bool,
+        /// Delay before each mock completion, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        mock_latency_ms: u64,
+    },
+    /// Chat with an agent in the terminal, without any messaging platform
+    Chat {
+        /// Agent to talk to (defaults to the default agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -117,13 +142,17 @@ enum SkillCommand {
 
 /// Tracks an active conversation channel and its message sender.
 struct ActiveChannel {
+    /// Agent running this channel. A conversation handed to another agent
+    /// gets a new channel.
+    agent_id: spacebot::AgentId,
     message_tx: mpsc::Sender<spacebot::InboundMessage>,
     /// Latest inbound message for this conversation, shared with the outbound
     /// routing task so status updates (e.g. typing indicators) target the
     /// most recent message rather than the first one the channel ever received.
     latest_message: Arc<tokio::sync::RwLock<spacebot::InboundMessage>>,
-    /// Retained so the outbound routing task stays alive.
-    _outbound_handle: tokio::task::JoinHandle<()>,
+    /// Outbound routing, which ends once the channel has stopped and its
+    /// last responses are sent. Shutdown waits on it.
+    outbound_handle: tokio::task::JoinHandle<()>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -142,8 +171,24 @@ fn main() -> anyhow::Result<()> {
             cmd_start(cli.config, cli.debug, foreground)
         }
         Command::Status => cmd_status(),
+        Command::Doctor => cmd_doctor(cli.config),
         Command::Skill(skill_cmd) => cmd_skill(cli.config, skill_cmd),
         Command::Auth(auth_cmd) => cmd_auth(cli.config, auth_cmd),
+        Command::Bench {
+            script,
+            concurrency,
+            repeat,
+            mock_llm,
+            mock_latency_ms,
+        } => cmd_bench(
+            cli.config,
+            cli.debug,
+            script,
+            concurrency,
+            repeat,
+            mock_llm.then(|| std::time::Duration::from_millis(mock_latency_ms)),
+        ),
+        Command::Chat { agent } => cmd_chat(cli.config, cli.debug, agent),
     }
 }
 
@@ -202,10 +247,110 @@ fn cmd_start(
             spacebot::daemon::init_background_tracing(&paths, debug, &config.telemetry)
         };
 
-        run(config, foreground, otel_provider).await
+        run(config, foreground, otel_provider, None).await
     })
 }
 
+fn cmd_bench(
+    config_path: Option<std::path::PathBuf>,
+    debug: bool,
+    script_path: std::path::PathBuf,
+    concurrency: Option<usize>,
+    repeat: Option<usize>,
+    mock_latency: Option<std::time::Duration>,
+) -> anyhow::Result<()> {
+    let mut script = spacebot::bench::BenchScript::load(&script_path)?;
+    script.concurrency = concurrency.unwrap_or(script.concurrency).max(1);
+    script.repeat = repeat.unwrap_or(script.repeat).max(1);
+
+    let mut config = load_config(&config_path)?;
+    let bench_dir = spacebot::bench::prepare_instance(&mut config)?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build Tokio runtime")?;
+
+    runtime.block_on(async {
+        let otel_provider = spacebot::daemon::init_foreground_tracing(debug, &config.telemetry);
+
+        if let Some(latency) = mock_latency {
+            let address = spacebot::bench::start_mock_llm(latency).await?;
+            spacebot::bench::use_mock_llm(&mut config, address);
+        } else if !config.llm.has_any_key() {
+            anyhow::bail!("no LLM providers configured, pass --mock-llm to bench against a mock");
+        }
+
+        eprintln!("bench instance: {}", bench_dir.display());
+        run(config, true, otel_provider, Some(Harness::Bench(script))).await
+    })
+}
+
+fn cmd_chat(
+    config_path: Option<std::path::PathBuf>,
+    debug: bool,
+    agent_id: Option<String>,
+) -> anyhow::Result<()> {
+    let paths = spacebot::daemon::DaemonPaths::from_default();
+    if let Some(pid) = spacebot::daemon::is_running(&paths) {
+        anyhow::bail!(
+            "spacebot is already running (pid {pid}); stop it first, chat opens the same databases"
+        );
+    }
+
+    let mut config = load_config(&config_path)?;
+    let agent_id = agent_id.unwrap_or_else(|| config.default_agent_id().to_string());
+    if !config.agents.iter().any(|agent| agent.id == agent_id) {
+        anyhow::bail!("agent not found: {agent_id}");
+    }
+    spacebot::messaging::cli::prepare_config(&mut config);
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build Tokio runtime")?;
+
+    runtime.block_on(async {
+        // Logs go to the log directory so they don't interleave with the chat.
+        let paths = spacebot::daemon::DaemonPaths::new(&config.instance_dir);
+        std::fs::create_dir_all(&paths.log_dir).with_context(|| {
+            format!(
+                "failed to create log directory: {}",
+                paths.log_dir.display()
+            )
+        })?;
+        let otel_provider =
+            spacebot::daemon::init_background_tracing(&paths, debug, &config.telemetry);
+
+        eprintln!("chatting with {agent_id}, /attach <path> to send a file, /quit to leave");
+        eprintln!("logs: {}", paths.log_dir.display());
+        let adapter = spacebot::messaging::cli::CliAdapter::new(agent_id);
+        run(config, false, otel_provider, Some(Harness::Chat(adapter))).await
+    })
+}
+
+#[tokio::main]
+async fn cmd_doctor(config_path: Option<std::path::PathBuf>) -> anyhow::Result<()> {
+    let config = load_config(&config_path);
+    let instance_dir = match (&config, &config_path) {
+        (Ok(config), _) => config.instance_dir.clone(),
+        (Err(_), Some(path)) => path
+            .parent()
+            .map(std::path::Path::to_path_buf)
+            .unwrap_or_else(spacebot::config::Config::default_instance_dir),
+        (Err(_), None) => spacebot::config::Config::default_instance_dir(),
+    };
+
+    eprintln!("checking {}...\n", instance_dir.display());
+    let report = spacebot::doctor::run(&instance_dir, &config).await;
+    println!("{report}");
+
+    if report.has_failures() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn cmd_stop() -> anyhow::Result<()> {
     let paths = spacebot::daemon::DaemonPaths::from_default();
@@ -233,16 +378,29 @@ async fn cmd_stop() -> anyhow::Result<()> {
         }
     }
 
-    if spacebot::daemon::wait_for_exit(pid) {
+    let timeout = stop_timeout();
+    if spacebot::daemon::wait_for_exit(pid, timeout) {
         eprintln!("spacebot stopped");
     } else {
-        eprintln!("spacebot did not stop within 10 seconds (pid {pid})");
+        eprintln!(
+            "spacebot did not stop within {} seconds (pid {pid})",
+            timeout.as_secs()
+        );
         std::process::exit(1);
     }
 
     Ok(())
 }
 
+/// How long to wait for the daemon to stop: its drain timeout, the write
+/// flush after it, and a little longer to close up after.
+fn stop_timeout() -> std::time::Duration {
+    let drain_timeout_secs = spacebot::config::Config::load()
+        .map(|config| config.shutdown.drain_timeout_secs)
+        .unwrap_or(spacebot::shutdown::ShutdownConfig::default().drain_timeout_secs);
+    std::time::Duration::from_secs(drain_timeout_secs + 10) + spacebot::shutdown::FLUSH_TIMEOUT
+}
+
 /// Stop if running, don't error if not.
 fn cmd_stop_if_running() {
     let paths = spacebot::daemon::DaemonPaths::from_default();
@@ -263,7 +421,7 @@ fn cmd_stop_if_running() {
             spacebot::daemon::send_command(&paths, spacebot::daemon::IpcCommand::Shutdown).await
         {
             eprintln!("stopping spacebot (pid {pid})...");
-            spacebot::daemon::wait_for_exit(pid);
+            spacebot::daemon::wait_for_exit(pid, stop_timeout());
         }
     });
 }
@@ -587,12 +745,22 @@ fn load_config(
     }
 }
 
+/// A local front end that drives the instance instead of the platforms.
+enum Harness {
+    /// Replay a bench script, print the report and stop.
+    Bench(spacebot::bench::BenchScript),
+    /// Chat with an agent in the terminal until the user quits.
+    Chat(spacebot::messaging::cli::CliAdapter),
+}
+
 async fn run(
     config: spacebot::config::Config,
     foreground: bool,
     otel_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+    harness: Option<Harness>,
 ) -> anyhow::Result<()> {
     let paths = spacebot::daemon::DaemonPaths::new(&config.instance_dir);
+    let shutdown_config = config.shutdown.clone();
 
     tracing::info!("starting spacebot");
     tracing::info!(instance_dir = %config.instance_dir.display(), "configuration loaded");
@@ -619,8 +787,20 @@ async fn run(
     let api_state = Arc::new(api_state);
 
     // Start background update checker
+    spacebot::update::initialize(
+        &api_state.update_status,
+        &config.instance_dir,
+        &config.update,
+    );
     spacebot::update::spawn_update_checker(api_state.update_status.clone());
 
+    let cluster = spacebot::cluster::Cluster::start(&config.cluster)
+        .await
+        .context("failed to join cluster")?;
+    if let Some(cluster) = &cluster {
+        api_state.set_cluster(cluster.clone());
+    }
+
     // Start metrics server if enabled (requires `metrics` cargo feature)
     #[cfg(feature = "metrics")]
     let _metrics_handle = if config.metrics.enabled {
@@ -671,26 +851,49 @@ async fn run(
         }
     }
 
+    // The LLM manager and the embedding model don't depend on each other.
+    // Loading the embedding model is blocking work that can take seconds on
+    // a cold cache, so it runs on the blocking pool while the LLM manager
+    // loads credentials.
+    let shared_started = std::time::Instant::now();
+
     // Shared LLM manager (same API keys for all agents)
     // This works even without keys; it will fail later at call time if no keys exist.
     // Loads OAuth credentials from auth.json if available.
-    let llm_manager = Arc::new(
+    let llm_manager_init = async {
         spacebot::llm::LlmManager::with_instance_dir(
             config.llm.clone(),
             config.instance_dir.clone(),
         )
         .await
-        .with_context(|| "failed to initialize LLM manager")?,
-    );
+        .map(Arc::new)
+        .with_context(|| "failed to initialize LLM manager")
+    };
 
     // Shared embedding model (stateless, agent-agnostic)
     let embedding_cache_dir = config.instance_dir.join("embedding_cache");
-    let embedding_model = Arc::new(
-        spacebot::memory::EmbeddingModel::new(&embedding_cache_dir)
-            .context("failed to initialize embedding model")?,
-    );
+    let embedding_model_init = async {
+        let started = std::time::Instant::now();
+        let model = tokio::task::spawn_blocking(move || {
+            spacebot::memory::EmbeddingModel::new(&embedding_cache_dir)
+        })
+        .await
+        .context("embedding model loader panicked")?
+        .context("failed to initialize embedding model")?;
+        tracing::info!(
+            stage = "embedding_model",
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "startup stage finished"
+        );
+        anyhow::Ok(Arc::new(model))
+    };
 
-    tracing::info!("shared resources initialized");
+    let (llm_manager, embedding_model) = tokio::try_join!(llm_manager_init, embedding_model_init)?;
+
+    tracing::info!(
+        elapsed_ms = shared_started.elapsed().as_millis() as u64,
+        "shared resources initialized"
+    );
 
     // Initialize the language for all text lookups (must happen before PromptEngine/tools)
     spacebot::prompts::text::init("en").with_context(|| "failed to initialize language")?;
@@ -713,6 +916,9 @@ async fn run(
     let bindings: Arc<ArcSwap<Vec<spacebot::config::Binding>>> =
         Arc::new(ArcSwap::from_pointee(config.bindings.clone()));
     api_state.set_bindings(bindings.clone()).await;
+    let middleware = Arc::new(ArcSwap::from_pointee(
+        spacebot::messaging::middleware::MiddlewarePipeline::from_config(&config.middleware),
+    ));
     let default_agent_id = config.default_agent_id().to_string();
 
     // Set the config path on the API state for config.toml writes
@@ -723,6 +929,44 @@ async fn run(
     api_state.set_prompt_engine(prompt_engine.clone()).await;
     api_state.set_defaults_config(config.defaults.clone()).await;
 
+    let session_store = spacebot::messaging::session::SessionStore::new(
+        &config.instance_dir.join("sessions.redb"),
+        config.messaging.sessions,
+    )
+    .context("failed to open session store")?;
+    match session_store.prune(chrono::Duration::days(30)) {
+        Ok(0) => {}
+        Ok(pruned) => tracing::info!(pruned, "pruned stale sessions"),
+        Err(error) => tracing::warn!(%error, "failed to prune stale sessions"),
+    }
+    api_state.set_session_store(Arc::new(session_store));
+
+    let delivery_store = spacebot::messaging::delivery::DeliveryStore::new(
+        &config.instance_dir.join("webhook_deliveries.redb"),
+    )
+    .context("failed to open webhook delivery store")?;
+    match delivery_store.prune(chrono::Duration::days(30)) {
+        Ok(0) => {}
+        Ok(pruned) => tracing::info!(pruned, "pruned stale webhook deliveries"),
+        Err(error) => tracing::warn!(%error, "failed to prune webhook deliveries"),
+    }
+    api_state.set_webhook_deliveries(Arc::new(delivery_store));
+
+    let handoff_store = Arc::new(
+        spacebot::agent::handoff::HandoffStore::new(&config.instance_dir.join("handoffs.redb"))
+            .context("failed to open handoff store")?,
+    );
+    api_state.set_handoff_store(handoff_store.clone());
+
+    let pairing_store = Arc::new(
+        spacebot::messaging::pairing::PairingStore::new(
+            &config.instance_dir.join("pairing.redb"),
+            config.messaging.pairing.clone(),
+        )
+        .context("failed to open pairing store")?,
+    );
+    api_state.set_pairing_store(pairing_store.clone());
+
     // Track whether agents have been initialized
     let mut agents_initialized = false;
 
@@ -736,6 +980,10 @@ async fn run(
         let mut slack_permissions = None;
         let mut telegram_permissions = None;
         let mut twitch_permissions = None;
+        let mut irc_permissions = None;
+        let mut email_permissions = None;
+        let mut xmpp_permissions = None;
+        let mut whatsapp_permissions = None;
         initialize_agents(
             &config,
             &llm_manager,
@@ -753,6 +1001,10 @@ async fn run(
             &mut slack_permissions,
             &mut telegram_permissions,
             &mut twitch_permissions,
+            &mut irc_permissions,
+            &mut email_permissions,
+            &mut xmpp_permissions,
+            &mut whatsapp_permissions,
         )
         .await?;
         agents_initialized = true;
@@ -766,8 +1018,14 @@ async fn run(
             slack_permissions,
             telegram_permissions,
             twitch_permissions,
+            irc_permissions,
+            email_permissions,
+            xmpp_permissions,
+            whatsapp_permissions,
             bindings.clone(),
+            middleware.clone(),
             Some(messaging_manager.clone()),
+            api_state.pairing_store.load().as_ref().clone(),
             llm_manager.clone(),
         );
     } else {
@@ -780,7 +1038,13 @@ async fn run(
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
             bindings.clone(),
+            middleware.clone(),
+            None,
             None,
             llm_manager.clone(),
         );
@@ -795,8 +1059,38 @@ async fn run(
         tracing::info!(pid = std::process::id(), "spacebot daemon started");
     }
 
+    // With a harness, drive the instance through its adapter and stop once
+    // the script finishes or the user leaves the chat.
+    let mut harness_run: std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> =
+        match harness {
+            Some(Harness::Bench(script)) if agents_initialized => {
+                let adapter = spacebot::bench::BenchAdapter::new();
+                messaging_manager
+                    .register_and_start(adapter.clone())
+                    .await
+                    .context("failed to start bench adapter")?;
+                Box::pin(async move {
+                    println!("{}", spacebot::bench::run_script(adapter, script).await);
+                })
+            }
+            Some(Harness::Chat(adapter)) if agents_initialized => {
+                messaging_manager
+                    .register_and_start(adapter.clone())
+                    .await
+                    .context("failed to start chat adapter")?;
+                Box::pin(async move { adapter.closed().await })
+            }
+            Some(_) => anyhow::bail!("this command needs an LLM provider, none is configured"),
+            None => Box::pin(std::future::pending()),
+        };
+
     // Active conversation channels: conversation_id -> ActiveChannel
     let mut active_channels: HashMap<String, ActiveChannel> = HashMap::new();
+    let mut rate_limiter = spacebot::messaging::rate_limit::RateLimiter::new();
+    // Hibernated channels are dropped from `active_channels` on this tick.
+    let mut hibernation_sweep = tokio::time::interval(std::time::Duration::from_secs(60));
+
+    let mut terminate = spacebot::shutdown::terminate_signal();
 
     // Main event loop: route inbound messages to agent channels
     loop {
@@ -809,8 +1103,38 @@ async fn run(
         };
         tokio::select! {
             Some(mut message) = inbound_next, if agents_initialized => {
+                // Middleware filters and rewrites messages before routing.
+                if let spacebot::messaging::middleware::MiddlewareAction::Block { reply } =
+                    middleware.load().run(&mut message)
+                {
+                    if let Some(reply) = reply
+                        && let Err(error) = messaging_manager
+                            .respond(&message, spacebot::OutboundResponse::Text(reply))
+                            .await
+                    {
+                        tracing::warn!(%error, "failed to send middleware reply");
+                    }
+                    continue;
+                }
+
+                // Handed-off conversations stay with the agent they were
+                // handed to, as long as it still exists.
+                let handed_off = match handoff_store.get(&message.conversation_id) {
+                    Ok(handoff) => handoff
+                        .map(|handoff| handoff.to_agent)
+                        .filter(|agent_id| agents.contains_key(agent_id.as_str())),
+                    Err(error) => {
+                        tracing::warn!(%error, "failed to look up conversation handoff");
+                        None
+                    }
+                };
+
                 let agent_id = if let Some(existing) = message.agent_id.as_ref() {
                     existing.clone()
+                } else if let Some(handed_off) = handed_off {
+                    let resolved: spacebot::AgentId = Arc::from(handed_off.as_str());
+                    message.agent_id = Some(resolved.clone());
+                    resolved
                 } else {
                     let current_bindings = bindings.load();
                     let resolved = spacebot::config::resolve_agent_for_message(
@@ -818,174 +1142,184 @@ async fn run(
                         &message,
                         &default_agent_id,
                     );
+                    let binding =
+                        spacebot::config::resolve_binding_for_message(&current_bindings, &message);
+                    // Mention-only, wake-word and thread-only bindings sit
+                    // quietly in busy channels until they're addressed.
+                    if binding.is_some_and(|binding| !binding.is_addressed(&message)) {
+                        tracing::trace!(
+                            conversation_id = %message.conversation_id,
+                            "message not addressed to the bot, ignoring"
+                        );
+                        continue;
+                    }
+                    // Digest bindings have their channel collect the message
+                    // for the next summary instead of replying to it.
+                    if let Some(interval_secs) =
+                        binding.and_then(|binding| binding.digest_interval_secs)
+                    {
+                        message.metadata.insert(
+                            spacebot::agent::channel::DIGEST_INTERVAL_KEY.into(),
+                            interval_secs.into(),
+                        );
+                    }
+                    if binding.is_some_and(|binding| binding.reply_to_message) {
+                        message.metadata.insert(
+                            spacebot::agent::channel::REPLY_TO_MESSAGE_KEY.into(),
+                            true.into(),
+                        );
+                    }
+                    if let Some(binding) = binding
+                        && binding.response_mode != spacebot::config::ResponseMode::Typing
+                    {
+                        message.metadata.insert(
+                            spacebot::messaging::response_mode::RESPONSE_MODE_KEY.into(),
+                            binding.response_mode.as_str().into(),
+                        );
+                    }
+                    if let Some(binding) = binding
+                        && !binding.overrides.is_empty()
+                        && let Ok(overrides) = serde_json::to_value(&binding.overrides)
+                    {
+                        message.metadata.insert(
+                            spacebot::agent::channel::BINDING_OVERRIDES_KEY.into(),
+                            overrides,
+                        );
+                    }
                     message.agent_id = Some(resolved.clone());
                     resolved
                 };
 
                 let conversation_id = message.conversation_id.clone();
 
-                // Find or create a channel for this conversation
-                if !active_channels.contains_key(&conversation_id) {
-                    let Some(agent) = agents.get(&agent_id) else {
+                if let Some(handoff) = spacebot::agent::handoff::seed_handoff(&message) {
+                    if !agents.contains_key(handoff.to_agent.as_str()) {
                         tracing::warn!(
-                            agent_id = %agent_id,
+                            to_agent = %handoff.to_agent,
                             conversation_id = %conversation_id,
-                            "message routed to unknown agent, dropping"
+                            "handoff to unknown agent, dropping"
                         );
                         continue;
-                    };
-
-                    // Create outbound response channel
-                    let (response_tx, mut response_rx) = mpsc::channel::<spacebot::OutboundResponse>(32);
-
-                    // Subscribe to the agent's event bus
-                    let event_rx = agent.deps.event_tx.subscribe();
-
-                    let channel_id: spacebot::ChannelId = Arc::from(conversation_id.as_str());
+                    }
+                    if let Err(error) = handoff_store.record(&handoff) {
+                        tracing::error!(%error, "failed to record conversation handoff");
+                    }
+                }
 
-                    let (channel, channel_tx) = spacebot::agent::channel::Channel::new(
-                        channel_id,
-                        agent.deps.clone(),
-                        response_tx,
-                        event_rx,
-                        agent.config.screenshot_dir(),
-                        agent.config.logs_dir(),
-                    );
+                // Every node receives messages from shared adapters; only the
+                // node holding the conversation's lease handles them.
+                if let Some(cluster) = &cluster
+                    && !cluster.should_handle(&message.source, &conversation_id).await
+                {
+                    continue;
+                }
 
-                    // Register the channel's status block with the API for snapshot queries
-                    api_state.register_channel_status(
-                        conversation_id.clone(),
-                        channel.state.status_block.clone(),
-                    ).await;
-
-                    // Register the channel state for API-driven cancellation
-                    api_state.register_channel_state(
-                        conversation_id.clone(),
-                        channel.state.clone(),
-                    ).await;
-
-                    // Backfill recent message history from the platform
-                    let backfill_count = agent.config.history_backfill_count();
-                    if backfill_count > 0 {
-                        match messaging_manager.fetch_history(&message, backfill_count).await {
-                            Ok(history_messages) if !history_messages.is_empty() => {
-                                let mut transcript = String::new();
-                                for entry in &history_messages {
-                                    let label = if entry.is_bot { "(you)" } else { &entry.author };
-                                    transcript.push_str(&format!("{}: {}\n", label, entry.content));
-                                }
+                // Admins answer pairing requests in the control channel.
+                if let Some(agent) = agents.get(&agent_id)
+                    && let Some(reply) = pairing_store
+                        .handle_command(&message, &agent.deps.runtime_config.rbac.load())
+                {
+                    if let Err(error) = messaging_manager
+                        .respond(&message, spacebot::OutboundResponse::Text(reply))
+                        .await
+                    {
+                        tracing::warn!(%error, "failed to answer pairing command");
+                    }
+                    continue;
+                }
 
-                                let prompt_engine = agent.deps.runtime_config.prompts.load();
-                                let backfill_text = prompt_engine
-                                    .render_system_history_backfill(transcript.trim_end())
-                                    .unwrap_or(transcript);
+                // Admins answer identity proposals in the reflection
+                // control channel.
+                if let Some(agent) = agents.get(&agent_id)
+                    && let Some(reply) =
+                        spacebot::agent::reflection::handle_command(&agent.deps, &message).await
+                {
+                    if let Err(error) = messaging_manager
+                        .respond(&message, spacebot::OutboundResponse::Text(reply))
+                        .await
+                    {
+                        tracing::warn!(%error, "failed to answer identity command");
+                    }
+                    continue;
+                }
 
-                                let mut history = channel.state.history.write().await;
-                                history.push(rig::message::Message::from(backfill_text));
-                                drop(history);
+                // Answers to a confirmation a tool is waiting on go straight
+                // to the tool; the channel is still busy with its turn.
+                if let Some(agent) = agents.get(&agent_id)
+                    && agent.deps.confirmations.resolve(&message)
+                {
+                    continue;
+                }
 
-                                tracing::info!(
-                                    conversation_id = %conversation_id,
-                                    message_count = history_messages.len(),
-                                    "backfilled channel history"
-                                );
-                            }
-                            Err(error) => {
-                                tracing::warn!(%error, "failed to backfill channel history");
-                            }
-                            _ => {}
+                // Senders and conversations over their limit are told once,
+                // then dropped until their allowance refills.
+                if let Some(agent) = agents.get(&agent_id) {
+                    let config = agent.deps.runtime_config.rate_limit.load();
+                    let decision = rate_limiter.check(&agent_id, &message, &config);
+                    if let spacebot::messaging::rate_limit::RateLimitDecision::Throttle {
+                        notify,
+                    } = decision
+                    {
+                        tracing::debug!(
+                            sender_id = %message.sender_id,
+                            conversation_id = %conversation_id,
+                            "message rate limited"
+                        );
+                        if notify
+                            && !config.message.is_empty()
+                            && let Err(error) = messaging_manager
+                                .respond(
+                                    &message,
+                                    spacebot::OutboundResponse::Text(config.message.clone()),
+                                )
+                                .await
+                        {
+                            tracing::warn!(%error, "failed to send rate limit notice");
                         }
+                        continue;
                     }
+                }
 
-                    // Spawn the channel's event loop
-                    tokio::spawn(async move {
-                        if let Err(error) = channel.run().await {
-                            tracing::error!(%error, "channel event loop failed");
-                        }
-                    });
+                // Scripts see the message first and may handle it themselves.
+                if let Some(agent) = agents.get(&agent_id)
+                    && spacebot::scripting::on_message(
+                        &spacebot::scripting::ScriptContext::new(agent),
+                        &message,
+                    )
+                    .await
+                {
+                    continue;
+                }
 
-                    // Spawn outbound response routing: reads from response_rx,
-                    // sends to the messaging adapter and forwards to SSE
-                    let messaging_for_outbound = messaging_manager.clone();
-                    let latest_message = Arc::new(tokio::sync::RwLock::new(message.clone()));
-                    let outbound_message = latest_message.clone();
-                    let outbound_conversation_id = conversation_id.clone();
-                    let api_event_tx = api_state.event_tx.clone();
-                    let sse_agent_id = agent_id.to_string();
-                    let sse_channel_id = conversation_id.clone();
-                    let outbound_handle = tokio::spawn(async move {
-                        while let Some(response) = response_rx.recv().await {
-                            // Forward relevant events to SSE clients
-                            match &response {
-                                spacebot::OutboundResponse::Text(text) => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        text: text.clone(),
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::RichMessage { text, .. } => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        text: text.clone(),
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::ThreadReply { text, .. } => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        text: text.clone(),
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::Status(spacebot::StatusUpdate::Thinking) => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::TypingState {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        is_typing: true,
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::Status(spacebot::StatusUpdate::StopTyping) => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::TypingState {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        is_typing: false,
-                                    }).ok();
-                                }
-                                _ => {}
-                            }
+                // A channel whose event loop has exited is materialized again
+                // from its conversation log, and one run by another agent is
+                // replaced after a handoff.
+                if active_channels.get(&conversation_id).is_some_and(|active| {
+                    active.message_tx.is_closed() || active.agent_id != agent_id
+                }) {
+                    active_channels.remove(&conversation_id);
+                }
 
-                            let current_message = outbound_message.read().await.clone();
-                            match response {
-                                spacebot::OutboundResponse::Status(status) => {
-                                    if let Err(error) = messaging_for_outbound
-                                        .send_status(&current_message, status)
-                                        .await
-                                    {
-                                        tracing::warn!(%error, "failed to send status update");
-                                    }
-                                }
-                                response => {
-                                    tracing::info!(
-                                        conversation_id = %outbound_conversation_id,
-                                        "routing outbound response to messaging adapter"
-                                    );
-                                    if let Err(error) = messaging_for_outbound
-                                        .respond(&current_message, response)
-                                        .await
-                                    {
-                                        tracing::error!(%error, "failed to send outbound response");
-                                    }
-                                }
-                            }
-                        }
-                    });
+                // Find or create a channel for this conversation
+                if !active_channels.contains_key(&conversation_id) {
+                    let Some(agent) = agents.get(&agent_id) else {
+                        tracing::warn!(
+                            agent_id = %agent_id,
+                            conversation_id = %conversation_id,
+                            "message routed to unknown agent, dropping"
+                        );
+                        continue;
+                    };
 
-                    active_channels.insert(conversation_id.clone(), ActiveChannel {
-                        message_tx: channel_tx,
-                        latest_message,
-                        _outbound_handle: outbound_handle,
-                    });
+                    let active = materialize_channel(
+                        agent,
+                        &conversation_id,
+                        &message,
+                        &api_state,
+                        &messaging_manager,
+                    )
+                    .await;
+                    active_channels.insert(conversation_id.clone(), active);
 
                     tracing::info!(
                         conversation_id = %conversation_id,
@@ -997,8 +1331,11 @@ async fn run(
                 // Forward the message to the channel
                 if let Some(active) = active_channels.get(&conversation_id) {
                     // Update the shared message reference so outbound routing
-                    // (typing indicators, reactions) targets this message
-                    *active.latest_message.write().await = message.clone();
+                    // (typing indicators, reactions) targets this message.
+                    // Replies keep their target even when a reaction starts a turn.
+                    if !matches!(message.content, spacebot::MessageContent::Reaction { .. }) {
+                        *active.latest_message.write().await = message.clone();
+                    }
 
                     // Emit inbound message to SSE clients
                     let sender_name = message.formatted_author.clone().or_else(|| {
@@ -1016,16 +1353,46 @@ async fn run(
                         text: message.content.to_string(),
                     }).ok();
 
-                    if let Err(error) = active.message_tx.send(message).await {
-                        tracing::error!(
-                            conversation_id = %conversation_id,
-                            %error,
-                            "failed to forward message to channel"
-                        );
+                    if let Err(mpsc::error::SendError(message)) =
+                        active.message_tx.send(message).await
+                    {
+                        // The channel hibernated after the check above, so
+                        // wake it again for this message.
                         active_channels.remove(&conversation_id);
+                        if let Some(agent) = agents.get(&agent_id) {
+                            let active = materialize_channel(
+                                agent,
+                                &conversation_id,
+                                &message,
+                                &api_state,
+                                &messaging_manager,
+                            )
+                            .await;
+                            if active.message_tx.send(message).await.is_ok() {
+                                active_channels.insert(conversation_id.clone(), active);
+                            } else {
+                                tracing::error!(
+                                    conversation_id = %conversation_id,
+                                    "failed to forward message to channel"
+                                );
+                            }
+                        }
                     }
                 }
             }
+            _ = hibernation_sweep.tick() => {
+                let hibernated: Vec<String> = active_channels
+                    .iter()
+                    .filter(|(_, active)| active.message_tx.is_closed())
+                    .map(|(conversation_id, _)| conversation_id.clone())
+                    .collect();
+                for conversation_id in hibernated {
+                    active_channels.remove(&conversation_id);
+                    api_state.unregister_channel_status(&conversation_id).await;
+                    api_state.unregister_channel_state(&conversation_id).await;
+                    tracing::debug!(conversation_id = %conversation_id, "dropped hibernated channel");
+                }
+            }
             Some(agent) = agent_rx.recv() => {
                 tracing::info!(agent_id = %agent.id, "registering new agent in main loop");
                 agents.insert(agent.id.clone(), agent);
@@ -1063,6 +1430,10 @@ async fn run(
                                 let mut new_slack_permissions = None;
                                 let mut new_telegram_permissions = None;
                                 let mut new_twitch_permissions = None;
+                                let mut new_irc_permissions = None;
+                                let mut new_email_permissions = None;
+                                let mut new_xmpp_permissions = None;
+                                let mut new_whatsapp_permissions = None;
                                 match initialize_agents(
                                     &new_config,
                                     &new_llm_manager,
@@ -1080,6 +1451,10 @@ async fn run(
                                     &mut new_slack_permissions,
                                     &mut new_telegram_permissions,
                                     &mut new_twitch_permissions,
+                                    &mut new_irc_permissions,
+                                    &mut new_email_permissions,
+                                    &mut new_xmpp_permissions,
+                                    &mut new_whatsapp_permissions,
                                 ).await {
                                     Ok(()) => {
                                         agents_initialized = true;
@@ -1092,8 +1467,14 @@ async fn run(
                                             new_slack_permissions,
                                             new_telegram_permissions,
                                             new_twitch_permissions,
+                                            new_irc_permissions,
+                                            new_email_permissions,
+                                            new_xmpp_permissions,
+                                            new_whatsapp_permissions,
                                             bindings.clone(),
+                                            middleware.clone(),
                                             Some(messaging_manager.clone()),
+                                            api_state.pairing_store.load().as_ref().clone(),
                                             new_llm_manager.clone(),
                                         );
                                         tracing::info!("agents initialized after provider setup");
@@ -1116,27 +1497,41 @@ async fn run(
                     }
                 }
             }
+            _ = &mut harness_run => break,
             _ = shutdown_rx.wait_for(|shutdown| *shutdown) => {
                 tracing::info!("shutdown signal received via IPC");
                 break;
             }
+            _ = spacebot::update::restart_requested() => {
+                tracing::info!("restarting to finish binary update");
+                break;
+            }
             _ = tokio::signal::ctrl_c() => {
                 tracing::info!("shutdown signal received");
                 break;
             }
+            _ = &mut terminate => {
+                tracing::info!("SIGTERM received");
+                break;
+            }
         }
     }
 
     // Graceful shutdown
-    drop(active_channels);
-
     for scheduler in &cron_schedulers_for_shutdown {
         scheduler.shutdown().await;
     }
     drop(cron_schedulers_for_shutdown);
 
+    drain(active_channels, &agents, &shutdown_config).await;
+
+    // Adapters go last, once the final replies are out.
     messaging_manager.shutdown().await;
 
+    if let Some(cluster) = &cluster {
+        cluster.leave().await;
+    }
+
     for (agent_id, agent) in agents {
         tracing::info!(%agent_id, "shutting down agent");
         agent.deps.mcp_manager.disconnect_all().await;
@@ -1155,78 +1550,374 @@ async fn run(
 
     spacebot::daemon::cleanup(&paths);
 
+    // A binary update swapped the executable; exec into it now that the old
+    // instance has released its sockets and PID file.
+    if spacebot::update::restart_pending() {
+        let error = spacebot::update::restart_process();
+        eprintln!("{error:#}");
+        std::process::exit(1);
+    }
+
     // Force exit — detached tasks (e.g. the serenity gateway client) may keep
     // the tokio runtime alive after all owned resources have been cleaned up.
     std::process::exit(0);
 }
 
-/// Initialize agents, messaging adapters, cron, cortex, and ingestion.
-/// Extracted so it can be called either at startup or after providers are configured.
-#[allow(clippy::too_many_arguments)]
-async fn initialize_agents(
+/// Materialize the channel for a conversation when its first message
+/// arrives: build the channel, restore its history, and spawn its event loop
+/// and outbound routing. Nothing exists for a conversation before that, so
+/// instances bound to many guilds and channels only pay for the ones in use.
+async fn materialize_channel(
+    agent: &spacebot::Agent,
+    conversation_id: &str,
+    message: &spacebot::InboundMessage,
+    api_state: &Arc<spacebot::api::ApiState>,
+    messaging_manager: &Arc<spacebot::messaging::MessagingManager>,
+) -> ActiveChannel {
+    // Create outbound response channel
+    let (response_tx, mut response_rx) = mpsc::channel::<spacebot::OutboundResponse>(32);
+
+    // Subscribe to the agent's event bus
+    let event_rx = agent.deps.event_tx.subscribe();
+
+    let channel_id: spacebot::ChannelId = Arc::from(conversation_id);
+
+    let (channel, channel_tx) = spacebot::agent::channel::Channel::new(
+        channel_id,
+        agent.deps.clone(),
+        response_tx,
+        event_rx,
+        agent.config.screenshot_dir(),
+        agent.config.logs_dir(),
+    );
+
+    // Register the channel's status block with the API for snapshot queries
+    api_state
+        .register_channel_status(
+            conversation_id.to_string(),
+            channel.state.status_block.clone(),
+        )
+        .await;
+
+    // Register the channel state for API-driven cancellation
+    api_state
+        .register_channel_state(conversation_id.to_string(), channel.state.clone())
+        .await;
+
+    // Restore history from the conversation log first, so a conversation
+    // that was active before a restart or eviction picks up where it left
+    // off. Fall back to the platform's history for new conversations.
+    let backfill_count = agent.config.history_backfill_count();
+    let restored = if backfill_count > 0 {
+        channel.rehydrate_history(backfill_count).await
+    } else {
+        0
+    };
+    if restored > 0 {
+        tracing::info!(
+            conversation_id = %conversation_id,
+            message_count = restored,
+            "restored channel history from conversation log"
+        );
+    } else if backfill_count > 0
+        && !message
+            .metadata
+            .contains_key(spacebot::agent::fork::FORK_HISTORY_KEY)
+        && !message
+            .metadata
+            .contains_key(spacebot::agent::handoff::HANDOFF_KEY)
+    {
+        // Forked threads start from their parent's history instead, and
+        // handoffs carry the recent messages in their brief.
+        match messaging_manager
+            .fetch_history(message, backfill_count)
+            .await
+        {
+            Ok(history_messages) if !history_messages.is_empty() => {
+                let mut transcript = String::new();
+                for entry in &history_messages {
+                    let label = if entry.is_bot { "(you)" } else { &entry.author };
+                    transcript.push_str(&format!("{}: {}\n", label, entry.content));
+                }
+
+                let prompt_engine = agent.deps.runtime_config.prompts.load();
+                let backfill_text = prompt_engine
+                    .render_system_history_backfill(transcript.trim_end())
+                    .unwrap_or(transcript);
+
+                let mut history = channel.state.history.write().await;
+                history.push(rig::message::Message::from(backfill_text));
+                drop(history);
+
+                tracing::info!(
+                    conversation_id = %conversation_id,
+                    message_count = history_messages.len(),
+                    "backfilled channel history"
+                );
+            }
+            Err(error) => {
+                tracing::warn!(%error, "failed to backfill channel history");
+            }
+            _ => {}
+        }
+    }
+
+    // Spawn the channel's event loop
+    let (stopped_tx, mut stopped_rx) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        if let Err(error) = channel.run().await {
+            tracing::error!(%error, "channel event loop failed");
+        }
+        stopped_tx.send(()).ok();
+    });
+
+    // Spawn outbound response routing: reads from response_rx,
+    // sends to the messaging adapter and forwards to SSE
+    let messaging_for_outbound = messaging_manager.clone();
+    let latest_message = Arc::new(tokio::sync::RwLock::new(message.clone()));
+    let outbound_message = latest_message.clone();
+    let outbound_conversation_id = conversation_id.to_string();
+    let api_event_tx = api_state.event_tx.clone();
+    let sse_agent_id = agent.deps.agent_id.to_string();
+    let sse_channel_id = conversation_id.to_string();
+    let outbound_deps = agent.deps.clone();
+    let outbound_handle = tokio::spawn(async move {
+        let mut shaper = spacebot::messaging::response_mode::ResponseShaper::default();
+        let mut channel_stopped = false;
+        loop {
+            let response = tokio::select! {
+                response = response_rx.recv() => response,
+                // Other holders of the sender outlive the channel. Once it
+                // has stopped, send what it left queued and finish.
+                _ = &mut stopped_rx, if !channel_stopped => {
+                    channel_stopped = true;
+                    response_rx.close();
+                    continue;
+                }
+            };
+            let Some(response) = response else {
+                break;
+            };
+            // Forward relevant events to SSE clients
+            match &response {
+                spacebot::OutboundResponse::Text(text) => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::OutboundMessage {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            text: text.clone(),
+                        })
+                        .ok();
+                }
+                spacebot::OutboundResponse::RichMessage { text, .. } => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::OutboundMessage {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            text: text.clone(),
+                        })
+                        .ok();
+                }
+                spacebot::OutboundResponse::ThreadReply { text, .. } => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::OutboundMessage {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            text: text.clone(),
+                        })
+                        .ok();
+                }
+                spacebot::OutboundResponse::Choices { prompt, options } => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::OutboundMessage {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            text: spacebot::choices_text(prompt, options),
+                        })
+                        .ok();
+                }
+                spacebot::OutboundResponse::Rich { text, card } => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::OutboundMessage {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            text: spacebot::rich_text(text, card),
+                        })
+                        .ok();
+                }
+                spacebot::OutboundResponse::Status(spacebot::StatusUpdate::Thinking) => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::TypingState {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            is_typing: true,
+                        })
+                        .ok();
+                }
+                spacebot::OutboundResponse::Status(spacebot::StatusUpdate::StopTyping) => {
+                    api_event_tx
+                        .send(spacebot::api::ApiEvent::TypingState {
+                            agent_id: sse_agent_id.clone(),
+                            channel_id: sse_channel_id.clone(),
+                            is_typing: false,
+                        })
+                        .ok();
+                }
+                _ => {}
+            }
+
+            let current_message = outbound_message.read().await.clone();
+            let speech = spacebot::agent::voice::reply_to_speak(&current_message, &response);
+            // The binding's response mode decides how replies are shown.
+            let mode = spacebot::messaging::response_mode::response_mode(&current_message);
+            let streaming = messaging_for_outbound
+                .supports_streaming(&current_message)
+                .await;
+            for response in shaper.shape(mode, streaming, response) {
+                match response {
+                    spacebot::OutboundResponse::Status(status) => {
+                        if let Err(error) = messaging_for_outbound
+                            .send_status(&current_message, status)
+                            .await
+                        {
+                            tracing::warn!(%error, "failed to send status update");
+                        }
+                    }
+                    response => {
+                        tracing::info!(
+                            conversation_id = %outbound_conversation_id,
+                            "routing outbound response to messaging adapter"
+                        );
+                        if let Err(error) = messaging_for_outbound
+                            .respond(&current_message, response)
+                            .await
+                        {
+                            tracing::error!(%error, "failed to send outbound response");
+                        }
+                    }
+                }
+            }
+            if let Some(text) = speech {
+                let deps = outbound_deps.clone();
+                tokio::spawn(async move {
+                    spacebot::agent::voice::speak_reply(&deps, &current_message, &text).await;
+                });
+            }
+        }
+    });
+
+    ActiveChannel {
+        agent_id: agent.id.clone(),
+        message_tx: channel_tx,
+        latest_message,
+        outbound_handle,
+    }
+}
+
+/// Let in-flight work finish before the process exits: channel turns and
+/// their workers, detached workers and background writes, up to the drain
+/// timeout. See [`spacebot::shutdown`].
+async fn drain(
+    active_channels: HashMap<String, ActiveChannel>,
+    agents: &HashMap<spacebot::AgentId, spacebot::Agent>,
+    config: &spacebot::shutdown::ShutdownConfig,
+) {
+    tracing::info!(
+        channels = active_channels.len(),
+        drain_timeout_secs = config.drain_timeout_secs,
+        "draining before shutdown"
+    );
+    for agent in agents.values() {
+        agent.deps.runtime_config.worker_slots.close();
+    }
+
+    // Dropping each channel's sender tells it no more messages are coming.
+    let outbound_handles: Vec<_> = active_channels
+        .into_values()
+        .map(|active| active.outbound_handle)
+        .collect();
+    let work = async {
+        futures::future::join_all(outbound_handles).await;
+        while agents
+            .values()
+            .any(|agent| agent.deps.runtime_config.worker_slots.counts().0 > 0)
+        {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    };
+    let timeout = std::time::Duration::from_secs(config.drain_timeout_secs);
+    match tokio::time::timeout(timeout, work).await {
+        Ok(()) => tracing::info!("drained"),
+        Err(_) => tracing::warn!("drain timed out, stopping with work still in flight"),
+    }
+
+    // Flushed even after a timed-out drain, so the audit and conversation
+    // log keep what the cut-off work already wrote.
+    let flush = spacebot::shutdown::flush_writes();
+    if tokio::time::timeout(spacebot::shutdown::FLUSH_TIMEOUT, flush)
+        .await
+        .is_err()
+    {
+        tracing::warn!("stopping with background writes still pending");
+    }
+}
+
+/// Agent state handed to the file watcher.
+type WatchedAgent = (
+    String,
+    std::path::PathBuf,
+    Arc<spacebot::config::RuntimeConfig>,
+    Arc<spacebot::mcp::McpManager>,
+);
+
+/// Await one startup stage and log how long it took.
+async fn timed_stage<T>(
+    agent_id: &str,
+    stage: &'static str,
+    future: impl std::future::Future<Output = T>,
+) -> T {
+    let started = std::time::Instant::now();
+    let output = future.await;
+    tracing::info!(
+        agent_id,
+        stage,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "startup stage finished"
+    );
+    output
+}
+
+/// Initialize a single agent.
+///
+/// Dependency order: storage (directories, SQLite migrations, LanceDB and
+/// the settings store), MCP server launches and identity/skill loading are
+/// independent and run concurrently. Memory search needs storage and the
+/// embedding model; the runtime config and spend tracker need all three.
+async fn initialize_agent(
     config: &spacebot::config::Config,
+    agent_config: &spacebot::config::ResolvedAgentConfig,
     llm_manager: &Arc<spacebot::llm::LlmManager>,
     embedding_model: &Arc<spacebot::memory::EmbeddingModel>,
     prompt_engine: &spacebot::prompts::PromptEngine,
-    api_state: &Arc<spacebot::api::ApiState>,
-    agents: &mut HashMap<spacebot::AgentId, spacebot::Agent>,
-    messaging_manager: &mut Arc<spacebot::messaging::MessagingManager>,
-    inbound_stream: &mut Option<
-        std::pin::Pin<Box<dyn futures::Stream<Item = spacebot::InboundMessage> + Send>>,
-    >,
-    cron_schedulers_for_shutdown: &mut Vec<Arc<spacebot::cron::Scheduler>>,
-    ingestion_handles: &mut Vec<tokio::task::JoinHandle<()>>,
-    cortex_handles: &mut Vec<tokio::task::JoinHandle<()>>,
-    watcher_agents: &mut Vec<(
-        String,
-        std::path::PathBuf,
-        Arc<spacebot::config::RuntimeConfig>,
-        Arc<spacebot::mcp::McpManager>,
-    )>,
-    discord_permissions: &mut Option<Arc<ArcSwap<spacebot::config::DiscordPermissions>>>,
-    slack_permissions: &mut Option<Arc<ArcSwap<spacebot::config::SlackPermissions>>>,
-    telegram_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TelegramPermissions>>>,
-    twitch_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TwitchPermissions>>>,
-) -> anyhow::Result<()> {
-    let resolved_agents = config.resolve_agents();
-
-    for agent_config in &resolved_agents {
-        tracing::info!(agent_id = %agent_config.id, "initializing agent");
-
-        // Ensure agent directories exist
-        std::fs::create_dir_all(&agent_config.workspace).with_context(|| {
-            format!(
-                "failed to create workspace: {}",
-                agent_config.workspace.display()
-            )
-        })?;
-        std::fs::create_dir_all(&agent_config.data_dir).with_context(|| {
-            format!(
-                "failed to create data dir: {}",
-                agent_config.data_dir.display()
-            )
-        })?;
-        std::fs::create_dir_all(&agent_config.archives_dir).with_context(|| {
-            format!(
-                "failed to create archives dir: {}",
-                agent_config.archives_dir.display()
-            )
-        })?;
-        std::fs::create_dir_all(agent_config.ingest_dir()).with_context(|| {
-            format!(
-                "failed to create ingest dir: {}",
-                agent_config.ingest_dir().display()
-            )
-        })?;
-        std::fs::create_dir_all(agent_config.logs_dir()).with_context(|| {
-            format!(
-                "failed to create logs dir: {}",
-                agent_config.logs_dir().display()
-            )
-        })?;
+) -> anyhow::Result<(spacebot::Agent, WatchedAgent)> {
+    tracing::info!(agent_id = %agent_config.id, "initializing agent");
+    let started = std::time::Instant::now();
+
+    // Ensure agent directories exist
+    for directory in [
+        agent_config.workspace.clone(),
+        agent_config.data_dir.clone(),
+        agent_config.archives_dir.clone(),
+        agent_config.ingest_dir(),
+        agent_config.logs_dir(),
+    ] {
+        std::fs::create_dir_all(&directory)
+            .with_context(|| format!("failed to create directory: {}", directory.display()))?;
+    }
 
+    let storage = timed_stage(&agent_config.id, "storage", async {
         // Per-agent database connections
-        let db = spacebot::db::Db::connect(&agent_config.data_dir)
+        let db = spacebot::db::Db::connect(&agent_config.data_dir, &agent_config.database)
             .await
             .with_context(|| {
                 format!(
@@ -1246,32 +1937,26 @@ async fn initialize_agents(
             })?,
         );
 
-        // Per-agent memory system
-        let memory_store = spacebot::memory::MemoryStore::with_agent_id(db.sqlite.clone(), &agent_config.id);
         let embedding_table = spacebot::memory::EmbeddingTable::open_or_create(&db.lance)
             .await
-            .with_context(|| {
-                format!("failed to init embeddings for agent '{}'", agent_config.id)
-            })?;
+            .with_context(|| format!("failed to init embeddings for agent '{}'", agent_config.id))?
+            .with_index_config(agent_config.vector_index.clone());
 
         // Ensure FTS index exists for full-text search queries
         if let Err(error) = embedding_table.ensure_fts_index().await {
             tracing::warn!(%error, agent = %agent_config.id, "failed to create FTS index");
         }
 
-        let memory_search = Arc::new(spacebot::memory::MemorySearch::new(
-            memory_store,
-            embedding_table,
-            embedding_model.clone(),
-        ));
-
-        // Per-agent event bus (broadcast for fan-out to multiple channels)
-        let (event_tx, _event_rx) = tokio::sync::broadcast::channel(256);
+        anyhow::Ok((db, settings_store, embedding_table))
+    });
 
-        let agent_id: spacebot::AgentId = Arc::from(agent_config.id.as_str());
+    let mcp = timed_stage(&agent_config.id, "mcp", async {
         let mcp_manager = Arc::new(spacebot::mcp::McpManager::new(agent_config.mcp.clone()));
         mcp_manager.connect_all().await;
+        anyhow::Ok(mcp_manager)
+    });
 
+    let identity = timed_stage(&agent_config.id, "identity", async {
         // Scaffold identity templates if missing, then load
         spacebot::identity::scaffold_identity_files(&agent_config.workspace)
             .await
@@ -1288,53 +1973,176 @@ async fn initialize_agents(
             spacebot::skills::SkillSet::load(&config.skills_dir(), &agent_config.skills_dir())
                 .await;
 
-        // Build the RuntimeConfig with all hot-reloadable values
-        let runtime_config = Arc::new(spacebot::config::RuntimeConfig::new(
-            &config.instance_dir,
-            agent_config,
-            &config.defaults,
-            prompt_engine.clone(),
-            identity,
-            skills,
-        ));
+        anyhow::Ok((identity, skills))
+    });
+
+    let ((db, settings_store, embedding_table), mcp_manager, (identity, skills)) =
+        tokio::try_join!(storage, mcp, identity)?;
 
-        // Set the settings store in RuntimeConfig and apply config-driven defaults
-        runtime_config.set_settings(settings_store.clone());
-        if let Err(error) = settings_store.set_worker_log_mode(config.defaults.worker_log_mode) {
-            tracing::warn!(%error, agent = %agent_config.id, "failed to set worker_log_mode from config");
+    // Building the vector index for a large store takes a while, so
+    // don't hold up startup for it.
+    let index_table = embedding_table.clone();
+    let index_agent_id = agent_config.id.clone();
+    tokio::spawn(async move {
+        if let Err(error) = index_table.ensure_vector_index().await {
+            tracing::warn!(%error, agent = %index_agent_id, "failed to create vector index");
         }
+    });
 
-        watcher_agents.push((
-            agent_config.id.clone(),
-            agent_config.workspace.clone(),
-            runtime_config.clone(),
-            mcp_manager.clone(),
-        ));
+    // Per-agent memory system
+    let memory_store =
+        spacebot::memory::MemoryStore::with_agent_id(db.sqlite.clone(), &agent_config.id);
+    let memory_search = Arc::new(spacebot::memory::MemorySearch::new(
+        memory_store,
+        embedding_table,
+        embedding_model.clone(),
+    ));
+    let history_archive = Arc::new(
+        spacebot::conversation::HistoryArchive::open(
+            &db,
+            agent_config.archives_dir.clone(),
+            agent_config.vector_index.clone(),
+            embedding_model.clone(),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "failed to init the history archive for agent '{}'",
+                agent_config.id
+            )
+        })?,
+    );
 
-        let deps = spacebot::AgentDeps {
-            agent_id: agent_id.clone(),
-            memory_search,
-            llm_manager: llm_manager.clone(),
-            mcp_manager,
-            cron_tool: None,
-            runtime_config,
-            event_tx,
-            sqlite_pool: db.sqlite.clone(),
-            messaging_manager: None,
-        };
+    // Per-agent event bus (broadcast for fan-out to multiple channels)
+    let (event_tx, _event_rx) = tokio::sync::broadcast::channel(256);
+
+    let agent_id: spacebot::AgentId = Arc::from(agent_config.id.as_str());
+
+    // Build the RuntimeConfig with all hot-reloadable values
+    let runtime_config = Arc::new(spacebot::config::RuntimeConfig::new(
+        &config.instance_dir,
+        agent_config,
+        &config.defaults,
+        prompt_engine.clone(),
+        identity,
+        skills,
+    ));
+
+    // Set the settings store in RuntimeConfig and apply config-driven defaults
+    runtime_config.set_settings(settings_store.clone());
+    runtime_config.set_rbac(config.rbac.clone());
+    if let Err(error) = settings_store.set_worker_log_mode(config.defaults.worker_log_mode) {
+        tracing::warn!(%error, agent = %agent_config.id, "failed to set worker_log_mode from config");
+    }
 
-        let agent = spacebot::Agent {
-            id: agent_id.clone(),
-            config: agent_config.clone(),
-            db,
-            deps,
-        };
+    let spend_tracker = spacebot::llm::spend::SpendTracker::new(
+        agent_id.clone(),
+        db.sqlite.clone(),
+        runtime_config.clone(),
+        llm_manager.spend_event_sender(),
+    )
+    .await;
+    llm_manager
+        .register_spend_tracker(&agent_config.id, Arc::new(spend_tracker))
+        .await;
+
+    let watched = (
+        agent_config.id.clone(),
+        agent_config.workspace.clone(),
+        runtime_config.clone(),
+        mcp_manager.clone(),
+    );
+
+    let deps = spacebot::AgentDeps {
+        agent_id: agent_id.clone(),
+        memory_search,
+        history_archive,
+        llm_manager: llm_manager.clone(),
+        mcp_manager,
+        cron_tool: None,
+        runtime_config,
+        event_tx,
+        sqlite_pool: db.sqlite.clone(),
+        messaging_manager: None,
+        confirmations: Default::default(),
+        turn_hooks: Default::default(),
+    };
+
+    let agent = spacebot::Agent {
+        id: agent_id,
+        config: agent_config.clone(),
+        db,
+        deps,
+    };
+
+    tracing::info!(
+        agent_id = %agent_config.id,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "agent initialized"
+    );
+    Ok((agent, watched))
+}
+
+/// Initialize agents, messaging adapters, cron, cortex, and ingestion.
+/// Extracted so it can be called either at startup or after providers are configured.
+#[allow(clippy::too_many_arguments)]
+async fn initialize_agents(
+    config: &spacebot::config::Config,
+    llm_manager: &Arc<spacebot::llm::LlmManager>,
+    embedding_model: &Arc<spacebot::memory::EmbeddingModel>,
+    prompt_engine: &spacebot::prompts::PromptEngine,
+    api_state: &Arc<spacebot::api::ApiState>,
+    agents: &mut HashMap<spacebot::AgentId, spacebot::Agent>,
+    messaging_manager: &mut Arc<spacebot::messaging::MessagingManager>,
+    inbound_stream: &mut Option<
+        std::pin::Pin<Box<dyn futures::Stream<Item = spacebot::InboundMessage> + Send>>,
+    >,
+    cron_schedulers_for_shutdown: &mut Vec<Arc<spacebot::cron::Scheduler>>,
+    ingestion_handles: &mut Vec<tokio::task::JoinHandle<()>>,
+    cortex_handles: &mut Vec<tokio::task::JoinHandle<()>>,
+    watcher_agents: &mut Vec<WatchedAgent>,
+    discord_permissions: &mut Option<Arc<ArcSwap<spacebot::config::DiscordPermissions>>>,
+    slack_permissions: &mut Option<Arc<ArcSwap<spacebot::config::SlackPermissions>>>,
+    telegram_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TelegramPermissions>>>,
+    twitch_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TwitchPermissions>>>,
+    irc_permissions: &mut Option<Arc<ArcSwap<spacebot::config::IrcPermissions>>>,
+    email_permissions: &mut Option<Arc<ArcSwap<spacebot::config::EmailPermissions>>>,
+    xmpp_permissions: &mut Option<Arc<ArcSwap<spacebot::config::XmppPermissions>>>,
+    whatsapp_permissions: &mut Option<Arc<ArcSwap<spacebot::config::WhatsAppPermissions>>>,
+) -> anyhow::Result<()> {
+    let resolved_agents = config.resolve_agents();
+    let object_storage =
+        spacebot::object_storage::ObjectStorage::from_config(&config.object_storage)?;
+
+    // Agents don't depend on each other, so they initialize concurrently.
+    // Within an agent, storage, MCP servers and identity also start side by
+    // side; see `initialize_agent` for the ordering.
+    let started = std::time::Instant::now();
+    let initialized = futures::future::try_join_all(resolved_agents.iter().map(|agent_config| {
+        initialize_agent(
+            config,
+            agent_config,
+            llm_manager,
+            embedding_model,
+            prompt_engine,
+        )
+    }))
+    .await?;
 
-        tracing::info!(agent_id = %agent_config.id, "agent initialized");
-        agents.insert(agent_id, agent);
+    for (agent, watcher) in initialized {
+        agent
+            .deps
+            .runtime_config
+            .set_object_storage(object_storage.clone());
+        watcher_agents.push(watcher);
+        agents.insert(agent.id.clone(), agent);
     }
 
-    tracing::info!(agent_count = agents.len(), "all agents initialized");
+    tracing::info!(
+        agent_count = agents.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "all agents initialized"
+    );
 
     // Wire agent event streams, DB pools, and config summaries into the API server
     {
@@ -1344,6 +2152,7 @@ async fn initialize_agents(
         let mut mcp_managers = std::collections::HashMap::new();
         let mut agent_workspaces = std::collections::HashMap::new();
         let mut runtime_configs = std::collections::HashMap::new();
+        let mut disk_quotas = std::collections::HashMap::new();
         for (agent_id, agent) in agents.iter() {
             let event_rx = agent.deps.event_tx.subscribe();
             api_state.register_agent_events(agent_id.to_string(), event_rx);
@@ -1352,6 +2161,14 @@ async fn initialize_agents(
             mcp_managers.insert(agent_id.to_string(), agent.deps.mcp_manager.clone());
             agent_workspaces.insert(agent_id.to_string(), agent.config.workspace.clone());
             runtime_configs.insert(agent_id.to_string(), agent.deps.runtime_config.clone());
+            let disk_quota = Arc::new(spacebot::quota::DiskQuota::new(
+                agent_id.to_string(),
+                agent.config.workspace.clone(),
+                agent.config.screenshot_dir(),
+                agent.deps.runtime_config.clone(),
+            ));
+            spacebot::quota::spawn_quota_loop(disk_quota.clone());
+            disk_quotas.insert(agent_id.to_string(), disk_quota);
             agent_configs.push(spacebot::api::AgentInfo {
                 id: agent.config.id.clone(),
                 workspace: agent.config.workspace.clone(),
@@ -1367,6 +2184,7 @@ async fn initialize_agents(
         api_state.set_mcp_managers(mcp_managers);
         api_state.set_runtime_configs(runtime_configs);
         api_state.set_agent_workspaces(agent_workspaces);
+        api_state.set_disk_quotas(disk_quotas);
         api_state.set_instance_dir(config.instance_dir.clone());
     }
 
@@ -1391,7 +2209,8 @@ async fn initialize_agents(
             discord_permissions.clone().ok_or_else(|| {
                 anyhow::anyhow!("discord permissions not initialized when discord is enabled")
             })?,
-        );
+        )
+        .with_pairing(api_state.pairing_store.load().as_ref().clone());
         new_messaging_manager.register(adapter).await;
     }
 
@@ -1439,7 +2258,8 @@ async fn initialize_agents(
             telegram_permissions.clone().ok_or_else(|| {
                 anyhow::anyhow!("telegram permissions not initialized when telegram is enabled")
             })?,
-        );
+        )
+        .with_pairing(api_state.pairing_store.load().as_ref().clone());
         new_messaging_manager.register(adapter).await;
     }
 
@@ -1450,6 +2270,16 @@ async fn initialize_agents(
             webhook_config.port,
             &webhook_config.bind,
             webhook_config.auth_token.clone(),
+        )
+        .with_signing(
+            webhook_config.signing_secret.clone(),
+            webhook_config.sources.clone(),
+        )
+        .with_sessions(api_state.session_store.load().as_ref().clone())
+        .with_deliveries(api_state.webhook_deliveries.load().as_ref().clone())
+        .with_callback(
+            webhook_config.callback_url.clone(),
+            webhook_config.max_delivery_attempts,
         );
         new_messaging_manager.register(adapter).await;
     }
@@ -1481,6 +2311,94 @@ async fn initialize_agents(
         new_messaging_manager.register(adapter).await;
     }
 
+    // Shared IRC permissions (hot-reloadable via file watcher)
+    *irc_permissions = config.messaging.irc.as_ref().map(|irc_config| {
+        let perms = spacebot::config::IrcPermissions::from_config(irc_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(irc_config) = &config.messaging.irc
+        && irc_config.enabled
+    {
+        let adapter = spacebot::messaging::irc::IrcAdapter::new(
+            irc_config,
+            irc_permissions.clone().ok_or_else(|| {
+                anyhow::anyhow!("irc permissions not initialized when irc is enabled")
+            })?,
+        );
+        new_messaging_manager.register(adapter).await;
+    }
+
+    // Shared email permissions (hot-reloadable via file watcher)
+    *email_permissions = config.messaging.email.as_ref().map(|email_config| {
+        let perms = spacebot::config::EmailPermissions::from_config(email_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(email_config) = &config.messaging.email
+        && email_config.enabled
+    {
+        let adapter = spacebot::messaging::email::EmailAdapter::new(
+            email_config,
+            email_permissions.clone().ok_or_else(|| {
+                anyhow::anyhow!("email permissions not initialized when email is enabled")
+            })?,
+        )?;
+        new_messaging_manager.register(adapter).await;
+    }
+
+    // Shared XMPP permissions (hot-reloadable via file watcher)
+    *xmpp_permissions = config.messaging.xmpp.as_ref().map(|xmpp_config| {
+        let perms = spacebot::config::XmppPermissions::from_config(xmpp_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(xmpp_config) = &config.messaging.xmpp
+        && xmpp_config.enabled
+    {
+        let adapter = spacebot::messaging::xmpp::XmppAdapter::new(
+            xmpp_config,
+            xmpp_permissions.clone().ok_or_else(|| {
+                anyhow::anyhow!("xmpp permissions not initialized when xmpp is enabled")
+            })?,
+        );
+        new_messaging_manager.register(adapter).await;
+    }
+
+    // Shared WhatsApp permissions (hot-reloadable via file watcher)
+    *whatsapp_permissions = config.messaging.whatsapp.as_ref().map(|whatsapp_config| {
+        let perms =
+            spacebot::config::WhatsAppPermissions::from_config(whatsapp_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(whatsapp_config) = &config.messaging.whatsapp
+        && whatsapp_config.enabled
+    {
+        let adapter = spacebot::messaging::whatsapp::WhatsAppAdapter::new(
+            whatsapp_config,
+            whatsapp_permissions.clone().ok_or_else(|| {
+                anyhow::anyhow!("whatsapp permissions not initialized when whatsapp is enabled")
+            })?,
+        );
+        new_messaging_manager.register(adapter).await;
+    }
+
+    for custom in &config.messaging.custom {
+        if !custom.enabled {
+            continue;
+        }
+        match spacebot::messaging::registry::build(custom) {
+            Some(Ok(adapter)) => new_messaging_manager.register_dyn(adapter).await,
+            Some(Err(error)) => {
+                tracing::error!(adapter = %custom.name, %error, "failed to build custom adapter");
+            }
+            None => {
+                tracing::warn!(adapter = %custom.name, "no adapter registered under this name");
+            }
+        }
+    }
+
     let webchat_adapter = Arc::new(spacebot::messaging::webchat::WebChatAdapter::new());
     new_messaging_manager
         .register_shared(webchat_adapter.clone())
@@ -1501,6 +2419,38 @@ async fn initialize_agents(
 
     tracing::info!("messaging adapters started");
 
+    let alert_dispatcher = Arc::new(spacebot::alerts::AlertDispatcher::new(
+        config.alerts.clone(),
+        messaging_manager.clone(),
+    ));
+    spacebot::alerts::spawn_monitor(alert_dispatcher.clone(), llm_manager.clone());
+    for agent in agents.values() {
+        spacebot::alerts::watch_agent_events(
+            alert_dispatcher.clone(),
+            agent.deps.agent_id.clone(),
+            agent.deps.event_tx.subscribe(),
+        );
+    }
+    api_state.set_alert_dispatcher(alert_dispatcher);
+
+    let event_webhook_dispatcher = Arc::new(spacebot::event_webhooks::EventWebhookDispatcher::new(
+        config.event_webhooks.clone(),
+    ));
+    if let Some(pairing_store) = api_state.pairing_store.load().as_ref() {
+        spacebot::messaging::pairing::spawn_notifier(
+            pairing_store.clone(),
+            messaging_manager.clone(),
+        );
+    }
+
+    for agent in agents.values() {
+        spacebot::event_webhooks::watch_agent_events(
+            event_webhook_dispatcher.clone(),
+            agent.deps.agent_id.clone(),
+            agent.deps.event_tx.subscribe(),
+        );
+    }
+
     // Initialize cron schedulers for each agent
     let mut cron_stores_map = std::collections::HashMap::new();
     let mut cron_schedulers_map = std::collections::HashMap::new();
@@ -1547,6 +2497,9 @@ async fn initialize_agents(
             .deps
             .runtime_config
             .set_cron(store.clone(), scheduler.clone());
+        if let Some(bindings) = api_state.bindings.read().await.clone() {
+            agent.deps.runtime_config.set_bindings(bindings);
+        }
 
         match store.load_all().await {
             Ok(configs) => {
@@ -1576,6 +2529,22 @@ async fn initialize_agents(
     api_state.set_cron_schedulers(cron_schedulers_map);
     tracing::info!("cron stores and schedulers registered with API state");
 
+    // Start reminder loops for each agent
+    for (agent_id, agent) in agents.iter() {
+        cortex_handles.push(spacebot::reminders::spawn_reminder_loop(agent.deps.clone()));
+        tracing::info!(agent_id = %agent_id, "reminder loop started");
+    }
+
+    // Resume workers that were running when Spacebot stopped
+    for agent in agents.values() {
+        spacebot::agent::checkpoint::resume_workers(
+            &agent.deps,
+            agent.config.screenshot_dir(),
+            agent.config.logs_dir(),
+        )
+        .await;
+    }
+
     // Start memory ingestion loops for each agent
     for (agent_id, agent) in agents.iter() {
         let ingestion_config = **agent.deps.runtime_config.ingestion.load();
@@ -1597,10 +2566,66 @@ async fn initialize_agents(
         cortex_handles.push(bulletin_handle);
         tracing::info!(agent_id = %agent_id, "cortex bulletin loop started");
 
-        let association_handle =
-            spacebot::agent::cortex::spawn_association_loop(agent.deps.clone(), cortex_logger);
+        let association_handle = spacebot::agent::cortex::spawn_association_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        );
         cortex_handles.push(association_handle);
         tracing::info!(agent_id = %agent_id, "cortex association loop started");
+
+        cortex_handles.push(spacebot::agent::cortex::spawn_consolidation_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        ));
+
+        cortex_handles.push(spacebot::agent::health::spawn_health_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        ));
+
+        cortex_handles.push(spacebot::agent::reflection::spawn_reflection_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        ));
+
+        cortex_handles.push(spacebot::agent::dream::spawn_dream_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        ));
+
+        cortex_handles.push(spacebot::agent::schedule::spawn_task_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        ));
+
+        cortex_handles.push(spacebot::agent::trigger::spawn_trigger_loop(
+            agent.deps.clone(),
+            cortex_logger.clone(),
+        ));
+
+        cortex_handles.push(spacebot::agent::task_queue::spawn_task_queue_loop(
+            agent.deps.clone(),
+            cortex_logger,
+            agent.config.screenshot_dir(),
+            agent.config.logs_dir(),
+        ));
+
+        cortex_handles.push(spacebot::agent::cortex::spawn_presence_loop(
+            agent.deps.clone(),
+        ));
+
+        cortex_handles
+            .push(spacebot::agent::circuit_breaker::spawn_circuit_breaker_loop(agent.deps.clone()));
+
+        cortex_handles.push(spacebot::audit::spawn_event_audit_loop(
+            agent.deps.agent_id.clone(),
+            agent.deps.event_tx.subscribe(),
+            spacebot::audit::AuditLogger::new(agent.db.sqlite.clone()),
+        ));
+
+        cortex_handles.push(spacebot::scripting::spawn_script_loop(
+            spacebot::scripting::ScriptContext::new(agent),
+        ));
     }
 
     // Create cortex chat sessions for each agent
@@ -1614,6 +2639,7 @@ async fn initialize_agents(
             let channel_store = spacebot::conversation::ChannelStore::new(agent.db.sqlite.clone());
             let tool_server = spacebot::tools::create_cortex_chat_tool_server(
                 agent.deps.memory_search.clone(),
+                agent.deps.history_archive.clone(),
                 conversation_logger,
                 channel_store,
                 browser_config,
@@ -1621,6 +2647,12 @@ async fn initialize_agents(
                 brave_search_key,
                 agent.deps.runtime_config.workspace_dir.clone(),
                 agent.deps.runtime_config.instance_dir.clone(),
+                spacebot::audit::AuditLogger::new(agent.db.sqlite.clone())
+                    .with_actor("cortex_chat"),
+                spacebot::egress::EgressPolicy::new(&agent.deps.runtime_config.egress.load()),
+                agent_id,
+                object_storage.clone(),
+                spacebot::tools::SendProactiveMessageTool::new(agent.deps.clone()),
             );
             let store = spacebot::agent::cortex_chat::CortexChatStore::new(agent.db.sqlite.clone());
             let session = spacebot::agent::cortex_chat::CortexChatSession::new(
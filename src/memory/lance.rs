@@ -1,11 +1,13 @@
 //! LanceDB table management and embedding storage with HNSW vector index and FTS.
 
+use crate::config::{VectorIndexConfig, VectorIndexType};
 use crate::error::{DbError, Result};
 use arrow_array::cast::AsArray;
 use arrow_array::types::Float32Type;
 use arrow_array::{Array, RecordBatchIterator};
 use futures::TryStreamExt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Schema constants for the embeddings table.
 const TABLE_NAME: &str = "memory_embeddings";
@@ -14,12 +16,17 @@ const EMBEDDING_DIM: i32 = 384; // all-MiniLM-L6-v2 dimension
 /// LanceDB table for memory embeddings with HNSW index and FTS.
 pub struct EmbeddingTable {
     table: lancedb::Table,
+    index_config: VectorIndexConfig,
+    /// Set once the vector index is known to exist, shared between clones.
+    vector_indexed: Arc<AtomicBool>,
 }
 
 impl Clone for EmbeddingTable {
     fn clone(&self) -> Self {
         Self {
             table: self.table.clone(),
+            index_config: self.index_config.clone(),
+            vector_indexed: self.vector_indexed.clone(),
         }
     }
 }
@@ -30,17 +37,26 @@ impl EmbeddingTable {
     /// If the table exists but is corrupted (e.g. process killed mid-write),
     /// it is dropped and recreated. Embeddings can be regenerated from SQLite.
     pub async fn open_or_create(connection: &lancedb::Connection) -> Result<Self> {
+        Self::open_or_create_named(connection, TABLE_NAME).await
+    }
+
+    /// Open or create an embeddings table other than the memory one, with the
+    /// same schema and recovery.
+    pub async fn open_or_create_named(
+        connection: &lancedb::Connection,
+        table_name: &str,
+    ) -> Result<Self> {
         // Try to open existing table
-        match connection.open_table(TABLE_NAME).execute().await {
-            Ok(table) => return Ok(Self { table }),
+        match connection.open_table(table_name).execute().await {
+            Ok(table) => return Ok(Self::from_table(table)),
             Err(error) => {
                 tracing::debug!(%error, "failed to open embeddings table, will create");
             }
         }
 
         // Table doesn't exist or is unreadable — try creating it
-        match Self::create_empty_table(connection).await {
-            Ok(table) => return Ok(Self { table }),
+        match Self::create_empty_table(connection, table_name).await {
+            Ok(table) => return Ok(Self::from_table(table)),
             Err(error) => {
                 tracing::warn!(
                     %error,
@@ -51,23 +67,40 @@ impl EmbeddingTable {
 
         // Both open and create failed — table data exists but is corrupted.
         // Drop it and recreate from scratch.
-        if let Err(error) = connection.drop_table(TABLE_NAME, &[]).await {
+        if let Err(error) = connection.drop_table(table_name, &[]).await {
             tracing::warn!(%error, "drop_table failed during recovery, proceeding anyway");
         }
 
-        let table = Self::create_empty_table(connection).await?;
+        let table = Self::create_empty_table(connection, table_name).await?;
         tracing::info!("embeddings table recovered — embeddings will be rebuilt from memory store");
 
-        Ok(Self { table })
+        Ok(Self::from_table(table))
+    }
+
+    fn from_table(table: lancedb::Table) -> Self {
+        Self {
+            table,
+            index_config: VectorIndexConfig::default(),
+            vector_indexed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Use the given vector index and search settings.
+    pub fn with_index_config(mut self, index_config: VectorIndexConfig) -> Self {
+        self.index_config = index_config;
+        self
     }
 
     /// Create an empty embeddings table.
-    async fn create_empty_table(connection: &lancedb::Connection) -> Result<lancedb::Table> {
+    async fn create_empty_table(
+        connection: &lancedb::Connection,
+        table_name: &str,
+    ) -> Result<lancedb::Table> {
         let schema = Self::schema();
         let batches = RecordBatchIterator::new(vec![].into_iter().map(Ok), Arc::new(schema));
 
         connection
-            .create_table(TABLE_NAME, Box::new(batches))
+            .create_table(table_name, Box::new(batches))
             .execute()
             .await
             .map_err(|e| DbError::LanceDb(e.to_string()).into())
@@ -194,13 +227,20 @@ impl EmbeddingTable {
 
         use lancedb::query::{ExecutableQuery, QueryBase};
 
-        // Use query() API with nearest_to for vector search
-        let results: Vec<arrow_array::RecordBatch> = self
+        // Use query() API with nearest_to for vector search. nprobes and
+        // refine_factor only apply once the vector index exists; before that
+        // LanceDB does an exact flat scan.
+        let mut query = self
             .table
             .query()
             .nearest_to(query_embedding)
             .map_err(|e| DbError::LanceDb(e.to_string()))?
-            .limit(limit)
+            .nprobes(self.index_config.nprobes)
+            .limit(limit);
+        if let Some(refine_factor) = self.index_config.refine_factor {
+            query = query.refine_factor(refine_factor);
+        }
+        let results: Vec<arrow_array::RecordBatch> = query
             .execute()
             .await
             .map_err(|e| DbError::LanceDb(e.to_string()))?
@@ -337,21 +377,104 @@ impl EmbeddingTable {
         Ok(matches)
     }
 
-    /// Create HNSW vector index and FTS index for better performance.
+    /// Create the vector index and FTS index for better performance.
     /// Should be called after enough data accumulates.
     pub async fn create_indexes(&self) -> Result<()> {
-        // Create HNSW vector index on embedding column
-        self.table
-            .create_index(&["embedding"], lancedb::index::Index::Auto)
-            .execute()
-            .await
-            .map_err(|e| DbError::LanceDb(format!("Failed to create vector index: {}", e)))?;
-
+        self.create_vector_index().await?;
         self.ensure_fts_index().await?;
 
         Ok(())
     }
 
+    /// Build the vector index once the table reaches the configured row
+    /// count. Returns whether an index was built by this call.
+    ///
+    /// Cheap to call after every write: once the index exists this returns
+    /// without touching the table. Rows added after the index was built are
+    /// still searched, with a flat scan over the unindexed part.
+    pub async fn ensure_vector_index(&self) -> Result<bool> {
+        if self.vector_indexed.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        let indices = self
+            .table
+            .list_indices()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?;
+        if indices
+            .iter()
+            .any(|index| index.columns.iter().any(|column| column == "embedding"))
+        {
+            self.vector_indexed.store(true, Ordering::Relaxed);
+            return Ok(false);
+        }
+
+        let rows = self
+            .table
+            .count_rows(None)
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?;
+        if rows < self.index_config.auto_index_threshold {
+            return Ok(false);
+        }
+
+        // Another clone may be building it concurrently.
+        if self.vector_indexed.swap(true, Ordering::Relaxed) {
+            return Ok(false);
+        }
+        tracing::info!(
+            rows,
+            index_type = ?self.index_config.index_type,
+            "building vector index for memory embeddings"
+        );
+        if let Err(error) = self.create_vector_index().await {
+            self.vector_indexed.store(false, Ordering::Relaxed);
+            return Err(error);
+        }
+        Ok(true)
+    }
+
+    /// Create the configured ANN index on the embedding column.
+    async fn create_vector_index(&self) -> Result<()> {
+        use lancedb::index::Index;
+        use lancedb::index::vector::{
+            IvfHnswPqIndexBuilder, IvfHnswSqIndexBuilder, IvfPqIndexBuilder,
+        };
+
+        let num_partitions = self.index_config.num_partitions;
+        let index = match self.index_config.index_type {
+            VectorIndexType::IvfPq => {
+                let mut builder = IvfPqIndexBuilder::default();
+                if let Some(partitions) = num_partitions {
+                    builder = builder.num_partitions(partitions);
+                }
+                Index::IvfPq(builder)
+            }
+            VectorIndexType::IvfHnswSq => {
+                let mut builder = IvfHnswSqIndexBuilder::default();
+                if let Some(partitions) = num_partitions {
+                    builder = builder.num_partitions(partitions);
+                }
+                Index::IvfHnswSq(builder)
+            }
+            VectorIndexType::IvfHnswPq => {
+                let mut builder = IvfHnswPqIndexBuilder::default();
+                if let Some(partitions) = num_partitions {
+                    builder = builder.num_partitions(partitions);
+                }
+                Index::IvfHnswPq(builder)
+            }
+        };
+
+        self.table
+            .create_index(&["embedding"], index)
+            .replace(true)
+            .execute()
+            .await
+            .map_err(|e| DbError::LanceDb(format!("Failed to create vector index: {}", e)).into())
+    }
+
     /// Ensure the FTS index exists on the content column.
     ///
     /// LanceDB requires an inverted index for `full_text_search()` queries.
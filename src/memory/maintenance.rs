@@ -1,145 +1,312 @@
-//! Memory maintenance: decay, prune, merge, reindex.
+//! Memory maintenance: merge near-duplicates, promote repeated facts, archive
+//! stale memories.
+//!
+//! Run by the cortex's consolidation loop while the agent is idle. Nothing is
+//! deleted: merged and stale memories are marked forgotten, which keeps them
+//! in the database but out of search and recall. Identity and pinned memories
+//! are never archived or merged away.
 
 use crate::error::Result;
-use crate::memory::MemoryStore;
-use crate::memory::types::MemoryType;
+use crate::memory::types::Memory;
+use crate::memory::{MemorySearch, MemoryStore};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sqlx::Row as _;
+
+use std::collections::HashSet;
+
+/// Memories checked for near-duplicates per pass, most important first.
+const MERGE_CANDIDATES: i64 = 500;
+
+/// Near-duplicates looked up per memory.
+const MERGE_NEIGHBORS: usize = 10;
+
+/// Importance a memory gains for each restatement merged into it.
+const PROMOTE_STEP: f32 = 0.1;
 
 /// Maintenance configuration.
 #[derive(Debug, Clone)]
 pub struct MaintenanceConfig {
-    /// Importance below which memories are considered for pruning.
-    pub prune_threshold: f32,
-    /// Decay rate per day (0.0 - 1.0).
-    pub decay_rate: f32,
-    /// Minimum age in days before a memory can be pruned.
-    pub min_age_days: i64,
-    /// Similarity threshold for merging memories (0.0 - 1.0).
+    /// Embedding similarity at which two memories of the same type are
+    /// merged (0.0 - 1.0).
     pub merge_similarity_threshold: f32,
+    /// Importance below which unused memories are archived.
+    pub archive_threshold: f32,
+    /// Days without being recalled before a memory counts as stale.
+    pub stale_days: i64,
 }
 
 impl Default for MaintenanceConfig {
     fn default() -> Self {
         Self {
-            prune_threshold: 0.1,
-            decay_rate: 0.05,
-            min_age_days: 30,
             merge_similarity_threshold: 0.95,
+            archive_threshold: 0.5,
+            stale_days: 90,
         }
     }
 }
 
 /// Run maintenance tasks on the memory store.
 pub async fn run_maintenance(
-    memory_store: &MemoryStore,
+    memory_search: &MemorySearch,
     config: &MaintenanceConfig,
 ) -> Result<MaintenanceReport> {
-    let mut report = MaintenanceReport::default();
-
-    // Apply decay to all non-identity memories
-    // Fields are assigned sequentially because the values are async — can't use struct literal.
-    #[allow(clippy::field_reassign_with_default)]
-    {
-        report.decayed = apply_decay(memory_store, config.decay_rate).await?;
-        report.pruned = prune_memories(memory_store, config).await?;
-        report.merged =
-            merge_similar_memories(memory_store, config.merge_similarity_threshold).await?;
-    }
+    let (merged, promoted) =
+        merge_similar_memories(memory_search, config.merge_similarity_threshold).await?;
+    let archived = archive_stale_memories(memory_search.store(), config).await?;
 
-    Ok(report)
+    Ok(MaintenanceReport {
+        merged,
+        promoted,
+        archived,
+    })
 }
 
-/// Apply importance decay based on recency and access patterns.
-async fn apply_decay(memory_store: &MemoryStore, decay_rate: f32) -> Result<usize> {
-    // Get all non-identity memories
-    let all_types: Vec<_> = MemoryType::ALL
-        .iter()
-        .copied()
-        .filter(|t| *t != MemoryType::Identity)
-        .collect();
-
-    let mut decayed_count = 0;
-
-    for mem_type in all_types {
-        let memories = memory_store.get_by_type(mem_type, 1000).await?;
-
-        for mut memory in memories {
-            let now = chrono::Utc::now();
-            let days_old = (now - memory.updated_at).num_days();
-            let days_since_access = (now - memory.last_accessed_at).num_days();
-
-            // Calculate decay multiplier
-            let age_decay = 1.0 - (days_old as f32 * decay_rate).min(0.5);
-            let access_boost = if days_since_access < 7 {
-                1.1 // Recent access boosts importance
-            } else if days_since_access > 30 {
-                0.9 // Long time since access reduces importance
-            } else {
-                1.0
-            };
+/// Merge near-duplicate memories into the more important one, which is
+/// promoted for each restatement it absorbs. Returns how many memories were
+/// merged away and how many were promoted.
+async fn merge_similar_memories(
+    memory_search: &MemorySearch,
+    similarity_threshold: f32,
+) -> Result<(usize, usize)> {
+    let store = memory_search.store();
+    let candidates = sqlx::query(
+        r#"
+        SELECT id FROM memories
+        WHERE forgotten = 0
+        AND memory_type != 'identity'
+        ORDER BY importance DESC, created_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(MERGE_CANDIDATES)
+    .fetch_all(store.pool())
+    .await
+    .context("failed to load merge candidates")?;
 
-            let new_importance = memory.importance * age_decay * access_boost;
+    let mut merged_away = HashSet::new();
+    let mut merged = 0;
+    let mut promoted = 0;
 
-            if (new_importance - memory.importance).abs() > 0.01 {
-                memory.importance = new_importance.clamp(0.0, 1.0);
-                memory.updated_at = now;
-                memory_store.update(&memory).await?;
-                decayed_count += 1;
+    for row in candidates {
+        let id: String = row.try_get("id")?;
+        if merged_away.contains(&id) {
+            continue;
+        }
+
+        let similar = match memory_search
+            .embedding_table()
+            .find_similar(&id, similarity_threshold, MERGE_NEIGHBORS)
+            .await
+        {
+            Ok(similar) => similar,
+            Err(error) => {
+                tracing::debug!(memory_id = %id, %error, "similarity search failed for memory");
+                continue;
+            }
+        };
+        if similar.is_empty() {
+            continue;
+        }
+
+        let Some(mut keeper) = store.load(&id).await? else {
+            continue;
+        };
+        let mut absorbed = 0;
+        for (other_id, _) in similar {
+            if merged_away.contains(&other_id) {
+                continue;
+            }
+            let Some(other) = store.load(&other_id).await? else {
+                continue;
+            };
+            if other.forgotten || other.pinned || other.memory_type != keeper.memory_type {
+                continue;
             }
+            merge_into(store, &mut keeper, &other).await?;
+            merged_away.insert(other_id);
+            absorbed += 1;
         }
+        if absorbed == 0 {
+            continue;
+        }
+
+        merged += absorbed;
+        let importance = (keeper.importance + PROMOTE_STEP * absorbed as f32).min(1.0);
+        if importance > keeper.importance {
+            keeper.importance = importance;
+            promoted += 1;
+        }
+        keeper.updated_at = chrono::Utc::now();
+        store.update(&keeper).await?;
+    }
+
+    Ok((merged, promoted))
+}
+
+/// Fold `other` into `keeper`: carry over its accesses and associations, then
+/// forget it. The caller saves `keeper`.
+async fn merge_into(store: &MemoryStore, keeper: &mut Memory, other: &Memory) -> Result<()> {
+    keeper.access_count += other.access_count;
+    keeper.last_accessed_at = keeper.last_accessed_at.max(other.last_accessed_at);
+
+    // Edges the keeper already has are left as they are.
+    for column in ["source_id", "target_id"] {
+        sqlx::query(&format!(
+            "UPDATE OR IGNORE associations SET {column} = ? WHERE {column} = ?"
+        ))
+        .bind(&keeper.id)
+        .bind(&other.id)
+        .execute(store.pool())
+        .await
+        .context("failed to move associations")?;
     }
+    sqlx::query(
+        "DELETE FROM associations WHERE source_id = ? OR target_id = ? OR source_id = target_id",
+    )
+    .bind(&other.id)
+    .bind(&other.id)
+    .execute(store.pool())
+    .await
+    .context("failed to delete merged associations")?;
 
-    Ok(decayed_count)
+    store.forget(&other.id).await?;
+    tracing::debug!(keeper = %keeper.id, merged = %other.id, "merged near-duplicate memory");
+    Ok(())
 }
 
-/// Prune memories that have fallen below the importance threshold.
-async fn prune_memories(memory_store: &MemoryStore, config: &MaintenanceConfig) -> Result<usize> {
+/// Archive memories that are unimportant and haven't been recalled in
+/// `stale_days`. Returns how many were archived.
+async fn archive_stale_memories(store: &MemoryStore, config: &MaintenanceConfig) -> Result<usize> {
     let now = chrono::Utc::now();
-    let min_age = chrono::Duration::days(config.min_age_days);
-    let cutoff_date = now - min_age;
+    let cutoff_date = now - chrono::Duration::days(config.stale_days);
 
-    // Get all memories below threshold that are old enough
-    let candidates = sqlx::query(
+    let result = sqlx::query(
         r#"
-        SELECT id FROM memories
-        WHERE importance < ? 
+        UPDATE memories SET forgotten = 1, updated_at = ?
+        WHERE forgotten = 0
         AND memory_type != 'identity'
+        AND pinned = 0
+        AND importance < ?
         AND created_at < ?
+        AND last_accessed_at < ?
         "#,
     )
-    .bind(config.prune_threshold)
+    .bind(now)
+    .bind(config.archive_threshold)
     .bind(cutoff_date)
-    .fetch_all(memory_store.pool())
-    .await?;
-
-    let mut pruned_count = 0;
-
-    for row in candidates {
-        let id: String = sqlx::Row::try_get(&row, "id")?;
-        memory_store.delete(&id).await?;
-        pruned_count += 1;
-    }
+    .bind(cutoff_date)
+    .execute(store.pool())
+    .await
+    .context("failed to archive stale memories")?;
 
-    Ok(pruned_count)
-}
-
-/// Merge near-duplicate memories.
-async fn merge_similar_memories(
-    _memory_store: &MemoryStore,
-    similarity_threshold: f32,
-) -> Result<usize> {
-    // For now, this is a placeholder
-    // Full implementation would:
-    // 1. Find pairs of memories with high embedding similarity
-    // 2. Merge them, keeping the higher importance one
-    // 3. Update associations to point to the merged memory
-    let _ = similarity_threshold;
-    Ok(0)
+    Ok(result.rows_affected() as usize)
 }
 
 /// Maintenance report.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MaintenanceReport {
-    pub decayed: usize,
-    pub pruned: usize,
+    /// Near-duplicates merged into another memory.
     pub merged: usize,
+    /// Memories that absorbed restatements and gained importance.
+    pub promoted: usize,
+    /// Stale memories archived.
+    pub archived: usize,
+}
+
+impl MaintenanceReport {
+    /// Whether the pass changed anything.
+    pub fn is_empty(&self) -> bool {
+        self.merged == 0 && self.promoted == 0 && self.archived == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::types::{Association, MemoryType, RelationType};
+
+    #[tokio::test]
+    async fn merge_moves_associations_and_forgets_the_duplicate() {
+        let store = MemoryStore::connect_in_memory().await;
+        let mut keeper = Memory::new("The user's cat is called Miso", MemoryType::Fact);
+        let mut duplicate = Memory::new("User has a cat named Miso", MemoryType::Fact);
+        duplicate.access_count = 3;
+        let related = Memory::new("The user lives in Lyon", MemoryType::Fact);
+        for memory in [&keeper, &duplicate, &related] {
+            store.save(memory).await.unwrap();
+        }
+        keeper.access_count = 1;
+        store
+            .create_association(&Association::new(
+                &duplicate.id,
+                &related.id,
+                RelationType::RelatedTo,
+            ))
+            .await
+            .unwrap();
+        store
+            .create_association(&Association::new(
+                &keeper.id,
+                &duplicate.id,
+                RelationType::Updates,
+            ))
+            .await
+            .unwrap();
+
+        merge_into(&store, &mut keeper, &duplicate).await.unwrap();
+
+        assert_eq!(keeper.access_count, 4);
+        assert!(store.load(&duplicate.id).await.unwrap().unwrap().forgotten);
+        let associations = store.get_associations(&keeper.id).await.unwrap();
+        assert_eq!(associations.len(), 1);
+        assert_eq!(associations[0].source_id, keeper.id);
+        assert_eq!(associations[0].target_id, related.id);
+        assert!(
+            store
+                .get_associations(&duplicate.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn archives_only_stale_unimportant_memories() {
+        let store = MemoryStore::connect_in_memory().await;
+        let long_ago = chrono::Utc::now() - chrono::Duration::days(200);
+        let stale = |content: &str, memory_type| {
+            let mut memory = Memory::new(content, memory_type);
+            memory.created_at = long_ago;
+            memory.last_accessed_at = long_ago;
+            memory
+        };
+
+        let old_event = stale("Deployed v1.2", MemoryType::Event);
+        let old_fact = stale("The API runs on port 8080", MemoryType::Fact);
+        let pinned_event = stale("First conversation", MemoryType::Event).pinned();
+        let mut recalled_event = stale("Moved to the new office", MemoryType::Event);
+        recalled_event.last_accessed_at = chrono::Utc::now();
+        let new_event = Memory::new("Released v2.0", MemoryType::Event);
+        for memory in [
+            &old_event,
+            &old_fact,
+            &pinned_event,
+            &recalled_event,
+            &new_event,
+        ] {
+            store.save(memory).await.unwrap();
+        }
+
+        let archived = archive_stale_memories(&store, &MaintenanceConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(archived, 1);
+        assert!(store.load(&old_event.id).await.unwrap().unwrap().forgotten);
+        for memory in [&old_fact, &pinned_event, &recalled_event, &new_event] {
+            assert!(!store.load(&memory.id).await.unwrap().unwrap().forgotten);
+        }
+    }
 }
@@ -51,8 +51,8 @@ impl MemoryStore {
         sqlx::query(
             r#"
             INSERT INTO memories (id, content, memory_type, importance, created_at, updated_at,
-                                 last_accessed_at, access_count, source, channel_id, forgotten)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                                 last_accessed_at, access_count, source, channel_id, forgotten, pinned)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&memory.id)
@@ -66,6 +66,7 @@ impl MemoryStore {
         .bind(&memory.source)
         .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
         .bind(memory.forgotten)
+        .bind(memory.pinned)
         .execute(&self.pool)
         .await
         .with_context(|| format!("failed to save memory {}", memory.id))?;
@@ -96,7 +97,7 @@ impl MemoryStore {
         let row = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, pinned
             FROM memories
             WHERE id = ?
             "#,
@@ -116,7 +117,7 @@ impl MemoryStore {
             UPDATE memories 
             SET content = ?, memory_type = ?, importance = ?, updated_at = ?, 
                 last_accessed_at = ?, access_count = ?, source = ?, channel_id = ?,
-                forgotten = ?
+                forgotten = ?, pinned = ?
             WHERE id = ?
             "#,
         )
@@ -129,6 +130,7 @@ impl MemoryStore {
         .bind(&memory.source)
         .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
         .bind(memory.forgotten)
+        .bind(memory.pinned)
         .bind(&memory.id)
         .execute(&self.pool)
         .await
@@ -359,7 +361,7 @@ impl MemoryStore {
         let rows = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, pinned
             FROM memories
             WHERE memory_type = ? AND forgotten = 0
             ORDER BY importance DESC, updated_at DESC
@@ -380,7 +382,7 @@ impl MemoryStore {
         let rows = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, pinned
             FROM memories
             WHERE importance >= ? AND forgotten = 0
             ORDER BY importance DESC, updated_at DESC
@@ -406,7 +408,7 @@ impl MemoryStore {
         let rows = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, pinned
             FROM memories
             WHERE created_at >= ? AND forgotten = 0
             ORDER BY created_at DESC
@@ -442,7 +444,7 @@ impl MemoryStore {
             (
                 format!(
                     "SELECT id, content, memory_type, importance, created_at, updated_at, \
-                     last_accessed_at, access_count, source, channel_id, forgotten \
+                     last_accessed_at, access_count, source, channel_id, forgotten, pinned \
                      FROM memories WHERE memory_type = ? AND forgotten = 0 {order_clause} LIMIT ?"
                 ),
                 Some(memory_type.to_string()),
@@ -451,7 +453,7 @@ impl MemoryStore {
             (
                 format!(
                     "SELECT id, content, memory_type, importance, created_at, updated_at, \
-                     last_accessed_at, access_count, source, channel_id, forgotten \
+                     last_accessed_at, access_count, source, channel_id, forgotten, pinned \
                      FROM memories WHERE forgotten = 0 {order_clause} LIMIT ?"
                 ),
                 None,
@@ -527,6 +529,7 @@ fn row_to_memory(row: &sqlx::sqlite::SqliteRow) -> Memory {
         source: row.try_get("source").ok(),
         channel_id: channel_id.map(|id| Arc::from(id) as crate::ChannelId),
         forgotten: row.try_get::<bool, _>("forgotten").unwrap_or(false),
+        pinned: row.try_get::<bool, _>("pinned").unwrap_or(false),
     }
 }
 
@@ -607,6 +610,18 @@ mod tests {
         assert_eq!(loaded.memory_type, MemoryType::Fact);
     }
 
+    #[tokio::test]
+    async fn test_pinned_round_trips() {
+        let store = MemoryStore::connect_in_memory().await;
+        let mut memory = Memory::new("Deploys happen on Tuesdays", MemoryType::Fact).pinned();
+        store.save(&memory).await.unwrap();
+        assert!(store.load(&memory.id).await.unwrap().unwrap().pinned);
+
+        memory.pinned = false;
+        store.update(&memory).await.unwrap();
+        assert!(!store.load(&memory.id).await.unwrap().unwrap().pinned);
+    }
+
     #[tokio::test]
     async fn test_get_sorted_recent() {
         let store = MemoryStore::connect_in_memory().await;
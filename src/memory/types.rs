@@ -19,6 +19,9 @@ pub struct Memory {
     /// Soft-delete flag. Forgotten memories are excluded from search and recall
     /// but remain in the database.
     pub forgotten: bool,
+    /// Saved on purpose by a user. Pinned memories don't decay and are never
+    /// pruned.
+    pub pinned: bool,
 }
 
 impl Memory {
@@ -40,6 +43,7 @@ impl Memory {
             source: None,
             channel_id: None,
             forgotten: false,
+            pinned: false,
         }
     }
 
@@ -55,6 +59,12 @@ impl Memory {
         self
     }
 
+    /// Pin the memory so maintenance leaves it alone.
+    pub fn pinned(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
+
     /// Set the channel ID.
     pub fn with_channel_id(mut self, channel_id: crate::ChannelId) -> Self {
         self.channel_id = Some(channel_id);
@@ -1,7 +1,20 @@
-//! Messaging adapters (Discord, Slack, Telegram, Twitch, Webhook, WebChat).
+//! Messaging adapters (Discord, Slack, Telegram, Twitch, IRC, Email, XMPP, WhatsApp, Webhook,
+//! WebChat, and the terminal for `spacebot chat`).
 
+pub mod announce;
+pub mod cli;
+pub mod delivery;
 pub mod discord;
+pub mod email;
+pub mod format;
+pub mod irc;
 pub mod manager;
+pub mod middleware;
+pub mod pairing;
+pub mod rate_limit;
+pub mod registry;
+pub mod response_mode;
+pub mod session;
 pub mod slack;
 pub mod target;
 pub mod telegram;
@@ -9,6 +22,8 @@ pub mod traits;
 pub mod twitch;
 pub mod webchat;
 pub mod webhook;
+pub mod whatsapp;
+pub mod xmpp;
 
 pub use manager::MessagingManager;
 pub use traits::Messaging;
@@ -0,0 +1,217 @@
+//! Announcements: one message fanned out to every conversation bound to an
+//! agent, like a maintenance notice or a changelog post.
+//!
+//! The conversations are the ones its bindings name (see
+//! [`binding_targets`]), optionally only those of bindings with one of a
+//! set of tags. Unlike proactive outreach, announcements are sent by an
+//! operator, so they don't need `allow_proactive` and have no daily limit.
+//! Each platform gets the announcement in the form it shows best, and a
+//! failed delivery doesn't stop the rest.
+
+use crate::config::Binding;
+use crate::messaging::MessagingManager;
+use crate::messaging::format;
+use crate::messaging::target::{BroadcastTarget, binding_targets};
+use crate::{Card, OutboundResponse};
+
+use serde::Serialize;
+
+/// Longest text Discord shows in an embed description. Longer announcements
+/// are sent as plain messages.
+const MAX_EMBED_DESCRIPTION: usize = 4096;
+
+/// Embed color of announcements on Discord.
+const ANNOUNCEMENT_COLOR: u32 = 0x5865F2;
+
+/// A message for all of an agent's bound conversations.
+#[derive(Debug, Clone)]
+pub struct Announcement {
+    /// Heading shown above the text.
+    pub title: Option<String>,
+    /// The announcement, in markdown.
+    pub text: String,
+    /// Only send to bindings with at least one of these tags. Empty sends to
+    /// all of the agent's bindings.
+    pub tags: Vec<String>,
+}
+
+/// Where an announcement was delivered, and where it failed.
+#[derive(Debug, Default, Serialize)]
+pub struct AnnouncementReport {
+    pub delivered: Vec<String>,
+    pub failed: Vec<FailedDelivery>,
+}
+
+/// A conversation an announcement couldn't be delivered to.
+#[derive(Debug, Serialize)]
+pub struct FailedDelivery {
+    pub target: String,
+    pub error: String,
+}
+
+/// Why an announcement wasn't sent at all.
+#[derive(Debug, thiserror::Error)]
+pub enum AnnounceError {
+    #[error("the announcement is empty")]
+    Empty,
+    #[error("no binding of this agent names a conversation to announce to")]
+    NoTargets,
+}
+
+/// Conversations of `agent_id`'s bindings that have one of `tags`, or of all
+/// its bindings when `tags` is empty.
+pub fn announcement_targets(
+    bindings: &[Binding],
+    agent_id: &str,
+    tags: &[String],
+) -> Vec<BroadcastTarget> {
+    binding_targets(bindings.iter().filter(|binding| {
+        binding.agent_id == agent_id
+            && (tags.is_empty() || binding.tags.iter().any(|tag| tags.contains(tag)))
+    }))
+}
+
+/// The announcement as `adapter` shows it: an embed on Discord, markdown
+/// where the adapter converts it, and plain text where there's no
+/// formatting.
+pub fn format_announcement(adapter: &str, title: Option<&str>, text: &str) -> OutboundResponse {
+    if adapter == "discord" && text.len() <= MAX_EMBED_DESCRIPTION {
+        return OutboundResponse::Rich {
+            text: String::new(),
+            card: Card {
+                title: title.map(str::to_string),
+                description: Some(text.to_string()),
+                color: Some(ANNOUNCEMENT_COLOR),
+                ..Default::default()
+            },
+        };
+    }
+
+    let markdown = match title {
+        Some(title) => format!("**{title}**\n\n{text}"),
+        None => text.to_string(),
+    };
+    match adapter {
+        // These adapters don't convert markdown when broadcasting.
+        "irc" | "twitch" | "xmpp" | "email" => {
+            OutboundResponse::Text(format::plain_text(&markdown))
+        }
+        _ => OutboundResponse::Text(markdown),
+    }
+}
+
+/// Send `announcement` to `agent_id`'s bound conversations, one at a time.
+pub async fn announce(
+    messaging_manager: &MessagingManager,
+    bindings: &[Binding],
+    agent_id: &str,
+    announcement: &Announcement,
+) -> Result<AnnouncementReport, AnnounceError> {
+    let text = announcement.text.trim();
+    if text.is_empty() {
+        return Err(AnnounceError::Empty);
+    }
+    let targets = announcement_targets(bindings, agent_id, &announcement.tags);
+    if targets.is_empty() {
+        return Err(AnnounceError::NoTargets);
+    }
+    let title = announcement
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|title| !title.is_empty());
+
+    let mut report = AnnouncementReport::default();
+    for target in targets {
+        let response = format_announcement(&target.adapter, title, text);
+        match messaging_manager
+            .broadcast(&target.adapter, &target.target, response)
+            .await
+        {
+            Ok(()) => report.delivered.push(target.to_string()),
+            Err(error) => {
+                tracing::warn!(agent_id, %target, %error, "failed to deliver announcement");
+                report.failed.push(FailedDelivery {
+                    target: target.to_string(),
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+
+    tracing::info!(
+        agent_id,
+        delivered = report.delivered.len(),
+        failed = report.failed.len(),
+        "announcement sent"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(agent_id: &str, channel_id: &str, tags: &[&str]) -> Binding {
+        Binding {
+            agent_id: agent_id.into(),
+            channel: "discord".into(),
+            guild_id: None,
+            workspace_id: None,
+            chat_id: None,
+            channel_ids: vec![channel_id.into()],
+            activation: Default::default(),
+            wake_words: Vec::new(),
+            reply_to_message: false,
+            dm_allowed_users: Vec::new(),
+            digest_interval_secs: None,
+            response_mode: Default::default(),
+            allow_proactive: false,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            overrides: Default::default(),
+        }
+    }
+
+    #[test]
+    fn announcements_go_to_tagged_bindings() {
+        let bindings = vec![
+            binding("main", "100", &["customers"]),
+            binding("main", "200", &["internal", "customers"]),
+            binding("main", "300", &[]),
+            binding("ops", "400", &["customers"]),
+        ];
+        let targets = |tags: &[&str]| -> Vec<String> {
+            let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+            announcement_targets(&bindings, "main", &tags)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+
+        assert_eq!(targets(&[]), ["discord:100", "discord:200", "discord:300"]);
+        assert_eq!(targets(&["customers"]), ["discord:100", "discord:200"]);
+        assert_eq!(targets(&["internal", "staff"]), ["discord:200"]);
+        assert!(targets(&["staff"]).is_empty());
+    }
+
+    #[test]
+    fn announcements_are_formatted_per_platform() {
+        let title = Some("Maintenance tonight");
+        let text = "Spacebot is **down** from 22:00 UTC.";
+
+        assert!(matches!(
+            format_announcement("discord", title, text),
+            OutboundResponse::Rich { card, .. }
+                if card.title.as_deref() == title && card.description.as_deref() == Some(text)
+        ));
+        assert!(matches!(
+            format_announcement("slack", title, text),
+            OutboundResponse::Text(text)
+                if text == "**Maintenance tonight**\n\nSpacebot is **down** from 22:00 UTC."
+        ));
+        assert!(matches!(
+            format_announcement("irc", None, text),
+            OutboundResponse::Text(text) if !text.contains("**")
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+//! Terminal messaging adapter behind `spacebot chat`.
+//!
+//! Reads lines from stdin and prints the agent's replies to stdout, so an
+//! agent can be tried locally without configuring a platform. Streamed
+//! replies are printed as they grow. `/attach <path>` queues a local file
+//! that goes out with the next message, carried inline as a `data:` URL.
+//! `/quit` (or end of input) ends the session.
+
+use crate::agent::attachment_cache::data_url;
+use crate::config::Config;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{Attachment, InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use tokio::io::AsyncBufReadExt as _;
+use tokio::sync::{Mutex, Notify, mpsc};
+
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Adapter name, and the `source` of every typed message.
+pub const CLI_ADAPTER: &str = "cli";
+
+/// Files larger than this can't be attached.
+const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+const PROMPT: &str = "> ";
+
+/// What's been printed of the reply currently streaming.
+#[derive(Default)]
+struct StreamState {
+    /// Text printed so far, when a stream is open.
+    printed: Option<String>,
+    /// Text of the last finished stream, so a final copy isn't printed twice.
+    finished: Option<String>,
+}
+
+/// Chat with one agent through the terminal.
+#[derive(Clone)]
+pub struct CliAdapter {
+    agent_id: String,
+    stream: Arc<Mutex<StreamState>>,
+    closed: Arc<Notify>,
+}
+
+impl CliAdapter {
+    pub fn new(agent_id: impl Into<String>) -> Self {
+        Self {
+            agent_id: agent_id.into(),
+            stream: Arc::new(Mutex::new(StreamState::default())),
+            closed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Resolves once the user quits or stdin closes.
+    pub async fn closed(&self) {
+        self.closed.notified().await;
+    }
+
+    fn conversation_id(&self) -> String {
+        format!("{CLI_ADAPTER}:{}", self.agent_id)
+    }
+
+    fn print_reply(&self, text: &str) {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "\r{}: {}", self.agent_id, text.trim_end()).ok();
+        write!(stdout, "{PROMPT}").ok();
+        stdout.flush().ok();
+    }
+}
+
+/// Strip everything but the agent from the config: no platform adapters and
+/// no API server, so a chat session doesn't answer anywhere else.
+pub fn prepare_config(config: &mut Config) {
+    config.messaging = crate::config::MessagingConfig {
+        sessions: config.messaging.sessions,
+        ..crate::config::MessagingConfig::default()
+    };
+    config.api.enabled = false;
+}
+
+impl Messaging for CliAdapter {
+    fn name(&self) -> &str {
+        CLI_ADAPTER
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(16);
+        let adapter = self.clone();
+
+        tokio::spawn(async move {
+            let sender_id = std::env::var("USER").unwrap_or_else(|_| "local".into());
+            let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+            let mut pending: Vec<Attachment> = Vec::new();
+
+            print!("{PROMPT}");
+            std::io::stdout().flush().ok();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim();
+                match parse_line(line) {
+                    Input::Quit => break,
+                    Input::Empty => {}
+                    Input::Attach(path) => match load_attachment(Path::new(path)).await {
+                        Ok(attachment) => {
+                            eprintln!(
+                                "attached {} ({}), sent with your next message",
+                                attachment.filename, attachment.mime_type
+                            );
+                            pending.push(attachment);
+                        }
+                        Err(error) => eprintln!("{error:#}"),
+                    },
+                    Input::Message(text) => {
+                        let message = InboundMessage {
+                            id: uuid::Uuid::new_v4().to_string(),
+                            source: CLI_ADAPTER.into(),
+                            conversation_id: adapter.conversation_id(),
+                            sender_id: sender_id.clone(),
+                            agent_id: Some(adapter.agent_id.as_str().into()),
+                            content: message_content(text, std::mem::take(&mut pending)),
+                            timestamp: chrono::Utc::now(),
+                            metadata: HashMap::from([(
+                                "sender_display_name".to_string(),
+                                serde_json::Value::String(sender_id.clone()),
+                            )]),
+                            formatted_author: Some(sender_id.clone()),
+                        };
+                        if inbound_tx.send(message).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+                print!("{PROMPT}");
+                std::io::stdout().flush().ok();
+            }
+
+            adapter.closed.notify_one();
+        });
+
+        Ok(Box::pin(tokio_stream::wrappers::ReceiverStream::new(
+            inbound_rx,
+        )))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                let mut stream = self.stream.lock().await;
+                if stream.finished.take().as_deref() != Some(text.as_str()) {
+                    self.print_reply(&text);
+                }
+            }
+            OutboundResponse::Choices { prompt, options } => {
+                self.print_reply(&crate::choices_text(&prompt, &options));
+            }
+            OutboundResponse::Rich { text, card } => {
+                self.print_reply(&crate::rich_text(&text, &card));
+            }
+            OutboundResponse::RichMessage { text, cards, .. } => {
+                let mut rendered = text;
+                for card in cards {
+                    for line in [card.title, card.description].into_iter().flatten() {
+                        rendered.push_str(&format!("\n  {line}"));
+                    }
+                    for field in card.fields {
+                        rendered.push_str(&format!("\n  {}: {}", field.name, field.value));
+                    }
+                }
+                self.print_reply(&rendered);
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                caption,
+                ..
+            } => {
+                let path = save_file(&filename, &data)
+                    .await
+                    .context("failed to save file from agent")?;
+                let note = match caption {
+                    Some(caption) => format!("{caption}\n[file saved to {}]", path.display()),
+                    None => format!("[file saved to {}]", path.display()),
+                };
+                self.print_reply(&note);
+            }
+            OutboundResponse::StreamStart => {
+                let mut stream = self.stream.lock().await;
+                stream.printed = Some(String::new());
+                stream.finished = None;
+                let mut stdout = std::io::stdout().lock();
+                write!(stdout, "\r{}: ", self.agent_id).ok();
+                stdout.flush().ok();
+            }
+            OutboundResponse::StreamChunk(text) => {
+                let mut stream = self.stream.lock().await;
+                if let Some(printed) = &mut stream.printed {
+                    let mut stdout = std::io::stdout().lock();
+                    write!(stdout, "{}", stream_delta(printed, &text)).ok();
+                    stdout.flush().ok();
+                    *printed = text;
+                }
+            }
+            OutboundResponse::StreamEnd => {
+                let mut stream = self.stream.lock().await;
+                stream.finished = stream.printed.take();
+                let mut stdout = std::io::stdout().lock();
+                write!(stdout, "\n{PROMPT}").ok();
+                stdout.flush().ok();
+            }
+            OutboundResponse::Reaction(emoji) => {
+                eprintln!("\r[{} reacted {emoji}]", self.agent_id);
+            }
+            OutboundResponse::RemoveReaction(_) => {}
+            OutboundResponse::Status(status) => {
+                self.send_status(message, status).await?;
+            }
+            OutboundResponse::Attachments { .. } => {}
+        }
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        _message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        match status {
+            StatusUpdate::ToolStarted { tool_name } => eprintln!("\r  · {tool_name}"),
+            StatusUpdate::WorkerStarted { task, .. } => eprintln!("\r  · worker: {task}"),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn broadcast(&self, _target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            self.print_reply(&text);
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// A line typed at the prompt.
+#[derive(Debug, PartialEq, Eq)]
+enum Input<'a> {
+    Quit,
+    Empty,
+    Attach(&'a str),
+    Message(&'a str),
+}
+
+fn parse_line(line: &str) -> Input<'_> {
+    match line {
+        "" => Input::Empty,
+        "/quit" | "/exit" => Input::Quit,
+        _ => match line.strip_prefix("/attach ") {
+            Some(path) => Input::Attach(path.trim()),
+            None => Input::Message(line),
+        },
+    }
+}
+
+fn message_content(text: &str, attachments: Vec<Attachment>) -> MessageContent {
+    if attachments.is_empty() {
+        MessageContent::Text(text.to_string())
+    } else {
+        MessageContent::Media {
+            text: Some(text.to_string()),
+            attachments,
+        }
+    }
+}
+
+/// Read a local file into an inline attachment.
+async fn load_attachment(path: &Path) -> anyhow::Result<Attachment> {
+    let size = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("can't attach {}", path.display()))?
+        .len();
+    if size > MAX_ATTACHMENT_BYTES {
+        anyhow::bail!(
+            "can't attach {}: {:.1} MB is over the {} MB limit",
+            path.display(),
+            size as f64 / (1024.0 * 1024.0),
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        );
+    }
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("can't attach {}", path.display()))?;
+    let mime_type = mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string();
+    Ok(Attachment {
+        filename: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "attachment".into()),
+        url: data_url(&mime_type, &bytes),
+        mime_type,
+        size_bytes: Some(size),
+    })
+}
+
+/// Save a file the agent sent under the temp directory.
+async fn save_file(filename: &str, data: &[u8]) -> std::io::Result<PathBuf> {
+    let directory = std::env::temp_dir().join("spacebot-chat");
+    tokio::fs::create_dir_all(&directory).await?;
+    let name = Path::new(filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".into());
+    let path = directory.join(name);
+    tokio::fs::write(&path, data).await?;
+    Ok(path)
+}
+
+/// The part of a streamed reply not printed yet. Chunks carry the whole
+/// reply so far; if it was rewritten rather than extended, it's printed
+/// again on a new line.
+fn stream_delta(printed: &str, text: &str) -> String {
+    match text.strip_prefix(printed) {
+        Some(delta) => delta.to_string(),
+        None => format!("\n{text}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_parse_into_commands_and_messages() {
+        assert_eq!(parse_line(""), Input::Empty);
+        assert_eq!(parse_line("/quit"), Input::Quit);
+        assert_eq!(
+            parse_line("/attach ./notes.txt"),
+            Input::Attach("./notes.txt")
+        );
+        assert_eq!(parse_line("hello there"), Input::Message("hello there"));
+    }
+
+    #[test]
+    fn streamed_chunks_print_only_new_text() {
+        assert_eq!(stream_delta("", "Hel"), "Hel");
+        assert_eq!(stream_delta("Hel", "Hello"), "lo");
+        assert_eq!(stream_delta("Hello", "Hi"), "\nHi");
+    }
+
+    #[tokio::test]
+    async fn attachments_load_as_data_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "remember the milk").unwrap();
+
+        let attachment = load_attachment(&path).await.unwrap();
+        assert_eq!(attachment.filename, "notes.txt");
+        assert_eq!(attachment.mime_type, "text/plain");
+        assert_eq!(attachment.size_bytes, Some(17));
+        assert!(attachment.url.starts_with("data:text/plain;base64,"));
+
+        assert!(
+            load_attachment(&dir.path().join("missing.txt"))
+                .await
+                .is_err()
+        );
+    }
+}
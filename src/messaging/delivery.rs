@@ -0,0 +1,318 @@
+//! Delivery tracking for webhook responses.
+//!
+//! Every response the webhook adapter produces gets a delivery record, so an
+//! integrator can reconcile whether an asynchronous answer reached their
+//! system. A record starts out `queued`. It becomes `delivered` once the
+//! callback URL accepted it or a poll returned it, `failed` once every
+//! callback attempt failed, and `read` when the integrator acknowledges it.
+//! Records live in an instance-level redb database.
+
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+/// delivery_id -> JSON-encoded [`Delivery`].
+const DELIVERIES_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("deliveries");
+
+/// Delivery storage errors.
+#[derive(Debug, thiserror::Error)]
+pub enum DeliveryError {
+    #[error("delivery not found")]
+    NotFound,
+
+    #[error("delivery storage error: {0}")]
+    Storage(String),
+}
+
+fn storage_error<E: std::fmt::Display>(context: &str) -> impl FnOnce(E) -> DeliveryError + '_ {
+    move |error| DeliveryError::Storage(format!("{context}: {error}"))
+}
+
+/// Where a response is on its way to the integrator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting to be polled or pushed.
+    Queued,
+    /// Accepted by the callback URL or returned by a poll.
+    Delivered,
+    /// Every callback attempt failed. The response can still be polled.
+    Failed,
+    /// Acknowledged by the integrator.
+    Read,
+}
+
+/// The delivery state of one webhook response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub id: String,
+    /// Conversation ID as the integrator knows it, without the `webhook:` prefix.
+    pub conversation_id: String,
+    pub response_type: String,
+    pub status: DeliveryStatus,
+    /// Callback attempts made so far.
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub read_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Delivery {
+    /// A new queued delivery.
+    pub fn queued(id: String, conversation_id: String, response_type: String) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id,
+            conversation_id,
+            response_type,
+            status: DeliveryStatus::Queued,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+            delivered_at: None,
+            read_at: None,
+        }
+    }
+
+    /// Mark as delivered, unless it was already acknowledged.
+    pub fn mark_delivered(&mut self) {
+        if self.status != DeliveryStatus::Read {
+            self.status = DeliveryStatus::Delivered;
+            self.delivered_at.get_or_insert_with(chrono::Utc::now);
+        }
+    }
+}
+
+/// Delivery store backed by redb.
+pub struct DeliveryStore {
+    db: Database,
+}
+
+impl std::fmt::Debug for DeliveryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryStore").finish_non_exhaustive()
+    }
+}
+
+impl DeliveryStore {
+    /// Open or create the delivery database at `path`.
+    pub fn new(path: &Path) -> Result<Self, DeliveryError> {
+        let db =
+            Database::create(path).map_err(storage_error("failed to open delivery database"))?;
+
+        let write_transaction = db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        write_transaction
+            .open_table(DELIVERIES_TABLE)
+            .map_err(storage_error("failed to open deliveries table"))?;
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit deliveries table"))?;
+
+        Ok(Self { db })
+    }
+
+    /// Store a delivery, replacing any record with the same ID.
+    pub fn put(&self, delivery: &Delivery) -> Result<(), DeliveryError> {
+        let encoded =
+            serde_json::to_vec(delivery).map_err(storage_error("failed to encode delivery"))?;
+        let write_transaction = self
+            .db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        {
+            let mut table = write_transaction
+                .open_table(DELIVERIES_TABLE)
+                .map_err(storage_error("failed to open deliveries table"))?;
+            table
+                .insert(delivery.id.as_str(), encoded.as_slice())
+                .map_err(storage_error("failed to store delivery"))?;
+        }
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit delivery"))
+    }
+
+    /// Look up a delivery by ID.
+    pub fn get(&self, id: &str) -> Result<Option<Delivery>, DeliveryError> {
+        let read_transaction = self
+            .db
+            .begin_read()
+            .map_err(storage_error("failed to begin read transaction"))?;
+        let table = read_transaction
+            .open_table(DELIVERIES_TABLE)
+            .map_err(storage_error("failed to open deliveries table"))?;
+        let Some(entry) = table
+            .get(id)
+            .map_err(storage_error("failed to read delivery"))?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_slice(entry.value())
+            .map(Some)
+            .map_err(storage_error("failed to decode delivery"))
+    }
+
+    /// Apply `change` to a stored delivery and save it.
+    pub fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut Delivery),
+    ) -> Result<Delivery, DeliveryError> {
+        let mut delivery = self.get(id)?.ok_or(DeliveryError::NotFound)?;
+        change(&mut delivery);
+        delivery.updated_at = chrono::Utc::now();
+        self.put(&delivery)?;
+        Ok(delivery)
+    }
+
+    /// Mark a delivery as delivered, unless it was already acknowledged.
+    pub fn mark_delivered(&self, id: &str) -> Result<Delivery, DeliveryError> {
+        self.update(id, Delivery::mark_delivered)
+    }
+
+    /// Mark a delivery as read by the integrator.
+    pub fn mark_read(&self, id: &str) -> Result<Delivery, DeliveryError> {
+        self.update(id, |delivery| {
+            let now = chrono::Utc::now();
+            delivery.status = DeliveryStatus::Read;
+            delivery.delivered_at.get_or_insert(now);
+            delivery.read_at.get_or_insert(now);
+        })
+    }
+
+    /// Deliveries of a conversation, optionally with one status, oldest first.
+    pub fn list(
+        &self,
+        conversation_id: &str,
+        status: Option<DeliveryStatus>,
+    ) -> Result<Vec<Delivery>, DeliveryError> {
+        let read_transaction = self
+            .db
+            .begin_read()
+            .map_err(storage_error("failed to begin read transaction"))?;
+        let table = read_transaction
+            .open_table(DELIVERIES_TABLE)
+            .map_err(storage_error("failed to open deliveries table"))?;
+
+        let mut deliveries = Vec::new();
+        for entry in table
+            .iter()
+            .map_err(storage_error("failed to iterate deliveries"))?
+        {
+            let (_, value) = entry.map_err(storage_error("failed to read delivery"))?;
+            let Ok(delivery) = serde_json::from_slice::<Delivery>(value.value()) else {
+                continue;
+            };
+            if delivery.conversation_id == conversation_id
+                && status.is_none_or(|status| delivery.status == status)
+            {
+                deliveries.push(delivery);
+            }
+        }
+        deliveries.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(deliveries)
+    }
+
+    /// Delete deliveries last updated before `now - older_than`. Returns how
+    /// many were removed.
+    pub fn prune(&self, older_than: chrono::Duration) -> Result<usize, DeliveryError> {
+        let cutoff = chrono::Utc::now() - older_than;
+        let write_transaction = self
+            .db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        let pruned = {
+            let mut table = write_transaction
+                .open_table(DELIVERIES_TABLE)
+                .map_err(storage_error("failed to open deliveries table"))?;
+            let stale: Vec<String> = table
+                .iter()
+                .map_err(storage_error("failed to iterate deliveries"))?
+                .filter_map(|entry| {
+                    let (key, value) = entry.ok()?;
+                    let delivery = serde_json::from_slice::<Delivery>(value.value()).ok();
+                    delivery
+                        .is_none_or(|delivery| delivery.updated_at < cutoff)
+                        .then(|| key.value().to_string())
+                })
+                .collect();
+            for id in &stale {
+                table
+                    .remove(id.as_str())
+                    .map_err(storage_error("failed to remove delivery"))?;
+            }
+            stale.len()
+        };
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit delivery pruning"))?;
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, DeliveryStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DeliveryStore::new(&dir.path().join("deliveries.redb")).unwrap();
+        (dir, store)
+    }
+
+    #[test]
+    fn deliveries_move_through_states() {
+        let (_dir, store) = store();
+        store
+            .put(&Delivery::queued("d1".into(), "c1".into(), "text".into()))
+            .unwrap();
+        store
+            .put(&Delivery::queued("d2".into(), "c2".into(), "text".into()))
+            .unwrap();
+
+        let delivered = store.mark_delivered("d1").unwrap();
+        assert_eq!(delivered.status, DeliveryStatus::Delivered);
+        assert!(delivered.delivered_at.is_some());
+
+        let read = store.mark_read("d1").unwrap();
+        assert_eq!(read.status, DeliveryStatus::Read);
+        // A late poll doesn't undo the acknowledgement.
+        assert_eq!(
+            store.mark_delivered("d1").unwrap().status,
+            DeliveryStatus::Read
+        );
+
+        assert_eq!(store.list("c1", None).unwrap().len(), 1);
+        assert!(
+            store
+                .list("c1", Some(DeliveryStatus::Queued))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            store.mark_read("missing"),
+            Err(DeliveryError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn prune_removes_stale_deliveries() {
+        let (_dir, store) = store();
+        let mut stale = Delivery::queued("old".into(), "c1".into(), "text".into());
+        stale.updated_at = chrono::Utc::now() - chrono::Duration::days(40);
+        store.put(&stale).unwrap();
+        store
+            .put(&Delivery::queued("new".into(), "c1".into(), "text".into()))
+            .unwrap();
+
+        assert_eq!(store.prune(chrono::Duration::days(30)).unwrap(), 1);
+        assert!(store.get("old").unwrap().is_none());
+        assert!(store.get("new").unwrap().is_some());
+    }
+}
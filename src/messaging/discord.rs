@@ -1,23 +1,32 @@
 //! Discord messaging adapter using serenity.
 
+use crate::agent::channel::REPLY_TO_MESSAGE_KEY;
 use crate::config::DiscordPermissions;
-use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging};
+use crate::messaging::format;
+use crate::messaging::pairing::PairingStore;
+use crate::messaging::traits::{
+    ActivityKind, CreatedThread, HistoryMessage, InboundStream, Messaging, Presence,
+};
 use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
 
 use anyhow::Context as _;
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use serenity::all::{
-    ButtonStyle, ChannelId, ChannelType, Context, CreateActionRow, CreateAttachment, CreateButton,
-    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
-    CreateMessage, CreatePoll, CreatePollAnswer, CreateSelectMenu, CreateSelectMenuKind,
-    CreateSelectMenuOption, CreateThread, EditMessage, EventHandler, GatewayIntents, GetMessages,
-    Http, Interaction, Message, MessageId, ReactionType, Ready, ShardManager, User, UserId,
+    ActionRowComponent, ActivityData, ButtonKind, ButtonStyle, ChannelId, ChannelType,
+    ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow, CreateAttachment,
+    CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreatePoll, CreatePollAnswer,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateThread, EditMessage,
+    EventHandler, GatewayIntents, GetMessages, GuildId, Http, Interaction, Message, MessageId,
+    OnlineStatus, Reaction, ReactionType, Ready, ShardManager, User, UserId,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc};
 
+mod voice;
+
 /// Discord adapter state.
 pub struct DiscordAdapter {
     token: String,
@@ -28,7 +37,11 @@ pub struct DiscordAdapter {
     active_messages: Arc<RwLock<HashMap<String, serenity::all::MessageId>>>,
     /// Typing handles per message. Typing stops when the handle is dropped.
     typing_tasks: Arc<RwLock<HashMap<String, serenity::http::Typing>>>,
+    /// Live status card message per Discord channel, edited in place.
+    status_messages: Arc<RwLock<HashMap<String, MessageId>>>,
     shard_manager: Arc<RwLock<Option<Arc<ShardManager>>>>,
+    voice: Arc<voice::DiscordVoice>,
+    pairing: Option<Arc<PairingStore>>,
 }
 
 impl DiscordAdapter {
@@ -40,10 +53,19 @@ impl DiscordAdapter {
             bot_user_id: Arc::new(RwLock::new(None)),
             active_messages: Arc::new(RwLock::new(HashMap::new())),
             typing_tasks: Arc::new(RwLock::new(HashMap::new())),
+            status_messages: Arc::new(RwLock::new(HashMap::new())),
             shard_manager: Arc::new(RwLock::new(None)),
+            voice: Arc::new(voice::DiscordVoice::new()),
+            pairing: None,
         }
     }
 
+    /// Let paired users DM the bot, and answer unknown ones with a pairing code.
+    pub fn with_pairing(mut self, pairing: Option<Arc<PairingStore>>) -> Self {
+        self.pairing = pairing;
+        self
+    }
+
     async fn get_http(&self) -> anyhow::Result<Arc<Http>> {
         self.http
             .read()
@@ -78,10 +100,71 @@ impl DiscordAdapter {
             .remove(&Self::channel_key(message));
     }
 
+    /// Post the channel's live status card, edit it in place if it's already
+    /// up, or delete it when `card` is `None`.
+    async fn update_live_status(
+        &self,
+        message: &InboundMessage,
+        card: Option<crate::Card>,
+        interactive_elements: Vec<crate::InteractiveElements>,
+    ) -> anyhow::Result<()> {
+        let http = self.get_http().await?;
+        let channel_id = self.extract_channel_id(message)?;
+        let key = Self::channel_key(message);
+
+        let Some(card) = card else {
+            let posted = self.status_messages.write().await.remove(&key);
+            if let Some(message_id) = posted
+                && let Err(error) = channel_id.delete_message(&*http, message_id).await
+            {
+                tracing::debug!(%error, "failed to delete discord status card");
+            }
+            return Ok(());
+        };
+
+        let embed = build_embed(&card);
+        let components: Vec<CreateActionRow> =
+            interactive_elements.iter().map(build_action_row).collect();
+
+        let posted = self.status_messages.read().await.get(&key).copied();
+        if let Some(message_id) = posted {
+            let builder = EditMessage::new()
+                .embed(embed.clone())
+                .components(components.clone());
+            match channel_id.edit_message(&*http, message_id, builder).await {
+                Ok(_) => return Ok(()),
+                // Most likely deleted by someone; post a fresh card.
+                Err(error) => tracing::debug!(%error, "failed to edit discord status card"),
+            }
+        }
+
+        let sent = channel_id
+            .send_message(
+                &*http,
+                CreateMessage::new().embed(embed).components(components),
+            )
+            .await
+            .context("failed to post discord status card")?;
+        self.status_messages.write().await.insert(key, sent.id);
+        Ok(())
+    }
+
+    /// The message a reply should reference: an explicit target, or the
+    /// triggering message when its binding has `reply_to_message` set.
     fn extract_reply_message_id(message: &InboundMessage) -> Option<MessageId> {
+        let reply_to_trigger = message
+            .metadata
+            .get(REPLY_TO_MESSAGE_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
         message
             .metadata
             .get("discord_reply_to_message_id")
+            .or_else(|| {
+                reply_to_trigger
+                    .then(|| message.metadata.get("discord_message_id"))
+                    .flatten()
+            })
             .and_then(|value| value.as_u64())
             .map(MessageId::new)
     }
@@ -92,7 +175,17 @@ impl Messaging for DiscordAdapter {
         "discord"
     }
 
+    fn supports_attachments(&self) -> bool {
+        true
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
     async fn start(&self) -> crate::Result<InboundStream> {
+        use songbird::SerenityInit as _;
+
         let (inbound_tx, inbound_rx) = mpsc::channel(256);
 
         let handler = Handler {
@@ -100,15 +193,21 @@ impl Messaging for DiscordAdapter {
             permissions: self.permissions.clone(),
             http_slot: self.http.clone(),
             bot_user_id_slot: self.bot_user_id.clone(),
+            voice: self.voice.clone(),
+            pairing: self.pairing.clone(),
         };
 
         let intents = GatewayIntents::GUILD_MESSAGES
             | GatewayIntents::DIRECT_MESSAGES
             | GatewayIntents::MESSAGE_CONTENT
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS
+            | GatewayIntents::DIRECT_MESSAGE_REACTIONS
+            | GatewayIntents::GUILD_VOICE_STATES
             | GatewayIntents::GUILDS;
 
         let mut client = serenity::Client::builder(&self.token, intents)
             .event_handler(handler)
+            .register_songbird_with(self.voice.songbird())
             .await
             .context("failed to build discord client")?;
 
@@ -138,6 +237,7 @@ impl Messaging for DiscordAdapter {
                 self.stop_typing(message).await;
                 let reply_to = Self::extract_reply_message_id(message);
 
+                let text = format::discord(&text);
                 for (index, chunk) in split_message(&text, 2000).into_iter().enumerate() {
                     let mut builder = CreateMessage::new().content(chunk);
                     if index == 0
@@ -202,8 +302,25 @@ impl Messaging for DiscordAdapter {
                         .context("failed to send discord rich message")?;
                 }
             }
+            OutboundResponse::Choices { prompt, options } => {
+                self.stop_typing(message).await;
+                let reply_to = Self::extract_reply_message_id(message);
+                send_choices(&http, channel_id, &prompt, &options, reply_to).await?;
+            }
+            OutboundResponse::Rich { text, card } => {
+                self.stop_typing(message).await;
+                let reply_to = Self::extract_reply_message_id(message);
+                send_rich(&http, channel_id, &text, &card, reply_to).await?;
+            }
+            OutboundResponse::Attachments { text, files } => {
+                self.stop_typing(message).await;
+                let reply_to = Self::extract_reply_message_id(message);
+                let text = text.unwrap_or_default();
+                send_attachments(&http, channel_id, &text, files, reply_to).await?;
+            }
             OutboundResponse::ThreadReply { thread_name, text } => {
                 self.stop_typing(message).await;
+                let text = format::discord(&text);
 
                 // Try to create a public thread from the source message.
                 // Requires the "Create Public Threads" bot permission.
@@ -297,8 +414,12 @@ impl Messaging for DiscordAdapter {
             OutboundResponse::StreamStart => {
                 self.stop_typing(message).await;
 
+                let mut builder = CreateMessage::new().content("\u{200B}");
+                if let Some(reply_message_id) = Self::extract_reply_message_id(message) {
+                    builder = builder.reference_message((channel_id, reply_message_id));
+                }
                 let placeholder = channel_id
-                    .say(&*http, "\u{200B}")
+                    .send_message(&*http, builder)
                     .await
                     .context("failed to send stream placeholder")?;
 
@@ -310,6 +431,7 @@ impl Messaging for DiscordAdapter {
             OutboundResponse::StreamChunk(text) => {
                 let active = self.active_messages.read().await;
                 if let Some(&message_id) = active.get(&message.id) {
+                    let text = format::discord(&text);
                     let display_text = if text.len() > 2000 {
                         let end = text.floor_char_boundary(1997);
                         format!("{}...", &text[..end])
@@ -365,11 +487,20 @@ impl Messaging for DiscordAdapter {
                 let http = self.get_http().await?;
                 let channel_id = self.extract_channel_id(message)?;
 
-                let typing = channel_id.start_typing(&http);
+                // The handle keeps typing alive on its own, so a repeated
+                // Thinking from the channel's keepalive leaves it running.
                 self.typing_tasks
                     .write()
                     .await
-                    .insert(Self::channel_key(message), typing);
+                    .entry(Self::channel_key(message))
+                    .or_insert_with(|| channel_id.start_typing(&http));
+            }
+            StatusUpdate::Live {
+                card,
+                interactive_elements,
+            } => {
+                self.update_live_status(message, card, interactive_elements)
+                    .await?;
             }
             _ => {
                 self.stop_typing(message).await;
@@ -403,6 +534,7 @@ impl Messaging for DiscordAdapter {
         };
 
         if let OutboundResponse::Text(text) = response {
+            let text = format::discord(&text);
             for chunk in split_message(&text, 2000) {
                 channel_id
                     .say(&*http, &chunk)
@@ -451,6 +583,13 @@ impl Messaging for DiscordAdapter {
                     .await
                     .context("failed to broadcast discord rich message")?;
             }
+        } else if let OutboundResponse::Choices { prompt, options } = response {
+            send_choices(&http, channel_id, &prompt, &options, None).await?;
+        } else if let OutboundResponse::Rich { text, card } = response {
+            send_rich(&http, channel_id, &text, &card, None).await?;
+        } else if let OutboundResponse::Attachments { text, files } = response {
+            let text = text.unwrap_or_default();
+            send_attachments(&http, channel_id, &text, files, None).await?;
         }
 
         Ok(())
@@ -532,6 +671,100 @@ impl Messaging for DiscordAdapter {
         Ok(history)
     }
 
+    async fn create_thread(
+        &self,
+        message: &InboundMessage,
+        name: &str,
+    ) -> crate::Result<CreatedThread> {
+        let http = self.get_http().await?;
+        let channel_id = self.extract_channel_id(message)?;
+        let guild_id = message
+            .metadata
+            .get("discord_guild_id")
+            .and_then(|v| v.as_u64())
+            .context("threads can't be created in direct messages")?;
+        if message
+            .metadata
+            .get("discord_is_thread")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            return Err(anyhow::anyhow!("threads can't be created inside a thread").into());
+        }
+
+        let builder = CreateThread::new(name).kind(ChannelType::PublicThread);
+        let thread = match message
+            .metadata
+            .get("discord_message_id")
+            .and_then(|v| v.as_u64())
+        {
+            Some(message_id) => {
+                channel_id
+                    .create_thread_from_message(&*http, MessageId::new(message_id), builder)
+                    .await
+            }
+            None => channel_id.create_thread(&*http, builder).await,
+        }
+        .context("failed to create discord thread")?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert("discord_channel_id".into(), thread.id.get().into());
+        metadata.insert("discord_guild_id".into(), guild_id.into());
+        metadata.insert("discord_channel_name".into(), thread.name.clone().into());
+        metadata.insert("discord_is_thread".into(), true.into());
+        metadata.insert("discord_parent_channel_id".into(), channel_id.get().into());
+        if let Some(guild_name) = message.metadata.get("discord_guild_name") {
+            metadata.insert("discord_guild_name".into(), guild_name.clone());
+        }
+
+        Ok(CreatedThread {
+            conversation_id: format!("discord:{}:{}", guild_id, thread.id),
+            metadata,
+        })
+    }
+
+    async fn speak(
+        &self,
+        message: &InboundMessage,
+        audio: Vec<u8>,
+        sample_rate: u32,
+    ) -> crate::Result<()> {
+        let guild_id = message
+            .metadata
+            .get("discord_guild_id")
+            .and_then(|v| v.as_u64())
+            .context("voice only works in servers")?;
+        self.voice
+            .speak(GuildId::new(guild_id), audio, sample_rate)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_presence(&self, presence: Presence) -> crate::Result<()> {
+        let shard_manager = self
+            .shard_manager
+            .read()
+            .await
+            .clone()
+            .context("discord not connected")?;
+        let activity = presence.activity.map(|activity| match activity.kind {
+            ActivityKind::Playing => ActivityData::playing(activity.text),
+            ActivityKind::Listening => ActivityData::listening(activity.text),
+            ActivityKind::Watching => ActivityData::watching(activity.text),
+            ActivityKind::Competing => ActivityData::competing(activity.text),
+            ActivityKind::Custom => ActivityData::custom(activity.text),
+        });
+        let status = if presence.idle {
+            OnlineStatus::Idle
+        } else {
+            OnlineStatus::Online
+        };
+        for runner in shard_manager.runners.lock().await.values() {
+            runner.runner_tx.set_presence(activity.clone(), status);
+        }
+        Ok(())
+    }
+
     async fn health_check(&self) -> crate::Result<()> {
         let http = self.get_http().await?;
         http.get_current_user()
@@ -559,6 +792,19 @@ struct Handler {
     permissions: Arc<ArcSwap<DiscordPermissions>>,
     http_slot: Arc<RwLock<Option<Arc<Http>>>>,
     bot_user_id_slot: Arc<RwLock<Option<UserId>>>,
+    voice: Arc<voice::DiscordVoice>,
+    pairing: Option<Arc<PairingStore>>,
+}
+
+impl Handler {
+    /// Whether `user_id` may DM the bot: listed in `dm_allowed_users`, or paired.
+    fn dm_allowed(&self, permissions: &DiscordPermissions, user_id: UserId) -> bool {
+        permissions.dm_allowed_users.contains(&user_id.get())
+            || self
+                .pairing
+                .as_ref()
+                .is_some_and(|pairing| pairing.is_paired("discord", &user_id.to_string()))
+    }
 }
 
 #[async_trait]
@@ -586,13 +832,25 @@ impl EventHandler for Handler {
             return;
         }
 
-        // DM filter: if no guild_id, it's a DM — only allow listed users
-        if message.guild_id.is_none()
-            && (permissions.dm_allowed_users.is_empty()
-                || !permissions
-                    .dm_allowed_users
-                    .contains(&message.author.id.get()))
-        {
+        // DM filter: if no guild_id, it's a DM — only allow listed or paired
+        // users. Others get a pairing code when pairing is on.
+        if message.guild_id.is_none() && !self.dm_allowed(&permissions, message.author.id) {
+            let request = self.pairing.as_ref().and_then(|pairing| {
+                pairing.request(
+                    "discord",
+                    &message.author.id.to_string(),
+                    &message.author.name,
+                    &format!("dm:{}", message.author.id),
+                )
+            });
+            if let Some(request) = request
+                && let Err(error) = message
+                    .channel_id
+                    .say(&ctx.http, request.instructions())
+                    .await
+            {
+                tracing::warn!(%error, "failed to send discord pairing code");
+            }
             return;
         }
 
@@ -624,6 +882,13 @@ impl EventHandler for Handler {
             }
         }
 
+        if permissions.voice
+            && let Some(command) = voice::parse_command(&message.content)
+        {
+            self.handle_voice_command(&ctx, &message, command).await;
+            return;
+        }
+
         let inbound = InboundMessage {
             id: message.id.to_string(),
             source: "discord".into(),
@@ -650,32 +915,41 @@ impl EventHandler for Handler {
             _ => return, // Only handle component interactions
         };
 
-        // Acknowledge the interaction immediately to prevent "This interaction failed" in the UI.
-        // We use Defer to indicate we've received it and might edit the message soon.
-        if let Err(error) = component
-            .create_response(
-                &ctx.http,
-                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
-            )
-            .await
-        {
-            tracing::warn!(%error, "failed to acknowledge interaction");
-        }
-
         let user = &component.user;
         let permissions = self.permissions.load();
 
-        if component.guild_id.is_none()
-            && (permissions.dm_allowed_users.is_empty()
-                || !permissions.dm_allowed_users.contains(&user.id.get()))
-        {
-            return;
+        let dm_denied = component.guild_id.is_none() && !self.dm_allowed(&permissions, user.id);
+        let guild_denied = permissions
+            .guild_filter
+            .as_ref()
+            .zip(component.guild_id)
+            .is_some_and(|(filter, guild_id)| !filter.contains(&guild_id.get()));
+        let permitted = !dm_denied && !guild_denied;
+        let choice = if permitted {
+            picked_choice(&component)
+        } else {
+            None
+        };
+
+        // Acknowledge the interaction immediately to prevent "This interaction failed" in the UI.
+        // A pick on a choices prompt retires the prompt in the same response so it can't be
+        // answered twice; anything else is deferred in case we edit the message soon.
+        let acknowledgement = match &choice {
+            Some((_, label)) => CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "{}\n\n**{}** picked: {label}",
+                        component.message.content, user.name
+                    ))
+                    .components(Vec::new()),
+            ),
+            None => CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+        };
+        if let Err(error) = component.create_response(&ctx.http, acknowledgement).await {
+            tracing::warn!(%error, "failed to acknowledge interaction");
         }
 
-        if let Some(filter) = &permissions.guild_filter
-            && let Some(guild_id) = component.guild_id
-            && !filter.contains(&guild_id.get())
-        {
+        if !permitted {
             return;
         }
 
@@ -684,17 +958,24 @@ impl EventHandler for Handler {
             None => format!("discord:dm:{}", user.id),
         };
 
-        let values = match &component.data.kind {
-            serenity::all::ComponentInteractionDataKind::StringSelect { values } => values.clone(),
-            _ => Vec::new(),
-        };
-
-        let content = MessageContent::Interaction {
-            action_id: component.data.custom_id.clone(),
-            block_id: None,
-            values,
-            label: None,
-            message_ts: Some(component.message.id.get().to_string()),
+        let content = match choice {
+            Some((value, label)) => MessageContent::Interaction {
+                action_id: crate::CHOICE_ACTION_ID.into(),
+                block_id: None,
+                values: vec![value],
+                label: Some(label),
+                message_ts: Some(component.message.id.get().to_string()),
+            },
+            None => MessageContent::Interaction {
+                action_id: component.data.custom_id.clone(),
+                block_id: None,
+                values: match &component.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => values.clone(),
+                    _ => Vec::new(),
+                },
+                label: None,
+                message_ts: Some(component.message.id.get().to_string()),
+            },
         };
 
         let mut metadata = HashMap::new();
@@ -710,6 +991,14 @@ impl EventHandler for Handler {
             "discord_mentions_or_replies_to_bot".into(),
             serde_json::Value::Bool(true),
         );
+        metadata.insert(
+            "discord_interaction_id".into(),
+            serde_json::Value::String(component.id.to_string()),
+        );
+        metadata.insert(
+            "discord_custom_id".into(),
+            serde_json::Value::String(component.data.custom_id.clone()),
+        );
         if let Some(guild_id) = component.guild_id {
             metadata.insert(
                 "discord_guild_id".into(),
@@ -746,6 +1035,169 @@ impl EventHandler for Handler {
             );
         }
     }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        // Our own confirmation reactions come back through the gateway too.
+        let bot_user_id = *self.bot_user_id_slot.read().await;
+        if bot_user_id == Some(user_id) {
+            return;
+        }
+
+        let permissions = self.permissions.load();
+
+        if reaction.guild_id.is_none() && !self.dm_allowed(&permissions, user_id) {
+            return;
+        }
+
+        if let Some(filter) = &permissions.guild_filter
+            && let Some(guild_id) = reaction.guild_id
+            && !filter.contains(&guild_id.get())
+        {
+            return;
+        }
+
+        if let Some(guild_id) = reaction.guild_id
+            && let Some(allowed_channels) = permissions.channel_filter.get(&guild_id.get())
+            && !allowed_channels.is_empty()
+            && !allowed_channels.contains(&reaction.channel_id.get())
+        {
+            let parent_channel_id = reaction
+                .channel_id
+                .to_channel(&ctx.http)
+                .await
+                .ok()
+                .and_then(|channel| channel.guild())
+                .and_then(|channel| channel.parent_id);
+            if !parent_channel_id.is_some_and(|parent| allowed_channels.contains(&parent.get())) {
+                return;
+            }
+        }
+
+        let conversation_id = match reaction.guild_id {
+            Some(guild_id) => format!("discord:{}:{}", guild_id, reaction.channel_id),
+            None => format!("discord:dm:{}", user_id),
+        };
+
+        let target = match reaction.message(&ctx.http).await {
+            Ok(target) => Some(target),
+            Err(error) => {
+                tracing::debug!(%error, "can't fetch reacted discord message");
+                None
+            }
+        };
+        let target_author = target.as_ref().map(|target| {
+            target
+                .author
+                .global_name
+                .clone()
+                .unwrap_or_else(|| target.author.name.clone())
+        });
+        let target_text = target
+            .as_ref()
+            .map(|target| resolve_mentions(&target.content, &target.mentions))
+            .filter(|text| !text.trim().is_empty());
+        let to_bot = target
+            .as_ref()
+            .is_some_and(|target| Some(target.author.id) == bot_user_id);
+
+        let display_name = match &reaction.member {
+            Some(member) => member.display_name().to_string(),
+            None => match user_id.to_user(&ctx.http).await {
+                Ok(user) => user.display_name().to_string(),
+                Err(_) => user_id.to_string(),
+            },
+        };
+        let formatted_author = format!("{} (<@{}>)", display_name, user_id);
+
+        let emoji = reaction.emoji.to_string();
+        let content = MessageContent::Reaction {
+            emoji: emoji.clone(),
+            message_id: reaction.message_id.to_string(),
+            target_author,
+            target_text,
+            to_bot,
+        };
+
+        // `discord_message_id` is the reacted message, so a reaction sent in
+        // response lands on it.
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "discord_channel_id".into(),
+            reaction.channel_id.get().into(),
+        );
+        metadata.insert(
+            "discord_message_id".into(),
+            reaction.message_id.get().into(),
+        );
+        if let Some(guild_id) = reaction.guild_id {
+            metadata.insert("discord_guild_id".into(), guild_id.get().into());
+        }
+        metadata.insert("discord_user_id".into(), user_id.get().into());
+        metadata.insert("sender_id".into(), user_id.get().into());
+        metadata.insert("sender_display_name".into(), display_name.into());
+
+        let inbound = InboundMessage {
+            id: format!("{}:{}:{}", reaction.message_id, user_id, emoji),
+            source: "discord".into(),
+            conversation_id,
+            sender_id: user_id.to_string(),
+            agent_id: None,
+            content,
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(formatted_author),
+        };
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send inbound reaction from Discord (receiver dropped)"
+            );
+        }
+    }
+}
+
+impl Handler {
+    /// Run a `/voice` command and answer it in the channel it was typed in.
+    async fn handle_voice_command(
+        &self,
+        ctx: &Context,
+        message: &Message,
+        command: voice::VoiceCommand,
+    ) {
+        let reply = match command {
+            voice::VoiceCommand::Join => self
+                .voice
+                .join(ctx, message, self.inbound_tx.clone())
+                .await
+                .map(|channel_id| format!("Listening in <#{channel_id}>.")),
+            voice::VoiceCommand::Leave => match message.guild_id {
+                Some(guild_id) => self
+                    .voice
+                    .leave(guild_id)
+                    .await
+                    .map(|()| "Left the voice channel.".to_string()),
+                None => Err(anyhow::anyhow!("voice only works in servers")),
+            },
+        };
+        let reply = reply.unwrap_or_else(|error| {
+            tracing::debug!(%error, "discord voice command failed");
+            format!("Can't do that: {error}")
+        });
+        message
+            .channel_id
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(reply)
+                    .reference_message(message),
+            )
+            .await
+            .ok();
+    }
 }
 
 fn is_mention_or_reply_to_bot(message: &Message, bot_user_id: Option<UserId>) -> bool {
@@ -972,6 +1424,9 @@ fn build_embed(card: &crate::Card) -> CreateEmbed {
     if let Some(footer) = &card.footer {
         embed = embed.footer(CreateEmbedFooter::new(footer));
     }
+    if let Some(image_url) = &card.image_url {
+        embed = embed.image(image_url);
+    }
 
     for (i, field) in card.fields.iter().enumerate() {
         if i >= 25 {
@@ -1045,6 +1500,205 @@ fn build_action_row(elements: &crate::InteractiveElements) -> CreateActionRow {
     }
 }
 
+/// Post a `Rich` response, with its embed on the last chunk.
+async fn send_rich(
+    http: &Http,
+    channel_id: ChannelId,
+    text: &str,
+    card: &crate::Card,
+    reply_to: Option<MessageId>,
+) -> anyhow::Result<()> {
+    let chunks = split_message(text, 2000);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut msg = CreateMessage::new();
+        if !chunk.is_empty() {
+            msg = msg.content(chunk);
+        }
+        if i == chunks.len() - 1 {
+            msg = msg.embed(build_embed(card));
+        }
+        if i == 0
+            && let Some(reply_message_id) = reply_to
+        {
+            msg = msg.reference_message((channel_id, reply_message_id));
+        }
+        channel_id
+            .send_message(http, msg)
+            .await
+            .context("failed to send discord rich message")?;
+    }
+    Ok(())
+}
+
+/// Discord's limit on files in one message.
+const MAX_FILES_PER_MESSAGE: usize = 10;
+
+/// Post an `Attachments` response. The files go on the last text chunk,
+/// at most [`MAX_FILES_PER_MESSAGE`] to a message.
+async fn send_attachments(
+    http: &Http,
+    channel_id: ChannelId,
+    text: &str,
+    files: Vec<crate::OutboundFile>,
+    reply_to: Option<MessageId>,
+) -> anyhow::Result<()> {
+    let mut messages = split_message(text, 2000)
+        .into_iter()
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| CreateMessage::new().content(chunk))
+        .collect::<Vec<_>>();
+    let mut files = files.into_iter().peekable();
+    let mut first_batch = true;
+    while files.peek().is_some() {
+        let message = if first_batch && let Some(last) = messages.pop() {
+            last
+        } else {
+            CreateMessage::new()
+        };
+        first_batch = false;
+        let batch = files.by_ref().take(MAX_FILES_PER_MESSAGE);
+        messages.push(
+            message.add_files(batch.map(|file| CreateAttachment::bytes(file.data, file.filename))),
+        );
+    }
+
+    for (i, mut msg) in messages.into_iter().enumerate() {
+        if i == 0
+            && let Some(reply_message_id) = reply_to
+        {
+            msg = msg.reference_message((channel_id, reply_message_id));
+        }
+        channel_id
+            .send_message(http, msg)
+            .await
+            .context("failed to send discord attachments")?;
+    }
+    Ok(())
+}
+
+/// Post a `Choices` prompt, with its options on the last chunk.
+async fn send_choices(
+    http: &Http,
+    channel_id: ChannelId,
+    prompt: &str,
+    options: &[crate::ChoiceOption],
+    reply_to: Option<MessageId>,
+) -> anyhow::Result<()> {
+    let chunks = split_message(prompt, 2000);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut msg = CreateMessage::new();
+        if !chunk.is_empty() {
+            msg = msg.content(chunk);
+        }
+        if i == chunks.len() - 1 {
+            msg = msg.components(build_choice_rows(options));
+        }
+        if i == 0
+            && let Some(reply_message_id) = reply_to
+        {
+            msg = msg.reference_message((channel_id, reply_message_id));
+        }
+        channel_id
+            .send_message(http, msg)
+            .await
+            .context("failed to send discord choices")?;
+    }
+    Ok(())
+}
+
+/// A row of buttons for up to five plain options, a select menu otherwise.
+/// Button custom IDs carry the option's value after `choice:`.
+fn build_choice_rows(options: &[crate::ChoiceOption]) -> Vec<CreateActionRow> {
+    if options.is_empty() {
+        return Vec::new();
+    }
+    if options.len() <= 5 && options.iter().all(|option| option.description.is_none()) {
+        let buttons = options
+            .iter()
+            .map(|option| {
+                let custom_id = format!("{}:{}", crate::CHOICE_ACTION_ID, option.value());
+                // Discord limits: custom_id max 100 characters, label max 80.
+                CreateButton::new(clip(&custom_id, 100))
+                    .label(clip(&option.label, 80))
+                    .style(ButtonStyle::Secondary)
+            })
+            .collect();
+        return vec![CreateActionRow::Buttons(buttons)];
+    }
+
+    // Discord limits: 25 options per menu, 100 characters per label, value and description.
+    let menu_options = options
+        .iter()
+        .take(25)
+        .map(|option| {
+            let mut menu_option =
+                CreateSelectMenuOption::new(clip(&option.label, 100), clip(option.value(), 100));
+            if let Some(description) = &option.description {
+                menu_option = menu_option.description(clip(description, 100));
+            }
+            menu_option
+        })
+        .collect();
+    let menu = CreateSelectMenu::new(
+        crate::CHOICE_ACTION_ID,
+        CreateSelectMenuKind::String {
+            options: menu_options,
+        },
+    )
+    .placeholder("Choose an option");
+    vec![CreateActionRow::SelectMenu(menu)]
+}
+
+/// The value and label of the option picked on a `Choices` prompt, or
+/// `None` for any other component.
+fn picked_choice(component: &ComponentInteraction) -> Option<(String, String)> {
+    let custom_id = component.data.custom_id.as_str();
+    let value = match &component.data.kind {
+        ComponentInteractionDataKind::StringSelect { values }
+            if custom_id == crate::CHOICE_ACTION_ID =>
+        {
+            values.first()?.clone()
+        }
+        ComponentInteractionDataKind::Button => custom_id
+            .strip_prefix(crate::CHOICE_ACTION_ID)?
+            .strip_prefix(':')?
+            .to_string(),
+        _ => return None,
+    };
+    let label =
+        choice_label(&component.message, custom_id, &value).unwrap_or_else(|| value.clone());
+    Some((value, label))
+}
+
+/// Look up the label of a picked option on the prompt message itself.
+fn choice_label(message: &Message, custom_id: &str, value: &str) -> Option<String> {
+    message
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::Button(button) => match &button.data {
+                ButtonKind::NonLink { custom_id: id, .. } if id == custom_id => {
+                    button.label.clone()
+                }
+                _ => None,
+            },
+            ActionRowComponent::SelectMenu(menu)
+                if menu.custom_id.as_deref() == Some(custom_id) =>
+            {
+                menu.options
+                    .iter()
+                    .find(|option| option.value == value)
+                    .map(|option| option.label.clone())
+            }
+            _ => None,
+        })
+}
+
+fn clip(text: &str, max_len: usize) -> &str {
+    &text[..text.floor_char_boundary(max_len)]
+}
+
 fn build_poll(
     poll: &crate::Poll,
 ) -> serenity::builder::CreatePoll<serenity::builder::create_poll::Ready> {
@@ -1117,6 +1771,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_choice_rows() {
+        let option = |label: &str, description: Option<&str>| crate::ChoiceOption {
+            label: label.into(),
+            value: None,
+            description: description.map(Into::into),
+        };
+
+        let short = vec![option("Yes", None), option("No", None)];
+        match build_choice_rows(&short).as_slice() {
+            [CreateActionRow::Buttons(buttons)] => assert_eq!(buttons.len(), 2),
+            _ => panic!("Expected one row of buttons"),
+        }
+
+        let described = vec![
+            option("Deploy", Some("Ship it to production")),
+            option("Wait", None),
+        ];
+        assert!(matches!(
+            build_choice_rows(&described).as_slice(),
+            [CreateActionRow::SelectMenu(_)]
+        ));
+
+        let long: Vec<_> = (0..30)
+            .map(|i| option(&format!("Option {i}"), None))
+            .collect();
+        assert!(matches!(
+            build_choice_rows(&long).as_slice(),
+            [CreateActionRow::SelectMenu(_)]
+        ));
+    }
+
     #[test]
     fn test_build_poll_limits() {
         let mut poll = Poll {
@@ -0,0 +1,380 @@
+//! Discord voice channels: listening to members and speaking replies.
+//!
+//! `/voice join` in a server text channel brings the bot into the voice
+//! channel the sender is in, and `/voice leave` takes it out. While it's
+//! connected, each member's speech is cut into utterances and posted to the
+//! text channel's conversation as a WAV attachment marked with
+//! [`SPEAK_REPLIES_KEY`], so the channel transcribes it like any voice note
+//! with the `routing.voice` model. Replies are posted in the text channel as
+//! usual and spoken in the voice channel with the agent's TTS model. Needs
+//! `voice = true` under `[messaging.discord]`.
+
+use crate::agent::attachment_cache::data_url;
+use crate::agent::voice::{SPEAK_REPLIES_KEY, encode_wav};
+use crate::{Attachment, InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use serenity::all::{ChannelId, Context, GuildId, Http, Message, UserId};
+use songbird::Songbird;
+use songbird::events::{CoreEvent, Event, EventContext, EventHandler as VoiceEventHandler};
+use tokio::sync::mpsc;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Rate utterances are sent at. Discord decodes at 48 kHz; a third of that
+/// is plenty for speech.
+const UTTERANCE_SAMPLE_RATE: u32 = 16_000;
+
+/// Length of one voice tick.
+const TICK_MS: u32 = 20;
+
+/// Quiet time that ends an utterance.
+const SILENCE_MS: u32 = 800;
+
+/// Utterances with less speech than this are dropped as noise.
+const MIN_SPEECH_MS: u32 = 300;
+
+/// Longest utterance before it's cut and sent.
+const MAX_UTTERANCE_MS: u32 = 30_000;
+
+/// A `/voice` command typed in a text channel.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum VoiceCommand {
+    Join,
+    Leave,
+}
+
+pub(super) fn parse_command(text: &str) -> Option<VoiceCommand> {
+    let mut words = text.split_whitespace();
+    if words.next()? != "/voice" {
+        return None;
+    }
+    let command = match words.next()? {
+        "join" => VoiceCommand::Join,
+        "leave" => VoiceCommand::Leave,
+        _ => return None,
+    };
+    words.next().is_none().then_some(command)
+}
+
+/// Voice connections, one per server.
+pub(super) struct DiscordVoice {
+    songbird: Arc<Songbird>,
+}
+
+impl DiscordVoice {
+    pub(super) fn new() -> Self {
+        let config = songbird::Config::default().decode_mode(songbird::driver::DecodeMode::Decode);
+        Self {
+            songbird: Songbird::serenity_from_config(config),
+        }
+    }
+
+    /// The voice manager to register with the serenity client.
+    pub(super) fn songbird(&self) -> Arc<Songbird> {
+        self.songbird.clone()
+    }
+
+    /// Join the voice channel `message`'s author is in. Utterances are sent
+    /// to the conversation of the channel `message` was typed in.
+    pub(super) async fn join(
+        &self,
+        ctx: &Context,
+        message: &Message,
+        inbound_tx: mpsc::Sender<InboundMessage>,
+    ) -> anyhow::Result<ChannelId> {
+        let guild_id = message.guild_id.context("voice only works in servers")?;
+        let voice_channel_id = ctx
+            .cache
+            .guild(guild_id)
+            .and_then(|guild| {
+                guild
+                    .voice_states
+                    .get(&message.author.id)
+                    .and_then(|state| state.channel_id)
+            })
+            .context("join a voice channel first")?;
+
+        let call = self
+            .songbird
+            .join(guild_id, voice_channel_id)
+            .await
+            .context("failed to join voice channel")?;
+        let listener = Listener(Arc::new(ListenerState {
+            guild_id,
+            text_channel_id: message.channel_id,
+            voice_channel_id,
+            http: ctx.http.clone(),
+            inbound_tx,
+            speakers: Mutex::new(Speakers::default()),
+        }));
+
+        let mut call = call.lock().await;
+        // Rejoining moves the bot, so drop the previous channel's listener.
+        call.remove_all_global_events();
+        call.add_global_event(CoreEvent::SpeakingStateUpdate.into(), listener.clone());
+        call.add_global_event(CoreEvent::VoiceTick.into(), listener);
+        Ok(voice_channel_id)
+    }
+
+    pub(super) async fn leave(&self, guild_id: GuildId) -> anyhow::Result<()> {
+        self.songbird
+            .remove(guild_id)
+            .await
+            .context("failed to leave voice channel")
+    }
+
+    /// Queue 16-bit mono PCM for playback in the server's voice channel.
+    pub(super) async fn speak(
+        &self,
+        guild_id: GuildId,
+        audio: Vec<u8>,
+        sample_rate: u32,
+    ) -> anyhow::Result<()> {
+        let call = self
+            .songbird
+            .get(guild_id)
+            .context("not in a voice channel in this server")?;
+        let samples = audio
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect::<Vec<_>>();
+        let input = songbird::input::Input::from(encode_wav(&samples, sample_rate));
+        call.lock().await.enqueue_input(input).await;
+        Ok(())
+    }
+}
+
+/// Receives a call's voice events and turns speech into messages.
+#[derive(Clone)]
+struct Listener(Arc<ListenerState>);
+
+struct ListenerState {
+    guild_id: GuildId,
+    text_channel_id: ChannelId,
+    voice_channel_id: ChannelId,
+    http: Arc<Http>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    speakers: Mutex<Speakers>,
+}
+
+#[derive(Default)]
+struct Speakers {
+    /// Who each audio stream belongs to.
+    users: HashMap<u32, UserId>,
+    segmenter: Segmenter,
+}
+
+#[async_trait]
+impl VoiceEventHandler for Listener {
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        match event {
+            EventContext::SpeakingStateUpdate(speaking) => {
+                if let Some(user_id) = speaking.user_id {
+                    let mut speakers = self.0.speakers.lock().expect("speakers lock poisoned");
+                    speakers.users.insert(speaking.ssrc, UserId::new(user_id.0));
+                }
+            }
+            EventContext::VoiceTick(tick) => {
+                let finished = {
+                    let mut speakers = self.0.speakers.lock().expect("speakers lock poisoned");
+                    let voiced = tick
+                        .speaking
+                        .iter()
+                        .filter_map(|(ssrc, data)| Some((*ssrc, data.decoded_voice.as_deref()?)));
+                    let utterances = speakers.segmenter.tick(voiced);
+                    utterances
+                        .into_iter()
+                        .filter_map(|(ssrc, samples)| Some((*speakers.users.get(&ssrc)?, samples)))
+                        .collect::<Vec<_>>()
+                };
+                for (user_id, samples) in finished {
+                    tokio::spawn(self.clone().send_utterance(user_id, samples));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Listener {
+    async fn send_utterance(self, user_id: UserId, samples: Vec<i16>) {
+        let state = &self.0;
+        let member = match state.guild_id.member(&state.http, user_id).await {
+            Ok(member) => member,
+            Err(error) => {
+                tracing::debug!(%error, %user_id, "can't resolve discord voice speaker");
+                return;
+            }
+        };
+        if member.user.bot {
+            return;
+        }
+        let display_name = member.display_name().to_string();
+
+        let wav = encode_wav(&samples, UTTERANCE_SAMPLE_RATE);
+        let attachment = Attachment {
+            filename: "voice.wav".into(),
+            mime_type: "audio/wav".into(),
+            url: data_url("audio/wav", &wav),
+            size_bytes: Some(wav.len() as u64),
+        };
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "discord_channel_id".into(),
+            state.text_channel_id.get().into(),
+        );
+        metadata.insert("discord_guild_id".into(), state.guild_id.get().into());
+        metadata.insert(
+            "discord_voice_channel_id".into(),
+            state.voice_channel_id.get().into(),
+        );
+        metadata.insert("discord_user_id".into(), user_id.get().into());
+        metadata.insert("sender_id".into(), user_id.get().into());
+        metadata.insert("sender_display_name".into(), display_name.clone().into());
+        metadata.insert(
+            "discord_user_mention".into(),
+            serde_json::Value::String(format!("<@{user_id}>")),
+        );
+        // Speech in a voice channel the bot was asked into is addressed to it.
+        metadata.insert("discord_mentions_or_replies_to_bot".into(), true.into());
+        metadata.insert(SPEAK_REPLIES_KEY.into(), true.into());
+
+        let inbound = InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "discord".into(),
+            conversation_id: format!("discord:{}:{}", state.guild_id, state.text_channel_id),
+            sender_id: user_id.to_string(),
+            agent_id: None,
+            content: MessageContent::Media {
+                text: None,
+                attachments: vec![attachment],
+            },
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(format!("{display_name} (<@{user_id}>)")),
+        };
+
+        if let Err(error) = state.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send inbound voice message from Discord (receiver dropped)"
+            );
+        }
+    }
+}
+
+/// Cuts each speaker's audio into utterances. Discord only sends audio while
+/// someone is talking, so a speaker going quiet for [`SILENCE_MS`] ends
+/// their utterance.
+#[derive(Default)]
+struct Segmenter {
+    utterances: HashMap<u32, Utterance>,
+}
+
+#[derive(Default)]
+struct Utterance {
+    /// 16 kHz mono.
+    samples: Vec<i16>,
+    speech_ms: u32,
+    quiet_ms: u32,
+}
+
+impl Segmenter {
+    /// Feed one tick of decoded 48 kHz stereo audio per audio stream that
+    /// sent any. Returns the utterances that ended, as 16 kHz mono.
+    fn tick<'a>(
+        &mut self,
+        voiced: impl IntoIterator<Item = (u32, &'a [i16])>,
+    ) -> Vec<(u32, Vec<i16>)> {
+        let mut heard = HashSet::new();
+        for (ssrc, stereo) in voiced {
+            let utterance = self.utterances.entry(ssrc).or_default();
+            utterance.samples.extend(downsample(stereo));
+            utterance.speech_ms += TICK_MS;
+            utterance.quiet_ms = 0;
+            heard.insert(ssrc);
+        }
+
+        let mut finished = Vec::new();
+        self.utterances.retain(|ssrc, utterance| {
+            if !heard.contains(ssrc) {
+                utterance.quiet_ms += TICK_MS;
+            }
+            let ended = utterance.quiet_ms >= SILENCE_MS || utterance.speech_ms >= MAX_UTTERANCE_MS;
+            if ended && utterance.speech_ms >= MIN_SPEECH_MS {
+                finished.push((*ssrc, std::mem::take(&mut utterance.samples)));
+            }
+            !ended
+        });
+        finished
+    }
+}
+
+/// 48 kHz interleaved stereo to 16 kHz mono, averaging each run of three
+/// frames.
+fn downsample(stereo: &[i16]) -> impl Iterator<Item = i16> + '_ {
+    stereo.chunks(6).map(|group| {
+        let sum: i32 = group.iter().map(|&sample| i32::from(sample)).sum();
+        (sum / group.len() as i32) as i16
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One 20 ms tick of 48 kHz stereo.
+    fn frame(amplitude: i16) -> Vec<i16> {
+        vec![amplitude; 48 * TICK_MS as usize * 2]
+    }
+
+    #[test]
+    fn voice_commands_parse() {
+        assert_eq!(parse_command("/voice join"), Some(VoiceCommand::Join));
+        assert_eq!(
+            parse_command("  /voice   leave "),
+            Some(VoiceCommand::Leave)
+        );
+        assert_eq!(parse_command("/voice"), None);
+        assert_eq!(parse_command("/voice join now"), None);
+        assert_eq!(parse_command("join voice"), None);
+    }
+
+    #[test]
+    fn utterances_end_after_silence() {
+        let mut segmenter = Segmenter::default();
+        let speech = frame(1_000);
+
+        for _ in 0..25 {
+            assert!(segmenter.tick([(7, speech.as_slice())]).is_empty());
+        }
+        let quiet_ticks = (SILENCE_MS / TICK_MS) as usize;
+        for _ in 0..quiet_ticks - 1 {
+            assert!(segmenter.tick([]).is_empty());
+        }
+        let finished = segmenter.tick([]);
+        let [(ssrc, samples)] = finished.as_slice() else {
+            panic!("expected one utterance, got {}", finished.len());
+        };
+        assert_eq!(*ssrc, 7);
+        // 500 ms at 16 kHz.
+        assert_eq!(samples.len(), 8_000);
+        assert!(samples.iter().all(|&sample| sample == 1_000));
+    }
+
+    #[test]
+    fn short_noises_are_dropped() {
+        let mut segmenter = Segmenter::default();
+        let click = frame(5_000);
+        segmenter.tick([(3, click.as_slice())]);
+        for _ in 0..SILENCE_MS / TICK_MS {
+            assert!(segmenter.tick([]).is_empty());
+        }
+        assert!(segmenter.utterances.is_empty());
+    }
+}
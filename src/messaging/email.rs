@@ -0,0 +1,726 @@
+//! Email messaging adapter: IMAP polling inbound, SMTP outbound.
+//!
+//! The inbox is polled for unseen mail on an interval. Each new message is
+//! marked seen and turned into an inbound message, with its attachments
+//! carried inline as `data:` URLs. A mail thread maps to one conversation:
+//! the conversation is keyed by the thread's root `Message-ID`, taken from
+//! the first `References` entry, else `In-Reply-To`, else the message's own
+//! ID. Replies go out over SMTP with `In-Reply-To` and `References` set, so
+//! they land in the same thread in the sender's client.
+
+use crate::agent::attachment_cache::data_url;
+use crate::config::EmailPermissions;
+use crate::messaging::format;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{Attachment, InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use futures::TryStreamExt as _;
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport as _, Tokio1Executor};
+use mail_parser::{HeaderValue, MessageParser, MimeHeaders as _};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::ServerName;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
+
+/// Attachments larger than this are listed by name instead of carried inline.
+const MAX_INLINE_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
+
+/// Email adapter state.
+pub struct EmailAdapter {
+    imap_host: String,
+    imap_port: u16,
+    username: String,
+    password: String,
+    mailbox: String,
+    from: Mailbox,
+    poll_interval: Duration,
+    permissions: Arc<ArcSwap<EmailPermissions>>,
+    smtp: AsyncSmtpTransport<Tokio1Executor>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+impl EmailAdapter {
+    pub fn new(
+        config: &crate::config::EmailConfig,
+        permissions: Arc<ArcSwap<EmailPermissions>>,
+    ) -> anyhow::Result<Self> {
+        let from = Mailbox::new(
+            config.from_name.clone(),
+            config
+                .from_address
+                .parse()
+                .with_context(|| format!("invalid from address '{}'", config.from_address))?,
+        );
+        // Port 465 speaks TLS from the start; anything else upgrades with STARTTLS.
+        let smtp = if config.smtp_port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+        }
+        .context("failed to configure smtp transport")?
+        .port(config.smtp_port)
+        .credentials(Credentials::new(
+            config.username.clone(),
+            config.password.clone(),
+        ))
+        .build();
+
+        Ok(Self {
+            imap_host: config.imap_host.clone(),
+            imap_port: config.imap_port,
+            username: config.username.clone(),
+            password: config.password.clone(),
+            mailbox: config.mailbox.clone(),
+            from,
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+            permissions,
+            smtp,
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    async fn send(
+        &self,
+        reply: Reply,
+        text: &str,
+        file: Option<OutgoingFile>,
+    ) -> anyhow::Result<()> {
+        let mut builder = lettre::Message::builder()
+            .from(self.from.clone())
+            .to(reply.to.parse().context("invalid recipient address")?)
+            .subject(reply.subject);
+        if let Some(in_reply_to) = reply.in_reply_to {
+            builder = builder.in_reply_to(format!("<{in_reply_to}>"));
+        }
+        if !reply.references.is_empty() {
+            let references = reply
+                .references
+                .iter()
+                .map(|id| format!("<{id}>"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            builder = builder.references(references);
+        }
+
+        let body = format::plain_text(text);
+        let email = match file {
+            Some(file) => {
+                let content_type = ContentType::parse(&file.mime_type)
+                    .or_else(|_| ContentType::parse("application/octet-stream"))
+                    .context("invalid attachment content type")?;
+                builder.multipart(
+                    MultiPart::mixed()
+                        .singlepart(SinglePart::plain(body))
+                        .singlepart(
+                            lettre::message::Attachment::new(file.filename)
+                                .body(file.data, content_type),
+                        ),
+                )
+            }
+            None => builder.body(body),
+        }
+        .context("failed to build email")?;
+
+        self.smtp
+            .send(email)
+            .await
+            .context("failed to send email")?;
+        Ok(())
+    }
+}
+
+/// Addressing and thread headers for an outgoing mail.
+#[derive(Debug)]
+struct Reply {
+    to: String,
+    subject: String,
+    in_reply_to: Option<String>,
+    references: Vec<String>,
+}
+
+struct OutgoingFile {
+    filename: String,
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+impl Messaging for EmailAdapter {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let mailbox = ImapMailbox {
+            host: self.imap_host.clone(),
+            port: self.imap_port,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            mailbox: self.mailbox.clone(),
+        };
+        let own_address = self.from.email.to_string();
+        let permissions = self.permissions.clone();
+        let poll_interval = self.poll_interval;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    _ = interval.tick() => {}
+                }
+
+                let raw_messages = match mailbox.fetch_unseen().await {
+                    Ok(raw_messages) => raw_messages,
+                    Err(error) => {
+                        tracing::warn!(host = %mailbox.host, %error, "email poll failed");
+                        continue;
+                    }
+                };
+                for raw in raw_messages {
+                    let Some(inbound) = to_inbound(&raw, &own_address, &permissions.load()) else {
+                        continue;
+                    };
+                    if inbound_tx.send(inbound).await.is_err() {
+                        tracing::warn!(
+                            "failed to send inbound message from email (receiver dropped)"
+                        );
+                        return;
+                    }
+                }
+            }
+
+            tracing::info!("email poll loop shutting down");
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let reply = reply_headers(message).context("missing email reply headers in metadata")?;
+
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                self.send(reply, &text, None).await?;
+            }
+            OutboundResponse::Choices { prompt, options } => {
+                let text = crate::choices_text(&prompt, &options);
+                self.send(reply, &text, None).await?;
+            }
+            OutboundResponse::Rich { text, card } => {
+                let text = crate::rich_text(&text, &card);
+                self.send(reply, &text, None).await?;
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => {
+                let text = caption.unwrap_or_else(|| format!("Attached: {filename}"));
+                let file = OutgoingFile {
+                    filename,
+                    mime_type,
+                    data,
+                };
+                self.send(reply, &text, Some(file)).await?;
+            }
+            // Mail can't be edited once sent; the final text arrives as a
+            // Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd => {}
+            OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => {}
+            OutboundResponse::Attachments { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            let reply = Reply {
+                to: target.to_string(),
+                subject: format!(
+                    "Message from {}",
+                    self.from.name.as_deref().unwrap_or("spacebot")
+                ),
+                in_reply_to: None,
+                references: Vec::new(),
+            };
+            self.send(reply, &text, None).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        let reachable = self
+            .smtp
+            .test_connection()
+            .await
+            .context("failed to reach smtp server")?;
+        if !reachable {
+            return Err(anyhow::anyhow!("smtp server not reachable").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("email adapter shut down");
+        Ok(())
+    }
+}
+
+/// IMAP connection settings for the polled mailbox.
+struct ImapMailbox {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    mailbox: String,
+}
+
+impl ImapMailbox {
+    /// Fetch every unseen message and mark it seen. A fresh session is
+    /// opened per poll, so a dropped connection costs one interval at most.
+    async fn fetch_unseen(&self) -> anyhow::Result<Vec<Vec<u8>>> {
+        let mut roots = tokio_rustls::rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls_config = tokio_rustls::rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name =
+            ServerName::try_from(self.host.clone()).context("invalid imap host name")?;
+
+        let tcp = tokio::net::TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .context("failed to connect to imap server")?;
+        let tls = TlsConnector::from(Arc::new(tls_config))
+            .connect(server_name, tcp)
+            .await
+            .context("imap tls handshake failed")?;
+
+        let mut client = async_imap::Client::new(tls);
+        client
+            .read_response()
+            .await
+            .context("no imap greeting")?
+            .context("failed to read imap greeting")?;
+        let mut session = client
+            .login(&self.username, &self.password)
+            .await
+            .map_err(|(error, _)| error)
+            .context("imap login failed")?;
+        session
+            .select(&self.mailbox)
+            .await
+            .with_context(|| format!("failed to select mailbox '{}'", self.mailbox))?;
+
+        let uids = session
+            .uid_search("UNSEEN")
+            .await
+            .context("imap search failed")?;
+        let mut raw_messages = Vec::new();
+        if !uids.is_empty() {
+            let mut uids: Vec<u32> = uids.into_iter().collect();
+            uids.sort_unstable();
+            let uid_set = uids
+                .iter()
+                .map(|uid| uid.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            // BODY.PEEK leaves the flags alone; messages are marked seen
+            // only once they've all been read.
+            let fetches: Vec<_> = session
+                .uid_fetch(&uid_set, "BODY.PEEK[]")
+                .await
+                .context("imap fetch failed")?
+                .try_collect()
+                .await
+                .context("imap fetch failed")?;
+            raw_messages.extend(
+                fetches
+                    .iter()
+                    .filter_map(|fetch| fetch.body().map(<[u8]>::to_vec)),
+            );
+
+            let _: Vec<_> = session
+                .uid_store(&uid_set, "+FLAGS (\\Seen)")
+                .await
+                .context("failed to mark messages seen")?
+                .try_collect()
+                .await
+                .context("failed to mark messages seen")?;
+        }
+
+        session.logout().await.ok();
+        Ok(raw_messages)
+    }
+}
+
+/// Parse a raw email into an inbound message, applying the sender allowlist
+/// and dropping our own and automated mail. Returns `None` for everything
+/// that shouldn't reach an agent.
+fn to_inbound(
+    raw: &[u8],
+    own_address: &str,
+    permissions: &EmailPermissions,
+) -> Option<InboundMessage> {
+    let email = MessageParser::default().parse(raw)?;
+    let sender = email.from()?.first()?;
+    let address = sender.address()?.to_lowercase();
+    if address.eq_ignore_ascii_case(own_address) || !sender_allowed(permissions, &address) {
+        return None;
+    }
+    // Auto-replies and list mail would otherwise answer each other forever.
+    let automated = email
+        .header_raw("Auto-Submitted")
+        .is_some_and(|value| !value.trim().eq_ignore_ascii_case("no"))
+        || email.header_raw("Precedence").is_some_and(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "bulk" | "list" | "junk"
+            )
+        });
+    if automated {
+        tracing::debug!(%address, "skipping automated email");
+        return None;
+    }
+
+    let message_id = email
+        .message_id()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}@spacebot", uuid::Uuid::new_v4()));
+    let in_reply_to = header_ids(email.in_reply_to()).into_iter().next();
+    let mut references = header_ids(email.references());
+    let root = thread_root(&message_id, in_reply_to.as_deref(), &references);
+    let subject = email.subject().unwrap_or_default().trim().to_string();
+
+    let body = email
+        .body_text(0)
+        .map(|body| strip_quoted_reply(&body))
+        .unwrap_or_default();
+    // The subject opens a thread; later mails only repeat it.
+    let text = if in_reply_to.is_none() && !subject.is_empty() {
+        format!("Subject: {subject}\n\n{body}")
+    } else {
+        body
+    };
+
+    let mut attachments = Vec::new();
+    let mut skipped = Vec::new();
+    for part in email.attachments() {
+        let filename = part.attachment_name().unwrap_or("unnamed").to_string();
+        let contents = part.contents();
+        if contents.len() > MAX_INLINE_ATTACHMENT_BYTES {
+            skipped.push(format!(
+                "[Attachment too large to process: {filename} ({:.1} MB)]",
+                contents.len() as f64 / (1024.0 * 1024.0)
+            ));
+            continue;
+        }
+        let mime_type = part
+            .content_type()
+            .map(|content_type| match content_type.subtype() {
+                Some(subtype) => format!("{}/{subtype}", content_type.ctype()),
+                None => content_type.ctype().to_string(),
+            })
+            .unwrap_or_else(|| "application/octet-stream".into());
+        attachments.push(Attachment {
+            url: data_url(&mime_type, contents),
+            filename,
+            mime_type,
+            size_bytes: Some(contents.len() as u64),
+        });
+    }
+    let text = if skipped.is_empty() {
+        text
+    } else {
+        format!("{text}\n\n{}", skipped.join("\n"))
+    };
+    if text.trim().is_empty() && attachments.is_empty() {
+        return None;
+    }
+
+    let display_name = sender
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| address.clone());
+    // Replies reference the whole chain, ending with the mail answered.
+    references.push(message_id.clone());
+
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "email_from".into(),
+        serde_json::Value::String(address.clone()),
+    );
+    metadata.insert("email_subject".into(), serde_json::Value::String(subject));
+    metadata.insert(
+        "email_message_id".into(),
+        serde_json::Value::String(message_id.clone()),
+    );
+    metadata.insert("email_references".into(), serde_json::json!(references));
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(display_name.clone()),
+    );
+
+    let content = if attachments.is_empty() {
+        MessageContent::Text(text)
+    } else {
+        MessageContent::Media {
+            text: (!text.trim().is_empty()).then_some(text),
+            attachments,
+        }
+    };
+
+    Some(InboundMessage {
+        id: message_id,
+        source: "email".into(),
+        conversation_id: format!("email:{root}"),
+        sender_id: address,
+        agent_id: None,
+        content,
+        timestamp: email
+            .date()
+            .and_then(|date| chrono::DateTime::from_timestamp(date.to_timestamp(), 0))
+            .unwrap_or_else(chrono::Utc::now),
+        metadata,
+        formatted_author: Some(display_name),
+    })
+}
+
+/// Whether mail from `address` may reach an agent. Entries are full
+/// addresses or `@domain` suffixes.
+fn sender_allowed(permissions: &EmailPermissions, address: &str) -> bool {
+    permissions.allowed_senders.iter().any(|allowed| {
+        let allowed = allowed.trim().to_lowercase();
+        if allowed.starts_with('@') {
+            address.ends_with(&allowed)
+        } else {
+            address == allowed
+        }
+    })
+}
+
+/// Message IDs in an `In-Reply-To` or `References` header, oldest first.
+fn header_ids(value: &HeaderValue) -> Vec<String> {
+    match value {
+        HeaderValue::Text(id) => vec![id.to_string()],
+        HeaderValue::TextList(ids) => ids.iter().map(|id| id.to_string()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The `Message-ID` that started a thread.
+fn thread_root(message_id: &str, in_reply_to: Option<&str>, references: &[String]) -> String {
+    references
+        .first()
+        .map(String::as_str)
+        .or(in_reply_to)
+        .unwrap_or(message_id)
+        .to_string()
+}
+
+/// Headers for replying to an inbound mail, from its metadata.
+fn reply_headers(message: &InboundMessage) -> Option<Reply> {
+    let text = |key: &str| message.metadata.get(key).and_then(|value| value.as_str());
+    let subject = text("email_subject").unwrap_or_default();
+    let references = message
+        .metadata
+        .get("email_references")
+        .and_then(|value| value.as_array())
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(Reply {
+        to: text("email_from")?.to_string(),
+        subject: reply_subject(subject),
+        in_reply_to: text("email_message_id").map(str::to_string),
+        references,
+    })
+}
+
+fn reply_subject(subject: &str) -> String {
+    let subject = subject.trim();
+    if subject.is_empty() {
+        "Re: your message".into()
+    } else if subject
+        .get(..3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("re:"))
+    {
+        subject.to_string()
+    } else {
+        format!("Re: {subject}")
+    }
+}
+
+/// Drop the quoted history most clients append below a reply.
+fn strip_quoted_reply(body: &str) -> String {
+    let mut kept = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        let quote_header = (trimmed.starts_with("On ") && trimmed.ends_with("wrote:"))
+            || trimmed.starts_with("-----Original Message-----");
+        if quote_header || trimmed.starts_with('>') {
+            break;
+        }
+        kept.push(line);
+    }
+    kept.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(allowed: &[&str]) -> EmailPermissions {
+        EmailPermissions {
+            allowed_senders: allowed.iter().map(|sender| sender.to_string()).collect(),
+        }
+    }
+
+    fn raw_email(extra_headers: &str, body: &str) -> Vec<u8> {
+        format!(
+            "From: Alice <alice@example.com>\r\n\
+             To: bot@spacebot.test\r\n\
+             Subject: Quarterly report\r\n\
+             Message-ID: <msg-2@example.com>\r\n\
+             {extra_headers}\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             \r\n\
+             {body}\r\n"
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn replies_thread_into_the_root_conversation() {
+        let raw = raw_email(
+            "In-Reply-To: <msg-1@spacebot.test>\r\n\
+             References: <root@example.com> <msg-1@spacebot.test>\r\n",
+            "Thanks, looks good.\r\n\r\nOn Mon, Bot wrote:\r\n> Here it is",
+        );
+        let inbound =
+            to_inbound(&raw, "bot@spacebot.test", &permissions(&["@example.com"])).unwrap();
+
+        assert_eq!(inbound.conversation_id, "email:root@example.com");
+        assert!(
+            matches!(inbound.content, MessageContent::Text(ref text) if text == "Thanks, looks good.")
+        );
+
+        let reply = reply_headers(&inbound).unwrap();
+        assert_eq!(reply.to, "alice@example.com");
+        assert_eq!(reply.subject, "Re: Quarterly report");
+        assert_eq!(reply.in_reply_to.as_deref(), Some("msg-2@example.com"));
+        assert_eq!(
+            reply.references,
+            [
+                "root@example.com",
+                "msg-1@spacebot.test",
+                "msg-2@example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn new_threads_start_from_their_own_message_id() {
+        let raw = raw_email("", "Can you summarize it?");
+        let inbound = to_inbound(
+            &raw,
+            "bot@spacebot.test",
+            &permissions(&["alice@example.com"]),
+        )
+        .unwrap();
+
+        assert_eq!(inbound.conversation_id, "email:msg-2@example.com");
+        assert!(matches!(
+            inbound.content,
+            MessageContent::Text(ref text) if text.starts_with("Subject: Quarterly report")
+        ));
+    }
+
+    #[test]
+    fn unlisted_and_automated_senders_are_dropped() {
+        let raw = raw_email("", "hello");
+        assert!(to_inbound(&raw, "bot@spacebot.test", &permissions(&[])).is_none());
+        assert!(to_inbound(&raw, "bot@spacebot.test", &permissions(&["@other.com"])).is_none());
+        assert!(to_inbound(&raw, "alice@example.com", &permissions(&["@example.com"])).is_none());
+
+        let auto_reply = raw_email("Auto-Submitted: auto-replied\r\n", "I'm out of office");
+        assert!(
+            to_inbound(
+                &auto_reply,
+                "bot@spacebot.test",
+                &permissions(&["@example.com"])
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn attachments_are_carried_inline() {
+        let raw = "From: alice@example.com\r\n\
+                   Subject: Logs\r\n\
+                   Message-ID: <logs@example.com>\r\n\
+                   Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+                   \r\n\
+                   --b\r\n\
+                   Content-Type: text/plain\r\n\
+                   \r\n\
+                   See attached.\r\n\
+                   --b\r\n\
+                   Content-Type: text/plain; name=\"app.log\"\r\n\
+                   Content-Disposition: attachment; filename=\"app.log\"\r\n\
+                   \r\n\
+                   error: disk full\r\n\
+                   --b--\r\n";
+        let inbound = to_inbound(
+            raw.as_bytes(),
+            "bot@spacebot.test",
+            &permissions(&["alice@example.com"]),
+        )
+        .unwrap();
+
+        let MessageContent::Media { text, attachments } = inbound.content else {
+            panic!("expected media content");
+        };
+        assert!(text.unwrap().contains("See attached."));
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].filename, "app.log");
+        assert_eq!(attachments[0].mime_type, "text/plain");
+        assert!(attachments[0].url.starts_with("data:text/plain;base64,"));
+    }
+}
@@ -0,0 +1,553 @@
+//! Markdown to platform formatting.
+//!
+//! Agents write markdown. Each adapter converts it in `respond` to what its
+//! platform renders: Discord markdown, Slack mrkdwn, Telegram HTML, WhatsApp
+//! markers, or plain text where there's no formatting at all (IRC, Twitch,
+//! XMPP, email).
+
+use regex::{Captures, Regex};
+
+use std::sync::LazyLock;
+
+// -- Discord --
+
+/// Discord renders most markdown itself. Tables become code blocks so their
+/// columns stay aligned, and headers deeper than `###` become bold.
+pub fn discord(markdown: &str) -> String {
+    let lines = markdown.lines().collect::<Vec<_>>();
+    let mut result = Vec::with_capacity(lines.len());
+    let mut in_code_block = false;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            let table_rows = lines[index..]
+                .iter()
+                .take_while(|line| line.trim_start().starts_with('|'))
+                .count();
+            if table_rows >= 2 && is_table_separator(lines[index + 1]) {
+                result.push("```".to_string());
+                result.extend(
+                    lines[index..index + table_rows]
+                        .iter()
+                        .map(|row| row.to_string()),
+                );
+                result.push("```".to_string());
+                index += table_rows;
+                continue;
+            }
+            let depth = line.chars().take_while(|&c| c == '#').count();
+            if depth > 3
+                && let Some(title) = line[depth..].strip_prefix(' ')
+            {
+                result.push(format!("**{}**", title.trim()));
+                index += 1;
+                continue;
+            }
+        }
+        result.push(line.to_string());
+        index += 1;
+    }
+
+    result.join("\n")
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+// -- Slack mrkdwn, WhatsApp and plain text --
+
+/// Markdown converted to Slack's mrkdwn: `*bold*`, `_italic_`, `~strike~`,
+/// `<url|text>` links, and `&`, `<`, `>` escaped.
+pub fn slack_mrkdwn(markdown: &str) -> String {
+    convert(markdown, Markers::Slack)
+}
+
+/// Markdown converted to WhatsApp's markers: `*bold*`, `_italic_`,
+/// `~strike~`, and links written out as `text (url)`.
+pub fn whatsapp(markdown: &str) -> String {
+    convert(markdown, Markers::WhatsApp)
+}
+
+/// Markdown with its markers removed, for platforms that show text as is.
+/// Links are written out as `text (url)`.
+pub fn plain_text(markdown: &str) -> String {
+    convert(markdown, Markers::Plain)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Markers {
+    Slack,
+    WhatsApp,
+    Plain,
+}
+
+/// Bold, italic, strikethrough and links, matched in one pass so converted
+/// markers aren't matched again. Italic needs a non-space on the inside of
+/// each `*` and underscores must sit on word boundaries, so `2 * 3 * 4` and
+/// `snake_case_names` are left alone.
+static SPAN_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\*\*(?P<bold>.+?)\*\*",
+        r"|\b__(?P<bold_underscore>.+?)__\b",
+        r"|\*(?P<italic>[^*\s](?:[^*]*[^*\s])?)\*",
+        r"|\b_(?P<italic_underscore>[^_]+?)_\b",
+        r"|~~(?P<strike>.+?)~~",
+        r"|\[(?P<label>[^\]]+)\]\((?P<url>[^)\s]+)\)",
+    ))
+    .expect("hardcoded regex")
+});
+
+fn convert(markdown: &str, markers: Markers) -> String {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            // Neither Slack nor WhatsApp highlight, so the language goes.
+            if markers != Markers::Plain {
+                lines.push("```".to_string());
+            }
+            continue;
+        }
+        if in_code_block {
+            lines.push(match markers {
+                Markers::Slack => escape_slack(line),
+                Markers::WhatsApp | Markers::Plain => line.to_string(),
+            });
+            continue;
+        }
+
+        let depth = line.chars().take_while(|&c| c == '#').count();
+        if depth > 0
+            && let Some(title) = line[depth..].strip_prefix(' ')
+        {
+            let title = convert_line(title.trim(), markers);
+            lines.push(match markers {
+                Markers::Slack | Markers::WhatsApp => format!("*{title}*"),
+                Markers::Plain => title,
+            });
+            continue;
+        }
+
+        lines.push(convert_line(line, markers));
+    }
+
+    lines.join("\n")
+}
+
+/// Convert one line, leaving inline code spans as they are.
+fn convert_line(line: &str, markers: Markers) -> String {
+    let segments = line.split('`').collect::<Vec<_>>();
+    let mut result = String::with_capacity(line.len());
+
+    for (index, segment) in segments.iter().enumerate() {
+        let text = match markers {
+            Markers::Slack => escape_slack(segment),
+            Markers::WhatsApp | Markers::Plain => segment.to_string(),
+        };
+        if index % 2 == 1 && index < segments.len() - 1 {
+            if markers == Markers::Plain {
+                result.push_str(&text);
+            } else {
+                result.push('`');
+                result.push_str(&text);
+                result.push('`');
+            }
+        } else {
+            if index % 2 == 1 {
+                // Unmatched trailing backtick — treat as literal
+                result.push('`');
+            }
+            result.push_str(&convert_spans(&text, markers));
+        }
+    }
+
+    result
+}
+
+fn convert_spans(text: &str, markers: Markers) -> String {
+    SPAN_PATTERN
+        .replace_all(text, |captures: &Captures| {
+            if let (Some(label), Some(url)) = (captures.name("label"), captures.name("url")) {
+                let label = convert_spans(label.as_str(), markers);
+                let url = url.as_str();
+                return match markers {
+                    Markers::Slack => format!("<{url}|{label}>"),
+                    _ if label == url => label,
+                    Markers::WhatsApp | Markers::Plain => format!("{label} ({url})"),
+                };
+            }
+
+            let (inner, marker) = if let Some(inner) = captures
+                .name("bold")
+                .or_else(|| captures.name("bold_underscore"))
+            {
+                (inner, "*")
+            } else if let Some(inner) = captures
+                .name("italic")
+                .or_else(|| captures.name("italic_underscore"))
+            {
+                (inner, "_")
+            } else if let Some(inner) = captures.name("strike") {
+                (inner, "~")
+            } else {
+                return captures[0].to_string();
+            };
+            let inner = convert_spans(inner.as_str(), markers);
+            match markers {
+                Markers::Slack | Markers::WhatsApp => format!("{marker}{inner}{marker}"),
+                Markers::Plain => inner,
+            }
+        })
+        .into_owned()
+}
+
+/// Escape the characters Slack treats as control sequences in mrkdwn.
+fn escape_slack(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// -- Telegram HTML --
+
+static BOLD_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*\*(.+?)\*\*").expect("hardcoded regex"));
+static BOLD_UNDERSCORE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"__(.+?)__").expect("hardcoded regex"));
+static ITALIC_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*(.+?)\*").expect("hardcoded regex"));
+static ITALIC_UNDERSCORE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"_(.+?)_").expect("hardcoded regex"));
+static STRIKETHROUGH_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"~~(.+?)~~").expect("hardcoded regex"));
+static LINK_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").expect("hardcoded regex"));
+
+/// Escape characters that have special meaning in Telegram's HTML parse mode.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Convert markdown to Telegram-compatible HTML.
+///
+/// Handles fenced code blocks, inline code, bold, italic, strikethrough,
+/// links, headers (rendered as bold), and blockquotes.
+pub fn telegram_html(markdown: &str) -> String {
+    let mut result = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    let mut code_language = String::new();
+    let mut code_lines: Vec<&str> = Vec::new();
+    let mut blockquote_lines: Vec<String> = Vec::new();
+
+    for line in markdown.lines() {
+        if let Some(rest) = line.strip_prefix("```") {
+            flush_blockquote(&mut result, &mut blockquote_lines);
+
+            if in_code_block {
+                let content = escape_html(&code_lines.join("\n"));
+                if code_language.is_empty() {
+                    result.push_str("<pre>");
+                    result.push_str(&content);
+                    result.push_str("</pre>\n");
+                } else {
+                    result.push_str("<pre><code class=\"language-");
+                    result.push_str(&code_language);
+                    result.push_str("\">");
+                    result.push_str(&content);
+                    result.push_str("</code></pre>\n");
+                }
+                in_code_block = false;
+                code_language.clear();
+                code_lines.clear();
+            } else {
+                in_code_block = true;
+                code_language = rest.trim().to_string();
+            }
+            continue;
+        }
+
+        if in_code_block {
+            code_lines.push(line);
+            continue;
+        }
+
+        if let Some(quote_text) = line.strip_prefix("> ") {
+            blockquote_lines.push(format_inline(quote_text));
+            continue;
+        } else if line == ">" {
+            blockquote_lines.push(String::new());
+            continue;
+        }
+
+        flush_blockquote(&mut result, &mut blockquote_lines);
+
+        if let Some(header_text) = line
+            .strip_prefix("### ")
+            .or_else(|| line.strip_prefix("## "))
+            .or_else(|| line.strip_prefix("# "))
+        {
+            result.push_str("<b>");
+            result.push_str(&format_inline(header_text));
+            result.push_str("</b>\n");
+            continue;
+        }
+
+        result.push_str(&format_inline(line));
+        result.push('\n');
+    }
+
+    if in_code_block {
+        result.push_str("<pre>");
+        result.push_str(&escape_html(&code_lines.join("\n")));
+        result.push_str("</pre>\n");
+    }
+
+    flush_blockquote(&mut result, &mut blockquote_lines);
+
+    while result.ends_with('\n') {
+        result.pop();
+    }
+
+    result
+}
+
+/// Append buffered blockquote lines to the result and clear the buffer.
+fn flush_blockquote(result: &mut String, lines: &mut Vec<String>) {
+    if lines.is_empty() {
+        return;
+    }
+    result.push_str("<blockquote>");
+    result.push_str(&lines.join("\n"));
+    result.push_str("</blockquote>\n");
+    lines.clear();
+}
+
+/// Convert inline markdown elements to HTML within a single line.
+///
+/// Splits on backticks to isolate inline code spans, then converts bold,
+/// italic, strikethrough and links in the remaining text. Content inside
+/// backticks is HTML-escaped but not processed for markdown.
+fn format_inline(line: &str) -> String {
+    let segments: Vec<&str> = line.split('`').collect();
+    let mut result = String::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        if index % 2 == 1 && index < segments.len() - 1 {
+            result.push_str("<code>");
+            result.push_str(&escape_html(segment));
+            result.push_str("</code>");
+        } else if index % 2 == 0 {
+            result.push_str(&format_markdown_spans(&escape_html(segment)));
+        } else {
+            // Unmatched trailing backtick — treat as literal
+            result.push('`');
+            result.push_str(&format_markdown_spans(&escape_html(segment)));
+        }
+    }
+
+    result
+}
+
+/// Replace markdown span markers with HTML tags in already-escaped text.
+///
+/// Bold (`**`) is processed before italic (`*`) so double-star patterns
+/// are consumed first and single stars only match true italic spans.
+fn format_markdown_spans(text: &str) -> String {
+    let text = BOLD_PATTERN.replace_all(text, "<b>$1</b>");
+    let text = BOLD_UNDERSCORE_PATTERN.replace_all(&text, "<b>$1</b>");
+    let text = ITALIC_PATTERN.replace_all(&text, "<i>$1</i>");
+    let text = ITALIC_UNDERSCORE_PATTERN.replace_all(&text, "<i>$1</i>");
+    let text = STRIKETHROUGH_PATTERN.replace_all(&text, "<s>$1</s>");
+    let text = LINK_PATTERN.replace_all(&text, r#"<a href="$2">$1</a>"#);
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bold() {
+        assert_eq!(telegram_html("**bold text**"), "<b>bold text</b>");
+    }
+
+    #[test]
+    fn italic() {
+        assert_eq!(telegram_html("*italic text*"), "<i>italic text</i>");
+    }
+
+    #[test]
+    fn bold_with_underscores() {
+        assert_eq!(telegram_html("__bold text__"), "<b>bold text</b>");
+    }
+
+    #[test]
+    fn italic_with_underscores() {
+        assert_eq!(telegram_html("_italic text_"), "<i>italic text</i>");
+    }
+
+    #[test]
+    fn bold_and_italic_nested() {
+        assert_eq!(telegram_html("***both***"), "<b><i>both</i></b>");
+    }
+
+    #[test]
+    fn inline_code() {
+        assert_eq!(
+            telegram_html("use `println!` here"),
+            "use <code>println!</code> here"
+        );
+    }
+
+    #[test]
+    fn code_block_with_language() {
+        let input = "```rust\nfn main() {}\n```";
+        let expected = "<pre><code class=\"language-rust\">fn main() {}</code></pre>";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn code_block_without_language() {
+        let input = "```\nhello world\n```";
+        let expected = "<pre>hello world</pre>";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn code_block_escapes_html() {
+        let input = "```\n<script>alert(1)</script>\n```";
+        let expected = "<pre>&lt;script&gt;alert(1)&lt;/script&gt;</pre>";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn link() {
+        assert_eq!(
+            telegram_html("[click](https://example.com)"),
+            r#"<a href="https://example.com">click</a>"#
+        );
+    }
+
+    #[test]
+    fn strikethrough() {
+        assert_eq!(telegram_html("~~deleted~~"), "<s>deleted</s>");
+    }
+
+    #[test]
+    fn headers_render_as_bold() {
+        assert_eq!(telegram_html("# Title"), "<b>Title</b>");
+        assert_eq!(telegram_html("## Sub"), "<b>Sub</b>");
+        assert_eq!(telegram_html("### Section"), "<b>Section</b>");
+    }
+
+    #[test]
+    fn blockquote() {
+        assert_eq!(
+            telegram_html("> quoted text"),
+            "<blockquote>quoted text</blockquote>"
+        );
+    }
+
+    #[test]
+    fn multiline_blockquote() {
+        let input = "> line one\n> line two";
+        let expected = "<blockquote>line one\nline two</blockquote>";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn html_entities_escaped_in_text() {
+        assert_eq!(telegram_html("x < y & a > b"), "x &lt; y &amp; a &gt; b");
+    }
+
+    #[test]
+    fn inline_code_escapes_html() {
+        assert_eq!(
+            telegram_html("`<b>not bold</b>`"),
+            "<code>&lt;b&gt;not bold&lt;/b&gt;</code>"
+        );
+    }
+
+    #[test]
+    fn mixed_formatting() {
+        let input = "Hello **world**, this is *important* and `code`";
+        let expected = "Hello <b>world</b>, this is <i>important</i> and <code>code</code>";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn plain_text_unchanged() {
+        assert_eq!(telegram_html("just plain text"), "just plain text");
+    }
+
+    #[test]
+    fn unclosed_code_block_handled() {
+        let input = "```python\nprint('hi')";
+        let expected = "<pre>print('hi')</pre>";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn list_items_pass_through() {
+        let input = "- item one\n- item two\n- item three";
+        let expected = "- item one\n- item two\n- item three";
+        assert_eq!(telegram_html(input), expected);
+    }
+
+    #[test]
+    fn discord_wraps_tables_in_code_blocks() {
+        let input = "Results:\n| name | score |\n|------|-------|\n| ada | 3 |\ndone";
+        let expected = "Results:\n```\n| name | score |\n|------|-------|\n| ada | 3 |\n```\ndone";
+        assert_eq!(discord(input), expected);
+    }
+
+    #[test]
+    fn discord_keeps_supported_markdown() {
+        let input = "## Plan\n**bold** and `code`\n#### Detail\n```\n#### not a header\n```";
+        let expected = "## Plan\n**bold** and `code`\n**Detail**\n```\n#### not a header\n```";
+        assert_eq!(discord(input), expected);
+    }
+
+    #[test]
+    fn slack_mrkdwn_converts_markers() {
+        assert_eq!(
+            slack_mrkdwn("**bold**, *italic*, ~~gone~~ and [docs](https://example.com)"),
+            "*bold*, _italic_, ~gone~ and <https://example.com|docs>"
+        );
+        assert_eq!(slack_mrkdwn("# Title"), "*Title*");
+        assert_eq!(slack_mrkdwn("a < b & `x > y`"), "a &lt; b &amp; `x &gt; y`");
+    }
+
+    #[test]
+    fn whatsapp_converts_markers() {
+        assert_eq!(
+            whatsapp("**bold** and _italic_, see [docs](https://example.com)"),
+            "*bold* and _italic_, see docs (https://example.com)"
+        );
+        assert_eq!(whatsapp("```rust\nlet x = 1;\n```"), "```\nlet x = 1;\n```");
+    }
+
+    #[test]
+    fn plain_text_strips_markers() {
+        let input = "## Summary\n**Done**: *all* `tests` pass, see [CI](https://ci.example.com)\n```sh\ncargo test\n```";
+        let expected = "Summary\nDone: all tests pass, see CI (https://ci.example.com)\ncargo test";
+        assert_eq!(plain_text(input), expected);
+    }
+
+    #[test]
+    fn plain_text_leaves_lookalikes_alone() {
+        assert_eq!(plain_text("2 * 3 * 4"), "2 * 3 * 4");
+        assert_eq!(plain_text("call snake_case_name"), "call snake_case_name");
+        assert_eq!(plain_text("- item one\n> quoted"), "- item one\n> quoted");
+    }
+}
@@ -0,0 +1,541 @@
+//! IRC messaging adapter using the irc crate.
+//!
+//! Joins the configured channels and answers there and in private messages.
+//! IRC has no message editing, threads or rich content, so everything goes
+//! out as plain `PRIVMSG` lines split to fit the protocol's line limit. The
+//! connection is re-established with backoff whenever it drops.
+
+use crate::config::IrcPermissions;
+use crate::messaging::format;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use ::irc::client::data::Config as IrcClientConfig;
+use ::irc::client::{Client, Sender};
+use ::irc::proto::{ChannelExt as _, Command, Message};
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use futures::StreamExt as _;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
+
+/// Payload bytes per `PRIVMSG`. A line is capped at 512 bytes, and the
+/// server prepends our full `nick!user@host` prefix when relaying it.
+const MAX_LINE_BYTES: usize = 400;
+
+/// Longest wait between reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+
+/// IRC adapter state.
+pub struct IrcAdapter {
+    server: String,
+    port: u16,
+    use_tls: bool,
+    nickname: String,
+    password: Option<String>,
+    nickserv_password: Option<String>,
+    channels: Vec<String>,
+    trigger_prefix: Option<String>,
+    permissions: Arc<ArcSwap<IrcPermissions>>,
+    /// Sender of the current connection; `None` while reconnecting.
+    sender: Arc<RwLock<Option<Sender>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+impl IrcAdapter {
+    pub fn new(
+        config: &crate::config::IrcConfig,
+        permissions: Arc<ArcSwap<IrcPermissions>>,
+    ) -> Self {
+        Self {
+            server: config.server.clone(),
+            port: config.port,
+            use_tls: config.use_tls,
+            nickname: config.nickname.clone(),
+            password: config.password.clone(),
+            nickserv_password: config.nickserv_password.clone(),
+            channels: config.channels.clone(),
+            trigger_prefix: config.trigger_prefix.clone(),
+            permissions,
+            sender: Arc::new(RwLock::new(None)),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn client_config(&self) -> IrcClientConfig {
+        IrcClientConfig {
+            nickname: Some(self.nickname.clone()),
+            server: Some(self.server.clone()),
+            port: Some(self.port),
+            use_tls: Some(self.use_tls),
+            password: self.password.clone(),
+            nick_password: self.nickserv_password.clone(),
+            channels: self
+                .channels
+                .iter()
+                .map(|channel| normalize_channel(channel))
+                .collect(),
+            ..IrcClientConfig::default()
+        }
+    }
+
+    async fn say(&self, target: &str, text: &str) -> anyhow::Result<()> {
+        let sender = self
+            .sender
+            .read()
+            .await
+            .clone()
+            .context("irc not connected")?;
+        for line in split_message(&format::plain_text(text), MAX_LINE_BYTES) {
+            sender
+                .send_privmsg(target, line)
+                .context("failed to send irc message")?;
+        }
+        Ok(())
+    }
+}
+
+impl Messaging for IrcAdapter {
+    fn name(&self) -> &str {
+        "irc"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let client_config = self.client_config();
+        let sender_slot = self.sender.clone();
+        let permissions = self.permissions.clone();
+        let trigger_prefix = self.trigger_prefix.clone();
+        let server = self.server.clone();
+
+        tokio::spawn(async move {
+            let mut attempt: u32 = 0;
+            loop {
+                let session = run_session(
+                    client_config.clone(),
+                    &sender_slot,
+                    &permissions,
+                    trigger_prefix.as_deref(),
+                    &inbound_tx,
+                    &mut attempt,
+                );
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    result = session => match result {
+                        SessionEnd::Closed => return,
+                        SessionEnd::Dropped(error) => {
+                            tracing::warn!(%server, %error, "irc connection lost");
+                        }
+                    },
+                }
+                *sender_slot.write().await = None;
+
+                attempt += 1;
+                let delay = Duration::from_secs(1 << attempt.min(8)).min(MAX_RECONNECT_DELAY);
+                tracing::info!(
+                    %server,
+                    attempt,
+                    delay_secs = delay.as_secs(),
+                    "reconnecting to irc"
+                );
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+
+            *sender_slot.write().await = None;
+            tracing::info!("irc message loop shutting down");
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let target = reply_target(message).context("missing irc reply target in metadata")?;
+
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                self.say(target, &text).await?;
+            }
+            OutboundResponse::Choices { prompt, options } => {
+                self.say(target, &crate::choices_text(&prompt, &options))
+                    .await?;
+            }
+            OutboundResponse::Rich { text, card } => {
+                self.say(target, &crate::rich_text(&text, &card)).await?;
+            }
+            OutboundResponse::File {
+                filename, caption, ..
+            } => {
+                // IRC is text-only — send a note about the file
+                let text = match caption {
+                    Some(caption) => format!("[File: {filename}] {caption}"),
+                    None => format!("[File: {filename}]"),
+                };
+                self.say(target, &text).await?;
+            }
+            // No message editing on IRC; the final text arrives as a Text
+            // response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd => {}
+            OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => {}
+            OutboundResponse::Attachments { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let target = match target.strip_prefix("dm:") {
+            Some(nick) => nick.to_string(),
+            None => normalize_channel(target),
+        };
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            self.say(&target, &text).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if self.sender.read().await.is_none() {
+            return Err(anyhow::anyhow!("irc not connected").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("irc adapter shut down");
+        Ok(())
+    }
+}
+
+/// Why a connection ended.
+enum SessionEnd {
+    /// The channel stopped listening; don't reconnect.
+    Closed,
+    /// The connection failed or dropped; reconnect.
+    Dropped(anyhow::Error),
+}
+
+/// Connect, register and forward messages until the connection ends.
+async fn run_session(
+    client_config: IrcClientConfig,
+    sender_slot: &RwLock<Option<Sender>>,
+    permissions: &ArcSwap<IrcPermissions>,
+    trigger_prefix: Option<&str>,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+    attempt: &mut u32,
+) -> SessionEnd {
+    let connected = async {
+        let mut client = Client::from_config(client_config)
+            .await
+            .context("failed to connect")?;
+        client.identify().context("failed to register")?;
+        let stream = client.stream().context("failed to open message stream")?;
+        anyhow::Ok((client, stream))
+    };
+    let (client, mut stream) = match connected.await {
+        Ok(connected) => connected,
+        Err(error) => return SessionEnd::Dropped(error),
+    };
+    *sender_slot.write().await = Some(client.sender());
+
+    while let Some(message) = stream.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => return SessionEnd::Dropped(error.into()),
+        };
+        if let Command::Response(::irc::proto::Response::RPL_WELCOME, _) = &message.command {
+            *attempt = 0;
+            tracing::info!(nickname = %client.current_nickname(), "irc connected");
+            continue;
+        }
+        let Some(inbound) = to_inbound(
+            &message,
+            client.current_nickname(),
+            &permissions.load(),
+            trigger_prefix,
+        ) else {
+            continue;
+        };
+        if inbound_tx.send(inbound).await.is_err() {
+            tracing::warn!("failed to send inbound message from IRC (receiver dropped)");
+            return SessionEnd::Closed;
+        }
+    }
+
+    SessionEnd::Dropped(anyhow::anyhow!("server closed the connection"))
+}
+
+/// Turn a `PRIVMSG` into an inbound message, applying the allowlists and the
+/// trigger prefix. Returns `None` for everything that shouldn't reach an agent.
+fn to_inbound(
+    message: &Message,
+    own_nickname: &str,
+    permissions: &IrcPermissions,
+    trigger_prefix: Option<&str>,
+) -> Option<InboundMessage> {
+    let Command::PRIVMSG(target, text) = &message.command else {
+        return None;
+    };
+    let nick = message.source_nickname()?;
+    if nick.eq_ignore_ascii_case(own_nickname) {
+        return None;
+    }
+
+    let nick_allowed = permissions
+        .allowed_nicks
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(nick));
+    let channel = target.is_channel_name().then(|| target.clone());
+    match &channel {
+        Some(channel) => {
+            if let Some(filter) = &permissions.channel_filter
+                && !filter
+                    .iter()
+                    .any(|allowed| normalize_channel(allowed).eq_ignore_ascii_case(channel))
+            {
+                return None;
+            }
+            if !permissions.allowed_nicks.is_empty() && !nick_allowed {
+                return None;
+            }
+        }
+        // Private messages only from explicitly allowed nicks.
+        None if !nick_allowed => return None,
+        None => {}
+    }
+
+    let mut text = text.trim().to_string();
+    if let Some(prefix) = trigger_prefix {
+        text = text.strip_prefix(prefix)?.trim_start().to_string();
+    }
+    if text.is_empty() {
+        return None;
+    }
+
+    let conversation_id = match &channel {
+        Some(channel) => format!("irc:{}", channel.to_lowercase()),
+        None => format!("irc:dm:{}", nick.to_lowercase()),
+    };
+
+    let mut metadata = HashMap::new();
+    if let Some(channel) = &channel {
+        metadata.insert(
+            "irc_channel".into(),
+            serde_json::Value::String(channel.clone()),
+        );
+        metadata.insert(
+            "irc_mentions_bot".into(),
+            serde_json::Value::Bool(text.to_lowercase().contains(&own_nickname.to_lowercase())),
+        );
+    }
+    metadata.insert("irc_nick".into(), serde_json::Value::String(nick.into()));
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(nick.into()),
+    );
+
+    Some(InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "irc".into(),
+        conversation_id,
+        sender_id: nick.to_lowercase(),
+        agent_id: None,
+        content: MessageContent::Text(text),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(nick.to_string()),
+    })
+}
+
+/// Where replies to `message` go: its channel, or the sender for a private message.
+fn reply_target(message: &InboundMessage) -> Option<&str> {
+    message
+        .metadata
+        .get("irc_channel")
+        .or_else(|| message.metadata.get("irc_nick"))
+        .and_then(|value| value.as_str())
+}
+
+/// Channel names from config may omit the `#`.
+fn normalize_channel(channel: &str) -> String {
+    if channel.is_channel_name() {
+        channel.to_string()
+    } else {
+        format!("#{channel}")
+    }
+}
+
+/// Split text into IRC lines: one per newline, with long lines broken at
+/// spaces (or hard-cut on a character boundary) to stay under `max_bytes`.
+fn split_message(text: &str, max_bytes: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut remaining = line.trim_end();
+        while remaining.len() > max_bytes {
+            let boundary = remaining.floor_char_boundary(max_bytes);
+            // Always take at least one whole character, so a limit smaller
+            // than the first character can't stall the loop.
+            let first_char = remaining.chars().next().map_or(0, char::len_utf8);
+            let split_at = remaining[..boundary]
+                .rfind(' ')
+                .filter(|&index| index > 0)
+                .unwrap_or(boundary)
+                .max(first_char);
+            lines.push(remaining[..split_at].to_string());
+            remaining = remaining[split_at..].trim_start();
+        }
+        if !remaining.is_empty() {
+            lines.push(remaining.to_string());
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn privmsg(nick: &str, target: &str, text: &str) -> Message {
+        format!(":{nick}!user@host PRIVMSG {target} :{text}\r\n")
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn long_lines_are_split_within_the_limit() {
+        let text = format!("short line\n\n{}", "word ".repeat(200));
+        let lines = split_message(&text, MAX_LINE_BYTES);
+
+        assert_eq!(lines[0], "short line");
+        assert!(lines.len() > 2);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_BYTES));
+        assert!(lines.iter().all(|line| !line.is_empty()));
+
+        let unbroken = "é".repeat(300);
+        let lines = split_message(&unbroken, MAX_LINE_BYTES);
+        assert_eq!(lines.concat(), unbroken);
+    }
+
+    #[test]
+    fn tiny_limits_still_make_progress() {
+        assert_eq!(split_message("éa", 1), vec!["é", "a"]);
+        assert_eq!(split_message("ab c", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn allowlists_filter_channels_and_private_messages() {
+        let permissions = IrcPermissions {
+            channel_filter: Some(vec!["ops".into()]),
+            allowed_nicks: Vec::new(),
+        };
+
+        let inbound = to_inbound(
+            &privmsg("alice", "#ops", "deploy status?"),
+            "spacebot",
+            &permissions,
+            None,
+        )
+        .unwrap();
+        assert_eq!(inbound.conversation_id, "irc:#ops");
+        assert_eq!(reply_target(&inbound), Some("#ops"));
+
+        assert!(
+            to_inbound(
+                &privmsg("alice", "#random", "hi"),
+                "spacebot",
+                &permissions,
+                None
+            )
+            .is_none()
+        );
+        // Private messages need an explicit nick allowlist.
+        assert!(
+            to_inbound(
+                &privmsg("alice", "spacebot", "hi"),
+                "spacebot",
+                &permissions,
+                None
+            )
+            .is_none()
+        );
+
+        let permissions = IrcPermissions {
+            channel_filter: None,
+            allowed_nicks: vec!["Alice".into()],
+        };
+        let inbound = to_inbound(
+            &privmsg("alice", "spacebot", "hi"),
+            "spacebot",
+            &permissions,
+            None,
+        )
+        .unwrap();
+        assert_eq!(inbound.conversation_id, "irc:dm:alice");
+        assert_eq!(reply_target(&inbound), Some("alice"));
+        assert!(
+            to_inbound(
+                &privmsg("mallory", "#ops", "hi"),
+                "spacebot",
+                &permissions,
+                None
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn trigger_prefix_is_required_and_stripped() {
+        let permissions = IrcPermissions::default();
+        let inbound = to_inbound(
+            &privmsg("alice", "#ops", "!ask what broke?"),
+            "spacebot",
+            &permissions,
+            Some("!ask"),
+        )
+        .unwrap();
+        assert!(matches!(inbound.content, MessageContent::Text(ref text) if text == "what broke?"));
+        assert!(
+            to_inbound(
+                &privmsg("alice", "#ops", "chatter"),
+                "spacebot",
+                &permissions,
+                Some("!ask")
+            )
+            .is_none()
+        );
+        assert!(
+            to_inbound(
+                &privmsg("spacebot", "#ops", "echo"),
+                "spacebot",
+                &permissions,
+                None
+            )
+            .is_none()
+        );
+    }
+}
@@ -1,6 +1,8 @@
 //! MessagingManager: Fan-in and routing for all adapters.
 
-use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging, MessagingDyn};
+use crate::messaging::traits::{
+    CreatedThread, HistoryMessage, InboundStream, Messaging, MessagingDyn, Presence,
+};
 use crate::{InboundMessage, OutboundResponse, StatusUpdate};
 
 use anyhow::Context as _;
@@ -45,6 +47,14 @@ impl MessagingManager {
         self.adapters.write().await.insert(name, adapter);
     }
 
+    /// Register an adapter built behind `dyn`, such as one from the
+    /// [`registry`](crate::messaging::registry).
+    pub async fn register_dyn(&self, adapter: Arc<dyn MessagingDyn>) {
+        let name = adapter.name().to_string();
+        tracing::info!(adapter = %name, "registered messaging adapter");
+        self.adapters.write().await.insert(name, adapter);
+    }
+
     /// Maximum number of retry attempts for failed adapters before giving up.
     const MAX_RETRY_ATTEMPTS: u32 = 12;
 
@@ -56,8 +66,23 @@ impl MessagingManager {
     /// retried in the background with exponential backoff.
     pub async fn start(&self) -> crate::Result<InboundStream> {
         let adapters = self.adapters.read().await;
-        for (name, adapter) in adapters.iter() {
-            match adapter.start().await {
+        // Adapters connect to unrelated services, so one slow handshake
+        // shouldn't hold up the others.
+        let results =
+            futures::future::join_all(adapters.iter().map(|(name, adapter)| async move {
+                let started_at = std::time::Instant::now();
+                let result = adapter.start().await;
+                tracing::info!(
+                    adapter = %name,
+                    elapsed_ms = started_at.elapsed().as_millis() as u64,
+                    success = result.is_ok(),
+                    "adapter start finished"
+                );
+                (name, adapter, result)
+            }))
+            .await;
+        for (name, adapter, result) in results {
+            match result {
                 Ok(stream) => Self::spawn_forwarder(name.clone(), stream, self.fan_in_tx.clone()),
                 Err(error) => {
                     tracing::warn!(
@@ -93,6 +118,15 @@ impl MessagingManager {
     /// channel, so the main loop's stream receives messages without any
     /// stream replacement or restart.
     pub async fn register_and_start(&self, adapter: impl Messaging) -> crate::Result<()> {
+        self.register_and_start_dyn(Arc::new(adapter)).await
+    }
+
+    /// [`register_and_start`](Self::register_and_start) for an adapter built
+    /// behind `dyn`.
+    pub async fn register_and_start_dyn(
+        &self,
+        adapter: Arc<dyn MessagingDyn>,
+    ) -> crate::Result<()> {
         let name = adapter.name().to_string();
 
         // Shut down existing adapter with the same name if present
@@ -106,8 +140,6 @@ impl MessagingManager {
             }
         }
 
-        let adapter: Arc<dyn MessagingDyn> = Arc::new(adapter);
-
         let stream = adapter
             .start()
             .await
@@ -209,9 +241,27 @@ impl MessagingManager {
         let adapter = adapters
             .get(&message.source)
             .with_context(|| format!("no messaging adapter named '{}'", message.source))?;
+        if let OutboundResponse::Attachments { text, files } = response
+            && !adapter.supports_attachments()
+        {
+            for response in crate::attachment_files(text, files) {
+                adapter.respond(message, response).await?;
+            }
+            return Ok(());
+        }
         adapter.respond(message, response).await
     }
 
+    /// Whether the adapter a message came from edits streamed responses into
+    /// place.
+    pub async fn supports_streaming(&self, message: &InboundMessage) -> bool {
+        self.adapters
+            .read()
+            .await
+            .get(&message.source)
+            .is_some_and(|adapter| adapter.supports_streaming())
+    }
+
     /// Route a status update to the correct adapter.
     pub async fn send_status(
         &self,
@@ -236,6 +286,14 @@ impl MessagingManager {
         let adapter = adapters
             .get(adapter_name)
             .with_context(|| format!("no messaging adapter named '{adapter_name}'"))?;
+        if let OutboundResponse::Attachments { text, files } = response
+            && !adapter.supports_attachments()
+        {
+            for response in crate::attachment_files(text, files) {
+                adapter.broadcast(target, response).await?;
+            }
+            return Ok(());
+        }
         adapter.broadcast(target, response).await
     }
 
@@ -252,6 +310,58 @@ impl MessagingManager {
         adapter.fetch_history(message, limit).await
     }
 
+    /// Start a thread off a message on its platform.
+    pub async fn create_thread(
+        &self,
+        message: &InboundMessage,
+        name: &str,
+    ) -> crate::Result<CreatedThread> {
+        let adapters = self.adapters.read().await;
+        let adapter = adapters
+            .get(&message.source)
+            .with_context(|| format!("no messaging adapter named '{}'", message.source))?;
+        adapter.create_thread(message, name).await
+    }
+
+    /// Play speech where a message was spoken, on its platform.
+    pub async fn speak(
+        &self,
+        message: &InboundMessage,
+        audio: Vec<u8>,
+        sample_rate: u32,
+    ) -> crate::Result<()> {
+        let adapters = self.adapters.read().await;
+        let adapter = adapters
+            .get(&message.source)
+            .with_context(|| format!("no messaging adapter named '{}'", message.source))?;
+        adapter.speak(message, audio, sample_rate).await
+    }
+
+    /// Run every adapter's health check. Errors are returned as strings so
+    /// callers can keep them across awaits.
+    pub async fn health_check_all(&self) -> Vec<(String, Result<(), String>)> {
+        let adapters = self.adapters.read().await;
+        let mut results = Vec::with_capacity(adapters.len());
+        for (name, adapter) in adapters.iter() {
+            let result = adapter
+                .health_check()
+                .await
+                .map_err(|error| error.to_string());
+            results.push((name.clone(), result));
+        }
+        results
+    }
+
+    /// Show `presence` on every adapter that has one.
+    pub async fn set_presence(&self, presence: Presence) {
+        let adapters = self.adapters.read().await;
+        for (name, adapter) in adapters.iter() {
+            if let Err(error) = adapter.set_presence(presence.clone()).await {
+                tracing::warn!(adapter = %name, %error, "failed to set presence");
+            }
+        }
+    }
+
     /// Remove and shut down a single adapter by name.
     pub async fn remove_adapter(&self, name: &str) -> crate::Result<()> {
         let adapter = self.adapters.write().await.remove(name);
@@ -278,3 +388,135 @@ impl Default for MessagingManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MessagingManager;
+    use crate::messaging::traits::{InboundStream, Messaging};
+    use crate::{InboundMessage, MessageContent, OutboundFile, OutboundResponse};
+
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Records what it's asked to send.
+    struct RecordingAdapter {
+        attachments: bool,
+        sent: Arc<Mutex<Vec<OutboundResponse>>>,
+    }
+
+    impl Messaging for RecordingAdapter {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn start(&self) -> crate::Result<InboundStream> {
+            Ok(Box::pin(futures::stream::empty()))
+        }
+
+        async fn respond(
+            &self,
+            _message: &InboundMessage,
+            response: OutboundResponse,
+        ) -> crate::Result<()> {
+            self.sent.lock().unwrap().push(response);
+            Ok(())
+        }
+
+        fn supports_attachments(&self) -> bool {
+            self.attachments
+        }
+
+        async fn broadcast(&self, _target: &str, response: OutboundResponse) -> crate::Result<()> {
+            self.sent.lock().unwrap().push(response);
+            Ok(())
+        }
+
+        async fn health_check(&self) -> crate::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn manager(attachments: bool) -> (MessagingManager, Arc<Mutex<Vec<OutboundResponse>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let manager = MessagingManager::new();
+        manager
+            .register(RecordingAdapter {
+                attachments,
+                sent: sent.clone(),
+            })
+            .await;
+        (manager, sent)
+    }
+
+    fn message() -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "recording".into(),
+            conversation_id: "recording:1".into(),
+            sender_id: "2".into(),
+            agent_id: None,
+            content: MessageContent::Text("send the logs".into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    fn attachments() -> OutboundResponse {
+        OutboundResponse::Attachments {
+            text: Some("Here they are".into()),
+            files: ["a.log", "b.log"]
+                .into_iter()
+                .map(|filename| OutboundFile {
+                    filename: filename.into(),
+                    data: b"log".to_vec(),
+                    mime_type: "text/plain".into(),
+                })
+                .collect(),
+        }
+    }
+
+    fn sent_filenames(sent: &Mutex<Vec<OutboundResponse>>) -> Vec<String> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .map(|response| match response {
+                OutboundResponse::File { filename, .. } => filename.clone(),
+                other => panic!("expected a file, got {other:?}"),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn attachments_are_split_for_adapters_without_support() {
+        let (manager, sent) = manager(false).await;
+
+        manager.respond(&message(), attachments()).await.unwrap();
+        manager
+            .broadcast("recording", "recording:1", attachments())
+            .await
+            .unwrap();
+
+        assert_eq!(sent_filenames(&sent), ["a.log", "b.log", "a.log", "b.log"]);
+    }
+
+    #[tokio::test]
+    async fn attachments_pass_through_to_adapters_with_support() {
+        let (manager, sent) = manager(true).await;
+
+        manager.respond(&message(), attachments()).await.unwrap();
+        manager
+            .broadcast("recording", "recording:1", attachments())
+            .await
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        for response in sent.iter() {
+            let OutboundResponse::Attachments { files, .. } = response else {
+                panic!("expected attachments, got {response:?}");
+            };
+            assert_eq!(files.len(), 2);
+        }
+    }
+}
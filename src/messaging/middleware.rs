@@ -0,0 +1,403 @@
+//! Inbound message middleware, run by the router on every message an adapter
+//! delivers before it's bound to an agent and handed to a channel.
+//!
+//! A middleware can let a message through, rewrite it in place, add metadata
+//! to it, or block it. Middleware runs in order and a blocked message stops
+//! the chain and is dropped, optionally with a reply to the sender. Messages
+//! Spacebot produces itself (`system`, `cron`) skip middleware.
+//!
+//! Two middlewares are built in and configured under `[middleware]`:
+//! mention stripping, which removes the bot's `@name` from the text, and a
+//! profanity filter that masks or blocks listed words. Others can be added
+//! to a [`MiddlewarePipeline`] by implementing [`InboundMiddleware`].
+
+use crate::{InboundMessage, MessageContent};
+
+use std::collections::HashSet;
+
+/// Sources produced by Spacebot itself, which middleware never sees.
+const EXEMPT_SOURCES: &[&str] = &["system", "cron"];
+
+/// Punctuation that may follow a mention, as in `@spacebot, hi`.
+const MENTION_SUFFIXES: &[char] = &[',', ':', '.', '!', '?', ';'];
+
+/// Instance-level middleware settings.
+#[derive(Debug, Clone, Default)]
+pub struct MiddlewareConfig {
+    pub strip_mentions: Option<StripMentionsConfig>,
+    pub profanity: Option<ProfanityConfig>,
+}
+
+/// Remove mentions of the bot from message text.
+#[derive(Debug, Clone, Default)]
+pub struct StripMentionsConfig {
+    /// Names the bot goes by, matched case-insensitively. The Telegram bot
+    /// username is always included.
+    pub names: Vec<String>,
+    /// Adapters this applies to. Empty means all.
+    pub sources: Vec<String>,
+}
+
+/// Mask or block messages containing listed words.
+#[derive(Debug, Clone, Default)]
+pub struct ProfanityConfig {
+    /// Words matched case-insensitively as whole words.
+    pub words: Vec<String>,
+    pub action: ProfanityAction,
+    /// Reply sent when a message is blocked. Empty drops it silently.
+    pub message: String,
+    /// Adapters this applies to. Empty means all.
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProfanityAction {
+    /// Replace each letter of a listed word with `*`.
+    #[default]
+    Mask,
+    /// Drop the message.
+    Block,
+}
+
+impl ProfanityAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mask" => Some(Self::Mask),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+}
+
+/// What the router should do with a message after a middleware ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MiddlewareAction {
+    Continue,
+    /// Drop the message, sending `reply` to the sender when there is one.
+    Block {
+        reply: Option<String>,
+    },
+}
+
+/// One step of inbound processing.
+pub trait InboundMiddleware: Send + Sync {
+    /// Name used in logs.
+    fn name(&self) -> &str;
+
+    /// Inspect a message, rewriting it in place if needed.
+    fn process(&self, message: &mut InboundMessage) -> MiddlewareAction;
+}
+
+struct Stage {
+    /// Adapters the middleware applies to. Empty means all.
+    sources: Vec<String>,
+    middleware: Box<dyn InboundMiddleware>,
+}
+
+/// The middleware chain, run in order on every inbound message.
+#[derive(Default)]
+pub struct MiddlewarePipeline {
+    stages: Vec<Stage>,
+}
+
+impl std::fmt::Debug for MiddlewarePipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.stages.iter().map(|stage| stage.middleware.name()))
+            .finish()
+    }
+}
+
+impl MiddlewarePipeline {
+    /// The built-in middlewares enabled in the config: mention stripping,
+    /// then the profanity filter.
+    pub fn from_config(config: &MiddlewareConfig) -> Self {
+        let mut pipeline = Self::default();
+        if let Some(config) = &config.strip_mentions {
+            pipeline.push(StripMentions::new(&config.names), config.sources.clone());
+        }
+        if let Some(config) = &config.profanity {
+            pipeline.push(ProfanityFilter::new(config), config.sources.clone());
+        }
+        pipeline
+    }
+
+    /// Add a middleware at the end of the chain, for messages from `sources`
+    /// (all when empty).
+    pub fn push(&mut self, middleware: impl InboundMiddleware + 'static, sources: Vec<String>) {
+        self.stages.push(Stage {
+            sources,
+            middleware: Box::new(middleware),
+        });
+    }
+
+    /// Run the chain on a message, stopping at the first middleware that
+    /// blocks it.
+    pub fn run(&self, message: &mut InboundMessage) -> MiddlewareAction {
+        if EXEMPT_SOURCES.contains(&message.source.as_str()) {
+            return MiddlewareAction::Continue;
+        }
+        for stage in &self.stages {
+            if !stage.sources.is_empty() && !stage.sources.contains(&message.source) {
+                continue;
+            }
+            let action = stage.middleware.process(message);
+            if action != MiddlewareAction::Continue {
+                tracing::debug!(
+                    middleware = stage.middleware.name(),
+                    conversation_id = %message.conversation_id,
+                    "inbound message blocked"
+                );
+                return action;
+            }
+        }
+        MiddlewareAction::Continue
+    }
+}
+
+/// The text of a message, for middleware that rewrites it.
+fn text_mut(message: &mut InboundMessage) -> Option<&mut String> {
+    match &mut message.content {
+        MessageContent::Text(text) => Some(text),
+        MessageContent::Media {
+            text: Some(text), ..
+        } => Some(text),
+        _ => None,
+    }
+}
+
+/// Removes `@name` mentions of the bot anywhere in the text, and a leading
+/// `name:` or `name,` address, so the model sees only what was asked.
+pub struct StripMentions {
+    names: HashSet<String>,
+}
+
+impl StripMentions {
+    pub fn new(names: &[String]) -> Self {
+        Self {
+            names: names.iter().map(|name| name.to_lowercase()).collect(),
+        }
+    }
+
+    fn strip(text: &str, names: &HashSet<String>) -> String {
+        let is_name = |word: &str| names.contains(&word.to_lowercase());
+        let mut stripped = text
+            .lines()
+            .map(|line| {
+                line.split(' ')
+                    .filter(|token| {
+                        !token
+                            .trim_end_matches(MENTION_SUFFIXES)
+                            .strip_prefix('@')
+                            .is_some_and(is_name)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let leading = stripped.trim_start();
+        if let Some((first, rest)) = leading.split_once(char::is_whitespace)
+            && (first.ends_with(':') || first.ends_with(','))
+            && is_name(&first[..first.len() - 1])
+        {
+            stripped = rest.to_string();
+        }
+        stripped.trim().to_string()
+    }
+}
+
+impl InboundMiddleware for StripMentions {
+    fn name(&self) -> &str {
+        "strip_mentions"
+    }
+
+    fn process(&self, message: &mut InboundMessage) -> MiddlewareAction {
+        let mut names = self.names.clone();
+        if let Some(username) = message
+            .metadata
+            .get("telegram_bot_username")
+            .and_then(|value| value.as_str())
+        {
+            names.insert(username.to_lowercase());
+        }
+        if let Some(text) = text_mut(message) {
+            let stripped = Self::strip(text, &names);
+            // A message that was only a mention keeps it, so it isn't empty.
+            if !stripped.is_empty() {
+                *text = stripped;
+            }
+        }
+        MiddlewareAction::Continue
+    }
+}
+
+/// Masks or blocks messages containing any listed word.
+pub struct ProfanityFilter {
+    words: HashSet<String>,
+    action: ProfanityAction,
+    message: String,
+}
+
+impl ProfanityFilter {
+    pub fn new(config: &ProfanityConfig) -> Self {
+        Self {
+            words: config
+                .words
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect(),
+            action: config.action,
+            message: config.message.clone(),
+        }
+    }
+
+    /// The text with listed words masked, or `None` when there are none.
+    fn mask(&self, text: &str) -> Option<String> {
+        let mut masked = String::with_capacity(text.len());
+        let mut found = false;
+        let mut word_start = None;
+        for (index, character) in text.char_indices().chain([(text.len(), ' ')]) {
+            if character.is_alphanumeric() || character == '\'' {
+                word_start.get_or_insert(index);
+                continue;
+            }
+            if let Some(start) = word_start.take() {
+                let word = &text[start..index];
+                if self.words.contains(&word.to_lowercase()) {
+                    found = true;
+                    masked.extend(word.chars().map(|_| '*'));
+                } else {
+                    masked.push_str(word);
+                }
+            }
+            if index < text.len() {
+                masked.push(character);
+            }
+        }
+        found.then_some(masked)
+    }
+}
+
+impl InboundMiddleware for ProfanityFilter {
+    fn name(&self) -> &str {
+        "profanity"
+    }
+
+    fn process(&self, message: &mut InboundMessage) -> MiddlewareAction {
+        let Some(text) = text_mut(message) else {
+            return MiddlewareAction::Continue;
+        };
+        let Some(masked) = self.mask(text) else {
+            return MiddlewareAction::Continue;
+        };
+        match self.action {
+            ProfanityAction::Mask => {
+                *text = masked;
+                MiddlewareAction::Continue
+            }
+            ProfanityAction::Block => MiddlewareAction::Block {
+                reply: (!self.message.is_empty()).then(|| self.message.clone()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn message(source: &str, text: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: source.into(),
+            conversation_id: format!("{source}:1"),
+            sender_id: "alice".into(),
+            agent_id: None,
+            content: MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    fn text(message: &InboundMessage) -> &str {
+        match &message.content {
+            MessageContent::Text(text) => text,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn mentions_of_the_bot_are_stripped() {
+        let pipeline = MiddlewarePipeline::from_config(&MiddlewareConfig {
+            strip_mentions: Some(StripMentionsConfig {
+                names: vec!["Spacebot".into()],
+                sources: Vec::new(),
+            }),
+            profanity: None,
+        });
+
+        let mut inbound = message(
+            "discord",
+            "@spacebot, what's up with @alice?\n@SpaceBot thanks",
+        );
+        assert_eq!(pipeline.run(&mut inbound), MiddlewareAction::Continue);
+        assert_eq!(text(&inbound), "what's up with @alice?\nthanks");
+
+        let mut inbound = message("irc", "spacebot: deploy status");
+        pipeline.run(&mut inbound);
+        assert_eq!(text(&inbound), "deploy status");
+
+        let mut inbound = message("telegram", "@ops_helper_bot ping");
+        inbound
+            .metadata
+            .insert("telegram_bot_username".into(), "ops_helper_bot".into());
+        pipeline.run(&mut inbound);
+        assert_eq!(text(&inbound), "ping");
+
+        let mut inbound = message("discord", "@spacebot");
+        pipeline.run(&mut inbound);
+        assert_eq!(text(&inbound), "@spacebot");
+    }
+
+    #[test]
+    fn profanity_is_masked_or_blocked_per_source() {
+        let mut config = ProfanityConfig {
+            words: vec!["darn".into()],
+            ..Default::default()
+        };
+        let masking = MiddlewarePipeline::from_config(&MiddlewareConfig {
+            strip_mentions: None,
+            profanity: Some(config.clone()),
+        });
+        let mut inbound = message("discord", "Darn it, darnation!");
+        assert_eq!(masking.run(&mut inbound), MiddlewareAction::Continue);
+        assert_eq!(text(&inbound), "**** it, darnation!");
+
+        config.action = ProfanityAction::Block;
+        config.message = "Please keep it civil.".into();
+        config.sources = vec!["discord".into()];
+        let blocking = MiddlewarePipeline::from_config(&MiddlewareConfig {
+            strip_mentions: None,
+            profanity: Some(config),
+        });
+        assert_eq!(
+            blocking.run(&mut message("discord", "darn")),
+            MiddlewareAction::Block {
+                reply: Some("Please keep it civil.".into())
+            }
+        );
+        assert_eq!(
+            blocking.run(&mut message("slack", "darn")),
+            MiddlewareAction::Continue
+        );
+        assert_eq!(
+            blocking.run(&mut message("cron", "darn")),
+            MiddlewareAction::Continue
+        );
+    }
+}
@@ -0,0 +1,553 @@
+//! Pairing unknown users who DM the bot.
+//!
+//! With pairing enabled, a DM from a user who isn't in `dm_allowed_users`
+//! gets a one-time code back instead of being dropped. The request is posted
+//! to the control channel, where an admin approves it by replying
+//! `approve <code>` (or `deny <code>`), or it's approved through the API.
+//! Only RBAC admins and the configured `approvers` can answer; with RBAC off
+//! everyone resolves to owner, so its admin role counts for nothing then.
+//! Paired users are kept in an instance-level redb database and let through
+//! on top of each adapter's `dm_allowed_users`, so nobody has to edit the
+//! config or restart. Pending codes live in memory and expire after
+//! `code_ttl_secs`. A user gets at most one code per `REQUEST_INTERVAL`,
+//! and new requests are refused while `MAX_PENDING` are waiting.
+
+use crate::config::PairingConfig;
+use crate::messaging::MessagingManager;
+use crate::rbac::{RbacConfig, Role};
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use rand::Rng as _;
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// `platform:user_id` -> JSON-encoded [`PairedUser`].
+const PAIRED_USERS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("paired_users");
+
+/// Characters of pairing codes, leaving out ones that are easy to mix up.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 6;
+
+/// Pending requests kept at once; new requests are refused past this.
+const MAX_PENDING: usize = 100;
+
+/// How long a user waits between pairing requests, so one sender can't keep
+/// the control channel busy by asking again after every denial.
+const REQUEST_INTERVAL: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+
+/// A user waiting for an admin to approve their code.
+#[derive(Debug, Clone, Serialize)]
+pub struct PairingRequest {
+    pub code: String,
+    pub platform: String,
+    pub user_id: String,
+    pub display_name: String,
+    /// Broadcast target that reaches the user's DMs on `platform`.
+    pub target: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PairingRequest {
+    /// What the user is told when the code is issued.
+    pub fn instructions(&self) -> String {
+        let minutes = (self.expires_at - self.created_at).num_minutes().max(1);
+        format!(
+            "I don't know you yet. Your pairing code is {}. Ask an admin to approve it; \
+             it expires in {minutes} minutes.",
+            self.code
+        )
+    }
+}
+
+/// A user allowed to DM the bot through pairing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairedUser {
+    pub platform: String,
+    pub user_id: String,
+    pub display_name: String,
+    pub target: String,
+    /// `platform:sender_id` of the admin, or `api`.
+    pub approved_by: String,
+    pub paired_at: DateTime<Utc>,
+}
+
+/// Changes the notifier relays to the control channel and to users.
+#[derive(Debug, Clone)]
+pub enum PairingEvent {
+    Requested(PairingRequest),
+    Approved(PairedUser),
+}
+
+/// Pending pairing codes and the users paired so far.
+pub struct PairingStore {
+    db: Database,
+    config: PairingConfig,
+    /// Oldest first.
+    pending: Mutex<Vec<PairingRequest>>,
+    /// `platform:user_id` -> when the user was last issued a code.
+    last_requested: Mutex<HashMap<String, DateTime<Utc>>>,
+    events: broadcast::Sender<PairingEvent>,
+}
+
+impl std::fmt::Debug for PairingStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PairingStore")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PairingStore {
+    /// Open or create the pairing database at `path`.
+    pub fn new(path: &Path, config: PairingConfig) -> anyhow::Result<Self> {
+        let db = Database::create(path).context("failed to open pairing database")?;
+
+        let write_transaction = db.begin_write()?;
+        write_transaction
+            .open_table(PAIRED_USERS_TABLE)
+            .context("failed to open paired users table")?;
+        write_transaction.commit()?;
+
+        let (events, _) = broadcast::channel(64);
+        Ok(Self {
+            db,
+            config,
+            pending: Mutex::new(Vec::new()),
+            last_requested: Mutex::new(HashMap::new()),
+            events,
+        })
+    }
+
+    /// Whether unknown users get a code. Paired users are let through either way.
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PairingEvent> {
+        self.events.subscribe()
+    }
+
+    /// Whether `user_id` was paired on `platform`.
+    pub fn is_paired(&self, platform: &str, user_id: &str) -> bool {
+        let key = format!("{platform}:{user_id}");
+        let result = (|| -> anyhow::Result<bool> {
+            let read_transaction = self.db.begin_read()?;
+            let table = read_transaction.open_table(PAIRED_USERS_TABLE)?;
+            Ok(table.get(key.as_str())?.is_some())
+        })();
+        result.unwrap_or_else(|error| {
+            tracing::warn!(%error, platform, user_id, "failed to look up paired user");
+            false
+        })
+    }
+
+    /// Issue a code for a user who isn't allowed to DM. Returns `None` when
+    /// pairing is off, the user already holds a live code or asked within
+    /// `REQUEST_INTERVAL`, or too many requests are pending.
+    pub fn request(
+        &self,
+        platform: &str,
+        user_id: &str,
+        display_name: &str,
+        target: &str,
+    ) -> Option<PairingRequest> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let now = Utc::now();
+        let request = {
+            let mut pending = self.lock_pending(now);
+            if pending
+                .iter()
+                .any(|request| request.platform == platform && request.user_id == user_id)
+            {
+                return None;
+            }
+            if pending.len() >= MAX_PENDING {
+                tracing::warn!(
+                    platform,
+                    user_id,
+                    "too many pending pairing requests, refusing"
+                );
+                return None;
+            }
+
+            let key = format!("{platform}:{user_id}");
+            let mut last_requested = self
+                .last_requested
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            last_requested.retain(|_, requested_at| now - *requested_at < REQUEST_INTERVAL);
+            if last_requested.contains_key(&key) {
+                tracing::debug!(platform, user_id, "pairing requested again too soon");
+                return None;
+            }
+            last_requested.insert(key, now);
+
+            let request = PairingRequest {
+                code: generate_code(),
+                platform: platform.to_string(),
+                user_id: user_id.to_string(),
+                display_name: display_name.to_string(),
+                target: target.to_string(),
+                created_at: now,
+                expires_at: now + chrono::Duration::seconds(self.config.code_ttl_secs as i64),
+            };
+            pending.push(request.clone());
+            request
+        };
+
+        tracing::info!(
+            platform,
+            user_id,
+            code = %request.code,
+            "pairing requested"
+        );
+        self.events
+            .send(PairingEvent::Requested(request.clone()))
+            .ok();
+        Some(request)
+    }
+
+    /// Live pending requests, oldest first.
+    pub fn pending(&self) -> Vec<PairingRequest> {
+        self.lock_pending(Utc::now()).clone()
+    }
+
+    /// Pair the user holding `code`. Returns `None` when no live request has it.
+    pub fn approve(&self, code: &str, approved_by: &str) -> anyhow::Result<Option<PairedUser>> {
+        let Some(request) = self.take_pending(code) else {
+            return Ok(None);
+        };
+
+        let user = PairedUser {
+            platform: request.platform,
+            user_id: request.user_id,
+            display_name: request.display_name,
+            target: request.target,
+            approved_by: approved_by.to_string(),
+            paired_at: Utc::now(),
+        };
+        let key = format!("{}:{}", user.platform, user.user_id);
+        let encoded = serde_json::to_vec(&user)?;
+        let write_transaction = self.db.begin_write()?;
+        {
+            let mut table = write_transaction.open_table(PAIRED_USERS_TABLE)?;
+            table.insert(key.as_str(), encoded.as_slice())?;
+        }
+        write_transaction.commit()?;
+
+        tracing::info!(
+            platform = %user.platform,
+            user_id = %user.user_id,
+            approved_by,
+            "user paired"
+        );
+        self.events.send(PairingEvent::Approved(user.clone())).ok();
+        Ok(Some(user))
+    }
+
+    /// Drop the request holding `code` without pairing anyone.
+    pub fn deny(&self, code: &str) -> Option<PairingRequest> {
+        let request = self.take_pending(code)?;
+        tracing::info!(
+            platform = %request.platform,
+            user_id = %request.user_id,
+            "pairing denied"
+        );
+        Some(request)
+    }
+
+    /// Every paired user, most recent first.
+    pub fn list(&self) -> anyhow::Result<Vec<PairedUser>> {
+        let read_transaction = self.db.begin_read()?;
+        let table = read_transaction.open_table(PAIRED_USERS_TABLE)?;
+        let mut users = Vec::new();
+        for entry in table.iter()? {
+            let (_, value) = entry?;
+            match serde_json::from_slice::<PairedUser>(value.value()) {
+                Ok(user) => users.push(user),
+                Err(error) => tracing::warn!(%error, "skipping unreadable paired user record"),
+            }
+        }
+        users.sort_by(|a, b| b.paired_at.cmp(&a.paired_at));
+        Ok(users)
+    }
+
+    /// Take a user's DM access away. Returns whether they were paired.
+    pub fn unpair(&self, platform: &str, user_id: &str) -> anyhow::Result<bool> {
+        let key = format!("{platform}:{user_id}");
+        let write_transaction = self.db.begin_write()?;
+        let removed = {
+            let mut table = write_transaction.open_table(PAIRED_USERS_TABLE)?;
+            table.remove(key.as_str())?.is_some()
+        };
+        write_transaction.commit()?;
+        Ok(removed)
+    }
+
+    /// Handle `approve <code>` or `deny <code>` sent in the control channel.
+    /// Returns the reply when `message` was such a command.
+    pub fn handle_command(&self, message: &InboundMessage, rbac: &RbacConfig) -> Option<String> {
+        let control_channel = self.config.control_channel.as_ref()?;
+        if message.source != control_channel.adapter
+            || message.conversation_id.rsplit(':').next() != Some(control_channel.target.as_str())
+        {
+            return None;
+        }
+        let MessageContent::Text(text) = &message.content else {
+            return None;
+        };
+        let (approve, code) = parse_command(text)?;
+
+        let approved_by = format!("{}:{}", message.source, message.sender_id);
+        if self.config.approvers.is_empty() && !rbac.enabled {
+            return Some(
+                "Pairing requests can't be answered until RBAC is enabled or approvers are set."
+                    .into(),
+            );
+        }
+        let is_admin =
+            rbac.enabled && rbac.resolve_role(&message.source, &message.sender_id) >= Role::Admin;
+        if !is_admin && !self.config.approvers.contains(&approved_by) {
+            return Some("Only admins can answer pairing requests.".into());
+        }
+
+        let reply = if approve {
+            match self.approve(code, &approved_by) {
+                Ok(Some(user)) => format!(
+                    "Paired {} ({}:{}).",
+                    user.display_name, user.platform, user.user_id
+                ),
+                Ok(None) => format!("No pending pairing request has the code {code}."),
+                Err(error) => {
+                    tracing::error!(%error, "failed to store paired user");
+                    "Couldn't save the pairing; see the logs.".into()
+                }
+            }
+        } else {
+            match self.deny(code) {
+                Some(request) => format!("Denied {}.", request.display_name),
+                None => format!("No pending pairing request has the code {code}."),
+            }
+        };
+        Some(reply)
+    }
+
+    /// The pending list with expired requests dropped.
+    fn lock_pending(&self, now: DateTime<Utc>) -> std::sync::MutexGuard<'_, Vec<PairingRequest>> {
+        let mut pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        pending.retain(|request| request.expires_at > now);
+        pending
+    }
+
+    fn take_pending(&self, code: &str) -> Option<PairingRequest> {
+        let mut pending = self.lock_pending(Utc::now());
+        let index = pending
+            .iter()
+            .position(|request| request.code.eq_ignore_ascii_case(code.trim()))?;
+        Some(pending.remove(index))
+    }
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// `approve <code>` or `deny <code>`, as (approve, code).
+fn parse_command(text: &str) -> Option<(bool, &str)> {
+    let mut words = text.split_whitespace();
+    let approve = match words.next()?.to_ascii_lowercase().as_str() {
+        "approve" => true,
+        "deny" => false,
+        _ => return None,
+    };
+    let code = words.next()?;
+    (words.next().is_none() && code.len() == CODE_LENGTH).then_some((approve, code))
+}
+
+/// Relay pairing events: new requests go to the control channel, and paired
+/// users are told they can talk to the bot now.
+pub fn spawn_notifier(
+    store: Arc<PairingStore>,
+    messaging_manager: Arc<MessagingManager>,
+) -> tokio::task::JoinHandle<()> {
+    let mut events = store.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "pairing notifier lagged");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let (adapter, target, text) = match event {
+                PairingEvent::Requested(request) => {
+                    let Some(control_channel) = &store.config.control_channel else {
+                        continue;
+                    };
+                    (
+                        control_channel.adapter.clone(),
+                        control_channel.target.clone(),
+                        format!(
+                            "{} ({}:{}) wants to DM the bot. Reply `approve {}` or `deny {}`.",
+                            request.display_name,
+                            request.platform,
+                            request.user_id,
+                            request.code,
+                            request.code
+                        ),
+                    )
+                }
+                PairingEvent::Approved(user) => (
+                    user.platform,
+                    user.target,
+                    "You've been approved. Send me a message!".to_string(),
+                ),
+            };
+            if let Err(error) = messaging_manager
+                .broadcast(&adapter, &target, OutboundResponse::Text(text))
+                .await
+            {
+                tracing::warn!(%error, adapter, "failed to send pairing notice");
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(dir: &tempfile::TempDir) -> PairingStore {
+        let config = PairingConfig {
+            enabled: true,
+            control_channel: crate::messaging::target::parse_delivery_target("discord:42"),
+            code_ttl_secs: 600,
+            approvers: vec!["discord:5".into()],
+        };
+        PairingStore::new(&dir.path().join("pairing.redb"), config).unwrap()
+    }
+
+    fn message(sender_id: &str, text: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:7:42".into(),
+            sender_id: sender_id.into(),
+            agent_id: None,
+            content: MessageContent::Text(text.into()),
+            timestamp: Utc::now(),
+            metadata: Default::default(),
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn admins_approve_codes_from_the_control_channel() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(&dir);
+        let rbac = RbacConfig {
+            enabled: true,
+            default_role: Role::Member,
+            users: vec![crate::rbac::RbacUser {
+                name: "ops".into(),
+                role: Role::Admin,
+                identities: vec!["discord:1".into()],
+            }],
+            capabilities: Default::default(),
+        };
+
+        let request = store.request("discord", "99", "newcomer", "dm:99").unwrap();
+        assert!(
+            store
+                .request("discord", "99", "newcomer", "dm:99")
+                .is_none()
+        );
+        assert!(!store.is_paired("discord", "99"));
+
+        let command = format!("approve {}", request.code.to_lowercase());
+        assert_eq!(
+            store
+                .handle_command(&message("2", &command), &rbac)
+                .as_deref(),
+            Some("Only admins can answer pairing requests.")
+        );
+        assert_eq!(store.handle_command(&message("1", "hello"), &rbac), None);
+        assert_eq!(
+            store
+                .handle_command(&message("1", &command), &rbac)
+                .as_deref(),
+            Some("Paired newcomer (discord:99).")
+        );
+        assert!(store.is_paired("discord", "99"));
+        assert_eq!(store.list().unwrap()[0].approved_by, "discord:1");
+
+        assert!(store.unpair("discord", "99").unwrap());
+        assert!(!store.is_paired("discord", "99"));
+    }
+
+    #[test]
+    fn approvals_need_rbac_or_listed_approvers() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(&dir);
+        let rbac = RbacConfig::default();
+        assert!(!rbac.enabled);
+
+        let request = store.request("discord", "99", "newcomer", "dm:99").unwrap();
+        let command = format!("approve {}", request.code);
+        assert_eq!(
+            store
+                .handle_command(&message("99", &command), &rbac)
+                .as_deref(),
+            Some("Only admins can answer pairing requests.")
+        );
+        assert_eq!(
+            store
+                .handle_command(&message("5", &command), &rbac)
+                .as_deref(),
+            Some("Paired newcomer (discord:99).")
+        );
+    }
+
+    #[test]
+    fn requests_are_limited_per_sender() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(&dir);
+
+        let request = store.request("discord", "99", "newcomer", "dm:99").unwrap();
+        assert!(store.deny(&request.code).is_some());
+        // Denied, but asking again right away is still too soon.
+        assert!(
+            store
+                .request("discord", "99", "newcomer", "dm:99")
+                .is_none()
+        );
+
+        for index in 0..MAX_PENDING {
+            let user_id = format!("spam{index}");
+            assert!(store.request("discord", &user_id, "spam", "dm").is_some());
+        }
+        assert!(store.request("discord", "other", "late", "dm").is_none());
+        // The earlier requests weren't pushed out.
+        assert_eq!(store.pending()[0].user_id, "spam0");
+    }
+}
@@ -0,0 +1,233 @@
+//! Token-bucket rate limiting of inbound messages, per sender and per
+//! conversation, applied by the router before messages reach a channel.
+
+use crate::config::RateLimitConfig;
+use crate::{InboundMessage, MessageContent};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Sources produced by Spacebot itself, which are never limited.
+const EXEMPT_SOURCES: &[&str] = &["system", "cron"];
+
+/// Buckets untouched for this long are dropped; a fresh one starts full.
+const IDLE_EVICTION: Duration = Duration::from_secs(3600);
+
+/// How often idle buckets are swept.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// The throttle reply went out and the bucket hasn't recovered since.
+    notified: bool,
+}
+
+impl Bucket {
+    fn full(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(burst),
+            updated: now,
+            notified: false,
+        }
+    }
+
+    fn refill(&mut self, burst: u32, per_minute: u32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(per_minute) / 60.0).min(f64::from(burst));
+        self.updated = now;
+    }
+}
+
+/// What the router should do with an inbound message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Allow,
+    /// Over the limit. `notify` is set on the first throttled message, so
+    /// the sender gets one reply rather than one per message.
+    Throttle {
+        notify: bool,
+    },
+}
+
+/// Per-sender and per-conversation buckets for every agent.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    senders: HashMap<String, Bucket>,
+    conversations: HashMap<String, Bucket>,
+    last_sweep: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a token for the message from its sender's and its conversation's
+    /// buckets, or throttle it when either is empty.
+    pub fn check(
+        &mut self,
+        agent_id: &str,
+        message: &InboundMessage,
+        config: &RateLimitConfig,
+    ) -> RateLimitDecision {
+        self.check_at(agent_id, message, config, Instant::now())
+    }
+
+    fn check_at(
+        &mut self,
+        agent_id: &str,
+        message: &InboundMessage,
+        config: &RateLimitConfig,
+        now: Instant,
+    ) -> RateLimitDecision {
+        // Reactions don't start turns of their own.
+        if !config.enabled
+            || EXEMPT_SOURCES.contains(&message.source.as_str())
+            || matches!(message.content, MessageContent::Reaction { .. })
+        {
+            return RateLimitDecision::Allow;
+        }
+
+        self.sweep(now);
+
+        let sender = self
+            .senders
+            .entry(format!(
+                "{agent_id}:{}:{}",
+                message.source, message.sender_id
+            ))
+            .or_insert_with(|| Bucket::full(config.sender_burst, now));
+        sender.refill(config.sender_burst, config.sender_per_minute, now);
+        let conversation = self
+            .conversations
+            .entry(format!("{agent_id}:{}", message.conversation_id))
+            .or_insert_with(|| Bucket::full(config.conversation_burst, now));
+        conversation.refill(
+            config.conversation_burst,
+            config.conversation_per_minute,
+            now,
+        );
+
+        let sender_empty = sender.tokens < 1.0;
+        let conversation_empty = conversation.tokens < 1.0;
+        if !sender_empty && !conversation_empty {
+            for bucket in [sender, conversation] {
+                bucket.tokens -= 1.0;
+                bucket.notified = false;
+            }
+            return RateLimitDecision::Allow;
+        }
+
+        let notify =
+            !(sender_empty && sender.notified) && !(conversation_empty && conversation.notified);
+        sender.notified |= sender_empty;
+        conversation.notified |= conversation_empty;
+        RateLimitDecision::Throttle { notify }
+    }
+
+    fn sweep(&mut self, now: Instant) {
+        if self
+            .last_sweep
+            .is_some_and(|last| now.saturating_duration_since(last) < SWEEP_INTERVAL)
+        {
+            return;
+        }
+        self.last_sweep = Some(now);
+        let active =
+            |bucket: &Bucket| now.saturating_duration_since(bucket.updated) < IDLE_EVICTION;
+        self.senders.retain(|_, bucket| active(bucket));
+        self.conversations.retain(|_, bucket| active(bucket));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sender_id: &str, conversation_id: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: conversation_id.into(),
+            sender_id: sender_id.into(),
+            agent_id: None,
+            content: MessageContent::Text("hi".into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn spammy_sender_is_throttled_until_the_bucket_refills() {
+        let config = RateLimitConfig {
+            enabled: true,
+            sender_burst: 2,
+            sender_per_minute: 6,
+            conversation_burst: 10,
+            conversation_per_minute: 60,
+            ..Default::default()
+        };
+        let mut limiter = RateLimiter::new();
+        let start = Instant::now();
+        let spam = message("alice", "discord:1");
+
+        assert_eq!(
+            limiter.check_at("main", &spam, &config, start),
+            RateLimitDecision::Allow
+        );
+        assert_eq!(
+            limiter.check_at("main", &spam, &config, start),
+            RateLimitDecision::Allow
+        );
+        assert_eq!(
+            limiter.check_at("main", &spam, &config, start),
+            RateLimitDecision::Throttle { notify: true }
+        );
+        assert_eq!(
+            limiter.check_at("main", &spam, &config, start),
+            RateLimitDecision::Throttle { notify: false }
+        );
+
+        // Others in the same conversation aren't affected.
+        assert_eq!(
+            limiter.check_at("main", &message("bob", "discord:1"), &config, start),
+            RateLimitDecision::Allow
+        );
+
+        // Six a minute is one token every ten seconds.
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            limiter.check_at("main", &spam, &config, later),
+            RateLimitDecision::Allow
+        );
+    }
+
+    #[test]
+    fn busy_conversation_is_throttled_across_senders() {
+        let config = RateLimitConfig {
+            enabled: true,
+            conversation_burst: 3,
+            ..Default::default()
+        };
+        let mut limiter = RateLimiter::new();
+        let now = Instant::now();
+
+        for sender in ["a", "b", "c"] {
+            assert_eq!(
+                limiter.check_at("main", &message(sender, "discord:1"), &config, now),
+                RateLimitDecision::Allow
+            );
+        }
+        assert_eq!(
+            limiter.check_at("main", &message("d", "discord:1"), &config, now),
+            RateLimitDecision::Throttle { notify: true }
+        );
+        assert_eq!(
+            limiter.check_at("main", &message("d", "discord:2"), &config, now),
+            RateLimitDecision::Allow
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Messaging adapters registered by crates that embed spacebot.
+//!
+//! A downstream crate implements [`Messaging`] and registers a factory under a
+//! name before the instance starts. Each enabled `[messaging.custom.<name>]`
+//! table then builds an adapter with the factory registered under that name,
+//! handing it the table's settings unparsed. Tables without a factory are
+//! skipped with a warning.
+
+use crate::config::CustomAdapterConfig;
+use crate::messaging::traits::{Messaging, MessagingDyn};
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+type Factory =
+    Arc<dyn Fn(&CustomAdapterConfig) -> crate::Result<Arc<dyn MessagingDyn>> + Send + Sync>;
+
+static FACTORIES: LazyLock<RwLock<HashMap<String, Factory>>> = LazyLock::new(Default::default);
+
+/// Register `factory` to build the adapter configured under
+/// `[messaging.custom.<name>]`. The adapter's [`Messaging::name`] should
+/// return `name`, since responses are routed back by it. A later registration
+/// under the same name replaces the earlier one.
+pub fn register<M, F>(name: impl Into<String>, factory: F)
+where
+    M: Messaging,
+    F: Fn(&CustomAdapterConfig) -> crate::Result<M> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let factory: Factory = Arc::new(move |config: &CustomAdapterConfig| {
+        factory(config).map(|adapter| Arc::new(adapter) as Arc<dyn MessagingDyn>)
+    });
+    tracing::debug!(adapter = %name, "registered custom messaging adapter factory");
+    FACTORIES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(name, factory);
+}
+
+/// Build the adapter for `config`, or `None` when nothing is registered under
+/// its name.
+pub fn build(config: &CustomAdapterConfig) -> Option<crate::Result<Arc<dyn MessagingDyn>>> {
+    let factory = FACTORIES
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&config.name)
+        .cloned()?;
+    Some(factory(config))
+}
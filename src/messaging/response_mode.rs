@@ -0,0 +1,236 @@
+//! Per-binding reply presentation, applied to a conversation's outbound
+//! responses before they reach its adapter.
+//!
+//! By default the agent's typing indicator shows while it works and each
+//! reply is posted as its own message. A binding's `response_mode` can
+//! change that: `stream` edits the replies of one stretch of work into a
+//! single message on adapters that can edit messages, showing each reply
+//! while the model is still writing it, and `final` drops the typing
+//! indicator so busy channels only see finished replies.
+
+use crate::config::ResponseMode;
+use crate::{InboundMessage, OutboundResponse, StatusUpdate};
+
+/// Metadata key the router sets on messages from a binding whose
+/// `response_mode` isn't the default.
+pub const RESPONSE_MODE_KEY: &str = "response_mode";
+
+/// Separates replies edited into the same streamed message.
+const STREAM_SEPARATOR: &str = "\n\n";
+
+/// The response mode of the binding a message arrived through.
+pub fn response_mode(message: &InboundMessage) -> ResponseMode {
+    message
+        .metadata
+        .get(RESPONSE_MODE_KEY)
+        .and_then(|value| value.as_str())
+        .and_then(ResponseMode::parse)
+        .unwrap_or_default()
+}
+
+/// Rewrites a conversation's outbound responses for its response mode.
+/// Holds the text of the streamed message currently being edited.
+///
+/// In `stream` mode the channel sends the reply it's generating as
+/// `StreamChunk`s holding the partial reply, which are shown below the
+/// replies already sent until the reply itself arrives as `Text`.
+#[derive(Debug, Default)]
+pub struct ResponseShaper {
+    stream: Option<String>,
+    /// The last reply added to the stream, to recognize previews of it that
+    /// arrive after it.
+    last_reply: String,
+}
+
+impl ResponseShaper {
+    /// The responses to send in place of `response`. `streaming` is whether
+    /// the adapter edits streamed responses into place; without it `stream`
+    /// behaves like `typing`.
+    pub fn shape(
+        &mut self,
+        mode: ResponseMode,
+        streaming: bool,
+        response: OutboundResponse,
+    ) -> Vec<OutboundResponse> {
+        let mode = match mode {
+            ResponseMode::Stream if !streaming => ResponseMode::Typing,
+            mode => mode,
+        };
+        match (mode, response) {
+            (ResponseMode::Stream, OutboundResponse::Text(text)) => {
+                self.last_reply = text.clone();
+                match &mut self.stream {
+                    Some(streamed) => {
+                        if !streamed.is_empty() {
+                            streamed.push_str(STREAM_SEPARATOR);
+                        }
+                        streamed.push_str(&text);
+                        vec![OutboundResponse::StreamChunk(streamed.clone())]
+                    }
+                    None => {
+                        self.stream = Some(text.clone());
+                        vec![
+                            OutboundResponse::StreamStart,
+                            OutboundResponse::StreamChunk(text),
+                        ]
+                    }
+                }
+            }
+            (ResponseMode::Stream, OutboundResponse::StreamChunk(partial)) => {
+                if partial.is_empty() || self.last_reply.starts_with(&partial) {
+                    return Vec::new();
+                }
+                match &self.stream {
+                    Some(streamed) if !streamed.is_empty() => vec![OutboundResponse::StreamChunk(
+                        format!("{streamed}{STREAM_SEPARATOR}{partial}"),
+                    )],
+                    Some(_) => vec![OutboundResponse::StreamChunk(partial)],
+                    None => {
+                        self.stream = Some(String::new());
+                        vec![
+                            OutboundResponse::StreamStart,
+                            OutboundResponse::StreamChunk(partial),
+                        ]
+                    }
+                }
+            }
+            // Previews only apply to streamed messages.
+            (_, OutboundResponse::StreamChunk(_)) => Vec::new(),
+            // The streamed message shows the agent is working.
+            (ResponseMode::Stream, OutboundResponse::Status(StatusUpdate::Thinking))
+                if self.stream.is_some() =>
+            {
+                Vec::new()
+            }
+            (ResponseMode::Final, OutboundResponse::Status(StatusUpdate::Thinking)) => Vec::new(),
+            (_, response @ OutboundResponse::Status(StatusUpdate::StopTyping)) => {
+                let mut responses: Vec<_> = self.close_stream().into_iter().collect();
+                responses.push(response);
+                responses
+            }
+            (
+                _,
+                response @ (OutboundResponse::Status(_)
+                | OutboundResponse::Reaction(_)
+                | OutboundResponse::RemoveReaction(_)),
+            ) => vec![response],
+            // Anything else is posted as its own message, so later replies
+            // can't be edited in above it.
+            (_, response) => {
+                let mut responses: Vec<_> = self.close_stream().into_iter().collect();
+                responses.push(response);
+                responses
+            }
+        }
+    }
+
+    fn close_stream(&mut self) -> Option<OutboundResponse> {
+        self.last_reply.clear();
+        self.stream.take().map(|_| OutboundResponse::StreamEnd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape_all(
+        mode: ResponseMode,
+        streaming: bool,
+        responses: Vec<OutboundResponse>,
+    ) -> Vec<String> {
+        let mut shaper = ResponseShaper::default();
+        responses
+            .into_iter()
+            .flat_map(|response| shaper.shape(mode, streaming, response))
+            .map(|response| match response {
+                OutboundResponse::Text(text) => format!("text:{text}"),
+                OutboundResponse::StreamStart => "start".into(),
+                OutboundResponse::StreamChunk(text) => format!("chunk:{text}"),
+                OutboundResponse::StreamEnd => "end".into(),
+                OutboundResponse::Status(StatusUpdate::Thinking) => "thinking".into(),
+                OutboundResponse::Status(StatusUpdate::StopTyping) => "stop".into(),
+                other => format!("{other:?}"),
+            })
+            .collect()
+    }
+
+    fn turn() -> Vec<OutboundResponse> {
+        vec![
+            OutboundResponse::Status(StatusUpdate::Thinking),
+            OutboundResponse::Text("one".into()),
+            OutboundResponse::Status(StatusUpdate::Thinking),
+            OutboundResponse::Text("two".into()),
+            OutboundResponse::Status(StatusUpdate::StopTyping),
+        ]
+    }
+
+    #[test]
+    fn typing_passes_responses_through() {
+        assert_eq!(
+            shape_all(ResponseMode::Typing, true, turn()),
+            ["thinking", "text:one", "thinking", "text:two", "stop"]
+        );
+    }
+
+    #[test]
+    fn stream_edits_a_turn_into_one_message() {
+        assert_eq!(
+            shape_all(ResponseMode::Stream, true, turn()),
+            [
+                "thinking",
+                "start",
+                "chunk:one",
+                "chunk:one\n\ntwo",
+                "end",
+                "stop"
+            ]
+        );
+        // Adapters that can't edit messages keep the typing behavior.
+        assert_eq!(
+            shape_all(ResponseMode::Stream, false, turn()),
+            shape_all(ResponseMode::Typing, false, turn())
+        );
+    }
+
+    #[test]
+    fn stream_shows_the_reply_being_written() {
+        let responses = vec![
+            OutboundResponse::Status(StatusUpdate::Thinking),
+            OutboundResponse::StreamChunk("Hel".into()),
+            OutboundResponse::StreamChunk("Hello the".into()),
+            OutboundResponse::Text("Hello there".into()),
+            // A preview that lost the race with its reply.
+            OutboundResponse::StreamChunk("Hello there".into()),
+            OutboundResponse::StreamChunk("Also".into()),
+            OutboundResponse::Text("Also this".into()),
+            OutboundResponse::Status(StatusUpdate::StopTyping),
+        ];
+        assert_eq!(
+            shape_all(ResponseMode::Stream, true, responses.clone()),
+            [
+                "thinking",
+                "start",
+                "chunk:Hel",
+                "chunk:Hello the",
+                "chunk:Hello there",
+                "chunk:Hello there\n\nAlso",
+                "chunk:Hello there\n\nAlso this",
+                "end",
+                "stop"
+            ]
+        );
+        assert_eq!(
+            shape_all(ResponseMode::Typing, true, responses),
+            ["thinking", "text:Hello there", "text:Also this", "stop"]
+        );
+    }
+
+    #[test]
+    fn final_drops_the_typing_indicator() {
+        assert_eq!(
+            shape_all(ResponseMode::Final, true, turn()),
+            ["text:one", "text:two", "stop"]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Session tokens for the webhook and webchat adapters.
+//!
+//! Neither adapter has platform-level user identity, so callers used to pick
+//! arbitrary sender and conversation IDs on every request. A trusted backend
+//! now issues a session for a user once and hands the opaque token to the
+//! client. The token resolves to a stable `user_id` and `conversation_id`
+//! until it expires or is revoked. Only a SHA-256 hash of each token is
+//! stored, in an instance-level redb database.
+
+use crate::config::SessionConfig;
+
+use rand::RngCore;
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::path::Path;
+
+/// session_id -> JSON-encoded [`Session`].
+const SESSIONS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("sessions");
+
+/// hex(sha256(token)) -> session_id.
+const TOKENS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("session_tokens");
+
+/// Prefix on issued tokens so they are recognizable in logs and secret scanners.
+const TOKEN_PREFIX: &str = "sbs_";
+
+/// Session lookup and storage errors.
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("session not found")]
+    NotFound,
+
+    #[error("session expired")]
+    Expired,
+
+    #[error("session revoked")]
+    Revoked,
+
+    #[error("session was issued for {expected}, not {actual}")]
+    WrongSource { expected: String, actual: String },
+
+    #[error("session storage error: {0}")]
+    Storage(String),
+}
+
+fn storage_error<E: std::fmt::Display>(context: &str) -> impl FnOnce(E) -> SessionError + '_ {
+    move |error| SessionError::Storage(format!("{context}: {error}"))
+}
+
+/// A user session bound to one adapter and conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// Adapter the session is valid for ("webhook" or "webchat").
+    pub source: String,
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub conversation_id: String,
+    pub agent_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Session {
+    pub fn is_active(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+
+    /// Name shown to the agent for messages in this session.
+    pub fn sender_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.user_id)
+    }
+}
+
+/// Parameters for issuing a session.
+#[derive(Debug, Clone)]
+pub struct NewSession {
+    pub source: String,
+    pub user_id: String,
+    pub display_name: Option<String>,
+    /// Defaults to a conversation derived from the user ID, so a user who
+    /// gets a fresh token picks up the same conversation.
+    pub conversation_id: Option<String>,
+    pub agent_id: Option<String>,
+    /// Requested lifetime, clamped to `max_ttl_secs`.
+    pub ttl_secs: Option<u64>,
+}
+
+/// The conversation a user lands in when no explicit one is requested.
+pub fn default_conversation_id(user_id: &str) -> String {
+    format!("user:{user_id}")
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0_u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    format!("{TOKEN_PREFIX}{}", hex::encode(bytes))
+}
+
+/// Session store backed by redb.
+pub struct SessionStore {
+    db: Database,
+    config: SessionConfig,
+}
+
+impl std::fmt::Debug for SessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionStore")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionStore {
+    /// Open or create the session database at `path`.
+    pub fn new(path: &Path, config: SessionConfig) -> Result<Self, SessionError> {
+        let db =
+            Database::create(path).map_err(storage_error("failed to open session database"))?;
+
+        let write_transaction = db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        {
+            write_transaction
+                .open_table(SESSIONS_TABLE)
+                .map_err(storage_error("failed to open sessions table"))?;
+            write_transaction
+                .open_table(TOKENS_TABLE)
+                .map_err(storage_error("failed to open session tokens table"))?;
+        }
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit session tables"))?;
+
+        Ok(Self { db, config })
+    }
+
+    /// Issue a new session. Returns the plaintext token, which is not stored
+    /// and can't be recovered later.
+    pub fn issue(&self, request: NewSession) -> Result<(String, Session), SessionError> {
+        let ttl_secs = request
+            .ttl_secs
+            .unwrap_or(self.config.ttl_secs)
+            .clamp(1, self.config.max_ttl_secs);
+        let now = chrono::Utc::now();
+
+        let session = Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: request.source,
+            conversation_id: request
+                .conversation_id
+                .unwrap_or_else(|| default_conversation_id(&request.user_id)),
+            user_id: request.user_id,
+            display_name: request.display_name,
+            agent_id: request.agent_id,
+            created_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl_secs as i64),
+            revoked_at: None,
+        };
+        let token = generate_token();
+
+        let write_transaction = self
+            .db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        {
+            put_session(&write_transaction, &session)?;
+            let mut tokens = write_transaction
+                .open_table(TOKENS_TABLE)
+                .map_err(storage_error("failed to open session tokens table"))?;
+            tokens
+                .insert(hash_token(&token).as_str(), session.id.as_str())
+                .map_err(storage_error("failed to store session token"))?;
+        }
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit session"))?;
+
+        tracing::info!(
+            session_id = %session.id,
+            source = %session.source,
+            user_id = %session.user_id,
+            expires_at = %session.expires_at,
+            "session issued"
+        );
+        Ok((token, session))
+    }
+
+    /// Resolve a token to its session, rejecting expired, revoked, or
+    /// cross-adapter tokens.
+    pub fn validate(&self, token: &str, source: &str) -> Result<Session, SessionError> {
+        let read_transaction = self
+            .db
+            .begin_read()
+            .map_err(storage_error("failed to begin read transaction"))?;
+        let tokens = read_transaction
+            .open_table(TOKENS_TABLE)
+            .map_err(storage_error("failed to open session tokens table"))?;
+        let session_id = tokens
+            .get(hash_token(token).as_str())
+            .map_err(storage_error("failed to read session token"))?
+            .ok_or(SessionError::NotFound)?
+            .value()
+            .to_string();
+
+        let session = self.get(&session_id)?.ok_or(SessionError::NotFound)?;
+        if session.source != source {
+            return Err(SessionError::WrongSource {
+                expected: session.source,
+                actual: source.to_string(),
+            });
+        }
+        if session.revoked_at.is_some() {
+            return Err(SessionError::Revoked);
+        }
+        if session.expires_at <= chrono::Utc::now() {
+            return Err(SessionError::Expired);
+        }
+        Ok(session)
+    }
+
+    pub fn get(&self, session_id: &str) -> Result<Option<Session>, SessionError> {
+        let read_transaction = self
+            .db
+            .begin_read()
+            .map_err(storage_error("failed to begin read transaction"))?;
+        let sessions = read_transaction
+            .open_table(SESSIONS_TABLE)
+            .map_err(storage_error("failed to open sessions table"))?;
+        let Some(value) = sessions
+            .get(session_id)
+            .map_err(storage_error("failed to read session"))?
+        else {
+            return Ok(None);
+        };
+
+        serde_json::from_slice(value.value())
+            .map(Some)
+            .map_err(storage_error("failed to decode session"))
+    }
+
+    /// List sessions, optionally filtered by source and user, newest first.
+    pub fn list(
+        &self,
+        source: Option<&str>,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Session>, SessionError> {
+        let read_transaction = self
+            .db
+            .begin_read()
+            .map_err(storage_error("failed to begin read transaction"))?;
+        let table = read_transaction
+            .open_table(SESSIONS_TABLE)
+            .map_err(storage_error("failed to open sessions table"))?;
+        let iter = table
+            .iter()
+            .map_err(storage_error("failed to iterate sessions"))?;
+
+        let mut sessions = Vec::new();
+        for entry in iter {
+            let (_, value) = entry.map_err(storage_error("failed to read session entry"))?;
+            let session: Session = serde_json::from_slice(value.value())
+                .map_err(storage_error("failed to decode session"))?;
+            if source.is_some_and(|source| session.source != source)
+                || user_id.is_some_and(|user_id| session.user_id != user_id)
+            {
+                continue;
+            }
+            sessions.push(session);
+        }
+
+        sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(sessions)
+    }
+
+    /// Revoke one session. Returns false if it doesn't exist.
+    pub fn revoke(&self, session_id: &str) -> Result<bool, SessionError> {
+        let Some(mut session) = self.get(session_id)? else {
+            return Ok(false);
+        };
+        if session.revoked_at.is_none() {
+            session.revoked_at = Some(chrono::Utc::now());
+            self.save(&session)?;
+            tracing::info!(%session_id, user_id = %session.user_id, "session revoked");
+        }
+        Ok(true)
+    }
+
+    /// Revoke every active session for a user on one adapter. Returns the
+    /// number of sessions revoked.
+    pub fn revoke_user(&self, source: &str, user_id: &str) -> Result<usize, SessionError> {
+        let now = chrono::Utc::now();
+        let mut revoked = 0;
+        for session in self.list(Some(source), Some(user_id))? {
+            if session.is_active(now) {
+                self.revoke(&session.id)?;
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    /// Delete sessions that expired or were revoked more than `retention`
+    /// ago, along with their token hashes.
+    pub fn prune(&self, retention: chrono::Duration) -> Result<usize, SessionError> {
+        let cutoff = chrono::Utc::now() - retention;
+        let stale = self
+            .list(None, None)?
+            .into_iter()
+            .filter(|session| {
+                session.expires_at < cutoff
+                    || session.revoked_at.is_some_and(|revoked| revoked < cutoff)
+            })
+            .map(|session| session.id)
+            .collect::<std::collections::HashSet<_>>();
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let write_transaction = self
+            .db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        {
+            let mut sessions = write_transaction
+                .open_table(SESSIONS_TABLE)
+                .map_err(storage_error("failed to open sessions table"))?;
+            for session_id in &stale {
+                sessions
+                    .remove(session_id.as_str())
+                    .map_err(storage_error("failed to remove session"))?;
+            }
+
+            let mut tokens = write_transaction
+                .open_table(TOKENS_TABLE)
+                .map_err(storage_error("failed to open session tokens table"))?;
+            tokens
+                .retain(|_, session_id| !stale.contains(session_id))
+                .map_err(storage_error("failed to remove session tokens"))?;
+        }
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit session pruning"))?;
+
+        Ok(stale.len())
+    }
+
+    fn save(&self, session: &Session) -> Result<(), SessionError> {
+        let write_transaction = self
+            .db
+            .begin_write()
+            .map_err(storage_error("failed to begin write transaction"))?;
+        put_session(&write_transaction, session)?;
+        write_transaction
+            .commit()
+            .map_err(storage_error("failed to commit session"))
+    }
+}
+
+fn put_session(
+    write_transaction: &redb::WriteTransaction,
+    session: &Session,
+) -> Result<(), SessionError> {
+    let encoded = serde_json::to_vec(session).map_err(storage_error("failed to encode session"))?;
+    let mut sessions = write_transaction
+        .open_table(SESSIONS_TABLE)
+        .map_err(storage_error("failed to open sessions table"))?;
+    sessions
+        .insert(session.id.as_str(), encoded.as_slice())
+        .map_err(storage_error("failed to store session"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, SessionStore) {
+        let directory = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(
+            &directory.path().join("sessions.redb"),
+            SessionConfig::default(),
+        )
+        .unwrap();
+        (directory, store)
+    }
+
+    fn new_session(user_id: &str) -> NewSession {
+        NewSession {
+            source: "webhook".into(),
+            user_id: user_id.into(),
+            display_name: None,
+            conversation_id: None,
+            agent_id: None,
+            ttl_secs: None,
+        }
+    }
+
+    #[test]
+    fn issued_token_resolves_to_stable_identity() {
+        let (_directory, store) = store();
+        let (first_token, first) = store.issue(new_session("alice")).unwrap();
+        let (second_token, second) = store.issue(new_session("alice")).unwrap();
+
+        assert_ne!(first_token, second_token);
+        assert!(first_token.starts_with(TOKEN_PREFIX));
+        assert_eq!(first.conversation_id, "user:alice");
+        assert_eq!(first.conversation_id, second.conversation_id);
+
+        let resolved = store.validate(&first_token, "webhook").unwrap();
+        assert_eq!(resolved.id, first.id);
+        assert_eq!(resolved.user_id, "alice");
+    }
+
+    #[test]
+    fn revoked_and_foreign_tokens_are_rejected() {
+        let (_directory, store) = store();
+        let (token, session) = store.issue(new_session("bob")).unwrap();
+
+        assert!(matches!(
+            store.validate(&token, "webchat"),
+            Err(SessionError::WrongSource { .. })
+        ));
+        assert!(matches!(
+            store.validate("sbs_unknown", "webhook"),
+            Err(SessionError::NotFound)
+        ));
+
+        assert!(store.revoke(&session.id).unwrap());
+        assert!(matches!(
+            store.validate(&token, "webhook"),
+            Err(SessionError::Revoked)
+        ));
+        assert!(!store.revoke("missing").unwrap());
+    }
+
+    #[test]
+    fn ttl_is_clamped_and_prune_removes_stale_sessions() {
+        let (_directory, store) = store();
+        let mut request = new_session("carol");
+        request.ttl_secs = Some(u64::MAX);
+        let (_, session) = store.issue(request).unwrap();
+        let lifetime = session.expires_at - session.created_at;
+        assert_eq!(
+            lifetime.num_seconds() as u64,
+            SessionConfig::default().max_ttl_secs
+        );
+
+        store.revoke_user("webhook", "carol").unwrap();
+        assert_eq!(store.prune(chrono::Duration::seconds(-1)).unwrap(), 1);
+        assert!(store.list(None, None).unwrap().is_empty());
+    }
+}
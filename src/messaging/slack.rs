@@ -22,6 +22,7 @@
 //! - DM broadcast via `conversations.open`
 
 use crate::config::{SlackCommandConfig, SlackPermissions};
+use crate::messaging::format;
 use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging};
 use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
 
@@ -199,8 +200,13 @@ async fn handle_message_event(
     };
 
     let content = extract_message_content(&msg_event.content);
+    let mentions_bot = msg_event
+        .content
+        .as_ref()
+        .and_then(|content| content.text.as_deref())
+        .is_some_and(|text| text.contains(&format!("<@{}>", adapter_state.bot_user_id)));
 
-    let (metadata, formatted_author) = build_metadata_and_author(
+    let (mut metadata, formatted_author) = build_metadata_and_author(
         &team_id_str,
         &channel_id,
         &ts,
@@ -213,6 +219,10 @@ async fn handle_message_event(
         &adapter_state.channel_name_cache,
     )
     .await;
+    metadata.insert(
+        "slack_mentions_bot".into(),
+        serde_json::Value::Bool(mentions_bot),
+    );
 
     send_inbound(
         &adapter_state.inbound_tx,
@@ -285,7 +295,7 @@ async fn handle_app_mention_event(
     let content = MessageContent::Text(text);
 
     let slack_uid = SlackUserId(user_id.clone());
-    let (metadata, formatted_author) = build_metadata_and_author(
+    let (mut metadata, formatted_author) = build_metadata_and_author(
         &team_id_str,
         &channel_id,
         &ts,
@@ -298,6 +308,7 @@ async fn handle_app_mention_event(
         &adapter_state.channel_name_cache,
     )
     .await;
+    metadata.insert("slack_mentions_bot".into(), serde_json::Value::Bool(true));
 
     send_inbound(
         &adapter_state.inbound_tx,
@@ -650,6 +661,10 @@ impl Messaging for SlackAdapter {
         "slack"
     }
 
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
     async fn start(&self) -> crate::Result<InboundStream> {
         let (inbound_tx, inbound_rx) = mpsc::channel(256);
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
@@ -766,6 +781,8 @@ impl Messaging for SlackAdapter {
         };
 
         let status_text = match &status {
+            // Assistant thread statuses are plain text; there's no card to edit.
+            StatusUpdate::Live { .. } => return Ok(()),
             StatusUpdate::Thinking => "Thinking…".to_string(),
             StatusUpdate::StopTyping => String::new(), // empty string clears the status
             StatusUpdate::ToolStarted { .. } => "Working…".to_string(),
@@ -948,6 +965,37 @@ impl Messaging for SlackAdapter {
                     .context("failed to send slack rich message")?;
             }
 
+            // No block generation for choices; the user answers the numbered list.
+            OutboundResponse::Choices { prompt, options } => {
+                let thread_ts = extract_thread_ts(message);
+                let text = crate::choices_text(&prompt, &options);
+                for chunk in split_message(&text, 12_000) {
+                    let mut req = SlackApiChatPostMessageRequest::new(
+                        channel_id.clone(),
+                        markdown_content(chunk),
+                    );
+                    req = req.opt_thread_ts(thread_ts.clone());
+                    session
+                        .chat_post_message(&req)
+                        .await
+                        .context("failed to send slack choices")?;
+                }
+            }
+            OutboundResponse::Rich { text, card } => {
+                let thread_ts = extract_thread_ts(message);
+                let text = crate::rich_text(&text, &card);
+                for chunk in split_message(&text, 12_000) {
+                    let mut req = SlackApiChatPostMessageRequest::new(
+                        channel_id.clone(),
+                        markdown_content(chunk),
+                    );
+                    req = req.opt_thread_ts(thread_ts.clone());
+                    session
+                        .chat_post_message(&req)
+                        .await
+                        .context("failed to send slack rich message")?;
+                }
+            }
             OutboundResponse::ScheduledMessage { text, post_at } => {
                 let thread_ts = extract_thread_ts(message);
                 let post_at_dt = chrono::DateTime::<chrono::Utc>::from_timestamp(post_at, 0)
@@ -1006,6 +1054,8 @@ impl Messaging for SlackAdapter {
             OutboundResponse::Status(_) => {
                 // Status updates are handled via send_status(); ignored here.
             }
+
+            OutboundResponse::Attachments { .. } => {}
         }
 
         Ok(())
@@ -1200,22 +1250,23 @@ fn extract_thread_ts(message: &InboundMessage) -> Option<SlackTs> {
 /// Build a `SlackMessageContent` using a Markdown block with plain text fallback.
 ///
 /// The Markdown block supports standard markdown (bold, italic, lists, code,
-/// headings, quotes, links) natively — no mrkdwn conversion needed. The `text`
-/// field is set as fallback for notifications and accessibility.
+/// headings, quotes, links) natively. The `text` field, converted to mrkdwn,
+/// is set as fallback for notifications and accessibility.
 ///
 /// Cumulative limit for all markdown blocks in a payload is 12,000 characters.
-/// For content exceeding 12,000 chars we fall back to plain text to avoid
+/// For content exceeding 12,000 chars we fall back to mrkdwn text to avoid
 /// Slack rejecting the payload.
 fn markdown_content(text: impl Into<String>) -> SlackMessageContent {
     let text = text.into();
+    let mrkdwn = format::slack_mrkdwn(&text);
     if text.len() <= 12_000 {
-        let block = SlackBlock::Markdown(SlackMarkdownBlock::new(text.clone()));
+        let block = SlackBlock::Markdown(SlackMarkdownBlock::new(text));
         SlackMessageContent::new()
-            .with_text(text)
+            .with_text(mrkdwn)
             .with_blocks(vec![block])
     } else {
-        // Exceeds markdown block limit — send as plain text
-        SlackMessageContent::new().with_text(text)
+        // Exceeds markdown block limit — send as mrkdwn text
+        SlackMessageContent::new().with_text(mrkdwn)
     }
 }
 
@@ -1446,10 +1497,13 @@ fn variant_name(response: &OutboundResponse) -> &'static str {
         OutboundResponse::Text(_) => "Text",
         OutboundResponse::ThreadReply { .. } => "ThreadReply",
         OutboundResponse::File { .. } => "File",
+        OutboundResponse::Attachments { .. } => "Attachments",
         OutboundResponse::Reaction(_) => "Reaction",
         OutboundResponse::RemoveReaction(_) => "RemoveReaction",
         OutboundResponse::Ephemeral { .. } => "Ephemeral",
         OutboundResponse::RichMessage { .. } => "RichMessage",
+        OutboundResponse::Choices { .. } => "Choices",
+        OutboundResponse::Rich { .. } => "Rich",
         OutboundResponse::ScheduledMessage { .. } => "ScheduledMessage",
         OutboundResponse::StreamStart => "StreamStart",
         OutboundResponse::StreamChunk(_) => "StreamChunk",
@@ -1,5 +1,6 @@
 //! Shared delivery target parsing and channel target resolution.
 
+use crate::config::Binding;
 use crate::conversation::channels::ChannelInfo;
 
 /// Canonical target for `MessagingManager::broadcast`.
@@ -101,6 +102,55 @@ pub fn resolve_broadcast_target(channel: &ChannelInfo) -> Option<BroadcastTarget
                 }
             }
         }
+        "irc" => {
+            if let Some(irc_channel) = channel
+                .platform_meta
+                .as_ref()
+                .and_then(|meta| meta.get("irc_channel"))
+                .and_then(json_value_to_string)
+            {
+                irc_channel
+            } else {
+                let parts: Vec<&str> = channel.id.split(':').collect();
+                match parts.as_slice() {
+                    ["irc", "dm", nick] => format!("dm:{nick}"),
+                    ["irc", irc_channel] => (*irc_channel).to_string(),
+                    _ => return None,
+                }
+            }
+        }
+        "xmpp" => {
+            let meta = channel.platform_meta.as_ref();
+            if let Some(room) = meta
+                .and_then(|meta| meta.get("xmpp_room"))
+                .and_then(json_value_to_string)
+            {
+                room
+            } else if let Some(jid) = meta
+                .and_then(|meta| meta.get("xmpp_jid"))
+                .and_then(json_value_to_string)
+            {
+                format!("dm:{jid}")
+            } else {
+                let parts: Vec<&str> = channel.id.split(':').collect();
+                match parts.as_slice() {
+                    ["xmpp", "dm", jid] => format!("dm:{jid}"),
+                    ["xmpp", room] => (*room).to_string(),
+                    _ => return None,
+                }
+            }
+        }
+        "email" => channel
+            .platform_meta
+            .as_ref()
+            .and_then(|meta| meta.get("email_from"))
+            .and_then(json_value_to_string)?,
+        "whatsapp" => channel
+            .platform_meta
+            .as_ref()
+            .and_then(|meta| meta.get("whatsapp_from"))
+            .and_then(json_value_to_string)
+            .or_else(|| channel.id.strip_prefix("whatsapp:").map(str::to_string))?,
         _ => return None,
     };
 
@@ -112,6 +162,29 @@ pub fn resolve_broadcast_target(channel: &ChannelInfo) -> Option<BroadcastTarget
     })
 }
 
+/// Conversations named by `bindings`: each of their `channel_ids`, and their
+/// Telegram `chat_id`, without duplicates. Bindings that only name a server
+/// or workspace have none.
+pub fn binding_targets<'a>(
+    bindings: impl IntoIterator<Item = &'a Binding>,
+) -> Vec<BroadcastTarget> {
+    let mut targets = Vec::new();
+    for binding in bindings {
+        for id in binding.channel_ids.iter().chain(&binding.chat_id) {
+            match parse_delivery_target(&format!("{}:{id}", binding.channel)) {
+                Some(target) if !targets.contains(&target) => targets.push(target),
+                Some(_) => {}
+                None => tracing::warn!(
+                    channel = %binding.channel,
+                    id,
+                    "binding channel can't be messaged"
+                ),
+            }
+        }
+    }
+    targets
+}
+
 fn normalize_target(adapter: &str, raw_target: &str) -> Option<String> {
     let trimmed = raw_target.trim();
     if trimmed.is_empty() {
@@ -123,6 +196,10 @@ fn normalize_target(adapter: &str, raw_target: &str) -> Option<String> {
         "slack" => normalize_slack_target(trimmed),
         "telegram" => normalize_telegram_target(trimmed),
         "twitch" => normalize_twitch_target(trimmed),
+        "irc" => normalize_irc_target(trimmed),
+        "email" => normalize_email_target(trimmed),
+        "xmpp" => normalize_xmpp_target(trimmed),
+        "whatsapp" => normalize_whatsapp_target(trimmed),
         _ => Some(trimmed.to_string()),
     }
 }
@@ -195,6 +272,61 @@ fn normalize_twitch_target(raw_target: &str) -> Option<String> {
     }
 }
 
+fn normalize_irc_target(raw_target: &str) -> Option<String> {
+    let target = strip_repeated_prefix(raw_target, "irc");
+
+    if let Some(nick) = target.strip_prefix("dm:") {
+        if nick.is_empty() {
+            return None;
+        }
+        return Some(format!("dm:{nick}"));
+    }
+
+    let channel = target.trim_start_matches('#');
+    if channel.is_empty() {
+        None
+    } else {
+        Some(format!("#{channel}"))
+    }
+}
+
+fn normalize_email_target(raw_target: &str) -> Option<String> {
+    let address = strip_repeated_prefix(raw_target, "email");
+    match address.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {
+            Some(address.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+fn normalize_xmpp_target(raw_target: &str) -> Option<String> {
+    let target = strip_repeated_prefix(raw_target, "xmpp");
+    let (prefix, jid) = match target.strip_prefix("dm:") {
+        Some(jid) => ("dm:", jid),
+        None => ("", target),
+    };
+    match jid.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {
+            Some(format!("{prefix}{}", jid.to_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+/// Phone numbers go to the Graph API as digits only, with the country code.
+fn normalize_whatsapp_target(raw_target: &str) -> Option<String> {
+    let number = strip_repeated_prefix(raw_target, "whatsapp");
+    if !number
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | ' ' | '-' | '(' | ')'))
+    {
+        return None;
+    }
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() < 7 { None } else { Some(digits) }
+}
+
 fn strip_repeated_prefix<'a>(raw_target: &'a str, adapter: &str) -> &'a str {
     let mut target = raw_target;
     let prefix = format!("{adapter}:");
@@ -227,6 +359,7 @@ mod tests {
             id: id.to_string(),
             platform: platform.to_string(),
             display_name: None,
+            title: None,
             platform_meta: None,
             is_active: true,
             created_at: chrono::Utc::now(),
@@ -283,4 +416,86 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn parse_irc_targets() {
+        assert_eq!(
+            parse_delivery_target("irc:ops"),
+            Some(super::BroadcastTarget {
+                adapter: "irc".to_string(),
+                target: "#ops".to_string(),
+            })
+        );
+        let channel = test_channel_info("irc:dm:alice", "irc");
+        assert_eq!(
+            resolve_broadcast_target(&channel),
+            Some(super::BroadcastTarget {
+                adapter: "irc".to_string(),
+                target: "dm:alice".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_email_targets() {
+        assert_eq!(
+            parse_delivery_target("email:Alice@Example.com"),
+            Some(super::BroadcastTarget {
+                adapter: "email".to_string(),
+                target: "alice@example.com".to_string(),
+            })
+        );
+        assert_eq!(parse_delivery_target("email:alice"), None);
+
+        let mut channel = test_channel_info("email:root@example.com", "email");
+        assert_eq!(resolve_broadcast_target(&channel), None);
+        channel.platform_meta = Some(serde_json::json!({ "email_from": "alice@example.com" }));
+        assert_eq!(
+            resolve_broadcast_target(&channel),
+            Some(super::BroadcastTarget {
+                adapter: "email".to_string(),
+                target: "alice@example.com".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_xmpp_targets() {
+        assert_eq!(
+            parse_delivery_target("xmpp:Ops@conference.example.com"),
+            Some(super::BroadcastTarget {
+                adapter: "xmpp".to_string(),
+                target: "ops@conference.example.com".to_string(),
+            })
+        );
+        let channel = test_channel_info("xmpp:dm:alice@example.com", "xmpp");
+        assert_eq!(
+            resolve_broadcast_target(&channel),
+            Some(super::BroadcastTarget {
+                adapter: "xmpp".to_string(),
+                target: "dm:alice@example.com".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_whatsapp_targets() {
+        assert_eq!(
+            parse_delivery_target("whatsapp:+1 (555) 010-4477"),
+            Some(super::BroadcastTarget {
+                adapter: "whatsapp".to_string(),
+                target: "15550104477".to_string(),
+            })
+        );
+        assert_eq!(parse_delivery_target("whatsapp:alice"), None);
+
+        let channel = test_channel_info("whatsapp:15550104477", "whatsapp");
+        assert_eq!(
+            resolve_broadcast_target(&channel),
+            Some(super::BroadcastTarget {
+                adapter: "whatsapp".to_string(),
+                target: "15550104477".to_string(),
+            })
+        );
+    }
 }
@@ -1,6 +1,8 @@
 //! Telegram messaging adapter using teloxide.
 
 use crate::config::TelegramPermissions;
+use crate::messaging::format;
+use crate::messaging::pairing::PairingStore;
 use crate::messaging::traits::{InboundStream, Messaging};
 use crate::{Attachment, InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
 
@@ -36,6 +38,7 @@ pub struct TelegramAdapter {
     typing_tasks: Arc<RwLock<HashMap<String, JoinHandle<()>>>>,
     /// Shutdown signal for the polling loop.
     shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    pairing: Option<Arc<PairingStore>>,
 }
 
 /// Tracks an in-progress streaming message edit.
@@ -43,6 +46,8 @@ struct ActiveStream {
     chat_id: ChatId,
     message_id: MessageId,
     last_edit: Instant,
+    /// Latest text held back by the edit interval, shown when the stream ends.
+    pending: Option<String>,
 }
 
 /// Telegram's per-message character limit.
@@ -51,6 +56,9 @@ const MAX_MESSAGE_LENGTH: usize = 4096;
 /// Smaller source-chunk target for markdown that expands heavily when HTML-escaped.
 const FORMATTED_SPLIT_LENGTH: usize = MAX_MESSAGE_LENGTH / 2;
 
+/// Largest file the Bot API's `getFile` will hand out.
+const MAX_DOWNLOAD_BYTES: u64 = 20 * 1024 * 1024;
+
 /// Minimum interval between streaming edits to avoid rate limits.
 const STREAM_EDIT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1000);
 
@@ -66,9 +74,16 @@ impl TelegramAdapter {
             active_messages: Arc::new(RwLock::new(HashMap::new())),
             typing_tasks: Arc::new(RwLock::new(HashMap::new())),
             shutdown_tx: Arc::new(RwLock::new(None)),
+            pairing: None,
         }
     }
 
+    /// Let paired users DM the bot, and answer unknown ones with a pairing code.
+    pub fn with_pairing(mut self, pairing: Option<Arc<PairingStore>>) -> Self {
+        self.pairing = pairing;
+        self
+    }
+
     fn extract_chat_id(&self, message: &InboundMessage) -> anyhow::Result<ChatId> {
         let id = message
             .metadata
@@ -100,6 +115,10 @@ impl Messaging for TelegramAdapter {
         "telegram"
     }
 
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
     async fn start(&self) -> crate::Result<InboundStream> {
         let (inbound_tx, inbound_rx) = mpsc::channel(256);
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
@@ -125,6 +144,7 @@ impl Messaging for TelegramAdapter {
         let permissions = self.permissions.clone();
         let bot_user_id = self.bot_user_id.clone();
         let bot_username = self.bot_username.clone();
+        let pairing = self.pairing.clone();
 
         tokio::spawn(async move {
             let mut offset = 0i32;
@@ -208,7 +228,28 @@ impl Messaging for TelegramAdapter {
                                         && !permissions
                                             .dm_allowed_users
                                             .contains(&(from.id.0 as i64))
+                                        && !pairing.as_ref().is_some_and(|pairing| {
+                                            pairing.is_paired("telegram", &from.id.0.to_string())
+                                        })
                                     {
+                                        // Unknown users get a pairing code when pairing is on.
+                                        let request = pairing.as_ref().and_then(|pairing| {
+                                            pairing.request(
+                                                "telegram",
+                                                &from.id.0.to_string(),
+                                                &from.full_name(),
+                                                &message.chat.id.0.to_string(),
+                                            )
+                                        });
+                                        if let Some(request) = request
+                                            && let Err(error) = bot
+                                                .send_message(message.chat.id, request.instructions())
+                                                .send()
+                                                .await
+                                        {
+                                            tracing::warn!(%error, "failed to send telegram pairing code");
+                                        }
+
                                         // Remember this user so we can nudge them if they're added later.
                                         let entry = (message.chat.id, from.id.0 as i64);
                                         if !rejected_users.iter().any(|(_, uid)| *uid == entry.1) {
@@ -319,7 +360,7 @@ impl Messaging for TelegramAdapter {
 
                 let input_file = InputFile::memory(data.clone()).file_name(filename.clone());
                 let sent = if let Some(ref caption_text) = caption {
-                    let html_caption = markdown_to_telegram_html(caption_text);
+                    let html_caption = format::telegram_html(caption_text);
                     self.bot
                         .send_document(chat_id, input_file)
                         .caption(&html_caption)
@@ -389,6 +430,7 @@ impl Messaging for TelegramAdapter {
                         chat_id,
                         message_id: placeholder.id,
                         last_edit: Instant::now(),
+                        pending: None,
                     },
                 );
             }
@@ -396,46 +438,28 @@ impl Messaging for TelegramAdapter {
                 let mut active = self.active_messages.write().await;
                 if let Some(stream) = active.get_mut(&message.conversation_id) {
                     if stream.last_edit.elapsed() < STREAM_EDIT_INTERVAL {
+                        stream.pending = Some(text);
                         return Ok(());
                     }
-
-                    let display_text = if text.len() > MAX_MESSAGE_LENGTH {
-                        let end = text.floor_char_boundary(MAX_MESSAGE_LENGTH - 3);
-                        format!("{}...", &text[..end])
-                    } else {
-                        text
-                    };
-
-                    let html = markdown_to_telegram_html(&display_text);
-                    if let Err(html_error) = self
-                        .bot
-                        .edit_message_text(stream.chat_id, stream.message_id, &html)
-                        .parse_mode(ParseMode::Html)
-                        .send()
-                        .await
-                    {
-                        tracing::debug!(%html_error, "HTML edit failed, retrying as plain text");
-                        if let Err(error) = self
-                            .bot
-                            .edit_message_text(stream.chat_id, stream.message_id, &display_text)
-                            .send()
-                            .await
-                        {
-                            tracing::debug!(%error, "failed to edit streaming message");
-                        }
-                    }
-                    stream.last_edit = Instant::now();
+                    edit_stream(&self.bot, stream, text).await;
                 }
             }
             OutboundResponse::StreamEnd => {
-                self.active_messages
+                let stream = self
+                    .active_messages
                     .write()
                     .await
                     .remove(&message.conversation_id);
+                if let Some(mut stream) = stream
+                    && let Some(text) = stream.pending.take()
+                {
+                    edit_stream(&self.bot, &mut stream, text).await;
+                }
             }
             OutboundResponse::Status(status) => {
                 self.send_status(message, status).await?;
             }
+            OutboundResponse::Attachments { .. } => {}
             // Slack-specific variants — graceful fallbacks for Telegram
             OutboundResponse::RemoveReaction(_) => {} // no-op
             OutboundResponse::Ephemeral { text, .. } => {
@@ -446,6 +470,16 @@ impl Messaging for TelegramAdapter {
                 // Telegram has no scheduled messages — send immediately
                 send_formatted(&self.bot, chat_id, &text, None).await?;
             }
+            OutboundResponse::Choices { prompt, options } => {
+                self.stop_typing(&message.conversation_id).await;
+                let text = crate::choices_text(&prompt, &options);
+                send_formatted(&self.bot, chat_id, &text, None).await?;
+            }
+            OutboundResponse::Rich { text, card } => {
+                self.stop_typing(&message.conversation_id).await;
+                let text = crate::rich_text(&text, &card);
+                send_formatted(&self.bot, chat_id, &text, None).await?;
+            }
         }
 
         Ok(())
@@ -478,11 +512,18 @@ impl Messaging for TelegramAdapter {
                     }
                 });
 
-                self.typing_tasks
+                // A repeated Thinking replaces the previous loop.
+                if let Some(previous) = self
+                    .typing_tasks
                     .write()
                     .await
-                    .insert(conversation_id, handle);
+                    .insert(conversation_id, handle)
+                {
+                    previous.abort();
+                }
             }
+            // No live status card on Telegram; the typing indicator covers it.
+            StatusUpdate::Live { .. } => {}
             _ => {
                 self.stop_typing(&message.conversation_id).await;
             }
@@ -549,6 +590,7 @@ fn extract_text(message: &teloxide::types::Message) -> Option<String> {
             MediaKind::Video(video) => video.caption.clone(),
             MediaKind::Voice(voice) => voice.caption.clone(),
             MediaKind::Audio(audio) => audio.caption.clone(),
+            MediaKind::Animation(animation) => animation.caption.clone(),
             _ => None,
         },
         _ => None,
@@ -565,6 +607,8 @@ fn has_attachments(message: &teloxide::types::Message) -> bool {
                 | MediaKind::Video(_)
                 | MediaKind::Voice(_)
                 | MediaKind::Audio(_)
+                | MediaKind::Animation(_)
+                | MediaKind::VideoNote(_)
         ),
         _ => false,
     }
@@ -586,6 +630,18 @@ async fn build_content(
 
     let mut resolved = Vec::with_capacity(attachments.len());
     for mut attachment in attachments {
+        // getFile refuses larger files, so don't spend a request on them.
+        if attachment
+            .size_bytes
+            .is_some_and(|size| size > MAX_DOWNLOAD_BYTES)
+        {
+            tracing::info!(
+                filename = %attachment.filename,
+                size_bytes = ?attachment.size_bytes,
+                "telegram attachment exceeds the bot API download limit, skipping"
+            );
+            continue;
+        }
         match resolve_file_url(bot, &attachment.url).await {
             Ok(url) => attachment.url = url,
             Err(error) => {
@@ -694,6 +750,31 @@ fn extract_attachments(message: &teloxide::types::Message) -> Vec<Attachment> {
                 size_bytes: Some(audio.audio.file.size as u64),
             });
         }
+        MediaKind::Animation(animation) => {
+            attachments.push(Attachment {
+                filename: animation
+                    .animation
+                    .file_name
+                    .clone()
+                    .unwrap_or_else(|| "animation.mp4".into()),
+                mime_type: animation
+                    .animation
+                    .mime_type
+                    .as_ref()
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| "video/mp4".into()),
+                url: animation.animation.file.id.to_string(),
+                size_bytes: Some(animation.animation.file.size as u64),
+            });
+        }
+        MediaKind::VideoNote(video_note) => {
+            attachments.push(Attachment {
+                filename: "video_note.mp4".into(),
+                mime_type: "video/mp4".into(),
+                url: video_note.video_note.file.id.to_string(),
+                size_bytes: Some(video_note.video_note.file.size as u64),
+            });
+        }
         _ => {}
     }
 
@@ -784,6 +865,16 @@ fn build_metadata(
 
     if let Some(bot_username) = bot_username {
         metadata.insert("telegram_bot_username".into(), bot_username.clone().into());
+
+        let handle = format!("@{}", bot_username.to_lowercase());
+        let mentions_bot = extract_text(message)
+            .is_some_and(|text| text.to_lowercase().contains(&handle))
+            || message
+                .reply_to_message()
+                .and_then(|reply| reply.from.as_ref())
+                .and_then(|from| from.username.as_deref())
+                .is_some_and(|username| username.eq_ignore_ascii_case(bot_username));
+        metadata.insert("telegram_mentions_bot".into(), mentions_bot.into());
     }
 
     // Reply-to context for threading
@@ -875,6 +966,35 @@ async fn send_poll(bot: &Bot, chat_id: ChatId, poll: &crate::Poll) -> anyhow::Re
     Ok(())
 }
 
+/// Edit a streaming message to show `text`, truncated to the message limit.
+async fn edit_stream(bot: &Bot, stream: &mut ActiveStream, text: String) {
+    let display_text = if text.len() > MAX_MESSAGE_LENGTH {
+        let end = text.floor_char_boundary(MAX_MESSAGE_LENGTH - 3);
+        format!("{}...", &text[..end])
+    } else {
+        text
+    };
+
+    let html = format::telegram_html(&display_text);
+    if let Err(html_error) = bot
+        .edit_message_text(stream.chat_id, stream.message_id, &html)
+        .parse_mode(ParseMode::Html)
+        .send()
+        .await
+    {
+        tracing::debug!(%html_error, "HTML edit failed, retrying as plain text");
+        if let Err(error) = bot
+            .edit_message_text(stream.chat_id, stream.message_id, &display_text)
+            .send()
+            .await
+        {
+            tracing::debug!(%error, "failed to edit streaming message");
+        }
+    }
+    stream.pending = None;
+    stream.last_edit = Instant::now();
+}
+
 /// Split a message into chunks that fit within Telegram's character limit.
 /// Tries to split at newlines, then spaces, then hard-cuts.
 fn split_message(text: &str, max_len: usize) -> Vec<String> {
@@ -908,28 +1028,6 @@ fn should_retry_plain_caption(error: &RequestError) -> bool {
     matches!(error, RequestError::Api(ApiError::CantParseEntities(_)))
 }
 
-// -- Markdown-to-Telegram-HTML formatting --
-
-static BOLD_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"\*\*(.+?)\*\*").expect("hardcoded regex"));
-static BOLD_UNDERSCORE_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"__(.+?)__").expect("hardcoded regex"));
-static ITALIC_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"\*(.+?)\*").expect("hardcoded regex"));
-static ITALIC_UNDERSCORE_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"_(.+?)_").expect("hardcoded regex"));
-static STRIKETHROUGH_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"~~(.+?)~~").expect("hardcoded regex"));
-static LINK_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").expect("hardcoded regex"));
-
-/// Escape characters that have special meaning in Telegram's HTML parse mode.
-fn escape_html(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-}
-
 /// Strip HTML tags and unescape entities, producing plain text for fallback.
 fn strip_html_tags(html: &str) -> String {
     static TAG_PATTERN: LazyLock<Regex> =
@@ -941,140 +1039,6 @@ fn strip_html_tags(html: &str) -> String {
         .replace("&gt;", ">")
 }
 
-/// Convert markdown to Telegram-compatible HTML.
-///
-/// Handles fenced code blocks, inline code, bold, italic, strikethrough,
-/// links, headers (rendered as bold), and blockquotes.
-fn markdown_to_telegram_html(markdown: &str) -> String {
-    let mut result = String::with_capacity(markdown.len());
-    let mut in_code_block = false;
-    let mut code_language = String::new();
-    let mut code_lines: Vec<&str> = Vec::new();
-    let mut blockquote_lines: Vec<String> = Vec::new();
-
-    for line in markdown.lines() {
-        if let Some(rest) = line.strip_prefix("```") {
-            flush_blockquote(&mut result, &mut blockquote_lines);
-
-            if in_code_block {
-                let content = escape_html(&code_lines.join("\n"));
-                if code_language.is_empty() {
-                    result.push_str("<pre>");
-                    result.push_str(&content);
-                    result.push_str("</pre>\n");
-                } else {
-                    result.push_str("<pre><code class=\"language-");
-                    result.push_str(&code_language);
-                    result.push_str("\">");
-                    result.push_str(&content);
-                    result.push_str("</code></pre>\n");
-                }
-                in_code_block = false;
-                code_language.clear();
-                code_lines.clear();
-            } else {
-                in_code_block = true;
-                code_language = rest.trim().to_string();
-            }
-            continue;
-        }
-
-        if in_code_block {
-            code_lines.push(line);
-            continue;
-        }
-
-        if let Some(quote_text) = line.strip_prefix("> ") {
-            blockquote_lines.push(format_inline(quote_text));
-            continue;
-        } else if line == ">" {
-            blockquote_lines.push(String::new());
-            continue;
-        }
-
-        flush_blockquote(&mut result, &mut blockquote_lines);
-
-        if let Some(header_text) = line
-            .strip_prefix("### ")
-            .or_else(|| line.strip_prefix("## "))
-            .or_else(|| line.strip_prefix("# "))
-        {
-            result.push_str("<b>");
-            result.push_str(&format_inline(header_text));
-            result.push_str("</b>\n");
-            continue;
-        }
-
-        result.push_str(&format_inline(line));
-        result.push('\n');
-    }
-
-    if in_code_block {
-        result.push_str("<pre>");
-        result.push_str(&escape_html(&code_lines.join("\n")));
-        result.push_str("</pre>\n");
-    }
-
-    flush_blockquote(&mut result, &mut blockquote_lines);
-
-    while result.ends_with('\n') {
-        result.pop();
-    }
-
-    result
-}
-
-/// Append buffered blockquote lines to the result and clear the buffer.
-fn flush_blockquote(result: &mut String, lines: &mut Vec<String>) {
-    if lines.is_empty() {
-        return;
-    }
-    result.push_str("<blockquote>");
-    result.push_str(&lines.join("\n"));
-    result.push_str("</blockquote>\n");
-    lines.clear();
-}
-
-/// Convert inline markdown elements to HTML within a single line.
-///
-/// Splits on backticks to isolate inline code spans, then converts bold,
-/// italic, strikethrough and links in the remaining text. Content inside
-/// backticks is HTML-escaped but not processed for markdown.
-fn format_inline(line: &str) -> String {
-    let segments: Vec<&str> = line.split('`').collect();
-    let mut result = String::new();
-
-    for (index, segment) in segments.iter().enumerate() {
-        if index % 2 == 1 && index < segments.len() - 1 {
-            result.push_str("<code>");
-            result.push_str(&escape_html(segment));
-            result.push_str("</code>");
-        } else if index % 2 == 0 {
-            result.push_str(&format_markdown_spans(&escape_html(segment)));
-        } else {
-            // Unmatched trailing backtick — treat as literal
-            result.push('`');
-            result.push_str(&format_markdown_spans(&escape_html(segment)));
-        }
-    }
-
-    result
-}
-
-/// Replace markdown span markers with HTML tags in already-escaped text.
-///
-/// Bold (`**`) is processed before italic (`*`) so double-star patterns
-/// are consumed first and single stars only match true italic spans.
-fn format_markdown_spans(text: &str) -> String {
-    let text = BOLD_PATTERN.replace_all(text, "<b>$1</b>");
-    let text = BOLD_UNDERSCORE_PATTERN.replace_all(&text, "<b>$1</b>");
-    let text = ITALIC_PATTERN.replace_all(&text, "<i>$1</i>");
-    let text = ITALIC_UNDERSCORE_PATTERN.replace_all(&text, "<i>$1</i>");
-    let text = STRIKETHROUGH_PATTERN.replace_all(&text, "<s>$1</s>");
-    let text = LINK_PATTERN.replace_all(&text, r#"<a href="$2">$1</a>"#);
-    text.into_owned()
-}
-
 /// Send a plain text Telegram message for formatting fallback paths.
 async fn send_plain_text(
     bot: &Bot,
@@ -1104,7 +1068,7 @@ async fn send_formatted(
     let mut pending_chunks: VecDeque<String> =
         VecDeque::from(split_message(text, MAX_MESSAGE_LENGTH));
     while let Some(markdown_chunk) = pending_chunks.pop_front() {
-        let html_chunk = markdown_to_telegram_html(&markdown_chunk);
+        let html_chunk = format::telegram_html(&markdown_chunk);
 
         if html_chunk.len() > MAX_MESSAGE_LENGTH {
             let smaller_chunks = split_message(&markdown_chunk, FORMATTED_SPLIT_LENGTH);
@@ -1139,148 +1103,6 @@ async fn send_formatted(
 mod tests {
     use super::*;
 
-    #[test]
-    fn bold() {
-        assert_eq!(
-            markdown_to_telegram_html("**bold text**"),
-            "<b>bold text</b>"
-        );
-    }
-
-    #[test]
-    fn italic() {
-        assert_eq!(
-            markdown_to_telegram_html("*italic text*"),
-            "<i>italic text</i>"
-        );
-    }
-
-    #[test]
-    fn bold_with_underscores() {
-        assert_eq!(
-            markdown_to_telegram_html("__bold text__"),
-            "<b>bold text</b>"
-        );
-    }
-
-    #[test]
-    fn italic_with_underscores() {
-        assert_eq!(
-            markdown_to_telegram_html("_italic text_"),
-            "<i>italic text</i>"
-        );
-    }
-
-    #[test]
-    fn bold_and_italic_nested() {
-        assert_eq!(
-            markdown_to_telegram_html("***both***"),
-            "<b><i>both</i></b>"
-        );
-    }
-
-    #[test]
-    fn inline_code() {
-        assert_eq!(
-            markdown_to_telegram_html("use `println!` here"),
-            "use <code>println!</code> here"
-        );
-    }
-
-    #[test]
-    fn code_block_with_language() {
-        let input = "```rust\nfn main() {}\n```";
-        let expected = "<pre><code class=\"language-rust\">fn main() {}</code></pre>";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
-    #[test]
-    fn code_block_without_language() {
-        let input = "```\nhello world\n```";
-        let expected = "<pre>hello world</pre>";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
-    #[test]
-    fn code_block_escapes_html() {
-        let input = "```\n<script>alert(1)</script>\n```";
-        let expected = "<pre>&lt;script&gt;alert(1)&lt;/script&gt;</pre>";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
-    #[test]
-    fn link() {
-        assert_eq!(
-            markdown_to_telegram_html("[click](https://example.com)"),
-            r#"<a href="https://example.com">click</a>"#
-        );
-    }
-
-    #[test]
-    fn strikethrough() {
-        assert_eq!(markdown_to_telegram_html("~~deleted~~"), "<s>deleted</s>");
-    }
-
-    #[test]
-    fn headers_render_as_bold() {
-        assert_eq!(markdown_to_telegram_html("# Title"), "<b>Title</b>");
-        assert_eq!(markdown_to_telegram_html("## Sub"), "<b>Sub</b>");
-        assert_eq!(markdown_to_telegram_html("### Section"), "<b>Section</b>");
-    }
-
-    #[test]
-    fn blockquote() {
-        assert_eq!(
-            markdown_to_telegram_html("> quoted text"),
-            "<blockquote>quoted text</blockquote>"
-        );
-    }
-
-    #[test]
-    fn multiline_blockquote() {
-        let input = "> line one\n> line two";
-        let expected = "<blockquote>line one\nline two</blockquote>";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
-    #[test]
-    fn html_entities_escaped_in_text() {
-        assert_eq!(
-            markdown_to_telegram_html("x < y & a > b"),
-            "x &lt; y &amp; a &gt; b"
-        );
-    }
-
-    #[test]
-    fn inline_code_escapes_html() {
-        assert_eq!(
-            markdown_to_telegram_html("`<b>not bold</b>`"),
-            "<code>&lt;b&gt;not bold&lt;/b&gt;</code>"
-        );
-    }
-
-    #[test]
-    fn mixed_formatting() {
-        let input = "Hello **world**, this is *important* and `code`";
-        let expected = "Hello <b>world</b>, this is <i>important</i> and <code>code</code>";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
-    #[test]
-    fn plain_text_unchanged() {
-        assert_eq!(
-            markdown_to_telegram_html("just plain text"),
-            "just plain text"
-        );
-    }
-
-    #[test]
-    fn unclosed_code_block_handled() {
-        let input = "```python\nprint('hi')";
-        let expected = "<pre>print('hi')</pre>";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
     #[test]
     fn strip_html_tags_and_unescape() {
         assert_eq!(
@@ -1289,13 +1111,6 @@ mod tests {
         );
     }
 
-    #[test]
-    fn list_items_pass_through() {
-        let input = "- item one\n- item two\n- item three";
-        let expected = "- item one\n- item two\n- item three";
-        assert_eq!(markdown_to_telegram_html(input), expected);
-    }
-
     #[test]
     fn retries_plain_caption_only_for_parse_entity_errors() {
         let parse_error = RequestError::Api(ApiError::CantParseEntities(
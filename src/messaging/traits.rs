@@ -3,6 +3,7 @@
 use crate::error::Result;
 use crate::{InboundMessage, OutboundResponse, StatusUpdate};
 use futures::Stream;
+use std::collections::HashMap;
 use std::pin::Pin;
 
 /// Message stream type.
@@ -16,6 +17,52 @@ pub struct HistoryMessage {
     pub is_bot: bool,
 }
 
+/// A thread created on the platform, addressed like any other conversation.
+#[derive(Debug, Clone)]
+pub struct CreatedThread {
+    pub conversation_id: String,
+    /// Adapter metadata that routes replies into the thread.
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+/// What the bot shows as its status on platforms with presence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Presence {
+    /// Shown as away instead of online.
+    pub idle: bool,
+    pub activity: Option<Activity>,
+}
+
+/// The line shown under the bot's name, such as "Watching 3 workers".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Activity {
+    pub kind: ActivityKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityKind {
+    Playing,
+    Listening,
+    Watching,
+    Competing,
+    /// Only the text, without a verb.
+    Custom,
+}
+
+impl ActivityKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "playing" => Some(Self::Playing),
+            "listening" => Some(Self::Listening),
+            "watching" => Some(Self::Watching),
+            "competing" => Some(Self::Competing),
+            "custom" => Some(Self::Custom),
+            _ => None,
+        }
+    }
+}
+
 /// Static trait for messaging adapters.
 /// Use this for type-safe implementations.
 pub trait Messaging: Send + Sync + 'static {
@@ -32,6 +79,21 @@ pub trait Messaging: Send + Sync + 'static {
         response: OutboundResponse,
     ) -> impl std::future::Future<Output = Result<()>> + Send;
 
+    /// Whether `respond` and `broadcast` send an `Attachments` response
+    /// themselves. Otherwise the manager splits it into one `File` response
+    /// per file (see [`crate::attachment_files`]) and the adapter never
+    /// receives `Attachments`, so it can ignore the variant.
+    fn supports_attachments(&self) -> bool {
+        false
+    }
+
+    /// Whether `respond` shows `StreamStart`/`StreamChunk`/`StreamEnd` by
+    /// editing a message in place. Otherwise a stream's text arrives as a
+    /// `Text` response after it ends.
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
     /// Send a status update.
     fn send_status(
         &self,
@@ -62,6 +124,47 @@ pub trait Messaging: Send + Sync + 'static {
         async { Ok(Vec::new()) }
     }
 
+    /// Start a thread off the given message, for forking a conversation.
+    fn create_thread(
+        &self,
+        message: &InboundMessage,
+        name: &str,
+    ) -> impl std::future::Future<Output = Result<CreatedThread>> + Send {
+        let _ = (message, name);
+        let adapter = self.name().to_string();
+        async move {
+            Err(crate::error::Error::Other(anyhow::anyhow!(
+                "{adapter} doesn't support threads"
+            )))
+        }
+    }
+
+    /// Play speech where the message was spoken, for adapters that listen
+    /// in voice channels. `audio` is 16-bit little-endian mono PCM.
+    fn speak(
+        &self,
+        message: &InboundMessage,
+        audio: Vec<u8>,
+        sample_rate: u32,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let _ = (message, audio, sample_rate);
+        let adapter = self.name().to_string();
+        async move {
+            Err(crate::error::Error::Other(anyhow::anyhow!(
+                "{adapter} doesn't support voice"
+            )))
+        }
+    }
+
+    /// Show `presence` as the bot's status. Adapters without presence ignore it.
+    fn set_presence(
+        &self,
+        presence: Presence,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let _ = presence;
+        async { Ok(()) }
+    }
+
     /// Health check.
     fn health_check(&self) -> impl std::future::Future<Output = Result<()>> + Send;
 
@@ -86,6 +189,10 @@ pub trait MessagingDyn: Send + Sync + 'static {
         response: OutboundResponse,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
 
+    fn supports_attachments(&self) -> bool;
+
+    fn supports_streaming(&self) -> bool;
+
     fn send_status<'a>(
         &'a self,
         message: &'a InboundMessage,
@@ -104,6 +211,24 @@ pub trait MessagingDyn: Send + Sync + 'static {
         limit: usize,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<Vec<HistoryMessage>>> + Send + 'a>>;
 
+    fn create_thread<'a>(
+        &'a self,
+        message: &'a InboundMessage,
+        name: &'a str,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<CreatedThread>> + Send + 'a>>;
+
+    fn speak<'a>(
+        &'a self,
+        message: &'a InboundMessage,
+        audio: Vec<u8>,
+        sample_rate: u32,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
+    fn set_presence<'a>(
+        &'a self,
+        presence: Presence,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
     fn health_check<'a>(
         &'a self,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
@@ -132,6 +257,14 @@ impl<T: Messaging> MessagingDyn for T {
         Box::pin(Messaging::respond(self, message, response))
     }
 
+    fn supports_attachments(&self) -> bool {
+        Messaging::supports_attachments(self)
+    }
+
+    fn supports_streaming(&self) -> bool {
+        Messaging::supports_streaming(self)
+    }
+
     fn send_status<'a>(
         &'a self,
         message: &'a InboundMessage,
@@ -156,6 +289,30 @@ impl<T: Messaging> MessagingDyn for T {
         Box::pin(Messaging::fetch_history(self, message, limit))
     }
 
+    fn create_thread<'a>(
+        &'a self,
+        message: &'a InboundMessage,
+        name: &'a str,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<CreatedThread>> + Send + 'a>> {
+        Box::pin(Messaging::create_thread(self, message, name))
+    }
+
+    fn speak<'a>(
+        &'a self,
+        message: &'a InboundMessage,
+        audio: Vec<u8>,
+        sample_rate: u32,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::speak(self, message, audio, sample_rate))
+    }
+
+    fn set_presence<'a>(
+        &'a self,
+        presence: Presence,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::set_presence(self, presence))
+    }
+
     fn health_check<'a>(
         &'a self,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
@@ -1,6 +1,7 @@
 //! Twitch chat messaging adapter using twitch-irc.
 
 use crate::config::TwitchPermissions;
+use crate::messaging::format;
 use crate::messaging::traits::{InboundStream, Messaging};
 use crate::{InboundMessage, MessageContent, OutboundResponse};
 
@@ -324,7 +325,7 @@ impl Messaging for TwitchAdapter {
 
         match response {
             OutboundResponse::Text(text) => {
-                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                for chunk in split_message(&format::plain_text(&text), MAX_MESSAGE_LENGTH) {
                     client
                         .say(channel.to_owned(), chunk)
                         .await
@@ -332,7 +333,7 @@ impl Messaging for TwitchAdapter {
                 }
             }
             OutboundResponse::RichMessage { text, .. } => {
-                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                for chunk in split_message(&format::plain_text(&text), MAX_MESSAGE_LENGTH) {
                     client
                         .say(channel.to_owned(), chunk)
                         .await
@@ -346,7 +347,7 @@ impl Messaging for TwitchAdapter {
                     .get("twitch_message_id")
                     .and_then(|v| v.as_str());
 
-                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                for chunk in split_message(&format::plain_text(&text), MAX_MESSAGE_LENGTH) {
                     if let Some(parent_id) = reply_to_id {
                         let reply_ref = (channel, parent_id);
                         client
@@ -386,6 +387,7 @@ impl Messaging for TwitchAdapter {
             OutboundResponse::Reaction(_)
             | OutboundResponse::RemoveReaction(_)
             | OutboundResponse::Status(_) => {}
+            OutboundResponse::Attachments { .. } => {}
             OutboundResponse::Ephemeral { text, .. } => {
                 // No ephemeral concept in Twitch — send as regular chat message
                 client
@@ -400,6 +402,24 @@ impl Messaging for TwitchAdapter {
                     .await
                     .context("failed to send scheduled message fallback on twitch")?;
             }
+            OutboundResponse::Choices { prompt, options } => {
+                let text = crate::choices_text(&prompt, &options);
+                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                    client
+                        .say(channel.to_owned(), chunk)
+                        .await
+                        .context("failed to send twitch choices")?;
+                }
+            }
+            OutboundResponse::Rich { text, card } => {
+                let text = crate::rich_text(&text, &card);
+                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                    client
+                        .say(channel.to_owned(), chunk)
+                        .await
+                        .context("failed to send twitch rich message")?;
+                }
+            }
         }
 
         Ok(())
@@ -413,7 +433,7 @@ impl Messaging for TwitchAdapter {
 
         if let OutboundResponse::Text(text) = response {
             let channel = target.strip_prefix('#').unwrap_or(target);
-            for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+            for chunk in split_message(&format::plain_text(&text), MAX_MESSAGE_LENGTH) {
                 client
                     .say(channel.to_owned(), chunk)
                     .await
@@ -421,7 +441,7 @@ impl Messaging for TwitchAdapter {
             }
         } else if let OutboundResponse::RichMessage { text, .. } = response {
             let channel = target.strip_prefix('#').unwrap_or(target);
-            for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+            for chunk in split_message(&format::plain_text(&text), MAX_MESSAGE_LENGTH) {
                 client
                     .say(channel.to_owned(), chunk)
                     .await
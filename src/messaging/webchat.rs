@@ -63,6 +63,10 @@ impl Messaging for WebChatAdapter {
         "webchat"
     }
 
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
     async fn start(&self) -> crate::Result<InboundStream> {
         // Inbound messages bypass the stream via inject_message, so return
         // a stream that stays open but never yields.
@@ -83,10 +87,18 @@ impl Messaging for WebChatAdapter {
         let (event, signals_done) = match response {
             OutboundResponse::Text(text) => (WebChatEvent::Text(text), true),
             OutboundResponse::ThreadReply { text, .. } => (WebChatEvent::Text(text), true),
+            OutboundResponse::Choices { prompt, options } => (
+                WebChatEvent::Text(crate::choices_text(&prompt, &options)),
+                true,
+            ),
+            OutboundResponse::Rich { text, card } => {
+                (WebChatEvent::Text(crate::rich_text(&text, &card)), true)
+            }
             OutboundResponse::StreamStart => (WebChatEvent::StreamStart, false),
             OutboundResponse::StreamChunk(text) => (WebChatEvent::StreamChunk(text), false),
             OutboundResponse::StreamEnd => (WebChatEvent::StreamEnd, true),
             OutboundResponse::File { .. }
+            | OutboundResponse::Attachments { .. }
             | OutboundResponse::Reaction(_)
             | OutboundResponse::RemoveReaction(_)
             | OutboundResponse::Ephemeral { .. }
@@ -4,6 +4,19 @@
 //! delivers responses via a per-conversation polling endpoint. This is
 //! the integration point for scripts, CI pipelines, and other programs
 //! that need to interact with Spacebot programmatically.
+//!
+//! A backend holding the auth token can issue per-user session tokens via
+//! `POST /sessions`. Clients then send and poll with `X-Session-Token`,
+//! and their sender and conversation come from the session.
+//!
+//! With a callback URL configured, responses are pushed there instead of
+//! buffered, retrying with backoff. Either way each response carries an ID
+//! whose delivery state can be queried under `/deliveries`.
+//!
+//! With a signing secret or named sources configured, `/send` bodies must be
+//! signed with HMAC-SHA256 (`X-Spacebot-Signature: sha256=<hex>`), and the
+//! auth token alone no longer lets a request in. Signing only covers `/send`,
+//! so config loading requires an auth token alongside it.
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -13,37 +26,71 @@ use axum::Router;
 use axum::extract::{Json, State};
 use axum::http::header::AUTHORIZATION;
 use axum::http::{HeaderMap, StatusCode};
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{RwLock, mpsc};
 
+use crate::messaging::delivery::{Delivery, DeliveryError, DeliveryStatus, DeliveryStore};
+use crate::messaging::session::{NewSession, Session, SessionError, SessionStore};
 use crate::messaging::traits::{InboundStream, Messaging};
-use crate::{InboundMessage, MessageContent, OutboundResponse};
+use crate::{InboundMessage, MessageContent, OutboundFile, OutboundResponse};
 
 /// Webhook adapter state.
 pub struct WebhookAdapter {
     port: u16,
     bind: String,
     auth_token: Option<String>,
+    signing: Arc<SigningConfig>,
     inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
     /// Buffered responses per conversation_id, waiting to be polled.
-    response_buffers: Arc<RwLock<HashMap<String, Vec<WebhookResponse>>>>,
+    response_buffers: Arc<RwLock<HashMap<String, Vec<BufferedResponse>>>>,
     shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    sessions: Option<Arc<SessionStore>>,
+    deliveries: Option<Arc<DeliveryStore>>,
+    callback: Option<CallbackConfig>,
+    /// Queue of responses for the callback pusher, in order.
+    callback_tx: Arc<RwLock<Option<mpsc::UnboundedSender<(String, BufferedResponse)>>>>,
+}
+
+/// Where and how persistently responses are pushed.
+#[derive(Debug, Clone)]
+struct CallbackConfig {
+    url: String,
+    max_attempts: u32,
+}
+
+/// Keys `/send` bodies are signed with.
+#[derive(Debug, Default)]
+struct SigningConfig {
+    /// Key for requests that don't name a source.
+    secret: Option<String>,
+    /// Keys of named sources, by name.
+    sources: HashMap<String, String>,
+}
+
+impl SigningConfig {
+    fn is_enabled(&self) -> bool {
+        self.secret.is_some() || !self.sources.is_empty()
+    }
 }
 
 /// Shared state for axum handlers.
 #[derive(Clone)]
 struct AppState {
     inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
-    response_buffers: Arc<RwLock<HashMap<String, Vec<WebhookResponse>>>>,
+    response_buffers: Arc<RwLock<HashMap<String, Vec<BufferedResponse>>>>,
     auth_token: Option<String>,
+    signing: Arc<SigningConfig>,
+    sessions: Option<Arc<SessionStore>>,
+    deliveries: Option<Arc<DeliveryStore>>,
 }
 
 /// Inbound webhook request body.
 #[derive(Debug, Deserialize)]
 struct WebhookRequest {
     /// Unique conversation identifier. Reuse the same ID to continue a conversation.
-    conversation_id: String,
+    /// Ignored when the request carries a session token.
+    conversation_id: Option<String>,
     /// Sender identifier (e.g. a username or service name).
     #[serde(default = "default_sender")]
     sender_id: String,
@@ -57,6 +104,34 @@ fn default_sender() -> String {
     "webhook".into()
 }
 
+/// Body for `POST /sessions`.
+#[derive(Debug, Deserialize)]
+struct CreateSessionRequest {
+    /// Stable identity of the user in the caller's system.
+    user_id: String,
+    display_name: Option<String>,
+    /// Defaults to a per-user conversation that survives token rotation.
+    conversation_id: Option<String>,
+    agent_id: Option<String>,
+    ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateSessionResponse {
+    token: String,
+    session: Session,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListSessionsQuery {
+    user_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListSessionsResponse {
+    sessions: Vec<Session>,
+}
+
 /// A buffered response waiting to be polled.
 #[derive(Debug, Clone, Serialize)]
 struct WebhookResponse {
@@ -66,12 +141,42 @@ struct WebhookResponse {
     filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     caption: Option<String>,
+    /// File contents, base64-encoded, for `file` and `attachments`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    files: Vec<OutboundFile>,
+}
+
+/// A response with the ID its delivery is tracked under.
+#[derive(Debug, Clone, Serialize)]
+struct BufferedResponse {
+    id: String,
+    #[serde(flatten)]
+    response: WebhookResponse,
 }
 
 /// Response from the poll endpoint.
 #[derive(Debug, Serialize)]
 struct PollResponse {
-    messages: Vec<WebhookResponse>,
+    messages: Vec<BufferedResponse>,
+}
+
+/// Body pushed to the callback URL.
+#[derive(Debug, Serialize)]
+struct CallbackPayload<'a> {
+    conversation_id: &'a str,
+    message: &'a BufferedResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListDeliveriesQuery {
+    /// Required with the auth token; a session token implies its conversation.
+    conversation_id: Option<String>,
+    status: Option<DeliveryStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListDeliveriesResponse {
+    deliveries: Vec<Delivery>,
 }
 
 impl WebhookAdapter {
@@ -80,11 +185,48 @@ impl WebhookAdapter {
             port,
             bind: bind.into(),
             auth_token,
+            signing: Arc::default(),
             inbound_tx: Arc::new(RwLock::new(None)),
             response_buffers: Arc::new(RwLock::new(HashMap::new())),
             shutdown_tx: Arc::new(RwLock::new(None)),
+            sessions: None,
+            deliveries: None,
+            callback: None,
+            callback_tx: Arc::new(RwLock::new(None)),
         }
     }
+
+    /// Enable session tokens backed by the given store.
+    pub fn with_sessions(mut self, sessions: Option<Arc<SessionStore>>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Track the delivery state of responses in the given store.
+    pub fn with_deliveries(mut self, deliveries: Option<Arc<DeliveryStore>>) -> Self {
+        self.deliveries = deliveries;
+        self
+    }
+
+    /// Require `/send` bodies to be signed with `secret`, or with the key of
+    /// the source named in `X-Webhook-Source`.
+    pub fn with_signing(
+        mut self,
+        secret: Option<String>,
+        sources: HashMap<String, String>,
+    ) -> Self {
+        self.signing = Arc::new(SigningConfig { secret, sources });
+        self
+    }
+
+    /// Push responses to `url`, trying each up to `max_attempts` times.
+    pub fn with_callback(mut self, url: Option<String>, max_attempts: u32) -> Self {
+        self.callback = url.map(|url| CallbackConfig {
+            url,
+            max_attempts: max_attempts.max(1),
+        });
+        self
+    }
 }
 
 impl Messaging for WebhookAdapter {
@@ -103,8 +245,23 @@ impl Messaging for WebhookAdapter {
             inbound_tx: self.inbound_tx.clone(),
             response_buffers: self.response_buffers.clone(),
             auth_token: self.auth_token.clone(),
+            signing: self.signing.clone(),
+            sessions: self.sessions.clone(),
+            deliveries: self.deliveries.clone(),
         };
 
+        if let Some(callback) = &self.callback {
+            let (callback_tx, callback_rx) = mpsc::unbounded_channel();
+            *self.callback_tx.write().await = Some(callback_tx);
+            tokio::spawn(push_responses(
+                callback.clone(),
+                callback_rx,
+                self.auth_token.clone(),
+                self.deliveries.clone(),
+                self.response_buffers.clone(),
+            ));
+        }
+
         if self.auth_token.is_none() {
             tracing::warn!(
                 "webhook authentication is disabled because no auth token is configured"
@@ -113,7 +270,16 @@ impl Messaging for WebhookAdapter {
 
         let app = Router::new()
             .route("/send", post(handle_send))
+            .route("/poll", get(handle_session_poll))
             .route("/poll/{conversation_id}", get(handle_poll))
+            .route(
+                "/sessions",
+                post(handle_create_session).get(handle_list_sessions),
+            )
+            .route("/sessions/{session_id}", delete(handle_revoke_session))
+            .route("/deliveries", get(handle_list_deliveries))
+            .route("/deliveries/{delivery_id}", get(handle_get_delivery))
+            .route("/deliveries/{delivery_id}/read", post(handle_mark_read))
             .route("/health", get(handle_health))
             .with_state(state);
 
@@ -153,44 +319,65 @@ impl Messaging for WebhookAdapter {
                 content: Some(text),
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             OutboundResponse::RichMessage { text, .. } => WebhookResponse {
                 response_type: "text".into(),
                 content: Some(text),
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             OutboundResponse::ThreadReply { text, .. } => WebhookResponse {
                 response_type: "text".into(),
                 content: Some(text),
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             OutboundResponse::File {
-                filename, caption, ..
+                filename,
+                data,
+                mime_type,
+                caption,
             } => WebhookResponse {
                 response_type: "file".into(),
                 content: None,
-                filename: Some(filename),
+                filename: Some(filename.clone()),
                 caption,
+                files: vec![OutboundFile {
+                    filename,
+                    data,
+                    mime_type,
+                }],
+            },
+            OutboundResponse::Attachments { text, files } => WebhookResponse {
+                response_type: "attachments".into(),
+                content: text,
+                filename: None,
+                caption: None,
+                files,
             },
             OutboundResponse::StreamStart => WebhookResponse {
                 response_type: "stream_start".into(),
                 content: None,
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             OutboundResponse::StreamChunk(text) => WebhookResponse {
                 response_type: "stream_chunk".into(),
                 content: Some(text),
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             OutboundResponse::StreamEnd => WebhookResponse {
                 response_type: "stream_end".into(),
                 content: None,
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             // Reactions, status updates, and remove-reaction aren't meaningful over webhook
             OutboundResponse::Reaction(_)
@@ -202,25 +389,74 @@ impl Messaging for WebhookAdapter {
                 content: Some(text),
                 filename: None,
                 caption: None,
+                files: Vec::new(),
             },
             OutboundResponse::ScheduledMessage { text, .. } => WebhookResponse {
                 response_type: "text".into(),
                 content: Some(text),
                 filename: None,
                 caption: None,
+                files: Vec::new(),
+            },
+            OutboundResponse::Choices { prompt, options } => WebhookResponse {
+                response_type: "text".into(),
+                content: Some(crate::choices_text(&prompt, &options)),
+                filename: None,
+                caption: None,
+                files: Vec::new(),
+            },
+            OutboundResponse::Rich { text, card } => WebhookResponse {
+                response_type: "text".into(),
+                content: Some(crate::rich_text(&text, &card)),
+                filename: None,
+                caption: None,
+                files: Vec::new(),
             },
         };
 
+        let buffered = BufferedResponse {
+            id: uuid::Uuid::new_v4().to_string(),
+            response: webhook_response,
+        };
+        // Stream chunks aren't tracked; their stream_end is.
+        if let Some(deliveries) = &self.deliveries
+            && buffered.response.response_type != "stream_chunk"
+        {
+            let conversation_id = message
+                .conversation_id
+                .strip_prefix("webhook:")
+                .unwrap_or(&message.conversation_id);
+            let delivery = Delivery::queued(
+                buffered.id.clone(),
+                conversation_id.to_string(),
+                buffered.response.response_type.clone(),
+            );
+            if let Err(error) = deliveries.put(&delivery) {
+                tracing::warn!(%error, "failed to record webhook delivery");
+            }
+        }
+
+        if let Some(callback_tx) = self.callback_tx.read().await.as_ref() {
+            callback_tx
+                .send((message.conversation_id.clone(), buffered))
+                .ok();
+            return Ok(());
+        }
+
         self.response_buffers
             .write()
             .await
             .entry(message.conversation_id.clone())
             .or_default()
-            .push(webhook_response);
+            .push(buffered);
 
         Ok(())
     }
 
+    fn supports_attachments(&self) -> bool {
+        true
+    }
+
     async fn health_check(&self) -> crate::Result<()> {
         Ok(())
     }
@@ -239,12 +475,49 @@ impl Messaging for WebhookAdapter {
 async fn handle_send(
     headers: HeaderMap,
     State(state): State<AppState>,
-    Json(request): Json<WebhookRequest>,
+    body: axum::body::Bytes,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    if !is_authorized(&headers, state.auth_token.as_deref()) {
-        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    let session = resolve_session(&headers, &state)?;
+    let source = match &session {
+        Some(_) => None,
+        None => authorize_send(&headers, &state, &body)?,
+    };
+    let mut request: WebhookRequest = serde_json::from_slice(&body).map_err(|error| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("invalid request body: {error}"),
+        )
+    })?;
+    // A named source speaks as itself unless the request names a sender.
+    if let Some(source) = &source
+        && request.sender_id == default_sender()
+    {
+        request.sender_id = source.clone();
     }
 
+    let (conversation_id, sender_id, display_name, agent_id) = match &session {
+        Some(session) => (
+            session.conversation_id.clone(),
+            session.user_id.clone(),
+            session.sender_name().to_string(),
+            session.agent_id.clone().or(request.agent_id),
+        ),
+        None => {
+            let Some(conversation_id) = request.conversation_id else {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "conversation_id is required without a session token".into(),
+                ));
+            };
+            (
+                conversation_id,
+                request.sender_id.clone(),
+                request.sender_id,
+                request.agent_id,
+            )
+        }
+    };
+
     let tx = state.inbound_tx.read().await;
     let Some(tx) = tx.as_ref() else {
         return Err((
@@ -256,25 +529,32 @@ async fn handle_send(
     let mut metadata = HashMap::new();
     metadata.insert(
         "webhook_conversation_id".into(),
-        serde_json::Value::String(request.conversation_id.clone()),
+        serde_json::Value::String(conversation_id.clone()),
     );
     metadata.insert(
         "display_name".into(),
-        serde_json::Value::String(request.sender_id.clone()),
+        serde_json::Value::String(display_name.clone()),
     );
-
-    let conversation_id = format!("webhook:{}", request.conversation_id);
+    if let Some(session) = &session {
+        metadata.insert(
+            "session_id".into(),
+            serde_json::Value::String(session.id.clone()),
+        );
+    }
+    if let Some(source) = source {
+        metadata.insert("webhook_source".into(), serde_json::Value::String(source));
+    }
 
     let inbound = InboundMessage {
         id: uuid::Uuid::new_v4().to_string(),
         source: "webhook".into(),
-        conversation_id,
-        sender_id: request.sender_id.clone(),
-        agent_id: request.agent_id.map(Into::into),
+        conversation_id: format!("webhook:{conversation_id}"),
+        sender_id,
+        agent_id: agent_id.map(Into::into),
         content: MessageContent::Text(request.content),
         timestamp: chrono::Utc::now(),
         metadata,
-        formatted_author: Some(request.sender_id),
+        formatted_author: Some(display_name),
     };
 
     tx.send(inbound)
@@ -289,10 +569,41 @@ async fn handle_poll(
     State(state): State<AppState>,
     axum::extract::Path(conversation_id): axum::extract::Path<String>,
 ) -> Result<Json<PollResponse>, (StatusCode, String)> {
-    if !is_authorized(&headers, state.auth_token.as_deref()) {
-        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    match resolve_session(&headers, &state)? {
+        Some(session) if session.conversation_id != conversation_id => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "session does not belong to this conversation".into(),
+            ));
+        }
+        Some(_) => {}
+        None if !is_authorized(&headers, state.auth_token.as_deref()) => {
+            return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+        }
+        None => {}
     }
 
+    Ok(Json(drain_responses(&state, &conversation_id).await))
+}
+
+/// Poll the conversation bound to the caller's session token.
+async fn handle_session_poll(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<PollResponse>, (StatusCode, String)> {
+    let Some(session) = resolve_session(&headers, &state)? else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "a session token is required".into(),
+        ));
+    };
+
+    Ok(Json(
+        drain_responses(&state, &session.conversation_id).await,
+    ))
+}
+
+async fn drain_responses(state: &AppState, conversation_id: &str) -> PollResponse {
     let key = format!("webhook:{conversation_id}");
     let messages = state
         .response_buffers
@@ -301,13 +612,366 @@ async fn handle_poll(
         .remove(&key)
         .unwrap_or_default();
 
-    Ok(Json(PollResponse { messages }))
+    if let Some(deliveries) = &state.deliveries {
+        for message in &messages {
+            match deliveries.mark_delivered(&message.id) {
+                Ok(_) | Err(DeliveryError::NotFound) => {}
+                Err(error) => tracing::warn!(%error, "failed to mark webhook delivery"),
+            }
+        }
+    }
+
+    PollResponse { messages }
+}
+
+/// Push queued responses to the callback URL one at a time, so a
+/// conversation's responses arrive in order. A response that fails every
+/// attempt is marked failed and buffered for polling instead.
+async fn push_responses(
+    callback: CallbackConfig,
+    mut queue: mpsc::UnboundedReceiver<(String, BufferedResponse)>,
+    auth_token: Option<String>,
+    deliveries: Option<Arc<DeliveryStore>>,
+    response_buffers: Arc<RwLock<HashMap<String, Vec<BufferedResponse>>>>,
+) {
+    let client = reqwest::Client::new();
+    while let Some((conversation_id, message)) = queue.recv().await {
+        let payload = CallbackPayload {
+            conversation_id: conversation_id
+                .strip_prefix("webhook:")
+                .unwrap_or(&conversation_id),
+            message: &message,
+        };
+
+        let mut last_error = String::new();
+        let mut attempts = 0;
+        for attempt in 1..=callback.max_attempts {
+            attempts = attempt;
+            let mut request = client
+                .post(&callback.url)
+                .header("x-spacebot-delivery-id", &message.id)
+                .json(&payload)
+                .timeout(std::time::Duration::from_secs(10));
+            if let Some(token) = &auth_token {
+                request = request.bearer_auth(token);
+            }
+
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    last_error.clear();
+                    break;
+                }
+                Ok(response) => last_error = format!("callback returned {}", response.status()),
+                Err(error) => last_error = format!("can't reach callback: {error}"),
+            }
+
+            record_attempt(deliveries.as_deref(), &message.id, attempt, &last_error);
+            if attempt < callback.max_attempts {
+                let backoff = std::time::Duration::from_secs(1 << (attempt - 1).min(6));
+                tokio::time::sleep(backoff).await;
+            }
+        }
+
+        if last_error.is_empty() {
+            if let Some(deliveries) = &deliveries {
+                let result = deliveries.update(&message.id, |delivery| {
+                    delivery.attempts = attempts;
+                    delivery.mark_delivered();
+                });
+                if let Err(error) = result
+                    && !matches!(error, DeliveryError::NotFound)
+                {
+                    tracing::warn!(%error, "failed to mark webhook delivery");
+                }
+            }
+            continue;
+        }
+
+        tracing::warn!(
+            delivery_id = %message.id,
+            %conversation_id,
+            error = %last_error,
+            "webhook callback failed, buffering response for polling"
+        );
+        if let Some(deliveries) = &deliveries {
+            let result = deliveries.update(&message.id, |delivery| {
+                delivery.status = DeliveryStatus::Failed;
+            });
+            if let Err(error) = result
+                && !matches!(error, DeliveryError::NotFound)
+            {
+                tracing::warn!(%error, "failed to mark webhook delivery");
+            }
+        }
+        response_buffers
+            .write()
+            .await
+            .entry(conversation_id)
+            .or_default()
+            .push(message);
+    }
+}
+
+fn record_attempt(deliveries: Option<&DeliveryStore>, id: &str, attempt: u32, error: &str) {
+    let Some(deliveries) = deliveries else {
+        return;
+    };
+    let result = deliveries.update(id, |delivery| {
+        delivery.attempts = attempt;
+        delivery.last_error = Some(error.to_string());
+    });
+    if let Err(error) = result
+        && !matches!(error, DeliveryError::NotFound)
+    {
+        tracing::warn!(%error, "failed to record webhook delivery attempt");
+    }
+}
+
+async fn handle_create_session(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(request): Json<CreateSessionRequest>,
+) -> Result<(StatusCode, Json<CreateSessionResponse>), (StatusCode, String)> {
+    if !is_authorized(&headers, state.auth_token.as_deref()) {
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+    let sessions = session_store(&state)?;
+
+    if request.user_id.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "user_id must not be empty".into()));
+    }
+
+    let (token, session) = sessions
+        .issue(NewSession {
+            source: "webhook".into(),
+            user_id: request.user_id,
+            display_name: request.display_name,
+            conversation_id: request.conversation_id,
+            agent_id: request.agent_id,
+            ttl_secs: request.ttl_secs,
+        })
+        .map_err(session_error_response)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateSessionResponse { token, session }),
+    ))
+}
+
+async fn handle_list_sessions(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<ListSessionsQuery>,
+) -> Result<Json<ListSessionsResponse>, (StatusCode, String)> {
+    if !is_authorized(&headers, state.auth_token.as_deref()) {
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+
+    let sessions = session_store(&state)?
+        .list(Some("webhook"), query.user_id.as_deref())
+        .map_err(session_error_response)?;
+
+    Ok(Json(ListSessionsResponse { sessions }))
+}
+
+/// Revoke a session. A client may revoke its own session with its token;
+/// revoking any other session requires the auth token.
+async fn handle_revoke_session(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    axum::extract::Path(session_id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let own_session =
+        resolve_session(&headers, &state)?.is_some_and(|session| session.id == session_id);
+    if !own_session && !is_authorized(&headers, state.auth_token.as_deref()) {
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+
+    let sessions = session_store(&state)?;
+    match sessions.get(&session_id).map_err(session_error_response)? {
+        Some(session) if session.source == "webhook" => {}
+        _ => return Err((StatusCode::NOT_FOUND, "session not found".into())),
+    }
+    sessions
+        .revoke(&session_id)
+        .map_err(session_error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List a conversation's deliveries. A session token lists its own
+/// conversation; the auth token can list any.
+async fn handle_list_deliveries(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<ListDeliveriesQuery>,
+) -> Result<Json<ListDeliveriesResponse>, (StatusCode, String)> {
+    let conversation_id = match resolve_session(&headers, &state)? {
+        Some(session) => session.conversation_id,
+        None if !is_authorized(&headers, state.auth_token.as_deref()) => {
+            return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+        }
+        None => query.conversation_id.ok_or((
+            StatusCode::BAD_REQUEST,
+            "conversation_id is required without a session token".into(),
+        ))?,
+    };
+
+    let deliveries = delivery_store(&state)?
+        .list(&conversation_id, query.status)
+        .map_err(delivery_error_response)?;
+
+    Ok(Json(ListDeliveriesResponse { deliveries }))
+}
+
+async fn handle_get_delivery(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    axum::extract::Path(delivery_id): axum::extract::Path<String>,
+) -> Result<Json<Delivery>, (StatusCode, String)> {
+    let delivery = authorized_delivery(&headers, &state, &delivery_id)?;
+    Ok(Json(delivery))
+}
+
+/// Acknowledge a response as read.
+async fn handle_mark_read(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    axum::extract::Path(delivery_id): axum::extract::Path<String>,
+) -> Result<Json<Delivery>, (StatusCode, String)> {
+    authorized_delivery(&headers, &state, &delivery_id)?;
+    let delivery = delivery_store(&state)?
+        .mark_read(&delivery_id)
+        .map_err(delivery_error_response)?;
+    Ok(Json(delivery))
+}
+
+/// Load a delivery the caller may see: any with the auth token, or one in
+/// the caller's own conversation with a session token.
+fn authorized_delivery(
+    headers: &HeaderMap,
+    state: &AppState,
+    delivery_id: &str,
+) -> Result<Delivery, (StatusCode, String)> {
+    let session = resolve_session(headers, state)?;
+    if session.is_none() && !is_authorized(headers, state.auth_token.as_deref()) {
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+
+    let delivery = delivery_store(state)?
+        .get(delivery_id)
+        .map_err(delivery_error_response)?
+        .ok_or_else(|| delivery_error_response(DeliveryError::NotFound))?;
+    if session.is_some_and(|session| session.conversation_id != delivery.conversation_id) {
+        return Err(delivery_error_response(DeliveryError::NotFound));
+    }
+    Ok(delivery)
 }
 
 async fn handle_health() -> StatusCode {
     StatusCode::OK
 }
 
+fn session_store(state: &AppState) -> Result<&Arc<SessionStore>, (StatusCode, String)> {
+    state.sessions.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "sessions are not enabled".into(),
+    ))
+}
+
+fn delivery_store(state: &AppState) -> Result<&Arc<DeliveryStore>, (StatusCode, String)> {
+    state.deliveries.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "delivery tracking is not enabled".into(),
+    ))
+}
+
+fn delivery_error_response(error: DeliveryError) -> (StatusCode, String) {
+    match error {
+        DeliveryError::NotFound => (StatusCode::NOT_FOUND, error.to_string()),
+        DeliveryError::Storage(_) => {
+            tracing::warn!(%error, "webhook delivery store failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "delivery store error".into(),
+            )
+        }
+    }
+}
+
+/// Resolve the `X-Session-Token` header, if present. A present but invalid
+/// token is an error rather than a fallback to the shared auth token.
+fn resolve_session(
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<Option<Session>, (StatusCode, String)> {
+    let Some(token) = headers
+        .get("x-session-token")
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Ok(None);
+    };
+
+    session_store(state)?
+        .validate(token, "webhook")
+        .map(Some)
+        .map_err(session_error_response)
+}
+
+fn session_error_response(error: SessionError) -> (StatusCode, String) {
+    match error {
+        SessionError::Storage(_) => {
+            tracing::warn!(%error, "webhook session store failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "session store error".into(),
+            )
+        }
+        other => (StatusCode::UNAUTHORIZED, other.to_string()),
+    }
+}
+
+/// Authorize a `/send` without a session token. With signing configured the
+/// body has to carry a valid signature; otherwise the auth token is checked.
+/// Returns the named source that signed the request.
+fn authorize_send(
+    headers: &HeaderMap,
+    state: &AppState,
+    body: &[u8],
+) -> Result<Option<String>, (StatusCode, String)> {
+    if !state.signing.is_enabled() {
+        return if is_authorized(headers, state.auth_token.as_deref()) {
+            Ok(None)
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "unauthorized".into()))
+        };
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let source = header("x-webhook-source");
+    let secret = match source {
+        Some(source) => state.signing.sources.get(source).ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                format!("unknown webhook source '{source}'"),
+            )
+        })?,
+        None => state.signing.secret.as_ref().ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                "X-Webhook-Source is required".into(),
+            )
+        })?,
+    };
+    if !crate::event_webhooks::signature_valid(secret, body, header("x-spacebot-signature")) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid signature".into(),
+        ));
+    }
+    Ok(source.map(str::to_string))
+}
+
 fn is_authorized(headers: &HeaderMap, expected_token: Option<&str>) -> bool {
     let Some(expected_token) = expected_token else {
         return true;
@@ -327,3 +991,70 @@ fn is_authorized(headers: &HeaderMap, expected_token: Option<&str>) -> bool {
         .and_then(|value| value.strip_prefix("Bearer "))
         .is_some_and(|token| token == expected_token)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(signing: SigningConfig) -> AppState {
+        AppState {
+            inbound_tx: Arc::new(RwLock::new(None)),
+            response_buffers: Arc::default(),
+            auth_token: Some("token".into()),
+            signing: Arc::new(signing),
+            sessions: None,
+            deliveries: None,
+        }
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn signed_sends_need_a_valid_signature_from_a_known_source() {
+        let body = br#"{"conversation_id":"ci","content":"build failed"}"#;
+        let state = state(SigningConfig {
+            secret: None,
+            sources: HashMap::from([("ci".to_string(), "ci-secret".to_string())]),
+        });
+        let signature = crate::event_webhooks::sign("ci-secret", body);
+
+        let source = authorize_send(
+            &headers(&[
+                ("x-webhook-source", "ci"),
+                ("x-spacebot-signature", &signature),
+            ]),
+            &state,
+            body,
+        )
+        .unwrap();
+        assert_eq!(source.as_deref(), Some("ci"));
+
+        // The auth token alone no longer gets in.
+        let bearer = headers(&[("authorization", "Bearer token")]);
+        assert!(authorize_send(&bearer, &state, body).is_err());
+        let tampered = headers(&[
+            ("x-webhook-source", "ci"),
+            ("x-spacebot-signature", &signature),
+        ]);
+        assert!(authorize_send(&tampered, &state, b"{}").is_err());
+        let unknown = headers(&[
+            ("x-webhook-source", "cd"),
+            ("x-spacebot-signature", &signature),
+        ]);
+        assert!(authorize_send(&unknown, &state, body).is_err());
+    }
+
+    #[test]
+    fn unsigned_mode_checks_the_auth_token() {
+        let state = state(SigningConfig::default());
+        let bearer = headers(&[("authorization", "Bearer token")]);
+        assert_eq!(authorize_send(&bearer, &state, b"{}").unwrap(), None);
+        assert!(authorize_send(&HeaderMap::new(), &state, b"{}").is_err());
+    }
+}
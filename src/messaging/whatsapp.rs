@@ -0,0 +1,1007 @@
+//! WhatsApp messaging adapter using the Meta Cloud API.
+//!
+//! Meta delivers inbound messages to a webhook served by this adapter, and
+//! replies go out through the Graph API. Each phone number is its own
+//! conversation. Media is downloaded through the Graph API with the access
+//! token and handed to the channel inline, since its URLs need the token
+//! too. Free-form messages can only be sent within 24 hours of the user's
+//! last message; outside that window the configured template is sent
+//! instead, with the reply as its parameter.
+
+use crate::agent::attachment_cache::data_url;
+use crate::config::{WhatsAppConfig, WhatsAppPermissions};
+use crate::messaging::format;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{Attachment, InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::get;
+use serde::Deserialize;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{RwLock, mpsc};
+
+const GRAPH_API_BASE: &str = "https://graph.facebook.com";
+
+/// Characters per text message.
+const MAX_TEXT_CHARS: usize = 4096;
+
+/// Characters per template body parameter.
+const MAX_TEMPLATE_PARAM_CHARS: usize = 1024;
+
+/// Media larger than this isn't downloaded.
+const MAX_MEDIA_BYTES: u64 = 25 * 1024 * 1024;
+
+/// How long after a user's last message free-form replies are allowed.
+const SERVICE_WINDOW: chrono::TimeDelta = chrono::TimeDelta::hours(24);
+
+/// Graph API error code for a free-form message outside the service window.
+const OUTSIDE_WINDOW_ERROR: i64 = 131047;
+
+/// When each phone number last wrote to us.
+type LastInbound = Arc<RwLock<HashMap<String, chrono::DateTime<chrono::Utc>>>>;
+
+/// WhatsApp adapter state.
+pub struct WhatsAppAdapter {
+    api: GraphApi,
+    verify_token: String,
+    app_secret: String,
+    port: u16,
+    bind: String,
+    template_name: Option<String>,
+    template_language: String,
+    permissions: Arc<ArcSwap<WhatsAppPermissions>>,
+    last_inbound: LastInbound,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+/// Shared state for axum handlers.
+#[derive(Clone)]
+struct AppState {
+    api: GraphApi,
+    verify_token: String,
+    app_secret: String,
+    permissions: Arc<ArcSwap<WhatsAppPermissions>>,
+    last_inbound: LastInbound,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+}
+
+impl WhatsAppAdapter {
+    pub fn new(config: &WhatsAppConfig, permissions: Arc<ArcSwap<WhatsAppPermissions>>) -> Self {
+        Self {
+            api: GraphApi {
+                client: reqwest::Client::new(),
+                base_url: format!("{GRAPH_API_BASE}/{}", config.api_version),
+                phone_number_id: config.phone_number_id.clone(),
+                access_token: config.access_token.clone(),
+            },
+            verify_token: config.verify_token.clone(),
+            app_secret: config.app_secret.clone(),
+            port: config.port,
+            bind: config.bind.clone(),
+            template_name: config.template_name.clone(),
+            template_language: config.template_language.clone(),
+            permissions,
+            last_inbound: Arc::new(RwLock::new(HashMap::new())),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Whether `number` wrote to us within the service window.
+    async fn window_open(&self, number: &str) -> bool {
+        self.last_inbound
+            .read()
+            .await
+            .get(number)
+            .is_some_and(|last| chrono::Utc::now() - *last < SERVICE_WINDOW)
+    }
+
+    /// Send text, split to fit, or the template when the window is closed.
+    async fn send_text(&self, to: &str, text: &str, reply_to: Option<&str>) -> anyhow::Result<()> {
+        if self.template_name.is_some() && !self.window_open(to).await {
+            return self.send_template(to, text).await;
+        }
+
+        let text = format::whatsapp(text);
+        for (index, chunk) in split_message(&text, MAX_TEXT_CHARS).into_iter().enumerate() {
+            let mut body = serde_json::json!({
+                "messaging_product": "whatsapp",
+                "recipient_type": "individual",
+                "to": to,
+                "type": "text",
+                "text": { "body": chunk, "preview_url": false },
+            });
+            if index == 0
+                && let Some(message_id) = reply_to
+            {
+                body["context"] = serde_json::json!({ "message_id": message_id });
+            }
+            match self.api.send_message(&body).await {
+                Err(error) if index == 0 && is_outside_window(&error) => {
+                    if self.template_name.is_none() {
+                        return Err(error.context(
+                            "whatsapp service window closed and no template_name is configured",
+                        ));
+                    }
+                    self.last_inbound.write().await.remove(to);
+                    return self.send_template(to, &text).await;
+                }
+                result => {
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the configured template with `text` as its body parameter.
+    async fn send_template(&self, to: &str, text: &str) -> anyhow::Result<()> {
+        let name = self
+            .template_name
+            .as_deref()
+            .context("no whatsapp template configured")?;
+        let body = serde_json::json!({
+            "messaging_product": "whatsapp",
+            "to": to,
+            "type": "template",
+            "template": {
+                "name": name,
+                "language": { "code": self.template_language },
+                "components": [{
+                    "type": "body",
+                    "parameters": [{ "type": "text", "text": template_parameter(text) }],
+                }],
+            },
+        });
+        self.api.send_message(&body).await?;
+        Ok(())
+    }
+
+    async fn send_file(
+        &self,
+        to: &str,
+        filename: &str,
+        data: Vec<u8>,
+        mime_type: &str,
+        caption: Option<String>,
+    ) -> anyhow::Result<()> {
+        let media_id = self.api.upload_media(filename, data, mime_type).await?;
+        let kind = media_kind(mime_type);
+        let mut media = serde_json::json!({ "id": media_id });
+        if let Some(caption) = caption {
+            media["caption"] = serde_json::Value::String(caption);
+        }
+        if kind == "document" {
+            media["filename"] = serde_json::Value::String(filename.to_string());
+        }
+        let mut body = serde_json::json!({
+            "messaging_product": "whatsapp",
+            "recipient_type": "individual",
+            "to": to,
+            "type": kind,
+        });
+        body[kind] = media;
+        self.api.send_message(&body).await?;
+        Ok(())
+    }
+
+    async fn react(&self, to: &str, message_id: &str, emoji: &str) -> anyhow::Result<()> {
+        let body = serde_json::json!({
+            "messaging_product": "whatsapp",
+            "recipient_type": "individual",
+            "to": to,
+            "type": "reaction",
+            "reaction": { "message_id": message_id, "emoji": emoji },
+        });
+        self.api.send_message(&body).await?;
+        Ok(())
+    }
+}
+
+impl Messaging for WhatsAppAdapter {
+    fn name(&self) -> &str {
+        "whatsapp"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let state = AppState {
+            api: self.api.clone(),
+            verify_token: self.verify_token.clone(),
+            app_secret: self.app_secret.clone(),
+            permissions: self.permissions.clone(),
+            last_inbound: self.last_inbound.clone(),
+            inbound_tx,
+        };
+        let app = Router::new()
+            .route("/webhook", get(handle_verify).post(handle_notification))
+            .with_state(state);
+
+        let bind = if self.bind.contains(':') {
+            format!("[{}]:{}", self.bind, self.port)
+        } else {
+            format!("{}:{}", self.bind, self.port)
+        };
+        let listener = tokio::net::TcpListener::bind(&bind)
+            .await
+            .with_context(|| format!("failed to bind whatsapp webhook server to {bind}"))?;
+        tracing::info!(%bind, "whatsapp webhook server listening");
+
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, app)
+                .with_graceful_shutdown(async move {
+                    shutdown_rx.recv().await.ok();
+                })
+                .await
+            {
+                tracing::error!(%error, "whatsapp webhook server exited with error");
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let to = message
+            .metadata
+            .get("whatsapp_from")
+            .and_then(|value| value.as_str())
+            .unwrap_or(&message.sender_id);
+        let message_id = message
+            .metadata
+            .get("whatsapp_message_id")
+            .and_then(|value| value.as_str());
+
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                self.send_text(to, &text, None).await?;
+            }
+            OutboundResponse::Choices { prompt, options } => {
+                self.send_text(to, &crate::choices_text(&prompt, &options), None)
+                    .await?;
+            }
+            OutboundResponse::Rich { text, card } => {
+                self.send_text(to, &crate::rich_text(&text, &card), None)
+                    .await?;
+            }
+            // No threads on WhatsApp; quote the message being answered instead.
+            OutboundResponse::ThreadReply { text, .. } => {
+                self.send_text(to, &text, message_id).await?;
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => {
+                self.send_file(to, &filename, data, &mime_type, caption)
+                    .await?;
+            }
+            OutboundResponse::Reaction(emoji) => {
+                if let Some(message_id) = message_id {
+                    self.react(to, message_id, &emoji).await?;
+                }
+            }
+            // An empty emoji clears our reaction.
+            OutboundResponse::RemoveReaction(_) => {
+                if let Some(message_id) = message_id {
+                    self.react(to, message_id, "").await?;
+                }
+            }
+            // No message editing on WhatsApp; the final text arrives as a
+            // Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd => {}
+            OutboundResponse::Status(status) => {
+                self.send_status(message, status).await?;
+            }
+            OutboundResponse::Attachments { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        // The typing indicator also marks the message read, and clears when
+        // we reply or after 25 seconds.
+        if let StatusUpdate::Thinking = status
+            && let Some(message_id) = message
+                .metadata
+                .get("whatsapp_message_id")
+                .and_then(|value| value.as_str())
+        {
+            let body = serde_json::json!({
+                "messaging_product": "whatsapp",
+                "status": "read",
+                "message_id": message_id,
+                "typing_indicator": { "type": "text" },
+            });
+            if let Err(error) = self.api.send_message(&body).await {
+                tracing::debug!(%error, "failed to show whatsapp typing indicator");
+            }
+        }
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        match response {
+            OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } => {
+                self.send_text(target, &text, None).await?;
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => {
+                self.send_file(target, &filename, data, &mime_type, caption)
+                    .await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        self.api.phone_number().await?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("whatsapp adapter shut down");
+        Ok(())
+    }
+}
+
+// -- Graph API --
+
+/// Client for the Graph API endpoints of one business phone number.
+#[derive(Clone)]
+struct GraphApi {
+    client: reqwest::Client,
+    /// Versioned API root, e.g. `https://graph.facebook.com/v21.0`.
+    base_url: String,
+    phone_number_id: String,
+    access_token: String,
+}
+
+/// An error response from the Graph API.
+#[derive(Debug)]
+struct GraphError {
+    status: reqwest::StatusCode,
+    code: Option<i64>,
+    message: String,
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(
+                f,
+                "whatsapp api error {code} ({}): {}",
+                self.status, self.message
+            ),
+            None => write!(f, "whatsapp api error ({}): {}", self.status, self.message),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+#[derive(Debug, Deserialize)]
+struct MediaInfo {
+    url: String,
+    mime_type: Option<String>,
+    file_size: Option<u64>,
+}
+
+impl GraphApi {
+    async fn send_message(&self, body: &serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/{}/messages",
+                self.base_url, self.phone_number_id
+            ))
+            .bearer_auth(&self.access_token)
+            .json(body)
+            .send()
+            .await
+            .context("failed to reach whatsapp api")?;
+        check_response(response).await
+    }
+
+    async fn phone_number(&self) -> anyhow::Result<serde_json::Value> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.base_url, self.phone_number_id))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .context("failed to reach whatsapp api")?;
+        check_response(response).await
+    }
+
+    /// Download inbound media. Returns its bytes and MIME type.
+    async fn download_media(&self, media_id: &str) -> anyhow::Result<(Vec<u8>, Option<String>)> {
+        let response = self
+            .client
+            .get(format!("{}/{media_id}", self.base_url))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .context("failed to reach whatsapp api")?;
+        let info: MediaInfo = serde_json::from_value(check_response(response).await?)
+            .context("unexpected whatsapp media response")?;
+        if let Some(size) = info.file_size
+            && size > MAX_MEDIA_BYTES
+        {
+            anyhow::bail!("media is {size} bytes, over the {MAX_MEDIA_BYTES} byte limit");
+        }
+
+        let bytes = self
+            .client
+            .get(&info.url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("failed to download whatsapp media")?
+            .bytes()
+            .await
+            .context("failed to download whatsapp media")?;
+        if bytes.len() as u64 > MAX_MEDIA_BYTES {
+            anyhow::bail!(
+                "media is {} bytes, over the {MAX_MEDIA_BYTES} byte limit",
+                bytes.len()
+            );
+        }
+        Ok((bytes.to_vec(), info.mime_type))
+    }
+
+    /// Upload a file for sending. Returns the media ID.
+    async fn upload_media(
+        &self,
+        filename: &str,
+        data: Vec<u8>,
+        mime_type: &str,
+    ) -> anyhow::Result<String> {
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(filename.to_string())
+            .mime_str(mime_type)
+            .context("invalid mime type for whatsapp upload")?;
+        let form = reqwest::multipart::Form::new()
+            .text("messaging_product", "whatsapp")
+            .text("type", mime_type.to_string())
+            .part("file", part);
+        let response = self
+            .client
+            .post(format!("{}/{}/media", self.base_url, self.phone_number_id))
+            .bearer_auth(&self.access_token)
+            .multipart(form)
+            .send()
+            .await
+            .context("failed to reach whatsapp api")?;
+        check_response(response).await?["id"]
+            .as_str()
+            .map(str::to_string)
+            .context("whatsapp media upload returned no id")
+    }
+}
+
+async fn check_response(response: reqwest::Response) -> anyhow::Result<serde_json::Value> {
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    if status.is_success() {
+        return Ok(body);
+    }
+    Err(GraphError {
+        status,
+        code: body["error"]["code"].as_i64(),
+        message: body["error"]["message"]
+            .as_str()
+            .unwrap_or("unknown error")
+            .to_string(),
+    }
+    .into())
+}
+
+fn is_outside_window(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<GraphError>()
+        .is_some_and(|error| error.code == Some(OUTSIDE_WINDOW_ERROR))
+}
+
+// -- Webhook --
+
+#[derive(Debug, Deserialize)]
+struct VerifyQuery {
+    #[serde(rename = "hub.mode")]
+    mode: Option<String>,
+    #[serde(rename = "hub.verify_token")]
+    verify_token: Option<String>,
+    #[serde(rename = "hub.challenge")]
+    challenge: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WebhookPayload {
+    #[serde(default)]
+    entry: Vec<WebhookEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookEntry {
+    #[serde(default)]
+    changes: Vec<WebhookChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookChange {
+    value: ChangeValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeValue {
+    metadata: Option<ChangeMetadata>,
+    #[serde(default)]
+    contacts: Vec<Contact>,
+    #[serde(default)]
+    messages: Vec<WaMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeMetadata {
+    phone_number_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Contact {
+    wa_id: String,
+    profile: Option<ContactProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContactProfile {
+    name: String,
+}
+
+/// An inbound message, as delivered to the webhook.
+#[derive(Debug, Deserialize)]
+struct WaMessage {
+    from: String,
+    id: String,
+    timestamp: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<WaText>,
+    image: Option<WaMedia>,
+    video: Option<WaMedia>,
+    audio: Option<WaMedia>,
+    document: Option<WaMedia>,
+    sticker: Option<WaMedia>,
+    location: Option<WaLocation>,
+    button: Option<WaButton>,
+    interactive: Option<WaInteractive>,
+    reaction: Option<WaReaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaText {
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaMedia {
+    id: String,
+    mime_type: Option<String>,
+    caption: Option<String>,
+    filename: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaLocation {
+    latitude: f64,
+    longitude: f64,
+    name: Option<String>,
+    address: Option<String>,
+}
+
+/// A quick-reply button tapped on a template message.
+#[derive(Debug, Deserialize)]
+struct WaButton {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaInteractive {
+    button_reply: Option<WaReply>,
+    list_reply: Option<WaReply>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaReply {
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaReaction {
+    message_id: String,
+    /// Absent when the user removed their reaction.
+    emoji: Option<String>,
+}
+
+async fn handle_verify(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyQuery>,
+) -> Result<String, StatusCode> {
+    match (query.mode.as_deref(), query.verify_token, query.challenge) {
+        (Some("subscribe"), Some(token), Some(challenge)) if token == state.verify_token => {
+            tracing::info!("whatsapp webhook verified");
+            Ok(challenge)
+        }
+        _ => Err(StatusCode::FORBIDDEN),
+    }
+}
+
+async fn handle_notification(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let signature = headers
+        .get("x-hub-signature-256")
+        .and_then(|value| value.to_str().ok());
+    if !crate::event_webhooks::signature_valid(&state.app_secret, &body, signature) {
+        tracing::warn!("rejected whatsapp webhook with a missing or invalid signature");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload: WebhookPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(error) => {
+            tracing::warn!(%error, "failed to parse whatsapp webhook payload");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    // Acknowledge right away; Meta retries deliveries that take too long.
+    tokio::spawn(async move {
+        for (message, profile_name) in incoming_messages(payload, &state.api.phone_number_id) {
+            if !state
+                .permissions
+                .load()
+                .allowed_numbers
+                .contains(&message.from)
+            {
+                tracing::debug!(from = %message.from, "ignoring whatsapp message from unlisted number");
+                continue;
+            }
+            state
+                .last_inbound
+                .write()
+                .await
+                .insert(message.from.clone(), chrono::Utc::now());
+
+            let Some(inbound) = to_inbound(&state.api, message, profile_name).await else {
+                continue;
+            };
+            if state.inbound_tx.send(inbound).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    StatusCode::OK
+}
+
+/// Messages in a webhook payload addressed to our number, each with the
+/// sender's profile name when Meta included it.
+fn incoming_messages(
+    payload: WebhookPayload,
+    phone_number_id: &str,
+) -> Vec<(WaMessage, Option<String>)> {
+    let mut messages = Vec::new();
+    for change in payload.entry.into_iter().flat_map(|entry| entry.changes) {
+        let value = change.value;
+        if value
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.phone_number_id != phone_number_id)
+        {
+            continue;
+        }
+        for message in value.messages {
+            let profile_name = value
+                .contacts
+                .iter()
+                .find(|contact| contact.wa_id == message.from)
+                .and_then(|contact| contact.profile.as_ref())
+                .map(|profile| profile.name.clone());
+            messages.push((message, profile_name));
+        }
+    }
+    messages
+}
+
+async fn to_inbound(
+    api: &GraphApi,
+    message: WaMessage,
+    profile_name: Option<String>,
+) -> Option<InboundMessage> {
+    let content = match message.kind.as_str() {
+        "text" => MessageContent::Text(message.text?.body),
+        "image" | "video" | "audio" | "document" | "sticker" => {
+            let media = message
+                .image
+                .or(message.video)
+                .or(message.audio)
+                .or(message.document)
+                .or(message.sticker)?;
+            let caption = media.caption.clone();
+            match download_attachment(api, &message.kind, media).await {
+                Ok(attachment) => MessageContent::Media {
+                    text: caption,
+                    attachments: vec![attachment],
+                },
+                Err(error) => {
+                    tracing::warn!(%error, from = %message.from, "failed to download whatsapp media");
+                    MessageContent::Text(format!(
+                        "[{} attachment that couldn't be downloaded]{}",
+                        message.kind,
+                        caption
+                            .map(|caption| format!(" {caption}"))
+                            .unwrap_or_default()
+                    ))
+                }
+            }
+        }
+        "location" => {
+            let location = message.location?;
+            let label = [location.name, location.address]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", ");
+            MessageContent::Text(format!(
+                "[location: {}{}, {}]",
+                if label.is_empty() {
+                    String::new()
+                } else {
+                    format!("{label} at ")
+                },
+                location.latitude,
+                location.longitude
+            ))
+        }
+        "button" => MessageContent::Text(message.button?.text),
+        "interactive" => {
+            let interactive = message.interactive?;
+            MessageContent::Text(interactive.button_reply.or(interactive.list_reply)?.title)
+        }
+        "reaction" => {
+            let reaction = message.reaction?;
+            MessageContent::Reaction {
+                emoji: reaction.emoji?,
+                message_id: reaction.message_id,
+                target_author: None,
+                target_text: None,
+                // Sent message IDs aren't kept, so this can't be told.
+                to_bot: false,
+            }
+        }
+        kind => {
+            tracing::debug!(%kind, "ignoring unsupported whatsapp message type");
+            return None;
+        }
+    };
+
+    let display_name = profile_name.unwrap_or_else(|| format!("+{}", message.from));
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "whatsapp_from".into(),
+        serde_json::Value::String(message.from.clone()),
+    );
+    metadata.insert(
+        "whatsapp_message_id".into(),
+        serde_json::Value::String(message.id.clone()),
+    );
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(display_name.clone()),
+    );
+
+    Some(InboundMessage {
+        id: message.id,
+        source: "whatsapp".into(),
+        conversation_id: format!("whatsapp:{}", message.from),
+        sender_id: message.from,
+        agent_id: None,
+        content,
+        timestamp: message
+            .timestamp
+            .and_then(|timestamp| timestamp.parse::<i64>().ok())
+            .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+            .unwrap_or_else(chrono::Utc::now),
+        metadata,
+        formatted_author: Some(display_name),
+    })
+}
+
+async fn download_attachment(
+    api: &GraphApi,
+    kind: &str,
+    media: WaMedia,
+) -> anyhow::Result<Attachment> {
+    let (bytes, mime_type) = api.download_media(&media.id).await?;
+    let mime_type = mime_type
+        .or(media.mime_type)
+        .unwrap_or_else(|| "application/octet-stream".into());
+    // Voice notes come as `audio/ogg; codecs=opus`.
+    let essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or(&mime_type)
+        .trim()
+        .to_string();
+    let filename = media.filename.unwrap_or_else(|| {
+        let extension = mime_guess::get_mime_extensions_str(&essence)
+            .and_then(|extensions| extensions.first())
+            .unwrap_or(&"bin");
+        format!("{kind}.{extension}")
+    });
+    Ok(Attachment {
+        filename,
+        url: data_url(&essence, &bytes),
+        size_bytes: Some(bytes.len() as u64),
+        mime_type: essence,
+    })
+}
+
+/// The message type a file is sent as. Anything WhatsApp can't show
+/// inline goes out as a document.
+fn media_kind(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/jpeg" | "image/png" => "image",
+        "video/mp4" | "video/3gpp" => "video",
+        _ => "document",
+    }
+}
+
+/// Fit text into a template body parameter, which can't contain newlines,
+/// tabs or runs of more than four spaces.
+fn template_parameter(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= MAX_TEMPLATE_PARAM_CHARS {
+        return collapsed;
+    }
+    let mut truncated: String = collapsed
+        .chars()
+        .take(MAX_TEMPLATE_PARAM_CHARS - 1)
+        .collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Split a message into chunks of at most `max_chars` characters.
+/// Tries to split at newlines, then spaces, then hard-cuts.
+fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+
+    while remaining.chars().count() > max_chars {
+        let limit = remaining
+            .char_indices()
+            .nth(max_chars)
+            .map_or(remaining.len(), |(index, _)| index);
+        let split_at = remaining[..limit]
+            .rfind('\n')
+            .or_else(|| remaining[..limit].rfind(' '))
+            .filter(|&index| index > 0)
+            .unwrap_or(limit);
+
+        chunks.push(remaining[..split_at].to_string());
+        remaining = remaining[split_at..].trim_start();
+    }
+
+    if !remaining.is_empty() || chunks.is_empty() {
+        chunks.push(remaining.to_string());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_payloads_yield_messages_for_our_number() {
+        let payload: WebhookPayload = serde_json::from_str(
+            r#"{
+                "object": "whatsapp_business_account",
+                "entry": [{
+                    "id": "WABA_ID",
+                    "changes": [{
+                        "field": "messages",
+                        "value": {
+                            "messaging_product": "whatsapp",
+                            "metadata": { "display_phone_number": "15550100000", "phone_number_id": "PHONE_ID" },
+                            "contacts": [{ "profile": { "name": "Alice" }, "wa_id": "15550104477" }],
+                            "messages": [{
+                                "from": "15550104477",
+                                "id": "wamid.1",
+                                "timestamp": "1760000000",
+                                "type": "text",
+                                "text": { "body": "hello" }
+                            }]
+                        }
+                    }, {
+                        "field": "messages",
+                        "value": {
+                            "metadata": { "phone_number_id": "OTHER_ID" },
+                            "messages": [{ "from": "1555", "id": "wamid.2", "type": "text", "text": { "body": "not ours" } }]
+                        }
+                    }, {
+                        "field": "messages",
+                        "value": {
+                            "metadata": { "phone_number_id": "PHONE_ID" },
+                            "statuses": [{ "id": "wamid.0", "status": "delivered" }]
+                        }
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let messages = incoming_messages(payload, "PHONE_ID");
+        assert_eq!(messages.len(), 1);
+        let (message, profile_name) = &messages[0];
+        assert_eq!(message.from, "15550104477");
+        assert_eq!(message.text.as_ref().unwrap().body, "hello");
+        assert_eq!(profile_name.as_deref(), Some("Alice"));
+    }
+
+    #[test]
+    fn template_parameters_are_single_line_and_capped() {
+        assert_eq!(
+            template_parameter("Reminder:\n\tstandup     at 10"),
+            "Reminder: standup at 10"
+        );
+        let long = template_parameter(&"a".repeat(2000));
+        assert_eq!(long.chars().count(), MAX_TEMPLATE_PARAM_CHARS);
+        assert!(long.ends_with('…'));
+    }
+
+    #[test]
+    fn long_messages_split_on_char_boundaries() {
+        let text = format!("{} {}", "é".repeat(6), "ü".repeat(6));
+        assert_eq!(split_message(&text, 8), vec!["éééééé", "üüüüüü"]);
+        assert_eq!(split_message(&"x".repeat(5), 2), vec!["xx", "xx", "x"]);
+        assert_eq!(split_message("", 10), vec![""]);
+    }
+}
@@ -0,0 +1,509 @@
+//! XMPP messaging adapter using tokio-xmpp.
+//!
+//! Signs in with a regular account, joins the configured multi-user chat
+//! rooms (MUC) and answers there and in direct chats. Room history isn't
+//! replayed on join, so only messages sent while connected reach an agent.
+//! The connection is re-established with backoff whenever it drops.
+
+use crate::config::XmppPermissions;
+use crate::messaging::format;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use futures::StreamExt as _;
+use tokio_xmpp::Event;
+use tokio_xmpp::jid::{BareJid, Jid};
+use tokio_xmpp::minidom::Element;
+use tokio_xmpp::parsers::message::{Message, MessageType};
+use tokio_xmpp::parsers::muc::Muc;
+use tokio_xmpp::parsers::muc::muc::History;
+use tokio_xmpp::parsers::presence::{Presence, Type as PresenceType};
+use tokio_xmpp::starttls::StartTlsAsyncClient as Client;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
+
+/// Longest wait between reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+
+/// XMPP adapter state.
+pub struct XmppAdapter {
+    jid: String,
+    password: String,
+    nickname: String,
+    rooms: Vec<String>,
+    permissions: Arc<ArcSwap<XmppPermissions>>,
+    /// Outgoing stanzas for the current connection; `None` while reconnecting.
+    sender: Arc<RwLock<Option<mpsc::Sender<Element>>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+impl XmppAdapter {
+    pub fn new(
+        config: &crate::config::XmppConfig,
+        permissions: Arc<ArcSwap<XmppPermissions>>,
+    ) -> Self {
+        Self {
+            jid: config.jid.clone(),
+            password: config.password.clone(),
+            nickname: config.nickname.clone(),
+            rooms: config.rooms.clone(),
+            permissions,
+            sender: Arc::new(RwLock::new(None)),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    async fn send_message(&self, target: ReplyTarget, text: String) -> anyhow::Result<()> {
+        let sender = self
+            .sender
+            .read()
+            .await
+            .clone()
+            .context("xmpp not connected")?;
+        let (to, kind) = match target {
+            ReplyTarget::Room(room) => (room, MessageType::Groupchat),
+            ReplyTarget::Direct(jid) => (jid, MessageType::Chat),
+        };
+        let to = Jid::new(&to).with_context(|| format!("invalid xmpp address '{to}'"))?;
+        let body = format::plain_text(&text);
+        let mut message = Message::new(Some(to)).with_body(String::new(), body);
+        message.type_ = kind;
+        sender
+            .send(message.into())
+            .await
+            .map_err(|_| anyhow::anyhow!("xmpp connection closed"))?;
+        Ok(())
+    }
+}
+
+/// Where a reply goes.
+#[derive(Debug, PartialEq, Eq)]
+enum ReplyTarget {
+    /// A room's bare JID.
+    Room(String),
+    /// A user's bare JID.
+    Direct(String),
+}
+
+/// Settings a session needs, cloned into the connection task.
+#[derive(Clone)]
+struct SessionConfig {
+    jid: String,
+    password: String,
+    nickname: String,
+    rooms: Vec<String>,
+}
+
+impl Messaging for XmppAdapter {
+    fn name(&self) -> &str {
+        "xmpp"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let jid =
+            BareJid::new(&self.jid).with_context(|| format!("invalid xmpp jid '{}'", self.jid))?;
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let session_config = SessionConfig {
+            jid: jid.to_string(),
+            password: self.password.clone(),
+            nickname: self.nickname.clone(),
+            rooms: self.rooms.clone(),
+        };
+        let sender_slot = self.sender.clone();
+        let permissions = self.permissions.clone();
+
+        tokio::spawn(async move {
+            let mut attempt: u32 = 0;
+            loop {
+                let session = run_session(
+                    session_config.clone(),
+                    &sender_slot,
+                    &permissions,
+                    &inbound_tx,
+                    &mut attempt,
+                );
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    result = session => match result {
+                        SessionEnd::Closed => return,
+                        SessionEnd::Dropped(error) => {
+                            tracing::warn!(jid = %session_config.jid, %error, "xmpp connection lost");
+                        }
+                    },
+                }
+                *sender_slot.write().await = None;
+
+                attempt += 1;
+                let delay = Duration::from_secs(1 << attempt.min(8)).min(MAX_RECONNECT_DELAY);
+                tracing::info!(
+                    jid = %session_config.jid,
+                    attempt,
+                    delay_secs = delay.as_secs(),
+                    "reconnecting to xmpp"
+                );
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+
+            *sender_slot.write().await = None;
+            tracing::info!("xmpp message loop shutting down");
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                let target =
+                    reply_target(message).context("missing xmpp reply target in metadata")?;
+                self.send_message(target, text).await?;
+            }
+            OutboundResponse::Choices { prompt, options } => {
+                let target =
+                    reply_target(message).context("missing xmpp reply target in metadata")?;
+                self.send_message(target, crate::choices_text(&prompt, &options))
+                    .await?;
+            }
+            OutboundResponse::Rich { text, card } => {
+                let target =
+                    reply_target(message).context("missing xmpp reply target in metadata")?;
+                self.send_message(target, crate::rich_text(&text, &card))
+                    .await?;
+            }
+            OutboundResponse::File {
+                filename, caption, ..
+            } => {
+                // No file upload without an HTTP upload service — send a note
+                let text = match caption {
+                    Some(caption) => format!("[File: {filename}] {caption}"),
+                    None => format!("[File: {filename}]"),
+                };
+                let target =
+                    reply_target(message).context("missing xmpp reply target in metadata")?;
+                self.send_message(target, text).await?;
+            }
+            // Messages aren't edited in place; the final text arrives as a
+            // Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd => {}
+            OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => {}
+            OutboundResponse::Attachments { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let target = match target.strip_prefix("dm:") {
+            Some(jid) => ReplyTarget::Direct(jid.to_string()),
+            None => ReplyTarget::Room(target.to_string()),
+        };
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            self.send_message(target, text).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if self.sender.read().await.is_none() {
+            return Err(anyhow::anyhow!("xmpp not connected").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("xmpp adapter shut down");
+        Ok(())
+    }
+}
+
+/// Why a connection ended.
+enum SessionEnd {
+    /// The channel stopped listening; don't reconnect.
+    Closed,
+    /// The connection failed or dropped; reconnect.
+    Dropped(anyhow::Error),
+}
+
+/// Connect, join the rooms and forward messages until the connection ends.
+async fn run_session(
+    config: SessionConfig,
+    sender_slot: &RwLock<Option<mpsc::Sender<Element>>>,
+    permissions: &ArcSwap<XmppPermissions>,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+    attempt: &mut u32,
+) -> SessionEnd {
+    let jid = match BareJid::new(&config.jid) {
+        Ok(jid) => jid,
+        Err(error) => return SessionEnd::Dropped(error.into()),
+    };
+    // Found through the domain's SRV record, then upgraded with STARTTLS.
+    let mut client = Client::new(jid, config.password.clone());
+    let (outbound_tx, mut outbound_rx) = mpsc::channel::<Element>(64);
+
+    loop {
+        tokio::select! {
+            event = client.next() => match event {
+                Some(Event::Online { .. }) => {
+                    *attempt = 0;
+                    tracing::info!(jid = %config.jid, "xmpp connected");
+                    for stanza in online_stanzas(&config) {
+                        if let Err(error) = client.send_stanza(stanza).await {
+                            return SessionEnd::Dropped(error.into());
+                        }
+                    }
+                    *sender_slot.write().await = Some(outbound_tx.clone());
+                }
+                Some(Event::Stanza(element)) => {
+                    // Presences and IQs don't parse as messages.
+                    let Ok(message) = Message::try_from(element) else {
+                        continue;
+                    };
+                    let Some(inbound) =
+                        to_inbound(&message, &config.nickname, &permissions.load())
+                    else {
+                        continue;
+                    };
+                    if inbound_tx.send(inbound).await.is_err() {
+                        tracing::warn!("failed to send inbound message from XMPP (receiver dropped)");
+                        return SessionEnd::Closed;
+                    }
+                }
+                Some(Event::Disconnected(error)) => return SessionEnd::Dropped(error.into()),
+                None => {
+                    return SessionEnd::Dropped(anyhow::anyhow!("server closed the connection"));
+                }
+            },
+            Some(stanza) = outbound_rx.recv() => {
+                if let Err(error) = client.send_stanza(stanza).await {
+                    return SessionEnd::Dropped(error.into());
+                }
+            }
+        }
+    }
+}
+
+/// Initial presence, then one join per room. History is suppressed so a
+/// reconnect doesn't replay the room to the agent.
+fn online_stanzas(config: &SessionConfig) -> Vec<Element> {
+    let mut stanzas = vec![Presence::new(PresenceType::None).into()];
+    for room in &config.rooms {
+        let occupant = format!("{}/{}", room.to_lowercase(), config.nickname);
+        let Ok(occupant) = Jid::new(&occupant) else {
+            tracing::warn!(%room, "skipping invalid xmpp room address");
+            continue;
+        };
+        let join = Presence::new(PresenceType::None)
+            .with_to(occupant)
+            .with_payloads(vec![
+                Muc::new()
+                    .with_history(History::new().with_maxstanzas(0))
+                    .into(),
+            ]);
+        stanzas.push(join.into());
+    }
+    stanzas
+}
+
+/// Turn a chat or groupchat message into an inbound message, applying the
+/// room filter and user allowlist. Returns `None` for everything that
+/// shouldn't reach an agent.
+fn to_inbound(
+    message: &Message,
+    own_nickname: &str,
+    permissions: &XmppPermissions,
+) -> Option<InboundMessage> {
+    let from = message.from.as_ref()?;
+    let body = message
+        .bodies
+        .get("")
+        .or_else(|| message.bodies.values().next())?;
+    let text = body.0.trim().to_string();
+    if text.is_empty() {
+        return None;
+    }
+    let bare = from.to_bare().to_string().to_lowercase();
+    let resource = from
+        .resource()
+        .map(|resource| resource.as_str().to_string());
+
+    let mut metadata = HashMap::new();
+    let (conversation_id, sender_id, display_name) = match message.type_ {
+        MessageType::Groupchat => {
+            // Room messages come from room@service/nick; our own echo too.
+            let nick = resource?;
+            if nick.eq_ignore_ascii_case(own_nickname) {
+                return None;
+            }
+            if let Some(filter) = &permissions.room_filter
+                && !filter.iter().any(|room| room.eq_ignore_ascii_case(&bare))
+            {
+                return None;
+            }
+            metadata.insert("xmpp_room".into(), serde_json::Value::String(bare.clone()));
+            metadata.insert(
+                "xmpp_mentions_bot".into(),
+                serde_json::Value::Bool(text.to_lowercase().contains(&own_nickname.to_lowercase())),
+            );
+            (format!("xmpp:{bare}"), format!("{bare}/{nick}"), nick)
+        }
+        MessageType::Chat | MessageType::Normal => {
+            // Direct chats only from explicitly allowed users.
+            if !permissions
+                .allowed_users
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&bare))
+            {
+                return None;
+            }
+            metadata.insert("xmpp_jid".into(), serde_json::Value::String(bare.clone()));
+            let display_name = bare.split('@').next().unwrap_or(&bare).to_string();
+            (format!("xmpp:dm:{bare}"), bare.clone(), display_name)
+        }
+        _ => return None,
+    };
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(display_name.clone()),
+    );
+
+    Some(InboundMessage {
+        id: message
+            .id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        source: "xmpp".into(),
+        conversation_id,
+        sender_id,
+        agent_id: None,
+        content: MessageContent::Text(text),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(display_name),
+    })
+}
+
+/// Where replies to `message` go: its room, or the sender of a direct chat.
+fn reply_target(message: &InboundMessage) -> Option<ReplyTarget> {
+    let text = |key: &str| message.metadata.get(key).and_then(|value| value.as_str());
+    if let Some(room) = text("xmpp_room") {
+        return Some(ReplyTarget::Room(room.to_string()));
+    }
+    text("xmpp_jid").map(|jid| ReplyTarget::Direct(jid.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(from: &str, kind: &str, body: &str) -> Message {
+        let element: Element = format!(
+            "<message xmlns='jabber:client' from='{from}' to='bot@example.com' type='{kind}'>\
+             <body>{body}</body></message>"
+        )
+        .parse()
+        .unwrap();
+        Message::try_from(element).unwrap()
+    }
+
+    #[test]
+    fn room_messages_map_to_the_room() {
+        let permissions = XmppPermissions {
+            room_filter: Some(vec!["ops@conference.example.com".into()]),
+            allowed_users: Vec::new(),
+        };
+
+        let inbound = to_inbound(
+            &message(
+                "ops@conference.example.com/alice",
+                "groupchat",
+                "spacebot: status?",
+            ),
+            "spacebot",
+            &permissions,
+        )
+        .unwrap();
+        assert_eq!(inbound.conversation_id, "xmpp:ops@conference.example.com");
+        assert_eq!(inbound.formatted_author.as_deref(), Some("alice"));
+        assert_eq!(inbound.metadata["xmpp_mentions_bot"], true);
+        assert_eq!(
+            reply_target(&inbound),
+            Some(ReplyTarget::Room("ops@conference.example.com".into()))
+        );
+
+        // Our own messages are echoed back by the room.
+        assert!(
+            to_inbound(
+                &message("ops@conference.example.com/spacebot", "groupchat", "done"),
+                "spacebot",
+                &permissions,
+            )
+            .is_none()
+        );
+        assert!(
+            to_inbound(
+                &message("random@conference.example.com/alice", "groupchat", "hi"),
+                "spacebot",
+                &permissions,
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn direct_chats_need_an_allowed_user() {
+        let permissions = XmppPermissions {
+            room_filter: None,
+            allowed_users: vec!["Alice@example.com".into()],
+        };
+
+        let inbound = to_inbound(
+            &message("alice@example.com/laptop", "chat", "hello"),
+            "spacebot",
+            &permissions,
+        )
+        .unwrap();
+        assert_eq!(inbound.conversation_id, "xmpp:dm:alice@example.com");
+        assert_eq!(inbound.sender_id, "alice@example.com");
+        assert_eq!(
+            reply_target(&inbound),
+            Some(ReplyTarget::Direct("alice@example.com".into()))
+        );
+
+        assert!(
+            to_inbound(
+                &message("mallory@example.com/phone", "chat", "hello"),
+                "spacebot",
+                &permissions,
+            )
+            .is_none()
+        );
+    }
+}
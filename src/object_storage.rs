@@ -0,0 +1,314 @@
+//! S3-compatible object storage for files that should outlive the container.
+//!
+//! When a bucket is configured, screenshots, files sent to users, backups,
+//! worker artifacts and compaction archives are written to it under
+//! `{prefix}/{scope}/{kind}/{id}/{name}`, where the scope is an agent ID or
+//! `instance`. Anything handed to a user is delivered as a time-limited
+//! signed URL, so the bucket itself can stay private. Works with AWS S3,
+//! MinIO, Cloudflare R2 and other S3-compatible services.
+
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::{Path as ObjectPath, PathPart};
+use object_store::signer::Signer as _;
+use object_store::{ObjectStore as _, PutPayload};
+
+use futures::TryStreamExt as _;
+use serde::Serialize;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The longest validity S3 allows for a presigned URL.
+pub const MAX_SIGNED_URL_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Scope for objects that don't belong to an agent, like backups.
+pub const INSTANCE_SCOPE: &str = "instance";
+
+/// Object storage settings (instance-level). Storage is off unless a bucket
+/// is set.
+#[derive(Debug, Clone)]
+pub struct ObjectStorageConfig {
+    pub bucket: Option<String>,
+    pub region: String,
+    /// Endpoint for S3-compatible services. `None` means AWS.
+    pub endpoint: Option<String>,
+    /// Credentials. When unset, the standard `AWS_*` environment variables
+    /// and instance metadata are used.
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// Key prefix, so several instances can share a bucket.
+    pub prefix: String,
+    /// Address the bucket in the path instead of the hostname. Needed by
+    /// MinIO and most self-hosted services.
+    pub path_style: bool,
+    /// How long signed URLs stay valid.
+    pub signed_url_ttl_secs: u64,
+    /// Largest file `send_file` uploads and links when it's too big to
+    /// attach.
+    pub max_upload_mb: u64,
+}
+
+impl Default for ObjectStorageConfig {
+    fn default() -> Self {
+        Self {
+            bucket: None,
+            region: "us-east-1".into(),
+            endpoint: None,
+            access_key_id: None,
+            secret_access_key: None,
+            prefix: String::new(),
+            path_style: false,
+            signed_url_ttl_secs: 24 * 60 * 60,
+            max_upload_mb: 512,
+        }
+    }
+}
+
+/// What an object is, which decides where it's stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Screenshot,
+    File,
+    Artifact,
+    Archive,
+    Backup,
+}
+
+impl ObjectKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Screenshot => "screenshots",
+            Self::File => "files",
+            Self::Artifact => "artifacts",
+            Self::Archive => "archives",
+            Self::Backup => "backups",
+        }
+    }
+}
+
+/// An object as listed from the bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredObject {
+    pub key: String,
+    pub size_bytes: u64,
+    pub last_modified: chrono::DateTime<chrono::Utc>,
+}
+
+/// A connected bucket.
+#[derive(Debug)]
+pub struct ObjectStorage {
+    store: AmazonS3,
+    prefix: Vec<String>,
+    signed_url_ttl: Duration,
+    max_upload_bytes: u64,
+}
+
+impl ObjectStorage {
+    /// Build the bucket client when a bucket is configured. Doesn't touch the
+    /// network; a misconfigured bucket surfaces on first use.
+    pub fn from_config(config: &ObjectStorageConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        let Some(bucket) = &config.bucket else {
+            return Ok(None);
+        };
+
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .with_region(&config.region)
+            .with_virtual_hosted_style_request(!config.path_style);
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let Some(access_key_id) = &config.access_key_id {
+            builder = builder.with_access_key_id(access_key_id);
+        }
+        if let Some(secret_access_key) = &config.secret_access_key {
+            builder = builder.with_secret_access_key(secret_access_key);
+        }
+        let store = builder
+            .build()
+            .map_err(|error| anyhow::anyhow!("can't configure object storage: {error}"))?;
+
+        tracing::info!(%bucket, endpoint = ?config.endpoint, "object storage enabled");
+        Ok(Some(Arc::new(Self {
+            store,
+            prefix: config
+                .prefix
+                .split('/')
+                .filter(|part| !part.is_empty())
+                .map(str::to_string)
+                .collect(),
+            signed_url_ttl: Duration::from_secs(config.signed_url_ttl_secs),
+            max_upload_bytes: config.max_upload_mb * 1024 * 1024,
+        })))
+    }
+
+    /// Largest file that is uploaded for link delivery.
+    pub fn max_upload_bytes(&self) -> u64 {
+        self.max_upload_bytes
+    }
+
+    /// Store bytes under a fresh key and return the key.
+    pub async fn put(
+        &self,
+        kind: ObjectKind,
+        scope: &str,
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> anyhow::Result<String> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let key = object_key(&self.prefix, scope, kind, &id, name);
+        let size = bytes.len();
+        self.store
+            .put(&key, PutPayload::from(bytes))
+            .await
+            .map_err(|error| anyhow::anyhow!("can't upload {key}: {error}"))?;
+        tracing::debug!(%key, size, "object stored");
+        Ok(key.to_string())
+    }
+
+    /// Store a local file under a fresh key and return the key.
+    pub async fn put_file(
+        &self,
+        kind: ObjectKind,
+        scope: &str,
+        path: &Path,
+    ) -> anyhow::Result<String> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".into());
+        let bytes = tokio::fs::read(path)
+            .await
+            .map_err(|error| anyhow::anyhow!("can't read {}: {error}", path.display()))?;
+        self.put(kind, scope, &name, bytes).await
+    }
+
+    /// Read an object back.
+    pub async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        let key = ObjectPath::parse(key)
+            .map_err(|error| anyhow::anyhow!("invalid object key '{key}': {error}"))?;
+        let result = self
+            .store
+            .get(&key)
+            .await
+            .map_err(|error| anyhow::anyhow!("can't download {key}: {error}"))?;
+        let bytes = result
+            .bytes()
+            .await
+            .map_err(|error| anyhow::anyhow!("can't download {key}: {error}"))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// A presigned GET URL for an object, valid for `signed_url_ttl_secs`.
+    pub async fn signed_url(&self, key: &str) -> anyhow::Result<String> {
+        let key = ObjectPath::parse(key)
+            .map_err(|error| anyhow::anyhow!("invalid object key '{key}': {error}"))?;
+        let url = self
+            .store
+            .signed_url(reqwest::Method::GET, &key, self.signed_url_ttl)
+            .await
+            .map_err(|error| anyhow::anyhow!("can't sign URL for {key}: {error}"))?;
+        Ok(url.to_string())
+    }
+
+    /// Upload and sign in one step, for files handed straight to a user.
+    pub async fn share(
+        &self,
+        kind: ObjectKind,
+        scope: &str,
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> anyhow::Result<String> {
+        let key = self.put(kind, scope, name, bytes).await?;
+        self.signed_url(&key).await
+    }
+
+    /// Objects of one kind in a scope, newest first.
+    pub async fn list(&self, kind: ObjectKind, scope: &str) -> anyhow::Result<Vec<StoredObject>> {
+        let prefix = ObjectPath::from_iter(
+            self.prefix
+                .iter()
+                .map(|part| PathPart::from(part.as_str()))
+                .chain([PathPart::from(scope), PathPart::from(kind.as_str())]),
+        );
+        let mut objects: Vec<StoredObject> = self
+            .store
+            .list(Some(&prefix))
+            .map_ok(|meta| StoredObject {
+                key: meta.location.to_string(),
+                size_bytes: meta.size,
+                last_modified: meta.last_modified,
+            })
+            .try_collect()
+            .await
+            .map_err(|error| anyhow::anyhow!("can't list {prefix}: {error}"))?;
+        objects.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
+        Ok(objects)
+    }
+}
+
+/// `{prefix}/{scope}/{kind}/{id}/{name}`. Each part is escaped on its own,
+/// so a name containing `/` can't escape its directory.
+fn object_key(
+    prefix: &[String],
+    scope: &str,
+    kind: ObjectKind,
+    id: &str,
+    name: &str,
+) -> ObjectPath {
+    ObjectPath::from_iter(
+        prefix
+            .iter()
+            .map(|part| PathPart::from(part.as_str()))
+            .chain([
+                PathPart::from(scope),
+                PathPart::from(kind.as_str()),
+                PathPart::from(id),
+                PathPart::from(name),
+            ]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_nest_under_prefix_scope_and_kind() {
+        let prefix = vec!["prod".to_string(), "bot".to_string()];
+        let key = object_key(&prefix, "main", ObjectKind::Screenshot, "abc", "shot.png");
+        assert_eq!(key.to_string(), "prod/bot/main/screenshots/abc/shot.png");
+
+        let key = object_key(
+            &[],
+            INSTANCE_SCOPE,
+            ObjectKind::Backup,
+            "abc",
+            "../../etc/passwd",
+        );
+        assert!(key.to_string().starts_with("instance/backups/abc/"));
+        assert_eq!(key.parts().count(), 4);
+    }
+
+    #[test]
+    fn storage_is_off_without_a_bucket() {
+        let storage = ObjectStorage::from_config(&ObjectStorageConfig::default()).unwrap();
+        assert!(storage.is_none());
+
+        let storage = ObjectStorage::from_config(&ObjectStorageConfig {
+            bucket: Some("spacebot".into()),
+            endpoint: Some("http://localhost:9000".into()),
+            access_key_id: Some("minio".into()),
+            secret_access_key: Some("minio-secret".into()),
+            prefix: "/prod/".into(),
+            path_style: true,
+            ..Default::default()
+        })
+        .unwrap()
+        .expect("bucket is configured");
+        assert_eq!(storage.prefix, vec!["prod".to_string()]);
+    }
+}
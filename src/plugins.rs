@@ -0,0 +1,340 @@
+//! WASM tool plugins.
+//!
+//! A plugin is a WebAssembly component implementing the `tool-plugin` world
+//! from `wit/plugin.wit`, dropped into an agent's `workspace/plugins/`
+//! directory. Each plugin declares its tools with JSON Schemas, and workers
+//! get them next to the built-in and MCP tools. No restart is needed: the
+//! directory is rescanned whenever a worker starts, and only new or changed
+//! files are compiled.
+//!
+//! Plugins are sandboxed. They get WASI with no preopened directories,
+//! environment, network or stdio, a memory cap, a fuel budget and a
+//! wall-clock timeout. Every call runs in a fresh instance, so no state
+//! carries over between calls.
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/plugin.wit",
+        world: "tool-plugin",
+    });
+}
+
+use crate::config::PluginConfig;
+use bindings::{LogLevel, ToolPlugin, ToolPluginImports};
+
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Engine, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How often the engine's epoch advances. Call timeouts are rounded up to
+/// this.
+const EPOCH_TICK: Duration = Duration::from_millis(100);
+
+/// Most instances a plugin may create, counting nested components.
+const MAX_INSTANCES: usize = 16;
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = wasmtime::Config::new();
+    config
+        .wasm_component_model(true)
+        .consume_fuel(true)
+        .epoch_interruption(true);
+    let engine = Engine::new(&config).expect("hardcoded wasmtime config");
+
+    let ticker = engine.clone();
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(EPOCH_TICK);
+            ticker.increment_epoch();
+        }
+    });
+    engine
+});
+
+static LINKER: LazyLock<Linker<PluginState>> = LazyLock::new(|| {
+    let mut linker = Linker::new(&ENGINE);
+    wasmtime_wasi::add_to_linker_sync(&mut linker).expect("WASI imports link once");
+    ToolPlugin::add_to_linker(&mut linker, |state: &mut PluginState| state)
+        .expect("plugin imports link once");
+    linker
+});
+
+/// Compiled plugins by path, with the modification time they were compiled
+/// from.
+static CACHE: LazyLock<Mutex<HashMap<PathBuf, (SystemTime, Arc<Plugin>)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Where plugins are loaded from inside an agent workspace.
+pub fn plugins_dir(workspace: &Path) -> PathBuf {
+    workspace.join("plugins")
+}
+
+/// A tool declared by a plugin.
+#[derive(Debug, Clone)]
+pub struct PluginToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+/// A compiled plugin and the tools it declared.
+pub struct Plugin {
+    name: String,
+    component: Component,
+    tools: Vec<PluginToolSpec>,
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("name", &self.name)
+            .field("tools", &self.tools.len())
+            .finish()
+    }
+}
+
+struct PluginState {
+    plugin: String,
+    wasi: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+}
+
+impl WasiView for PluginState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl ToolPluginImports for PluginState {
+    fn log(&mut self, level: LogLevel, message: String) {
+        let plugin = self.plugin.as_str();
+        match level {
+            LogLevel::Debug => tracing::debug!(plugin, "{message}"),
+            LogLevel::Info => tracing::info!(plugin, "{message}"),
+            LogLevel::Warn => tracing::warn!(plugin, "{message}"),
+            LogLevel::Error => tracing::error!(plugin, "{message}"),
+        }
+    }
+}
+
+impl Plugin {
+    /// Plugin name, taken from the file name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tools(&self) -> &[PluginToolSpec] {
+        &self.tools
+    }
+
+    fn compile(path: &Path, config: &PluginConfig) -> anyhow::Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "plugin".into());
+        let component = Component::from_file(&ENGINE, path)
+            .map_err(|error| anyhow::anyhow!("can't compile plugin: {error}"))?;
+
+        let mut plugin = Self {
+            name,
+            component,
+            tools: Vec::new(),
+        };
+        let mut store = plugin.new_store(config)?;
+        let instance = ToolPlugin::instantiate(&mut store, &plugin.component, &LINKER)
+            .map_err(|error| describe_failure(error, config))?;
+        let declared = instance
+            .call_describe(&mut store)
+            .map_err(|error| describe_failure(error, config))?;
+
+        let mut seen = HashSet::new();
+        for tool in declared {
+            if !seen.insert(tool.name.clone()) {
+                anyhow::bail!("tool '{}' is declared twice", tool.name);
+            }
+            let parameters: serde_json::Value =
+                serde_json::from_str(&tool.parameters).map_err(|error| {
+                    anyhow::anyhow!("tool '{}' has an invalid schema: {error}", tool.name)
+                })?;
+            if !parameters.is_object() {
+                anyhow::bail!("tool '{}' schema must be a JSON object", tool.name);
+            }
+            plugin.tools.push(PluginToolSpec {
+                name: tool.name,
+                description: tool.description,
+                parameters,
+            });
+        }
+        Ok(plugin)
+    }
+
+    fn new_store(&self, config: &PluginConfig) -> anyhow::Result<Store<PluginState>> {
+        let state = PluginState {
+            plugin: self.name.clone(),
+            // No preopens, environment, arguments or network; stdio is
+            // discarded.
+            wasi: WasiCtxBuilder::new().build(),
+            table: ResourceTable::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(config.max_memory_mb as usize * 1024 * 1024)
+                .instances(MAX_INSTANCES)
+                .build(),
+        };
+        let mut store = Store::new(&ENGINE, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(config.fuel)?;
+        store.set_epoch_deadline(
+            (config.timeout_secs * 1000).div_ceil(EPOCH_TICK.as_millis() as u64),
+        );
+        Ok(store)
+    }
+
+    /// Run one of the plugin's tools in a fresh instance.
+    pub async fn invoke(
+        self: &Arc<Self>,
+        tool: &str,
+        arguments: &serde_json::Value,
+        config: &PluginConfig,
+    ) -> anyhow::Result<String> {
+        let plugin = self.clone();
+        let tool = tool.to_string();
+        let arguments = arguments.to_string();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut store = plugin.new_store(&config)?;
+            let instance = ToolPlugin::instantiate(&mut store, &plugin.component, &LINKER)
+                .map_err(|error| describe_failure(error, &config))?;
+            instance
+                .call_invoke(&mut store, &tool, &arguments)
+                .map_err(|error| describe_failure(error, &config))?
+                .map_err(|message| anyhow::anyhow!("{message}"))
+        })
+        .await
+        .map_err(|error| anyhow::anyhow!("plugin task failed: {error}"))?
+    }
+}
+
+/// Turn resource-limit traps into messages the model can act on.
+fn describe_failure(error: anyhow::Error, config: &PluginConfig) -> anyhow::Error {
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => {
+            anyhow::anyhow!("plugin exceeded its fuel budget of {}", config.fuel)
+        }
+        Some(Trap::Interrupt) => {
+            anyhow::anyhow!("plugin timed out after {}s", config.timeout_secs)
+        }
+        _ => anyhow::anyhow!("plugin failed: {error:#}"),
+    }
+}
+
+/// Load the plugins in an agent workspace. Plugins that fail to compile or
+/// describe themselves are logged and skipped.
+pub async fn load(workspace: &Path, config: &PluginConfig) -> Vec<Arc<Plugin>> {
+    if !config.enabled {
+        return Vec::new();
+    }
+    let directory = plugins_dir(workspace);
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || load_blocking(&directory, &config))
+        .await
+        .unwrap_or_else(|error| {
+            tracing::warn!(%error, "plugin loading task failed");
+            Vec::new()
+        })
+}
+
+fn load_blocking(directory: &Path, config: &PluginConfig) -> Vec<Arc<Plugin>> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "wasm")
+        })
+        .collect();
+    paths.sort();
+
+    let mut plugins = Vec::new();
+    for path in &paths {
+        let Some(modified) = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+        else {
+            continue;
+        };
+
+        let cached = CACHE
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(path).cloned())
+            .filter(|(compiled_from, _)| *compiled_from == modified)
+            .map(|(_, plugin)| plugin);
+        if let Some(plugin) = cached {
+            plugins.push(plugin);
+            continue;
+        }
+
+        match Plugin::compile(path, config) {
+            Ok(plugin) => {
+                tracing::info!(
+                    plugin = %plugin.name,
+                    tools = plugin.tools.len(),
+                    "plugin loaded"
+                );
+                let plugin = Arc::new(plugin);
+                if let Ok(mut cache) = CACHE.lock() {
+                    cache.insert(path.clone(), (modified, plugin.clone()));
+                }
+                plugins.push(plugin);
+            }
+            Err(error) => {
+                tracing::warn!(%error, path = %path.display(), "can't load plugin");
+            }
+        }
+    }
+
+    // Forget plugins that were removed from this directory.
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.retain(|path, _| path.parent() != Some(directory) || paths.contains(path));
+    }
+    plugins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalid_plugins_are_skipped() {
+        let workspace = tempfile::tempdir().unwrap();
+        let directory = plugins_dir(workspace.path());
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("broken.wasm"), b"not a component").unwrap();
+        std::fs::write(directory.join("notes.txt"), b"ignored").unwrap();
+
+        let plugins = load(workspace.path(), &PluginConfig::default()).await;
+        assert!(plugins.is_empty());
+    }
+
+    #[tokio::test]
+    async fn disabled_plugins_are_not_scanned() {
+        let workspace = tempfile::tempdir().unwrap();
+        let config = PluginConfig {
+            enabled: false,
+            ..PluginConfig::default()
+        };
+        assert!(load(workspace.path(), &config).await.is_empty());
+    }
+}
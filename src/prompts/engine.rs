@@ -44,16 +44,31 @@ impl PromptEngine {
             crate::prompts::text::get("cortex_bulletin"),
         )?;
         env.add_template("compactor", crate::prompts::text::get("compactor"))?;
+        env.add_template("channel_title", crate::prompts::text::get("channel_title"))?;
+        env.add_template(
+            "channel_summary",
+            crate::prompts::text::get("channel_summary"),
+        )?;
         env.add_template(
             "memory_persistence",
             crate::prompts::text::get("memory_persistence"),
         )?;
         env.add_template("ingestion", crate::prompts::text::get("ingestion"))?;
         env.add_template("cortex_chat", crate::prompts::text::get("cortex_chat"))?;
+        env.add_template("voice", crate::prompts::text::get("voice"))?;
         env.add_template(
             "cortex_profile",
             crate::prompts::text::get("cortex_profile"),
         )?;
+        env.add_template(
+            "cortex_outreach",
+            crate::prompts::text::get("cortex_outreach"),
+        )?;
+        env.add_template(
+            "cortex_reflection",
+            crate::prompts::text::get("cortex_reflection"),
+        )?;
+        env.add_template("cortex_dream", crate::prompts::text::get("cortex_dream"))?;
 
         // Fragment templates
         env.add_template(
@@ -82,6 +97,10 @@ impl PromptEngine {
             "fragments/system/retrigger",
             crate::prompts::text::get("fragments/system/retrigger"),
         )?;
+        env.add_template(
+            "fragments/system/branch_race",
+            crate::prompts::text::get("fragments/system/branch_race"),
+        )?;
         env.add_template(
             "fragments/system/truncation",
             crate::prompts::text::get("fragments/system/truncation"),
@@ -90,6 +109,10 @@ impl PromptEngine {
             "fragments/system/worker_overflow",
             crate::prompts::text::get("fragments/system/worker_overflow"),
         )?;
+        env.add_template(
+            "fragments/system/worker_resume",
+            crate::prompts::text::get("fragments/system/worker_resume"),
+        )?;
         env.add_template(
             "fragments/system/worker_compact",
             crate::prompts::text::get("fragments/system/worker_compact"),
@@ -114,14 +137,62 @@ impl PromptEngine {
             "fragments/system/history_backfill",
             crate::prompts::text::get("fragments/system/history_backfill"),
         )?;
+        env.add_template(
+            "fragments/system/fork",
+            crate::prompts::text::get("fragments/system/fork"),
+        )?;
+        env.add_template(
+            "fragments/system/handoff",
+            crate::prompts::text::get("fragments/system/handoff"),
+        )?;
+        env.add_template(
+            "fragments/system/reminder",
+            crate::prompts::text::get("fragments/system/reminder"),
+        )?;
+        env.add_template(
+            "fragments/system/deferred_task",
+            crate::prompts::text::get("fragments/system/deferred_task"),
+        )?;
+        env.add_template(
+            "fragments/system/proactive_outreach",
+            crate::prompts::text::get("fragments/system/proactive_outreach"),
+        )?;
+        env.add_template(
+            "fragments/system/identity_reflection",
+            crate::prompts::text::get("fragments/system/identity_reflection"),
+        )?;
+        env.add_template(
+            "fragments/system/dream",
+            crate::prompts::text::get("fragments/system/dream"),
+        )?;
+        env.add_template(
+            "fragments/system/channel_title",
+            crate::prompts::text::get("fragments/system/channel_title"),
+        )?;
+        env.add_template(
+            "fragments/system/channel_summary",
+            crate::prompts::text::get("fragments/system/channel_summary"),
+        )?;
+        env.add_template(
+            "fragments/system/attachment_summary",
+            crate::prompts::text::get("fragments/system/attachment_summary"),
+        )?;
         env.add_template(
             "fragments/system/tool_syntax_correction",
             crate::prompts::text::get("fragments/system/tool_syntax_correction"),
         )?;
+        env.add_template(
+            "fragments/system/prompt_injection_notice",
+            crate::prompts::text::get("fragments/system/prompt_injection_notice"),
+        )?;
         env.add_template(
             "fragments/coalesce_hint",
             crate::prompts::text::get("fragments/coalesce_hint"),
         )?;
+        env.add_template(
+            "fragments/digest_hint",
+            crate::prompts::text::get("fragments/digest_hint"),
+        )?;
 
         Ok(Self {
             env: Arc::new(env),
@@ -258,11 +329,26 @@ impl PromptEngine {
         self.render_static("fragments/system/retrigger")
     }
 
+    /// Note telling a racing branch how many branches it races against.
+    pub fn render_system_branch_race(&self, racers: usize) -> Result<String> {
+        self.render(
+            "fragments/system/branch_race",
+            context! {
+                racers => racers,
+            },
+        )
+    }
+
     /// Correction message when the LLM outputs tool call syntax as plain text.
     pub fn render_system_tool_syntax_correction(&self) -> Result<String> {
         self.render_static("fragments/system/tool_syntax_correction")
     }
 
+    /// Notice attached to tool results that look like a prompt injection.
+    pub fn render_system_prompt_injection_notice(&self) -> Result<String> {
+        self.render_static("fragments/system/prompt_injection_notice")
+    }
+
     /// Convenience method for rendering truncation marker.
     pub fn render_system_truncation(&self, remove_count: usize) -> Result<String> {
         self.render(
@@ -278,6 +364,11 @@ impl PromptEngine {
         self.render_static("fragments/system/worker_overflow")
     }
 
+    /// Prompt for a worker resuming from its checkpoint after a restart.
+    pub fn render_system_worker_resume(&self) -> Result<String> {
+        self.render_static("fragments/system/worker_resume")
+    }
+
     /// Convenience method for rendering worker compaction message.
     pub fn render_system_worker_compact(&self, remove_count: usize, recap: &str) -> Result<String> {
         self.render(
@@ -353,6 +444,159 @@ impl PromptEngine {
         )
     }
 
+    /// Render the note that opens a forked thread's history.
+    pub fn render_system_fork(&self, parent: &str) -> Result<String> {
+        self.render(
+            "fragments/system/fork",
+            context! {
+                parent => parent,
+            },
+        )
+    }
+
+    /// Render the brief that opens a conversation handed over from another agent.
+    pub fn render_system_handoff(
+        &self,
+        from_agent: &str,
+        reason: &str,
+        summary: &str,
+        transcript: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/handoff",
+            context! {
+                from_agent => from_agent,
+                reason => reason,
+                summary => summary,
+                transcript => transcript,
+            },
+        )
+    }
+
+    /// Render the message that wakes a conversation when a reminder is due.
+    pub fn render_system_reminder(&self, message: &str, set_at: &str) -> Result<String> {
+        self.render(
+            "fragments/system/reminder",
+            context! {
+                message => message,
+                set_at => set_at,
+            },
+        )
+    }
+
+    /// Render the message that brings a deferred task's result back to its
+    /// conversation.
+    pub fn render_system_deferred_task(
+        &self,
+        task: &str,
+        result: &str,
+        failed: bool,
+        queued_at: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/deferred_task",
+            context! {
+                task => task,
+                result => result,
+                failed => failed,
+                queued_at => queued_at,
+            },
+        )
+    }
+
+    /// Render the event the cortex writes a proactive message about.
+    pub fn render_system_proactive_outreach(&self, event: &str) -> Result<String> {
+        self.render(
+            "fragments/system/proactive_outreach",
+            context! {
+                event => event,
+            },
+        )
+    }
+
+    /// Render the identity files and conversations the cortex reflects on.
+    pub fn render_system_identity_reflection(
+        &self,
+        identity: Option<&str>,
+        user: Option<&str>,
+        transcript: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/identity_reflection",
+            context! {
+                identity => identity,
+                user => user,
+                transcript => transcript,
+            },
+        )
+    }
+
+    /// Render the day of conversations the cortex dreams about.
+    pub fn render_system_dream(
+        &self,
+        date: &str,
+        max_memories: usize,
+        bulletin: Option<&str>,
+        transcript: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/dream",
+            context! {
+                date => date,
+                max_memories => max_memories,
+                bulletin => bulletin,
+                transcript => transcript,
+            },
+        )
+    }
+
+    /// Render the conversation start a channel title is generated from.
+    pub fn render_system_channel_title(&self, transcript: &str) -> Result<String> {
+        self.render(
+            "fragments/system/channel_title",
+            context! {
+                transcript => transcript,
+            },
+        )
+    }
+
+    /// Render the previous summary and latest messages a channel's rolling
+    /// summary is rewritten from.
+    pub fn render_system_channel_summary(
+        &self,
+        previous_summary: Option<&str>,
+        transcript: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/channel_summary",
+            context! {
+                previous_summary => previous_summary,
+                transcript => transcript,
+            },
+        )
+    }
+
+    /// Render the task of a worker summarizing a large text attachment.
+    pub fn render_system_attachment_summary(
+        &self,
+        filename: &str,
+        path: &str,
+        size: u64,
+        line_count: usize,
+        summary_path: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/attachment_summary",
+            context! {
+                filename => filename,
+                path => path,
+                size => size,
+                line_count => line_count,
+                summary_path => summary_path,
+            },
+        )
+    }
+
     /// Render the coalesce hint fragment for batched messages.
     pub fn render_coalesce_hint(
         &self,
@@ -370,6 +614,23 @@ impl PromptEngine {
         )
     }
 
+    /// Render the digest hint for a digest-mode channel's periodic summary.
+    pub fn render_digest_hint(
+        &self,
+        message_count: usize,
+        interval: &str,
+        unique_senders: usize,
+    ) -> Result<String> {
+        self.render(
+            "fragments/digest_hint",
+            context! {
+                message_count => message_count,
+                interval => interval,
+                unique_senders => unique_senders,
+            },
+        )
+    }
+
     /// Render the complete channel system prompt with all dynamic components.
     #[allow(clippy::too_many_arguments)]
     pub fn render_channel_prompt(
@@ -379,6 +640,7 @@ impl PromptEngine {
         skills_prompt: Option<String>,
         worker_capabilities: String,
         conversation_context: Option<String>,
+        conversation_summary: Option<String>,
         status_text: Option<String>,
         coalesce_hint: Option<String>,
         available_channels: Option<String>,
@@ -391,6 +653,7 @@ impl PromptEngine {
                 skills_prompt => skills_prompt,
                 worker_capabilities => worker_capabilities,
                 conversation_context => conversation_context,
+                conversation_summary => conversation_summary,
                 status_text => status_text,
                 coalesce_hint => coalesce_hint,
                 available_channels => available_channels,
@@ -417,6 +680,21 @@ impl PromptEngine {
         )
     }
 
+    /// Render the voice system prompt.
+    pub fn render_voice_prompt(
+        &self,
+        identity_context: Option<String>,
+        memory_bulletin: Option<String>,
+    ) -> Result<String> {
+        self.render(
+            "voice",
+            context! {
+                identity_context => identity_context,
+                memory_bulletin => memory_bulletin,
+            },
+        )
+    }
+
     /// Get the configured language code.
     pub fn language(&self) -> &str {
         &self.language
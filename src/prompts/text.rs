@@ -60,10 +60,16 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "cortex") => include_str!("../../prompts/en/cortex.md.j2"),
         ("en", "cortex_bulletin") => include_str!("../../prompts/en/cortex_bulletin.md.j2"),
         ("en", "cortex_profile") => include_str!("../../prompts/en/cortex_profile.md.j2"),
+        ("en", "cortex_outreach") => include_str!("../../prompts/en/cortex_outreach.md.j2"),
+        ("en", "cortex_reflection") => include_str!("../../prompts/en/cortex_reflection.md.j2"),
+        ("en", "cortex_dream") => include_str!("../../prompts/en/cortex_dream.md.j2"),
+        ("en", "channel_title") => include_str!("../../prompts/en/channel_title.md.j2"),
+        ("en", "channel_summary") => include_str!("../../prompts/en/channel_summary.md.j2"),
         ("en", "compactor") => include_str!("../../prompts/en/compactor.md.j2"),
         ("en", "memory_persistence") => include_str!("../../prompts/en/memory_persistence.md.j2"),
         ("en", "ingestion") => include_str!("../../prompts/en/ingestion.md.j2"),
         ("en", "cortex_chat") => include_str!("../../prompts/en/cortex_chat.md.j2"),
+        ("en", "voice") => include_str!("../../prompts/en/voice.md.j2"),
 
         // Fragment Templates
         ("en", "fragments/worker_capabilities") => {
@@ -86,12 +92,18 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "fragments/system/retrigger") => {
             include_str!("../../prompts/en/fragments/system/retrigger.md.j2")
         }
+        ("en", "fragments/system/branch_race") => {
+            include_str!("../../prompts/en/fragments/system/branch_race.md.j2")
+        }
         ("en", "fragments/system/truncation") => {
             include_str!("../../prompts/en/fragments/system/truncation.md.j2")
         }
         ("en", "fragments/system/worker_overflow") => {
             include_str!("../../prompts/en/fragments/system/worker_overflow.md.j2")
         }
+        ("en", "fragments/system/worker_resume") => {
+            include_str!("../../prompts/en/fragments/system/worker_resume.md.j2")
+        }
         ("en", "fragments/system/worker_compact") => {
             include_str!("../../prompts/en/fragments/system/worker_compact.md.j2")
         }
@@ -110,14 +122,50 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "fragments/system/history_backfill") => {
             include_str!("../../prompts/en/fragments/system/history_backfill.md.j2")
         }
+        ("en", "fragments/system/fork") => {
+            include_str!("../../prompts/en/fragments/system/fork.md.j2")
+        }
+        ("en", "fragments/system/handoff") => {
+            include_str!("../../prompts/en/fragments/system/handoff.md.j2")
+        }
+        ("en", "fragments/system/reminder") => {
+            include_str!("../../prompts/en/fragments/system/reminder.md.j2")
+        }
+        ("en", "fragments/system/deferred_task") => {
+            include_str!("../../prompts/en/fragments/system/deferred_task.md.j2")
+        }
+        ("en", "fragments/system/proactive_outreach") => {
+            include_str!("../../prompts/en/fragments/system/proactive_outreach.md.j2")
+        }
+        ("en", "fragments/system/identity_reflection") => {
+            include_str!("../../prompts/en/fragments/system/identity_reflection.md.j2")
+        }
+        ("en", "fragments/system/dream") => {
+            include_str!("../../prompts/en/fragments/system/dream.md.j2")
+        }
+        ("en", "fragments/system/channel_title") => {
+            include_str!("../../prompts/en/fragments/system/channel_title.md.j2")
+        }
+        ("en", "fragments/system/channel_summary") => {
+            include_str!("../../prompts/en/fragments/system/channel_summary.md.j2")
+        }
+        ("en", "fragments/system/attachment_summary") => {
+            include_str!("../../prompts/en/fragments/system/attachment_summary.md.j2")
+        }
         ("en", "fragments/system/tool_syntax_correction") => {
             include_str!("../../prompts/en/fragments/system/tool_syntax_correction.md.j2")
         }
+        ("en", "fragments/system/prompt_injection_notice") => {
+            include_str!("../../prompts/en/fragments/system/prompt_injection_notice.md.j2")
+        }
 
         // Coalesce Hint
         ("en", "fragments/coalesce_hint") => {
             include_str!("../../prompts/en/fragments/coalesce_hint.md.j2")
         }
+        ("en", "fragments/digest_hint") => {
+            include_str!("../../prompts/en/fragments/digest_hint.md.j2")
+        }
 
         // Tool Descriptions
         ("en", "tools/reply") => include_str!("../../prompts/en/tools/reply_description.md.j2"),
@@ -128,10 +176,38 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "tools/route") => include_str!("../../prompts/en/tools/route_description.md.j2"),
         ("en", "tools/cancel") => include_str!("../../prompts/en/tools/cancel_description.md.j2"),
         ("en", "tools/skip") => include_str!("../../prompts/en/tools/skip_description.md.j2"),
+        ("en", "tools/conclude") => {
+            include_str!("../../prompts/en/tools/conclude_description.md.j2")
+        }
         ("en", "tools/react") => include_str!("../../prompts/en/tools/react_description.md.j2"),
+        ("en", "tools/fork") => include_str!("../../prompts/en/tools/fork_description.md.j2"),
+        ("en", "tools/handoff") => {
+            include_str!("../../prompts/en/tools/handoff_description.md.j2")
+        }
+        ("en", "tools/set_reminder") => {
+            include_str!("../../prompts/en/tools/set_reminder_description.md.j2")
+        }
+        ("en", "tools/defer_task") => {
+            include_str!("../../prompts/en/tools/defer_task_description.md.j2")
+        }
+        ("en", "tools/send_proactive_message") => {
+            include_str!("../../prompts/en/tools/send_proactive_message_description.md.j2")
+        }
         ("en", "tools/set_status") => {
             include_str!("../../prompts/en/tools/set_status_description.md.j2")
         }
+        ("en", "tools/spawn_sub_worker") => {
+            include_str!("../../prompts/en/tools/spawn_sub_worker_description.md.j2")
+        }
+        ("en", "tools/send_worker_message") => {
+            include_str!("../../prompts/en/tools/send_worker_message_description.md.j2")
+        }
+        ("en", "tools/read_worker_messages") => {
+            include_str!("../../prompts/en/tools/read_worker_messages_description.md.j2")
+        }
+        ("en", "tools/emit_artifact") => {
+            include_str!("../../prompts/en/tools/emit_artifact_description.md.j2")
+        }
         ("en", "tools/shell") => include_str!("../../prompts/en/tools/shell_description.md.j2"),
         ("en", "tools/file") => include_str!("../../prompts/en/tools/file_description.md.j2"),
         ("en", "tools/exec") => include_str!("../../prompts/en/tools/exec_description.md.j2"),
@@ -151,6 +227,12 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "tools/channel_recall") => {
             include_str!("../../prompts/en/tools/channel_recall_description.md.j2")
         }
+        ("en", "tools/pin_message") => {
+            include_str!("../../prompts/en/tools/pin_message_description.md.j2")
+        }
+        ("en", "tools/archive_search") => {
+            include_str!("../../prompts/en/tools/archive_search_description.md.j2")
+        }
         ("en", "tools/send_file") => {
             include_str!("../../prompts/en/tools/send_file_description.md.j2")
         }
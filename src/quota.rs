@@ -0,0 +1,334 @@
+//! Per-agent disk quotas for the workspace and screenshot directory.
+//!
+//! A background loop measures both directories on an interval. When one is
+//! over its limit and the cleanup policy allows it, the least recently
+//! modified files are deleted until usage is back under the limit. Identity
+//! files, skills and pending ingest files are never deleted, so a worker
+//! looping on downloads loses its own artifacts first.
+
+use crate::config::{DiskCleanup, RuntimeConfig};
+
+use serde::Serialize;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Workspace entries that cleanup never touches, relative to the workspace root.
+const PROTECTED_WORKSPACE_ENTRIES: &[&str] =
+    &["SOUL.md", "IDENTITY.md", "USER.md", "skills", "ingest"];
+
+/// Usage of one quota-managed directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryUsage {
+    pub path: PathBuf,
+    pub used_bytes: u64,
+    pub limit_bytes: Option<u64>,
+    pub file_count: usize,
+}
+
+impl DirectoryUsage {
+    pub fn over_limit(&self) -> bool {
+        self.limit_bytes
+            .is_some_and(|limit| self.used_bytes > limit)
+    }
+}
+
+/// Disk usage of an agent's quota-managed directories.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsage {
+    pub workspace: DirectoryUsage,
+    pub screenshots: DirectoryUsage,
+}
+
+/// Outcome of cleaning up one directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Cleanup {
+    removed_files: usize,
+    freed_bytes: u64,
+}
+
+#[derive(Debug)]
+struct FileEntry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Measures and enforces the disk quotas of one agent.
+#[derive(Debug)]
+pub struct DiskQuota {
+    agent_id: String,
+    workspace: PathBuf,
+    screenshot_dir: PathBuf,
+    runtime_config: Arc<RuntimeConfig>,
+}
+
+impl DiskQuota {
+    pub fn new(
+        agent_id: impl Into<String>,
+        workspace: PathBuf,
+        screenshot_dir: PathBuf,
+        runtime_config: Arc<RuntimeConfig>,
+    ) -> Self {
+        Self {
+            agent_id: agent_id.into(),
+            workspace,
+            screenshot_dir,
+            runtime_config,
+        }
+    }
+
+    /// Measure current usage against the configured limits.
+    pub async fn usage(&self) -> anyhow::Result<DiskUsage> {
+        let config = self.runtime_config.disk_quota.load();
+        let workspace = self.workspace.clone();
+        let screenshot_dir = self.screenshot_dir.clone();
+        let workspace_limit = config.workspace_limit_mb.map(megabytes);
+        let screenshots_limit = config.screenshots_limit_mb.map(megabytes);
+
+        tokio::task::spawn_blocking(move || {
+            Ok(DiskUsage {
+                workspace: measure(&workspace, workspace_limit)?,
+                screenshots: measure(&screenshot_dir, screenshots_limit)?,
+            })
+        })
+        .await?
+    }
+
+    /// Measure both directories and clean up any that are over their limit.
+    pub async fn enforce(&self) -> anyhow::Result<DiskUsage> {
+        let config = self.runtime_config.disk_quota.load();
+        let usage = self.usage().await?;
+        if config.cleanup == DiskCleanup::None {
+            for directory in [&usage.workspace, &usage.screenshots] {
+                if directory.over_limit() {
+                    tracing::warn!(
+                        agent_id = %self.agent_id,
+                        path = %directory.path.display(),
+                        used_bytes = directory.used_bytes,
+                        limit_bytes = directory.limit_bytes,
+                        "directory over disk quota"
+                    );
+                }
+            }
+            return Ok(usage);
+        }
+
+        let mut cleaned = false;
+        for (directory, protected) in [
+            (&usage.workspace, PROTECTED_WORKSPACE_ENTRIES),
+            (&usage.screenshots, &[][..]),
+        ] {
+            let Some(limit) = directory.limit_bytes else {
+                continue;
+            };
+            if !directory.over_limit() {
+                continue;
+            }
+
+            let path = directory.path.clone();
+            let cleanup =
+                tokio::task::spawn_blocking(move || clean_oldest_first(&path, limit, protected))
+                    .await??;
+            tracing::warn!(
+                agent_id = %self.agent_id,
+                path = %directory.path.display(),
+                limit_bytes = limit,
+                removed_files = cleanup.removed_files,
+                freed_bytes = cleanup.freed_bytes,
+                "directory over disk quota, removed oldest files"
+            );
+            cleaned = true;
+        }
+
+        if cleaned {
+            self.usage().await
+        } else {
+            Ok(usage)
+        }
+    }
+}
+
+/// Spawn the quota enforcement loop for an agent.
+pub fn spawn_quota_loop(quota: Arc<DiskQuota>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let config = quota.runtime_config.disk_quota.load();
+            let interval = Duration::from_secs(config.check_interval_secs);
+            let has_limits =
+                config.workspace_limit_mb.is_some() || config.screenshots_limit_mb.is_some();
+            drop(config);
+
+            if has_limits && let Err(error) = quota.enforce().await {
+                tracing::warn!(%error, agent_id = %quota.agent_id, "disk quota check failed");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+fn megabytes(value: u64) -> u64 {
+    value.saturating_mul(1024 * 1024)
+}
+
+fn measure(root: &Path, limit_bytes: Option<u64>) -> anyhow::Result<DirectoryUsage> {
+    let files = collect_files(root, &[])?;
+    Ok(DirectoryUsage {
+        path: root.to_path_buf(),
+        used_bytes: files.iter().map(|file| file.size).sum(),
+        limit_bytes,
+        file_count: files.len(),
+    })
+}
+
+/// All regular files under `root`, skipping symlinks and the given top-level
+/// entries.
+fn collect_files(root: &Path, skip: &[&str]) -> anyhow::Result<Vec<FileEntry>> {
+    let mut files = Vec::new();
+    if !root.exists() {
+        return Ok(files);
+    }
+
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(path) = stack.pop() {
+        // Files can disappear mid-scan while workers are running.
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.file_type().is_symlink() {
+            continue;
+        }
+        if metadata.is_file() {
+            files.push(FileEntry {
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                path,
+            });
+            continue;
+        }
+        if metadata.is_dir() {
+            for entry in std::fs::read_dir(&path)? {
+                let entry = entry?;
+                let is_skipped = path == root
+                    && entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| skip.contains(&name));
+                if !is_skipped {
+                    stack.push(entry.path());
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Delete the least recently modified files under `root` until the total
+/// size of the directory is at or below `limit_bytes`.
+fn clean_oldest_first(
+    root: &Path,
+    limit_bytes: u64,
+    protected: &[&str],
+) -> anyhow::Result<Cleanup> {
+    let total: u64 = collect_files(root, &[])?.iter().map(|file| file.size).sum();
+    let mut candidates = collect_files(root, protected)?;
+    candidates.sort_by_key(|file| file.modified);
+
+    let mut remaining = total;
+    let mut cleanup = Cleanup::default();
+    for file in candidates {
+        if remaining <= limit_bytes {
+            break;
+        }
+        match std::fs::remove_file(&file.path) {
+            Ok(()) => {
+                remaining = remaining.saturating_sub(file.size);
+                cleanup.removed_files += 1;
+                cleanup.freed_bytes += file.size;
+            }
+            Err(error) => {
+                tracing::debug!(%error, path = %file.path.display(), "can't remove file over quota");
+            }
+        }
+    }
+    Ok(cleanup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(path: &Path, size: usize, modified: SystemTime) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0u8; size]).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn cleanup_removes_oldest_files_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_file(&dir.path().join("old.bin"), 400, base);
+        write_file(
+            &dir.path().join("downloads/middle.bin"),
+            400,
+            base + Duration::from_secs(10),
+        );
+        write_file(
+            &dir.path().join("new.bin"),
+            400,
+            base + Duration::from_secs(20),
+        );
+
+        let cleanup = clean_oldest_first(dir.path(), 800, &[]).unwrap();
+
+        assert_eq!(
+            cleanup,
+            Cleanup {
+                removed_files: 1,
+                freed_bytes: 400
+            }
+        );
+        assert!(!dir.path().join("old.bin").exists());
+        assert!(dir.path().join("downloads/middle.bin").exists());
+        assert!(dir.path().join("new.bin").exists());
+    }
+
+    #[test]
+    fn cleanup_skips_protected_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_file(&dir.path().join("SOUL.md"), 500, base);
+        write_file(&dir.path().join("skills/tool/SKILL.md"), 500, base);
+        write_file(
+            &dir.path().join("artifact.bin"),
+            500,
+            base + Duration::from_secs(10),
+        );
+
+        let cleanup = clean_oldest_first(dir.path(), 100, PROTECTED_WORKSPACE_ENTRIES).unwrap();
+
+        assert_eq!(cleanup.removed_files, 1);
+        assert!(dir.path().join("SOUL.md").exists());
+        assert!(dir.path().join("skills/tool/SKILL.md").exists());
+        assert!(!dir.path().join("artifact.bin").exists());
+    }
+
+    #[test]
+    fn measure_reports_overage() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("a.bin"), 300, SystemTime::now());
+
+        let usage = measure(dir.path(), Some(200)).unwrap();
+        assert_eq!(usage.used_bytes, 300);
+        assert_eq!(usage.file_count, 1);
+        assert!(usage.over_limit());
+        assert!(!measure(dir.path(), None).unwrap().over_limit());
+    }
+}
@@ -0,0 +1,234 @@
+//! Role-based access control: maps platform users to roles and gates capabilities.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+/// Message sources produced by Spacebot itself rather than an external user.
+///
+/// These always resolve to [`Role::Owner`]. `webchat` is included because it
+/// is only reachable through the (token-protected) control API.
+const TRUSTED_SOURCES: &[&str] = &["system", "cron", "webchat"];
+
+/// A user's role. Ordered from least to most privileged so roles can be
+/// compared directly (`role >= Role::Admin`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Guest,
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Guest => "guest",
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "guest" => Some(Self::Guest),
+            "member" => Some(Self::Member),
+            "admin" => Some(Self::Admin),
+            "owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something a user can ask the agent to do that is gated by role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Invoke built-in platform commands (e.g. Slack slash commands).
+    Commands,
+    /// Have the channel spawn builtin workers (shell, file, exec).
+    Workers,
+    /// Have the channel spawn OpenCode coding workers.
+    OpencodeWorkers,
+    /// Answer or steer running workers (route follow-ups, cancel processes).
+    ToolApproval,
+    /// Change agent settings such as scheduled cron jobs.
+    Settings,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Self::Commands,
+        Self::Workers,
+        Self::OpencodeWorkers,
+        Self::ToolApproval,
+        Self::Settings,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Commands => "commands",
+            Self::Workers => "workers",
+            Self::OpencodeWorkers => "opencode_workers",
+            Self::ToolApproval => "tool_approval",
+            Self::Settings => "settings",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.as_str() == value)
+    }
+
+    /// Minimum role required when the config doesn't override it.
+    pub fn default_min_role(&self) -> Role {
+        match self {
+            Self::Commands | Self::Workers | Self::ToolApproval => Role::Member,
+            Self::OpencodeWorkers | Self::Settings => Role::Admin,
+        }
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A configured user and the platform identities linked to them.
+#[derive(Debug, Clone)]
+pub struct RbacUser {
+    /// Human-readable name, used in logs only.
+    pub name: String,
+    pub role: Role,
+    /// Linked identities in `platform:sender_id` form (e.g. `discord:1234`).
+    pub identities: Vec<String>,
+}
+
+/// Instance-wide RBAC configuration.
+#[derive(Debug, Clone)]
+pub struct RbacConfig {
+    /// When disabled, every sender is treated as an owner.
+    pub enabled: bool,
+    /// Role for senders that don't match any configured user.
+    pub default_role: Role,
+    pub users: Vec<RbacUser>,
+    /// Per-capability minimum role overrides.
+    pub capabilities: HashMap<Capability, Role>,
+}
+
+impl Default for RbacConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_role: Role::Member,
+            users: Vec::new(),
+            capabilities: HashMap::new(),
+        }
+    }
+}
+
+impl RbacConfig {
+    /// Resolve the role for a sender on a given platform.
+    ///
+    /// If the same identity is linked to several users, the most privileged
+    /// role wins.
+    pub fn resolve_role(&self, source: &str, sender_id: &str) -> Role {
+        if !self.enabled || TRUSTED_SOURCES.contains(&source) {
+            return Role::Owner;
+        }
+
+        let identity = format!("{source}:{sender_id}");
+        self.users
+            .iter()
+            .filter(|user| user.identities.iter().any(|linked| *linked == identity))
+            .map(|user| user.role)
+            .max()
+            .unwrap_or(self.default_role)
+    }
+
+    /// Minimum role required for a capability.
+    pub fn min_role(&self, capability: Capability) -> Role {
+        self.capabilities
+            .get(&capability)
+            .copied()
+            .unwrap_or_else(|| capability.default_min_role())
+    }
+
+    /// Check whether a role may use a capability.
+    pub fn allows(&self, role: Role, capability: Capability) -> bool {
+        !self.enabled || role >= self.min_role(capability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RbacConfig {
+        RbacConfig {
+            enabled: true,
+            default_role: Role::Guest,
+            users: vec![
+                RbacUser {
+                    name: "alice".into(),
+                    role: Role::Owner,
+                    identities: vec!["discord:111".into(), "slack:U111".into()],
+                },
+                RbacUser {
+                    name: "bob".into(),
+                    role: Role::Member,
+                    identities: vec!["telegram:222".into()],
+                },
+            ],
+            capabilities: HashMap::from([(Capability::Workers, Role::Admin)]),
+        }
+    }
+
+    #[test]
+    fn linked_identities_share_a_role() {
+        let config = config();
+        assert_eq!(config.resolve_role("discord", "111"), Role::Owner);
+        assert_eq!(config.resolve_role("slack", "U111"), Role::Owner);
+        assert_eq!(config.resolve_role("telegram", "222"), Role::Member);
+    }
+
+    #[test]
+    fn unknown_senders_get_default_role() {
+        let config = config();
+        assert_eq!(config.resolve_role("discord", "999"), Role::Guest);
+        // Same ID on a different platform is a different identity.
+        assert_eq!(config.resolve_role("telegram", "111"), Role::Guest);
+    }
+
+    #[test]
+    fn trusted_sources_are_owners() {
+        let config = config();
+        assert_eq!(config.resolve_role("cron", "anything"), Role::Owner);
+        assert_eq!(config.resolve_role("system", "system"), Role::Owner);
+    }
+
+    #[test]
+    fn capability_overrides_apply() {
+        let config = config();
+        assert!(!config.allows(Role::Member, Capability::Workers));
+        assert!(config.allows(Role::Admin, Capability::Workers));
+        assert!(config.allows(Role::Member, Capability::Commands));
+        assert!(!config.allows(Role::Member, Capability::OpencodeWorkers));
+    }
+
+    #[test]
+    fn disabled_allows_everything() {
+        let config = RbacConfig::default();
+        assert_eq!(config.resolve_role("discord", "999"), Role::Owner);
+        assert!(config.allows(Role::Guest, Capability::Settings));
+    }
+}
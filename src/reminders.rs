@@ -0,0 +1,290 @@
+//! Reminders: messages an agent set for itself to bring up in a conversation
+//! later, like "remind me in 2 hours".
+//!
+//! The `set_reminder` channel tool saves a reminder to the agent's database
+//! with a copy of the message it was set from. A loop per agent checks for
+//! due reminders every [`POLL_INTERVAL`] and injects each one into its
+//! conversation as a message from the same sender, which wakes the channel
+//! (or starts a new one after a restart) and lets it deliver the reminder.
+//! A reminder is deleted once it's delivered, and reminders that came due
+//! while Spacebot was down are delivered when it starts.
+
+use crate::error::Result;
+use crate::{AgentDeps, InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use sqlx::{Row as _, SqlitePool};
+
+use std::time::Duration;
+
+/// How often each agent checks for due reminders.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Furthest ahead a reminder can be set.
+pub const MAX_REMINDER_DAYS: i64 = 366;
+
+/// Metadata keys of the source message that only apply to it, and aren't
+/// copied to the reminder.
+pub(crate) const ONE_SHOT_KEYS: &[&str] = &[
+    crate::agent::fork::FORK_HISTORY_KEY,
+    crate::agent::fork::FORK_PARENT_KEY,
+    crate::agent::handoff::HANDOFF_KEY,
+    "offline_replay",
+];
+
+/// A reminder waiting to be delivered.
+#[derive(Debug, Clone)]
+pub struct Reminder {
+    pub id: String,
+    pub conversation_id: String,
+    /// What to remind about, in the words of the agent that set it.
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// The message the reminder was set from, replayed when it's due.
+    pub source: InboundMessage,
+}
+
+/// Reminder storage (SQLite).
+#[derive(Debug, Clone)]
+pub struct ReminderStore {
+    pool: SqlitePool,
+}
+
+impl ReminderStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Save a reminder set from `source`, due at `due_at`.
+    pub async fn create(
+        &self,
+        source: &InboundMessage,
+        message: &str,
+        due_at: DateTime<Utc>,
+    ) -> Result<Reminder> {
+        let mut source = source.clone();
+        for key in ONE_SHOT_KEYS {
+            source.metadata.remove(*key);
+        }
+        let reminder = Reminder {
+            id: uuid::Uuid::new_v4().to_string(),
+            conversation_id: source.conversation_id.clone(),
+            message: message.to_string(),
+            due_at,
+            created_at: Utc::now(),
+            source,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO reminders (id, conversation_id, message, due_at, created_at, source)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&reminder.id)
+        .bind(&reminder.conversation_id)
+        .bind(&reminder.message)
+        .bind(reminder.due_at.timestamp())
+        .bind(reminder.created_at.timestamp())
+        .bind(serde_json::to_string(&reminder.source).context("failed to encode reminder")?)
+        .execute(&self.pool)
+        .await
+        .context("failed to save reminder")?;
+
+        Ok(reminder)
+    }
+
+    /// Reminders due at or before `now`, oldest first.
+    pub async fn due(&self, now: DateTime<Utc>) -> Result<Vec<Reminder>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, conversation_id, message, due_at, created_at, source
+            FROM reminders
+            WHERE due_at <= ?
+            ORDER BY due_at ASC
+            "#,
+        )
+        .bind(now.timestamp())
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to load due reminders")?;
+
+        let mut reminders = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.try_get("id").unwrap_or_default();
+            let source = row
+                .try_get::<String, _>("source")
+                .ok()
+                .and_then(|source| serde_json::from_str::<InboundMessage>(&source).ok());
+            let Some(source) = source else {
+                tracing::warn!(reminder_id = %id, "can't read reminder, dropping it");
+                self.delete(&id).await?;
+                continue;
+            };
+            let timestamp = |column: &str| {
+                row.try_get::<i64, _>(column)
+                    .ok()
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                    .unwrap_or(now)
+            };
+            reminders.push(Reminder {
+                conversation_id: row.try_get("conversation_id").unwrap_or_default(),
+                message: row.try_get("message").unwrap_or_default(),
+                due_at: timestamp("due_at"),
+                created_at: timestamp("created_at"),
+                id,
+                source,
+            });
+        }
+        Ok(reminders)
+    }
+
+    /// Delete a reminder.
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM reminders WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("failed to delete reminder")?;
+
+        Ok(())
+    }
+}
+
+/// When a reminder is due. `when` is a delay like `90s`, `45m`, `2h`,
+/// `1d 6h` or `in 1 hour 30 minutes`, or an RFC 3339 timestamp. Returns
+/// `None` when it can't be read or isn't in the future.
+pub fn parse_when(when: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let when = when.trim();
+    let due_at = match DateTime::parse_from_rfc3339(when) {
+        Ok(timestamp) => timestamp.with_timezone(&Utc),
+        Err(_) => now + parse_delay(when)?,
+    };
+    (due_at > now && due_at <= now + chrono::Duration::days(MAX_REMINDER_DAYS)).then_some(due_at)
+}
+
+/// A delay made of number and unit pairs, like `1h30m` or `2 days`.
+fn parse_delay(delay: &str) -> Option<chrono::Duration> {
+    let delay = delay.to_lowercase();
+    let mut rest = delay.strip_prefix("in ").unwrap_or(&delay);
+    let mut total = chrono::Duration::zero();
+    let mut parts = 0;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i32 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+        let letters = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..letters] {
+            "s" | "sec" | "secs" | "second" | "seconds" => chrono::Duration::seconds(1),
+            "m" | "min" | "mins" | "minute" | "minutes" => chrono::Duration::minutes(1),
+            "h" | "hr" | "hrs" | "hour" | "hours" => chrono::Duration::hours(1),
+            "d" | "day" | "days" => chrono::Duration::days(1),
+            "w" | "week" | "weeks" => chrono::Duration::weeks(1),
+            _ => return None,
+        };
+        total = total.checked_add(&unit.checked_mul(amount)?)?;
+        rest = rest[letters..]
+            .strip_prefix(" and")
+            .unwrap_or(&rest[letters..]);
+        parts += 1;
+    }
+    (parts > 0).then_some(total)
+}
+
+/// Check for due reminders every [`POLL_INTERVAL`] and inject them into
+/// their conversations. Does nothing for agents without messaging.
+pub fn spawn_reminder_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let Some(messaging_manager) = deps.messaging_manager.clone() else {
+            tracing::debug!(agent_id = %deps.agent_id, "no messaging, reminders disabled");
+            return;
+        };
+        let store = ReminderStore::new(deps.sqlite_pool.clone());
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let reminders = match store.due(Utc::now()).await {
+                Ok(reminders) => reminders,
+                Err(error) => {
+                    tracing::warn!(agent_id = %deps.agent_id, %error, "failed to check reminders");
+                    continue;
+                }
+            };
+            for reminder in reminders {
+                let text = match deps.runtime_config.prompts.load().render_system_reminder(
+                    &reminder.message,
+                    &reminder.created_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+                ) {
+                    Ok(text) => text,
+                    Err(error) => {
+                        tracing::warn!(%error, "failed to render reminder");
+                        continue;
+                    }
+                };
+                let message = InboundMessage {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    agent_id: Some(deps.agent_id.clone()),
+                    content: MessageContent::Text(text),
+                    timestamp: Utc::now(),
+                    ..reminder.source
+                };
+                if let Err(error) = messaging_manager.inject_message(message).await {
+                    tracing::warn!(reminder_id = %reminder.id, %error, "failed to deliver reminder");
+                    continue;
+                }
+                tracing::info!(
+                    agent_id = %deps.agent_id,
+                    reminder_id = %reminder.id,
+                    conversation_id = %reminder.conversation_id,
+                    "reminder delivered"
+                );
+                if let Err(error) = store.delete(&reminder.id).await {
+                    tracing::warn!(reminder_id = %reminder.id, %error, "failed to delete delivered reminder");
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reminder_times_are_parsed() {
+        let now = DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let after = |when: &str| parse_when(when, now).map(|due_at| due_at - now);
+
+        assert_eq!(after("90s"), Some(chrono::Duration::seconds(90)));
+        assert_eq!(after("2h"), Some(chrono::Duration::hours(2)));
+        assert_eq!(after("1h30m"), Some(chrono::Duration::minutes(90)));
+        assert_eq!(
+            after("in 1 day, 2 hours and 5 minutes"),
+            Some(chrono::Duration::minutes(26 * 60 + 5))
+        );
+        assert_eq!(
+            after("2026-03-01T14:00:00+01:00"),
+            Some(chrono::Duration::hours(1))
+        );
+
+        // Past, too far off or unreadable.
+        assert_eq!(after("2026-03-01T11:00:00Z"), None);
+        assert_eq!(after("0m"), None);
+        assert_eq!(after("400 days"), None);
+        assert_eq!(after("tomorrow"), None);
+        assert_eq!(after("2 fortnights"), None);
+    }
+}
@@ -0,0 +1,328 @@
+//! Container isolation for worker shell, exec and file tools.
+//!
+//! A `Sandbox` owns one ephemeral container per worker. The container is
+//! started lazily on the first command, runs `sleep infinity`, and every
+//! tool call becomes a `<runtime> exec` into it. The agent workspace is
+//! bind-mounted at `/workspace`. File reads, writes and listings are run in
+//! the container too, as `cat`, `sh` and `find`, so they see the same
+//! filesystem, users and mounts as the worker's commands.
+
+use crate::WorkerId;
+use crate::config::SandboxConfig;
+
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+
+/// Mount point of the agent workspace inside the container.
+pub const CONTAINER_WORKSPACE: &str = "/workspace";
+
+/// Errors from starting or addressing a worker container.
+#[derive(Debug, thiserror::Error)]
+pub enum SandboxError {
+    #[error("can't start {runtime} container: {message}")]
+    StartFailed { runtime: String, message: String },
+
+    #[error("can't run in container: {0} is outside the mounted workspace")]
+    OutsideWorkspace(PathBuf),
+}
+
+/// An ephemeral container that a single worker's commands run in.
+#[derive(Debug)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    runtime: &'static str,
+    workspace: PathBuf,
+    container_name: String,
+    started: OnceCell<()>,
+}
+
+impl Sandbox {
+    /// Create a sandbox for a worker. Returns None for the host backend.
+    pub fn new(
+        config: SandboxConfig,
+        workspace: PathBuf,
+        worker_id: WorkerId,
+    ) -> Option<Arc<Self>> {
+        let runtime = config.backend.runtime()?;
+        Some(Arc::new(Self {
+            config,
+            runtime,
+            workspace,
+            container_name: format!("spacebot-worker-{worker_id}"),
+            started: OnceCell::new(),
+        }))
+    }
+
+    pub fn container_name(&self) -> &str {
+        &self.container_name
+    }
+
+    /// Build a command that runs `program` inside the container, starting
+    /// the container first if this is the worker's first command.
+    pub async fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+    ) -> Result<Command, SandboxError> {
+        self.build_command(program, args, working_dir, env, false)
+            .await
+    }
+
+    /// Like [`Sandbox::command`], but keeps the command's stdin attached so
+    /// input piped to it reaches the program in the container.
+    pub async fn command_with_stdin(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+    ) -> Result<Command, SandboxError> {
+        self.build_command(program, args, working_dir, env, true)
+            .await
+    }
+
+    async fn build_command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+        interactive: bool,
+    ) -> Result<Command, SandboxError> {
+        let exec_args = self.exec_args(program, args, working_dir, env, interactive)?;
+        self.ensure_started().await?;
+
+        let mut command = Command::new(self.runtime);
+        command.args(exec_args).kill_on_drop(true);
+        Ok(command)
+    }
+
+    async fn ensure_started(&self) -> Result<(), SandboxError> {
+        self.started
+            .get_or_try_init(|| async {
+                let output = Command::new(self.runtime)
+                    .args(self.run_args())
+                    .stdin(Stdio::null())
+                    .output()
+                    .await
+                    .map_err(|error| SandboxError::StartFailed {
+                        runtime: self.runtime.to_string(),
+                        message: error.to_string(),
+                    })?;
+
+                if !output.status.success() {
+                    return Err(SandboxError::StartFailed {
+                        runtime: self.runtime.to_string(),
+                        message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                    });
+                }
+
+                tracing::info!(
+                    container = %self.container_name,
+                    image = %self.config.image,
+                    network = %self.config.network,
+                    "worker container started"
+                );
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Arguments for `<runtime> run` that start the long-lived container.
+    fn run_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "--detach".into(),
+            "--rm".into(),
+            "--name".into(),
+            self.container_name.clone(),
+            "--network".into(),
+            self.config.network.clone(),
+            "--security-opt".into(),
+            "no-new-privileges".into(),
+        ];
+
+        if let Some(memory_limit) = &self.config.memory_limit {
+            args.extend(["--memory".into(), memory_limit.clone()]);
+        }
+        if let Some(cpus) = self.config.cpus {
+            args.extend(["--cpus".into(), cpus.to_string()]);
+        }
+        if let Some(pids_limit) = self.config.pids_limit {
+            args.extend(["--pids-limit".into(), pids_limit.to_string()]);
+        }
+
+        args.extend([
+            "--volume".into(),
+            format!("{}:{CONTAINER_WORKSPACE}", self.workspace.display()),
+            "--workdir".into(),
+            CONTAINER_WORKSPACE.into(),
+            self.config.image.clone(),
+            "sleep".into(),
+            "infinity".into(),
+        ]);
+        args
+    }
+
+    /// Arguments for `<runtime> exec` that run one command in the container.
+    /// `interactive` keeps stdin open; without it the runtime never passes
+    /// piped input on.
+    fn exec_args(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+        interactive: bool,
+    ) -> Result<Vec<String>, SandboxError> {
+        let container_dir = match working_dir {
+            Some(dir) => self.container_path(dir)?,
+            None => PathBuf::from(CONTAINER_WORKSPACE),
+        };
+
+        let mut exec_args = vec![
+            "exec".to_string(),
+            "--workdir".into(),
+            container_dir.display().to_string(),
+        ];
+        if interactive {
+            exec_args.push("--interactive".into());
+        }
+        for (key, value) in env {
+            exec_args.extend(["--env".into(), format!("{key}={value}")]);
+        }
+        exec_args.push(self.container_name.clone());
+        exec_args.push(program.to_string());
+        exec_args.extend(args.iter().cloned());
+        Ok(exec_args)
+    }
+
+    /// Map a host path inside the workspace to its path in the container.
+    pub fn container_path(&self, host_path: &Path) -> Result<PathBuf, SandboxError> {
+        let relative = host_path
+            .strip_prefix(&self.workspace)
+            .or_else(|_| {
+                let canonical_workspace = self.workspace.canonicalize()?;
+                let canonical_path = host_path.canonicalize()?;
+                canonical_path
+                    .strip_prefix(&canonical_workspace)
+                    .map(Path::to_path_buf)
+                    .map_err(std::io::Error::other)
+            })
+            .map_err(|_| SandboxError::OutsideWorkspace(host_path.to_path_buf()))?;
+
+        Ok(Path::new(CONTAINER_WORKSPACE).join(relative))
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if self.started.initialized() {
+            let result = std::process::Command::new(self.runtime)
+                .args(["rm", "--force", &self.container_name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            if let Err(error) = result {
+                tracing::warn!(%error, container = %self.container_name, "failed to remove worker container");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SandboxBackend;
+
+    fn sandbox() -> Arc<Sandbox> {
+        let config = SandboxConfig {
+            backend: SandboxBackend::Docker,
+            ..SandboxConfig::default()
+        };
+        Sandbox::new(
+            config,
+            PathBuf::from("/srv/agent/workspace"),
+            uuid::Uuid::nil(),
+        )
+        .expect("docker backend yields a sandbox")
+    }
+
+    #[test]
+    fn host_backend_has_no_sandbox() {
+        let sandbox = Sandbox::new(
+            SandboxConfig::default(),
+            PathBuf::from("/tmp"),
+            uuid::Uuid::nil(),
+        );
+        assert!(sandbox.is_none());
+    }
+
+    #[test]
+    fn run_args_apply_limits_and_mount() {
+        let args = sandbox().run_args();
+        let joined = args.join(" ");
+        assert!(joined.contains("--network none"));
+        assert!(joined.contains("--memory 1g"));
+        assert!(joined.contains("--pids-limit 256"));
+        assert!(joined.contains("--volume /srv/agent/workspace:/workspace"));
+        assert!(joined.ends_with("debian:bookworm-slim sleep infinity"));
+    }
+
+    #[test]
+    fn exec_args_map_working_dir_into_container() {
+        let sandbox = sandbox();
+        let args = sandbox
+            .exec_args(
+                "cargo",
+                &["test".to_string()],
+                Some(Path::new("/srv/agent/workspace/project")),
+                &[("RUST_LOG".to_string(), "debug".to_string())],
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "exec",
+                "--workdir",
+                "/workspace/project",
+                "--env",
+                "RUST_LOG=debug",
+                sandbox.container_name(),
+                "cargo",
+                "test",
+            ]
+        );
+    }
+
+    #[test]
+    fn exec_args_reject_paths_outside_workspace() {
+        let result = sandbox().exec_args("ls", &[], Some(Path::new("/etc")), &[], false);
+        assert!(matches!(result, Err(SandboxError::OutsideWorkspace(_))));
+    }
+
+    #[test]
+    fn exec_args_keep_stdin_open_when_piped() {
+        let sandbox = sandbox();
+        let args = sandbox.exec_args("cat", &[], None, &[], true).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "exec",
+                "--workdir",
+                "/workspace",
+                "--interactive",
+                sandbox.container_name(),
+                "cat",
+            ]
+        );
+    }
+}
@@ -0,0 +1,698 @@
+//! Rhai scripts for small per-agent automations.
+//!
+//! Every `.rhai` file in an agent's `workspace/scripts/` directory is loaded
+//! and may define any of these hooks:
+//!
+//! - `on_message(message)`: runs for each inbound message before the agent
+//!   sees it. Returning `true` marks the message handled, and it never
+//!   reaches the channel.
+//! - `on_worker_complete(worker)`: runs when one of the agent's workers
+//!   finishes.
+//! - `on_schedule()`: runs every `SCHEDULE_SECS` seconds, a constant the
+//!   script declares.
+//!
+//! Scripts can't touch the filesystem, network or other modules. They act
+//! through a small API, `send_message`, `reply`, `save_memory`,
+//! `spawn_worker` and `log`. Calls are collected while the script runs and
+//! carried out once it returns, so a script never blocks on the network.
+//! Only functions and constants are read from a script; top-level
+//! statements don't run. The directory is rescanned every few seconds, so
+//! edits apply without a restart.
+
+use crate::config::ScriptingConfig;
+use crate::events::{EventFilter, EventSubscription};
+use crate::messaging::target::{BroadcastTarget, parse_delivery_target};
+use crate::tools::{MemorySaveArgs, MemorySaveTool};
+use crate::{Agent, AgentDeps, InboundMessage, OutboundResponse, ProcessEvent, WorkerId};
+
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope};
+use rig::tool::Tool as _;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the scripts directory is checked for changes.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Constant a script declares to run `on_schedule` periodically.
+const SCHEDULE_CONSTANT: &str = "SCHEDULE_SECS";
+
+/// Workers spawned by scripts, so `on_worker_complete` can tell them apart
+/// and scripts don't trigger themselves by accident.
+static SCRIPT_WORKERS: LazyLock<Mutex<HashSet<WorkerId>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Where scripts are loaded from inside an agent workspace.
+pub fn scripts_dir(workspace: &Path) -> PathBuf {
+    workspace.join("scripts")
+}
+
+/// Something a script asked for, carried out after it returns.
+#[derive(Debug, Clone, PartialEq)]
+enum ScriptAction {
+    Reply(String),
+    SendMessage {
+        target: BroadcastTarget,
+        text: String,
+    },
+    SaveMemory {
+        content: String,
+        memory_type: String,
+    },
+    SpawnWorker(String),
+}
+
+/// A compiled script and the hooks it defines.
+#[derive(Debug, Clone)]
+struct Script {
+    name: String,
+    ast: AST,
+    on_message: bool,
+    on_worker_complete: bool,
+    schedule: Option<Duration>,
+}
+
+impl Script {
+    fn compile(path: &Path, config: &ScriptingConfig) -> anyhow::Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "script".into());
+        let source = std::fs::read_to_string(path)?;
+        let engine = sandboxed_engine(&name, config);
+        let ast = engine
+            .compile(&source)
+            .map_err(|error| anyhow::anyhow!("can't compile: {error}"))?;
+
+        let defines = |hook: &str, arity: usize| {
+            ast.iter_functions()
+                .any(|function| function.name == hook && function.params.len() == arity)
+        };
+        let on_message = defines("on_message", 1);
+        let on_worker_complete = defines("on_worker_complete", 1);
+        let schedule = if defines("on_schedule", 0) {
+            let seconds = ast
+                .iter_literal_variables(true, false)
+                .find(|(constant, ..)| *constant == SCHEDULE_CONSTANT)
+                .and_then(|(.., value)| value.as_int().ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("on_schedule needs `const {SCHEDULE_CONSTANT} = <seconds>;`")
+                })?;
+            if seconds < 1 {
+                anyhow::bail!("{SCHEDULE_CONSTANT} must be at least 1");
+            }
+            Some(Duration::from_secs(seconds as u64))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name,
+            ast,
+            on_message,
+            on_worker_complete,
+            schedule,
+        })
+    }
+
+    /// Call a hook and collect the actions it requested. `message` enables
+    /// `reply`.
+    fn run(
+        &self,
+        hook: &str,
+        argument: Option<Dynamic>,
+        message: Option<&InboundMessage>,
+        config: &ScriptingConfig,
+    ) -> (Dynamic, Vec<ScriptAction>) {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = sandboxed_engine(&self.name, config);
+        register_api(&mut engine, &actions, message.is_some(), config.max_actions);
+
+        let options = CallFnOptions::new().eval_ast(false);
+        let mut scope = Scope::new();
+        let result = match argument {
+            Some(argument) => engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut scope,
+                &self.ast,
+                hook,
+                (argument,),
+            ),
+            None => {
+                engine.call_fn_with_options::<Dynamic>(options, &mut scope, &self.ast, hook, ())
+            }
+        };
+        let result = match result {
+            Ok(value) => value,
+            Err(error) => {
+                // Actions requested before the error are still carried out.
+                tracing::warn!(script = %self.name, hook, %error, "script failed");
+                Dynamic::UNIT
+            }
+        };
+        let actions = actions
+            .lock()
+            .map(|mut actions| std::mem::take(&mut *actions))
+            .unwrap_or_default();
+        (result, actions)
+    }
+}
+
+/// An engine that can't reach outside the script, with resource limits.
+fn sandboxed_engine(name: &str, config: &ScriptingConfig) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .set_max_operations(config.max_operations)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1024 * 1024)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000);
+    engine.disable_symbol("eval");
+
+    let script = name.to_string();
+    engine.on_print(move |text| tracing::info!(script = %script, "{text}"));
+    let script = name.to_string();
+    engine.on_debug(move |text, _, _| tracing::debug!(script = %script, "{text}"));
+    let script = name.to_string();
+    engine.register_fn(
+        "log",
+        move |text: &str| tracing::info!(script = %script, "{text}"),
+    );
+    engine
+}
+
+fn register_api(
+    engine: &mut Engine,
+    actions: &Arc<Mutex<Vec<ScriptAction>>>,
+    can_reply: bool,
+    max_actions: usize,
+) {
+    let push = {
+        let actions = actions.clone();
+        move |action: ScriptAction| -> Result<(), Box<EvalAltResult>> {
+            let mut actions = actions.lock().map_err(|_| "script actions poisoned")?;
+            if actions.len() >= max_actions {
+                return Err(
+                    format!("scripts may request at most {max_actions} actions per run").into(),
+                );
+            }
+            actions.push(action);
+            Ok(())
+        }
+    };
+
+    let send = push.clone();
+    engine.register_fn("send_message", move |target: &str, text: &str| {
+        let target = parse_delivery_target(target).ok_or_else(|| {
+            Box::<EvalAltResult>::from(format!(
+                "invalid target '{target}', expected 'adapter:target'"
+            ))
+        })?;
+        send(ScriptAction::SendMessage {
+            target,
+            text: text.to_string(),
+        })
+    });
+
+    let save = push.clone();
+    engine.register_fn("save_memory", move |content: &str| {
+        save(ScriptAction::SaveMemory {
+            content: content.to_string(),
+            memory_type: "fact".into(),
+        })
+    });
+    let save = push.clone();
+    engine.register_fn("save_memory", move |content: &str, memory_type: &str| {
+        save(ScriptAction::SaveMemory {
+            content: content.to_string(),
+            memory_type: memory_type.to_string(),
+        })
+    });
+
+    let spawn = push.clone();
+    engine.register_fn("spawn_worker", move |task: &str| {
+        spawn(ScriptAction::SpawnWorker(task.to_string()))
+    });
+
+    if can_reply {
+        engine.register_fn("reply", move |text: &str| {
+            push(ScriptAction::Reply(text.to_string()))
+        });
+    }
+}
+
+/// The scripts currently loaded for an agent.
+#[derive(Debug, Default)]
+pub struct ScriptSet {
+    scripts: Vec<Script>,
+    /// File paths and modification times the set was loaded from.
+    sources: Vec<(PathBuf, SystemTime)>,
+}
+
+impl ScriptSet {
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    fn load(directory: &Path, config: &ScriptingConfig) -> Self {
+        let sources = scan(directory);
+        let scripts = sources
+            .iter()
+            .filter_map(|(path, _)| match Script::compile(path, config) {
+                Ok(script) => {
+                    tracing::info!(script = %script.name, "script loaded");
+                    Some(script)
+                }
+                Err(error) => {
+                    tracing::warn!(%error, path = %path.display(), "can't load script");
+                    None
+                }
+            })
+            .collect();
+        Self { scripts, sources }
+    }
+}
+
+fn scan(directory: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut sources: Vec<(PathBuf, SystemTime)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "rhai")
+        })
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    sources.sort();
+    sources
+}
+
+/// What a script needs to carry out its actions.
+#[derive(Clone)]
+pub struct ScriptContext {
+    deps: AgentDeps,
+    workspace: PathBuf,
+    screenshot_dir: PathBuf,
+    logs_dir: PathBuf,
+}
+
+impl ScriptContext {
+    pub fn new(agent: &Agent) -> Self {
+        Self {
+            deps: agent.deps.clone(),
+            workspace: agent.config.workspace.clone(),
+            screenshot_dir: agent.config.screenshot_dir(),
+            logs_dir: agent.config.logs_dir(),
+        }
+    }
+
+    async fn perform(
+        &self,
+        script: &str,
+        actions: Vec<ScriptAction>,
+        message: Option<&InboundMessage>,
+    ) {
+        for action in actions {
+            if let Err(error) = self.perform_one(action, message).await {
+                tracing::warn!(script, %error, "script action failed");
+            }
+        }
+    }
+
+    async fn perform_one(
+        &self,
+        action: ScriptAction,
+        message: Option<&InboundMessage>,
+    ) -> anyhow::Result<()> {
+        let messaging = || {
+            self.deps
+                .messaging_manager
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("messaging is not available"))
+        };
+        match action {
+            ScriptAction::Reply(text) => {
+                let message = message.ok_or_else(|| anyhow::anyhow!("no message to reply to"))?;
+                messaging()?
+                    .respond(message, OutboundResponse::Text(text))
+                    .await?;
+            }
+            ScriptAction::SendMessage { target, text } => {
+                messaging()?
+                    .broadcast(
+                        &target.adapter,
+                        &target.target,
+                        OutboundResponse::Text(text),
+                    )
+                    .await?;
+            }
+            ScriptAction::SaveMemory {
+                content,
+                memory_type,
+            } => {
+                MemorySaveTool::new(self.deps.memory_search.clone())
+                    .call(MemorySaveArgs {
+                        content,
+                        memory_type,
+                        importance: None,
+                        source: Some("script".into()),
+                        channel_id: message.map(|message| message.conversation_id.clone()),
+                        associations: Vec::new(),
+                        pinned: false,
+                    })
+                    .await
+                    .map_err(|error| anyhow::anyhow!("{error}"))?;
+            }
+            ScriptAction::SpawnWorker(task) => {
+                let worker_id = crate::agent::channel::spawn_detached_worker(
+                    &self.deps,
+                    task,
+                    crate::agent::task_queue::TaskPriority::Normal,
+                    self.screenshot_dir.clone(),
+                    self.logs_dir.clone(),
+                )?;
+                if let Ok(mut spawned) = SCRIPT_WORKERS.lock() {
+                    spawned.insert(worker_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scripts(&self) -> Arc<ScriptSet> {
+        self.deps.runtime_config.scripts.load_full()
+    }
+
+    fn config(&self) -> ScriptingConfig {
+        (**self.deps.runtime_config.scripting.load()).clone()
+    }
+
+    /// Run every script's hook on a blocking thread. Returns whether any
+    /// script's hook returned `true`, with the actions per script.
+    async fn run_hook(
+        &self,
+        hook: &'static str,
+        selected: impl Fn(&Script) -> bool + Send + 'static,
+        argument: Option<Dynamic>,
+        message: Option<InboundMessage>,
+    ) -> (bool, Vec<(String, Vec<ScriptAction>)>) {
+        let scripts = self.scripts();
+        let config = self.config();
+        tokio::task::spawn_blocking(move || {
+            let mut handled = false;
+            let mut requested = Vec::new();
+            for script in scripts.scripts.iter().filter(|script| selected(script)) {
+                let (result, actions) =
+                    script.run(hook, argument.clone(), message.as_ref(), &config);
+                handled |= result.as_bool().unwrap_or(false);
+                if !actions.is_empty() {
+                    requested.push((script.name.clone(), actions));
+                }
+            }
+            (handled, requested)
+        })
+        .await
+        .unwrap_or_else(|error| {
+            tracing::warn!(%error, hook, "script task failed");
+            (false, Vec::new())
+        })
+    }
+}
+
+/// Run the agent's `on_message` hooks. Returns `true` when a script handled
+/// the message and the agent shouldn't see it. Actions run in the
+/// background so the caller isn't held up by the network.
+pub async fn on_message(context: &ScriptContext, message: &InboundMessage) -> bool {
+    if !context.config().enabled
+        || !context
+            .scripts()
+            .scripts
+            .iter()
+            .any(|script| script.on_message)
+    {
+        return false;
+    }
+
+    let mut map = Map::new();
+    map.insert("source".into(), message.source.clone().into());
+    map.insert(
+        "conversation_id".into(),
+        message.conversation_id.clone().into(),
+    );
+    map.insert("sender_id".into(), message.sender_id.clone().into());
+    map.insert("text".into(), message.content.to_string().into());
+
+    let (handled, requested) = context
+        .run_hook(
+            "on_message",
+            |script| script.on_message,
+            Some(map.into()),
+            Some(message.clone()),
+        )
+        .await;
+
+    if !requested.is_empty() {
+        let context = context.clone();
+        let message = message.clone();
+        tokio::spawn(async move {
+            for (script, actions) in requested {
+                context.perform(&script, actions, Some(&message)).await;
+            }
+        });
+    }
+    if handled {
+        tracing::info!(
+            conversation_id = %message.conversation_id,
+            "message handled by script"
+        );
+    }
+    handled
+}
+
+/// Keep the agent's scripts loaded and run their worker and schedule hooks.
+pub fn spawn_script_loop(context: ScriptContext) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let agent_id = context.deps.agent_id.clone();
+        let mut events = EventSubscription::new(
+            agent_id.clone(),
+            "scripting",
+            context.deps.event_tx.subscribe(),
+            EventFilter::all().agent(agent_id),
+        );
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        let mut last_scan: Option<Instant> = None;
+        let mut last_runs: std::collections::HashMap<String, Instant> = Default::default();
+
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    if last_scan.is_none_or(|scanned| scanned.elapsed() >= RESCAN_INTERVAL) {
+                        last_scan = Some(Instant::now());
+                        reload_if_changed(&context).await;
+                    }
+                    run_due_schedules(&context, &mut last_runs).await;
+                }
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    if let ProcessEvent::WorkerComplete { worker_id, channel_id, result, .. } = event {
+                        on_worker_complete(&context, worker_id, channel_id.as_deref(), result).await;
+                    }
+                }
+            }
+        }
+    })
+}
+
+async fn reload_if_changed(context: &ScriptContext) {
+    let config = context.config();
+    let directory = scripts_dir(&context.workspace);
+    let current = context.scripts();
+    let unchanged = tokio::task::spawn_blocking({
+        let directory = directory.clone();
+        move || scan(&directory)
+    })
+    .await
+    .is_ok_and(|sources| sources == current.sources);
+    if unchanged {
+        return;
+    }
+
+    let loaded = tokio::task::spawn_blocking(move || ScriptSet::load(&directory, &config)).await;
+    if let Ok(scripts) = loaded {
+        context.deps.runtime_config.scripts.store(Arc::new(scripts));
+    }
+}
+
+async fn run_due_schedules(
+    context: &ScriptContext,
+    last_runs: &mut std::collections::HashMap<String, Instant>,
+) {
+    if !context.config().enabled {
+        return;
+    }
+    let scripts = context.scripts();
+    let mut due = HashSet::new();
+    for script in &scripts.scripts {
+        let Some(interval) = script.schedule else {
+            continue;
+        };
+        // The first run waits a full interval, like a cron job.
+        let last_run = last_runs
+            .entry(script.name.clone())
+            .or_insert_with(Instant::now);
+        if last_run.elapsed() >= interval {
+            *last_run = Instant::now();
+            due.insert(script.name.clone());
+        }
+    }
+    if due.is_empty() {
+        return;
+    }
+
+    let (_, requested) = context
+        .run_hook(
+            "on_schedule",
+            move |script| due.contains(&script.name),
+            None,
+            None,
+        )
+        .await;
+    for (script, actions) in requested {
+        context.perform(&script, actions, None).await;
+    }
+}
+
+async fn on_worker_complete(
+    context: &ScriptContext,
+    worker_id: WorkerId,
+    channel_id: Option<&str>,
+    result: String,
+) {
+    let from_script = SCRIPT_WORKERS
+        .lock()
+        .map(|mut spawned| spawned.remove(&worker_id))
+        .unwrap_or(false);
+    if !context.config().enabled
+        || !context
+            .scripts()
+            .scripts
+            .iter()
+            .any(|script| script.on_worker_complete)
+    {
+        return;
+    }
+
+    let mut map = Map::new();
+    map.insert("worker_id".into(), worker_id.to_string().into());
+    map.insert(
+        "channel_id".into(),
+        channel_id
+            .map(|channel_id| channel_id.to_string().into())
+            .unwrap_or(Dynamic::UNIT),
+    );
+    map.insert("result".into(), result.into());
+    map.insert("from_script".into(), from_script.into());
+
+    let (_, requested) = context
+        .run_hook(
+            "on_worker_complete",
+            |script| script.on_worker_complete,
+            Some(map.into()),
+            None,
+        )
+        .await;
+    for (script, actions) in requested {
+        context.perform(&script, actions, None).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> anyhow::Result<Script> {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("test.rhai");
+        std::fs::write(&path, source).unwrap();
+        Script::compile(&path, &ScriptingConfig::default())
+    }
+
+    #[test]
+    fn hooks_are_detected_from_functions() {
+        let script = compile(
+            "const SCHEDULE_SECS = 60;\n\
+             fn on_message(message) { false }\n\
+             fn on_schedule() { log(\"tick\"); }",
+        )
+        .unwrap();
+        assert!(script.on_message);
+        assert!(!script.on_worker_complete);
+        assert_eq!(script.schedule, Some(Duration::from_secs(60)));
+
+        assert!(compile("fn on_schedule() {}").is_err());
+    }
+
+    #[test]
+    fn actions_are_collected_and_limited() {
+        let script = compile(
+            "fn on_message(message) {\n\
+                 if message.text == \"ping\" { reply(\"pong\"); return true; }\n\
+                 false\n\
+             }\n\
+             fn on_schedule() {\n\
+                 send_message(\"discord:123\", \"hello\");\n\
+                 save_memory(\"the sky is blue\", \"observation\");\n\
+             }\n\
+             const SCHEDULE_SECS = 10;",
+        )
+        .unwrap();
+        let config = ScriptingConfig::default();
+
+        let mut map = Map::new();
+        map.insert("text".into(), "ping".into());
+        let (result, actions) = script.run("on_message", Some(map.into()), None, &config);
+        // `reply` only exists when there's a message to reply to.
+        assert!(!result.as_bool().unwrap_or(false));
+        assert!(actions.is_empty());
+
+        let (_, actions) = script.run("on_schedule", None, None, &config);
+        assert_eq!(actions.len(), 2);
+        assert_eq!(
+            actions[1],
+            ScriptAction::SaveMemory {
+                content: "the sky is blue".into(),
+                memory_type: "observation".into(),
+            }
+        );
+
+        let limited = ScriptingConfig {
+            max_actions: 1,
+            ..config
+        };
+        let (_, actions) = script.run("on_schedule", None, None, &limited);
+        assert_eq!(actions.len(), 1);
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        let script = compile("fn on_schedule() { loop {} }\nconst SCHEDULE_SECS = 1;").unwrap();
+        let config = ScriptingConfig {
+            max_operations: 10_000,
+            ..ScriptingConfig::default()
+        };
+        let (result, actions) = script.run("on_schedule", None, None, &config);
+        assert!(result.is_unit());
+        assert!(actions.is_empty());
+    }
+}
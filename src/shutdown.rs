@@ -0,0 +1,133 @@
+//! Graceful shutdown: finishing in-flight work before the process exits.
+//!
+//! On SIGTERM, Ctrl-C or `spacebot stop`, the main loop stops routing inbound
+//! messages and drains:
+//!
+//! 1. Every agent's worker queue closes. Workers that haven't started yet
+//!    stay queued and new ones can't be spawned.
+//! 2. Every channel is told no more messages are coming. It finishes the
+//!    turn it's in, saves memories from what's been said since its last
+//!    memory persistence, waits for its branches and running workers, relays
+//!    their results and stops.
+//! 3. Workers outside channels finish.
+//! 4. Background writes to the conversation log, process runs, audit log and
+//!    cortex events land (see [`PendingWrite`]).
+//! 5. Messaging adapters are shut down, once the last replies are out.
+//!
+//! Steps 1 to 3 are bounded by `[shutdown] drain_timeout_secs`. Anything
+//! still running at the deadline is dropped; fire-and-forget workers keep
+//! their checkpoint and resume on the next start. The writes get their own
+//! [`FLUSH_TIMEOUT`] after that, so work cut off at the deadline doesn't
+//! take what it already logged with it.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Background writes that haven't landed yet.
+static PENDING_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// How often [`flush_writes`] checks for writes still pending.
+const FLUSH_POLL: Duration = Duration::from_millis(20);
+
+/// Most time to wait for background writes once the drain is over.
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Shutdown settings from the `[shutdown]` config section.
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+    /// Most seconds to wait for in-flight turns, workers and writes. 0 exits
+    /// without waiting.
+    pub drain_timeout_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain_timeout_secs: 60,
+        }
+    }
+}
+
+/// A background write in flight. Shutdown waits for every one of these to be
+/// dropped, so hold it until the write has landed.
+#[derive(Debug)]
+pub struct PendingWrite(());
+
+impl PendingWrite {
+    pub fn new() -> Self {
+        PENDING_WRITES.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Default for PendingWrite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Spawn a fire-and-forget write that shutdown waits for.
+pub fn spawn_write<F>(write: F) -> tokio::task::JoinHandle<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let pending = PendingWrite::new();
+    tokio::spawn(async move {
+        write.await;
+        drop(pending);
+    })
+}
+
+/// Wait until no background write is pending.
+pub async fn flush_writes() {
+    while PENDING_WRITES.load(Ordering::SeqCst) > 0 {
+        tokio::time::sleep(FLUSH_POLL).await;
+    }
+}
+
+/// Resolves when the process gets SIGTERM. Never resolves where there's no
+/// SIGTERM. Create it once and poll it by reference, so a signal that
+/// arrives between polls isn't missed.
+pub fn terminate_signal() -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    #[cfg(unix)]
+    match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(mut signal) => {
+            return Box::pin(async move {
+                signal.recv().await;
+            });
+        }
+        Err(error) => tracing::warn!(%error, "failed to listen for SIGTERM"),
+    }
+    Box::pin(std::future::pending())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn flush_waits_for_spawned_writes() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        spawn_write(async move {
+            rx.await.ok();
+        });
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), flush_writes())
+                .await
+                .is_err()
+        );
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), flush_writes())
+            .await
+            .expect("writes should have flushed");
+    }
+}
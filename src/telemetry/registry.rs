@@ -74,6 +74,11 @@ pub struct Metrics {
     /// Memory mutation operations.
     /// Labels: agent_id, operation (save/update/delete/forget).
     pub memory_updates_total: IntCounterVec,
+
+    // -- Event bus --
+    /// Process events dropped because a subscriber fell behind.
+    /// Labels: agent_id, subscriber.
+    pub event_bus_lagged_total: IntCounterVec,
 }
 
 impl Metrics {
@@ -229,9 +234,21 @@ impl Metrics {
         registry
             .register(Box::new(process_errors_total.clone()))
             .expect("hardcoded metric");
+        let event_bus_lagged_total = IntCounterVec::new(
+            Opts::new(
+                "spacebot_event_bus_lagged_total",
+                "Process events dropped because a subscriber fell behind",
+            ),
+            &["agent_id", "subscriber"],
+        )
+        .expect("hardcoded metric descriptor");
+
         registry
             .register(Box::new(memory_updates_total.clone()))
             .expect("hardcoded metric");
+        registry
+            .register(Box::new(event_bus_lagged_total.clone()))
+            .expect("hardcoded metric");
 
         Self {
             registry,
@@ -249,6 +266,7 @@ impl Metrics {
             worker_duration_seconds,
             process_errors_total,
             memory_updates_total,
+            event_bus_lagged_total,
         }
     }
 
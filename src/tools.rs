@@ -6,43 +6,68 @@
 //! ## ToolServer Topology
 //!
 //! **Channel ToolServer** (one per channel):
-//! - `reply`, `branch`, `spawn_worker`, `route`, `cancel`, `skip`, `react` — added
-//!   dynamically per conversation turn via `add_channel_tools()` /
-//!   `remove_channel_tools()` because they hold per-channel state.
+//! - `reply`, `branch`, `spawn_worker`, `route`, `cancel`, `skip`, `react`,
+//!   `pin_message` — added dynamically per conversation turn via
+//!   `add_channel_tools()` / `remove_channel_tools()` because they hold
+//!   per-channel state.
 //! - No memory tools — the channel delegates memory work to branches.
 //!
 //! **Branch ToolServer** (one per branch, isolated):
 //! - `memory_save` + `memory_recall` + `memory_delete` — registered at creation
+//! - `channel_recall` + `archive_search` — registered at creation
+//! - `conclude` — added by the branch when it starts running
 //!
 //! **Worker ToolServer** (one per worker, created at spawn time):
 //! - `shell`, `file`, `exec` — stateless, registered at creation
 //! - `set_status` — per-worker instance, registered at creation
+//! - `send_worker_message` + `read_worker_messages` — bound to the worker's
+//!   mailbox, registered at creation
+//! - `emit_artifact` — per-worker instance, registered at creation
+//! - `spawn_sub_worker` — added by the worker when it starts, unless it's at the
+//!   deepest nesting allowed
 //!
 //! **Cortex ToolServer** (one per agent):
 //! - `memory_save` — registered at startup
 
+pub mod archive_search;
 pub mod branch_tool;
 pub mod browser;
 pub mod cancel;
 pub mod channel_recall;
+pub mod conclude;
 pub mod cron;
+pub mod defer_task;
+pub mod emit_artifact;
 pub mod exec;
 pub mod file;
+pub mod fork;
+pub mod handoff;
 pub mod mcp;
 pub mod memory_delete;
 pub mod memory_recall;
 pub mod memory_save;
+pub mod pin_message;
+pub mod plugin;
 pub mod react;
+pub mod read_worker_messages;
 pub mod reply;
 pub mod route;
+pub mod sanitize;
 pub mod send_file;
 pub mod send_message_to_another_channel;
+pub mod send_proactive_message;
+pub mod send_worker_message;
+pub mod set_reminder;
 pub mod set_status;
 pub mod shell;
 pub mod skip;
+pub mod spawn_sub_worker;
 pub mod spawn_worker;
 pub mod web_search;
 
+pub use archive_search::{
+    ArchiveSearchArgs, ArchiveSearchError, ArchiveSearchOutput, ArchiveSearchTool,
+};
 pub use branch_tool::{BranchArgs, BranchError, BranchOutput, BranchTool};
 pub use browser::{
     ActKind, BrowserAction, BrowserArgs, BrowserError, BrowserOutput, BrowserTool, ElementSummary,
@@ -52,9 +77,18 @@ pub use cancel::{CancelArgs, CancelError, CancelOutput, CancelTool};
 pub use channel_recall::{
     ChannelRecallArgs, ChannelRecallError, ChannelRecallOutput, ChannelRecallTool,
 };
+pub use conclude::{
+    ConcludeError, ConcludeOutput, ConcludeTool, ConclusionSlot, new_conclusion_slot,
+};
 pub use cron::{CronArgs, CronError, CronOutput, CronTool};
+pub use defer_task::{DeferTaskArgs, DeferTaskError, DeferTaskOutput, DeferTaskTool};
+pub use emit_artifact::{
+    EmitArtifactArgs, EmitArtifactError, EmitArtifactOutput, EmitArtifactTool,
+};
 pub use exec::{EnvVar, ExecArgs, ExecError, ExecOutput, ExecResult, ExecTool};
 pub use file::{FileArgs, FileEntry, FileEntryOutput, FileError, FileOutput, FileTool, FileType};
+pub use fork::{ForkArgs, ForkError, ForkOutput, ForkTool};
+pub use handoff::{HandoffArgs, HandoffError, HandoffOutput, HandoffTool};
 pub use mcp::{McpToolAdapter, McpToolError, McpToolOutput};
 pub use memory_delete::{
     MemoryDeleteArgs, MemoryDeleteError, MemoryDeleteOutput, MemoryDeleteTool,
@@ -65,22 +99,49 @@ pub use memory_recall::{
 pub use memory_save::{
     AssociationInput, MemorySaveArgs, MemorySaveError, MemorySaveOutput, MemorySaveTool,
 };
+pub use pin_message::{PinMessageArgs, PinMessageError, PinMessageOutput, PinMessageTool};
+pub use plugin::{PluginTool, PluginToolError, PluginToolOutput};
 pub use react::{ReactArgs, ReactError, ReactOutput, ReactTool};
+pub use read_worker_messages::{
+    ReadWorkerMessagesArgs, ReadWorkerMessagesError, ReadWorkerMessagesOutput,
+    ReadWorkerMessagesTool,
+};
 pub use reply::{RepliedFlag, ReplyArgs, ReplyError, ReplyOutput, ReplyTool, new_replied_flag};
 pub use route::{RouteArgs, RouteError, RouteOutput, RouteTool};
-pub use send_file::{SendFileArgs, SendFileError, SendFileOutput, SendFileTool};
+pub use sanitize::Sanitized;
+pub use send_file::{SendFileArgs, SendFileError, SendFileOutput, SendFileTool, SentFile};
 pub use send_message_to_another_channel::{
     SendMessageArgs, SendMessageError, SendMessageOutput, SendMessageTool,
 };
+pub use send_proactive_message::{
+    SendProactiveMessageArgs, SendProactiveMessageError, SendProactiveMessageOutput,
+    SendProactiveMessageTool,
+};
+pub use send_worker_message::{
+    SendWorkerMessageArgs, SendWorkerMessageError, SendWorkerMessageOutput, SendWorkerMessageTool,
+};
+pub use set_reminder::{SetReminderArgs, SetReminderError, SetReminderOutput, SetReminderTool};
 pub use set_status::{SetStatusArgs, SetStatusError, SetStatusOutput, SetStatusTool};
 pub use shell::{ShellArgs, ShellError, ShellOutput, ShellResult, ShellTool};
 pub use skip::{SkipArgs, SkipError, SkipFlag, SkipOutput, SkipTool, new_skip_flag};
+pub use spawn_sub_worker::{
+    SpawnSubWorkerArgs, SpawnSubWorkerError, SpawnSubWorkerOutput, SpawnSubWorkerTool,
+};
 pub use spawn_worker::{SpawnWorkerArgs, SpawnWorkerError, SpawnWorkerOutput, SpawnWorkerTool};
 pub use web_search::{SearchResult, WebSearchArgs, WebSearchError, WebSearchOutput, WebSearchTool};
 
-use crate::agent::channel::ChannelState;
+use crate::agent::artifact::ArtifactStore;
+use crate::agent::channel::{ChannelState, check_capability};
+use crate::agent::mailbox::Mailbox;
+use crate::audit::AuditLogger;
 use crate::config::BrowserConfig;
+use crate::conversation::HistoryArchive;
+use crate::egress::EgressPolicy;
+use crate::injection::InjectionGuard;
 use crate::memory::MemorySearch;
+use crate::object_storage::ObjectStorage;
+use crate::rbac::Capability;
+use crate::sandbox::Sandbox;
 use crate::{AgentId, ChannelId, OutboundResponse, ProcessEvent, WorkerId};
 use rig::tool::Tool as _;
 use rig::tool::server::{ToolServer, ToolServerHandle};
@@ -143,8 +204,12 @@ pub async fn add_channel_tools(
             replied_flag.clone(),
         ))
         .await?;
+    let settings_allowed = check_capability(&state, Capability::Settings).await.is_ok();
+
     handle.add_tool(BranchTool::new(state.clone())).await?;
-    handle.add_tool(SpawnWorkerTool::new(state.clone())).await?;
+    handle
+        .add_tool(SpawnWorkerTool::new(state.clone(), response_tx.clone()))
+        .await?;
     handle.add_tool(RouteTool::new(state.clone())).await?;
     if let Some(messaging_manager) = &state.deps.messaging_manager {
         handle
@@ -153,16 +218,47 @@ pub async fn add_channel_tools(
                 state.channel_store.clone(),
             ))
             .await?;
+        handle.add_tool(ForkTool::new(state.clone())).await?;
+        handle.add_tool(SetReminderTool::new(state.clone())).await?;
+        handle.add_tool(DeferTaskTool::new(state.clone())).await?;
+
+        // Only agents with handoff targets can pass conversations on.
+        let agent_id = state.deps.agent_id.to_string();
+        let targets: Vec<String> = state
+            .deps
+            .runtime_config
+            .handoff
+            .load()
+            .targets
+            .iter()
+            .filter(|target| **target != agent_id)
+            .cloned()
+            .collect();
+        if !targets.is_empty() {
+            handle
+                .add_tool(HandoffTool::new(state.clone(), skip_flag.clone(), targets))
+                .await?;
+        }
     }
+    let object_storage = state.deps.runtime_config.object_storage.load_full();
+    let agent_id = state.deps.agent_id.to_string();
+    let typing = state.typing.clone();
+    let pin_state = state.clone();
     handle.add_tool(CancelTool::new(state)).await?;
+    handle.add_tool(SkipTool::new(skip_flag, typing)).await?;
     handle
-        .add_tool(SkipTool::new(skip_flag, response_tx.clone()))
-        .await?;
-    handle
-        .add_tool(SendFileTool::new(response_tx.clone()))
+        .add_tool(
+            SendFileTool::new(response_tx.clone())
+                .with_object_storage((*object_storage).clone(), agent_id),
+        )
         .await?;
     handle.add_tool(ReactTool::new(response_tx)).await?;
-    if let Some(cron) = cron_tool {
+    handle.add_tool(PinMessageTool::new(pin_state)).await?;
+    // Scheduling jobs changes agent behavior, so it's only offered to senders
+    // whose role allows settings changes.
+    if let Some(cron) = cron_tool
+        && settings_allowed
+    {
         handle.add_tool(cron).await?;
     }
     Ok(())
@@ -183,9 +279,15 @@ pub async fn remove_channel_tools(
     handle.remove_tool(SkipTool::NAME).await?;
     handle.remove_tool(SendFileTool::NAME).await?;
     handle.remove_tool(ReactTool::NAME).await?;
-    // Cron and send_message removal is best-effort since not all channels have them
+    handle.remove_tool(PinMessageTool::NAME).await?;
+    // Cron, send_message, fork, set_reminder, defer_task and handoff removal is best-effort
+    // since not all channels have them
     let _ = handle.remove_tool(CronTool::NAME).await;
     let _ = handle.remove_tool(SendMessageTool::NAME).await;
+    let _ = handle.remove_tool(ForkTool::NAME).await;
+    let _ = handle.remove_tool(SetReminderTool::NAME).await;
+    let _ = handle.remove_tool(DeferTaskTool::NAME).await;
+    let _ = handle.remove_tool(HandoffTool::NAME).await;
     Ok(())
 }
 
@@ -196,13 +298,19 @@ pub async fn remove_channel_tools(
 /// registered at creation.
 pub fn create_branch_tool_server(
     memory_search: Arc<MemorySearch>,
+    history_archive: Arc<HistoryArchive>,
     conversation_logger: crate::conversation::history::ConversationLogger,
     channel_store: crate::conversation::ChannelStore,
+    audit_logger: AuditLogger,
 ) -> ToolServerHandle {
     ToolServer::new()
         .tool(MemorySaveTool::new(memory_search.clone()))
         .tool(MemoryRecallTool::new(memory_search.clone()))
-        .tool(MemoryDeleteTool::new(memory_search))
+        .tool(MemoryDeleteTool::new(memory_search).with_audit(audit_logger))
+        .tool(ArchiveSearchTool::new(
+            history_archive,
+            channel_store.clone(),
+        ))
         .tool(ChannelRecallTool::new(conversation_logger, channel_store))
         .run()
 }
@@ -210,11 +318,18 @@ pub fn create_branch_tool_server(
 /// Create a per-worker ToolServer with task-appropriate tools.
 ///
 /// Each worker gets its own isolated ToolServer. The `set_status` tool is bound to
-/// the specific worker's ID so status updates route correctly. The browser tool
-/// is included when browser automation is enabled in the agent config.
+/// the specific worker's ID so status updates route correctly, and the worker
+/// messaging tools to its `mailbox`. The browser tool is included when browser
+/// automation is enabled in the agent config.
 ///
 /// File operations are restricted to `workspace`. Shell and exec commands are
-/// blocked from accessing sensitive files in `instance_dir`.
+/// blocked from accessing sensitive files in `instance_dir`. All three run
+/// inside `sandbox` when the agent uses a container backend.
+///
+/// Tools that can return untrusted content are wrapped in `injection_guard`,
+/// which flags or neutralizes embedded instructions before the result enters
+/// the worker's history. Their output is sanitized first: shell and exec clean
+/// process output themselves, the rest are wrapped in [`Sanitized`].
 #[allow(clippy::too_many_arguments)]
 pub fn create_worker_tool_server(
     agent_id: AgentId,
@@ -227,25 +342,70 @@ pub fn create_worker_tool_server(
     workspace: PathBuf,
     instance_dir: PathBuf,
     mcp_tools: Vec<McpToolAdapter>,
+    plugin_tools: Vec<PluginTool>,
+    audit_logger: AuditLogger,
+    sandbox: Option<Arc<Sandbox>>,
+    injection_guard: InjectionGuard,
+    egress: Arc<EgressPolicy>,
+    object_storage: Option<Arc<ObjectStorage>>,
+    mailbox: Arc<Mailbox>,
+    artifacts: ArtifactStore,
 ) -> ToolServerHandle {
+    let screenshot_scope = agent_id.to_string();
+    let emit_artifact = EmitArtifactTool::new(
+        artifacts,
+        worker_id,
+        channel_id.clone(),
+        workspace.clone(),
+        screenshot_dir.clone(),
+    );
     let mut server = ToolServer::new()
-        .tool(ShellTool::new(instance_dir.clone(), workspace.clone()))
-        .tool(FileTool::new(workspace.clone()))
-        .tool(ExecTool::new(instance_dir, workspace))
+        .tool(
+            injection_guard.wrap(
+                ShellTool::new(instance_dir.clone(), workspace.clone())
+                    .with_audit(audit_logger.clone())
+                    .with_sandbox(sandbox.clone()),
+            ),
+        )
+        .tool(injection_guard.wrap(Sanitized::new(
+            FileTool::new(workspace.clone()).with_sandbox(sandbox.clone()),
+        )))
+        .tool(
+            injection_guard.wrap(
+                ExecTool::new(instance_dir, workspace)
+                    .with_audit(audit_logger)
+                    .with_sandbox(sandbox),
+            ),
+        )
         .tool(SetStatusTool::new(
             agent_id, worker_id, channel_id, event_tx,
-        ));
+        ))
+        .tool(SendWorkerMessageTool::new(mailbox.clone()))
+        .tool(injection_guard.wrap(Sanitized::new(ReadWorkerMessagesTool::new(mailbox))))
+        .tool(emit_artifact);
 
     if browser_config.enabled {
-        server = server.tool(BrowserTool::new(browser_config, screenshot_dir));
+        server = server.tool(
+            injection_guard.wrap(Sanitized::new(
+                BrowserTool::new(browser_config, screenshot_dir)
+                    .with_egress(egress.clone())
+                    .with_object_storage(object_storage, screenshot_scope),
+            )),
+        );
     }
 
     if let Some(key) = brave_search_key {
-        server = server.tool(WebSearchTool::new(key));
+        server = server.tool(
+            injection_guard.wrap(Sanitized::new(WebSearchTool::new(key).with_egress(&egress))),
+        );
     }
 
     for mcp_tool in mcp_tools {
-        server = server.tool(mcp_tool);
+        server = server.tool(injection_guard.wrap(Sanitized::new(mcp_tool)));
+    }
+
+    for plugin_tool in plugin_tools {
+        server = server.tool(injection_guard.wrap(Sanitized::new(plugin_tool)));
     }
 
     server.run()
@@ -265,10 +425,12 @@ pub fn create_cortex_tool_server(memory_search: Arc<MemorySearch>) -> ToolServer
 ///
 /// Combines branch tools (memory) with worker tools (shell, file, exec) to give
 /// the interactive cortex full capabilities. Does not include channel-specific
-/// tools (reply, react, skip) since the cortex chat doesn't talk to platforms.
+/// tools (reply, react, skip) since the cortex chat isn't in a conversation;
+/// it can only message one unprompted through `send_proactive_message`.
 #[allow(clippy::too_many_arguments)]
 pub fn create_cortex_chat_tool_server(
     memory_search: Arc<MemorySearch>,
+    history_archive: Arc<HistoryArchive>,
     conversation_logger: crate::conversation::history::ConversationLogger,
     channel_store: crate::conversation::ChannelStore,
     browser_config: BrowserConfig,
@@ -276,22 +438,39 @@ pub fn create_cortex_chat_tool_server(
     brave_search_key: Option<String>,
     workspace: PathBuf,
     instance_dir: PathBuf,
+    audit_logger: AuditLogger,
+    egress: Arc<EgressPolicy>,
+    agent_id: &str,
+    object_storage: Option<Arc<ObjectStorage>>,
+    proactive_message: SendProactiveMessageTool,
 ) -> ToolServerHandle {
     let mut server = ToolServer::new()
         .tool(MemorySaveTool::new(memory_search.clone()))
         .tool(MemoryRecallTool::new(memory_search.clone()))
-        .tool(MemoryDeleteTool::new(memory_search))
+        .tool(MemoryDeleteTool::new(memory_search).with_audit(audit_logger.clone()))
+        .tool(ArchiveSearchTool::new(
+            history_archive,
+            channel_store.clone(),
+        ))
         .tool(ChannelRecallTool::new(conversation_logger, channel_store))
-        .tool(ShellTool::new(instance_dir.clone(), workspace.clone()))
-        .tool(FileTool::new(workspace.clone()))
-        .tool(ExecTool::new(instance_dir, workspace));
+        .tool(
+            ShellTool::new(instance_dir.clone(), workspace.clone())
+                .with_audit(audit_logger.clone()),
+        )
+        .tool(Sanitized::new(FileTool::new(workspace.clone())))
+        .tool(ExecTool::new(instance_dir, workspace).with_audit(audit_logger))
+        .tool(proactive_message);
 
     if browser_config.enabled {
-        server = server.tool(BrowserTool::new(browser_config, screenshot_dir));
+        server = server.tool(Sanitized::new(
+            BrowserTool::new(browser_config, screenshot_dir)
+                .with_egress(egress.clone())
+                .with_object_storage(object_storage, agent_id),
+        ));
     }
 
     if let Some(key) = brave_search_key {
-        server = server.tool(WebSearchTool::new(key));
+        server = server.tool(Sanitized::new(WebSearchTool::new(key).with_egress(&egress)));
     }
 
     server.run()
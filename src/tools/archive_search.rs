@@ -0,0 +1,160 @@
+//! Search over conversation history compaction archived.
+
+use crate::conversation::archive::HistoryArchive;
+use crate::conversation::channels::ChannelStore;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Most archive excerpts returned by one search.
+const MAX_RESULTS: usize = 20;
+
+/// Tool for searching compacted conversation history.
+#[derive(Clone)]
+pub struct ArchiveSearchTool {
+    history_archive: Arc<HistoryArchive>,
+    channel_store: ChannelStore,
+}
+
+impl ArchiveSearchTool {
+    pub fn new(history_archive: Arc<HistoryArchive>, channel_store: ChannelStore) -> Self {
+        Self {
+            history_archive,
+            channel_store,
+        }
+    }
+}
+
+/// Error type for archive search tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Archive search failed: {0}")]
+pub struct ArchiveSearchError(String);
+
+/// Arguments for archive search tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ArchiveSearchArgs {
+    /// What to look for in the archived conversations.
+    pub query: String,
+    /// Only search this channel, by name or full channel ID. Searches every
+    /// channel if omitted.
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Maximum number of excerpts to return (default 5, max 20).
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    5
+}
+
+/// An archived excerpt matching the search.
+#[derive(Debug, Serialize)]
+pub struct ArchiveExcerpt {
+    pub channel_id: String,
+    pub archived_at: String,
+    /// The transcript file the excerpt is from, relative to the archives
+    /// directory.
+    pub path: String,
+    pub content: String,
+}
+
+/// Output from archive search tool.
+#[derive(Debug, Serialize)]
+pub struct ArchiveSearchOutput {
+    pub excerpts: Vec<ArchiveExcerpt>,
+    /// Formatted excerpts for the agent.
+    pub summary: String,
+}
+
+impl Tool for ArchiveSearchTool {
+    const NAME: &'static str = "archive_search";
+
+    type Error = ArchiveSearchError;
+    type Args = ArchiveSearchArgs;
+    type Output = ArchiveSearchOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/archive_search").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What to look for in the archived conversations"
+                    },
+                    "channel": {
+                        "type": "string",
+                        "description": "Channel name or full channel ID to limit the search to. Omit to search every channel."
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 20,
+                        "default": 5,
+                        "description": "Maximum number of excerpts to return (1-20)"
+                    }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> std::result::Result<Self::Output, Self::Error> {
+        let channel_id = match &args.channel {
+            Some(channel_query) => {
+                let found = self
+                    .channel_store
+                    .find_by_name(channel_query)
+                    .await
+                    .map_err(|e| ArchiveSearchError(format!("Failed to search channels: {e}")))?;
+                // An unknown name may still be an ID whose channel is gone.
+                Some(found.map_or_else(|| channel_query.clone(), |channel| channel.id))
+            }
+            None => None,
+        };
+
+        let chunks = self
+            .history_archive
+            .search(
+                &args.query,
+                channel_id.as_deref(),
+                args.limit.clamp(1, MAX_RESULTS),
+            )
+            .await
+            .map_err(|e| ArchiveSearchError(format!("Failed to search archives: {e}")))?;
+
+        let excerpts: Vec<ArchiveExcerpt> = chunks
+            .into_iter()
+            .map(|chunk| ArchiveExcerpt {
+                channel_id: chunk.channel_id,
+                archived_at: chunk.archived_at.to_rfc3339(),
+                path: chunk.path,
+                content: chunk.content,
+            })
+            .collect();
+        let summary = format_excerpts(&args.query, &excerpts);
+
+        Ok(ArchiveSearchOutput { excerpts, summary })
+    }
+}
+
+fn format_excerpts(query: &str, excerpts: &[ArchiveExcerpt]) -> String {
+    if excerpts.is_empty() {
+        return format!("No archived conversation matches \"{query}\".");
+    }
+
+    let mut output = format!("## Archived conversation matching \"{query}\"\n\n");
+    for excerpt in excerpts {
+        output.push_str(&format!(
+            "### {} (archived {})\n\n{}\n\n",
+            excerpt.channel_id, excerpt.archived_at, excerpt.content
+        ));
+    }
+    output
+}
@@ -1,7 +1,9 @@
 //! Branch tool for forking context and thinking (channel only).
 
 use crate::BranchId;
-use crate::agent::channel::{ChannelState, spawn_branch_from_state};
+use crate::agent::channel::{
+    ChannelState, MAX_RACE_BRANCHES, spawn_branch_from_state, spawn_branch_race,
+};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
@@ -30,13 +32,20 @@ pub struct BranchError(String);
 pub struct BranchArgs {
     /// Description of what the branch should think about or investigate.
     pub description: String,
+    /// Race this many branches on the question and keep the first confident
+    /// conclusion.
+    #[serde(default)]
+    pub race: Option<usize>,
 }
 
 /// Output from branch tool.
 #[derive(Debug, Serialize)]
 pub struct BranchOutput {
-    /// The ID of the created branch.
+    /// The ID of the created branch, or of the first one when racing.
     pub branch_id: BranchId,
+    /// Every branch in the race, when racing.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub racing: Vec<BranchId>,
     /// Whether the branch was spawned successfully.
     pub spawned: bool,
     /// Message about the branch status.
@@ -60,6 +69,12 @@ impl Tool for BranchTool {
                     "description": {
                         "type": "string",
                         "description": "What the branch should investigate or think about. Be specific about what conclusion you want."
+                    },
+                    "race": {
+                        "type": "integer",
+                        "minimum": 2,
+                        "maximum": MAX_RACE_BRANCHES,
+                        "description": "Optional: race this many branches on the same question. The first confident conclusion wins and the rest are cancelled. Use it when a quick answer matters more than cost."
                     }
                 },
                 "required": ["description"]
@@ -68,12 +83,33 @@ impl Tool for BranchTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if let Some(racers) = args.race.filter(|racers| *racers > 1) {
+            if racers > MAX_RACE_BRANCHES {
+                return Err(BranchError(format!(
+                    "at most {MAX_RACE_BRANCHES} branches can race"
+                )));
+            }
+            let racing = spawn_branch_race(&self.state, &args.description, racers)
+                .await
+                .map_err(|e| BranchError(format!("{e}")))?;
+            return Ok(BranchOutput {
+                branch_id: racing[0],
+                spawned: true,
+                message: format!(
+                    "{racers} branches racing. The first confident conclusion will be used: {}",
+                    args.description
+                ),
+                racing,
+            });
+        }
+
         let branch_id = spawn_branch_from_state(&self.state, &args.description)
             .await
             .map_err(|e| BranchError(format!("{e}")))?;
 
         Ok(BranchOutput {
             branch_id,
+            racing: Vec::new(),
             spawned: true,
             message: format!(
                 "Branch {branch_id} spawned. It will investigate: {}",
@@ -6,6 +6,8 @@
 
 
 use crate::config::BrowserConfig;
+use crate::egress::{EgressPolicy, EgressScope, is_private_ip};
+use crate::object_storage::{ObjectKind, ObjectStorage};
 
 use chromiumoxide::browser::{Browser, BrowserConfig as ChromeConfig};
 use chromiumoxide::page::ScreenshotParams;
@@ -23,7 +25,7 @@ use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -61,7 +63,7 @@ fn validate_url(url: &str) -> Result<(), BrowserError> {
 
     // If the host parses as an IP address, check against blocked ranges
     if let Ok(ip) = host.parse::<IpAddr>() {
-        if is_blocked_ip(ip) {
+        if is_private_ip(ip) {
             return Err(BrowserError::new(format!(
                 "navigation to private/loopback address {ip} is blocked"
             )));
@@ -71,7 +73,7 @@ fn validate_url(url: &str) -> Result<(), BrowserError> {
     // IPv6 addresses in brackets (url crate strips them for host_str)
     if let Some(stripped) = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
         if let Ok(ip) = stripped.parse::<IpAddr>() {
-            if is_blocked_ip(ip) {
+            if is_private_ip(ip) {
                 return Err(BrowserError::new(format!(
                     "navigation to private/loopback address {ip} is blocked"
                 )));
@@ -82,57 +84,16 @@ fn validate_url(url: &str) -> Result<(), BrowserError> {
     Ok(())
 }
 
-/// Returns true if the IP address belongs to a private, loopback, or
-/// link-local range that should not be reachable from the browser tool.
-fn is_blocked_ip(ip: IpAddr) -> bool {
-    match ip {
-        IpAddr::V4(v4) => {
-            v4.is_loopback()                             // 127.0.0.0/8
-            || v4.is_private()                            // 10/8, 172.16/12, 192.168/16
-            || v4.is_link_local()                         // 169.254.0.0/16
-            || v4.is_broadcast()                          // 255.255.255.255
-            || v4.is_unspecified()                        // 0.0.0.0
-            || is_v4_cgnat(v4) // 100.64.0.0/10
-        }
-        IpAddr::V6(v6) => {
-            v6.is_loopback()                             // ::1
-            || v6.is_unspecified()                        // ::
-            || is_v6_unique_local(v6)                    // fd00::/8 (fc00::/7)
-            || is_v6_link_local(v6)                      // fe80::/10
-            || is_v4_mapped_blocked(v6)
-        }
-    }
-}
-
-fn is_v4_cgnat(ip: Ipv4Addr) -> bool {
-    let octets = ip.octets();
-    octets[0] == 100 && (octets[1] & 0xC0) == 64 // 100.64.0.0/10
-}
-
-fn is_v6_unique_local(ip: Ipv6Addr) -> bool {
-    (ip.segments()[0] & 0xFE00) == 0xFC00 // fc00::/7
-}
-
-fn is_v6_link_local(ip: Ipv6Addr) -> bool {
-    (ip.segments()[0] & 0xFFC0) == 0xFE80 // fe80::/10
-}
-
-/// Check if an IPv6 address is a v4-mapped address (::ffff:x.x.x.x)
-/// pointing to a blocked IPv4 range.
-fn is_v4_mapped_blocked(ip: Ipv6Addr) -> bool {
-    if let Some(v4) = ip.to_ipv4_mapped() {
-        is_blocked_ip(IpAddr::V4(v4))
-    } else {
-        false
-    }
-}
-
 /// Tool for browser automation (worker-only).
 #[derive(Debug, Clone)]
 pub struct BrowserTool {
     state: Arc<Mutex<BrowserState>>,
     config: BrowserConfig,
     screenshot_dir: PathBuf,
+    egress: Arc<EgressPolicy>,
+    /// Bucket screenshots are copied to, with the agent ID they're filed
+    /// under.
+    object_storage: Option<(Arc<ObjectStorage>, String)>,
 }
 
 /// Internal browser state managed across tool invocations within a single worker.
@@ -186,8 +147,35 @@ impl BrowserTool {
             })),
             config,
             screenshot_dir,
+            egress: Arc::new(EgressPolicy::default()),
+            object_storage: None,
         }
     }
+
+    /// Copy screenshots to object storage so they survive the container.
+    pub fn with_object_storage(
+        mut self,
+        storage: Option<Arc<ObjectStorage>>,
+        agent_id: impl Into<String>,
+    ) -> Self {
+        let agent_id = agent_id.into();
+        self.object_storage = storage.map(|storage| (storage, agent_id));
+        self
+    }
+
+    /// Check navigations against the agent's egress policy.
+    pub fn with_egress(mut self, egress: Arc<EgressPolicy>) -> Self {
+        self.egress = egress;
+        self
+    }
+
+    async fn check_egress(&self, url: &str) -> Result<(), BrowserError> {
+        self.egress
+            .check_url_resolved(EgressScope::Browser, url)
+            .await
+            .map(|_| ())
+            .map_err(|error| BrowserError::new(error.to_string()))
+    }
 }
 
 /// Error type for browser tool operations.
@@ -299,6 +287,10 @@ pub struct BrowserOutput {
     /// Path to saved screenshot file.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub screenshot_path: Option<String>,
+    /// Signed download URL of the screenshot, when object storage is
+    /// configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_url: Option<String>,
     /// JavaScript evaluation result.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub eval_result: Option<serde_json::Value>,
@@ -317,6 +309,7 @@ impl BrowserOutput {
             elements: None,
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
         }
@@ -514,6 +507,7 @@ impl BrowserTool {
         };
 
         validate_url(&url)?;
+        self.check_egress(&url).await?;
 
         let mut state = self.state.lock().await;
         let page = self.get_or_create_page(&mut state, Some(&url)).await?;
@@ -546,6 +540,7 @@ impl BrowserTool {
 
         if target_url != "about:blank" {
             validate_url(target_url)?;
+            self.check_egress(target_url).await?;
         }
 
         let page = browser
@@ -568,6 +563,7 @@ impl BrowserTool {
             tabs: None,
             elements: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
             success: true,
@@ -603,6 +599,7 @@ impl BrowserTool {
             elements: None,
             tabs: Some(tabs),
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
         })
@@ -739,6 +736,7 @@ impl BrowserTool {
             elements: Some(elements),
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
         })
@@ -872,6 +870,27 @@ impl BrowserTool {
 
         tracing::debug!(path = %path_str, size_kb, "screenshot saved");
 
+        // The local copy is still returned, so a failed upload only costs
+        // the link.
+        let screenshot_url = match &self.object_storage {
+            Some((storage, agent_id)) => match storage
+                .share(
+                    ObjectKind::Screenshot,
+                    agent_id,
+                    &filename,
+                    screenshot_data.clone(),
+                )
+                .await
+            {
+                Ok(url) => Some(url),
+                Err(error) => {
+                    tracing::warn!(%error, "can't upload screenshot");
+                    None
+                }
+            },
+            None => None,
+        };
+
         Ok(BrowserOutput {
             success: true,
             message: format!("Screenshot saved ({size_kb}KB)"),
@@ -880,6 +899,7 @@ impl BrowserTool {
             elements: None,
             tabs: None,
             screenshot_path: Some(path_str),
+            screenshot_url,
             eval_result: None,
             content: None,
         })
@@ -914,6 +934,7 @@ impl BrowserTool {
             elements: None,
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: value,
             content: None,
         })
@@ -950,6 +971,7 @@ impl BrowserTool {
             elements: None,
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: Some(truncated),
         })
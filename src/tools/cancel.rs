@@ -1,6 +1,7 @@
 //! Cancel tool for stopping workers or branches.
 
-use crate::agent::channel::ChannelState;
+use crate::agent::channel::{ChannelState, check_capability};
+use crate::rbac::Capability;
 use crate::{BranchId, WorkerId};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
@@ -83,12 +84,16 @@ impl Tool for CancelTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        check_capability(&self.state, Capability::ToolApproval)
+            .await
+            .map_err(|error| CancelError(format!("{error}")))?;
+
         match args.process_type.as_str() {
             "branch" => {
                 let branch_id = args
                     .process_id
                     .parse::<BranchId>()
-                    .map_err(|e| CancelError(format!("Invalid branch ID: {e}")))?;
+                    .map_err(|error| CancelError(format!("Invalid branch ID: {error}")))?;
                 self.state
                     .cancel_branch(branch_id)
                     .await
@@ -98,7 +103,7 @@ impl Tool for CancelTool {
                 let worker_id = args
                     .process_id
                     .parse::<WorkerId>()
-                    .map_err(|e| CancelError(format!("Invalid worker ID: {e}")))?;
+                    .map_err(|error| CancelError(format!("Invalid worker ID: {error}")))?;
                 self.state
                     .cancel_worker(worker_id)
                     .await
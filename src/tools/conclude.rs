@@ -0,0 +1,95 @@
+//! Conclude tool: lets a branch hand back a structured conclusion.
+//!
+//! The branch calls this once it's done thinking. The conclusion is stored in
+//! the branch's slot and the hook ends the branch's turn, so the branch
+//! reports it instead of its last free-text message.
+
+use crate::agent::branch::BranchConclusion;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Shared slot between the ConcludeTool and the branch that registered it.
+pub type ConclusionSlot = Arc<Mutex<Option<BranchConclusion>>>;
+
+/// Create an empty conclusion slot.
+pub fn new_conclusion_slot() -> ConclusionSlot {
+    Arc::new(Mutex::new(None))
+}
+
+/// Tool for submitting a branch's conclusion.
+#[derive(Debug, Clone)]
+pub struct ConcludeTool {
+    slot: ConclusionSlot,
+}
+
+impl ConcludeTool {
+    pub fn new(slot: ConclusionSlot) -> Self {
+        Self { slot }
+    }
+}
+
+/// Error type for conclude tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Concluding failed: {0}")]
+pub struct ConcludeError(String);
+
+/// Output from conclude tool.
+#[derive(Debug, Serialize)]
+pub struct ConcludeOutput {
+    pub concluded: bool,
+}
+
+impl Tool for ConcludeTool {
+    const NAME: &'static str = "conclude";
+
+    type Error = ConcludeError;
+    type Args = BranchConclusion;
+    type Output = ConcludeOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/conclude").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "summary": {
+                        "type": "string",
+                        "description": "The answer to what the channel branched for, in a sentence or a short paragraph."
+                    },
+                    "recommendation": {
+                        "type": "string",
+                        "description": "What the channel should do or say next, if anything."
+                    },
+                    "confidence": {
+                        "type": "string",
+                        "enum": ["low", "medium", "high"],
+                        "description": "How sure you are of the summary. Low when memories were missing or contradicted each other."
+                    },
+                    "supporting_points": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Short facts the summary rests on, like recalled memories or decisions."
+                    }
+                },
+                "required": ["summary"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.summary.trim().is_empty() {
+            return Err(ConcludeError("the summary is empty".into()));
+        }
+
+        tracing::info!(confidence = %args.confidence, "branch conclusion submitted");
+        *self
+            .slot
+            .lock()
+            .map_err(|_| ConcludeError("the conclusion slot is poisoned".into()))? = Some(args);
+
+        Ok(ConcludeOutput { concluded: true })
+    }
+}
@@ -0,0 +1,115 @@
+//! Defer task tool for queueing background work whose result comes back to
+//! the conversation later (channel only).
+
+use crate::agent::channel::ChannelState;
+use crate::agent::task_queue::{NewTask, TaskOrigin, TaskPriority, TaskQueue};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Tool for adding a task to the cortex's task queue.
+#[derive(Debug, Clone)]
+pub struct DeferTaskTool {
+    state: ChannelState,
+}
+
+impl DeferTaskTool {
+    /// Create a new defer task tool with access to channel state.
+    pub fn new(state: ChannelState) -> Self {
+        Self { state }
+    }
+}
+
+/// Error type for defer task tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Deferring the task failed: {0}")]
+pub struct DeferTaskError(String);
+
+/// Arguments for defer task tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeferTaskArgs {
+    /// The complete task for the worker that runs it.
+    pub task: String,
+    /// How urgent the task is. Defaults to normal.
+    #[serde(default)]
+    pub priority: Option<TaskPriority>,
+}
+
+/// Output from defer task tool.
+#[derive(Debug, Serialize)]
+pub struct DeferTaskOutput {
+    pub success: bool,
+    pub task_id: String,
+    pub priority: TaskPriority,
+}
+
+impl Tool for DeferTaskTool {
+    const NAME: &'static str = "defer_task";
+
+    type Error = DeferTaskError;
+    type Args = DeferTaskArgs;
+    type Output = DeferTaskOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/defer_task").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "task": {
+                        "type": "string",
+                        "description": "The complete task, with everything the worker needs to know. It sees nothing of this conversation."
+                    },
+                    "priority": {
+                        "type": "string",
+                        "enum": ["low", "normal", "high", "urgent"],
+                        "description": "How urgent the task is compared to other queued work. Defaults to normal."
+                    }
+                },
+                "required": ["task"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let task = args.task.trim();
+        if task.is_empty() {
+            return Err(DeferTaskError("the task is empty".into()));
+        }
+        let mut source = self
+            .state
+            .trigger_message
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| DeferTaskError("there is no conversation to report back to".into()))?;
+        for key in crate::reminders::ONE_SHOT_KEYS {
+            source.metadata.remove(*key);
+        }
+
+        let queued = TaskQueue::new(self.state.deps.sqlite_pool.clone())
+            .enqueue(NewTask {
+                priority: args.priority.unwrap_or_default(),
+                origin_id: Some(self.state.channel_id.to_string()),
+                source: Some(source),
+                ..NewTask::new(task, TaskOrigin::Channel)
+            })
+            .await
+            .map_err(|error| DeferTaskError(error.to_string()))?;
+
+        tracing::info!(
+            channel_id = %self.state.channel_id,
+            task_id = %queued.id,
+            priority = ?queued.priority,
+            "task deferred"
+        );
+
+        Ok(DeferTaskOutput {
+            success: true,
+            task_id: queued.id,
+            priority: queued.priority,
+        })
+    }
+}
@@ -0,0 +1,154 @@
+//! Emit artifact tool: lets a worker hand back a file as output (worker only).
+
+use crate::agent::artifact::ArtifactStore;
+use crate::{ChannelId, WorkerId};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Tool for emitting a file as one of the worker's artifacts.
+///
+/// Files are taken from the workspace or the screenshot directory and copied
+/// into the artifact store, so they're listed with the worker's result.
+#[derive(Debug, Clone)]
+pub struct EmitArtifactTool {
+    store: ArtifactStore,
+    worker_id: WorkerId,
+    channel_id: Option<ChannelId>,
+    workspace: PathBuf,
+    screenshot_dir: PathBuf,
+}
+
+impl EmitArtifactTool {
+    pub fn new(
+        store: ArtifactStore,
+        worker_id: WorkerId,
+        channel_id: Option<ChannelId>,
+        workspace: PathBuf,
+        screenshot_dir: PathBuf,
+    ) -> Self {
+        Self {
+            store,
+            worker_id,
+            channel_id,
+            workspace,
+            screenshot_dir,
+        }
+    }
+
+    /// Resolve a path against the workspace, keeping it inside the
+    /// workspace or the screenshot directory.
+    fn resolve_path(&self, raw: &str) -> Result<PathBuf, EmitArtifactError> {
+        let path = Path::new(raw.trim());
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.workspace.join(path)
+        };
+        let canonical = path
+            .canonicalize()
+            .map_err(|error| EmitArtifactError(format!("can't read '{raw}': {error}")))?;
+
+        let allowed = [&self.workspace, &self.screenshot_dir]
+            .into_iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .any(|root| canonical.starts_with(root));
+        if !allowed {
+            return Err(EmitArtifactError(format!(
+                "'{raw}' is outside the workspace ({}). Write the file there first.",
+                self.workspace.display()
+            )));
+        }
+        Ok(canonical)
+    }
+}
+
+/// Error type for emit artifact tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Artifact not emitted: {0}")]
+pub struct EmitArtifactError(String);
+
+/// Arguments for emit artifact tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EmitArtifactArgs {
+    /// Path of the file, relative to the workspace or absolute.
+    pub path: String,
+    /// File name to give the artifact. Defaults to the file's own name.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// What the file is.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Output from emit artifact tool.
+#[derive(Debug, Serialize)]
+pub struct EmitArtifactOutput {
+    pub artifact_id: String,
+    pub name: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+}
+
+impl Tool for EmitArtifactTool {
+    const NAME: &'static str = "emit_artifact";
+
+    type Error = EmitArtifactError;
+    type Args = EmitArtifactArgs;
+    type Output = EmitArtifactOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/emit_artifact").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the file to emit. Relative paths are resolved from the workspace root."
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "File name to give the artifact, e.g. \"report.pdf\". Defaults to the file's own name."
+                    },
+                    "description": {
+                        "type": "string",
+                        "description": "A short note on what the file is."
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.resolve_path(&args.path)?;
+        let description = args
+            .description
+            .as_deref()
+            .map(str::trim)
+            .filter(|description| !description.is_empty());
+
+        let artifact = self
+            .store
+            .emit(
+                self.worker_id,
+                self.channel_id.as_ref(),
+                &path,
+                args.name.as_deref().map(str::trim),
+                description,
+            )
+            .await
+            .map_err(|error| EmitArtifactError(error.to_string()))?;
+
+        Ok(EmitArtifactOutput {
+            artifact_id: artifact.id,
+            name: artifact.name,
+            mime_type: artifact.mime_type,
+            size_bytes: artifact.size_bytes,
+        })
+    }
+}
@@ -1,11 +1,14 @@
 //! Exec tool for running subprocesses (task workers only).
 
+use crate::audit::{AuditAction, AuditLogger};
+use crate::sandbox::Sandbox;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::Arc;
 use tokio::process::Command;
 
 /// Tool for executing subprocesses, with path restrictions to prevent
@@ -14,6 +17,8 @@ use tokio::process::Command;
 pub struct ExecTool {
     instance_dir: PathBuf,
     workspace: PathBuf,
+    audit_logger: Option<AuditLogger>,
+    sandbox: Option<Arc<Sandbox>>,
 }
 
 impl ExecTool {
@@ -22,9 +27,55 @@ impl ExecTool {
         Self {
             instance_dir,
             workspace,
+            audit_logger: None,
+            sandbox: None,
         }
     }
 
+    /// Record every program that passes validation in the audit log.
+    pub fn with_audit(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Run commands inside the worker's container instead of on the host.
+    pub fn with_sandbox(mut self, sandbox: Option<Arc<Sandbox>>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Build a command that runs directly on the host.
+    fn host_command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&str>,
+        env: Vec<(String, String)>,
+    ) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+
+        // Default to workspace as working directory
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        } else {
+            cmd.current_dir(&self.workspace);
+        }
+
+        // Prepend persistent tools directory to PATH so user-installed
+        // binaries survive container restarts.
+        let tools_bin = self.instance_dir.join("tools/bin");
+        if let Ok(current_path) = std::env::var("PATH") {
+            cmd.env("PATH", format!("{}:{current_path}", tools_bin.display()));
+        }
+
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
+
+        cmd
+    }
+
     /// Check if program arguments reference sensitive instance paths.
     fn check_args(&self, program: &str, args: &[String]) -> Result<(), ExecError> {
         let instance_str = self.instance_dir.to_string_lossy();
@@ -240,26 +291,33 @@ impl Tool for ExecTool {
             }
         }
 
-        let mut cmd = Command::new(&args.program);
-        cmd.args(&args.args);
-
-        // Default to workspace as working directory
-        if let Some(dir) = args.working_dir {
-            cmd.current_dir(dir);
-        } else {
-            cmd.current_dir(&self.workspace);
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log(
+                AuditAction::Exec,
+                &args.program,
+                serde_json::json!({ "args": args.args, "working_dir": args.working_dir }),
+            );
         }
 
-        // Prepend persistent tools directory to PATH so user-installed
-        // binaries survive container restarts.
-        let tools_bin = self.instance_dir.join("tools/bin");
-        if let Ok(current_path) = std::env::var("PATH") {
-            cmd.env("PATH", format!("{}:{current_path}", tools_bin.display()));
-        }
-
-        for env_var in args.env {
-            cmd.env(env_var.key, env_var.value);
-        }
+        let env = args
+            .env
+            .into_iter()
+            .map(|env_var| (env_var.key, env_var.value))
+            .collect::<Vec<_>>();
+
+        let mut cmd = match &self.sandbox {
+            Some(sandbox) => {
+                let working_dir = args.working_dir.as_deref().map(std::path::Path::new);
+                sandbox
+                    .command(&args.program, &args.args, working_dir, &env)
+                    .await
+                    .map_err(|error| ExecError {
+                        message: error.to_string(),
+                        exit_code: -1,
+                    })?
+            }
+            None => self.host_command(&args.program, &args.args, args.working_dir.as_deref(), env),
+        };
 
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
@@ -276,12 +334,12 @@ impl Tool for ExecTool {
                 exit_code: -1,
             })?;
 
-        let stdout = crate::tools::truncate_output(
-            &String::from_utf8_lossy(&output.stdout),
+        let stdout = crate::tools::sanitize::sanitize_bytes(
+            &output.stdout,
             crate::tools::MAX_TOOL_OUTPUT_BYTES,
         );
-        let stderr = crate::tools::truncate_output(
-            &String::from_utf8_lossy(&output.stderr),
+        let stderr = crate::tools::sanitize::sanitize_bytes(
+            &output.stderr,
             crate::tools::MAX_TOOL_OUTPUT_BYTES,
         );
         let exit_code = output.status.code().unwrap_or(-1);
@@ -1,21 +1,37 @@
 //! File tool for reading/writing/listing files (task workers only).
 
+use crate::sandbox::Sandbox;
+
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt as _;
 
 /// Tool for file operations, restricted to the agent's workspace directory.
 #[derive(Debug, Clone)]
 pub struct FileTool {
     workspace: PathBuf,
+    sandbox: Option<Arc<Sandbox>>,
 }
 
 impl FileTool {
     /// Create a new file tool restricted to the given workspace directory.
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self {
+            workspace,
+            sandbox: None,
+        }
+    }
+
+    /// Read and write files inside the worker's container instead of on the
+    /// host. Paths are still checked against the workspace first.
+    pub fn with_sandbox(mut self, sandbox: Option<Arc<Sandbox>>) -> Self {
+        self.sandbox = sandbox;
+        self
     }
 
     /// Resolve and validate a path, ensuring it stays within the workspace boundary.
@@ -198,7 +214,10 @@ impl Tool for FileTool {
         let path = self.resolve_path(&args.path)?;
 
         match args.operation.as_str() {
-            "read" => do_file_read(&path).await,
+            "read" => match &self.sandbox {
+                Some(sandbox) => sandboxed_read(sandbox, &path).await,
+                None => do_file_read(&path).await,
+            },
             "write" => {
                 // Identity files remain readable, but writes must go through
                 // the dedicated identity API to keep update flow consistent.
@@ -215,9 +234,17 @@ impl Tool for FileTool {
                 let content = args.content.ok_or_else(|| {
                     FileError("Content is required for write operation".to_string())
                 })?;
-                do_file_write(&path, content, args.create_dirs).await
+                match &self.sandbox {
+                    Some(sandbox) => {
+                        sandboxed_write(sandbox, &path, content, args.create_dirs).await
+                    }
+                    None => do_file_write(&path, content, args.create_dirs).await,
+                }
             }
-            "list" => do_file_list(&path).await,
+            "list" => match &self.sandbox {
+                Some(sandbox) => sandboxed_list(sandbox, &path).await,
+                None => do_file_list(&path).await,
+            },
             _ => Err(FileError(format!("Unknown operation: {}", args.operation))),
         }
     }
@@ -305,7 +332,22 @@ async fn do_file_list(path: &Path) -> Result<FileOutput, FileError> {
         }
     }
 
-    // Sort entries: directories first, then files, both alphabetically
+    Ok(listing_output(
+        path.to_string_lossy().to_string(),
+        entries,
+        total_count,
+    ))
+}
+
+/// The output of a `list`: directories first, then files, both
+/// alphabetically, with a notice if there were more than the entries kept.
+fn listing_output(
+    path: String,
+    mut entries: Vec<FileEntryOutput>,
+    total_count: usize,
+) -> FileOutput {
+    let max_entries = crate::tools::MAX_DIR_ENTRIES;
+
     entries.sort_by(|a, b| {
         let a_is_dir = a.entry_type == "directory";
         let b_is_dir = b.entry_type == "directory";
@@ -327,16 +369,179 @@ async fn do_file_list(path: &Path) -> Result<FileOutput, FileError> {
         });
     }
 
-    Ok(FileOutput {
+    FileOutput {
         success: true,
         operation: "list".to_string(),
-        path: path.to_string_lossy().to_string(),
+        path,
         content: None,
         entries: Some(entries),
         error: None,
+    }
+}
+
+async fn sandboxed_read(sandbox: &Sandbox, path: &Path) -> Result<FileOutput, FileError> {
+    let container_path = container_path(sandbox, path)?;
+    let raw = run_in_sandbox(
+        sandbox,
+        "cat",
+        &["--".into(), container_path.clone()],
+        None,
+        "read file",
+    )
+    .await?;
+
+    let content = crate::tools::truncate_output(
+        &String::from_utf8_lossy(&raw),
+        crate::tools::MAX_TOOL_OUTPUT_BYTES,
+    );
+
+    Ok(FileOutput {
+        success: true,
+        operation: "read".to_string(),
+        path: container_path,
+        content: Some(content),
+        entries: None,
+        error: None,
+    })
+}
+
+async fn sandboxed_write(
+    sandbox: &Sandbox,
+    path: &Path,
+    content: String,
+    create_dirs: bool,
+) -> Result<FileOutput, FileError> {
+    let container_path = container_path(sandbox, path)?;
+    let script = if create_dirs {
+        r#"mkdir -p -- "$(dirname -- "$1")" && cat > "$1""#
+    } else {
+        r#"cat > "$1""#
+    };
+    run_in_sandbox(
+        sandbox,
+        "sh",
+        &[
+            "-c".into(),
+            script.into(),
+            "sh".into(),
+            container_path.clone(),
+        ],
+        Some(content.as_bytes()),
+        "write file",
+    )
+    .await?;
+
+    Ok(FileOutput {
+        success: true,
+        operation: "write".to_string(),
+        path: container_path,
+        content: None,
+        entries: None,
+        error: None,
     })
 }
 
+async fn sandboxed_list(sandbox: &Sandbox, path: &Path) -> Result<FileOutput, FileError> {
+    let container_path = container_path(sandbox, path)?;
+    // NUL-separated, so names containing newlines can't split an entry.
+    let raw = run_in_sandbox(
+        sandbox,
+        "sh",
+        &[
+            "-c".into(),
+            r#"cd -- "$1" && find . -mindepth 1 -maxdepth 1 -printf '%y\t%s\t%f\0'"#.into(),
+            "sh".into(),
+            container_path.clone(),
+        ],
+        None,
+        "read directory",
+    )
+    .await?;
+
+    let listed = parse_find_entries(&String::from_utf8_lossy(&raw));
+    let total_count = listed.len();
+    let entries = listed
+        .into_iter()
+        .take(crate::tools::MAX_DIR_ENTRIES)
+        .collect();
+    Ok(listing_output(container_path, entries, total_count))
+}
+
+/// Entries printed by `find -printf '%y\t%s\t%f\0'`.
+fn parse_find_entries(raw: &str) -> Vec<FileEntryOutput> {
+    raw.split('\0')
+        .filter_map(|record| {
+            let mut fields = record.splitn(3, '\t');
+            let entry_type = match fields.next()? {
+                "f" => "file",
+                "d" => "directory",
+                _ => "other",
+            };
+            let size = fields.next()?.parse().unwrap_or(0);
+            let name = fields.next()?;
+            Some(FileEntryOutput {
+                name: name.to_string(),
+                entry_type: entry_type.to_string(),
+                size,
+            })
+        })
+        .collect()
+}
+
+fn container_path(sandbox: &Sandbox, path: &Path) -> Result<String, FileError> {
+    sandbox
+        .container_path(path)
+        .map(|path| path.display().to_string())
+        .map_err(|error| FileError(error.to_string()))
+}
+
+/// Run `program` in the worker's container, feeding it `stdin`, and return
+/// what it printed. A failed command's stderr becomes the error.
+async fn run_in_sandbox(
+    sandbox: &Sandbox,
+    program: &str,
+    args: &[String],
+    stdin: Option<&[u8]>,
+    action: &str,
+) -> Result<Vec<u8>, FileError> {
+    let command = match stdin {
+        Some(_) => sandbox.command_with_stdin(program, args, None, &[]).await,
+        None => sandbox.command(program, args, None, &[]).await,
+    };
+    let mut command = command.map_err(|error| FileError(error.to_string()))?;
+    command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| FileError(format!("Failed to {action}: {e}")))?;
+    if let Some(input) = stdin
+        && let Some(mut pipe) = child.stdin.take()
+    {
+        pipe.write_all(input)
+            .await
+            .map_err(|e| FileError(format!("Failed to {action}: {e}")))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| FileError(format!("Failed to {action}: {e}")))?;
+
+    if !output.status.success() {
+        return Err(FileError(format!(
+            "Failed to {action}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
 /// File entry metadata (legacy).
 #[derive(Debug, Clone)]
 pub struct FileEntry {
@@ -399,3 +604,53 @@ pub async fn file_list(path: impl AsRef<Path>) -> crate::error::Result<Vec<FileE
         })
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_output_parses_into_entries() {
+        let entries = parse_find_entries("f\t12\tnotes.md\0d\t4096\tsrc\0l\t7\tline\nbreak\0");
+        let parsed: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.entry_type.as_str(), entry.size))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("notes.md", "file", 12),
+                ("src", "directory", 4096),
+                ("line\nbreak", "other", 7),
+            ]
+        );
+        assert!(parse_find_entries("").is_empty());
+    }
+
+    #[tokio::test]
+    async fn sandboxed_write_reads_back() {
+        // Runs a real container, so it needs a working docker.
+        let docker_ready = std::process::Command::new("docker")
+            .arg("info")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !docker_ready {
+            return;
+        }
+
+        let workspace = tempfile::tempdir().unwrap();
+        let config = crate::config::SandboxConfig {
+            backend: crate::config::SandboxBackend::Docker,
+            ..Default::default()
+        };
+        let sandbox = Sandbox::new(config, workspace.path().to_path_buf(), uuid::Uuid::new_v4())
+            .expect("docker backend yields a sandbox");
+        let path = workspace.path().join("notes/todo.md");
+
+        sandboxed_write(&sandbox, &path, "first\nsecond\n".into(), true)
+            .await
+            .unwrap();
+        let read = sandboxed_read(&sandbox, &path).await.unwrap();
+        assert_eq!(read.content.as_deref(), Some("first\nsecond\n"));
+    }
+}
@@ -0,0 +1,92 @@
+//! Fork tool for moving a tangent into its own thread (channel only).
+
+use crate::agent::channel::ChannelState;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Tool for forking the conversation into a new thread.
+#[derive(Debug, Clone)]
+pub struct ForkTool {
+    state: ChannelState,
+}
+
+impl ForkTool {
+    /// Create a new fork tool with access to channel state.
+    pub fn new(state: ChannelState) -> Self {
+        Self { state }
+    }
+}
+
+/// Error type for fork tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Fork failed: {0}")]
+pub struct ForkError(String);
+
+/// Arguments for fork tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ForkArgs {
+    /// Name of the new thread.
+    pub thread_name: String,
+    /// Opening request answered in the thread. Leave empty to only open it.
+    #[serde(default)]
+    pub topic: String,
+}
+
+/// Output from fork tool.
+#[derive(Debug, Serialize)]
+pub struct ForkOutput {
+    pub success: bool,
+    pub thread_name: String,
+    pub conversation_id: String,
+}
+
+impl Tool for ForkTool {
+    const NAME: &'static str = "fork";
+
+    type Error = ForkError;
+    type Args = ForkArgs;
+    type Output = ForkOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/fork").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "thread_name": {
+                        "type": "string",
+                        "description": "Short name for the new thread (at most 100 characters)."
+                    },
+                    "topic": {
+                        "type": "string",
+                        "description": "The user's request to pick up in the thread, answered there. Omit to only open the thread."
+                    }
+                },
+                "required": ["thread_name"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let source = self
+            .state
+            .trigger_message
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| ForkError("there is no message to start a thread from".into()))?;
+
+        let fork = crate::agent::fork::fork(&self.state, &source, &args.thread_name, &args.topic)
+            .await
+            .map_err(|error| ForkError(error.to_string()))?;
+
+        Ok(ForkOutput {
+            success: true,
+            thread_name: fork.thread_name,
+            conversation_id: fork.conversation_id,
+        })
+    }
+}
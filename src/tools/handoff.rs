@@ -0,0 +1,113 @@
+//! Handoff tool for passing the conversation to another agent (channel only).
+
+use crate::agent::channel::ChannelState;
+use crate::tools::SkipFlag;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::sync::atomic::Ordering;
+
+/// Tool for handing the conversation to one of the configured target agents.
+#[derive(Debug, Clone)]
+pub struct HandoffTool {
+    state: ChannelState,
+    skip_flag: SkipFlag,
+    targets: Vec<String>,
+}
+
+impl HandoffTool {
+    /// Create a new handoff tool limited to `targets`.
+    pub fn new(state: ChannelState, skip_flag: SkipFlag, targets: Vec<String>) -> Self {
+        Self {
+            state,
+            skip_flag,
+            targets,
+        }
+    }
+}
+
+/// Error type for handoff tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Handoff failed: {0}")]
+pub struct HandoffError(String);
+
+/// Arguments for handoff tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HandoffArgs {
+    /// Agent to hand the conversation to.
+    pub agent_id: String,
+    /// Why this agent is the better fit.
+    pub reason: String,
+    /// Briefing for the receiving agent.
+    pub summary: String,
+}
+
+/// Output from handoff tool.
+#[derive(Debug, Serialize)]
+pub struct HandoffOutput {
+    pub success: bool,
+    pub agent_id: String,
+}
+
+impl Tool for HandoffTool {
+    const NAME: &'static str = "handoff";
+
+    type Error = HandoffError;
+    type Args = HandoffArgs;
+    type Output = HandoffOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "{} Available agents: {}.",
+                crate::prompts::text::get("tools/handoff").trim_end(),
+                self.targets.join(", ")
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "agent_id": {
+                        "type": "string",
+                        "enum": self.targets,
+                        "description": "The agent to hand the conversation to."
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "Why that agent is the better fit, in a sentence."
+                    },
+                    "summary": {
+                        "type": "string",
+                        "description": "Briefing for the other agent: who the user is, what they want, and what's been tried or decided so far."
+                    }
+                },
+                "required": ["agent_id", "reason", "summary"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if !self.targets.contains(&args.agent_id) {
+            return Err(HandoffError(format!(
+                "{} isn't an agent you can hand off to; pick one of: {}",
+                args.agent_id,
+                self.targets.join(", ")
+            )));
+        }
+
+        crate::agent::handoff::hand_off(&self.state, &args.agent_id, &args.reason, &args.summary)
+            .await
+            .map_err(|error| HandoffError(error.to_string()))?;
+
+        // The receiving agent talks to the user from here on.
+        self.skip_flag.store(true, Ordering::Relaxed);
+
+        Ok(HandoffOutput {
+            success: true,
+            agent_id: args.agent_id,
+        })
+    }
+}
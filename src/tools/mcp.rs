@@ -139,7 +139,7 @@ impl Tool for McpToolAdapter {
     }
 }
 
-fn sanitize_tool_identifier(raw: &str) -> String {
+pub(crate) fn sanitize_tool_identifier(raw: &str) -> String {
     let mut value = raw
         .chars()
         .map(|character| {
@@ -3,6 +3,7 @@
 //! Soft-deletes a memory by setting its `forgotten` flag. The memory stays in
 //! the database but is excluded from all search and recall operations.
 
+use crate::audit::{AuditAction, AuditLogger};
 use crate::memory::MemorySearch;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
@@ -14,12 +15,22 @@ use std::sync::Arc;
 #[derive(Debug, Clone)]
 pub struct MemoryDeleteTool {
     memory_search: Arc<MemorySearch>,
+    audit_logger: Option<AuditLogger>,
 }
 
 impl MemoryDeleteTool {
     /// Create a new memory delete tool.
     pub fn new(memory_search: Arc<MemorySearch>) -> Self {
-        Self { memory_search }
+        Self {
+            memory_search,
+            audit_logger: None,
+        }
+    }
+
+    /// Record every successful deletion in the audit log.
+    pub fn with_audit(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
     }
 }
 
@@ -122,6 +133,17 @@ impl Tool for MemoryDeleteTool {
                 "memory forgotten"
             );
 
+            if let Some(audit_logger) = &self.audit_logger {
+                audit_logger.log(
+                    AuditAction::MemoryDeleted,
+                    &args.memory_id,
+                    serde_json::json!({
+                        "memory_type": memory.memory_type.to_string(),
+                        "reason": args.reason,
+                    }),
+                );
+            }
+
             let preview = memory.content.lines().next().unwrap_or("(empty)");
             Ok(MemoryDeleteOutput {
                 forgotten: true,
@@ -54,6 +54,10 @@ pub struct MemorySaveArgs {
     /// Optional associations to create with other memories.
     #[serde(default)]
     pub associations: Vec<AssociationInput>,
+    /// Pin the memory so maintenance never decays or prunes it. Set by
+    /// reaction capture, never by the model.
+    #[serde(skip)]
+    pub pinned: bool,
 }
 
 fn default_memory_type() -> String {
@@ -210,6 +214,10 @@ impl Tool for MemorySaveTool {
             memory = memory.with_channel_id(Arc::from(channel_id.as_str()));
         }
 
+        if args.pinned {
+            memory = memory.pinned();
+        }
+
         // Save to SQLite database
         let store = self.memory_search.store();
         store
@@ -290,6 +298,16 @@ impl Tool for MemorySaveTool {
             tracing::warn!(%error, "failed to ensure FTS index after memory save");
         }
 
+        // Build the vector index once the store outgrows flat scans.
+        if let Err(error) = self
+            .memory_search
+            .embedding_table()
+            .ensure_vector_index()
+            .await
+        {
+            tracing::warn!(%error, "failed to ensure vector index after memory save");
+        }
+
         #[cfg(feature = "metrics")]
         {
             let metrics = crate::telemetry::Metrics::global();
@@ -322,6 +340,7 @@ pub async fn save_fact(
         source: None,
         channel_id: channel_id.map(|id| id.to_string()),
         associations: vec![],
+        pinned: false,
     };
 
     let output = tool
@@ -0,0 +1,186 @@
+//! Pin message tool for keeping a message through compaction (channel only).
+
+use crate::agent::channel::ChannelState;
+use crate::conversation::PinStore;
+use crate::conversation::pins::message_texts;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Tool for pinning messages in the current conversation.
+#[derive(Debug, Clone)]
+pub struct PinMessageTool {
+    state: ChannelState,
+}
+
+impl PinMessageTool {
+    /// Create a new pin message tool with access to channel state.
+    pub fn new(state: ChannelState) -> Self {
+        Self { state }
+    }
+}
+
+/// Error type for pin message tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Pinning failed: {0}")]
+pub struct PinMessageError(String);
+
+/// Arguments for pin message tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PinMessageArgs {
+    /// Words from the message to pin or unpin, copied exactly. Omit to pin
+    /// the message you're responding to.
+    #[serde(default)]
+    pub quote: Option<String>,
+    /// Unpin the messages matching `quote` instead.
+    #[serde(default)]
+    pub unpin: bool,
+}
+
+/// Output from pin message tool.
+#[derive(Debug, Serialize)]
+pub struct PinMessageOutput {
+    pub success: bool,
+    /// The pins made or removed.
+    pub pin_ids: Vec<String>,
+    /// How many messages are pinned in this conversation now.
+    pub pinned_count: usize,
+}
+
+impl Tool for PinMessageTool {
+    const NAME: &'static str = "pin_message";
+
+    type Error = PinMessageError;
+    type Args = PinMessageArgs;
+    type Output = PinMessageOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/pin_message").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "quote": {
+                        "type": "string",
+                        "description": "Words from the message to pin or unpin, copied exactly. Omit to pin the message you're responding to."
+                    },
+                    "unpin": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Unpin the pinned messages containing `quote` instead of pinning."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let store = PinStore::new(self.state.deps.sqlite_pool.clone());
+        let channel_id = self.state.channel_id.as_ref();
+        let quote = args
+            .quote
+            .as_deref()
+            .map(str::trim)
+            .filter(|quote| !quote.is_empty());
+
+        if args.unpin {
+            let quote =
+                quote.ok_or_else(|| PinMessageError("say which message to unpin".into()))?;
+            let pins = store
+                .list(channel_id)
+                .await
+                .map_err(|error| PinMessageError(error.to_string()))?;
+            let mut pin_ids = Vec::new();
+            for pin in pins.iter().filter(|pin| pin.content.contains(quote)) {
+                store
+                    .unpin(channel_id, &pin.id)
+                    .await
+                    .map_err(|error| PinMessageError(error.to_string()))?;
+                pin_ids.push(pin.id.clone());
+            }
+            if pin_ids.is_empty() {
+                return Err(PinMessageError(format!(
+                    "no pinned message contains \"{quote}\""
+                )));
+            }
+            return Ok(PinMessageOutput {
+                success: true,
+                pinned_count: pins.len() - pin_ids.len(),
+                pin_ids,
+            });
+        }
+
+        let (message_id, content) = match quote {
+            Some(quote) => self.find(&store, quote).await?,
+            None => {
+                let message = self.state.trigger_message.read().await.clone();
+                let content = message
+                    .map(|message| message.content.to_string())
+                    .filter(|content| !content.trim().is_empty())
+                    .ok_or_else(|| {
+                        PinMessageError("there is no message to pin, quote one".into())
+                    })?;
+                (None, content)
+            }
+        };
+
+        let pin = store
+            .pin(channel_id, message_id.as_deref(), &content, "agent")
+            .await
+            .map_err(|error| PinMessageError(error.to_string()))?;
+        let pinned_count = store
+            .list(channel_id)
+            .await
+            .map_err(|error| PinMessageError(error.to_string()))?
+            .len();
+
+        tracing::info!(
+            channel_id = %self.state.channel_id,
+            pin_id = %pin.id,
+            "message pinned"
+        );
+
+        Ok(PinMessageOutput {
+            success: true,
+            pin_ids: vec![pin.id],
+            pinned_count,
+        })
+    }
+}
+
+impl PinMessageTool {
+    /// The newest message quoting `quote`, from the channel's history or
+    /// else its log, as `(message_id, content)`.
+    async fn find(
+        &self,
+        store: &PinStore,
+        quote: &str,
+    ) -> Result<(Option<String>, String), PinMessageError> {
+        let in_history = {
+            let history = self.state.history.read().await;
+            history.iter().rev().find_map(|message| {
+                message_texts(message)
+                    .into_iter()
+                    .find(|text| text.contains(quote))
+                    .map(str::to_string)
+            })
+        };
+        if let Some(content) = in_history {
+            return Ok((None, content));
+        }
+
+        let logged = store
+            .find_logged(self.state.channel_id.as_ref(), quote)
+            .await
+            .map_err(|error| PinMessageError(error.to_string()))?;
+        match logged {
+            Some((message_id, content)) => Ok((Some(message_id), content)),
+            None => Err(PinMessageError(format!(
+                "no message in this conversation contains \"{quote}\""
+            ))),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Tool adapters for WASM plugin tools.
+
+use crate::config::PluginConfig;
+use crate::plugins::{Plugin, PluginToolSpec};
+use crate::tools::mcp::sanitize_tool_identifier;
+use crate::tools::truncate_output;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// One tool of a loaded plugin, exposed as `<plugin>_<tool>`.
+#[derive(Debug, Clone)]
+pub struct PluginTool {
+    plugin: Arc<Plugin>,
+    spec: PluginToolSpec,
+    config: PluginConfig,
+}
+
+impl PluginTool {
+    /// Adapters for every tool of the given plugins.
+    pub fn for_plugins(plugins: &[Arc<Plugin>], config: &PluginConfig) -> Vec<Self> {
+        plugins
+            .iter()
+            .flat_map(|plugin| {
+                plugin.tools().iter().map(|spec| Self {
+                    plugin: plugin.clone(),
+                    spec: spec.clone(),
+                    config: config.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn namespaced_name(&self) -> String {
+        format!(
+            "{}_{}",
+            sanitize_tool_identifier(self.plugin.name()),
+            sanitize_tool_identifier(&self.spec.name)
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Plugin tool call failed: {0}")]
+pub struct PluginToolError(String);
+
+#[derive(Debug, Serialize)]
+pub struct PluginToolOutput {
+    pub result: String,
+}
+
+impl Tool for PluginTool {
+    const NAME: &'static str = "plugin_tool";
+
+    type Error = PluginToolError;
+    type Args = Value;
+    type Output = PluginToolOutput;
+
+    fn name(&self) -> String {
+        self.namespaced_name()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.namespaced_name(),
+            description: self.spec.description.clone(),
+            parameters: self.spec.parameters.clone(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let result = self
+            .plugin
+            .invoke(&self.spec.name, &args, &self.config)
+            .await
+            .map_err(|error| PluginToolError(error.to_string()))?;
+
+        let result = truncate_output(&result, crate::tools::MAX_TOOL_OUTPUT_BYTES);
+        if result.is_empty() {
+            return Ok(PluginToolOutput {
+                result: "[tool returned no content]".to_string(),
+            });
+        }
+        Ok(PluginToolOutput { result })
+    }
+}
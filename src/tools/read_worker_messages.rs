@@ -0,0 +1,81 @@
+//! Read worker messages tool: lets a worker read the messages other workers
+//! left in its mailbox, optionally waiting for one to arrive.
+
+use crate::WorkerId;
+use crate::agent::mailbox::{MAX_WAIT_SECS, Mailbox, WorkerMail};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tool for reading a worker's mailbox.
+#[derive(Debug, Clone)]
+pub struct ReadWorkerMessagesTool {
+    mailbox: Arc<Mailbox>,
+}
+
+impl ReadWorkerMessagesTool {
+    pub fn new(mailbox: Arc<Mailbox>) -> Self {
+        Self { mailbox }
+    }
+}
+
+/// Error type for read worker messages tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Reading messages failed: {0}")]
+pub struct ReadWorkerMessagesError(String);
+
+/// Arguments for read worker messages tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReadWorkerMessagesArgs {
+    /// Seconds to wait for a message when the mailbox is empty.
+    #[serde(default)]
+    pub wait_secs: u64,
+}
+
+/// Output from read worker messages tool.
+#[derive(Debug, Serialize)]
+pub struct ReadWorkerMessagesOutput {
+    /// This worker's ID, for telling other workers where to reply.
+    pub your_worker_id: WorkerId,
+    pub messages: Vec<WorkerMail>,
+}
+
+impl Tool for ReadWorkerMessagesTool {
+    const NAME: &'static str = "read_worker_messages";
+
+    type Error = ReadWorkerMessagesError;
+    type Args = ReadWorkerMessagesArgs;
+    type Output = ReadWorkerMessagesOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/read_worker_messages").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "wait_secs": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_WAIT_SECS,
+                        "description": "Seconds to wait for a message if none are waiting. 0 returns right away."
+                    }
+                },
+                "required": []
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let wait = Duration::from_secs(args.wait_secs.min(MAX_WAIT_SECS));
+        let messages = self.mailbox.receive(wait).await;
+
+        Ok(ReadWorkerMessagesOutput {
+            your_worker_id: self.mailbox.worker_id(),
+            messages,
+        })
+    }
+}
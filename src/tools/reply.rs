@@ -93,6 +93,16 @@ pub struct ReplyArgs {
     /// Optional: a poll to attach to the message.
     #[serde(default)]
     pub poll: Option<crate::Poll>,
+    /// Optional: ask the user to pick one of these options, with `content`
+    /// as the question. Shown as buttons or a select menu on Discord and as
+    /// a numbered list elsewhere. Takes precedence over cards and polls.
+    #[serde(default)]
+    pub choices: Option<Vec<crate::ChoiceOption>>,
+    /// Optional: a single embed for results and summaries, sent below
+    /// `content`. Shown as an embed on Discord and as formatted text
+    /// elsewhere. Ignored when `choices` are set.
+    #[serde(default)]
+    pub embed: Option<crate::Card>,
 }
 
 /// Output from reply tool.
@@ -258,10 +268,35 @@ impl Tool for ReplyTool {
                                     "required": ["name", "value"]
                                 }
                             },
-                            "footer": { "type": "string" }
+                            "footer": { "type": "string" },
+                            "image_url": { "type": "string" }
                         }
                     }
                 },
+                "embed": {
+                    "type": "object",
+                    "description": "Optional: one embed sent below `content`, for worker results, reports and status summaries that would otherwise be a wall of text. Put each result in a field. Rendered as an embed on Discord and as formatted text elsewhere, unlike `cards`, which only Discord shows. Ignored when `choices` are set.",
+                    "properties": {
+                        "title": { "type": "string" },
+                        "description": { "type": "string" },
+                        "color": { "type": "integer", "description": "Decimal color code" },
+                        "url": { "type": "string" },
+                        "fields": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": { "type": "string" },
+                                    "value": { "type": "string" },
+                                    "inline": { "type": "boolean" }
+                                },
+                                "required": ["name", "value"]
+                            }
+                        },
+                        "footer": { "type": "string" },
+                        "image_url": { "type": "string" }
+                    }
+                },
                 "interactive_elements": {
                     "type": "array",
                     "description": "Optional: interactive components to attach. Button clicks will be sent back to you as an inbound InteractionEvent with the corresponding custom_id. Max 5 elements (rows).",
@@ -319,6 +354,19 @@ impl Tool for ReplyTool {
                         "duration_hours": { "type": "integer", "description": "Defaults to 24 if omitted" }
                     },
                     "required": ["question", "answers"]
+                },
+                "choices": {
+                    "type": "array",
+                    "description": "Optional: ask the user to pick one option, with `content` as the question. Rendered as buttons or a select menu on Discord and a numbered list elsewhere. The pick comes back as an interaction with action_id \"choice\" and the option's value. Max 25 options. Takes precedence over cards, interactive_elements and poll.",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "label": { "type": "string" },
+                            "value": { "type": "string", "description": "Sent back when picked. Defaults to the label" },
+                            "description": { "type": "string" }
+                        },
+                        "required": ["label"]
+                    }
                 }
             },
             "required": ["content"]
@@ -351,10 +399,27 @@ impl Tool for ReplyTool {
         )
         .await;
 
+        let choices = args.choices.filter(|options| !options.is_empty());
+        // Log choices and embeds in full so the history shows what was sent.
+        let logged_content = match (&choices, &args.embed) {
+            (Some(options), _) => crate::choices_text(&converted_content, options),
+            (None, Some(card)) => crate::rich_text(&converted_content, card),
+            (None, None) => converted_content.clone(),
+        };
         self.conversation_logger
-            .log_bot_message(&self.channel_id, &converted_content);
+            .log_bot_message(&self.channel_id, &logged_content);
 
-        let response = if let Some(ref name) = args.thread_name {
+        let response = if let Some(options) = choices {
+            OutboundResponse::Choices {
+                prompt: converted_content.clone(),
+                options,
+            }
+        } else if let Some(card) = args.embed {
+            OutboundResponse::Rich {
+                text: converted_content.clone(),
+                card,
+            }
+        } else if let Some(ref name) = args.thread_name {
             // Cap thread names at 100 characters (Discord limit)
             let thread_name = if name.len() > 100 {
                 name[..name.floor_char_boundary(100)].to_string()
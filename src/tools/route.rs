@@ -1,7 +1,9 @@
 //! Route tool for sending follow-ups to active workers.
 
 use crate::WorkerId;
-use crate::agent::channel::ChannelState;
+use crate::agent::channel::{ChannelState, audit_logger_for_turn, check_capability};
+use crate::audit::AuditAction;
+use crate::rbac::Capability;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
@@ -74,10 +76,14 @@ impl Tool for RouteTool {
     }
 
     async fn call(&self, args: Self::Args) -> std::result::Result<Self::Output, Self::Error> {
+        check_capability(&self.state, Capability::ToolApproval)
+            .await
+            .map_err(|error| RouteError(format!("{error}")))?;
+
         let worker_id = args
             .worker_id
             .parse::<WorkerId>()
-            .map_err(|e| RouteError(format!("Invalid worker ID: {e}")))?;
+            .map_err(|error| RouteError(format!("Invalid worker ID: {error}")))?;
 
         // Look up the input sender for this worker
         let inputs = self.state.worker_inputs.read().await;
@@ -101,6 +107,12 @@ impl Tool for RouteTool {
             "message routed to worker"
         );
 
+        audit_logger_for_turn(&self.state).await.log(
+            AuditAction::WorkerFollowUp,
+            &worker_id.to_string(),
+            serde_json::json!({ "channel_id": self.state.channel_id.to_string() }),
+        );
+
         Ok(RouteOutput {
             routed: true,
             worker_id,
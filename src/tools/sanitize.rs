@@ -0,0 +1,235 @@
+//! Sanitization of tool output before it enters a process's history.
+//!
+//! Terminal escape codes, progress-bar redraws, binary blobs and huge
+//! outputs all cost tokens without telling the model anything, and a single
+//! bad result can derail every later turn. Process output is cleaned from
+//! raw bytes with [`sanitize_bytes`]. Every other tool is wrapped in
+//! [`Sanitized`], which cleans each string in its JSON output.
+
+use regex::Regex;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+
+use std::sync::LazyLock;
+
+/// Bytes inspected when deciding whether content is binary.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// Share of control or undecodable characters above which text counts as binary.
+const BINARY_THRESHOLD: f64 = 0.1;
+
+/// ANSI/VT escape sequences: CSI (colors, cursor movement), OSC (titles,
+/// hyperlinks) and two-byte escapes.
+static ANSI_ESCAPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]")
+        .expect("hardcoded regex")
+});
+
+/// Clean raw process output: summarize binary content, otherwise decode,
+/// strip escape codes and collapse to `max_bytes`.
+pub fn sanitize_bytes(bytes: &[u8], max_bytes: usize) -> String {
+    if looks_binary(bytes) {
+        return binary_summary(bytes);
+    }
+    sanitize_text(&String::from_utf8_lossy(bytes), max_bytes)
+}
+
+/// Clean a tool output string. Returns the input unchanged when there is
+/// nothing to clean.
+pub fn sanitize_text(text: &str, max_bytes: usize) -> String {
+    if looks_binary(text.as_bytes()) || looks_undecodable(text) {
+        return binary_summary(text.as_bytes());
+    }
+    let cleaned = strip_terminal_noise(text);
+    excerpt(&cleaned, max_bytes)
+}
+
+/// Apply [`sanitize_text`] to every string in a JSON value.
+pub fn sanitize_value(value: &mut serde_json::Value, max_bytes: usize) {
+    match value {
+        serde_json::Value::String(text) => {
+            let cleaned = sanitize_text(text, max_bytes);
+            if cleaned != *text {
+                *text = cleaned;
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                sanitize_value(item, max_bytes);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                sanitize_value(field, max_bytes);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Remove escape sequences, carriage-return redraws and stray control
+/// characters. Newlines and tabs are kept.
+fn strip_terminal_noise(text: &str) -> String {
+    let without_escapes = ANSI_ESCAPE.replace_all(text, "");
+
+    let mut cleaned = String::with_capacity(without_escapes.len());
+    for (index, line) in without_escapes.split('\n').enumerate() {
+        if index > 0 {
+            cleaned.push('\n');
+        }
+        // A progress bar redraws the line with `\r`; only the last frame matters.
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let line = line.rsplit('\r').next().unwrap_or(line);
+        cleaned.extend(
+            line.chars()
+                .filter(|character| *character == '\t' || !character.is_control()),
+        );
+    }
+    cleaned
+}
+
+/// Keep the head and tail of an oversized output with a note in between.
+/// Errors and summaries tend to be at the end, so the tail is kept as well.
+fn excerpt(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+
+    let head_end = text.floor_char_boundary(max_bytes * 2 / 3);
+    let tail_start = text.ceil_char_boundary(text.len() - max_bytes / 3);
+    let omitted = tail_start - head_end;
+    format!(
+        "{}\n\n[... {omitted} of {} bytes omitted. Use head/tail/offset or grep to read specific sections ...]\n\n{}",
+        &text[..head_end],
+        text.len(),
+        &text[tail_start..]
+    )
+}
+
+/// NUL bytes or a high share of invalid UTF-8 in the first few KB.
+fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+    if sample.is_empty() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+    let decoded = String::from_utf8_lossy(sample);
+    let replacements = decoded.chars().filter(|c| *c == '\u{FFFD}').count();
+    replacements as f64 / decoded.chars().count() as f64 > BINARY_THRESHOLD
+}
+
+/// Text that already went through lossy decoding, or is mostly control
+/// characters that aren't escape codes.
+fn looks_undecodable(text: &str) -> bool {
+    let sample_end = text.floor_char_boundary(text.len().min(BINARY_SNIFF_BYTES));
+    let sample = ANSI_ESCAPE.replace_all(&text[..sample_end], "");
+    let total = sample.chars().count();
+    if total == 0 {
+        return false;
+    }
+    let garbage = sample
+        .chars()
+        .filter(|character| {
+            *character == '\u{FFFD}' || (character.is_control() && !"\n\r\t".contains(*character))
+        })
+        .count();
+    garbage as f64 / total as f64 > BINARY_THRESHOLD
+}
+
+fn binary_summary(bytes: &[u8]) -> String {
+    let kind = match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "PNG image",
+        [0xFF, 0xD8, 0xFF, ..] => "JPEG image",
+        [b'G', b'I', b'F', b'8', ..] => "GIF image",
+        [b'%', b'P', b'D', b'F', ..] => "PDF document",
+        [b'P', b'K', 0x03, 0x04, ..] => "ZIP archive",
+        [0x1F, 0x8B, ..] => "gzip archive",
+        [0x7F, b'E', b'L', b'F', ..] => "ELF executable",
+        [b'R', b'I', b'F', b'F', ..] => "RIFF media file",
+        _ => "binary data",
+    };
+    format!(
+        "[binary output omitted: {} bytes, looks like {kind}. Write it to a file and inspect it with a tool such as `file`, `xxd` or `strings` instead]",
+        bytes.len()
+    )
+}
+
+/// A tool whose output strings are sanitized before reaching the model.
+#[derive(Debug, Clone)]
+pub struct Sanitized<T> {
+    inner: T,
+}
+
+impl<T: Tool> Sanitized<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Tool> Tool for Sanitized<T> {
+    const NAME: &'static str = T::NAME;
+
+    type Error = T::Error;
+    type Args = T::Args;
+    type Output = serde_json::Value;
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let output = self.inner.call(args).await?;
+        let mut value = serde_json::to_value(&output).unwrap_or_else(|error| {
+            serde_json::Value::String(format!("failed to serialize tool output: {error}"))
+        });
+        sanitize_value(&mut value, crate::tools::MAX_TOOL_OUTPUT_BYTES);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_ansi_and_progress_redraws() {
+        let text = "\x1b[32mok\x1b[0m build\n 10%\r 50%\r100%\r\ndone\x07";
+        assert_eq!(sanitize_text(text, 1024), "ok build\n100%\ndone");
+    }
+
+    #[test]
+    fn summarizes_binary_content() {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        let summary = sanitize_bytes(&png, 1024);
+        assert!(summary.starts_with("[binary output omitted: 12 bytes"));
+        assert!(summary.contains("PNG image"));
+
+        let lossy = String::from_utf8_lossy(&[0xC3, 0x28, 0xA0, 0xA1, 0xE2, 0x28]).into_owned();
+        assert!(sanitize_text(&lossy, 1024).contains("binary data"));
+    }
+
+    #[test]
+    fn collapses_large_output_to_head_and_tail() {
+        let text = format!("HEAD{}TAIL", "x".repeat(10_000));
+        let collapsed = sanitize_text(&text, 300);
+
+        assert!(collapsed.starts_with("HEAD"));
+        assert!(collapsed.ends_with("TAIL"));
+        assert!(collapsed.contains("bytes omitted"));
+        assert!(collapsed.len() < 600);
+    }
+
+    #[test]
+    fn leaves_clean_values_untouched() {
+        let mut value = serde_json::json!({ "stdout": "hello\tworld\n", "exit_code": 0 });
+        let expected = value.clone();
+        sanitize_value(&mut value, 1024);
+        assert_eq!(value, expected);
+    }
+}
@@ -1,26 +1,46 @@
 //! Send file tool for delivering file attachments to users (channel only).
 
-use crate::OutboundResponse;
+use crate::object_storage::{ObjectKind, ObjectStorage};
+use crate::{OutboundFile, OutboundResponse};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
 /// Tool for sending files to users.
 ///
-/// Reads a file from the local filesystem and sends it as an attachment
-/// in the conversation. The channel process creates a response sender per
+/// Reads files from the local filesystem and sends them as attachments
+/// in the conversation, several at once when given a list. The channel process creates a response sender per
 /// conversation turn and this tool routes file responses through it.
+///
+/// Files over the attachment limit are uploaded to object storage, when
+/// configured, and sent as a signed download link instead.
 #[derive(Debug, Clone)]
 pub struct SendFileTool {
     response_tx: mpsc::Sender<OutboundResponse>,
+    object_storage: Option<(Arc<ObjectStorage>, String)>,
 }
 
 impl SendFileTool {
     pub fn new(response_tx: mpsc::Sender<OutboundResponse>) -> Self {
-        Self { response_tx }
+        Self {
+            response_tx,
+            object_storage: None,
+        }
+    }
+
+    /// Deliver oversized files as links from this agent's object storage.
+    pub fn with_object_storage(
+        mut self,
+        storage: Option<Arc<ObjectStorage>>,
+        agent_id: impl Into<String>,
+    ) -> Self {
+        let agent_id = agent_id.into();
+        self.object_storage = storage.map(|storage| (storage, agent_id));
+        self
     }
 }
 
@@ -33,7 +53,11 @@ pub struct SendFileError(String);
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct SendFileArgs {
     /// The absolute path to the file to send.
-    pub file_path: String,
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// Absolute paths of several files to send together in one message.
+    #[serde(default)]
+    pub file_paths: Vec<String>,
     /// Optional caption/message to accompany the file.
     #[serde(default)]
     pub caption: Option<String>,
@@ -43,13 +67,24 @@ pub struct SendFileArgs {
 #[derive(Debug, Serialize)]
 pub struct SendFileOutput {
     pub success: bool,
+    pub files: Vec<SentFile>,
+}
+
+/// One file delivered by send_file.
+#[derive(Debug, Serialize)]
+pub struct SentFile {
     pub filename: String,
     pub size_bytes: u64,
+    /// Whether the file was sent as a download link rather than attached.
+    pub sent_as_link: bool,
 }
 
 /// Maximum file size: 25 MB (Discord's limit for non-boosted servers).
 const MAX_FILE_SIZE_BYTES: u64 = 25 * 1024 * 1024;
 
+/// Most files sent in one call.
+const MAX_FILES: usize = 10;
+
 impl Tool for SendFileTool {
     const NAME: &'static str = "send_file";
 
@@ -68,24 +103,111 @@ impl Tool for SendFileTool {
                         "type": "string",
                         "description": "The absolute path to the file to send."
                     },
+                    "file_paths": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "maxItems": MAX_FILES,
+                        "description": "Absolute paths of several files to send together in one message, e.g. screenshots or logs a worker produced. Use instead of file_path."
+                    },
                     "caption": {
                         "type": "string",
                         "description": "Optional caption or message to accompany the file."
                     }
-                },
-                "required": ["file_path"]
+                }
             }),
         }
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let path = PathBuf::from(&args.file_path);
+        let paths = args
+            .file_path
+            .into_iter()
+            .chain(args.file_paths)
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return Err(SendFileError("pass file_path or file_paths".into()));
+        }
+        if paths.len() > MAX_FILES {
+            return Err(SendFileError(format!(
+                "too many files ({}, max {MAX_FILES})",
+                paths.len()
+            )));
+        }
+
+        let mut files = Vec::with_capacity(paths.len());
+        for path in &paths {
+            files.push(self.read_file(path).await?);
+        }
+
+        let mut sent = Vec::with_capacity(files.len());
+        let mut attached = Vec::new();
+        let mut links = Vec::new();
+        for file in files {
+            let size_bytes = file.data.len() as u64;
+            let sent_as_link = size_bytes > MAX_FILE_SIZE_BYTES;
+            sent.push(SentFile {
+                filename: file.filename.clone(),
+                size_bytes,
+                sent_as_link,
+            });
+            if let Some((storage, agent_id)) = &self.object_storage
+                && sent_as_link
+            {
+                let url = storage
+                    .share(ObjectKind::File, agent_id, &file.filename, file.data)
+                    .await
+                    .map_err(|error| SendFileError(format!("failed to upload file: {error}")))?;
+                links.push(format!("{}: {url}", file.filename));
+            } else {
+                attached.push(file);
+            }
+        }
+
+        let text = args
+            .caption
+            .into_iter()
+            .chain(links)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = (!text.is_empty()).then_some(text);
+        let response = match (attached.len(), text) {
+            (0, text) => OutboundResponse::Text(text.unwrap_or_default()),
+            (1, caption) => {
+                let file = attached.remove(0);
+                OutboundResponse::File {
+                    filename: file.filename,
+                    data: file.data,
+                    mime_type: file.mime_type,
+                    caption,
+                }
+            }
+            (_, text) => OutboundResponse::Attachments {
+                text,
+                files: attached,
+            },
+        };
+
+        self.response_tx
+            .send(response)
+            .await
+            .map_err(|error| SendFileError(format!("failed to send file: {error}")))?;
+
+        Ok(SendFileOutput {
+            success: true,
+            files: sent,
+        })
+    }
+}
 
+impl SendFileTool {
+    /// Check and read one file to send.
+    async fn read_file(&self, path: &Path) -> Result<OutboundFile, SendFileError> {
         if !path.is_absolute() {
-            return Err(SendFileError("file_path must be an absolute path".into()));
+            return Err(SendFileError("file paths must be absolute".into()));
         }
 
-        let metadata = tokio::fs::metadata(&path).await.map_err(|error| {
+        let metadata = tokio::fs::metadata(path).await.map_err(|error| {
             SendFileError(format!("can't read file '{}': {error}", path.display()))
         })?;
 
@@ -93,15 +215,20 @@ impl Tool for SendFileTool {
             return Err(SendFileError(format!("'{}' is not a file", path.display())));
         }
 
-        if metadata.len() > MAX_FILE_SIZE_BYTES {
+        let max_bytes = match &self.object_storage {
+            Some((storage, _)) => storage.max_upload_bytes().max(MAX_FILE_SIZE_BYTES),
+            None => MAX_FILE_SIZE_BYTES,
+        };
+        if metadata.len() > max_bytes {
             return Err(SendFileError(format!(
-                "file is too large ({} bytes, max {} bytes)",
+                "'{}' is too large ({} bytes, max {} bytes)",
+                path.display(),
                 metadata.len(),
-                MAX_FILE_SIZE_BYTES,
+                max_bytes,
             )));
         }
 
-        let data = tokio::fs::read(&path).await.map_err(|error| {
+        let data = tokio::fs::read(path).await.map_err(|error| {
             SendFileError(format!("failed to read '{}': {error}", path.display()))
         })?;
 
@@ -110,36 +237,22 @@ impl Tool for SendFileTool {
             .map(|name| name.to_string_lossy().into_owned())
             .unwrap_or_else(|| "file".into());
 
-        let mime_type = mime_guess::from_path(&path)
+        let mime_type = mime_guess::from_path(path)
             .first_or_octet_stream()
             .to_string();
 
-        let size_bytes = data.len() as u64;
-
         tracing::info!(
             file_path = %path.display(),
             filename = %filename,
             mime_type = %mime_type,
-            size_bytes,
+            size_bytes = data.len(),
             "send_file tool called"
         );
 
-        let response = OutboundResponse::File {
-            filename: filename.clone(),
+        Ok(OutboundFile {
+            filename,
             data,
             mime_type,
-            caption: args.caption,
-        };
-
-        self.response_tx
-            .send(response)
-            .await
-            .map_err(|error| SendFileError(format!("failed to send file: {error}")))?;
-
-        Ok(SendFileOutput {
-            success: true,
-            filename,
-            size_bytes,
         })
     }
 }
@@ -0,0 +1,99 @@
+//! Send proactive message tool for messaging a conversation unprompted
+//! (cortex chat only).
+
+use crate::AgentDeps;
+use crate::agent::outreach;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Tool for sending a message to a conversation nobody asked the agent to.
+#[derive(Debug, Clone)]
+pub struct SendProactiveMessageTool {
+    deps: AgentDeps,
+}
+
+impl SendProactiveMessageTool {
+    pub fn new(deps: AgentDeps) -> Self {
+        Self { deps }
+    }
+}
+
+/// Error type for send proactive message tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Proactive message not sent: {0}")]
+pub struct SendProactiveMessageError(String);
+
+/// Arguments for send proactive message tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SendProactiveMessageArgs {
+    /// The message to send.
+    pub text: String,
+    /// Where to send it, as `adapter:target`. Every allowed channel when
+    /// left out.
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+/// Output from send proactive message tool.
+#[derive(Debug, Serialize)]
+pub struct SendProactiveMessageOutput {
+    pub success: bool,
+    /// Channels the message was delivered to.
+    pub delivered_to: Vec<String>,
+}
+
+impl Tool for SendProactiveMessageTool {
+    const NAME: &'static str = "send_proactive_message";
+
+    type Error = SendProactiveMessageError;
+    type Args = SendProactiveMessageArgs;
+    type Output = SendProactiveMessageOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let targets: Vec<String> = outreach::targets(&self.deps)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let target_description = if targets.is_empty() {
+            "No binding allows proactive messages yet.".to_string()
+        } else {
+            format!("One of: {}.", targets.join(", "))
+        };
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/send_proactive_message").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "The message to send."
+                    },
+                    "target": {
+                        "type": "string",
+                        "description": format!("Where to send it, as adapter:target. Leave out to send to every allowed channel. {target_description}")
+                    }
+                },
+                "required": ["text"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let text = args.text.trim();
+        if text.is_empty() {
+            return Err(SendProactiveMessageError("the message is empty".into()));
+        }
+        let delivered = outreach::send_proactive(&self.deps, args.target.as_deref(), text)
+            .await
+            .map_err(|error| SendProactiveMessageError(error.to_string()))?;
+
+        Ok(SendProactiveMessageOutput {
+            success: true,
+            delivered_to: delivered.iter().map(ToString::to_string).collect(),
+        })
+    }
+}
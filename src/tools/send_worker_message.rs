@@ -0,0 +1,111 @@
+//! Send worker message tool: lets a worker leave a message in another running
+//! worker's mailbox.
+
+use crate::WorkerId;
+use crate::agent::mailbox::{MailError, Mailbox};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Tool for messaging another worker.
+#[derive(Debug, Clone)]
+pub struct SendWorkerMessageTool {
+    mailbox: Arc<Mailbox>,
+}
+
+impl SendWorkerMessageTool {
+    pub fn new(mailbox: Arc<Mailbox>) -> Self {
+        Self { mailbox }
+    }
+
+    /// List the other running workers, so the worker can correct a bad ID.
+    fn describe_peers(&self) -> String {
+        let peers = self.mailbox.peers();
+        if peers.is_empty() {
+            return "No other workers are running.".into();
+        }
+        let peers = peers
+            .iter()
+            .map(|(worker_id, task)| format!("{worker_id} ({task})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("Running workers: {peers}")
+    }
+}
+
+/// Error type for send worker message tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Message not sent: {0}")]
+pub struct SendWorkerMessageError(String);
+
+/// Arguments for send worker message tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SendWorkerMessageArgs {
+    /// The ID of the worker to message.
+    pub worker_id: String,
+    /// The message.
+    pub message: String,
+}
+
+/// Output from send worker message tool.
+#[derive(Debug, Serialize)]
+pub struct SendWorkerMessageOutput {
+    pub sent: bool,
+    pub to: WorkerId,
+}
+
+impl Tool for SendWorkerMessageTool {
+    const NAME: &'static str = "send_worker_message";
+
+    type Error = SendWorkerMessageError;
+    type Args = SendWorkerMessageArgs;
+    type Output = SendWorkerMessageOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/send_worker_message").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "worker_id": {
+                        "type": "string",
+                        "description": "The ID of the running worker to message."
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "What to tell the worker. Include everything it needs; it can't see your history."
+                    }
+                },
+                "required": ["worker_id", "message"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.message.trim().is_empty() {
+            return Err(SendWorkerMessageError("the message is empty".into()));
+        }
+        let to = args.worker_id.trim().parse::<WorkerId>().map_err(|_| {
+            SendWorkerMessageError(format!(
+                "'{}' isn't a worker ID. {}",
+                args.worker_id,
+                self.describe_peers()
+            ))
+        })?;
+
+        self.mailbox
+            .send(to, &args.message)
+            .map_err(|error| match error {
+                MailError::UnknownWorker(_) => {
+                    SendWorkerMessageError(format!("{error}. {}", self.describe_peers()))
+                }
+                error => SendWorkerMessageError(error.to_string()),
+            })?;
+
+        tracing::debug!(from = %self.mailbox.worker_id(), %to, "worker message sent");
+        Ok(SendWorkerMessageOutput { sent: true, to })
+    }
+}
@@ -0,0 +1,112 @@
+//! Set reminder tool for bringing something up in the conversation later
+//! (channel only).
+
+use crate::agent::channel::ChannelState;
+use crate::reminders::{self, ReminderStore};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Tool for setting a reminder in the current conversation.
+#[derive(Debug, Clone)]
+pub struct SetReminderTool {
+    state: ChannelState,
+}
+
+impl SetReminderTool {
+    /// Create a new set reminder tool with access to channel state.
+    pub fn new(state: ChannelState) -> Self {
+        Self { state }
+    }
+}
+
+/// Error type for set reminder tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Setting the reminder failed: {0}")]
+pub struct SetReminderError(String);
+
+/// Arguments for set reminder tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetReminderArgs {
+    /// A delay like `30m`, `2h` or `1d 4h`, or an RFC 3339 timestamp.
+    pub when: String,
+    /// What to remind about.
+    pub message: String,
+}
+
+/// Output from set reminder tool.
+#[derive(Debug, Serialize)]
+pub struct SetReminderOutput {
+    pub success: bool,
+    pub reminder_id: String,
+    /// When the reminder is due, as an RFC 3339 timestamp.
+    pub due_at: String,
+}
+
+impl Tool for SetReminderTool {
+    const NAME: &'static str = "set_reminder";
+
+    type Error = SetReminderError;
+    type Args = SetReminderArgs;
+    type Output = SetReminderOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/set_reminder").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "when": {
+                        "type": "string",
+                        "description": "When the reminder is due: a delay like \"30m\", \"2h\" or \"1d 4h\", or an RFC 3339 timestamp like \"2026-03-01T09:00:00+01:00\"."
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "What to remind the user about."
+                    }
+                },
+                "required": ["when", "message"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.message.trim().is_empty() {
+            return Err(SetReminderError("the reminder message is empty".into()));
+        }
+        let due_at = reminders::parse_when(&args.when, chrono::Utc::now()).ok_or_else(|| {
+            SetReminderError(format!(
+                "can't read '{}' as a time in the next {} days; use a delay like 2h or an RFC 3339 timestamp",
+                args.when,
+                reminders::MAX_REMINDER_DAYS
+            ))
+        })?;
+        let source = self
+            .state
+            .trigger_message
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| SetReminderError("there is no conversation to remind".into()))?;
+
+        let reminder = ReminderStore::new(self.state.deps.sqlite_pool.clone())
+            .create(&source, args.message.trim(), due_at)
+            .await
+            .map_err(|error| SetReminderError(error.to_string()))?;
+
+        tracing::info!(
+            channel_id = %self.state.channel_id,
+            reminder_id = %reminder.id,
+            due_at = %reminder.due_at,
+            "reminder set"
+        );
+
+        Ok(SetReminderOutput {
+            success: true,
+            reminder_id: reminder.id,
+            due_at: reminder.due_at.to_rfc3339(),
+        })
+    }
+}
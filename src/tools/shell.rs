@@ -1,11 +1,14 @@
 //! Shell tool for executing shell commands (task workers only).
 
+use crate::audit::{AuditAction, AuditLogger};
+use crate::sandbox::Sandbox;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::Arc;
 use tokio::process::Command;
 
 /// Sensitive filenames that should not be accessible via shell commands.
@@ -35,6 +38,8 @@ pub const SECRET_ENV_VARS: &[&str] = &[
 pub struct ShellTool {
     instance_dir: PathBuf,
     workspace: PathBuf,
+    audit_logger: Option<AuditLogger>,
+    sandbox: Option<Arc<Sandbox>>,
 }
 
 impl ShellTool {
@@ -43,9 +48,52 @@ impl ShellTool {
         Self {
             instance_dir,
             workspace,
+            audit_logger: None,
+            sandbox: None,
         }
     }
 
+    /// Record every command that passes validation in the audit log.
+    pub fn with_audit(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Run commands inside the worker's container instead of on the host.
+    pub fn with_sandbox(mut self, sandbox: Option<Arc<Sandbox>>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Build a command that runs directly on the host.
+    fn host_command(&self, command: &str, working_dir: Option<&str>) -> Command {
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(command);
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c").arg(command);
+            c
+        };
+
+        // Default to workspace as working directory
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        } else {
+            cmd.current_dir(&self.workspace);
+        }
+
+        // Prepend persistent tools directory to PATH so user-installed
+        // binaries survive container restarts.
+        let tools_bin = self.instance_dir.join("tools/bin");
+        if let Ok(current_path) = std::env::var("PATH") {
+            cmd.env("PATH", format!("{}:{current_path}", tools_bin.display()));
+        }
+
+        cmd
+    }
+
     /// Check if a command references sensitive instance paths or secret env vars.
     fn check_command(&self, command: &str) -> Result<(), ShellError> {
         let instance_str = self.instance_dir.to_string_lossy();
@@ -319,29 +367,32 @@ impl Tool for ShellTool {
             }
         }
 
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut c = Command::new("cmd");
-            c.arg("/C").arg(&args.command);
-            c
-        } else {
-            let mut c = Command::new("sh");
-            c.arg("-c").arg(&args.command);
-            c
-        };
-
-        // Default to workspace as working directory
-        if let Some(dir) = args.working_dir {
-            cmd.current_dir(dir);
-        } else {
-            cmd.current_dir(&self.workspace);
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log(
+                AuditAction::ShellExec,
+                &args.command,
+                serde_json::json!({ "working_dir": args.working_dir }),
+            );
         }
 
-        // Prepend persistent tools directory to PATH so user-installed
-        // binaries survive container restarts.
-        let tools_bin = self.instance_dir.join("tools/bin");
-        if let Ok(current_path) = std::env::var("PATH") {
-            cmd.env("PATH", format!("{}:{current_path}", tools_bin.display()));
-        }
+        let mut cmd = match &self.sandbox {
+            Some(sandbox) => {
+                let working_dir = args.working_dir.as_deref().map(std::path::Path::new);
+                sandbox
+                    .command(
+                        "sh",
+                        &["-c".to_string(), args.command.clone()],
+                        working_dir,
+                        &[],
+                    )
+                    .await
+                    .map_err(|error| ShellError {
+                        message: error.to_string(),
+                        exit_code: -1,
+                    })?
+            }
+            None => self.host_command(&args.command, args.working_dir.as_deref()),
+        };
 
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
@@ -359,12 +410,12 @@ impl Tool for ShellTool {
                 exit_code: -1,
             })?;
 
-        let stdout = crate::tools::truncate_output(
-            &String::from_utf8_lossy(&output.stdout),
+        let stdout = crate::tools::sanitize::sanitize_bytes(
+            &output.stdout,
             crate::tools::MAX_TOOL_OUTPUT_BYTES,
         );
-        let stderr = crate::tools::truncate_output(
-            &String::from_utf8_lossy(&output.stderr),
+        let stderr = crate::tools::sanitize::sanitize_bytes(
+            &output.stderr,
             crate::tools::MAX_TOOL_OUTPUT_BYTES,
         );
         let exit_code = output.status.code().unwrap_or(-1);
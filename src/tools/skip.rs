@@ -5,14 +5,13 @@
 //! instead of `reply`. The channel checks the skip flag after the LLM turn and
 //! suppresses any fallback text output.
 
-use crate::OutboundResponse;
+use crate::agent::typing::{TypingHolder, TypingKeepalive};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::sync::mpsc;
 
 /// Shared flag between the SkipTool and the channel event loop.
 ///
@@ -29,12 +28,12 @@ pub fn new_skip_flag() -> SkipFlag {
 #[derive(Debug, Clone)]
 pub struct SkipTool {
     flag: SkipFlag,
-    response_tx: mpsc::Sender<OutboundResponse>,
+    typing: TypingKeepalive,
 }
 
 impl SkipTool {
-    pub fn new(flag: SkipFlag, response_tx: mpsc::Sender<OutboundResponse>) -> Self {
-        Self { flag, response_tx }
+    pub fn new(flag: SkipFlag, typing: TypingKeepalive) -> Self {
+        Self { flag, typing }
     }
 }
 
@@ -84,10 +83,7 @@ impl Tool for SkipTool {
         self.flag.store(true, Ordering::Relaxed);
 
         // Cancel the typing indicator so it doesn't linger
-        let _ = self
-            .response_tx
-            .send(OutboundResponse::Status(crate::StatusUpdate::StopTyping))
-            .await;
+        self.typing.release(TypingHolder::Turn);
 
         let reason = args.reason.as_deref().unwrap_or("no reason given");
         tracing::info!(reason, "skip tool called, suppressing response");
@@ -0,0 +1,86 @@
+//! Spawn sub-worker tool: lets a worker hand part of its task to a worker of
+//! its own. The sub-worker's result arrives in the worker's mailbox.
+
+use crate::WorkerId;
+use crate::agent::sub_worker::SubWorkerSpawner;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Tool for spawning sub-workers.
+#[derive(Debug, Clone)]
+pub struct SpawnSubWorkerTool {
+    spawner: Arc<SubWorkerSpawner>,
+}
+
+impl SpawnSubWorkerTool {
+    pub fn new(spawner: Arc<SubWorkerSpawner>) -> Self {
+        Self { spawner }
+    }
+}
+
+/// Error type for spawn sub-worker tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Sub-worker spawn failed: {0}")]
+pub struct SpawnSubWorkerError(String);
+
+/// Arguments for spawn sub-worker tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SpawnSubWorkerArgs {
+    /// The sub-worker's task, with everything it needs to know.
+    pub task: String,
+}
+
+/// Output from spawn sub-worker tool.
+#[derive(Debug, Serialize)]
+pub struct SpawnSubWorkerOutput {
+    /// The ID of the spawned sub-worker.
+    pub worker_id: WorkerId,
+    /// Status message.
+    pub message: String,
+}
+
+impl Tool for SpawnSubWorkerTool {
+    const NAME: &'static str = "spawn_sub_worker";
+
+    type Error = SpawnSubWorkerError;
+    type Args = SpawnSubWorkerArgs;
+    type Output = SpawnSubWorkerOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/spawn_sub_worker").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "task": {
+                        "type": "string",
+                        "description": "What the sub-worker should do. It sees only this, not your task or history, so include everything it needs."
+                    }
+                },
+                "required": ["task"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.task.trim().is_empty() {
+            return Err(SpawnSubWorkerError("the task is empty".into()));
+        }
+
+        let worker_id = self
+            .spawner
+            .spawn(&args.task)
+            .map_err(|error| SpawnSubWorkerError(error.to_string()))?;
+
+        Ok(SpawnSubWorkerOutput {
+            worker_id,
+            message: format!(
+                "Sub-worker {worker_id} spawned. Its result will arrive in your mailbox; wait for it with read_worker_messages before you finish, or it's stopped."
+            ),
+        })
+    }
+}
@@ -1,24 +1,57 @@
 //! Spawn worker tool for creating new workers.
 
-use crate::WorkerId;
 use crate::agent::channel::{
     ChannelState, spawn_opencode_worker_from_state, spawn_worker_from_state,
 };
+use crate::agent::confirmation::{APPROVE, ConfirmationRequest};
+use crate::agent::task_queue::TaskPriority;
+use crate::{OutboundResponse, WorkerId};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use std::time::Duration;
 
 /// Tool for spawning workers.
 #[derive(Debug, Clone)]
 pub struct SpawnWorkerTool {
     state: ChannelState,
+    /// Carries the approval prompt when workers need confirming.
+    response_tx: mpsc::Sender<OutboundResponse>,
 }
 
 impl SpawnWorkerTool {
     /// Create a new spawn worker tool with access to channel state.
-    pub fn new(state: ChannelState) -> Self {
-        Self { state }
+    pub fn new(state: ChannelState, response_tx: mpsc::Sender<OutboundResponse>) -> Self {
+        Self { state, response_tx }
+    }
+
+    /// Ask the user to approve the worker, when the agent requires it.
+    async fn confirm(&self, task: &str) -> Result<(), SpawnWorkerError> {
+        let config = self.state.deps.runtime_config.confirmations.load();
+        if !config.spawn_worker {
+            return Ok(());
+        }
+
+        let request = ConfirmationRequest::new(format!("Start a worker for: {task}?"))
+            .with_timeout(Duration::from_secs(config.timeout_secs));
+        let answer = self
+            .state
+            .deps
+            .confirmations
+            .request(&self.state.channel_id, &self.response_tx, request)
+            .await;
+        match answer.as_deref() {
+            Some(APPROVE) => Ok(()),
+            Some(_) => Err(SpawnWorkerError(
+                "the user declined to start this worker".into(),
+            )),
+            None => Err(SpawnWorkerError(
+                "the user didn't approve the worker in time".into(),
+            )),
+        }
     }
 }
 
@@ -49,6 +82,10 @@ pub struct SpawnWorkerArgs {
     /// The OpenCode agent will operate in this directory.
     #[serde(default)]
     pub directory: Option<String>,
+    /// How urgent the worker is, for when it has to wait for a slot.
+    /// Defaults to normal.
+    #[serde(default)]
+    pub priority: Option<TaskPriority>,
 }
 
 /// Output from spawn worker tool.
@@ -109,6 +146,11 @@ impl Tool for SpawnWorkerTool {
             "skill": {
                 "type": "string",
                 "description": "Name of a skill to load into the worker. The worker receives the full skill instructions in its system prompt. Only use skill names from <available_skills>."
+            },
+            "priority": {
+                "type": "string",
+                "enum": ["low", "normal", "high", "urgent"],
+                "description": "How urgent the worker is. When too many workers are running it waits in a queue, and more urgent workers start first. Defaults to normal."
             }
         });
 
@@ -146,28 +188,48 @@ impl Tool for SpawnWorkerTool {
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let is_opencode = args.worker_type.as_deref() == Some("opencode");
+        let priority = args.priority.unwrap_or_default();
+        self.confirm(&args.task).await?;
 
         let worker_id = if is_opencode {
             let directory = args.directory.as_deref().ok_or_else(|| {
                 SpawnWorkerError("directory is required for opencode workers".into())
             })?;
 
-            spawn_opencode_worker_from_state(&self.state, &args.task, directory, args.interactive)
-                .await
-                .map_err(|e| SpawnWorkerError(format!("{e}")))?
+            spawn_opencode_worker_from_state(
+                &self.state,
+                &args.task,
+                directory,
+                args.interactive,
+                priority,
+            )
+            .await
+            .map_err(|e| SpawnWorkerError(format!("{e}")))?
         } else {
             spawn_worker_from_state(
                 &self.state,
                 &args.task,
                 args.interactive,
                 args.skill.as_deref(),
+                priority,
             )
             .await
             .map_err(|e| SpawnWorkerError(format!("{e}")))?
         };
 
         let worker_type_label = if is_opencode { "OpenCode" } else { "builtin" };
-        let message = if args.interactive {
+        let queued = self
+            .state
+            .deps
+            .runtime_config
+            .worker_slots
+            .position(worker_id);
+        let message = if let Some(position) = queued {
+            format!(
+                "{worker_type_label} worker {worker_id} queued at position {position} for: {}. Too many workers are running; it starts when a slot frees up.",
+                args.task
+            )
+        } else if args.interactive {
             format!(
                 "Interactive {worker_type_label} worker {worker_id} spawned for: {}. Route follow-ups with route_to_worker.",
                 args.task
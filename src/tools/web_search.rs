@@ -1,9 +1,11 @@
 //! Web search tool using the Brave Search API (task workers only).
 
+use crate::egress::{EgressPolicy, EgressScope};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 const BRAVE_WEB_SEARCH_URL: &str = "https://api.search.brave.com/res/v1/web/search";
 
@@ -26,6 +28,18 @@ impl WebSearchTool {
             api_key: api_key.into(),
         }
     }
+
+    /// Route search requests through a client bound to the agent's egress policy.
+    pub fn with_egress(mut self, egress: &Arc<EgressPolicy>) -> Self {
+        if let Ok(client) = egress
+            .client_builder(EgressScope::WebSearch)
+            .gzip(true)
+            .build()
+        {
+            self.client = client;
+        }
+        self
+    }
 }
 
 /// Error type for web search tool.
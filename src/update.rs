@@ -1,12 +1,23 @@
-//! Update checking and Docker self-update.
+//! Update checking and self-update.
 //!
-//! Checks GitHub releases for new versions and optionally performs
-//! in-place container updates when the Docker socket is available.
+//! Checks GitHub releases for new versions and optionally applies them:
+//! Docker installs recreate their container when the Docker socket is
+//! available, native installs download the release binary, verify it against
+//! the release checksums, swap it in place of the running executable and
+//! restart.
+//!
+//! Checks follow a release channel (stable, beta or nightly). Every version
+//! the instance starts on is recorded in `update_history.json`, so an update
+//! that misbehaves can be rolled back to the previously running version.
 
 use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt as _;
 
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
 use std::time::Duration;
 
 /// GitHub repository for release checks.
@@ -18,6 +29,17 @@ pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Default check interval (1 hour).
 const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
 
+/// Versions kept in the persisted history.
+const MAX_HISTORY: usize = 20;
+
+/// Release asset listing SHA-256 checksums of the other assets, in
+/// `sha256sum` format.
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// Set once a new binary is in place and the process should restart into it.
+static RESTART_PENDING: AtomicBool = AtomicBool::new(false);
+static RESTART_NOTIFY: LazyLock<tokio::sync::Notify> = LazyLock::new(tokio::sync::Notify::new);
+
 /// Deployment environment, detected from SPACEBOT_DEPLOYMENT env var.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -39,6 +61,60 @@ impl Deployment {
     }
 }
 
+/// Which releases the update checker offers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    /// Full releases only.
+    #[default]
+    Stable,
+    /// Full releases and pre-releases such as `0.3.0-beta.1` or `-rc.1`.
+    Beta,
+    /// Everything, including `-nightly` builds.
+    Nightly,
+}
+
+impl UpdateChannel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stable" => Some(Self::Stable),
+            "beta" => Some(Self::Beta),
+            "nightly" => Some(Self::Nightly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+            Self::Nightly => "nightly",
+        }
+    }
+
+    /// Whether a release version belongs to this channel.
+    fn includes(&self, version: &semver::Version) -> bool {
+        match self {
+            Self::Stable => version.pre.is_empty(),
+            Self::Beta => !version.pre.as_str().contains("nightly"),
+            Self::Nightly => true,
+        }
+    }
+}
+
+/// Update settings from the `[update]` config section.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateConfig {
+    pub channel: UpdateChannel,
+}
+
+/// A version this instance has started on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionRecord {
+    pub version: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
 /// Result of an update check.
 #[derive(Debug, Clone, Serialize)]
 pub struct UpdateStatus {
@@ -48,10 +124,17 @@ pub struct UpdateStatus {
     pub release_url: Option<String>,
     pub release_notes: Option<String>,
     pub deployment: Deployment,
-    /// Whether the Docker socket is accessible (enables one-click update).
+    /// Whether a one-click update is possible: the Docker socket is
+    /// accessible, or the native binary's directory is writable.
     pub can_apply: bool,
     pub checked_at: Option<chrono::DateTime<chrono::Utc>>,
     pub error: Option<String>,
+    pub channel: UpdateChannel,
+    /// Versions this instance has run, oldest first. The last entry is the
+    /// running version.
+    pub history: Vec<VersionRecord>,
+    /// Version a rollback would return to, when one is possible.
+    pub rollback_version: Option<String>,
 }
 
 impl Default for UpdateStatus {
@@ -66,6 +149,9 @@ impl Default for UpdateStatus {
             can_apply: false,
             checked_at: None,
             error: None,
+            channel: UpdateChannel::default(),
+            history: Vec::new(),
+            rollback_version: None,
         }
     }
 }
@@ -75,49 +161,140 @@ pub type SharedUpdateStatus = Arc<ArcSwap<UpdateStatus>>;
 
 pub fn new_shared_status() -> SharedUpdateStatus {
     let mut status = UpdateStatus::default();
-    // Probe Docker socket availability on init
-    status.can_apply = status.deployment == Deployment::Docker && docker_socket_available();
+    // Probe whether this install can update itself on init
+    status.can_apply = match status.deployment {
+        Deployment::Docker => docker_socket_available(),
+        Deployment::Native => executable_replaceable(),
+        Deployment::Hosted => false,
+    };
     Arc::new(ArcSwap::from_pointee(status))
 }
 
+/// Load the version history from the instance directory, record the running
+/// version if it changed, and apply the configured channel. Called once at
+/// startup, before the first check.
+pub fn initialize(status: &SharedUpdateStatus, instance_dir: &Path, config: &UpdateConfig) {
+    let history_path = instance_dir.join("update_history.json");
+    let mut history: Vec<VersionRecord> = std::fs::read_to_string(&history_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    if record_version(&mut history, CURRENT_VERSION, chrono::Utc::now()) {
+        let result = serde_json::to_string_pretty(&history)
+            .map_err(std::io::Error::other)
+            .and_then(|content| std::fs::write(&history_path, content));
+        if let Err(error) = result {
+            tracing::warn!(%error, path = %history_path.display(), "can't write update history");
+        }
+    }
+
+    let mut next = (**status.load()).clone();
+    next.channel = config.channel;
+    next.rollback_version = rollback_target(&history).map(|record| record.version.clone());
+    next.history = history;
+    status.store(Arc::new(next));
+}
+
+/// Append `version` unless it is already the latest entry. Returns whether
+/// the history changed.
+fn record_version(
+    history: &mut Vec<VersionRecord>,
+    version: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    if history
+        .last()
+        .is_some_and(|record| record.version == version)
+    {
+        return false;
+    }
+    history.push(VersionRecord {
+        version: version.to_string(),
+        started_at: now,
+    });
+    if history.len() > MAX_HISTORY {
+        history.drain(..history.len() - MAX_HISTORY);
+    }
+    true
+}
+
+/// The most recent version before the running one.
+fn rollback_target(history: &[VersionRecord]) -> Option<&VersionRecord> {
+    let (current, earlier) = history.split_last()?;
+    earlier
+        .iter()
+        .rev()
+        .find(|record| record.version != current.version)
+}
+
+/// Switch the release channel and re-check against it.
+pub async fn set_channel(status: &SharedUpdateStatus, channel: UpdateChannel) {
+    let mut next = (**status.load()).clone();
+    next.channel = channel;
+    status.store(Arc::new(next));
+    check_for_update(status).await;
+}
+
 /// Minimal GitHub release response.
 #[derive(Deserialize)]
 struct GitHubRelease {
     tag_name: String,
     html_url: String,
     body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+/// A downloadable file attached to a release.
+#[derive(Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl GitHubRelease {
+    fn asset(&self, name: &str) -> Option<&GitHubAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+
+    /// Release version without the `v` prefix.
+    fn version(&self) -> &str {
+        self.tag_name.strip_prefix('v').unwrap_or(&self.tag_name)
+    }
 }
 
-/// Check GitHub for the latest release and compare with current version.
+/// Check GitHub for the latest release on the selected channel and compare
+/// with the current version.
 pub async fn check_for_update(status: &SharedUpdateStatus) {
-    let result = fetch_latest_release().await;
+    let result = fetch_channel_release(status.load().channel).await;
 
-    let current = status.load();
-    let mut next = UpdateStatus {
-        current_version: CURRENT_VERSION.to_string(),
-        deployment: current.deployment,
-        can_apply: current.can_apply,
-        checked_at: Some(chrono::Utc::now()),
-        ..Default::default()
-    };
+    // Keep settings and history, reset the fields the check fills in.
+    let mut next = (**status.load()).clone();
+    next.latest_version = None;
+    next.update_available = false;
+    next.release_url = None;
+    next.release_notes = None;
+    next.checked_at = Some(chrono::Utc::now());
+    next.error = None;
 
     match result {
         Ok(release) => {
-            let tag = release
-                .tag_name
-                .strip_prefix('v')
-                .unwrap_or(&release.tag_name);
+            let tag = release.version();
             let is_newer = is_newer_version(tag, CURRENT_VERSION);
 
             next.latest_version = Some(tag.to_string());
             next.update_available = is_newer;
-            next.release_url = Some(release.html_url);
-            next.release_notes = release.body;
+            next.release_url = Some(release.html_url.clone());
+            next.release_notes = release.body.clone();
 
             if is_newer {
                 tracing::info!(
                     current = CURRENT_VERSION,
                     latest = tag,
+                    channel = next.channel.as_str(),
                     "new version available"
                 );
             }
@@ -152,12 +329,10 @@ async fn fetch_latest_release() -> anyhow::Result<GitHubRelease> {
         GITHUB_REPO
     );
 
-    let client = reqwest::Client::builder()
-        .user_agent(format!("spacebot/{}", CURRENT_VERSION))
-        .timeout(Duration::from_secs(15))
-        .build()?;
-
-    let response = client.get(&url).send().await?;
+    let response = http_client(Duration::from_secs(15))?
+        .get(&url)
+        .send()
+        .await?;
 
     if !response.status().is_success() {
         anyhow::bail!("GitHub API returned {}", response.status());
@@ -166,6 +341,45 @@ async fn fetch_latest_release() -> anyhow::Result<GitHubRelease> {
     Ok(response.json().await?)
 }
 
+/// Fetch the newest release on a channel. Stable uses GitHub's "latest"
+/// release; the other channels scan recent releases, pre-releases included.
+async fn fetch_channel_release(channel: UpdateChannel) -> anyhow::Result<GitHubRelease> {
+    if channel == UpdateChannel::Stable {
+        return fetch_latest_release().await;
+    }
+
+    let url = format!(
+        "https://api.github.com/repos/{}/releases?per_page=30",
+        GITHUB_REPO
+    );
+    let response = http_client(Duration::from_secs(15))?
+        .get(&url)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("GitHub API returned {}", response.status());
+    }
+    let releases: Vec<GitHubRelease> = response.json().await?;
+
+    releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| {
+            let version = semver::Version::parse(release.version()).ok()?;
+            channel.includes(&version).then_some((version, release))
+        })
+        .max_by(|(left, _), (right, _)| left.cmp(right))
+        .map(|(_, release)| release)
+        .ok_or_else(|| anyhow::anyhow!("no releases on the {} channel", channel.as_str()))
+}
+
+fn http_client(timeout: Duration) -> anyhow::Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent(format!("spacebot/{}", CURRENT_VERSION))
+        .timeout(timeout)
+        .build()?)
+}
+
 /// Compare two semver strings. Returns true if `latest` is newer than `current`.
 fn is_newer_version(latest: &str, current: &str) -> bool {
     let Ok(latest) = semver::Version::parse(latest) else {
@@ -182,6 +396,287 @@ fn docker_socket_available() -> bool {
     std::path::Path::new("/var/run/docker.sock").exists()
 }
 
+/// Apply the available update the way this deployment supports.
+pub async fn apply_update(status: &SharedUpdateStatus) -> anyhow::Result<()> {
+    match status.load().deployment {
+        Deployment::Docker => apply_docker_update(status).await,
+        Deployment::Native => apply_binary_update(status).await,
+        Deployment::Hosted => anyhow::bail!("hosted instances are updated by the platform"),
+    }
+}
+
+/// Whether a binary update has been applied and the process should restart
+/// into the new executable once it has shut down.
+pub fn restart_pending() -> bool {
+    RESTART_PENDING.load(Ordering::SeqCst)
+}
+
+/// Resolves once a binary update asks the process to restart. The main loop
+/// selects on this to start a graceful shutdown.
+pub async fn restart_requested() {
+    RESTART_NOTIFY.notified().await;
+}
+
+/// Replace this process with the executable at its original path, keeping
+/// the same arguments. Called after shutdown when [`restart_pending`] is set.
+/// Keeping the PID means a supervisor such as systemd sees one continuous
+/// service. Only returns if the exec fails.
+pub fn restart_process() -> anyhow::Error {
+    use std::os::unix::process::CommandExt as _;
+
+    let executable = match current_executable() {
+        Ok(executable) => executable,
+        Err(error) => return error,
+    };
+    tracing::info!(executable = %executable.display(), "restarting into updated binary");
+    let error = std::process::Command::new(&executable)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    anyhow::anyhow!("can't restart into {}: {error}", executable.display())
+}
+
+/// Path of the running executable, resolved before any swap so a rename
+/// doesn't change what it points at.
+fn current_executable() -> anyhow::Result<PathBuf> {
+    static EXECUTABLE: LazyLock<std::io::Result<PathBuf>> =
+        LazyLock::new(|| std::env::current_exe().and_then(std::fs::canonicalize));
+    EXECUTABLE
+        .as_ref()
+        .cloned()
+        .map_err(|error| anyhow::anyhow!("can't locate the running executable: {error}"))
+}
+
+/// Whether the running executable can be replaced: its directory must be
+/// writable, since the new binary is staged next to it and renamed over it.
+fn executable_replaceable() -> bool {
+    let Ok(executable) = current_executable() else {
+        return false;
+    };
+    let Some(directory) = executable.parent() else {
+        return false;
+    };
+    tempfile::Builder::new()
+        .prefix(".spacebot-update-probe")
+        .tempfile_in(directory)
+        .is_ok()
+}
+
+/// Release asset name of the binary for this platform, e.g.
+/// `spacebot-linux-x86_64`.
+fn binary_asset_name() -> String {
+    format!(
+        "spacebot-{}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+/// Find the checksum for `asset` in a `sha256sum`-style listing. Lines look
+/// like `<hex>  <name>`, with `*` before the name in binary mode.
+fn parse_checksum(listing: &str, asset: &str) -> Option<String> {
+    listing.lines().find_map(|line| {
+        let (checksum, name) = line.trim().split_once(char::is_whitespace)?;
+        let name = name.trim_start().trim_start_matches('*');
+        (name == asset && checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| checksum.to_ascii_lowercase())
+    })
+}
+
+/// Apply a native self-update: download this platform's binary from the
+/// latest release, verify its SHA-256 against the release's `SHA256SUMS`,
+/// check that it runs, then swap it in place of the running executable.
+///
+/// The previous binary is kept next to the new one with an `.old` suffix.
+/// On success a restart is requested; the process shuts down gracefully and
+/// execs the new binary. On failure the running binary is left untouched.
+pub async fn apply_binary_update(status: &SharedUpdateStatus) -> anyhow::Result<()> {
+    let current = status.load();
+
+    if !current.update_available {
+        anyhow::bail!("no update available");
+    }
+    if current.deployment != Deployment::Native {
+        anyhow::bail!("not a native install");
+    }
+    if !current.can_apply {
+        anyhow::bail!("the executable's directory is not writable");
+    }
+    if restart_pending() {
+        anyhow::bail!("an update is already waiting for restart");
+    }
+
+    let executable = current_executable()?;
+    let directory = executable
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("executable has no parent directory"))?;
+
+    let release = fetch_channel_release(current.channel).await?;
+    let version = release.version();
+    if !is_newer_version(version, CURRENT_VERSION) {
+        anyhow::bail!("latest release {version} is not newer than {CURRENT_VERSION}");
+    }
+
+    let asset_name = binary_asset_name();
+    let binary_asset = release
+        .asset(&asset_name)
+        .ok_or_else(|| anyhow::anyhow!("release {version} has no {asset_name} binary"))?;
+    let checksums_asset = release
+        .asset(CHECKSUMS_ASSET)
+        .ok_or_else(|| anyhow::anyhow!("release {version} has no {CHECKSUMS_ASSET}"))?;
+
+    tracing::info!(
+        from = CURRENT_VERSION,
+        to = version,
+        asset = %asset_name,
+        "applying binary update"
+    );
+
+    let client = http_client(Duration::from_secs(600))?;
+    let listing = client
+        .get(&checksums_asset.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let expected = parse_checksum(&listing, &asset_name)
+        .ok_or_else(|| anyhow::anyhow!("{CHECKSUMS_ASSET} has no entry for {asset_name}"))?;
+
+    // Stage the download in the executable's directory so the final rename
+    // stays on one filesystem and is atomic.
+    let staged = tempfile::Builder::new()
+        .prefix(".spacebot-update")
+        .tempfile_in(directory)?
+        .into_temp_path();
+    let actual = download_hashed(&client, &binary_asset.browser_download_url, &staged).await?;
+    if actual != expected {
+        anyhow::bail!("checksum mismatch for {asset_name}: expected {expected}, got {actual}");
+    }
+
+    verify_runs(&staged, version).await?;
+
+    swap_executable(&staged, &executable)?;
+    // The staged file now lives at the executable's path.
+    staged.keep()?;
+
+    tracing::info!(to = version, "binary updated, restarting");
+    RESTART_PENDING.store(true, Ordering::SeqCst);
+    RESTART_NOTIFY.notify_one();
+    Ok(())
+}
+
+/// Stream a download to `destination`, returning its hex SHA-256.
+async fn download_hashed(
+    client: &reqwest::Client,
+    url: &str,
+    destination: &Path,
+) -> anyhow::Result<String> {
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    let mut file = tokio::fs::File::create(destination).await?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    file.sync_all().await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Make the staged binary executable and check that it starts and reports
+/// the expected version, so a truncated or wrong-architecture download never
+/// replaces a working binary.
+async fn verify_runs(staged: &Path, version: &str) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+    std::fs::set_permissions(staged, std::fs::Permissions::from_mode(0o755))?;
+
+    let output = tokio::time::timeout(
+        Duration::from_secs(30),
+        tokio::process::Command::new(staged)
+            .arg("--version")
+            .output(),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("new binary timed out reporting its version"))??;
+    let reported = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || !reported.contains(version) {
+        anyhow::bail!(
+            "new binary failed its version check (exit {}, output {:?})",
+            output.status,
+            reported.trim()
+        );
+    }
+    Ok(())
+}
+
+/// Move the running executable to `<name>.old` and the staged binary into
+/// its place. Restores the original if the second rename fails.
+fn swap_executable(staged: &Path, executable: &Path) -> anyhow::Result<()> {
+    let backup = backup_path(executable);
+    std::fs::rename(executable, &backup)
+        .map_err(|error| anyhow::anyhow!("can't move the current binary aside: {error}"))?;
+    if let Err(error) = std::fs::rename(staged, executable) {
+        std::fs::rename(&backup, executable).ok();
+        anyhow::bail!("can't install the new binary: {error}");
+    }
+    Ok(())
+}
+
+/// Where the previous binary is kept after an update.
+fn backup_path(executable: &Path) -> PathBuf {
+    let mut name = executable.file_name().unwrap_or_default().to_os_string();
+    name.push(".old");
+    executable.with_file_name(name)
+}
+
+/// Roll back to the previously running version: the `.old` binary kept by
+/// the last native update, or the previous version's image on Docker.
+///
+/// Like an update, a native rollback swaps the executables and requests a
+/// restart, and a Docker rollback replaces the container.
+pub async fn rollback(status: &SharedUpdateStatus) -> anyhow::Result<()> {
+    let current = status.load();
+    let target = current
+        .rollback_version
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("no previous version to roll back to"))?;
+    if !current.can_apply {
+        anyhow::bail!("this install can't replace itself");
+    }
+
+    tracing::info!(from = CURRENT_VERSION, to = %target, "rolling back");
+
+    match current.deployment {
+        Deployment::Docker => recreate_container(&target).await,
+        Deployment::Native => {
+            if restart_pending() {
+                anyhow::bail!("an update is already waiting for restart");
+            }
+            let executable = current_executable()?;
+            let backup = backup_path(&executable);
+            if !backup.exists() {
+                anyhow::bail!("no previous binary at {}", backup.display());
+            }
+            verify_runs(&backup, &target).await?;
+
+            // Move the backup aside first so the swap can put the running
+            // binary in its place; a later rollback then returns here.
+            let staged = executable.with_file_name(".spacebot-rollback");
+            std::fs::rename(&backup, &staged)?;
+            if let Err(error) = swap_executable(&staged, &executable) {
+                std::fs::rename(&staged, &backup).ok();
+                return Err(error);
+            }
+
+            tracing::info!(to = %target, "binary rolled back, restarting");
+            RESTART_PENDING.store(true, Ordering::SeqCst);
+            RESTART_NOTIFY.notify_one();
+            Ok(())
+        }
+        Deployment::Hosted => anyhow::bail!("hosted instances are managed by the platform"),
+    }
+}
+
 /// Apply a Docker self-update: pull the new image, recreate this container.
 ///
 /// This function does not return on success — the current container is stopped
@@ -210,6 +705,12 @@ pub async fn apply_docker_update(status: &SharedUpdateStatus) -> anyhow::Result<
         "applying Docker update"
     );
 
+    recreate_container(latest_version).await
+}
+
+/// Pull the image for `target_version` and replace this container with one
+/// running it. Does not return on success.
+async fn recreate_container(target_version: &str) -> anyhow::Result<()> {
     let docker = bollard::Docker::connect_with_local_defaults()
         .map_err(|e| anyhow::anyhow!("failed to connect to Docker: {}", e))?;
 
@@ -229,7 +730,7 @@ pub async fn apply_docker_update(status: &SharedUpdateStatus) -> anyhow::Result<
 
     // Resolve the target image: same base name, new version tag.
     // e.g. ghcr.io/spacedriveapp/spacebot:v0.1.0-slim -> ghcr.io/spacedriveapp/spacebot:v0.2.0-slim
-    let target_image = resolve_target_image(&current_image, latest_version);
+    let target_image = resolve_target_image(&current_image, target_version);
 
     tracing::info!(
         current_image = %current_image,
@@ -437,6 +938,75 @@ mod tests {
         assert!(!is_newer_version("0.0.9", "0.1.0"));
     }
 
+    #[test]
+    fn test_parse_checksum() {
+        let hash = "a".repeat(64);
+        let listing = format!(
+            "{}  spacebot-linux-aarch64\n{hash} *spacebot-linux-x86_64\nnot a checksum line\n",
+            "b".repeat(64)
+        );
+        assert_eq!(
+            parse_checksum(&listing, "spacebot-linux-x86_64").as_deref(),
+            Some(hash.as_str())
+        );
+        assert!(parse_checksum(&listing, "spacebot-macos-aarch64").is_none());
+        assert!(parse_checksum("abc123  spacebot-linux-x86_64", "spacebot-linux-x86_64").is_none());
+    }
+
+    #[test]
+    fn test_swap_executable_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join("spacebot");
+        let staged = dir.path().join(".spacebot-update");
+        std::fs::write(&executable, "old").unwrap();
+        std::fs::write(&staged, "new").unwrap();
+
+        swap_executable(&staged, &executable).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&executable).unwrap(), "new");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("spacebot.old")).unwrap(),
+            "old"
+        );
+        assert!(!staged.exists());
+    }
+
+    #[test]
+    fn test_channel_includes() {
+        let stable = semver::Version::parse("0.3.0").unwrap();
+        let beta = semver::Version::parse("0.3.0-beta.1").unwrap();
+        let nightly = semver::Version::parse("0.3.0-nightly.20260101").unwrap();
+
+        assert!(UpdateChannel::Stable.includes(&stable));
+        assert!(!UpdateChannel::Stable.includes(&beta));
+        assert!(UpdateChannel::Beta.includes(&beta));
+        assert!(!UpdateChannel::Beta.includes(&nightly));
+        assert!(UpdateChannel::Nightly.includes(&nightly));
+        assert!(is_newer_version("0.3.0-beta.1", "0.2.0"));
+    }
+
+    #[test]
+    fn test_version_history_and_rollback_target() {
+        let now = chrono::Utc::now();
+        let mut history = Vec::new();
+        assert!(record_version(&mut history, "0.1.0", now));
+        assert!(!record_version(&mut history, "0.1.0", now));
+        assert!(rollback_target(&history).is_none());
+
+        assert!(record_version(&mut history, "0.2.0", now));
+        assert_eq!(rollback_target(&history).unwrap().version, "0.1.0");
+
+        // Rolling back records the old version again; the next rollback
+        // returns to the newer one.
+        assert!(record_version(&mut history, "0.1.0", now));
+        assert_eq!(rollback_target(&history).unwrap().version, "0.2.0");
+
+        for minor in 0..MAX_HISTORY {
+            record_version(&mut history, &format!("1.{minor}.0"), now);
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+    }
+
     #[test]
     fn test_resolve_target_image() {
         assert_eq!(
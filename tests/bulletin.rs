@@ -30,7 +30,7 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
     let resolved_agents = config.resolve_agents();
     let agent_config = resolved_agents.first().context("no agents configured")?;
 
-    let db = spacebot::db::Db::connect(&agent_config.data_dir)
+    let db = spacebot::db::Db::connect(&agent_config.data_dir, &agent_config.database)
         .await
         .context("failed to connect databases")?;
 
@@ -80,6 +80,7 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
         event_tx,
         sqlite_pool: db.sqlite.clone(),
         messaging_manager: None,
+        confirmations: Default::default(),
     })
 }
 
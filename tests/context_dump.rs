@@ -30,7 +30,7 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
     let resolved_agents = config.resolve_agents();
     let agent_config = resolved_agents.first().context("no agents configured")?;
 
-    let db = spacebot::db::Db::connect(&agent_config.data_dir)
+    let db = spacebot::db::Db::connect(&agent_config.data_dir, &agent_config.database)
         .await
         .context("failed to connect databases")?;
 
@@ -80,6 +80,7 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
         event_tx,
         sqlite_pool: db.sqlite.clone(),
         messaging_manager: None,
+        confirmations: Default::default(),
     };
 
     Ok((deps, config))
@@ -150,6 +151,7 @@ fn build_channel_system_prompt(rc: &spacebot::config::RuntimeConfig) -> String {
             None,
             None,
             None,
+            None,
         )
         .expect("failed to render channel prompt")
 }
@@ -258,6 +260,7 @@ async fn dump_branch_context() {
         deps.memory_search.clone(),
         conversation_logger,
         channel_store,
+        spacebot::audit::AuditLogger::new(deps.sqlite_pool.clone()),
     );
 
     let tool_defs = branch_tool_server
@@ -319,6 +322,19 @@ async fn dump_worker_context() {
         std::path::PathBuf::from("/tmp"),
         std::path::PathBuf::from("/tmp"),
         vec![],
+        vec![],
+        spacebot::audit::AuditLogger::new(deps.sqlite_pool.clone()),
+        None,
+        spacebot::injection::InjectionGuard::new(
+            Default::default(),
+            String::new(),
+            deps.agent_id.clone(),
+            spacebot::ProcessId::Worker(uuid::Uuid::nil()),
+            None,
+            deps.event_tx.clone(),
+        ),
+        spacebot::egress::EgressPolicy::new(&deps.runtime_config.egress.load()),
+        None,
     );
 
     let tool_defs = worker_tool_server
@@ -437,6 +453,7 @@ async fn dump_all_contexts() {
         deps.memory_search.clone(),
         conversation_logger,
         channel_store,
+        spacebot::audit::AuditLogger::new(deps.sqlite_pool.clone()),
     );
     let branch_tool_defs = branch_tool_server.get_tool_defs(None).await.unwrap();
     let branch_tools_text = format_tool_defs(&branch_tool_defs);
@@ -468,6 +485,19 @@ async fn dump_all_contexts() {
         std::path::PathBuf::from("/tmp"),
         std::path::PathBuf::from("/tmp"),
         vec![],
+        vec![],
+        spacebot::audit::AuditLogger::new(deps.sqlite_pool.clone()),
+        None,
+        spacebot::injection::InjectionGuard::new(
+            Default::default(),
+            String::new(),
+            deps.agent_id.clone(),
+            spacebot::ProcessId::Worker(uuid::Uuid::nil()),
+            None,
+            deps.event_tx.clone(),
+        ),
+        spacebot::egress::EgressPolicy::new(&deps.runtime_config.egress.load()),
+        None,
     );
     let worker_tool_defs = worker_tool_server.get_tool_defs(None).await.unwrap();
     let worker_tools_text = format_tool_defs(&worker_tool_defs);